@@ -7,6 +7,7 @@ use halo2::pasta::{EqAffine, Fp};
 use halo2::plonk::*;
 use halo2::poly::{commitment::Params, Rotation};
 use halo2::transcript::{Blake2bRead, Blake2bWrite};
+use rand::rngs::OsRng;
 
 use std::marker::PhantomData;
 
@@ -168,6 +169,8 @@ fn bench_with_k(name: &str, k: u32, c: &mut Criterion) {
 
     impl<F: FieldExt> Circuit<F> for MyCircuit<F> {
         type Config = PLONKConfig;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
 
         fn configure(meta: &mut ConstraintSystem<F>) -> PLONKConfig {
             let a = meta.advice_column();
@@ -257,7 +260,7 @@ fn bench_with_k(name: &str, k: u32, c: &mut Criterion) {
 
             // Create a proof
             let mut transcript = Blake2bWrite::init(vec![]);
-            create_proof(&params, &pk, &[circuit], &[&[]], &mut transcript)
+            create_proof(&params, &pk, &[circuit], &[&[]], OsRng, &mut transcript)
                 .expect("proof generation should not fail")
         });
     });
@@ -269,7 +272,7 @@ fn bench_with_k(name: &str, k: u32, c: &mut Criterion) {
 
     // Create a proof
     let mut transcript = Blake2bWrite::init(vec![]);
-    create_proof(&params, &pk, &[circuit], &[&[]], &mut transcript)
+    create_proof(&params, &pk, &[circuit], &[&[]], OsRng, &mut transcript)
         .expect("proof generation should not fail");
     let proof = transcript.finalize();
 
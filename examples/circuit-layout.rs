@@ -239,6 +239,8 @@ fn main() {
 
     impl<F: FieldExt> Circuit<F> for MyCircuit<F> {
         type Config = PLONKConfig;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
 
         fn configure(meta: &mut ConstraintSystem<F>) -> PLONKConfig {
             let e = meta.advice_column();
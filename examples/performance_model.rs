@@ -10,6 +10,7 @@ use halo2::{
     },
     transcript::{Blake2bRead, Blake2bWrite},
 };
+use rand::rngs::OsRng;
 
 use std::marker::PhantomData;
 
@@ -182,6 +183,8 @@ impl<'a, FF: FieldExt, CS: Assignment<FF>> StandardCS<FF> for StandardPLONK<'a,
 
 impl<F: FieldExt> Circuit<F> for MyCircuit<F> {
     type Config = PLONKConfig;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
 
     fn configure(meta: &mut ConstraintSystem<F>) -> PLONKConfig {
         let a = meta.advice_column();
@@ -296,8 +299,15 @@ fn main() {
 
     // Create a proof
     let mut transcript = Blake2bWrite::init(vec![]);
-    create_proof(&params, &pk, &[circuit], &[&[pubinputs]], &mut transcript)
-        .expect("proof generation should not fail");
+    create_proof(
+        &params,
+        &pk,
+        &[circuit],
+        &[&[pubinputs]],
+        OsRng,
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
     let proof: Vec<u8> = transcript.finalize();
 
     println!("[Prover] {}", recorder);
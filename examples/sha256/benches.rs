@@ -10,6 +10,7 @@ use halo2::{
     poly::commitment::Params,
     transcript::{Blake2bRead, Blake2bWrite},
 };
+use rand::rngs::OsRng;
 
 use std::{
     fs::File,
@@ -27,6 +28,8 @@ fn bench(name: &str, k: u32, c: &mut Criterion) {
 
     impl<F: FieldExt> Circuit<F> for MyCircuit {
         type Config = Table16Config;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
 
         fn configure(meta: &mut ConstraintSystem<F>) -> Table16Config {
             Table16Chip::configure(meta)
@@ -104,9 +107,13 @@ fn bench(name: &str, k: u32, c: &mut Criterion) {
     }
 
     let vk_fs = File::open(&vk_path).expect("couldn't load sha256_params");
-    let vk: VerifyingKey<EqAffine> =
-        VerifyingKey::<EqAffine>::read::<_, MyCircuit>(&mut BufReader::new(vk_fs), &params)
-            .expect("Failed to read vk");
+    let vk: VerifyingKey<EqAffine> = VerifyingKey::<EqAffine>::read::<_, MyCircuit>(
+        &mut BufReader::new(vk_fs),
+        &params,
+        #[cfg(feature = "circuit-params")]
+        (),
+    )
+    .expect("Failed to read vk");
 
     let pk = keygen_pk(&params, vk, &empty_circuit).expect("keygen_pk should not fail");
 
@@ -119,7 +126,7 @@ fn bench(name: &str, k: u32, c: &mut Criterion) {
     // c.bench_function(&prover_name, |b| {
     //     b.iter(|| {
     //         let mut transcript = Blake2bWrite::init(Fq::one());
-    //         create_proof(&params, &pk, &circuit, &[], &mut transcript)
+    //         create_proof(&params, &pk, &circuit, &[], OsRng, &mut transcript)
     //             .expect("proof generation should not fail");
     //         let proof: Vec<u8> = transcript.finalize();
     //     });
@@ -129,7 +136,7 @@ fn bench(name: &str, k: u32, c: &mut Criterion) {
     let proof_path = Path::new("./benches/sha256_assets/sha256_proof");
     if File::open(&proof_path).is_err() {
         let mut transcript = Blake2bWrite::init(vec![]);
-        create_proof(&params, &pk, &[circuit], &[], &mut transcript)
+        create_proof(&params, &pk, &[circuit], &[], OsRng, &mut transcript)
             .expect("proof generation should not fail");
         let proof: Vec<u8> = transcript.finalize();
         let mut file = File::create(&proof_path).expect("Failed to create sha256_proof");
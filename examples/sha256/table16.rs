@@ -343,6 +343,8 @@ mod tests {
 
         impl<F: FieldExt> Circuit<F> for MyCircuit {
             type Config = Table16Config;
+            #[cfg(feature = "circuit-params")]
+            type Params = ();
 
             fn configure(meta: &mut ConstraintSystem<F>) -> Table16Config {
                 Table16Chip::configure(meta)
@@ -399,6 +401,8 @@ mod tests {
 
         impl<F: FieldExt> Circuit<F> for MyCircuit {
             type Config = Table16Config;
+            #[cfg(feature = "circuit-params")]
+            type Params = ();
 
             fn configure(meta: &mut ConstraintSystem<F>) -> Table16Config {
                 Table16Chip::configure(meta)
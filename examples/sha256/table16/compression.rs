@@ -817,6 +817,8 @@ mod tests {
 
         impl<F: FieldExt> Circuit<F> for MyCircuit {
             type Config = Table16Config;
+            #[cfg(feature = "circuit-params")]
+            type Params = ();
 
             fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
                 let a = meta.advice_column();
@@ -254,7 +254,8 @@ mod tests {
         dev::MockProver,
         pasta::Fp,
         plonk::{
-            Advice, Any, Assignment, Circuit, Column, ConstraintSystem, Error, Fixed, Permutation,
+            Advice, Assignment, Challenge, Circuit, Column, ConstraintSystem, Error, Fixed,
+            Permutation, Selector,
         },
     };
 
@@ -277,7 +278,7 @@ mod tests {
             config: MyConfig,
             regions: Vec<usize>,
             /// Stores the first empty row for each column.
-            columns: HashMap<Column<Any>, usize>,
+            columns: HashMap<layouter::RegionColumn, usize>,
             _marker: PhantomData<F>,
         }
 
@@ -364,6 +365,14 @@ mod tests {
                 Ok(result)
             }
 
+            fn get_challenge(&self, challenge: Challenge) -> Option<F> {
+                self.cs.get_challenge(challenge)
+            }
+
+            fn witness_required(&self) -> bool {
+                self.cs.witness_required()
+            }
+
             fn get_root(&mut self) -> &mut Self::Root {
                 self
             }
@@ -409,6 +418,19 @@ mod tests {
         impl<'r, 'a, F: FieldExt, CS: Assignment<F> + 'a> layouter::RegionLayouter<Table16Chip<F>>
             for MyRegion<'r, 'a, F, CS>
         {
+            fn enable_selector<'v>(
+                &'v mut self,
+                annotation: &'v (dyn Fn() -> String + 'v),
+                selector: &Selector,
+                offset: usize,
+            ) -> Result<(), Error> {
+                self.layouter.cs.enable_selector(
+                    annotation,
+                    selector,
+                    self.layouter.regions[*self.region_index] + offset,
+                )
+            }
+
             fn assign_advice<'v>(
                 &'v mut self,
                 annotation: &'v (dyn Fn() -> String + 'v),
@@ -466,10 +488,20 @@ mod tests {
 
                 Ok(())
             }
+
+            fn get_challenge(&self, challenge: Challenge) -> Option<F> {
+                self.layouter.cs.get_challenge(challenge)
+            }
+
+            fn witness_required(&self) -> bool {
+                self.layouter.cs.witness_required()
+            }
         }
 
         impl<F: FieldExt> Circuit<F> for MyCircuit {
             type Config = MyConfig;
+            #[cfg(feature = "circuit-params")]
+            type Params = ();
 
             fn configure(meta: &mut ConstraintSystem<F>) -> MyConfig {
                 let a = meta.advice_column();
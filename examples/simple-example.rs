@@ -276,6 +276,8 @@ struct MyCircuit<F: FieldExt> {
 impl<F: FieldExt> Circuit<F> for MyCircuit<F> {
     // Since we are using a single chip for everything, we can just reuse its config.
     type Config = FieldConfig;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
 
     fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
         // We create the two advice columns that FieldChip uses for I/O.
@@ -348,6 +350,8 @@ fn main() {
         Err(VerifyFailure::Gate {
             gate_index: 1,
             gate_name: "public input",
+            constraint_index: 0,
+            constraint_name: "",
             row: 6,
         })
     );
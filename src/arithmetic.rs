@@ -1,15 +1,28 @@
 //! This module provides common utilities, traits and structures for group,
 //! field and polynomial arithmetic.
 
-use crossbeam_utils::thread;
 pub use ff::Field;
 use group::Group as _;
+use lazy_static::lazy_static;
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
 
+use crate::multicore;
+
+#[cfg(feature = "cuda")]
+mod cuda;
 mod curves;
 mod fields;
+#[cfg(target_arch = "x86_64")]
+mod simd;
 
+#[cfg(feature = "cuda")]
+pub use cuda::*;
 pub use curves::*;
 pub use fields::*;
+#[cfg(target_arch = "x86_64")]
+pub use simd::*;
 
 /// This represents an element of a group with basic operations that can be
 /// performed. This allows an FFT implementation (for example) to operate
@@ -183,20 +196,78 @@ pub fn small_multiexp<C: CurveAffine>(coeffs: &[C::Scalar], bases: &[C]) -> C::C
     acc
 }
 
+/// An implementation of multi-exponentiation over `C`, such as one on a GPU, that
+/// [`best_multiexp`] hands its work to once it is registered with
+/// [`set_multiexp_backend`].
+pub trait MultiexpBackend<C: CurveAffine>: Send + Sync {
+    /// Returns $\sum_i [\mathsf{coeffs}_i] \mathsf{bases}_i$, or `None` if the backend
+    /// cannot compute it, for example because its device is unavailable or the
+    /// multi-exponentiation is too small to be worth moving to it. [`best_multiexp`]
+    /// then computes it on the CPU.
+    ///
+    /// `coeffs` and `bases` have the same length.
+    fn multiexp(&self, coeffs: &[C::Scalar], bases: &[C]) -> Option<C::Curve>;
+}
+
+lazy_static! {
+    static ref MULTIEXP_BACKENDS: RwLock<HashMap<TypeId, Arc<dyn Any + Send + Sync>>> =
+        RwLock::new(HashMap::new());
+}
+
+/// Registers `backend` to compute the multi-exponentiations over `C` of
+/// [`best_multiexp`], replacing any backend registered for `C` before it.
+pub fn set_multiexp_backend<C: CurveAffine>(backend: Arc<dyn MultiexpBackend<C>>) {
+    let backend: Arc<dyn Any + Send + Sync> = Arc::new(backend);
+    MULTIEXP_BACKENDS
+        .write()
+        .unwrap()
+        .insert(TypeId::of::<C>(), backend);
+}
+
+/// Removes the backend registered for `C`, if any, so that [`best_multiexp`] computes
+/// its multi-exponentiations on the CPU.
+pub fn clear_multiexp_backend<C: CurveAffine>() {
+    MULTIEXP_BACKENDS
+        .write()
+        .unwrap()
+        .remove(&TypeId::of::<C>());
+}
+
 /// Performs a multi-exponentiation operation.
 ///
 /// This function will panic if coeffs and bases have a different length.
 ///
-/// This will use multithreading if beneficial.
+/// This will use the backend registered for `C` with [`set_multiexp_backend`] if there
+/// is one that accepts the work, and multithreading if beneficial otherwise.
 pub fn best_multiexp<C: CurveAffine>(coeffs: &[C::Scalar], bases: &[C]) -> C::Curve {
     assert_eq!(coeffs.len(), bases.len());
 
-    let num_cpus = num_cpus::get();
+    let backend = MULTIEXP_BACKENDS
+        .read()
+        .unwrap()
+        .get(&TypeId::of::<C>())
+        .cloned();
+    if let Some(backend) = backend {
+        let backend = backend
+            .downcast_ref::<Arc<dyn MultiexpBackend<C>>>()
+            .expect("keyed by curve type");
+        if let Some(result) = backend.multiexp(coeffs, bases) {
+            return result;
+        }
+    }
+
+    cpu_multiexp(coeffs, bases)
+}
+
+/// Performs a multi-exponentiation operation on the CPU, with multithreading if
+/// beneficial.
+fn cpu_multiexp<C: CurveAffine>(coeffs: &[C::Scalar], bases: &[C]) -> C::Curve {
+    let num_cpus = num_threads();
     if coeffs.len() > num_cpus {
         let chunk = coeffs.len() / num_cpus;
         let num_chunks = coeffs.chunks(chunk).len();
         let mut results = vec![C::Curve::identity(); num_chunks];
-        thread::scope(|scope| {
+        multicore::scope(|scope| {
             let chunk = coeffs.len() / num_cpus;
 
             for ((coeffs, bases), acc) in coeffs
@@ -227,28 +298,190 @@ pub fn best_multiexp<C: CurveAffine>(coeffs: &[C::Scalar], bases: &[C]) -> C::Cu
 /// $\omega^{-1}$ in place of $\omega$ and dividing each resulting field element
 /// by $n$.
 ///
-/// This will use multithreading if beneficial.
+/// This will use the backend registered for `G` with [`set_fft_backend`] if there is
+/// one that accepts the work, and multithreading if beneficial otherwise.
 pub fn best_fft<G: Group>(a: &mut [G], omega: G::Scalar, log_n: u32) {
-    let cpus = num_cpus::get();
-    let log_cpus = log2_floor(cpus);
+    if backend_fft(a, omega, log_n) {
+        return;
+    }
 
+    let log_cpus = log2_floor(num_threads());
     if log_n <= log_cpus {
         serial_fft(a, omega, log_n);
     } else {
-        parallel_fft(a, omega, log_n, log_cpus);
+        parallel_fft(a, omega, log_n, log_cpus, serial_fft);
     }
 }
 
-fn serial_fft<G: Group>(a: &mut [G], omega: G::Scalar, log_n: u32) {
-    fn bitreverse(mut n: u32, l: u32) -> u32 {
-        let mut r = 0;
-        for _ in 0..l {
-            r = (r << 1) | (n & 1);
-            n >>= 1;
+/// Performs an FFT as [`best_fft`] does, over the $\omega$ of `twiddles` or the
+/// power of it of multiplicative order $2^{\mathsf{log\_n}}$, without computing its
+/// twiddle factors again.
+///
+/// This function will panic if `log_n` is larger than the size of `twiddles`.
+pub fn best_fft_with_twiddles<G: Group>(
+    a: &mut [G],
+    log_n: u32,
+    twiddles: &FftTwiddles<G::Scalar>,
+) {
+    let omega = twiddles.omega(log_n);
+    if backend_fft(a, omega, log_n) {
+        return;
+    }
+
+    let log_cpus = log2_floor(num_threads());
+    if log_n <= log_cpus {
+        serial_fft_with_twiddles(a, log_n, twiddles);
+    } else {
+        // The sub-FFTs are over a power of omega, which the same twiddle factors serve.
+        parallel_fft(a, omega, log_n, log_cpus, |a, _, log_n| {
+            serial_fft_with_twiddles(a, log_n, twiddles)
+        });
+    }
+}
+
+/// The twiddle factors of an FFT of size $2^k$ over an element `omega` of
+/// multiplicative order $2^k$, which are the powers of `omega` and the bit-reversal
+/// permutation of $2^k$ elements, computed once for many FFTs with
+/// [`best_fft_with_twiddles`].
+///
+/// Since $\omega^{2^{k - k'}}$ has multiplicative order $2^{k'}$, the same twiddle
+/// factors serve the FFTs of every smaller size $2^{k'}$ over it.
+#[derive(Clone)]
+pub struct FftTwiddles<F: FieldExt> {
+    log_n: u32,
+    omega: F,
+    /// $\omega^i$ for $0 \leq i < 2^{k - 1}$.
+    powers: Vec<F>,
+    /// The index of each of $2^k$ elements after the bit-reversal permutation, which
+    /// the twiddle factors over $\omega$ and over $\omega^{-1}$ share.
+    bit_reversal: Arc<Vec<u32>>,
+}
+
+impl<F: FieldExt> std::fmt::Debug for FftTwiddles<F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // The tables are too large to be worth printing.
+        f.debug_struct("FftTwiddles")
+            .field("log_n", &self.log_n)
+            .field("omega", &self.omega)
+            .finish()
+    }
+}
+
+impl<F: FieldExt> FftTwiddles<F> {
+    /// Computes the twiddle factors of an FFT of size $2^{\mathsf{log\_n}}$ over
+    /// `omega`, which must have multiplicative order $2^{\mathsf{log\_n}}$.
+    pub fn new(omega: F, log_n: u32) -> Self {
+        let mut powers = vec![F::zero(); (1 << log_n) / 2];
+        parallelize(&mut powers, |powers, start| {
+            let mut cur = omega.pow_vartime(&[start as u64, 0, 0, 0]);
+            for power in powers {
+                *power = cur;
+                cur *= &omega;
+            }
+        });
+
+        let bit_reversal = (0..(1u32 << log_n)).map(|i| bitreverse(i, log_n)).collect();
+
+        FftTwiddles {
+            log_n,
+            omega,
+            powers,
+            bit_reversal: Arc::new(bit_reversal),
+        }
+    }
+
+    /// Returns the twiddle factors of the inverse FFT, over $\omega^{-1}$.
+    pub fn inverse(&self) -> Self {
+        // Since $\omega^{2^{k - 1}} = -1$, $\omega^{-i} = -\omega^{2^{k - 1} - i}$.
+        let half = self.powers.len();
+        let powers = (0..half)
+            .map(|i| {
+                if i == 0 {
+                    F::one()
+                } else {
+                    -self.powers[half - i]
+                }
+            })
+            .collect();
+
+        FftTwiddles {
+            log_n: self.log_n,
+            omega: self.omega.invert().unwrap(),
+            powers,
+            bit_reversal: self.bit_reversal.clone(),
+        }
+    }
+
+    /// Returns the element of multiplicative order $2^{\mathsf{log\_n}}$ that these
+    /// twiddle factors serve FFTs over.
+    fn omega(&self, log_n: u32) -> F {
+        assert!(log_n <= self.log_n);
+        let mut omega = self.omega;
+        for _ in log_n..self.log_n {
+            omega = omega.square();
         }
-        r
+        omega
     }
+}
+
+fn bitreverse(mut n: u32, l: u32) -> u32 {
+    let mut r = 0;
+    for _ in 0..l {
+        r = (r << 1) | (n & 1);
+        n >>= 1;
+    }
+    r
+}
+
+/// Hands an FFT to the backend registered for `G`, returning whether it accepted the
+/// work.
+fn backend_fft<G: Group>(a: &mut [G], omega: G::Scalar, log_n: u32) -> bool {
+    let backend = FFT_BACKENDS
+        .read()
+        .unwrap()
+        .get(&TypeId::of::<G>())
+        .cloned();
+    match backend {
+        Some(backend) => backend
+            .downcast_ref::<Arc<dyn FftBackend<G>>>()
+            .expect("keyed by group type")
+            .fft(a, omega, log_n),
+        None => false,
+    }
+}
+
+/// An implementation of the FFT over `G`, such as a vectorized one or one on a GPU,
+/// that [`best_fft`] hands its work to once it is registered with [`set_fft_backend`].
+pub trait FftBackend<G: Group>: Send + Sync {
+    /// Transforms `a` in place as [`best_fft`] does and returns `true`, or returns
+    /// `false` without modifying `a` if the backend cannot transform it, for example
+    /// because its device is unavailable or `a` is too small to be worth moving to it.
+    /// [`best_fft`] then transforms it on the CPU.
+    fn fft(&self, a: &mut [G], omega: G::Scalar, log_n: u32) -> bool;
+}
+
+lazy_static! {
+    static ref FFT_BACKENDS: RwLock<HashMap<TypeId, Arc<dyn Any + Send + Sync>>> =
+        RwLock::new(HashMap::new());
+}
+
+/// Registers `backend` to compute the FFTs over `G` of [`best_fft`], replacing any
+/// backend registered for `G` before it.
+pub fn set_fft_backend<G: Group>(backend: Arc<dyn FftBackend<G>>) {
+    let backend: Arc<dyn Any + Send + Sync> = Arc::new(backend);
+    FFT_BACKENDS
+        .write()
+        .unwrap()
+        .insert(TypeId::of::<G>(), backend);
+}
+
+/// Removes the backend registered for `G`, if any, so that [`best_fft`] computes its
+/// FFTs on the CPU.
+pub fn clear_fft_backend<G: Group>() {
+    FFT_BACKENDS.write().unwrap().remove(&TypeId::of::<G>());
+}
 
+fn serial_fft<G: Group>(a: &mut [G], omega: G::Scalar, log_n: u32) {
     let n = a.len() as u32;
     assert_eq!(n, 1 << log_n);
 
@@ -282,7 +515,56 @@ fn serial_fft<G: Group>(a: &mut [G], omega: G::Scalar, log_n: u32) {
     }
 }
 
-fn parallel_fft<G: Group>(a: &mut [G], omega: G::Scalar, log_n: u32, log_cpus: u32) {
+/// Performs an FFT as [`serial_fft`] does, reading the twiddle factors from `twiddles`
+/// rather than computing them.
+fn serial_fft_with_twiddles<G: Group>(a: &mut [G], log_n: u32, twiddles: &FftTwiddles<G::Scalar>) {
+    let n = a.len() as u32;
+    assert_eq!(n, 1 << log_n);
+    assert!(log_n <= twiddles.log_n);
+
+    // The twiddle factors may be those of a larger FFT, whose bit-reversal permutation
+    // of an index below $n$ is ours shifted left.
+    let shift = twiddles.log_n - log_n;
+    for k in 0..n {
+        let rk = twiddles.bit_reversal[k as usize] >> shift;
+        if k < rk {
+            a.swap(rk as usize, k as usize);
+        }
+    }
+
+    let mut m = 1;
+    for _ in 0..log_n {
+        // w_m = omega^(n / 2m) is the (2^{twiddles.log_n} / 2m)-th power of the
+        // twiddles' omega.
+        let stride = (1usize << twiddles.log_n) / (2 * m as usize);
+
+        let mut k = 0;
+        while k < n {
+            for j in 0..m {
+                let w = &twiddles.powers[j as usize * stride];
+                let mut t = a[(k + j + m) as usize];
+                t.group_scale(w);
+                a[(k + j + m) as usize] = a[(k + j) as usize];
+                a[(k + j + m) as usize].group_sub(&t);
+                a[(k + j) as usize].group_add(&t);
+            }
+
+            k += 2 * m;
+        }
+
+        m *= 2;
+    }
+}
+
+/// Performs an FFT by shuffling `a` into $2^{\mathsf{log\_cpus}}$ smaller FFTs, which
+/// `sub_fft` performs in parallel given their omega and the log of their size.
+fn parallel_fft<G: Group, S: Fn(&mut [G], G::Scalar, u32) + Sync>(
+    a: &mut [G],
+    omega: G::Scalar,
+    log_n: u32,
+    log_cpus: u32,
+    sub_fft: S,
+) {
     assert!(log_n >= log_cpus);
 
     let num_cpus = 1 << log_cpus;
@@ -290,8 +572,9 @@ fn parallel_fft<G: Group>(a: &mut [G], omega: G::Scalar, log_n: u32, log_cpus: u
     let mut tmp = vec![vec![G::group_zero(); 1 << log_new_n]; num_cpus];
     let new_omega = omega.pow(&[num_cpus as u64, 0, 0, 0]);
 
-    thread::scope(|scope| {
+    multicore::scope(|scope| {
         let a = &*a;
+        let sub_fft = &sub_fft;
 
         for (j, tmp) in tmp.iter_mut().enumerate() {
             scope.spawn(move |_| {
@@ -313,7 +596,7 @@ fn parallel_fft<G: Group>(a: &mut [G], omega: G::Scalar, log_n: u32, log_cpus: u
                 }
 
                 // Perform sub-FFT
-                serial_fft(tmp, new_omega, log_new_n);
+                sub_fft(tmp, new_omega, log_new_n);
             });
         }
     })
@@ -376,17 +659,39 @@ where
     q
 }
 
+/// Runs `f` with the parallel operations of this crate, such as those of proving and
+/// keygen, limited to `threads` threads rather than one per CPU.
+///
+/// The limit is a budget that the operations `f` runs on this thread share with the
+/// threads that they spawn, counting this thread: a nested operation only spawns
+/// threads that the budget has to spare, so that at most `threads` threads run at once,
+/// and a server creating many proofs at once can bound the CPU that each of them
+/// takes. A limit set within another can only lower it.
+///
+/// This function will panic if `threads` is zero.
+pub fn with_thread_limit<R>(threads: usize, f: impl FnOnce() -> R) -> R {
+    assert!(threads > 0, "at least one thread is required");
+    multicore::with_budget(threads, f)
+}
+
+/// Returns the number of threads that a parallel operation started now may use, which
+/// is the number of CPUs (or one, without the `multicore` feature) unless it is limited
+/// with [`with_thread_limit`].
+fn num_threads() -> usize {
+    multicore::available_threads()
+}
+
 /// This simple utility function will parallelize an operation that is to be
 /// performed over a mutable slice.
 pub fn parallelize<T: Send, F: Fn(&mut [T], usize) + Send + Clone>(v: &mut [T], f: F) {
     let n = v.len();
-    let num_cpus = num_cpus::get();
+    let num_cpus = num_threads();
     let mut chunk = (n as usize) / num_cpus;
     if chunk < num_cpus {
         chunk = n as usize;
     }
 
-    thread::scope(|scope| {
+    multicore::scope(|scope| {
         for (chunk_num, v) in v.chunks_mut(chunk).enumerate() {
             let f = f.clone();
             scope.spawn(move |_| {
@@ -398,6 +703,53 @@ pub fn parallelize<T: Send, F: Fn(&mut [T], usize) + Send + Clone>(v: &mut [T],
     .unwrap();
 }
 
+/// This replaces each element of `v` with the product of it and every element
+/// before it. The products are computed over chunks of `v` in parallel, and the
+/// product of each chunk is then carried into the chunks after it.
+pub fn parallel_prefix_product<F: Field>(v: &mut [F]) {
+    let n = v.len();
+    if n == 0 {
+        return;
+    }
+    let num_cpus = num_threads();
+    let mut chunk = n / num_cpus;
+    if chunk < num_cpus {
+        chunk = n;
+    }
+
+    multicore::scope(|scope| {
+        for v in v.chunks_mut(chunk) {
+            scope.spawn(move |_| {
+                let mut acc = F::one();
+                for value in v.iter_mut() {
+                    acc *= *value;
+                    *value = acc;
+                }
+            });
+        }
+    })
+    .unwrap();
+
+    // The product of all of the chunks before each chunk
+    let mut carries = Vec::with_capacity(n / chunk + 1);
+    let mut carry = F::one();
+    for v in v.chunks(chunk) {
+        carries.push(carry);
+        carry *= v[v.len() - 1];
+    }
+
+    multicore::scope(|scope| {
+        for (v, carry) in v.chunks_mut(chunk).zip(carries).skip(1) {
+            scope.spawn(move |_| {
+                for value in v.iter_mut() {
+                    *value *= carry;
+                }
+            });
+        }
+    })
+    .unwrap();
+}
+
 fn log2_floor(num: usize) -> u32 {
     assert!(num > 0);
 
@@ -488,3 +840,185 @@ fn test_lagrange_interpolate() {
         }
     }
 }
+
+#[test]
+fn test_parallel_prefix_product() {
+    for n in [0, 1, 2, 7, 100, 1 << 10] {
+        let values = (0..n).map(|_| Fp::rand()).collect::<Vec<_>>();
+
+        let mut products = values.clone();
+        parallel_prefix_product(&mut products);
+
+        let mut acc = Fp::one();
+        for (value, product) in values.iter().zip(products.iter()) {
+            acc *= value;
+            assert_eq!(acc, *product);
+        }
+    }
+}
+
+#[test]
+fn test_fft_backend() {
+    use ff::PrimeField;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// A group that no other test transforms, so that the backend registered for it
+    /// is not seen by tests running concurrently.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    struct Element(Fp);
+
+    impl Group for Element {
+        type Scalar = Fp;
+
+        fn group_zero() -> Self {
+            Element(Fp::zero())
+        }
+
+        fn group_add(&mut self, rhs: &Self) {
+            self.0 += rhs.0;
+        }
+
+        fn group_sub(&mut self, rhs: &Self) {
+            self.0 -= rhs.0;
+        }
+
+        fn group_scale(&mut self, by: &Fp) {
+            self.0 *= by;
+        }
+    }
+
+    /// A backend that only accepts FFTs of at least $2^4$ elements.
+    struct ThresholdBackend(AtomicUsize);
+
+    impl FftBackend<Element> for ThresholdBackend {
+        fn fft(&self, a: &mut [Element], omega: Fp, log_n: u32) -> bool {
+            if log_n < 4 {
+                return false;
+            }
+            self.0.fetch_add(1, Ordering::SeqCst);
+            serial_fft(a, omega, log_n);
+            true
+        }
+    }
+
+    let fft = |log_n: u32| {
+        let omega = Fp::ROOT_OF_UNITY.pow_vartime(&[1 << (Fp::S - log_n), 0, 0, 0]);
+        let values: Vec<_> = (0..(1 << log_n)).map(|_| Element(Fp::rand())).collect();
+        let mut expected = values.clone();
+        serial_fft(&mut expected, omega, log_n);
+        let mut a = values;
+        best_fft(&mut a, omega, log_n);
+        assert_eq!(a, expected);
+    };
+
+    let backend = Arc::new(ThresholdBackend(AtomicUsize::new(0)));
+    set_fft_backend::<Element>(backend.clone());
+    fft(2);
+    assert_eq!(backend.0.load(Ordering::SeqCst), 0);
+    fft(6);
+    assert_eq!(backend.0.load(Ordering::SeqCst), 1);
+
+    clear_fft_backend::<Element>();
+    fft(6);
+    assert_eq!(backend.0.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn test_thread_limit() {
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+    use std::thread::{self, ThreadId};
+
+    let chunks = |threads: usize| {
+        with_thread_limit(threads, || {
+            let chunks = AtomicUsize::new(0);
+            let mut v = vec![0u64; 1 << 10];
+            parallelize(&mut v, |v, start| {
+                chunks.fetch_add(1, Ordering::SeqCst);
+                for (i, v) in v.iter_mut().enumerate() {
+                    *v = (start + i) as u64;
+                }
+            });
+            assert!(v.iter().enumerate().all(|(i, v)| *v == i as u64));
+            chunks.load(Ordering::SeqCst)
+        })
+    };
+    assert_eq!(chunks(1), 1);
+    assert_eq!(chunks(4), 4);
+
+    // The limit only applies within `with_thread_limit`.
+    assert_eq!(num_threads(), multicore::current_num_threads());
+
+    // Parallel operations nested in those of spawned threads share the same budget, so
+    // the threads that run at once never exceed the limit.
+    let peak = |threads: usize| {
+        // The number of closures running on each thread, and the most threads that
+        // ran closures at once.
+        let running = Mutex::new((HashMap::<ThreadId, usize>::new(), 0));
+        let enter = || {
+            let mut running = running.lock().unwrap();
+            *running.0.entry(thread::current().id()).or_insert(0) += 1;
+            running.1 = running.1.max(running.0.len());
+        };
+        let exit = || {
+            let mut running = running.lock().unwrap();
+            let id = thread::current().id();
+            running.0.entry(id).and_modify(|count| *count -= 1);
+            if running.0[&id] == 0 {
+                running.0.remove(&id);
+            }
+        };
+        with_thread_limit(threads, || {
+            let mut v = vec![0u64; 1 << 8];
+            parallelize(&mut v, |v, _| {
+                enter();
+                let mut w = vec![0u64; 1 << 8];
+                parallelize(&mut w, |w, _| {
+                    enter();
+                    thread::sleep(std::time::Duration::from_millis(10));
+                    w.iter_mut().for_each(|w| *w += 1);
+                    exit();
+                });
+                v.iter_mut().for_each(|v| *v = w.iter().sum());
+                exit();
+            });
+            assert!(v.iter().all(|v| *v == 1 << 8));
+        });
+        running.into_inner().unwrap().1
+    };
+    assert_eq!(peak(1), 1);
+    assert!(peak(3) <= 3);
+    #[cfg(feature = "multicore")]
+    assert!(peak(4) > 1);
+}
+
+#[test]
+fn test_fft_twiddles() {
+    use ff::PrimeField;
+
+    const LOG_N: u32 = 6;
+    let omega = Fp::ROOT_OF_UNITY.pow_vartime(&[1 << (Fp::S - LOG_N), 0, 0, 0]);
+    let twiddles = FftTwiddles::new(omega, LOG_N);
+    let inv_twiddles = twiddles.inverse();
+
+    // The twiddle factors serve the FFTs of their own size and of every smaller one.
+    for log_n in [0, 1, 3, LOG_N] {
+        let omega = twiddles.omega(log_n);
+        let coeffs: Vec<_> = (0..(1 << log_n)).map(|_| Fp::rand()).collect();
+
+        let mut a = coeffs.clone();
+        best_fft_with_twiddles(&mut a, log_n, &twiddles);
+        let mut point = Fp::one();
+        for eval in a.iter() {
+            assert_eq!(*eval, eval_polynomial(&coeffs, point));
+            point *= omega;
+        }
+
+        best_fft_with_twiddles(&mut a, log_n, &inv_twiddles);
+        let divisor = Fp::from_u64(1 << log_n).invert().unwrap();
+        for (value, coeff) in a.iter().zip(coeffs.iter()) {
+            assert_eq!(*value * divisor, *coeff);
+        }
+    }
+}
@@ -0,0 +1,1209 @@
+//! Backends that compute on an NVIDIA GPU through CUDA, enabled with the `cuda`
+//! feature.
+//!
+//! The CUDA driver and the NVRTC runtime compiler are loaded when a backend is
+//! created, rather than linked, so that a binary built with the feature runs on
+//! machines without them: [`CudaMultiexp::new`] and [`CudaFft::new`] then return `None`, and the work
+//! stays on the CPU. The kernels are compiled for the device the first time a
+//! backend is created, and work with the 256-bit fields of this crate in Montgomery
+//! form with 32-bit limbs.
+
+use std::ffi::{c_void, CStr, CString};
+use std::fmt;
+use std::os::raw::{c_char, c_int, c_uint};
+use std::ptr;
+use std::sync::{Arc, Mutex};
+
+use ff::Field;
+use group::Group as _;
+
+use super::{
+    bitreverse, parallelize, CurveAffine, CurveExt, FftBackend, FieldExt, Group, MultiexpBackend,
+};
+
+/// The source of the kernels, in CUDA C.
+const KERNELS: &str = r#"
+typedef unsigned int u32;
+typedef unsigned long long u64;
+
+#define LIMBS 8
+
+struct Consts {
+    u32 p[LIMBS];
+    u32 r2[LIMBS];
+    u32 one[LIMBS];
+    u32 inv;
+};
+
+__device__ void load_consts(struct Consts *c, const u32 *consts) {
+    for (int i = 0; i < LIMBS; i++) {
+        c->p[i] = consts[i];
+        c->r2[i] = consts[LIMBS + i];
+        c->one[i] = i == 0;
+    }
+    c->inv = consts[2 * LIMBS];
+}
+
+__device__ bool is_zero(const u32 *a) {
+    u32 acc = 0;
+    for (int i = 0; i < LIMBS; i++) acc |= a[i];
+    return acc == 0;
+}
+
+__device__ void copy(u32 *r, const u32 *a) {
+    for (int i = 0; i < LIMBS; i++) r[i] = a[i];
+}
+
+// Sets r = t - p if t + 2^(32 LIMBS) top >= p, and r = t otherwise.
+__device__ void reduce_once(u32 *r, const u32 *t, u32 top, const u32 *p) {
+    u32 d[LIMBS];
+    u64 borrow = 0;
+    for (int i = 0; i < LIMBS; i++) {
+        u64 x = (u64)t[i] - p[i] - borrow;
+        d[i] = (u32)x;
+        borrow = x >> 63;
+    }
+    bool keep = top == 0 && borrow != 0;
+    for (int i = 0; i < LIMBS; i++) r[i] = keep ? t[i] : d[i];
+}
+
+__device__ void fadd(u32 *r, const u32 *a, const u32 *b, const u32 *p) {
+    u32 t[LIMBS];
+    u64 carry = 0;
+    for (int i = 0; i < LIMBS; i++) {
+        carry += (u64)a[i] + b[i];
+        t[i] = (u32)carry;
+        carry >>= 32;
+    }
+    reduce_once(r, t, (u32)carry, p);
+}
+
+__device__ void fsub(u32 *r, const u32 *a, const u32 *b, const u32 *p) {
+    u32 t[LIMBS];
+    u64 borrow = 0;
+    for (int i = 0; i < LIMBS; i++) {
+        u64 x = (u64)a[i] - b[i] - borrow;
+        t[i] = (u32)x;
+        borrow = x >> 63;
+    }
+    u64 carry = 0;
+    for (int i = 0; i < LIMBS; i++) {
+        carry += (u64)t[i] + (borrow ? p[i] : 0);
+        r[i] = (u32)carry;
+        carry >>= 32;
+    }
+}
+
+// Montgomery multiplication, r = a b / 2^(32 LIMBS), by coarsely integrated
+// operand scanning.
+__device__ void fmul(u32 *r, const u32 *a, const u32 *b, const struct Consts *c) {
+    u32 t[LIMBS + 2];
+    for (int i = 0; i < LIMBS + 2; i++) t[i] = 0;
+    for (int i = 0; i < LIMBS; i++) {
+        u64 carry = 0;
+        for (int j = 0; j < LIMBS; j++) {
+            carry += (u64)a[j] * b[i] + t[j];
+            t[j] = (u32)carry;
+            carry >>= 32;
+        }
+        carry += t[LIMBS];
+        t[LIMBS] = (u32)carry;
+        t[LIMBS + 1] = (u32)(carry >> 32);
+
+        u32 m = t[0] * c->inv;
+        carry = ((u64)m * c->p[0] + t[0]) >> 32;
+        for (int j = 1; j < LIMBS; j++) {
+            carry += (u64)m * c->p[j] + t[j];
+            t[j - 1] = (u32)carry;
+            carry >>= 32;
+        }
+        carry += t[LIMBS];
+        t[LIMBS - 1] = (u32)carry;
+        t[LIMBS] = t[LIMBS + 1] + (u32)(carry >> 32);
+    }
+    reduce_once(r, t, t[LIMBS], c->p);
+}
+
+// A point in Jacobian coordinates, which is the identity when z = 0.
+struct Point {
+    u32 x[LIMBS];
+    u32 y[LIMBS];
+    u32 z[LIMBS];
+};
+
+// Doubles a point on a curve y^2 = x^3 + b.
+__device__ void dbl(struct Point *q, const struct Consts *c) {
+    const u32 *p = c->p;
+    u32 a[LIMBS], b[LIMBS], cc[LIMBS], d[LIMBS], e[LIMBS], f[LIMBS], t[LIMBS];
+    fmul(a, q->x, q->x, c);
+    fmul(b, q->y, q->y, c);
+    fmul(cc, b, b, c);
+    // d = 2 ((x + b)^2 - a - cc)
+    fadd(t, q->x, b, p);
+    fmul(t, t, t, c);
+    fsub(t, t, a, p);
+    fsub(t, t, cc, p);
+    fadd(d, t, t, p);
+    // e = 3 a, f = e^2
+    fadd(e, a, a, p);
+    fadd(e, e, a, p);
+    fmul(f, e, e, c);
+    // z = 2 y z
+    fmul(t, q->y, q->z, c);
+    fadd(q->z, t, t, p);
+    // x = f - 2 d
+    fsub(t, f, d, p);
+    fsub(q->x, t, d, p);
+    // y = e (d - x) - 8 cc
+    fsub(t, d, q->x, p);
+    fmul(t, e, t, c);
+    fadd(cc, cc, cc, p);
+    fadd(cc, cc, cc, p);
+    fadd(cc, cc, cc, p);
+    fsub(q->y, t, cc, p);
+}
+
+// Adds the affine point (x2, y2) to a point on a curve y^2 = x^3 + b.
+__device__ void madd(struct Point *q, const u32 *x2, const u32 *y2, const struct Consts *c,
+                     const u32 *one) {
+    const u32 *p = c->p;
+    if (is_zero(q->z)) {
+        copy(q->x, x2);
+        copy(q->y, y2);
+        copy(q->z, one);
+        return;
+    }
+    u32 z1z1[LIMBS], u2[LIMBS], s2[LIMBS], h[LIMBS], hh[LIMBS], i[LIMBS], j[LIMBS], r[LIMBS],
+        v[LIMBS], t[LIMBS];
+    fmul(z1z1, q->z, q->z, c);
+    fmul(u2, x2, z1z1, c);
+    fmul(s2, y2, q->z, c);
+    fmul(s2, s2, z1z1, c);
+    fsub(h, u2, q->x, p);
+    fsub(r, s2, q->y, p);
+    if (is_zero(h)) {
+        if (is_zero(r)) {
+            dbl(q, c);
+        } else {
+            for (int k = 0; k < LIMBS; k++) q->z[k] = 0;
+        }
+        return;
+    }
+    fadd(r, r, r, p);
+    fmul(hh, h, h, c);
+    fadd(i, hh, hh, p);
+    fadd(i, i, i, p);
+    fmul(j, h, i, c);
+    fmul(v, q->x, i, c);
+    // z = (z + h)^2 - z1z1 - hh
+    fadd(t, q->z, h, p);
+    fmul(t, t, t, c);
+    fsub(t, t, z1z1, p);
+    fsub(q->z, t, hh, p);
+    // x = r^2 - j - 2 v
+    fmul(t, r, r, c);
+    fsub(t, t, j, p);
+    fsub(t, t, v, p);
+    fsub(q->x, t, v, p);
+    // y = r (v - x) - 2 y j
+    fsub(t, v, q->x, p);
+    fmul(t, r, t, c);
+    fmul(j, q->y, j, c);
+    fadd(j, j, j, p);
+    fsub(q->y, t, j, p);
+}
+
+// Adds the point r to the point q on a curve y^2 = x^3 + b.
+__device__ void add(struct Point *q, const struct Point *r, const struct Consts *c) {
+    const u32 *p = c->p;
+    if (is_zero(r->z)) return;
+    if (is_zero(q->z)) {
+        *q = *r;
+        return;
+    }
+    u32 z1z1[LIMBS], z2z2[LIMBS], u1[LIMBS], u2[LIMBS], s1[LIMBS], s2[LIMBS], h[LIMBS],
+        i[LIMBS], j[LIMBS], rr[LIMBS], v[LIMBS], t[LIMBS];
+    fmul(z1z1, q->z, q->z, c);
+    fmul(z2z2, r->z, r->z, c);
+    fmul(u1, q->x, z2z2, c);
+    fmul(u2, r->x, z1z1, c);
+    fmul(s1, q->y, r->z, c);
+    fmul(s1, s1, z2z2, c);
+    fmul(s2, r->y, q->z, c);
+    fmul(s2, s2, z1z1, c);
+    fsub(h, u2, u1, p);
+    fsub(rr, s2, s1, p);
+    if (is_zero(h)) {
+        if (is_zero(rr)) {
+            dbl(q, c);
+        } else {
+            for (int k = 0; k < LIMBS; k++) q->z[k] = 0;
+        }
+        return;
+    }
+    fadd(rr, rr, rr, p);
+    // i = (2 h)^2, j = h i
+    fadd(i, h, h, p);
+    fmul(i, i, i, c);
+    fmul(j, h, i, c);
+    fmul(v, u1, i, c);
+    // z = ((z1 + z2)^2 - z1z1 - z2z2) h
+    fadd(t, q->z, r->z, p);
+    fmul(t, t, t, c);
+    fsub(t, t, z1z1, p);
+    fsub(t, t, z2z2, p);
+    fmul(q->z, t, h, c);
+    // x = rr^2 - j - 2 v
+    fmul(t, rr, rr, c);
+    fsub(t, t, j, p);
+    fsub(t, t, v, p);
+    fsub(q->x, t, v, p);
+    // y = rr (v - x) - 2 s1 j
+    fsub(t, v, q->x, p);
+    fmul(t, rr, t, c);
+    fmul(j, s1, j, c);
+    fadd(j, j, j, p);
+    fsub(q->y, t, j, p);
+}
+
+__device__ void set_identity(struct Point *q) {
+    for (int k = 0; k < LIMBS; k++) {
+        q->x[k] = 0;
+        q->y[k] = 0;
+        q->z[k] = 0;
+    }
+}
+
+// Converts the n field elements of values from their canonical form to Montgomery
+// form.
+extern "C" __global__ void to_montgomery(u32 *values, u32 n, const u32 *consts) {
+    u32 index = blockIdx.x * blockDim.x + threadIdx.x;
+    if (index >= n) return;
+    struct Consts c;
+    load_consts(&c, consts);
+    u32 *value = values + index * LIMBS;
+    fmul(value, value, c.r2, &c);
+}
+
+// Sums the bases in each of the buckets, one bucket per thread. The bases in bucket t
+// are those at indices[offsets[t]] to indices[offsets[t + 1] - 1], each an affine point
+// in Montgomery form, and their sum is written to sums in Montgomery form.
+extern "C" __global__ void bucket_sums(const u32 *bases, const u32 *indices, const u32 *offsets,
+                                       u32 buckets, const u32 *consts, u32 *sums) {
+    u32 bucket = blockIdx.x * blockDim.x + threadIdx.x;
+    if (bucket >= buckets) return;
+    struct Consts c;
+    load_consts(&c, consts);
+    u32 one[LIMBS];
+    fmul(one, c.one, c.r2, &c);
+
+    struct Point acc;
+    set_identity(&acc);
+    for (u32 i = offsets[bucket]; i < offsets[bucket + 1]; i++) {
+        const u32 *base = bases + (u64)indices[i] * 2 * LIMBS;
+        madd(&acc, base, base + LIMBS, &c, one);
+    }
+
+    u32 *sum = sums + (u64)bucket * 3 * LIMBS;
+    copy(sum, acc.x);
+    copy(sum + LIMBS, acc.y);
+    copy(sum + 2 * LIMBS, acc.z);
+}
+
+// Computes the sum of [j + 1] B_j over the sums B_j of the buckets of each of the
+// windows, one window per thread, as the sum of the running sums of B_j from the last
+// bucket. The sums are written to out in canonical Jacobian coordinates.
+extern "C" __global__ void window_sums(const u32 *sums, u32 buckets, u32 windows,
+                                       const u32 *consts, u32 *out) {
+    u32 window = blockIdx.x * blockDim.x + threadIdx.x;
+    if (window >= windows) return;
+    struct Consts c;
+    load_consts(&c, consts);
+
+    struct Point running, acc, bucket;
+    set_identity(&running);
+    set_identity(&acc);
+    for (int j = (int)buckets - 1; j >= 0; j--) {
+        const u32 *sum = sums + ((u64)window * buckets + j) * 3 * LIMBS;
+        copy(bucket.x, sum);
+        copy(bucket.y, sum + LIMBS);
+        copy(bucket.z, sum + 2 * LIMBS);
+        add(&running, &bucket, &c);
+        add(&acc, &running, &c);
+    }
+
+    u32 *result = out + window * 3 * LIMBS;
+    fmul(result, acc.x, c.one, &c);
+    fmul(result + LIMBS, acc.y, c.one, &c);
+    fmul(result + 2 * LIMBS, acc.z, c.one, &c);
+}
+
+// Performs the layer of radix-2 butterflies between the canonical elements of values
+// half apart in each run of 2 half, one per thread. twiddles holds w^j 2^(32 LIMBS)
+// for j < n / 2, so that its Montgomery product with an element is the element times
+// w^j.
+extern "C" __global__ void fft_layer(u32 *values, const u32 *twiddles, u32 n, u32 half,
+                                     const u32 *consts) {
+    u32 index = blockIdx.x * blockDim.x + threadIdx.x;
+    if (index >= n / 2) return;
+    struct Consts c;
+    load_consts(&c, consts);
+
+    u32 j = index % half;
+    u32 *lo = values + ((u64)(index - j) * 2 + j) * LIMBS;
+    u32 *hi = lo + (u64)half * LIMBS;
+    u32 t[LIMBS];
+    fmul(t, hi, twiddles + (u64)j * (n / (2 * half)) * LIMBS, &c);
+    fsub(hi, lo, t, c.p);
+    fadd(lo, lo, t, c.p);
+}
+"#;
+
+/// The number of 32-bit limbs of a field element.
+const LIMBS: usize = 8;
+
+/// The number of threads in a block of each launch.
+const BLOCK_SIZE: u32 = 128;
+
+/// The most bits of a window of a multi-exponentiation, which bounds the buckets whose
+/// running sum a thread computes.
+const MAX_WINDOW_BITS: usize = 12;
+
+#[cfg(unix)]
+extern "C" {
+    fn dlopen(filename: *const c_char, flag: c_int) -> *mut c_void;
+    fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void;
+}
+
+#[cfg(unix)]
+const RTLD_NOW: c_int = 2;
+
+/// Opens the first of `names` that the dynamic loader finds.
+#[cfg(unix)]
+fn open_library(names: &[&str]) -> Option<*mut c_void> {
+    names.iter().find_map(|name| {
+        let name = CString::new(*name).unwrap();
+        // `name` is a nul-terminated string that outlives the call.
+        #[allow(unsafe_code)]
+        let handle = unsafe { dlopen(name.as_ptr(), RTLD_NOW) };
+        if handle.is_null() {
+            None
+        } else {
+            Some(handle)
+        }
+    })
+}
+
+#[cfg(not(unix))]
+fn open_library(_: &[&str]) -> Option<*mut c_void> {
+    None
+}
+
+/// Looks up the function `name` in `library`, as a function pointer of type `F`.
+///
+/// # Safety
+///
+/// `F` must be a function pointer type with the signature of the function.
+#[cfg(unix)]
+#[allow(unsafe_code)]
+unsafe fn symbol<F: Copy>(library: *mut c_void, name: &str) -> Option<F> {
+    assert_eq!(std::mem::size_of::<F>(), std::mem::size_of::<*mut c_void>());
+    let name = CString::new(name).unwrap();
+    let address = dlsym(library, name.as_ptr());
+    if address.is_null() {
+        None
+    } else {
+        Some(std::mem::transmute_copy(&address))
+    }
+}
+
+#[cfg(not(unix))]
+#[allow(unsafe_code)]
+unsafe fn symbol<F: Copy>(_: *mut c_void, _: &str) -> Option<F> {
+    None
+}
+
+type CuResult = c_int;
+type CuDevice = c_int;
+type CuContext = *mut c_void;
+type CuModule = *mut c_void;
+type CuFunction = *mut c_void;
+type CuDevicePtr = u64;
+type NvrtcProgram = *mut c_void;
+
+const CUDA_SUCCESS: CuResult = 0;
+
+/// The functions of the CUDA driver API that the backends call.
+#[allow(non_snake_case)]
+struct Driver {
+    cuInit: unsafe extern "C" fn(c_uint) -> CuResult,
+    cuDeviceGet: unsafe extern "C" fn(*mut CuDevice, c_int) -> CuResult,
+    cuDevicePrimaryCtxRetain: unsafe extern "C" fn(*mut CuContext, CuDevice) -> CuResult,
+    cuCtxSetCurrent: unsafe extern "C" fn(CuContext) -> CuResult,
+    cuModuleLoadData: unsafe extern "C" fn(*mut CuModule, *const c_void) -> CuResult,
+    cuModuleGetFunction: unsafe extern "C" fn(*mut CuFunction, CuModule, *const c_char) -> CuResult,
+    cuMemAlloc_v2: unsafe extern "C" fn(*mut CuDevicePtr, usize) -> CuResult,
+    cuMemFree_v2: unsafe extern "C" fn(CuDevicePtr) -> CuResult,
+    cuMemcpyHtoD_v2: unsafe extern "C" fn(CuDevicePtr, *const c_void, usize) -> CuResult,
+    cuMemcpyDtoH_v2: unsafe extern "C" fn(*mut c_void, CuDevicePtr, usize) -> CuResult,
+    #[allow(clippy::type_complexity)]
+    cuLaunchKernel: unsafe extern "C" fn(
+        CuFunction,
+        c_uint,
+        c_uint,
+        c_uint,
+        c_uint,
+        c_uint,
+        c_uint,
+        c_uint,
+        *mut c_void,
+        *mut *mut c_void,
+        *mut *mut c_void,
+    ) -> CuResult,
+    cuCtxSynchronize: unsafe extern "C" fn() -> CuResult,
+}
+
+/// The functions of NVRTC that compile the kernels.
+#[allow(non_snake_case)]
+struct Nvrtc {
+    nvrtcCreateProgram: unsafe extern "C" fn(
+        *mut NvrtcProgram,
+        *const c_char,
+        *const c_char,
+        c_int,
+        *const *const c_char,
+        *const *const c_char,
+    ) -> c_int,
+    nvrtcCompileProgram: unsafe extern "C" fn(NvrtcProgram, c_int, *const *const c_char) -> c_int,
+    nvrtcGetPTXSize: unsafe extern "C" fn(NvrtcProgram, *mut usize) -> c_int,
+    nvrtcGetPTX: unsafe extern "C" fn(NvrtcProgram, *mut c_char) -> c_int,
+    nvrtcDestroyProgram: unsafe extern "C" fn(*mut NvrtcProgram) -> c_int,
+}
+
+macro_rules! load {
+    ($library:expr, $ty:ident { $($name:ident),* $(,)? }) => {
+        $ty {
+            $($name: symbol($library, stringify!($name))?,)*
+        }
+    };
+}
+
+/// Converts a status of the driver API to a `Result`.
+fn check(result: CuResult) -> Option<()> {
+    if result == CUDA_SUCCESS {
+        Some(())
+    } else {
+        None
+    }
+}
+
+/// A CUDA device, with the kernels compiled for it.
+struct Device {
+    driver: Driver,
+    context: CuContext,
+    functions: Vec<(&'static str, CuFunction)>,
+    // Launches on the device are serialized, so that each sees its own buffers.
+    lock: Mutex<()>,
+}
+
+// The driver API may be called from any thread once the context is made current on
+// it, which `Device::run` does.
+#[allow(unsafe_code)]
+unsafe impl Send for Device {}
+#[allow(unsafe_code)]
+unsafe impl Sync for Device {}
+
+impl Device {
+    /// Opens the first CUDA device and compiles the kernels for it, or returns `None`
+    /// if the driver, NVRTC or a device is unavailable.
+    fn open() -> Option<Self> {
+        // The fields of `Driver` and `Nvrtc` have the signatures of the functions that
+        // they are named after.
+        let library = open_library(&["libcuda.so.1", "libcuda.so"])?;
+        #[allow(unsafe_code)]
+        let driver = unsafe {
+            load!(
+                library,
+                Driver {
+                    cuInit,
+                    cuDeviceGet,
+                    cuDevicePrimaryCtxRetain,
+                    cuCtxSetCurrent,
+                    cuModuleLoadData,
+                    cuModuleGetFunction,
+                    cuMemAlloc_v2,
+                    cuMemFree_v2,
+                    cuMemcpyHtoD_v2,
+                    cuMemcpyDtoH_v2,
+                    cuLaunchKernel,
+                    cuCtxSynchronize,
+                }
+            )
+        };
+        let library = open_library(&[
+            "libnvrtc.so",
+            "libnvrtc.so.13",
+            "libnvrtc.so.12",
+            "libnvrtc.so.11.2",
+        ])?;
+        #[allow(unsafe_code)]
+        let nvrtc = unsafe {
+            load!(
+                library,
+                Nvrtc {
+                    nvrtcCreateProgram,
+                    nvrtcCompileProgram,
+                    nvrtcGetPTXSize,
+                    nvrtcGetPTX,
+                    nvrtcDestroyProgram,
+                }
+            )
+        };
+        let ptx = compile(&nvrtc)?;
+
+        // Each call is passed pointers to locals that outlive it, and the module is
+        // loaded from the nul-terminated PTX.
+        #[allow(unsafe_code)]
+        let (context, functions) = unsafe {
+            check((driver.cuInit)(0))?;
+            let mut device = 0;
+            check((driver.cuDeviceGet)(&mut device, 0))?;
+            let mut context = ptr::null_mut();
+            check((driver.cuDevicePrimaryCtxRetain)(&mut context, device))?;
+            check((driver.cuCtxSetCurrent)(context))?;
+
+            let mut module = ptr::null_mut();
+            check((driver.cuModuleLoadData)(
+                &mut module,
+                ptx.as_ptr() as *const c_void,
+            ))?;
+            let functions = ["to_montgomery", "bucket_sums", "window_sums", "fft_layer"]
+                .iter()
+                .map(|name| {
+                    let mut function = ptr::null_mut();
+                    let c_name = CString::new(*name).unwrap();
+                    check((driver.cuModuleGetFunction)(
+                        &mut function,
+                        module,
+                        c_name.as_ptr(),
+                    ))?;
+                    Some((*name, function))
+                })
+                .collect::<Option<_>>()?;
+            (context, functions)
+        };
+
+        Some(Device {
+            driver,
+            context,
+            functions,
+            lock: Mutex::new(()),
+        })
+    }
+
+    /// Runs `f` with exclusive use of the device, returning `None` if any call to the
+    /// driver fails.
+    fn run<T>(&self, f: impl FnOnce(&Session) -> Option<T>) -> Option<T> {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        // The context was retained when the device was opened.
+        #[allow(unsafe_code)]
+        let result = unsafe { (self.driver.cuCtxSetCurrent)(self.context) };
+        check(result)?;
+        f(&Session { device: self })
+    }
+}
+
+/// Compiles the kernels to PTX.
+// The strings are nul-terminated and outlive the program, the PTX is read into a buffer
+// of the size that NVRTC reports, and the program is destroyed once.
+#[allow(unsafe_code)]
+fn compile(nvrtc: &Nvrtc) -> Option<CString> {
+    let source = CString::new(KERNELS).unwrap();
+    let name = CString::new("halo2.cu").unwrap();
+    unsafe {
+        let mut program = ptr::null_mut();
+        if (nvrtc.nvrtcCreateProgram)(
+            &mut program,
+            source.as_ptr(),
+            name.as_ptr(),
+            0,
+            ptr::null(),
+            ptr::null(),
+        ) != 0
+        {
+            return None;
+        }
+        let ptx = (|| {
+            if (nvrtc.nvrtcCompileProgram)(program, 0, ptr::null()) != 0 {
+                return None;
+            }
+            let mut size = 0;
+            if (nvrtc.nvrtcGetPTXSize)(program, &mut size) != 0 {
+                return None;
+            }
+            let mut ptx = vec![0u8; size];
+            if (nvrtc.nvrtcGetPTX)(program, ptx.as_mut_ptr() as *mut c_char) != 0 {
+                return None;
+            }
+            Some(CStr::from_bytes_until_nul(&ptx).ok()?.to_owned())
+        })();
+        (nvrtc.nvrtcDestroyProgram)(&mut program);
+        ptx
+    }
+}
+
+/// Exclusive use of a [`Device`], during which buffers are allocated and kernels
+/// launched.
+struct Session<'a> {
+    device: &'a Device,
+}
+
+/// A buffer of 32-bit words in the memory of a device, freed when dropped.
+struct Buffer<'a> {
+    driver: &'a Driver,
+    pointer: CuDevicePtr,
+    len: usize,
+}
+
+impl<'a> Buffer<'a> {
+    fn read(&self) -> Option<Vec<u32>> {
+        let mut words = vec![0u32; self.len];
+        // The buffer and `words` both hold `len` words.
+        #[allow(unsafe_code)]
+        let result = unsafe {
+            (self.driver.cuMemcpyDtoH_v2)(
+                words.as_mut_ptr() as *mut c_void,
+                self.pointer,
+                self.len * 4,
+            )
+        };
+        check(result)?;
+        Some(words)
+    }
+}
+
+impl<'a> Drop for Buffer<'a> {
+    fn drop(&mut self) {
+        // The buffer was allocated by the driver, and is freed once.
+        #[allow(unsafe_code)]
+        let _ = unsafe { (self.driver.cuMemFree_v2)(self.pointer) };
+    }
+}
+
+/// An argument of a kernel.
+enum Arg<'a, 'b> {
+    Buffer(&'b Buffer<'a>),
+    U32(u32),
+}
+
+impl<'a> Session<'a> {
+    /// Allocates a buffer of `len` words.
+    fn alloc(&self, len: usize) -> Option<Buffer<'a>> {
+        let driver = &self.device.driver;
+        let mut pointer = 0;
+        // `pointer` outlives the call.
+        #[allow(unsafe_code)]
+        let result = unsafe { (driver.cuMemAlloc_v2)(&mut pointer, len.max(1) * 4) };
+        check(result)?;
+        Some(Buffer {
+            driver,
+            pointer,
+            len,
+        })
+    }
+
+    /// Allocates a buffer holding `words`.
+    fn upload(&self, words: &[u32]) -> Option<Buffer<'a>> {
+        let buffer = self.alloc(words.len())?;
+        // The buffer and `words` both hold `words.len()` words.
+        #[allow(unsafe_code)]
+        let result = unsafe {
+            (self.device.driver.cuMemcpyHtoD_v2)(
+                buffer.pointer,
+                words.as_ptr() as *const c_void,
+                words.len() * 4,
+            )
+        };
+        check(result)?;
+        Some(buffer)
+    }
+
+    /// Launches the kernel `name` on `threads` threads with `args`, and waits for it
+    /// to complete.
+    fn launch(&self, name: &str, threads: usize, args: &[Arg]) -> Option<()> {
+        let device = self.device;
+        let function = device
+            .functions
+            .iter()
+            .find(|(function, _)| *function == name)
+            .expect("kernel is compiled")
+            .1;
+        let mut values: Vec<u64> = args
+            .iter()
+            .map(|arg| match arg {
+                Arg::Buffer(buffer) => buffer.pointer,
+                Arg::U32(value) => *value as u64,
+            })
+            .collect();
+        // Each parameter points at its value, which the driver reads with the size of
+        // the kernel's parameter; the low bytes of a u64 hold a u32 on the little-endian
+        // hosts that CUDA supports.
+        let mut params: Vec<*mut c_void> = values
+            .iter_mut()
+            .map(|value| value as *mut u64 as *mut c_void)
+            .collect();
+        let blocks = (threads as u32).div_ceil(BLOCK_SIZE);
+        // `params` has a pointer to a value for each parameter of the kernel, and both
+        // outlive the launch, which is waited for.
+        #[allow(unsafe_code)]
+        let result = unsafe {
+            check((device.driver.cuLaunchKernel)(
+                function,
+                blocks.max(1),
+                1,
+                1,
+                BLOCK_SIZE,
+                1,
+                1,
+                0,
+                ptr::null_mut(),
+                params.as_mut_ptr(),
+                ptr::null_mut(),
+            ))?;
+            (device.driver.cuCtxSynchronize)()
+        };
+        check(result)
+    }
+}
+
+/// Returns the words of the canonical encoding of a field element.
+fn to_words<F: FieldExt>(value: &F) -> [u32; LIMBS] {
+    let bytes = value.to_bytes();
+    let mut words = [0; LIMBS];
+    for (word, bytes) in words.iter_mut().zip(bytes.chunks(4)) {
+        *word = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    }
+    words
+}
+
+/// Returns the field element with the canonical encoding `words`, if it is one.
+fn from_words<F: FieldExt>(words: &[u32]) -> Option<F> {
+    let mut bytes = [0; 32];
+    for (bytes, word) in bytes.chunks_mut(4).zip(words.iter()) {
+        bytes.copy_from_slice(&word.to_le_bytes());
+    }
+    Option::from(F::from_bytes(&bytes))
+}
+
+/// Returns the constants that the kernels take for arithmetic in `F`: its modulus $p$,
+/// $2^{512} \bmod p$ and $-p^{-1} \bmod 2^{32}$.
+fn consts<F: FieldExt>() -> Vec<u32> {
+    // p - 1 is even, so adding one to it does not carry.
+    let mut p = to_words(&-F::one());
+    p[0] += 1;
+    let r2 = to_words(&F::from_u64(2).pow(&[512, 0, 0, 0]));
+
+    // p^{-1} mod 2^32 by Newton iteration, doubling the correct bits each time.
+    let mut inv = 1u32;
+    for _ in 0..5 {
+        inv = inv.wrapping_mul(2u32.wrapping_sub(p[0].wrapping_mul(inv)));
+    }
+
+    p.iter()
+        .chain(r2.iter())
+        .copied()
+        .chain(Some(inv.wrapping_neg()))
+        .collect()
+}
+
+/// Returns the `c` bits of the scalar `words` from bit `start`, which is below 256.
+fn digit(words: &[u32; LIMBS], start: usize, c: usize) -> usize {
+    let (word, shift) = (start / 32, start % 32);
+    let mut bits = u64::from(words[word]) >> shift;
+    if word + 1 < LIMBS {
+        bits |= u64::from(words[word + 1]) << (32 - shift);
+    }
+    (bits & ((1 << c) - 1)) as usize
+}
+
+/// Sorts the indices of `scalars` into the buckets of their digits in each window of
+/// `c` bits, where bucket $j$ of a window holds the scalars whose digit in the window is
+/// $j + 1$. Returns the indices of the buckets of each window in turn, and the offset of
+/// each bucket in them, followed by their number.
+fn sort_into_buckets(scalars: &[[u32; LIMBS]], c: usize) -> (Vec<u32>, Vec<u32>) {
+    let windows = (32 * LIMBS).div_ceil(c);
+    let buckets = (1 << c) - 1;
+
+    let mut sorted = vec![(vec![], vec![]); windows];
+    parallelize(&mut sorted, |sorted, start| {
+        for (window, (indices, counts)) in sorted.iter_mut().enumerate() {
+            let digits: Vec<usize> = scalars
+                .iter()
+                .map(|scalar| digit(scalar, (start + window) * c, c))
+                .collect();
+            *counts = vec![0u32; buckets];
+            for digit in digits.iter().filter(|digit| **digit != 0) {
+                counts[digit - 1] += 1;
+            }
+            let mut next: Vec<usize> = counts
+                .iter()
+                .scan(0, |offset, count| {
+                    let start = *offset;
+                    *offset += *count as usize;
+                    Some(start)
+                })
+                .collect();
+            *indices = vec![0u32; counts.iter().sum::<u32>() as usize];
+            for (index, digit) in digits.iter().enumerate() {
+                if *digit != 0 {
+                    indices[next[digit - 1]] = index as u32;
+                    next[digit - 1] += 1;
+                }
+            }
+        }
+    });
+
+    let offsets = Some(0)
+        .into_iter()
+        .chain(
+            sorted
+                .iter()
+                .flat_map(|(_, counts)| counts.iter())
+                .scan(0, |offset, count| {
+                    *offset += count;
+                    Some(*offset)
+                }),
+        )
+        .collect();
+    let indices = sorted
+        .into_iter()
+        .flat_map(|(indices, _)| indices)
+        .collect();
+    (indices, offsets)
+}
+
+/// A [`MultiexpBackend`] that computes multi-exponentiations on a CUDA device, over
+/// curves of the form $y^2 = x^3 + b$, with Pippenger's bucket method.
+///
+/// The scalars are split into windows of $c$ bits, and the terms are sorted into the
+/// buckets of the digits of each window on the CPU. A thread on the device sums the
+/// bases of each bucket, and then a thread per window sums its buckets weighted by
+/// their digits. The windows are combined on the CPU. Multi-exponentiations smaller
+/// than [`min_size`](CudaMultiexp::with_min_size), or over another form of curve, are
+/// left to the CPU, as is any on which the device fails.
+#[derive(Clone)]
+pub struct CudaMultiexp {
+    device: Arc<Device>,
+    min_size: usize,
+}
+
+impl fmt::Debug for CudaMultiexp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CudaMultiexp")
+            .field("min_size", &self.min_size)
+            .finish()
+    }
+}
+
+impl CudaMultiexp {
+    /// Opens the first CUDA device and compiles the kernels for it, or returns `None`
+    /// if the CUDA driver, NVRTC or a device is unavailable.
+    pub fn new() -> Option<Self> {
+        Some(CudaMultiexp {
+            device: Arc::new(Device::open()?),
+            min_size: 1 << 12,
+        })
+    }
+
+    /// Sets the number of terms below which multi-exponentiations are left to the
+    /// CPU, which by default is $2^{12}$.
+    pub fn with_min_size(mut self, min_size: usize) -> Self {
+        self.min_size = min_size;
+        self
+    }
+}
+
+impl<C: CurveAffine> MultiexpBackend<C> for CudaMultiexp {
+    fn multiexp(&self, coeffs: &[C::Scalar], bases: &[C]) -> Option<C::Curve> {
+        if coeffs.len() < self.min_size || !C::a().is_zero() {
+            return None;
+        }
+
+        // The identity has no affine coordinates, and adds nothing to the sum.
+        let mut base_words = Vec::with_capacity(bases.len() * 2 * LIMBS);
+        let mut scalars = Vec::with_capacity(coeffs.len());
+        for (coeff, base) in coeffs.iter().zip(bases.iter()) {
+            if let Some((x, y)) = Option::from(base.get_xy()) {
+                base_words.extend_from_slice(&to_words(&x));
+                base_words.extend_from_slice(&to_words(&y));
+                scalars.push(to_words(coeff));
+            }
+        }
+        let n = scalars.len();
+        if n == 0 {
+            return Some(C::Curve::identity());
+        }
+
+        let c = ((n as f64).ln().ceil() as usize).clamp(1, MAX_WINDOW_BITS);
+        let windows = (32 * LIMBS).div_ceil(c);
+        let buckets = (1 << c) - 1;
+        // The indices of the terms of all the buckets are addressed with 32 bits.
+        if n.checked_mul(windows)? > u32::MAX as usize {
+            return None;
+        }
+
+        let (indices, offsets) = sort_into_buckets(&scalars, c);
+
+        let sums = self.device.run(|session| {
+            let consts = session.upload(&consts::<C::Base>())?;
+            let bases = session.upload(&base_words)?;
+            let indices = session.upload(&indices)?;
+            let offsets = session.upload(&offsets)?;
+            let bucket_sums = session.alloc(windows * buckets * 3 * LIMBS)?;
+            let out = session.alloc(windows * 3 * LIMBS)?;
+            session.launch(
+                "to_montgomery",
+                2 * n,
+                &[
+                    Arg::Buffer(&bases),
+                    Arg::U32((2 * n) as u32),
+                    Arg::Buffer(&consts),
+                ],
+            )?;
+            session.launch(
+                "bucket_sums",
+                windows * buckets,
+                &[
+                    Arg::Buffer(&bases),
+                    Arg::Buffer(&indices),
+                    Arg::Buffer(&offsets),
+                    Arg::U32((windows * buckets) as u32),
+                    Arg::Buffer(&consts),
+                    Arg::Buffer(&bucket_sums),
+                ],
+            )?;
+            session.launch(
+                "window_sums",
+                windows,
+                &[
+                    Arg::Buffer(&bucket_sums),
+                    Arg::U32(buckets as u32),
+                    Arg::U32(windows as u32),
+                    Arg::Buffer(&consts),
+                    Arg::Buffer(&out),
+                ],
+            )?;
+            out.read()
+        })?;
+
+        // The sum is that of the window sums, each shifted by the bits below it.
+        sums.chunks(3 * LIMBS)
+            .rev()
+            .try_fold(C::Curve::identity(), |acc, sum| {
+                let acc = (0..c).fold(acc, |acc, _| acc.double());
+                let z: <C::CurveExt as CurveExt>::Base = from_words(&sum[2 * LIMBS..])?;
+                if z.is_zero() {
+                    return Some(acc);
+                }
+                let x = from_words(&sum[..LIMBS])?;
+                let y = from_words(&sum[LIMBS..2 * LIMBS])?;
+                let sum: C::Curve = Option::from(C::CurveExt::new_jacobian(x, y, z))?;
+                Some(acc + sum)
+            })
+    }
+}
+
+/// An [`FftBackend`] that computes the FFTs over the fields of this crate on a CUDA
+/// device.
+///
+/// The elements are copied to the device in bit-reversed order, and each layer of
+/// butterflies is a launch with a thread per butterfly. FFTs smaller than
+/// $2^{\mathsf{min\_log\_n}}$, which is $2^{14}$ unless set with
+/// [`with_min_log_n`](CudaFft::with_min_log_n), are left to the CPU, as are those over
+/// groups other than fields and any on which the device fails.
+#[derive(Clone)]
+pub struct CudaFft {
+    device: Arc<Device>,
+    min_log_n: u32,
+}
+
+impl fmt::Debug for CudaFft {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CudaFft")
+            .field("min_log_n", &self.min_log_n)
+            .finish()
+    }
+}
+
+impl CudaFft {
+    /// Opens the first CUDA device and compiles the kernels for it, or returns `None`
+    /// if the CUDA driver, NVRTC or a device is unavailable.
+    pub fn new() -> Option<Self> {
+        Some(CudaFft {
+            device: Arc::new(Device::open()?),
+            min_log_n: 14,
+        })
+    }
+
+    /// Sets the log of the size below which FFTs are left to the CPU.
+    pub fn with_min_log_n(mut self, min_log_n: u32) -> Self {
+        self.min_log_n = min_log_n;
+        self
+    }
+
+    fn try_fft<F: FieldExt>(&self, a: &mut [F], omega: F, log_n: u32) -> Option<()> {
+        let n = a.len();
+
+        let mut values = vec![[0; LIMBS]; n];
+        {
+            let a = &*a;
+            parallelize(&mut values, |values, start| {
+                for (index, value) in values.iter_mut().enumerate() {
+                    let index = bitreverse((start + index) as u32, log_n);
+                    *value = to_words(&a[index as usize]);
+                }
+            });
+        }
+        // w^j 2^256 for j < n / 2.
+        let r = F::from_u64(2).pow(&[256, 0, 0, 0]);
+        let mut twiddles = vec![[0; LIMBS]; n / 2];
+        parallelize(&mut twiddles, |twiddles, start| {
+            let mut w = r * omega.pow_vartime([start as u64, 0, 0, 0]);
+            for twiddle in twiddles.iter_mut() {
+                *twiddle = to_words(&w);
+                w *= omega;
+            }
+        });
+        let (value_words, twiddle_words) = (values.concat(), twiddles.concat());
+
+        let words = self.device.run(|session| {
+            let consts = session.upload(&consts::<F>())?;
+            let values = session.upload(&value_words)?;
+            let twiddles = session.upload(&twiddle_words)?;
+            for layer in 0..log_n {
+                session.launch(
+                    "fft_layer",
+                    n / 2,
+                    &[
+                        Arg::Buffer(&values),
+                        Arg::Buffer(&twiddles),
+                        Arg::U32(n as u32),
+                        Arg::U32(1 << layer),
+                        Arg::Buffer(&consts),
+                    ],
+                )?;
+            }
+            values.read()
+        })?;
+
+        // Every element is canonical, so none fails to decode.
+        parallelize(a, |a, start| {
+            for (a, words) in a.iter_mut().zip(words[start * LIMBS..].chunks(LIMBS)) {
+                *a = from_words(words).unwrap();
+            }
+        });
+        Some(())
+    }
+}
+
+impl<F: FieldExt + Group<Scalar = F>> FftBackend<F> for CudaFft {
+    fn fft(&self, a: &mut [F], omega: F, log_n: u32) -> bool {
+        // The indices on the device are 32 bits.
+        if log_n < self.min_log_n.max(1) || log_n > 31 {
+            return false;
+        }
+        self.try_fft(a, omega, log_n).is_some()
+    }
+}
+
+#[test]
+fn test_consts() {
+    use crate::pasta::Fp;
+
+    // 2^512 mod p is the Montgomery form of 2^256 mod p, and -p^{-1} p = -1.
+    let consts = consts::<Fp>();
+    let p0 = consts[0];
+    assert_eq!(p0.wrapping_mul(consts[2 * LIMBS]), u32::MAX);
+    assert_eq!(
+        from_words::<Fp>(&consts[LIMBS..2 * LIMBS]),
+        Some(Fp::from_u64(2).pow(&[512, 0, 0, 0]))
+    );
+    assert_eq!(from_words::<Fp>(&consts[..LIMBS]), None);
+}
+
+#[test]
+fn test_digit() {
+    let mut words = [0; LIMBS];
+    words[0] = 0xdead_beef;
+    words[1] = 0x1234_5678;
+    words[LIMBS - 1] = 0x8000_0001;
+    assert_eq!(digit(&words, 0, 8), 0xef);
+    assert_eq!(digit(&words, 28, 8), 0x8d);
+    assert_eq!(digit(&words, 32, 12), 0x678);
+    assert_eq!(digit(&words, 32 * (LIMBS - 1), 1), 1);
+    assert_eq!(digit(&words, 32 * LIMBS - 4, 12), 0x8);
+}
+
+#[test]
+fn test_sort_into_buckets() {
+    use crate::arithmetic::best_multiexp;
+    use crate::pasta::{Eq, EqAffine, Fp};
+    use group::{prime::PrimeCurveAffine, Curve};
+
+    // The sums of the kernels, on the CPU.
+    let n = 50;
+    let bases: Vec<EqAffine> = (0..n)
+        .map(|_| (EqAffine::generator() * Fp::rand()).to_affine())
+        .collect();
+    let mut coeffs: Vec<Fp> = (0..n).map(|_| Fp::rand()).collect();
+    coeffs[0] = Fp::zero();
+    coeffs[1] = -Fp::one();
+    let scalars: Vec<_> = coeffs.iter().map(to_words).collect();
+
+    for c in [1, 5, 7, MAX_WINDOW_BITS] {
+        let (indices, offsets) = sort_into_buckets(&scalars, c);
+        let (windows, buckets) = ((32 * LIMBS).div_ceil(c), (1 << c) - 1);
+        assert_eq!(offsets.len(), windows * buckets + 1);
+
+        let sums: Vec<_> = offsets
+            .windows(2)
+            .map(|range| {
+                indices[range[0] as usize..range[1] as usize]
+                    .iter()
+                    .fold(Eq::identity(), |acc, index| acc + bases[*index as usize])
+            })
+            .collect();
+        let windows = sums.chunks(buckets).map(|sums| {
+            let mut running = Eq::identity();
+            sums.iter().rev().fold(running, |acc, sum| {
+                running += sum;
+                acc + running
+            })
+        });
+        let result = windows.rev().fold(Eq::identity(), |acc, sum| {
+            (0..c).fold(acc, |acc, _| acc.double()) + sum
+        });
+        assert_eq!(result, best_multiexp(&coeffs, &bases));
+    }
+}
+
+#[test]
+fn test_cuda_fft() {
+    use crate::arithmetic::serial_fft;
+    use crate::pasta::Fp;
+    use ff::PrimeField;
+
+    let backend = match CudaFft::new() {
+        Some(backend) => backend.with_min_log_n(0),
+        None => return,
+    };
+
+    let log_n = 10;
+    let omega = Fp::ROOT_OF_UNITY.pow_vartime([1 << (Fp::S - log_n), 0, 0, 0]);
+    let mut a: Vec<_> = (0..(1 << log_n)).map(|_| Fp::rand()).collect();
+    a[0] = -Fp::one();
+    let mut expected = a.clone();
+    serial_fft(&mut expected, omega, log_n);
+    assert!(FftBackend::<Fp>::fft(&backend, &mut a, omega, log_n));
+    assert_eq!(a, expected);
+}
@@ -0,0 +1,627 @@
+//! An FFT backend vectorized with AVX2 or AVX-512, for `x86_64` processors.
+//!
+//! The elements are transformed in their canonical form, with each of their eight
+//! 32-bit limbs in a 64-bit lane of a vector, so that the product of two limbs and the
+//! carries into it fit in the lane. Each vector holds a limb of as many elements as it
+//! has lanes, and each butterfly multiplies by a twiddle factor $w$ with the Montgomery
+//! product by $w \cdot 2^{256}$, which is $w$ times the element in canonical form.
+//!
+//! The vector operations are safe functions that call the intrinsics of their
+//! instructions. They are only reached through a [`SimdFft`], which is only created
+//! once the processor is found to support the instructions.
+
+use std::arch::x86_64::*;
+
+use super::{bitreverse, num_threads, parallelize, FftBackend, FieldExt, Group};
+use crate::multicore;
+
+/// The number of 32-bit limbs of a field element.
+const LIMBS: usize = 8;
+
+/// The most lanes of any vector.
+const MAX_LANES: usize = 8;
+
+/// A field element with each of its limbs in a lane of `V`.
+type Element<V> = [V; LIMBS];
+
+/// The operations on vectors of 64-bit lanes that the butterflies are written with.
+///
+/// The operations on the vectors of an instruction set may only be called on a
+/// processor that supports it.
+trait Lanes: Copy + Send + Sync + 'static {
+    /// The number of lanes of the vector.
+    const LANES: usize;
+
+    fn splat(value: u64) -> Self;
+    fn load(lanes: &[u64]) -> Self;
+    fn store(self, lanes: &mut [u64]);
+    fn add(self, rhs: Self) -> Self;
+    fn sub(self, rhs: Self) -> Self;
+    /// Multiplies the low 32 bits of each lane of `self` and `rhs`.
+    fn mul(self, rhs: Self) -> Self;
+    fn shr32(self) -> Self;
+    fn shr63(self) -> Self;
+    fn and(self, rhs: Self) -> Self;
+    /// Returns `!self & rhs`.
+    fn and_not(self, rhs: Self) -> Self;
+    fn or(self, rhs: Self) -> Self;
+
+    /// Sets `lo[i], hi[i]` to `lo[i] + hi[i] w_i, lo[i] - hi[i] w_i` for each `i`, where
+    /// `twiddles[i]` is $w_i \cdot 2^{256}$.
+    fn butterflies(
+        lo: &mut [Element<Self>],
+        hi: &mut [Element<Self>],
+        twiddles: &[Element<Self>],
+        consts: &Consts<Self>,
+    );
+}
+
+/// A single lane, for the butterflies between the lanes of a vector.
+impl Lanes for u64 {
+    const LANES: usize = 1;
+
+    fn splat(value: u64) -> Self {
+        value
+    }
+    fn load(lanes: &[u64]) -> Self {
+        lanes[0]
+    }
+    fn store(self, lanes: &mut [u64]) {
+        lanes[0] = self;
+    }
+    fn add(self, rhs: Self) -> Self {
+        self.wrapping_add(rhs)
+    }
+    fn sub(self, rhs: Self) -> Self {
+        self.wrapping_sub(rhs)
+    }
+    fn mul(self, rhs: Self) -> Self {
+        (self & 0xffff_ffff) * (rhs & 0xffff_ffff)
+    }
+    fn shr32(self) -> Self {
+        self >> 32
+    }
+    fn shr63(self) -> Self {
+        self >> 63
+    }
+    fn and(self, rhs: Self) -> Self {
+        self & rhs
+    }
+    fn and_not(self, rhs: Self) -> Self {
+        !self & rhs
+    }
+    fn or(self, rhs: Self) -> Self {
+        self | rhs
+    }
+    fn butterflies(
+        lo: &mut [Element<Self>],
+        hi: &mut [Element<Self>],
+        twiddles: &[Element<Self>],
+        consts: &Consts<Self>,
+    ) {
+        butterflies(lo, hi, twiddles, consts)
+    }
+}
+
+macro_rules! impl_lanes {
+    (
+        $vec:ty, $lanes:expr, $feature:literal, $butterflies:ident, $set1:ident, $loadu:ident,
+        $storeu:ident, $add:ident, $sub:ident, $mul:ident, $srli:ident, $and:ident,
+        $andnot:ident, $or:ident
+    ) => {
+        /// Performs the butterflies of [`Lanes::butterflies`] in code compiled with the
+        /// instructions of the vectors, into which their operations are inlined.
+        #[target_feature(enable = $feature)]
+        fn $butterflies(
+            lo: &mut [Element<$vec>],
+            hi: &mut [Element<$vec>],
+            twiddles: &[Element<$vec>],
+            consts: &Consts<$vec>,
+        ) {
+            butterflies(lo, hi, twiddles, consts)
+        }
+
+        // Each operation calls the intrinsic of its instruction, which the processor
+        // supports whenever the vectors are in use. The loads and stores are kept within
+        // their slices by the assertions.
+        #[allow(unsafe_code)]
+        impl Lanes for $vec {
+            const LANES: usize = $lanes;
+
+            #[inline(always)]
+            fn splat(value: u64) -> Self {
+                unsafe { $set1(value as i64) }
+            }
+            #[inline(always)]
+            fn load(lanes: &[u64]) -> Self {
+                assert!(lanes.len() >= $lanes);
+                unsafe { $loadu(lanes.as_ptr() as *const _) }
+            }
+            #[inline(always)]
+            fn store(self, lanes: &mut [u64]) {
+                assert!(lanes.len() >= $lanes);
+                unsafe { $storeu(lanes.as_mut_ptr() as *mut _, self) }
+            }
+            #[inline(always)]
+            fn add(self, rhs: Self) -> Self {
+                unsafe { $add(self, rhs) }
+            }
+            #[inline(always)]
+            fn sub(self, rhs: Self) -> Self {
+                unsafe { $sub(self, rhs) }
+            }
+            #[inline(always)]
+            fn mul(self, rhs: Self) -> Self {
+                unsafe { $mul(self, rhs) }
+            }
+            #[inline(always)]
+            fn shr32(self) -> Self {
+                unsafe { $srli::<32>(self) }
+            }
+            #[inline(always)]
+            fn shr63(self) -> Self {
+                unsafe { $srli::<63>(self) }
+            }
+            #[inline(always)]
+            fn and(self, rhs: Self) -> Self {
+                unsafe { $and(self, rhs) }
+            }
+            #[inline(always)]
+            fn and_not(self, rhs: Self) -> Self {
+                unsafe { $andnot(self, rhs) }
+            }
+            #[inline(always)]
+            fn or(self, rhs: Self) -> Self {
+                unsafe { $or(self, rhs) }
+            }
+            fn butterflies(
+                lo: &mut [Element<Self>],
+                hi: &mut [Element<Self>],
+                twiddles: &[Element<Self>],
+                consts: &Consts<Self>,
+            ) {
+                unsafe { $butterflies(lo, hi, twiddles, consts) }
+            }
+        }
+    };
+}
+
+impl_lanes!(
+    __m256i,
+    4,
+    "avx2",
+    butterflies_avx2,
+    _mm256_set1_epi64x,
+    _mm256_loadu_si256,
+    _mm256_storeu_si256,
+    _mm256_add_epi64,
+    _mm256_sub_epi64,
+    _mm256_mul_epu32,
+    _mm256_srli_epi64,
+    _mm256_and_si256,
+    _mm256_andnot_si256,
+    _mm256_or_si256
+);
+
+impl_lanes!(
+    __m512i,
+    8,
+    "avx512f",
+    butterflies_avx512,
+    _mm512_set1_epi64,
+    _mm512_loadu_si512,
+    _mm512_storeu_si512,
+    _mm512_add_epi64,
+    _mm512_sub_epi64,
+    _mm512_mul_epu32,
+    _mm512_srli_epi64,
+    _mm512_and_si512,
+    _mm512_andnot_si512,
+    _mm512_or_si512
+);
+
+/// The constants of arithmetic modulo $p$, in every lane.
+#[derive(Clone, Copy)]
+struct Consts<V> {
+    p: Element<V>,
+    // -p^{-1} mod 2^32
+    inv: V,
+    mask: V,
+    zero: V,
+}
+
+impl<V: Lanes> Consts<V> {
+    /// Returns the constants of the field `F`, in vectors whose instructions the
+    /// processor supports.
+    fn new<F: FieldExt>() -> Self {
+        // p - 1 is even, so adding one to it does not carry.
+        let mut p = to_words(&-F::one());
+        p[0] += 1;
+
+        // p^{-1} mod 2^32 by Newton iteration, doubling the correct bits each time.
+        let mut inv = 1u32;
+        for _ in 0..5 {
+            inv = inv.wrapping_mul(2u32.wrapping_sub((p[0] as u32).wrapping_mul(inv)));
+        }
+
+        let mut p_lanes = [V::splat(0); LIMBS];
+        for (lanes, limb) in p_lanes.iter_mut().zip(p.iter()) {
+            *lanes = V::splat(*limb);
+        }
+        Consts {
+            p: p_lanes,
+            inv: V::splat(inv.wrapping_neg() as u64),
+            mask: V::splat(0xffff_ffff),
+            zero: V::splat(0),
+        }
+    }
+}
+
+/// Sets `t` to `t - p` if `t + 2^256 top` is at least $p$, for `top` zero or one.
+#[inline(always)]
+fn reduce_once<V: Lanes>(t: &Element<V>, top: V, c: &Consts<V>) -> Element<V> {
+    let mut d = [c.zero; LIMBS];
+    let mut borrow = c.zero;
+    for i in 0..LIMBS {
+        let x = t[i].sub(c.p[i]).sub(borrow);
+        d[i] = x.and(c.mask);
+        borrow = x.shr63();
+    }
+    // Keep t where subtracting p borrows past the top limb.
+    let keep = c.zero.sub(top.and_not(borrow));
+    let mut r = [c.zero; LIMBS];
+    for i in 0..LIMBS {
+        r[i] = t[i].and(keep).or(keep.and_not(d[i]));
+    }
+    r
+}
+
+#[inline(always)]
+fn add_mod<V: Lanes>(a: &Element<V>, b: &Element<V>, c: &Consts<V>) -> Element<V> {
+    let mut t = [c.zero; LIMBS];
+    let mut carry = c.zero;
+    for i in 0..LIMBS {
+        let s = a[i].add(b[i]).add(carry);
+        t[i] = s.and(c.mask);
+        carry = s.shr32();
+    }
+    reduce_once(&t, carry, c)
+}
+
+#[inline(always)]
+fn sub_mod<V: Lanes>(a: &Element<V>, b: &Element<V>, c: &Consts<V>) -> Element<V> {
+    let mut t = [c.zero; LIMBS];
+    let mut borrow = c.zero;
+    for i in 0..LIMBS {
+        let x = a[i].sub(b[i]).sub(borrow);
+        t[i] = x.and(c.mask);
+        borrow = x.shr63();
+    }
+    // Add p back where the subtraction borrowed.
+    let borrow = c.zero.sub(borrow);
+    let mut carry = c.zero;
+    for (t, p) in t.iter_mut().zip(c.p.iter()) {
+        let s = t.add(p.and(borrow)).add(carry);
+        *t = s.and(c.mask);
+        carry = s.shr32();
+    }
+    t
+}
+
+/// Returns the Montgomery product $a b / 2^{256}$, by coarsely integrated operand
+/// scanning.
+#[inline(always)]
+fn mont_mul<V: Lanes>(a: &Element<V>, b: &Element<V>, c: &Consts<V>) -> Element<V> {
+    let mut t = [c.zero; LIMBS + 2];
+    for b in b.iter() {
+        let mut carry = c.zero;
+        for j in 0..LIMBS {
+            let s = a[j].mul(*b).add(t[j]).add(carry);
+            t[j] = s.and(c.mask);
+            carry = s.shr32();
+        }
+        let s = t[LIMBS].add(carry);
+        t[LIMBS] = s.and(c.mask);
+        t[LIMBS + 1] = s.shr32();
+
+        let m = t[0].mul(c.inv).and(c.mask);
+        let mut carry = m.mul(c.p[0]).add(t[0]).shr32();
+        for j in 1..LIMBS {
+            let s = m.mul(c.p[j]).add(t[j]).add(carry);
+            t[j - 1] = s.and(c.mask);
+            carry = s.shr32();
+        }
+        let s = t[LIMBS].add(carry);
+        t[LIMBS - 1] = s.and(c.mask);
+        t[LIMBS] = t[LIMBS + 1].add(s.shr32());
+    }
+    let mut r = [c.zero; LIMBS];
+    r.copy_from_slice(&t[..LIMBS]);
+    reduce_once(&r, t[LIMBS], c)
+}
+
+#[inline(always)]
+fn butterflies<V: Lanes>(
+    lo: &mut [Element<V>],
+    hi: &mut [Element<V>],
+    twiddles: &[Element<V>],
+    consts: &Consts<V>,
+) {
+    for ((lo, hi), w) in lo.iter_mut().zip(hi.iter_mut()).zip(twiddles.iter()) {
+        let t = mont_mul(hi, w, consts);
+        *hi = sub_mod(lo, &t, consts);
+        *lo = add_mod(lo, &t, consts);
+    }
+}
+
+/// Returns the limbs of the canonical encoding of a field element.
+fn to_words<F: FieldExt>(value: &F) -> [u64; LIMBS] {
+    let bytes = value.to_bytes();
+    let mut words = [0; LIMBS];
+    for (word, bytes) in words.iter_mut().zip(bytes.chunks(4)) {
+        *word = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as u64;
+    }
+    words
+}
+
+/// Returns the field element with the canonical encoding `words`.
+fn from_words<F: FieldExt>(words: &[u64; LIMBS]) -> F {
+    let mut bytes = [0; 32];
+    for (bytes, word) in bytes.chunks_mut(4).zip(words.iter()) {
+        bytes.copy_from_slice(&(*word as u32).to_le_bytes());
+    }
+    F::from_bytes(&bytes).unwrap()
+}
+
+/// Performs the FFT of `a` with vectors `V`, which the processor must support.
+fn fft<F: FieldExt, V: Lanes>(a: &mut [F], omega: F, log_n: u32) {
+    let n = a.len();
+    let lanes = V::LANES;
+    assert_eq!(n, 1 << log_n);
+    assert!(n >= lanes);
+    let consts = Consts::<V>::new::<F>();
+    let scalar = Consts::<u64>::new::<F>();
+
+    // The twiddle factors w^j 2^256 for j < n / 2, from which each layer of butterflies
+    // takes every (n / 2m)-th.
+    let r = F::from_u64(2).pow(&[256, 0, 0, 0]);
+    let mut powers = vec![[0u64; LIMBS]; n / 2];
+    parallelize(&mut powers, |powers, start| {
+        let mut w = r * omega.pow_vartime([start as u64, 0, 0, 0]);
+        for power in powers.iter_mut() {
+            *power = to_words(&w);
+            w *= omega;
+        }
+    });
+    let powers = &powers;
+
+    // Pack the elements in bit-reversed order, and perform the layers of butterflies
+    // between the lanes of each vector on its lanes one at a time.
+    let mut blocks = vec![[V::splat(0); LIMBS]; n / lanes];
+    {
+        let a = &*a;
+        parallelize(&mut blocks, |blocks, start| {
+            for (index, block) in blocks.iter_mut().enumerate() {
+                let mut elements = [[0u64; LIMBS]; MAX_LANES];
+                for (lane, element) in elements[..lanes].iter_mut().enumerate() {
+                    let index = bitreverse(((start + index) * lanes + lane) as u32, log_n);
+                    *element = to_words(&a[index as usize]);
+                }
+
+                let mut m = 1;
+                while m < lanes {
+                    let stride = n / (2 * m);
+                    for k in (0..lanes).step_by(2 * m) {
+                        let (lo, hi) = elements[k..k + 2 * m].split_at_mut(m);
+                        for (j, (lo, hi)) in lo.iter_mut().zip(hi.iter_mut()).enumerate() {
+                            u64::butterflies(
+                                std::slice::from_mut(lo),
+                                std::slice::from_mut(hi),
+                                &[powers[j * stride]],
+                                &scalar,
+                            );
+                        }
+                    }
+                    m *= 2;
+                }
+
+                for (limb, vector) in block.iter_mut().enumerate() {
+                    let mut values = [0u64; MAX_LANES];
+                    for (value, element) in values.iter_mut().zip(elements[..lanes].iter()) {
+                        *value = element[limb];
+                    }
+                    *vector = V::load(&values[..lanes]);
+                }
+            }
+        });
+    }
+
+    // The remaining layers pair whole vectors.
+    let mut m = lanes;
+    while m < n {
+        let half = m / lanes;
+        let stride = n / (2 * m);
+        let mut twiddles = vec![[V::splat(0); LIMBS]; half];
+        parallelize(&mut twiddles, |twiddles, start| {
+            for (index, twiddle) in twiddles.iter_mut().enumerate() {
+                for (limb, vector) in twiddle.iter_mut().enumerate() {
+                    let mut values = [0u64; MAX_LANES];
+                    for (lane, value) in values[..lanes].iter_mut().enumerate() {
+                        *value = powers[((start + index) * lanes + lane) * stride][limb];
+                    }
+                    *vector = V::load(&values[..lanes]);
+                }
+            }
+        });
+        layer(&mut blocks, &twiddles, half, &consts);
+        m *= 2;
+    }
+
+    // Unpack the elements in order.
+    let chunk = (n / lanes).div_ceil(num_threads());
+    multicore::scope(|scope| {
+        for (a, blocks) in a.chunks_mut(chunk * lanes).zip(blocks.chunks(chunk)) {
+            scope.spawn(move |_| {
+                for (a, block) in a.chunks_mut(lanes).zip(blocks.iter()) {
+                    let mut elements = [[0u64; LIMBS]; MAX_LANES];
+                    for (limb, vector) in block.iter().enumerate() {
+                        let mut values = [0u64; MAX_LANES];
+                        vector.store(&mut values[..lanes]);
+                        for (element, value) in elements.iter_mut().zip(values[..lanes].iter()) {
+                            element[limb] = *value;
+                        }
+                    }
+                    for (a, element) in a.iter_mut().zip(elements.iter()) {
+                        *a = from_words(element);
+                    }
+                }
+            });
+        }
+    })
+    .unwrap();
+}
+
+/// Performs a layer of butterflies between the vectors `half` apart in each run of
+/// `2 half` vectors, in parallel.
+fn layer<V: Lanes>(
+    blocks: &mut [Element<V>],
+    twiddles: &[Element<V>],
+    half: usize,
+    consts: &Consts<V>,
+) {
+    let threads = num_threads();
+    let runs = blocks.len() / (2 * half);
+    multicore::scope(|scope| {
+        if runs >= threads {
+            // Each thread takes whole runs.
+            for runs in blocks.chunks_mut(runs.div_ceil(threads) * 2 * half) {
+                scope.spawn(move |_| {
+                    for run in runs.chunks_mut(2 * half) {
+                        let (lo, hi) = run.split_at_mut(half);
+                        V::butterflies(lo, hi, twiddles, consts);
+                    }
+                });
+            }
+        } else {
+            // Each thread takes a piece of a run.
+            let piece = half.div_ceil(threads / runs);
+            for run in blocks.chunks_mut(2 * half) {
+                let (lo, hi) = run.split_at_mut(half);
+                for ((lo, hi), twiddles) in lo
+                    .chunks_mut(piece)
+                    .zip(hi.chunks_mut(piece))
+                    .zip(twiddles.chunks(piece))
+                {
+                    scope.spawn(move |_| V::butterflies(lo, hi, twiddles, consts));
+                }
+            }
+        }
+    })
+    .unwrap();
+}
+
+/// The vector instructions that a [`SimdFft`] computes with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Isa {
+    Avx2,
+    Avx512,
+}
+
+/// An [`FftBackend`] that computes the FFTs over the fields of this crate with AVX2
+/// or AVX-512 instructions, on the threads of [`best_fft`](super::best_fft).
+///
+/// It accepts the FFTs of at least $2^{\mathsf{min\_log\_n}}$ elements, which is
+/// $2^{10}$ unless set with [`with_min_log_n`](SimdFft::with_min_log_n), and leaves
+/// smaller ones and those over groups other than fields to the CPU.
+#[derive(Clone, Copy, Debug)]
+pub struct SimdFft {
+    isa: Isa,
+    min_log_n: u32,
+}
+
+impl SimdFft {
+    /// Returns the backend with the widest vectors that this processor supports, or
+    /// `None` if it supports neither AVX-512 nor AVX2.
+    pub fn new() -> Option<Self> {
+        Self::avx512().or_else(Self::avx2)
+    }
+
+    /// Returns the backend with AVX2 instructions, or `None` if this processor does
+    /// not support them.
+    pub fn avx2() -> Option<Self> {
+        Self::with_isa(Isa::Avx2, is_x86_feature_detected!("avx2"))
+    }
+
+    /// Returns the backend with AVX-512 instructions, or `None` if this processor does
+    /// not support them.
+    pub fn avx512() -> Option<Self> {
+        Self::with_isa(Isa::Avx512, is_x86_feature_detected!("avx512f"))
+    }
+
+    fn with_isa(isa: Isa, supported: bool) -> Option<Self> {
+        if supported {
+            Some(SimdFft { isa, min_log_n: 10 })
+        } else {
+            None
+        }
+    }
+
+    /// Sets the log of the size below which FFTs are left to the CPU.
+    pub fn with_min_log_n(mut self, min_log_n: u32) -> Self {
+        self.min_log_n = min_log_n;
+        self
+    }
+}
+
+impl<F: FieldExt + Group<Scalar = F>> FftBackend<F> for SimdFft {
+    fn fft(&self, a: &mut [F], omega: F, log_n: u32) -> bool {
+        // Every FFT fills at least one vector.
+        if log_n < self.min_log_n.max(3) {
+            return false;
+        }
+        // The processor supports the instructions, as checked when the backend was
+        // created.
+        match self.isa {
+            Isa::Avx2 => fft::<F, __m256i>(a, omega, log_n),
+            Isa::Avx512 => fft::<F, __m512i>(a, omega, log_n),
+        }
+        true
+    }
+}
+
+#[test]
+fn test_simd_fft() {
+    use super::{serial_fft, with_thread_limit};
+    use crate::pasta::{Fp, Fq};
+    use crate::primitives::bn254;
+
+    fn check<F: FieldExt + Group<Scalar = F>>(backend: SimdFft, log_n: u32) {
+        let omega = F::ROOT_OF_UNITY.pow_vartime([1 << (F::S - log_n), 0, 0, 0]);
+        let mut a: Vec<_> = (0..(1 << log_n)).map(|_| F::rand()).collect();
+        // The largest values exercise the carries.
+        a[0] = -F::one();
+        a[1] = -F::one();
+        let mut expected = a.clone();
+        serial_fft(&mut expected, omega, log_n);
+        assert!(FftBackend::<F>::fft(&backend, &mut a, omega, log_n));
+        assert_eq!(a, expected);
+    }
+
+    for backend in [SimdFft::avx2(), SimdFft::avx512()].iter().flatten() {
+        let backend = backend.with_min_log_n(0);
+        for log_n in [3, 4, 9] {
+            check::<Fp>(backend, log_n);
+        }
+        check::<Fq>(backend, 8);
+        check::<bn254::Scalar>(backend, 8);
+        // With fewer threads than runs of vectors in the last layers.
+        with_thread_limit(2, || check::<Fp>(backend, 9));
+
+        assert!(!FftBackend::<Fp>::fft(
+            &backend.with_min_log_n(4),
+            &mut [Fp::zero(); 8],
+            Fp::one(),
+            3
+        ));
+    }
+}
@@ -4,7 +4,7 @@ use std::{fmt, marker::PhantomData};
 
 use crate::{
     arithmetic::FieldExt,
-    plonk::{Advice, Any, Column, Error, Fixed, Permutation},
+    plonk::{Advice, Any, Challenge, Column, Error, Fixed, Permutation, Selector},
 };
 
 pub mod layouter;
@@ -108,6 +108,21 @@ impl<'r, C: Chip> From<&'r mut dyn layouter::RegionLayouter<C>> for Region<'r, C
 }
 
 impl<'r, C: Chip> Region<'r, C> {
+    /// Enables a selector at the given offset.
+    pub(crate) fn enable_selector<A, AR>(
+        &mut self,
+        annotation: A,
+        selector: &Selector,
+        offset: usize,
+    ) -> Result<(), Error>
+    where
+        A: Fn() -> AR,
+        AR: Into<String>,
+    {
+        self.region
+            .enable_selector(&|| annotation().into(), selector, offset)
+    }
+
     /// Assign an advice column value (witness).
     ///
     /// Even though `to` has `FnMut` bounds, it is guaranteed to be called at most once.
@@ -157,6 +172,23 @@ impl<'r, C: Chip> Region<'r, C> {
     ) -> Result<(), Error> {
         self.region.constrain_equal(permutation, left, right)
     }
+
+    /// Returns the value of `challenge`, or `None` if it is not known, such as while the
+    /// shape of the region is measured, or before its phase has been committed to.
+    ///
+    /// This lets a region assign cells of a later phase, such as a random linear
+    /// combination of values committed to in an earlier phase.
+    pub fn get_challenge(&self, challenge: Challenge) -> Option<C::Field> {
+        self.region.get_challenge(challenge)
+    }
+
+    /// Returns whether the values assigned to advice cells of this region are used.
+    ///
+    /// They are not while the shape of the region is measured, or during key
+    /// generation, when a chip may skip computing its witness.
+    pub fn witness_required(&self) -> bool {
+        self.region.witness_required()
+    }
 }
 
 /// A layout strategy for a specific chip within a circuit.
@@ -196,6 +228,14 @@ pub trait Layouter<C: Chip> {
         N: Fn() -> NR,
         NR: Into<String>;
 
+    /// Returns the value of `challenge`, or `None` if it is not known, such as during
+    /// key generation, or before its phase has been committed to.
+    fn get_challenge(&self, challenge: Challenge) -> Option<C::Field>;
+
+    /// Returns whether the values assigned to advice cells are used, which they are
+    /// not during key generation, when a chip may skip computing its witness.
+    fn witness_required(&self) -> bool;
+
     /// Gets the "root" of this assignment, bypassing the namespacing.
     ///
     /// Not intended for downstream consumption; use [`Layouter::namespace`] instead.
@@ -251,6 +291,14 @@ impl<'a, C: Chip, L: Layouter<C> + 'a> Layouter<C> for NamespacedLayouter<'a, C,
         self.0.assign_region(name, assignment)
     }
 
+    fn get_challenge(&self, challenge: Challenge) -> Option<C::Field> {
+        self.0.get_challenge(challenge)
+    }
+
+    fn witness_required(&self) -> bool {
+        self.0.witness_required()
+    }
+
     fn get_root(&mut self) -> &mut Self::Root {
         self.0.get_root()
     }
@@ -6,7 +6,9 @@ use std::fmt;
 use std::marker::PhantomData;
 
 use super::{Cell, Chip, Layouter, Region, RegionIndex, RegionStart};
-use crate::plonk::{Advice, Any, Assignment, Column, Error, Fixed, Permutation};
+use crate::plonk::{
+    Advice, Any, Assignment, Challenge, Column, Error, Fixed, Permutation, Selector,
+};
 
 /// Helper trait for implementing a custom [`Layouter`].
 ///
@@ -37,6 +39,14 @@ use crate::plonk::{Advice, Any, Assignment, Column, Error, Fixed, Permutation};
 /// "logical" columns that are guaranteed to correspond to the chip (and have come from
 /// `Chip::Config`).
 pub trait RegionLayouter<C: Chip>: fmt::Debug {
+    /// Enables a selector at the given offset.
+    fn enable_selector<'v>(
+        &'v mut self,
+        annotation: &'v (dyn Fn() -> String + 'v),
+        selector: &Selector,
+        offset: usize,
+    ) -> Result<(), Error>;
+
     /// Assign an advice column value (witness)
     fn assign_advice<'v>(
         &'v mut self,
@@ -64,6 +74,12 @@ pub trait RegionLayouter<C: Chip>: fmt::Debug {
         left: Cell,
         right: Cell,
     ) -> Result<(), Error>;
+
+    /// Returns the value of `challenge`, or `None` if it is not known.
+    fn get_challenge(&self, challenge: Challenge) -> Option<C::Field>;
+
+    /// Returns whether the values assigned to advice cells are used.
+    fn witness_required(&self) -> bool;
 }
 
 /// A [`Layouter`] for a single-chip circuit.
@@ -74,7 +90,7 @@ pub struct SingleChip<'a, C: Chip, CS: Assignment<C::Field> + 'a> {
     /// Stores the starting row for each region.
     regions: Vec<RegionStart>,
     /// Stores the first empty row for each column.
-    columns: HashMap<Column<Any>, usize>,
+    columns: HashMap<RegionColumn, usize>,
     _marker: PhantomData<C>,
 }
 
@@ -155,6 +171,14 @@ impl<'a, C: Chip, CS: Assignment<C::Field> + 'a> Layouter<C> for SingleChip<'a,
         Ok(result)
     }
 
+    fn get_challenge(&self, challenge: Challenge) -> Option<C::Field> {
+        self.cs.get_challenge(challenge)
+    }
+
+    fn witness_required(&self) -> bool {
+        self.cs.witness_required()
+    }
+
     fn get_root(&mut self) -> &mut Self::Root {
         self
     }
@@ -172,12 +196,34 @@ impl<'a, C: Chip, CS: Assignment<C::Field> + 'a> Layouter<C> for SingleChip<'a,
     }
 }
 
+/// A column used by a region, which is either a column of the circuit, or a
+/// selector that may later be combined into a fixed column.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum RegionColumn {
+    /// A column of the circuit.
+    Column(Column<Any>),
+    /// A selector.
+    Selector(Selector),
+}
+
+impl From<Column<Any>> for RegionColumn {
+    fn from(column: Column<Any>) -> RegionColumn {
+        RegionColumn::Column(column)
+    }
+}
+
+impl From<Selector> for RegionColumn {
+    fn from(selector: Selector) -> RegionColumn {
+        RegionColumn::Selector(selector)
+    }
+}
+
 /// The shape of a region. For a region at a certain index, we track
 /// the set of columns it uses as well as the number of rows it uses.
 #[derive(Debug)]
 pub struct RegionShape {
     region_index: RegionIndex,
-    columns: HashSet<Column<Any>>,
+    columns: HashSet<RegionColumn>,
     row_count: usize,
 }
 
@@ -197,7 +243,7 @@ impl RegionShape {
     }
 
     /// Get a reference to the set of `columns` used in a `RegionShape`.
-    pub fn columns(&self) -> &HashSet<Column<Any>> {
+    pub fn columns(&self) -> &HashSet<RegionColumn> {
         &self.columns
     }
 
@@ -208,6 +254,18 @@ impl RegionShape {
 }
 
 impl<C: Chip> RegionLayouter<C> for RegionShape {
+    fn enable_selector<'v>(
+        &'v mut self,
+        _: &'v (dyn Fn() -> String + 'v),
+        selector: &Selector,
+        offset: usize,
+    ) -> Result<(), Error> {
+        // Track the selector as part of the region's shape.
+        self.columns.insert((*selector).into());
+        self.row_count = cmp::max(self.row_count, offset + 1);
+        Ok(())
+    }
+
     fn assign_advice<'v>(
         &'v mut self,
         _: &'v (dyn Fn() -> String + 'v),
@@ -215,7 +273,7 @@ impl<C: Chip> RegionLayouter<C> for RegionShape {
         offset: usize,
         _to: &'v mut (dyn FnMut() -> Result<C::Field, Error> + 'v),
     ) -> Result<Cell, Error> {
-        self.columns.insert(column.into());
+        self.columns.insert(Column::<Any>::from(column).into());
         self.row_count = cmp::max(self.row_count, offset + 1);
 
         Ok(Cell {
@@ -232,7 +290,7 @@ impl<C: Chip> RegionLayouter<C> for RegionShape {
         offset: usize,
         _to: &'v mut (dyn FnMut() -> Result<C::Field, Error> + 'v),
     ) -> Result<Cell, Error> {
-        self.columns.insert(column.into());
+        self.columns.insert(Column::<Any>::from(column).into());
         self.row_count = cmp::max(self.row_count, offset + 1);
 
         Ok(Cell {
@@ -251,6 +309,16 @@ impl<C: Chip> RegionLayouter<C> for RegionShape {
         // Equality constraints don't affect the region shape.
         Ok(())
     }
+
+    fn get_challenge(&self, _: Challenge) -> Option<C::Field> {
+        // The shape of a region doesn't depend on the values assigned in it.
+        None
+    }
+
+    fn witness_required(&self) -> bool {
+        // The shape of a region doesn't depend on the values assigned in it.
+        false
+    }
 }
 
 struct SingleChipRegion<'r, 'a, C: Chip, CS: Assignment<C::Field> + 'a> {
@@ -281,6 +349,19 @@ impl<'r, 'a, C: Chip, CS: Assignment<C::Field> + 'a> SingleChipRegion<'r, 'a, C,
 impl<'r, 'a, C: Chip, CS: Assignment<C::Field> + 'a> RegionLayouter<C>
     for SingleChipRegion<'r, 'a, C, CS>
 {
+    fn enable_selector<'v>(
+        &'v mut self,
+        annotation: &'v (dyn Fn() -> String + 'v),
+        selector: &Selector,
+        offset: usize,
+    ) -> Result<(), Error> {
+        self.layouter.cs.enable_selector(
+            annotation,
+            selector,
+            *self.layouter.regions[*self.region_index] + offset,
+        )
+    }
+
     fn assign_advice<'v>(
         &'v mut self,
         annotation: &'v (dyn Fn() -> String + 'v),
@@ -339,4 +420,12 @@ impl<'r, 'a, C: Chip, CS: Assignment<C::Field> + 'a> RegionLayouter<C>
 
         Ok(())
     }
+
+    fn get_challenge(&self, challenge: Challenge) -> Option<C::Field> {
+        self.layouter.cs.get_challenge(challenge)
+    }
+
+    fn witness_required(&self) -> bool {
+        self.layouter.cs.witness_required()
+    }
 }
@@ -1,12 +1,14 @@
 //! Tools for developing circuits.
 
 use ff::Field;
+use std::collections::BTreeSet;
+use std::ops::Range;
 
 use crate::{
     arithmetic::{FieldExt, Group},
     plonk::{
-        permutation, Advice, Any, Assignment, Circuit, Column, ColumnType, ConstraintSystem, Error,
-        Expression, Fixed, Permutation,
+        configure_circuit, permutation, Advice, Any, Assignment, Challenge, Circuit, Column,
+        ColumnType, ConstraintSystem, Error, Expression, Fixed, Permutation, Selector,
     },
     poly::Rotation,
 };
@@ -30,6 +32,12 @@ pub enum VerifyFailure {
         /// The name of the gate that is not satisfied. These are specified by the gate
         /// creator (such as a chip implementation), and may not be unique.
         gate_name: &'static str,
+        /// The index of the constraint of the gate that is not satisfied, in the
+        /// order in which the gate returns its constraints.
+        constraint_index: usize,
+        /// The name of the constraint that is not satisfied, or `""` if the gate did
+        /// not name it.
+        constraint_name: &'static str,
         /// The row on which this gate is not satisfied.
         row: usize,
     },
@@ -42,6 +50,14 @@ pub enum VerifyFailure {
         /// The row on which this lookup is not satisfied.
         row: usize,
     },
+    /// The rows of the input expressions of a shuffle were not a permutation of the
+    /// rows of its shuffle expressions.
+    Shuffle {
+        /// The index of the shuffle that is not satisfied. These indices are assigned in
+        /// the order in which `ConstraintSystem::shuffle` is called during
+        /// `Circuit::configure`.
+        shuffle_index: usize,
+    },
     /// A permutation did not preserve the original value of a cell.
     Permutation {
         /// The index of the permutation that is not satisfied. These indices are assigned
@@ -90,6 +106,8 @@ pub enum VerifyFailure {
 ///
 /// impl<F: FieldExt> Circuit<F> for MyCircuit {
 ///     type Config = MyConfig;
+///     #[cfg(feature = "circuit-params")]
+///     type Params = ();
 ///
 ///     fn configure(meta: &mut ConstraintSystem<F>) -> MyConfig {
 ///         let a = meta.advice_column();
@@ -138,6 +156,8 @@ pub enum VerifyFailure {
 ///     Err(VerifyFailure::Gate {
 ///         gate_index: 0,
 ///         gate_name: "R1CS constraint",
+///         constraint_index: 0,
+///         constraint_name: "",
 ///         row: 0
 ///     })
 /// );
@@ -146,13 +166,19 @@ pub enum VerifyFailure {
 pub struct MockProver<F: Group + Field> {
     n: u32,
     cs: ConstraintSystem<F>,
+    // The rows that the circuit may assign, which exclude the blinding rows.
+    usable_rows: Range<usize>,
 
+    // The rows at which each selector is enabled, arranged as [selector][row].
+    selectors: Vec<Vec<bool>>,
     // The fixed cells in the circuit, arranged as [column][row].
     fixed: Vec<Vec<F>>,
     // The advice cells in the circuit, arranged as [column][row].
     advice: Vec<Vec<F>>,
     // The instance cells in the circuit, arranged as [column][row].
     instance: Vec<Vec<F>>,
+    // The values of the challenges, which the mock prover picks at random.
+    challenges: Vec<F>,
 
     permutations: Vec<permutation::keygen::Assembly>,
 }
@@ -167,6 +193,24 @@ impl<F: Field + Group> Assignment<F> for MockProver<F> {
 
     fn exit_region(&mut self) {}
 
+    fn enable_selector<A, AR>(&mut self, _: A, selector: &Selector, row: usize) -> Result<(), Error>
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        if !self.usable_rows.contains(&row) {
+            return Err(Error::BoundsFailure);
+        }
+
+        *self
+            .selectors
+            .get_mut(selector.0)
+            .and_then(|v| v.get_mut(row))
+            .ok_or(Error::BoundsFailure)? = true;
+
+        Ok(())
+    }
+
     fn assign_advice<V, A, AR>(
         &mut self,
         _: A,
@@ -179,6 +223,10 @@ impl<F: Field + Group> Assignment<F> for MockProver<F> {
         A: FnOnce() -> AR,
         AR: Into<String>,
     {
+        if !self.usable_rows.contains(&row) {
+            return Err(Error::BoundsFailure);
+        }
+
         *self
             .advice
             .get_mut(column.index())
@@ -200,6 +248,10 @@ impl<F: Field + Group> Assignment<F> for MockProver<F> {
         A: FnOnce() -> AR,
         AR: Into<String>,
     {
+        if !self.usable_rows.contains(&row) {
+            return Err(Error::BoundsFailure);
+        }
+
         *self
             .fixed
             .get_mut(column.index())
@@ -218,7 +270,10 @@ impl<F: Field + Group> Assignment<F> for MockProver<F> {
         right_row: usize,
     ) -> Result<(), crate::plonk::Error> {
         // Check bounds first
-        if permutation.index() >= self.permutations.len() {
+        if permutation.index() >= self.permutations.len()
+            || !self.usable_rows.contains(&left_row)
+            || !self.usable_rows.contains(&right_row)
+        {
             return Err(Error::BoundsFailure);
         }
 
@@ -252,11 +307,22 @@ impl<F: Field + Group> Assignment<F> for MockProver<F> {
     fn pop_namespace(&mut self, _: Option<String>) {
         // TODO: Do something with namespaces :)
     }
+
+    fn get_challenge(&self, challenge: Challenge) -> Option<F> {
+        self.challenges.get(challenge.index()).cloned()
+    }
+
+    fn witness_required(&self) -> bool {
+        true
+    }
 }
 
 impl<F: FieldExt> MockProver<F> {
     /// Runs a synthetic keygen-and-prove operation on the given circuit, collecting data
     /// about the constraints and their assignments.
+    ///
+    /// `instance` holds the values of each instance column from the first row; rows
+    /// without a value are zero.
     pub fn run<ConcreteCircuit: Circuit<F>>(
         k: u32,
         circuit: &ConcreteCircuit,
@@ -265,27 +331,77 @@ impl<F: FieldExt> MockProver<F> {
         let n = 1 << k;
 
         let mut cs = ConstraintSystem::default();
-        let config = ConcreteCircuit::configure(&mut cs);
+        let config = configure_circuit(&mut cs, circuit);
 
+        // Instance columns may omit their trailing zero rows.
+        if instance.len() != cs.num_instance_columns {
+            return Err(Error::IncompatibleParams);
+        }
+        let instance = instance
+            .into_iter()
+            .map(|mut column| {
+                if column.len() > n as usize {
+                    return Err(Error::BoundsFailure);
+                }
+                column.resize(n as usize, F::zero());
+                Ok(column)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let selectors = vec![vec![false; n as usize]; cs.num_selectors];
         let fixed = vec![vec![F::zero(); n as usize]; cs.num_fixed_columns];
         let advice = vec![vec![F::zero(); n as usize]; cs.num_advice_columns];
+        let challenges = (0..cs.num_challenges).map(|_| F::rand()).collect();
         let permutations = cs
             .permutations
             .iter()
             .map(|p| permutation::keygen::Assembly::new(n as usize, p))
             .collect();
 
+        let usable_rows = cs.usable_rows(k);
         let mut prover = MockProver {
             n,
             cs,
+            usable_rows,
+            selectors,
             fixed,
             advice,
             instance,
+            challenges,
             permutations,
         };
 
         circuit.synthesize(&mut prover, config)?;
 
+        // As the prover does, the blinding rows of the advice columns are filled with
+        // random values, but for the unblinded columns.
+        for (index, column) in prover.advice.iter_mut().enumerate() {
+            if !prover.cs.unblinded_advice_columns.contains(&index) {
+                for value in column[prover.usable_rows.end..].iter_mut() {
+                    *value = F::rand();
+                }
+            }
+        }
+
+        // The intermediate columns of split gates are computed from the witness.
+        for phase in prover.cs.phases() {
+            let (selectors, challenges) = (&prover.selectors, &prover.challenges);
+            prover.cs.assign_intermediate_columns(
+                phase,
+                &|selector, row| {
+                    if selectors[selector.0][row] {
+                        F::one()
+                    } else {
+                        F::zero()
+                    }
+                },
+                &prover.fixed,
+                &mut prover.advice,
+                &prover.instance,
+                &|challenge| challenges[challenge.index()],
+            );
+        }
+
         Ok(prover)
     }
 
@@ -295,79 +411,104 @@ impl<F: FieldExt> MockProver<F> {
         let n = self.n as i32;
 
         // Check that all gates are satisfied for all rows.
-        for (gate_index, (gate_name, gate)) in self.cs.gates.iter().enumerate() {
-            // We iterate from n..2n so we can just reduce to handle wrapping.
-            for row in n..(2 * n) {
-                fn load<'a, F: FieldExt, T: ColumnType>(
-                    n: i32,
-                    row: i32,
-                    queries: &'a [(Column<T>, Rotation)],
-                    cells: &'a [Vec<F>],
-                ) -> impl Fn(usize) -> F + 'a {
-                    move |index| {
-                        let (column, at) = &queries[index];
-                        let resolved_row = (row + at.0) % n;
-                        cells[column.index()][resolved_row as usize]
+        for (gate_index, gate) in self.cs.gates.iter().enumerate() {
+            for (constraint_index, poly) in gate.polynomials().iter().enumerate() {
+                // Rows are reduced modulo n, so that rotations of any size wrap.
+                for row in 0..n {
+                    fn load<'a, F: FieldExt, T: ColumnType>(
+                        n: i32,
+                        row: i32,
+                        queries: &'a [(Column<T>, Rotation)],
+                        cells: &'a [Vec<F>],
+                    ) -> impl Fn(usize) -> F + 'a {
+                        move |index| {
+                            let (column, at) = &queries[index];
+                            let resolved_row = (row + at.0).rem_euclid(n);
+                            cells[column.index()][resolved_row as usize]
+                        }
                     }
-                }
 
-                if gate.evaluate(
-                    &|scalar| scalar,
-                    &load(n, row, &self.cs.fixed_queries, &self.fixed),
-                    &load(n, row, &self.cs.advice_queries, &self.advice),
-                    &load(n, row, &self.cs.instance_queries, &self.instance),
-                    &|a, b| a + &b,
-                    &|a, b| a * &b,
-                    &|a, scalar| a * scalar,
-                ) != F::zero()
-                {
-                    return Err(VerifyFailure::Gate {
-                        gate_index,
-                        gate_name,
-                        row: (row - n) as usize,
-                    });
+                    if poly.evaluate(
+                        &|scalar| scalar,
+                        &|selector, at| {
+                            let resolved_row = (row + at.0).rem_euclid(n);
+                            if self.selectors[selector.0][resolved_row as usize] {
+                                F::one()
+                            } else {
+                                F::zero()
+                            }
+                        },
+                        &load(n, row, &self.cs.fixed_queries, &self.fixed),
+                        &load(n, row, &self.cs.advice_queries, &self.advice),
+                        &load(n, row, &self.cs.instance_queries, &self.instance),
+                        &|challenge| self.challenges[challenge.index()],
+                        &|a, b| a + &b,
+                        &|a, b| a * &b,
+                        &|a, scalar| a * scalar,
+                    ) != F::zero()
+                    {
+                        return Err(VerifyFailure::Gate {
+                            gate_index,
+                            gate_name: gate.name(),
+                            constraint_index,
+                            constraint_name: gate.constraint_name(constraint_index),
+                            row: row as usize,
+                        });
+                    }
                 }
             }
         }
 
         // Check that all lookups exist in their respective tables.
         for (lookup_index, lookup) in self.cs.lookups.iter().enumerate() {
-            for input_row in 0..n {
-                let load = |expression: &Expression<F>, row| {
-                    expression.evaluate(
-                        &|scalar| scalar,
-                        &|index| {
-                            let column_index = self.cs.fixed_queries[index].0.index();
-                            self.fixed[column_index][row as usize]
-                        },
-                        &|index| {
-                            let column_index = self.cs.advice_queries[index].0.index();
-                            self.advice[column_index][row as usize]
-                        },
-                        &|index| {
-                            let column_index = self.cs.instance_queries[index].0.index();
-                            self.instance[column_index][row as usize]
-                        },
-                        &|a, b| a + b,
-                        &|a, b| a * b,
-                        &|a, scalar| a * scalar,
-                    )
-                };
+            let load = |expression: &Expression<F>, row: i32| {
+                let resolve = |at: Rotation| (((row + at.0) % n + n) % n) as usize;
+                expression.evaluate(
+                    &|scalar| scalar,
+                    &|selector, at| {
+                        if self.selectors[selector.0][resolve(at)] {
+                            F::one()
+                        } else {
+                            F::zero()
+                        }
+                    },
+                    &|index| {
+                        let (column, at) = self.cs.fixed_queries[index];
+                        self.fixed[column.index()][resolve(at)]
+                    },
+                    &|index| {
+                        let (column, at) = self.cs.advice_queries[index];
+                        self.advice[column.index()][resolve(at)]
+                    },
+                    &|index| {
+                        let (column, at) = self.cs.instance_queries[index];
+                        self.instance[column.index()][resolve(at)]
+                    },
+                    &|challenge| self.challenges[challenge.index()],
+                    &|a, b| a + b,
+                    &|a, b| a * b,
+                    &|a, scalar| a * scalar,
+                )
+            };
+
+            // Collect the rows of the table once, so that each input is a single search.
+            let table: BTreeSet<Vec<_>> = (0..n)
+                .map(|table_row| {
+                    lookup
+                        .table_expressions
+                        .iter()
+                        .map(|c| load(c, table_row).to_bytes())
+                        .collect()
+                })
+                .collect();
 
+            for input_row in 0..n {
                 let inputs: Vec<_> = lookup
                     .input_expressions
                     .iter()
-                    .map(|c| load(c, input_row))
+                    .map(|c| load(c, input_row).to_bytes())
                     .collect();
-                let lookup_passes = (0..n)
-                    .map(|table_row| {
-                        lookup
-                            .table_expressions
-                            .iter()
-                            .map(move |c| load(c, table_row))
-                    })
-                    .any(|table_row| table_row.eq(inputs.iter().cloned()));
-                if !lookup_passes {
+                if !table.contains(&inputs) {
                     return Err(VerifyFailure::Lookup {
                         lookup_index,
                         row: input_row as usize,
@@ -376,6 +517,50 @@ impl<F: FieldExt> MockProver<F> {
             }
         }
 
+        // Check that the inputs of all shuffles are permutations of their shuffles.
+        for (shuffle_index, shuffle) in self.cs.shuffles.iter().enumerate() {
+            let load = |expression: &Expression<F>, row: i32| {
+                let resolve = |at: Rotation| (((row + at.0) % n + n) % n) as usize;
+                expression.evaluate(
+                    &|scalar| scalar,
+                    &|selector, at| {
+                        if self.selectors[selector.0][resolve(at)] {
+                            F::one()
+                        } else {
+                            F::zero()
+                        }
+                    },
+                    &|index| {
+                        let (column, at) = self.cs.fixed_queries[index];
+                        self.fixed[column.index()][resolve(at)]
+                    },
+                    &|index| {
+                        let (column, at) = self.cs.advice_queries[index];
+                        self.advice[column.index()][resolve(at)]
+                    },
+                    &|index| {
+                        let (column, at) = self.cs.instance_queries[index];
+                        self.instance[column.index()][resolve(at)]
+                    },
+                    &|challenge| self.challenges[challenge.index()],
+                    &|a, b| a + b,
+                    &|a, b| a * b,
+                    &|a, scalar| a * scalar,
+                )
+            };
+            let rows = |expressions: &[Expression<F>]| {
+                let mut rows: Vec<Vec<F>> = (0..n)
+                    .map(|row| expressions.iter().map(|c| load(c, row)).collect())
+                    .collect();
+                rows.sort();
+                rows
+            };
+
+            if rows(&shuffle.input_expressions) != rows(&shuffle.shuffle_expressions) {
+                return Err(VerifyFailure::Shuffle { shuffle_index });
+            }
+        }
+
         // Check that permutations preserve the original values of the cells.
         for (perm_index, assembly) in self.permutations.iter().enumerate() {
             // Original values of columns involved in the permutation
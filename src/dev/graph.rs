@@ -2,7 +2,8 @@ use ff::Field;
 use tabbycat::{AttrList, Edge, GraphBuilder, GraphType, Identity, StmtList};
 
 use crate::plonk::{
-    Advice, Any, Assignment, Circuit, Column, ConstraintSystem, Error, Fixed, Permutation,
+    configure_circuit, Advice, Any, Assignment, Challenge, Circuit, Column, ConstraintSystem,
+    Error, Fixed, Permutation, Selector,
 };
 
 pub mod layout;
@@ -18,7 +19,7 @@ pub fn circuit_dot_graph<F: Field, ConcreteCircuit: Circuit<F>>(
 ) -> String {
     // Collect the graph details.
     let mut cs = ConstraintSystem::default();
-    let config = ConcreteCircuit::configure(&mut cs);
+    let config = configure_circuit(&mut cs, circuit);
     let mut graph = Graph::default();
     circuit.synthesize(&mut graph, config).unwrap();
 
@@ -86,6 +87,15 @@ impl<F: Field> Assignment<F> for Graph {
         // Do nothing; we don't care about regions in this context.
     }
 
+    fn enable_selector<A, AR>(&mut self, _: A, _: &Selector, _: usize) -> Result<(), Error>
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        // Do nothing; selectors are not columns until keygen.
+        Ok(())
+    }
+
     fn assign_advice<V, A, AR>(
         &mut self,
         _: A,
@@ -159,4 +169,12 @@ impl<F: Field> Assignment<F> for Graph {
         // Pop the namespace.
         self.current_namespace.pop();
     }
+
+    fn get_challenge(&self, _: Challenge) -> Option<F> {
+        None
+    }
+
+    fn witness_required(&self) -> bool {
+        false
+    }
 }
@@ -7,7 +7,8 @@ use std::cmp;
 use std::collections::HashSet;
 
 use crate::plonk::{
-    Advice, Any, Assignment, Circuit, Column, ConstraintSystem, Error, Fixed, Permutation,
+    configure_circuit, Advice, Any, Assignment, Challenge, Circuit, Column, ConstraintSystem,
+    Error, Fixed, Permutation, Selector,
 };
 
 /// Renders the circuit layout on the given drawing area.
@@ -41,7 +42,7 @@ pub fn circuit_layout<F: Field, ConcreteCircuit: Circuit<F>, DB: DrawingBackend>
 
     // Collect the layout details.
     let mut cs = ConstraintSystem::default();
-    let config = ConcreteCircuit::configure(&mut cs);
+    let config = configure_circuit(&mut cs, circuit);
     let mut layout = Layout::default();
     circuit.synthesize(&mut layout, config).unwrap();
 
@@ -219,6 +220,15 @@ impl<F: Field> Assignment<F> for Layout {
         self.current_region = None;
     }
 
+    fn enable_selector<A, AR>(&mut self, _: A, _: &Selector, _: usize) -> Result<(), Error>
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        // Do nothing; selectors are not columns until keygen.
+        Ok(())
+    }
+
     fn assign_advice<V, A, AR>(
         &mut self,
         _: A,
@@ -274,4 +284,12 @@ impl<F: Field> Assignment<F> for Layout {
     fn pop_namespace(&mut self, _: Option<String>) {
         // Do nothing; we don't care about namespaces in this context.
     }
+
+    fn get_challenge(&self, _: Challenge) -> Option<F> {
+        None
+    }
+
+    fn witness_required(&self) -> bool {
+        false
+    }
 }
@@ -0,0 +1,35 @@
+//! Gadgets and chips for commonly-used circuit components.
+
+pub mod aes;
+pub mod biguint;
+pub mod bitwise;
+pub mod blake2b;
+pub mod boolean;
+pub mod comparison;
+pub mod division;
+pub mod ecc;
+pub mod ecdsa;
+pub mod ed25519;
+pub mod foreign_field;
+pub mod hash;
+pub mod hash_to_curve;
+pub mod ipa;
+pub mod keccak;
+pub mod merkle;
+pub mod mimc;
+pub mod modexp;
+pub mod msm;
+pub mod pedersen;
+pub mod poseidon;
+pub mod range_check;
+pub mod rescue;
+pub mod rsa;
+pub mod running_sum;
+pub mod schnorr;
+pub mod sha256;
+pub mod signed;
+pub mod sinsemilla;
+pub mod transcript;
+pub mod utilities;
+pub mod verifier;
+pub mod word;
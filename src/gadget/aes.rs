@@ -0,0 +1,164 @@
+//! Gadget and chips for AES-128 encryption.
+//!
+//! [`Aes128`] is the in-circuit counterpart of [`crate::primitives::aes::encrypt`], with
+//! the state laid out in the same order. The byte operations of the cipher are provided
+//! by the chip: the S-box and multiplication by $2$ in $\mathbb{F}_{2^8}$ are 8-bit
+//! lookups, and MixColumns computes each output byte as
+//! $a_i \oplus (a_0 \oplus a_1 \oplus a_2 \oplus a_3) \oplus 2 (a_i \oplus a_{i + 1})$,
+//! which takes 13 XORs and 4 doublings per column.
+
+use std::convert::TryInto;
+use std::fmt;
+
+use crate::{
+    circuit::{Chip, Layouter},
+    plonk::Error,
+    primitives::aes::{BLOCK_BYTES, ROUNDS, ROUND_CONSTANTS},
+};
+
+mod chip;
+pub use chip::{AesChip, AesConfig, AssignedByte};
+
+/// The set of circuit instructions required to use the [`Aes128`] gadget.
+pub trait AesInstructions: Chip {
+    /// Variable representing a byte.
+    type Byte: Clone + fmt::Debug;
+
+    /// Witnesses a byte.
+    ///
+    /// The byte is not constrained until it is used in an operation.
+    fn witness_byte(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<u8>,
+    ) -> Result<Self::Byte, Error>;
+
+    /// Assigns a byte that is fixed by the circuit.
+    fn constant_byte(layouter: &mut impl Layouter<Self>, value: u8) -> Result<Self::Byte, Error>;
+
+    /// Returns $a \oplus b$, constraining `a` and `b` to be bytes.
+    fn xor(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Byte,
+        b: &Self::Byte,
+    ) -> Result<Self::Byte, Error>;
+
+    /// Returns the S-box of `a`, constraining `a` to be a byte.
+    fn sub_byte(layouter: &mut impl Layouter<Self>, a: &Self::Byte) -> Result<Self::Byte, Error>;
+
+    /// Returns $2 \cdot a$ in $\mathbb{F}_{2^8}$, constraining `a` to be a byte.
+    fn xtime(layouter: &mut impl Layouter<Self>, a: &Self::Byte) -> Result<Self::Byte, Error>;
+
+    /// Constrains `a` and `b` to be the same byte.
+    fn constrain_equal(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Byte,
+        b: &Self::Byte,
+    ) -> Result<(), Error>;
+}
+
+/// A block of bytes, or the state of the cipher.
+pub type Block<AesChip> = [<AesChip as AesInstructions>::Byte; BLOCK_BYTES];
+
+fn xor_blocks<AesChip: AesInstructions>(
+    layouter: &mut impl Layouter<AesChip>,
+    a: &Block<AesChip>,
+    b: &Block<AesChip>,
+) -> Result<Block<AesChip>, Error> {
+    let bytes = a
+        .iter()
+        .zip(b.iter())
+        .map(|(a, b)| AesChip::xor(layouter, a, b))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(bytes.try_into().unwrap())
+}
+
+/// AES-128 with a key schedule computed in the circuit.
+#[derive(Debug)]
+pub struct Aes128<AesChip: AesInstructions> {
+    round_keys: Vec<Block<AesChip>>,
+}
+
+impl<AesChip: AesInstructions> Aes128<AesChip> {
+    /// Expands `key` into the round keys, as
+    /// [`expand_key`](crate::primitives::aes::expand_key) does.
+    pub fn new(mut layouter: impl Layouter<AesChip>, key: &Block<AesChip>) -> Result<Self, Error> {
+        let mut round_keys = vec![key.clone()];
+        for (round, rcon) in ROUND_CONSTANTS.iter().enumerate() {
+            let mut layouter = layouter.namespace(|| format!("round key {}", round + 1));
+            let prev = &round_keys[round];
+
+            let mut temp = [13, 14, 15, 12]
+                .iter()
+                .map(|&i| AesChip::sub_byte(&mut layouter, &prev[i]))
+                .collect::<Result<Vec<_>, _>>()?;
+            let rcon = AesChip::constant_byte(&mut layouter, *rcon)?;
+            temp[0] = AesChip::xor(&mut layouter, &temp[0], &rcon)?;
+
+            let mut next: Vec<AesChip::Byte> = Vec::with_capacity(BLOCK_BYTES);
+            for i in 0..BLOCK_BYTES {
+                let prior = if i < 4 { &temp[i] } else { &next[i - 4] };
+                let byte = AesChip::xor(&mut layouter, &prev[i], prior)?;
+                next.push(byte);
+            }
+            round_keys.push(next.try_into().unwrap());
+        }
+        Ok(Aes128 { round_keys })
+    }
+
+    /// Encrypts `block`, as [`encrypt`](crate::primitives::aes::encrypt) does.
+    pub fn encrypt(
+        &self,
+        mut layouter: impl Layouter<AesChip>,
+        block: &Block<AesChip>,
+    ) -> Result<Block<AesChip>, Error> {
+        let mut state = xor_blocks(&mut layouter, block, &self.round_keys[0])?;
+        for (round, round_key) in self.round_keys.iter().enumerate().skip(1) {
+            let mut layouter = layouter.namespace(|| format!("round {}", round));
+
+            // SubBytes and ShiftRows.
+            let mut shifted = Vec::with_capacity(BLOCK_BYTES);
+            for i in 0..BLOCK_BYTES {
+                let (r, c) = (i % 4, i / 4);
+                shifted.push(AesChip::sub_byte(
+                    &mut layouter,
+                    &state[r + 4 * ((c + r) % 4)],
+                )?);
+            }
+            state = shifted.try_into().unwrap();
+
+            if round != ROUNDS {
+                let mut mixed = Vec::with_capacity(BLOCK_BYTES);
+                for column in state.chunks(4) {
+                    mixed.extend(mix_column(&mut layouter, column)?);
+                }
+                state = mixed.try_into().unwrap();
+            }
+
+            state = xor_blocks(&mut layouter, &state, round_key)?;
+        }
+        Ok(state)
+    }
+}
+
+/// Returns the given column after the MixColumns step.
+fn mix_column<AesChip: AesInstructions>(
+    layouter: &mut impl Layouter<AesChip>,
+    column: &[AesChip::Byte],
+) -> Result<Vec<AesChip::Byte>, Error> {
+    let sum01 = AesChip::xor(layouter, &column[0], &column[1])?;
+    let sum23 = AesChip::xor(layouter, &column[2], &column[3])?;
+    let sum = AesChip::xor(layouter, &sum01, &sum23)?;
+
+    (0..4)
+        .map(|i| {
+            let pair = match i {
+                0 => sum01.clone(),
+                2 => sum23.clone(),
+                _ => AesChip::xor(layouter, &column[i], &column[(i + 1) % 4])?,
+            };
+            let doubled = AesChip::xtime(layouter, &pair)?;
+            let byte = AesChip::xor(layouter, &column[i], &sum)?;
+            AesChip::xor(layouter, &byte, &doubled)
+        })
+        .collect()
+}
@@ -0,0 +1,402 @@
+//! A chip implementing [`AesInstructions`] with a table of byte operations.
+//!
+//! # Layout
+//!
+//! The chip uses three advice columns $a, b, o$ in a permutation, and a table of
+//! $(\mathsf{tag}, x, y, z)$ with the entries $(0, x, y, x \oplus y)$ for all 4-bit $x$
+//! and $y$, $(1, x, 0, S(x))$ and $(2, x, 0, 2 \cdot x)$ for all bytes $x$, which has
+//! 768 rows.
+//!
+//! An XOR uses three rows: row 0 holds the bytes $a$, $b$ and $o = a \oplus b$, and row
+//! $1 + i$ holds their $i$-th little-endian nibbles, which are looked up in the table.
+//! The bytes of row 0 are constrained to be the sum of their nibbles. The S-box and
+//! doubling use a single row holding $a$, $0$ and the output, which is looked up with
+//! the tag of the operation in a fixed column. Constants are assigned in the $a$ column
+//! and constrained to a fixed column.
+
+use std::marker::PhantomData;
+
+use super::AesInstructions;
+use crate::{
+    arithmetic::FieldExt,
+    circuit::{Cell, Chip, Layouter, Region},
+    plonk::{Advice, Column, ConstraintSystem, Error, Fixed, Permutation, Selector},
+    poly::Rotation,
+    primitives::aes::{sbox, xtime},
+};
+
+/// The tag of XOR entries in the table.
+const TAG_XOR: u64 = 0;
+
+/// The tag of S-box entries in the table.
+const TAG_SBOX: u64 = 1;
+
+/// The tag of doubling entries in the table.
+const TAG_XTIME: u64 = 2;
+
+/// A byte assigned in the circuit.
+#[derive(Clone, Copy, Debug)]
+pub struct AssignedByte {
+    cell: Cell,
+    value: Option<u8>,
+}
+
+impl AssignedByte {
+    /// The cell containing this byte.
+    pub fn cell(&self) -> Cell {
+        self.cell
+    }
+
+    /// The byte, if known.
+    pub fn value(&self) -> Option<u8> {
+        self.value
+    }
+}
+
+/// Configuration for an [`AesChip`].
+#[derive(Clone, Debug)]
+pub struct AesConfig {
+    advices: [Column<Advice>; 3],
+    tag: Column<Fixed>,
+    table: [Column<Fixed>; 4],
+    constant: Column<Fixed>,
+    q_byte: Selector,
+    q_lookup: Selector,
+    s_constant: Selector,
+    perm: Permutation,
+}
+
+/// A chip implementing [`AesInstructions`].
+#[derive(Debug)]
+pub struct AesChip<F: FieldExt> {
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt> Chip for AesChip<F> {
+    type Config = AesConfig;
+    type Loaded = ();
+    type Field = F;
+
+    fn load(layouter: &mut impl Layouter<Self>) -> Result<(), Error> {
+        let config = layouter.config().clone();
+
+        let xor = (0..16u8).flat_map(|x| (0..16u8).map(move |y| (TAG_XOR, x, y, x ^ y)));
+        let sbox = (0..=255u8).map(|x| (TAG_SBOX, x, 0, sbox(x)));
+        let xtime = (0..=255u8).map(|x| (TAG_XTIME, x, 0, xtime(x)));
+
+        layouter.assign_region(
+            || "aes table",
+            |mut region| {
+                for (row, (tag, x, y, z)) in xor
+                    .clone()
+                    .chain(sbox.clone())
+                    .chain(xtime.clone())
+                    .enumerate()
+                {
+                    let values = [tag, x as u64, y as u64, z as u64];
+                    for (column, value) in config.table.iter().zip(values) {
+                        region.assign_fixed(|| "table", *column, row, || Ok(F::from_u64(value)))?;
+                    }
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+impl<F: FieldExt> AesChip<F> {
+    /// Configures this chip for use in a circuit.
+    pub fn configure(meta: &mut ConstraintSystem<F>) -> AesConfig {
+        let advices = [
+            meta.advice_column(),
+            meta.advice_column(),
+            meta.advice_column(),
+        ];
+        let config = AesConfig {
+            advices,
+            tag: meta.fixed_column(),
+            table: [
+                meta.fixed_column(),
+                meta.fixed_column(),
+                meta.fixed_column(),
+                meta.fixed_column(),
+            ],
+            constant: meta.fixed_column(),
+            q_byte: meta.selector(),
+            q_lookup: meta.selector(),
+            s_constant: meta.selector(),
+            perm: Permutation::new(
+                meta,
+                &[advices[0].into(), advices[1].into(), advices[2].into()],
+            ),
+        };
+
+        // byte = lo + 16 hi
+        for column in advices.iter() {
+            meta.create_gate("aes nibbles", |meta| {
+                let byte = meta.query_advice(*column, Rotation::cur());
+                let lo = meta.query_advice(*column, Rotation::next());
+                let hi = meta.query_advice(*column, Rotation(2));
+                let q_byte = meta.query_selector(config.q_byte, Rotation::cur());
+
+                q_byte * (byte - lo - hi * F::from_u64(16))
+            });
+        }
+
+        meta.create_gate("aes constant", |meta| {
+            let byte = meta.query_advice(advices[0], Rotation::cur());
+            let constant = meta.query_fixed(config.constant, Rotation::cur());
+            let s_constant = meta.query_selector(config.s_constant, Rotation::cur());
+
+            s_constant * (byte - constant)
+        });
+
+        // Rows without a lookup look up (0, 0, 0, 0), which is an XOR entry.
+        let q_lookup = meta.query_selector(config.q_lookup, Rotation::cur());
+        let tag = meta.query_fixed(config.tag, Rotation::cur());
+        let mut input = vec![q_lookup.clone() * tag];
+        for column in advices.iter() {
+            input.push(q_lookup.clone() * meta.query_advice(*column, Rotation::cur()));
+        }
+        let table: Vec<_> = config
+            .table
+            .iter()
+            .map(|column| meta.query_fixed(*column, Rotation::cur()))
+            .collect();
+        meta.lookup(&input, &table);
+
+        config
+    }
+}
+
+impl AesConfig {
+    /// Assigns `value` in column `i` at `offset`.
+    fn assign_byte<F: FieldExt>(
+        &self,
+        region: &mut Region<'_, AesChip<F>>,
+        i: usize,
+        offset: usize,
+        value: Option<u8>,
+    ) -> Result<AssignedByte, Error> {
+        let cell = region.assign_advice(
+            || "byte",
+            self.advices[i],
+            offset,
+            || {
+                value
+                    .map(|value| F::from_u64(value as u64))
+                    .ok_or(Error::SynthesisError)
+            },
+        )?;
+        Ok(AssignedByte { cell, value })
+    }
+
+    /// Assigns a copy of `byte` in column `i` at `offset`.
+    fn copy_byte<F: FieldExt>(
+        &self,
+        region: &mut Region<'_, AesChip<F>>,
+        i: usize,
+        offset: usize,
+        byte: &AssignedByte,
+    ) -> Result<AssignedByte, Error> {
+        let copy = self.assign_byte(region, i, offset, byte.value)?;
+        region.constrain_equal(&self.perm, byte.cell, copy.cell)?;
+        Ok(copy)
+    }
+
+    /// Looks up `a` in the table entries with `tag`, returning the output.
+    fn lookup<F: FieldExt>(
+        &self,
+        layouter: &mut impl Layouter<AesChip<F>>,
+        tag: u64,
+        a: &AssignedByte,
+        f: fn(u8) -> u8,
+    ) -> Result<AssignedByte, Error> {
+        layouter.assign_region(
+            || "aes lookup",
+            |mut region| {
+                self.q_lookup.enable(&mut region, 0)?;
+                region.assign_fixed(|| "tag", self.tag, 0, || Ok(F::from_u64(tag)))?;
+                self.copy_byte(&mut region, 0, 0, a)?;
+                self.assign_byte(&mut region, 1, 0, Some(0))?;
+                self.assign_byte(&mut region, 2, 0, a.value.map(f))
+            },
+        )
+    }
+}
+
+impl<F: FieldExt> AesInstructions for AesChip<F> {
+    type Byte = AssignedByte;
+
+    fn witness_byte(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<u8>,
+    ) -> Result<Self::Byte, Error> {
+        let config = layouter.config().clone();
+        layouter.assign_region(
+            || "witness byte",
+            |mut region| config.assign_byte(&mut region, 0, 0, value),
+        )
+    }
+
+    fn constant_byte(layouter: &mut impl Layouter<Self>, value: u8) -> Result<Self::Byte, Error> {
+        let config = layouter.config().clone();
+        layouter.assign_region(
+            || "constant byte",
+            |mut region| {
+                config.s_constant.enable(&mut region, 0)?;
+                region.assign_fixed(
+                    || "constant",
+                    config.constant,
+                    0,
+                    || Ok(F::from_u64(value as u64)),
+                )?;
+                config.assign_byte(&mut region, 0, 0, Some(value))
+            },
+        )
+    }
+
+    fn xor(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Byte,
+        b: &Self::Byte,
+    ) -> Result<Self::Byte, Error> {
+        let config = layouter.config().clone();
+        layouter.assign_region(
+            || "xor",
+            |mut region| {
+                config.q_byte.enable(&mut region, 0)?;
+                config.copy_byte(&mut region, 0, 0, a)?;
+                config.copy_byte(&mut region, 1, 0, b)?;
+                let out = a.value.zip(b.value).map(|(a, b)| a ^ b);
+                let res = config.assign_byte(&mut region, 2, 0, out)?;
+
+                for (i, value) in [a.value, b.value, out].iter().enumerate() {
+                    for nibble in 0..2 {
+                        let row = 1 + nibble;
+                        config.assign_byte(
+                            &mut region,
+                            i,
+                            row,
+                            value.map(|value| (value >> (4 * nibble)) & 0xf),
+                        )?;
+                    }
+                }
+                for row in 1..3 {
+                    config.q_lookup.enable(&mut region, row)?;
+                    region.assign_fixed(|| "tag", config.tag, row, || Ok(F::from_u64(TAG_XOR)))?;
+                }
+
+                Ok(res)
+            },
+        )
+    }
+
+    fn sub_byte(layouter: &mut impl Layouter<Self>, a: &Self::Byte) -> Result<Self::Byte, Error> {
+        layouter
+            .config()
+            .clone()
+            .lookup(layouter, TAG_SBOX, a, sbox)
+    }
+
+    fn xtime(layouter: &mut impl Layouter<Self>, a: &Self::Byte) -> Result<Self::Byte, Error> {
+        layouter
+            .config()
+            .clone()
+            .lookup(layouter, TAG_XTIME, a, xtime)
+    }
+
+    fn constrain_equal(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Byte,
+        b: &Self::Byte,
+    ) -> Result<(), Error> {
+        let config = layouter.config().clone();
+        layouter.assign_region(
+            || "constrain equal",
+            |mut region| region.constrain_equal(&config.perm, a.cell, b.cell),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryInto;
+
+    use super::{AesChip, AesConfig};
+    use crate::{
+        circuit::{layouter, Layouter},
+        dev::MockProver,
+        gadget::aes::{Aes128, AesInstructions, Block},
+        pasta::Fp,
+        plonk::{Assignment, Circuit, ConstraintSystem, Error},
+        primitives::aes,
+    };
+
+    struct AesCircuit {
+        key: aes::Block,
+        plaintext: aes::Block,
+        ciphertext: aes::Block,
+    }
+
+    impl Circuit<Fp> for AesCircuit {
+        type Config = AesConfig;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> AesConfig {
+            AesChip::configure(meta)
+        }
+
+        fn synthesize(&self, cs: &mut impl Assignment<Fp>, config: AesConfig) -> Result<(), Error> {
+            let mut layouter = layouter::SingleChip::<AesChip<Fp>, _>::new(cs, config)?;
+
+            let mut witness = |bytes: &aes::Block| -> Result<Block<AesChip<Fp>>, Error> {
+                let bytes = bytes
+                    .iter()
+                    .map(|byte| AesChip::witness_byte(&mut layouter, Some(*byte)))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(bytes.try_into().unwrap())
+            };
+            let key = witness(&self.key)?;
+            let plaintext = witness(&self.plaintext)?;
+
+            let cipher = Aes128::new(layouter.namespace(|| "key schedule"), &key)?;
+            let ciphertext = cipher.encrypt(layouter.namespace(|| "encrypt"), &plaintext)?;
+
+            for (byte, value) in ciphertext.iter().zip(self.ciphertext.iter()) {
+                let expected = AesChip::constant_byte(&mut layouter, *value)?;
+                AesChip::constrain_equal(&mut layouter, byte, &expected)?;
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn aes_matches_host() {
+        let key: aes::Block = (0..16).collect::<Vec<_>>().try_into().unwrap();
+        let plaintext: aes::Block = (0..16)
+            .map(|i| 0x11 * i)
+            .collect::<Vec<_>>()
+            .try_into()
+            .unwrap();
+        let ciphertext = aes::encrypt(&key, &plaintext);
+
+        let circuit = AesCircuit {
+            key,
+            plaintext,
+            ciphertext,
+        };
+        let prover = MockProver::run(12, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        let mut wrong = ciphertext;
+        wrong[5] ^= 1;
+        let circuit = AesCircuit {
+            ciphertext: wrong,
+            ..circuit
+        };
+        let prover = MockProver::run(12, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
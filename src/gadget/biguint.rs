@@ -0,0 +1,224 @@
+//! Gadget and chips for arithmetic on arbitrary-precision unsigned integers.
+//!
+//! An integer is represented by a number of range-checked 64-bit limbs chosen when it is
+//! witnessed, so values of 4096 bits or more can be used for statements about RSA and
+//! other classical cryptography. Operations produce as many limbs as their result can
+//! need, and are checked as exact integer identities without any modular reduction,
+//! except by [`AssignedBigUint::div_rem`]. The values being constrained are computed
+//! outside of a circuit by [`crate::primitives::biguint`].
+
+use std::fmt;
+
+use crate::{
+    circuit::{Chip, Layouter},
+    plonk::Error,
+    primitives::biguint::BigUint,
+};
+
+mod chip;
+pub use chip::{AssignedLimbs, BigUintChip, BigUintConfig, MAX_LIMBS};
+
+/// The set of circuit instructions required to use the [`AssignedBigUint`] gadget.
+pub trait BigUintInstructions: Chip {
+    /// Variable representing an integer.
+    type BigUint: Clone + fmt::Debug;
+
+    /// Witnesses an integer as `num_limbs` 64-bit limbs.
+    ///
+    /// Returns an error if `value` does not fit in that many limbs.
+    fn witness_biguint(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<&BigUint>,
+        num_limbs: usize,
+    ) -> Result<Self::BigUint, Error>;
+
+    /// Returns an integer constrained to equal `value`.
+    fn constant(
+        layouter: &mut impl Layouter<Self>,
+        value: &BigUint,
+    ) -> Result<Self::BigUint, Error>;
+
+    /// Returns the value of `a`, if known.
+    fn value(a: &Self::BigUint) -> Option<BigUint>;
+
+    /// Returns the number of limbs of `a`.
+    fn num_limbs(a: &Self::BigUint) -> usize;
+
+    /// Returns `a` as `num_limbs` limbs, by dropping or appending its top limbs.
+    ///
+    /// The circuit is not satisfied if any dropped limb is non-zero.
+    fn resize(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::BigUint,
+        num_limbs: usize,
+    ) -> Result<Self::BigUint, Error>;
+
+    /// Returns `a + b`.
+    fn add(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::BigUint,
+        b: &Self::BigUint,
+    ) -> Result<Self::BigUint, Error>;
+
+    /// Returns `a - b`.
+    ///
+    /// The circuit is not satisfied if `b` is greater than `a`.
+    fn sub(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::BigUint,
+        b: &Self::BigUint,
+    ) -> Result<Self::BigUint, Error>;
+
+    /// Returns `a * b`.
+    fn mul(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::BigUint,
+        b: &Self::BigUint,
+    ) -> Result<Self::BigUint, Error>;
+
+    /// Returns the quotient and remainder of `a` divided by `b`.
+    ///
+    /// The circuit is not satisfied if `b` is zero.
+    fn div_rem(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::BigUint,
+        b: &Self::BigUint,
+    ) -> Result<(Self::BigUint, Self::BigUint), Error>;
+
+    /// Constrains `a` to be less than `b`.
+    fn constrain_less_than(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::BigUint,
+        b: &Self::BigUint,
+    ) -> Result<(), Error>;
+
+    /// Constrains `a` and `b` to be equal, even if they have different numbers of limbs.
+    fn constrain_equal(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::BigUint,
+        b: &Self::BigUint,
+    ) -> Result<(), Error>;
+}
+
+/// An arbitrary-precision unsigned integer assigned in a circuit.
+#[derive(Debug)]
+pub struct AssignedBigUint<BigUintChip: BigUintInstructions> {
+    inner: BigUintChip::BigUint,
+}
+
+impl<BigUintChip: BigUintInstructions> AssignedBigUint<BigUintChip> {
+    /// Witnesses an integer as `num_limbs` 64-bit limbs.
+    pub fn new(
+        mut layouter: impl Layouter<BigUintChip>,
+        value: Option<&BigUint>,
+        num_limbs: usize,
+    ) -> Result<Self, Error> {
+        BigUintChip::witness_biguint(&mut layouter, value, num_limbs)
+            .map(|inner| AssignedBigUint { inner })
+    }
+
+    /// Returns an integer constrained to equal `value`.
+    pub fn constant(
+        mut layouter: impl Layouter<BigUintChip>,
+        value: &BigUint,
+    ) -> Result<Self, Error> {
+        BigUintChip::constant(&mut layouter, value).map(|inner| AssignedBigUint { inner })
+    }
+
+    /// The value of this integer, if known.
+    pub fn value(&self) -> Option<BigUint> {
+        BigUintChip::value(&self.inner)
+    }
+
+    /// The number of limbs of this integer.
+    pub fn num_limbs(&self) -> usize {
+        BigUintChip::num_limbs(&self.inner)
+    }
+
+    /// Returns this integer as `num_limbs` limbs, which must be enough to hold it.
+    pub fn resize(
+        &self,
+        mut layouter: impl Layouter<BigUintChip>,
+        num_limbs: usize,
+    ) -> Result<Self, Error> {
+        BigUintChip::resize(&mut layouter, &self.inner, num_limbs)
+            .map(|inner| AssignedBigUint { inner })
+    }
+
+    /// Returns `self + other`.
+    pub fn add(
+        &self,
+        mut layouter: impl Layouter<BigUintChip>,
+        other: &Self,
+    ) -> Result<Self, Error> {
+        BigUintChip::add(&mut layouter, &self.inner, &other.inner)
+            .map(|inner| AssignedBigUint { inner })
+    }
+
+    /// Returns `self - other`, which must not be negative.
+    pub fn sub(
+        &self,
+        mut layouter: impl Layouter<BigUintChip>,
+        other: &Self,
+    ) -> Result<Self, Error> {
+        BigUintChip::sub(&mut layouter, &self.inner, &other.inner)
+            .map(|inner| AssignedBigUint { inner })
+    }
+
+    /// Returns `self * other`.
+    pub fn mul(
+        &self,
+        mut layouter: impl Layouter<BigUintChip>,
+        other: &Self,
+    ) -> Result<Self, Error> {
+        BigUintChip::mul(&mut layouter, &self.inner, &other.inner)
+            .map(|inner| AssignedBigUint { inner })
+    }
+
+    /// Returns the quotient and remainder of `self` divided by `divisor`, which must be
+    /// non-zero.
+    pub fn div_rem(
+        &self,
+        mut layouter: impl Layouter<BigUintChip>,
+        divisor: &Self,
+    ) -> Result<(Self, Self), Error> {
+        BigUintChip::div_rem(&mut layouter, &self.inner, &divisor.inner)
+            .map(|(q, r)| (AssignedBigUint { inner: q }, AssignedBigUint { inner: r }))
+    }
+
+    /// Returns `self` modulo `modulus`, which must be non-zero.
+    pub fn rem(&self, layouter: impl Layouter<BigUintChip>, modulus: &Self) -> Result<Self, Error> {
+        self.div_rem(layouter, modulus).map(|(_, r)| r)
+    }
+
+    /// Constrains `self` to be less than `other`.
+    pub fn constrain_less_than(
+        &self,
+        mut layouter: impl Layouter<BigUintChip>,
+        other: &Self,
+    ) -> Result<(), Error> {
+        BigUintChip::constrain_less_than(&mut layouter, &self.inner, &other.inner)
+    }
+
+    /// Constrains `self` and `other` to be equal.
+    pub fn constrain_equal(
+        &self,
+        mut layouter: impl Layouter<BigUintChip>,
+        other: &Self,
+    ) -> Result<(), Error> {
+        BigUintChip::constrain_equal(&mut layouter, &self.inner, &other.inner)
+    }
+
+    /// Returns the chip-specific representation of this integer.
+    pub fn inner(&self) -> &BigUintChip::BigUint {
+        &self.inner
+    }
+}
+
+impl<BigUintChip: BigUintInstructions> Clone for AssignedBigUint<BigUintChip> {
+    fn clone(&self) -> Self {
+        AssignedBigUint {
+            inner: self.inner.clone(),
+        }
+    }
+}
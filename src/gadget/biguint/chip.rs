@@ -0,0 +1,813 @@
+//! A chip for arithmetic on multi-limb integers.
+//!
+//! Every operation is checked as an identity $\sum_k t_k 2^{64 k} = \sum_k c_k 2^{64 k}$
+//! between column sums $t_k$ of its operands and the limbs $c_k$ of an output, where
+//! each $t_k$ is a sum of terms $x y$ or $x + y$ of operand limbs plus a small constant.
+//! The identity is checked column by column with carries $v_k$: the terms of column $k$
+//! are accumulated onto $v_{k - 1}$ and the total is split as $c_k + 2^{64} v_k$, with
+//! $v_{-1} = 0$ and a zero final carry. A column sum of products of an operand with at
+//! most [`MAX_LIMBS`] limbs is less than $2^{135}$, so the carries fit in 72 bits and
+//! the accumulator never overflows the circuit field.
+//!
+//! Subtraction, division and comparison witness their results and check an addition or
+//! a product plus a remainder against their operands.
+//!
+//! # Layout
+//!
+//! Each term uses one row, holding copies of its limbs in the `x` and `y` columns, its
+//! coefficients and constant in fixed columns, and the accumulator before the term in
+//! `acc`. The terms of each column are followed by a carry row, which holds the
+//! accumulated total in `acc`, the output limb $c_k$ in `limb` and the carry $v_k$ in
+//! `carry`, and the next row starts with $v_k$ in `acc`. Limbs are range-checked by eight
+//! 8-bit chunks and carries by nine, which are looked up in a table. A witnessed integer
+//! uses one row per limb, in the `limb` column.
+
+use std::convert::TryInto;
+use std::marker::PhantomData;
+
+use super::BigUintInstructions;
+use crate::{
+    arithmetic::FieldExt,
+    circuit::{Cell, Chip, Layouter, Region},
+    plonk::{Advice, Column, ConstraintSystem, Error, Expression, Fixed, Permutation, Selector},
+    poly::Rotation,
+    primitives::biguint::BigUint,
+};
+
+/// The maximum number of limbs in the shorter operand of a product, so that integers of
+/// up to 4096 bits can be multiplied.
+pub const MAX_LIMBS: usize = 64;
+
+/// The number of bits in each range-checked chunk.
+const CHUNK_BITS: usize = 8;
+
+/// The number of chunks in the range check of a limb.
+const LIMB_CHUNKS: usize = 8;
+
+/// The number of chunks in the range check of a carry.
+const CARRY_CHUNKS: usize = 9;
+
+/// An integer assigned in the circuit, as range-checked 64-bit limbs.
+#[derive(Clone, Debug)]
+pub struct AssignedLimbs {
+    cells: Vec<Cell>,
+    value: Option<BigUint>,
+}
+
+impl AssignedLimbs {
+    /// The cells containing the limbs of this integer, from the least significant.
+    pub fn cells(&self) -> &[Cell] {
+        &self.cells
+    }
+
+    /// The value of this integer, if known.
+    pub fn value(&self) -> Option<&BigUint> {
+        self.value.as_ref()
+    }
+
+    /// The number of limbs of this integer.
+    pub fn num_limbs(&self) -> usize {
+        self.cells.len()
+    }
+
+    fn limb(&self, i: usize) -> Option<Limb> {
+        self.cells.get(i).map(|cell| Limb {
+            cell: *cell,
+            value: self.value.as_ref().map(|value| value.limb(i)),
+        })
+    }
+}
+
+/// A limb of an assigned integer.
+#[derive(Clone, Copy, Debug)]
+struct Limb {
+    cell: Cell,
+    value: Option<u64>,
+}
+
+/// A term of a column sum: $x y + c$ if `product` is set, and $x + y + c$ otherwise,
+/// where missing limbs are zero.
+#[derive(Clone, Copy, Debug, Default)]
+struct Term {
+    x: Option<Limb>,
+    y: Option<Limb>,
+    product: bool,
+    constant: u64,
+}
+
+impl Term {
+    fn value(&self) -> Option<BigUint> {
+        let x = self.x.map_or(Some(0), |x| x.value)?;
+        let y = self.y.map_or(Some(0), |y| y.value)?;
+        let c = BigUint::from(self.constant);
+        Some(if self.product {
+            BigUint::from(x).mul(&BigUint::from(y)).add(&c)
+        } else {
+            BigUint::from(x).add(&BigUint::from(y)).add(&c)
+        })
+    }
+}
+
+/// Returns an integer less than the field modulus as a field element.
+fn to_field<F: FieldExt>(value: &BigUint) -> F {
+    let two_pow_64 = F::from_u128(1 << 64);
+    value
+        .limbs()
+        .iter()
+        .rev()
+        .fold(F::zero(), |acc, limb| acc * two_pow_64 + F::from_u64(*limb))
+}
+
+/// Configuration for a [`BigUintChip`].
+#[derive(Clone, Debug)]
+pub struct BigUintConfig {
+    x: Column<Advice>,
+    y: Column<Advice>,
+    acc: Column<Advice>,
+    limb: Column<Advice>,
+    carry: Column<Advice>,
+    limb_chunks: [Column<Advice>; LIMB_CHUNKS],
+    carry_chunks: [Column<Advice>; CARRY_CHUNKS],
+    k_mul: Column<Fixed>,
+    k_add: Column<Fixed>,
+    constant: Column<Fixed>,
+    table: Column<Fixed>,
+    s_start: Selector,
+    s_term: Selector,
+    s_carry: Selector,
+    s_last: Selector,
+    s_limb: Selector,
+    s_wide: Selector,
+    s_constant: Selector,
+    s_zero: Selector,
+    perm: Permutation,
+}
+
+/// A chip implementing [`BigUintInstructions`].
+#[derive(Debug)]
+pub struct BigUintChip<F: FieldExt> {
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt> Chip for BigUintChip<F> {
+    type Config = BigUintConfig;
+    type Loaded = ();
+    type Field = F;
+
+    fn load(layouter: &mut impl Layouter<Self>) -> Result<(), Error> {
+        let table = layouter.config().table;
+
+        layouter.assign_region(
+            || "range-check table",
+            |mut region| {
+                for value in 0..(1 << CHUNK_BITS) {
+                    region.assign_fixed(
+                        || "table",
+                        table,
+                        value,
+                        || Ok(F::from_u64(value as u64)),
+                    )?;
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+impl<F: FieldExt> BigUintChip<F> {
+    /// Configures this chip for use in a circuit.
+    ///
+    /// This allocates 22 advice columns, three of which are placed in a permutation, and
+    /// four fixed columns.
+    pub fn configure(meta: &mut ConstraintSystem<F>) -> BigUintConfig {
+        let advice = |meta: &mut ConstraintSystem<F>, n: usize| -> Vec<Column<Advice>> {
+            (0..n).map(|_| meta.advice_column()).collect()
+        };
+
+        let x = meta.advice_column();
+        let y = meta.advice_column();
+        let acc = meta.advice_column();
+        let limb = meta.advice_column();
+        let carry = meta.advice_column();
+        let perm = Permutation::new(meta, &[x.into(), y.into(), limb.into()]);
+
+        let config = BigUintConfig {
+            x,
+            y,
+            acc,
+            limb,
+            carry,
+            limb_chunks: advice(meta, LIMB_CHUNKS).try_into().unwrap(),
+            carry_chunks: advice(meta, CARRY_CHUNKS).try_into().unwrap(),
+            k_mul: meta.fixed_column(),
+            k_add: meta.fixed_column(),
+            constant: meta.fixed_column(),
+            table: meta.fixed_column(),
+            s_start: meta.selector(),
+            s_term: meta.selector(),
+            s_carry: meta.selector(),
+            s_last: meta.selector(),
+            s_limb: meta.selector(),
+            s_wide: meta.selector(),
+            s_constant: meta.selector(),
+            s_zero: meta.selector(),
+            perm,
+        };
+        config.create_gates(meta);
+
+        config
+    }
+}
+
+impl BigUintConfig {
+    fn create_gates<F: FieldExt>(&self, meta: &mut ConstraintSystem<F>) {
+        let two_pow_64 = F::from_u128(1 << 64);
+
+        meta.create_gate("accumulator start", |meta| {
+            let acc = meta.query_advice(self.acc, Rotation::cur());
+            let s_start = meta.query_selector(self.s_start, Rotation::cur());
+
+            s_start * acc
+        });
+
+        // acc' = acc + k_mul x y + k_add (x + y) + c
+        meta.create_gate("column term", |meta| {
+            let x = meta.query_advice(self.x, Rotation::cur());
+            let y = meta.query_advice(self.y, Rotation::cur());
+            let acc = meta.query_advice(self.acc, Rotation::cur());
+            let acc_next = meta.query_advice(self.acc, Rotation::next());
+            let k_mul = meta.query_fixed(self.k_mul, Rotation::cur());
+            let k_add = meta.query_fixed(self.k_add, Rotation::cur());
+            let constant = meta.query_fixed(self.constant, Rotation::cur());
+            let s_term = meta.query_selector(self.s_term, Rotation::cur());
+
+            s_term * (acc_next - acc - k_mul * x.clone() * y.clone() - k_add * (x + y) - constant)
+        });
+
+        // acc = c_k + 2^64 v_k, and the next column starts with v_k.
+        meta.create_gate("column carry", |meta| {
+            let acc = meta.query_advice(self.acc, Rotation::cur());
+            let limb = meta.query_advice(self.limb, Rotation::cur());
+            let carry = meta.query_advice(self.carry, Rotation::cur());
+            let s_carry = meta.query_selector(self.s_carry, Rotation::cur());
+
+            s_carry * (acc - limb - carry * two_pow_64)
+        });
+        meta.create_gate("column carry", |meta| {
+            let acc_next = meta.query_advice(self.acc, Rotation::next());
+            let carry = meta.query_advice(self.carry, Rotation::cur());
+            let s_carry = meta.query_selector(self.s_carry, Rotation::cur());
+
+            s_carry * (acc_next - carry)
+        });
+        meta.create_gate("final carry", |meta| {
+            let carry = meta.query_advice(self.carry, Rotation::cur());
+            let s_last = meta.query_selector(self.s_last, Rotation::cur());
+
+            s_last * carry
+        });
+
+        for (name, value, chunks, selector) in [
+            (
+                "limb range check",
+                self.limb,
+                &self.limb_chunks[..],
+                self.s_limb,
+            ),
+            (
+                "carry range check",
+                self.carry,
+                &self.carry_chunks[..],
+                self.s_wide,
+            ),
+        ]
+        .iter()
+        {
+            meta.create_gate(name, |meta| {
+                let value = meta.query_advice(*value, Rotation::cur());
+                let selector = meta.query_selector(*selector, Rotation::cur());
+                let sum = chunks.iter().enumerate().fold(
+                    Expression::Constant(F::zero()),
+                    |acc, (j, chunk)| {
+                        acc + meta.query_advice(*chunk, Rotation::cur())
+                            * F::from_u128(1 << (CHUNK_BITS * j))
+                    },
+                );
+
+                selector * (value - sum)
+            });
+        }
+        for chunk in self.limb_chunks.iter().chain(self.carry_chunks.iter()) {
+            let input = meta.query_advice(*chunk, Rotation::cur());
+            let table = meta.query_fixed(self.table, Rotation::cur());
+            meta.lookup(&[input], &[table]);
+        }
+
+        meta.create_gate("constant limb", |meta| {
+            let limb = meta.query_advice(self.limb, Rotation::cur());
+            let constant = meta.query_fixed(self.constant, Rotation::cur());
+            let s_constant = meta.query_selector(self.s_constant, Rotation::cur());
+
+            s_constant * (limb - constant)
+        });
+        meta.create_gate("zero limb", |meta| {
+            let x = meta.query_advice(self.x, Rotation::cur());
+            let s_zero = meta.query_selector(self.s_zero, Rotation::cur());
+
+            s_zero * x
+        });
+    }
+
+    /// Assigns `value` and its chunks at `row`, and returns the cell of the value.
+    fn assign_range<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        region: &mut Region<'_, Ch>,
+        (column, chunks): (Column<Advice>, &[Column<Advice>]),
+        row: usize,
+        value: Option<&BigUint>,
+    ) -> Result<Cell, Error> {
+        for (j, chunk) in chunks.iter().enumerate() {
+            region.assign_advice(
+                || format!("chunk {}", j),
+                *chunk,
+                row,
+                || {
+                    value
+                        .map(|value| F::from_u64((value.limb(j / 8) >> (8 * (j % 8))) & 0xff))
+                        .ok_or(Error::SynthesisError)
+                },
+            )?;
+        }
+        region.assign_advice(
+            || "value",
+            column,
+            row,
+            || value.map(to_field).ok_or(Error::SynthesisError),
+        )
+    }
+
+    /// Assigns a copy of `limb` in `column` at `row`, or zero if it is missing.
+    fn assign_operand<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        region: &mut Region<'_, Ch>,
+        column: Column<Advice>,
+        row: usize,
+        limb: Option<Limb>,
+    ) -> Result<(), Error> {
+        let value = limb.map_or(Some(0), |limb| limb.value);
+        let cell = region.assign_advice(
+            || "operand",
+            column,
+            row,
+            || value.map(F::from_u64).ok_or(Error::SynthesisError),
+        )?;
+        if let Some(limb) = limb {
+            region.constrain_equal(&self.perm, cell, limb.cell)?;
+        }
+        Ok(())
+    }
+
+    /// Assigns the identity whose column sums are given by `columns` in its own region,
+    /// and returns its output integer, which has one limb per column.
+    fn assign_columns<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        layouter: &mut impl Layouter<Ch>,
+        columns: &[Vec<Term>],
+    ) -> Result<AssignedLimbs, Error> {
+        layouter.assign_region(
+            || "integer identity",
+            |mut region| {
+                self.s_start.enable(&mut region, 0)?;
+
+                let mut row = 0;
+                let mut acc = Some(BigUint::zero());
+                let mut cells = vec![];
+                let mut limbs = Some(vec![]);
+                for (k, terms) in columns.iter().enumerate() {
+                    for term in terms {
+                        self.s_term.enable(&mut region, row)?;
+                        region.assign_advice(
+                            || "acc",
+                            self.acc,
+                            row,
+                            || acc.as_ref().map(to_field).ok_or(Error::SynthesisError),
+                        )?;
+                        self.assign_operand(&mut region, self.x, row, term.x)?;
+                        self.assign_operand(&mut region, self.y, row, term.y)?;
+                        let (k_mul, k_add) = if term.product { (1, 0) } else { (0, 1) };
+                        for (name, column, value) in [
+                            ("k_mul", self.k_mul, k_mul),
+                            ("k_add", self.k_add, k_add),
+                            ("constant", self.constant, term.constant),
+                        ]
+                        .iter()
+                        {
+                            region.assign_fixed(
+                                || *name,
+                                *column,
+                                row,
+                                || Ok(F::from_u64(*value)),
+                            )?;
+                        }
+                        acc = acc.zip(term.value()).map(|(acc, t)| acc.add(&t));
+                        row += 1;
+                    }
+
+                    self.s_carry.enable(&mut region, row)?;
+                    self.s_limb.enable(&mut region, row)?;
+                    self.s_wide.enable(&mut region, row)?;
+                    if k + 1 == columns.len() {
+                        self.s_last.enable(&mut region, row)?;
+                    }
+                    region.assign_advice(
+                        || "acc",
+                        self.acc,
+                        row,
+                        || acc.as_ref().map(to_field).ok_or(Error::SynthesisError),
+                    )?;
+                    let limb = acc.as_ref().map(|acc| BigUint::from(acc.limb(0)));
+                    let carry = acc.as_ref().map(|acc| acc.shr(64));
+                    cells.push(self.assign_range(
+                        &mut region,
+                        (self.limb, &self.limb_chunks),
+                        row,
+                        limb.as_ref(),
+                    )?);
+                    self.assign_range(
+                        &mut region,
+                        (self.carry, &self.carry_chunks),
+                        row,
+                        carry.as_ref(),
+                    )?;
+                    limbs = limbs.zip(limb).map(|(mut limbs, limb)| {
+                        limbs.push(limb.limb(0));
+                        limbs
+                    });
+                    acc = carry;
+                    row += 1;
+                }
+                region.assign_advice(
+                    || "acc",
+                    self.acc,
+                    row,
+                    || acc.as_ref().map(to_field).ok_or(Error::SynthesisError),
+                )?;
+
+                Ok(AssignedLimbs {
+                    cells,
+                    value: limbs.map(|limbs| BigUint::from_limbs(&limbs)),
+                })
+            },
+        )
+    }
+
+    /// Returns the columns of `a + b`, plus an empty top column.
+    fn sum_columns(a: &AssignedLimbs, b: &AssignedLimbs) -> Vec<Vec<Term>> {
+        let len = a.num_limbs().max(b.num_limbs());
+        (0..=len)
+            .map(|k| {
+                if k < len {
+                    vec![Term {
+                        x: a.limb(k),
+                        y: b.limb(k),
+                        ..Default::default()
+                    }]
+                } else {
+                    vec![]
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the columns of `a * b`, including an empty top column.
+    fn product_columns(a: &AssignedLimbs, b: &AssignedLimbs) -> Vec<Vec<Term>> {
+        assert!(a.num_limbs().min(b.num_limbs()) <= MAX_LIMBS);
+
+        let mut columns = vec![vec![]; a.num_limbs() + b.num_limbs()];
+        for i in 0..a.num_limbs() {
+            for j in 0..b.num_limbs() {
+                columns[i + j].push(Term {
+                    x: a.limb(i),
+                    y: b.limb(j),
+                    product: true,
+                    constant: 0,
+                });
+            }
+        }
+        columns
+    }
+
+    /// Witnesses `value` as `num_limbs` range-checked limbs in its own region.
+    fn witness<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        layouter: &mut impl Layouter<Ch>,
+        value: Option<&BigUint>,
+        num_limbs: usize,
+    ) -> Result<AssignedLimbs, Error> {
+        assert!(num_limbs > 0);
+        if value.is_some_and(|value| value.limbs().len() > num_limbs) {
+            return Err(Error::SynthesisError);
+        }
+
+        layouter.assign_region(
+            || "witness integer",
+            |mut region| {
+                let cells = (0..num_limbs)
+                    .map(|i| {
+                        self.s_limb.enable(&mut region, i)?;
+                        let limb = value.map(|value| BigUint::from(value.limb(i)));
+                        self.assign_range(
+                            &mut region,
+                            (self.limb, &self.limb_chunks),
+                            i,
+                            limb.as_ref(),
+                        )
+                    })
+                    .collect::<Result<_, _>>()?;
+                Ok(AssignedLimbs {
+                    cells,
+                    value: value.cloned(),
+                })
+            },
+        )
+    }
+}
+
+impl<F: FieldExt> BigUintInstructions for BigUintChip<F> {
+    type BigUint = AssignedLimbs;
+
+    fn witness_biguint(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<&BigUint>,
+        num_limbs: usize,
+    ) -> Result<Self::BigUint, Error> {
+        let config = layouter.config().clone();
+        config.witness(layouter, value, num_limbs)
+    }
+
+    fn constant(
+        layouter: &mut impl Layouter<Self>,
+        value: &BigUint,
+    ) -> Result<Self::BigUint, Error> {
+        let config = layouter.config().clone();
+        layouter.assign_region(
+            || "constant integer",
+            |mut region| {
+                let cells = (0..value.limbs().len().max(1))
+                    .map(|i| {
+                        config.s_constant.enable(&mut region, i)?;
+                        let limb = F::from_u64(value.limb(i));
+                        region.assign_fixed(|| "constant", config.constant, i, || Ok(limb))?;
+                        region.assign_advice(|| "limb", config.limb, i, || Ok(limb))
+                    })
+                    .collect::<Result<_, _>>()?;
+                Ok(AssignedLimbs {
+                    cells,
+                    value: Some(value.clone()),
+                })
+            },
+        )
+    }
+
+    fn value(a: &Self::BigUint) -> Option<BigUint> {
+        a.value.clone()
+    }
+
+    fn num_limbs(a: &Self::BigUint) -> usize {
+        a.num_limbs()
+    }
+
+    fn resize(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::BigUint,
+        num_limbs: usize,
+    ) -> Result<Self::BigUint, Error> {
+        assert!(num_limbs > 0);
+        let config = layouter.config().clone();
+        layouter.assign_region(
+            || "resize integer",
+            |mut region| {
+                let mut cells = a.cells.clone();
+                cells.truncate(num_limbs);
+                for (row, i) in (num_limbs..a.num_limbs()).enumerate() {
+                    config.s_zero.enable(&mut region, row)?;
+                    config.assign_operand(&mut region, config.x, row, a.limb(i))?;
+                }
+                for row in 0..num_limbs.saturating_sub(a.num_limbs()) {
+                    config.s_constant.enable(&mut region, row)?;
+                    region.assign_fixed(|| "zero", config.constant, row, || Ok(F::zero()))?;
+                    cells.push(region.assign_advice(
+                        || "zero",
+                        config.limb,
+                        row,
+                        || Ok(F::zero()),
+                    )?);
+                }
+                Ok(AssignedLimbs {
+                    cells,
+                    value: a.value.clone(),
+                })
+            },
+        )
+    }
+
+    fn add(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::BigUint,
+        b: &Self::BigUint,
+    ) -> Result<Self::BigUint, Error> {
+        let config = layouter.config().clone();
+        config.assign_columns(layouter, &BigUintConfig::sum_columns(a, b))
+    }
+
+    fn sub(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::BigUint,
+        b: &Self::BigUint,
+    ) -> Result<Self::BigUint, Error> {
+        let config = layouter.config().clone();
+
+        // a = b + d
+        let difference = a
+            .value
+            .as_ref()
+            .zip(b.value.as_ref())
+            .map(|(a, b)| a.checked_sub(b).unwrap_or_default());
+        let difference = config.witness(layouter, difference.as_ref(), a.num_limbs())?;
+        let sum = config.assign_columns(layouter, &BigUintConfig::sum_columns(b, &difference))?;
+        Self::constrain_equal(layouter, &sum, a)?;
+
+        Ok(difference)
+    }
+
+    fn mul(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::BigUint,
+        b: &Self::BigUint,
+    ) -> Result<Self::BigUint, Error> {
+        let config = layouter.config().clone();
+        config.assign_columns(layouter, &BigUintConfig::product_columns(a, b))
+    }
+
+    fn div_rem(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::BigUint,
+        b: &Self::BigUint,
+    ) -> Result<(Self::BigUint, Self::BigUint), Error> {
+        let config = layouter.config().clone();
+
+        // a = q b + r, with r < b. A zero divisor gives q = 0 and r = a, which fails the
+        // comparison.
+        let values = a.value.as_ref().zip(b.value.as_ref()).map(|(a, b)| {
+            if b.is_zero() {
+                (BigUint::zero(), BigUint::zero())
+            } else {
+                a.div_rem(b)
+            }
+        });
+        let q_limbs = (a.num_limbs() + 1).saturating_sub(b.num_limbs()).max(1);
+        let q = config.witness(layouter, values.as_ref().map(|(q, _)| q), q_limbs)?;
+        let r = config.witness(layouter, values.as_ref().map(|(_, r)| r), b.num_limbs())?;
+
+        let mut columns = BigUintConfig::product_columns(&q, b);
+        for (k, column) in columns.iter_mut().enumerate().take(r.num_limbs()) {
+            column.push(Term {
+                x: r.limb(k),
+                ..Default::default()
+            });
+        }
+        let sum = config.assign_columns(layouter, &columns)?;
+        Self::constrain_equal(layouter, &sum, a)?;
+        Self::constrain_less_than(layouter, &r, b)?;
+
+        Ok((q, r))
+    }
+
+    fn constrain_less_than(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::BigUint,
+        b: &Self::BigUint,
+    ) -> Result<(), Error> {
+        let config = layouter.config().clone();
+
+        // b = a + d + 1
+        let difference = a
+            .value
+            .as_ref()
+            .zip(b.value.as_ref())
+            .map(|(a, b)| b.checked_sub(&a.add(&BigUint::from(1))).unwrap_or_default());
+        let difference = config.witness(layouter, difference.as_ref(), b.num_limbs())?;
+        let mut columns = BigUintConfig::sum_columns(a, &difference);
+        columns[0][0].constant = 1;
+        let sum = config.assign_columns(layouter, &columns)?;
+        Self::constrain_equal(layouter, &sum, b)
+    }
+
+    fn constrain_equal(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::BigUint,
+        b: &Self::BigUint,
+    ) -> Result<(), Error> {
+        let config = layouter.config().clone();
+        layouter.assign_region(
+            || "constrain equal",
+            |mut region| {
+                for (a, b) in a.cells.iter().zip(b.cells.iter()) {
+                    region.constrain_equal(&config.perm, *a, *b)?;
+                }
+
+                // The extra limbs of the longer integer are zero.
+                let longer = if a.num_limbs() > b.num_limbs() { a } else { b };
+                let shorter = a.num_limbs().min(b.num_limbs());
+                for (row, i) in (shorter..longer.num_limbs()).enumerate() {
+                    config.s_zero.enable(&mut region, row)?;
+                    config.assign_operand(&mut region, config.x, row, longer.limb(i))?;
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BigUintChip, BigUintConfig};
+    use crate::{
+        circuit::{layouter, Layouter},
+        dev::MockProver,
+        gadget::biguint::AssignedBigUint,
+        pasta::Fp,
+        plonk::{Assignment, Circuit, ConstraintSystem, Error},
+        primitives::biguint::BigUint,
+    };
+
+    struct ArithmeticCircuit {
+        a: Option<BigUint>,
+        b: Option<BigUint>,
+        m: Option<BigUint>,
+        expected: BigUint,
+    }
+
+    impl Circuit<Fp> for ArithmeticCircuit {
+        type Config = BigUintConfig;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> BigUintConfig {
+            BigUintChip::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            config: BigUintConfig,
+        ) -> Result<(), Error> {
+            let mut layouter = layouter::SingleChip::<BigUintChip<Fp>, _>::new(cs, config)?;
+
+            // (a b + a - b) mod m
+            let a = AssignedBigUint::new(layouter.namespace(|| "a"), self.a.as_ref(), 4)?;
+            let b = AssignedBigUint::new(layouter.namespace(|| "b"), self.b.as_ref(), 2)?;
+            let m = AssignedBigUint::new(layouter.namespace(|| "m"), self.m.as_ref(), 3)?;
+            let ab = a.mul(layouter.namespace(|| "a b"), &b)?;
+            let sum = ab
+                .add(layouter.namespace(|| "a b + a"), &a)?
+                .sub(layouter.namespace(|| "a b + a - b"), &b)?;
+            let result = sum.rem(layouter.namespace(|| "mod m"), &m)?;
+
+            let expected =
+                AssignedBigUint::constant(layouter.namespace(|| "expected"), &self.expected)?;
+            result.constrain_equal(layouter.namespace(|| "result"), &expected)?;
+            b.constrain_less_than(layouter.namespace(|| "b < a"), &a)
+        }
+    }
+
+    fn arithmetic_circuit(a: BigUint, b: BigUint, m: BigUint) -> ArithmeticCircuit {
+        let expected = a.mul(&b).add(&a).checked_sub(&b).unwrap().rem(&m);
+        ArithmeticCircuit {
+            a: Some(a),
+            b: Some(b),
+            m: Some(m),
+            expected,
+        }
+    }
+
+    #[test]
+    fn biguint_arithmetic() {
+        let a = BigUint::from_limbs(&[u64::MAX, 0x0123_4567_89ab_cdef, u64::MAX, 1 << 62]);
+        let b = BigUint::from_limbs(&[42, u64::MAX]);
+        let m = BigUint::from_limbs(&[7, 0, 1 << 40]);
+
+        let mut circuit = arithmetic_circuit(a, b.clone(), m.clone());
+        let prover = MockProver::run(9, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        circuit.expected = circuit.expected.add(&BigUint::from(1));
+        let prover = MockProver::run(9, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+
+        // b must be less than a.
+        let circuit = arithmetic_circuit(BigUint::from(3), b, m);
+        let prover = MockProver::run(9, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
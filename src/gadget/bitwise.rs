@@ -0,0 +1,136 @@
+//! Gadget and chips for bitwise operations on bytes with a lookup table.
+//!
+//! Each byte is split into chunks of $c$ bits, where $c$ divides 8 and is chosen when
+//! the chip is configured, and the operation is applied to each pair of chunks by a
+//! lookup in a table of $(\mathsf{op}, x, y, x \circ y)$ for all $c$-bit $x$ and $y$.
+//! With $c = 8$ a byte operation is a single lookup in a table of $2^{16}$ rows per
+//! operation; with $c = 4$ the table only has $2^8$ rows per operation, at the cost of
+//! two lookups. The lookups also constrain the inputs and output to be bytes.
+
+use std::fmt;
+
+use crate::{
+    circuit::{Chip, Layouter},
+    plonk::Error,
+};
+
+mod chip;
+pub use chip::{AssignedByte, BitwiseChip, BitwiseConfig};
+
+/// A bitwise operation supported by the table.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BitwiseOp {
+    /// Exclusive disjunction.
+    Xor,
+    /// Conjunction.
+    And,
+}
+
+impl BitwiseOp {
+    /// All operations in the table.
+    pub const ALL: [BitwiseOp; 2] = [BitwiseOp::Xor, BitwiseOp::And];
+
+    /// Applies this operation to `a` and `b`.
+    pub fn apply(self, a: u8, b: u8) -> u8 {
+        match self {
+            BitwiseOp::Xor => a ^ b,
+            BitwiseOp::And => a & b,
+        }
+    }
+
+    /// The tag of this operation in the table.
+    ///
+    /// The tag of [`BitwiseOp::Xor`] is zero, so that rows without a lookup look up the
+    /// entry $(0, 0, 0, 0)$.
+    pub(crate) fn tag(self) -> u64 {
+        match self {
+            BitwiseOp::Xor => 0,
+            BitwiseOp::And => 1,
+        }
+    }
+}
+
+/// The set of circuit instructions required to use the [`Byte`] gadget.
+pub trait BitwiseInstructions: Chip {
+    /// Variable representing a byte.
+    type Byte: Clone + fmt::Debug;
+
+    /// Witnesses a byte.
+    ///
+    /// The byte is not constrained until it is used in an operation.
+    fn witness_byte(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<u8>,
+    ) -> Result<Self::Byte, Error>;
+
+    /// Returns $a \circ b$, constraining `a` and `b` to be bytes.
+    fn apply(
+        layouter: &mut impl Layouter<Self>,
+        op: BitwiseOp,
+        a: &Self::Byte,
+        b: &Self::Byte,
+    ) -> Result<Self::Byte, Error>;
+}
+
+/// A byte assigned in the circuit.
+#[derive(Debug)]
+pub struct Byte<BitwiseChip: BitwiseInstructions> {
+    inner: BitwiseChip::Byte,
+}
+
+impl<BitwiseChip: BitwiseInstructions> Byte<BitwiseChip> {
+    /// Witnesses a byte.
+    pub fn new(mut layouter: impl Layouter<BitwiseChip>, value: Option<u8>) -> Result<Self, Error> {
+        BitwiseChip::witness_byte(&mut layouter, value).map(|inner| Byte { inner })
+    }
+
+    /// Returns the exclusive disjunction of this byte and `other`.
+    pub fn xor(&self, layouter: impl Layouter<BitwiseChip>, other: &Self) -> Result<Self, Error> {
+        self.apply(layouter, BitwiseOp::Xor, other)
+    }
+
+    /// Returns the conjunction of this byte and `other`.
+    pub fn and(&self, layouter: impl Layouter<BitwiseChip>, other: &Self) -> Result<Self, Error> {
+        self.apply(layouter, BitwiseOp::And, other)
+    }
+
+    fn apply(
+        &self,
+        mut layouter: impl Layouter<BitwiseChip>,
+        op: BitwiseOp,
+        other: &Self,
+    ) -> Result<Self, Error> {
+        BitwiseChip::apply(&mut layouter, op, &self.inner, &other.inner).map(|inner| Byte { inner })
+    }
+
+    /// Returns the chip-specific representation of this byte.
+    pub fn inner(&self) -> &BitwiseChip::Byte {
+        &self.inner
+    }
+}
+
+impl<BitwiseChip: BitwiseInstructions> Clone for Byte<BitwiseChip> {
+    fn clone(&self) -> Self {
+        Byte {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+/// Returns the bytewise exclusive disjunction of `a` and `b`.
+///
+/// # Panics
+///
+/// Panics if `a` and `b` have different lengths.
+pub fn xor_bytes<BitwiseChip: BitwiseInstructions>(
+    mut layouter: impl Layouter<BitwiseChip>,
+    a: &[Byte<BitwiseChip>],
+    b: &[Byte<BitwiseChip>],
+) -> Result<Vec<Byte<BitwiseChip>>, Error> {
+    assert_eq!(a.len(), b.len());
+    a.iter()
+        .zip(b.iter())
+        .enumerate()
+        .map(|(i, (a, b))| a.xor(layouter.namespace(|| format!("xor {}", i)), b))
+        .collect()
+}
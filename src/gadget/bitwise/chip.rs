@@ -0,0 +1,380 @@
+//! A chip implementing [`BitwiseInstructions`] with a table of chunk operations.
+//!
+//! # Layout
+//!
+//! The chip uses three advice columns $a, b, o$ in a permutation. An operation on $a$
+//! and $b$ with $n = 8 / c$ chunks uses $n + 1$ rows: row 0 holds the bytes $a$, $b$
+//! and $o = a \circ b$, and row $1 + i$ holds their $i$-th little-endian chunks, which
+//! are looked up in the table with the tag of the operation in a fixed column. The
+//! bytes of row 0 are constrained to be the sum of their chunks.
+
+use std::marker::PhantomData;
+
+use super::{BitwiseInstructions, BitwiseOp};
+use crate::{
+    arithmetic::FieldExt,
+    circuit::{Cell, Chip, Layouter},
+    plonk::{Advice, Column, ConstraintSystem, Error, Fixed, Permutation, Selector},
+    poly::Rotation,
+};
+
+/// A byte assigned in the circuit.
+#[derive(Clone, Copy, Debug)]
+pub struct AssignedByte {
+    cell: Cell,
+    value: Option<u8>,
+}
+
+impl AssignedByte {
+    /// The cell containing this byte.
+    pub fn cell(&self) -> Cell {
+        self.cell
+    }
+
+    /// The byte, if known.
+    pub fn value(&self) -> Option<u8> {
+        self.value
+    }
+}
+
+/// Configuration for a [`BitwiseChip`].
+#[derive(Clone, Debug)]
+pub struct BitwiseConfig {
+    advices: [Column<Advice>; 3],
+    tag: Column<Fixed>,
+    table: [Column<Fixed>; 4],
+    q_byte: Selector,
+    q_lookup: Selector,
+    perm: Permutation,
+    chunk_bits: usize,
+}
+
+/// A chip implementing [`BitwiseInstructions`].
+#[derive(Debug)]
+pub struct BitwiseChip<F: FieldExt> {
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt> Chip for BitwiseChip<F> {
+    type Config = BitwiseConfig;
+    type Loaded = ();
+    type Field = F;
+
+    fn load(layouter: &mut impl Layouter<Self>) -> Result<(), Error> {
+        let config = layouter.config().clone();
+        let size = 1u64 << config.chunk_bits;
+
+        layouter.assign_region(
+            || "bitwise table",
+            |mut region| {
+                let mut row = 0;
+                for op in BitwiseOp::ALL.iter() {
+                    for x in 0..size {
+                        for y in 0..size {
+                            let out = op.apply(x as u8, y as u8) as u64;
+                            for (column, value) in config.table.iter().zip([op.tag(), x, y, out]) {
+                                region.assign_fixed(
+                                    || "table",
+                                    *column,
+                                    row,
+                                    || Ok(F::from_u64(value)),
+                                )?;
+                            }
+                            row += 1;
+                        }
+                    }
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+impl<F: FieldExt> BitwiseChip<F> {
+    /// Configures this chip with chunks of `chunk_bits` bits, which uses a table of
+    /// $2^{2 \cdot \mathsf{chunk\_bits} + 1}$ rows.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_bits` does not divide 8.
+    pub fn configure(meta: &mut ConstraintSystem<F>, chunk_bits: usize) -> BitwiseConfig {
+        assert!(chunk_bits > 0 && 8 % chunk_bits == 0);
+
+        let advices = [
+            meta.advice_column(),
+            meta.advice_column(),
+            meta.advice_column(),
+        ];
+        let config = BitwiseConfig {
+            advices,
+            tag: meta.fixed_column(),
+            table: [
+                meta.fixed_column(),
+                meta.fixed_column(),
+                meta.fixed_column(),
+                meta.fixed_column(),
+            ],
+            q_byte: meta.selector(),
+            q_lookup: meta.selector(),
+            perm: Permutation::new(
+                meta,
+                &[advices[0].into(), advices[1].into(), advices[2].into()],
+            ),
+            chunk_bits,
+        };
+
+        // byte = sum_i chunk_i 2^{c i}
+        let num_chunks = 8 / chunk_bits;
+        for column in advices.iter() {
+            meta.create_gate("bitwise chunks", |meta| {
+                let byte = meta.query_advice(*column, Rotation::cur());
+                let chunks = (0..num_chunks).rev().fold(None, |acc, i| {
+                    let chunk = meta.query_advice(*column, Rotation(1 + i as i32));
+                    Some(match acc {
+                        None => chunk,
+                        Some(acc) => acc * F::from_u64(1 << chunk_bits) + chunk,
+                    })
+                });
+                let q_byte = meta.query_selector(config.q_byte, Rotation::cur());
+
+                q_byte * (byte - chunks.unwrap())
+            });
+        }
+
+        // Rows without a lookup look up (0, 0, 0, 0), which is an XOR entry.
+        let q_lookup = meta.query_selector(config.q_lookup, Rotation::cur());
+        let tag = meta.query_fixed(config.tag, Rotation::cur());
+        let mut input = vec![q_lookup.clone() * tag];
+        for column in advices.iter() {
+            input.push(q_lookup.clone() * meta.query_advice(*column, Rotation::cur()));
+        }
+        let table: Vec<_> = config
+            .table
+            .iter()
+            .map(|column| meta.query_fixed(*column, Rotation::cur()))
+            .collect();
+        meta.lookup(&input, &table);
+
+        config
+    }
+}
+
+impl<F: FieldExt> BitwiseInstructions for BitwiseChip<F> {
+    type Byte = AssignedByte;
+
+    fn witness_byte(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<u8>,
+    ) -> Result<Self::Byte, Error> {
+        let config = layouter.config().clone();
+        layouter.assign_region(
+            || "witness byte",
+            |mut region| {
+                let cell = region.assign_advice(
+                    || "byte",
+                    config.advices[0],
+                    0,
+                    || {
+                        value
+                            .map(|value| F::from_u64(value as u64))
+                            .ok_or(Error::SynthesisError)
+                    },
+                )?;
+                Ok(AssignedByte { cell, value })
+            },
+        )
+    }
+
+    fn apply(
+        layouter: &mut impl Layouter<Self>,
+        op: BitwiseOp,
+        a: &Self::Byte,
+        b: &Self::Byte,
+    ) -> Result<Self::Byte, Error> {
+        let config = layouter.config().clone();
+        let chunk_bits = config.chunk_bits;
+        let mask = ((1u16 << chunk_bits) - 1) as u8;
+
+        layouter.assign_region(
+            || format!("{:?}", op),
+            |mut region| {
+                config.q_byte.enable(&mut region, 0)?;
+
+                let out = a.value.zip(b.value).map(|(a, b)| op.apply(a, b));
+                let mut res = None;
+                for (i, (column, value)) in config
+                    .advices
+                    .iter()
+                    .zip([a.value, b.value, out])
+                    .enumerate()
+                {
+                    let cell = region.assign_advice(
+                        || "byte",
+                        *column,
+                        0,
+                        || {
+                            value
+                                .map(|value| F::from_u64(value as u64))
+                                .ok_or(Error::SynthesisError)
+                        },
+                    )?;
+                    match i {
+                        0 => region.constrain_equal(&config.perm, cell, a.cell)?,
+                        1 => region.constrain_equal(&config.perm, cell, b.cell)?,
+                        _ => res = Some(AssignedByte { cell, value }),
+                    }
+
+                    for chunk in 0..8 / chunk_bits {
+                        let row = 1 + chunk;
+                        let chunk_value = value.map(|value| (value >> (chunk * chunk_bits)) & mask);
+                        region.assign_advice(
+                            || "chunk",
+                            *column,
+                            row,
+                            || {
+                                chunk_value
+                                    .map(|value| F::from_u64(value as u64))
+                                    .ok_or(Error::SynthesisError)
+                            },
+                        )?;
+                    }
+                }
+
+                for chunk in 0..8 / chunk_bits {
+                    let row = 1 + chunk;
+                    config.q_lookup.enable(&mut region, row)?;
+                    region.assign_fixed(|| "tag", config.tag, row, || Ok(F::from_u64(op.tag())))?;
+                }
+
+                Ok(res.unwrap())
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BitwiseChip, BitwiseConfig};
+    use crate::{
+        arithmetic::FieldExt,
+        circuit::{layouter, Layouter},
+        dev::MockProver,
+        gadget::bitwise::{xor_bytes, Byte},
+        pasta::Fp,
+        plonk::{Assignment, Circuit, ConstraintSystem, Error},
+    };
+
+    struct BitwiseCircuit {
+        a: [u8; 4],
+        b: [u8; 4],
+    }
+
+    impl Circuit<Fp> for BitwiseCircuit {
+        type Config = BitwiseConfig;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> BitwiseConfig {
+            BitwiseChip::configure(meta, 4)
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            config: BitwiseConfig,
+        ) -> Result<(), Error> {
+            let mut layouter = layouter::SingleChip::<BitwiseChip<Fp>, _>::new(cs, config)?;
+
+            let mut a = Vec::with_capacity(4);
+            let mut b = Vec::with_capacity(4);
+            for i in 0..4 {
+                a.push(Byte::new(layouter.namespace(|| "a"), Some(self.a[i]))?);
+                b.push(Byte::new(layouter.namespace(|| "b"), Some(self.b[i]))?);
+            }
+
+            let xor = xor_bytes(layouter.namespace(|| "xor"), &a, &b)?;
+            for i in 0..4 {
+                assert_eq!(xor[i].inner().value(), Some(self.a[i] ^ self.b[i]));
+                let and = a[i].and(layouter.namespace(|| "and"), &b[i])?;
+                assert_eq!(and.inner().value(), Some(self.a[i] & self.b[i]));
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn bitwise() {
+        let circuit = BitwiseCircuit {
+            a: [0x00, 0xff, 0xa5, 0x3c],
+            b: [0xff, 0xff, 0x5a, 0x0f],
+        };
+        assert_eq!(
+            MockProver::run(10, &circuit, vec![]).unwrap().verify(),
+            Ok(())
+        );
+    }
+
+    /// A circuit assigning an XOR of `a` and `b` with the output `out`, and consistent
+    /// chunks.
+    struct BadXorCircuit {
+        a: u8,
+        b: u8,
+        out: u8,
+    }
+
+    impl Circuit<Fp> for BadXorCircuit {
+        type Config = BitwiseConfig;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> BitwiseConfig {
+            BitwiseChip::configure(meta, 4)
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            config: BitwiseConfig,
+        ) -> Result<(), Error> {
+            let mut layouter = layouter::SingleChip::<BitwiseChip<Fp>, _>::new(cs, config)?;
+            let config = layouter.config().clone();
+
+            layouter.assign_region(
+                || "xor",
+                |mut region| {
+                    config.q_byte.enable(&mut region, 0)?;
+                    for row in 1..3 {
+                        config.q_lookup.enable(&mut region, row)?;
+                    }
+                    for (column, value) in config.advices.iter().zip([self.a, self.b, self.out]) {
+                        for (row, value) in [value, value & 0xf, value >> 4].iter().enumerate() {
+                            region.assign_advice(
+                                || "value",
+                                *column,
+                                row,
+                                || Ok(Fp::from_u64(*value as u64)),
+                            )?;
+                        }
+                    }
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    #[test]
+    fn bad_xor() {
+        let run = |out: u8| {
+            let circuit = BadXorCircuit {
+                a: 0x12,
+                b: 0x34,
+                out,
+            };
+            MockProver::run(10, &circuit, vec![]).unwrap().verify()
+        };
+
+        assert_eq!(run(0x26), Ok(()));
+        assert!(run(0x27).is_err());
+        assert!(run(0x36).is_err());
+    }
+}
@@ -0,0 +1,207 @@
+//! Gadget and chips for the BLAKE2b hash function.
+//!
+//! [`Blake2b`] computes unkeyed BLAKE2b with a configurable digest length and
+//! personalization, matching `blake2b_simd::Params` with `hash_length` and `personal`
+//! set. Messages are provided as 64-bit little-endian words, so their length must be a
+//! multiple of eight bytes, and digests are returned in the same form.
+//!
+//! The parameters and the length of the message are part of the circuit structure, and
+//! the compression function is provided by a chip implementing
+//! [`Blake2bInstructions`], such as [`Blake2bChip`].
+
+use std::convert::TryInto;
+use std::fmt;
+
+use crate::{
+    circuit::{Chip, Layouter},
+    plonk::Error,
+};
+
+mod compression;
+pub use compression::{AssignedWord, Blake2bChip, Blake2bConfig};
+
+/// The number of 64-bit words in a message block.
+pub const BLOCK_SIZE: usize = 16;
+
+/// The number of 64-bit words in the state.
+pub const STATE_SIZE: usize = 8;
+
+/// The number of rounds of the compression function.
+pub const ROUNDS: usize = 12;
+
+/// The maximum length in bytes of a personalization string.
+pub const PERSONALIZATION_LENGTH: usize = 16;
+
+/// The initialization vector.
+pub const IV: [u64; STATE_SIZE] = [
+    0x6a09_e667_f3bc_c908,
+    0xbb67_ae85_84ca_a73b,
+    0x3c6e_f372_fe94_f82b,
+    0xa54f_f53a_5f1d_36f1,
+    0x510e_527f_ade6_82d1,
+    0x9b05_688c_2b3e_6c1f,
+    0x1f83_d9ab_fb41_bd6b,
+    0x5be0_cd19_137e_2179,
+];
+
+/// The message word permutations, used cyclically by the rounds.
+pub const SIGMA: [[usize; BLOCK_SIZE]; 10] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+    [11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+    [7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+    [9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+    [2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+    [12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+    [13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+    [6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+    [10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0],
+];
+
+/// A word of a message block: either part of the message, or a zero padding word that
+/// is fixed by the circuit.
+#[derive(Clone, Copy, Debug)]
+pub enum BlockWord<W> {
+    /// A message word provided by the prover.
+    Message(W),
+    /// A zero word padding the last block.
+    Padding,
+}
+
+/// The set of circuit instructions required to use the [`Blake2b`] gadget.
+pub trait Blake2bInstructions: Chip {
+    /// Variable representing a 64-bit word.
+    type Word: Copy + fmt::Debug;
+
+    /// Witnesses a 64-bit message word.
+    fn load_word(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<u64>,
+    ) -> Result<Self::Word, Error>;
+
+    /// Returns the initial state, fixed to the given constants.
+    fn initial_state(
+        layouter: &mut impl Layouter<Self>,
+        state: &[u64; STATE_SIZE],
+    ) -> Result<[Self::Word; STATE_SIZE], Error>;
+
+    /// Applies the compression function to the given state and message block.
+    ///
+    /// `counter` is the number of message bytes compressed so far, including this
+    /// block, and `last` is set for the final block.
+    fn compress(
+        layouter: &mut impl Layouter<Self>,
+        state: &[Self::Word; STATE_SIZE],
+        block: &[BlockWord<Self::Word>; BLOCK_SIZE],
+        counter: u128,
+        last: bool,
+    ) -> Result<[Self::Word; STATE_SIZE], Error>;
+}
+
+/// A BLAKE2b hasher.
+#[derive(Debug)]
+pub struct Blake2b<Blake2bChip: Blake2bInstructions> {
+    state: [Blake2bChip::Word; STATE_SIZE],
+    cur_block: Vec<Blake2bChip::Word>,
+    digest_length: usize,
+    len: usize,
+}
+
+impl<Blake2bChip: Blake2bInstructions> Blake2b<Blake2bChip> {
+    /// Creates a new hasher instance, with a digest of `digest_length` bytes and the
+    /// given personalization.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `digest_length` is not a multiple of eight between 8 and 64, or if
+    /// `personalization` is longer than [`PERSONALIZATION_LENGTH`] bytes.
+    pub fn new(
+        mut layouter: impl Layouter<Blake2bChip>,
+        digest_length: usize,
+        personalization: &[u8],
+    ) -> Result<Self, Error> {
+        assert!((8..=64).step_by(8).any(|len| len == digest_length));
+        assert!(personalization.len() <= PERSONALIZATION_LENGTH);
+
+        // The parameter block of an unkeyed hash with no salt, in the sequential mode.
+        let mut personal = [0; PERSONALIZATION_LENGTH];
+        personal[..personalization.len()].copy_from_slice(personalization);
+        let mut state = IV;
+        state[0] ^= 0x0101_0000 ^ digest_length as u64;
+        state[6] ^= u64::from_le_bytes(personal[..8].try_into().unwrap());
+        state[7] ^= u64::from_le_bytes(personal[8..].try_into().unwrap());
+
+        Ok(Blake2b {
+            state: Blake2bChip::initial_state(&mut layouter, &state)?,
+            cur_block: Vec::with_capacity(BLOCK_SIZE),
+            digest_length,
+            len: 0,
+        })
+    }
+
+    /// Digests the given message words, compressing each block once it is known not to
+    /// be the last.
+    pub fn update(
+        &mut self,
+        mut layouter: impl Layouter<Blake2bChip>,
+        data: &[Blake2bChip::Word],
+    ) -> Result<(), Error> {
+        for word in data {
+            if self.cur_block.len() == BLOCK_SIZE {
+                let block = self.take_block();
+                self.state = Blake2bChip::compress(
+                    &mut layouter.namespace(|| "compress"),
+                    &self.state,
+                    &block,
+                    (self.len * 8) as u128,
+                    false,
+                )?;
+            }
+            self.cur_block.push(*word);
+            self.len += 1;
+        }
+        Ok(())
+    }
+
+    /// Pads and compresses the last block, and returns the digest.
+    pub fn finalize(
+        mut self,
+        mut layouter: impl Layouter<Blake2bChip>,
+    ) -> Result<Vec<Blake2bChip::Word>, Error> {
+        let block = self.take_block();
+        let state = Blake2bChip::compress(
+            &mut layouter.namespace(|| "compress"),
+            &self.state,
+            &block,
+            (self.len * 8) as u128,
+            true,
+        )?;
+
+        Ok(state[..self.digest_length / 8].to_vec())
+    }
+
+    /// Convenience function to compute the hash of the given message.
+    pub fn digest(
+        mut layouter: impl Layouter<Blake2bChip>,
+        digest_length: usize,
+        personalization: &[u8],
+        data: &[Blake2bChip::Word],
+    ) -> Result<Vec<Blake2bChip::Word>, Error> {
+        let mut hasher = Self::new(
+            layouter.namespace(|| "init"),
+            digest_length,
+            personalization,
+        )?;
+        hasher.update(layouter.namespace(|| "update"), data)?;
+        hasher.finalize(layouter.namespace(|| "finalize"))
+    }
+
+    /// Removes the current block, padded with zero words.
+    fn take_block(&mut self) -> [BlockWord<Blake2bChip::Word>; BLOCK_SIZE] {
+        let mut block = [BlockWord::Padding; BLOCK_SIZE];
+        for (word, message) in block.iter_mut().zip(self.cur_block.drain(..)) {
+            *word = BlockWord::Message(message);
+        }
+        block
+    }
+}
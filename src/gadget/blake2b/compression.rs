@@ -0,0 +1,678 @@
+//! A chip for the BLAKE2b compression function.
+//!
+//! Every 64-bit word is assigned alongside its bit decomposition, so that XORs are
+//! low-degree polynomials in the bits and rotations are free. The chip has four lanes
+//! (one for each argument of the mixing function `G`), each made of a word column and
+//! 64 bit columns, along with a message column and carry columns for the `a` and `c`
+//! lanes.
+//!
+//! # Layout
+//!
+//! The compression function occupies 305 rows. The first nine rows hold constants in the
+//! `a` lane: the last eight words of the initial working vector (which depend only on
+//! the IV, the byte counter and the final block flag), and a zero word that is copied in
+//! place of padding words.
+//!
+//! Each of the 96 applications of `G` then occupies three rows: its four input words,
+//! the words after its first half, and its output words. The message words mixed in by
+//! each half are copied into the message column of the first two rows.
+//!
+//! The last eight rows hold the next state, each computed in the `d` lane as the XOR of
+//! a word of the initial state (in the `a` lane) and two words of the working vector
+//! (in the `b` and `c` lanes).
+
+use std::convert::TryInto;
+use std::marker::PhantomData;
+
+use super::{Blake2bInstructions, BlockWord, BLOCK_SIZE, IV, ROUNDS, SIGMA, STATE_SIZE};
+use crate::{
+    arithmetic::FieldExt,
+    circuit::{Cell, Chip, Layouter, Region},
+    plonk::{Advice, Column, ConstraintSystem, Error, Expression, Fixed, Permutation, Selector},
+    poly::Rotation,
+};
+
+const WORD_BITS: usize = 64;
+
+/// The rotations applied to `d` and `b` by each half of `G`.
+const G_ROTATIONS: [(usize, usize); 2] = [(32, 24), (16, 63)];
+
+/// The lanes of the working vector mixed by each application of `G` within a round.
+const G_LANES: [[usize; 4]; 8] = [
+    [0, 4, 8, 12],
+    [1, 5, 9, 13],
+    [2, 6, 10, 14],
+    [3, 7, 11, 15],
+    [0, 5, 10, 15],
+    [1, 6, 11, 12],
+    [2, 7, 8, 13],
+    [3, 4, 9, 14],
+];
+
+/// The offset of the zero word within the compression region.
+const ZERO: usize = STATE_SIZE;
+
+/// The offset of the first application of `G` within the compression region.
+const FIRST_ROUND: usize = ZERO + 1;
+
+/// The number of rows occupied by an application of `G`.
+const G_ROWS: usize = 3;
+
+/// The offset of the next state within the compression region.
+const NEXT_STATE: usize = FIRST_ROUND + ROUNDS * G_LANES.len() * G_ROWS;
+
+/// A 64-bit word assigned in the circuit.
+#[derive(Clone, Copy, Debug)]
+pub struct AssignedWord {
+    var: Cell,
+    value: Option<u64>,
+}
+
+impl AssignedWord {
+    /// The cell containing this word.
+    pub fn cell(&self) -> Cell {
+        self.var
+    }
+
+    /// The value of this word, if known.
+    pub fn value(&self) -> Option<u64> {
+        self.value
+    }
+}
+
+/// A word column, with its bit decomposition.
+#[derive(Clone, Debug)]
+struct Lane {
+    word: Column<Advice>,
+    bits: [Column<Advice>; WORD_BITS],
+    s_word: Selector,
+}
+
+impl Lane {
+    fn configure<F: FieldExt>(meta: &mut ConstraintSystem<F>) -> Self {
+        let word = meta.advice_column();
+        let bits: [Column<Advice>; WORD_BITS] = (0..WORD_BITS)
+            .map(|_| meta.advice_column())
+            .collect::<Vec<_>>()
+            .try_into()
+            .unwrap();
+        let s_word = meta.selector();
+
+        meta.create_gate("word decomposition", |meta| {
+            let value = meta.query_advice(word, Rotation::cur());
+            let bits = query_bits(meta, &bits, 0);
+            let s_word = meta.query_selector(s_word, Rotation::cur());
+
+            s_word * (compose(bits) - value)
+        });
+        for bit in bits.iter().copied() {
+            meta.create_gate("boolean bit", |meta| {
+                let bit = meta.query_advice(bit, Rotation::cur());
+                let s_word = meta.query_selector(s_word, Rotation::cur());
+
+                s_word * bit.clone() * (Expression::Constant(F::one()) - bit)
+            });
+        }
+
+        Lane { word, bits, s_word }
+    }
+
+    /// Assigns `value` and its bit decomposition at `offset`.
+    fn assign_word<F: FieldExt, C: Chip<Field = F>>(
+        &self,
+        region: &mut Region<'_, C>,
+        offset: usize,
+        value: Option<u64>,
+    ) -> Result<AssignedWord, Error> {
+        self.s_word.enable(region, offset)?;
+        let var = region.assign_advice(
+            || "word",
+            self.word,
+            offset,
+            || value.map(F::from_u64).ok_or(Error::SynthesisError),
+        )?;
+        for (i, column) in self.bits.iter().enumerate() {
+            region.assign_advice(
+                || format!("bit {}", i),
+                *column,
+                offset,
+                || {
+                    value
+                        .map(|v| F::from_u64((v >> i) & 1))
+                        .ok_or(Error::SynthesisError)
+                },
+            )?;
+        }
+        Ok(AssignedWord { var, value })
+    }
+
+    /// Assigns a copy of `word` and its bit decomposition at `offset`.
+    fn copy_word<F: FieldExt, C: Chip<Field = F>>(
+        &self,
+        region: &mut Region<'_, C>,
+        offset: usize,
+        word: &AssignedWord,
+        perm: &Permutation,
+    ) -> Result<AssignedWord, Error> {
+        let copy = self.assign_word(region, offset, word.value)?;
+        region.constrain_equal(perm, word.var, copy.var)?;
+        Ok(copy)
+    }
+
+    /// Assigns the sum of `terms` modulo $2^{64}$ at `offset`, and its carry in the
+    /// `carry` column.
+    fn assign_sum<F: FieldExt, C: Chip<Field = F>>(
+        &self,
+        region: &mut Region<'_, C>,
+        carry: Column<Advice>,
+        offset: usize,
+        terms: &[Option<u64>],
+    ) -> Result<AssignedWord, Error> {
+        let sum = terms
+            .iter()
+            .copied()
+            .collect::<Option<Vec<_>>>()
+            .map(|terms| terms.into_iter().map(u128::from).sum::<u128>());
+
+        region.assign_advice(
+            || "carry",
+            carry,
+            offset,
+            || {
+                sum.map(|sum| F::from_u64((sum >> 64) as u64))
+                    .ok_or(Error::SynthesisError)
+            },
+        )?;
+        self.assign_word(region, offset, sum.map(|sum| sum as u64))
+    }
+}
+
+fn query_bits<F: FieldExt>(
+    meta: &mut ConstraintSystem<F>,
+    bits: &[Column<Advice>; WORD_BITS],
+    at: i32,
+) -> Vec<Expression<F>> {
+    bits.iter()
+        .map(|column| meta.query_advice(*column, Rotation(at)))
+        .collect()
+}
+
+/// Returns the word with the given little-endian bits.
+fn compose<F: FieldExt>(bits: impl IntoIterator<Item = Expression<F>>) -> Expression<F> {
+    bits.into_iter()
+        .enumerate()
+        .fold(Expression::Constant(F::zero()), |acc, (i, bit)| {
+            acc + bit * F::from_u64(1 << i)
+        })
+}
+
+/// Returns the XOR of the given bits.
+fn xor<F: FieldExt>(bits: impl IntoIterator<Item = Expression<F>>) -> Expression<F> {
+    let mut bits = bits.into_iter();
+    let first = bits.next().unwrap();
+    bits.fold(first, |acc, bit| {
+        acc.clone() + bit.clone() - acc * bit * F::from_u64(2)
+    })
+}
+
+/// Configuration for a [`Blake2bChip`].
+#[derive(Clone, Debug)]
+pub struct Blake2bConfig {
+    a: Lane,
+    b: Lane,
+    c: Lane,
+    d: Lane,
+    m: Column<Advice>,
+    carry_a: Column<Advice>,
+    carry_c: Column<Advice>,
+    constant: Column<Fixed>,
+    s_half: [Selector; 2],
+    s_next_state: Selector,
+    s_constant: Selector,
+    perm: Permutation,
+}
+
+/// A chip implementing the BLAKE2b compression function over bit-decomposed words.
+///
+/// Each compression uses 305 rows.
+#[derive(Debug)]
+pub struct Blake2bChip<F: FieldExt> {
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt> Blake2bChip<F> {
+    /// Configures this chip for use in a circuit.
+    ///
+    /// The chip allocates 263 advice columns and 1 fixed column.
+    pub fn configure(meta: &mut ConstraintSystem<F>) -> Blake2bConfig {
+        let constant = meta.fixed_column();
+        let a = Lane::configure(meta);
+        let b = Lane::configure(meta);
+        let c = Lane::configure(meta);
+        let d = Lane::configure(meta);
+        let m = meta.advice_column();
+        let carry_a = meta.advice_column();
+        let carry_c = meta.advice_column();
+
+        let s_half = [meta.selector(), meta.selector()];
+        let s_next_state = meta.selector();
+        let s_constant = meta.selector();
+
+        let perm = Permutation::new(
+            meta,
+            &[
+                a.word.into(),
+                b.word.into(),
+                c.word.into(),
+                d.word.into(),
+                m.into(),
+            ],
+        );
+
+        let two_pow_64 = F::from_u128(1 << 64);
+
+        // Each half of G reads its inputs from the current row, and writes its outputs
+        // to the next row:
+        //
+        //   a' = a + b + m
+        //   d' = (d ^ a') >>> r_1
+        //   c' = c + d'
+        //   b' = (b ^ c') >>> r_2
+        for (s_half, (r_1, r_2)) in s_half.iter().copied().zip(G_ROTATIONS.iter().copied()) {
+            meta.create_gate("G a", |meta| {
+                let a_cur = meta.query_advice(a.word, Rotation::cur());
+                let b_cur = meta.query_advice(b.word, Rotation::cur());
+                let m = meta.query_advice(m, Rotation::cur());
+                let a_next = meta.query_advice(a.word, Rotation::next());
+                let carry = meta.query_advice(carry_a, Rotation::next());
+                let s_half = meta.query_selector(s_half, Rotation::cur());
+
+                s_half * (a_cur + b_cur + m - a_next - carry * two_pow_64)
+            });
+            meta.create_gate("G c", |meta| {
+                let c_cur = meta.query_advice(c.word, Rotation::cur());
+                let d_next = meta.query_advice(d.word, Rotation::next());
+                let c_next = meta.query_advice(c.word, Rotation::next());
+                let carry = meta.query_advice(carry_c, Rotation::next());
+                let s_half = meta.query_selector(s_half, Rotation::cur());
+
+                s_half * (c_cur + d_next - c_next - carry * two_pow_64)
+            });
+            // Carries are small, so that the additions cannot wrap around the field.
+            for carry in [carry_a, carry_c].iter().copied() {
+                meta.create_gate("G carry", |meta| {
+                    let carry = meta.query_advice(carry, Rotation::next());
+                    let s_half = meta.query_selector(s_half, Rotation::cur());
+
+                    let range = (0..3).fold(Expression::Constant(F::one()), |acc, i| {
+                        acc * (carry.clone() - Expression::Constant(F::from_u64(i)))
+                    });
+                    s_half * range
+                });
+            }
+
+            for (x, y, r) in [(&d, &a, r_1), (&b, &c, r_2)].iter() {
+                for i in 0..WORD_BITS {
+                    meta.create_gate("G xor", |meta| {
+                        let source = (i + r) % WORD_BITS;
+                        let x_cur = meta.query_advice(x.bits[source], Rotation::cur());
+                        let y_next = meta.query_advice(y.bits[source], Rotation::next());
+                        let x_next = meta.query_advice(x.bits[i], Rotation::next());
+                        let s_half = meta.query_selector(s_half, Rotation::cur());
+
+                        s_half * (xor(vec![x_cur, y_next]) - x_next)
+                    });
+                }
+            }
+        }
+
+        for i in 0..WORD_BITS {
+            meta.create_gate("next state", |meta| {
+                let h = meta.query_advice(a.bits[i], Rotation::cur());
+                let v_low = meta.query_advice(b.bits[i], Rotation::cur());
+                let v_high = meta.query_advice(c.bits[i], Rotation::cur());
+                let next = meta.query_advice(d.bits[i], Rotation::cur());
+                let s_next_state = meta.query_selector(s_next_state, Rotation::cur());
+
+                s_next_state * (xor(vec![h, v_low, v_high]) - next)
+            });
+        }
+
+        meta.create_gate("constant word", |meta| {
+            let word = meta.query_advice(a.word, Rotation::cur());
+            let constant = meta.query_fixed(constant, Rotation::cur());
+            let s_constant = meta.query_selector(s_constant, Rotation::cur());
+
+            s_constant * (word - constant)
+        });
+
+        Blake2bConfig {
+            a,
+            b,
+            c,
+            d,
+            m,
+            carry_a,
+            carry_c,
+            constant,
+            s_half,
+            s_next_state,
+            s_constant,
+            perm,
+        }
+    }
+
+    fn assign_constant(
+        region: &mut Region<'_, Self>,
+        config: &Blake2bConfig,
+        offset: usize,
+        value: u64,
+    ) -> Result<AssignedWord, Error> {
+        config.s_constant.enable(region, offset)?;
+        region.assign_fixed(
+            || "constant",
+            config.constant,
+            offset,
+            || Ok(F::from_u64(value)),
+        )?;
+        config.a.assign_word(region, offset, Some(value))
+    }
+
+    /// Assigns an application of `G` to the given lanes of the working vector at
+    /// `offset`, mixing in the message words `m`.
+    fn assign_g(
+        region: &mut Region<'_, Self>,
+        config: &Blake2bConfig,
+        offset: usize,
+        v: &mut [AssignedWord; 2 * STATE_SIZE],
+        lanes: [usize; 4],
+        m: [&AssignedWord; 2],
+    ) -> Result<(), Error> {
+        let [a, b, c, d] = lanes;
+        let mut words = [
+            config.a.copy_word(region, offset, &v[a], &config.perm)?,
+            config.b.copy_word(region, offset, &v[b], &config.perm)?,
+            config.c.copy_word(region, offset, &v[c], &config.perm)?,
+            config.d.copy_word(region, offset, &v[d], &config.perm)?,
+        ];
+
+        for (half, ((r_1, r_2), m)) in G_ROTATIONS.iter().zip(m.iter()).enumerate() {
+            let row = offset + half;
+            config.s_half[half].enable(region, row)?;
+            let var = region.assign_advice(
+                || "m",
+                config.m,
+                row,
+                || m.value.map(F::from_u64).ok_or(Error::SynthesisError),
+            )?;
+            region.constrain_equal(&config.perm, m.var, var)?;
+
+            let [a, b, c, d] = words;
+            let a = config.a.assign_sum(
+                region,
+                config.carry_a,
+                row + 1,
+                &[a.value, b.value, m.value],
+            )?;
+            let d = config.d.assign_word(
+                region,
+                row + 1,
+                d.value
+                    .zip(a.value)
+                    .map(|(d, a)| (d ^ a).rotate_right(*r_1 as u32)),
+            )?;
+            let c = config
+                .c
+                .assign_sum(region, config.carry_c, row + 1, &[c.value, d.value])?;
+            let b = config.b.assign_word(
+                region,
+                row + 1,
+                b.value
+                    .zip(c.value)
+                    .map(|(b, c)| (b ^ c).rotate_right(*r_2 as u32)),
+            )?;
+            words = [a, b, c, d];
+        }
+
+        for (lane, word) in lanes.iter().zip(words.iter()) {
+            v[*lane] = *word;
+        }
+        Ok(())
+    }
+}
+
+impl<F: FieldExt> Chip for Blake2bChip<F> {
+    type Config = Blake2bConfig;
+    type Loaded = ();
+    type Field = F;
+
+    fn load(_layouter: &mut impl Layouter<Self>) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<F: FieldExt> Blake2bInstructions for Blake2bChip<F> {
+    type Word = AssignedWord;
+
+    fn load_word(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<u64>,
+    ) -> Result<Self::Word, Error> {
+        let config = layouter.config().clone();
+
+        layouter.assign_region(
+            || "load word",
+            |mut region| config.a.assign_word(&mut region, 0, value),
+        )
+    }
+
+    fn initial_state(
+        layouter: &mut impl Layouter<Self>,
+        state: &[u64; STATE_SIZE],
+    ) -> Result<[Self::Word; STATE_SIZE], Error> {
+        let config = layouter.config().clone();
+
+        layouter.assign_region(
+            || "initial state",
+            |mut region| {
+                let state = state
+                    .iter()
+                    .enumerate()
+                    .map(|(i, value)| Self::assign_constant(&mut region, &config, i, *value))
+                    .collect::<Result<Vec<_>, Error>>()?;
+                Ok(state.try_into().unwrap())
+            },
+        )
+    }
+
+    fn compress(
+        layouter: &mut impl Layouter<Self>,
+        state: &[Self::Word; STATE_SIZE],
+        block: &[BlockWord<Self::Word>; BLOCK_SIZE],
+        counter: u128,
+        last: bool,
+    ) -> Result<[Self::Word; STATE_SIZE], Error> {
+        let config = layouter.config().clone();
+
+        let mut constants = IV;
+        constants[4] ^= counter as u64;
+        constants[5] ^= (counter >> 64) as u64;
+        if last {
+            constants[6] = !constants[6];
+        }
+
+        layouter.assign_region(
+            || "compress",
+            |mut region| {
+                let mut v = [state[0]; 2 * STATE_SIZE];
+                v[..STATE_SIZE].copy_from_slice(state);
+                for (i, value) in constants.iter().enumerate() {
+                    v[STATE_SIZE + i] = Self::assign_constant(&mut region, &config, i, *value)?;
+                }
+                let zero = Self::assign_constant(&mut region, &config, ZERO, 0)?;
+                let m: Vec<_> = block
+                    .iter()
+                    .map(|word| match word {
+                        BlockWord::Message(word) => word,
+                        BlockWord::Padding => &zero,
+                    })
+                    .collect();
+
+                let mut offset = FIRST_ROUND;
+                for round in 0..ROUNDS {
+                    let s = &SIGMA[round % SIGMA.len()];
+                    for (i, lanes) in G_LANES.iter().enumerate() {
+                        Self::assign_g(
+                            &mut region,
+                            &config,
+                            offset,
+                            &mut v,
+                            *lanes,
+                            [m[s[2 * i]], m[s[2 * i + 1]]],
+                        )?;
+                        offset += G_ROWS;
+                    }
+                }
+
+                let mut next_state = *state;
+                for (i, word) in next_state.iter_mut().enumerate() {
+                    let row = NEXT_STATE + i;
+                    config.s_next_state.enable(&mut region, row)?;
+                    let h = config
+                        .a
+                        .copy_word(&mut region, row, &state[i], &config.perm)?;
+                    let v_low = config.b.copy_word(&mut region, row, &v[i], &config.perm)?;
+                    let v_high =
+                        config
+                            .c
+                            .copy_word(&mut region, row, &v[STATE_SIZE + i], &config.perm)?;
+                    *word = config.d.assign_word(
+                        &mut region,
+                        row,
+                        h.value
+                            .zip(v_low.value)
+                            .zip(v_high.value)
+                            .map(|((h, v_low), v_high)| h ^ v_low ^ v_high),
+                    )?;
+                }
+
+                Ok(next_state)
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryInto;
+
+    use super::{Blake2bChip, Blake2bConfig};
+    use crate::{
+        circuit::{layouter, Layouter},
+        dev::MockProver,
+        gadget::blake2b::{Blake2b, Blake2bInstructions},
+        pasta::Fp,
+        plonk::{Assignment, Circuit, ConstraintSystem, Error},
+    };
+
+    struct Blake2bCircuit {
+        message: Option<Vec<u64>>,
+        len: usize,
+        personalization: &'static [u8],
+        digest: Vec<u64>,
+    }
+
+    impl Circuit<Fp> for Blake2bCircuit {
+        type Config = Blake2bConfig;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Blake2bConfig {
+            Blake2bChip::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            config: Blake2bConfig,
+        ) -> Result<(), Error> {
+            let mut layouter = layouter::SingleChip::<Blake2bChip<Fp>, _>::new(cs, config)?;
+            let config = layouter.config().clone();
+
+            let message = (0..self.len)
+                .map(|i| {
+                    Blake2bChip::load_word(
+                        &mut layouter,
+                        self.message.as_ref().map(|message| message[i]),
+                    )
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+
+            let digest = Blake2b::digest(
+                layouter.namespace(|| "blake2b"),
+                self.digest.len() * 8,
+                self.personalization,
+                &message,
+            )?;
+
+            for (word, expected) in digest.iter().zip(self.digest.iter()) {
+                layouter.assign_region(
+                    || "constrain digest",
+                    |mut region| {
+                        let expected =
+                            Blake2bChip::assign_constant(&mut region, &config, 0, *expected)?;
+                        region.constrain_equal(&config.perm, word.var, expected.var)
+                    },
+                )?;
+            }
+
+            Ok(())
+        }
+    }
+
+    fn words(bytes: &[u8]) -> Vec<u64> {
+        bytes
+            .chunks(8)
+            .map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap()))
+            .collect()
+    }
+
+    fn circuit(
+        message: &[u8],
+        digest_length: usize,
+        personalization: &'static [u8],
+    ) -> Blake2bCircuit {
+        let digest = blake2b_simd::Params::new()
+            .hash_length(digest_length)
+            .personal(personalization)
+            .hash(message);
+
+        Blake2bCircuit {
+            message: Some(words(message)),
+            len: message.len() / 8,
+            personalization,
+            digest: words(digest.as_bytes()),
+        }
+    }
+
+    #[test]
+    fn blake2b_empty() {
+        let circuit = circuit(&[], 64, b"");
+        let prover = MockProver::run(9, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()))
+    }
+
+    #[test]
+    fn blake2b_personalized() {
+        // One more word than fits in a block, so that the counter and final block flag
+        // of both compressions are exercised.
+        let message: Vec<u8> = (0..136).map(|i| (i * 7 + 3) as u8).collect();
+        let mut circuit = circuit(&message, 32, b"Zcash_RedPallasH");
+        let prover = MockProver::run(10, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        circuit.digest[3] ^= 1 << 63;
+        let prover = MockProver::run(10, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
@@ -0,0 +1,172 @@
+//! Gadget and chips for boolean logic.
+//!
+//! A [`Bit`] is a cell constrained to be zero or one by $b \cdot (1 - b) = 0$. The
+//! logic operations on bits use one constraint each, whose output is boolean whenever
+//! its inputs are:
+//!
+//! - $\mathsf{AND}(a, b) = a b$
+//! - $\mathsf{OR}(a, b) = a + b - a b$
+//! - $\mathsf{XOR}(a, b) = a + b - 2 a b$
+//! - $\mathsf{NOT}(a) = 1 - a$
+//!
+//! Bit vectors are little-endian: [`pack`] returns $\sum_i b_i 2^i$, and [`unpack`]
+//! witnesses the bits of a field element and constrains them to pack to it.
+
+use std::fmt;
+
+use ff::PrimeField;
+
+use crate::{
+    circuit::{Chip, Layouter},
+    plonk::Error,
+};
+
+mod chip;
+pub use chip::{AssignedBit, BooleanChip, BooleanConfig};
+
+/// The set of circuit instructions required to use the [`Bit`] gadget.
+pub trait BooleanInstructions: Chip {
+    /// Variable representing a field element.
+    type Value: Clone + fmt::Debug;
+
+    /// Variable representing a bit.
+    type Bit: Clone + fmt::Debug;
+
+    /// Witnesses a field element.
+    fn witness_value(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<Self::Field>,
+    ) -> Result<Self::Value, Error>;
+
+    /// Witnesses a bit.
+    fn witness_bit(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<bool>,
+    ) -> Result<Self::Bit, Error>;
+
+    /// Constrains `a` to be boolean, and returns it as a bit.
+    fn assert_bool(layouter: &mut impl Layouter<Self>, a: &Self::Value)
+        -> Result<Self::Bit, Error>;
+
+    /// Returns $a \wedge b$.
+    fn and(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Bit,
+        b: &Self::Bit,
+    ) -> Result<Self::Bit, Error>;
+
+    /// Returns $a \vee b$.
+    fn or(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Bit,
+        b: &Self::Bit,
+    ) -> Result<Self::Bit, Error>;
+
+    /// Returns $a \oplus b$.
+    fn xor(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Bit,
+        b: &Self::Bit,
+    ) -> Result<Self::Bit, Error>;
+
+    /// Returns $\neg a$.
+    fn not(layouter: &mut impl Layouter<Self>, a: &Self::Bit) -> Result<Self::Bit, Error>;
+
+    /// Returns the field element whose little-endian bits are `bits`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bits` is empty or longer than the capacity of the field.
+    fn pack(layouter: &mut impl Layouter<Self>, bits: &[Self::Bit]) -> Result<Self::Value, Error>;
+
+    /// Returns the `num_bits` little-endian bits of `a`, constraining `a` to fit in
+    /// them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `num_bits` is zero or greater than the capacity of the field.
+    fn unpack(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Value,
+        num_bits: usize,
+    ) -> Result<Vec<Self::Bit>, Error>;
+}
+
+/// Returns the maximum number of bits that can be packed into an element of `F`.
+pub fn max_bits<F: PrimeField>() -> usize {
+    F::CAPACITY as usize
+}
+
+/// A bit assigned in the circuit.
+#[derive(Debug)]
+pub struct Bit<BoolChip: BooleanInstructions> {
+    inner: BoolChip::Bit,
+}
+
+impl<BoolChip: BooleanInstructions> Bit<BoolChip> {
+    /// Witnesses a bit.
+    pub fn new(mut layouter: impl Layouter<BoolChip>, value: Option<bool>) -> Result<Self, Error> {
+        BoolChip::witness_bit(&mut layouter, value).map(|inner| Bit { inner })
+    }
+
+    /// Constrains `value` to be boolean, and returns it as a bit.
+    pub fn from_value(
+        mut layouter: impl Layouter<BoolChip>,
+        value: &BoolChip::Value,
+    ) -> Result<Self, Error> {
+        BoolChip::assert_bool(&mut layouter, value).map(|inner| Bit { inner })
+    }
+
+    /// Returns the conjunction of this bit and `other`.
+    pub fn and(&self, mut layouter: impl Layouter<BoolChip>, other: &Self) -> Result<Self, Error> {
+        BoolChip::and(&mut layouter, &self.inner, &other.inner).map(|inner| Bit { inner })
+    }
+
+    /// Returns the disjunction of this bit and `other`.
+    pub fn or(&self, mut layouter: impl Layouter<BoolChip>, other: &Self) -> Result<Self, Error> {
+        BoolChip::or(&mut layouter, &self.inner, &other.inner).map(|inner| Bit { inner })
+    }
+
+    /// Returns the exclusive disjunction of this bit and `other`.
+    pub fn xor(&self, mut layouter: impl Layouter<BoolChip>, other: &Self) -> Result<Self, Error> {
+        BoolChip::xor(&mut layouter, &self.inner, &other.inner).map(|inner| Bit { inner })
+    }
+
+    /// Returns the negation of this bit.
+    pub fn not(&self, mut layouter: impl Layouter<BoolChip>) -> Result<Self, Error> {
+        BoolChip::not(&mut layouter, &self.inner).map(|inner| Bit { inner })
+    }
+
+    /// Returns the chip-specific representation of this bit.
+    pub fn inner(&self) -> &BoolChip::Bit {
+        &self.inner
+    }
+}
+
+impl<BoolChip: BooleanInstructions> Clone for Bit<BoolChip> {
+    fn clone(&self) -> Self {
+        Bit {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+/// Returns the field element whose little-endian bits are `bits`.
+pub fn pack<BoolChip: BooleanInstructions>(
+    mut layouter: impl Layouter<BoolChip>,
+    bits: &[Bit<BoolChip>],
+) -> Result<BoolChip::Value, Error> {
+    let bits: Vec<_> = bits.iter().map(|bit| bit.inner.clone()).collect();
+    BoolChip::pack(&mut layouter, &bits)
+}
+
+/// Returns the `num_bits` little-endian bits of `value`, constraining it to fit in
+/// them.
+pub fn unpack<BoolChip: BooleanInstructions>(
+    mut layouter: impl Layouter<BoolChip>,
+    value: &BoolChip::Value,
+    num_bits: usize,
+) -> Result<Vec<Bit<BoolChip>>, Error> {
+    BoolChip::unpack(&mut layouter, value, num_bits)
+        .map(|bits| bits.into_iter().map(|inner| Bit { inner }).collect())
+}
@@ -0,0 +1,508 @@
+//! A chip implementing [`BooleanInstructions`].
+//!
+//! # Layout
+//!
+//! The chip uses three advice columns $a, b, c$ in a permutation. A witnessed bit uses
+//! one row holding it in $a$. A binary operation uses one row holding its inputs in $a$
+//! and $b$ and its output in $c$, and a negation uses one row holding its input in $a$
+//! and its output in $b$.
+//!
+//! Packing $n$ bits uses $n$ rows, from the most significant bit: row $i$ holds the bit
+//! $b_{n - 1 - i}$ in $a$ and the accumulator $s_i = 2 s_{i - 1} + b_{n - 1 - i}$ in
+//! $b$, with $s_0 = b_{n - 1}$, so that $s_{n - 1}$ is the packed value.
+
+use std::marker::PhantomData;
+
+use super::{max_bits, BooleanInstructions};
+use crate::{
+    arithmetic::FieldExt,
+    circuit::{Cell, Chip, Layouter, Region},
+    gadget::utilities::AssignedValue,
+    plonk::{Advice, Column, ConstraintSystem, Error, Expression, Permutation, Selector},
+    poly::Rotation,
+};
+
+/// A bit assigned in the circuit.
+#[derive(Clone, Copy, Debug)]
+pub struct AssignedBit {
+    cell: Cell,
+    value: Option<bool>,
+}
+
+impl AssignedBit {
+    /// The cell containing this bit.
+    pub fn cell(&self) -> Cell {
+        self.cell
+    }
+
+    /// The bit, if known.
+    pub fn value(&self) -> Option<bool> {
+        self.value
+    }
+}
+
+/// Configuration for a [`BooleanChip`].
+#[derive(Clone, Debug)]
+pub struct BooleanConfig {
+    a: Column<Advice>,
+    b: Column<Advice>,
+    c: Column<Advice>,
+    q_bool: Selector,
+    q_and: Selector,
+    q_or: Selector,
+    q_xor: Selector,
+    q_not: Selector,
+    q_pack_start: Selector,
+    q_pack: Selector,
+    perm: Permutation,
+}
+
+/// A chip implementing [`BooleanInstructions`].
+#[derive(Debug)]
+pub struct BooleanChip<F: FieldExt> {
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt> Chip for BooleanChip<F> {
+    type Config = BooleanConfig;
+    type Loaded = ();
+    type Field = F;
+
+    fn load(_layouter: &mut impl Layouter<Self>) -> Result<(), Error> {
+        // This chip has no tables.
+        Ok(())
+    }
+}
+
+impl<F: FieldExt> BooleanChip<F> {
+    /// Configures this chip with three advice columns, which are placed in a
+    /// permutation.
+    pub fn configure(meta: &mut ConstraintSystem<F>) -> BooleanConfig {
+        let (a, b, c) = (
+            meta.advice_column(),
+            meta.advice_column(),
+            meta.advice_column(),
+        );
+        let config = BooleanConfig {
+            a,
+            b,
+            c,
+            q_bool: meta.selector(),
+            q_and: meta.selector(),
+            q_or: meta.selector(),
+            q_xor: meta.selector(),
+            q_not: meta.selector(),
+            q_pack_start: meta.selector(),
+            q_pack: meta.selector(),
+            perm: Permutation::new(meta, &[a.into(), b.into(), c.into()]),
+        };
+        let one = || Expression::Constant(F::one());
+
+        meta.create_gate("bool", |meta| {
+            let a = meta.query_advice(a, Rotation::cur());
+            let q_bool = meta.query_selector(config.q_bool, Rotation::cur());
+
+            q_bool * a.clone() * (one() - a)
+        });
+
+        // Each binary operation is c = k (a + b) + l a b.
+        for (name, selector, k, l) in [
+            ("and", config.q_and, 0, F::one()),
+            ("or", config.q_or, 1, -F::one()),
+            ("xor", config.q_xor, 1, -F::from_u64(2)),
+        ] {
+            meta.create_gate(name, |meta| {
+                let a = meta.query_advice(a, Rotation::cur());
+                let b = meta.query_advice(b, Rotation::cur());
+                let c = meta.query_advice(c, Rotation::cur());
+                let q = meta.query_selector(selector, Rotation::cur());
+
+                let linear = (a.clone() + b.clone()) * F::from_u64(k);
+                q * (c - linear - a * b * l)
+            });
+        }
+        meta.create_gate("not", |meta| {
+            let a = meta.query_advice(a, Rotation::cur());
+            let b = meta.query_advice(b, Rotation::cur());
+            let q_not = meta.query_selector(config.q_not, Rotation::cur());
+
+            q_not * (a + b - one())
+        });
+
+        meta.create_gate("pack start", |meta| {
+            let bit = meta.query_advice(a, Rotation::cur());
+            let acc = meta.query_advice(b, Rotation::cur());
+            let q_pack_start = meta.query_selector(config.q_pack_start, Rotation::cur());
+
+            q_pack_start * (acc - bit)
+        });
+        meta.create_gate("pack", |meta| {
+            let bit = meta.query_advice(a, Rotation::cur());
+            let acc_prev = meta.query_advice(b, Rotation::prev());
+            let acc = meta.query_advice(b, Rotation::cur());
+            let q_pack = meta.query_selector(config.q_pack, Rotation::cur());
+
+            q_pack * (acc - acc_prev * F::from_u64(2) - bit)
+        });
+
+        config
+    }
+
+    /// Assigns a bit in the `a` column at `offset`, constraining it to be boolean.
+    fn assign_bit(
+        config: &BooleanConfig,
+        region: &mut Region<'_, Self>,
+        offset: usize,
+        value: Option<bool>,
+    ) -> Result<AssignedBit, Error> {
+        config.q_bool.enable(region, offset)?;
+        Self::assign_output(region, config.a, offset, value)
+    }
+
+    /// Assigns a bit in `column` at `offset`, without constraining it.
+    fn assign_output(
+        region: &mut Region<'_, Self>,
+        column: Column<Advice>,
+        offset: usize,
+        value: Option<bool>,
+    ) -> Result<AssignedBit, Error> {
+        let cell = region.assign_advice(
+            || "bit",
+            column,
+            offset,
+            || {
+                value
+                    .map(|b| if b { F::one() } else { F::zero() })
+                    .ok_or(Error::SynthesisError)
+            },
+        )?;
+        Ok(AssignedBit { cell, value })
+    }
+
+    /// Copies `bit` into `column` at `offset`.
+    fn copy_bit(
+        config: &BooleanConfig,
+        region: &mut Region<'_, Self>,
+        column: Column<Advice>,
+        offset: usize,
+        bit: &AssignedBit,
+    ) -> Result<(), Error> {
+        let copy = Self::assign_output(region, column, offset, bit.value)?;
+        region.constrain_equal(&config.perm, copy.cell, bit.cell)
+    }
+
+    fn binary(
+        layouter: &mut impl Layouter<Self>,
+        name: &'static str,
+        selector: impl Fn(&BooleanConfig) -> Selector,
+        a: &AssignedBit,
+        b: &AssignedBit,
+        op: impl Fn(bool, bool) -> bool,
+    ) -> Result<AssignedBit, Error> {
+        let config = layouter.config().clone();
+        layouter.assign_region(
+            || name,
+            |mut region| {
+                selector(&config).enable(&mut region, 0)?;
+                Self::copy_bit(&config, &mut region, config.a, 0, a)?;
+                Self::copy_bit(&config, &mut region, config.b, 0, b)?;
+                let value = a.value.zip(b.value).map(|(a, b)| op(a, b));
+                Self::assign_output(&mut region, config.c, 0, value)
+            },
+        )
+    }
+
+    /// Assigns the packing of `bits` at the start of `region`, where each bit is
+    /// assigned by `assign`, and returns the packed value.
+    fn assign_pack(
+        config: &BooleanConfig,
+        region: &mut Region<'_, Self>,
+        bits: &[Option<bool>],
+        mut assign: impl FnMut(&mut Region<'_, Self>, usize, usize) -> Result<(), Error>,
+    ) -> Result<AssignedValue<F>, Error> {
+        assert!(!bits.is_empty() && bits.len() <= max_bits::<F>());
+
+        let mut acc = Some(F::zero());
+        let mut res = None;
+        for (row, i) in (0..bits.len()).rev().enumerate() {
+            if row == 0 {
+                config.q_pack_start.enable(region, row)?;
+            } else {
+                config.q_pack.enable(region, row)?;
+            }
+            assign(region, row, i)?;
+
+            acc = acc.zip(bits[i]).map(|(acc, bit)| {
+                let acc = acc + acc;
+                if bit {
+                    acc + F::one()
+                } else {
+                    acc
+                }
+            });
+            let cell = region.assign_advice(
+                || "accumulator",
+                config.b,
+                row,
+                || acc.ok_or(Error::SynthesisError),
+            )?;
+            res = Some(AssignedValue { cell, value: acc });
+        }
+        Ok(res.unwrap())
+    }
+}
+
+impl<F: FieldExt> BooleanInstructions for BooleanChip<F> {
+    type Value = AssignedValue<F>;
+    type Bit = AssignedBit;
+
+    fn witness_value(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<F>,
+    ) -> Result<Self::Value, Error> {
+        let config = layouter.config().clone();
+        layouter.assign_region(
+            || "witness value",
+            |mut region| {
+                let cell = region.assign_advice(
+                    || "value",
+                    config.a,
+                    0,
+                    || value.ok_or(Error::SynthesisError),
+                )?;
+                Ok(AssignedValue { cell, value })
+            },
+        )
+    }
+
+    fn witness_bit(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<bool>,
+    ) -> Result<Self::Bit, Error> {
+        let config = layouter.config().clone();
+        layouter.assign_region(
+            || "witness bit",
+            |mut region| Self::assign_bit(&config, &mut region, 0, value),
+        )
+    }
+
+    fn assert_bool(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Value,
+    ) -> Result<Self::Bit, Error> {
+        let config = layouter.config().clone();
+        layouter.assign_region(
+            || "assert bool",
+            |mut region| {
+                // A value that is not boolean is witnessed as one, and fails the check.
+                let value = a.value.map(|a| a != F::zero());
+                config.q_bool.enable(&mut region, 0)?;
+                let cell = region.assign_advice(
+                    || "bit",
+                    config.a,
+                    0,
+                    || a.value.ok_or(Error::SynthesisError),
+                )?;
+                region.constrain_equal(&config.perm, cell, a.cell)?;
+                Ok(AssignedBit { cell, value })
+            },
+        )
+    }
+
+    fn and(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Bit,
+        b: &Self::Bit,
+    ) -> Result<Self::Bit, Error> {
+        Self::binary(layouter, "and", |config| config.q_and, a, b, |a, b| a & b)
+    }
+
+    fn or(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Bit,
+        b: &Self::Bit,
+    ) -> Result<Self::Bit, Error> {
+        Self::binary(layouter, "or", |config| config.q_or, a, b, |a, b| a | b)
+    }
+
+    fn xor(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Bit,
+        b: &Self::Bit,
+    ) -> Result<Self::Bit, Error> {
+        Self::binary(layouter, "xor", |config| config.q_xor, a, b, |a, b| a ^ b)
+    }
+
+    fn not(layouter: &mut impl Layouter<Self>, a: &Self::Bit) -> Result<Self::Bit, Error> {
+        let config = layouter.config().clone();
+        layouter.assign_region(
+            || "not",
+            |mut region| {
+                config.q_not.enable(&mut region, 0)?;
+                Self::copy_bit(&config, &mut region, config.a, 0, a)?;
+                Self::assign_output(&mut region, config.b, 0, a.value.map(|a| !a))
+            },
+        )
+    }
+
+    fn pack(layouter: &mut impl Layouter<Self>, bits: &[Self::Bit]) -> Result<Self::Value, Error> {
+        let config = layouter.config().clone();
+        let values: Vec<_> = bits.iter().map(|bit| bit.value).collect();
+        layouter.assign_region(
+            || "pack",
+            |mut region| {
+                Self::assign_pack(&config, &mut region, &values, |region, row, i| {
+                    Self::copy_bit(&config, region, config.a, row, &bits[i])
+                })
+            },
+        )
+    }
+
+    fn unpack(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Value,
+        num_bits: usize,
+    ) -> Result<Vec<Self::Bit>, Error> {
+        let config = layouter.config().clone();
+        let values: Vec<_> = (0..num_bits)
+            .map(|i| {
+                a.value.map(|a| {
+                    let repr = a.to_repr();
+                    repr.as_ref()
+                        .get(i / 8)
+                        .is_some_and(|b| (b >> (i % 8)) & 1 == 1)
+                })
+            })
+            .collect();
+        layouter.assign_region(
+            || "unpack",
+            |mut region| {
+                let mut bits = vec![None; num_bits];
+                let packed = Self::assign_pack(&config, &mut region, &values, |region, row, i| {
+                    bits[i] = Some(Self::assign_bit(&config, region, row, values[i])?);
+                    Ok(())
+                })?;
+                region.constrain_equal(&config.perm, packed.cell, a.cell)?;
+                Ok(bits.into_iter().map(Option::unwrap).collect())
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BooleanChip, BooleanConfig};
+    use crate::{
+        arithmetic::FieldExt,
+        circuit::{layouter, Layouter},
+        dev::MockProver,
+        gadget::boolean::{pack, unpack, Bit, BooleanInstructions},
+        pasta::Fp,
+        plonk::{Assignment, Circuit, ConstraintSystem, Error},
+    };
+
+    struct BooleanCircuit {
+        a: Option<bool>,
+        b: Option<bool>,
+        value: Option<u64>,
+    }
+
+    impl Circuit<Fp> for BooleanCircuit {
+        type Config = BooleanConfig;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> BooleanConfig {
+            BooleanChip::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            config: BooleanConfig,
+        ) -> Result<(), Error> {
+            let mut layouter = layouter::SingleChip::<BooleanChip<Fp>, _>::new(cs, config)?;
+
+            let a = Bit::new(layouter.namespace(|| "a"), self.a)?;
+            let b = Bit::new(layouter.namespace(|| "b"), self.b)?;
+            let ops = [
+                a.and(layouter.namespace(|| "and"), &b)?,
+                a.or(layouter.namespace(|| "or"), &b)?,
+                a.xor(layouter.namespace(|| "xor"), &b)?,
+                a.not(layouter.namespace(|| "not"))?,
+            ];
+            let expected = self.a.zip(self.b).map(|(a, b)| [a & b, a | b, a ^ b, !a]);
+            for (i, op) in ops.iter().enumerate() {
+                assert_eq!(op.inner().value(), expected.map(|expected| expected[i]));
+            }
+
+            // Unpacking and repacking a value gives the same value.
+            let value = BooleanChip::witness_value(&mut layouter, self.value.map(Fp::from_u64))?;
+            let bits = unpack(layouter.namespace(|| "unpack"), &value, 10)?;
+            let packed = pack(layouter.namespace(|| "pack"), &bits)?;
+            assert_eq!(packed.value(), self.value.map(|v| Fp::from_u64(v & 0x3ff)));
+
+            // The value is also boolean if it is zero or one.
+            if self.value.is_some_and(|value| value <= 1) {
+                Bit::from_value(layouter.namespace(|| "assert bool"), &value)?;
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn boolean() {
+        let run = |a: bool, b: bool, value: u64| {
+            let circuit = BooleanCircuit {
+                a: Some(a),
+                b: Some(b),
+                value: Some(value),
+            };
+            MockProver::run(5, &circuit, vec![]).unwrap().verify()
+        };
+
+        for (a, b) in [(false, false), (false, true), (true, false), (true, true)] {
+            assert_eq!(run(a, b, 0x2a5), Ok(()));
+        }
+        assert_eq!(run(true, false, 0), Ok(()));
+        assert_eq!(run(true, false, 1), Ok(()));
+
+        // A value of more than 10 bits cannot be unpacked into 10 bits.
+        assert!(run(true, false, 0x400).is_err());
+    }
+
+    struct AssertBoolCircuit(Fp);
+
+    impl Circuit<Fp> for AssertBoolCircuit {
+        type Config = BooleanConfig;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> BooleanConfig {
+            BooleanChip::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            config: BooleanConfig,
+        ) -> Result<(), Error> {
+            let mut layouter = layouter::SingleChip::<BooleanChip<Fp>, _>::new(cs, config)?;
+            let value = BooleanChip::witness_value(&mut layouter, Some(self.0))?;
+            Bit::from_value(layouter, &value).map(|_| ())
+        }
+    }
+
+    #[test]
+    fn assert_bool() {
+        let run = |value: u64| {
+            MockProver::run(3, &AssertBoolCircuit(Fp::from_u64(value)), vec![])
+                .unwrap()
+                .verify()
+        };
+
+        assert_eq!(run(0), Ok(()));
+        assert_eq!(run(1), Ok(()));
+        assert!(run(2).is_err());
+    }
+}
@@ -0,0 +1,158 @@
+//! Gadget and chips for comparing unsigned integers.
+//!
+//! Comparing two $N$-bit integers $a$ and $b$ witnesses a bit $c$ and constrains
+//! $d = a - b + c \cdot 2^N$ to $N$ bits with the range-check lookup. Since $a - b$ is in
+//! $(-2^N, 2^N)$, this only holds if $c = 1$ when $a < b$ and $c = 0$ otherwise, so no
+//! borrow bits are needed.
+
+use std::fmt;
+
+use crate::{
+    circuit::{Chip, Layouter},
+    plonk::Error,
+};
+
+mod chip;
+pub use chip::{ComparisonChip, ComparisonConfig};
+
+/// The set of circuit instructions required to use the [`Unsigned`] gadget.
+pub trait ComparisonInstructions: Chip {
+    /// Variable representing an unsigned integer.
+    type Value: Clone + fmt::Debug;
+
+    /// Variable representing the boolean result of a comparison.
+    type Bool: Clone + fmt::Debug;
+
+    /// Witnesses an unsigned integer.
+    fn witness_value(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<Self::Field>,
+    ) -> Result<Self::Value, Error>;
+
+    /// Returns whether $a < b$, constraining both to $\mathsf{num\_bits}$ bits.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `num_bits` is not less than the capacity of the field.
+    fn less_than(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Value,
+        b: &Self::Value,
+        num_bits: usize,
+    ) -> Result<Self::Bool, Error>;
+
+    /// Returns whether $a \leq b$, constraining both to $\mathsf{num\_bits}$ bits.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `num_bits` is not less than the capacity of the field.
+    fn less_than_or_equal(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Value,
+        b: &Self::Value,
+        num_bits: usize,
+    ) -> Result<Self::Bool, Error>;
+
+    /// Constrains $a < b$, and both to $\mathsf{num\_bits}$ bits.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `num_bits` is not less than the capacity of the field.
+    fn assert_less_than(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Value,
+        b: &Self::Value,
+        num_bits: usize,
+    ) -> Result<(), Error>;
+
+    /// Constrains $a \leq b$, and both to $\mathsf{num\_bits}$ bits.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `num_bits` is not less than the capacity of the field.
+    fn assert_less_than_or_equal(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Value,
+        b: &Self::Value,
+        num_bits: usize,
+    ) -> Result<(), Error>;
+}
+
+/// An unsigned integer assigned in the circuit, which can be compared.
+#[derive(Debug)]
+pub struct Unsigned<ComparisonChip: ComparisonInstructions> {
+    inner: ComparisonChip::Value,
+}
+
+impl<ComparisonChip: ComparisonInstructions> Unsigned<ComparisonChip> {
+    /// Witnesses an unsigned integer.
+    pub fn new(
+        mut layouter: impl Layouter<ComparisonChip>,
+        value: Option<ComparisonChip::Field>,
+    ) -> Result<Self, Error> {
+        ComparisonChip::witness_value(&mut layouter, value).map(|inner| Unsigned { inner })
+    }
+
+    /// Wraps an integer returned by the chip, for use by other gadgets.
+    pub fn from_inner(inner: ComparisonChip::Value) -> Self {
+        Unsigned { inner }
+    }
+
+    /// Returns whether this integer is less than `other`, as `num_bits`-bit integers.
+    pub fn less_than(
+        &self,
+        mut layouter: impl Layouter<ComparisonChip>,
+        other: &Self,
+        num_bits: usize,
+    ) -> Result<ComparisonChip::Bool, Error> {
+        ComparisonChip::less_than(&mut layouter, &self.inner, &other.inner, num_bits)
+    }
+
+    /// Returns whether this integer is at most `other`, as `num_bits`-bit integers.
+    pub fn less_than_or_equal(
+        &self,
+        mut layouter: impl Layouter<ComparisonChip>,
+        other: &Self,
+        num_bits: usize,
+    ) -> Result<ComparisonChip::Bool, Error> {
+        ComparisonChip::less_than_or_equal(&mut layouter, &self.inner, &other.inner, num_bits)
+    }
+
+    /// Constrains this integer to be less than `other`, as `num_bits`-bit integers.
+    pub fn assert_less_than(
+        &self,
+        mut layouter: impl Layouter<ComparisonChip>,
+        other: &Self,
+        num_bits: usize,
+    ) -> Result<(), Error> {
+        ComparisonChip::assert_less_than(&mut layouter, &self.inner, &other.inner, num_bits)
+    }
+
+    /// Constrains this integer to be at most `other`, as `num_bits`-bit integers.
+    pub fn assert_less_than_or_equal(
+        &self,
+        mut layouter: impl Layouter<ComparisonChip>,
+        other: &Self,
+        num_bits: usize,
+    ) -> Result<(), Error> {
+        ComparisonChip::assert_less_than_or_equal(
+            &mut layouter,
+            &self.inner,
+            &other.inner,
+            num_bits,
+        )
+    }
+
+    /// Returns the chip-specific representation of this integer.
+    pub fn inner(&self) -> &ComparisonChip::Value {
+        &self.inner
+    }
+}
+
+impl<ComparisonChip: ComparisonInstructions> Clone for Unsigned<ComparisonChip> {
+    fn clone(&self) -> Self {
+        Unsigned {
+            inner: self.inner.clone(),
+        }
+    }
+}
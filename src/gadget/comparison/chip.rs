@@ -0,0 +1,316 @@
+//! A chip implementing [`ComparisonInstructions`] with the range-check lookup.
+//!
+//! # Layout
+//!
+//! A comparison of $a$ and $b$ uses the two advice columns of a [`RangeCheckConfig`].
+//! Its first row holds $a$ and $b$, and its second row holds $d$ and the result $c$,
+//! with $2^N$ and an offset $o$ in fixed columns of the first row, so that
+//! $d = a - b - o + c \cdot 2^N$. The result is $a < b$ if $o = 0$ and $a \leq b$ if
+//! $o = 1$. The following rows range-check $a$, $b$ and $d$ to $N$ bits.
+
+use std::cmp::Ordering;
+use std::marker::PhantomData;
+
+use super::ComparisonInstructions;
+use crate::{
+    arithmetic::FieldExt,
+    circuit::{Chip, Layouter, Region},
+    gadget::range_check::{max_bits, AssignedValue, RangeCheckConfig},
+    plonk::{Column, ConstraintSystem, Error, Expression, Fixed, Selector},
+    poly::Rotation,
+};
+
+/// Compares two field elements as integers.
+fn compare<F: FieldExt>(a: &F, b: &F) -> Ordering {
+    let (a, b) = (a.to_repr(), b.to_repr());
+    a.as_ref().iter().rev().cmp(b.as_ref().iter().rev())
+}
+
+/// Configuration for a [`ComparisonChip`].
+#[derive(Clone, Debug)]
+pub struct ComparisonConfig {
+    range: RangeCheckConfig,
+    two_pow_n: Column<Fixed>,
+    offset: Column<Fixed>,
+    q_compare: Selector,
+    q_assert: Selector,
+}
+
+/// A chip implementing [`ComparisonInstructions`].
+#[derive(Debug)]
+pub struct ComparisonChip<F: FieldExt> {
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt> Chip for ComparisonChip<F> {
+    type Config = ComparisonConfig;
+    type Loaded = ();
+    type Field = F;
+
+    fn load(layouter: &mut impl Layouter<Self>) -> Result<(), Error> {
+        let config = layouter.config().clone();
+        config.range.load(layouter)
+    }
+}
+
+impl<F: FieldExt> ComparisonChip<F> {
+    /// Configures this chip with a range-check table of `word_bits`-bit values.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `word_bits` is zero or greater than 24.
+    pub fn configure(meta: &mut ConstraintSystem<F>, word_bits: usize) -> ComparisonConfig {
+        let range = RangeCheckConfig::configure(meta, word_bits);
+        let config = ComparisonConfig {
+            range,
+            two_pow_n: meta.fixed_column(),
+            offset: meta.fixed_column(),
+            q_compare: meta.selector(),
+            q_assert: meta.selector(),
+        };
+
+        let (z, word) = (config.range.z(), config.range.word());
+        meta.create_gate("comparison", |meta| {
+            let a = meta.query_advice(z, Rotation::cur());
+            let b = meta.query_advice(word, Rotation::cur());
+            let d = meta.query_advice(z, Rotation::next());
+            let c = meta.query_advice(word, Rotation::next());
+            let two_pow_n = meta.query_fixed(config.two_pow_n, Rotation::cur());
+            let offset = meta.query_fixed(config.offset, Rotation::cur());
+            let q_compare = meta.query_selector(config.q_compare, Rotation::cur());
+
+            q_compare * (a - b - offset + c * two_pow_n - d)
+        });
+        meta.create_gate("comparison bool", |meta| {
+            let c = meta.query_advice(word, Rotation::next());
+            let q_compare = meta.query_selector(config.q_compare, Rotation::cur());
+
+            q_compare * c.clone() * (Expression::Constant(F::one()) - c)
+        });
+        meta.create_gate("comparison assert", |meta| {
+            let c = meta.query_advice(word, Rotation::next());
+            let q_assert = meta.query_selector(config.q_assert, Rotation::cur());
+
+            q_assert * (Expression::Constant(F::one()) - c)
+        });
+
+        config
+    }
+}
+
+impl ComparisonConfig {
+    /// The range-check configuration, whose columns hold the comparisons.
+    pub(crate) fn range(&self) -> &RangeCheckConfig {
+        &self.range
+    }
+
+    /// Assigns the comparison $a < b + \mathsf{offset}$ at the start of `region`,
+    /// constraining it to hold if `assert` is set, and returns its result.
+    pub(crate) fn assign_comparison<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        region: &mut Region<'_, Ch>,
+        a: &AssignedValue<F>,
+        b: &AssignedValue<F>,
+        num_bits: usize,
+        offset: u64,
+        assert: bool,
+    ) -> Result<AssignedValue<F>, Error> {
+        assert!(num_bits <= max_bits::<F>());
+        let (z, word) = (self.range.z(), self.range.word());
+        let two_pow_n = (0..num_bits).fold(F::one(), |acc, _| acc + acc);
+
+        self.q_compare.enable(region, 0)?;
+        if assert {
+            self.q_assert.enable(region, 0)?;
+        }
+        region.assign_fixed(|| "2^N", self.two_pow_n, 0, || Ok(two_pow_n))?;
+        region.assign_fixed(|| "offset", self.offset, 0, || Ok(F::from_u64(offset)))?;
+
+        let a_cell = region.assign_advice(|| "a", z, 0, || a.value.ok_or(Error::SynthesisError))?;
+        region.constrain_equal(self.range.perm(), a_cell, a.cell)?;
+        let b_cell =
+            region.assign_advice(|| "b", word, 0, || b.value.ok_or(Error::SynthesisError))?;
+        region.constrain_equal(self.range.perm(), b_cell, b.cell)?;
+
+        let c = a.value.zip(b.value).map(|(a, b)| {
+            let less = match compare(&a, &b) {
+                Ordering::Less => true,
+                Ordering::Equal => offset == 1,
+                Ordering::Greater => false,
+            };
+            if less {
+                F::one()
+            } else {
+                F::zero()
+            }
+        });
+        let d = a
+            .value
+            .zip(b.value)
+            .zip(c)
+            .map(|((a, b), c)| a - b - F::from_u64(offset) + c * two_pow_n);
+        let d = self.range.assign_value(region, 1, d)?;
+        let c_cell = region.assign_advice(|| "c", word, 1, || c.ok_or(Error::SynthesisError))?;
+
+        let mut row = 2;
+        for value in [a, b, &d] {
+            row += self
+                .range
+                .assign_range_check(region, row, value, num_bits)?;
+        }
+
+        Ok(AssignedValue {
+            cell: c_cell,
+            value: c,
+        })
+    }
+}
+
+impl<F: FieldExt> ComparisonChip<F> {
+    fn compare(
+        layouter: &mut impl Layouter<Self>,
+        a: &AssignedValue<F>,
+        b: &AssignedValue<F>,
+        num_bits: usize,
+        offset: u64,
+        assert: bool,
+    ) -> Result<AssignedValue<F>, Error> {
+        let config = layouter.config().clone();
+        layouter.assign_region(
+            || "comparison",
+            |mut region| config.assign_comparison(&mut region, a, b, num_bits, offset, assert),
+        )
+    }
+}
+
+impl<F: FieldExt> ComparisonInstructions for ComparisonChip<F> {
+    type Value = AssignedValue<F>;
+    type Bool = AssignedValue<F>;
+
+    fn witness_value(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<F>,
+    ) -> Result<Self::Value, Error> {
+        let config = layouter.config().clone();
+        layouter.assign_region(
+            || "witness value",
+            |mut region| config.range.assign_value(&mut region, 0, value),
+        )
+    }
+
+    fn less_than(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Value,
+        b: &Self::Value,
+        num_bits: usize,
+    ) -> Result<Self::Bool, Error> {
+        Self::compare(layouter, a, b, num_bits, 0, false)
+    }
+
+    fn less_than_or_equal(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Value,
+        b: &Self::Value,
+        num_bits: usize,
+    ) -> Result<Self::Bool, Error> {
+        Self::compare(layouter, a, b, num_bits, 1, false)
+    }
+
+    fn assert_less_than(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Value,
+        b: &Self::Value,
+        num_bits: usize,
+    ) -> Result<(), Error> {
+        Self::compare(layouter, a, b, num_bits, 0, true).map(|_| ())
+    }
+
+    fn assert_less_than_or_equal(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Value,
+        b: &Self::Value,
+        num_bits: usize,
+    ) -> Result<(), Error> {
+        Self::compare(layouter, a, b, num_bits, 1, true).map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ComparisonChip, ComparisonConfig};
+    use crate::{
+        arithmetic::FieldExt,
+        circuit::{layouter, Layouter},
+        dev::MockProver,
+        gadget::comparison::Unsigned,
+        pasta::Fp,
+        plonk::{Assignment, Circuit, ConstraintSystem, Error},
+    };
+
+    struct ComparisonCircuit {
+        a: Option<Fp>,
+        b: Option<Fp>,
+        num_bits: usize,
+        expected: [bool; 2],
+        assert: bool,
+    }
+
+    impl Circuit<Fp> for ComparisonCircuit {
+        type Config = ComparisonConfig;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> ComparisonConfig {
+            ComparisonChip::configure(meta, 8)
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            config: ComparisonConfig,
+        ) -> Result<(), Error> {
+            let mut layouter = layouter::SingleChip::<ComparisonChip<Fp>, _>::new(cs, config)?;
+
+            let a = Unsigned::new(layouter.namespace(|| "a"), self.a)?;
+            let b = Unsigned::new(layouter.namespace(|| "b"), self.b)?;
+
+            let lt = a.less_than(layouter.namespace(|| "a < b"), &b, self.num_bits)?;
+            let le = a.less_than_or_equal(layouter.namespace(|| "a <= b"), &b, self.num_bits)?;
+            let bool_value = |b: bool| Some(if b { Fp::one() } else { Fp::zero() });
+            assert_eq!(lt.value(), bool_value(self.expected[0]));
+            assert_eq!(le.value(), bool_value(self.expected[1]));
+
+            if self.assert {
+                a.assert_less_than(layouter.namespace(|| "assert a < b"), &b, self.num_bits)?;
+            }
+            a.assert_less_than_or_equal(layouter.namespace(|| "assert a <= a"), &a, self.num_bits)
+        }
+    }
+
+    #[test]
+    fn comparison() {
+        let run = |a: u64, b: u64, num_bits: usize, expected: [bool; 2], assert: bool| {
+            let circuit = ComparisonCircuit {
+                a: Some(Fp::from_u64(a)),
+                b: Some(Fp::from_u64(b)),
+                num_bits,
+                expected,
+                assert,
+            };
+            MockProver::run(9, &circuit, vec![]).unwrap().verify()
+        };
+
+        assert_eq!(run(3, 5, 4, [true, true], true), Ok(()));
+        assert_eq!(run(5, 5, 4, [false, true], false), Ok(()));
+        assert_eq!(run(6, 5, 4, [false, false], false), Ok(()));
+        assert_eq!(run(0, (1 << 20) - 1, 20, [true, true], true), Ok(()));
+        assert_eq!(run((1 << 20) - 1, 0, 20, [false, false], false), Ok(()));
+
+        // Asserting a false comparison fails.
+        assert!(run(5, 5, 4, [false, true], true).is_err());
+        assert!(run(6, 5, 4, [false, false], true).is_err());
+
+        // The inputs must fit in the bit length.
+        assert!(run(3, 16, 4, [true, true], false).is_err());
+    }
+}
@@ -0,0 +1,41 @@
+//! Gadget and chips for division with remainder of unsigned integers.
+//!
+//! Dividing an $N$-bit integer $a$ by an $N$-bit integer $b$ witnesses the quotient $q$
+//! and the remainder $r$, and constrains $a = q \cdot b + r$ with $q$ and $r$ in
+//! $N$ bits and $r < b$. As $q \cdot b + r < 2^{2N}$, this cannot wrap around the
+//! modulus when $2N$ is less than the capacity of the field, so $q$ and $r$ are the
+//! unique integer quotient and remainder. Division by zero cannot satisfy $r < b$.
+
+use super::comparison::{ComparisonInstructions, Unsigned};
+use crate::{circuit::Layouter, plonk::Error};
+
+mod chip;
+pub use chip::{DivisionChip, DivisionConfig};
+
+/// The set of circuit instructions required to use the [`div_rem`] gadget.
+pub trait DivisionInstructions: ComparisonInstructions {
+    /// Returns the quotient and remainder of $a / b$, constraining $a$ and $b$ to
+    /// $\mathsf{num\_bits}$ bits.
+    ///
+    /// # Panics
+    ///
+    /// Panics if $2 \cdot \mathsf{num\_bits}$ is not less than the capacity of the
+    /// field.
+    fn div_rem(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Value,
+        b: &Self::Value,
+        num_bits: usize,
+    ) -> Result<(Self::Value, Self::Value), Error>;
+}
+
+/// Returns the quotient and remainder of $a / b$ as `num_bits`-bit integers.
+pub fn div_rem<DivisionChip: DivisionInstructions>(
+    mut layouter: impl Layouter<DivisionChip>,
+    a: &Unsigned<DivisionChip>,
+    b: &Unsigned<DivisionChip>,
+    num_bits: usize,
+) -> Result<(Unsigned<DivisionChip>, Unsigned<DivisionChip>), Error> {
+    let (q, r) = DivisionChip::div_rem(&mut layouter, a.inner(), b.inner(), num_bits)?;
+    Ok((Unsigned::from_inner(q), Unsigned::from_inner(r)))
+}
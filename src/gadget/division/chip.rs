@@ -0,0 +1,278 @@
+//! A chip implementing [`DivisionInstructions`] with the comparison chip.
+//!
+//! # Layout
+//!
+//! The chip includes a [`ComparisonConfig`], and implements [`ComparisonInstructions`]
+//! with it. A division of $a$ by $b$ uses the two advice columns of its range-check
+//! configuration: the first row holds $a$ and $b$, and the second row holds $q$ and $r$.
+//! The following rows range-check $a$ and $q$ to $N$ bits, and $r < b$ is asserted by a
+//! comparison in a separate region, which range-checks $r$ and $b$.
+
+use std::marker::PhantomData;
+
+use super::DivisionInstructions;
+use crate::{
+    arithmetic::FieldExt,
+    circuit::{Chip, Layouter},
+    gadget::{
+        comparison::{ComparisonChip, ComparisonConfig, ComparisonInstructions},
+        range_check::{max_bits, AssignedValue},
+    },
+    plonk::{ConstraintSystem, Error, Selector},
+    poly::Rotation,
+};
+
+/// Configuration for a [`DivisionChip`].
+#[derive(Clone, Debug)]
+pub struct DivisionConfig {
+    comparison: ComparisonConfig,
+    q_div: Selector,
+}
+
+/// A chip implementing [`DivisionInstructions`].
+#[derive(Debug)]
+pub struct DivisionChip<F: FieldExt> {
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt> Chip for DivisionChip<F> {
+    type Config = DivisionConfig;
+    type Loaded = ();
+    type Field = F;
+
+    fn load(layouter: &mut impl Layouter<Self>) -> Result<(), Error> {
+        let config = layouter.config().clone();
+        config.comparison.range().load(layouter)
+    }
+}
+
+impl<F: FieldExt> DivisionChip<F> {
+    /// Configures this chip with a range-check table of `word_bits`-bit values.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `word_bits` is zero or greater than 24.
+    pub fn configure(meta: &mut ConstraintSystem<F>, word_bits: usize) -> DivisionConfig {
+        let comparison = ComparisonChip::configure(meta, word_bits);
+        let q_div = meta.selector();
+
+        let (z, word) = (comparison.range().z(), comparison.range().word());
+        meta.create_gate("division", |meta| {
+            let a = meta.query_advice(z, Rotation::cur());
+            let b = meta.query_advice(word, Rotation::cur());
+            let q = meta.query_advice(z, Rotation::next());
+            let r = meta.query_advice(word, Rotation::next());
+            let q_div = meta.query_selector(q_div, Rotation::cur());
+
+            q_div * (a - q * b - r)
+        });
+
+        DivisionConfig { comparison, q_div }
+    }
+
+    fn compare(
+        layouter: &mut impl Layouter<Self>,
+        a: &AssignedValue<F>,
+        b: &AssignedValue<F>,
+        num_bits: usize,
+        offset: u64,
+        assert: bool,
+    ) -> Result<AssignedValue<F>, Error> {
+        let config = layouter.config().clone();
+        layouter.assign_region(
+            || "comparison",
+            |mut region| {
+                config
+                    .comparison
+                    .assign_comparison(&mut region, a, b, num_bits, offset, assert)
+            },
+        )
+    }
+}
+
+impl<F: FieldExt> ComparisonInstructions for DivisionChip<F> {
+    type Value = AssignedValue<F>;
+    type Bool = AssignedValue<F>;
+
+    fn witness_value(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<F>,
+    ) -> Result<Self::Value, Error> {
+        let config = layouter.config().clone();
+        layouter.assign_region(
+            || "witness value",
+            |mut region| {
+                config
+                    .comparison
+                    .range()
+                    .assign_value(&mut region, 0, value)
+            },
+        )
+    }
+
+    fn less_than(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Value,
+        b: &Self::Value,
+        num_bits: usize,
+    ) -> Result<Self::Bool, Error> {
+        Self::compare(layouter, a, b, num_bits, 0, false)
+    }
+
+    fn less_than_or_equal(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Value,
+        b: &Self::Value,
+        num_bits: usize,
+    ) -> Result<Self::Bool, Error> {
+        Self::compare(layouter, a, b, num_bits, 1, false)
+    }
+
+    fn assert_less_than(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Value,
+        b: &Self::Value,
+        num_bits: usize,
+    ) -> Result<(), Error> {
+        Self::compare(layouter, a, b, num_bits, 0, true).map(|_| ())
+    }
+
+    fn assert_less_than_or_equal(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Value,
+        b: &Self::Value,
+        num_bits: usize,
+    ) -> Result<(), Error> {
+        Self::compare(layouter, a, b, num_bits, 1, true).map(|_| ())
+    }
+}
+
+impl<F: FieldExt> DivisionInstructions for DivisionChip<F> {
+    fn div_rem(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Value,
+        b: &Self::Value,
+        num_bits: usize,
+    ) -> Result<(Self::Value, Self::Value), Error> {
+        assert!(2 * num_bits < max_bits::<F>());
+        let config = layouter.config().clone();
+        let range = config.comparison.range().clone();
+
+        // The values fit in 128 bits. Dividing by zero gives q = 0 and r = a, which
+        // fails the comparison.
+        let quotient = a.value.zip(b.value).map(|(a, b)| {
+            let (a, b) = (a.get_lower_128(), b.get_lower_128());
+            a.checked_div(b).zip(a.checked_rem(b)).unwrap_or((0, a))
+        });
+
+        let (q, r) = layouter.assign_region(
+            || "division",
+            |mut region| {
+                config.q_div.enable(&mut region, 0)?;
+
+                let a_cell = region.assign_advice(
+                    || "a",
+                    range.z(),
+                    0,
+                    || a.value.ok_or(Error::SynthesisError),
+                )?;
+                region.constrain_equal(range.perm(), a_cell, a.cell)?;
+                let b_cell = region.assign_advice(
+                    || "b",
+                    range.word(),
+                    0,
+                    || b.value.ok_or(Error::SynthesisError),
+                )?;
+                region.constrain_equal(range.perm(), b_cell, b.cell)?;
+
+                let q =
+                    range.assign_value(&mut region, 1, quotient.map(|(q, _)| F::from_u128(q)))?;
+                let r_value = quotient.map(|(_, r)| F::from_u128(r));
+                let r_cell = region.assign_advice(
+                    || "r",
+                    range.word(),
+                    1,
+                    || r_value.ok_or(Error::SynthesisError),
+                )?;
+                let r = AssignedValue {
+                    cell: r_cell,
+                    value: r_value,
+                };
+
+                let mut row = 2;
+                for value in [a, &q] {
+                    row += range.assign_range_check(&mut region, row, value, num_bits)?;
+                }
+                Ok((q, r))
+            },
+        )?;
+
+        Self::compare(layouter, &r, b, num_bits, 0, true)?;
+        Ok((q, r))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DivisionChip, DivisionConfig};
+    use crate::{
+        arithmetic::FieldExt,
+        circuit::{layouter, Layouter},
+        dev::MockProver,
+        gadget::{comparison::Unsigned, division::div_rem},
+        pasta::Fp,
+        plonk::{Assignment, Circuit, ConstraintSystem, Error},
+    };
+
+    struct DivisionCircuit {
+        a: u64,
+        b: u64,
+        num_bits: usize,
+    }
+
+    impl Circuit<Fp> for DivisionCircuit {
+        type Config = DivisionConfig;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> DivisionConfig {
+            DivisionChip::configure(meta, 8)
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            config: DivisionConfig,
+        ) -> Result<(), Error> {
+            let mut layouter = layouter::SingleChip::<DivisionChip<Fp>, _>::new(cs, config)?;
+
+            let a = Unsigned::new(layouter.namespace(|| "a"), Some(Fp::from_u64(self.a)))?;
+            let b = Unsigned::new(layouter.namespace(|| "b"), Some(Fp::from_u64(self.b)))?;
+            let (q, r) = div_rem(layouter.namespace(|| "a / b"), &a, &b, self.num_bits)?;
+            if let Some(expected) = self.a.checked_div(self.b) {
+                assert_eq!(q.inner().value(), Some(Fp::from_u64(expected)));
+                assert_eq!(r.inner().value(), Some(Fp::from_u64(self.a % self.b)));
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn division() {
+        let run = |a: u64, b: u64, num_bits: usize| {
+            let circuit = DivisionCircuit { a, b, num_bits };
+            MockProver::run(9, &circuit, vec![]).unwrap().verify()
+        };
+
+        assert_eq!(run(17, 5, 8), Ok(()));
+        assert_eq!(run(15, 5, 8), Ok(()));
+        assert_eq!(run(3, 5, 8), Ok(()));
+        assert_eq!(run(0, 1, 1), Ok(()));
+        assert_eq!(run((1 << 40) - 1, 1000, 40), Ok(()));
+
+        // Division by zero, and inputs that do not fit in the bit length, fail.
+        assert!(run(17, 0, 8).is_err());
+        assert!(run(300, 5, 8).is_err());
+        assert!(run(17, 300, 8).is_err());
+    }
+}
@@ -0,0 +1,210 @@
+//! Gadget and chips for elliptic curve arithmetic over curves whose base field is the
+//! circuit field, such as Pallas and Vesta.
+//!
+//! Points are represented in affine coordinates, with the identity represented as
+//! $(0, 0)$ (which is not on any curve with $b \neq 0$). Witnessed points are checked to
+//! be on the curve, and are therefore never the identity; the identity can only be
+//! obtained as the result of an addition.
+//!
+//! Multiplication by a [`FixedPoint`] (such as a generator) uses tables precomputed
+//! from the point, which must be known when the circuit is constructed. A variable
+//! [`Point`] can be multiplied by a bit string using endoscaling, which maps the bits to
+//! a scalar as described in the [`endoscale`](crate::primitives::endoscale) module.
+
+use std::fmt;
+
+use crate::{
+    arithmetic::CurveAffine,
+    circuit::{Chip, Layouter},
+    plonk::Error,
+};
+
+mod chip;
+pub use chip::{EccChip, EccConfig, EccFixedPoint, EccPoint, EccX};
+
+/// The set of circuit instructions required to use the [`Point`] gadget.
+pub trait EccInstructions<C: CurveAffine>: Chip<Field = C::Base> {
+    /// Variable representing a point on the curve, or the identity.
+    type Point: Clone + fmt::Debug;
+
+    /// Variable representing the $x$-coordinate of a point.
+    type X: Clone + fmt::Debug;
+
+    /// A point that is fixed by the circuit, along with any precomputed values used to
+    /// multiply by it.
+    type FixedPoint: Clone + fmt::Debug;
+
+    /// Precomputes the values used to multiply by `base`.
+    fn fixed_point(base: C) -> Self::FixedPoint;
+
+    /// Witnesses a point, constraining it to be on the curve.
+    ///
+    /// Returns an error if `value` is the identity.
+    fn witness_point(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<C>,
+    ) -> Result<Self::Point, Error>;
+
+    /// Returns the $x$-coordinate of `point`, which is zero for the identity.
+    fn extract_p(point: &Self::Point) -> Self::X;
+
+    /// Returns `a + b`, using complete addition.
+    fn add(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Point,
+        b: &Self::Point,
+    ) -> Result<Self::Point, Error>;
+
+    /// Returns `[2] a`.
+    fn double(layouter: &mut impl Layouter<Self>, a: &Self::Point) -> Result<Self::Point, Error>;
+
+    /// Returns `[scalar] base`.
+    fn mul_fixed(
+        layouter: &mut impl Layouter<Self>,
+        scalar: Option<C::Scalar>,
+        base: &Self::FixedPoint,
+    ) -> Result<Self::Point, Error>;
+
+    /// Returns `[k] base`, where `k` is the scalar encoded by the little-endian bit
+    /// string `bits` using endoscaling.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bits` has an odd length.
+    fn mul_endo(
+        layouter: &mut impl Layouter<Self>,
+        base: &Self::Point,
+        bits: &[Option<bool>],
+    ) -> Result<Self::Point, Error>;
+
+    /// Constrains `a` and `b` to be the same point.
+    fn constrain_equal(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Point,
+        b: &Self::Point,
+    ) -> Result<(), Error>;
+}
+
+/// A point on the curve `C`, or the identity.
+#[derive(Debug)]
+pub struct Point<C: CurveAffine, EccChip: EccInstructions<C>> {
+    inner: EccChip::Point,
+}
+
+impl<C: CurveAffine, EccChip: EccInstructions<C>> Point<C, EccChip> {
+    /// Witnesses the given point.
+    pub fn new(mut layouter: impl Layouter<EccChip>, value: Option<C>) -> Result<Self, Error> {
+        EccChip::witness_point(&mut layouter, value).map(|inner| Point { inner })
+    }
+
+    /// Wraps a point returned by the chip, for use by other gadgets.
+    pub fn from_inner(inner: EccChip::Point) -> Self {
+        Point { inner }
+    }
+
+    /// Returns the $x$-coordinate of `self`, which is zero for the identity.
+    pub fn extract_p(&self) -> X<C, EccChip> {
+        X {
+            inner: EccChip::extract_p(&self.inner),
+        }
+    }
+
+    /// Returns `self + other`.
+    pub fn add(&self, mut layouter: impl Layouter<EccChip>, other: &Self) -> Result<Self, Error> {
+        EccChip::add(&mut layouter, &self.inner, &other.inner).map(|inner| Point { inner })
+    }
+
+    /// Returns `[2] self`.
+    pub fn double(&self, mut layouter: impl Layouter<EccChip>) -> Result<Self, Error> {
+        EccChip::double(&mut layouter, &self.inner).map(|inner| Point { inner })
+    }
+
+    /// Returns `[k] self`, where `k` is the scalar encoded by the little-endian bit
+    /// string `bits` using endoscaling.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bits` has an odd length.
+    pub fn mul_endo(
+        &self,
+        mut layouter: impl Layouter<EccChip>,
+        bits: &[Option<bool>],
+    ) -> Result<Self, Error> {
+        EccChip::mul_endo(&mut layouter, &self.inner, bits).map(|inner| Point { inner })
+    }
+
+    /// Constrains `self` and `other` to be the same point.
+    pub fn constrain_equal(
+        &self,
+        mut layouter: impl Layouter<EccChip>,
+        other: &Self,
+    ) -> Result<(), Error> {
+        EccChip::constrain_equal(&mut layouter, &self.inner, &other.inner)
+    }
+
+    /// Returns the chip-specific representation of this point.
+    pub fn inner(&self) -> &EccChip::Point {
+        &self.inner
+    }
+}
+
+impl<C: CurveAffine, EccChip: EccInstructions<C>> Clone for Point<C, EccChip> {
+    fn clone(&self) -> Self {
+        Point {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+/// The $x$-coordinate of a point on the curve `C`.
+#[derive(Debug)]
+pub struct X<C: CurveAffine, EccChip: EccInstructions<C>> {
+    inner: EccChip::X,
+}
+
+impl<C: CurveAffine, EccChip: EccInstructions<C>> X<C, EccChip> {
+    /// Returns the chip-specific representation of this coordinate.
+    pub fn inner(&self) -> &EccChip::X {
+        &self.inner
+    }
+}
+
+impl<C: CurveAffine, EccChip: EccInstructions<C>> Clone for X<C, EccChip> {
+    fn clone(&self) -> Self {
+        X {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+/// A point on the curve `C` that is fixed by the circuit.
+#[derive(Debug)]
+pub struct FixedPoint<C: CurveAffine, EccChip: EccInstructions<C>> {
+    inner: EccChip::FixedPoint,
+}
+
+impl<C: CurveAffine, EccChip: EccInstructions<C>> FixedPoint<C, EccChip> {
+    /// Precomputes the values used to multiply by `base`.
+    pub fn new(base: C) -> Self {
+        FixedPoint {
+            inner: EccChip::fixed_point(base),
+        }
+    }
+
+    /// Returns `[scalar] self`.
+    pub fn mul(
+        &self,
+        mut layouter: impl Layouter<EccChip>,
+        scalar: Option<C::Scalar>,
+    ) -> Result<Point<C, EccChip>, Error> {
+        EccChip::mul_fixed(&mut layouter, scalar, &self.inner).map(|inner| Point { inner })
+    }
+}
+
+impl<C: CurveAffine, EccChip: EccInstructions<C>> Clone for FixedPoint<C, EccChip> {
+    fn clone(&self) -> Self {
+        FixedPoint {
+            inner: self.inner.clone(),
+        }
+    }
+}
@@ -0,0 +1,695 @@
+//! A chip implementing [`EccInstructions`] with complete affine addition.
+//!
+//! # Layout
+//!
+//! Witnessing a point uses one row, holding its coordinates in the `x_p` and `y_p`
+//! columns.
+//!
+//! An addition $R = P + Q$ uses two rows: the first holds $P$ in `x_p, y_p`, $Q$ in
+//! `x_q, y_q`, and the auxiliary witnesses $\lambda, \alpha, \beta, \gamma, \delta$,
+//! and the second holds $R$ in `x_p, y_p`. The constraints are those of the complete
+//! addition formulae used by the Orchard circuit: $\lambda$ is the slope of the line
+//! through $P$ and $Q$ (or of the tangent at $P$ when $x_P = x_Q$), and $\alpha,
+//! \beta, \gamma, \delta$ are the inverses (or zero) of $x_Q - x_P$, $x_P$, $x_Q$ and
+//! $y_Q + y_P$ respectively, which select between the cases where $P$ or $Q$ is the
+//! identity, $P = -Q$, and the general case.
+//!
+//! Fixed-base scalar multiplication is described in the [`mul_fixed`] module, and
+//! variable-base scalar multiplication with endoscaling in the [`mul_endo`] module.
+
+use std::convert::TryInto;
+use std::marker::PhantomData;
+
+use ff::Field;
+
+use super::EccInstructions;
+use crate::{
+    arithmetic::{CurveAffine, FieldExt},
+    circuit::{Cell, Chip, Layouter, Region},
+    plonk::{Advice, Column, ConstraintSystem, Error, Expression, Fixed, Permutation, Selector},
+    poly::Rotation,
+};
+
+pub mod mul_endo;
+pub mod mul_fixed;
+pub use mul_fixed::EccFixedPoint;
+
+/// A point assigned in the circuit, or the identity.
+#[derive(Clone, Copy, Debug)]
+pub struct EccPoint<C: CurveAffine> {
+    x: Cell,
+    y: Cell,
+    value: Option<C>,
+}
+
+impl<C: CurveAffine> EccPoint<C> {
+    /// The cell containing the $x$-coordinate of this point.
+    pub fn x(&self) -> Cell {
+        self.x
+    }
+
+    /// The cell containing the $y$-coordinate of this point.
+    pub fn y(&self) -> Cell {
+        self.y
+    }
+
+    /// The value of this point, if known.
+    pub fn point(&self) -> Option<C> {
+        self.value
+    }
+
+    /// The $x$-coordinate of this point, which is zero for the identity.
+    pub fn extract_p(&self) -> EccX<C::Base> {
+        EccX {
+            cell: self.x,
+            value: self.value.map(|value| coordinates(&value).0),
+        }
+    }
+}
+
+/// The $x$-coordinate of a point assigned in the circuit.
+#[derive(Clone, Copy, Debug)]
+pub struct EccX<F: FieldExt> {
+    cell: Cell,
+    value: Option<F>,
+}
+
+impl<F: FieldExt> EccX<F> {
+    /// The cell containing this coordinate.
+    pub fn cell(&self) -> Cell {
+        self.cell
+    }
+
+    /// The value of this coordinate, if known.
+    pub fn value(&self) -> Option<F> {
+        self.value
+    }
+}
+
+/// Returns the coordinates of `point`, or $(0, 0)$ for the identity.
+fn coordinates<C: CurveAffine>(point: &C) -> (C::Base, C::Base) {
+    Option::from(point.get_xy()).unwrap_or((C::Base::zero(), C::Base::zero()))
+}
+
+/// Returns the inverse of `value`, or zero if `value` is zero.
+fn inv0<F: FieldExt>(value: F) -> F {
+    Option::from(value.invert()).unwrap_or_else(F::zero)
+}
+
+/// Configuration for an [`EccChip`].
+#[derive(Clone, Debug)]
+pub struct EccConfig {
+    pub(crate) x_p: Column<Advice>,
+    pub(crate) y_p: Column<Advice>,
+    x_q: Column<Advice>,
+    y_q: Column<Advice>,
+    lambda: Column<Advice>,
+    alpha: Column<Advice>,
+    beta: Column<Advice>,
+    gamma: Column<Advice>,
+    delta: Column<Advice>,
+    pub(crate) window: Column<Advice>,
+    pub(crate) negate: Column<Advice>,
+    x_base: Column<Advice>,
+    y_base: Column<Advice>,
+    lagrange_coeffs: [[Column<Fixed>; mul_fixed::H]; 2],
+    s_point: Selector,
+    s_add: Selector,
+    s_mul_fixed: Selector,
+    s_identity: Selector,
+    s_phi: Selector,
+    s_endo: Selector,
+    perm: Permutation,
+}
+
+impl EccConfig {
+    /// Assigns a copy of `point` in the given columns at `offset`.
+    pub(crate) fn copy_point<C: CurveAffine, Ch: Chip<Field = C::Base>>(
+        &self,
+        region: &mut Region<'_, Ch>,
+        (x_column, y_column): (Column<Advice>, Column<Advice>),
+        offset: usize,
+        point: &EccPoint<C>,
+    ) -> Result<EccPoint<C>, Error> {
+        let copy = self.assign_point(region, (x_column, y_column), offset, point.value)?;
+        region.constrain_equal(&self.perm, point.x, copy.x)?;
+        region.constrain_equal(&self.perm, point.y, copy.y)?;
+        Ok(copy)
+    }
+
+    /// Assigns the coordinates of `value` in the given columns at `offset`.
+    pub(crate) fn assign_point<C: CurveAffine, Ch: Chip<Field = C::Base>>(
+        &self,
+        region: &mut Region<'_, Ch>,
+        (x_column, y_column): (Column<Advice>, Column<Advice>),
+        offset: usize,
+        value: Option<C>,
+    ) -> Result<EccPoint<C>, Error> {
+        let xy = value.map(|value| coordinates(&value));
+        let x = region.assign_advice(
+            || "x",
+            x_column,
+            offset,
+            || xy.map(|(x, _)| x).ok_or(Error::SynthesisError),
+        )?;
+        let y = region.assign_advice(
+            || "y",
+            y_column,
+            offset,
+            || xy.map(|(_, y)| y).ok_or(Error::SynthesisError),
+        )?;
+        Ok(EccPoint { x, y, value })
+    }
+
+    /// Assigns the complete addition of `a` and `b` at `offset`, and returns the sum,
+    /// which is assigned at the next row.
+    fn assign_add<C: CurveAffine, Ch: Chip<Field = C::Base>>(
+        &self,
+        region: &mut Region<'_, Ch>,
+        offset: usize,
+        a: &EccPoint<C>,
+        b: &EccPoint<C>,
+    ) -> Result<EccPoint<C>, Error> {
+        let p = self.copy_point(region, (self.x_p, self.y_p), offset, a)?;
+        let q = self.copy_point(region, (self.x_q, self.y_q), offset, b)?;
+        self.assign_add_witnesses(region, offset, &p, &q)
+    }
+
+    /// Assigns the auxiliary witnesses for the complete addition of `p` and `q`, which
+    /// must already be assigned in the `x_p, y_p` and `x_q, y_q` columns at `offset`,
+    /// and returns the sum, which is assigned at the next row.
+    fn assign_add_witnesses<C: CurveAffine, Ch: Chip<Field = C::Base>>(
+        &self,
+        region: &mut Region<'_, Ch>,
+        offset: usize,
+        p: &EccPoint<C>,
+        q: &EccPoint<C>,
+    ) -> Result<EccPoint<C>, Error> {
+        self.s_add.enable(region, offset)?;
+
+        let witnesses = p.value.zip(q.value).map(|(p, q)| {
+            let (x_p, y_p) = coordinates(&p);
+            let (x_q, y_q) = coordinates(&q);
+
+            let lambda = if x_q != x_p {
+                (y_q - y_p) * inv0(x_q - x_p)
+            } else if y_p != C::Base::zero() {
+                (x_p.square() * C::Base::from_u64(3) + C::a()) * inv0(y_p.double())
+            } else {
+                C::Base::zero()
+            };
+            let delta = if x_q == x_p {
+                inv0(y_q + y_p)
+            } else {
+                C::Base::zero()
+            };
+            [lambda, inv0(x_q - x_p), inv0(x_p), inv0(x_q), delta]
+        });
+        for (i, (name, column)) in [
+            ("lambda", self.lambda),
+            ("alpha", self.alpha),
+            ("beta", self.beta),
+            ("gamma", self.gamma),
+            ("delta", self.delta),
+        ]
+        .iter()
+        .enumerate()
+        {
+            region.assign_advice(
+                || *name,
+                *column,
+                offset,
+                || witnesses.map(|w| w[i]).ok_or(Error::SynthesisError),
+            )?;
+        }
+
+        let r = p.value.zip(q.value).map(|(p, q)| (p + q).into());
+        self.assign_point(region, (self.x_p, self.y_p), offset + 1, r)
+    }
+}
+
+/// The instructions of [`EccChip`], for use by other chips that include an
+/// [`EccConfig`] in their own configuration.
+impl EccConfig {
+    /// Witnesses a point, constraining it to be on the curve.
+    pub(crate) fn witness_point<C: CurveAffine, Ch: Chip<Field = C::Base>>(
+        &self,
+        layouter: &mut impl Layouter<Ch>,
+        value: Option<C>,
+    ) -> Result<EccPoint<C>, Error> {
+        if value.map(|value| bool::from(value.is_identity())) == Some(true) {
+            return Err(Error::SynthesisError);
+        }
+        layouter.assign_region(
+            || "witness point",
+            |mut region| {
+                self.s_point.enable(&mut region, 0)?;
+                self.assign_point(&mut region, (self.x_p, self.y_p), 0, value)
+            },
+        )
+    }
+
+    /// Returns `a + b`, using complete addition.
+    pub(crate) fn add<C: CurveAffine, Ch: Chip<Field = C::Base>>(
+        &self,
+        layouter: &mut impl Layouter<Ch>,
+        a: &EccPoint<C>,
+        b: &EccPoint<C>,
+    ) -> Result<EccPoint<C>, Error> {
+        layouter.assign_region(
+            || "complete addition",
+            |mut region| self.assign_add(&mut region, 0, a, b),
+        )
+    }
+
+    /// Returns `[2] a`.
+    pub(crate) fn double<C: CurveAffine, Ch: Chip<Field = C::Base>>(
+        &self,
+        layouter: &mut impl Layouter<Ch>,
+        a: &EccPoint<C>,
+    ) -> Result<EccPoint<C>, Error> {
+        layouter.assign_region(
+            || "double",
+            |mut region| self.assign_add(&mut region, 0, a, a),
+        )
+    }
+
+    /// Returns `[scalar] base`.
+    pub(crate) fn mul_fixed<C: CurveAffine, Ch: Chip<Field = C::Base>>(
+        &self,
+        layouter: &mut impl Layouter<Ch>,
+        scalar: Option<C::Scalar>,
+        base: &EccFixedPoint<C>,
+    ) -> Result<EccPoint<C>, Error> {
+        layouter.assign_region(
+            || "fixed-base scalar multiplication",
+            |mut region| self.assign_mul_fixed(&mut region, 0, scalar, base),
+        )
+    }
+
+    /// Returns `[k] base`, where `k` is encoded by `bits` using endoscaling.
+    pub(crate) fn mul_endo<C: CurveAffine, Ch: Chip<Field = C::Base>>(
+        &self,
+        layouter: &mut impl Layouter<Ch>,
+        base: &EccPoint<C>,
+        bits: &[Option<bool>],
+    ) -> Result<EccPoint<C>, Error> {
+        layouter.assign_region(
+            || "endoscaling scalar multiplication",
+            |mut region| self.assign_mul_endo(&mut region, 0, base, bits),
+        )
+    }
+
+    /// Constrains `a` and `b` to be the same point.
+    pub(crate) fn constrain_equal<C: CurveAffine, Ch: Chip<Field = C::Base>>(
+        &self,
+        layouter: &mut impl Layouter<Ch>,
+        a: &EccPoint<C>,
+        b: &EccPoint<C>,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "constrain equal",
+            |mut region| {
+                region.constrain_equal(&self.perm, a.x, b.x)?;
+                region.constrain_equal(&self.perm, a.y, b.y)
+            },
+        )
+    }
+}
+
+/// A chip implementing elliptic curve arithmetic over the curve `C`, whose base field
+/// is the circuit field.
+///
+/// Witnessing a point uses one row, an addition or doubling uses two rows, and a
+/// fixed-base scalar multiplication uses one more row than there are 3-bit windows in a
+/// scalar (86 rows over Pallas and Vesta). An endoscaling multiplication by $n$ bits
+/// uses $n + 3$ rows.
+#[derive(Debug)]
+pub struct EccChip<C: CurveAffine> {
+    _marker: PhantomData<C>,
+}
+
+impl<C: CurveAffine> EccChip<C> {
+    /// Configures this chip for use in a circuit.
+    ///
+    /// The chip allocates 13 advice columns, and places six of them in a permutation,
+    /// along with 16 fixed columns for fixed-base scalar multiplication.
+    pub fn configure(meta: &mut ConstraintSystem<C::Base>) -> EccConfig {
+        let x_p = meta.advice_column();
+        let y_p = meta.advice_column();
+        let x_q = meta.advice_column();
+        let y_q = meta.advice_column();
+        let lambda = meta.advice_column();
+        let alpha = meta.advice_column();
+        let beta = meta.advice_column();
+        let gamma = meta.advice_column();
+        let delta = meta.advice_column();
+        let window = meta.advice_column();
+        let negate = meta.advice_column();
+        let x_base = meta.advice_column();
+        let y_base = meta.advice_column();
+        let mut lagrange_coeffs = || -> [Column<Fixed>; mul_fixed::H] {
+            (0..mul_fixed::H)
+                .map(|_| meta.fixed_column())
+                .collect::<Vec<_>>()
+                .try_into()
+                .unwrap()
+        };
+        let lagrange_coeffs = [lagrange_coeffs(), lagrange_coeffs()];
+
+        let s_point = meta.selector();
+        let s_add = meta.selector();
+        let s_mul_fixed = meta.selector();
+        let s_identity = meta.selector();
+        let s_phi = meta.selector();
+        let s_endo = meta.selector();
+
+        let perm = Permutation::new(
+            meta,
+            &[
+                x_p.into(),
+                y_p.into(),
+                x_q.into(),
+                y_q.into(),
+                x_base.into(),
+                y_base.into(),
+            ],
+        );
+
+        // y^2 = x^3 + a x + b
+        meta.create_gate("point on curve", |meta| {
+            let x = meta.query_advice(x_p, Rotation::cur());
+            let y = meta.query_advice(y_p, Rotation::cur());
+            let s_point = meta.query_selector(s_point, Rotation::cur());
+
+            s_point
+                * (y.clone() * y
+                    - x.clone() * x.clone() * x.clone()
+                    - x * C::a()
+                    - Expression::Constant(C::b()))
+        });
+
+        let one = || Expression::Constant(C::Base::one());
+        let query = |meta: &mut ConstraintSystem<C::Base>| {
+            (
+                meta.query_advice(x_p, Rotation::cur()),
+                meta.query_advice(y_p, Rotation::cur()),
+                meta.query_advice(x_q, Rotation::cur()),
+                meta.query_advice(y_q, Rotation::cur()),
+                meta.query_advice(x_p, Rotation::next()),
+                meta.query_advice(y_p, Rotation::next()),
+                meta.query_advice(lambda, Rotation::cur()),
+                meta.query_selector(s_add, Rotation::cur()),
+            )
+        };
+
+        // If x_q != x_p, lambda is the slope (y_q - y_p) / (x_q - x_p).
+        meta.create_gate("complete addition", |meta| {
+            let (x_p, y_p, x_q, y_q, _, _, lambda, s_add) = query(meta);
+            let dx = x_q - x_p;
+
+            s_add * dx.clone() * (dx * lambda - (y_q - y_p))
+        });
+        // If x_q = x_p, then alpha = 0 and lambda is the tangent slope at P.
+        meta.create_gate("complete addition", |meta| {
+            let (x_p, y_p, x_q, _, _, _, lambda, s_add) = query(meta);
+            let alpha = meta.query_advice(alpha, Rotation::cur());
+
+            s_add
+                * (one() - (x_q - x_p.clone()) * alpha)
+                * (y_p * lambda * C::Base::from_u64(2)
+                    - x_p.clone() * x_p * C::Base::from_u64(3)
+                    - Expression::Constant(C::a()))
+        });
+        // If neither point is the identity, and either x_q != x_p or y_q = y_p, then R
+        // is given by the usual formulae.
+        for nonzero in 0..2 {
+            meta.create_gate("complete addition", |meta| {
+                let (x_p, y_p, x_q, y_q, x_r, _, lambda, s_add) = query(meta);
+                let nonzero = if nonzero == 0 {
+                    x_q.clone() - x_p.clone()
+                } else {
+                    y_q + y_p
+                };
+
+                s_add
+                    * x_p.clone()
+                    * x_q.clone()
+                    * nonzero
+                    * (lambda.clone() * lambda - x_p - x_q - x_r)
+            });
+            meta.create_gate("complete addition", |meta| {
+                let (x_p, y_p, x_q, y_q, x_r, y_r, lambda, s_add) = query(meta);
+                let nonzero = if nonzero == 0 {
+                    x_q.clone() - x_p.clone()
+                } else {
+                    y_q + y_p.clone()
+                };
+
+                s_add * x_p.clone() * x_q * nonzero * (lambda * (x_p - x_r) - y_p - y_r)
+            });
+        }
+        // If P is the identity, then R = Q.
+        for (r_column, q_column) in [(x_p, x_q), (y_p, y_q)].iter().copied() {
+            meta.create_gate("complete addition", |meta| {
+                let x = meta.query_advice(x_p, Rotation::cur());
+                let beta = meta.query_advice(beta, Rotation::cur());
+                let q = meta.query_advice(q_column, Rotation::cur());
+                let r = meta.query_advice(r_column, Rotation::next());
+                let s_add = meta.query_selector(s_add, Rotation::cur());
+
+                s_add * (one() - x * beta) * (r - q)
+            });
+        }
+        // If Q is the identity, then R = P.
+        for p_column in [x_p, y_p].iter().copied() {
+            meta.create_gate("complete addition", |meta| {
+                let x = meta.query_advice(x_q, Rotation::cur());
+                let gamma = meta.query_advice(gamma, Rotation::cur());
+                let p = meta.query_advice(p_column, Rotation::cur());
+                let r = meta.query_advice(p_column, Rotation::next());
+                let s_add = meta.query_selector(s_add, Rotation::cur());
+
+                s_add * (one() - x * gamma) * (r - p)
+            });
+        }
+        // If P = -Q, then R is the identity.
+        for p_column in [x_p, y_p].iter().copied() {
+            meta.create_gate("complete addition", |meta| {
+                let (x_p, y_p, x_q, y_q, _, _, _, s_add) = query(meta);
+                let alpha = meta.query_advice(alpha, Rotation::cur());
+                let delta = meta.query_advice(delta, Rotation::cur());
+                let r = meta.query_advice(p_column, Rotation::next());
+
+                s_add * (one() - (x_q - x_p) * alpha - (y_q + y_p) * delta) * r
+            });
+        }
+
+        let config = EccConfig {
+            x_p,
+            y_p,
+            x_q,
+            y_q,
+            lambda,
+            alpha,
+            beta,
+            gamma,
+            delta,
+            window,
+            negate,
+            x_base,
+            y_base,
+            lagrange_coeffs,
+            s_point,
+            s_add,
+            s_mul_fixed,
+            s_identity,
+            s_phi,
+            s_endo,
+            perm,
+        };
+        config.create_mul_fixed_gates::<C::Base>(meta);
+        config.create_mul_endo_gates::<C::Base>(meta);
+        config
+    }
+}
+
+impl<C: CurveAffine> Chip for EccChip<C> {
+    type Config = EccConfig;
+    type Loaded = ();
+    type Field = C::Base;
+
+    fn load(_layouter: &mut impl Layouter<Self>) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<C: CurveAffine> EccInstructions<C> for EccChip<C> {
+    type Point = EccPoint<C>;
+    type X = EccX<C::Base>;
+    type FixedPoint = EccFixedPoint<C>;
+
+    fn fixed_point(base: C) -> Self::FixedPoint {
+        EccFixedPoint::new(base)
+    }
+
+    fn witness_point(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<C>,
+    ) -> Result<Self::Point, Error> {
+        layouter.config().clone().witness_point(layouter, value)
+    }
+
+    fn extract_p(point: &Self::Point) -> Self::X {
+        point.extract_p()
+    }
+
+    fn add(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Point,
+        b: &Self::Point,
+    ) -> Result<Self::Point, Error> {
+        layouter.config().clone().add(layouter, a, b)
+    }
+
+    fn double(layouter: &mut impl Layouter<Self>, a: &Self::Point) -> Result<Self::Point, Error> {
+        layouter.config().clone().double(layouter, a)
+    }
+
+    fn mul_fixed(
+        layouter: &mut impl Layouter<Self>,
+        scalar: Option<C::Scalar>,
+        base: &Self::FixedPoint,
+    ) -> Result<Self::Point, Error> {
+        layouter.config().clone().mul_fixed(layouter, scalar, base)
+    }
+
+    fn mul_endo(
+        layouter: &mut impl Layouter<Self>,
+        base: &Self::Point,
+        bits: &[Option<bool>],
+    ) -> Result<Self::Point, Error> {
+        layouter.config().clone().mul_endo(layouter, base, bits)
+    }
+
+    fn constrain_equal(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Point,
+        b: &Self::Point,
+    ) -> Result<(), Error> {
+        layouter.config().clone().constrain_equal(layouter, a, b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use group::{prime::PrimeCurveAffine, Curve};
+
+    use super::{EccChip, EccConfig};
+    use crate::{
+        arithmetic::{CurveAffine, FieldExt},
+        circuit::{layouter, Layouter},
+        dev::MockProver,
+        gadget::ecc::Point,
+        pasta::{pallas, vesta},
+        plonk::{Assignment, Circuit, ConstraintSystem, Error},
+    };
+
+    struct EccCircuit<C: CurveAffine> {
+        p: Option<C>,
+        q: Option<C>,
+        sum: Option<C>,
+    }
+
+    impl<C: CurveAffine> Circuit<C::Base> for EccCircuit<C> {
+        type Config = EccConfig;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<C::Base>) -> EccConfig {
+            EccChip::<C>::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<C::Base>,
+            config: EccConfig,
+        ) -> Result<(), Error> {
+            let mut layouter = layouter::SingleChip::<EccChip<C>, _>::new(cs, config)?;
+
+            let p = Point::new(layouter.namespace(|| "P"), self.p)?;
+            let q = Point::new(layouter.namespace(|| "Q"), self.q)?;
+            let neg_p = Point::new(layouter.namespace(|| "-P"), self.p.map(|p| -p))?;
+            let sum = Point::new(layouter.namespace(|| "P + Q"), self.sum)?;
+
+            // P + Q
+            let p_plus_q = p.add(layouter.namespace(|| "P + Q"), &q)?;
+            p_plus_q.constrain_equal(layouter.namespace(|| "P + Q"), &sum)?;
+
+            // Q + P
+            let q_plus_p = q.add(layouter.namespace(|| "Q + P"), &p)?;
+            q_plus_p.constrain_equal(layouter.namespace(|| "Q + P"), &sum)?;
+
+            // [2] P = P + P
+            let double = p.double(layouter.namespace(|| "[2] P"))?;
+            let p_plus_p = p.add(layouter.namespace(|| "P + P"), &p)?;
+            double.constrain_equal(layouter.namespace(|| "[2] P"), &p_plus_p)?;
+
+            // P + (-P) = O
+            let identity = p.add(layouter.namespace(|| "P + (-P)"), &neg_p)?;
+
+            // O + Q = Q + O = Q
+            let left = identity.add(layouter.namespace(|| "O + Q"), &q)?;
+            left.constrain_equal(layouter.namespace(|| "O + Q"), &q)?;
+            let right = q.add(layouter.namespace(|| "Q + O"), &identity)?;
+            right.constrain_equal(layouter.namespace(|| "Q + O"), &q)?;
+
+            // O + O = [2] O = O
+            let identity_sum = identity.add(layouter.namespace(|| "O + O"), &identity)?;
+            identity_sum.constrain_equal(layouter.namespace(|| "O + O"), &identity)?;
+            let identity_double = identity.double(layouter.namespace(|| "[2] O"))?;
+            identity_double.constrain_equal(layouter.namespace(|| "[2] O"), &identity)
+        }
+    }
+
+    fn ecc_circuit<C: CurveAffine>() {
+        let generator = C::generator();
+        let p: C = (generator * C::Scalar::from_u64(5)).to_affine();
+        let q: C = (generator * C::Scalar::from_u64(12)).to_affine();
+
+        let mut circuit = EccCircuit {
+            p: Some(p),
+            q: Some(q),
+            sum: Some((p + q).into()),
+        };
+        let prover = MockProver::run(5, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        circuit.sum = Some(q);
+        let prover = MockProver::run(5, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn ecc_pallas() {
+        ecc_circuit::<pallas::Affine>();
+    }
+
+    #[test]
+    fn ecc_vesta() {
+        ecc_circuit::<vesta::Affine>();
+    }
+
+    #[test]
+    fn witness_identity() {
+        let circuit = EccCircuit::<pallas::Affine> {
+            p: Some(pallas::Affine::identity()),
+            q: Some(pallas::Affine::generator()),
+            sum: Some(pallas::Affine::generator()),
+        };
+        assert!(matches!(
+            MockProver::run(5, &circuit, vec![]),
+            Err(Error::SynthesisError)
+        ));
+    }
+}
@@ -0,0 +1,224 @@
+//! Variable-base scalar multiplication with endoscaling.
+//!
+//! The scalar is given as a bit string, which is mapped to a scalar as described in the
+//! [`endoscale`](crate::primitives::endoscale) module. The accumulator is initialized to
+//! $[2] (\phi(P) + P)$, and then each pair of bits, from the most significant pair,
+//! selects $S \in \{P, -P, \phi(P), -\phi(P)\}$ and updates the accumulator to
+//! $(A + S) + A$. Each round therefore uses two complete additions for two bits of the
+//! scalar, instead of the two additions per bit of double-and-add.
+//!
+//! # Layout
+//!
+//! The initialization uses two rows: the first holds $P$ in `x_p, y_p` and $\phi(P)$
+//! in `x_q, y_q`, and the second holds their sum in both `x_p, y_p` and `x_q, y_q`.
+//!
+//! Each round then uses two rows. The first holds the accumulator $A$ in `x_p, y_p`, a
+//! copy of $P$ in `x_base, y_base`, the pair of bits in the `window` and `negate`
+//! columns, and the selected point $S$ in `x_q, y_q`. The second holds $A + S$ in
+//! `x_p, y_p` and a copy of $A$ in `x_q, y_q`, and the following row holds the new
+//! accumulator. A multiplication by $n$ bits thus uses $n + 3$ rows.
+
+use group::Curve;
+
+use super::{EccConfig, EccPoint};
+use crate::{
+    arithmetic::{CurveAffine, FieldExt},
+    circuit::{Chip, Region},
+    plonk::{ConstraintSystem, Error, Expression},
+    poly::Rotation,
+};
+
+impl EccConfig {
+    pub(super) fn create_mul_endo_gates<F: FieldExt>(&self, meta: &mut ConstraintSystem<F>) {
+        let one = || Expression::Constant(F::one());
+
+        // phi(P) = (zeta x_P, y_P)
+        meta.create_gate("endomorphism", |meta| {
+            let x_p = meta.query_advice(self.x_p, Rotation::cur());
+            let x_q = meta.query_advice(self.x_q, Rotation::cur());
+            let s_phi = meta.query_selector(self.s_phi, Rotation::cur());
+
+            s_phi * (x_q - x_p * F::ZETA)
+        });
+        meta.create_gate("endomorphism", |meta| {
+            let y_p = meta.query_advice(self.y_p, Rotation::cur());
+            let y_q = meta.query_advice(self.y_q, Rotation::cur());
+            let s_phi = meta.query_selector(self.s_phi, Rotation::cur());
+
+            s_phi * (y_q - y_p)
+        });
+
+        // Both bits are boolean.
+        for column in [self.window, self.negate].iter() {
+            meta.create_gate("endoscaling bit", |meta| {
+                let bit = meta.query_advice(*column, Rotation::cur());
+                let s_endo = meta.query_selector(self.s_endo, Rotation::cur());
+
+                s_endo * bit.clone() * (one() - bit)
+            });
+        }
+
+        // x_S = x_P if the endomorphism bit is unset, and zeta x_P otherwise.
+        meta.create_gate("endoscaling", |meta| {
+            let x_base = meta.query_advice(self.x_base, Rotation::cur());
+            let x_q = meta.query_advice(self.x_q, Rotation::cur());
+            let endo = meta.query_advice(self.window, Rotation::cur());
+            let s_endo = meta.query_selector(self.s_endo, Rotation::cur());
+
+            s_endo * (x_q - x_base * (one() + endo * (F::ZETA - F::one())))
+        });
+        // y_S = y_P if the negation bit is unset, and -y_P otherwise.
+        meta.create_gate("endoscaling", |meta| {
+            let y_base = meta.query_advice(self.y_base, Rotation::cur());
+            let y_q = meta.query_advice(self.y_q, Rotation::cur());
+            let negate = meta.query_advice(self.negate, Rotation::cur());
+            let s_endo = meta.query_selector(self.s_endo, Rotation::cur());
+
+            s_endo * (y_q - y_base * (one() - negate * F::from_u64(2)))
+        });
+    }
+
+    /// Assigns the endoscaling multiplication of `base` by `bits` at `offset`.
+    ///
+    /// The round for the `i`-th pair of bits from the most significant is at row
+    /// `offset + 2 + 2 * i`, so other chips can constrain the bits in the `window` and
+    /// `negate` columns.
+    pub(crate) fn assign_mul_endo<C: CurveAffine, Ch: Chip<Field = C::Base>>(
+        &self,
+        region: &mut Region<'_, Ch>,
+        offset: usize,
+        base: &EccPoint<C>,
+        bits: &[Option<bool>],
+    ) -> Result<EccPoint<C>, Error> {
+        assert_eq!(bits.len() % 2, 0);
+
+        let phi = |point: C| (point.to_curve() * C::Scalar::ZETA).to_affine();
+        let select = |endo: Option<bool>, negate: Option<bool>| {
+            base.value
+                .zip(endo)
+                .zip(negate)
+                .map(|((point, endo), negate)| {
+                    let point = if negate { -point } else { point };
+                    if endo {
+                        phi(point)
+                    } else {
+                        point
+                    }
+                })
+        };
+
+        // A = [2] (phi(P) + P)
+        self.s_phi.enable(region, offset)?;
+        let p = self.copy_point(region, (self.x_p, self.y_p), offset, base)?;
+        let phi_p = self.assign_point(region, (self.x_q, self.y_q), offset, base.value.map(phi))?;
+        let sum = self.assign_add_witnesses(region, offset, &p, &phi_p)?;
+        let copy = self.copy_point(region, (self.x_q, self.y_q), offset + 1, &sum)?;
+        let mut acc = self.assign_add_witnesses(region, offset + 1, &sum, &copy)?;
+
+        for (i, pair) in bits.chunks(2).rev().enumerate() {
+            let row = offset + 2 + 2 * i;
+            self.s_endo.enable(region, row)?;
+
+            self.copy_point(region, (self.x_base, self.y_base), row, base)?;
+            for (name, column, bit) in [
+                ("endo", self.window, pair[0]),
+                ("negate", self.negate, pair[1]),
+            ]
+            .iter()
+            {
+                region.assign_advice(
+                    || *name,
+                    *column,
+                    row,
+                    || {
+                        bit.map(|bit| C::Base::from_u64(bit as u64))
+                            .ok_or(Error::SynthesisError)
+                    },
+                )?;
+            }
+            let s =
+                self.assign_point(region, (self.x_q, self.y_q), row, select(pair[0], pair[1]))?;
+
+            // A <- (A + S) + A
+            let sum = self.assign_add_witnesses(region, row, &acc, &s)?;
+            let copy = self.copy_point(region, (self.x_q, self.y_q), row + 1, &acc)?;
+            acc = self.assign_add_witnesses(region, row + 1, &sum, &copy)?;
+        }
+
+        Ok(acc)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use group::Curve;
+
+    use super::super::{EccChip, EccConfig};
+    use crate::{
+        arithmetic::{CurveAffine, FieldExt},
+        circuit::{layouter, Layouter},
+        dev::MockProver,
+        gadget::ecc::Point,
+        pasta::{pallas, vesta},
+        plonk::{Assignment, Circuit, ConstraintSystem, Error},
+        primitives::endoscale::endoscale_scalar,
+    };
+
+    struct MulEndoCircuit<C: CurveAffine> {
+        base: Option<C>,
+        bits: Vec<Option<bool>>,
+        product: Option<C>,
+    }
+
+    impl<C: CurveAffine> Circuit<C::Base> for MulEndoCircuit<C> {
+        type Config = EccConfig;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<C::Base>) -> EccConfig {
+            EccChip::<C>::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<C::Base>,
+            config: EccConfig,
+        ) -> Result<(), Error> {
+            let mut layouter = layouter::SingleChip::<EccChip<C>, _>::new(cs, config)?;
+
+            let base = Point::new(layouter.namespace(|| "P"), self.base)?;
+            let expected = Point::new(layouter.namespace(|| "expected"), self.product)?;
+            let result = base.mul_endo(layouter.namespace(|| "[k] P"), &self.bits)?;
+            result.constrain_equal(layouter.namespace(|| "[k] P"), &expected)
+        }
+    }
+
+    fn mul_endo<C: CurveAffine>() {
+        let challenge = 0x0123_4567_89ab_cdef_fedc_ba98_7654_3210u128;
+        let bits: Vec<_> = (0..128).map(|i| (challenge >> i) & 1 == 1).collect();
+        let base = (C::generator() * C::Scalar::from_u64(7)).to_affine();
+        let product = (base * endoscale_scalar::<C::Scalar>(&bits)).to_affine();
+
+        let mut circuit = MulEndoCircuit {
+            base: Some(base),
+            bits: bits.into_iter().map(Some).collect(),
+            product: Some(product),
+        };
+        let prover = MockProver::run(8, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        circuit.bits[0] = circuit.bits[0].map(|bit| !bit);
+        let prover = MockProver::run(8, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn mul_endo_pallas() {
+        mul_endo::<pallas::Affine>();
+    }
+
+    #[test]
+    fn mul_endo_vesta() {
+        mul_endo::<vesta::Affine>();
+    }
+}
@@ -0,0 +1,299 @@
+//! Fixed-base scalar multiplication.
+//!
+//! The scalar is split into 3-bit windows $k = \sum_i k_i \cdot 8^i$, and the product
+//! $[k] B$ is computed as the sum of the window points $[k_i \cdot 8^i] B$. For each
+//! window, the coordinates of the eight possible window points (with the identity as
+//! $(0, 0)$) are interpolated by a pair of degree-7 polynomials in $k_i$, whose
+//! coefficients are assigned in fixed columns. The window point is then constrained to
+//! the evaluations of these polynomials at the witnessed $k_i$, which is range-checked
+//! to $[0, 8)$.
+//!
+//! # Layout
+//!
+//! A multiplication uses one row per window, and one more row for the result. Row $i$
+//! holds the running sum in `x_p, y_p` (starting from the identity), $k_i$ in the
+//! `window` column, and the window point in `x_q, y_q`, which are added by the complete
+//! addition gate into the next row.
+
+use std::convert::TryInto;
+
+use ff::PrimeField;
+use group::{Curve, Group};
+
+use super::{coordinates, EccConfig, EccPoint};
+use crate::{
+    arithmetic::{lagrange_interpolate, CurveAffine, FieldExt},
+    circuit::{Chip, Region},
+    plonk::{ConstraintSystem, Error, Expression},
+    poly::Rotation,
+};
+
+/// The number of bits in a window.
+pub const WINDOW_BITS: usize = 3;
+
+/// The number of points in the table for each window.
+pub const H: usize = 1 << WINDOW_BITS;
+
+/// Returns the number of windows in a scalar of the curve `C`.
+pub fn num_windows<C: CurveAffine>() -> usize {
+    (0..C::Scalar::NUM_BITS as usize)
+        .step_by(WINDOW_BITS)
+        .count()
+}
+
+/// Returns the little-endian `WINDOW_BITS`-bit windows of `scalar`.
+fn decompose<C: CurveAffine>(scalar: &C::Scalar) -> Vec<u8> {
+    let bytes = scalar.to_bytes();
+    let bit = |i: usize| bytes.get(i / 8).map_or(0, |byte| (byte >> (i % 8)) & 1);
+
+    (0..num_windows::<C>())
+        .map(|w| (0..WINDOW_BITS).fold(0, |acc, i| acc | bit(w * WINDOW_BITS + i) << i))
+        .collect()
+}
+
+/// A fixed base, with the window tables used to multiply by it.
+#[derive(Clone, Debug)]
+pub struct EccFixedPoint<C: CurveAffine> {
+    base: C,
+    /// For each window, the multiples $[k \cdot 8^i] B$ of the base for $k \in [0, 8)$.
+    windows: Vec<[C; H]>,
+    /// For each window, the coefficients of the polynomials interpolating the $x$ and
+    /// $y$ coordinates of the window points.
+    lagrange_coeffs: Vec<[[C::Base; H]; 2]>,
+}
+
+impl<C: CurveAffine> EccFixedPoint<C> {
+    pub(super) fn new(base: C) -> Self {
+        let mut window_base = base.to_curve();
+        let windows: Vec<[C; H]> = (0..num_windows::<C>())
+            .map(|_| {
+                let mut multiple = C::Curve::identity();
+                let points = (0..H)
+                    .map(|_| {
+                        let point = multiple.to_affine();
+                        multiple += window_base;
+                        point
+                    })
+                    .collect::<Vec<_>>();
+                window_base = multiple;
+                points.try_into().unwrap()
+            })
+            .collect();
+
+        let points: Vec<_> = (0..H).map(|k| C::Base::from_u64(k as u64)).collect();
+        let lagrange_coeffs = windows
+            .iter()
+            .map(|window| {
+                let (x, y): (Vec<_>, Vec<_>) = window.iter().map(coordinates).unzip();
+                [
+                    lagrange_interpolate(&points, &x).try_into().unwrap(),
+                    lagrange_interpolate(&points, &y).try_into().unwrap(),
+                ]
+            })
+            .collect();
+
+        EccFixedPoint {
+            base,
+            windows,
+            lagrange_coeffs,
+        }
+    }
+
+    /// The fixed base.
+    pub fn base(&self) -> C {
+        self.base
+    }
+}
+
+impl EccConfig {
+    pub(super) fn create_mul_fixed_gates<F: FieldExt>(&self, meta: &mut ConstraintSystem<F>) {
+        // The window point is the evaluation of the interpolating polynomials at k.
+        for (coeffs, column) in self.lagrange_coeffs.iter().zip([self.x_q, self.y_q].iter()) {
+            meta.create_gate("fixed-base window", |meta| {
+                let k = meta.query_advice(self.window, Rotation::cur());
+                let coordinate = meta.query_advice(*column, Rotation::cur());
+                let s_mul_fixed = meta.query_selector(self.s_mul_fixed, Rotation::cur());
+
+                // Horner's rule, from the highest coefficient.
+                let evaluation = coeffs
+                    .iter()
+                    .rev()
+                    .fold(Expression::Constant(F::zero()), |acc, coeff| {
+                        acc * k.clone() + meta.query_fixed(*coeff, Rotation::cur())
+                    });
+                s_mul_fixed * (evaluation - coordinate)
+            });
+        }
+        meta.create_gate("fixed-base window range", |meta| {
+            let k = meta.query_advice(self.window, Rotation::cur());
+            let s_mul_fixed = meta.query_selector(self.s_mul_fixed, Rotation::cur());
+
+            let range = (0..H).fold(Expression::Constant(F::one()), |acc, i| {
+                acc * (k.clone() - Expression::Constant(F::from_u64(i as u64)))
+            });
+            s_mul_fixed * range
+        });
+
+        // The running sum starts from the identity.
+        for column in [self.x_p, self.y_p].iter() {
+            meta.create_gate("identity", |meta| {
+                let coordinate = meta.query_advice(*column, Rotation::cur());
+                let s_identity = meta.query_selector(self.s_identity, Rotation::cur());
+
+                s_identity * coordinate
+            });
+        }
+    }
+
+    /// Assigns the multiplication of `base` by `scalar` at `offset`.
+    pub(super) fn assign_mul_fixed<C: CurveAffine, Ch: Chip<Field = C::Base>>(
+        &self,
+        region: &mut Region<'_, Ch>,
+        offset: usize,
+        scalar: Option<C::Scalar>,
+        base: &EccFixedPoint<C>,
+    ) -> Result<EccPoint<C>, Error> {
+        self.s_identity.enable(region, offset)?;
+        let mut acc =
+            self.assign_point(region, (self.x_p, self.y_p), offset, Some(C::identity()))?;
+
+        let windows = scalar.map(|scalar| decompose::<C>(&scalar));
+        for (i, (points, coeffs)) in base
+            .windows
+            .iter()
+            .zip(base.lagrange_coeffs.iter())
+            .enumerate()
+        {
+            let row = offset + i;
+            self.s_mul_fixed.enable(region, row)?;
+            for (columns, coeffs) in self.lagrange_coeffs.iter().zip(coeffs.iter()) {
+                for (j, (column, coeff)) in columns.iter().zip(coeffs.iter()).enumerate() {
+                    region.assign_fixed(
+                        || format!("lagrange coefficient {}", j),
+                        *column,
+                        row,
+                        || Ok(*coeff),
+                    )?;
+                }
+            }
+
+            let k = windows.as_ref().map(|windows| windows[i]);
+            region.assign_advice(
+                || format!("k_{}", i),
+                self.window,
+                row,
+                || {
+                    k.map(|k| C::Base::from_u64(k as u64))
+                        .ok_or(Error::SynthesisError)
+                },
+            )?;
+            let window_point = self.assign_point(
+                region,
+                (self.x_q, self.y_q),
+                row,
+                k.map(|k| points[k as usize]),
+            )?;
+
+            acc = self.assign_add_witnesses(region, row, &acc, &window_point)?;
+        }
+
+        Ok(acc)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ff::Field;
+    use group::Curve;
+
+    use super::super::{EccChip, EccConfig};
+    use crate::{
+        arithmetic::{CurveAffine, FieldExt},
+        circuit::{layouter, Layouter},
+        dev::MockProver,
+        gadget::ecc::{FixedPoint, Point},
+        pasta::{pallas, vesta},
+        plonk::{Assignment, Circuit, ConstraintSystem, Error},
+    };
+
+    struct MulFixedCircuit<C: CurveAffine> {
+        scalars: Vec<Option<C::Scalar>>,
+        products: Vec<Option<C>>,
+    }
+
+    impl<C: CurveAffine> Circuit<C::Base> for MulFixedCircuit<C> {
+        type Config = EccConfig;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<C::Base>) -> EccConfig {
+            EccChip::<C>::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<C::Base>,
+            config: EccConfig,
+        ) -> Result<(), Error> {
+            let mut layouter = layouter::SingleChip::<EccChip<C>, _>::new(cs, config)?;
+            let generator = FixedPoint::<C, EccChip<C>>::new(C::generator());
+
+            let mut identity = None;
+            for (scalar, product) in self.scalars.iter().zip(self.products.iter()) {
+                let result = generator.mul(layouter.namespace(|| "[k] G"), *scalar)?;
+                match product {
+                    Some(product) if bool::from(product.is_identity()) => {
+                        identity = Some(result);
+                    }
+                    _ => {
+                        let expected = Point::new(layouter.namespace(|| "expected"), *product)?;
+                        result.constrain_equal(layouter.namespace(|| "[k] G"), &expected)?;
+                    }
+                }
+            }
+
+            // The product by zero is the identity, which cannot be witnessed; check that
+            // it is the neutral element instead.
+            if let Some(identity) = identity {
+                let g = Point::new(layouter.namespace(|| "G"), Some(C::generator()))?;
+                let sum = g.add(layouter.namespace(|| "G + O"), &identity)?;
+                sum.constrain_equal(layouter.namespace(|| "G + O"), &g)?;
+            }
+            Ok(())
+        }
+    }
+
+    fn mul_fixed<C: CurveAffine>() {
+        let scalars = vec![
+            C::Scalar::zero(),
+            C::Scalar::one(),
+            C::Scalar::from_u64(0x0123_4567_89ab_cdef).square(),
+            -C::Scalar::one(),
+        ];
+        let products = scalars
+            .iter()
+            .map(|scalar| Some((C::generator() * *scalar).to_affine()))
+            .collect();
+
+        let mut circuit = MulFixedCircuit {
+            scalars: scalars.into_iter().map(Some).collect(),
+            products,
+        };
+        let prover = MockProver::run(9, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        circuit.scalars[1] = Some(C::Scalar::from_u64(2));
+        let prover = MockProver::run(9, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn mul_fixed_pallas() {
+        mul_fixed::<pallas::Affine>();
+    }
+
+    #[test]
+    fn mul_fixed_vesta() {
+        mul_fixed::<vesta::Affine>();
+    }
+}
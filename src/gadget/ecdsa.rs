@@ -0,0 +1,171 @@
+//! Gadget and chips for verifying ECDSA signatures over secp256k1.
+//!
+//! The secp256k1 fields are not the circuit field, so their elements are represented
+//! as several native limbs, and each field operation is checked as an integer relation
+//! between limbs. Verifying a signature $(r, s)$ on a message hash $z$ under a public
+//! key $Q$ then follows the usual algorithm: compute $w = s^{-1}$, $u_1 = z w$ and
+//! $u_2 = r w \pmod{n}$, and check that the $x$-coordinate of $\[u_1\] G + \[u_2\] Q$ is
+//! congruent to $r$ modulo $n$.
+//!
+//! The values being verified are computed outside of a circuit by
+//! [`crate::primitives::secp256k1`].
+
+use std::fmt;
+
+use crate::{
+    circuit::{Chip, Layouter},
+    plonk::Error,
+    primitives::secp256k1,
+};
+
+mod chip;
+pub use chip::{AssignedElement, AssignedPoint, EcdsaChip, EcdsaConfig};
+
+/// The set of circuit instructions required to use the [`PublicKey`], [`Scalar`] and
+/// [`Signature`] gadgets.
+pub trait EcdsaInstructions: Chip {
+    /// Variable representing an integer congruent to an element of the scalar field of
+    /// secp256k1.
+    type Scalar: Clone + fmt::Debug;
+
+    /// Variable representing a secp256k1 public key.
+    type PublicKey: Clone + fmt::Debug;
+
+    /// Witnesses an integer less than $2^{256}$, such as a message hash.
+    fn witness_scalar(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<secp256k1::Scalar>,
+    ) -> Result<Self::Scalar, Error>;
+
+    /// Witnesses a public key, constraining it to be on the curve.
+    ///
+    /// Returns an error if `value` is the identity.
+    fn witness_public_key(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<secp256k1::Affine>,
+    ) -> Result<Self::PublicKey, Error>;
+
+    /// Constrains $(r, s)$ to be a valid signature of `message_hash` by `public_key`.
+    fn verify(
+        layouter: &mut impl Layouter<Self>,
+        public_key: &Self::PublicKey,
+        message_hash: &Self::Scalar,
+        r: &Self::Scalar,
+        s: &Self::Scalar,
+    ) -> Result<(), Error>;
+}
+
+/// An integer representing an element of the scalar field of secp256k1.
+#[derive(Debug)]
+pub struct Scalar<EcdsaChip: EcdsaInstructions> {
+    inner: EcdsaChip::Scalar,
+}
+
+impl<EcdsaChip: EcdsaInstructions> Scalar<EcdsaChip> {
+    /// Witnesses a scalar.
+    pub fn new(
+        mut layouter: impl Layouter<EcdsaChip>,
+        value: Option<secp256k1::Scalar>,
+    ) -> Result<Self, Error> {
+        EcdsaChip::witness_scalar(&mut layouter, value).map(|inner| Scalar { inner })
+    }
+
+    /// Returns the chip-specific representation of this scalar.
+    pub fn inner(&self) -> &EcdsaChip::Scalar {
+        &self.inner
+    }
+}
+
+impl<EcdsaChip: EcdsaInstructions> Clone for Scalar<EcdsaChip> {
+    fn clone(&self) -> Self {
+        Scalar {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+/// An ECDSA signature.
+#[derive(Debug)]
+pub struct Signature<EcdsaChip: EcdsaInstructions> {
+    r: Scalar<EcdsaChip>,
+    s: Scalar<EcdsaChip>,
+}
+
+impl<EcdsaChip: EcdsaInstructions> Signature<EcdsaChip> {
+    /// Witnesses a signature.
+    ///
+    /// The components are only constrained to be canonical by
+    /// [`PublicKey::verify`].
+    pub fn new(
+        mut layouter: impl Layouter<EcdsaChip>,
+        value: Option<secp256k1::Signature>,
+    ) -> Result<Self, Error> {
+        let r = Scalar::new(layouter.namespace(|| "r"), value.map(|sig| sig.r))?;
+        let s = Scalar::new(layouter.namespace(|| "s"), value.map(|sig| sig.s))?;
+        Ok(Signature { r, s })
+    }
+
+    /// The $x$-coordinate of the nonce point, reduced modulo $n$.
+    pub fn r(&self) -> &Scalar<EcdsaChip> {
+        &self.r
+    }
+
+    /// The proof of knowledge of the secret key.
+    pub fn s(&self) -> &Scalar<EcdsaChip> {
+        &self.s
+    }
+}
+
+impl<EcdsaChip: EcdsaInstructions> Clone for Signature<EcdsaChip> {
+    fn clone(&self) -> Self {
+        Signature {
+            r: self.r.clone(),
+            s: self.s.clone(),
+        }
+    }
+}
+
+/// A secp256k1 public key.
+#[derive(Debug)]
+pub struct PublicKey<EcdsaChip: EcdsaInstructions> {
+    inner: EcdsaChip::PublicKey,
+}
+
+impl<EcdsaChip: EcdsaInstructions> PublicKey<EcdsaChip> {
+    /// Witnesses a public key.
+    pub fn new(
+        mut layouter: impl Layouter<EcdsaChip>,
+        value: Option<secp256k1::Affine>,
+    ) -> Result<Self, Error> {
+        EcdsaChip::witness_public_key(&mut layouter, value).map(|inner| PublicKey { inner })
+    }
+
+    /// Returns the chip-specific representation of this public key.
+    pub fn inner(&self) -> &EcdsaChip::PublicKey {
+        &self.inner
+    }
+
+    /// Constrains `signature` to be a valid signature of `message_hash` by this key.
+    pub fn verify(
+        &self,
+        mut layouter: impl Layouter<EcdsaChip>,
+        message_hash: &Scalar<EcdsaChip>,
+        signature: &Signature<EcdsaChip>,
+    ) -> Result<(), Error> {
+        EcdsaChip::verify(
+            &mut layouter,
+            &self.inner,
+            &message_hash.inner,
+            &signature.r.inner,
+            &signature.s.inner,
+        )
+    }
+}
+
+impl<EcdsaChip: EcdsaInstructions> Clone for PublicKey<EcdsaChip> {
+    fn clone(&self) -> Self {
+        PublicKey {
+            inner: self.inner.clone(),
+        }
+    }
+}
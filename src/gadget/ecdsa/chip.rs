@@ -0,0 +1,400 @@
+//! A chip implementing [`EcdsaInstructions`] with non-native field arithmetic.
+//!
+//! # Layout
+//!
+//! Every operation on secp256k1 field elements is a relation between elements, as
+//! described in the `foreign_field` module, which uses six rows. Relations are combined
+//! into point additions and a double-scalar multiplication as described in the `mul`
+//! module. Witnessing a public key uses four relations: the two coordinates, $x^2$, and
+//! the curve equation $y^2 = x^2 \cdot x + 7$.
+//!
+//! Verifying a signature $(r, s)$ on $z$ under $Q$ then checks, in a single region:
+//!
+//! - that $r$ and $s$ are less than $n$, by witnessing $n - 1 - r$ and $n - 1 - s$;
+//! - that $r$ and $s$ are invertible modulo $n$, which gives $w = s^{-1}$;
+//! - $u_1 = z w$ and $u_2 = r w \pmod{n}$;
+//! - that $Q \neq \pm H_2$ and $Q \neq \pm (H_2 + G)$, and the sums $H_2 + Q$ and
+//!   $H_2 + G + Q$;
+//! - the accumulator of $\[u_1\] G + \[u_2\] Q$, and that it is not $\pm$ the correction
+//!   point, before adding the correction to obtain $R$;
+//! - that $x_R$ is less than $p$, and congruent to $r$ modulo $n$.
+
+use std::marker::PhantomData;
+
+use super::EcdsaInstructions;
+use crate::{
+    arithmetic::FieldExt,
+    circuit::{Chip, Layouter, Region},
+    gadget::foreign_field::{Operand, Relation, RelationConfig, SelectConfig, RELATION_ROWS},
+    plonk::{ConstraintSystem, Error},
+    primitives::secp256k1::{self, Affine, Base},
+};
+
+mod mul;
+
+pub use crate::gadget::foreign_field::AssignedElement;
+use mul::{NUM_BITS, STEP_ROWS};
+
+/// A secp256k1 point assigned in the circuit, in affine coordinates.
+#[derive(Clone, Copy, Debug)]
+pub struct AssignedPoint {
+    x: AssignedElement,
+    y: AssignedElement,
+}
+
+impl AssignedPoint {
+    /// The $x$-coordinate of this point.
+    pub fn x(&self) -> AssignedElement {
+        self.x
+    }
+
+    /// The $y$-coordinate of this point.
+    pub fn y(&self) -> AssignedElement {
+        self.y
+    }
+
+    /// The value of this point, if known and on the curve.
+    pub fn point(&self) -> Option<Affine> {
+        self.coordinates().and_then(|(x, y)| Affine::from_xy(x, y))
+    }
+
+    fn coordinates(&self) -> Option<(Base, Base)> {
+        self.x
+            .value
+            .zip(self.y.value)
+            .map(|(x, y)| (Base::reduce(x), Base::reduce(y)))
+    }
+}
+
+/// Configuration for an [`EcdsaChip`].
+#[derive(Clone, Debug)]
+pub struct EcdsaConfig {
+    ff: RelationConfig,
+    select: SelectConfig,
+}
+
+/// A chip verifying ECDSA signatures over secp256k1 in the circuit field `F`.
+///
+/// The range-check table uses 256 rows, and verifying a signature uses 9,640 rows, so
+/// the chip needs $k \geq 14$.
+#[derive(Debug)]
+pub struct EcdsaChip<F: FieldExt> {
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt> EcdsaChip<F> {
+    /// Configures this chip for use in a circuit.
+    ///
+    /// The chip allocates 44 advice columns, 20 of which are placed in a permutation,
+    /// and 15 fixed columns.
+    pub fn configure(meta: &mut ConstraintSystem<F>) -> EcdsaConfig {
+        let ff = RelationConfig::configure(meta);
+        let (_, h_2) = mul::auxiliary_points();
+        let fixed = [
+            mul::coordinates(&h_2),
+            mul::coordinates(&(h_2 + Affine::generator())),
+        ];
+        let select = SelectConfig::configure(meta, &ff, fixed, NUM_BITS, STEP_ROWS);
+
+        EcdsaConfig { ff, select }
+    }
+}
+
+impl<F: FieldExt> Chip for EcdsaChip<F> {
+    type Config = EcdsaConfig;
+    type Loaded = ();
+    type Field = F;
+
+    fn load(layouter: &mut impl Layouter<Self>) -> Result<(), Error> {
+        let config = layouter.config().ff.clone();
+        config.load(layouter)
+    }
+}
+
+impl EcdsaConfig {
+    /// Assigns a public key at `offset`, which uses four relations.
+    fn assign_public_key<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        region: &mut Region<'_, Ch>,
+        offset: usize,
+        value: Option<Affine>,
+    ) -> Result<AssignedPoint, Error> {
+        let coordinates = match value {
+            Some(value) => Some(value.coordinates().ok_or(Error::SynthesisError)?),
+            None => None,
+        };
+
+        let x = self
+            .ff
+            .assign_element(region, offset, coordinates.map(|(x, _)| x.to_limbs()))?;
+        let y = self.ff.assign_element(
+            region,
+            offset + RELATION_ROWS,
+            coordinates.map(|(_, y)| y.to_limbs()),
+        )?;
+
+        let relation = Relation {
+            modulus: Some(Base::MODULUS),
+            products: vec![(1, Operand::Element(&x), Operand::Element(&x))],
+            output: -1,
+            ..Default::default()
+        };
+        let x_squared = self.ff.assign_relation(
+            region,
+            offset + 2 * RELATION_ROWS,
+            &relation,
+            coordinates.map(|(x, _)| x.square().to_limbs()),
+        )?;
+
+        // y^2 - x^2 x - 7 = 0
+        let relation = Relation {
+            modulus: Some(Base::MODULUS),
+            products: vec![
+                (1, Operand::Element(&y), Operand::Element(&y)),
+                (-1, Operand::Element(&x_squared), Operand::Element(&x)),
+            ],
+            constant: (-1, secp256k1::B.to_limbs()),
+            ..Default::default()
+        };
+        self.ff
+            .assign_relation(region, offset + 3 * RELATION_ROWS, &relation, Some([0; 4]))?;
+
+        Ok(AssignedPoint { x, y })
+    }
+
+    fn assign_constant_point<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        region: &mut Region<'_, Ch>,
+        offset: usize,
+        point: Affine,
+    ) -> Result<AssignedPoint, Error> {
+        let (x, y) = point.coordinates().unwrap();
+        Ok(AssignedPoint {
+            x: self.ff.assign_constant(region, offset, x.to_limbs())?,
+            y: self
+                .ff
+                .assign_constant(region, offset + RELATION_ROWS, y.to_limbs())?,
+        })
+    }
+
+    /// Assigns the inverse of `value` modulo $n$ at `offset`.
+    fn assign_inverse<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        region: &mut Region<'_, Ch>,
+        offset: usize,
+        value: &AssignedElement,
+    ) -> Result<AssignedElement, Error> {
+        let inverse = value.value.map(|value| {
+            secp256k1::Scalar::reduce(value)
+                .invert()
+                .unwrap_or_else(secp256k1::Scalar::zero)
+                .to_limbs()
+        });
+        let relation = Relation {
+            modulus: Some(secp256k1::Scalar::MODULUS),
+            products: vec![(1, Operand::Element(value), Operand::Output)],
+            constant: (-1, [1, 0, 0, 0]),
+            ..Default::default()
+        };
+        self.ff.assign_relation(region, offset, &relation, inverse)
+    }
+
+    /// Assigns the product of `a` and `b` modulo $n$ at `offset`.
+    fn assign_scalar_mul<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        region: &mut Region<'_, Ch>,
+        offset: usize,
+        a: &AssignedElement,
+        b: &AssignedElement,
+    ) -> Result<AssignedElement, Error> {
+        let product = a
+            .value
+            .zip(b.value)
+            .map(|(a, b)| (secp256k1::Scalar::reduce(a) * secp256k1::Scalar::reduce(b)).to_limbs());
+        let relation = Relation {
+            modulus: Some(secp256k1::Scalar::MODULUS),
+            products: vec![(1, Operand::Element(a), Operand::Element(b))],
+            output: -1,
+            ..Default::default()
+        };
+        self.ff.assign_relation(region, offset, &relation, product)
+    }
+
+    /// Assigns the verification of a signature, starting at row 0.
+    fn assign_verify<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        region: &mut Region<'_, Ch>,
+        public_key: &AssignedPoint,
+        message_hash: &AssignedElement,
+        r: &AssignedElement,
+        s: &AssignedElement,
+    ) -> Result<(), Error> {
+        let n = secp256k1::Scalar::MODULUS;
+        let mut offset = 0;
+        let mut next = |rows: usize| {
+            let current = offset;
+            offset += rows;
+            current
+        };
+
+        self.ff
+            .assign_canonical(region, next(RELATION_ROWS), r, n)?;
+        self.ff
+            .assign_canonical(region, next(RELATION_ROWS), s, n)?;
+        self.assign_inverse(region, next(RELATION_ROWS), r)?;
+        let w = self.assign_inverse(region, next(RELATION_ROWS), s)?;
+        let u_1 = self.assign_scalar_mul(region, next(RELATION_ROWS), message_hash, &w)?;
+        let u_2 = self.assign_scalar_mul(region, next(RELATION_ROWS), r, &w)?;
+
+        let (h_1, h_2) = mul::auxiliary_points();
+        let h_1 = self.assign_constant_point(region, next(2 * RELATION_ROWS), h_1)?;
+        let p_1 =
+            self.assign_constant_point(region, next(2 * RELATION_ROWS), h_2 + Affine::generator())?;
+        let h_2 = self.assign_constant_point(region, next(2 * RELATION_ROWS), h_2)?;
+        let correction =
+            self.assign_constant_point(region, next(2 * RELATION_ROWS), mul::correction())?;
+
+        self.assign_distinct_x(region, next(RELATION_ROWS), public_key, &h_2)?;
+        let p_2 = self.assign_add(region, next(3 * RELATION_ROWS), &h_2, public_key)?;
+        self.assign_distinct_x(region, next(RELATION_ROWS), public_key, &p_1)?;
+        let p_3 = self.assign_add(region, next(3 * RELATION_ROWS), &p_1, public_key)?;
+
+        let acc = self.assign_mul(
+            region,
+            next(NUM_BITS * STEP_ROWS),
+            &u_1,
+            &u_2,
+            &p_2,
+            &p_3,
+            h_1,
+        )?;
+        self.assign_distinct_x(region, next(RELATION_ROWS), &acc, &correction)?;
+        let result = self.assign_add(region, next(3 * RELATION_ROWS), &acc, &correction)?;
+
+        // x_R < p, and x_R = r (mod n).
+        self.ff
+            .assign_canonical(region, next(RELATION_ROWS), &result.x, Base::MODULUS)?;
+        let relation = Relation {
+            modulus: Some(n),
+            linear: vec![(1, &result.x), (-1, r)],
+            ..Default::default()
+        };
+        self.ff
+            .assign_relation(region, next(RELATION_ROWS), &relation, Some([0; 4]))?;
+
+        Ok(())
+    }
+}
+
+impl<F: FieldExt> EcdsaInstructions for EcdsaChip<F> {
+    type Scalar = AssignedElement;
+    type PublicKey = AssignedPoint;
+
+    fn witness_scalar(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<secp256k1::Scalar>,
+    ) -> Result<Self::Scalar, Error> {
+        let config = layouter.config().clone();
+
+        layouter.assign_region(
+            || "scalar",
+            |mut region| {
+                config
+                    .ff
+                    .assign_element(&mut region, 0, value.map(|value| value.to_limbs()))
+            },
+        )
+    }
+
+    fn witness_public_key(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<Affine>,
+    ) -> Result<Self::PublicKey, Error> {
+        let config = layouter.config().clone();
+
+        layouter.assign_region(
+            || "public key",
+            |mut region| config.assign_public_key(&mut region, 0, value),
+        )
+    }
+
+    fn verify(
+        layouter: &mut impl Layouter<Self>,
+        public_key: &Self::PublicKey,
+        message_hash: &Self::Scalar,
+        r: &Self::Scalar,
+        s: &Self::Scalar,
+    ) -> Result<(), Error> {
+        let config = layouter.config().clone();
+
+        layouter.assign_region(
+            || "ECDSA verification",
+            |mut region| config.assign_verify(&mut region, public_key, message_hash, r, s),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EcdsaChip, EcdsaConfig};
+    use crate::{
+        circuit::{layouter, Layouter},
+        dev::MockProver,
+        gadget::ecdsa::{PublicKey, Scalar, Signature},
+        pasta::Fp,
+        plonk::{Assignment, Circuit, ConstraintSystem, Error},
+        primitives::secp256k1::{self, Affine},
+    };
+
+    struct EcdsaCircuit {
+        public_key: Option<Affine>,
+        message_hash: Option<secp256k1::Scalar>,
+        signature: Option<secp256k1::Signature>,
+    }
+
+    impl Circuit<Fp> for EcdsaCircuit {
+        type Config = EcdsaConfig;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> EcdsaConfig {
+            EcdsaChip::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            config: EcdsaConfig,
+        ) -> Result<(), Error> {
+            let mut layouter = layouter::SingleChip::<EcdsaChip<Fp>, _>::new(cs, config)?;
+
+            let public_key = PublicKey::new(layouter.namespace(|| "public key"), self.public_key)?;
+            let message_hash =
+                Scalar::new(layouter.namespace(|| "message hash"), self.message_hash)?;
+            let signature = Signature::new(layouter.namespace(|| "signature"), self.signature)?;
+            public_key.verify(layouter.namespace(|| "verify"), &message_hash, &signature)
+        }
+    }
+
+    #[test]
+    fn ecdsa_verify() {
+        let secret_key = secp256k1::Scalar::from_be_bytes_reduced(&[0x42; 32]);
+        let public_key = Affine::generator().mul(&secret_key);
+        let message_hash = secp256k1::Scalar::from_be_bytes_reduced(&[0xe3; 32]);
+        let nonce = secp256k1::Scalar::from_be_bytes_reduced(&[0x17; 32]);
+        let signature = secp256k1::sign(&secret_key, &message_hash, &nonce).unwrap();
+
+        let mut circuit = EcdsaCircuit {
+            public_key: Some(public_key),
+            message_hash: Some(message_hash),
+            signature: Some(signature),
+        };
+        let prover = MockProver::run(14, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // A signature of a different message is rejected.
+        circuit.message_hash = Some(message_hash + secp256k1::Scalar::one());
+        let prover = MockProver::run(14, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
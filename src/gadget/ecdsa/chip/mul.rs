@@ -0,0 +1,236 @@
+//! Point addition and double-scalar multiplication on secp256k1.
+//!
+//! Points are added with incomplete addition, which uses three relations: one for the
+//! slope $\lambda$ and one for each coordinate of the sum. The exceptional cases
+//! $P = \pm Q$ are excluded where they could be chosen by the prover, by showing that
+//! $x_P - x_Q$ is invertible.
+//!
+//! $\[u_1\] G + \[u_2\] Q$ is computed with a single double-and-add loop over the bits of
+//! both scalars, from the most significant. The accumulator starts at an auxiliary
+//! point $A_0 = H_1$, and each step computes $A_{i+1} = (A_i + T_i) + A_i$, where $T_i$
+//! is selected by the bits of $u_1$ and $u_2$ from $H_2$, $H_2 + G$, $H_2 + Q$ and
+//! $H_2 + G + Q$, for a second auxiliary point $H_2$. After $N = 256$ steps, the
+//! accumulator is $\[2^N\] H_1 + \[2^N - 1\] H_2 + \[u_1\] G + \[u_2\] Q$, and adding the
+//! fixed correction $-(\[2^N\] H_1 + \[2^N - 1\] H_2)$ gives the result. The auxiliary
+//! points are derived by hashing, so reaching an exceptional case in the loop would
+//! require a discrete logarithm relation between them, $G$ and $Q$.
+//!
+//! # Layout
+//!
+//! Each step uses a selection row, which selects $T_i$ from $P_0 = H_2$, $P_1 = H_2 + G$,
+//! $P_2 = H_2 + Q$ and $P_3 = H_2 + G + Q$, followed by six relations.
+
+use std::convert::TryInto;
+
+use super::{AssignedElement, AssignedPoint, EcdsaConfig};
+use crate::{
+    arithmetic::FieldExt,
+    circuit::{Chip, Region},
+    gadget::foreign_field::{Coordinates, Operand, Relation, RELATION_ROWS},
+    plonk::Error,
+    primitives::secp256k1::{Affine, Base, B},
+};
+
+/// The number of bits of each scalar.
+pub(super) const NUM_BITS: usize = 256;
+
+/// The number of rows used by each step of a multiplication.
+pub(super) const STEP_ROWS: usize = 1 + 6 * RELATION_ROWS;
+
+/// The personalization used to derive the auxiliary points.
+const AUXILIARY_PERSONALIZATION: &[u8; 16] = b"halo2-ecdsa-aux\0";
+
+/// Returns the auxiliary point with the given index.
+///
+/// The point is found by hashing the index and a counter with BLAKE2b until the digest
+/// is the $x$-coordinate of a point.
+fn auxiliary_point(index: u8) -> Affine {
+    (0u32..)
+        .find_map(|counter| {
+            let digest = blake2b_simd::Params::new()
+                .hash_length(32)
+                .personal(AUXILIARY_PERSONALIZATION)
+                .to_state()
+                .update(&[index])
+                .update(&counter.to_le_bytes())
+                .finalize();
+            let x = Base::from_be_bytes(digest.as_bytes().try_into().unwrap())?;
+            let y = (x.square() * x + B).sqrt()?;
+            Affine::from_xy(x, y)
+        })
+        .unwrap()
+}
+
+/// Returns the auxiliary points $H_1$ and $H_2$.
+pub(super) fn auxiliary_points() -> (Affine, Affine) {
+    (auxiliary_point(1), auxiliary_point(2))
+}
+
+/// Returns the correction $-(\[2^N\] H_1 + \[2^N - 1\] H_2)$.
+pub(super) fn correction() -> Affine {
+    let (h_1, h_2) = auxiliary_points();
+    -(0..NUM_BITS).fold(h_1, |acc, _| acc.double() + h_2)
+}
+
+/// Returns the coordinates of a point that is not the identity.
+pub(super) fn coordinates(point: &Affine) -> Coordinates {
+    let (x, y) = point.coordinates().unwrap();
+    [x.to_limbs(), y.to_limbs()]
+}
+
+fn inv0(value: Base) -> Base {
+    value.invert().unwrap_or_else(Base::zero)
+}
+
+impl EcdsaConfig {
+    /// Assigns `x_a - x_b` at `offset` along with its inverse modulo $p$, which
+    /// constrains the points to have distinct $x$-coordinates.
+    pub(super) fn assign_distinct_x<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        region: &mut Region<'_, Ch>,
+        offset: usize,
+        a: &AssignedPoint,
+        b: &AssignedPoint,
+    ) -> Result<(), Error> {
+        let inv = a
+            .coordinates()
+            .zip(b.coordinates())
+            .map(|((x_a, _), (x_b, _))| inv0(x_a - x_b).to_limbs());
+        let relation = Relation {
+            modulus: Some(Base::MODULUS),
+            products: vec![
+                (1, Operand::Output, Operand::Element(&a.x)),
+                (-1, Operand::Output, Operand::Element(&b.x)),
+            ],
+            constant: (-1, [1, 0, 0, 0]),
+            ..Default::default()
+        };
+        self.ff
+            .assign_relation(region, offset, &relation, inv)
+            .map(|_| ())
+    }
+
+    /// Assigns `a + b` at `offset` using incomplete addition, which uses three
+    /// relations.
+    pub(super) fn assign_add<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        region: &mut Region<'_, Ch>,
+        offset: usize,
+        a: &AssignedPoint,
+        b: &AssignedPoint,
+    ) -> Result<AssignedPoint, Error> {
+        // The witnesses are only meaningful outside of the exceptional cases.
+        let witnesses = a
+            .coordinates()
+            .zip(b.coordinates())
+            .map(|((x_a, y_a), (x_b, y_b))| {
+                let lambda = (y_b - y_a) * inv0(x_b - x_a);
+                let x_r = lambda.square() - x_a - x_b;
+                let y_r = lambda * (x_a - x_r) - y_a;
+                (lambda, x_r, y_r)
+            });
+
+        // lambda (x_b - x_a) = y_b - y_a
+        let relation = Relation {
+            modulus: Some(Base::MODULUS),
+            products: vec![
+                (1, Operand::Output, Operand::Element(&b.x)),
+                (-1, Operand::Output, Operand::Element(&a.x)),
+            ],
+            linear: vec![(-1, &b.y), (1, &a.y)],
+            ..Default::default()
+        };
+        let lambda = self.ff.assign_relation(
+            region,
+            offset,
+            &relation,
+            witnesses.map(|w| w.0.to_limbs()),
+        )?;
+
+        // x_r = lambda^2 - x_a - x_b
+        let relation = Relation {
+            modulus: Some(Base::MODULUS),
+            products: vec![(1, Operand::Element(&lambda), Operand::Element(&lambda))],
+            linear: vec![(-1, &a.x), (-1, &b.x)],
+            output: -1,
+            ..Default::default()
+        };
+        let x = self.ff.assign_relation(
+            region,
+            offset + RELATION_ROWS,
+            &relation,
+            witnesses.map(|w| w.1.to_limbs()),
+        )?;
+
+        // y_r = lambda (x_a - x_r) - y_a
+        let relation = Relation {
+            modulus: Some(Base::MODULUS),
+            products: vec![
+                (1, Operand::Element(&lambda), Operand::Element(&a.x)),
+                (-1, Operand::Element(&lambda), Operand::Element(&x)),
+            ],
+            linear: vec![(-1, &a.y)],
+            output: -1,
+            ..Default::default()
+        };
+        let y = self.ff.assign_relation(
+            region,
+            offset + 2 * RELATION_ROWS,
+            &relation,
+            witnesses.map(|w| w.2.to_limbs()),
+        )?;
+
+        Ok(AssignedPoint { x, y })
+    }
+
+    /// Assigns $\[u_1\] G + \[u_2\] Q$ at `offset`, starting from the accumulator
+    /// `acc`, where `p_2` and `p_3` are $H_2 + Q$ and $H_2 + G + Q$.
+    ///
+    /// The multiplication uses [`NUM_BITS`] steps of [`STEP_ROWS`] rows, and returns the
+    /// final accumulator, which must then be corrected.
+    #[allow(clippy::too_many_arguments)]
+    pub(super) fn assign_mul<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        region: &mut Region<'_, Ch>,
+        offset: usize,
+        u_1: &AssignedElement,
+        u_2: &AssignedElement,
+        p_2: &AssignedPoint,
+        p_3: &AssignedPoint,
+        mut acc: AssignedPoint,
+    ) -> Result<AssignedPoint, Error> {
+        self.select.assign(
+            &self.ff,
+            region,
+            offset,
+            u_1,
+            u_2,
+            [&p_2.x, &p_2.y],
+            [&p_3.x, &p_3.y],
+            |region, offset, [x, y]| {
+                // A_{i+1} = (A_i + T_i) + A_i
+                let sum = self.assign_add(region, offset, &acc, &AssignedPoint { x, y })?;
+                acc = self.assign_add(region, offset + 3 * RELATION_ROWS, &sum, &acc)?;
+                Ok(())
+            },
+        )?;
+
+        Ok(acc)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{auxiliary_points, correction, NUM_BITS};
+    use crate::primitives::secp256k1::Affine;
+
+    #[test]
+    fn correction_cancels_accumulator() {
+        let (h_1, h_2) = auxiliary_points();
+        assert_ne!(h_1, h_2);
+
+        // With all-zero scalars, each step computes A + (A + H_2).
+        let acc = (0..NUM_BITS).fold(h_1, |acc, _| (acc + h_2) + acc);
+        assert_eq!(acc + correction(), Affine::identity());
+    }
+}
@@ -0,0 +1,216 @@
+//! Gadget and chips for verifying Ed25519 signatures.
+//!
+//! The fields of edwards25519 are not the circuit field, so their elements are
+//! represented as several native limbs, as in [`crate::gadget::ecdsa`]. Verifying a
+//! signature $(R, S)$ under a public key $A$ checks that $S < \ell$ and that
+//! $\[S\] B = R + \[k\] A$, where $k = H(R \| A \| M) \bmod \ell$ is the challenge.
+//!
+//! Ed25519 defines $H$ as SHA-512, but the message $M$ and the way it is hashed depend
+//! on the statement being proven, so the challenge is computed by a [`ChallengeHash`]
+//! provided by the caller. The values being verified are computed outside of a circuit
+//! by [`crate::primitives::ed25519`].
+
+use std::fmt;
+
+use crate::{
+    circuit::{Chip, Layouter},
+    plonk::Error,
+    primitives::ed25519,
+};
+
+mod chip;
+pub use chip::{AssignedElement, AssignedPoint, Ed25519Chip, Ed25519Config};
+
+/// The set of circuit instructions required to use the [`PublicKey`], [`Scalar`] and
+/// [`Signature`] gadgets.
+pub trait Ed25519Instructions: Chip {
+    /// Variable representing an integer congruent to an element of the scalar field of
+    /// edwards25519.
+    type Scalar: Clone + fmt::Debug;
+
+    /// Variable representing a point on edwards25519.
+    type Point: Clone + fmt::Debug;
+
+    /// Witnesses an integer less than $2^{256}$.
+    fn witness_scalar(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<ed25519::Scalar>,
+    ) -> Result<Self::Scalar, Error>;
+
+    /// Witnesses a point, constraining it to be on the curve.
+    fn witness_point(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<ed25519::Affine>,
+    ) -> Result<Self::Point, Error>;
+
+    /// Constrains $\[s\] B = r + \[k\] A$ for the public key $A$ and the challenge $k$,
+    /// and $s, k < \ell$.
+    fn verify(
+        layouter: &mut impl Layouter<Self>,
+        public_key: &Self::Point,
+        r: &Self::Point,
+        s: &Self::Scalar,
+        challenge: &Self::Scalar,
+    ) -> Result<(), Error>;
+}
+
+/// A hash function computing the challenge of a signature in the circuit.
+///
+/// An implementation holds the message, and must constrain the returned challenge to
+/// be $H(R \| A \| M) \bmod \ell$ for the encodings of the given points. A challenge
+/// that is only witnessed allows signatures to be forged.
+pub trait ChallengeHash<Ed25519Chip: Ed25519Instructions> {
+    /// Returns the challenge of a signature with the nonce point `r` under
+    /// `public_key`.
+    fn challenge(
+        &self,
+        layouter: impl Layouter<Ed25519Chip>,
+        r: &Point<Ed25519Chip>,
+        public_key: &PublicKey<Ed25519Chip>,
+    ) -> Result<Scalar<Ed25519Chip>, Error>;
+}
+
+/// An integer representing an element of the scalar field of edwards25519.
+#[derive(Debug)]
+pub struct Scalar<Ed25519Chip: Ed25519Instructions> {
+    inner: Ed25519Chip::Scalar,
+}
+
+impl<Ed25519Chip: Ed25519Instructions> Scalar<Ed25519Chip> {
+    /// Witnesses a scalar.
+    pub fn new(
+        mut layouter: impl Layouter<Ed25519Chip>,
+        value: Option<ed25519::Scalar>,
+    ) -> Result<Self, Error> {
+        Ed25519Chip::witness_scalar(&mut layouter, value).map(|inner| Scalar { inner })
+    }
+
+    /// Returns the chip-specific representation of this scalar.
+    pub fn inner(&self) -> &Ed25519Chip::Scalar {
+        &self.inner
+    }
+}
+
+impl<Ed25519Chip: Ed25519Instructions> Clone for Scalar<Ed25519Chip> {
+    fn clone(&self) -> Self {
+        Scalar {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+/// A point on edwards25519.
+#[derive(Debug)]
+pub struct Point<Ed25519Chip: Ed25519Instructions> {
+    inner: Ed25519Chip::Point,
+}
+
+impl<Ed25519Chip: Ed25519Instructions> Point<Ed25519Chip> {
+    /// Witnesses a point.
+    pub fn new(
+        mut layouter: impl Layouter<Ed25519Chip>,
+        value: Option<ed25519::Affine>,
+    ) -> Result<Self, Error> {
+        Ed25519Chip::witness_point(&mut layouter, value).map(|inner| Point { inner })
+    }
+
+    /// Returns the chip-specific representation of this point.
+    pub fn inner(&self) -> &Ed25519Chip::Point {
+        &self.inner
+    }
+}
+
+impl<Ed25519Chip: Ed25519Instructions> Clone for Point<Ed25519Chip> {
+    fn clone(&self) -> Self {
+        Point {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+/// An Ed25519 signature.
+#[derive(Debug)]
+pub struct Signature<Ed25519Chip: Ed25519Instructions> {
+    r: Point<Ed25519Chip>,
+    s: Scalar<Ed25519Chip>,
+}
+
+impl<Ed25519Chip: Ed25519Instructions> Signature<Ed25519Chip> {
+    /// Witnesses a signature.
+    ///
+    /// $S$ is only constrained to be canonical by [`PublicKey::verify`].
+    pub fn new(
+        mut layouter: impl Layouter<Ed25519Chip>,
+        value: Option<ed25519::Signature>,
+    ) -> Result<Self, Error> {
+        let r = Point::new(layouter.namespace(|| "R"), value.map(|sig| sig.r))?;
+        let s = Scalar::new(layouter.namespace(|| "S"), value.map(|sig| sig.s))?;
+        Ok(Signature { r, s })
+    }
+
+    /// The nonce point.
+    pub fn r(&self) -> &Point<Ed25519Chip> {
+        &self.r
+    }
+
+    /// The response to the challenge.
+    pub fn s(&self) -> &Scalar<Ed25519Chip> {
+        &self.s
+    }
+}
+
+impl<Ed25519Chip: Ed25519Instructions> Clone for Signature<Ed25519Chip> {
+    fn clone(&self) -> Self {
+        Signature {
+            r: self.r.clone(),
+            s: self.s.clone(),
+        }
+    }
+}
+
+/// An Ed25519 public key.
+#[derive(Debug)]
+pub struct PublicKey<Ed25519Chip: Ed25519Instructions> {
+    inner: Point<Ed25519Chip>,
+}
+
+impl<Ed25519Chip: Ed25519Instructions> PublicKey<Ed25519Chip> {
+    /// Witnesses a public key.
+    pub fn new(
+        layouter: impl Layouter<Ed25519Chip>,
+        value: Option<ed25519::Affine>,
+    ) -> Result<Self, Error> {
+        Point::new(layouter, value).map(|inner| PublicKey { inner })
+    }
+
+    /// The point $A$ of this public key.
+    pub fn point(&self) -> &Point<Ed25519Chip> {
+        &self.inner
+    }
+
+    /// Constrains `signature` to be a valid signature by this key, for the challenge
+    /// computed by `hash`.
+    pub fn verify(
+        &self,
+        mut layouter: impl Layouter<Ed25519Chip>,
+        signature: &Signature<Ed25519Chip>,
+        hash: &impl ChallengeHash<Ed25519Chip>,
+    ) -> Result<(), Error> {
+        let challenge = hash.challenge(layouter.namespace(|| "challenge"), &signature.r, self)?;
+        Ed25519Chip::verify(
+            &mut layouter,
+            &self.inner.inner,
+            &signature.r.inner,
+            &signature.s.inner,
+            &challenge.inner,
+        )
+    }
+}
+
+impl<Ed25519Chip: Ed25519Instructions> Clone for PublicKey<Ed25519Chip> {
+    fn clone(&self) -> Self {
+        PublicKey {
+            inner: self.inner.clone(),
+        }
+    }
+}
@@ -0,0 +1,406 @@
+//! A chip implementing [`Ed25519Instructions`] with non-native field arithmetic.
+//!
+//! # Layout
+//!
+//! Every operation on edwards25519 field elements is a relation between elements, as
+//! described in the `foreign_field` module, which uses six rows. Relations are combined
+//! into point additions, doublings and a double-scalar multiplication as described in
+//! the `mul` module. Witnessing a point uses seven relations: the two coordinates, $x^2$,
+//! $y^2$, the constant $d$, $d x^2$ and the curve equation
+//! $y^2 - x^2 = 1 + d x^2 \cdot y^2$.
+//!
+//! Verifying a signature $(R, S)$ with the challenge $k$ under $A$ then checks, in a
+//! single region:
+//!
+//! - that $S$ and $k$ are less than $\ell$, by witnessing $\ell - 1 - S$ and
+//!   $\ell - 1 - k$;
+//! - $-A$, and the sum $B - A$;
+//! - $\[S\] B + \[k\] (-A)$, which is then compared with $R$ coordinate by coordinate.
+
+use std::marker::PhantomData;
+
+use super::Ed25519Instructions;
+use crate::{
+    arithmetic::FieldExt,
+    circuit::{Chip, Layouter, Region},
+    gadget::foreign_field::{Operand, Relation, RelationConfig, SelectConfig, RELATION_ROWS},
+    plonk::{ConstraintSystem, Error},
+    primitives::ed25519::{self, Affine, Base},
+};
+
+mod mul;
+
+pub use crate::gadget::foreign_field::AssignedElement;
+use mul::{NUM_BITS, STEP_ROWS};
+
+/// An edwards25519 point assigned in the circuit, in affine coordinates.
+#[derive(Clone, Copy, Debug)]
+pub struct AssignedPoint {
+    x: AssignedElement,
+    y: AssignedElement,
+}
+
+impl AssignedPoint {
+    /// The $x$-coordinate of this point.
+    pub fn x(&self) -> AssignedElement {
+        self.x
+    }
+
+    /// The $y$-coordinate of this point.
+    pub fn y(&self) -> AssignedElement {
+        self.y
+    }
+
+    /// The value of this point, if known and on the curve.
+    pub fn point(&self) -> Option<Affine> {
+        self.coordinates().and_then(|(x, y)| Affine::from_xy(x, y))
+    }
+
+    fn coordinates(&self) -> Option<(Base, Base)> {
+        self.x
+            .value
+            .zip(self.y.value)
+            .map(|(x, y)| (Base::reduce(x), Base::reduce(y)))
+    }
+}
+
+/// Configuration for an [`Ed25519Chip`].
+#[derive(Clone, Debug)]
+pub struct Ed25519Config {
+    ff: RelationConfig,
+    select: SelectConfig,
+}
+
+/// A chip verifying Ed25519 signatures in the circuit field `F`.
+///
+/// The range-check table uses 256 rows, and verifying a signature uses 18,571 rows, so
+/// the chip needs $k \geq 15$.
+#[derive(Debug)]
+pub struct Ed25519Chip<F: FieldExt> {
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt> Ed25519Chip<F> {
+    /// Configures this chip for use in a circuit.
+    ///
+    /// The chip allocates 44 advice columns, 20 of which are placed in a permutation,
+    /// and 15 fixed columns.
+    pub fn configure(meta: &mut ConstraintSystem<F>) -> Ed25519Config {
+        let ff = RelationConfig::configure(meta);
+        let fixed = [
+            mul::coordinates(&Affine::identity()),
+            mul::coordinates(&Affine::generator()),
+        ];
+        let select = SelectConfig::configure(meta, &ff, fixed, NUM_BITS, STEP_ROWS);
+
+        Ed25519Config { ff, select }
+    }
+}
+
+impl<F: FieldExt> Chip for Ed25519Chip<F> {
+    type Config = Ed25519Config;
+    type Loaded = ();
+    type Field = F;
+
+    fn load(layouter: &mut impl Layouter<Self>) -> Result<(), Error> {
+        let config = layouter.config().ff.clone();
+        config.load(layouter)
+    }
+}
+
+impl Ed25519Config {
+    /// Assigns a point at `offset`, which uses seven relations.
+    fn assign_point<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        region: &mut Region<'_, Ch>,
+        offset: usize,
+        value: Option<Affine>,
+    ) -> Result<AssignedPoint, Error> {
+        let coordinates = value.map(|value| value.coordinates());
+        let x = self
+            .ff
+            .assign_element(region, offset, coordinates.map(|(x, _)| x.to_limbs()))?;
+        let y = self.ff.assign_element(
+            region,
+            offset + RELATION_ROWS,
+            coordinates.map(|(_, y)| y.to_limbs()),
+        )?;
+
+        let x_squared = self.assign_mul(region, offset + 2 * RELATION_ROWS, &x, &x)?;
+        let y_squared = self.assign_mul(region, offset + 3 * RELATION_ROWS, &y, &y)?;
+        let d =
+            self.ff
+                .assign_constant(region, offset + 4 * RELATION_ROWS, ed25519::D.to_limbs())?;
+        let d_x_squared = self.assign_mul(region, offset + 5 * RELATION_ROWS, &d, &x_squared)?;
+
+        // y^2 - x^2 - 1 - d x^2 y^2 = 0
+        let relation = Relation {
+            modulus: Some(Base::MODULUS),
+            products: vec![(
+                -1,
+                Operand::Element(&d_x_squared),
+                Operand::Element(&y_squared),
+            )],
+            linear: vec![(1, &y_squared), (-1, &x_squared)],
+            constant: (-1, [1, 0, 0, 0]),
+            ..Default::default()
+        };
+        self.ff
+            .assign_relation(region, offset + 6 * RELATION_ROWS, &relation, Some([0; 4]))?;
+
+        Ok(AssignedPoint { x, y })
+    }
+
+    fn assign_constant_point<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        region: &mut Region<'_, Ch>,
+        offset: usize,
+        point: Affine,
+    ) -> Result<AssignedPoint, Error> {
+        let (x, y) = point.coordinates();
+        Ok(AssignedPoint {
+            x: self.ff.assign_constant(region, offset, x.to_limbs())?,
+            y: self
+                .ff
+                .assign_constant(region, offset + RELATION_ROWS, y.to_limbs())?,
+        })
+    }
+
+    /// Assigns the product of `a` and `b` modulo $p$ at `offset`.
+    fn assign_mul<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        region: &mut Region<'_, Ch>,
+        offset: usize,
+        a: &AssignedElement,
+        b: &AssignedElement,
+    ) -> Result<AssignedElement, Error> {
+        let product = a
+            .value
+            .zip(b.value)
+            .map(|(a, b)| (Base::reduce(a) * Base::reduce(b)).to_limbs());
+        let relation = Relation {
+            modulus: Some(Base::MODULUS),
+            products: vec![(1, Operand::Element(a), Operand::Element(b))],
+            output: -1,
+            ..Default::default()
+        };
+        self.ff.assign_relation(region, offset, &relation, product)
+    }
+
+    /// Constrains `a` and `b` to be congruent modulo $p$, at `offset`.
+    fn assign_congruent<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        region: &mut Region<'_, Ch>,
+        offset: usize,
+        a: &AssignedElement,
+        b: &AssignedElement,
+    ) -> Result<(), Error> {
+        let relation = Relation {
+            modulus: Some(Base::MODULUS),
+            linear: vec![(1, a), (-1, b)],
+            ..Default::default()
+        };
+        self.ff
+            .assign_relation(region, offset, &relation, Some([0; 4]))
+            .map(|_| ())
+    }
+
+    /// Assigns the verification of a signature, starting at row 0.
+    fn assign_verify<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        region: &mut Region<'_, Ch>,
+        public_key: &AssignedPoint,
+        r: &AssignedPoint,
+        s: &AssignedElement,
+        challenge: &AssignedElement,
+    ) -> Result<(), Error> {
+        let l = ed25519::Scalar::MODULUS;
+        let mut offset = 0;
+        let mut next = |rows: usize| {
+            let current = offset;
+            offset += rows;
+            current
+        };
+
+        self.ff
+            .assign_canonical(region, next(RELATION_ROWS), s, l)?;
+        self.ff
+            .assign_canonical(region, next(RELATION_ROWS), challenge, l)?;
+
+        let d = self
+            .ff
+            .assign_constant(region, next(RELATION_ROWS), ed25519::D.to_limbs())?;
+        let identity =
+            self.assign_constant_point(region, next(2 * RELATION_ROWS), Affine::identity())?;
+        let generator =
+            self.assign_constant_point(region, next(2 * RELATION_ROWS), Affine::generator())?;
+
+        // -A = (-x_A, y_A)
+        let neg_x = public_key.coordinates().map(|(x, _)| (-x).to_limbs());
+        let relation = Relation {
+            modulus: Some(Base::MODULUS),
+            linear: vec![(1, &public_key.x)],
+            output: 1,
+            ..Default::default()
+        };
+        let neg_x = self
+            .ff
+            .assign_relation(region, next(RELATION_ROWS), &relation, neg_x)?;
+        let neg_public_key = AssignedPoint {
+            x: neg_x,
+            y: public_key.y,
+        };
+        let difference = self.assign_add(
+            region,
+            next(7 * RELATION_ROWS),
+            &d,
+            &generator,
+            &neg_public_key,
+        )?;
+
+        let result = self.assign_double_mul(
+            region,
+            next(NUM_BITS * STEP_ROWS),
+            &d,
+            s,
+            challenge,
+            &neg_public_key,
+            &difference,
+            identity,
+        )?;
+        self.assign_congruent(region, next(RELATION_ROWS), &result.x, &r.x)?;
+        self.assign_congruent(region, next(RELATION_ROWS), &result.y, &r.y)?;
+
+        Ok(())
+    }
+}
+
+impl<F: FieldExt> Ed25519Instructions for Ed25519Chip<F> {
+    type Scalar = AssignedElement;
+    type Point = AssignedPoint;
+
+    fn witness_scalar(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<ed25519::Scalar>,
+    ) -> Result<Self::Scalar, Error> {
+        let config = layouter.config().clone();
+
+        layouter.assign_region(
+            || "scalar",
+            |mut region| {
+                config
+                    .ff
+                    .assign_element(&mut region, 0, value.map(|value| value.to_limbs()))
+            },
+        )
+    }
+
+    fn witness_point(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<Affine>,
+    ) -> Result<Self::Point, Error> {
+        let config = layouter.config().clone();
+
+        layouter.assign_region(
+            || "point",
+            |mut region| config.assign_point(&mut region, 0, value),
+        )
+    }
+
+    fn verify(
+        layouter: &mut impl Layouter<Self>,
+        public_key: &Self::Point,
+        r: &Self::Point,
+        s: &Self::Scalar,
+        challenge: &Self::Scalar,
+    ) -> Result<(), Error> {
+        let config = layouter.config().clone();
+
+        layouter.assign_region(
+            || "Ed25519 verification",
+            |mut region| config.assign_verify(&mut region, public_key, r, s, challenge),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Ed25519Chip, Ed25519Config};
+    use crate::{
+        circuit::{layouter, Layouter},
+        dev::MockProver,
+        gadget::ed25519::{ChallengeHash, Point, PublicKey, Scalar, Signature},
+        pasta::Fp,
+        plonk::{Assignment, Circuit, ConstraintSystem, Error},
+        primitives::ed25519::{self, Affine},
+    };
+
+    /// A challenge computed outside of the circuit and only witnessed, as a stand-in
+    /// for an in-circuit hash.
+    struct WitnessedChallenge(Option<ed25519::Scalar>);
+
+    impl ChallengeHash<Ed25519Chip<Fp>> for WitnessedChallenge {
+        fn challenge(
+            &self,
+            layouter: impl Layouter<Ed25519Chip<Fp>>,
+            _: &Point<Ed25519Chip<Fp>>,
+            _: &PublicKey<Ed25519Chip<Fp>>,
+        ) -> Result<Scalar<Ed25519Chip<Fp>>, Error> {
+            Scalar::new(layouter, self.0)
+        }
+    }
+
+    struct Ed25519Circuit {
+        public_key: Option<Affine>,
+        signature: Option<ed25519::Signature>,
+        challenge: Option<ed25519::Scalar>,
+    }
+
+    impl Circuit<Fp> for Ed25519Circuit {
+        type Config = Ed25519Config;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Ed25519Config {
+            Ed25519Chip::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            config: Ed25519Config,
+        ) -> Result<(), Error> {
+            let mut layouter = layouter::SingleChip::<Ed25519Chip<Fp>, _>::new(cs, config)?;
+
+            let public_key = PublicKey::new(layouter.namespace(|| "public key"), self.public_key)?;
+            let signature = Signature::new(layouter.namespace(|| "signature"), self.signature)?;
+            public_key.verify(
+                layouter.namespace(|| "verify"),
+                &signature,
+                &WitnessedChallenge(self.challenge),
+            )
+        }
+    }
+
+    #[test]
+    fn ed25519_verify() {
+        let secret_key = [0x42; 32];
+        let public_key = ed25519::public_key(&secret_key);
+        let message = b"halo2";
+        let signature = ed25519::sign(&secret_key, message);
+        let challenge = ed25519::challenge(&signature.r, &public_key, message);
+
+        let mut circuit = Ed25519Circuit {
+            public_key: Some(public_key),
+            signature: Some(signature),
+            challenge: Some(challenge),
+        };
+        let prover = MockProver::run(15, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // The challenge of a different message is rejected.
+        circuit.challenge = Some(ed25519::challenge(&signature.r, &public_key, b"halo3"));
+        let prover = MockProver::run(15, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
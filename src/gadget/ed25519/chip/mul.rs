@@ -0,0 +1,248 @@
+//! Point addition, doubling and double-scalar multiplication on edwards25519.
+//!
+//! The Edwards addition law is complete, so no exceptional cases need to be excluded.
+//! Adding $P_1$ and $P_2$ uses seven relations, for $c = x_1 x_2$,
+//! $e = y_1 y_2 + c$, $s = x_1 y_2 + y_1 x_2$, $d c$, $f = d c \cdot (e - c)$ and the
+//! coordinates of the sum, which satisfy $x_3 (1 + f) = s$ and $y_3 (1 - f) = e$.
+//! Doubling uses five relations, for $x^2$, $y^2$, $x y$ and the coordinates of the
+//! result, which satisfy $x_3 (y^2 - x^2) = 2 x y$ and
+//! $y_3 (2 - y^2 + x^2) = y^2 + x^2$. The denominators are never zero for points on the
+//! curve, so each coordinate is uniquely determined.
+//!
+//! $\[a\] B + \[b\] P$ is computed by double-and-add over the $N = 253$ bits of both
+//! scalars, from the most significant. The accumulator starts at the identity, and
+//! each step computes $A_{i+1} = \[2\] A_i + T_i$, where $T_i$ is selected by the bits
+//! of $a$ and $b$ from $P_0 = O$, $P_1 = B$, $P_2 = P$ and $P_3 = B + P$.
+//!
+//! # Layout
+//!
+//! Each step uses a selection row followed by twelve relations, five for the doubling
+//! and seven for the addition.
+
+use super::{AssignedElement, AssignedPoint, Ed25519Config};
+use crate::{
+    arithmetic::FieldExt,
+    circuit::{Chip, Region},
+    gadget::foreign_field::{Coordinates, Operand, Relation, RELATION_ROWS},
+    plonk::Error,
+    primitives::ed25519::{Affine, Base, D},
+};
+
+/// The number of bits of each scalar.
+pub(super) const NUM_BITS: usize = 253;
+
+/// The number of rows used by each step of a multiplication.
+pub(super) const STEP_ROWS: usize = 1 + 12 * RELATION_ROWS;
+
+/// Returns the coordinates of a point.
+pub(super) fn coordinates(point: &Affine) -> Coordinates {
+    let (x, y) = point.coordinates();
+    [x.to_limbs(), y.to_limbs()]
+}
+
+impl Ed25519Config {
+    /// Assigns `a + b` at `offset`, where `d` is the curve constant, which uses seven
+    /// relations.
+    pub(super) fn assign_add<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        region: &mut Region<'_, Ch>,
+        offset: usize,
+        d: &AssignedElement,
+        a: &AssignedPoint,
+        b: &AssignedPoint,
+    ) -> Result<AssignedPoint, Error> {
+        let values = a.coordinates().zip(b.coordinates());
+        let witness = |f: &dyn Fn(Base, Base, Base, Base) -> Base| {
+            values.map(|((x_1, y_1), (x_2, y_2))| f(x_1, y_1, x_2, y_2).to_limbs())
+        };
+        let f_value = |x_1: Base, y_1: Base, x_2: Base, y_2: Base| D * x_1 * x_2 * y_1 * y_2;
+
+        let c = self.assign_mul(region, offset, &a.x, &b.x)?;
+
+        // e = y_1 y_2 + c
+        let relation = Relation {
+            modulus: Some(Base::MODULUS),
+            products: vec![(1, Operand::Element(&a.y), Operand::Element(&b.y))],
+            linear: vec![(1, &c)],
+            output: -1,
+            ..Default::default()
+        };
+        let e = self.ff.assign_relation(
+            region,
+            offset + RELATION_ROWS,
+            &relation,
+            witness(&|x_1, y_1, x_2, y_2| y_1 * y_2 + x_1 * x_2),
+        )?;
+
+        // s = x_1 y_2 + y_1 x_2
+        let relation = Relation {
+            modulus: Some(Base::MODULUS),
+            products: vec![
+                (1, Operand::Element(&a.x), Operand::Element(&b.y)),
+                (1, Operand::Element(&a.y), Operand::Element(&b.x)),
+            ],
+            output: -1,
+            ..Default::default()
+        };
+        let s = self.ff.assign_relation(
+            region,
+            offset + 2 * RELATION_ROWS,
+            &relation,
+            witness(&|x_1, y_1, x_2, y_2| x_1 * y_2 + y_1 * x_2),
+        )?;
+
+        let d_c = self.assign_mul(region, offset + 3 * RELATION_ROWS, d, &c)?;
+
+        // f = d c e - d c c
+        let relation = Relation {
+            modulus: Some(Base::MODULUS),
+            products: vec![
+                (1, Operand::Element(&d_c), Operand::Element(&e)),
+                (-1, Operand::Element(&d_c), Operand::Element(&c)),
+            ],
+            output: -1,
+            ..Default::default()
+        };
+        let f = self.ff.assign_relation(
+            region,
+            offset + 4 * RELATION_ROWS,
+            &relation,
+            witness(&f_value),
+        )?;
+
+        // x_3 (1 + f) = s
+        let relation = Relation {
+            modulus: Some(Base::MODULUS),
+            products: vec![(1, Operand::Output, Operand::Element(&f))],
+            linear: vec![(-1, &s)],
+            output: 1,
+            ..Default::default()
+        };
+        let x = self.ff.assign_relation(
+            region,
+            offset + 5 * RELATION_ROWS,
+            &relation,
+            witness(&|x_1, y_1, x_2, y_2| {
+                (x_1 * y_2 + y_1 * x_2) * inv0(Base::one() + f_value(x_1, y_1, x_2, y_2))
+            }),
+        )?;
+
+        // y_3 (1 - f) = e
+        let relation = Relation {
+            modulus: Some(Base::MODULUS),
+            products: vec![(-1, Operand::Output, Operand::Element(&f))],
+            linear: vec![(-1, &e)],
+            output: 1,
+            ..Default::default()
+        };
+        let y = self.ff.assign_relation(
+            region,
+            offset + 6 * RELATION_ROWS,
+            &relation,
+            witness(&|x_1, y_1, x_2, y_2| {
+                (y_1 * y_2 + x_1 * x_2) * inv0(Base::one() - f_value(x_1, y_1, x_2, y_2))
+            }),
+        )?;
+
+        Ok(AssignedPoint { x, y })
+    }
+
+    /// Assigns `[2] a` at `offset`, which uses five relations.
+    pub(super) fn assign_double<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        region: &mut Region<'_, Ch>,
+        offset: usize,
+        a: &AssignedPoint,
+    ) -> Result<AssignedPoint, Error> {
+        let doubled = a
+            .coordinates()
+            .map(|(x, y)| Affine::from_xy(x, y).map_or((x, y), |a| a.double().coordinates()));
+
+        let xx = self.assign_mul(region, offset, &a.x, &a.x)?;
+        let yy = self.assign_mul(region, offset + RELATION_ROWS, &a.y, &a.y)?;
+        let xy = self.assign_mul(region, offset + 2 * RELATION_ROWS, &a.x, &a.y)?;
+
+        // x_3 (y^2 - x^2) = 2 x y
+        let relation = Relation {
+            modulus: Some(Base::MODULUS),
+            products: vec![
+                (1, Operand::Output, Operand::Element(&yy)),
+                (-1, Operand::Output, Operand::Element(&xx)),
+            ],
+            linear: vec![(-2, &xy)],
+            ..Default::default()
+        };
+        let x = self.ff.assign_relation(
+            region,
+            offset + 3 * RELATION_ROWS,
+            &relation,
+            doubled.map(|(x, _)| x.to_limbs()),
+        )?;
+
+        // y_3 (2 - y^2 + x^2) = y^2 + x^2
+        let relation = Relation {
+            modulus: Some(Base::MODULUS),
+            products: vec![
+                (-1, Operand::Output, Operand::Element(&yy)),
+                (1, Operand::Output, Operand::Element(&xx)),
+            ],
+            linear: vec![(-1, &yy), (-1, &xx)],
+            output: 2,
+            ..Default::default()
+        };
+        let y = self.ff.assign_relation(
+            region,
+            offset + 4 * RELATION_ROWS,
+            &relation,
+            doubled.map(|(_, y)| y.to_limbs()),
+        )?;
+
+        Ok(AssignedPoint { x, y })
+    }
+
+    /// Assigns $\[a\] B + \[b\] P$ at `offset`, starting from the accumulator `acc`,
+    /// where `p_2` and `p_3` are $P$ and $B + P$.
+    ///
+    /// The multiplication uses [`NUM_BITS`] steps of [`STEP_ROWS`] rows, so `a` and `b`
+    /// are constrained to be less than $2^{253}$.
+    #[allow(clippy::too_many_arguments)]
+    pub(super) fn assign_double_mul<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        region: &mut Region<'_, Ch>,
+        offset: usize,
+        d: &AssignedElement,
+        a: &AssignedElement,
+        b: &AssignedElement,
+        p_2: &AssignedPoint,
+        p_3: &AssignedPoint,
+        mut acc: AssignedPoint,
+    ) -> Result<AssignedPoint, Error> {
+        self.select.assign(
+            &self.ff,
+            region,
+            offset,
+            a,
+            b,
+            [&p_2.x, &p_2.y],
+            [&p_3.x, &p_3.y],
+            |region, offset, [x, y]| {
+                // A_{i+1} = [2] A_i + T_i
+                let doubled = self.assign_double(region, offset, &acc)?;
+                acc = self.assign_add(
+                    region,
+                    offset + 5 * RELATION_ROWS,
+                    d,
+                    &doubled,
+                    &AssignedPoint { x, y },
+                )?;
+                Ok(())
+            },
+        )?;
+
+        Ok(acc)
+    }
+}
+
+fn inv0(value: Base) -> Base {
+    value.invert().unwrap_or_else(Base::zero)
+}
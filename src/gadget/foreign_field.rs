@@ -0,0 +1,160 @@
+//! Gadget and chips for arithmetic over fields other than the circuit field.
+//!
+//! Elements of a foreign field are represented as several native limbs, and each field
+//! operation is checked as an integer relation between limbs, with a witnessed
+//! quotient by the modulus and carries between limbs that are range-checked with
+//! lookups. Elements are integers less than $2^{256}$ given as little-endian 64-bit
+//! words, and are only reduced modulo the field modulus by [`Element::reduce`].
+//!
+//! The relations are shared by the gadgets that verify signatures over non-native
+//! curves, and the [`Element`] gadget exposes them for a modulus chosen by the circuit,
+//! using a chip implementing [`ForeignFieldInstructions`] such as [`ForeignFieldChip`].
+
+use std::fmt;
+
+use crate::{
+    circuit::{Chip, Layouter},
+    plonk::Error,
+};
+
+mod chip;
+pub use chip::{AssignedBit, AssignedElement, ForeignFieldChip, ForeignFieldConfig};
+pub(crate) use chip::{
+    Coordinates, Operand, Relation, RelationConfig, SelectConfig, RELATION_ROWS,
+};
+
+/// The set of circuit instructions required to use the [`Element`] gadget.
+pub trait ForeignFieldInstructions: Chip {
+    /// Variable representing an integer that is congruent to a field element.
+    type Element: Clone + fmt::Debug;
+
+    /// Witnesses an integer less than $2^{256}$.
+    fn witness_element(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<[u64; 4]>,
+    ) -> Result<Self::Element, Error>;
+
+    /// Returns an element constrained to equal `value`.
+    fn constant(
+        layouter: &mut impl Layouter<Self>,
+        value: [u64; 4],
+    ) -> Result<Self::Element, Error>;
+
+    /// Returns an element congruent to `a + b`.
+    fn add(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Element,
+        b: &Self::Element,
+    ) -> Result<Self::Element, Error>;
+
+    /// Returns an element congruent to `a - b`.
+    fn sub(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Element,
+        b: &Self::Element,
+    ) -> Result<Self::Element, Error>;
+
+    /// Returns an element congruent to `a * b`.
+    fn mul(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Element,
+        b: &Self::Element,
+    ) -> Result<Self::Element, Error>;
+
+    /// Returns an element congruent to the inverse of `a`.
+    ///
+    /// The circuit is not satisfied if `a` is congruent to zero.
+    fn invert(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Element,
+    ) -> Result<Self::Element, Error>;
+
+    /// Returns the element congruent to `a` that is less than the modulus.
+    fn reduce(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Element,
+    ) -> Result<Self::Element, Error>;
+
+    /// Constrains `a` and `b` to be congruent.
+    fn constrain_equal(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Element,
+        b: &Self::Element,
+    ) -> Result<(), Error>;
+}
+
+/// An element of a foreign field, represented by an integer less than $2^{256}$.
+#[derive(Debug)]
+pub struct Element<FieldChip: ForeignFieldInstructions> {
+    inner: FieldChip::Element,
+}
+
+impl<FieldChip: ForeignFieldInstructions> Element<FieldChip> {
+    /// Witnesses an element, given as little-endian 64-bit words.
+    pub fn new(
+        mut layouter: impl Layouter<FieldChip>,
+        value: Option<[u64; 4]>,
+    ) -> Result<Self, Error> {
+        FieldChip::witness_element(&mut layouter, value).map(|inner| Element { inner })
+    }
+
+    /// Returns an element constrained to equal `value`.
+    pub fn constant(
+        mut layouter: impl Layouter<FieldChip>,
+        value: [u64; 4],
+    ) -> Result<Self, Error> {
+        FieldChip::constant(&mut layouter, value).map(|inner| Element { inner })
+    }
+
+    /// Wraps an element returned by the chip, for use by other gadgets.
+    pub fn from_inner(inner: FieldChip::Element) -> Self {
+        Element { inner }
+    }
+
+    /// Returns `self + other`.
+    pub fn add(&self, mut layouter: impl Layouter<FieldChip>, other: &Self) -> Result<Self, Error> {
+        FieldChip::add(&mut layouter, &self.inner, &other.inner).map(|inner| Element { inner })
+    }
+
+    /// Returns `self - other`.
+    pub fn sub(&self, mut layouter: impl Layouter<FieldChip>, other: &Self) -> Result<Self, Error> {
+        FieldChip::sub(&mut layouter, &self.inner, &other.inner).map(|inner| Element { inner })
+    }
+
+    /// Returns `self * other`.
+    pub fn mul(&self, mut layouter: impl Layouter<FieldChip>, other: &Self) -> Result<Self, Error> {
+        FieldChip::mul(&mut layouter, &self.inner, &other.inner).map(|inner| Element { inner })
+    }
+
+    /// Returns the inverse of `self`, which must be non-zero.
+    pub fn invert(&self, mut layouter: impl Layouter<FieldChip>) -> Result<Self, Error> {
+        FieldChip::invert(&mut layouter, &self.inner).map(|inner| Element { inner })
+    }
+
+    /// Returns the canonical representative of `self`, which is less than the modulus.
+    pub fn reduce(&self, mut layouter: impl Layouter<FieldChip>) -> Result<Self, Error> {
+        FieldChip::reduce(&mut layouter, &self.inner).map(|inner| Element { inner })
+    }
+
+    /// Constrains `self` and `other` to be the same field element.
+    pub fn constrain_equal(
+        &self,
+        mut layouter: impl Layouter<FieldChip>,
+        other: &Self,
+    ) -> Result<(), Error> {
+        FieldChip::constrain_equal(&mut layouter, &self.inner, &other.inner)
+    }
+
+    /// Returns the chip-specific representation of this element.
+    pub fn inner(&self) -> &FieldChip::Element {
+        &self.inner
+    }
+}
+
+impl<FieldChip: ForeignFieldInstructions> Clone for Element<FieldChip> {
+    fn clone(&self) -> Self {
+        Element {
+            inner: self.inner.clone(),
+        }
+    }
+}
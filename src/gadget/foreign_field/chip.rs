@@ -0,0 +1,1255 @@
+//! Relations between non-native field elements.
+//!
+//! An element is represented by three limbs $a = a_0 + 2^{88} a_1 + 2^{176} a_2$, of 88,
+//! 88 and 80 bits, so every element is an integer less than $2^{256}$ that need not be
+//! reduced. A relation row checks an identity
+//!
+//! $$k_1 x_1 y_1 + k_2 x_2 y_2 + k_3 l_1 + k_4 l_2 + k_5 r + c \equiv 0 \pmod{m}$$
+//!
+//! with small fixed coefficients $k_i$ and constant $c$, where $r$ is a new element
+//! produced by the relation, such as a product or a slope. The check is done over the
+//! integers, by witnessing a quotient $q' = q + 2^{261}$ and adding $2^{261} m$ to $c$,
+//! so that the identity becomes $\sum_j t_j 2^{88 j} = 0$ with column sums $t_j$ of
+//! products of limbs. Each column sum is less than $2^{190}$, so the identity is checked
+//! without overflowing the circuit field by propagating signed carries $v_j$ from
+//! column to column: $t_0 = 2^{88} v_0$, $t_j + v_{j - 1} = 2^{88} v_j$ and
+//! $t_5 + v_4 = 0$. Setting $m = 0$ instead checks the identity exactly, which is used
+//! to compare elements with constants.
+//!
+//! [`ForeignFieldChip`] checks each operation of [`ForeignFieldInstructions`] with a
+//! relation modulo a fixed prime $m$. The quotient of a product by $m$ must fit in the
+//! limbs of $q'$, so $m$ must be at least $2^{249}$.
+//!
+//! # Layout
+//!
+//! The limbs of the operands $x_1, y_1, x_2, y_2, l_1, l_2$ are copied into the 18
+//! `operands` columns of the first row of a relation, and the fixed columns of that row
+//! hold the coefficients, the modulus and the limbs of the constant. The limbs of $r$
+//! and $q'$ and the biased carries $v_j + 2^{95}$ are then placed in the two `values`
+//! columns of the relation's six rows, and every value is range-checked in the same row
+//! by decomposing it into twelve 8-bit chunks, which are looked up in a table. The
+//! limbs of $r$ use 88 or 80 bits, and the other values 96 bits.
+
+use std::convert::TryInto;
+use std::marker::PhantomData;
+
+use super::ForeignFieldInstructions;
+use crate::gadget::modexp::ModExpInstructions;
+use crate::{
+    arithmetic::FieldExt,
+    circuit::{Cell, Chip, Layouter, Region},
+    plonk::{
+        Advice, Any, Column, ConstraintSystem, Error, Expression, Fixed, Permutation, Selector,
+    },
+    poly::Rotation,
+};
+
+mod select;
+pub(crate) use select::{Coordinates, SelectConfig};
+
+mod window;
+pub use window::AssignedBit;
+use window::WindowConfig;
+
+/// The number of bits in each of the low limbs of an element.
+pub(crate) const LIMB_BITS: usize = 88;
+
+/// The number of bits in the top limb of an element.
+const TOP_LIMB_BITS: usize = 256 - 2 * LIMB_BITS;
+
+/// The number of bits in each range-checked chunk.
+pub(crate) const CHUNK_BITS: usize = 8;
+
+/// The number of chunks in a range check.
+pub(crate) const NUM_CHUNKS: usize = 12;
+
+/// The number of rows used by a relation.
+pub(crate) const RELATION_ROWS: usize = 6;
+
+/// The number of columns in the integer form of a relation.
+const NUM_COLUMNS: usize = 6;
+
+/// The offset added to the quotient of a relation to make it non-negative.
+const QUOTIENT_SHIFT: usize = 261;
+
+/// The offset added to each carry to make it non-negative.
+const CARRY_SHIFT: u32 = 95;
+
+/// A non-native field element assigned in the circuit, as three limbs.
+///
+/// The limbs of an element are range-checked, so that it is an integer less than
+/// $2^{256}$, but it is not necessarily reduced.
+#[derive(Clone, Copy, Debug)]
+pub struct AssignedElement {
+    pub(crate) cells: [Cell; 3],
+    pub(crate) value: Option<[u64; 4]>,
+}
+
+impl AssignedElement {
+    /// The cells containing the limbs of this element, from the least significant.
+    pub fn cells(&self) -> [Cell; 3] {
+        self.cells
+    }
+
+    /// The value of this element as little-endian 64-bit words, if known.
+    pub fn value(&self) -> Option<[u64; 4]> {
+        self.value
+    }
+}
+
+/// The columns and selectors used to check relations between elements.
+#[derive(Clone, Debug)]
+pub(crate) struct RelationConfig {
+    operands: [Column<Advice>; 18],
+    pub(crate) values: [Column<Advice>; 2],
+    pub(crate) chunks: [[Column<Advice>; NUM_CHUNKS]; 2],
+    coefficients: [Column<Fixed>; 5],
+    modulus: [Column<Fixed>; 3],
+    constants: [Column<Fixed>; 6],
+    table: Column<Fixed>,
+    s_relation: Selector,
+    s_range: [Selector; 2],
+    s_narrow: [Selector; 2],
+    s_top: Selector,
+    pub(crate) perm: Permutation,
+}
+
+/// An operand of a relation.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Operand<'a> {
+    /// A previously assigned element.
+    Element(&'a AssignedElement),
+    /// The element produced by the relation itself.
+    Output,
+}
+
+/// A relation between elements, checked by [`RelationConfig::assign_relation`].
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Relation<'a> {
+    /// The modulus of the relation, or `None` to check it over the integers.
+    pub(crate) modulus: Option<[u64; 4]>,
+    /// At most two products $k x y$.
+    pub(crate) products: Vec<(i64, Operand<'a>, Operand<'a>)>,
+    /// At most two linear terms $k l$.
+    pub(crate) linear: Vec<(i64, &'a AssignedElement)>,
+    /// The coefficient of the output.
+    pub(crate) output: i64,
+    /// The constant term, as a coefficient and an integer.
+    pub(crate) constant: (i64, [u64; 4]),
+}
+
+/// Returns the limbs of an element.
+pub(crate) fn limbs(value: &[u64; 4]) -> [u128; 3] {
+    let value = Uint::from_limbs(value);
+    [value.limb(0), value.limb(1), value.limb(2)]
+}
+
+fn signed<F: FieldExt>(value: i64) -> F {
+    let magnitude = F::from_u64(value.unsigned_abs());
+    if value < 0 {
+        -magnitude
+    } else {
+        magnitude
+    }
+}
+
+fn limb_field<F: FieldExt>(value: &[u64; 4]) -> [F; 3] {
+    let limbs = limbs(value);
+    [
+        F::from_u128(limbs[0]),
+        F::from_u128(limbs[1]),
+        F::from_u128(limbs[2]),
+    ]
+}
+
+/// An unsigned integer wide enough for the integer form of a relation.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct Uint([u64; 9]);
+
+impl Uint {
+    fn from_limbs(value: &[u64; 4]) -> Self {
+        let mut res = Uint::default();
+        res.0[..4].copy_from_slice(value);
+        res
+    }
+
+    fn product(a: &[u64; 4], b: &[u64; 4]) -> Self {
+        let mut res = Uint::default();
+        for (i, a_i) in a.iter().enumerate() {
+            let mut carry = 0;
+            for (j, b_j) in b.iter().enumerate() {
+                let (limb, c) = crate::arithmetic::mac(res.0[i + j], *a_i, *b_j, carry);
+                res.0[i + j] = limb;
+                carry = c;
+            }
+            res.0[i + 4] = carry;
+        }
+        res
+    }
+
+    fn scale(&self, k: u64) -> Self {
+        let mut res = Uint::default();
+        let mut carry = 0;
+        for (res, limb) in res.0.iter_mut().zip(self.0.iter()) {
+            let (l, c) = crate::arithmetic::mac(0, *limb, k, carry);
+            *res = l;
+            carry = c;
+        }
+        res
+    }
+
+    fn add(&self, other: &Self) -> Self {
+        let mut res = Uint::default();
+        let mut carry = 0;
+        for i in 0..res.0.len() {
+            let (limb, c) = crate::arithmetic::adc(self.0[i], other.0[i], carry);
+            res.0[i] = limb;
+            carry = c;
+        }
+        res
+    }
+
+    fn checked_sub(&self, other: &Self) -> Option<Self> {
+        let mut res = Uint::default();
+        let mut borrow = 0;
+        for i in 0..res.0.len() {
+            let (limb, b) = crate::arithmetic::sbb(self.0[i], other.0[i], borrow);
+            res.0[i] = limb;
+            borrow = b;
+        }
+        if borrow == 0 {
+            Some(res)
+        } else {
+            None
+        }
+    }
+
+    fn shl(&self, bits: usize) -> Self {
+        let (words, bits) = (bits / 64, bits % 64);
+        let mut res = Uint::default();
+        for i in words..res.0.len() {
+            res.0[i] = self.0[i - words] << bits;
+            if bits > 0 && i > words {
+                res.0[i] |= self.0[i - words - 1] >> (64 - bits);
+            }
+        }
+        res
+    }
+
+    fn bit(&self, i: usize) -> bool {
+        (self.0[i / 64] >> (i % 64)) & 1 == 1
+    }
+
+    /// Returns the quotient of this integer by `m`, rounded down.
+    fn div(&self, m: &[u64; 4]) -> Self {
+        self.div_rem(m).0
+    }
+
+    /// Returns the remainder of this integer modulo `m`.
+    fn rem(&self, m: &[u64; 4]) -> [u64; 4] {
+        let remainder = self.div_rem(m).1;
+        remainder.0[..4].try_into().unwrap()
+    }
+
+    fn div_rem(&self, m: &[u64; 4]) -> (Self, Self) {
+        let m = Uint::from_limbs(m);
+        let mut quotient = Uint::default();
+        let mut remainder = Uint::default();
+        for i in (0..self.0.len() * 64).rev() {
+            remainder = remainder.shl(1);
+            remainder.0[0] |= self.bit(i) as u64;
+            if let Some(reduced) = remainder.checked_sub(&m) {
+                remainder = reduced;
+                quotient.0[i / 64] |= 1 << (i % 64);
+            }
+        }
+        (quotient, remainder)
+    }
+
+    /// Returns the `k`-th 88-bit limb of this integer.
+    fn limb(&self, k: usize) -> u128 {
+        (0..LIMB_BITS)
+            .map(|i| k * LIMB_BITS + i)
+            .filter(|i| *i < self.0.len() * 64)
+            .fold(0, |acc, i| {
+                acc | (self.bit(i) as u128) << (i - k * LIMB_BITS)
+            })
+    }
+}
+
+/// Returns `m - 1`, for an odd modulus `m`.
+fn predecessor(m: [u64; 4]) -> [u64; 4] {
+    [m[0] - 1, m[1], m[2], m[3]]
+}
+
+impl RelationConfig {
+    /// Configures the columns and gates for relations.
+    ///
+    /// This allocates 44 advice columns, 20 of which are placed in a permutation, and
+    /// 15 fixed columns.
+    pub(crate) fn configure<F: FieldExt>(meta: &mut ConstraintSystem<F>) -> Self {
+        Self::configure_with_permutation(meta, &[])
+    }
+
+    /// Configures the columns and gates for relations, with `columns` of other chips
+    /// added to the permutation, so that elements can be copied from and to them.
+    pub(crate) fn configure_with_permutation<F: FieldExt>(
+        meta: &mut ConstraintSystem<F>,
+        columns: &[Column<Any>],
+    ) -> Self {
+        let advice = |meta: &mut ConstraintSystem<F>, n: usize| -> Vec<Column<Advice>> {
+            (0..n).map(|_| meta.advice_column()).collect()
+        };
+        let fixed = |meta: &mut ConstraintSystem<F>, n: usize| -> Vec<Column<Fixed>> {
+            (0..n).map(|_| meta.fixed_column()).collect()
+        };
+
+        let operands = advice(meta, 18);
+        let values = advice(meta, 2);
+        let chunks = [
+            advice(meta, NUM_CHUNKS).try_into().unwrap(),
+            advice(meta, NUM_CHUNKS).try_into().unwrap(),
+        ];
+        let coefficients = fixed(meta, 5);
+        let modulus = fixed(meta, 3);
+        let constants = fixed(meta, 6);
+        let table = meta.fixed_column();
+
+        let perm = Permutation::new(
+            meta,
+            &operands
+                .iter()
+                .chain(values.iter())
+                .map(|column| (*column).into())
+                .chain(columns.iter().copied())
+                .collect::<Vec<_>>(),
+        );
+
+        let config = RelationConfig {
+            operands: operands.try_into().unwrap(),
+            values: values.try_into().unwrap(),
+            chunks,
+            coefficients: coefficients.try_into().unwrap(),
+            modulus: modulus.try_into().unwrap(),
+            constants: constants.try_into().unwrap(),
+            table,
+            s_relation: meta.selector(),
+            s_range: [meta.selector(), meta.selector()],
+            s_narrow: [meta.selector(), meta.selector()],
+            s_top: meta.selector(),
+            perm,
+        };
+        config.create_gates(meta);
+
+        config
+    }
+
+    /// Loads the range-check table, which uses 256 rows.
+    pub(crate) fn load<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        layouter: &mut impl Layouter<Ch>,
+    ) -> Result<(), Error> {
+        let table = self.table;
+
+        layouter.assign_region(
+            || "range-check table",
+            |mut region| {
+                for value in 0..(1 << CHUNK_BITS) {
+                    region.assign_fixed(
+                        || "table",
+                        table,
+                        value,
+                        || Ok(F::from_u64(value as u64)),
+                    )?;
+                }
+                Ok(())
+            },
+        )
+    }
+
+    fn create_gates<F: FieldExt>(&self, meta: &mut ConstraintSystem<F>) {
+        // Each value is the sum of its chunks, and the top chunks of the narrower values
+        // are zero.
+        for half in 0..2 {
+            meta.create_gate("range check", |meta| {
+                let value = meta.query_advice(self.values[half], Rotation::cur());
+                let s_range = meta.query_selector(self.s_range[half], Rotation::cur());
+                let sum = self.chunks[half].iter().enumerate().fold(
+                    Expression::Constant(F::zero()),
+                    |acc, (j, chunk)| {
+                        acc + meta.query_advice(*chunk, Rotation::cur())
+                            * F::from_u128(1 << (CHUNK_BITS * j))
+                    },
+                );
+
+                s_range * (value - sum)
+            });
+            meta.create_gate("range check", |meta| {
+                let chunk = meta.query_advice(self.chunks[half][NUM_CHUNKS - 1], Rotation::cur());
+                let s_narrow = meta.query_selector(self.s_narrow[half], Rotation::cur());
+
+                s_narrow * chunk
+            });
+        }
+        meta.create_gate("range check", |meta| {
+            let chunk = meta.query_advice(self.chunks[0][NUM_CHUNKS - 2], Rotation::cur());
+            let s_top = meta.query_selector(self.s_top, Rotation::cur());
+
+            s_top * chunk
+        });
+
+        for chunk in self.chunks.iter().flat_map(|chunks| chunks.iter()) {
+            let input = meta.query_advice(*chunk, Rotation::cur());
+            let table = meta.query_fixed(self.table, Rotation::cur());
+            meta.lookup(&[input], &[table]);
+        }
+
+        // The column sums and carries of the relation.
+        let two_pow_limb = F::from_u128(1 << LIMB_BITS);
+        let carry_shift = F::from_u128(1 << CARRY_SHIFT);
+        for j in 0..NUM_COLUMNS {
+            meta.create_gate("foreign-field relation", |meta| {
+                let operand = |meta: &mut ConstraintSystem<F>, slot: usize, limb: usize| {
+                    meta.query_advice(self.operands[3 * slot + limb], Rotation::cur())
+                };
+                // The values of the relation in order: r, q' and the carries.
+                let value = |meta: &mut ConstraintSystem<F>, index: usize| {
+                    meta.query_advice(self.values[index % 2], Rotation((index / 2) as i32))
+                };
+                let coefficient = |meta: &mut ConstraintSystem<F>, index: usize| {
+                    meta.query_fixed(self.coefficients[index], Rotation::cur())
+                };
+                let carry = |meta: &mut ConstraintSystem<F>, k: usize| {
+                    value(meta, 6 + k) - Expression::Constant(carry_shift)
+                };
+                let pairs = (0..3).flat_map(|a| (0..3).map(move |b| (a, b)));
+
+                let mut sum = meta.query_fixed(self.constants[j], Rotation::cur());
+                for product in 0..2 {
+                    let k = coefficient(meta, product);
+                    for (a, b) in pairs.clone().filter(|(a, b)| a + b == j) {
+                        sum = sum
+                            + k.clone()
+                                * operand(meta, 2 * product, a)
+                                * operand(meta, 2 * product + 1, b);
+                    }
+                }
+                if j < 3 {
+                    for term in 0..2 {
+                        sum = sum + coefficient(meta, 2 + term) * operand(meta, 4 + term, j);
+                    }
+                    sum = sum + coefficient(meta, 4) * value(meta, j);
+                }
+                for (a, b) in pairs.filter(|(a, b)| a + b == j) {
+                    sum = sum
+                        - value(meta, 3 + a) * meta.query_fixed(self.modulus[b], Rotation::cur());
+                }
+                if j > 0 {
+                    sum = sum + carry(meta, j - 1);
+                }
+                if j + 1 < NUM_COLUMNS {
+                    sum = sum - carry(meta, j) * two_pow_limb;
+                }
+                let s_relation = meta.query_selector(self.s_relation, Rotation::cur());
+
+                s_relation * sum
+            });
+        }
+    }
+
+    /// Assigns a range-checked value as value `index` of the relation at `offset`.
+    fn assign_range<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        region: &mut Region<'_, Ch>,
+        offset: usize,
+        index: usize,
+        value: Option<F>,
+        bits: usize,
+    ) -> Result<Cell, Error> {
+        let (row, half) = (offset + index / 2, index % 2);
+        self.s_range[half].enable(region, row)?;
+        if bits <= LIMB_BITS {
+            self.s_narrow[half].enable(region, row)?;
+        }
+        if bits <= TOP_LIMB_BITS {
+            assert_eq!(half, 0);
+            self.s_top.enable(region, row)?;
+        }
+
+        let bytes = value.map(|value| value.to_bytes());
+        for (j, chunk) in self.chunks[half].iter().enumerate() {
+            region.assign_advice(
+                || format!("chunk {}", j),
+                *chunk,
+                row,
+                || {
+                    bytes
+                        .map(|bytes| F::from_u64(bytes[j] as u64))
+                        .ok_or(Error::SynthesisError)
+                },
+            )?;
+        }
+        region.assign_advice(
+            || "value",
+            self.values[half],
+            row,
+            || value.ok_or(Error::SynthesisError),
+        )
+    }
+
+    /// Assigns `relation` at `offset`, whose output has the value `output`, and returns
+    /// the output.
+    ///
+    /// The relation uses [`RELATION_ROWS`] rows.
+    pub(crate) fn assign_relation<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        region: &mut Region<'_, Ch>,
+        offset: usize,
+        relation: &Relation<'_>,
+        output: Option<[u64; 4]>,
+    ) -> Result<AssignedElement, Error> {
+        assert!(relation.products.len() <= 2 && relation.linear.len() <= 2);
+        self.s_relation.enable(region, offset)?;
+
+        let output_limbs = output.map(|output| limb_field::<F>(&output));
+        let mut cells = vec![];
+        for i in 0..3 {
+            let bits = if i == 2 { TOP_LIMB_BITS } else { LIMB_BITS };
+            cells.push(self.assign_range(region, offset, i, output_limbs.map(|l| l[i]), bits)?);
+        }
+        let output_element = AssignedElement {
+            cells: [cells[0], cells[1], cells[2]],
+            value: output,
+        };
+        let resolve = |operand: &Operand<'_>| match operand {
+            Operand::Element(element) => **element,
+            Operand::Output => output_element,
+        };
+
+        // Collect the operands and coefficients in slot order.
+        let mut operands = [None; 6];
+        let mut coefficients = [0; 5];
+        for (i, (k, x, y)) in relation.products.iter().enumerate() {
+            coefficients[i] = *k;
+            operands[2 * i] = Some(resolve(x));
+            operands[2 * i + 1] = Some(resolve(y));
+        }
+        for (i, (k, l)) in relation.linear.iter().enumerate() {
+            coefficients[2 + i] = *k;
+            operands[4 + i] = Some(**l);
+        }
+        coefficients[4] = relation.output;
+        assert!(coefficients.iter().all(|k| k.abs() <= 3));
+
+        for (column, k) in self.coefficients.iter().zip(coefficients.iter()) {
+            region.assign_fixed(|| "coefficient", *column, offset, || Ok(signed::<F>(*k)))?;
+        }
+        for (slot, operand) in operands.iter().enumerate() {
+            if let Some(operand) = operand {
+                let limbs = operand.value.map(|value| limb_field::<F>(&value));
+                for (i, source) in operand.cells.iter().enumerate() {
+                    let cell = region.assign_advice(
+                        || "operand",
+                        self.operands[3 * slot + i],
+                        offset,
+                        || limbs.map(|limbs| limbs[i]).ok_or(Error::SynthesisError),
+                    )?;
+                    region.constrain_equal(&self.perm, *source, cell)?;
+                }
+            }
+        }
+
+        let modulus = relation.modulus.unwrap_or([0; 4]);
+        let modulus_limbs = limb_field::<F>(&modulus);
+        for (column, limb) in self.modulus.iter().zip(modulus_limbs.iter()) {
+            region.assign_fixed(|| "modulus", *column, offset, || Ok(*limb))?;
+        }
+
+        // The constant term includes the shift of the quotient.
+        let shift = Uint::from_limbs(&modulus).shl(QUOTIENT_SHIFT);
+        let (k, constant) = relation.constant;
+        let constant = Uint::from_limbs(&constant).scale(k.unsigned_abs());
+        let (constant, negative) = if k >= 0 {
+            (shift.add(&constant), false)
+        } else {
+            match shift.checked_sub(&constant) {
+                Some(constant) => (constant, false),
+                None => (constant.checked_sub(&shift).unwrap(), true),
+            }
+        };
+        let constant_limbs: Vec<F> = (0..NUM_COLUMNS)
+            .map(|j| {
+                let limb = F::from_u128(constant.limb(j));
+                if negative {
+                    -limb
+                } else {
+                    limb
+                }
+            })
+            .collect();
+        for (column, limb) in self.constants.iter().zip(constant_limbs.iter()) {
+            region.assign_fixed(|| "constant", *column, offset, || Ok(*limb))?;
+        }
+
+        // Compute the quotient over the integers. If the relation does not hold, the
+        // witness is still assigned, and the carries will not satisfy the constraints.
+        let values: Option<Vec<[u64; 4]>> = operands
+            .iter()
+            .map(|operand| operand.map_or(Some([0; 4]), |operand| operand.value))
+            .collect();
+        let quotient = values.clone().zip(output).map(|(values, output)| {
+            let (mut positive, mut negative) = if negative {
+                (Uint::default(), constant)
+            } else {
+                (constant, Uint::default())
+            };
+            let mut accumulate = |k: i64, term: Uint| {
+                let term = term.scale(k.unsigned_abs());
+                if k < 0 {
+                    negative = negative.add(&term);
+                } else {
+                    positive = positive.add(&term);
+                }
+            };
+            for i in 0..2 {
+                accumulate(
+                    coefficients[i],
+                    Uint::product(&values[2 * i], &values[2 * i + 1]),
+                );
+                accumulate(coefficients[2 + i], Uint::from_limbs(&values[4 + i]));
+            }
+            accumulate(coefficients[4], Uint::from_limbs(&output));
+
+            match (positive.checked_sub(&negative), relation.modulus) {
+                (Some(total), Some(modulus)) => total.div(&modulus),
+                _ => Uint::default(),
+            }
+        });
+        let quotient_limbs = quotient.map(|q| {
+            [
+                F::from_u128(q.limb(0)),
+                F::from_u128(q.limb(1)),
+                F::from_u128(q.limb(2)),
+            ]
+        });
+        for i in 0..3 {
+            self.assign_range(
+                region,
+                offset,
+                3 + i,
+                quotient_limbs.map(|q| q[i]),
+                NUM_CHUNKS * CHUNK_BITS,
+            )?;
+        }
+
+        // Compute the carries in the circuit field, exactly as in the constraints.
+        let carries =
+            values
+                .zip(output_limbs)
+                .zip(quotient_limbs)
+                .map(|((values, output), quotient)| {
+                    let values: Vec<_> = values.iter().map(limb_field::<F>).collect();
+                    let k: Vec<F> = coefficients.iter().map(|k| signed(*k)).collect();
+                    let two_pow_limb_inv = F::from_u128(1 << LIMB_BITS).invert().unwrap();
+                    let mut carries = [F::zero(); NUM_COLUMNS - 1];
+                    let mut carry = F::zero();
+                    for (j, carry_j) in carries.iter_mut().enumerate() {
+                        let mut sum = constant_limbs[j] + carry;
+                        for a in 0..3 {
+                            for b in 0..3 {
+                                if a + b == j {
+                                    sum += k[0] * values[0][a] * values[1][b]
+                                        + k[1] * values[2][a] * values[3][b]
+                                        - quotient[a] * modulus_limbs[b];
+                                }
+                            }
+                        }
+                        if j < 3 {
+                            sum += k[2] * values[4][j] + k[3] * values[5][j] + k[4] * output[j];
+                        }
+                        carry = sum * two_pow_limb_inv;
+                        *carry_j = carry + F::from_u128(1 << CARRY_SHIFT);
+                    }
+                    carries
+                });
+        for i in 0..(NUM_COLUMNS - 1) {
+            self.assign_range(
+                region,
+                offset,
+                6 + i,
+                carries.map(|c| c[i]),
+                NUM_CHUNKS * CHUNK_BITS,
+            )?;
+        }
+
+        Ok(output_element)
+    }
+
+    /// Assigns an element at `offset` that is only range-checked.
+    pub(crate) fn assign_element<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        region: &mut Region<'_, Ch>,
+        offset: usize,
+        value: Option<[u64; 4]>,
+    ) -> Result<AssignedElement, Error> {
+        self.assign_relation(region, offset, &Relation::default(), value)
+    }
+
+    /// Constrains `value` to be less than `modulus`, by witnessing
+    /// `modulus - 1 - value` at `offset`.
+    pub(crate) fn assign_canonical<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        region: &mut Region<'_, Ch>,
+        offset: usize,
+        value: &AssignedElement,
+        modulus: [u64; 4],
+    ) -> Result<(), Error> {
+        let max = predecessor(modulus);
+        let difference = value.value.map(|value| {
+            let mut res = [0; 4];
+            let mut borrow = 0;
+            for i in 0..4 {
+                let (limb, b) = crate::arithmetic::sbb(max[i], value[i], borrow);
+                res[i] = limb;
+                borrow = b;
+            }
+            res
+        });
+        let relation = Relation {
+            linear: vec![(1, value)],
+            output: 1,
+            constant: (-1, max),
+            ..Default::default()
+        };
+        self.assign_relation(region, offset, &relation, difference)
+            .map(|_| ())
+    }
+
+    /// Assigns an element at `offset` that is constrained to equal `value`.
+    pub(crate) fn assign_constant<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        region: &mut Region<'_, Ch>,
+        offset: usize,
+        value: [u64; 4],
+    ) -> Result<AssignedElement, Error> {
+        let relation = Relation {
+            output: -1,
+            constant: (1, value),
+            ..Default::default()
+        };
+        self.assign_relation(region, offset, &relation, Some(value))
+    }
+}
+
+/// Configuration for a [`ForeignFieldChip`].
+#[derive(Clone, Debug)]
+pub struct ForeignFieldConfig {
+    pub(crate) relations: RelationConfig,
+    window: WindowConfig,
+    modulus: [u64; 4],
+}
+
+/// A chip implementing [`ForeignFieldInstructions`] for a prime modulus chosen when the
+/// circuit is configured.
+///
+/// Each operation uses one relation, except for [`ForeignFieldInstructions::reduce`],
+/// which uses two.
+#[derive(Debug)]
+pub struct ForeignFieldChip<F: FieldExt> {
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt> ForeignFieldChip<F> {
+    /// Configures this chip for arithmetic modulo `modulus`, given as little-endian
+    /// 64-bit words.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `modulus` is even, or not between $2^{249}$ and $2^{256}$, which bounds
+    /// the quotients of the relations. The modulus must also be prime for
+    /// [`ForeignFieldInstructions::invert`] to compute inverses.
+    pub fn configure(meta: &mut ConstraintSystem<F>, modulus: [u64; 4]) -> ForeignFieldConfig {
+        Self::configure_with_permutation(meta, modulus, &[])
+    }
+
+    /// Configures this chip as [`ForeignFieldChip::configure`] does, with `columns` of
+    /// other chips added to the permutation of the relations.
+    pub(crate) fn configure_with_permutation(
+        meta: &mut ConstraintSystem<F>,
+        modulus: [u64; 4],
+        columns: &[Column<Any>],
+    ) -> ForeignFieldConfig {
+        assert!(modulus[0] & 1 == 1 && modulus[3] >> 57 != 0);
+
+        let relations = RelationConfig::configure_with_permutation(meta, columns);
+        let window = WindowConfig::configure(meta, &relations);
+
+        ForeignFieldConfig {
+            relations,
+            window,
+            modulus,
+        }
+    }
+}
+
+impl<F: FieldExt> Chip for ForeignFieldChip<F> {
+    type Config = ForeignFieldConfig;
+    type Loaded = ();
+    type Field = F;
+
+    fn load(layouter: &mut impl Layouter<Self>) -> Result<(), Error> {
+        let config = layouter.config().relations.clone();
+        config.load(layouter)
+    }
+}
+
+impl ForeignFieldConfig {
+    /// The modulus of the field, as little-endian 64-bit words.
+    pub fn modulus(&self) -> [u64; 4] {
+        self.modulus
+    }
+
+    /// Assigns `relation` modulo the field modulus in its own region.
+    fn relation<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        layouter: &mut impl Layouter<Ch>,
+        relation: Relation<'_>,
+        output: Option<[u64; 4]>,
+    ) -> Result<AssignedElement, Error> {
+        let relation = Relation {
+            modulus: Some(self.modulus),
+            ..relation
+        };
+        layouter.assign_region(
+            || "foreign-field relation",
+            |mut region| {
+                self.relations
+                    .assign_relation(&mut region, 0, &relation, output)
+            },
+        )
+    }
+}
+
+/// Returns the inverse of `a` modulo the prime `m`, or zero if there is none.
+fn invert_mod(a: &[u64; 4], m: &[u64; 4]) -> [u64; 4] {
+    let exponent = Uint::from_limbs(m)
+        .checked_sub(&Uint::from_limbs(&[2, 0, 0, 0]))
+        .unwrap();
+    (0..256).rev().fold([1, 0, 0, 0], |acc, i| {
+        let acc = Uint::product(&acc, &acc).rem(m);
+        if exponent.bit(i) {
+            Uint::product(&acc, a).rem(m)
+        } else {
+            acc
+        }
+    })
+}
+
+/// The instructions of [`ForeignFieldChip`], for use by other chips that include a
+/// [`ForeignFieldConfig`] in their own configuration.
+impl ForeignFieldConfig {
+    /// Witnesses an integer less than $2^{256}$.
+    pub(crate) fn witness_element<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        layouter: &mut impl Layouter<Ch>,
+        value: Option<[u64; 4]>,
+    ) -> Result<AssignedElement, Error> {
+        layouter.assign_region(
+            || "witness element",
+            |mut region| self.relations.assign_element(&mut region, 0, value),
+        )
+    }
+
+    /// Returns an element constrained to equal `value`.
+    pub(crate) fn constant<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        layouter: &mut impl Layouter<Ch>,
+        value: [u64; 4],
+    ) -> Result<AssignedElement, Error> {
+        layouter.assign_region(
+            || "constant",
+            |mut region| self.relations.assign_constant(&mut region, 0, value),
+        )
+    }
+
+    /// Returns an element congruent to `a + b`.
+    pub(crate) fn add<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        layouter: &mut impl Layouter<Ch>,
+        a: &AssignedElement,
+        b: &AssignedElement,
+    ) -> Result<AssignedElement, Error> {
+        self.combine(layouter, &[], &[(1, a), (1, b)], (0, [0; 4]))
+    }
+
+    /// Returns an element congruent to `a - b`.
+    pub(crate) fn sub<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        layouter: &mut impl Layouter<Ch>,
+        a: &AssignedElement,
+        b: &AssignedElement,
+    ) -> Result<AssignedElement, Error> {
+        self.combine(layouter, &[], &[(1, a), (-1, b)], (0, [0; 4]))
+    }
+
+    /// Returns an element congruent to `a * b`.
+    pub(crate) fn mul<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        layouter: &mut impl Layouter<Ch>,
+        a: &AssignedElement,
+        b: &AssignedElement,
+    ) -> Result<AssignedElement, Error> {
+        self.combine(layouter, &[(1, a, b)], &[], (0, [0; 4]))
+    }
+
+    /// Returns the reduced element congruent to
+    /// $\sum_i k_i x_i y_i + \sum_j k_j l_j + k c$ for the given `products`, `linear`
+    /// terms and `constant`, with a single relation.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are more than two products or linear terms, or a coefficient is
+    /// greater than 3 in absolute value.
+    pub(crate) fn combine<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        layouter: &mut impl Layouter<Ch>,
+        products: &[(i64, &AssignedElement, &AssignedElement)],
+        linear: &[(i64, &AssignedElement)],
+        constant: (i64, [u64; 4]),
+    ) -> Result<AssignedElement, Error> {
+        let m = self.modulus;
+        let terms: Option<Vec<(i64, Uint)>> = products
+            .iter()
+            .map(|(k, x, y)| {
+                x.value
+                    .zip(y.value)
+                    .map(|(x, y)| (*k, Uint::product(&x, &y)))
+            })
+            .chain(
+                linear
+                    .iter()
+                    .map(|(k, l)| l.value.map(|l| (*k, Uint::from_limbs(&l)))),
+            )
+            .chain(Some(Some((constant.0, Uint::from_limbs(&constant.1)))))
+            .collect();
+        let value = terms.map(|terms| {
+            // Each term is reduced before it is subtracted, so the sum stays non-negative.
+            let (positive, negative) = terms.iter().fold(
+                (Uint::default(), Uint::default()),
+                |(positive, negative), (k, term)| {
+                    let term = Uint::from_limbs(&term.rem(&m)).scale(k.unsigned_abs());
+                    if *k < 0 {
+                        (positive, negative.add(&term))
+                    } else {
+                        (positive.add(&term), negative)
+                    }
+                },
+            );
+            let negative = negative.rem(&m);
+            Uint::from_limbs(&positive.rem(&m))
+                .add(&Uint::from_limbs(&m))
+                .checked_sub(&Uint::from_limbs(&negative))
+                .unwrap()
+                .rem(&m)
+        });
+
+        let relation = Relation {
+            products: products
+                .iter()
+                .map(|(k, x, y)| (*k, Operand::Element(x), Operand::Element(y)))
+                .collect(),
+            linear: linear.to_vec(),
+            output: -1,
+            constant,
+            ..Default::default()
+        };
+        self.relation(layouter, relation, value)
+    }
+
+    /// Returns an element congruent to the inverse of `a`.
+    pub(crate) fn invert<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        layouter: &mut impl Layouter<Ch>,
+        a: &AssignedElement,
+    ) -> Result<AssignedElement, Error> {
+        let m = self.modulus;
+        let inverse = a
+            .value
+            .map(|a| invert_mod(&Uint::from_limbs(&a).rem(&m), &m));
+        let relation = Relation {
+            products: vec![(1, Operand::Element(a), Operand::Output)],
+            constant: (-1, [1, 0, 0, 0]),
+            ..Default::default()
+        };
+        self.relation(layouter, relation, inverse)
+    }
+
+    /// Returns the element congruent to `a` that is less than the modulus.
+    pub(crate) fn reduce<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        layouter: &mut impl Layouter<Ch>,
+        a: &AssignedElement,
+    ) -> Result<AssignedElement, Error> {
+        let m = self.modulus;
+        let reduced = a.value.map(|a| Uint::from_limbs(&a).rem(&m));
+        let relation = Relation {
+            modulus: Some(m),
+            linear: vec![(1, a)],
+            output: -1,
+            ..Default::default()
+        };
+        layouter.assign_region(
+            || "reduce",
+            |mut region| {
+                let reduced = self
+                    .relations
+                    .assign_relation(&mut region, 0, &relation, reduced)?;
+                self.relations
+                    .assign_canonical(&mut region, RELATION_ROWS, &reduced, m)?;
+                Ok(reduced)
+            },
+        )
+    }
+
+    /// Constrains `a` and `b` to be congruent.
+    pub(crate) fn constrain_equal<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        layouter: &mut impl Layouter<Ch>,
+        a: &AssignedElement,
+        b: &AssignedElement,
+    ) -> Result<(), Error> {
+        let relation = Relation {
+            linear: vec![(1, a), (-1, b)],
+            ..Default::default()
+        };
+        self.relation(layouter, relation, Some([0; 4])).map(|_| ())
+    }
+
+    /// Decomposes `e` into `num_bits` bits, and returns them from the most
+    /// significant.
+    pub(crate) fn decompose<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        layouter: &mut impl Layouter<Ch>,
+        e: &AssignedElement,
+        num_bits: usize,
+    ) -> Result<Vec<AssignedBit>, Error> {
+        layouter.assign_region(
+            || "decompose",
+            |mut region| {
+                self.window
+                    .assign_decomposition(&self.relations, &mut region, 0, e, num_bits)
+            },
+        )
+    }
+}
+
+impl<F: FieldExt> ForeignFieldInstructions for ForeignFieldChip<F> {
+    type Element = AssignedElement;
+
+    fn witness_element(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<[u64; 4]>,
+    ) -> Result<Self::Element, Error> {
+        layouter.config().clone().witness_element(layouter, value)
+    }
+
+    fn constant(
+        layouter: &mut impl Layouter<Self>,
+        value: [u64; 4],
+    ) -> Result<Self::Element, Error> {
+        layouter.config().clone().constant(layouter, value)
+    }
+
+    fn add(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Element,
+        b: &Self::Element,
+    ) -> Result<Self::Element, Error> {
+        layouter.config().clone().add(layouter, a, b)
+    }
+
+    fn sub(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Element,
+        b: &Self::Element,
+    ) -> Result<Self::Element, Error> {
+        layouter.config().clone().sub(layouter, a, b)
+    }
+
+    fn mul(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Element,
+        b: &Self::Element,
+    ) -> Result<Self::Element, Error> {
+        layouter.config().clone().mul(layouter, a, b)
+    }
+
+    fn invert(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Element,
+    ) -> Result<Self::Element, Error> {
+        layouter.config().clone().invert(layouter, a)
+    }
+
+    fn reduce(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Element,
+    ) -> Result<Self::Element, Error> {
+        layouter.config().clone().reduce(layouter, a)
+    }
+
+    fn constrain_equal(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Element,
+        b: &Self::Element,
+    ) -> Result<(), Error> {
+        layouter.config().clone().constrain_equal(layouter, a, b)
+    }
+}
+
+impl<F: FieldExt> ModExpInstructions for ForeignFieldChip<F> {
+    type Bit = AssignedBit;
+
+    fn decompose(
+        layouter: &mut impl Layouter<Self>,
+        e: &Self::Element,
+        num_bits: usize,
+    ) -> Result<Vec<Self::Bit>, Error> {
+        layouter.config().clone().decompose(layouter, e, num_bits)
+    }
+
+    fn select(
+        layouter: &mut impl Layouter<Self>,
+        bits: &[Self::Bit],
+        table: &[Self::Element],
+    ) -> Result<Self::Element, Error> {
+        assert_eq!(table.len(), 1 << bits.len());
+        if bits.is_empty() {
+            return Ok(table[0]);
+        }
+
+        let config = layouter.config().clone();
+        layouter.assign_region(
+            || "select",
+            |mut region| {
+                // Each level of the tree halves the candidates by one bit.
+                let mut level = table.to_vec();
+                let mut row = 0;
+                for bit in bits {
+                    let mut next = Vec::with_capacity(level.len() / 2);
+                    for pair in level.chunks(2) {
+                        next.push(config.window.assign_mux(
+                            &config.relations,
+                            &mut region,
+                            row,
+                            bit,
+                            &pair[0],
+                            &pair[1],
+                        )?);
+                        row += 1;
+                    }
+                    level = next;
+                }
+                Ok(level[0])
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{limbs, ForeignFieldChip, ForeignFieldConfig, Uint};
+    use crate::{
+        circuit::{layouter, Layouter},
+        dev::MockProver,
+        gadget::foreign_field::Element,
+        pasta::Fp,
+        plonk::{Assignment, Circuit, ConstraintSystem, Error},
+        primitives::ed25519::Base,
+    };
+
+    struct ArithmeticCircuit {
+        a: Option<[u64; 4]>,
+        b: Option<[u64; 4]>,
+        expected: [u64; 4],
+    }
+
+    impl Circuit<Fp> for ArithmeticCircuit {
+        type Config = ForeignFieldConfig;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> ForeignFieldConfig {
+            ForeignFieldChip::configure(meta, Base::MODULUS)
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            config: ForeignFieldConfig,
+        ) -> Result<(), Error> {
+            let mut layouter = layouter::SingleChip::<ForeignFieldChip<Fp>, _>::new(cs, config)?;
+
+            // (a b - a + b) / b
+            let a = Element::new(layouter.namespace(|| "a"), self.a)?;
+            let b = Element::new(layouter.namespace(|| "b"), self.b)?;
+            let ab = a.mul(layouter.namespace(|| "a b"), &b)?;
+            let sum = ab
+                .sub(layouter.namespace(|| "a b - a"), &a)?
+                .add(layouter.namespace(|| "a b - a + b"), &b)?;
+            let b_inv = b.invert(layouter.namespace(|| "1 / b"))?;
+            let result = sum
+                .mul(layouter.namespace(|| "result"), &b_inv)?
+                .reduce(layouter.namespace(|| "reduce"))?;
+
+            let expected = Element::constant(layouter.namespace(|| "expected"), self.expected)?;
+            result.constrain_equal(layouter.namespace(|| "result"), &expected)?;
+
+            // Congruence does not require the same representative.
+            let a_reduced = a.reduce(layouter.namespace(|| "reduce a"))?;
+            a.constrain_equal(layouter.namespace(|| "a"), &a_reduced)
+        }
+    }
+
+    #[test]
+    fn foreign_field_arithmetic() {
+        // a is not reduced.
+        let mut a = Base::MODULUS;
+        a[0] += 7;
+        let b = Base::from_u64(0x0123_4567_89ab_cdef).square().square();
+        let a_reduced = Base::reduce(a);
+        let expected = (a_reduced * b - a_reduced + b) * b.invert().unwrap();
+
+        let mut circuit = ArithmeticCircuit {
+            a: Some(a),
+            b: Some(b.to_limbs()),
+            expected: expected.to_limbs(),
+        };
+        let prover = MockProver::run(9, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        circuit.expected = (expected + Base::one()).to_limbs();
+        let prover = MockProver::run(9, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn uint_arithmetic() {
+        let a = [u64::MAX, 1, 2, 3];
+        let b = [5, 0, 0, 1 << 63];
+        let product = Uint::product(&a, &b);
+        assert_eq!(product.div(&b), Uint::from_limbs(&a));
+        assert_eq!(product.div(&a), Uint::from_limbs(&b));
+        assert_eq!(
+            product.add(&Uint::from_limbs(&[1, 0, 0, 0])).div(&a),
+            Uint::from_limbs(&b)
+        );
+        assert_eq!(Uint::from_limbs(&a).checked_sub(&product), None);
+        assert_eq!(Uint::from_limbs(&[1, 0, 0, 0]).shl(100).limb(1), 1 << 12);
+
+        let l = limbs(&a);
+        assert_eq!(l[0], (1 << 65) - 1);
+        assert_eq!(l[1], 1 << 41);
+        assert_eq!(l[2], 3 << 16);
+    }
+}
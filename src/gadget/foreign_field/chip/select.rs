@@ -0,0 +1,239 @@
+//! Selection of points by the bits of two scalars.
+//!
+//! A double-scalar multiplication $\[a\] P + \[b\] Q$ over a non-native curve is
+//! computed with a single double-and-add loop over the bits of both scalars, from the
+//! most significant. Each step adds a point $T_i$ selected by the bits $a_i$ and $b_i$
+//! from four points $P_0, P_1, P_2, P_3$, of which the first two are fixed.
+//!
+//! # Layout
+//!
+//! Each step uses a selection row followed by the rows of the point arithmetic, which
+//! are assigned by the caller. The selection row holds copies of $P_2$ and $P_3$ and
+//! the selected $T_i$ in the `operands` columns, the bits in the first two chunk
+//! columns, and running sums of the bits of the current limbs of both scalars in the
+//! `values` columns. The running sums restart at the most significant bit of each
+//! limb, and are copied into the limbs of the scalars at the least significant bit, so
+//! the scalars are constrained to be less than $2^N$ for $N$ bits.
+
+use std::convert::TryInto;
+
+use super::{limbs, AssignedElement, RelationConfig, LIMB_BITS};
+use crate::{
+    arithmetic::FieldExt,
+    circuit::{Chip, Region},
+    plonk::{ConstraintSystem, Error, Expression, Selector},
+    poly::Rotation,
+};
+
+/// The coordinates of an affine point, as little-endian 64-bit words.
+pub(crate) type Coordinates = [[u64; 4]; 2];
+
+/// Returns the six limbs of the coordinates of a point.
+fn coordinate_limbs<F: FieldExt>(point: &Coordinates) -> [F; 6] {
+    let x = limbs(&point[0]);
+    let y = limbs(&point[1]);
+    [
+        F::from_u128(x[0]),
+        F::from_u128(x[1]),
+        F::from_u128(x[2]),
+        F::from_u128(y[0]),
+        F::from_u128(y[1]),
+        F::from_u128(y[2]),
+    ]
+}
+
+/// Configuration for the selection rows of a double-scalar multiplication.
+#[derive(Clone, Debug)]
+pub(crate) struct SelectConfig {
+    fixed: [Coordinates; 2],
+    num_bits: usize,
+    step_rows: usize,
+    s_select: Selector,
+    s_start: Selector,
+}
+
+impl SelectConfig {
+    /// Configures selection rows that are `step_rows` apart, for scalars of `num_bits`
+    /// bits and the fixed points $P_0$ and $P_1$.
+    pub(crate) fn configure<F: FieldExt>(
+        meta: &mut ConstraintSystem<F>,
+        ff: &RelationConfig,
+        fixed: [Coordinates; 2],
+        num_bits: usize,
+        step_rows: usize,
+    ) -> Self {
+        let config = SelectConfig {
+            fixed,
+            num_bits,
+            step_rows,
+            s_select: meta.selector(),
+            s_start: meta.selector(),
+        };
+        let one = || Expression::Constant(F::one());
+        let p_0 = coordinate_limbs::<F>(&fixed[0]);
+        let p_1 = coordinate_limbs::<F>(&fixed[1]);
+
+        // Both bits are boolean.
+        for bit in ff.chunks[0][..2].iter() {
+            meta.create_gate("scalar bit", |meta| {
+                let bit = meta.query_advice(*bit, Rotation::cur());
+                let s_select = meta.query_selector(config.s_select, Rotation::cur());
+
+                s_select * bit.clone() * (one() - bit)
+            });
+        }
+
+        // T = P_0 + a (P_1 - P_0) + b (P_2 - P_0) + a b (P_3 - P_2 - P_1 + P_0)
+        for limb in 0..6 {
+            meta.create_gate("point selection", |meta| {
+                let a = meta.query_advice(ff.chunks[0][0], Rotation::cur());
+                let b = meta.query_advice(ff.chunks[0][1], Rotation::cur());
+                let p_2 = meta.query_advice(ff.operands[limb], Rotation::cur());
+                let p_3 = meta.query_advice(ff.operands[6 + limb], Rotation::cur());
+                let t = meta.query_advice(ff.operands[12 + limb], Rotation::cur());
+                let s_select = meta.query_selector(config.s_select, Rotation::cur());
+                let p_0 = Expression::Constant(p_0[limb]);
+                let p_1 = Expression::Constant(p_1[limb]);
+
+                let selected = p_0.clone()
+                    + a.clone() * (p_1.clone() - p_0.clone())
+                    + b.clone() * (p_2.clone() - p_0.clone())
+                    + a * b * (p_3 - p_2 - p_1 + p_0);
+                s_select * (t - selected)
+            });
+        }
+
+        // z = 2 z_prev + bit, where z_prev is taken as zero at the start of a limb.
+        for half in 0..2 {
+            meta.create_gate("scalar running sum", |meta| {
+                let z = meta.query_advice(ff.values[half], Rotation::cur());
+                let z_prev = meta.query_advice(ff.values[half], Rotation(-(step_rows as i32)));
+                let bit = meta.query_advice(ff.chunks[0][half], Rotation::cur());
+                let s_select = meta.query_selector(config.s_select, Rotation::cur());
+                let s_start = meta.query_selector(config.s_start, Rotation::cur());
+
+                s_select * (z - (one() - s_start) * z_prev * F::from_u64(2) - bit)
+            });
+        }
+
+        config
+    }
+
+    /// Assigns the selection rows of a multiplication by the scalars `a` and `b` at
+    /// `offset`, where `p_2` and `p_3` are the coordinates of $P_2$ and $P_3$.
+    ///
+    /// For each step, `step` is called with the offset of the rows following the
+    /// selection row and the selected $T_i$. The multiplication uses `num_bits` steps
+    /// of `step_rows` rows.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn assign<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        ff: &RelationConfig,
+        region: &mut Region<'_, Ch>,
+        offset: usize,
+        a: &AssignedElement,
+        b: &AssignedElement,
+        p_2: [&AssignedElement; 2],
+        p_3: [&AssignedElement; 2],
+        mut step: impl FnMut(&mut Region<'_, Ch>, usize, [AssignedElement; 2]) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        let bit = |u: &AssignedElement, i: usize| u.value.map(|u| (u[i / 64] >> (i % 64)) & 1 == 1);
+        let value = |point: [&AssignedElement; 2]| point[0].value.zip(point[1].value);
+        let mut z = [F::zero(); 2];
+
+        for index in 0..self.num_bits {
+            let row = offset + index * self.step_rows;
+            let i = self.num_bits - 1 - index;
+            let (limb, limb_bit) = (i / LIMB_BITS, i % LIMB_BITS);
+            self.s_select.enable(region, row)?;
+            if index == 0 || limb_bit + 1 == LIMB_BITS {
+                self.s_start.enable(region, row)?;
+                z = [F::zero(); 2];
+            }
+
+            // Copy P_2 and P_3.
+            for (base, point) in [(0, p_2), (6, p_3)].iter() {
+                for (j, coordinate) in point.iter().enumerate() {
+                    let limbs = coordinate.value.map(|v| limbs(&v));
+                    for (k, cell) in coordinate.cells.iter().enumerate() {
+                        let copy = region.assign_advice(
+                            || "P",
+                            ff.operands[base + 3 * j + k],
+                            row,
+                            || {
+                                limbs
+                                    .map(|l| F::from_u128(l[k]))
+                                    .ok_or(Error::SynthesisError)
+                            },
+                        )?;
+                        region.constrain_equal(&ff.perm, *cell, copy)?;
+                    }
+                }
+            }
+
+            // Assign the bits and running sums.
+            let bits = [bit(a, i), bit(b, i)];
+            for (half, (u, b)) in [a, b].iter().zip(bits.iter()).enumerate() {
+                region.assign_advice(
+                    || "bit",
+                    ff.chunks[0][half],
+                    row,
+                    || {
+                        b.map(|b| F::from_u64(b as u64))
+                            .ok_or(Error::SynthesisError)
+                    },
+                )?;
+                z[half] = z[half].double() + F::from_u64(b.unwrap_or(false) as u64);
+                let cell = region.assign_advice(
+                    || "z",
+                    ff.values[half],
+                    row,
+                    || b.map(|_| z[half]).ok_or(Error::SynthesisError),
+                )?;
+                if limb_bit == 0 {
+                    region.constrain_equal(&ff.perm, u.cells[limb], cell)?;
+                }
+            }
+
+            // Select T and assign it.
+            let t = match bits {
+                [Some(b_1), Some(b_2)] => {
+                    let p = [
+                        Some(self.fixed[0]),
+                        Some(self.fixed[1]),
+                        value(p_2).map(|(x, y)| [x, y]),
+                        value(p_3).map(|(x, y)| [x, y]),
+                    ];
+                    p[(b_1 as usize) | (b_2 as usize) << 1]
+                }
+                _ => None,
+            };
+            let mut coordinates = vec![];
+            for j in 0..2 {
+                let value = t.map(|t| t[j]);
+                let limbs = value.map(|v| limbs(&v));
+                let mut cells = vec![];
+                for k in 0..3 {
+                    cells.push(region.assign_advice(
+                        || "T",
+                        ff.operands[12 + 3 * j + k],
+                        row,
+                        || {
+                            limbs
+                                .map(|l| F::from_u128(l[k]))
+                                .ok_or(Error::SynthesisError)
+                        },
+                    )?);
+                }
+                coordinates.push(AssignedElement {
+                    cells: cells.try_into().unwrap(),
+                    value,
+                });
+            }
+
+            step(region, row + 1, [coordinates[0], coordinates[1]])?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,248 @@
+//! Decomposition of elements into bits, and selection of elements by bits.
+//!
+//! A windowed exponentiation $b^e$ decomposes the exponent $e$ into bits, and selects
+//! the multiplier of each window from a table $b^0, b^1, \ldots, b^{2^w - 1}$ by the bits
+//! of the window, with a tree of multiplexers.
+//!
+//! # Layout
+//!
+//! A decomposition of $e$ into $N$ bits uses a row for each bit, from the most
+//! significant, which holds the bit in the second `values` column and a running sum of
+//! the bits of the current limb of $e$ in the first. As for the scalars of a
+//! double-scalar multiplication, the running sum restarts at the most significant bit of
+//! each limb, and is copied into the limb at its least significant bit. The limbs of $e$
+//! that are above its $N$ bits are copied into the first `values` column of one row each,
+//! where they are constrained to be zero.
+//!
+//! A multiplexer uses a single row, which holds copies of the inputs $x$ and $y$ and the
+//! output $x + b (y - x)$ in the first nine `operands` columns, and a copy of the bit $b$
+//! in the second `values` column. The output is exactly one of the inputs, so its limbs
+//! are not range-checked again.
+
+use std::convert::TryInto;
+
+use super::{limbs, AssignedElement, RelationConfig, LIMB_BITS};
+use crate::{
+    arithmetic::FieldExt,
+    circuit::{Cell, Chip, Region},
+    plonk::{ConstraintSystem, Error, Expression, Selector},
+    poly::Rotation,
+};
+
+/// A bit assigned in the circuit, which is constrained to be boolean.
+#[derive(Clone, Copy, Debug)]
+pub struct AssignedBit {
+    pub(crate) cell: Cell,
+    pub(crate) value: Option<bool>,
+}
+
+impl AssignedBit {
+    /// The cell containing this bit.
+    pub fn cell(&self) -> Cell {
+        self.cell
+    }
+
+    /// The value of this bit, if known.
+    pub fn value(&self) -> Option<bool> {
+        self.value
+    }
+}
+
+/// Configuration for the decomposition and multiplexer rows.
+#[derive(Clone, Debug)]
+pub(crate) struct WindowConfig {
+    s_bit: Selector,
+    s_start: Selector,
+    s_zero: Selector,
+    s_mux: Selector,
+}
+
+impl WindowConfig {
+    /// Configures decomposition and multiplexer rows over the columns of `ff`.
+    pub(crate) fn configure<F: FieldExt>(
+        meta: &mut ConstraintSystem<F>,
+        ff: &RelationConfig,
+    ) -> Self {
+        let config = WindowConfig {
+            s_bit: meta.selector(),
+            s_start: meta.selector(),
+            s_zero: meta.selector(),
+            s_mux: meta.selector(),
+        };
+        let one = || Expression::Constant(F::one());
+
+        meta.create_gate("exponent bit", |meta| {
+            let bit = meta.query_advice(ff.values[1], Rotation::cur());
+            let s_bit = meta.query_selector(config.s_bit, Rotation::cur());
+
+            s_bit * bit.clone() * (one() - bit)
+        });
+
+        // z = 2 z_prev + bit, where z_prev is taken as zero at the start of a limb.
+        meta.create_gate("exponent running sum", |meta| {
+            let z = meta.query_advice(ff.values[0], Rotation::cur());
+            let z_prev = meta.query_advice(ff.values[0], Rotation::prev());
+            let bit = meta.query_advice(ff.values[1], Rotation::cur());
+            let s_bit = meta.query_selector(config.s_bit, Rotation::cur());
+            let s_start = meta.query_selector(config.s_start, Rotation::cur());
+
+            s_bit * (z - (one() - s_start) * z_prev * F::from_u64(2) - bit)
+        });
+
+        meta.create_gate("exponent zero limb", |meta| {
+            let limb = meta.query_advice(ff.values[0], Rotation::cur());
+            let s_zero = meta.query_selector(config.s_zero, Rotation::cur());
+
+            s_zero * limb
+        });
+
+        // out = x + b (y - x)
+        for limb in 0..3 {
+            meta.create_gate("multiplexer", |meta| {
+                let x = meta.query_advice(ff.operands[limb], Rotation::cur());
+                let y = meta.query_advice(ff.operands[3 + limb], Rotation::cur());
+                let out = meta.query_advice(ff.operands[6 + limb], Rotation::cur());
+                let bit = meta.query_advice(ff.values[1], Rotation::cur());
+                let s_mux = meta.query_selector(config.s_mux, Rotation::cur());
+
+                s_mux * (out - x.clone() - bit * (y - x))
+            });
+        }
+
+        config
+    }
+
+    /// Assigns the decomposition of `e` into `num_bits` bits at `offset`, and returns
+    /// the bits from the most significant.
+    ///
+    /// The decomposition uses `num_bits` rows, and a row for each limb of `e` above its
+    /// `num_bits` bits.
+    pub(crate) fn assign_decomposition<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        ff: &RelationConfig,
+        region: &mut Region<'_, Ch>,
+        offset: usize,
+        e: &AssignedElement,
+        num_bits: usize,
+    ) -> Result<Vec<AssignedBit>, Error> {
+        assert!(num_bits <= 256);
+        let mut bits = Vec::with_capacity(num_bits);
+        let mut z = F::zero();
+
+        for index in 0..num_bits {
+            let row = offset + index;
+            let i = num_bits - 1 - index;
+            let (limb, limb_bit) = (i / LIMB_BITS, i % LIMB_BITS);
+            self.s_bit.enable(region, row)?;
+            if index == 0 || limb_bit + 1 == LIMB_BITS {
+                self.s_start.enable(region, row)?;
+                z = F::zero();
+            }
+
+            let bit = e.value.map(|e| (e[i / 64] >> (i % 64)) & 1 == 1);
+            let cell = region.assign_advice(
+                || "bit",
+                ff.values[1],
+                row,
+                || {
+                    bit.map(|b| F::from_u64(b as u64))
+                        .ok_or(Error::SynthesisError)
+                },
+            )?;
+            z = z.double() + F::from_u64(bit.unwrap_or(false) as u64);
+            let z_cell = region.assign_advice(
+                || "z",
+                ff.values[0],
+                row,
+                || bit.map(|_| z).ok_or(Error::SynthesisError),
+            )?;
+            if limb_bit == 0 {
+                region.constrain_equal(&ff.perm, e.cells[limb], z_cell)?;
+            }
+            bits.push(AssignedBit { cell, value: bit });
+        }
+
+        // The limbs that no bit reaches are zero.
+        let first_zero = num_bits.div_ceil(LIMB_BITS);
+        for (row, limb) in (offset + num_bits..).zip(first_zero..3) {
+            self.s_zero.enable(region, row)?;
+            let value = e.value.map(|e| F::from_u128(limbs(&e)[limb]));
+            let cell = region.assign_advice(
+                || "zero limb",
+                ff.values[0],
+                row,
+                || value.ok_or(Error::SynthesisError),
+            )?;
+            region.constrain_equal(&ff.perm, e.cells[limb], cell)?;
+        }
+
+        Ok(bits)
+    }
+
+    /// Assigns a multiplexer at `offset` that returns `x` if `bit` is zero and `y`
+    /// otherwise.
+    pub(crate) fn assign_mux<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        ff: &RelationConfig,
+        region: &mut Region<'_, Ch>,
+        offset: usize,
+        bit: &AssignedBit,
+        x: &AssignedElement,
+        y: &AssignedElement,
+    ) -> Result<AssignedElement, Error> {
+        self.s_mux.enable(region, offset)?;
+
+        let bit_cell = region.assign_advice(
+            || "bit",
+            ff.values[1],
+            offset,
+            || {
+                bit.value
+                    .map(|b| F::from_u64(b as u64))
+                    .ok_or(Error::SynthesisError)
+            },
+        )?;
+        region.constrain_equal(&ff.perm, bit.cell, bit_cell)?;
+
+        for (base, input) in [(0, x), (3, y)] {
+            let limbs = input.value.map(|v| limbs(&v));
+            for (k, cell) in input.cells.iter().enumerate() {
+                let copy = region.assign_advice(
+                    || "input",
+                    ff.operands[base + k],
+                    offset,
+                    || {
+                        limbs
+                            .map(|l| F::from_u128(l[k]))
+                            .ok_or(Error::SynthesisError)
+                    },
+                )?;
+                region.constrain_equal(&ff.perm, *cell, copy)?;
+            }
+        }
+
+        let value = bit
+            .value
+            .zip(x.value.zip(y.value))
+            .map(|(b, (x, y))| if b { y } else { x });
+        let output_limbs = value.map(|v| limbs(&v));
+        let mut cells = vec![];
+        for k in 0..3 {
+            cells.push(region.assign_advice(
+                || "output",
+                ff.operands[6 + k],
+                offset,
+                || {
+                    output_limbs
+                        .map(|l| F::from_u128(l[k]))
+                        .ok_or(Error::SynthesisError)
+                },
+            )?);
+        }
+
+        Ok(AssignedElement {
+            cells: cells.try_into().unwrap(),
+            value,
+        })
+    }
+}
@@ -0,0 +1,313 @@
+//! A hash interface for writing gadgets once over several hash functions.
+//!
+//! [`HashInstructions`] absorbs assigned words into a state, and finalizes the state
+//! into an output, so a higher-level gadget such as a commitment scheme or a signature
+//! can take the hash as a chip parameter. The words and outputs are chip-specific:
+//!
+//! - [`Pow5Chip`](super::poseidon::Pow5Chip) absorbs field elements into a Poseidon
+//!   sponge in the [`VariableLength`](crate::primitives::poseidon::VariableLength)
+//!   domain, and outputs a field element.
+//! - [`Sha256Chip`](super::sha256::Sha256Chip) absorbs 32-bit message words, and outputs
+//!   the SHA-256 digest of their concatenation.
+//! - [`SinsemillaChip`](super::sinsemilla::SinsemillaChip) absorbs message pieces of
+//!   [`max_piece_words`](super::sinsemilla::max_piece_words) words, and outputs the
+//!   $x$-coordinate of their Sinsemilla hash in the
+//!   [`GENERIC_HASH_DOMAIN`](super::sinsemilla::GENERIC_HASH_DOMAIN) domain.
+//!
+//! This is distinct from [`merkle::HashInstructions`](super::merkle::HashInstructions),
+//! which hashes the children of a node in a tree.
+
+use std::fmt;
+
+use crate::{
+    arithmetic::FieldExt,
+    circuit::{Chip, Layouter},
+    plonk::Error,
+};
+
+/// The set of circuit instructions required to use the [`Hasher`] gadget.
+pub trait HashInstructions<F: FieldExt>: Chip<Field = F> {
+    /// Variable representing a word absorbed by the hash.
+    type Word: Clone + fmt::Debug;
+
+    /// The state of a hash that is absorbing words.
+    type State: fmt::Debug;
+
+    /// Variable representing the output of the hash.
+    type Output: Clone + fmt::Debug;
+
+    /// Witnesses a word with the given value.
+    ///
+    /// Chips whose words are smaller than a field element document how they treat
+    /// values that do not fit.
+    fn witness_word(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<F>,
+    ) -> Result<Self::Word, Error>;
+
+    /// Returns the state of a hash that has not absorbed any words.
+    fn init(layouter: &mut impl Layouter<Self>) -> Result<Self::State, Error>;
+
+    /// Absorbs `words` into `state`.
+    fn absorb(
+        layouter: &mut impl Layouter<Self>,
+        state: &mut Self::State,
+        words: &[Self::Word],
+    ) -> Result<(), Error>;
+
+    /// Pads the words absorbed into `state`, and returns their hash.
+    fn finalize(
+        layouter: &mut impl Layouter<Self>,
+        state: Self::State,
+    ) -> Result<Self::Output, Error>;
+}
+
+/// A hash of words absorbed in one or more calls to [`Hasher::update`].
+#[derive(Debug)]
+pub struct Hasher<F: FieldExt, HashChip: HashInstructions<F>> {
+    state: HashChip::State,
+}
+
+impl<F: FieldExt, HashChip: HashInstructions<F>> Hasher<F, HashChip> {
+    /// Creates a new hasher instance.
+    pub fn new(mut layouter: impl Layouter<HashChip>) -> Result<Self, Error> {
+        HashChip::init(&mut layouter).map(|state| Hasher { state })
+    }
+
+    /// Absorbs the given words.
+    pub fn update(
+        &mut self,
+        mut layouter: impl Layouter<HashChip>,
+        words: &[HashChip::Word],
+    ) -> Result<(), Error> {
+        HashChip::absorb(&mut layouter, &mut self.state, words)
+    }
+
+    /// Pads the absorbed words, and returns their hash.
+    pub fn finalize(
+        self,
+        mut layouter: impl Layouter<HashChip>,
+    ) -> Result<HashChip::Output, Error> {
+        HashChip::finalize(&mut layouter, self.state)
+    }
+
+    /// Convenience function to compute the hash of the given words.
+    pub fn hash(
+        mut layouter: impl Layouter<HashChip>,
+        words: &[HashChip::Word],
+    ) -> Result<HashChip::Output, Error> {
+        let mut hasher = Self::new(layouter.namespace(|| "init"))?;
+        hasher.update(layouter.namespace(|| "update"), words)?;
+        hasher.finalize(layouter.namespace(|| "finalize"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryInto;
+    use std::marker::PhantomData;
+
+    use super::{HashInstructions, Hasher};
+    use crate::{
+        arithmetic::FieldExt,
+        circuit::{layouter, Chip, Layouter},
+        dev::MockProver,
+        gadget::{
+            poseidon::{Pow5Chip, Pow5Config},
+            sha256::{Sha256Chip, Sha256Config},
+            sinsemilla::{max_piece_words, SinsemillaChip, SinsemillaConfig, GENERIC_HASH_DOMAIN},
+        },
+        pasta::{pallas, Fp},
+        plonk::{Assignment, Circuit, ConstraintSystem, Error},
+        primitives::{
+            poseidon::{self, P128Pow5T3, VariableLength},
+            sinsemilla::{self, K},
+        },
+    };
+
+    /// Hashes `message` in two updates, and returns the output.
+    fn hash_in_parts<F: FieldExt, HashChip: HashInstructions<F>>(
+        mut layouter: impl Layouter<HashChip>,
+        message: &[F],
+        split: usize,
+    ) -> Result<HashChip::Output, Error> {
+        let words = message
+            .iter()
+            .enumerate()
+            .map(|(i, value)| {
+                HashChip::witness_word(
+                    &mut layouter.namespace(|| format!("word {}", i)),
+                    Some(*value),
+                )
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        let mut hasher = Hasher::<F, HashChip>::new(layouter.namespace(|| "init"))?;
+        let (left, right) = words.split_at(split);
+        hasher.update(layouter.namespace(|| "left"), left)?;
+        hasher.update(layouter.namespace(|| "right"), right)?;
+        hasher.finalize(layouter.namespace(|| "finalize"))
+    }
+
+    /// A circuit that hashes `message` with a chip, and checks the output.
+    struct HashCircuit<HashChip: HashInstructions<Fp>> {
+        message: Vec<Fp>,
+        split: usize,
+        check: fn(&HashChip::Output),
+        _marker: PhantomData<HashChip>,
+    }
+
+    impl<HashChip: HashInstructions<Fp>> HashCircuit<HashChip> {
+        fn run(message: Vec<Fp>, split: usize, check: fn(&HashChip::Output), k: u32)
+        where
+            Self: Circuit<Fp>,
+        {
+            let circuit = HashCircuit {
+                message,
+                split,
+                check,
+                _marker: PhantomData,
+            };
+            let prover = MockProver::run(k, &circuit, vec![]).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+        }
+
+        fn synthesize_with(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            config: <HashChip as Chip>::Config,
+        ) -> Result<(), Error> {
+            let mut layouter = layouter::SingleChip::<HashChip, _>::new(cs, config)?;
+            let output = hash_in_parts(layouter.namespace(|| "hash"), &self.message, self.split)?;
+            (self.check)(&output);
+            Ok(())
+        }
+    }
+
+    impl Circuit<Fp> for HashCircuit<Pow5Chip<Fp, 3, 2>> {
+        type Config = Pow5Config<Fp, 3, 2>;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let state = (0..3)
+                .map(|_| meta.advice_column())
+                .collect::<Vec<_>>()
+                .try_into()
+                .unwrap();
+            let partial_sbox = meta.advice_column();
+            Pow5Chip::configure::<P128Pow5T3>(meta, state, partial_sbox)
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            config: Self::Config,
+        ) -> Result<(), Error> {
+            self.synthesize_with(cs, config)
+        }
+    }
+
+    impl Circuit<Fp> for HashCircuit<Sha256Chip<Fp>> {
+        type Config = Sha256Config;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            Sha256Chip::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            config: Self::Config,
+        ) -> Result<(), Error> {
+            self.synthesize_with(cs, config)
+        }
+    }
+
+    impl Circuit<Fp> for HashCircuit<SinsemillaChip<pallas::Affine>> {
+        type Config = SinsemillaConfig;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            SinsemillaChip::<pallas::Affine>::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            config: Self::Config,
+        ) -> Result<(), Error> {
+            self.synthesize_with(cs, config)
+        }
+    }
+
+    #[test]
+    fn poseidon() {
+        HashCircuit::<Pow5Chip<Fp, 3, 2>>::run(
+            (1..6).map(Fp::from_u64).collect(),
+            3,
+            |output| {
+                let message: Vec<_> = (1..6).map(Fp::from_u64).collect();
+                let expected =
+                    poseidon::Hash::<_, P128Pow5T3, _, 3, 2>::hash(VariableLength, &message);
+                assert_eq!(output.value(), Some(expected));
+            },
+            7,
+        );
+    }
+
+    #[test]
+    fn sha256() {
+        // The 56-byte message of the FIPS 180-2 examples.
+        let message = b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"
+            .chunks(4)
+            .map(|chunk| Fp::from_u64(u32::from_be_bytes(chunk.try_into().unwrap()) as u64))
+            .collect();
+        HashCircuit::<Sha256Chip<Fp>>::run(
+            message,
+            5,
+            |digest| {
+                let words: Vec<_> = digest.0.iter().map(|word| word.value().unwrap()).collect();
+                assert_eq!(
+                    words,
+                    [
+                        0x248d_6a61,
+                        0xd206_38b8,
+                        0xe5c0_2693,
+                        0x0c3e_6039,
+                        0xa33c_e459,
+                        0x64ff_2167,
+                        0xf6ec_edd4,
+                        0x19db_06c1,
+                    ]
+                );
+            },
+            9,
+        );
+    }
+
+    #[test]
+    fn sinsemilla() {
+        let message: Vec<_> = (1..4).map(|i| Fp::from_u64(i * 0x0123_4567_89ab)).collect();
+        HashCircuit::<SinsemillaChip<pallas::Affine>>::run(
+            message,
+            1,
+            |output| {
+                let bits: Vec<_> = (1..4u64)
+                    .flat_map(|i| {
+                        let value = i * 0x0123_4567_89ab;
+                        (0..max_piece_words::<Fp>() * K)
+                            .map(move |j| j < 64 && (value >> j) & 1 == 1)
+                    })
+                    .collect();
+                let expected =
+                    sinsemilla::HashDomain::<pallas::Affine>::new(GENERIC_HASH_DOMAIN).hash(&bits);
+                assert_eq!(output.value(), expected);
+            },
+            11,
+        );
+    }
+}
@@ -0,0 +1,69 @@
+//! Gadget and chips for hashing to the Pallas curve in a circuit over $\mathbb{F}_p$.
+//!
+//! The [`map_to_curve`] gadget is the in-circuit counterpart of
+//! [`pallas::map_to_curve`]: it applies the simplified SWU map from [WB2019] to the
+//! isogenous curve iso-Pallas, followed by the isogeny to Pallas. The [`hash_to_curve`]
+//! gadget adds the points that two field elements are mapped to, which matches
+//! [`CurveExt::hash_to_curve`] for the outputs of [`pallas::hash_to_field`]. A circuit
+//! can therefore derive a generator or a nullifier point from data without trusting a
+//! precomputed constant.
+//!
+//! The field elements are inputs to these gadgets. They are derived with BLAKE2b by the
+//! host, so a circuit that must also bind them to the data should derive them with a
+//! hash that is efficient in the circuit, such as Poseidon.
+//!
+//! [WB2019]: https://eprint.iacr.org/2019/403
+//! [`pallas::map_to_curve`]: crate::pasta::pallas::map_to_curve
+//! [`pallas::hash_to_field`]: crate::pasta::pallas::hash_to_field
+//! [`CurveExt::hash_to_curve`]: crate::arithmetic::CurveExt::hash_to_curve
+
+use std::fmt;
+
+use super::ecc::{EccInstructions, Point};
+use crate::{arithmetic::CurveAffine, circuit::Layouter, plonk::Error};
+
+mod chip;
+pub use chip::{HashToCurveChip, HashToCurveConfig};
+
+/// The set of circuit instructions required to use the [`map_to_curve`] and
+/// [`hash_to_curve`] gadgets.
+pub trait HashToCurveInstructions<C: CurveAffine>: EccInstructions<C> {
+    /// Variable representing a field element to be mapped to the curve.
+    type Element: Clone + fmt::Debug;
+
+    /// Witnesses a field element.
+    fn witness_element(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<C::Base>,
+    ) -> Result<Self::Element, Error>;
+
+    /// Maps `u` to a point on the curve.
+    fn map_to_curve(
+        layouter: &mut impl Layouter<Self>,
+        u: &Self::Element,
+    ) -> Result<Self::Point, Error>;
+}
+
+/// Maps `u` to a point on the curve, as [`pallas::map_to_curve`] does.
+///
+/// [`pallas::map_to_curve`]: crate::pasta::pallas::map_to_curve
+pub fn map_to_curve<C: CurveAffine, HashToCurveChip: HashToCurveInstructions<C>>(
+    mut layouter: impl Layouter<HashToCurveChip>,
+    u: &HashToCurveChip::Element,
+) -> Result<Point<C, HashToCurveChip>, Error> {
+    HashToCurveChip::map_to_curve(&mut layouter, u).map(Point::from_inner)
+}
+
+/// Hashes the field elements `us` to a point on the curve, as
+/// [`CurveExt::hash_to_curve`] does for the outputs of [`pallas::hash_to_field`].
+///
+/// [`CurveExt::hash_to_curve`]: crate::arithmetic::CurveExt::hash_to_curve
+/// [`pallas::hash_to_field`]: crate::pasta::pallas::hash_to_field
+pub fn hash_to_curve<C: CurveAffine, HashToCurveChip: HashToCurveInstructions<C>>(
+    mut layouter: impl Layouter<HashToCurveChip>,
+    us: [&HashToCurveChip::Element; 2],
+) -> Result<Point<C, HashToCurveChip>, Error> {
+    let q0 = map_to_curve(layouter.namespace(|| "map u_0"), us[0])?;
+    let q1 = map_to_curve(layouter.namespace(|| "map u_1"), us[1])?;
+    q0.add(layouter.namespace(|| "Q_0 + Q_1"), &q1)
+}
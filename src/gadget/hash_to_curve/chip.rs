@@ -0,0 +1,522 @@
+//! A chip implementing [`HashToCurveInstructions`] for Pallas with the ECC chip.
+//!
+//! # Layout
+//!
+//! The chip includes an [`EccConfig`], and implements [`EccInstructions`] with it.
+//!
+//! A map uses one row for the simplified SWU map and the isogeny, in which $u$, $u^2$,
+//! $\mathsf{inv0}(t)$ for $t = Z^2 u^4 + Z u^2$, a flag for $t = 0$, $x_1$, a flag $e$
+//! for whether $g(x_1)$ is a square, and the coordinates $(x, y)$ on iso-Pallas are
+//! witnessed, with the output point in the `x_p, y_p` columns of the ECC
+//! configuration. The gate constrains $x = x_1$ if $e = 1$ and $x = Z u^2 x_1$
+//! otherwise, and $y^2 = g(x)$. As $g(Z u^2 x_1) = Z^3 u^6 g(x_1)$ and $Z$ is a
+//! nonsquare, exactly one of these is satisfiable for $u \neq 0$, and $e = 1$ is
+//! required when $t = 0$. The isogeny is evaluated in affine coordinates by
+//! constraining $x' \cdot d_x(x) = n_x(x)$ and $y' \cdot d_y(x) = y \cdot n_y(x)$.
+//!
+//! The signs of $u$ and $y$ are compared by decomposing each of them with a
+//! [`RunningSumConfig`] into 127 2-bit windows, which proves that they are less than
+//! $2^{254}$ and so canonical, and splitting the first window into its low bit and
+//! high bit in the `parity` and `half` columns. Field elements of at least $2^{254}$,
+//! and points $(x, y)$ with $d_x(x) d_y(x) = 0$, cannot be mapped, but occur with
+//! negligible probability for hashed inputs.
+
+use ff::Field;
+
+use super::HashToCurveInstructions;
+use crate::{
+    arithmetic::{CurveAffine, CurveExt, FieldExt},
+    circuit::{Cell, Chip, Layouter, Region},
+    gadget::{
+        ecc::{EccChip, EccConfig, EccFixedPoint, EccInstructions, EccPoint, EccX},
+        running_sum::RunningSumConfig,
+        utilities::AssignedValue,
+    },
+    pasta::{pallas, Ep, IsoEp},
+    plonk::{Advice, Column, ConstraintSystem, Error, Expression, Permutation, Selector},
+    poly::Rotation,
+};
+
+/// The number of windows proving that a field element is less than $2^{254}$.
+const NUM_WINDOWS: usize = 127;
+
+/// Configuration for a [`HashToCurveChip`].
+#[derive(Clone, Debug)]
+pub struct HashToCurveConfig {
+    ecc: EccConfig,
+    u: Column<Advice>,
+    u2: Column<Advice>,
+    ta_inv: Column<Advice>,
+    ta_zero: Column<Advice>,
+    x1: Column<Advice>,
+    e: Column<Advice>,
+    x: Column<Advice>,
+    y: Column<Advice>,
+    running_sum: RunningSumConfig,
+    parity: Column<Advice>,
+    half: Column<Advice>,
+    s_map: Selector,
+    s_parity: Selector,
+    perm: Permutation,
+}
+
+/// A chip hashing to the Pallas curve.
+#[derive(Debug)]
+pub struct HashToCurveChip;
+
+impl HashToCurveChip {
+    /// Configures this chip for use in a circuit.
+    pub fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> HashToCurveConfig {
+        let ecc = EccChip::<pallas::Affine>::configure(meta);
+        let u = meta.advice_column();
+        let u2 = meta.advice_column();
+        let ta_inv = meta.advice_column();
+        let ta_zero = meta.advice_column();
+        let x1 = meta.advice_column();
+        let e = meta.advice_column();
+        let x = meta.advice_column();
+        let y = meta.advice_column();
+
+        let z = meta.advice_column();
+        let window = meta.advice_column();
+        let running_sum = RunningSumConfig::configure(meta, z, window, 2, true);
+        let parity = meta.advice_column();
+        let half = meta.advice_column();
+
+        let s_map = meta.selector();
+        let s_parity = meta.selector();
+        let perm = Permutation::new(meta, &[u.into(), y.into(), z.into(), parity.into()]);
+
+        let constant = Expression::Constant;
+        let one = constant(pallas::Base::one());
+        let (a, b, z) = (IsoEp::a(), IsoEp::b(), Ep::Z);
+        let iso = Ep::ISOGENY_CONSTANTS;
+
+        // Each constraint of a map, without the selector.
+        let map_constraints = |meta: &mut ConstraintSystem<pallas::Base>| {
+            let u = meta.query_advice(u, Rotation::cur());
+            let u2 = meta.query_advice(u2, Rotation::cur());
+            let ta_inv = meta.query_advice(ta_inv, Rotation::cur());
+            let ta_zero = meta.query_advice(ta_zero, Rotation::cur());
+            let x1 = meta.query_advice(x1, Rotation::cur());
+            let e = meta.query_advice(e, Rotation::cur());
+            let x = meta.query_advice(x, Rotation::cur());
+            let y = meta.query_advice(y, Rotation::cur());
+            let x_out = meta.query_advice(ecc.x_p, Rotation::cur());
+            let y_out = meta.query_advice(ecc.y_p, Rotation::cur());
+
+            // t = Z^2 u^4 + Z u^2
+            let ta = u2.clone() * u2.clone() * (z * z) + u2.clone() * z;
+            // x_1 = (-B / A)(1 + inv0(t)) if t != 0, and B / (Z A) otherwise
+            let x1_expected = (one.clone() - ta_zero.clone())
+                * (one.clone() + ta_inv.clone())
+                * (-b * a.invert().unwrap())
+                + ta_zero.clone() * (b * (z * a).invert().unwrap());
+            // x = x_1 if e = 1, and Z u^2 x_1 otherwise
+            let x_expected =
+                e.clone() * x1.clone() + (one.clone() - e.clone()) * u2.clone() * x1.clone() * z;
+            let gx = x.clone() * x.clone() * x.clone() + x.clone() * a + constant(b);
+
+            let num_x = ((x.clone() * iso[0] + constant(iso[1])) * x.clone() + constant(iso[2]))
+                * x.clone()
+                + constant(iso[3]);
+            let div_x = (x.clone() + constant(iso[4])) * x.clone() + constant(iso[5]);
+            let num_y = ((x.clone() * iso[6] + constant(iso[7])) * x.clone() + constant(iso[8]))
+                * x.clone()
+                + constant(iso[9]);
+            let div_y = ((x.clone() + constant(iso[10])) * x.clone() + constant(iso[11]))
+                * x.clone()
+                + constant(iso[12]);
+
+            [
+                ("swu u^2", u.clone() * u - u2),
+                (
+                    "swu inv0(t)",
+                    ta.clone() * ta_inv - (one.clone() - ta_zero.clone()),
+                ),
+                ("swu t = 0", ta * ta_zero.clone()),
+                ("swu x_1", x1 - x1_expected),
+                ("swu e", e.clone() * (one.clone() - e.clone())),
+                ("swu e if t = 0", ta_zero * (one.clone() - e)),
+                ("swu x", x.clone() - x_expected),
+                ("swu y", y.clone() * y.clone() - gx),
+                ("isogeny x", x_out * div_x - num_x),
+                ("isogeny y", y_out * div_y - y * num_y),
+            ]
+        };
+        for i in 0..10 {
+            let name = map_constraints(meta)[i].0;
+            meta.create_gate(name, |meta| {
+                let s_map = meta.query_selector(s_map, Rotation::cur());
+                let (_, poly) = map_constraints(meta)[i].clone();
+                s_map * poly
+            });
+        }
+
+        let two = constant(pallas::Base::from_u64(2));
+        meta.create_gate("sign", |meta| {
+            let window = meta.query_advice(window, Rotation::cur());
+            let parity = meta.query_advice(parity, Rotation::cur());
+            let half = meta.query_advice(half, Rotation::cur());
+            let s_parity = meta.query_selector(s_parity, Rotation::cur());
+
+            s_parity * (window - parity - half * two)
+        });
+        for (name, column) in [("sign parity", parity), ("sign half", half)] {
+            meta.create_gate(name, |meta| {
+                let bit = meta.query_advice(column, Rotation::cur());
+                let s_parity = meta.query_selector(s_parity, Rotation::cur());
+
+                s_parity * bit.clone() * (one.clone() - bit)
+            });
+        }
+
+        HashToCurveConfig {
+            ecc,
+            u,
+            u2,
+            ta_inv,
+            ta_zero,
+            x1,
+            e,
+            x,
+            y,
+            running_sum,
+            parity,
+            half,
+            s_map,
+            s_parity,
+            perm,
+        }
+    }
+}
+
+impl HashToCurveConfig {
+    /// Decomposes the value of `cell` at `offset`, and returns the cell holding its low
+    /// bit.
+    fn assign_sign(
+        &self,
+        region: &mut Region<'_, HashToCurveChip>,
+        offset: usize,
+        cell: Cell,
+        value: Option<pallas::Base>,
+    ) -> Result<Cell, Error> {
+        let running_sum = self
+            .running_sum
+            .assign(region, offset, value, NUM_WINDOWS, true)?;
+        region.constrain_equal(&self.perm, running_sum.zs()[0].cell(), cell)?;
+
+        self.s_parity.enable(region, offset)?;
+        let window = running_sum.windows()[0].value();
+        let parity = region.assign_advice(
+            || "parity",
+            self.parity,
+            offset,
+            || {
+                window
+                    .map(|k| pallas::Base::from_u64(k.get_lower_32() as u64 & 1))
+                    .ok_or(Error::SynthesisError)
+            },
+        )?;
+        region.assign_advice(
+            || "half",
+            self.half,
+            offset,
+            || {
+                window
+                    .map(|k| pallas::Base::from_u64(k.get_lower_32() as u64 >> 1))
+                    .ok_or(Error::SynthesisError)
+            },
+        )?;
+        Ok(parity)
+    }
+}
+
+/// The witnesses of the simplified SWU map and the isogeny for an input $u$.
+struct MapWitness {
+    u2: pallas::Base,
+    ta_inv: pallas::Base,
+    ta_zero: bool,
+    x1: pallas::Base,
+    e: bool,
+    x: pallas::Base,
+    y: pallas::Base,
+    output: pallas::Affine,
+}
+
+impl MapWitness {
+    fn new(u: pallas::Base) -> Option<Self> {
+        let (a, b, z) = (IsoEp::a(), IsoEp::b(), Ep::Z);
+        let iso = Ep::ISOGENY_CONSTANTS;
+        let sign = |v: pallas::Base| v.get_lower_32() & 1;
+        let g = |x: pallas::Base| x.square() * x + a * x + b;
+
+        let u2 = u.square();
+        let ta = z.square() * u2.square() + z * u2;
+        let ta_inv = ta.invert().unwrap_or_else(pallas::Base::zero);
+        let ta_zero = ta.is_zero();
+        let x1 = if ta_zero {
+            b * (z * a).invert().unwrap()
+        } else {
+            -b * a.invert().unwrap() * (pallas::Base::one() + ta_inv)
+        };
+
+        let y1 = g(x1).sqrt();
+        let e = bool::from(y1.is_some());
+        let (x, y) = if e {
+            (x1, y1.unwrap())
+        } else {
+            let x2 = z * u2 * x1;
+            (x2, g(x2).sqrt().unwrap())
+        };
+        let y = if sign(u) == sign(y) { y } else { -y };
+
+        let num_x = ((iso[0] * x + iso[1]) * x + iso[2]) * x + iso[3];
+        let div_x = (x + iso[4]) * x + iso[5];
+        let num_y = ((iso[6] * x + iso[7]) * x + iso[8]) * x + iso[9];
+        let div_y = ((x + iso[10]) * x + iso[11]) * x + iso[12];
+        let div_x_inv = Option::<pallas::Base>::from(div_x.invert())?;
+        let div_y_inv = Option::<pallas::Base>::from(div_y.invert())?;
+        let output = pallas::Affine::from_xy(num_x * div_x_inv, y * num_y * div_y_inv).unwrap();
+
+        Some(MapWitness {
+            u2,
+            ta_inv,
+            ta_zero,
+            x1,
+            e,
+            x,
+            y,
+            output,
+        })
+    }
+}
+
+impl Chip for HashToCurveChip {
+    type Config = HashToCurveConfig;
+    type Loaded = ();
+    type Field = pallas::Base;
+
+    fn load(_layouter: &mut impl Layouter<Self>) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl EccInstructions<pallas::Affine> for HashToCurveChip {
+    type Point = EccPoint<pallas::Affine>;
+    type X = EccX<pallas::Base>;
+    type FixedPoint = EccFixedPoint<pallas::Affine>;
+
+    fn fixed_point(base: pallas::Affine) -> Self::FixedPoint {
+        EccChip::<pallas::Affine>::fixed_point(base)
+    }
+
+    fn witness_point(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<pallas::Affine>,
+    ) -> Result<Self::Point, Error> {
+        layouter.config().ecc.clone().witness_point(layouter, value)
+    }
+
+    fn extract_p(point: &Self::Point) -> Self::X {
+        point.extract_p()
+    }
+
+    fn add(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Point,
+        b: &Self::Point,
+    ) -> Result<Self::Point, Error> {
+        layouter.config().ecc.clone().add(layouter, a, b)
+    }
+
+    fn double(layouter: &mut impl Layouter<Self>, a: &Self::Point) -> Result<Self::Point, Error> {
+        layouter.config().ecc.clone().double(layouter, a)
+    }
+
+    fn mul_fixed(
+        layouter: &mut impl Layouter<Self>,
+        scalar: Option<pallas::Scalar>,
+        base: &Self::FixedPoint,
+    ) -> Result<Self::Point, Error> {
+        layouter
+            .config()
+            .ecc
+            .clone()
+            .mul_fixed(layouter, scalar, base)
+    }
+
+    fn mul_endo(
+        layouter: &mut impl Layouter<Self>,
+        base: &Self::Point,
+        bits: &[Option<bool>],
+    ) -> Result<Self::Point, Error> {
+        layouter.config().ecc.clone().mul_endo(layouter, base, bits)
+    }
+
+    fn constrain_equal(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Point,
+        b: &Self::Point,
+    ) -> Result<(), Error> {
+        layouter
+            .config()
+            .ecc
+            .clone()
+            .constrain_equal(layouter, a, b)
+    }
+}
+
+impl HashToCurveInstructions<pallas::Affine> for HashToCurveChip {
+    type Element = AssignedValue<pallas::Base>;
+
+    fn witness_element(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<pallas::Base>,
+    ) -> Result<Self::Element, Error> {
+        let config = layouter.config().clone();
+        layouter.assign_region(
+            || "witness element",
+            |mut region| {
+                let cell = region.assign_advice(
+                    || "u",
+                    config.u,
+                    0,
+                    || value.ok_or(Error::SynthesisError),
+                )?;
+                Ok(AssignedValue { cell, value })
+            },
+        )
+    }
+
+    fn map_to_curve(
+        layouter: &mut impl Layouter<Self>,
+        u: &Self::Element,
+    ) -> Result<Self::Point, Error> {
+        let config = layouter.config().clone();
+        let witness = u
+            .value
+            .map(|u| MapWitness::new(u).ok_or(Error::SynthesisError))
+            .transpose()?;
+
+        layouter.assign_region(
+            || "map to curve",
+            |mut region| {
+                config.s_map.enable(&mut region, 0)?;
+
+                let u_copy = region.assign_advice(
+                    || "u",
+                    config.u,
+                    0,
+                    || u.value.ok_or(Error::SynthesisError),
+                )?;
+                region.constrain_equal(&config.perm, u.cell, u_copy)?;
+
+                let bit = |b: bool| pallas::Base::from_u64(b as u64);
+                let mut assign = |name: &'static str, column, value: Option<pallas::Base>| {
+                    region.assign_advice(|| name, column, 0, || value.ok_or(Error::SynthesisError))
+                };
+                let w = witness.as_ref();
+                assign("u^2", config.u2, w.map(|w| w.u2))?;
+                assign("inv0(t)", config.ta_inv, w.map(|w| w.ta_inv))?;
+                assign("t = 0", config.ta_zero, w.map(|w| bit(w.ta_zero)))?;
+                assign("x_1", config.x1, w.map(|w| w.x1))?;
+                assign("e", config.e, w.map(|w| bit(w.e)))?;
+                assign("x", config.x, w.map(|w| w.x))?;
+                let y = w.map(|w| w.y);
+                let y_cell = assign("y", config.y, y)?;
+
+                let output = config.ecc.assign_point(
+                    &mut region,
+                    (config.ecc.x_p, config.ecc.y_p),
+                    0,
+                    witness.as_ref().map(|w| w.output),
+                )?;
+
+                let u_sign = config.assign_sign(&mut region, 1, u_copy, u.value)?;
+                let y_sign = config.assign_sign(&mut region, NUM_WINDOWS + 2, y_cell, y)?;
+                region.constrain_equal(&config.perm, u_sign, y_sign)?;
+
+                Ok(output)
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use group::Curve;
+
+    use super::{HashToCurveChip, HashToCurveConfig};
+    use crate::{
+        circuit::{layouter, Layouter},
+        dev::MockProver,
+        gadget::{
+            ecc::Point,
+            hash_to_curve::{hash_to_curve, map_to_curve, HashToCurveInstructions},
+        },
+        pasta::pallas,
+        plonk::{Assignment, Circuit, ConstraintSystem, Error},
+    };
+
+    struct HashToCurveCircuit {
+        us: [pallas::Base; 2],
+        expected: [pallas::Affine; 2],
+    }
+
+    impl Circuit<pallas::Base> for HashToCurveCircuit {
+        type Config = HashToCurveConfig;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> HashToCurveConfig {
+            HashToCurveChip::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<pallas::Base>,
+            config: HashToCurveConfig,
+        ) -> Result<(), Error> {
+            let mut layouter = layouter::SingleChip::<HashToCurveChip, _>::new(cs, config)?;
+
+            let u0 = HashToCurveChip::witness_element(&mut layouter, Some(self.us[0]))?;
+            let u1 = HashToCurveChip::witness_element(&mut layouter, Some(self.us[1]))?;
+
+            let mapped = map_to_curve(layouter.namespace(|| "map"), &u0)?;
+            let expected = Point::new(layouter.namespace(|| "mapped"), Some(self.expected[0]))?;
+            mapped.constrain_equal(layouter.namespace(|| "check map"), &expected)?;
+
+            let hashed = hash_to_curve(layouter.namespace(|| "hash"), [&u0, &u1])?;
+            let expected = Point::new(layouter.namespace(|| "hashed"), Some(self.expected[1]))?;
+            hashed.constrain_equal(layouter.namespace(|| "check hash"), &expected)
+        }
+    }
+
+    #[test]
+    fn hash_to_curve_matches_host() {
+        let us = pallas::hash_to_field("z.cash:test", b"world");
+        let mapped = pallas::map_to_curve(&us[0]);
+        let hashed = mapped + pallas::map_to_curve(&us[1]);
+
+        // The second input is zero, for which t = 0.
+        for us in [us, [us[0], pallas::Base::zero()]] {
+            let circuit = HashToCurveCircuit {
+                us,
+                expected: [
+                    mapped.to_affine(),
+                    (mapped + pallas::map_to_curve(&us[1])).to_affine(),
+                ],
+            };
+            let prover = MockProver::run(11, &circuit, vec![]).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+        }
+
+        let circuit = HashToCurveCircuit {
+            us,
+            expected: [mapped.to_affine(), (hashed + mapped).to_affine()],
+        };
+        let prover = MockProver::run(11, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
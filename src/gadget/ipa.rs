@@ -0,0 +1,240 @@
+//! Gadget and chips for verifying inner product argument opening proofs in a circuit
+//! over the base field of the commitment curve, such as opening proofs for commitments
+//! over Pallas in a circuit over $\mathbb{F}_p$.
+//!
+//! The scalars of such a proof are not native to the circuit, so the [`OpeningProof`]
+//! gadget only performs the part of [`verify_proof`] that involves its points. It
+//! reduces the commitment $P$ being opened to
+//! $$P' = P + \[\iota\] S + \sum_i (\[u_i^{-1}\] L_i + \[u_i\] R_i),$$
+//! where each challenge is a 128-bit endoscaling scalar, and $\[u_i^{-1}\] L_i$ is
+//! witnessed and constrained by multiplying it by $u_i$. The returned [`Accumulator`]
+//! exposes $P'$, the purported $G = \langle s(u), \mathbf{G} \rangle + H$ and the
+//! challenges as public inputs, and the remaining check, which involves the scalars of
+//! the proof and the linear-time computation of $G$, is deferred to
+//! [`OpeningProof::decide`](crate::poly::commitment::OpeningProof::decide).
+//!
+//! The challenges are witnessed, so a circuit using this gadget must also bind them to
+//! the transcript of the proof.
+//!
+//! [`verify_proof`]: crate::poly::commitment::verify_proof
+
+use std::fmt;
+
+use ff::Field;
+
+use super::ecc::{EccInstructions, Point};
+use crate::{
+    arithmetic::{CurveAffine, FieldExt},
+    circuit::Layouter,
+    plonk::Error,
+    poly::commitment,
+    transcript::Challenge,
+};
+
+mod chip;
+pub use chip::{AssignedChallenge, IpaChip, IpaConfig};
+
+mod circuit;
+pub use circuit::OpeningCircuit;
+
+/// The set of circuit instructions required to use the [`OpeningProof`] and
+/// [`Accumulator`] gadgets.
+pub trait IpaInstructions<C: CurveAffine>: EccInstructions<C> {
+    /// Variable representing a 128-bit verifier challenge.
+    type Challenge: Clone + fmt::Debug;
+
+    /// Witnesses a challenge.
+    fn witness_challenge(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<Challenge>,
+    ) -> Result<Self::Challenge, Error>;
+
+    /// Returns `[u] base`, where `u` is the scalar of `challenge`.
+    fn mul_challenge(
+        layouter: &mut impl Layouter<Self>,
+        base: &Self::Point,
+        challenge: &Self::Challenge,
+    ) -> Result<Self::Point, Error>;
+
+    /// Returns `[u^{-1}] point`, where `u` is the scalar of `challenge`.
+    ///
+    /// Returns an error if `point` is the identity.
+    fn div_challenge(
+        layouter: &mut impl Layouter<Self>,
+        point: &Self::Point,
+        challenge: &Self::Challenge,
+    ) -> Result<Self::Point, Error>;
+
+    /// Exposes the coordinates of `point` as the next two public inputs.
+    fn expose_point(layouter: &mut impl Layouter<Self>, point: &Self::Point) -> Result<(), Error>;
+
+    /// Exposes `challenge` as the next public input.
+    fn expose_challenge(
+        layouter: &mut impl Layouter<Self>,
+        challenge: &Self::Challenge,
+    ) -> Result<(), Error>;
+}
+
+/// The points and challenges of an opening proof.
+#[derive(Debug)]
+pub struct OpeningProof<C: CurveAffine, IpaChip: IpaInstructions<C>> {
+    s_poly_commitment: Point<C, IpaChip>,
+    iota: IpaChip::Challenge,
+    z: IpaChip::Challenge,
+    rounds: Vec<Round<C, IpaChip>>,
+}
+
+/// The points $L_i$ and $R_i$ of a round, and its challenge $u_i$.
+type Round<C, IpaChip> = (
+    Point<C, IpaChip>,
+    Point<C, IpaChip>,
+    <IpaChip as IpaInstructions<C>>::Challenge,
+);
+
+impl<C: CurveAffine, IpaChip: IpaInstructions<C>> OpeningProof<C, IpaChip> {
+    /// Witnesses the points and challenges of an opening proof with `k` rounds.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `value` does not have `k` rounds.
+    pub fn new(
+        mut layouter: impl Layouter<IpaChip>,
+        k: usize,
+        value: Option<&commitment::OpeningProof<C>>,
+    ) -> Result<Self, Error> {
+        assert!(value.is_none_or(|proof| proof.rounds.len() == k));
+
+        let s_poly_commitment = Point::new(
+            layouter.namespace(|| "S"),
+            value.map(|proof| proof.s_poly_commitment),
+        )?;
+        let iota = IpaChip::witness_challenge(&mut layouter, value.map(|proof| proof.iota))?;
+        let z = IpaChip::witness_challenge(&mut layouter, value.map(|proof| proof.z))?;
+
+        let rounds = (0..k)
+            .map(|i| {
+                let round = value.map(|proof| proof.rounds[i]);
+                let l = Point::new(
+                    layouter.namespace(|| format!("L_{}", i)),
+                    round.map(|(l, _, _)| l),
+                )?;
+                let r = Point::new(
+                    layouter.namespace(|| format!("R_{}", i)),
+                    round.map(|(_, r, _)| r),
+                )?;
+                let challenge =
+                    IpaChip::witness_challenge(&mut layouter, round.map(|(_, _, u)| u))?;
+                Ok((l, r, challenge))
+            })
+            .collect::<Result<_, Error>>()?;
+
+        Ok(OpeningProof {
+            s_poly_commitment,
+            iota,
+            z,
+            rounds,
+        })
+    }
+
+    /// The number of rounds of this proof.
+    pub fn rounds(&self) -> usize {
+        self.rounds.len()
+    }
+
+    /// Reduces `commitment` to $P'$, and witnesses the purported `g`.
+    ///
+    /// `g` is only constrained by the deferred check, so it should be computed with
+    /// [`OpeningProof::compute_g`](crate::poly::commitment::OpeningProof::compute_g).
+    pub fn accumulate(
+        &self,
+        mut layouter: impl Layouter<IpaChip>,
+        commitment: &Point<C, IpaChip>,
+        g: Option<C>,
+    ) -> Result<Accumulator<C, IpaChip>, Error> {
+        let s = IpaChip::mul_challenge(&mut layouter, self.s_poly_commitment.inner(), &self.iota)
+            .map(Point::from_inner)?;
+        let mut reduced = commitment.add(layouter.namespace(|| "P + [iota] S"), &s)?;
+
+        for (i, (l, r, challenge)) in self.rounds.iter().enumerate() {
+            let mut layouter = layouter.namespace(|| format!("round {}", i));
+            let l = IpaChip::div_challenge(&mut layouter, l.inner(), challenge)
+                .map(Point::from_inner)?;
+            let r = IpaChip::mul_challenge(&mut layouter, r.inner(), challenge)
+                .map(Point::from_inner)?;
+            reduced = reduced.add(layouter.namespace(|| "L"), &l)?;
+            reduced = reduced.add(layouter.namespace(|| "R"), &r)?;
+        }
+
+        let g = Point::new(layouter.namespace(|| "G"), g)?;
+
+        Ok(Accumulator {
+            reduced,
+            g,
+            iota: self.iota.clone(),
+            z: self.z.clone(),
+            challenges: self.rounds.iter().map(|(_, _, u)| u.clone()).collect(),
+        })
+    }
+}
+
+/// An opening proof reduced in a circuit, whose remaining check is deferred.
+#[derive(Debug)]
+pub struct Accumulator<C: CurveAffine, IpaChip: IpaInstructions<C>> {
+    reduced: Point<C, IpaChip>,
+    g: Point<C, IpaChip>,
+    iota: IpaChip::Challenge,
+    z: IpaChip::Challenge,
+    challenges: Vec<IpaChip::Challenge>,
+}
+
+impl<C: CurveAffine, IpaChip: IpaInstructions<C>> Accumulator<C, IpaChip> {
+    /// The reduced commitment $P'$.
+    pub fn reduced(&self) -> &Point<C, IpaChip> {
+        &self.reduced
+    }
+
+    /// The purported $G$.
+    pub fn g(&self) -> &Point<C, IpaChip> {
+        &self.g
+    }
+
+    /// Exposes this accumulator as public inputs, in the order given by
+    /// [`public_inputs`].
+    pub fn expose(&self, mut layouter: impl Layouter<IpaChip>) -> Result<(), Error> {
+        IpaChip::expose_point(&mut layouter, self.reduced.inner())?;
+        IpaChip::expose_point(&mut layouter, self.g.inner())?;
+        IpaChip::expose_challenge(&mut layouter, &self.iota)?;
+        IpaChip::expose_challenge(&mut layouter, &self.z)?;
+        for challenge in &self.challenges {
+            IpaChip::expose_challenge(&mut layouter, challenge)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the public inputs exposed by [`Accumulator::expose`] for `proof`, with the
+/// reduced commitment `reduced` and the purported `g`.
+///
+/// These are the coordinates of $P'$ and $G$, followed by $\iota$, $z$ and the
+/// challenges $u_i$ of the rounds. The identity has coordinates $(0, 0)$.
+pub fn public_inputs<C: CurveAffine>(
+    proof: &commitment::OpeningProof<C>,
+    reduced: C,
+    g: C,
+) -> Vec<C::Base> {
+    let coordinates = |point: C| {
+        let (x, y) = Option::from(point.get_xy()).unwrap_or((C::Base::zero(), C::Base::zero()));
+        [x, y]
+    };
+
+    let mut inputs = Vec::with_capacity(6 + proof.rounds.len());
+    inputs.extend_from_slice(&coordinates(reduced));
+    inputs.extend_from_slice(&coordinates(g));
+    for challenge in [proof.iota, proof.z]
+        .iter()
+        .chain(proof.rounds.iter().map(|(_, _, u)| u))
+    {
+        inputs.push(C::Base::from_u128(challenge.0));
+    }
+    inputs
+}
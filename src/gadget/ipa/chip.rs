@@ -0,0 +1,518 @@
+//! A chip implementing [`IpaInstructions`] with the ECC chip.
+//!
+//! # Layout
+//!
+//! The chip includes an [`EccConfig`], and implements [`EccInstructions`] with it. A
+//! permutation over the `x_p, y_p` columns of the ECC configuration, a `z` column and a
+//! `public` column connects the points and challenges to the gates of the chip.
+//!
+//! A challenge is witnessed in one row of the `z` column. Multiplying by a challenge
+//! lays out the rows of [`EccInstructions::mul_endo`] with its 128 bits, and places a
+//! running sum in the `z` column of each round row, as in the
+//! [`SchnorrChip`](crate::gadget::schnorr::SchnorrChip). The final running sum is
+//! constrained to equal the challenge, so the bits are its unique encoding. Dividing a
+//! point by a challenge witnesses the quotient, and constrains its product with the
+//! challenge to equal the point.
+//!
+//! Exposing a value copies it into one row of the `public` column, which is constrained
+//! to equal the same row of an instance column. The `public` column is only used to
+//! expose values, so the $i$-th value exposed by the chip is the $i$-th public input.
+
+use std::marker::PhantomData;
+
+use ff::Field;
+use group::Curve;
+
+use super::IpaInstructions;
+use crate::{
+    arithmetic::{CurveAffine, FieldExt},
+    circuit::{Cell, Chip, Layouter},
+    gadget::ecc::{EccChip, EccConfig, EccFixedPoint, EccInstructions, EccPoint, EccX},
+    plonk::{Advice, Any, Column, ConstraintSystem, Error, Expression, Permutation, Selector},
+    poly::Rotation,
+    transcript::{Challenge, ChallengeScalar},
+};
+
+/// The number of bits of a challenge.
+const CHALLENGE_BITS: usize = 128;
+
+/// A challenge assigned in the circuit.
+#[derive(Clone, Copy, Debug)]
+pub struct AssignedChallenge {
+    pub(crate) cell: Cell,
+    pub(crate) value: Option<Challenge>,
+}
+
+impl AssignedChallenge {
+    /// The cell containing this challenge.
+    pub fn cell(&self) -> Cell {
+        self.cell
+    }
+
+    /// The challenge, if known.
+    pub fn value(&self) -> Option<Challenge> {
+        self.value
+    }
+}
+
+/// Configuration for an [`IpaChip`].
+#[derive(Clone, Debug)]
+pub struct IpaConfig {
+    ecc: EccConfig,
+    z: Column<Advice>,
+    public: Column<Advice>,
+    s_challenge: Selector,
+    s_start: Selector,
+    s_public: Selector,
+    perm: Permutation,
+}
+
+/// A chip reducing opening proofs for commitments over the curve `C`.
+#[derive(Debug)]
+pub struct IpaChip<C: CurveAffine> {
+    _marker: PhantomData<C>,
+}
+
+impl<C: CurveAffine> IpaChip<C> {
+    /// Configures this chip for use in a circuit.
+    pub fn configure(meta: &mut ConstraintSystem<C::Base>) -> IpaConfig {
+        let ecc = EccChip::<C>::configure(meta);
+        IpaConfig::configure(meta, ecc, &[])
+    }
+}
+
+impl IpaConfig {
+    /// Configures the challenge and public input gates over the columns of `ecc`, with
+    /// `columns` of other chips added to the permutation, so that challenges can be
+    /// copied from them.
+    pub(crate) fn configure<F: FieldExt>(
+        meta: &mut ConstraintSystem<F>,
+        ecc: EccConfig,
+        columns: &[Column<Any>],
+    ) -> Self {
+        let z = meta.advice_column();
+        let public = meta.advice_column();
+        let instance = meta.instance_column();
+
+        let s_challenge = meta.selector();
+        let s_start = meta.selector();
+        let s_public = meta.selector();
+
+        let perm = Permutation::new(
+            meta,
+            &[ecc.x_p.into(), ecc.y_p.into(), z.into(), public.into()]
+                .iter()
+                .chain(columns.iter())
+                .copied()
+                .collect::<Vec<_>>(),
+        );
+
+        // z = 4 z_prev + window + 2 negate, where z_prev is taken as zero at the start.
+        meta.create_gate("challenge running sum", |meta| {
+            let z_cur = meta.query_advice(z, Rotation::cur());
+            let z_prev = meta.query_advice(z, Rotation(-2));
+            let window = meta.query_advice(ecc.window, Rotation::cur());
+            let negate = meta.query_advice(ecc.negate, Rotation::cur());
+            let s_challenge = meta.query_selector(s_challenge, Rotation::cur());
+            let s_start = meta.query_selector(s_start, Rotation::cur());
+            let one = Expression::Constant(F::one());
+
+            s_challenge
+                * (z_cur
+                    - (one - s_start) * z_prev * F::from_u64(4)
+                    - window
+                    - negate * F::from_u64(2))
+        });
+
+        meta.create_gate("public input", |meta| {
+            let public = meta.query_advice(public, Rotation::cur());
+            let instance = meta.query_instance(instance, Rotation::cur());
+            let s_public = meta.query_selector(s_public, Rotation::cur());
+
+            s_public * (public - instance)
+        });
+
+        IpaConfig {
+            ecc,
+            z,
+            public,
+            s_challenge,
+            s_start,
+            s_public,
+            perm,
+        }
+    }
+
+    /// Exposes the value in `cell` as the next public input.
+    fn expose<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        layouter: &mut impl Layouter<Ch>,
+        cell: Cell,
+        value: Option<F>,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "expose",
+            |mut region| {
+                self.s_public.enable(&mut region, 0)?;
+                let var = region.assign_advice(
+                    || "public",
+                    self.public,
+                    0,
+                    || value.ok_or(Error::SynthesisError),
+                )?;
+                region.constrain_equal(&self.perm, cell, var)
+            },
+        )
+    }
+}
+
+impl<C: CurveAffine> Chip for IpaChip<C> {
+    type Config = IpaConfig;
+    type Loaded = ();
+    type Field = C::Base;
+
+    fn load(_layouter: &mut impl Layouter<Self>) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<C: CurveAffine> EccInstructions<C> for IpaChip<C> {
+    type Point = EccPoint<C>;
+    type X = EccX<C::Base>;
+    type FixedPoint = EccFixedPoint<C>;
+
+    fn fixed_point(base: C) -> Self::FixedPoint {
+        EccChip::<C>::fixed_point(base)
+    }
+
+    fn witness_point(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<C>,
+    ) -> Result<Self::Point, Error> {
+        layouter.config().ecc.clone().witness_point(layouter, value)
+    }
+
+    fn extract_p(point: &Self::Point) -> Self::X {
+        point.extract_p()
+    }
+
+    fn add(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Point,
+        b: &Self::Point,
+    ) -> Result<Self::Point, Error> {
+        layouter.config().ecc.clone().add(layouter, a, b)
+    }
+
+    fn double(layouter: &mut impl Layouter<Self>, a: &Self::Point) -> Result<Self::Point, Error> {
+        layouter.config().ecc.clone().double(layouter, a)
+    }
+
+    fn mul_fixed(
+        layouter: &mut impl Layouter<Self>,
+        scalar: Option<C::Scalar>,
+        base: &Self::FixedPoint,
+    ) -> Result<Self::Point, Error> {
+        layouter
+            .config()
+            .ecc
+            .clone()
+            .mul_fixed(layouter, scalar, base)
+    }
+
+    fn mul_endo(
+        layouter: &mut impl Layouter<Self>,
+        base: &Self::Point,
+        bits: &[Option<bool>],
+    ) -> Result<Self::Point, Error> {
+        layouter.config().ecc.clone().mul_endo(layouter, base, bits)
+    }
+
+    fn constrain_equal(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Point,
+        b: &Self::Point,
+    ) -> Result<(), Error> {
+        layouter
+            .config()
+            .ecc
+            .clone()
+            .constrain_equal(layouter, a, b)
+    }
+}
+
+/// The instructions of [`IpaChip`], for use by other chips that include an
+/// [`IpaConfig`] in their own configuration.
+impl IpaConfig {
+    /// Returns `[u] base`, where `u` is the scalar of `challenge`.
+    pub(crate) fn mul_challenge<C: CurveAffine, Ch: Chip<Field = C::Base>>(
+        &self,
+        layouter: &mut impl Layouter<Ch>,
+        base: &EccPoint<C>,
+        challenge: &AssignedChallenge,
+    ) -> Result<EccPoint<C>, Error> {
+        let bits: Vec<_> = (0..CHALLENGE_BITS)
+            .map(|i| challenge.value.map(|value| (value.0 >> i) & 1 == 1))
+            .collect();
+
+        layouter.assign_region(
+            || "challenge multiplication",
+            |mut region| {
+                let product = self.ecc.assign_mul_endo(&mut region, 0, base, &bits)?;
+
+                let mut z = Some(C::Base::zero());
+                let mut cell = None;
+                for (i, pair) in bits.chunks(2).rev().enumerate() {
+                    let row = 2 + 2 * i;
+                    self.s_challenge.enable(&mut region, row)?;
+                    if i == 0 {
+                        self.s_start.enable(&mut region, row)?;
+                    }
+
+                    z = z.zip(pair[0]).zip(pair[1]).map(|((z, low), high)| {
+                        z * C::Base::from_u64(4)
+                            + C::Base::from_u64(low as u64)
+                            + C::Base::from_u64(2 * high as u64)
+                    });
+                    cell = Some(region.assign_advice(
+                        || format!("z_{}", i + 1),
+                        self.z,
+                        row,
+                        || z.ok_or(Error::SynthesisError),
+                    )?);
+                }
+                region.constrain_equal(&self.perm, cell.unwrap(), challenge.cell)?;
+
+                Ok(product)
+            },
+        )
+    }
+
+    /// Returns `[u^{-1}] point`, where `u` is the scalar of `challenge`.
+    pub(crate) fn div_challenge<C: CurveAffine, Ch: Chip<Field = C::Base>>(
+        &self,
+        layouter: &mut impl Layouter<Ch>,
+        point: &EccPoint<C>,
+        challenge: &AssignedChallenge,
+    ) -> Result<EccPoint<C>, Error> {
+        let quotient = point
+            .point()
+            .zip(challenge.value)
+            .map(|(point, challenge)| {
+                let challenge = *ChallengeScalar::<C, ()>::from(challenge);
+                Option::from(challenge.invert())
+                    .map(|inv| (point * inv).to_affine())
+                    .ok_or(Error::SynthesisError)
+            })
+            .transpose()?;
+
+        let quotient = self.ecc.witness_point(layouter, quotient)?;
+        let product = self.mul_challenge(layouter, &quotient, challenge)?;
+        self.ecc.constrain_equal(layouter, &product, point)?;
+        Ok(quotient)
+    }
+
+    /// Exposes the coordinates of `point` as the next two public inputs.
+    pub(crate) fn expose_point<C: CurveAffine, Ch: Chip<Field = C::Base>>(
+        &self,
+        layouter: &mut impl Layouter<Ch>,
+        point: &EccPoint<C>,
+    ) -> Result<(), Error> {
+        let coordinates = point.point().map(|point| {
+            Option::from(point.get_xy()).unwrap_or((C::Base::zero(), C::Base::zero()))
+        });
+        self.expose(layouter, point.x(), coordinates.map(|(x, _)| x))?;
+        self.expose(layouter, point.y(), coordinates.map(|(_, y)| y))
+    }
+
+    /// Exposes `challenge` as the next public input.
+    pub(crate) fn expose_challenge<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        layouter: &mut impl Layouter<Ch>,
+        challenge: &AssignedChallenge,
+    ) -> Result<(), Error> {
+        let value = challenge.value.map(|value| F::from_u128(value.0));
+        self.expose(layouter, challenge.cell, value)
+    }
+}
+
+impl<C: CurveAffine> IpaInstructions<C> for IpaChip<C> {
+    type Challenge = AssignedChallenge;
+
+    fn witness_challenge(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<Challenge>,
+    ) -> Result<Self::Challenge, Error> {
+        let config = layouter.config().clone();
+        layouter.assign_region(
+            || "witness challenge",
+            |mut region| {
+                let cell = region.assign_advice(
+                    || "challenge",
+                    config.z,
+                    0,
+                    || {
+                        value
+                            .map(|value| C::Base::from_u128(value.0))
+                            .ok_or(Error::SynthesisError)
+                    },
+                )?;
+                Ok(AssignedChallenge { cell, value })
+            },
+        )
+    }
+
+    fn mul_challenge(
+        layouter: &mut impl Layouter<Self>,
+        base: &Self::Point,
+        challenge: &Self::Challenge,
+    ) -> Result<Self::Point, Error> {
+        layouter
+            .config()
+            .clone()
+            .mul_challenge(layouter, base, challenge)
+    }
+
+    fn div_challenge(
+        layouter: &mut impl Layouter<Self>,
+        point: &Self::Point,
+        challenge: &Self::Challenge,
+    ) -> Result<Self::Point, Error> {
+        layouter
+            .config()
+            .clone()
+            .div_challenge(layouter, point, challenge)
+    }
+
+    fn expose_point(layouter: &mut impl Layouter<Self>, point: &Self::Point) -> Result<(), Error> {
+        layouter.config().clone().expose_point(layouter, point)
+    }
+
+    fn expose_challenge(
+        layouter: &mut impl Layouter<Self>,
+        challenge: &Self::Challenge,
+    ) -> Result<(), Error> {
+        layouter
+            .config()
+            .clone()
+            .expose_challenge(layouter, challenge)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use group::Curve;
+    use rand::rngs::OsRng;
+
+    use super::{IpaChip, IpaConfig};
+    use crate::{
+        arithmetic::{eval_polynomial, FieldExt},
+        circuit::{layouter, Layouter},
+        dev::MockProver,
+        gadget::{
+            ecc::Point,
+            ipa::{public_inputs, OpeningProof},
+        },
+        pasta::{pallas, EpAffine},
+        plonk::{Assignment, Circuit, ConstraintSystem, Error},
+        poly::{
+            commitment::{self, create_proof, read_opening_proof, Blind, Params},
+            EvaluationDomain,
+        },
+        transcript::{Blake2bRead, Blake2bWrite, ChallengeScalar, TranscriptRead, TranscriptWrite},
+    };
+
+    const K: u32 = 3;
+
+    struct IpaCircuit {
+        commitment: Option<EpAffine>,
+        proof: Option<commitment::OpeningProof<EpAffine>>,
+        g: Option<EpAffine>,
+    }
+
+    impl Circuit<pallas::Base> for IpaCircuit {
+        type Config = IpaConfig;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> IpaConfig {
+            IpaChip::<EpAffine>::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<pallas::Base>,
+            config: IpaConfig,
+        ) -> Result<(), Error> {
+            let mut layouter = layouter::SingleChip::<IpaChip<EpAffine>, _>::new(cs, config)?;
+
+            let commitment = Point::new(layouter.namespace(|| "P"), self.commitment)?;
+            let proof = OpeningProof::new(
+                layouter.namespace(|| "proof"),
+                K as usize,
+                self.proof.as_ref(),
+            )?;
+            let accumulator =
+                proof.accumulate(layouter.namespace(|| "accumulate"), &commitment, self.g)?;
+            accumulator.expose(layouter.namespace(|| "expose"))
+        }
+    }
+
+    #[test]
+    fn ipa_accumulate() {
+        let params = Params::<EpAffine>::new(K);
+        let domain = EvaluationDomain::new(1, K);
+        let mut px = domain.empty_coeff();
+        for (i, a) in px.iter_mut().enumerate() {
+            *a = pallas::Scalar::from_u64(i as u64 + 1);
+        }
+        let blind = Blind(pallas::Scalar::rand());
+        let p = params.commit(&px, blind).to_affine();
+
+        let mut transcript = Blake2bWrite::<Vec<u8>, EpAffine>::init(vec![]);
+        transcript.write_point(p).unwrap();
+        let x = ChallengeScalar::<_, ()>::get(&mut transcript);
+        let v = eval_polynomial(&px, *x);
+        transcript.write_scalar(v).unwrap();
+        create_proof(&params, OsRng, &mut transcript, &px, blind, *x).unwrap();
+        let proof = transcript.finalize();
+
+        let mut transcript = Blake2bRead::<&[u8], EpAffine>::init(&proof[..]);
+        transcript.read_point().unwrap();
+        ChallengeScalar::<_, ()>::get(&mut transcript);
+        transcript.read_scalar().unwrap();
+        let proof = read_opening_proof(&params, &mut transcript).unwrap();
+
+        // The deferred check passes for the reduced commitment.
+        let reduced = proof.reduce(p);
+        let g = proof.compute_g(&params);
+        assert!(proof.decide(&params, *x, v, reduced, g));
+        assert!(!proof.decide(&params, *x, v + pallas::Scalar::one(), reduced, g));
+
+        let mut instance = public_inputs(&proof, reduced, g);
+        instance.resize(1 << 11, pallas::Base::zero());
+
+        let circuit = IpaCircuit {
+            commitment: Some(p),
+            proof: Some(proof.clone()),
+            g: Some(g),
+        };
+        let prover = MockProver::run(11, &circuit, vec![instance.clone()]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // A wrong public input is rejected.
+        let mut bad_instance = instance.clone();
+        bad_instance[0] += pallas::Base::one();
+        let prover = MockProver::run(11, &circuit, vec![bad_instance]).unwrap();
+        assert!(prover.verify().is_err());
+
+        // A wrong commitment reduces to a different point.
+        let circuit = IpaCircuit {
+            commitment: Some((p + params.g[0]).to_affine()),
+            proof: Some(proof),
+            g: Some(g),
+        };
+        let prover = MockProver::run(11, &circuit, vec![instance]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
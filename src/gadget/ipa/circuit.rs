@@ -0,0 +1,190 @@
+//! A circuit reducing one opening proof, for recursion over a cycle of curves.
+//!
+//! The [`OpeningCircuit`] for a curve `C` is a circuit over `C::Base` that reduces an
+//! opening proof for a commitment over `C`, and exposes its accumulator. Over the
+//! Pasta cycle, proofs for circuits over $\mathbb{F}_q$ have commitments over Pallas,
+//! whose openings are reduced by `OpeningCircuit<pallas::Affine>` over
+//! $\mathbb{F}_p$, and vice versa with Vesta.
+
+use super::{public_inputs, IpaChip, IpaConfig, OpeningProof};
+use crate::{
+    arithmetic::CurveAffine,
+    circuit::{layouter, Layouter},
+    gadget::ecc::Point,
+    plonk::{Assignment, Circuit, ConstraintSystem, Error},
+    poly::commitment::{self, Params},
+};
+
+/// A circuit that reduces an opening proof for `commitment`, and exposes the
+/// accumulator of [`OpeningProof::accumulate`] as its public inputs.
+#[derive(Debug)]
+pub struct OpeningCircuit<C: CurveAffine> {
+    k: usize,
+    commitment: Option<C>,
+    proof: Option<commitment::OpeningProof<C>>,
+    g: Option<C>,
+}
+
+impl<C: CurveAffine> OpeningCircuit<C> {
+    /// Returns the circuit without witnesses, for proofs with `k` rounds, such as for
+    /// key generation.
+    pub fn empty(k: usize) -> Self {
+        OpeningCircuit {
+            k,
+            commitment: None,
+            proof: None,
+            g: None,
+        }
+    }
+
+    /// Returns the circuit that reduces `proof` for `commitment`, computing the
+    /// purported $G$ from the challenges of `proof`.
+    pub fn new(params: &Params<C>, commitment: C, proof: commitment::OpeningProof<C>) -> Self {
+        OpeningCircuit {
+            k: proof.rounds.len(),
+            commitment: Some(commitment),
+            g: Some(proof.compute_g(params)),
+            proof: Some(proof),
+        }
+    }
+
+    /// Returns the public inputs of this circuit, or `None` if it has no witnesses.
+    pub fn public_inputs(&self) -> Option<Vec<C::Base>> {
+        let proof = self.proof.as_ref()?;
+        Some(public_inputs(
+            proof,
+            proof.reduce(self.commitment?),
+            self.g?,
+        ))
+    }
+
+    /// Returns the accumulator whose check this circuit defers, which can be folded
+    /// with those of other proofs by
+    /// [`create_accumulation_proof`](commitment::create_accumulation_proof).
+    pub fn accumulator(&self) -> Option<commitment::Accumulator<C>> {
+        let proof = self.proof.as_ref()?;
+        Some(commitment::Accumulator {
+            g: self.g?,
+            challenges_packed: proof.rounds.iter().map(|(_, _, u)| *u).collect(),
+        })
+    }
+
+    /// Returns the reduced commitment $P'$ exposed by this circuit, or `None` if it
+    /// has no witnesses.
+    pub fn reduced(&self) -> Option<C> {
+        Some(self.proof.as_ref()?.reduce(self.commitment?))
+    }
+}
+
+impl<C: CurveAffine> Circuit<C::Base> for OpeningCircuit<C> {
+    type Config = IpaConfig;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn configure(meta: &mut ConstraintSystem<C::Base>) -> IpaConfig {
+        IpaChip::<C>::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        cs: &mut impl Assignment<C::Base>,
+        config: IpaConfig,
+    ) -> Result<(), Error> {
+        let mut layouter = layouter::SingleChip::<IpaChip<C>, _>::new(cs, config)?;
+
+        let commitment = Point::new(layouter.namespace(|| "P"), self.commitment)?;
+        let proof = OpeningProof::new(layouter.namespace(|| "proof"), self.k, self.proof.as_ref())?;
+        let accumulator =
+            proof.accumulate(layouter.namespace(|| "accumulate"), &commitment, self.g)?;
+        accumulator.expose(layouter.namespace(|| "expose"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ff::Field;
+    use group::Curve;
+    use rand::rngs::OsRng;
+
+    use super::OpeningCircuit;
+    use crate::{
+        arithmetic::{eval_polynomial, CurveAffine, FieldExt},
+        dev::MockProver,
+        pasta::{EpAffine, EqAffine},
+        poly::{
+            commitment::{
+                create_accumulation_proof, create_proof, read_opening_proof,
+                verify_accumulation_proof, Blind, Params,
+            },
+            EvaluationDomain,
+        },
+        transcript::{Blake2bRead, Blake2bWrite, ChallengeScalar, TranscriptRead, TranscriptWrite},
+    };
+
+    const K: u32 = 3;
+
+    /// Opens a commitment over `C`, reduces the opening in a circuit over `C::Base`,
+    /// and folds its accumulator.
+    fn recursion_step<C: CurveAffine>() {
+        let params = Params::<C>::new(K);
+        let domain = EvaluationDomain::new(1, K);
+        let mut px = domain.empty_coeff();
+        for (i, a) in px.iter_mut().enumerate() {
+            *a = C::Scalar::from_u64(i as u64 + 3);
+        }
+        let blind = Blind(C::Scalar::rand());
+        let p = params.commit(&px, blind).to_affine();
+
+        let mut transcript = Blake2bWrite::<Vec<u8>, C>::init(vec![]);
+        transcript.write_point(p).unwrap();
+        let x = ChallengeScalar::<_, ()>::get(&mut transcript);
+        let v = eval_polynomial(&px, *x);
+        transcript.write_scalar(v).unwrap();
+        create_proof(&params, OsRng, &mut transcript, &px, blind, *x).unwrap();
+        let proof = transcript.finalize();
+
+        let mut transcript = Blake2bRead::<&[u8], C>::init(&proof[..]);
+        transcript.read_point().unwrap();
+        ChallengeScalar::<_, ()>::get(&mut transcript);
+        transcript.read_scalar().unwrap();
+        let proof = read_opening_proof(&params, &mut transcript).unwrap();
+
+        let circuit = OpeningCircuit::new(&params, p, proof.clone());
+        let reduced = circuit.reduced().unwrap();
+        let accumulator = circuit.accumulator().unwrap();
+        assert!(proof.decide(&params, *x, v, reduced, accumulator.g));
+
+        let mut instance = circuit.public_inputs().unwrap();
+        instance.resize(1 << 11, C::Base::zero());
+        let prover = MockProver::run(11, &circuit, vec![instance.clone()]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // The circuit does not reduce a different commitment to the same point.
+        let circuit = OpeningCircuit::new(
+            &params,
+            (p.to_curve() + params.get_g()[0]).to_affine(),
+            proof,
+        );
+        let prover = MockProver::run(11, &circuit, vec![instance]).unwrap();
+        assert!(prover.verify().is_err());
+
+        // The deferred accumulators fold into one.
+        let accumulators = vec![accumulator.clone(), accumulator];
+        let mut transcript = Blake2bWrite::<Vec<u8>, C>::init(vec![]);
+        create_accumulation_proof(&params, OsRng, &mut transcript, &accumulators).unwrap();
+        let proof = transcript.finalize();
+        let mut transcript = Blake2bRead::<&[u8], C>::init(&proof[..]);
+        let guard = verify_accumulation_proof(&params, &mut transcript, &accumulators).unwrap();
+        assert!(guard.use_challenges().eval());
+    }
+
+    #[test]
+    fn pallas_openings_over_fp() {
+        recursion_step::<EpAffine>();
+    }
+
+    #[test]
+    fn vesta_openings_over_fq() {
+        recursion_step::<EqAffine>();
+    }
+}
@@ -0,0 +1,132 @@
+//! Gadget and chips for the Keccak-f\[1600\] permutation and the Keccak-256 hash
+//! function.
+//!
+//! [`Keccak256`] is the in-circuit counterpart of
+//! [`crate::primitives::keccak::keccak256`], with the message provided as 64-bit lanes
+//! (so its length must be a multiple of eight bytes). Each lane holds eight bytes of
+//! the message in little-endian order, and the digest is returned as four such lanes.
+
+use std::convert::TryInto;
+use std::fmt;
+
+use crate::{
+    circuit::{Chip, Layouter},
+    plonk::Error,
+    primitives::keccak::{padding, DIGEST_LANES, LANES, RATE},
+};
+
+mod permutation;
+pub use permutation::{AssignedLane, KeccakChip, KeccakConfig};
+
+/// A lane of a message block: either part of the message, or a constant padding lane
+/// that is fixed by the circuit.
+#[derive(Clone, Copy, Debug)]
+pub enum BlockLane<L> {
+    /// A message lane provided by the prover.
+    Message(L),
+    /// A padding lane that is known when the circuit is configured.
+    Padding(u64),
+}
+
+/// The set of circuit instructions required to use the [`Keccak256`] gadget.
+pub trait KeccakInstructions: Chip {
+    /// Variable representing a 64-bit lane.
+    type Lane: Copy + fmt::Debug;
+
+    /// Witnesses a 64-bit message lane.
+    fn load_lane(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<u64>,
+    ) -> Result<Self::Lane, Error>;
+
+    /// Returns the all-zero initial state.
+    fn initial_state(layouter: &mut impl Layouter<Self>) -> Result<[Self::Lane; LANES], Error>;
+
+    /// XORs the given block into the first [`RATE`] lanes of the state.
+    fn absorb(
+        layouter: &mut impl Layouter<Self>,
+        state: &[Self::Lane; LANES],
+        block: &[BlockLane<Self::Lane>; RATE],
+    ) -> Result<[Self::Lane; LANES], Error>;
+
+    /// Applies the Keccak-f\[1600\] permutation to the given state.
+    fn permute(
+        layouter: &mut impl Layouter<Self>,
+        state: &[Self::Lane; LANES],
+    ) -> Result<[Self::Lane; LANES], Error>;
+}
+
+/// A Keccak-256 hasher.
+#[derive(Debug)]
+pub struct Keccak256<KeccakChip: KeccakInstructions> {
+    state: [KeccakChip::Lane; LANES],
+    cur_block: Vec<BlockLane<KeccakChip::Lane>>,
+    len: usize,
+}
+
+impl<KeccakChip: KeccakInstructions> Keccak256<KeccakChip> {
+    /// Creates a new hasher instance.
+    pub fn new(mut layouter: impl Layouter<KeccakChip>) -> Result<Self, Error> {
+        Ok(Keccak256 {
+            state: KeccakChip::initial_state(&mut layouter)?,
+            cur_block: Vec::with_capacity(RATE),
+            len: 0,
+        })
+    }
+
+    /// Absorbs the given message lanes, permuting the state as each block is filled.
+    pub fn update(
+        &mut self,
+        mut layouter: impl Layouter<KeccakChip>,
+        data: &[KeccakChip::Lane],
+    ) -> Result<(), Error> {
+        for lane in data {
+            self.push(&mut layouter, BlockLane::Message(*lane))?;
+        }
+        self.len += data.len();
+        Ok(())
+    }
+
+    /// Pads the absorbed message, and returns its hash.
+    pub fn finalize(
+        mut self,
+        mut layouter: impl Layouter<KeccakChip>,
+    ) -> Result<[KeccakChip::Lane; DIGEST_LANES], Error> {
+        for lane in padding(self.len, 0x01) {
+            self.push(&mut layouter, BlockLane::Padding(lane))?;
+        }
+        assert!(self.cur_block.is_empty());
+
+        Ok(self.state[..DIGEST_LANES].try_into().unwrap())
+    }
+
+    /// Convenience function to compute the hash of the given message.
+    pub fn digest(
+        mut layouter: impl Layouter<KeccakChip>,
+        data: &[KeccakChip::Lane],
+    ) -> Result<[KeccakChip::Lane; DIGEST_LANES], Error> {
+        let mut hasher = Self::new(layouter.namespace(|| "init"))?;
+        hasher.update(layouter.namespace(|| "update"), data)?;
+        hasher.finalize(layouter.namespace(|| "finalize"))
+    }
+
+    fn push(
+        &mut self,
+        layouter: &mut impl Layouter<KeccakChip>,
+        lane: BlockLane<KeccakChip::Lane>,
+    ) -> Result<(), Error> {
+        self.cur_block.push(lane);
+        if self.cur_block.len() == RATE {
+            let block: [_; RATE] = self
+                .cur_block
+                .drain(..)
+                .collect::<Vec<_>>()
+                .try_into()
+                .unwrap();
+            let state =
+                KeccakChip::absorb(&mut layouter.namespace(|| "absorb"), &self.state, &block)?;
+            self.state = KeccakChip::permute(&mut layouter.namespace(|| "permute"), &state)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,632 @@
+//! A chip for the Keccak-f\[1600\] permutation.
+//!
+//! Each lane is assigned alongside its 64 bits, so that the θ and χ steps are
+//! low-degree gates over bits and the other steps cost nothing: ρ and π only change
+//! which bit cells the θ gate writes to, and ι is folded into the χ gate.
+//!
+//! # Layout
+//!
+//! The chip works on planes: each row holds five lanes, with the lane at $(x, y)$ of a
+//! state in column $x$ of row $y$.
+//!
+//! Each round occupies 11 rows: the five planes of the input state, a row holding the
+//! column parities $C[x]$ of θ, and the five planes of the state after θ, ρ and π. The
+//! χ and ι steps are applied by a gate on the following five rows, which hold the output
+//! of the round and so the input of the next round. Keccak-f\[1600\] therefore occupies
+//! $24 \cdot 11 + 5 = 269$ rows.
+//!
+//! Absorbing a block uses three rows per plane: the state, the message lanes (with the
+//! lanes outside the rate fixed to zero), and their XOR.
+
+use std::convert::TryInto;
+use std::marker::PhantomData;
+
+use super::{BlockLane, KeccakInstructions};
+use crate::{
+    arithmetic::FieldExt,
+    circuit::{Cell, Chip, Layouter, Region},
+    plonk::{Advice, Column, ConstraintSystem, Error, Expression, Fixed, Permutation, Selector},
+    poly::Rotation,
+    primitives::keccak::{pi, State, LANES, RATE, ROTATIONS, ROUNDS, ROUND_CONSTANTS},
+};
+
+const LANE_BITS: usize = 64;
+
+/// The offset of the row holding the θ column parities within a round.
+const PARITY: usize = 5;
+
+/// The offset of the state after θ, ρ and π within a round.
+const THETA: usize = PARITY + 1;
+
+/// The number of rows in a round.
+const ROUND_ROWS: usize = THETA + 5;
+
+/// The bits that may be set in a round constant.
+const RC_BITS: [usize; 7] = [0, 1, 3, 7, 15, 31, 63];
+
+/// A 64-bit lane assigned in the circuit.
+#[derive(Clone, Copy, Debug)]
+pub struct AssignedLane {
+    var: Cell,
+    value: Option<u64>,
+}
+
+impl AssignedLane {
+    /// The cell containing this lane.
+    pub fn cell(&self) -> Cell {
+        self.var
+    }
+
+    /// The value of this lane, if known.
+    pub fn value(&self) -> Option<u64> {
+        self.value
+    }
+}
+
+/// Returns the XOR of the given bits.
+fn xor<F: FieldExt>(bits: impl IntoIterator<Item = Expression<F>>) -> Expression<F> {
+    let mut bits = bits.into_iter();
+    let first = bits.next().unwrap();
+    bits.fold(first, |acc, bit| {
+        acc.clone() + bit.clone() - acc * bit * F::from_u64(2)
+    })
+}
+
+/// Configuration for a [`KeccakChip`].
+#[derive(Clone, Debug)]
+pub struct KeccakConfig {
+    lanes: [Column<Advice>; 5],
+    bits: [[Column<Advice>; LANE_BITS]; 5],
+    constants: [Column<Fixed>; 5],
+    round_constant: [Column<Fixed>; RC_BITS.len()],
+    s_plane: Selector,
+    s_constant: [Selector; 5],
+    s_xor: Selector,
+    s_round: Selector,
+    s_chi: Selector,
+    perm: Permutation,
+}
+
+impl KeccakConfig {
+    /// Assigns the bits of the lane `value` in column `x` at `offset`.
+    fn assign_bits<F: FieldExt, C: Chip<Field = F>>(
+        &self,
+        region: &mut Region<'_, C>,
+        x: usize,
+        offset: usize,
+        value: Option<u64>,
+    ) -> Result<(), Error> {
+        for (z, column) in self.bits[x].iter().enumerate() {
+            region.assign_advice(
+                || format!("bit {}", z),
+                *column,
+                offset,
+                || {
+                    value
+                        .map(|v| F::from_u64((v >> z) & 1))
+                        .ok_or(Error::SynthesisError)
+                },
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Assigns the lane `value` and its bits in column `x` at `offset`.
+    ///
+    /// The caller must enable `s_plane` at `offset`.
+    fn assign_lane<F: FieldExt, C: Chip<Field = F>>(
+        &self,
+        region: &mut Region<'_, C>,
+        x: usize,
+        offset: usize,
+        value: Option<u64>,
+    ) -> Result<AssignedLane, Error> {
+        let var = region.assign_advice(
+            || "lane",
+            self.lanes[x],
+            offset,
+            || value.map(F::from_u64).ok_or(Error::SynthesisError),
+        )?;
+        self.assign_bits(region, x, offset, value)?;
+        Ok(AssignedLane { var, value })
+    }
+
+    /// Assigns a copy of `lane` and its bits in column `x` at `offset`.
+    fn copy_lane<F: FieldExt, C: Chip<Field = F>>(
+        &self,
+        region: &mut Region<'_, C>,
+        x: usize,
+        offset: usize,
+        lane: &AssignedLane,
+    ) -> Result<AssignedLane, Error> {
+        let copy = self.assign_lane(region, x, offset, lane.value)?;
+        region.constrain_equal(&self.perm, lane.var, copy.var)?;
+        Ok(copy)
+    }
+
+    /// Assigns the constant `value` and its bits in column `x` at `offset`.
+    fn assign_constant<F: FieldExt, C: Chip<Field = F>>(
+        &self,
+        region: &mut Region<'_, C>,
+        x: usize,
+        offset: usize,
+        value: u64,
+    ) -> Result<AssignedLane, Error> {
+        self.s_constant[x].enable(region, offset)?;
+        region.assign_fixed(
+            || "constant",
+            self.constants[x],
+            offset,
+            || Ok(F::from_u64(value)),
+        )?;
+        self.assign_lane(region, x, offset, Some(value))
+    }
+}
+
+/// A chip implementing Keccak-f\[1600\] over bit-decomposed lanes.
+#[derive(Debug)]
+pub struct KeccakChip<F: FieldExt> {
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt> KeccakChip<F> {
+    /// Configures this chip for use in a circuit.
+    ///
+    /// The chip allocates 325 advice columns and 12 fixed columns.
+    pub fn configure(meta: &mut ConstraintSystem<F>) -> KeccakConfig {
+        let lanes: [Column<Advice>; 5] = (0..5)
+            .map(|_| meta.advice_column())
+            .collect::<Vec<_>>()
+            .try_into()
+            .unwrap();
+        let bits: [[Column<Advice>; LANE_BITS]; 5] = (0..5)
+            .map(|_| {
+                (0..LANE_BITS)
+                    .map(|_| meta.advice_column())
+                    .collect::<Vec<_>>()
+                    .try_into()
+                    .unwrap()
+            })
+            .collect::<Vec<_>>()
+            .try_into()
+            .unwrap();
+        let constants: [Column<Fixed>; 5] = (0..5)
+            .map(|_| meta.fixed_column())
+            .collect::<Vec<_>>()
+            .try_into()
+            .unwrap();
+        let round_constant: [Column<Fixed>; RC_BITS.len()] = (0..RC_BITS.len())
+            .map(|_| meta.fixed_column())
+            .collect::<Vec<_>>()
+            .try_into()
+            .unwrap();
+
+        let s_plane = meta.selector();
+        let s_constant: [Selector; 5] = (0..5)
+            .map(|_| meta.selector())
+            .collect::<Vec<_>>()
+            .try_into()
+            .unwrap();
+        let s_xor = meta.selector();
+        let s_round = meta.selector();
+        let s_chi = meta.selector();
+
+        let perm = Permutation::new(
+            meta,
+            &lanes
+                .iter()
+                .map(|column| (*column).into())
+                .collect::<Vec<_>>(),
+        );
+
+        for x in 0..5 {
+            meta.create_gate("lane decomposition", |meta| {
+                let lane = meta.query_advice(lanes[x], Rotation::cur());
+                let s_plane = meta.query_selector(s_plane, Rotation::cur());
+
+                let composed = bits[x].iter().enumerate().fold(
+                    Expression::Constant(F::zero()),
+                    |acc, (z, bit)| {
+                        acc + meta.query_advice(*bit, Rotation::cur()) * F::from_u64(1 << z)
+                    },
+                );
+                s_plane * (composed - lane)
+            });
+            for bit in bits[x].iter().copied() {
+                meta.create_gate("boolean bit", |meta| {
+                    let bit = meta.query_advice(bit, Rotation::cur());
+                    let s_plane = meta.query_selector(s_plane, Rotation::cur());
+
+                    s_plane * bit.clone() * (Expression::Constant(F::one()) - bit)
+                });
+            }
+
+            meta.create_gate("constant lane", |meta| {
+                let lane = meta.query_advice(lanes[x], Rotation::cur());
+                let constant = meta.query_fixed(constants[x], Rotation::cur());
+                let s_constant = meta.query_selector(s_constant[x], Rotation::cur());
+
+                s_constant * (lane - constant)
+            });
+
+            for bit in bits[x].iter().copied() {
+                meta.create_gate("xor", |meta| {
+                    let a = meta.query_advice(bit, Rotation(-2));
+                    let b = meta.query_advice(bit, Rotation::prev());
+                    let output = meta.query_advice(bit, Rotation::cur());
+                    let s_xor = meta.query_selector(s_xor, Rotation::cur());
+
+                    s_xor * (xor(vec![a, b]) - output)
+                });
+            }
+        }
+
+        // θ: C[x] is the XOR of the lanes in column x.
+        for column_bits in bits.iter() {
+            for bit in column_bits.iter().copied() {
+                meta.create_gate("theta parity", |meta| {
+                    let column: Vec<_> = (0..5)
+                        .map(|y| meta.query_advice(bit, Rotation(y)))
+                        .collect();
+                    let parity = meta.query_advice(bit, Rotation(PARITY as i32));
+                    let s_round = meta.query_selector(s_round, Rotation::cur());
+
+                    s_round * (xor(column) - parity)
+                });
+            }
+        }
+
+        // θ, ρ and π: each lane is XORed with C[x - 1] and with C[x + 1] rotated by one
+        // bit, then rotated by ρ and moved by π.
+        #[allow(clippy::needless_range_loop)]
+        for x in 0..5 {
+            for y in 0..5 {
+                let target = pi(x, y);
+                let r = ROTATIONS[x][y] as usize;
+                for z in 0..LANE_BITS {
+                    let source = (z + LANE_BITS - r) % LANE_BITS;
+                    meta.create_gate("theta", |meta| {
+                        let input = meta.query_advice(bits[x][source], Rotation(y as i32));
+                        let prev =
+                            meta.query_advice(bits[(x + 4) % 5][source], Rotation(PARITY as i32));
+                        let next = meta.query_advice(
+                            bits[(x + 1) % 5][(source + LANE_BITS - 1) % LANE_BITS],
+                            Rotation(PARITY as i32),
+                        );
+                        let output = meta.query_advice(
+                            bits[target % 5][z],
+                            Rotation((THETA + target / 5) as i32),
+                        );
+                        let s_round = meta.query_selector(s_round, Rotation::cur());
+
+                        s_round * (xor(vec![input, prev, next]) - output)
+                    });
+                }
+            }
+        }
+
+        // χ and ι: each output bit is B[x] ^ (!B[x + 1] & B[x + 2]) within a plane,
+        // XORed with the round constant in the first lane.
+        #[allow(clippy::needless_range_loop)]
+        for x in 0..5 {
+            for z in 0..LANE_BITS {
+                meta.create_gate("chi", |meta| {
+                    let mut b = |x: usize| meta.query_advice(bits[x % 5][z], Rotation(-5));
+                    let (b_0, b_1, b_2) = (b(x), b(x + 1), b(x + 2));
+                    let not_and = (Expression::Constant(F::one()) - b_1) * b_2;
+                    let chi = b_0.clone() + not_and.clone() - b_0 * not_and * F::from_u64(2);
+
+                    let expected = match RC_BITS.iter().position(|bit| x == 0 && *bit == z) {
+                        Some(j) => xor(vec![
+                            chi,
+                            meta.query_fixed(round_constant[j], Rotation::cur()),
+                        ]),
+                        None => chi,
+                    };
+                    let output = meta.query_advice(bits[x][z], Rotation::cur());
+                    let s_chi = meta.query_selector(s_chi, Rotation::cur());
+
+                    s_chi * (expected - output)
+                });
+            }
+        }
+
+        KeccakConfig {
+            lanes,
+            bits,
+            constants,
+            round_constant,
+            s_plane,
+            s_constant,
+            s_xor,
+            s_round,
+            s_chi,
+            perm,
+        }
+    }
+}
+
+impl<F: FieldExt> Chip for KeccakChip<F> {
+    type Config = KeccakConfig;
+    type Loaded = ();
+    type Field = F;
+
+    fn load(_layouter: &mut impl Layouter<Self>) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<F: FieldExt> KeccakInstructions for KeccakChip<F> {
+    type Lane = AssignedLane;
+
+    fn load_lane(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<u64>,
+    ) -> Result<Self::Lane, Error> {
+        let config = layouter.config().clone();
+
+        layouter.assign_region(
+            || "load lane",
+            |mut region| {
+                config.s_plane.enable(&mut region, 0)?;
+                config.assign_lane(&mut region, 0, 0, value)
+            },
+        )
+    }
+
+    fn initial_state(layouter: &mut impl Layouter<Self>) -> Result<[Self::Lane; LANES], Error> {
+        let config = layouter.config().clone();
+
+        let zero = layouter.assign_region(
+            || "initial state",
+            |mut region| {
+                config.s_plane.enable(&mut region, 0)?;
+                config.assign_constant(&mut region, 0, 0, 0)
+            },
+        )?;
+        Ok([zero; LANES])
+    }
+
+    fn absorb(
+        layouter: &mut impl Layouter<Self>,
+        state: &[Self::Lane; LANES],
+        block: &[BlockLane<Self::Lane>; RATE],
+    ) -> Result<[Self::Lane; LANES], Error> {
+        let config = layouter.config().clone();
+
+        layouter.assign_region(
+            || "absorb",
+            |mut region| {
+                let mut next_state = *state;
+                for y in 0..5 {
+                    let offset = 3 * y;
+                    config.s_plane.enable(&mut region, offset)?;
+                    config.s_plane.enable(&mut region, offset + 1)?;
+                    config.s_plane.enable(&mut region, offset + 2)?;
+                    config.s_xor.enable(&mut region, offset + 2)?;
+
+                    for x in 0..5 {
+                        let i = x + 5 * y;
+                        let lane = config.copy_lane(&mut region, x, offset, &state[i])?;
+                        let input = match block.get(i) {
+                            Some(BlockLane::Message(input)) => {
+                                config.copy_lane(&mut region, x, offset + 1, input)?
+                            }
+                            Some(BlockLane::Padding(value)) => {
+                                config.assign_constant(&mut region, x, offset + 1, *value)?
+                            }
+                            None => config.assign_constant(&mut region, x, offset + 1, 0)?,
+                        };
+
+                        next_state[i] = config.assign_lane(
+                            &mut region,
+                            x,
+                            offset + 2,
+                            lane.value.zip(input.value).map(|(a, b)| a ^ b),
+                        )?;
+                    }
+                }
+                Ok(next_state)
+            },
+        )
+    }
+
+    fn permute(
+        layouter: &mut impl Layouter<Self>,
+        state: &[Self::Lane; LANES],
+    ) -> Result<[Self::Lane; LANES], Error> {
+        let config = layouter.config().clone();
+
+        layouter.assign_region(
+            || "keccak-f",
+            |mut region| {
+                for y in 0..5 {
+                    config.s_plane.enable(&mut region, y)?;
+                    for x in 0..5 {
+                        config.copy_lane(&mut region, x, y, &state[x + 5 * y])?;
+                    }
+                }
+                let mut values: Option<State> = state
+                    .iter()
+                    .map(|lane| lane.value)
+                    .collect::<Option<Vec<_>>>()
+                    .map(|values| values.try_into().unwrap());
+
+                for (round, rc) in ROUND_CONSTANTS.iter().enumerate() {
+                    let offset = round * ROUND_ROWS;
+                    config.s_round.enable(&mut region, offset)?;
+
+                    let parity = values.map(|a| {
+                        let mut c = [0u64; 5];
+                        for (x, c) in c.iter_mut().enumerate() {
+                            *c = (0..5).fold(0, |acc, y| acc ^ a[x + 5 * y]);
+                        }
+                        c
+                    });
+                    for x in 0..5 {
+                        config.assign_bits(
+                            &mut region,
+                            x,
+                            offset + PARITY,
+                            parity.map(|c| c[x]),
+                        )?;
+                    }
+
+                    let b = values.zip(parity).map(|(a, c)| {
+                        let mut b = [0u64; LANES];
+                        for x in 0..5 {
+                            for y in 0..5 {
+                                let d = c[(x + 4) % 5] ^ c[(x + 1) % 5].rotate_left(1);
+                                b[pi(x, y)] = (a[x + 5 * y] ^ d).rotate_left(ROTATIONS[x][y]);
+                            }
+                        }
+                        b
+                    });
+                    for i in 0..LANES {
+                        config.assign_bits(
+                            &mut region,
+                            i % 5,
+                            offset + THETA + i / 5,
+                            b.map(|b| b[i]),
+                        )?;
+                    }
+
+                    values = b.map(|b| {
+                        let mut next = [0u64; LANES];
+                        for (i, lane) in next.iter_mut().enumerate() {
+                            let (x, y) = (i % 5, i / 5);
+                            *lane = b[i] ^ (!b[(x + 1) % 5 + 5 * y] & b[(x + 2) % 5 + 5 * y]);
+                        }
+                        next[0] ^= rc;
+                        next
+                    });
+
+                    let output = offset + ROUND_ROWS;
+                    for (j, bit) in RC_BITS.iter().enumerate() {
+                        region.assign_fixed(
+                            || format!("round constant bit {}", bit),
+                            config.round_constant[j],
+                            output,
+                            || Ok(F::from_u64((rc >> bit) & 1)),
+                        )?;
+                    }
+                    for y in 0..5 {
+                        config.s_chi.enable(&mut region, output + y)?;
+                        if round + 1 < ROUNDS {
+                            for x in 0..5 {
+                                config.assign_bits(
+                                    &mut region,
+                                    x,
+                                    output + y,
+                                    values.map(|a| a[x + 5 * y]),
+                                )?;
+                            }
+                        }
+                    }
+                }
+
+                let offset = ROUNDS * ROUND_ROWS;
+                let mut next_state = *state;
+                for y in 0..5 {
+                    config.s_plane.enable(&mut region, offset + y)?;
+                    for x in 0..5 {
+                        let i = x + 5 * y;
+                        next_state[i] =
+                            config.assign_lane(&mut region, x, offset + y, values.map(|a| a[i]))?;
+                    }
+                }
+                Ok(next_state)
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryInto;
+
+    use super::{KeccakChip, KeccakConfig};
+    use crate::{
+        circuit::{layouter, Layouter},
+        dev::MockProver,
+        gadget::keccak::{Keccak256, KeccakInstructions},
+        pasta::Fp,
+        plonk::{Assignment, Circuit, ConstraintSystem, Error},
+        primitives::keccak::{keccak256, DIGEST_LANES},
+    };
+
+    struct Keccak256Circuit {
+        message: Option<Vec<u64>>,
+        len: usize,
+        digest: [u64; DIGEST_LANES],
+    }
+
+    impl Circuit<Fp> for Keccak256Circuit {
+        type Config = KeccakConfig;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> KeccakConfig {
+            KeccakChip::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            config: KeccakConfig,
+        ) -> Result<(), Error> {
+            let mut layouter = layouter::SingleChip::<KeccakChip<Fp>, _>::new(cs, config)?;
+            let config = layouter.config().clone();
+
+            let message = (0..self.len)
+                .map(|i| {
+                    KeccakChip::load_lane(
+                        &mut layouter,
+                        self.message.as_ref().map(|message| message[i]),
+                    )
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+
+            let digest = Keccak256::digest(layouter.namespace(|| "keccak256"), &message)?;
+
+            layouter.assign_region(
+                || "constrain digest",
+                |mut region| {
+                    config.s_plane.enable(&mut region, 0)?;
+                    for (x, (lane, expected)) in digest.iter().zip(self.digest.iter()).enumerate() {
+                        let expected = config.assign_constant(&mut region, x, 0, *expected)?;
+                        region.constrain_equal(&config.perm, lane.var, expected.var)?;
+                    }
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    fn lanes(bytes: &[u8]) -> Vec<u64> {
+        bytes
+            .chunks(8)
+            .map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn keccak256_public_key() {
+        // The length of an uncompressed secp256k1 public key, from which Ethereum
+        // addresses are derived.
+        let message: Vec<u8> = (0..64).map(|i| (i * 7 + 3) as u8).collect();
+        let digest: [u64; DIGEST_LANES] = lanes(&keccak256(&message)).try_into().unwrap();
+
+        let mut circuit = Keccak256Circuit {
+            message: Some(lanes(&message)),
+            len: 8,
+            digest,
+        };
+        let prover = MockProver::run(9, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        circuit.digest[0] ^= 1;
+        let prover = MockProver::run(9, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
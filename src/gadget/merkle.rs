@@ -0,0 +1,258 @@
+//! Gadget and chips for Merkle path verification, generic over the hash.
+//!
+//! A tree of arity $n$ and depth $d$ has $n^d$ leaves, and each internal node at layer
+//! $l$ (where the parents of the leaves are at layer 0) is the hash of its $n$ children
+//! at that layer. An authentication path gives the $n - 1$ siblings of each node from
+//! the leaf up to, but excluding, the root, and the position of the leaf, whose base-$n$
+//! digits give the index of each node among its siblings.
+//!
+//! The [`MerklePath`] gadget only depends on [`MerklePathInstructions`], so the same
+//! inclusion-proof code can be used with any hash that provides them. The
+//! [`PlaceConfig`] gate implements the placement of a node among its siblings for
+//! hashes whose nodes are single field elements.
+//!
+//! The [`SparseMerklePath`] gadget extends binary paths to sparse trees, whose paths
+//! are bound to their keys, to prove membership, non-membership and updates.
+
+use std::fmt;
+use std::marker::PhantomData;
+
+use crate::{
+    circuit::{Chip, Layouter},
+    plonk::Error,
+};
+
+mod place;
+mod sparse;
+pub use place::PlaceConfig;
+pub use sparse::{SparseConfig, SparseMerkleInstructions, SparseMerklePath};
+
+/// The set of circuit instructions required to hash the children of a node.
+pub trait HashInstructions: Chip {
+    /// Variable representing a node of the tree.
+    type Node: Clone + fmt::Debug;
+    /// The value of a node.
+    type NodeValue: Clone + fmt::Debug;
+
+    /// Returns the hash of the given children of a node at `layer`.
+    fn hash_children(
+        layouter: &mut impl Layouter<Self>,
+        layer: usize,
+        children: &[Self::Node],
+    ) -> Result<Self::Node, Error>;
+}
+
+/// The set of circuit instructions required to use the [`MerklePath`] gadget.
+pub trait MerklePathInstructions: HashInstructions {
+    /// Witnesses a node.
+    fn witness_node(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<Self::NodeValue>,
+    ) -> Result<Self::Node, Error>;
+
+    /// Witnesses `siblings` and `index`, and returns the children of their parent with
+    /// `node` at `index` and the siblings in order around it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is not at most the number of siblings.
+    fn place(
+        layouter: &mut impl Layouter<Self>,
+        node: &Self::Node,
+        siblings: &[Option<Self::NodeValue>],
+        index: Option<usize>,
+    ) -> Result<Vec<Self::Node>, Error>;
+
+    /// Constrains `a` and `b` to be equal.
+    fn constrain_equal(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Node,
+        b: &Self::Node,
+    ) -> Result<(), Error>;
+}
+
+/// An authentication path from a leaf to the root of a Merkle tree.
+#[derive(Debug)]
+pub struct MerklePath<MerkleChip: MerklePathInstructions> {
+    arity: usize,
+    position: Option<u64>,
+    path: Vec<Vec<Option<MerkleChip::NodeValue>>>,
+    _marker: PhantomData<MerkleChip>,
+}
+
+impl<MerkleChip: MerklePathInstructions> MerklePath<MerkleChip> {
+    /// Constructs a path in a tree of the given arity for the leaf at `position`, with
+    /// the siblings of the nodes from the leaf up to (but excluding) the root. The depth
+    /// of the tree is given by the length of `path`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `arity` is less than 2, if a layer of `path` does not contain
+    /// `arity - 1` siblings, or if the tree has more than $2^{64}$ leaves.
+    pub fn new(
+        arity: usize,
+        position: Option<u64>,
+        path: Vec<Vec<Option<MerkleChip::NodeValue>>>,
+    ) -> Self {
+        assert!(arity >= 2);
+        assert!(path.iter().all(|siblings| siblings.len() == arity - 1));
+        assert!((arity as u128)
+            .checked_pow(path.len() as u32)
+            .is_some_and(|leaves| leaves <= 1 << 64));
+        MerklePath {
+            arity,
+            position,
+            path,
+            _marker: PhantomData,
+        }
+    }
+
+    /// The arity of the tree.
+    pub fn arity(&self) -> usize {
+        self.arity
+    }
+
+    /// The depth of the tree.
+    pub fn depth(&self) -> usize {
+        self.path.len()
+    }
+
+    /// Computes the root of the tree from the given leaf.
+    ///
+    /// The index of each node among its siblings is constrained to be valid, but the
+    /// position itself is not exposed; circuits that need it should constrain it
+    /// separately.
+    pub fn calculate_root(
+        &self,
+        mut layouter: impl Layouter<MerkleChip>,
+        leaf: MerkleChip::Node,
+    ) -> Result<MerkleChip::Node, Error> {
+        let arity = self.arity as u64;
+        self.path
+            .iter()
+            .enumerate()
+            .try_fold(
+                (leaf, self.position),
+                |(node, position), (layer, siblings)| {
+                    let mut layouter = layouter.namespace(|| format!("layer {}", layer));
+                    let index = position.map(|position| (position % arity) as usize);
+                    let children = MerkleChip::place(&mut layouter, &node, siblings, index)?;
+                    let parent = MerkleChip::hash_children(&mut layouter, layer, &children)?;
+                    Ok((parent, position.map(|position| position / arity)))
+                },
+            )
+            .map(|(root, _)| root)
+    }
+
+    /// Constrains the root of the tree computed from the given leaf to equal `root`.
+    pub fn verify(
+        &self,
+        mut layouter: impl Layouter<MerkleChip>,
+        leaf: MerkleChip::Node,
+        root: &MerkleChip::Node,
+    ) -> Result<(), Error> {
+        let calculated = self.calculate_root(layouter.namespace(|| "calculate root"), leaf)?;
+        MerkleChip::constrain_equal(&mut layouter, &calculated, root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryInto;
+
+    use super::{MerklePath, MerklePathInstructions};
+    use crate::{
+        arithmetic::FieldExt,
+        circuit::{layouter, Layouter},
+        dev::MockProver,
+        gadget::poseidon::{Pow5T3Chip, Pow5T3Config},
+        pasta::Fp,
+        plonk::{Assignment, Circuit, ConstraintSystem, Error},
+        primitives::poseidon::{merkle::IncrementalTree, P128Pow5T3},
+    };
+
+    const DEPTH: usize = 4;
+
+    struct MerkleCircuit {
+        leaf: Option<Fp>,
+        position: Option<u64>,
+        path: Option<Vec<Fp>>,
+        root: Option<Fp>,
+    }
+
+    impl Circuit<Fp> for MerkleCircuit {
+        type Config = Pow5T3Config<Fp>;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Pow5T3Config<Fp> {
+            let state = (0..3)
+                .map(|_| meta.advice_column())
+                .collect::<Vec<_>>()
+                .try_into()
+                .unwrap();
+            let partial_sbox = meta.advice_column();
+
+            let mut config = Pow5T3Chip::configure::<P128Pow5T3>(meta, state, partial_sbox);
+            Pow5T3Chip::configure_merkle_path(meta, &mut config);
+            config
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            config: Pow5T3Config<Fp>,
+        ) -> Result<(), Error> {
+            let mut layouter = layouter::SingleChip::<Pow5T3Chip<Fp>, _>::new(cs, config)?;
+
+            let leaf = Pow5T3Chip::witness_node(&mut layouter, self.leaf)?;
+            // For the purpose of this test, witness the root.
+            let root = Pow5T3Chip::witness_node(&mut layouter, self.root)?;
+
+            let path = MerklePath::<Pow5T3Chip<Fp>>::new(
+                2,
+                self.position,
+                (0..DEPTH)
+                    .map(|i| vec![self.path.as_ref().map(|path| path[i])])
+                    .collect(),
+            );
+            path.verify(layouter.namespace(|| "merkle path"), leaf, &root)
+        }
+    }
+
+    fn tree() -> IncrementalTree<Fp, P128Pow5T3, 3> {
+        let mut tree = IncrementalTree::new(DEPTH);
+        for i in 0..11 {
+            tree.append(Fp::from_u64(i * 7 + 1));
+        }
+        tree
+    }
+
+    #[test]
+    fn merkle_path() {
+        let tree = tree();
+        for &position in &[0, 5, 10] {
+            let circuit = MerkleCircuit {
+                leaf: Some(Fp::from_u64(position * 7 + 1)),
+                position: Some(position),
+                path: tree.path(position),
+                root: Some(tree.root()),
+            };
+            let prover = MockProver::run(9, &circuit, vec![]).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn merkle_path_wrong_position() {
+        let tree = tree();
+        let circuit = MerkleCircuit {
+            leaf: Some(Fp::from_u64(5 * 7 + 1)),
+            position: Some(4),
+            path: tree.path(5),
+            root: Some(tree.root()),
+        };
+        let prover = MockProver::run(9, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
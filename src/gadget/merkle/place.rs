@@ -0,0 +1,178 @@
+//! A gate placing a node among its siblings.
+//!
+//! # Layout
+//!
+//! Placing a node $x$ among the siblings $s_0, \ldots, s_{n - 2}$ of a node of arity $n$
+//! uses three advice columns in a permutation and $2n$ rows. Row $j < n$ holds the bit
+//! $b_j$, which is set only at the index of the node, the prefix sum
+//! $t_j = \sum_{k \leq j} b_k$, and the child
+//! $$c_j = b_j \cdot x + (t_j - b_j) \cdot s_{j - 1} + (1 - t_j) \cdot s_j,$$
+//! and row $n + j$ holds a copy of $x$ and the sibling $s_j$. Constraining the bits to
+//! be boolean and $t_{n - 1} = 1$ ensures exactly one bit is set, so the children are
+//! the siblings in order with $x$ inserted at the index.
+
+use std::cmp::Ordering;
+
+use crate::{
+    arithmetic::FieldExt,
+    circuit::{Cell, Chip, Layouter},
+    plonk::{Advice, Column, ConstraintSystem, Error, Expression, Permutation, Selector},
+    poly::Rotation,
+};
+
+/// Configuration for the gate implementing [`MerklePathInstructions::place`] for
+/// nodes that are single field elements.
+///
+/// [`MerklePathInstructions::place`]: super::MerklePathInstructions::place
+#[derive(Clone, Debug)]
+pub struct PlaceConfig {
+    columns: [Column<Advice>; 3],
+    arity: usize,
+    q_place: Selector,
+    q_first: Selector,
+    q_next: Selector,
+    q_last: Selector,
+    perm: Permutation,
+}
+
+impl PlaceConfig {
+    /// Configures the gate for nodes of the given arity over the given columns, which
+    /// must be in `perm`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `arity` is less than 2.
+    pub fn configure<F: FieldExt>(
+        meta: &mut ConstraintSystem<F>,
+        columns: [Column<Advice>; 3],
+        perm: Permutation,
+        arity: usize,
+    ) -> Self {
+        assert!(arity >= 2);
+
+        let config = PlaceConfig {
+            columns,
+            arity,
+            q_place: meta.selector(),
+            q_first: meta.selector(),
+            q_next: meta.selector(),
+            q_last: meta.selector(),
+            perm,
+        };
+        let n = arity as i32;
+        let one = || Expression::Constant(F::one());
+
+        meta.create_gate("merkle place", |meta| {
+            let bit = meta.query_advice(columns[0], Rotation::cur());
+            let sum = meta.query_advice(columns[1], Rotation::cur());
+            let child = meta.query_advice(columns[2], Rotation::cur());
+            let node = meta.query_advice(columns[0], Rotation(n));
+            let sibling = meta.query_advice(columns[1], Rotation(n));
+            let prev_sibling = meta.query_advice(columns[1], Rotation(n - 1));
+            let q_place = meta.query_selector(config.q_place, Rotation::cur());
+
+            q_place
+                * (bit.clone() * node
+                    + (sum.clone() - bit) * prev_sibling
+                    + (one() - sum) * sibling
+                    - child)
+        });
+        meta.create_gate("merkle place bit", |meta| {
+            let bit = meta.query_advice(columns[0], Rotation::cur());
+            let q_place = meta.query_selector(config.q_place, Rotation::cur());
+
+            q_place * bit.clone() * (one() - bit)
+        });
+        meta.create_gate("merkle place first sum", |meta| {
+            let bit = meta.query_advice(columns[0], Rotation::cur());
+            let sum = meta.query_advice(columns[1], Rotation::cur());
+            let q_first = meta.query_selector(config.q_first, Rotation::cur());
+
+            q_first * (sum - bit)
+        });
+        meta.create_gate("merkle place sum", |meta| {
+            let bit = meta.query_advice(columns[0], Rotation::cur());
+            let sum = meta.query_advice(columns[1], Rotation::cur());
+            let prev_sum = meta.query_advice(columns[1], Rotation::prev());
+            let q_next = meta.query_selector(config.q_next, Rotation::cur());
+
+            q_next * (sum - prev_sum - bit)
+        });
+        meta.create_gate("merkle place last sum", |meta| {
+            let sum = meta.query_advice(columns[1], Rotation::cur());
+            let q_last = meta.query_selector(config.q_last, Rotation::cur());
+
+            q_last * (one() - sum)
+        });
+
+        config
+    }
+
+    /// The arity this gate was configured with.
+    pub fn arity(&self) -> usize {
+        self.arity
+    }
+
+    /// Witnesses `siblings` and `index`, and returns the cells and values of the
+    /// children with the node in `cell` at `index`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `siblings` does not contain `arity - 1` elements, or if `index` is not
+    /// less than the arity.
+    pub fn place<F: FieldExt, C: Chip<Field = F>>(
+        &self,
+        layouter: &mut impl Layouter<C>,
+        cell: Cell,
+        value: Option<F>,
+        siblings: &[Option<F>],
+        index: Option<usize>,
+    ) -> Result<Vec<(Cell, Option<F>)>, Error> {
+        let n = self.arity;
+        assert_eq!(siblings.len(), n - 1);
+        assert!(index.is_none_or(|index| index < n));
+
+        let witness = |value: Option<F>| move || value.ok_or(Error::SynthesisError);
+        layouter.assign_region(
+            || "merkle place",
+            |mut region| {
+                self.q_first.enable(&mut region, 0)?;
+                self.q_last.enable(&mut region, n - 1)?;
+
+                let mut children = Vec::with_capacity(n);
+                for j in 0..n {
+                    self.q_place.enable(&mut region, j)?;
+                    if j > 0 {
+                        self.q_next.enable(&mut region, j)?;
+                    }
+
+                    let bit = index.map(|index| index == j);
+                    let sum = index.map(|index| index <= j);
+                    let from_bool =
+                        |b: Option<bool>| b.map(|b| if b { F::one() } else { F::zero() });
+                    region.assign_advice(|| "bit", self.columns[0], j, witness(from_bool(bit)))?;
+                    region.assign_advice(|| "sum", self.columns[1], j, witness(from_bool(sum)))?;
+
+                    let child = index.and_then(|index| match j.cmp(&index) {
+                        Ordering::Less => siblings[j],
+                        Ordering::Equal => value,
+                        Ordering::Greater => siblings[j - 1],
+                    });
+                    let child_cell =
+                        region.assign_advice(|| "child", self.columns[2], j, witness(child))?;
+                    children.push((child_cell, child));
+
+                    let copy =
+                        region.assign_advice(|| "node", self.columns[0], n + j, witness(value))?;
+                    region.constrain_equal(&self.perm, cell, copy)?;
+                    // The last sibling cell is not used by the gate, but is assigned so
+                    // that every queried cell has a value.
+                    let sibling = siblings.get(j).copied().unwrap_or(Some(F::zero()));
+                    region.assign_advice(|| "sibling", self.columns[1], n + j, witness(sibling))?;
+                }
+
+                Ok(children)
+            },
+        )
+    }
+}
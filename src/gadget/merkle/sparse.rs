@@ -0,0 +1,362 @@
+//! Sparse Merkle trees, with membership, non-membership and update proofs.
+//!
+//! A sparse tree of depth $d$ has a leaf for every $d$-bit key, and leaves that have
+//! not been set are zero. Unlike a [`MerklePath`](super::MerklePath), whose position is
+//! only used to order the nodes, the path of a key is given by its bits, which are
+//! constrained to recompose to the key. Proving that the leaf at a key is zero is then
+//! a proof of non-membership, and proving the old and new roots of a tree with the same
+//! key and siblings is a proof that only the leaf at the key was changed.
+//!
+//! The host-side counterpart is
+//! [`crate::primitives::poseidon::merkle::SparseTree`].
+
+use std::fmt;
+use std::marker::PhantomData;
+
+use super::MerklePathInstructions;
+use crate::{circuit::Layouter, plonk::Error};
+
+mod config;
+pub use config::SparseConfig;
+
+/// The set of circuit instructions required to use the [`SparseMerklePath`] gadget,
+/// on top of those for binary Merkle paths.
+pub trait SparseMerkleInstructions: MerklePathInstructions {
+    /// Variable representing a bit of a key.
+    type Bit: Clone + fmt::Debug;
+
+    /// Witnesses a key of `depth` bits, and returns it with its little-endian bits.
+    ///
+    /// The key is constrained to be less than $2^\mathsf{depth}$.
+    fn witness_key(
+        layouter: &mut impl Layouter<Self>,
+        key: Option<u64>,
+        depth: usize,
+    ) -> Result<(Self::Node, Vec<Self::Bit>), Error>;
+
+    /// Returns an empty leaf, constrained to be zero.
+    fn empty_leaf(layouter: &mut impl Layouter<Self>) -> Result<Self::Node, Error>;
+
+    /// Returns `[node, sibling]` if `bit` is zero, or `[sibling, node]` if it is one.
+    fn swap(
+        layouter: &mut impl Layouter<Self>,
+        node: &Self::Node,
+        sibling: &Self::Node,
+        bit: &Self::Bit,
+    ) -> Result<[Self::Node; 2], Error>;
+}
+
+/// An authentication path from the leaf at a key to the root of a sparse Merkle tree.
+#[derive(Debug)]
+pub struct SparseMerklePath<MerkleChip: SparseMerkleInstructions> {
+    key: Option<u64>,
+    path: Vec<Option<MerkleChip::NodeValue>>,
+    _marker: PhantomData<MerkleChip>,
+}
+
+impl<MerkleChip: SparseMerkleInstructions> SparseMerklePath<MerkleChip> {
+    /// Constructs a path for the leaf at `key`, with the siblings of the nodes from the
+    /// leaf up to (but excluding) the root. The depth of the tree is given by the
+    /// length of `path`.
+    pub fn new(key: Option<u64>, path: Vec<Option<MerkleChip::NodeValue>>) -> Self {
+        assert!(path.len() < 64);
+        SparseMerklePath {
+            key,
+            path,
+            _marker: PhantomData,
+        }
+    }
+
+    /// The depth of the tree.
+    pub fn depth(&self) -> usize {
+        self.path.len()
+    }
+
+    /// Computes the root of the tree from the given leaf, and returns the key and the
+    /// root.
+    pub fn calculate_root(
+        &self,
+        mut layouter: impl Layouter<MerkleChip>,
+        leaf: MerkleChip::Node,
+    ) -> Result<(MerkleChip::Node, MerkleChip::Node), Error> {
+        let (key, bits, siblings) = self.witness(&mut layouter)?;
+        let root = Self::root(&mut layouter, &bits, &siblings, leaf)?;
+        Ok((key, root))
+    }
+
+    /// Constrains the leaf at the key of the tree with the given root to be `leaf`,
+    /// and returns the key.
+    pub fn verify_membership(
+        &self,
+        mut layouter: impl Layouter<MerkleChip>,
+        leaf: MerkleChip::Node,
+        root: &MerkleChip::Node,
+    ) -> Result<MerkleChip::Node, Error> {
+        let (key, calculated) =
+            self.calculate_root(layouter.namespace(|| "calculate root"), leaf)?;
+        MerkleChip::constrain_equal(&mut layouter, &calculated, root)?;
+        Ok(key)
+    }
+
+    /// Constrains the leaf at the key of the tree with the given root to be empty, and
+    /// returns the key.
+    pub fn verify_non_membership(
+        &self,
+        mut layouter: impl Layouter<MerkleChip>,
+        root: &MerkleChip::Node,
+    ) -> Result<MerkleChip::Node, Error> {
+        let leaf = MerkleChip::empty_leaf(&mut layouter)?;
+        self.verify_membership(layouter, leaf, root)
+    }
+
+    /// Constrains the leaf at the key of the tree with root `old_root` to be
+    /// `old_leaf`, and returns the key and the root of the tree with that leaf set to
+    /// `new_leaf`.
+    pub fn update(
+        &self,
+        mut layouter: impl Layouter<MerkleChip>,
+        old_leaf: MerkleChip::Node,
+        new_leaf: MerkleChip::Node,
+        old_root: &MerkleChip::Node,
+    ) -> Result<(MerkleChip::Node, MerkleChip::Node), Error> {
+        let (key, bits, siblings) = self.witness(&mut layouter)?;
+        let calculated = Self::root(
+            &mut layouter.namespace(|| "old root"),
+            &bits,
+            &siblings,
+            old_leaf,
+        )?;
+        MerkleChip::constrain_equal(&mut layouter, &calculated, old_root)?;
+        let new_root = Self::root(
+            &mut layouter.namespace(|| "new root"),
+            &bits,
+            &siblings,
+            new_leaf,
+        )?;
+        Ok((key, new_root))
+    }
+
+    /// Constrains the leaf at the key of the tree with root `old_root` to be empty,
+    /// and returns the key and the root of the tree with `leaf` inserted at the key.
+    pub fn insert(
+        &self,
+        mut layouter: impl Layouter<MerkleChip>,
+        leaf: MerkleChip::Node,
+        old_root: &MerkleChip::Node,
+    ) -> Result<(MerkleChip::Node, MerkleChip::Node), Error> {
+        let empty = MerkleChip::empty_leaf(&mut layouter)?;
+        self.update(layouter, empty, leaf, old_root)
+    }
+
+    /// Witnesses the key, its bits and the siblings.
+    #[allow(clippy::type_complexity)]
+    fn witness(
+        &self,
+        layouter: &mut impl Layouter<MerkleChip>,
+    ) -> Result<
+        (
+            MerkleChip::Node,
+            Vec<MerkleChip::Bit>,
+            Vec<MerkleChip::Node>,
+        ),
+        Error,
+    > {
+        let (key, bits) = MerkleChip::witness_key(layouter, self.key, self.depth())?;
+        let siblings = self
+            .path
+            .iter()
+            .map(|sibling| MerkleChip::witness_node(layouter, sibling.clone()))
+            .collect::<Result<_, _>>()?;
+        Ok((key, bits, siblings))
+    }
+
+    fn root(
+        layouter: &mut impl Layouter<MerkleChip>,
+        bits: &[MerkleChip::Bit],
+        siblings: &[MerkleChip::Node],
+        leaf: MerkleChip::Node,
+    ) -> Result<MerkleChip::Node, Error> {
+        bits.iter().zip(siblings.iter()).enumerate().try_fold(
+            leaf,
+            |node, (layer, (bit, sibling))| {
+                let mut layouter = layouter.namespace(|| format!("layer {}", layer));
+                let children = MerkleChip::swap(&mut layouter, &node, sibling, bit)?;
+                MerkleChip::hash_children(&mut layouter, layer, &children)
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryInto;
+
+    use super::SparseMerklePath;
+    use crate::{
+        arithmetic::FieldExt,
+        circuit::{layouter, Layouter},
+        dev::MockProver,
+        gadget::{
+            merkle::MerklePathInstructions,
+            poseidon::{Pow5T3Chip, Pow5T3Config},
+        },
+        pasta::Fp,
+        plonk::{Assignment, Circuit, ConstraintSystem, Error},
+        primitives::poseidon::{merkle::SparseTree, P128Pow5T3},
+    };
+
+    const DEPTH: usize = 4;
+
+    #[derive(Clone, Copy)]
+    enum Proof {
+        Membership(Fp),
+        NonMembership,
+        Update(Fp, Fp),
+        Insert(Fp),
+    }
+
+    struct SparseCircuit {
+        proof: Proof,
+        key: u64,
+        path: Vec<Fp>,
+        root: Fp,
+        new_root: Option<Fp>,
+    }
+
+    impl Circuit<Fp> for SparseCircuit {
+        type Config = Pow5T3Config<Fp>;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Pow5T3Config<Fp> {
+            let state = (0..3)
+                .map(|_| meta.advice_column())
+                .collect::<Vec<_>>()
+                .try_into()
+                .unwrap();
+            let partial_sbox = meta.advice_column();
+
+            let mut config = Pow5T3Chip::configure::<P128Pow5T3>(meta, state, partial_sbox);
+            Pow5T3Chip::configure_sparse_merkle(meta, &mut config);
+            config
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            config: Pow5T3Config<Fp>,
+        ) -> Result<(), Error> {
+            let mut layouter = layouter::SingleChip::<Pow5T3Chip<Fp>, _>::new(cs, config)?;
+            let mut witness = |value| Pow5T3Chip::witness_node(&mut layouter, Some(value));
+
+            // For the purpose of this test, witness the roots.
+            let root = witness(self.root)?;
+            let new_root = self.new_root.map(&mut witness).transpose()?;
+            let (old_leaf, new_leaf) = match self.proof {
+                Proof::Membership(leaf) => (Some(witness(leaf)?), None),
+                Proof::NonMembership => (None, None),
+                Proof::Update(old, new) => (Some(witness(old)?), Some(witness(new)?)),
+                Proof::Insert(leaf) => (None, Some(witness(leaf)?)),
+            };
+
+            let path = SparseMerklePath::<Pow5T3Chip<Fp>>::new(
+                Some(self.key),
+                self.path.iter().copied().map(Some).collect(),
+            );
+            let mut layouter = layouter.namespace(|| "sparse merkle");
+            let calculated = match (old_leaf, new_leaf) {
+                (Some(leaf), None) => {
+                    path.verify_membership(layouter.namespace(|| "membership"), leaf, &root)?;
+                    None
+                }
+                (None, None) => {
+                    path.verify_non_membership(layouter.namespace(|| "non-membership"), &root)?;
+                    None
+                }
+                (Some(old), Some(new)) => Some(
+                    path.update(layouter.namespace(|| "update"), old, new, &root)?
+                        .1,
+                ),
+                (None, Some(leaf)) => {
+                    Some(path.insert(layouter.namespace(|| "insert"), leaf, &root)?.1)
+                }
+            };
+            if let (Some(calculated), Some(new_root)) = (calculated, new_root) {
+                Pow5T3Chip::constrain_equal(&mut layouter, &calculated, &new_root)?;
+            }
+            Ok(())
+        }
+    }
+
+    fn tree() -> SparseTree<Fp, P128Pow5T3, 3> {
+        let mut tree = SparseTree::new(DEPTH);
+        for &key in &[1, 6, 13] {
+            tree.set(key, Fp::from_u64(key * 7 + 1));
+        }
+        tree
+    }
+
+    fn verify(proof: Proof, key: u64, path_key: u64, new_root: Option<Fp>) -> bool {
+        let tree = tree();
+        let circuit = SparseCircuit {
+            proof,
+            key,
+            path: tree.path(path_key),
+            root: tree.root(),
+            new_root,
+        };
+        MockProver::run(10, &circuit, vec![])
+            .unwrap()
+            .verify()
+            .is_ok()
+    }
+
+    #[test]
+    fn membership() {
+        let leaf = Fp::from_u64(6 * 7 + 1);
+        assert!(verify(Proof::Membership(leaf), 6, 6, None));
+        assert!(!verify(Proof::Membership(leaf), 7, 6, None));
+        assert!(!verify(Proof::Membership(leaf + Fp::one()), 6, 6, None));
+    }
+
+    #[test]
+    fn non_membership() {
+        assert!(verify(Proof::NonMembership, 7, 7, None));
+        assert!(verify(Proof::NonMembership, 0, 0, None));
+        assert!(!verify(Proof::NonMembership, 6, 6, None));
+        // The proof is bound to the key, so an empty leaf elsewhere does not help.
+        assert!(!verify(Proof::NonMembership, 6, 7, None));
+    }
+
+    #[test]
+    fn update_and_insert() {
+        let new = Fp::from_u64(1000);
+
+        let mut updated = tree();
+        updated.set(13, new);
+        let old = Fp::from_u64(13 * 7 + 1);
+        assert!(verify(
+            Proof::Update(old, new),
+            13,
+            13,
+            Some(updated.root())
+        ));
+        assert!(!verify(
+            Proof::Update(old + Fp::one(), new),
+            13,
+            13,
+            Some(updated.root())
+        ));
+        assert!(!verify(
+            Proof::Update(old, new + Fp::one()),
+            13,
+            13,
+            Some(updated.root())
+        ));
+
+        let mut inserted = tree();
+        inserted.set(9, new);
+        assert!(verify(Proof::Insert(new), 9, 9, Some(inserted.root())));
+        assert!(!verify(Proof::Insert(new), 13, 13, Some(updated.root())));
+    }
+}
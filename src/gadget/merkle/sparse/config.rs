@@ -0,0 +1,208 @@
+//! Gates for sparse Merkle paths over nodes that are single field elements.
+//!
+//! # Layout
+//!
+//! The gates use three advice columns in a permutation.
+//!
+//! - A key $k$ of $d$ bits uses $d + 1$ rows: row $i$ holds the running sum $z_i$ and,
+//!   for $i < d$, the bit $b_i$, where $z_0 = k$, $z_i = 2 z_{i + 1} + b_i$ and
+//!   $z_d = 0$.
+//! - A swap uses two rows: the first holds copies of the node, the sibling and the bit,
+//!   and the second the two children.
+//! - An empty leaf uses one row, constrained to be zero.
+
+use crate::{
+    arithmetic::FieldExt,
+    circuit::{Chip, Layouter},
+    gadget::utilities::AssignedValue,
+    plonk::{Advice, Column, ConstraintSystem, Error, Expression, Permutation, Selector},
+    poly::Rotation,
+};
+
+/// Configuration for the gates implementing
+/// [`SparseMerkleInstructions`](super::SparseMerkleInstructions) for nodes that are
+/// single field elements.
+#[derive(Clone, Debug)]
+pub struct SparseConfig {
+    columns: [Column<Advice>; 3],
+    q_decompose: Selector,
+    q_zero: Selector,
+    q_swap: Selector,
+    perm: Permutation,
+}
+
+impl SparseConfig {
+    /// Configures the gates over the given columns, which must be in `perm`.
+    pub fn configure<F: FieldExt>(
+        meta: &mut ConstraintSystem<F>,
+        columns: [Column<Advice>; 3],
+        perm: Permutation,
+    ) -> Self {
+        let config = SparseConfig {
+            columns,
+            q_decompose: meta.selector(),
+            q_zero: meta.selector(),
+            q_swap: meta.selector(),
+            perm,
+        };
+        let one = || Expression::Constant(F::one());
+
+        meta.create_gate("sparse merkle key bit", |meta| {
+            let bit = meta.query_advice(columns[1], Rotation::cur());
+            let q_decompose = meta.query_selector(config.q_decompose, Rotation::cur());
+
+            q_decompose * bit.clone() * (one() - bit)
+        });
+        meta.create_gate("sparse merkle key decomposition", |meta| {
+            let z_cur = meta.query_advice(columns[0], Rotation::cur());
+            let z_next = meta.query_advice(columns[0], Rotation::next());
+            let bit = meta.query_advice(columns[1], Rotation::cur());
+            let q_decompose = meta.query_selector(config.q_decompose, Rotation::cur());
+
+            q_decompose * (z_cur - z_next * F::from_u64(2) - bit)
+        });
+        meta.create_gate("sparse merkle zero", |meta| {
+            let value = meta.query_advice(columns[0], Rotation::cur());
+            let q_zero = meta.query_selector(config.q_zero, Rotation::cur());
+
+            q_zero * value
+        });
+
+        // The bit is boolean, as it is a copy of a bit of the key.
+        meta.create_gate("sparse merkle swap left", |meta| {
+            let node = meta.query_advice(columns[0], Rotation::cur());
+            let sibling = meta.query_advice(columns[1], Rotation::cur());
+            let bit = meta.query_advice(columns[2], Rotation::cur());
+            let left = meta.query_advice(columns[0], Rotation::next());
+            let q_swap = meta.query_selector(config.q_swap, Rotation::cur());
+
+            q_swap * (node.clone() + bit * (sibling - node) - left)
+        });
+        meta.create_gate("sparse merkle swap right", |meta| {
+            let node = meta.query_advice(columns[0], Rotation::cur());
+            let sibling = meta.query_advice(columns[1], Rotation::cur());
+            let bit = meta.query_advice(columns[2], Rotation::cur());
+            let right = meta.query_advice(columns[1], Rotation::next());
+            let q_swap = meta.query_selector(config.q_swap, Rotation::cur());
+
+            q_swap * (sibling.clone() + bit * (node - sibling) - right)
+        });
+
+        config
+    }
+
+    /// Witnesses a key of `depth` bits, and returns it with its little-endian bits.
+    pub fn witness_key<F: FieldExt, C: Chip<Field = F>>(
+        &self,
+        layouter: &mut impl Layouter<C>,
+        key: Option<u64>,
+        depth: usize,
+    ) -> Result<(AssignedValue<F>, Vec<AssignedValue<F>>), Error> {
+        layouter.assign_region(
+            || "sparse merkle key",
+            |mut region| {
+                let mut z = None;
+                let mut bits = Vec::with_capacity(depth);
+                for i in 0..=depth {
+                    let value = key.map(|key| F::from_u64(key.checked_shr(i as u32).unwrap_or(0)));
+                    let cell = region.assign_advice(
+                        || "z",
+                        self.columns[0],
+                        i,
+                        || value.ok_or(Error::SynthesisError),
+                    )?;
+                    if i == 0 {
+                        z = Some(AssignedValue { cell, value });
+                    }
+
+                    if i == depth {
+                        self.q_zero.enable(&mut region, i)?;
+                    } else {
+                        self.q_decompose.enable(&mut region, i)?;
+                        let value = key.map(|key| F::from_u64((key >> i) & 1));
+                        let cell = region.assign_advice(
+                            || "bit",
+                            self.columns[1],
+                            i,
+                            || value.ok_or(Error::SynthesisError),
+                        )?;
+                        bits.push(AssignedValue { cell, value });
+                    }
+                }
+
+                Ok((z.unwrap(), bits))
+            },
+        )
+    }
+
+    /// Returns a cell constrained to be zero.
+    pub fn zero<F: FieldExt, C: Chip<Field = F>>(
+        &self,
+        layouter: &mut impl Layouter<C>,
+    ) -> Result<AssignedValue<F>, Error> {
+        layouter.assign_region(
+            || "zero",
+            |mut region| {
+                self.q_zero.enable(&mut region, 0)?;
+                let value = Some(F::zero());
+                let cell = region.assign_advice(|| "zero", self.columns[0], 0, || Ok(F::zero()))?;
+                Ok(AssignedValue { cell, value })
+            },
+        )
+    }
+
+    /// Returns `[node, sibling]` if `bit` is zero, or `[sibling, node]` if it is one.
+    ///
+    /// `bit` must be a bit returned by [`SparseConfig::witness_key`].
+    pub fn swap<F: FieldExt, C: Chip<Field = F>>(
+        &self,
+        layouter: &mut impl Layouter<C>,
+        node: &AssignedValue<F>,
+        sibling: &AssignedValue<F>,
+        bit: &AssignedValue<F>,
+    ) -> Result<[AssignedValue<F>; 2], Error> {
+        layouter.assign_region(
+            || "sparse merkle swap",
+            |mut region| {
+                self.q_swap.enable(&mut region, 0)?;
+
+                for (column, input) in self.columns.iter().zip([node, sibling, bit]) {
+                    let cell = region.assign_advice(
+                        || "input",
+                        *column,
+                        0,
+                        || input.value.ok_or(Error::SynthesisError),
+                    )?;
+                    region.constrain_equal(&self.perm, cell, input.cell)?;
+                }
+
+                let swap = bit.value.map(|bit| bit == F::one());
+                let pair =
+                    node.value
+                        .zip(sibling.value)
+                        .zip(swap)
+                        .map(|((node, sibling), swap)| {
+                            if swap {
+                                [sibling, node]
+                            } else {
+                                [node, sibling]
+                            }
+                        });
+
+                let mut children = Vec::with_capacity(2);
+                for (i, column) in self.columns[..2].iter().enumerate() {
+                    let value = pair.map(|pair| pair[i]);
+                    let cell = region.assign_advice(
+                        || "child",
+                        *column,
+                        1,
+                        || value.ok_or(Error::SynthesisError),
+                    )?;
+                    children.push(AssignedValue { cell, value });
+                }
+
+                Ok([children[0], children[1]])
+            },
+        )
+    }
+}
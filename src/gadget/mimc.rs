@@ -0,0 +1,355 @@
+//! A chip for the MiMC and GMiMC permutations.
+//!
+//! [`MimcChip`] implements the same sponge instructions as the Poseidon chips. The
+//! permutation can be used directly via [`PoseidonInstructions::permute`] (Feistel
+//! mode), or for hashing via the [`Hash`](struct@crate::gadget::poseidon::Hash) gadget
+//! (sponge mode).
+
+use std::convert::TryInto;
+use std::marker::PhantomData;
+
+use crate::{
+    arithmetic::FieldExt,
+    circuit::{Chip, Layouter},
+    gadget::poseidon::{
+        sponge::{SpongeConfig, StateWord},
+        PoseidonDuplexInstructions, PoseidonInstructions, SpongeState,
+    },
+    plonk::{Advice, Column, ConstraintSystem, Error, Selector},
+    poly::Rotation,
+    primitives::{mimc::MimcSpec, poseidon::State},
+};
+
+/// Configuration for a [`MimcChip`].
+#[derive(Clone, Debug)]
+pub struct MimcConfig<F: FieldExt, const WIDTH: usize, const RATE: usize> {
+    sponge: SpongeConfig<WIDTH, RATE>,
+    s_round: Selector,
+
+    round_constants: Vec<F>,
+}
+
+/// A GMiMC-erf chip with `WIDTH` branches and a sponge rate of `RATE`. With a width
+/// of 2, this is the MiMC Feistel network.
+///
+/// Each round uses one row and a single gate of degree $\alpha + 1$, so the permutation
+/// occupies one more row than it has rounds.
+#[derive(Debug)]
+pub struct MimcChip<F: FieldExt, const WIDTH: usize, const RATE: usize> {
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt, const WIDTH: usize, const RATE: usize> MimcChip<F, WIDTH, RATE> {
+    /// Configures this chip for use in a circuit.
+    ///
+    /// The chip uses the given `state` columns for its inputs and outputs, and places
+    /// all of them in a permutation.
+    pub fn configure<S: MimcSpec<F, WIDTH, RATE>>(
+        meta: &mut ConstraintSystem<F>,
+        state: [Column<Advice>; WIDTH],
+    ) -> MimcConfig<F, WIDTH, RATE> {
+        assert!(WIDTH >= 2);
+        let round_constants = S::constants();
+
+        let sponge = SpongeConfig::configure(meta, state);
+        let rc = sponge.rc_a[0];
+
+        let s_round = meta.selector();
+
+        let round_function = |meta: &mut ConstraintSystem<F>| {
+            let v = meta.query_advice(state[0], Rotation::cur())
+                + meta.query_fixed(rc, Rotation::cur());
+            (1..F::RESCUE_ALPHA).fold(v.clone(), |acc, _| acc * v.clone())
+        };
+
+        // Each branch but the first absorbs the round function, and the branches are
+        // rotated by one position.
+        for idx in 0..(WIDTH - 1) {
+            meta.create_gate("mimc round", |meta| {
+                let f = round_function(meta);
+                let cur = meta.query_advice(state[idx + 1], Rotation::cur());
+                let next = meta.query_advice(state[idx], Rotation::next());
+                let s_round = meta.query_selector(s_round, Rotation::cur());
+
+                s_round * (cur + f - next)
+            });
+        }
+        meta.create_gate("mimc round", |meta| {
+            let cur = meta.query_advice(state[0], Rotation::cur());
+            let next = meta.query_advice(state[WIDTH - 1], Rotation::next());
+            let s_round = meta.query_selector(s_round, Rotation::cur());
+
+            s_round * (cur - next)
+        });
+
+        MimcConfig {
+            sponge,
+            s_round,
+            round_constants,
+        }
+    }
+}
+
+impl<F: FieldExt, const WIDTH: usize, const RATE: usize> Chip for MimcChip<F, WIDTH, RATE> {
+    type Config = MimcConfig<F, WIDTH, RATE>;
+    type Loaded = ();
+    type Field = F;
+
+    fn load(_layouter: &mut impl Layouter<Self>) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<F: FieldExt, const WIDTH: usize, const RATE: usize> PoseidonInstructions<WIDTH>
+    for MimcChip<F, WIDTH, RATE>
+{
+    type Word = StateWord<F>;
+
+    fn permute(
+        layouter: &mut impl Layouter<Self>,
+        initial_state: &State<Self::Word, WIDTH>,
+    ) -> Result<State<Self::Word, WIDTH>, Error> {
+        let config = layouter.config().clone();
+
+        layouter.assign_region(
+            || "permute state",
+            |mut region| {
+                // Load the initial state into this region.
+                let state = config.sponge.load_state(&mut region, 0, initial_state)?;
+
+                config
+                    .round_constants
+                    .iter()
+                    .enumerate()
+                    .try_fold(state, |state, (round, rc)| {
+                        config.s_round.enable(&mut region, round)?;
+                        region.assign_fixed(
+                            || format!("round_{} rc", round),
+                            config.sponge.rc_a[0],
+                            round,
+                            || Ok(*rc),
+                        )?;
+
+                        let next = state
+                            .iter()
+                            .map(|word| word.value)
+                            .collect::<Option<Vec<_>>>()
+                            .map(|mut values| {
+                                let f = (values[0] + rc).pow_vartime([F::RESCUE_ALPHA, 0, 0, 0]);
+                                for value in values.iter_mut().skip(1) {
+                                    *value += f;
+                                }
+                                values.rotate_left(1);
+                                values
+                            });
+
+                        let mut next_state_word = |i: usize| {
+                            let value = next.as_ref().map(|next| next[i]);
+                            let var = region.assign_advice(
+                                || format!("round_{} state_{}", round + 1, i),
+                                config.sponge.state[i],
+                                round + 1,
+                                || value.ok_or(Error::SynthesisError),
+                            )?;
+                            Ok(StateWord { var, value })
+                        };
+
+                        let next_state: Result<Vec<_>, Error> =
+                            (0..WIDTH).map(&mut next_state_word).collect();
+                        Ok(next_state?.try_into().unwrap())
+                    })
+            },
+        )
+    }
+}
+
+impl<F: FieldExt, const WIDTH: usize, const RATE: usize> PoseidonDuplexInstructions<WIDTH, RATE>
+    for MimcChip<F, WIDTH, RATE>
+{
+    fn load_message(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<F>,
+    ) -> Result<Self::Word, Error> {
+        let config = layouter.config().sponge.clone();
+        config.load_message(layouter, value)
+    }
+
+    fn initial_state(
+        layouter: &mut impl Layouter<Self>,
+        initial_capacity_element: F,
+    ) -> Result<State<Self::Word, WIDTH>, Error> {
+        let config = layouter.config().sponge.clone();
+        config.initial_state(layouter, initial_capacity_element)
+    }
+
+    fn pad_and_add(
+        layouter: &mut impl Layouter<Self>,
+        initial_state: &State<Self::Word, WIDTH>,
+        input: &SpongeState<Self::Word, F, RATE>,
+    ) -> Result<State<Self::Word, WIDTH>, Error> {
+        let config = layouter.config().sponge.clone();
+        config.pad_and_add(layouter, initial_state, input)
+    }
+
+    fn get_output(state: &State<Self::Word, WIDTH>) -> [Self::Word; RATE] {
+        state[..RATE].try_into().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ff::Field;
+    use std::convert::TryInto;
+
+    use super::{MimcChip, MimcConfig};
+    use crate::{
+        circuit::{layouter, Layouter},
+        dev::MockProver,
+        gadget::poseidon::{
+            sponge::SpongeConfig,
+            tests::{HashChip, HashCircuit},
+            PoseidonDuplexInstructions, PoseidonInstructions,
+        },
+        pasta::Fp,
+        plonk::{Assignment, Circuit, ConstraintSystem, Error},
+        primitives::{
+            mimc::{self, tests::GmimcT3, MimcFeistel, MimcSpec},
+            poseidon::VariableLength,
+        },
+    };
+
+    fn configure<S: MimcSpec<Fp, WIDTH, RATE>, const WIDTH: usize, const RATE: usize>(
+        meta: &mut ConstraintSystem<Fp>,
+    ) -> MimcConfig<Fp, WIDTH, RATE> {
+        let state = (0..WIDTH)
+            .map(|_| meta.advice_column())
+            .collect::<Vec<_>>()
+            .try_into()
+            .unwrap();
+
+        MimcChip::configure::<S>(meta, state)
+    }
+
+    /// Witnesses `value` and constrains it to equal `word`.
+    fn constrain_output<const WIDTH: usize, const RATE: usize>(
+        layouter: &mut impl Layouter<MimcChip<Fp, WIDTH, RATE>>,
+        word: <MimcChip<Fp, WIDTH, RATE> as PoseidonInstructions<WIDTH>>::Word,
+        value: Option<Fp>,
+    ) -> Result<(), Error> {
+        let config = layouter.config().clone();
+        layouter.assign_region(
+            || "constrain output",
+            |mut region| {
+                let expected_var = region.assign_advice(
+                    || "load output",
+                    config.sponge.state[0],
+                    0,
+                    || value.ok_or(Error::SynthesisError),
+                )?;
+                region.constrain_equal(&config.sponge.perm, word.var, expected_var)
+            },
+        )
+    }
+
+    struct PermuteCircuit {
+        input: Option<[Fp; 2]>,
+        output: Option<[Fp; 2]>,
+    }
+
+    impl Circuit<Fp> for PermuteCircuit {
+        type Config = MimcConfig<Fp, 2, 1>;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> MimcConfig<Fp, 2, 1> {
+            configure::<MimcFeistel, 2, 1>(meta)
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            config: MimcConfig<Fp, 2, 1>,
+        ) -> Result<(), Error> {
+            let mut layouter = layouter::SingleChip::<MimcChip<Fp, 2, 1>, _>::new(cs, config)?;
+
+            let state = [
+                MimcChip::load_message(&mut layouter, self.input.map(|input| input[0]))?,
+                MimcChip::load_message(&mut layouter, self.input.map(|input| input[1]))?,
+            ];
+            let output = MimcChip::permute(&mut layouter, &state)?;
+
+            for i in 0..2 {
+                constrain_output(
+                    &mut layouter,
+                    output[i],
+                    self.output.map(|output| output[i]),
+                )?;
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn mimc_feistel_permute() {
+        let input = [Fp::random(rand::rngs::OsRng), Fp::random(rand::rngs::OsRng)];
+        let mut output = input;
+        mimc::permute::<_, MimcFeistel, 2, 1>(
+            &mut output,
+            &<MimcFeistel as MimcSpec<Fp, 2, 1>>::constants(),
+        );
+
+        let circuit = PermuteCircuit {
+            input: Some(input),
+            output: Some(output),
+        };
+        let prover = MockProver::run(8, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        let circuit = PermuteCircuit {
+            input: Some(input),
+            output: Some([output[1], output[0]]),
+        };
+        let prover = MockProver::run(8, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    impl<S: MimcSpec<Fp, WIDTH, RATE>, const WIDTH: usize, const RATE: usize>
+        HashChip<S, WIDTH, RATE> for MimcChip<Fp, WIDTH, RATE>
+    {
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> MimcConfig<Fp, WIDTH, RATE> {
+            configure::<S, WIDTH, RATE>(meta)
+        }
+
+        fn sponge(config: &MimcConfig<Fp, WIDTH, RATE>) -> &SpongeConfig<WIDTH, RATE> {
+            &config.sponge
+        }
+    }
+
+    fn check_hash<S: MimcSpec<Fp, WIDTH, RATE>, const WIDTH: usize, const RATE: usize>(
+        len: usize,
+        k: u32,
+    ) {
+        let message: Vec<_> = (0..len).map(|_| Fp::random(rand::rngs::OsRng)).collect();
+        let output = mimc::hash::<_, S, _, WIDTH, RATE>(VariableLength, &message);
+
+        let circuit = HashCircuit::<MimcChip<Fp, WIDTH, RATE>, S, _, WIDTH, RATE>::new(
+            VariableLength,
+            message,
+        );
+        let prover = MockProver::run(k, &circuit, vec![vec![output]]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        let prover = MockProver::run(k, &circuit, vec![vec![output + Fp::one()]]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn mimc_feistel_hash() {
+        check_hash::<MimcFeistel, 2, 1>(2, 10);
+    }
+
+    #[test]
+    fn gmimc_hash() {
+        check_hash::<GmimcT3, 3, 2>(3, 10);
+    }
+}
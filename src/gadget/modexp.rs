@@ -0,0 +1,287 @@
+//! Gadgets for modular exponentiation over foreign fields.
+//!
+//! $b^e$ is computed by windowed square-and-multiply from the most significant bit of
+//! $e$: with a window of $w$ bits, the accumulator is squared $w$ times for each window
+//! and multiplied by $b^v$ for the value $v$ of the window, which is taken from a table
+//! of $b^0, \ldots, b^{2^w - 1}$. Larger windows trade the cost of the table, and of
+//! selecting from it, for fewer multiplications. [`pow`] selects each multiplier by the
+//! witnessed bits of the exponent, while [`pow_fixed`] is given an exponent when the
+//! circuit is synthesized, and skips the multiplications of zero windows.
+//!
+//! The results are only congruent to $b^e$, and can be reduced with
+//! [`Element::reduce`]. Such exponentiations show up in RSA and Diffie–Hellman style
+//! statements, and in the verification of exponentiation-based VDFs.
+
+use std::fmt;
+
+use super::foreign_field::{Element, ForeignFieldInstructions};
+use crate::{circuit::Layouter, plonk::Error};
+
+/// The set of circuit instructions required to use the [`pow`] gadget.
+pub trait ModExpInstructions: ForeignFieldInstructions {
+    /// Variable representing a bit.
+    type Bit: Clone + fmt::Debug;
+
+    /// Decomposes the integer representing `e` into $\mathsf{num\_bits}$ bits, from the
+    /// most significant.
+    ///
+    /// The circuit is not satisfied if the integer does not fit in `num_bits` bits.
+    fn decompose(
+        layouter: &mut impl Layouter<Self>,
+        e: &Self::Element,
+        num_bits: usize,
+    ) -> Result<Vec<Self::Bit>, Error>;
+
+    /// Returns the element of `table` whose index has the little-endian bits `bits`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `table` does not have $2^{\mathsf{bits.len()}}$ elements.
+    fn select(
+        layouter: &mut impl Layouter<Self>,
+        bits: &[Self::Bit],
+        table: &[Self::Element],
+    ) -> Result<Self::Element, Error>;
+}
+
+/// Returns the table $b^0, \ldots, b^{n - 1}$.
+fn powers<FieldChip: ForeignFieldInstructions>(
+    mut layouter: impl Layouter<FieldChip>,
+    base: &Element<FieldChip>,
+    n: usize,
+) -> Result<Vec<Element<FieldChip>>, Error> {
+    let mut table = vec![Element::constant(
+        layouter.namespace(|| "one"),
+        [1, 0, 0, 0],
+    )?];
+    for i in 1..n {
+        let power = if i == 1 {
+            base.clone()
+        } else {
+            table[i - 1].mul(layouter.namespace(|| format!("b^{}", i)), base)?
+        };
+        table.push(power);
+    }
+    Ok(table)
+}
+
+/// Returns the lengths of the windows of `num_bits` bits, from the most significant.
+///
+/// Every window has `window_bits` bits, except that the first is shorter if
+/// `window_bits` does not divide `num_bits`.
+fn window_lengths(num_bits: usize, window_bits: usize) -> impl Iterator<Item = usize> {
+    let first = match num_bits % window_bits {
+        0 => window_bits,
+        short => short,
+    };
+    let count = num_bits.div_ceil(window_bits);
+    (0..count).map(move |i| if i == 0 { first } else { window_bits })
+}
+
+/// Squares `acc` `n` times.
+fn square<FieldChip: ForeignFieldInstructions>(
+    mut layouter: impl Layouter<FieldChip>,
+    acc: Element<FieldChip>,
+    n: usize,
+) -> Result<Element<FieldChip>, Error> {
+    (0..n).try_fold(acc, |acc, i| {
+        acc.mul(layouter.namespace(|| format!("square {}", i)), &acc)
+    })
+}
+
+/// Returns an element congruent to `base^exponent`, for the integer representing a
+/// witnessed exponent of at most `num_bits` bits, with windows of `window_bits` bits.
+///
+/// The circuit is not satisfied if `exponent` does not fit in `num_bits` bits.
+///
+/// # Panics
+///
+/// Panics if `window_bits` is zero, or if `num_bits` is greater than 256.
+pub fn pow<ModExpChip: ModExpInstructions>(
+    mut layouter: impl Layouter<ModExpChip>,
+    base: &Element<ModExpChip>,
+    exponent: &Element<ModExpChip>,
+    num_bits: usize,
+    window_bits: usize,
+) -> Result<Element<ModExpChip>, Error> {
+    assert!(window_bits > 0);
+    let bits = ModExpChip::decompose(
+        &mut layouter.namespace(|| "decompose exponent"),
+        exponent.inner(),
+        num_bits,
+    )?;
+    let table = powers(
+        layouter.namespace(|| "table"),
+        base,
+        1 << window_bits.min(num_bits),
+    )?;
+    let table: Vec<_> = table.iter().map(|power| power.inner().clone()).collect();
+
+    let mut acc: Option<Element<ModExpChip>> = None;
+    let mut start = 0;
+    for (i, len) in window_lengths(num_bits, window_bits).enumerate() {
+        let window: Vec<_> = bits[start..start + len].iter().rev().cloned().collect();
+        start += len;
+        let multiplier = Element::from_inner(ModExpChip::select(
+            &mut layouter.namespace(|| format!("select window {}", i)),
+            &window,
+            &table[..1 << len],
+        )?);
+
+        acc = Some(match acc {
+            None => multiplier,
+            Some(acc) => square(layouter.namespace(|| format!("window {}", i)), acc, len)?.mul(
+                layouter.namespace(|| format!("multiply window {}", i)),
+                &multiplier,
+            )?,
+        });
+    }
+
+    match acc {
+        Some(acc) => Ok(acc),
+        None => Element::constant(layouter.namespace(|| "one"), [1, 0, 0, 0]),
+    }
+}
+
+/// Returns an element congruent to `base^exponent`, for an exponent known when the
+/// circuit is synthesized, with windows of `window_bits` bits.
+///
+/// # Panics
+///
+/// Panics if `window_bits` is zero.
+pub fn pow_fixed<FieldChip: ForeignFieldInstructions>(
+    mut layouter: impl Layouter<FieldChip>,
+    base: &Element<FieldChip>,
+    exponent: [u64; 4],
+    window_bits: usize,
+) -> Result<Element<FieldChip>, Error> {
+    assert!(window_bits > 0);
+    let bit = |i: usize| (exponent[i / 64] >> (i % 64)) & 1;
+    let num_bits = (0..256)
+        .rev()
+        .find(|i| bit(*i) == 1)
+        .map_or(0, |top| top + 1);
+    if num_bits == 0 {
+        return Element::constant(layouter.namespace(|| "one"), [1, 0, 0, 0]);
+    }
+    let table = powers(
+        layouter.namespace(|| "table"),
+        base,
+        1 << window_bits.min(num_bits),
+    )?;
+
+    // The first window contains the top bit, so it is not zero.
+    let mut acc: Option<Element<FieldChip>> = None;
+    let mut end = num_bits;
+    for (i, len) in window_lengths(num_bits, window_bits).enumerate() {
+        let value = (end - len..end).rev().fold(0, |v, j| 2 * v + bit(j)) as usize;
+        end -= len;
+
+        acc = Some(match acc {
+            None => table[value].clone(),
+            Some(acc) => {
+                let acc = square(layouter.namespace(|| format!("window {}", i)), acc, len)?;
+                if value == 0 {
+                    acc
+                } else {
+                    acc.mul(
+                        layouter.namespace(|| format!("multiply window {}", i)),
+                        &table[value],
+                    )?
+                }
+            }
+        });
+    }
+    Ok(acc.unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{pow, pow_fixed, window_lengths};
+    use crate::{
+        circuit::{layouter, Layouter},
+        dev::MockProver,
+        gadget::foreign_field::{Element, ForeignFieldChip, ForeignFieldConfig},
+        pasta::Fp,
+        plonk::{Assignment, Circuit, ConstraintSystem, Error},
+        primitives::ed25519::Base,
+    };
+
+    #[test]
+    fn windows() {
+        assert_eq!(window_lengths(10, 4).collect::<Vec<_>>(), vec![2, 4, 4]);
+        assert_eq!(window_lengths(8, 4).collect::<Vec<_>>(), vec![4, 4]);
+        assert_eq!(window_lengths(3, 1).collect::<Vec<_>>(), vec![1, 1, 1]);
+        assert_eq!(window_lengths(0, 2).count(), 0);
+    }
+
+    struct PowCircuit {
+        base: [u64; 4],
+        exponent: [u64; 4],
+        num_bits: usize,
+        window_bits: usize,
+    }
+
+    impl Circuit<Fp> for PowCircuit {
+        type Config = ForeignFieldConfig;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> ForeignFieldConfig {
+            ForeignFieldChip::configure(meta, Base::MODULUS)
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            config: ForeignFieldConfig,
+        ) -> Result<(), Error> {
+            let mut layouter = layouter::SingleChip::<ForeignFieldChip<Fp>, _>::new(cs, config)?;
+            let expected = Base::reduce(self.base).pow(&self.exponent).to_limbs();
+            let expected = Element::constant(layouter.namespace(|| "expected"), expected)?;
+
+            let base = Element::new(layouter.namespace(|| "base"), Some(self.base))?;
+            let exponent = Element::new(layouter.namespace(|| "exponent"), Some(self.exponent))?;
+            let result = pow(
+                layouter.namespace(|| "pow"),
+                &base,
+                &exponent,
+                self.num_bits,
+                self.window_bits,
+            )?;
+            result.constrain_equal(layouter.namespace(|| "pow"), &expected)?;
+
+            let result = pow_fixed(
+                layouter.namespace(|| "pow_fixed"),
+                &base,
+                self.exponent,
+                self.window_bits,
+            )?;
+            result.constrain_equal(layouter.namespace(|| "pow_fixed"), &expected)
+        }
+    }
+
+    #[test]
+    fn modexp() {
+        let run = |exponent: [u64; 4], num_bits: usize, window_bits: usize| {
+            let circuit = PowCircuit {
+                base: Base::from_u64(0x0123_4567_89ab_cdef).square().to_limbs(),
+                exponent,
+                num_bits,
+                window_bits,
+            };
+            MockProver::run(12, &circuit, vec![]).unwrap().verify()
+        };
+
+        assert_eq!(run([0b10_1101_1001, 0, 0, 0], 10, 4), Ok(()));
+        assert_eq!(run([0b10_1101_1001, 0, 0, 0], 12, 1), Ok(()));
+        assert_eq!(run([65537, 0, 0, 0], 17, 3), Ok(()));
+        assert_eq!(run([0, 0, 0, 0], 0, 2), Ok(()));
+        // The running sums cross the first limb boundary.
+        assert_eq!(run([0x5555, 1 << 30, 0, 0], 95, 5), Ok(()));
+
+        // An exponent that does not fit in the bit length fails.
+        assert!(run([0b10_1101_1001, 0, 0, 0], 9, 4).is_err());
+        assert!(run([1, 0, 1, 0], 95, 5).is_err());
+    }
+}
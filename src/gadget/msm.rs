@@ -0,0 +1,112 @@
+//! Gadget and chips for multi-scalar multiplication of variable points.
+//!
+//! The [`msm`] gadget computes $\sum_i \[k_i\] P_i$ for scalars given as little-endian
+//! bit strings by interleaved double-and-add: starting from the most significant bit,
+//! the accumulator is doubled once per bit, and each point whose scalar has that bit
+//! set is added to it. The doublings are therefore shared by all of the terms, so an
+//! MSM of $n$ terms of $m$ bits uses $m - 1$ doublings instead of the $n (m - 1)$ of
+//! independent scalar multiplications.
+
+use std::fmt;
+
+use super::ecc::{EccInstructions, Point};
+use crate::{arithmetic::CurveAffine, circuit::Layouter, plonk::Error};
+
+mod chip;
+pub use chip::{MsmChip, MsmConfig};
+
+/// The set of circuit instructions required to use the [`msm`] gadget.
+pub trait MsmInstructions<C: CurveAffine>: EccInstructions<C> {
+    /// Variable representing a bit of a scalar.
+    type Bit: Clone + fmt::Debug;
+
+    /// Witnesses a bit, constraining it to be boolean.
+    fn witness_bit(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<bool>,
+    ) -> Result<Self::Bit, Error>;
+
+    /// Returns `point` if `bit` is set, and the identity otherwise.
+    fn select(
+        layouter: &mut impl Layouter<Self>,
+        bit: &Self::Bit,
+        point: &Self::Point,
+    ) -> Result<Self::Point, Error>;
+}
+
+/// A scalar given by its little-endian bits.
+#[derive(Debug)]
+pub struct Scalar<C: CurveAffine, MsmChip: MsmInstructions<C>> {
+    bits: Vec<MsmChip::Bit>,
+}
+
+impl<C: CurveAffine, MsmChip: MsmInstructions<C>> Scalar<C, MsmChip> {
+    /// Witnesses the little-endian bits of a scalar.
+    pub fn new(mut layouter: impl Layouter<MsmChip>, bits: &[Option<bool>]) -> Result<Self, Error> {
+        bits.iter()
+            .map(|bit| MsmChip::witness_bit(&mut layouter, *bit))
+            .collect::<Result<_, _>>()
+            .map(|bits| Scalar { bits })
+    }
+
+    /// Wraps bits returned by the chip, for use by other gadgets.
+    pub fn from_bits(bits: Vec<MsmChip::Bit>) -> Self {
+        Scalar { bits }
+    }
+
+    /// The little-endian bits of this scalar.
+    pub fn bits(&self) -> &[MsmChip::Bit] {
+        &self.bits
+    }
+}
+
+impl<C: CurveAffine, MsmChip: MsmInstructions<C>> Clone for Scalar<C, MsmChip> {
+    fn clone(&self) -> Self {
+        Scalar {
+            bits: self.bits.clone(),
+        }
+    }
+}
+
+/// Returns $\sum_i \[k_i\] P_i$ for the given terms $(k_i, P_i)$.
+///
+/// Scalars may have different numbers of bits.
+///
+/// # Panics
+///
+/// Panics if no term has a scalar with at least one bit.
+#[allow(clippy::type_complexity)]
+pub fn msm<C: CurveAffine, MsmChip: MsmInstructions<C>>(
+    mut layouter: impl Layouter<MsmChip>,
+    terms: &[(&Scalar<C, MsmChip>, &Point<C, MsmChip>)],
+) -> Result<Point<C, MsmChip>, Error> {
+    let num_bits = terms
+        .iter()
+        .map(|(scalar, _)| scalar.bits.len())
+        .max()
+        .unwrap_or(0);
+    assert!(num_bits > 0);
+
+    let mut acc: Option<Point<C, MsmChip>> = None;
+    for j in (0..num_bits).rev() {
+        let mut layouter = layouter.namespace(|| format!("bit {}", j));
+        if let Some(prev) = acc {
+            acc = Some(prev.double(layouter.namespace(|| "double"))?);
+        }
+
+        for (i, (scalar, point)) in terms.iter().enumerate() {
+            if let Some(bit) = scalar.bits.get(j) {
+                let selected =
+                    MsmChip::select(&mut layouter, bit, point.inner()).map(Point::from_inner)?;
+                acc = Some(match acc {
+                    None => selected,
+                    Some(prev) => {
+                        prev.add(layouter.namespace(|| format!("term {}", i)), &selected)?
+                    }
+                });
+            }
+        }
+    }
+
+    Ok(acc.unwrap())
+}
@@ -0,0 +1,357 @@
+//! A chip implementing [`MsmInstructions`] with the ECC chip.
+//!
+//! # Layout
+//!
+//! The chip includes an [`EccConfig`], and implements [`EccInstructions`] with it. Bits
+//! are witnessed in a `bit` column in a permutation, and constrained to be boolean.
+//!
+//! A selection uses two rows: the first holds a copy of the point in the `x_p, y_p`
+//! columns of the ECC configuration and a copy of the bit, and the second holds the
+//! result $(b \cdot x, b \cdot y)$ in the same columns, which is the point if $b = 1$
+//! and the identity $(0, 0)$ otherwise.
+
+use std::marker::PhantomData;
+
+use ff::Field;
+
+use super::MsmInstructions;
+use crate::{
+    arithmetic::{CurveAffine, FieldExt},
+    circuit::{Cell, Chip, Layouter},
+    gadget::{
+        ecc::{EccChip, EccConfig, EccFixedPoint, EccInstructions, EccPoint, EccX},
+        utilities::AssignedValue,
+    },
+    plonk::{Advice, Any, Column, ConstraintSystem, Error, Expression, Permutation, Selector},
+    poly::Rotation,
+};
+
+/// Configuration for an [`MsmChip`].
+#[derive(Clone, Debug)]
+pub struct MsmConfig {
+    ecc: EccConfig,
+    bit: Column<Advice>,
+    s_bit: Selector,
+    s_select: Selector,
+    perm: Permutation,
+}
+
+/// A chip implementing multi-scalar multiplication over the curve `C`.
+#[derive(Debug)]
+pub struct MsmChip<C: CurveAffine> {
+    _marker: PhantomData<C>,
+}
+
+impl<C: CurveAffine> MsmChip<C> {
+    /// Configures this chip for use in a circuit.
+    pub fn configure(meta: &mut ConstraintSystem<C::Base>) -> MsmConfig {
+        let ecc = EccChip::<C>::configure(meta);
+        MsmConfig::configure(meta, ecc, &[])
+    }
+}
+
+impl MsmConfig {
+    /// Configures the bit and selection gates over the columns of `ecc`, with `columns`
+    /// of other chips added to the permutation of the `bit` column, so that bits can be
+    /// copied from them.
+    pub(crate) fn configure<F: FieldExt>(
+        meta: &mut ConstraintSystem<F>,
+        ecc: EccConfig,
+        columns: &[Column<Any>],
+    ) -> Self {
+        let bit = meta.advice_column();
+        let s_bit = meta.selector();
+        let s_select = meta.selector();
+        let perm = Permutation::new(
+            meta,
+            &[bit.into()]
+                .iter()
+                .chain(columns.iter())
+                .copied()
+                .collect::<Vec<_>>(),
+        );
+
+        meta.create_gate("msm bit", |meta| {
+            let bit = meta.query_advice(bit, Rotation::cur());
+            let s_bit = meta.query_selector(s_bit, Rotation::cur());
+            let one = Expression::Constant(F::one());
+
+            s_bit * bit.clone() * (one - bit)
+        });
+        for (name, column) in [("msm select x", ecc.x_p), ("msm select y", ecc.y_p)] {
+            meta.create_gate(name, |meta| {
+                let input = meta.query_advice(column, Rotation::cur());
+                let output = meta.query_advice(column, Rotation::next());
+                let bit = meta.query_advice(bit, Rotation::cur());
+                let s_select = meta.query_selector(s_select, Rotation::cur());
+
+                s_select * (output - bit * input)
+            });
+        }
+
+        MsmConfig {
+            ecc,
+            bit,
+            s_bit,
+            s_select,
+            perm,
+        }
+    }
+
+    /// Witnesses a bit, constraining it to be boolean.
+    pub(crate) fn witness_bit<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        layouter: &mut impl Layouter<Ch>,
+        value: Option<bool>,
+    ) -> Result<AssignedValue<F>, Error> {
+        let value = value.map(|bit| F::from_u64(bit as u64));
+        layouter.assign_region(
+            || "witness bit",
+            |mut region| {
+                self.s_bit.enable(&mut region, 0)?;
+                let cell = region.assign_advice(
+                    || "bit",
+                    self.bit,
+                    0,
+                    || value.ok_or(Error::SynthesisError),
+                )?;
+                Ok(AssignedValue { cell, value })
+            },
+        )
+    }
+
+    /// Returns `point` if the bit in `cell`, whose value is `bit`, is set, and the
+    /// identity otherwise.
+    ///
+    /// The bit must already be constrained to be boolean.
+    pub(crate) fn select<C: CurveAffine, Ch: Chip<Field = C::Base>>(
+        &self,
+        layouter: &mut impl Layouter<Ch>,
+        cell: Cell,
+        bit: Option<bool>,
+        point: &EccPoint<C>,
+    ) -> Result<EccPoint<C>, Error> {
+        layouter.assign_region(
+            || "select",
+            |mut region| {
+                self.s_select.enable(&mut region, 0)?;
+
+                let point =
+                    self.ecc
+                        .copy_point(&mut region, (self.ecc.x_p, self.ecc.y_p), 0, point)?;
+                let bit_copy = region.assign_advice(
+                    || "bit",
+                    self.bit,
+                    0,
+                    || {
+                        bit.map(|bit| C::Base::from_u64(bit as u64))
+                            .ok_or(Error::SynthesisError)
+                    },
+                )?;
+                region.constrain_equal(&self.perm, cell, bit_copy)?;
+
+                let selected = point.point().zip(bit).map(
+                    |(point, bit)| {
+                        if bit {
+                            point
+                        } else {
+                            C::identity()
+                        }
+                    },
+                );
+                self.ecc
+                    .assign_point(&mut region, (self.ecc.x_p, self.ecc.y_p), 1, selected)
+            },
+        )
+    }
+}
+
+impl<C: CurveAffine> Chip for MsmChip<C> {
+    type Config = MsmConfig;
+    type Loaded = ();
+    type Field = C::Base;
+
+    fn load(_layouter: &mut impl Layouter<Self>) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<C: CurveAffine> EccInstructions<C> for MsmChip<C> {
+    type Point = EccPoint<C>;
+    type X = EccX<C::Base>;
+    type FixedPoint = EccFixedPoint<C>;
+
+    fn fixed_point(base: C) -> Self::FixedPoint {
+        EccChip::<C>::fixed_point(base)
+    }
+
+    fn witness_point(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<C>,
+    ) -> Result<Self::Point, Error> {
+        layouter.config().ecc.clone().witness_point(layouter, value)
+    }
+
+    fn extract_p(point: &Self::Point) -> Self::X {
+        point.extract_p()
+    }
+
+    fn add(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Point,
+        b: &Self::Point,
+    ) -> Result<Self::Point, Error> {
+        layouter.config().ecc.clone().add(layouter, a, b)
+    }
+
+    fn double(layouter: &mut impl Layouter<Self>, a: &Self::Point) -> Result<Self::Point, Error> {
+        layouter.config().ecc.clone().double(layouter, a)
+    }
+
+    fn mul_fixed(
+        layouter: &mut impl Layouter<Self>,
+        scalar: Option<C::Scalar>,
+        base: &Self::FixedPoint,
+    ) -> Result<Self::Point, Error> {
+        layouter
+            .config()
+            .ecc
+            .clone()
+            .mul_fixed(layouter, scalar, base)
+    }
+
+    fn mul_endo(
+        layouter: &mut impl Layouter<Self>,
+        base: &Self::Point,
+        bits: &[Option<bool>],
+    ) -> Result<Self::Point, Error> {
+        layouter.config().ecc.clone().mul_endo(layouter, base, bits)
+    }
+
+    fn constrain_equal(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Point,
+        b: &Self::Point,
+    ) -> Result<(), Error> {
+        layouter
+            .config()
+            .ecc
+            .clone()
+            .constrain_equal(layouter, a, b)
+    }
+}
+
+impl<C: CurveAffine> MsmInstructions<C> for MsmChip<C> {
+    type Bit = AssignedValue<C::Base>;
+
+    fn witness_bit(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<bool>,
+    ) -> Result<Self::Bit, Error> {
+        layouter.config().clone().witness_bit(layouter, value)
+    }
+
+    fn select(
+        layouter: &mut impl Layouter<Self>,
+        bit: &Self::Bit,
+        point: &Self::Point,
+    ) -> Result<Self::Point, Error> {
+        let value = bit.value.map(|bit| bit == C::Base::one());
+        layouter
+            .config()
+            .clone()
+            .select(layouter, bit.cell, value, point)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use group::{prime::PrimeCurveAffine, Curve, Group};
+
+    use super::{MsmChip, MsmConfig};
+    use crate::{
+        arithmetic::FieldExt,
+        circuit::{layouter, Layouter},
+        dev::MockProver,
+        gadget::{
+            ecc::Point,
+            msm::{msm, Scalar},
+        },
+        pasta::pallas,
+        plonk::{Assignment, Circuit, ConstraintSystem, Error},
+    };
+
+    struct MsmCircuit {
+        terms: Vec<(u64, usize, pallas::Affine)>,
+        expected: pallas::Affine,
+    }
+
+    impl Circuit<pallas::Base> for MsmCircuit {
+        type Config = MsmConfig;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> MsmConfig {
+            MsmChip::<pallas::Affine>::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<pallas::Base>,
+            config: MsmConfig,
+        ) -> Result<(), Error> {
+            let mut layouter = layouter::SingleChip::<MsmChip<pallas::Affine>, _>::new(cs, config)?;
+
+            let mut scalars = vec![];
+            let mut points = vec![];
+            for (i, &(k, num_bits, point)) in self.terms.iter().enumerate() {
+                let bits: Vec<_> = (0..num_bits).map(|j| Some((k >> j) & 1 == 1)).collect();
+                scalars.push(Scalar::new(
+                    layouter.namespace(|| format!("k_{}", i)),
+                    &bits,
+                )?);
+                points.push(Point::new(
+                    layouter.namespace(|| format!("P_{}", i)),
+                    Some(point),
+                )?);
+            }
+            let terms: Vec<_> = scalars.iter().zip(points.iter()).collect();
+            let result = msm(layouter.namespace(|| "msm"), &terms)?;
+
+            let expected = Point::new(layouter.namespace(|| "expected"), Some(self.expected))?;
+            result.constrain_equal(layouter.namespace(|| "check"), &expected)
+        }
+    }
+
+    #[test]
+    fn msm_matches_host() {
+        let g = pallas::Affine::generator();
+        let points = [
+            g,
+            (g * pallas::Scalar::from_u64(3)).to_affine(),
+            (g * pallas::Scalar::from_u64(10)).to_affine(),
+        ];
+        // The scalars have different lengths, and the last has a leading zero bit.
+        let terms = vec![(13, 4, points[0]), (0b101, 3, points[1]), (6, 5, points[2])];
+        let expected = terms
+            .iter()
+            .fold(pallas::Point::identity(), |acc, &(k, _, point)| {
+                acc + point * pallas::Scalar::from_u64(k)
+            })
+            .to_affine();
+
+        let circuit = MsmCircuit {
+            terms: terms.clone(),
+            expected,
+        };
+        let prover = MockProver::run(8, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        let circuit = MsmCircuit {
+            terms,
+            expected: (expected + g).to_affine(),
+        };
+        let prover = MockProver::run(8, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
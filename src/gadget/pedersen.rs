@@ -0,0 +1,135 @@
+//! Gadget for Pedersen commitments to scalars of a curve whose base field is the
+//! circuit field, such as Pallas.
+//!
+//! The [`CommitmentKey`] gadget computes the commitment $\[v\] G + \[r\] H$ with two
+//! fixed-base multiplications, using tables precomputed from the generators of a
+//! [`pedersen::CommitmentKey`]. It only requires [`EccInstructions`], so it can be used
+//! with [`EccChip`](super::ecc::EccChip) or any chip that includes it. The values being
+//! committed to are computed outside of a circuit by [`crate::primitives::pedersen`].
+
+use super::ecc::{EccInstructions, FixedPoint, Point};
+use crate::{arithmetic::CurveAffine, circuit::Layouter, plonk::Error, primitives::pedersen};
+
+/// The generators of Pedersen commitments, fixed by the circuit.
+#[derive(Debug)]
+pub struct CommitmentKey<C: CurveAffine, EccChip: EccInstructions<C>> {
+    g: FixedPoint<C, EccChip>,
+    h: FixedPoint<C, EccChip>,
+}
+
+impl<C: CurveAffine, EccChip: EccInstructions<C>> CommitmentKey<C, EccChip> {
+    /// Precomputes the values used to commit with `key`.
+    pub fn new(key: &pedersen::CommitmentKey<C>) -> Self {
+        CommitmentKey {
+            g: FixedPoint::new(key.g()),
+            h: FixedPoint::new(key.h()),
+        }
+    }
+
+    /// Returns the commitment $\[v\] G + \[r\] H$ to `value` with the blinding factor
+    /// `blind`.
+    pub fn commit(
+        &self,
+        mut layouter: impl Layouter<EccChip>,
+        value: Option<C::Scalar>,
+        blind: Option<C::Scalar>,
+    ) -> Result<Point<C, EccChip>, Error> {
+        let v = self.g.mul(layouter.namespace(|| "[v] G"), value)?;
+        let r = self.h.mul(layouter.namespace(|| "[r] H"), blind)?;
+        v.add(layouter.namespace(|| "[v] G + [r] H"), &r)
+    }
+
+    /// Constrains `commitment` to open to `value` with the blinding factor `blind`.
+    pub fn open(
+        &self,
+        mut layouter: impl Layouter<EccChip>,
+        commitment: &Point<C, EccChip>,
+        value: Option<C::Scalar>,
+        blind: Option<C::Scalar>,
+    ) -> Result<(), Error> {
+        let expected = self.commit(layouter.namespace(|| "commit"), value, blind)?;
+        expected.constrain_equal(layouter.namespace(|| "open"), commitment)
+    }
+}
+
+impl<C: CurveAffine, EccChip: EccInstructions<C>> Clone for CommitmentKey<C, EccChip> {
+    fn clone(&self) -> Self {
+        CommitmentKey {
+            g: self.g.clone(),
+            h: self.h.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CommitmentKey;
+    use crate::{
+        arithmetic::FieldExt,
+        circuit::{layouter, Layouter},
+        dev::MockProver,
+        gadget::ecc::{EccChip, EccConfig, Point},
+        pasta::pallas,
+        plonk::{Assignment, Circuit, ConstraintSystem, Error},
+        primitives::pedersen,
+    };
+
+    struct PedersenCircuit {
+        key: pedersen::CommitmentKey<pallas::Affine>,
+        commitment: pallas::Affine,
+        value: pallas::Scalar,
+        blind: pallas::Scalar,
+    }
+
+    impl Circuit<pallas::Base> for PedersenCircuit {
+        type Config = EccConfig;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> EccConfig {
+            EccChip::<pallas::Affine>::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<pallas::Base>,
+            config: EccConfig,
+        ) -> Result<(), Error> {
+            let mut layouter = layouter::SingleChip::<EccChip<pallas::Affine>, _>::new(cs, config)?;
+
+            let key = CommitmentKey::new(&self.key);
+            let commitment =
+                Point::new(layouter.namespace(|| "commitment"), Some(self.commitment))?;
+            key.open(
+                layouter.namespace(|| "open"),
+                &commitment,
+                Some(self.value),
+                Some(self.blind),
+            )
+        }
+    }
+
+    #[test]
+    fn pedersen_open() {
+        let key = pedersen::CommitmentKey::new("halo2:test-pedersen");
+        let value = pallas::Scalar::from_u64(42);
+        let blind = pallas::Scalar::from_u64(0x0123_4567_89ab_cdef);
+        let commitment = key.commit(&value, &blind);
+
+        let circuit = PedersenCircuit {
+            key,
+            commitment,
+            value,
+            blind,
+        };
+        let prover = MockProver::run(11, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        let circuit = PedersenCircuit {
+            value: value + pallas::Scalar::one(),
+            ..circuit
+        };
+        let prover = MockProver::run(11, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
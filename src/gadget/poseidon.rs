@@ -0,0 +1,506 @@
+//! Gadget and chips for the Poseidon algebraic hash function.
+
+use std::fmt;
+
+use crate::{
+    circuit::{Chip, Layouter},
+    plonk::Error,
+    primitives::poseidon::{Domain, State},
+};
+
+mod encryption;
+mod inv;
+mod merkle;
+mod poseidon2;
+mod pow5;
+mod sparse;
+pub(crate) mod sponge;
+pub use encryption::{decrypt, encrypt};
+pub use inv::{InvChip, InvConfig};
+pub use merkle::{MerkleInstructions, MerklePath};
+pub use poseidon2::{Poseidon2Chip, Poseidon2Config};
+pub use pow5::{Pow5Chip, Pow5Config, Pow5T3Chip, Pow5T3Config};
+pub use sparse::{Pow5SparseChip, Pow5SparseConfig};
+pub use sponge::StateWord;
+
+/// A word that is absorbed into the sponge: either part of the message, or a constant
+/// padding element that is fixed by the circuit.
+#[derive(Clone, Copy, Debug)]
+pub enum PaddedWord<W, F> {
+    /// A message word provided by the prover.
+    Message(W),
+    /// A padding value that is known when the circuit is configured.
+    Padding(F),
+}
+
+/// The type used to hold the sponge rate in-circuit.
+pub type SpongeState<W, F, const RATE: usize> = [Option<PaddedWord<W, F>>; RATE];
+
+/// The set of circuit instructions required to use the Poseidon permutation with a
+/// width of `T` field elements.
+pub trait PoseidonInstructions<const T: usize>: Chip {
+    /// Variable representing the word over which the Poseidon permutation operates.
+    type Word: Copy + fmt::Debug;
+
+    /// Applies the Poseidon permutation to the given state.
+    fn permute(
+        layouter: &mut impl Layouter<Self>,
+        initial_state: &State<Self::Word, T>,
+    ) -> Result<State<Self::Word, T>, Error>;
+}
+
+/// The set of circuit instructions required to use the [`Duplex`] and
+/// [`Hash`](struct@Hash) gadgets.
+pub trait PoseidonDuplexInstructions<const T: usize, const RATE: usize>:
+    PoseidonInstructions<T>
+{
+    /// Witnesses a message word, so that it can be absorbed into a sponge.
+    fn load_message(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<Self::Field>,
+    ) -> Result<Self::Word, Error>;
+
+    /// Returns the initial empty state, with the capacity element set to
+    /// `initial_capacity_element`.
+    fn initial_state(
+        layouter: &mut impl Layouter<Self>,
+        initial_capacity_element: Self::Field,
+    ) -> Result<State<Self::Word, T>, Error>;
+
+    /// Adds the given input to the rate portion of the state. Missing input words are
+    /// treated as zero padding.
+    fn pad_and_add(
+        layouter: &mut impl Layouter<Self>,
+        initial_state: &State<Self::Word, T>,
+        input: &SpongeState<Self::Word, Self::Field, RATE>,
+    ) -> Result<State<Self::Word, T>, Error>;
+
+    /// Extracts the rate portion of the state.
+    fn get_output(state: &State<Self::Word, T>) -> [Self::Word; RATE];
+
+    /// Adds each of the given inputs to the rate portion of the state in turn, applying
+    /// the permutation after each one, and returns the resulting state.
+    ///
+    /// The default implementation calls [`PoseidonDuplexInstructions::pad_and_add`] and
+    /// [`PoseidonInstructions::permute`] for each input. Chips can override it to lay
+    /// out consecutive permutations more efficiently.
+    fn absorb_blocks(
+        layouter: &mut impl Layouter<Self>,
+        initial_state: &State<Self::Word, T>,
+        inputs: &[SpongeState<Self::Word, Self::Field, RATE>],
+    ) -> Result<State<Self::Word, T>, Error> {
+        inputs.iter().try_fold(*initial_state, |state, input| {
+            let padded_state = Self::pad_and_add(layouter, &state, input)?;
+            Self::permute(layouter, &padded_state)
+        })
+    }
+}
+
+#[derive(Debug)]
+enum Sponge<W, F, const RATE: usize> {
+    Absorbing(SpongeState<W, F, RATE>),
+    Squeezing([Option<W>; RATE]),
+}
+
+impl<W: Copy, F: Copy, const RATE: usize> Sponge<W, F, RATE> {
+    fn absorb(val: PaddedWord<W, F>) -> Self {
+        let mut input = [None; RATE];
+        input[0] = Some(val);
+        Sponge::Absorbing(input)
+    }
+}
+
+fn poseidon_duplex<
+    PoseidonChip: PoseidonDuplexInstructions<T, RATE>,
+    const T: usize,
+    const RATE: usize,
+>(
+    layouter: &mut impl Layouter<PoseidonChip>,
+    state: &State<PoseidonChip::Word, T>,
+    input: &SpongeState<PoseidonChip::Word, PoseidonChip::Field, RATE>,
+) -> Result<State<PoseidonChip::Word, T>, Error> {
+    let padded_state = PoseidonChip::pad_and_add(layouter, state, input)?;
+    PoseidonChip::permute(layouter, &padded_state)
+}
+
+/// A Poseidon duplex sponge.
+///
+/// This is the in-circuit counterpart of [`crate::primitives::poseidon::Duplex`], and
+/// produces the same outputs for the same sequence of operations.
+#[derive(Debug)]
+pub struct Duplex<
+    PoseidonChip: PoseidonDuplexInstructions<T, RATE>,
+    const T: usize,
+    const RATE: usize,
+> {
+    sponge: Sponge<PoseidonChip::Word, PoseidonChip::Field, RATE>,
+    state: State<PoseidonChip::Word, T>,
+}
+
+impl<PoseidonChip: PoseidonDuplexInstructions<T, RATE>, const T: usize, const RATE: usize>
+    Duplex<PoseidonChip, T, RATE>
+{
+    /// Constructs a new duplex sponge, with the capacity portion of the state set to
+    /// `initial_capacity_element`.
+    pub fn new(
+        mut layouter: impl Layouter<PoseidonChip>,
+        initial_capacity_element: PoseidonChip::Field,
+    ) -> Result<Self, Error> {
+        PoseidonChip::initial_state(&mut layouter, initial_capacity_element).map(|state| Duplex {
+            sponge: Sponge::Absorbing([None; RATE]),
+            state,
+        })
+    }
+
+    fn absorb_padded(
+        &mut self,
+        layouter: &mut impl Layouter<PoseidonChip>,
+        value: PaddedWord<PoseidonChip::Word, PoseidonChip::Field>,
+    ) -> Result<(), Error> {
+        match self.sponge {
+            Sponge::Absorbing(ref mut input) => {
+                for entry in input.iter_mut() {
+                    if entry.is_none() {
+                        *entry = Some(value);
+                        return Ok(());
+                    }
+                }
+
+                // We've already absorbed as many elements as we can
+                self.state = poseidon_duplex(layouter, &self.state, input)?;
+                self.sponge = Sponge::absorb(value);
+            }
+            Sponge::Squeezing(_) => {
+                // Drop the remaining output elements
+                self.sponge = Sponge::absorb(value);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Absorbs an element into the sponge.
+    pub fn absorb(
+        &mut self,
+        mut layouter: impl Layouter<PoseidonChip>,
+        value: PoseidonChip::Word,
+    ) -> Result<(), Error> {
+        self.absorb_padded(&mut layouter, PaddedWord::Message(value))
+    }
+
+    /// Absorbs a sequence of elements into the sponge.
+    ///
+    /// This is equivalent to calling [`Duplex::absorb`] for each element, but applies
+    /// all of the permutations that this requires with a single call to
+    /// [`PoseidonDuplexInstructions::absorb_blocks`], which allows chips to lay them out
+    /// efficiently. It is intended for absorbing large inputs.
+    pub fn absorb_chunk(
+        &mut self,
+        mut layouter: impl Layouter<PoseidonChip>,
+        values: &[PoseidonChip::Word],
+    ) -> Result<(), Error> {
+        if values.is_empty() {
+            return Ok(());
+        }
+
+        let mut input = match self.sponge {
+            Sponge::Absorbing(input) => input,
+            // Drop the remaining output elements
+            Sponge::Squeezing(_) => [None; RATE],
+        };
+
+        let mut blocks = vec![];
+        for value in values {
+            if input.iter().all(|entry| entry.is_some()) {
+                // We've already absorbed as many elements as we can
+                blocks.push(input);
+                input = [None; RATE];
+            }
+            if let Some(entry) = input.iter_mut().find(|entry| entry.is_none()) {
+                *entry = Some(PaddedWord::Message(*value));
+            }
+        }
+
+        self.state = PoseidonChip::absorb_blocks(&mut layouter, &self.state, &blocks)?;
+        self.sponge = Sponge::Absorbing(input);
+
+        Ok(())
+    }
+
+    /// Squeezes an element from the sponge.
+    pub fn squeeze(
+        &mut self,
+        mut layouter: impl Layouter<PoseidonChip>,
+    ) -> Result<PoseidonChip::Word, Error> {
+        loop {
+            match self.sponge {
+                Sponge::Absorbing(ref input) => {
+                    self.state = poseidon_duplex(&mut layouter, &self.state, input)?;
+
+                    let mut output = [None; RATE];
+                    for (entry, word) in output
+                        .iter_mut()
+                        .zip(PoseidonChip::get_output(&self.state).iter())
+                    {
+                        *entry = Some(*word);
+                    }
+                    self.sponge = Sponge::Squeezing(output);
+                }
+                Sponge::Squeezing(ref mut output) => {
+                    for entry in output.iter_mut() {
+                        if let Some(inner) = entry.take() {
+                            return Ok(inner);
+                        }
+                    }
+
+                    // We've already squeezed out all available elements
+                    self.sponge = Sponge::Absorbing([None; RATE]);
+                }
+            }
+        }
+    }
+}
+
+/// A Poseidon hash function used within the given [`Domain`], built on the [`Duplex`]
+/// gadget.
+///
+/// This is the in-circuit counterpart of [`crate::primitives::poseidon::Hash`], and uses
+/// the same capacity element and padding for each domain.
+#[derive(Debug)]
+pub struct Hash<
+    PoseidonChip: PoseidonDuplexInstructions<T, RATE>,
+    D: Domain<PoseidonChip::Field, RATE>,
+    const T: usize,
+    const RATE: usize,
+> {
+    duplex: Duplex<PoseidonChip, T, RATE>,
+    domain: D,
+    len: usize,
+}
+
+impl<
+        PoseidonChip: PoseidonDuplexInstructions<T, RATE>,
+        D: Domain<PoseidonChip::Field, RATE>,
+        const T: usize,
+        const RATE: usize,
+    > Hash<PoseidonChip, D, T, RATE>
+{
+    /// Initializes a new hasher for the given domain.
+    pub fn init(layouter: impl Layouter<PoseidonChip>, domain: D) -> Result<Self, Error> {
+        Duplex::new(layouter, domain.initial_capacity_element()).map(|duplex| Hash {
+            duplex,
+            domain,
+            len: 0,
+        })
+    }
+
+    /// Absorbs the given message into the hasher.
+    ///
+    /// The message may be long: the permutations needed to absorb it are laid out
+    /// together via [`Duplex::absorb_chunk`].
+    pub fn update(
+        &mut self,
+        mut layouter: impl Layouter<PoseidonChip>,
+        message: &[PoseidonChip::Word],
+    ) -> Result<(), Error> {
+        self.duplex
+            .absorb_chunk(layouter.namespace(|| "absorb"), message)?;
+        self.len += message.len();
+        Ok(())
+    }
+
+    /// Pads the absorbed message and returns its hash.
+    pub fn finalize(
+        self,
+        layouter: impl Layouter<PoseidonChip>,
+    ) -> Result<PoseidonChip::Word, Error> {
+        self.finalize_n(layouter, 1).map(|output| output[0])
+    }
+
+    /// Pads the absorbed message and squeezes `n` output elements.
+    ///
+    /// This is the in-circuit counterpart of
+    /// [`crate::primitives::poseidon::Hash::finalize_n`].
+    pub fn finalize_n(
+        mut self,
+        mut layouter: impl Layouter<PoseidonChip>,
+        n: usize,
+    ) -> Result<Vec<PoseidonChip::Word>, Error> {
+        for value in self.domain.padding(self.len) {
+            self.duplex
+                .absorb_padded(&mut layouter, PaddedWord::Padding(value))?;
+        }
+        (0..n)
+            .map(|i| {
+                self.duplex
+                    .squeeze(layouter.namespace(|| format!("squeeze {}", i)))
+            })
+            .collect()
+    }
+
+    /// Convenience function to hash the given message within the given domain. It will
+    /// handle hasher creation, message absorption, and finalization.
+    pub fn hash(
+        mut layouter: impl Layouter<PoseidonChip>,
+        domain: D,
+        message: &[PoseidonChip::Word],
+    ) -> Result<PoseidonChip::Word, Error> {
+        let mut hasher = Self::init(layouter.namespace(|| "init"), domain)?;
+        hasher.update(layouter.namespace(|| "update"), message)?;
+        hasher.finalize(layouter.namespace(|| "finalize"))
+    }
+
+    /// Convenience function to hash the given message within the given domain,
+    /// returning `n` output elements. See [`Hash::finalize_n`].
+    pub fn hash_n(
+        mut layouter: impl Layouter<PoseidonChip>,
+        domain: D,
+        message: &[PoseidonChip::Word],
+        n: usize,
+    ) -> Result<Vec<PoseidonChip::Word>, Error> {
+        let mut hasher = Self::init(layouter.namespace(|| "init"), domain)?;
+        hasher.update(layouter.namespace(|| "update"), message)?;
+        hasher.finalize_n(layouter.namespace(|| "finalize"), n)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::marker::PhantomData;
+
+    use super::{sponge::SpongeConfig, Hash, PoseidonDuplexInstructions, StateWord};
+    use crate::{
+        circuit::{layouter, Layouter},
+        pasta::Fp,
+        plonk::{
+            Advice, Assignment, Circuit, Column, ConstraintSystem, Error, Permutation, Selector,
+        },
+        poly::Rotation,
+        primitives::poseidon::Domain,
+    };
+
+    /// A chip that [`HashCircuit`] hashes with, configured for the specification `S`.
+    pub(crate) trait HashChip<S, const WIDTH: usize, const RATE: usize>:
+        PoseidonDuplexInstructions<WIDTH, RATE, Field = Fp, Word = StateWord<Fp>>
+    {
+        /// Configures the chip over new columns.
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config;
+
+        /// Returns the sponge of `config`.
+        fn sponge(config: &Self::Config) -> &SpongeConfig<WIDTH, RATE>;
+    }
+
+    /// The configuration of a [`HashCircuit`] over a chip configured with `C`.
+    #[derive(Clone, Debug)]
+    pub(crate) struct HashConfig<C> {
+        chip: C,
+        public: Column<Advice>,
+        s_public: Selector,
+        perm: Permutation,
+    }
+
+    /// A circuit that hashes a message with `Chip` in `domain`, and exposes the hash in
+    /// the first row of its instance column.
+    pub(crate) struct HashCircuit<Chip, S, D, const WIDTH: usize, const RATE: usize> {
+        domain: D,
+        message: Vec<Fp>,
+        _marker: PhantomData<(Chip, S)>,
+    }
+
+    impl<Chip, S, D, const WIDTH: usize, const RATE: usize> HashCircuit<Chip, S, D, WIDTH, RATE> {
+        /// Returns a circuit that hashes `message` in `domain`.
+        pub(crate) fn new(domain: D, message: Vec<Fp>) -> Self {
+            HashCircuit {
+                domain,
+                message,
+                _marker: PhantomData,
+            }
+        }
+    }
+
+    impl<Chip, S, D, const WIDTH: usize, const RATE: usize> Circuit<Fp>
+        for HashCircuit<Chip, S, D, WIDTH, RATE>
+    where
+        Chip: HashChip<S, WIDTH, RATE>,
+        Chip::Config: Clone,
+        D: Domain<Fp, RATE>,
+    {
+        type Config = HashConfig<Chip::Config>;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let chip = Chip::configure(meta);
+            let public = meta.advice_column();
+            let instance = meta.instance_column();
+            let s_public = meta.selector();
+
+            let perm = Permutation::new(
+                meta,
+                &Chip::sponge(&chip)
+                    .state
+                    .iter()
+                    .map(|column| (*column).into())
+                    .chain(Some(public.into()))
+                    .collect::<Vec<_>>(),
+            );
+
+            meta.create_gate("public output", |meta| {
+                let public = meta.query_advice(public, Rotation::cur());
+                let instance = meta.query_instance(instance, Rotation::cur());
+                let s_public = meta.query_selector(s_public, Rotation::cur());
+
+                s_public * (public - instance)
+            });
+
+            HashConfig {
+                chip,
+                public,
+                s_public,
+                perm,
+            }
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            config: Self::Config,
+        ) -> Result<(), Error> {
+            let HashConfig {
+                chip,
+                public,
+                s_public,
+                perm,
+            } = config;
+            let mut layouter = layouter::SingleChip::<Chip, _>::new(cs, chip)?;
+
+            let message = self
+                .message
+                .iter()
+                .map(|value| Chip::load_message(&mut layouter, Some(*value)))
+                .collect::<Result<Vec<_>, Error>>()?;
+
+            let output = Hash::<_, _, WIDTH, RATE>::hash(
+                layouter.namespace(|| "hash"),
+                self.domain,
+                &message,
+            )?;
+
+            // Nothing else uses the public column, so the output is laid out in its
+            // first row.
+            layouter.assign_region(
+                || "expose output",
+                |mut region| {
+                    s_public.enable(&mut region, 0)?;
+                    let var = region.assign_advice(
+                        || "output",
+                        public,
+                        0,
+                        || output.value.ok_or(Error::SynthesisError),
+                    )?;
+                    region.constrain_equal(&perm, output.var, var)
+                },
+            )
+        }
+    }
+}
@@ -0,0 +1,262 @@
+//! Gadgets for Poseidon duplex-sponge encryption.
+//!
+//! These are the in-circuit counterparts of
+//! [`crate::primitives::poseidon::encryption`], and produce the same ciphertexts for the
+//! same key, nonce and message.
+
+use super::{poseidon_duplex, PaddedWord, PoseidonDuplexInstructions, SpongeState, StateWord};
+use crate::{
+    arithmetic::FieldExt,
+    circuit::Layouter,
+    plonk::Error,
+    primitives::poseidon::{
+        encryption::{initial_capacity_element, Ciphertext},
+        State,
+    },
+};
+
+/// Absorbs `key` and `nonce` into a fresh sponge, returning the state that the first
+/// message block is added to.
+fn keyed_state<
+    PoseidonChip: PoseidonDuplexInstructions<T, RATE>,
+    const T: usize,
+    const RATE: usize,
+>(
+    layouter: &mut impl Layouter<PoseidonChip>,
+    key: &[PoseidonChip::Word],
+    nonce: PoseidonChip::Word,
+    message_len: usize,
+) -> Result<State<PoseidonChip::Word, T>, Error> {
+    let mut state = PoseidonChip::initial_state(layouter, initial_capacity_element(message_len))?;
+
+    let input: Vec<_> = key.iter().copied().chain(Some(nonce)).collect();
+    for chunk in input.chunks(RATE) {
+        let mut block: SpongeState<_, _, RATE> = [None; RATE];
+        for (word, value) in block.iter_mut().zip(chunk.iter()) {
+            *word = Some(PaddedWord::Message(*value));
+        }
+        state = poseidon_duplex(layouter, &state, &block)?;
+    }
+
+    Ok(state)
+}
+
+/// Encrypts `message` under the given key and nonce, returning the ciphertext words and
+/// the authentication tag.
+pub fn encrypt<
+    PoseidonChip: PoseidonDuplexInstructions<T, RATE>,
+    const T: usize,
+    const RATE: usize,
+>(
+    mut layouter: impl Layouter<PoseidonChip>,
+    key: &[PoseidonChip::Word],
+    nonce: PoseidonChip::Word,
+    message: &[PoseidonChip::Word],
+) -> Result<Ciphertext<PoseidonChip::Word>, Error> {
+    let mut state = keyed_state(&mut layouter, key, nonce, message.len())?;
+
+    let mut words = Vec::with_capacity(message.len());
+    for block in message.chunks(RATE) {
+        let mut input: SpongeState<_, _, RATE> = [None; RATE];
+        for (word, value) in input.iter_mut().zip(block.iter()) {
+            *word = Some(PaddedWord::Message(*value));
+        }
+        state = PoseidonChip::pad_and_add(&mut layouter, &state, &input)?;
+        words.extend_from_slice(&PoseidonChip::get_output(&state)[..block.len()]);
+        state = PoseidonChip::permute(&mut layouter, &state)?;
+    }
+
+    Ok(Ciphertext {
+        words,
+        tag: PoseidonChip::get_output(&state)[0],
+    })
+}
+
+/// Decrypts the given ciphertext under the given key and nonce.
+///
+/// Returns the decrypted message words, along with the ciphertext that they encrypt to.
+/// The caller must constrain the latter (including its tag) to be equal to the
+/// ciphertext being decrypted; the message words are otherwise unconstrained.
+#[allow(clippy::type_complexity)]
+pub fn decrypt<
+    F: FieldExt,
+    PoseidonChip: PoseidonDuplexInstructions<T, RATE, Field = F, Word = StateWord<F>>,
+    const T: usize,
+    const RATE: usize,
+>(
+    mut layouter: impl Layouter<PoseidonChip>,
+    key: &[PoseidonChip::Word],
+    nonce: PoseidonChip::Word,
+    ciphertext: &[Option<F>],
+) -> Result<(Vec<StateWord<F>>, Ciphertext<StateWord<F>>), Error> {
+    let mut state = keyed_state(&mut layouter, key, nonce, ciphertext.len())?;
+
+    let mut message = Vec::with_capacity(ciphertext.len());
+    let mut words = Vec::with_capacity(ciphertext.len());
+    for block in ciphertext.chunks(RATE) {
+        let rate: [_; RATE] = PoseidonChip::get_output(&state);
+
+        let mut input: SpongeState<_, _, RATE> = [None; RATE];
+        for ((word, value), state_word) in input.iter_mut().zip(block.iter()).zip(rate.iter()) {
+            let m = value.zip(state_word.value).map(|(c, s)| c - s);
+            let m = PoseidonChip::load_message(&mut layouter, m)?;
+            message.push(m);
+            *word = Some(PaddedWord::Message(m));
+        }
+        state = PoseidonChip::pad_and_add(&mut layouter, &state, &input)?;
+        words.extend_from_slice(&PoseidonChip::get_output(&state)[..block.len()]);
+        state = PoseidonChip::permute(&mut layouter, &state)?;
+    }
+
+    let tag = PoseidonChip::get_output(&state)[0];
+    Ok((message, Ciphertext { words, tag }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryInto;
+
+    use super::{decrypt, encrypt};
+    use crate::{
+        arithmetic::FieldExt,
+        circuit::{layouter, Cell, Layouter},
+        dev::MockProver,
+        gadget::poseidon::{PoseidonDuplexInstructions, Pow5T3Chip, Pow5T3Config},
+        pasta::Fp,
+        plonk::{Assignment, Circuit, ConstraintSystem, Error},
+        primitives::poseidon::{
+            encryption::{self, Ciphertext},
+            P128Pow5T3,
+        },
+    };
+
+    struct EncryptionCircuit {
+        key: Option<[Fp; 2]>,
+        nonce: Option<Fp>,
+        message: Option<Vec<Fp>>,
+        len: usize,
+        // For the purpose of this test, witness the expected ciphertext.
+        ciphertext: Option<Ciphertext<Fp>>,
+    }
+
+    impl Circuit<Fp> for EncryptionCircuit {
+        type Config = Pow5T3Config<Fp>;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Pow5T3Config<Fp> {
+            let state = (0..3)
+                .map(|_| meta.advice_column())
+                .collect::<Vec<_>>()
+                .try_into()
+                .unwrap();
+            let partial_sbox = meta.advice_column();
+
+            Pow5T3Chip::configure::<P128Pow5T3>(meta, state, partial_sbox)
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            config: Pow5T3Config<Fp>,
+        ) -> Result<(), Error> {
+            let mut layouter = layouter::SingleChip::<Pow5T3Chip<Fp>, _>::new(cs, config)?;
+            let config = layouter.config().clone();
+
+            let key = (0..2)
+                .map(|i| Pow5T3Chip::load_message(&mut layouter, self.key.map(|key| key[i])))
+                .collect::<Result<Vec<_>, Error>>()?;
+            let nonce = Pow5T3Chip::load_message(&mut layouter, self.nonce)?;
+            let message = (0..self.len)
+                .map(|i| {
+                    Pow5T3Chip::load_message(
+                        &mut layouter,
+                        self.message.as_ref().map(|message| message[i]),
+                    )
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+
+            let ciphertext = encrypt(layouter.namespace(|| "encrypt"), &key, nonce, &message)?;
+
+            let values: Vec<_> = ciphertext.words.iter().map(|word| word.value).collect();
+            let (decrypted, recomputed) =
+                decrypt(layouter.namespace(|| "decrypt"), &key, nonce, &values)?;
+
+            let mut constrain = |a: Cell, b: Cell| {
+                layouter.assign_region(
+                    || "constrain",
+                    |mut region| region.constrain_equal(&config.sponge.perm, a, b),
+                )
+            };
+            for (a, b) in ciphertext.words.iter().zip(recomputed.words.iter()) {
+                constrain(a.var, b.var)?;
+            }
+            constrain(ciphertext.tag.var, recomputed.tag.var)?;
+            for (a, b) in message.iter().zip(decrypted.iter()) {
+                constrain(a.var, b.var)?;
+            }
+
+            let expected = (0..self.len)
+                .map(|i| self.ciphertext.as_ref().map(|c| c.words[i]))
+                .chain(Some(self.ciphertext.as_ref().map(|c| c.tag)));
+            let actual = ciphertext.words.iter().chain(Some(&ciphertext.tag));
+            for (expected, actual) in expected.zip(actual) {
+                layouter.assign_region(
+                    || "constrain ciphertext",
+                    |mut region| {
+                        let expected_var = region.assign_advice(
+                            || "load ciphertext",
+                            config.sponge.state[0],
+                            0,
+                            || expected.ok_or(Error::SynthesisError),
+                        )?;
+                        region.constrain_equal(&config.sponge.perm, actual.var, expected_var)
+                    },
+                )?;
+            }
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn encrypt_and_decrypt() {
+        let key = [Fp::from_u64(11), Fp::from_u64(12)];
+        let nonce = Fp::from_u64(7);
+
+        for len in 0..4 {
+            let message: Vec<_> = (0..len).map(|i| Fp::from_u64(i as u64 + 100)).collect();
+            let ciphertext = encryption::encrypt::<_, P128Pow5T3, 3, 2>(&key, nonce, &message);
+
+            let circuit = EncryptionCircuit {
+                key: Some(key),
+                nonce: Some(nonce),
+                message: Some(message),
+                len,
+                ciphertext: Some(ciphertext),
+            };
+            let prover = MockProver::run(10, &circuit, vec![]).unwrap();
+            assert_eq!(prover.verify(), Ok(()))
+        }
+    }
+
+    #[test]
+    fn encrypt_wrong_ciphertext() {
+        let key = [Fp::from_u64(11), Fp::from_u64(12)];
+        let nonce = Fp::from_u64(7);
+        let message = vec![Fp::from_u64(1), Fp::from_u64(2), Fp::from_u64(3)];
+
+        let mut ciphertext = encryption::encrypt::<_, P128Pow5T3, 3, 2>(&key, nonce, &message);
+        ciphertext.tag += Fp::one();
+
+        let circuit = EncryptionCircuit {
+            key: Some(key),
+            nonce: Some(nonce),
+            message: Some(message),
+            len: 3,
+            ciphertext: Some(ciphertext),
+        };
+        let prover = MockProver::run(10, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
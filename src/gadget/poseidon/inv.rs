@@ -0,0 +1,403 @@
+use std::convert::TryInto;
+use std::marker::PhantomData;
+
+use super::{
+    sponge::{SpongeConfig, StateWord},
+    PoseidonDuplexInstructions, PoseidonInstructions, SpongeState,
+};
+use crate::{
+    arithmetic::FieldExt,
+    circuit::{Chip, Layouter, Region},
+    plonk::{Advice, Column, ConstraintSystem, Error, Expression, Selector},
+    poly::Rotation,
+    primitives::poseidon::{Mds, PermutationType, SboxType, Spec, State},
+};
+
+/// Configuration for an [`InvChip`].
+#[derive(Clone, Debug)]
+pub struct InvConfig<F: FieldExt, const WIDTH: usize, const RATE: usize> {
+    sponge: SpongeConfig<WIDTH, RATE>,
+    sbox: [Column<Advice>; WIDTH],
+    s_full: Selector,
+    s_partial: Selector,
+
+    full_rounds: usize,
+    partial_rounds: usize,
+    round_constants: Vec<[F; WIDTH]>,
+    m_reg: Mds<F, WIDTH>,
+}
+
+/// A Poseidon chip using the inverse S-Box $x^{-1}$ (with $0^{-1} = 0$), with a width of
+/// `WIDTH` and a sponge rate of `RATE`.
+///
+/// The inverse cannot be expressed as a polynomial of low degree, so the S-box outputs
+/// are witnessed in the `sbox` columns and constrained by the gates. Each round uses one
+/// row.
+#[derive(Debug)]
+pub struct InvChip<F: FieldExt, const WIDTH: usize, const RATE: usize> {
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt, const WIDTH: usize, const RATE: usize> InvChip<F, WIDTH, RATE> {
+    /// Configures this chip for use in a circuit.
+    ///
+    /// The chip uses the given `state` columns for its inputs and outputs, and places
+    /// all of them in a permutation. The `sbox` columns are used to witness the S-box
+    /// outputs of each round.
+    pub fn configure<S: Spec<F, WIDTH, RATE>>(
+        meta: &mut ConstraintSystem<F>,
+        state: [Column<Advice>; WIDTH],
+        sbox: [Column<Advice>; WIDTH],
+    ) -> InvConfig<F, WIDTH, RATE> {
+        assert_eq!(S::sbox_type(), SboxType::Inv);
+        assert_eq!(S::permutation_type(), PermutationType::Poseidon);
+        assert!(S::full_rounds() & 1 == 0);
+        let (round_constants, m_reg, _) = S::constants();
+
+        let sponge = SpongeConfig::configure(meta, state);
+        let rc_a = sponge.rc_a;
+
+        let s_full = meta.selector();
+        let s_partial = meta.selector();
+
+        // Constrains `output` to be the inverse of `input`, or zero if `input` is zero:
+        //     input * (input * output - 1) = 0
+        //     output * (input * output - 1) = 0
+        let mut inverse_gates = |selector: Selector, idx: usize| {
+            meta.create_gate("inverse S-box", |meta| {
+                let input = meta.query_advice(state[idx], Rotation::cur())
+                    + meta.query_fixed(rc_a[idx], Rotation::cur());
+                let output = meta.query_advice(sbox[idx], Rotation::cur());
+                let selector = meta.query_selector(selector, Rotation::cur());
+
+                selector * input.clone() * (input * output - Expression::Constant(F::one()))
+            });
+            meta.create_gate("inverse S-box", |meta| {
+                let input = meta.query_advice(state[idx], Rotation::cur())
+                    + meta.query_fixed(rc_a[idx], Rotation::cur());
+                let output = meta.query_advice(sbox[idx], Rotation::cur());
+                let selector = meta.query_selector(selector, Rotation::cur());
+
+                selector * output.clone() * (input * output - Expression::Constant(F::one()))
+            });
+        };
+        for idx in 0..WIDTH {
+            inverse_gates(s_full, idx);
+        }
+        inverse_gates(s_partial, 0);
+
+        for idx in 0..WIDTH {
+            meta.create_gate("full round", |meta| {
+                let s_full = meta.query_selector(s_full, Rotation::cur());
+
+                let expr = (0..WIDTH)
+                    .map(|j| meta.query_advice(sbox[j], Rotation::cur()) * m_reg[idx][j])
+                    .fold(Expression::Constant(F::zero()), |acc, term| acc + term);
+                let state_next = meta.query_advice(state[idx], Rotation::next());
+
+                s_full * (expr - state_next)
+            });
+
+            meta.create_gate("partial round", |meta| {
+                let s_partial = meta.query_selector(s_partial, Rotation::cur());
+
+                let sbox_0 = meta.query_advice(sbox[0], Rotation::cur());
+                let expr = (1..WIDTH).fold(sbox_0 * m_reg[idx][0], |acc, j| {
+                    let cur = meta.query_advice(state[j], Rotation::cur());
+                    let rc_a = meta.query_fixed(rc_a[j], Rotation::cur());
+                    acc + (cur + rc_a) * m_reg[idx][j]
+                });
+                let state_next = meta.query_advice(state[idx], Rotation::next());
+
+                s_partial * (expr - state_next)
+            });
+        }
+
+        InvConfig {
+            sponge,
+            sbox,
+            s_full,
+            s_partial,
+            full_rounds: S::full_rounds(),
+            partial_rounds: S::partial_rounds(),
+            round_constants,
+            m_reg,
+        }
+    }
+}
+
+impl<F: FieldExt, const WIDTH: usize, const RATE: usize> Chip for InvChip<F, WIDTH, RATE> {
+    type Config = InvConfig<F, WIDTH, RATE>;
+    type Loaded = ();
+    type Field = F;
+
+    fn load(_layouter: &mut impl Layouter<Self>) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<F: FieldExt, const WIDTH: usize, const RATE: usize> PoseidonInstructions<WIDTH>
+    for InvChip<F, WIDTH, RATE>
+{
+    type Word = StateWord<F>;
+
+    fn permute(
+        layouter: &mut impl Layouter<Self>,
+        initial_state: &State<Self::Word, WIDTH>,
+    ) -> Result<State<Self::Word, WIDTH>, Error> {
+        let config = layouter.config().clone();
+
+        layouter.assign_region(
+            || "permute state",
+            |mut region| {
+                // Load the initial state into this region.
+                let state = config.sponge.load_state(&mut region, 0, initial_state)?;
+
+                let r_f = config.full_rounds / 2;
+                let r_p = config.partial_rounds;
+                (0..(2 * r_f + r_p)).try_fold(state, |state, round| {
+                    let is_full = round < r_f || round >= r_f + r_p;
+                    inv_round(&mut region, &config, &state, round, is_full)
+                })
+            },
+        )
+    }
+}
+
+impl<F: FieldExt, const WIDTH: usize, const RATE: usize> PoseidonDuplexInstructions<WIDTH, RATE>
+    for InvChip<F, WIDTH, RATE>
+{
+    fn load_message(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<F>,
+    ) -> Result<Self::Word, Error> {
+        let config = layouter.config().sponge.clone();
+        config.load_message(layouter, value)
+    }
+
+    fn initial_state(
+        layouter: &mut impl Layouter<Self>,
+        initial_capacity_element: F,
+    ) -> Result<State<Self::Word, WIDTH>, Error> {
+        let config = layouter.config().sponge.clone();
+        config.initial_state(layouter, initial_capacity_element)
+    }
+
+    fn pad_and_add(
+        layouter: &mut impl Layouter<Self>,
+        initial_state: &State<Self::Word, WIDTH>,
+        input: &SpongeState<Self::Word, F, RATE>,
+    ) -> Result<State<Self::Word, WIDTH>, Error> {
+        let config = layouter.config().sponge.clone();
+        config.pad_and_add(layouter, initial_state, input)
+    }
+
+    fn get_output(state: &State<Self::Word, WIDTH>) -> [Self::Word; RATE] {
+        state[..RATE].try_into().unwrap()
+    }
+}
+
+/// Assigns a single round at offset `round` of the permutation region, returning the
+/// state at the start of the next round.
+fn inv_round<F: FieldExt, const WIDTH: usize, const RATE: usize>(
+    region: &mut Region<InvChip<F, WIDTH, RATE>>,
+    config: &InvConfig<F, WIDTH, RATE>,
+    state: &State<StateWord<F>, WIDTH>,
+    round: usize,
+    is_full: bool,
+) -> Result<State<StateWord<F>, WIDTH>, Error> {
+    let offset = round;
+    let rcs = &config.round_constants[round];
+
+    if is_full {
+        config.s_full.enable(region, offset)?;
+    } else {
+        config.s_partial.enable(region, offset)?;
+    }
+
+    for (i, rc) in rcs.iter().enumerate() {
+        region.assign_fixed(
+            || format!("round_{} rc_{}", round, i),
+            config.sponge.rc_a[i],
+            offset,
+            || Ok(*rc),
+        )?;
+    }
+
+    // Apply the round constants and the S-box layer.
+    let sbox_inputs = state
+        .iter()
+        .zip(rcs.iter())
+        .map(|(word, rc)| word.value.map(|v| v + rc))
+        .collect::<Vec<_>>();
+    let sbox_outputs = sbox_inputs
+        .iter()
+        .enumerate()
+        .map(|(i, input)| {
+            if is_full || i == 0 {
+                input.map(|v| Option::from(v.invert()).unwrap_or_else(F::zero))
+            } else {
+                *input
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let sbox_columns = if is_full { WIDTH } else { 1 };
+    for (i, output) in sbox_outputs.iter().enumerate().take(sbox_columns) {
+        region.assign_advice(
+            || format!("round_{} sbox_{}", round, i),
+            config.sbox[i],
+            offset,
+            || output.ok_or(Error::SynthesisError),
+        )?;
+    }
+
+    // Apply the MDS matrix.
+    let sbox_outputs = sbox_outputs.into_iter().collect::<Option<Vec<_>>>();
+    let mut next_state = |i: usize| {
+        let value = sbox_outputs.as_ref().map(|s| {
+            config.m_reg[i]
+                .iter()
+                .zip(s.iter())
+                .fold(F::zero(), |acc, (m_ij, s_j)| acc + *m_ij * s_j)
+        });
+        let var = region.assign_advice(
+            || format!("round_{} state_{}", round + 1, i),
+            config.sponge.state[i],
+            offset + 1,
+            || value.ok_or(Error::SynthesisError),
+        )?;
+        Ok(StateWord { var, value })
+    };
+
+    let next_state: Result<Vec<_>, Error> = (0..WIDTH).map(&mut next_state).collect();
+    Ok(next_state?.try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryInto;
+    use std::marker::PhantomData;
+
+    use super::{InvChip, InvConfig};
+    use crate::{
+        arithmetic::FieldExt,
+        circuit::{layouter, Layouter},
+        dev::MockProver,
+        gadget::poseidon::{Hash, PoseidonDuplexInstructions},
+        pasta::Fp,
+        plonk::{Assignment, Circuit, ConstraintSystem, Error},
+        primitives::poseidon::{self, SboxType, Spec, VariableLength},
+    };
+
+    #[derive(Debug)]
+    struct InvT3;
+
+    impl<F: FieldExt> Spec<F, 3, 2> for InvT3 {
+        fn full_rounds() -> usize {
+            8
+        }
+
+        fn partial_rounds() -> usize {
+            61
+        }
+
+        fn sbox(val: F) -> F {
+            Option::from(val.invert()).unwrap_or_else(F::zero)
+        }
+
+        fn sbox_type() -> SboxType {
+            SboxType::Inv
+        }
+
+        fn secure_mds() -> usize {
+            0
+        }
+    }
+
+    struct HashCircuit {
+        message: Option<Vec<Fp>>,
+        len: usize,
+        output: Option<Fp>,
+        _spec: PhantomData<InvT3>,
+    }
+
+    impl Circuit<Fp> for HashCircuit {
+        type Config = InvConfig<Fp, 3, 2>;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> InvConfig<Fp, 3, 2> {
+            let state = (0..3)
+                .map(|_| meta.advice_column())
+                .collect::<Vec<_>>()
+                .try_into()
+                .unwrap();
+            let sbox = (0..3)
+                .map(|_| meta.advice_column())
+                .collect::<Vec<_>>()
+                .try_into()
+                .unwrap();
+
+            InvChip::configure::<InvT3>(meta, state, sbox)
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            config: InvConfig<Fp, 3, 2>,
+        ) -> Result<(), Error> {
+            let mut layouter = layouter::SingleChip::<InvChip<Fp, 3, 2>, _>::new(cs, config)?;
+            let config = layouter.config().sponge.clone();
+
+            let message = (0..self.len)
+                .map(|i| {
+                    InvChip::load_message(
+                        &mut layouter,
+                        self.message.as_ref().map(|message| message[i]),
+                    )
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+
+            let output =
+                Hash::<_, _, 3, 2>::hash(layouter.namespace(|| "hash"), VariableLength, &message)?;
+
+            layouter.assign_region(
+                || "constrain output",
+                |mut region| {
+                    let expected_var = region.assign_advice(
+                        || "load output",
+                        config.state[0],
+                        0,
+                        || self.output.ok_or(Error::SynthesisError),
+                    )?;
+                    region.constrain_equal(&config.perm, output.var, expected_var)
+                },
+            )
+        }
+    }
+
+    #[test]
+    fn inverse_sbox_hash() {
+        let message = vec![Fp::zero(), Fp::from_u64(2), Fp::from_u64(3)];
+        let output = poseidon::Hash::<_, InvT3, _, 3, 2>::hash(VariableLength, &message);
+
+        let circuit = HashCircuit {
+            message: Some(message.clone()),
+            len: 3,
+            output: Some(output),
+            _spec: PhantomData,
+        };
+        let prover = MockProver::run(8, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        let circuit = HashCircuit {
+            message: Some(message),
+            len: 3,
+            output: Some(output + Fp::one()),
+            _spec: PhantomData,
+        };
+        let prover = MockProver::run(8, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
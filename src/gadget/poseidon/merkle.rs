@@ -0,0 +1,334 @@
+//! Merkle path verification, built on the Poseidon [`Hash`] gadget.
+
+use std::marker::PhantomData;
+
+use super::{sponge::StateWord, Hash, PoseidonDuplexInstructions};
+use crate::{
+    arithmetic::FieldExt,
+    circuit::{Chip, Layouter},
+    plonk::{Advice, Column, ConstraintSystem, Error, Expression, Permutation, Selector},
+    poly::Rotation,
+    primitives::poseidon::MerkleNode,
+};
+
+/// The set of circuit instructions required to use the [`MerklePath`] gadget, on top
+/// of a Poseidon chip with a rate of 2.
+pub trait MerkleInstructions<const T: usize>: PoseidonDuplexInstructions<T, 2> {
+    /// Witnesses `sibling` and `swap`, and returns `[node, sibling]` if `swap` is
+    /// false, or `[sibling, node]` if it is true.
+    fn swap(
+        layouter: &mut impl Layouter<Self>,
+        node: Self::Word,
+        sibling: Option<Self::Field>,
+        swap: Option<bool>,
+    ) -> Result<[Self::Word; 2], Error>;
+
+    /// Constrains `a` and `b` to be equal.
+    fn constrain_equal(
+        layouter: &mut impl Layouter<Self>,
+        a: Self::Word,
+        b: Self::Word,
+    ) -> Result<(), Error>;
+}
+
+/// An authentication path from a leaf to the root of a Merkle tree.
+///
+/// Nodes are hashed in the same way as by
+/// [`crate::primitives::poseidon::merkle::IncrementalTree`].
+#[derive(Debug)]
+pub struct MerklePath<PoseidonChip: MerkleInstructions<T>, const T: usize> {
+    position: Option<u64>,
+    path: Vec<Option<PoseidonChip::Field>>,
+    _marker: PhantomData<PoseidonChip>,
+}
+
+impl<PoseidonChip: MerkleInstructions<T>, const T: usize> MerklePath<PoseidonChip, T> {
+    /// Constructs a path for the leaf at `position`, with the siblings of the nodes
+    /// from the leaf up to (but excluding) the root. The depth of the tree is given by
+    /// the length of `path`.
+    pub fn new(position: Option<u64>, path: Vec<Option<PoseidonChip::Field>>) -> Self {
+        assert!(path.len() < 64);
+        MerklePath {
+            position,
+            path,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Computes the root of the tree from the given leaf.
+    ///
+    /// Each bit of the position is constrained to be boolean, but the position itself
+    /// is not exposed; circuits that need it should constrain it separately.
+    pub fn calculate_root(
+        &self,
+        mut layouter: impl Layouter<PoseidonChip>,
+        leaf: PoseidonChip::Word,
+    ) -> Result<PoseidonChip::Word, Error> {
+        self.path
+            .iter()
+            .enumerate()
+            .try_fold(leaf, |node, (layer, sibling)| {
+                let mut layouter = layouter.namespace(|| format!("layer {}", layer));
+                let swap = self.position.map(|position| (position >> layer) & 1 == 1);
+                let pair = PoseidonChip::swap(&mut layouter, node, *sibling, swap)?;
+                Hash::<PoseidonChip, _, T, 2>::hash(
+                    layouter.namespace(|| "hash"),
+                    MerkleNode {
+                        layer: layer as u32,
+                    },
+                    &pair,
+                )
+            })
+    }
+
+    /// Constrains the root of the tree computed from the given leaf to equal `root`.
+    pub fn verify(
+        &self,
+        mut layouter: impl Layouter<PoseidonChip>,
+        leaf: PoseidonChip::Word,
+        root: PoseidonChip::Word,
+    ) -> Result<(), Error> {
+        let calculated = self.calculate_root(layouter.namespace(|| "calculate root"), leaf)?;
+        PoseidonChip::constrain_equal(&mut layouter, calculated, root)
+    }
+}
+
+/// The gate used by the Poseidon chips to implement [`MerkleInstructions::swap`].
+#[derive(Clone, Debug)]
+pub(super) struct SwapConfig {
+    columns: [Column<Advice>; 3],
+    s_swap: Selector,
+    perm: Permutation,
+}
+
+impl SwapConfig {
+    /// Configures the swap gate over the given columns, which must be in `perm`.
+    pub(super) fn configure<F: FieldExt>(
+        meta: &mut ConstraintSystem<F>,
+        columns: [Column<Advice>; 3],
+        perm: Permutation,
+    ) -> Self {
+        let s_swap = meta.selector();
+
+        // The current row holds (node, sibling, swap), and the next row holds the
+        // (left, right) pair.
+        meta.create_gate("merkle swap is boolean", |meta| {
+            let swap = meta.query_advice(columns[2], Rotation::cur());
+            let s_swap = meta.query_selector(s_swap, Rotation::cur());
+
+            s_swap * swap.clone() * (swap - Expression::Constant(F::one()))
+        });
+        meta.create_gate("merkle swap left", |meta| {
+            let node = meta.query_advice(columns[0], Rotation::cur());
+            let sibling = meta.query_advice(columns[1], Rotation::cur());
+            let swap = meta.query_advice(columns[2], Rotation::cur());
+            let left = meta.query_advice(columns[0], Rotation::next());
+            let s_swap = meta.query_selector(s_swap, Rotation::cur());
+
+            s_swap * (node.clone() + swap * (sibling - node) - left)
+        });
+        meta.create_gate("merkle swap right", |meta| {
+            let node = meta.query_advice(columns[0], Rotation::cur());
+            let sibling = meta.query_advice(columns[1], Rotation::cur());
+            let swap = meta.query_advice(columns[2], Rotation::cur());
+            let right = meta.query_advice(columns[1], Rotation::next());
+            let s_swap = meta.query_selector(s_swap, Rotation::cur());
+
+            s_swap * (sibling.clone() + swap * (node - sibling) - right)
+        });
+
+        SwapConfig {
+            columns,
+            s_swap,
+            perm,
+        }
+    }
+
+    pub(super) fn swap<F: FieldExt, C: Chip<Field = F>>(
+        &self,
+        layouter: &mut impl Layouter<C>,
+        node: StateWord<F>,
+        sibling: Option<F>,
+        swap: Option<bool>,
+    ) -> Result<[StateWord<F>; 2], Error> {
+        layouter.assign_region(
+            || "merkle swap",
+            |mut region| {
+                self.s_swap.enable(&mut region, 0)?;
+
+                let var = region.assign_advice(
+                    || "node",
+                    self.columns[0],
+                    0,
+                    || node.value.ok_or(Error::SynthesisError),
+                )?;
+                region.constrain_equal(&self.perm, node.var, var)?;
+                region.assign_advice(
+                    || "sibling",
+                    self.columns[1],
+                    0,
+                    || sibling.ok_or(Error::SynthesisError),
+                )?;
+                region.assign_advice(
+                    || "swap",
+                    self.columns[2],
+                    0,
+                    || {
+                        swap.map(|swap| if swap { F::one() } else { F::zero() })
+                            .ok_or(Error::SynthesisError)
+                    },
+                )?;
+
+                let pair = node
+                    .value
+                    .zip(sibling)
+                    .zip(swap)
+                    .map(|((node, sibling), swap)| {
+                        if swap {
+                            (sibling, node)
+                        } else {
+                            (node, sibling)
+                        }
+                    });
+                let left = pair.map(|(left, _)| left);
+                let right = pair.map(|(_, right)| right);
+
+                let left_var = region.assign_advice(
+                    || "left",
+                    self.columns[0],
+                    1,
+                    || left.ok_or(Error::SynthesisError),
+                )?;
+                let right_var = region.assign_advice(
+                    || "right",
+                    self.columns[1],
+                    1,
+                    || right.ok_or(Error::SynthesisError),
+                )?;
+
+                Ok([
+                    StateWord {
+                        var: left_var,
+                        value: left,
+                    },
+                    StateWord {
+                        var: right_var,
+                        value: right,
+                    },
+                ])
+            },
+        )
+    }
+
+    pub(super) fn constrain_equal<F: FieldExt, C: Chip<Field = F>>(
+        &self,
+        layouter: &mut impl Layouter<C>,
+        a: StateWord<F>,
+        b: StateWord<F>,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "constrain equal",
+            |mut region| region.constrain_equal(&self.perm, a.var, b.var),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryInto;
+
+    use super::MerklePath;
+    use crate::{
+        arithmetic::FieldExt,
+        circuit::{layouter, Layouter},
+        dev::MockProver,
+        gadget::poseidon::{PoseidonDuplexInstructions, Pow5T3Chip, Pow5T3Config},
+        pasta::Fp,
+        plonk::{Assignment, Circuit, ConstraintSystem, Error},
+        primitives::poseidon::{merkle::IncrementalTree, P128Pow5T3},
+    };
+
+    const DEPTH: usize = 4;
+
+    struct MerkleCircuit {
+        leaf: Option<Fp>,
+        position: Option<u64>,
+        path: Option<Vec<Fp>>,
+        root: Option<Fp>,
+    }
+
+    impl Circuit<Fp> for MerkleCircuit {
+        type Config = Pow5T3Config<Fp>;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Pow5T3Config<Fp> {
+            let state = (0..3)
+                .map(|_| meta.advice_column())
+                .collect::<Vec<_>>()
+                .try_into()
+                .unwrap();
+            let partial_sbox = meta.advice_column();
+
+            let mut config = Pow5T3Chip::configure::<P128Pow5T3>(meta, state, partial_sbox);
+            Pow5T3Chip::configure_merkle(meta, &mut config);
+            config
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            config: Pow5T3Config<Fp>,
+        ) -> Result<(), Error> {
+            let mut layouter = layouter::SingleChip::<Pow5T3Chip<Fp>, _>::new(cs, config)?;
+
+            let leaf = Pow5T3Chip::load_message(&mut layouter, self.leaf)?;
+            // For the purpose of this test, witness the root.
+            let root = Pow5T3Chip::load_message(&mut layouter, self.root)?;
+
+            let path = MerklePath::<Pow5T3Chip<Fp>, 3>::new(
+                self.position,
+                (0..DEPTH)
+                    .map(|i| self.path.as_ref().map(|path| path[i]))
+                    .collect(),
+            );
+            path.verify(layouter.namespace(|| "merkle path"), leaf, root)
+        }
+    }
+
+    fn tree() -> IncrementalTree<Fp, P128Pow5T3, 3> {
+        let mut tree = IncrementalTree::new(DEPTH);
+        for i in 0..11 {
+            tree.append(Fp::from_u64(i * 7 + 1));
+        }
+        tree
+    }
+
+    #[test]
+    fn merkle_path() {
+        let tree = tree();
+        for &position in &[0, 5, 10] {
+            let circuit = MerkleCircuit {
+                leaf: Some(Fp::from_u64(position * 7 + 1)),
+                position: Some(position),
+                path: tree.path(position),
+                root: Some(tree.root()),
+            };
+            let prover = MockProver::run(9, &circuit, vec![]).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn merkle_path_wrong_position() {
+        let tree = tree();
+        let circuit = MerkleCircuit {
+            leaf: Some(Fp::from_u64(5 * 7 + 1)),
+            position: Some(4),
+            path: tree.path(5),
+            root: Some(tree.root()),
+        };
+        let prover = MockProver::run(9, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
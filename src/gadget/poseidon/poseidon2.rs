@@ -0,0 +1,461 @@
+use std::convert::TryInto;
+use std::marker::PhantomData;
+
+use super::{
+    sponge::{SpongeConfig, StateWord},
+    PoseidonDuplexInstructions, PoseidonInstructions, SpongeState,
+};
+use crate::{
+    arithmetic::FieldExt,
+    circuit::{Chip, Layouter, Region},
+    plonk::{Advice, Column, ConstraintSystem, Error, Expression, Selector},
+    poly::Rotation,
+    primitives::poseidon::{Mds, PermutationType, SboxType, Spec, State},
+};
+
+/// Configuration for a [`Poseidon2Chip`].
+#[derive(Clone, Debug)]
+pub struct Poseidon2Config<F: FieldExt, const WIDTH: usize, const RATE: usize> {
+    sponge: SpongeConfig<WIDTH, RATE>,
+    s_initial: Selector,
+    s_external: Selector,
+    s_internal: Selector,
+
+    half_full_rounds: usize,
+    partial_rounds: usize,
+    round_constants: Vec<[F; WIDTH]>,
+    m_external: Mds<F, WIDTH>,
+    m_internal: Mds<F, WIDTH>,
+}
+
+/// A Poseidon2 chip using an $x^5$ S-Box, with a width of `WIDTH` and a sponge rate of
+/// `RATE`.
+///
+/// The initial linear layer and each round use one row. Unlike [`Pow5Chip`], no
+/// additional columns are needed to witness intermediate S-box outputs, because the
+/// internal rounds only apply the S-box to a single word.
+///
+/// [`Pow5Chip`]: super::Pow5Chip
+#[derive(Debug)]
+pub struct Poseidon2Chip<F: FieldExt, const WIDTH: usize, const RATE: usize> {
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt, const WIDTH: usize, const RATE: usize> Poseidon2Chip<F, WIDTH, RATE> {
+    /// Configures this chip for use in a circuit.
+    ///
+    /// The chip uses the given `state` columns for its inputs and outputs, and places
+    /// all of them in a permutation.
+    pub fn configure<S: Spec<F, WIDTH, RATE>>(
+        meta: &mut ConstraintSystem<F>,
+        state: [Column<Advice>; WIDTH],
+    ) -> Poseidon2Config<F, WIDTH, RATE> {
+        assert_eq!(S::sbox_type(), SboxType::Pow);
+        assert_eq!(S::permutation_type(), PermutationType::Poseidon2);
+        assert!(S::full_rounds() & 1 == 0);
+        let (round_constants, m_external, m_internal) = S::constants();
+
+        let sponge = SpongeConfig::configure(meta, state);
+        let rc_a = sponge.rc_a;
+
+        let s_initial = meta.selector();
+        let s_external = meta.selector();
+        let s_internal = meta.selector();
+
+        let pow_5 = |v: Expression<F>| {
+            let v2 = v.clone() * v.clone();
+            v2.clone() * v2 * v
+        };
+
+        for idx in 0..WIDTH {
+            meta.create_gate("initial linear layer", |meta| {
+                let s_initial = meta.query_selector(s_initial, Rotation::cur());
+
+                let expr = (0..WIDTH)
+                    .map(|j| meta.query_advice(state[j], Rotation::cur()) * m_external[idx][j])
+                    .fold(Expression::Constant(F::zero()), |acc, term| acc + term);
+                let state_next = meta.query_advice(state[idx], Rotation::next());
+
+                s_initial * (expr - state_next)
+            });
+        }
+
+        for idx in 0..WIDTH {
+            meta.create_gate("external round", |meta| {
+                let s_external = meta.query_selector(s_external, Rotation::cur());
+
+                let expr = (0..WIDTH)
+                    .map(|j| {
+                        let state_cur = meta.query_advice(state[j], Rotation::cur());
+                        let rc_a = meta.query_fixed(rc_a[j], Rotation::cur());
+                        pow_5(state_cur + rc_a) * m_external[idx][j]
+                    })
+                    .fold(Expression::Constant(F::zero()), |acc, term| acc + term);
+                let state_next = meta.query_advice(state[idx], Rotation::next());
+
+                s_external * (expr - state_next)
+            });
+        }
+
+        for idx in 0..WIDTH {
+            meta.create_gate("internal round", |meta| {
+                let s_internal = meta.query_selector(s_internal, Rotation::cur());
+
+                let cur_0 = meta.query_advice(state[0], Rotation::cur());
+                let rc_a0 = meta.query_fixed(rc_a[0], Rotation::cur());
+                let expr = (1..WIDTH).fold(pow_5(cur_0 + rc_a0) * m_internal[idx][0], |acc, j| {
+                    acc + meta.query_advice(state[j], Rotation::cur()) * m_internal[idx][j]
+                });
+                let state_next = meta.query_advice(state[idx], Rotation::next());
+
+                s_internal * (expr - state_next)
+            });
+        }
+
+        Poseidon2Config {
+            sponge,
+            s_initial,
+            s_external,
+            s_internal,
+            half_full_rounds: S::full_rounds() / 2,
+            partial_rounds: S::partial_rounds(),
+            round_constants,
+            m_external,
+            m_internal,
+        }
+    }
+}
+
+impl<F: FieldExt, const WIDTH: usize, const RATE: usize> Chip for Poseidon2Chip<F, WIDTH, RATE> {
+    type Config = Poseidon2Config<F, WIDTH, RATE>;
+    type Loaded = ();
+    type Field = F;
+
+    fn load(_layouter: &mut impl Layouter<Self>) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<F: FieldExt, const WIDTH: usize, const RATE: usize> PoseidonInstructions<WIDTH>
+    for Poseidon2Chip<F, WIDTH, RATE>
+{
+    type Word = StateWord<F>;
+
+    fn permute(
+        layouter: &mut impl Layouter<Self>,
+        initial_state: &State<Self::Word, WIDTH>,
+    ) -> Result<State<Self::Word, WIDTH>, Error> {
+        let config = layouter.config().clone();
+
+        layouter.assign_region(
+            || "permute state",
+            |mut region| {
+                // Load the initial state into this region.
+                let state = config.sponge.load_state(&mut region, 0, initial_state)?;
+
+                // Apply the initial linear layer.
+                config.s_initial.enable(&mut region, 0)?;
+                let values = state
+                    .iter()
+                    .map(|word| word.value)
+                    .collect::<Option<Vec<_>>>();
+                let next = matrix_mul(&config.m_external, values.as_deref());
+                let state = assign_state(&mut region, &config, "initial", 1, next)?;
+
+                let rounds = 2 * config.half_full_rounds + config.partial_rounds;
+                let state = (0..rounds).try_fold(state, |state, round| {
+                    poseidon2_round(&mut region, &config, round, state)
+                })?;
+
+                Ok(state)
+            },
+        )
+    }
+}
+
+impl<F: FieldExt, const WIDTH: usize, const RATE: usize> PoseidonDuplexInstructions<WIDTH, RATE>
+    for Poseidon2Chip<F, WIDTH, RATE>
+{
+    fn load_message(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<F>,
+    ) -> Result<Self::Word, Error> {
+        let config = layouter.config().sponge.clone();
+        config.load_message(layouter, value)
+    }
+
+    fn initial_state(
+        layouter: &mut impl Layouter<Self>,
+        initial_capacity_element: F,
+    ) -> Result<State<Self::Word, WIDTH>, Error> {
+        let config = layouter.config().sponge.clone();
+        config.initial_state(layouter, initial_capacity_element)
+    }
+
+    fn pad_and_add(
+        layouter: &mut impl Layouter<Self>,
+        initial_state: &State<Self::Word, WIDTH>,
+        input: &SpongeState<Self::Word, F, RATE>,
+    ) -> Result<State<Self::Word, WIDTH>, Error> {
+        let config = layouter.config().sponge.clone();
+        config.pad_and_add(layouter, initial_state, input)
+    }
+
+    fn get_output(state: &State<Self::Word, WIDTH>) -> [Self::Word; RATE] {
+        state[..RATE].try_into().unwrap()
+    }
+}
+
+/// Assigns a single (external or internal) round, whose input `state` is at row
+/// `round + 1` of the region.
+fn poseidon2_round<F: FieldExt, const WIDTH: usize, const RATE: usize>(
+    region: &mut Region<Poseidon2Chip<F, WIDTH, RATE>>,
+    config: &Poseidon2Config<F, WIDTH, RATE>,
+    round: usize,
+    state: State<StateWord<F>, WIDTH>,
+) -> Result<State<StateWord<F>, WIDTH>, Error> {
+    let offset = round + 1;
+    let rcs = &config.round_constants[round];
+    let is_internal =
+        round >= config.half_full_rounds && round < config.half_full_rounds + config.partial_rounds;
+
+    // Load the round constants.
+    for (i, rc) in rcs.iter().enumerate() {
+        region.assign_fixed(
+            || format!("round_{} rc_{}", round, i),
+            config.sponge.rc_a[i],
+            offset,
+            || Ok(*rc),
+        )?;
+    }
+
+    let values = state
+        .iter()
+        .map(|word| word.value)
+        .collect::<Option<Vec<_>>>();
+    let next = if is_internal {
+        config.s_internal.enable(region, offset)?;
+        let sboxed = values.map(|mut values| {
+            values[0] = (values[0] + rcs[0]).pow(&[5, 0, 0, 0]);
+            values
+        });
+        matrix_mul(&config.m_internal, sboxed.as_deref())
+    } else {
+        config.s_external.enable(region, offset)?;
+        let sboxed = values.map(|values| {
+            values
+                .iter()
+                .zip(rcs.iter())
+                .map(|(value, rc)| (*value + rc).pow(&[5, 0, 0, 0]))
+                .collect::<Vec<_>>()
+        });
+        matrix_mul(&config.m_external, sboxed.as_deref())
+    };
+
+    assign_state(
+        region,
+        config,
+        &format!("round_{}", round + 1),
+        offset + 1,
+        next,
+    )
+}
+
+/// Witnesses the given state in the state columns at `offset`.
+fn assign_state<F: FieldExt, const WIDTH: usize, const RATE: usize>(
+    region: &mut Region<Poseidon2Chip<F, WIDTH, RATE>>,
+    config: &Poseidon2Config<F, WIDTH, RATE>,
+    name: &str,
+    offset: usize,
+    state: [Option<F>; WIDTH],
+) -> Result<State<StateWord<F>, WIDTH>, Error> {
+    let mut state_word = |i: usize| {
+        let value = state[i];
+        let var = region.assign_advice(
+            || format!("{} state_{}", name, i),
+            config.sponge.state[i],
+            offset,
+            || value.ok_or(Error::SynthesisError),
+        )?;
+        Ok(StateWord { var, value })
+    };
+
+    let state: Result<Vec<_>, Error> = (0..WIDTH).map(&mut state_word).collect();
+    Ok(state?.try_into().unwrap())
+}
+
+/// Multiplies the given (possibly unknown) state by the matrix `m`.
+fn matrix_mul<F: FieldExt, const WIDTH: usize>(
+    m: &Mds<F, WIDTH>,
+    state: Option<&[F]>,
+) -> [Option<F>; WIDTH] {
+    let mut next_state = [None; WIDTH];
+    for (next, m_i) in next_state.iter_mut().zip(m.iter()) {
+        *next = state.map(|state| {
+            m_i.iter()
+                .zip(state.iter())
+                .fold(F::zero(), |acc, (m_ij, s_j)| acc + *m_ij * s_j)
+        });
+    }
+    next_state
+}
+
+#[cfg(test)]
+mod tests {
+    use ff::Field;
+    use std::convert::TryInto;
+    use std::marker::PhantomData;
+
+    use super::{Poseidon2Chip, Poseidon2Config};
+    use crate::{
+        arithmetic::FieldExt,
+        circuit::{layouter, Layouter},
+        dev::MockProver,
+        gadget::poseidon::{Hash, PoseidonDuplexInstructions},
+        pasta::Fp,
+        plonk::{Assignment, Circuit, ConstraintSystem, Error},
+        primitives::poseidon::{self, PermutationType, Poseidon2Pow5T3, Spec, VariableLength},
+    };
+
+    /// A width-4 Poseidon2 specification, exercising the $M_4$-based external matrix.
+    #[derive(Debug)]
+    struct Poseidon2Pow5T4;
+
+    impl<F: FieldExt> Spec<F, 4, 3> for Poseidon2Pow5T4 {
+        fn full_rounds() -> usize {
+            8
+        }
+
+        fn partial_rounds() -> usize {
+            56
+        }
+
+        fn sbox(val: F) -> F {
+            val.pow_vartime([5])
+        }
+
+        fn permutation_type() -> PermutationType {
+            PermutationType::Poseidon2
+        }
+
+        fn secure_mds() -> usize {
+            0
+        }
+    }
+
+    struct HashCircuit<S: Spec<Fp, WIDTH, RATE>, const WIDTH: usize, const RATE: usize> {
+        message: Option<Vec<Fp>>,
+        len: usize,
+        // For the purpose of this test, witness the result.
+        output: Option<Fp>,
+        _spec: PhantomData<S>,
+    }
+
+    impl<S: Spec<Fp, WIDTH, RATE>, const WIDTH: usize, const RATE: usize> Circuit<Fp>
+        for HashCircuit<S, WIDTH, RATE>
+    {
+        type Config = Poseidon2Config<Fp, WIDTH, RATE>;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Poseidon2Config<Fp, WIDTH, RATE> {
+            let state = (0..WIDTH)
+                .map(|_| meta.advice_column())
+                .collect::<Vec<_>>()
+                .try_into()
+                .unwrap();
+
+            Poseidon2Chip::configure::<S>(meta, state)
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            config: Poseidon2Config<Fp, WIDTH, RATE>,
+        ) -> Result<(), Error> {
+            let mut layouter =
+                layouter::SingleChip::<Poseidon2Chip<Fp, WIDTH, RATE>, _>::new(cs, config)?;
+            let config = layouter.config().clone();
+
+            let message = (0..self.len)
+                .map(|i| {
+                    Poseidon2Chip::load_message(
+                        &mut layouter,
+                        self.message.as_ref().map(|message| message[i]),
+                    )
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+
+            let output = Hash::<_, _, WIDTH, RATE>::hash(
+                layouter.namespace(|| "hash"),
+                VariableLength,
+                &message,
+            )?;
+
+            layouter.assign_region(
+                || "constrain output",
+                |mut region| {
+                    let expected_var = region.assign_advice(
+                        || "load output",
+                        config.sponge.state[0],
+                        0,
+                        || self.output.ok_or(Error::SynthesisError),
+                    )?;
+                    region.constrain_equal(&config.sponge.perm, output.var, expected_var)
+                },
+            )
+        }
+    }
+
+    #[test]
+    fn poseidon2_hash() {
+        for len in 0..4 {
+            let message: Vec<_> = (0..len).map(|_| Fp::random(rand::rngs::OsRng)).collect();
+            let output =
+                poseidon::Hash::<_, Poseidon2Pow5T3, _, 3, 2>::hash(VariableLength, &message);
+
+            let k = 8;
+            let circuit = HashCircuit::<Poseidon2Pow5T3, 3, 2> {
+                message: Some(message),
+                len,
+                output: Some(output),
+                _spec: PhantomData,
+            };
+            let prover = MockProver::run(k, &circuit, vec![]).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn poseidon2_hash_wide() {
+        let message: Vec<_> = (0..5).map(|_| Fp::random(rand::rngs::OsRng)).collect();
+        let output = poseidon::Hash::<_, Poseidon2Pow5T4, _, 4, 3>::hash(VariableLength, &message);
+
+        let k = 8;
+        let circuit = HashCircuit::<Poseidon2Pow5T4, 4, 3> {
+            message: Some(message),
+            len: 5,
+            output: Some(output),
+            _spec: PhantomData,
+        };
+        let prover = MockProver::run(k, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn poseidon2_hash_wrong_output() {
+        let message = vec![Fp::from_u64(1), Fp::from_u64(2)];
+        let output = poseidon::Hash::<_, Poseidon2Pow5T3, _, 3, 2>::hash(VariableLength, &message)
+            + Fp::one();
+
+        let k = 8;
+        let circuit = HashCircuit::<Poseidon2Pow5T3, 3, 2> {
+            message: Some(message),
+            len: 2,
+            output: Some(output),
+            _spec: PhantomData,
+        };
+        let prover = MockProver::run(k, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
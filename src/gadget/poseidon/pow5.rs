@@ -0,0 +1,1185 @@
+use std::convert::TryInto;
+use std::marker::PhantomData;
+
+use super::{
+    merkle::SwapConfig,
+    sponge::{SpongeConfig, StateWord},
+    Hash, MerkleInstructions, PoseidonDuplexInstructions, PoseidonInstructions, SpongeState,
+};
+use crate::{
+    arithmetic::FieldExt,
+    circuit::{Chip, Layouter, Region},
+    gadget::{
+        hash,
+        merkle::{
+            HashInstructions, MerklePathInstructions, PlaceConfig, SparseConfig,
+            SparseMerkleInstructions,
+        },
+        utilities::AssignedValue,
+    },
+    plonk::{Advice, Column, ConstraintSystem, Error, Expression, Selector},
+    poly::Rotation,
+    primitives::poseidon::{
+        Mds, MerkleNode, PermutationType, SboxType, Spec, State, VariableLength,
+    },
+};
+
+/// Configuration for a [`Pow5Chip`].
+#[derive(Clone, Debug)]
+pub struct Pow5Config<F: FieldExt, const WIDTH: usize, const RATE: usize> {
+    pub(crate) sponge: SpongeConfig<WIDTH, RATE>,
+    partial_sbox: Column<Advice>,
+    s_full: Selector,
+    s_partial: Selector,
+
+    half_full_rounds: usize,
+    half_partial_rounds: usize,
+    round_constants: Vec<[F; WIDTH]>,
+    m_reg: Mds<F, WIDTH>,
+    merkle: Option<SwapConfig>,
+    place: Option<PlaceConfig>,
+    sparse: Option<SparseConfig>,
+}
+
+/// A Poseidon chip using an $x^5$ S-Box, with a width of `WIDTH` and a sponge rate of
+/// `RATE`.
+///
+/// The chip applies two partial rounds per row, so the [`Spec`] it is configured with
+/// must use an even number of partial rounds.
+#[derive(Debug)]
+pub struct Pow5Chip<F: FieldExt, const WIDTH: usize, const RATE: usize> {
+    _marker: PhantomData<F>,
+}
+
+/// A [`Pow5Chip`] with a width of 3, suitable for a 2:1 reduction.
+pub type Pow5T3Chip<F> = Pow5Chip<F, 3, 2>;
+
+/// Configuration for a [`Pow5T3Chip`].
+pub type Pow5T3Config<F> = Pow5Config<F, 3, 2>;
+
+impl<F: FieldExt, const WIDTH: usize, const RATE: usize> Pow5Chip<F, WIDTH, RATE> {
+    /// Configures this chip for use in a circuit.
+    ///
+    /// The chip uses the given `state` columns for its inputs and outputs, and places
+    /// all of them in a permutation. `partial_sbox` is used to witness the S-box output
+    /// of the first of each pair of partial rounds.
+    pub fn configure<S: Spec<F, WIDTH, RATE>>(
+        meta: &mut ConstraintSystem<F>,
+        state: [Column<Advice>; WIDTH],
+        partial_sbox: Column<Advice>,
+    ) -> Pow5Config<F, WIDTH, RATE> {
+        assert_eq!(S::sbox_type(), SboxType::Pow);
+        assert_eq!(S::permutation_type(), PermutationType::Poseidon);
+
+        // Generate constants for the Poseidon permutation.
+        // This gadget requires R_F and R_P to be even.
+        assert!(S::full_rounds() & 1 == 0);
+        assert!(S::partial_rounds() & 1 == 0);
+        let half_full_rounds = S::full_rounds() / 2;
+        let half_partial_rounds = S::partial_rounds() / 2;
+        let (round_constants, m_reg, m_inv) = S::constants();
+
+        let sponge = SpongeConfig::configure(meta, state);
+        let rc_a = sponge.rc_a;
+        let rc_b = sponge.rc_b;
+
+        let s_full = meta.selector();
+        let s_partial = meta.selector();
+
+        let pow_5 = |v: Expression<F>| {
+            let v2 = v.clone() * v.clone();
+            v2.clone() * v2 * v
+        };
+
+        for idx in 0..WIDTH {
+            meta.create_gate("full round", |meta| {
+                let s_full = meta.query_selector(s_full, Rotation::cur());
+
+                let expr = (0..WIDTH)
+                    .map(|j| {
+                        let state_cur = meta.query_advice(state[j], Rotation::cur());
+                        let rc_a = meta.query_fixed(rc_a[j], Rotation::cur());
+                        pow_5(state_cur + rc_a) * m_reg[idx][j]
+                    })
+                    .fold(Expression::Constant(F::zero()), |acc, term| acc + term);
+                let state_next = meta.query_advice(state[idx], Rotation::next());
+
+                s_full * (expr - state_next)
+            });
+        }
+
+        meta.create_gate("partial round S-box", |meta| {
+            let cur_0 = meta.query_advice(state[0], Rotation::cur());
+            let mid_0 = meta.query_advice(partial_sbox, Rotation::cur());
+            let rc_a0 = meta.query_fixed(rc_a[0], Rotation::cur());
+            let s_partial = meta.query_selector(s_partial, Rotation::cur());
+
+            s_partial * (pow_5(cur_0 + rc_a0) - mid_0)
+        });
+
+        // The state after the first partial round of the pair, expressed in terms of the
+        // current row.
+        let mid = |idx: usize, meta: &mut ConstraintSystem<F>| {
+            let mid_0 = meta.query_advice(partial_sbox, Rotation::cur());
+            (1..WIDTH).fold(mid_0 * m_reg[idx][0], |acc, j| {
+                let cur = meta.query_advice(state[j], Rotation::cur());
+                let rc_a = meta.query_fixed(rc_a[j], Rotation::cur());
+                acc + (cur + rc_a) * m_reg[idx][j]
+            })
+        };
+
+        // The state after the S-box layer of the second partial round of the pair,
+        // expressed in terms of the next row.
+        let next = |idx: usize, meta: &mut ConstraintSystem<F>| {
+            (0..WIDTH)
+                .map(|j| meta.query_advice(state[j], Rotation::next()) * m_inv[idx][j])
+                .fold(Expression::Constant(F::zero()), |acc, term| acc + term)
+        };
+
+        meta.create_gate("partial round", |meta| {
+            let rc_b0 = meta.query_fixed(rc_b[0], Rotation::cur());
+            let s_partial = meta.query_selector(s_partial, Rotation::cur());
+
+            s_partial * (pow_5(mid(0, meta) + rc_b0) - next(0, meta))
+        });
+        for idx in 1..WIDTH {
+            meta.create_gate("partial round", |meta| {
+                let rc_b = meta.query_fixed(rc_b[idx], Rotation::cur());
+                let s_partial = meta.query_selector(s_partial, Rotation::cur());
+
+                s_partial * (mid(idx, meta) + rc_b - next(idx, meta))
+            });
+        }
+
+        Pow5Config {
+            sponge,
+            partial_sbox,
+            s_full,
+            s_partial,
+            half_full_rounds,
+            half_partial_rounds,
+            round_constants,
+            m_reg,
+            merkle: None,
+            place: None,
+            sparse: None,
+        }
+    }
+}
+
+impl<F: FieldExt, const WIDTH: usize> Pow5Chip<F, WIDTH, 2> {
+    /// Configures the additional gate that is needed to use this chip with the
+    /// [`MerklePath`](super::MerklePath) gadget.
+    pub fn configure_merkle(meta: &mut ConstraintSystem<F>, config: &mut Pow5Config<F, WIDTH, 2>) {
+        let state = &config.sponge.state;
+        config.merkle = Some(SwapConfig::configure(
+            meta,
+            [state[0], state[1], state[2]],
+            config.sponge.perm.clone(),
+        ));
+    }
+
+    /// Configures the additional gates that are needed to use this chip with the
+    /// [`SparseMerklePath`](crate::gadget::merkle::SparseMerklePath) gadget.
+    pub fn configure_sparse_merkle(
+        meta: &mut ConstraintSystem<F>,
+        config: &mut Pow5Config<F, WIDTH, 2>,
+    ) {
+        let state = &config.sponge.state;
+        config.sparse = Some(SparseConfig::configure(
+            meta,
+            [state[0], state[1], state[2]],
+            config.sponge.perm.clone(),
+        ));
+    }
+}
+
+impl<F: FieldExt, const WIDTH: usize, const RATE: usize> Pow5Chip<F, WIDTH, RATE> {
+    /// Configures the additional gate that is needed to use this chip with the
+    /// hash-generic [`MerklePath`](crate::gadget::merkle::MerklePath) gadget, for trees
+    /// of arity `RATE`.
+    pub fn configure_merkle_path(
+        meta: &mut ConstraintSystem<F>,
+        config: &mut Pow5Config<F, WIDTH, RATE>,
+    ) {
+        let state = &config.sponge.state;
+        config.place = Some(PlaceConfig::configure(
+            meta,
+            [state[0], state[1], state[2]],
+            config.sponge.perm.clone(),
+            RATE,
+        ));
+    }
+}
+
+impl<F: FieldExt, const WIDTH: usize, const RATE: usize> Chip for Pow5Chip<F, WIDTH, RATE> {
+    type Config = Pow5Config<F, WIDTH, RATE>;
+    type Loaded = ();
+    type Field = F;
+
+    fn load(_layouter: &mut impl Layouter<Self>) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<F: FieldExt, const WIDTH: usize, const RATE: usize> PoseidonInstructions<WIDTH>
+    for Pow5Chip<F, WIDTH, RATE>
+{
+    type Word = StateWord<F>;
+
+    fn permute(
+        layouter: &mut impl Layouter<Self>,
+        initial_state: &State<Self::Word, WIDTH>,
+    ) -> Result<State<Self::Word, WIDTH>, Error> {
+        layouter.config().clone().permute(layouter, initial_state)
+    }
+}
+
+impl<F: FieldExt, const WIDTH: usize, const RATE: usize> PoseidonDuplexInstructions<WIDTH, RATE>
+    for Pow5Chip<F, WIDTH, RATE>
+{
+    fn load_message(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<F>,
+    ) -> Result<Self::Word, Error> {
+        let config = layouter.config().sponge.clone();
+        config.load_message(layouter, value)
+    }
+
+    fn initial_state(
+        layouter: &mut impl Layouter<Self>,
+        initial_capacity_element: F,
+    ) -> Result<State<Self::Word, WIDTH>, Error> {
+        let config = layouter.config().sponge.clone();
+        config.initial_state(layouter, initial_capacity_element)
+    }
+
+    fn pad_and_add(
+        layouter: &mut impl Layouter<Self>,
+        initial_state: &State<Self::Word, WIDTH>,
+        input: &SpongeState<Self::Word, F, RATE>,
+    ) -> Result<State<Self::Word, WIDTH>, Error> {
+        let config = layouter.config().sponge.clone();
+        config.pad_and_add(layouter, initial_state, input)
+    }
+
+    fn get_output(state: &State<Self::Word, WIDTH>) -> [Self::Word; RATE] {
+        state[..RATE].try_into().unwrap()
+    }
+
+    fn absorb_blocks(
+        layouter: &mut impl Layouter<Self>,
+        initial_state: &State<Self::Word, WIDTH>,
+        inputs: &[SpongeState<Self::Word, F, RATE>],
+    ) -> Result<State<Self::Word, WIDTH>, Error> {
+        layouter
+            .config()
+            .clone()
+            .absorb_blocks(layouter, initial_state, inputs)
+    }
+}
+
+impl<F: FieldExt, const WIDTH: usize, const RATE: usize> Pow5Config<F, WIDTH, RATE> {
+    /// Applies the permutation to `initial_state`.
+    ///
+    /// This is generic over the chip, so that chips that include a [`Pow5Config`] can
+    /// implement [`PoseidonInstructions`] with it.
+    pub(crate) fn permute<C: Chip<Field = F>>(
+        &self,
+        layouter: &mut impl Layouter<C>,
+        initial_state: &State<StateWord<F>, WIDTH>,
+    ) -> Result<State<StateWord<F>, WIDTH>, Error> {
+        layouter.assign_region(
+            || "permute state",
+            |mut region| {
+                // Load the initial state into this region.
+                let state = self.sponge.load_state(&mut region, 0, initial_state)?;
+
+                permute_in_region(&mut region, self, 0, state)
+            },
+        )
+    }
+
+    /// Adds each of `inputs` to the rate portion of the state in turn, applying the
+    /// permutation after each one, within a single region.
+    pub(crate) fn absorb_blocks<C: Chip<Field = F>>(
+        &self,
+        layouter: &mut impl Layouter<C>,
+        initial_state: &State<StateWord<F>, WIDTH>,
+        inputs: &[SpongeState<StateWord<F>, F, RATE>],
+    ) -> Result<State<StateWord<F>, WIDTH>, Error> {
+        if inputs.is_empty() {
+            return Ok(*initial_state);
+        }
+
+        let permutation_rows = 2 * self.half_full_rounds + self.half_partial_rounds;
+
+        layouter.assign_region(
+            || "absorb blocks",
+            |mut region| {
+                // Load the initial state into this region. Each block then takes the
+                // state from the last row of the previous block, so only the initial
+                // state needs to be copied in.
+                let state = self.sponge.load_state(&mut region, 0, initial_state)?;
+
+                inputs
+                    .iter()
+                    .enumerate()
+                    .try_fold(state, |state, (i, input)| {
+                        let offset = i * (permutation_rows + 2);
+                        let state = self.sponge.assign_pad_and_add(
+                            &mut region,
+                            offset + 1,
+                            &state,
+                            input,
+                        )?;
+                        permute_in_region(&mut region, self, offset + 2, state)
+                    })
+            },
+        )
+    }
+}
+
+/// Assigns the permutation of `state`, which must already be assigned to the state
+/// columns of `region` at `offset`, and returns the output state.
+fn permute_in_region<F: FieldExt, C: Chip<Field = F>, const WIDTH: usize, const RATE: usize>(
+    region: &mut Region<C>,
+    config: &Pow5Config<F, WIDTH, RATE>,
+    offset: usize,
+    state: State<StateWord<F>, WIDTH>,
+) -> Result<State<StateWord<F>, WIDTH>, Error> {
+    let state = (0..config.half_full_rounds).try_fold(Pow5State(state), |state, r| {
+        state.full_round(region, config, r, offset + r)
+    })?;
+
+    let state = (0..config.half_partial_rounds).try_fold(state, |state, r| {
+        state.partial_round(
+            region,
+            config,
+            config.half_full_rounds + 2 * r,
+            offset + config.half_full_rounds + r,
+        )
+    })?;
+
+    let state = (0..config.half_full_rounds).try_fold(state, |state, r| {
+        state.full_round(
+            region,
+            config,
+            config.half_full_rounds + 2 * config.half_partial_rounds + r,
+            offset + config.half_full_rounds + config.half_partial_rounds + r,
+        )
+    })?;
+
+    Ok(state.0)
+}
+
+impl<F: FieldExt, const WIDTH: usize> MerkleInstructions<WIDTH> for Pow5Chip<F, WIDTH, 2> {
+    fn swap(
+        layouter: &mut impl Layouter<Self>,
+        node: Self::Word,
+        sibling: Option<F>,
+        swap: Option<bool>,
+    ) -> Result<[Self::Word; 2], Error> {
+        let config = layouter
+            .config()
+            .merkle
+            .clone()
+            .ok_or(Error::SynthesisError)?;
+        config.swap(layouter, node, sibling, swap)
+    }
+
+    fn constrain_equal(
+        layouter: &mut impl Layouter<Self>,
+        a: Self::Word,
+        b: Self::Word,
+    ) -> Result<(), Error> {
+        let config = layouter
+            .config()
+            .merkle
+            .clone()
+            .ok_or(Error::SynthesisError)?;
+        config.constrain_equal(layouter, a, b)
+    }
+}
+
+/// Nodes are hashed in the [`MerkleNode`] domain for their layer, so `children` must
+/// contain `RATE` nodes.
+impl<F: FieldExt, const WIDTH: usize, const RATE: usize> HashInstructions
+    for Pow5Chip<F, WIDTH, RATE>
+{
+    type Node = StateWord<F>;
+    type NodeValue = F;
+
+    fn hash_children(
+        layouter: &mut impl Layouter<Self>,
+        layer: usize,
+        children: &[Self::Node],
+    ) -> Result<Self::Node, Error> {
+        Hash::<Self, _, WIDTH, RATE>::hash(
+            layouter.namespace(|| "hash"),
+            MerkleNode {
+                layer: layer as u32,
+            },
+            children,
+        )
+    }
+}
+
+/// Words are absorbed into a sponge in the [`VariableLength`] domain.
+impl<F: FieldExt, const WIDTH: usize, const RATE: usize> hash::HashInstructions<F>
+    for Pow5Chip<F, WIDTH, RATE>
+{
+    type Word = StateWord<F>;
+    type State = Hash<Self, VariableLength, WIDTH, RATE>;
+    type Output = StateWord<F>;
+
+    fn witness_word(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<F>,
+    ) -> Result<Self::Word, Error> {
+        Self::load_message(layouter, value)
+    }
+
+    fn init(layouter: &mut impl Layouter<Self>) -> Result<Self::State, Error> {
+        Hash::init(layouter.namespace(|| "init"), VariableLength)
+    }
+
+    fn absorb(
+        layouter: &mut impl Layouter<Self>,
+        state: &mut Self::State,
+        words: &[Self::Word],
+    ) -> Result<(), Error> {
+        state.update(layouter.namespace(|| "absorb"), words)
+    }
+
+    fn finalize(
+        layouter: &mut impl Layouter<Self>,
+        state: Self::State,
+    ) -> Result<Self::Output, Error> {
+        state.finalize(layouter.namespace(|| "finalize"))
+    }
+}
+
+impl<F: FieldExt, const WIDTH: usize, const RATE: usize> MerklePathInstructions
+    for Pow5Chip<F, WIDTH, RATE>
+{
+    fn witness_node(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<F>,
+    ) -> Result<Self::Node, Error> {
+        Self::load_message(layouter, value)
+    }
+
+    fn place(
+        layouter: &mut impl Layouter<Self>,
+        node: &Self::Node,
+        siblings: &[Option<F>],
+        index: Option<usize>,
+    ) -> Result<Vec<Self::Node>, Error> {
+        let config = layouter
+            .config()
+            .place
+            .clone()
+            .ok_or(Error::SynthesisError)?;
+        let children = config.place(layouter, node.var, node.value, siblings, index)?;
+        Ok(children
+            .into_iter()
+            .map(|(var, value)| StateWord { var, value })
+            .collect())
+    }
+
+    fn constrain_equal(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Node,
+        b: &Self::Node,
+    ) -> Result<(), Error> {
+        let perm = layouter.config().sponge.perm.clone();
+        layouter.assign_region(
+            || "constrain equal",
+            |mut region| region.constrain_equal(&perm, a.var, b.var),
+        )
+    }
+}
+
+impl<F: FieldExt, const WIDTH: usize> SparseMerkleInstructions for Pow5Chip<F, WIDTH, 2> {
+    type Bit = AssignedValue<F>;
+
+    fn witness_key(
+        layouter: &mut impl Layouter<Self>,
+        key: Option<u64>,
+        depth: usize,
+    ) -> Result<(Self::Node, Vec<Self::Bit>), Error> {
+        let config = layouter
+            .config()
+            .sparse
+            .clone()
+            .ok_or(Error::SynthesisError)?;
+        let (key, bits) = config.witness_key(layouter, key, depth)?;
+        Ok((key.into(), bits))
+    }
+
+    fn empty_leaf(layouter: &mut impl Layouter<Self>) -> Result<Self::Node, Error> {
+        let config = layouter
+            .config()
+            .sparse
+            .clone()
+            .ok_or(Error::SynthesisError)?;
+        config.zero(layouter).map(Into::into)
+    }
+
+    fn swap(
+        layouter: &mut impl Layouter<Self>,
+        node: &Self::Node,
+        sibling: &Self::Node,
+        bit: &Self::Bit,
+    ) -> Result<[Self::Node; 2], Error> {
+        let config = layouter
+            .config()
+            .sparse
+            .clone()
+            .ok_or(Error::SynthesisError)?;
+        let [left, right] = config.swap(layouter, &(*node).into(), &(*sibling).into(), bit)?;
+        Ok([left.into(), right.into()])
+    }
+}
+
+#[derive(Debug)]
+struct Pow5State<F: FieldExt, const WIDTH: usize>([StateWord<F>; WIDTH]);
+
+impl<F: FieldExt, const WIDTH: usize> Pow5State<F, WIDTH> {
+    fn full_round<C: Chip<Field = F>, const RATE: usize>(
+        self,
+        region: &mut Region<C>,
+        config: &Pow5Config<F, WIDTH, RATE>,
+        round: usize,
+        offset: usize,
+    ) -> Result<Self, Error> {
+        Self::round(region, config, round, offset, config.s_full, |_| {
+            let q = self
+                .0
+                .iter()
+                .enumerate()
+                .map(|(idx, word)| word.value.map(|v| v + config.round_constants[round][idx]))
+                .collect::<Option<Vec<_>>>();
+            let r = q.map(|q| {
+                q.into_iter()
+                    .map(|q| q.pow(&[5, 0, 0, 0]))
+                    .collect::<Vec<_>>()
+            });
+
+            Ok((round + 1, mds_mul(&config.m_reg, r.as_deref())))
+        })
+    }
+
+    fn partial_round<C: Chip<Field = F>, const RATE: usize>(
+        self,
+        region: &mut Region<C>,
+        config: &Pow5Config<F, WIDTH, RATE>,
+        round: usize,
+        offset: usize,
+    ) -> Result<Self, Error> {
+        Self::round(region, config, round, offset, config.s_partial, |region| {
+            let m = &config.m_reg;
+            let p = self
+                .0
+                .iter()
+                .map(|word| word.value)
+                .collect::<Option<Vec<_>>>();
+
+            let r = p.map(|p| {
+                let r_0 = (p[0] + config.round_constants[round][0]).pow(&[5, 0, 0, 0]);
+                let r_i = p[1..]
+                    .iter()
+                    .enumerate()
+                    .map(|(i, p_i)| *p_i + config.round_constants[round][i + 1]);
+                std::iter::once(r_0).chain(r_i).collect::<Vec<_>>()
+            });
+
+            region.assign_advice(
+                || format!("round_{} partial_sbox", round),
+                config.partial_sbox,
+                offset,
+                || r.as_ref().map(|r| r[0]).ok_or(Error::SynthesisError),
+            )?;
+
+            let p_mid = mds_mul(m, r.as_deref());
+
+            // Load the second round constants.
+            let mut load_round_constant = |i: usize| {
+                region.assign_fixed(
+                    || format!("round_{} rc_{}", round + 1, i),
+                    config.sponge.rc_b[i],
+                    offset,
+                    || Ok(config.round_constants[round + 1][i]),
+                )
+            };
+            for i in 0..WIDTH {
+                load_round_constant(i)?;
+            }
+
+            let r_mid = p_mid.iter().cloned().collect::<Option<Vec<_>>>().map(|p| {
+                let r_0 = (p[0] + config.round_constants[round + 1][0]).pow(&[5, 0, 0, 0]);
+                let r_i = p[1..]
+                    .iter()
+                    .enumerate()
+                    .map(|(i, p_i)| *p_i + config.round_constants[round + 1][i + 1]);
+                std::iter::once(r_0).chain(r_i).collect::<Vec<_>>()
+            });
+
+            Ok((round + 2, mds_mul(m, r_mid.as_deref())))
+        })
+    }
+
+    fn round<C: Chip<Field = F>, const RATE: usize>(
+        region: &mut Region<C>,
+        config: &Pow5Config<F, WIDTH, RATE>,
+        round: usize,
+        offset: usize,
+        round_gate: Selector,
+        round_fn: impl FnOnce(&mut Region<C>) -> Result<(usize, [Option<F>; WIDTH]), Error>,
+    ) -> Result<Self, Error> {
+        // Enable the required gate.
+        round_gate.enable(region, offset)?;
+
+        // Load the round constants.
+        let mut load_round_constant = |i: usize| {
+            region.assign_fixed(
+                || format!("round_{} rc_{}", round, i),
+                config.sponge.rc_a[i],
+                offset,
+                || Ok(config.round_constants[round][i]),
+            )
+        };
+        for i in 0..WIDTH {
+            load_round_constant(i)?;
+        }
+
+        // Compute the next round's state.
+        let (next_round, next_state) = round_fn(region)?;
+
+        let mut next_state_word = |i: usize| {
+            let value = next_state[i];
+            let var = region.assign_advice(
+                || format!("round_{} state_{}", next_round, i),
+                config.sponge.state[i],
+                offset + 1,
+                || value.ok_or(Error::SynthesisError),
+            )?;
+            Ok(StateWord { var, value })
+        };
+
+        let next_state: Result<Vec<_>, Error> = (0..WIDTH).map(&mut next_state_word).collect();
+        Ok(Pow5State(next_state?.try_into().unwrap()))
+    }
+}
+
+/// Multiplies the given (possibly unknown) state by the MDS matrix.
+fn mds_mul<F: FieldExt, const WIDTH: usize>(
+    m: &Mds<F, WIDTH>,
+    state: Option<&[F]>,
+) -> [Option<F>; WIDTH] {
+    let mut next_state = [None; WIDTH];
+    for (next, m_i) in next_state.iter_mut().zip(m.iter()) {
+        *next = state.map(|state| {
+            m_i.iter()
+                .zip(state.iter())
+                .fold(F::zero(), |acc, (m_ij, s_j)| acc + *m_ij * s_j)
+        });
+    }
+    next_state
+}
+
+#[cfg(test)]
+mod tests {
+    use ff::Field;
+    use std::convert::TryInto;
+    use std::marker::PhantomData;
+
+    use super::{PoseidonInstructions, Pow5Chip, Pow5Config, StateWord};
+    use crate::{
+        arithmetic::FieldExt,
+        circuit::{layouter, Layouter},
+        dev::MockProver,
+        gadget::{
+            merkle::{MerklePath, MerklePathInstructions},
+            poseidon::{
+                sponge::SpongeConfig,
+                tests::{HashChip, HashCircuit},
+                Duplex, Hash, PoseidonDuplexInstructions,
+            },
+        },
+        pasta::Fp,
+        plonk::{Assignment, Circuit, ConstraintSystem, Error},
+        primitives::poseidon::{
+            self, ConstantLength, Domain, MerkleNode, P128Pow5T3, Spec, VariableLength,
+        },
+    };
+
+    /// A width-5 specification, used to exercise wider permutations.
+    #[derive(Debug)]
+    struct P128Pow5T5;
+
+    impl<F: FieldExt> Spec<F, 5, 4> for P128Pow5T5 {
+        fn full_rounds() -> usize {
+            8
+        }
+
+        fn partial_rounds() -> usize {
+            56
+        }
+
+        fn sbox(val: F) -> F {
+            val.pow_vartime([5])
+        }
+
+        fn secure_mds() -> usize {
+            0
+        }
+    }
+
+    struct PermuteCircuit<S: Spec<Fp, WIDTH, RATE>, const WIDTH: usize, const RATE: usize>(
+        PhantomData<S>,
+    );
+
+    impl<S: Spec<Fp, WIDTH, RATE>, const WIDTH: usize, const RATE: usize> Circuit<Fp>
+        for PermuteCircuit<S, WIDTH, RATE>
+    {
+        type Config = Pow5Config<Fp, WIDTH, RATE>;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Pow5Config<Fp, WIDTH, RATE> {
+            let state = (0..WIDTH)
+                .map(|_| meta.advice_column())
+                .collect::<Vec<_>>()
+                .try_into()
+                .unwrap();
+            let partial_sbox = meta.advice_column();
+
+            Pow5Chip::configure::<S>(meta, state, partial_sbox)
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            config: Pow5Config<Fp, WIDTH, RATE>,
+        ) -> Result<(), Error> {
+            let mut layouter =
+                layouter::SingleChip::<Pow5Chip<Fp, WIDTH, RATE>, _>::new(cs, config)?;
+            let config = layouter.config().clone();
+
+            let initial_state = layouter.assign_region(
+                || "prepare initial state",
+                |mut region| {
+                    let mut state_word = |i: usize| {
+                        let value = Some(Fp::from(i as u64));
+                        let var = region.assign_advice(
+                            || format!("load state_{}", i),
+                            config.sponge.state[i],
+                            0,
+                            || value.ok_or(Error::SynthesisError),
+                        )?;
+                        Ok(StateWord { var, value })
+                    };
+
+                    let state: Result<Vec<_>, Error> = (0..WIDTH).map(&mut state_word).collect();
+                    Ok(state?.try_into().unwrap())
+                },
+            )?;
+
+            let final_state = Pow5Chip::permute(&mut layouter, &initial_state)?;
+
+            // For the purpose of this test, compute the real final state inline.
+            let mut expected_final_state = [Fp::zero(); WIDTH];
+            for (i, word) in expected_final_state.iter_mut().enumerate() {
+                *word = Fp::from(i as u64);
+            }
+            let (round_constants, mds, _) = S::constants();
+            poseidon::permute::<_, S, WIDTH, RATE>(
+                &mut expected_final_state,
+                &mds,
+                &round_constants,
+            );
+
+            layouter.assign_region(
+                || "constrain final state",
+                |mut region| {
+                    for i in 0..WIDTH {
+                        let var = region.assign_advice(
+                            || format!("load final_state_{}", i),
+                            config.sponge.state[i],
+                            0,
+                            || Ok(expected_final_state[i]),
+                        )?;
+                        region.constrain_equal(&config.sponge.perm, final_state[i].var, var)?;
+                    }
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    #[test]
+    fn poseidon_permute() {
+        let k = 6;
+        let circuit = PermuteCircuit::<P128Pow5T3, 3, 2>(PhantomData);
+        let prover = MockProver::run(k, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()))
+    }
+
+    #[test]
+    fn poseidon_permute_wide() {
+        let k = 6;
+        let circuit = PermuteCircuit::<P128Pow5T5, 5, 4>(PhantomData);
+        let prover = MockProver::run(k, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()))
+    }
+
+    impl<S: Spec<Fp, WIDTH, RATE>, const WIDTH: usize, const RATE: usize> HashChip<S, WIDTH, RATE>
+        for Pow5Chip<Fp, WIDTH, RATE>
+    {
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Pow5Config<Fp, WIDTH, RATE> {
+            PermuteCircuit::<S, WIDTH, RATE>::configure(meta)
+        }
+
+        fn sponge(config: &Pow5Config<Fp, WIDTH, RATE>) -> &SpongeConfig<WIDTH, RATE> {
+            &config.sponge
+        }
+    }
+
+    struct HashNCircuit {
+        message: Option<Vec<Fp>>,
+        len: usize,
+        // For the purpose of this test, witness the results.
+        outputs: Vec<Option<Fp>>,
+    }
+
+    impl Circuit<Fp> for HashNCircuit {
+        type Config = Pow5Config<Fp, 3, 2>;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Pow5Config<Fp, 3, 2> {
+            PermuteCircuit::<P128Pow5T3, 3, 2>::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            config: Pow5Config<Fp, 3, 2>,
+        ) -> Result<(), Error> {
+            let mut layouter = layouter::SingleChip::<Pow5Chip<Fp, 3, 2>, _>::new(cs, config)?;
+            let config = layouter.config().clone();
+
+            let message = (0..self.len)
+                .map(|i| {
+                    Pow5Chip::load_message(
+                        &mut layouter,
+                        self.message.as_ref().map(|message| message[i]),
+                    )
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+
+            let outputs = Hash::<_, _, 3, 2>::hash_n(
+                layouter.namespace(|| "hash"),
+                VariableLength,
+                &message,
+                self.outputs.len(),
+            )?;
+
+            for (output, expected) in outputs.iter().zip(self.outputs.iter()) {
+                layouter.assign_region(
+                    || "constrain output",
+                    |mut region| {
+                        let expected_var = region.assign_advice(
+                            || "load output",
+                            config.sponge.state[0],
+                            0,
+                            || expected.ok_or(Error::SynthesisError),
+                        )?;
+                        region.constrain_equal(&config.sponge.perm, output.var, expected_var)
+                    },
+                )?;
+            }
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn poseidon_hash_multiple_outputs() {
+        let message = vec![Fp::from_u64(1), Fp::from_u64(2), Fp::from_u64(3)];
+        let outputs = poseidon::Hash::<_, P128Pow5T3, _, 3, 2>::hash_n(VariableLength, &message, 5);
+
+        let k = 9;
+        let circuit = HashNCircuit {
+            message: Some(message.clone()),
+            len: 3,
+            outputs: outputs.iter().copied().map(Some).collect(),
+        };
+        let prover = MockProver::run(k, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // Outputs after the first squeezed block depend on the interleaved permutation.
+        let mut wrong = outputs;
+        wrong[2] = wrong[0];
+        let circuit = HashNCircuit {
+            message: Some(message),
+            len: 3,
+            outputs: wrong.into_iter().map(Some).collect(),
+        };
+        let prover = MockProver::run(k, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn poseidon_hash() {
+        for len in 0..5 {
+            let message: Vec<_> = (0..len).map(|_| Fp::random(rand::rngs::OsRng)).collect();
+            let output = poseidon::hash(&message);
+
+            let k = 8;
+            let circuit = HashCircuit::<Pow5Chip<Fp, 3, 2>, P128Pow5T3, _, 3, 2>::new(
+                VariableLength,
+                message,
+            );
+            let prover = MockProver::run(k, &circuit, vec![vec![output]]).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn poseidon_hash_long_message() {
+        let message: Vec<_> = (0..41).map(|_| Fp::random(rand::rngs::OsRng)).collect();
+        let output = poseidon::hash(&message);
+
+        let k = 10;
+        let circuit =
+            HashCircuit::<Pow5Chip<Fp, 3, 2>, P128Pow5T3, _, 3, 2>::new(VariableLength, message);
+        let prover = MockProver::run(k, &circuit, vec![vec![output]]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    /// Interleaves single and chunked absorption with squeezing.
+    struct DuplexCircuit {
+        message: Option<Vec<Fp>>,
+        // For the purpose of this test, witness the results.
+        outputs: Option<[Fp; 3]>,
+    }
+
+    impl Circuit<Fp> for DuplexCircuit {
+        type Config = Pow5Config<Fp, 3, 2>;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Pow5Config<Fp, 3, 2> {
+            PermuteCircuit::<P128Pow5T3, 3, 2>::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            config: Pow5Config<Fp, 3, 2>,
+        ) -> Result<(), Error> {
+            let mut layouter = layouter::SingleChip::<Pow5Chip<Fp, 3, 2>, _>::new(cs, config)?;
+            let config = layouter.config().clone();
+
+            let message = (0..8)
+                .map(|i| {
+                    Pow5Chip::load_message(
+                        &mut layouter,
+                        self.message.as_ref().map(|message| message[i]),
+                    )
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+
+            let mut duplex = Duplex::<_, 3, 2>::new(layouter.namespace(|| "new"), Fp::zero())?;
+            duplex.absorb(layouter.namespace(|| "absorb"), message[0])?;
+            duplex.absorb_chunk(layouter.namespace(|| "absorb chunk"), &message[1..4])?;
+            let a = duplex.squeeze(layouter.namespace(|| "squeeze"))?;
+            duplex.absorb_chunk(layouter.namespace(|| "absorb chunk"), &message[4..7])?;
+            duplex.absorb(layouter.namespace(|| "absorb"), message[7])?;
+            let b = duplex.squeeze(layouter.namespace(|| "squeeze"))?;
+            let c = duplex.squeeze(layouter.namespace(|| "squeeze"))?;
+
+            for (i, output) in [a, b, c].iter().enumerate() {
+                layouter.assign_region(
+                    || "constrain output",
+                    |mut region| {
+                        let expected_var = region.assign_advice(
+                            || "load output",
+                            config.sponge.state[0],
+                            0,
+                            || {
+                                self.outputs
+                                    .map(|outputs| outputs[i])
+                                    .ok_or(Error::SynthesisError)
+                            },
+                        )?;
+                        region.constrain_equal(&config.sponge.perm, output.var, expected_var)
+                    },
+                )?;
+            }
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn poseidon_duplex_chunks() {
+        let message: Vec<_> = (0..8).map(|_| Fp::random(rand::rngs::OsRng)).collect();
+
+        let mut duplex = poseidon::Duplex::<_, P128Pow5T3, 3, 2>::new(Fp::zero());
+        for value in &message[..4] {
+            duplex.absorb(*value);
+        }
+        let a = duplex.squeeze();
+        for value in &message[4..] {
+            duplex.absorb(*value);
+        }
+        let b = duplex.squeeze();
+        let c = duplex.squeeze();
+
+        let k = 9;
+        let circuit = DuplexCircuit {
+            message: Some(message.clone()),
+            outputs: Some([a, b, c]),
+        };
+        let prover = MockProver::run(k, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        let circuit = DuplexCircuit {
+            message: Some(message),
+            outputs: Some([a, c, b]),
+        };
+        let prover = MockProver::run(k, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn poseidon_hash_wide() {
+        let message: Vec<_> = (0..6).map(|_| Fp::random(rand::rngs::OsRng)).collect();
+        let output = poseidon::Hash::<_, P128Pow5T5, _, 5, 4>::hash(VariableLength, &message);
+
+        let k = 8;
+        let circuit =
+            HashCircuit::<Pow5Chip<Fp, 5, 4>, P128Pow5T5, _, 5, 4>::new(VariableLength, message);
+        let prover = MockProver::run(k, &circuit, vec![vec![output]]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn poseidon_hash_wrong_output() {
+        let message = vec![Fp::from_u64(1), Fp::from_u64(2)];
+        let output =
+            poseidon::Hash::<_, P128Pow5T3, _, 3, 2>::hash(VariableLength, &message) + Fp::one();
+
+        let k = 8;
+        let circuit =
+            HashCircuit::<Pow5Chip<Fp, 3, 2>, P128Pow5T3, _, 3, 2>::new(VariableLength, message);
+        let prover = MockProver::run(k, &circuit, vec![vec![output]]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn poseidon_hash_domains() {
+        fn check<D: Domain<Fp, 2>>(domain: D) {
+            let message = vec![Fp::from_u64(1), Fp::from_u64(2)];
+            let output = poseidon::Hash::<_, P128Pow5T3, _, 3, 2>::hash(domain, &message);
+
+            let k = 7;
+            let circuit =
+                HashCircuit::<Pow5Chip<Fp, 3, 2>, P128Pow5T3, _, 3, 2>::new(domain, message);
+            let prover = MockProver::run(k, &circuit, vec![vec![output]]).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+        }
+
+        check(ConstantLength::<2>);
+        check(MerkleNode { layer: 3 });
+    }
+
+    struct QuaternaryMerkleCircuit {
+        leaf: Fp,
+        position: u64,
+        path: Vec<Vec<Fp>>,
+        root: Fp,
+    }
+
+    impl Circuit<Fp> for QuaternaryMerkleCircuit {
+        type Config = Pow5Config<Fp, 5, 4>;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Pow5Config<Fp, 5, 4> {
+            let state = (0..5)
+                .map(|_| meta.advice_column())
+                .collect::<Vec<_>>()
+                .try_into()
+                .unwrap();
+            let partial_sbox = meta.advice_column();
+
+            let mut config = Pow5Chip::configure::<P128Pow5T5>(meta, state, partial_sbox);
+            Pow5Chip::configure_merkle_path(meta, &mut config);
+            config
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            config: Pow5Config<Fp, 5, 4>,
+        ) -> Result<(), Error> {
+            let mut layouter = layouter::SingleChip::<Pow5Chip<Fp, 5, 4>, _>::new(cs, config)?;
+
+            let leaf = Pow5Chip::witness_node(&mut layouter, Some(self.leaf))?;
+            let root = Pow5Chip::witness_node(&mut layouter, Some(self.root))?;
+
+            let path = MerklePath::<Pow5Chip<Fp, 5, 4>>::new(
+                4,
+                Some(self.position),
+                self.path
+                    .iter()
+                    .map(|siblings| siblings.iter().copied().map(Some).collect())
+                    .collect(),
+            );
+            path.verify(layouter.namespace(|| "merkle path"), leaf, &root)
+        }
+    }
+
+    #[test]
+    fn poseidon_merkle_path_quaternary() {
+        // A tree of depth 2 and arity 4.
+        let leaves: Vec<_> = (0..16).map(|i| Fp::from_u64(i * 3 + 2)).collect();
+        let hash = |layer, children: &[Fp]| {
+            poseidon::Hash::<_, P128Pow5T5, _, 5, 4>::hash(MerkleNode { layer }, children)
+        };
+        let nodes: Vec<_> = leaves.chunks(4).map(|children| hash(0, children)).collect();
+        let root = hash(1, &nodes);
+
+        let circuit = |leaf: u64, position: u64| {
+            let siblings = |layer: &[Fp], index: usize| {
+                let start = index - index % 4;
+                (start..start + 4)
+                    .filter(|&i| i != index)
+                    .map(|i| layer[i])
+                    .collect::<Vec<_>>()
+            };
+            QuaternaryMerkleCircuit {
+                leaf: leaves[leaf as usize],
+                position,
+                path: vec![
+                    siblings(&leaves, leaf as usize),
+                    siblings(&nodes, leaf as usize / 4),
+                ],
+                root,
+            }
+        };
+
+        let k = 9;
+        for &position in &[0, 6, 15] {
+            let prover = MockProver::run(k, &circuit(position, position), vec![]).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+        }
+        let prover = MockProver::run(k, &circuit(6, 7), vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
@@ -0,0 +1,521 @@
+use std::convert::TryInto;
+use std::marker::PhantomData;
+
+use super::{
+    sponge::{SpongeConfig, StateWord},
+    PoseidonDuplexInstructions, PoseidonInstructions, SpongeState,
+};
+use crate::{
+    arithmetic::FieldExt,
+    circuit::{Chip, Layouter, Region},
+    plonk::{Advice, Column, ConstraintSystem, Error, Expression, Selector},
+    poly::Rotation,
+    primitives::poseidon::{sparse::SparseConstants, Mds, PermutationType, SboxType, Spec, State},
+};
+
+/// Configuration for a [`Pow5SparseChip`].
+#[derive(Clone, Debug)]
+pub struct Pow5SparseConfig<F: FieldExt, const WIDTH: usize, const RATE: usize> {
+    sponge: SpongeConfig<WIDTH, RATE>,
+    partial_sbox: Column<Advice>,
+    s_full: Selector,
+    s_partial_init: Selector,
+    s_partial: Selector,
+
+    constants: SparseConstants<F, WIDTH>,
+}
+
+/// A Poseidon chip using an $x^5$ S-Box, with a width of `WIDTH` and a sponge rate of
+/// `RATE`, that uses the [sparse representation] of the partial rounds.
+///
+/// Each partial round uses one row, constraining a single S-box and a sparse linear
+/// layer whose coefficients are loaded into the fixed columns. Compared to
+/// [`Pow5Chip`], the partial rounds need no dense MDS multiplication in their gates
+/// (and the linear layer is constrained with degree 3 rather than 6), at the cost of
+/// using one row per partial round instead of one row per pair of partial rounds.
+///
+/// [sparse representation]: crate::primitives::poseidon::sparse
+/// [`Pow5Chip`]: super::Pow5Chip
+#[derive(Debug)]
+pub struct Pow5SparseChip<F: FieldExt, const WIDTH: usize, const RATE: usize> {
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt, const WIDTH: usize, const RATE: usize> Pow5SparseChip<F, WIDTH, RATE> {
+    /// Configures this chip for use in a circuit.
+    ///
+    /// The chip uses the given `state` columns for its inputs and outputs, and places
+    /// all of them in a permutation. `partial_sbox` is used to witness the S-box output
+    /// of each partial round.
+    pub fn configure<S: Spec<F, WIDTH, RATE>>(
+        meta: &mut ConstraintSystem<F>,
+        state: [Column<Advice>; WIDTH],
+        partial_sbox: Column<Advice>,
+    ) -> Pow5SparseConfig<F, WIDTH, RATE> {
+        assert_eq!(S::sbox_type(), SboxType::Pow);
+        assert_eq!(S::permutation_type(), PermutationType::Poseidon);
+        assert!(S::full_rounds() & 1 == 0);
+        let constants = SparseConstants::for_spec::<S, RATE>();
+        let m_reg = constants.mds;
+        let m_pre = constants.partial_initial_matrix;
+        // The top-left entry of every sparse matrix is that of the MDS matrix.
+        let m_00 = m_reg[0][0];
+
+        let sponge = SpongeConfig::configure(meta, state);
+        let rc_a = sponge.rc_a;
+        let rc_b = sponge.rc_b;
+
+        let s_full = meta.selector();
+        let s_partial_init = meta.selector();
+        let s_partial = meta.selector();
+
+        let pow_5 = |v: Expression<F>| {
+            let v2 = v.clone() * v.clone();
+            v2.clone() * v2 * v
+        };
+
+        for idx in 0..WIDTH {
+            meta.create_gate("full round", |meta| {
+                let s_full = meta.query_selector(s_full, Rotation::cur());
+
+                let expr = (0..WIDTH)
+                    .map(|j| {
+                        let state_cur = meta.query_advice(state[j], Rotation::cur());
+                        let rc_a = meta.query_fixed(rc_a[j], Rotation::cur());
+                        pow_5(state_cur + rc_a) * m_reg[idx][j]
+                    })
+                    .fold(Expression::Constant(F::zero()), |acc, term| acc + term);
+                let state_next = meta.query_advice(state[idx], Rotation::next());
+
+                s_full * (expr - state_next)
+            });
+        }
+
+        for idx in 0..WIDTH {
+            meta.create_gate("partial rounds initial layer", |meta| {
+                let s_partial_init = meta.query_selector(s_partial_init, Rotation::cur());
+
+                let expr = (0..WIDTH)
+                    .map(|j| {
+                        let state_cur = meta.query_advice(state[j], Rotation::cur());
+                        let rc_a = meta.query_fixed(rc_a[j], Rotation::cur());
+                        (state_cur + rc_a) * m_pre[idx][j]
+                    })
+                    .fold(Expression::Constant(F::zero()), |acc, term| acc + term);
+                let state_next = meta.query_advice(state[idx], Rotation::next());
+
+                s_partial_init * (expr - state_next)
+            });
+        }
+
+        meta.create_gate("partial round S-box", |meta| {
+            let cur_0 = meta.query_advice(state[0], Rotation::cur());
+            let mid_0 = meta.query_advice(partial_sbox, Rotation::cur());
+            let s_partial = meta.query_selector(s_partial, Rotation::cur());
+
+            s_partial * (pow_5(cur_0) - mid_0)
+        });
+
+        // In a partial round, rc_a[0] holds the constant added to the S-box output,
+        // rc_a[1..] holds the first column of the sparse matrix, and rc_b[1..] holds its
+        // first row.
+        meta.create_gate("partial round sparse layer", |meta| {
+            let mid_0 = meta.query_advice(partial_sbox, Rotation::cur());
+            let rc_a0 = meta.query_fixed(rc_a[0], Rotation::cur());
+            let next_0 = meta.query_advice(state[0], Rotation::next());
+            let s_partial = meta.query_selector(s_partial, Rotation::cur());
+
+            let expr = (1..WIDTH).fold((mid_0 + rc_a0) * m_00, |acc, j| {
+                let cur = meta.query_advice(state[j], Rotation::cur());
+                let w = meta.query_fixed(rc_b[j], Rotation::cur());
+                acc + w * cur
+            });
+
+            s_partial * (expr - next_0)
+        });
+        for idx in 1..WIDTH {
+            meta.create_gate("partial round sparse layer", |meta| {
+                let mid_0 = meta.query_advice(partial_sbox, Rotation::cur());
+                let rc_a0 = meta.query_fixed(rc_a[0], Rotation::cur());
+                let v = meta.query_fixed(rc_a[idx], Rotation::cur());
+                let cur = meta.query_advice(state[idx], Rotation::cur());
+                let next = meta.query_advice(state[idx], Rotation::next());
+                let s_partial = meta.query_selector(s_partial, Rotation::cur());
+
+                s_partial * (v * (mid_0 + rc_a0) + cur - next)
+            });
+        }
+
+        Pow5SparseConfig {
+            sponge,
+            partial_sbox,
+            s_full,
+            s_partial_init,
+            s_partial,
+            constants,
+        }
+    }
+}
+
+impl<F: FieldExt, const WIDTH: usize, const RATE: usize> Chip for Pow5SparseChip<F, WIDTH, RATE> {
+    type Config = Pow5SparseConfig<F, WIDTH, RATE>;
+    type Loaded = ();
+    type Field = F;
+
+    fn load(_layouter: &mut impl Layouter<Self>) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<F: FieldExt, const WIDTH: usize, const RATE: usize> PoseidonInstructions<WIDTH>
+    for Pow5SparseChip<F, WIDTH, RATE>
+{
+    type Word = StateWord<F>;
+
+    fn permute(
+        layouter: &mut impl Layouter<Self>,
+        initial_state: &State<Self::Word, WIDTH>,
+    ) -> Result<State<Self::Word, WIDTH>, Error> {
+        let config = layouter.config().clone();
+        let constants = &config.constants;
+        let half_full_rounds = constants.half_full_rounds();
+        let partial_rounds = constants.sparse_matrices.len();
+
+        layouter.assign_region(
+            || "permute state",
+            |mut region| {
+                // Load the initial state into this region.
+                let state = config.sponge.load_state(&mut region, 0, initial_state)?;
+
+                let state = (0..half_full_rounds).try_fold(state, |state, r| {
+                    full_round(&mut region, &config, r, r, state)
+                })?;
+
+                let state = partial_rounds_initial(&mut region, &config, half_full_rounds, state)?;
+
+                let state = (0..partial_rounds).try_fold(state, |state, r| {
+                    partial_round(&mut region, &config, r, half_full_rounds + 1 + r, state)
+                })?;
+
+                let state = (0..half_full_rounds).try_fold(state, |state, r| {
+                    full_round(
+                        &mut region,
+                        &config,
+                        half_full_rounds + r,
+                        half_full_rounds + 1 + partial_rounds + r,
+                        state,
+                    )
+                })?;
+
+                Ok(state)
+            },
+        )
+    }
+}
+
+impl<F: FieldExt, const WIDTH: usize, const RATE: usize> PoseidonDuplexInstructions<WIDTH, RATE>
+    for Pow5SparseChip<F, WIDTH, RATE>
+{
+    fn load_message(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<F>,
+    ) -> Result<Self::Word, Error> {
+        let config = layouter.config().sponge.clone();
+        config.load_message(layouter, value)
+    }
+
+    fn initial_state(
+        layouter: &mut impl Layouter<Self>,
+        initial_capacity_element: F,
+    ) -> Result<State<Self::Word, WIDTH>, Error> {
+        let config = layouter.config().sponge.clone();
+        config.initial_state(layouter, initial_capacity_element)
+    }
+
+    fn pad_and_add(
+        layouter: &mut impl Layouter<Self>,
+        initial_state: &State<Self::Word, WIDTH>,
+        input: &SpongeState<Self::Word, F, RATE>,
+    ) -> Result<State<Self::Word, WIDTH>, Error> {
+        let config = layouter.config().sponge.clone();
+        config.pad_and_add(layouter, initial_state, input)
+    }
+
+    fn get_output(state: &State<Self::Word, WIDTH>) -> [Self::Word; RATE] {
+        state[..RATE].try_into().unwrap()
+    }
+}
+
+type SparseRegion<'r, F, const WIDTH: usize, const RATE: usize> =
+    Region<'r, Pow5SparseChip<F, WIDTH, RATE>>;
+
+/// Assigns the full round with index `round` (among the full rounds) at `offset`.
+fn full_round<F: FieldExt, const WIDTH: usize, const RATE: usize>(
+    region: &mut SparseRegion<'_, F, WIDTH, RATE>,
+    config: &Pow5SparseConfig<F, WIDTH, RATE>,
+    round: usize,
+    offset: usize,
+    state: State<StateWord<F>, WIDTH>,
+) -> Result<State<StateWord<F>, WIDTH>, Error> {
+    config.s_full.enable(region, offset)?;
+    let rcs = &config.constants.full_round_constants[round];
+    load_fixed(region, config, "full", round, offset, rcs, None)?;
+
+    let values = state
+        .iter()
+        .zip(rcs.iter())
+        .map(|(word, rc)| word.value.map(|v| (v + rc).pow(&[5, 0, 0, 0])))
+        .collect::<Option<Vec<_>>>();
+    let next = matrix_mul(&config.constants.mds, values.as_deref());
+    assign_state(region, config, offset + 1, next)
+}
+
+/// Assigns the constant addition and dense matrix preceding the partial rounds.
+fn partial_rounds_initial<F: FieldExt, const WIDTH: usize, const RATE: usize>(
+    region: &mut SparseRegion<'_, F, WIDTH, RATE>,
+    config: &Pow5SparseConfig<F, WIDTH, RATE>,
+    offset: usize,
+    state: State<StateWord<F>, WIDTH>,
+) -> Result<State<StateWord<F>, WIDTH>, Error> {
+    config.s_partial_init.enable(region, offset)?;
+    let rcs = &config.constants.partial_initial_constants;
+    load_fixed(region, config, "partial initial", 0, offset, rcs, None)?;
+
+    let values = state
+        .iter()
+        .zip(rcs.iter())
+        .map(|(word, rc)| word.value.map(|v| v + rc))
+        .collect::<Option<Vec<_>>>();
+    let next = matrix_mul(&config.constants.partial_initial_matrix, values.as_deref());
+    assign_state(region, config, offset + 1, next)
+}
+
+/// Assigns the partial round with index `round` (among the partial rounds) at `offset`.
+fn partial_round<F: FieldExt, const WIDTH: usize, const RATE: usize>(
+    region: &mut SparseRegion<'_, F, WIDTH, RATE>,
+    config: &Pow5SparseConfig<F, WIDTH, RATE>,
+    round: usize,
+    offset: usize,
+    state: State<StateWord<F>, WIDTH>,
+) -> Result<State<StateWord<F>, WIDTH>, Error> {
+    config.s_partial.enable(region, offset)?;
+    let m = &config.constants.sparse_matrices[round];
+    let rc = config.constants.partial_round_constants[round];
+
+    // rc_a = (rc, v_1, ..., v_{t-1}), rc_b = (0, w_1, ..., w_{t-1})
+    let mut rc_a = m.col;
+    rc_a[0] = rc;
+    let mut rc_b = m.row;
+    rc_b[0] = F::zero();
+    load_fixed(region, config, "partial", round, offset, &rc_a, Some(&rc_b))?;
+
+    let mid = state[0].value.map(|v| v.pow(&[5, 0, 0, 0]));
+    region.assign_advice(
+        || format!("partial round_{} sbox", round),
+        config.partial_sbox,
+        offset,
+        || mid.ok_or(Error::SynthesisError),
+    )?;
+
+    let mut next = [None; WIDTH];
+    let values = state
+        .iter()
+        .map(|word| word.value)
+        .collect::<Option<Vec<_>>>();
+    if let (Some(mid), Some(values)) = (mid, values) {
+        let mut words = [F::zero(); WIDTH];
+        words.copy_from_slice(&values);
+        words[0] = mid + rc;
+        m.apply(&mut words);
+        for (next, word) in next.iter_mut().zip(words.iter()) {
+            *next = Some(*word);
+        }
+    }
+    assign_state(region, config, offset + 1, next)
+}
+
+/// Loads the given values into the `rc_a` (and optionally `rc_b`) fixed columns.
+fn load_fixed<F: FieldExt, const WIDTH: usize, const RATE: usize>(
+    region: &mut SparseRegion<'_, F, WIDTH, RATE>,
+    config: &Pow5SparseConfig<F, WIDTH, RATE>,
+    kind: &str,
+    round: usize,
+    offset: usize,
+    rc_a: &[F; WIDTH],
+    rc_b: Option<&[F; WIDTH]>,
+) -> Result<(), Error> {
+    for (i, value) in rc_a.iter().enumerate() {
+        region.assign_fixed(
+            || format!("{} round_{} rc_a_{}", kind, round, i),
+            config.sponge.rc_a[i],
+            offset,
+            || Ok(*value),
+        )?;
+    }
+    if let Some(rc_b) = rc_b {
+        for (i, value) in rc_b.iter().enumerate() {
+            region.assign_fixed(
+                || format!("{} round_{} rc_b_{}", kind, round, i),
+                config.sponge.rc_b[i],
+                offset,
+                || Ok(*value),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Witnesses the given state in the state columns at `offset`.
+fn assign_state<F: FieldExt, const WIDTH: usize, const RATE: usize>(
+    region: &mut SparseRegion<'_, F, WIDTH, RATE>,
+    config: &Pow5SparseConfig<F, WIDTH, RATE>,
+    offset: usize,
+    state: [Option<F>; WIDTH],
+) -> Result<State<StateWord<F>, WIDTH>, Error> {
+    let mut state_word = |i: usize| {
+        let value = state[i];
+        let var = region.assign_advice(
+            || format!("state_{}", i),
+            config.sponge.state[i],
+            offset,
+            || value.ok_or(Error::SynthesisError),
+        )?;
+        Ok(StateWord { var, value })
+    };
+
+    let state: Result<Vec<_>, Error> = (0..WIDTH).map(&mut state_word).collect();
+    Ok(state?.try_into().unwrap())
+}
+
+/// Multiplies the given (possibly unknown) state by the matrix `m`.
+fn matrix_mul<F: FieldExt, const WIDTH: usize>(
+    m: &Mds<F, WIDTH>,
+    state: Option<&[F]>,
+) -> [Option<F>; WIDTH] {
+    let mut next_state = [None; WIDTH];
+    for (next, m_i) in next_state.iter_mut().zip(m.iter()) {
+        *next = state.map(|state| {
+            m_i.iter()
+                .zip(state.iter())
+                .fold(F::zero(), |acc, (m_ij, s_j)| acc + *m_ij * s_j)
+        });
+    }
+    next_state
+}
+
+#[cfg(test)]
+mod tests {
+    use ff::Field;
+    use std::convert::TryInto;
+    use std::marker::PhantomData;
+
+    use super::{Pow5SparseChip, Pow5SparseConfig};
+    use crate::{
+        arithmetic::FieldExt,
+        circuit::{layouter, Layouter},
+        dev::MockProver,
+        gadget::poseidon::{Hash, PoseidonDuplexInstructions},
+        pasta::Fp,
+        plonk::{Assignment, Circuit, ConstraintSystem, Error},
+        primitives::poseidon::{self, P128Pow5T3, Spec, VariableLength},
+    };
+
+    struct HashCircuit<S: Spec<Fp, WIDTH, RATE>, const WIDTH: usize, const RATE: usize> {
+        message: Option<Vec<Fp>>,
+        len: usize,
+        // For the purpose of this test, witness the result.
+        output: Option<Fp>,
+        _spec: PhantomData<S>,
+    }
+
+    impl<S: Spec<Fp, WIDTH, RATE>, const WIDTH: usize, const RATE: usize> Circuit<Fp>
+        for HashCircuit<S, WIDTH, RATE>
+    {
+        type Config = Pow5SparseConfig<Fp, WIDTH, RATE>;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Pow5SparseConfig<Fp, WIDTH, RATE> {
+            let state = (0..WIDTH)
+                .map(|_| meta.advice_column())
+                .collect::<Vec<_>>()
+                .try_into()
+                .unwrap();
+            let partial_sbox = meta.advice_column();
+
+            Pow5SparseChip::configure::<S>(meta, state, partial_sbox)
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            config: Pow5SparseConfig<Fp, WIDTH, RATE>,
+        ) -> Result<(), Error> {
+            let mut layouter =
+                layouter::SingleChip::<Pow5SparseChip<Fp, WIDTH, RATE>, _>::new(cs, config)?;
+            let config = layouter.config().clone();
+
+            let message = (0..self.len)
+                .map(|i| {
+                    Pow5SparseChip::load_message(
+                        &mut layouter,
+                        self.message.as_ref().map(|message| message[i]),
+                    )
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+
+            let output = Hash::<_, _, WIDTH, RATE>::hash(
+                layouter.namespace(|| "hash"),
+                VariableLength,
+                &message,
+            )?;
+
+            layouter.assign_region(
+                || "constrain output",
+                |mut region| {
+                    let expected_var = region.assign_advice(
+                        || "load output",
+                        config.sponge.state[0],
+                        0,
+                        || self.output.ok_or(Error::SynthesisError),
+                    )?;
+                    region.constrain_equal(&config.sponge.perm, output.var, expected_var)
+                },
+            )
+        }
+    }
+
+    #[test]
+    fn sparse_poseidon_hash() {
+        for len in 0..4 {
+            let message: Vec<_> = (0..len).map(|_| Fp::random(rand::rngs::OsRng)).collect();
+            let output = poseidon::hash(&message);
+
+            let k = 8;
+            let circuit = HashCircuit::<P128Pow5T3, 3, 2> {
+                message: Some(message),
+                len,
+                output: Some(output),
+                _spec: PhantomData,
+            };
+            let prover = MockProver::run(k, &circuit, vec![]).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn sparse_poseidon_hash_wrong_output() {
+        let message = vec![Fp::from_u64(1), Fp::from_u64(2)];
+        let output = poseidon::hash(&message) + Fp::one();
+
+        let k = 8;
+        let circuit = HashCircuit::<P128Pow5T3, 3, 2> {
+            message: Some(message),
+            len: 2,
+            output: Some(output),
+            _spec: PhantomData,
+        };
+        let prover = MockProver::run(k, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
@@ -0,0 +1,325 @@
+//! Circuit logic for the duplex sponge that is shared between the Poseidon chips.
+
+use std::convert::TryInto;
+
+use super::{PaddedWord, SpongeState};
+use crate::{
+    arithmetic::FieldExt,
+    circuit::{Cell, Chip, Layouter, Region},
+    gadget::utilities::AssignedValue,
+    plonk::{Advice, Column, ConstraintSystem, Error, Fixed, Permutation, Selector},
+    poly::Rotation,
+    primitives::poseidon::State,
+};
+
+/// A word in the Poseidon state.
+#[derive(Clone, Copy, Debug)]
+pub struct StateWord<F: FieldExt> {
+    pub(crate) var: Cell,
+    pub(crate) value: Option<F>,
+}
+
+impl<F: FieldExt> StateWord<F> {
+    /// The cell containing this word.
+    pub fn cell(&self) -> Cell {
+        self.var
+    }
+
+    /// The value of this word, if known.
+    pub fn value(&self) -> Option<F> {
+        self.value
+    }
+}
+
+impl<F: FieldExt> From<AssignedValue<F>> for StateWord<F> {
+    fn from(value: AssignedValue<F>) -> Self {
+        StateWord {
+            var: value.cell,
+            value: value.value,
+        }
+    }
+}
+
+impl<F: FieldExt> From<StateWord<F>> for AssignedValue<F> {
+    fn from(word: StateWord<F>) -> Self {
+        AssignedValue {
+            cell: word.var,
+            value: word.value,
+        }
+    }
+}
+
+/// The columns and gates used to load, initialize and absorb into the Poseidon state.
+///
+/// The fixed columns `rc_a` and `rc_b` are also available to the permutation rounds,
+/// which typically use them for round constants.
+#[derive(Clone, Debug)]
+pub(crate) struct SpongeConfig<const WIDTH: usize, const RATE: usize> {
+    pub(crate) state: [Column<Advice>; WIDTH],
+    pub(crate) rc_a: [Column<Fixed>; WIDTH],
+    pub(crate) rc_b: [Column<Fixed>; WIDTH],
+    s_pad_and_add: Selector,
+    s_constant: Selector,
+    pub(crate) perm: Permutation,
+}
+
+impl<const WIDTH: usize, const RATE: usize> SpongeConfig<WIDTH, RATE> {
+    pub(crate) fn configure<F: FieldExt>(
+        meta: &mut ConstraintSystem<F>,
+        state: [Column<Advice>; WIDTH],
+    ) -> Self {
+        assert!(RATE < WIDTH);
+
+        let rc_a: [Column<Fixed>; WIDTH] = (0..WIDTH)
+            .map(|_| meta.fixed_column())
+            .collect::<Vec<_>>()
+            .try_into()
+            .unwrap();
+        let rc_b: [Column<Fixed>; WIDTH] = (0..WIDTH)
+            .map(|_| meta.fixed_column())
+            .collect::<Vec<_>>()
+            .try_into()
+            .unwrap();
+
+        let s_pad_and_add = meta.selector();
+        let s_constant = meta.selector();
+
+        let perm = Permutation::new(
+            meta,
+            &state
+                .iter()
+                .map(|column| (*column).into())
+                .collect::<Vec<_>>(),
+        );
+
+        // Each rate word of the input is either a message word (rc_a = 1, rc_b = 0) or
+        // a fixed padding value (rc_a = 0, rc_b = padding).
+        for idx in 0..RATE {
+            meta.create_gate("pad-and-add", |meta| {
+                let initial_state = meta.query_advice(state[idx], Rotation::prev());
+                let input = meta.query_advice(state[idx], Rotation::cur());
+                let is_message = meta.query_fixed(rc_a[idx], Rotation::cur());
+                let padding = meta.query_fixed(rc_b[idx], Rotation::cur());
+                let output_state = meta.query_advice(state[idx], Rotation::next());
+                let s_pad_and_add = meta.query_selector(s_pad_and_add, Rotation::cur());
+
+                s_pad_and_add * (initial_state + is_message * input + padding - output_state)
+            });
+        }
+        for column in state.iter().skip(RATE).copied() {
+            meta.create_gate("pad-and-add", |meta| {
+                let initial_state = meta.query_advice(column, Rotation::prev());
+                let output_state = meta.query_advice(column, Rotation::next());
+                let s_pad_and_add = meta.query_selector(s_pad_and_add, Rotation::cur());
+
+                // The capacity elements are never altered by the input.
+                s_pad_and_add * (initial_state - output_state)
+            });
+        }
+
+        for idx in 0..WIDTH {
+            meta.create_gate("constant state", |meta| {
+                let word = meta.query_advice(state[idx], Rotation::cur());
+                let constant = meta.query_fixed(rc_b[idx], Rotation::cur());
+                let s_constant = meta.query_selector(s_constant, Rotation::cur());
+
+                s_constant * (word - constant)
+            });
+        }
+
+        SpongeConfig {
+            state,
+            rc_a,
+            rc_b,
+            s_pad_and_add,
+            s_constant,
+            perm,
+        }
+    }
+
+    /// Copies the given state into the state columns of `region` at `offset`.
+    pub(crate) fn load_state<F: FieldExt, C: Chip<Field = F>>(
+        &self,
+        region: &mut Region<C>,
+        offset: usize,
+        state: &State<StateWord<F>, WIDTH>,
+    ) -> Result<State<StateWord<F>, WIDTH>, Error> {
+        let mut load_state_word = |i: usize| {
+            let value = state[i].value;
+            let var = region.assign_advice(
+                || format!("load state_{}", i),
+                self.state[i],
+                offset,
+                || value.ok_or(Error::SynthesisError),
+            )?;
+            region.constrain_equal(&self.perm, state[i].var, var)?;
+            Ok(StateWord { var, value })
+        };
+
+        let state: Result<Vec<_>, Error> = (0..WIDTH).map(&mut load_state_word).collect();
+        Ok(state?.try_into().unwrap())
+    }
+
+    pub(crate) fn load_message<F: FieldExt, C: Chip<Field = F>>(
+        &self,
+        layouter: &mut impl Layouter<C>,
+        value: Option<F>,
+    ) -> Result<StateWord<F>, Error> {
+        layouter.assign_region(
+            || "load message",
+            |mut region| {
+                let var = region.assign_advice(
+                    || "message",
+                    self.state[0],
+                    0,
+                    || value.ok_or(Error::SynthesisError),
+                )?;
+                Ok(StateWord { var, value })
+            },
+        )
+    }
+
+    pub(crate) fn initial_state<F: FieldExt, C: Chip<Field = F>>(
+        &self,
+        layouter: &mut impl Layouter<C>,
+        initial_capacity_element: F,
+    ) -> Result<State<StateWord<F>, WIDTH>, Error> {
+        layouter.assign_region(
+            || "initial state",
+            |mut region| {
+                self.s_constant.enable(&mut region, 0)?;
+
+                let mut load_word = |i: usize| {
+                    let value = if i == RATE {
+                        initial_capacity_element
+                    } else {
+                        F::zero()
+                    };
+                    region.assign_fixed(
+                        || format!("initial_state_{}", i),
+                        self.rc_b[i],
+                        0,
+                        || Ok(value),
+                    )?;
+                    let var = region.assign_advice(
+                        || format!("state_{}", i),
+                        self.state[i],
+                        0,
+                        || Ok(value),
+                    )?;
+                    Ok(StateWord {
+                        var,
+                        value: Some(value),
+                    })
+                };
+
+                let state: Result<Vec<_>, Error> = (0..WIDTH).map(&mut load_word).collect();
+                Ok(state?.try_into().unwrap())
+            },
+        )
+    }
+
+    pub(crate) fn pad_and_add<F: FieldExt, C: Chip<Field = F>>(
+        &self,
+        layouter: &mut impl Layouter<C>,
+        initial_state: &State<StateWord<F>, WIDTH>,
+        input: &SpongeState<StateWord<F>, F, RATE>,
+    ) -> Result<State<StateWord<F>, WIDTH>, Error> {
+        layouter.assign_region(
+            || "pad-and-add",
+            |mut region| {
+                // Load the initial state into this region.
+                let initial_state = self.load_state(&mut region, 0, initial_state)?;
+
+                self.assign_pad_and_add(&mut region, 1, &initial_state, input)
+            },
+        )
+    }
+
+    /// Adds `input` to `initial_state`, which must already be assigned to the state
+    /// columns of `region` at `offset - 1`. The input is assigned at `offset`, and the
+    /// output state at `offset + 1`.
+    pub(crate) fn assign_pad_and_add<F: FieldExt, C: Chip<Field = F>>(
+        &self,
+        region: &mut Region<C>,
+        offset: usize,
+        initial_state: &State<StateWord<F>, WIDTH>,
+        input: &SpongeState<StateWord<F>, F, RATE>,
+    ) -> Result<State<StateWord<F>, WIDTH>, Error> {
+        self.s_pad_and_add.enable(region, offset)?;
+
+        // Load the input into this region.
+        let mut load_input_word = |i: usize| {
+            let (message, padding) = match input[i] {
+                Some(PaddedWord::Message(word)) => (Some(word), F::zero()),
+                Some(PaddedWord::Padding(padding)) => (None, padding),
+                None => (None, F::zero()),
+            };
+
+            region.assign_fixed(
+                || format!("is_message_{}", i),
+                self.rc_a[i],
+                offset,
+                || {
+                    Ok(if message.is_some() {
+                        F::one()
+                    } else {
+                        F::zero()
+                    })
+                },
+            )?;
+            region.assign_fixed(
+                || format!("padding_{}", i),
+                self.rc_b[i],
+                offset,
+                || Ok(padding),
+            )?;
+
+            let value = match message {
+                Some(word) => {
+                    let var = region.assign_advice(
+                        || format!("load input_{}", i),
+                        self.state[i],
+                        offset,
+                        || word.value.ok_or(Error::SynthesisError),
+                    )?;
+                    region.constrain_equal(&self.perm, word.var, var)?;
+                    word.value
+                }
+                None => {
+                    region.assign_advice(
+                        || format!("unused input_{}", i),
+                        self.state[i],
+                        offset,
+                        || Ok(F::zero()),
+                    )?;
+                    Some(padding)
+                }
+            };
+
+            Ok(value)
+        };
+        let input_values: Result<Vec<_>, Error> = (0..RATE).map(&mut load_input_word).collect();
+        let input_values = input_values?;
+
+        // Constrain the output.
+        let mut constrain_output_word = |i: usize| {
+            let value = initial_state[i].value.and_then(|initial_word| {
+                input_values
+                    .get(i)
+                    .map(|word| word.map(|word| word + initial_word))
+                    .unwrap_or(Some(initial_word))
+            });
+            let var = region.assign_advice(
+                || format!("load output_{}", i),
+                self.state[i],
+                offset + 1,
+                || value.ok_or(Error::SynthesisError),
+            )?;
+            Ok(StateWord { var, value })
+        };
+
+        let output: Result<Vec<_>, Error> = (0..WIDTH).map(&mut constrain_output_word).collect();
+        Ok(output?.try_into().unwrap())
+    }
+}
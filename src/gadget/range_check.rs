@@ -0,0 +1,110 @@
+//! Gadget and chips for range checks with a lookup table.
+//!
+//! A value is range-checked by decomposing it into $K$-bit words with a running sum,
+//! where $K$ is chosen when the chip is configured and each word is looked up in a
+//! table of the $2^K$ values that fit in $K$ bits. A bit length that is not a multiple
+//! of $K$ is handled by checking the top word with a short range check, which also
+//! looks up the word shifted left by the missing number of bits. Checking a value to
+//! $n$ bits therefore costs $\lceil n / K \rceil + 1$ rows.
+
+use std::fmt;
+
+use ff::PrimeField;
+
+use crate::{
+    circuit::{Chip, Layouter},
+    plonk::Error,
+};
+
+mod chip;
+pub use chip::{AssignedValue, RangeCheckChip, RangeCheckConfig};
+
+/// The set of circuit instructions required to use the [`Num`] gadget.
+pub trait RangeCheckInstructions: Chip {
+    /// Variable representing a field element.
+    type Value: Clone + fmt::Debug;
+
+    /// Witnesses a field element.
+    fn witness_value(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<Self::Field>,
+    ) -> Result<Self::Value, Error>;
+
+    /// Constrains `a` to be less than $2^{\mathsf{num\_bits}}$.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `num_bits` is not less than the capacity of the field.
+    fn range_check(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Value,
+        num_bits: usize,
+    ) -> Result<(), Error>;
+
+    /// Returns the little-endian $K$-bit words of `a`, constraining it to be less than
+    /// $2^{K \cdot \mathsf{num\_words}}$.
+    ///
+    /// # Panics
+    ///
+    /// Panics if $K \cdot \mathsf{num\_words}$ is not less than the capacity of the
+    /// field.
+    fn decompose(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Value,
+        num_words: usize,
+    ) -> Result<Vec<Self::Value>, Error>;
+}
+
+/// Returns the maximum bit length that can be range-checked over the field `F`.
+pub fn max_bits<F: PrimeField>() -> usize {
+    F::CAPACITY as usize - 1
+}
+
+/// A field element assigned in the circuit, which can be range-checked.
+#[derive(Debug)]
+pub struct Num<RangeChip: RangeCheckInstructions> {
+    inner: RangeChip::Value,
+}
+
+impl<RangeChip: RangeCheckInstructions> Num<RangeChip> {
+    /// Witnesses a field element.
+    pub fn new(
+        mut layouter: impl Layouter<RangeChip>,
+        value: Option<RangeChip::Field>,
+    ) -> Result<Self, Error> {
+        RangeChip::witness_value(&mut layouter, value).map(|inner| Num { inner })
+    }
+
+    /// Constrains this value to be less than $2^{\mathsf{num\_bits}}$.
+    pub fn range_check(
+        &self,
+        mut layouter: impl Layouter<RangeChip>,
+        num_bits: usize,
+    ) -> Result<(), Error> {
+        RangeChip::range_check(&mut layouter, &self.inner, num_bits)
+    }
+
+    /// Returns the little-endian words of this value, constraining it to fit in
+    /// `num_words` words.
+    pub fn decompose(
+        &self,
+        mut layouter: impl Layouter<RangeChip>,
+        num_words: usize,
+    ) -> Result<Vec<Self>, Error> {
+        RangeChip::decompose(&mut layouter, &self.inner, num_words)
+            .map(|words| words.into_iter().map(|inner| Num { inner }).collect())
+    }
+
+    /// Returns the chip-specific representation of this value.
+    pub fn inner(&self) -> &RangeChip::Value {
+        &self.inner
+    }
+}
+
+impl<RangeChip: RangeCheckInstructions> Clone for Num<RangeChip> {
+    fn clone(&self) -> Self {
+        Num {
+            inner: self.inner.clone(),
+        }
+    }
+}
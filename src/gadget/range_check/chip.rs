@@ -0,0 +1,318 @@
+//! A chip implementing [`RangeCheckInstructions`] with a table of $K$-bit values.
+//!
+//! # Layout
+//!
+//! Decomposing a value $z_0$ into $n$ words uses $n + 1$ rows. Row $i$ holds the running
+//! sum $z_i$ and the word $m_i = z_i - 2^K z_{i + 1}$, which is looked up in the table,
+//! and row $n$ holds $z_n$, which is constrained to be zero. A short range check of the
+//! top word to $s < K$ bits also looks up $2^{K - s} m_{n - 1}$, where the shift is
+//! held in a fixed column of row $n - 1$, and both lookups only succeed if
+//! $m_{n - 1} < 2^s$.
+
+use std::marker::PhantomData;
+
+use super::{max_bits, RangeCheckInstructions};
+use crate::{
+    arithmetic::FieldExt,
+    circuit::{Chip, Layouter, Region},
+    gadget::running_sum::RunningSumConfig,
+    plonk::{Advice, Column, ConstraintSystem, Error, Fixed, Permutation, Selector},
+    poly::Rotation,
+};
+
+pub use crate::gadget::utilities::AssignedValue;
+
+/// Configuration for a [`RangeCheckChip`].
+#[derive(Clone, Debug)]
+pub struct RangeCheckConfig {
+    running_sum: RunningSumConfig,
+    table: Column<Fixed>,
+    shift: Column<Fixed>,
+    q_short: Selector,
+    perm: Permutation,
+}
+
+/// A chip implementing [`RangeCheckInstructions`].
+#[derive(Debug)]
+pub struct RangeCheckChip<F: FieldExt> {
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt> Chip for RangeCheckChip<F> {
+    type Config = RangeCheckConfig;
+    type Loaded = ();
+    type Field = F;
+
+    fn load(layouter: &mut impl Layouter<Self>) -> Result<(), Error> {
+        let config = layouter.config().clone();
+        config.load(layouter)
+    }
+}
+
+impl<F: FieldExt> RangeCheckChip<F> {
+    /// Configures this chip with a table of `word_bits`-bit values, which uses
+    /// $2^{\mathsf{word\_bits}}$ rows.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `word_bits` is zero or greater than 24.
+    pub fn configure(meta: &mut ConstraintSystem<F>, word_bits: usize) -> RangeCheckConfig {
+        RangeCheckConfig::configure(meta, word_bits)
+    }
+}
+
+impl RangeCheckConfig {
+    /// Configures the columns, gates and lookups of a range check.
+    ///
+    /// This allocates two advice columns, which are placed in a permutation, and two
+    /// fixed columns.
+    pub(crate) fn configure<F: FieldExt>(meta: &mut ConstraintSystem<F>, word_bits: usize) -> Self {
+        let z = meta.advice_column();
+        let word = meta.advice_column();
+        let config = RangeCheckConfig {
+            running_sum: RunningSumConfig::configure(meta, z, word, word_bits, false),
+            table: meta.fixed_column(),
+            shift: meta.fixed_column(),
+            q_short: meta.selector(),
+            perm: Permutation::new(meta, &[z.into(), word.into()]),
+        };
+
+        // Rows without a lookup look up zero, which is in the table.
+        let word = meta.query_advice(word, Rotation::cur());
+        let shift = meta.query_fixed(config.shift, Rotation::cur());
+        let q_lookup = meta.query_selector(config.running_sum.q_running(), Rotation::cur());
+        let q_short = meta.query_selector(config.q_short, Rotation::cur());
+        for input in [q_lookup * word.clone(), q_short * word * shift] {
+            let table = meta.query_fixed(config.table, Rotation::cur());
+            meta.lookup(&[input], &[table]);
+        }
+
+        config
+    }
+
+    /// The number of bits $K$ of each word of a decomposition.
+    pub fn word_bits(&self) -> usize {
+        self.running_sum.window_bits()
+    }
+
+    /// Loads the table of `word_bits`-bit values.
+    pub(crate) fn load<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        layouter: &mut impl Layouter<Ch>,
+    ) -> Result<(), Error> {
+        let table = self.table;
+        let size = 1usize << self.word_bits();
+
+        layouter.assign_region(
+            || "range-check table",
+            |mut region| {
+                for value in 0..size {
+                    region.assign_fixed(
+                        || "table",
+                        table,
+                        value,
+                        || Ok(F::from_u64(value as u64)),
+                    )?;
+                }
+                Ok(())
+            },
+        )
+    }
+
+    /// Assigns `value` in the running-sum column at `offset`.
+    pub(crate) fn assign_value<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        region: &mut Region<'_, Ch>,
+        offset: usize,
+        value: Option<F>,
+    ) -> Result<AssignedValue<F>, Error> {
+        self.running_sum.assign_value(region, offset, value)
+    }
+
+    /// Assigns the decomposition of `a` into `num_words` words at `offset`, checking
+    /// the top word to `top_bits` bits, and returns the words.
+    ///
+    /// The decomposition uses `num_words + 1` rows. If `num_words` is zero, `a` is
+    /// constrained to be zero.
+    pub(crate) fn assign_decomposition<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        region: &mut Region<'_, Ch>,
+        offset: usize,
+        a: &AssignedValue<F>,
+        num_words: usize,
+        top_bits: usize,
+    ) -> Result<Vec<AssignedValue<F>>, Error> {
+        let word_bits = self.word_bits();
+        assert!(num_words * word_bits <= max_bits::<F>());
+        assert!(top_bits > 0 && top_bits <= word_bits);
+
+        let running_sum = self
+            .running_sum
+            .assign(region, offset, a.value, num_words, true)?;
+        region.constrain_equal(&self.perm, running_sum.zs()[0].cell, a.cell)?;
+
+        if num_words > 0 && top_bits < word_bits {
+            let row = offset + num_words - 1;
+            self.q_short.enable(region, row)?;
+            region.assign_fixed(
+                || "shift",
+                self.shift,
+                row,
+                || Ok(F::from_u64(1 << (word_bits - top_bits))),
+            )?;
+        }
+
+        Ok(running_sum.windows().to_vec())
+    }
+
+    /// Assigns a range check of `a` to `num_bits` bits at `offset`, and returns the
+    /// number of rows it uses.
+    pub(crate) fn assign_range_check<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        region: &mut Region<'_, Ch>,
+        offset: usize,
+        a: &AssignedValue<F>,
+        num_bits: usize,
+    ) -> Result<usize, Error> {
+        assert!(num_bits <= max_bits::<F>());
+        let k = self.word_bits();
+
+        // Only zero fits in no bits, which is checked by an empty decomposition.
+        let num_words = num_bits.div_ceil(k);
+        let top_bits = if num_words == 0 {
+            k
+        } else {
+            num_bits - k * (num_words - 1)
+        };
+        self.assign_decomposition(region, offset, a, num_words, top_bits)?;
+        Ok(num_words + 1)
+    }
+
+    /// The running-sum column, which is in [`Self::perm`].
+    pub(crate) fn z(&self) -> Column<Advice> {
+        self.running_sum.z()
+    }
+
+    /// The word column, which is in [`Self::perm`].
+    pub(crate) fn word(&self) -> Column<Advice> {
+        self.running_sum.window()
+    }
+
+    /// The permutation over the advice columns.
+    pub(crate) fn perm(&self) -> &Permutation {
+        &self.perm
+    }
+}
+
+impl<F: FieldExt> RangeCheckInstructions for RangeCheckChip<F> {
+    type Value = AssignedValue<F>;
+
+    fn witness_value(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<F>,
+    ) -> Result<Self::Value, Error> {
+        let config = layouter.config().clone();
+        layouter.assign_region(
+            || "witness value",
+            |mut region| config.assign_value(&mut region, 0, value),
+        )
+    }
+
+    fn range_check(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Value,
+        num_bits: usize,
+    ) -> Result<(), Error> {
+        let config = layouter.config().clone();
+        layouter.assign_region(
+            || "range check",
+            |mut region| {
+                config
+                    .assign_range_check(&mut region, 0, a, num_bits)
+                    .map(|_| ())
+            },
+        )
+    }
+
+    fn decompose(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Value,
+        num_words: usize,
+    ) -> Result<Vec<Self::Value>, Error> {
+        let config = layouter.config().clone();
+        let k = config.word_bits();
+        layouter.assign_region(
+            || "decompose",
+            |mut region| config.assign_decomposition(&mut region, 0, a, num_words, k),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RangeCheckChip, RangeCheckConfig};
+    use crate::{
+        arithmetic::FieldExt,
+        circuit::{layouter, Layouter},
+        dev::MockProver,
+        gadget::range_check::Num,
+        pasta::Fp,
+        plonk::{Assignment, Circuit, ConstraintSystem, Error},
+    };
+
+    struct RangeCheckCircuit {
+        value: Option<Fp>,
+        num_bits: usize,
+    }
+
+    impl Circuit<Fp> for RangeCheckCircuit {
+        type Config = RangeCheckConfig;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> RangeCheckConfig {
+            RangeCheckChip::configure(meta, 8)
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            config: RangeCheckConfig,
+        ) -> Result<(), Error> {
+            let mut layouter = layouter::SingleChip::<RangeCheckChip<Fp>, _>::new(cs, config)?;
+
+            let value = Num::new(layouter.namespace(|| "value"), self.value)?;
+            value.range_check(layouter.namespace(|| "range check"), self.num_bits)?;
+
+            // The words of a decomposition can themselves be range-checked.
+            let words = value.decompose(layouter.namespace(|| "decompose"), 4)?;
+            words[0].range_check(layouter.namespace(|| "low word"), 8)
+        }
+    }
+
+    #[test]
+    fn range_check() {
+        let run = |value: u64, num_bits: usize| {
+            let circuit = RangeCheckCircuit {
+                value: Some(Fp::from_u64(value)),
+                num_bits,
+            };
+            MockProver::run(9, &circuit, vec![]).unwrap().verify()
+        };
+
+        // Short range checks.
+        assert_eq!(run(0, 0), Ok(()));
+        assert!(run(1, 0).is_err());
+        assert_eq!(run(0x1f, 5), Ok(()));
+        assert!(run(0x20, 5).is_err());
+
+        // Whole words, and partial top words.
+        assert_eq!(run(0xffff, 16), Ok(()));
+        assert!(run(0x1_0000, 16).is_err());
+        assert_eq!(run(0x3ff_ffff, 26), Ok(()));
+        assert!(run(0x400_0000, 26).is_err());
+
+        // The decomposition itself fails for values of more than 32 bits.
+        assert!(run(1 << 32, 40).is_err());
+    }
+}
@@ -0,0 +1,318 @@
+//! A chip for the Rescue-Prime permutation.
+//!
+//! [`RescueChip`] implements the same sponge instructions as the Poseidon chips, so it
+//! can be used with the [`Duplex`](crate::gadget::poseidon::Duplex) and
+//! [`Hash`](struct@crate::gadget::poseidon::Hash) gadgets.
+
+use std::convert::TryInto;
+use std::marker::PhantomData;
+
+use crate::{
+    arithmetic::FieldExt,
+    circuit::{Chip, Layouter, Region},
+    gadget::poseidon::{
+        sponge::{SpongeConfig, StateWord},
+        PoseidonDuplexInstructions, PoseidonInstructions, SpongeState,
+    },
+    plonk::{Advice, Column, ConstraintSystem, Error, Expression, Selector},
+    poly::Rotation,
+    primitives::{
+        poseidon::{Mds, State},
+        rescue::RescueSpec,
+    },
+};
+
+/// Configuration for a [`RescueChip`].
+#[derive(Clone, Debug)]
+pub struct RescueConfig<F: FieldExt, const WIDTH: usize, const RATE: usize> {
+    sponge: SpongeConfig<WIDTH, RATE>,
+    s_forward: Selector,
+    s_inverse: Selector,
+
+    round_constants: Vec<[F; WIDTH]>,
+    mds: Mds<F, WIDTH>,
+}
+
+/// A Rescue-Prime chip, with a width of `WIDTH` and a sponge rate of `RATE`.
+///
+/// Each round uses two rows: the forward S-box layer is constrained directly, and the
+/// inverse S-box layer is constrained by raising its witnessed output to the power
+/// $\alpha$. The permutation therefore occupies $2N + 1$ rows, with gates of degree
+/// $\alpha + 1$.
+#[derive(Debug)]
+pub struct RescueChip<F: FieldExt, const WIDTH: usize, const RATE: usize> {
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt, const WIDTH: usize, const RATE: usize> RescueChip<F, WIDTH, RATE> {
+    /// Configures this chip for use in a circuit.
+    ///
+    /// The chip uses the given `state` columns for its inputs and outputs, and places
+    /// all of them in a permutation.
+    pub fn configure<S: RescueSpec<F, WIDTH, RATE>>(
+        meta: &mut ConstraintSystem<F>,
+        state: [Column<Advice>; WIDTH],
+    ) -> RescueConfig<F, WIDTH, RATE> {
+        let (round_constants, mds) = S::constants();
+
+        let sponge = SpongeConfig::configure(meta, state);
+        let rc_a = sponge.rc_a;
+
+        let s_forward = meta.selector();
+        let s_inverse = meta.selector();
+
+        let pow_alpha =
+            |v: Expression<F>| (1..F::RESCUE_ALPHA).fold(v.clone(), |acc, _| acc * v.clone());
+
+        // The current row holds the round input, and the next row the output of the
+        // inverse S-box layer, y, where y^alpha = MDS(x^alpha) + rc.
+        for idx in 0..WIDTH {
+            meta.create_gate("rescue forward half-round", |meta| {
+                let s_forward = meta.query_selector(s_forward, Rotation::cur());
+
+                let expr = (0..WIDTH)
+                    .map(|j| {
+                        let state_cur = meta.query_advice(state[j], Rotation::cur());
+                        pow_alpha(state_cur) * mds[idx][j]
+                    })
+                    .fold(Expression::Constant(F::zero()), |acc, term| acc + term);
+                let rc = meta.query_fixed(rc_a[idx], Rotation::cur());
+                let state_next = meta.query_advice(state[idx], Rotation::next());
+
+                s_forward * (expr + rc - pow_alpha(state_next))
+            });
+        }
+
+        // The current row holds the output of the inverse S-box layer, and the next row
+        // the round output.
+        for idx in 0..WIDTH {
+            meta.create_gate("rescue inverse half-round", |meta| {
+                let s_inverse = meta.query_selector(s_inverse, Rotation::cur());
+
+                let expr = (0..WIDTH)
+                    .map(|j| meta.query_advice(state[j], Rotation::cur()) * mds[idx][j])
+                    .fold(Expression::Constant(F::zero()), |acc, term| acc + term);
+                let rc = meta.query_fixed(rc_a[idx], Rotation::cur());
+                let state_next = meta.query_advice(state[idx], Rotation::next());
+
+                s_inverse * (expr + rc - state_next)
+            });
+        }
+
+        RescueConfig {
+            sponge,
+            s_forward,
+            s_inverse,
+            round_constants,
+            mds,
+        }
+    }
+}
+
+impl<F: FieldExt, const WIDTH: usize, const RATE: usize> Chip for RescueChip<F, WIDTH, RATE> {
+    type Config = RescueConfig<F, WIDTH, RATE>;
+    type Loaded = ();
+    type Field = F;
+
+    fn load(_layouter: &mut impl Layouter<Self>) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<F: FieldExt, const WIDTH: usize, const RATE: usize> PoseidonInstructions<WIDTH>
+    for RescueChip<F, WIDTH, RATE>
+{
+    type Word = StateWord<F>;
+
+    fn permute(
+        layouter: &mut impl Layouter<Self>,
+        initial_state: &State<Self::Word, WIDTH>,
+    ) -> Result<State<Self::Word, WIDTH>, Error> {
+        let config = layouter.config().clone();
+
+        layouter.assign_region(
+            || "permute state",
+            |mut region| {
+                // Load the initial state into this region.
+                let state = config.sponge.load_state(&mut region, 0, initial_state)?;
+
+                (0..config.round_constants.len()).try_fold(state, |state, half_round| {
+                    half_round_step(&mut region, &config, half_round, state)
+                })
+            },
+        )
+    }
+}
+
+impl<F: FieldExt, const WIDTH: usize, const RATE: usize> PoseidonDuplexInstructions<WIDTH, RATE>
+    for RescueChip<F, WIDTH, RATE>
+{
+    fn load_message(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<F>,
+    ) -> Result<Self::Word, Error> {
+        let config = layouter.config().sponge.clone();
+        config.load_message(layouter, value)
+    }
+
+    fn initial_state(
+        layouter: &mut impl Layouter<Self>,
+        initial_capacity_element: F,
+    ) -> Result<State<Self::Word, WIDTH>, Error> {
+        let config = layouter.config().sponge.clone();
+        config.initial_state(layouter, initial_capacity_element)
+    }
+
+    fn pad_and_add(
+        layouter: &mut impl Layouter<Self>,
+        initial_state: &State<Self::Word, WIDTH>,
+        input: &SpongeState<Self::Word, F, RATE>,
+    ) -> Result<State<Self::Word, WIDTH>, Error> {
+        let config = layouter.config().sponge.clone();
+        config.pad_and_add(layouter, initial_state, input)
+    }
+
+    fn get_output(state: &State<Self::Word, WIDTH>) -> [Self::Word; RATE] {
+        state[..RATE].try_into().unwrap()
+    }
+}
+
+/// Assigns half-round `half_round` of the permutation, which starts at row
+/// `half_round` of the region, and returns its output state.
+fn half_round_step<F: FieldExt, const WIDTH: usize, const RATE: usize>(
+    region: &mut Region<RescueChip<F, WIDTH, RATE>>,
+    config: &RescueConfig<F, WIDTH, RATE>,
+    half_round: usize,
+    state: State<StateWord<F>, WIDTH>,
+) -> Result<State<StateWord<F>, WIDTH>, Error> {
+    let offset = half_round;
+    let rcs = &config.round_constants[half_round];
+    let forward = half_round & 1 == 0;
+
+    if forward {
+        config.s_forward.enable(region, offset)?;
+    } else {
+        config.s_inverse.enable(region, offset)?;
+    }
+
+    for (i, rc) in rcs.iter().enumerate() {
+        region.assign_fixed(
+            || format!("half-round {} rc_{}", half_round, i),
+            config.sponge.rc_a[i],
+            offset,
+            || Ok(*rc),
+        )?;
+    }
+
+    let input = state
+        .iter()
+        .map(|word| {
+            word.value.map(|v| {
+                if forward {
+                    v.pow_vartime([F::RESCUE_ALPHA, 0, 0, 0])
+                } else {
+                    v
+                }
+            })
+        })
+        .collect::<Option<Vec<_>>>();
+    let output = input.map(|input| {
+        config
+            .mds
+            .iter()
+            .zip(rcs.iter())
+            .map(|(m_i, rc)| {
+                let v = m_i
+                    .iter()
+                    .zip(input.iter())
+                    .fold(*rc, |acc, (m_ij, s_j)| acc + *m_ij * s_j);
+                if forward {
+                    v.pow_vartime(F::RESCUE_INVALPHA)
+                } else {
+                    v
+                }
+            })
+            .collect::<Vec<_>>()
+    });
+
+    let mut next_state_word = |i: usize| {
+        let value = output.as_ref().map(|output| output[i]);
+        let var = region.assign_advice(
+            || format!("half-round {} state_{}", half_round + 1, i),
+            config.sponge.state[i],
+            offset + 1,
+            || value.ok_or(Error::SynthesisError),
+        )?;
+        Ok(StateWord { var, value })
+    };
+
+    let next_state: Result<Vec<_>, Error> = (0..WIDTH).map(&mut next_state_word).collect();
+    Ok(next_state?.try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use ff::Field;
+    use std::convert::TryInto;
+
+    use super::{RescueChip, RescueConfig};
+    use crate::{
+        dev::MockProver,
+        gadget::poseidon::{
+            sponge::SpongeConfig,
+            tests::{HashChip, HashCircuit},
+        },
+        pasta::Fp,
+        plonk::ConstraintSystem,
+        primitives::{
+            poseidon::VariableLength,
+            rescue::{self, RescuePrimeT3, RescueSpec},
+        },
+    };
+
+    impl<S: RescueSpec<Fp, WIDTH, RATE>, const WIDTH: usize, const RATE: usize>
+        HashChip<S, WIDTH, RATE> for RescueChip<Fp, WIDTH, RATE>
+    {
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> RescueConfig<Fp, WIDTH, RATE> {
+            let state = (0..WIDTH)
+                .map(|_| meta.advice_column())
+                .collect::<Vec<_>>()
+                .try_into()
+                .unwrap();
+
+            RescueChip::configure::<S>(meta, state)
+        }
+
+        fn sponge(config: &RescueConfig<Fp, WIDTH, RATE>) -> &SpongeConfig<WIDTH, RATE> {
+            &config.sponge
+        }
+    }
+
+    #[test]
+    fn rescue_hash() {
+        for len in 0..4 {
+            let message: Vec<_> = (0..len).map(|_| Fp::random(rand::rngs::OsRng)).collect();
+            let output = rescue::hash::<_, RescuePrimeT3, _, 3, 2>(VariableLength, &message);
+
+            let circuit = HashCircuit::<RescueChip<Fp, 3, 2>, RescuePrimeT3, _, 3, 2>::new(
+                VariableLength,
+                message,
+            );
+            let prover = MockProver::run(7, &circuit, vec![vec![output]]).unwrap();
+            assert_eq!(prover.verify(), Ok(()))
+        }
+    }
+
+    #[test]
+    fn rescue_hash_wrong_output() {
+        let message = vec![Fp::one(), Fp::zero()];
+        let output = rescue::hash::<_, RescuePrimeT3, _, 3, 2>(VariableLength, &message);
+
+        let circuit = HashCircuit::<RescueChip<Fp, 3, 2>, RescuePrimeT3, _, 3, 2>::new(
+            VariableLength,
+            message,
+        );
+        let prover = MockProver::run(7, &circuit, vec![vec![output + Fp::one()]]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
@@ -0,0 +1,387 @@
+//! Gadgets for verifying RSA signatures and RSA accumulator membership.
+//!
+//! Both statements reduce to a modular exponentiation on [`AssignedBigUint`]s, computed
+//! by square-and-multiply with a reduction after every step, so that intermediate
+//! values keep the number of limbs of the modulus. The public exponent of a signature
+//! key is fixed when the circuit is synthesized, so [`pow_mod_fixed`] only multiplies
+//! for its set bits: a signature under $e = 65537$ costs sixteen squarings and one
+//! multiplication. The element of a membership proof is usually secret, so
+//! [`pow_mod`] decomposes its exponent into witnessed bits and selects the multiplier
+//! of each step from them. The values being verified are computed outside of a circuit
+//! by [`crate::primitives::rsa`].
+
+use super::biguint::{AssignedBigUint, BigUintInstructions};
+use crate::{
+    circuit::Layouter,
+    plonk::Error,
+    primitives::{
+        biguint::BigUint,
+        rsa::{self, DIGEST_BYTES},
+    },
+};
+
+/// Returns `base^exponent` modulo `modulus`, for an exponent known when the circuit is
+/// synthesized.
+pub fn pow_mod_fixed<BigUintChip: BigUintInstructions>(
+    mut layouter: impl Layouter<BigUintChip>,
+    base: &AssignedBigUint<BigUintChip>,
+    exponent: &BigUint,
+    modulus: &AssignedBigUint<BigUintChip>,
+) -> Result<AssignedBigUint<BigUintChip>, Error> {
+    if exponent.is_zero() {
+        return AssignedBigUint::constant(layouter.namespace(|| "one"), &BigUint::from(1))?
+            .rem(layouter.namespace(|| "reduce"), modulus);
+    }
+
+    let top = exponent.bits() - 1;
+    let mut acc = base.rem(layouter.namespace(|| "reduce base"), modulus)?;
+    for i in (0..top).rev() {
+        acc = acc
+            .mul(layouter.namespace(|| format!("square {}", i)), &acc)?
+            .rem(
+                layouter.namespace(|| format!("reduce square {}", i)),
+                modulus,
+            )?;
+        if exponent.bit(i) {
+            acc = acc
+                .mul(layouter.namespace(|| format!("multiply {}", i)), base)?
+                .rem(
+                    layouter.namespace(|| format!("reduce product {}", i)),
+                    modulus,
+                )?;
+        }
+    }
+    Ok(acc)
+}
+
+/// Returns `base^exponent` modulo `modulus`, for a witnessed exponent of at most
+/// `num_bits` bits.
+///
+/// The circuit is not satisfied if `exponent` does not fit in `num_bits` bits.
+pub fn pow_mod<BigUintChip: BigUintInstructions>(
+    mut layouter: impl Layouter<BigUintChip>,
+    base: &AssignedBigUint<BigUintChip>,
+    exponent: &AssignedBigUint<BigUintChip>,
+    num_bits: usize,
+    modulus: &AssignedBigUint<BigUintChip>,
+) -> Result<AssignedBigUint<BigUintChip>, Error> {
+    let one = AssignedBigUint::constant(layouter.namespace(|| "one"), &BigUint::from(1))?;
+    let two = AssignedBigUint::constant(layouter.namespace(|| "two"), &BigUint::from(2))?;
+    let exponent_limbs = num_bits.div_ceil(64);
+    let value = exponent.value();
+
+    let mut recomposed: Option<AssignedBigUint<BigUintChip>> = None;
+    let mut acc = one.rem(layouter.namespace(|| "reduce one"), modulus)?;
+    for i in (0..num_bits).rev() {
+        let bit = value.as_ref().map(|e| BigUint::from(e.bit(i) as u64));
+        let bit =
+            AssignedBigUint::new(layouter.namespace(|| format!("bit {}", i)), bit.as_ref(), 1)?;
+        bit.constrain_less_than(layouter.namespace(|| format!("bit {} is boolean", i)), &two)?;
+
+        // e' = 2 e' + b
+        recomposed = Some(match recomposed {
+            None => bit.clone(),
+            Some(e) => e
+                .add(layouter.namespace(|| format!("double {}", i)), &e)?
+                .add(layouter.namespace(|| format!("add bit {}", i)), &bit)?
+                .resize(
+                    layouter.namespace(|| format!("resize {}", i)),
+                    exponent_limbs,
+                )?,
+        });
+
+        // acc = acc^2 (b base + 1 - b)
+        let not_bit = one.sub(layouter.namespace(|| format!("1 - bit {}", i)), &bit)?;
+        let factor = bit
+            .mul(layouter.namespace(|| format!("select base {}", i)), base)?
+            .add(layouter.namespace(|| format!("select one {}", i)), &not_bit)?;
+        acc = acc
+            .mul(layouter.namespace(|| format!("square {}", i)), &acc)?
+            .rem(
+                layouter.namespace(|| format!("reduce square {}", i)),
+                modulus,
+            )?
+            .mul(layouter.namespace(|| format!("multiply {}", i)), &factor)?
+            .rem(
+                layouter.namespace(|| format!("reduce product {}", i)),
+                modulus,
+            )?;
+    }
+
+    if let Some(recomposed) = recomposed {
+        recomposed.constrain_equal(layouter.namespace(|| "exponent"), exponent)?;
+    }
+    Ok(acc)
+}
+
+/// An RSA public key with a public exponent fixed when the circuit is synthesized.
+#[derive(Debug)]
+pub struct PublicKey<BigUintChip: BigUintInstructions> {
+    modulus: AssignedBigUint<BigUintChip>,
+    modulus_bytes: usize,
+    exponent: BigUint,
+}
+
+impl<BigUintChip: BigUintInstructions> PublicKey<BigUintChip> {
+    /// Witnesses the modulus of a public key whose modulus has `modulus_bytes` bytes,
+    /// such as 256 for RSA-2048.
+    pub fn new(
+        layouter: impl Layouter<BigUintChip>,
+        modulus: Option<&BigUint>,
+        modulus_bytes: usize,
+        exponent: BigUint,
+    ) -> Result<Self, Error> {
+        let modulus = AssignedBigUint::new(layouter, modulus, modulus_bytes.div_ceil(8))?;
+        Ok(PublicKey {
+            modulus,
+            modulus_bytes,
+            exponent,
+        })
+    }
+
+    /// The modulus $n$ of this key.
+    pub fn modulus(&self) -> &AssignedBigUint<BigUintChip> {
+        &self.modulus
+    }
+
+    /// The public exponent $e$ of this key.
+    pub fn exponent(&self) -> &BigUint {
+        &self.exponent
+    }
+
+    /// Constrains `signature` to be a valid PKCS #1 v1.5 signature by this key of
+    /// `digest`, a SHA-256 digest given as a big-endian integer.
+    ///
+    /// Returns an error if the modulus is too short for the encoding.
+    pub fn verify_pkcs1_v15(
+        &self,
+        mut layouter: impl Layouter<BigUintChip>,
+        digest: &AssignedBigUint<BigUintChip>,
+        signature: &AssignedBigUint<BigUintChip>,
+    ) -> Result<(), Error> {
+        let prefix = rsa::encode_pkcs1_v15(&[0; DIGEST_BYTES], self.modulus_bytes)
+            .ok_or(Error::SynthesisError)?;
+
+        signature.constrain_less_than(layouter.namespace(|| "s < n"), &self.modulus)?;
+        let encoded = pow_mod_fixed(
+            layouter.namespace(|| "s^e"),
+            signature,
+            &self.exponent,
+            &self.modulus,
+        )?;
+
+        let digest = digest.resize(layouter.namespace(|| "digest"), DIGEST_BYTES / 8)?;
+        let expected = AssignedBigUint::constant(layouter.namespace(|| "prefix"), &prefix)?
+            .add(layouter.namespace(|| "EM"), &digest)?;
+        encoded.constrain_equal(layouter.namespace(|| "verify"), &expected)
+    }
+}
+
+impl<BigUintChip: BigUintInstructions> Clone for PublicKey<BigUintChip> {
+    fn clone(&self) -> Self {
+        PublicKey {
+            modulus: self.modulus.clone(),
+            modulus_bytes: self.modulus_bytes,
+            exponent: self.exponent.clone(),
+        }
+    }
+}
+
+/// An RSA accumulator.
+#[derive(Debug)]
+pub struct Accumulator<BigUintChip: BigUintInstructions> {
+    value: AssignedBigUint<BigUintChip>,
+    modulus: AssignedBigUint<BigUintChip>,
+}
+
+impl<BigUintChip: BigUintInstructions> Accumulator<BigUintChip> {
+    /// Witnesses an accumulator modulo `modulus`.
+    pub fn new(
+        layouter: impl Layouter<BigUintChip>,
+        value: Option<&BigUint>,
+        modulus: &AssignedBigUint<BigUintChip>,
+    ) -> Result<Self, Error> {
+        AssignedBigUint::new(layouter, value, modulus.num_limbs()).map(|value| Accumulator {
+            value,
+            modulus: modulus.clone(),
+        })
+    }
+
+    /// The value of this accumulator.
+    pub fn value(&self) -> &AssignedBigUint<BigUintChip> {
+        &self.value
+    }
+
+    /// Constrains `witness` to prove that `element`, of at most `element_bits` bits, is
+    /// in this accumulator.
+    pub fn verify_membership(
+        &self,
+        mut layouter: impl Layouter<BigUintChip>,
+        element: &AssignedBigUint<BigUintChip>,
+        element_bits: usize,
+        witness: &AssignedBigUint<BigUintChip>,
+    ) -> Result<(), Error> {
+        let result = pow_mod(
+            layouter.namespace(|| "w^x"),
+            witness,
+            element,
+            element_bits,
+            &self.modulus,
+        )?;
+        result.constrain_equal(layouter.namespace(|| "verify"), &self.value)
+    }
+}
+
+impl<BigUintChip: BigUintInstructions> Clone for Accumulator<BigUintChip> {
+    fn clone(&self) -> Self {
+        Accumulator {
+            value: self.value.clone(),
+            modulus: self.modulus.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Accumulator, PublicKey};
+    use crate::{
+        circuit::{layouter, Layouter},
+        dev::MockProver,
+        gadget::biguint::{AssignedBigUint, BigUintChip, BigUintConfig},
+        pasta::Fp,
+        plonk::{Assignment, Circuit, ConstraintSystem, Error},
+        primitives::{
+            biguint::BigUint,
+            rsa::{self, tests::test_key},
+        },
+    };
+
+    struct SignatureCircuit {
+        public_key: rsa::PublicKey,
+        digest: Option<BigUint>,
+        signature: Option<BigUint>,
+    }
+
+    impl Circuit<Fp> for SignatureCircuit {
+        type Config = BigUintConfig;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> BigUintConfig {
+            BigUintChip::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            config: BigUintConfig,
+        ) -> Result<(), Error> {
+            let mut layouter = layouter::SingleChip::<BigUintChip<Fp>, _>::new(cs, config)?;
+
+            let bytes = self.public_key.modulus_bytes();
+            let public_key = PublicKey::new(
+                layouter.namespace(|| "public key"),
+                Some(&self.public_key.modulus),
+                bytes,
+                self.public_key.exponent.clone(),
+            )?;
+            let digest =
+                AssignedBigUint::new(layouter.namespace(|| "digest"), self.digest.as_ref(), 4)?;
+            let signature = AssignedBigUint::new(
+                layouter.namespace(|| "signature"),
+                self.signature.as_ref(),
+                public_key.modulus().num_limbs(),
+            )?;
+            public_key.verify_pkcs1_v15(layouter.namespace(|| "verify"), &digest, &signature)
+        }
+    }
+
+    #[test]
+    fn pkcs1_v15_signature() {
+        let (public_key, d) = test_key();
+        let digest = [0x5a; 32];
+        let signature = rsa::sign_pkcs1_v15(&public_key, &d, &digest).unwrap();
+
+        let mut circuit = SignatureCircuit {
+            public_key,
+            digest: Some(BigUint::from_bytes_be(&digest)),
+            signature: Some(signature),
+        };
+        let prover = MockProver::run(13, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        let mut other = digest;
+        other[0] ^= 1;
+        circuit.digest = Some(BigUint::from_bytes_be(&other));
+        let prover = MockProver::run(13, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    struct MembershipCircuit {
+        modulus: BigUint,
+        accumulator: Option<BigUint>,
+        element: Option<BigUint>,
+        witness: Option<BigUint>,
+    }
+
+    impl Circuit<Fp> for MembershipCircuit {
+        type Config = BigUintConfig;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> BigUintConfig {
+            BigUintChip::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            config: BigUintConfig,
+        ) -> Result<(), Error> {
+            let mut layouter = layouter::SingleChip::<BigUintChip<Fp>, _>::new(cs, config)?;
+
+            let modulus =
+                AssignedBigUint::constant(layouter.namespace(|| "modulus"), &self.modulus)?;
+            let accumulator = Accumulator::new(
+                layouter.namespace(|| "accumulator"),
+                self.accumulator.as_ref(),
+                &modulus,
+            )?;
+            let element =
+                AssignedBigUint::new(layouter.namespace(|| "element"), self.element.as_ref(), 1)?;
+            let witness = AssignedBigUint::new(
+                layouter.namespace(|| "witness"),
+                self.witness.as_ref(),
+                modulus.num_limbs(),
+            )?;
+            accumulator.verify_membership(
+                layouter.namespace(|| "membership"),
+                &element,
+                9,
+                &witness,
+            )
+        }
+    }
+
+    #[test]
+    fn accumulator_membership() {
+        let modulus = BigUint::from_limbs(&[u64::MAX, u64::MAX, 0x0123_4567]);
+        let g = BigUint::from(3);
+        let elements: Vec<_> = [251, 257, 263].iter().map(|x| BigUint::from(*x)).collect();
+        let accumulator = rsa::accumulate(&g, &elements, &modulus);
+        let witness = rsa::accumulate(&g, &elements[..2], &modulus);
+
+        let mut circuit = MembershipCircuit {
+            modulus,
+            accumulator: Some(accumulator),
+            element: Some(elements[2].clone()),
+            witness: Some(witness),
+        };
+        let prover = MockProver::run(12, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        circuit.element = Some(elements[1].clone());
+        let prover = MockProver::run(12, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
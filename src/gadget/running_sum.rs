@@ -0,0 +1,294 @@
+//! Running-sum decomposition of field elements into windows.
+//!
+//! A field element $z_0$ is decomposed into $n$ little-endian windows $k_i$ of $K$ bits
+//! by the running sum $z_{i + 1} = (z_i - k_i) / 2^K$, so that
+//! $z_0 = \sum_{i < n} k_i 2^{K i} + 2^{K n} z_n$. A strict decomposition also
+//! constrains $z_n = 0$, which proves that $z_0$ fits in $n$ windows, as long as every
+//! window is constrained to $[0, 2^K)$. Windows of a few bits, such as the 3-bit windows
+//! of fixed-base scalar multiplication, can be range-checked by a polynomial gate, and
+//! wider windows by a lookup enabled by [`RunningSumConfig::q_running`], which is how
+//! [`RangeCheckChip`](super::range_check::RangeCheckChip) uses this decomposition.
+//!
+//! # Layout
+//!
+//! A decomposition into $n$ windows uses $n + 1$ rows of two advice columns chosen by
+//! the chip that configures it. Row $i$ holds $z_i$ and $k_i$, and row $n$ holds $z_n$.
+
+use crate::{
+    arithmetic::FieldExt,
+    circuit::{Chip, Region},
+    gadget::utilities::AssignedValue,
+    plonk::{Advice, Column, ConstraintSystem, Error, Expression, Selector},
+    poly::Rotation,
+};
+
+/// An assigned running-sum decomposition.
+#[derive(Clone, Debug)]
+pub struct RunningSum<F: FieldExt> {
+    zs: Vec<AssignedValue<F>>,
+    windows: Vec<AssignedValue<F>>,
+}
+
+impl<F: FieldExt> RunningSum<F> {
+    /// The running sums $z_0, \dots, z_n$, where $z_0$ is the decomposed value.
+    pub fn zs(&self) -> &[AssignedValue<F>] {
+        &self.zs
+    }
+
+    /// The windows $k_0, \dots, k_{n - 1}$, from the least significant.
+    pub fn windows(&self) -> &[AssignedValue<F>] {
+        &self.windows
+    }
+}
+
+/// Returns the little-endian `window_bits`-bit windows of `value`.
+pub fn decompose_windows<F: FieldExt>(
+    value: &F,
+    window_bits: usize,
+    num_windows: usize,
+) -> Vec<u64> {
+    assert!(window_bits <= 64);
+
+    let repr = value.to_repr();
+    let bytes = repr.as_ref();
+    let bit = |i: usize| bytes.get(i / 8).map_or(0, |byte| (byte >> (i % 8)) & 1);
+    (0..num_windows)
+        .map(|w| (0..window_bits).fold(0, |acc, i| acc | (bit(w * window_bits + i) as u64) << i))
+        .collect()
+}
+
+/// Configuration for a running-sum decomposition.
+#[derive(Clone, Copy, Debug)]
+pub struct RunningSumConfig {
+    z: Column<Advice>,
+    window: Column<Advice>,
+    q_running: Selector,
+    q_strict: Selector,
+    window_bits: usize,
+}
+
+impl RunningSumConfig {
+    /// Configures a decomposition into `window_bits`-bit windows in the given columns.
+    ///
+    /// If `range_check_windows` is set, each window is constrained to $[0, 2^K)$ by a
+    /// gate of degree $2^K + 1$, which is only practical for a few bits. Otherwise the
+    /// caller must range-check the windows, usually with a lookup.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `window_bits` is zero or greater than 24.
+    pub fn configure<F: FieldExt>(
+        meta: &mut ConstraintSystem<F>,
+        z: Column<Advice>,
+        window: Column<Advice>,
+        window_bits: usize,
+        range_check_windows: bool,
+    ) -> Self {
+        assert!(window_bits > 0 && window_bits <= 24);
+
+        let config = RunningSumConfig {
+            z,
+            window,
+            q_running: meta.selector(),
+            q_strict: meta.selector(),
+            window_bits,
+        };
+
+        // z_i = k_i + 2^K z_{i + 1}
+        let two_pow_k = F::from_u64(1 << window_bits);
+        meta.create_gate("running sum", |meta| {
+            let z_cur = meta.query_advice(config.z, Rotation::cur());
+            let z_next = meta.query_advice(config.z, Rotation::next());
+            let window = meta.query_advice(config.window, Rotation::cur());
+            let q_running = meta.query_selector(config.q_running, Rotation::cur());
+
+            q_running * (z_cur - window - z_next * two_pow_k)
+        });
+        if range_check_windows {
+            meta.create_gate("running sum window", |meta| {
+                let window = meta.query_advice(config.window, Rotation::cur());
+                let q_running = meta.query_selector(config.q_running, Rotation::cur());
+
+                let range = (0..1u64 << window_bits)
+                    .fold(Expression::Constant(F::one()), |acc, i| {
+                        acc * (window.clone() - Expression::Constant(F::from_u64(i)))
+                    });
+                q_running * range
+            });
+        }
+        meta.create_gate("running sum end", |meta| {
+            let z = meta.query_advice(config.z, Rotation::cur());
+            let q_strict = meta.query_selector(config.q_strict, Rotation::cur());
+
+            q_strict * z
+        });
+
+        config
+    }
+
+    /// The column holding the running sums.
+    pub fn z(&self) -> Column<Advice> {
+        self.z
+    }
+
+    /// The column holding the windows.
+    pub fn window(&self) -> Column<Advice> {
+        self.window
+    }
+
+    /// The selector enabled on each row holding a window.
+    pub fn q_running(&self) -> Selector {
+        self.q_running
+    }
+
+    /// The number of bits $K$ of each window.
+    pub fn window_bits(&self) -> usize {
+        self.window_bits
+    }
+
+    /// Assigns `value` in the running-sum column at `offset`, without constraining it.
+    pub fn assign_value<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        region: &mut Region<'_, Ch>,
+        offset: usize,
+        value: Option<F>,
+    ) -> Result<AssignedValue<F>, Error> {
+        let cell = region.assign_advice(
+            || "z",
+            self.z,
+            offset,
+            || value.ok_or(Error::SynthesisError),
+        )?;
+        Ok(AssignedValue { cell, value })
+    }
+
+    /// Assigns the decomposition of `value` into `num_windows` windows at `offset`,
+    /// constraining the final running sum to zero if `strict` is set.
+    ///
+    /// The caller is responsible for constraining $z_0$ to equal the value being
+    /// decomposed.
+    pub fn assign<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        region: &mut Region<'_, Ch>,
+        offset: usize,
+        value: Option<F>,
+        num_windows: usize,
+        strict: bool,
+    ) -> Result<RunningSum<F>, Error> {
+        let windows = value.map(|value| decompose_windows(&value, self.window_bits, num_windows));
+        let two_pow_k_inv = F::from_u64(1 << self.window_bits).invert().unwrap();
+
+        let mut z = value;
+        let mut zs = vec![self.assign_value(region, offset, z)?];
+        let mut assigned_windows = Vec::with_capacity(num_windows);
+        for i in 0..num_windows {
+            let row = offset + i;
+            self.q_running.enable(region, row)?;
+
+            let k = windows.as_ref().map(|windows| F::from_u64(windows[i]));
+            let cell = region.assign_advice(
+                || format!("k_{}", i),
+                self.window,
+                row,
+                || k.ok_or(Error::SynthesisError),
+            )?;
+            assigned_windows.push(AssignedValue { cell, value: k });
+
+            z = z.zip(k).map(|(z, k)| (z - k) * two_pow_k_inv);
+            zs.push(self.assign_value(region, row + 1, z)?);
+        }
+        if strict {
+            self.q_strict.enable(region, offset + num_windows)?;
+        }
+
+        Ok(RunningSum {
+            zs,
+            windows: assigned_windows,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RunningSum, RunningSumConfig};
+    use crate::{
+        arithmetic::FieldExt,
+        circuit::{layouter, Chip, Layouter},
+        dev::MockProver,
+        pasta::Fp,
+        plonk::{Assignment, Circuit, ConstraintSystem, Error},
+    };
+
+    #[derive(Debug)]
+    struct RunningSumChip;
+
+    impl Chip for RunningSumChip {
+        type Config = RunningSumConfig;
+        type Loaded = ();
+        type Field = Fp;
+
+        fn load(_: &mut impl Layouter<Self>) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    struct RunningSumCircuit {
+        value: Option<Fp>,
+        num_windows: usize,
+        strict: bool,
+        expected_top: Option<Fp>,
+    }
+
+    impl Circuit<Fp> for RunningSumCircuit {
+        type Config = RunningSumConfig;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> RunningSumConfig {
+            let z = meta.advice_column();
+            let window = meta.advice_column();
+            RunningSumConfig::configure(meta, z, window, 3, true)
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            config: RunningSumConfig,
+        ) -> Result<(), Error> {
+            let mut layouter = layouter::SingleChip::<RunningSumChip, _>::new(cs, config)?;
+
+            let running_sum: RunningSum<Fp> = layouter.assign_region(
+                || "decompose",
+                |mut region| {
+                    config.assign(&mut region, 0, self.value, self.num_windows, self.strict)
+                },
+            )?;
+            let top = running_sum.zs().last().unwrap().value();
+            assert_eq!(running_sum.windows().len(), self.num_windows);
+            if self.value.is_some() && top != self.expected_top {
+                return Err(Error::SynthesisError);
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn running_sum() {
+        let run = |value: u64, num_windows: usize, strict: bool, top: u64| {
+            let circuit = RunningSumCircuit {
+                value: Some(Fp::from_u64(value)),
+                num_windows,
+                strict,
+                expected_top: Some(Fp::from_u64(top)),
+            };
+            MockProver::run(4, &circuit, vec![]).unwrap().verify()
+        };
+
+        // 0o7654 in four 3-bit windows.
+        assert_eq!(run(0o7654, 4, true, 0), Ok(()));
+        // A non-strict decomposition leaves the high part in z_n.
+        assert_eq!(run(0o7654, 2, false, 0o76), Ok(()));
+        // A strict decomposition fails if the value does not fit.
+        assert!(run(0o7654, 2, true, 0o76).is_err());
+    }
+}
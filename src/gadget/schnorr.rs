@@ -0,0 +1,139 @@
+//! Gadget and chips for verifying Schnorr signatures over a curve whose base field is
+//! the circuit field, such as Pallas.
+//!
+//! Verifying a signature $(R, s)$ on a message $M$ under a public key $P$ hashes
+//! $(x_R, y_R, x_P, y_P, M)$ with the [`Hash`](struct@Hash) gadget, and checks that
+//! $\[s\] G = R + \[e\] P$. The bits of the hash are used directly as an endoscaling
+//! scalar $e$, so $\[e\] P$ is computed by
+//! [`EccInstructions::mul_endo`](super::ecc::EccInstructions::mul_endo), and
+//! $\[s\] G$ by a fixed-base multiplication. The values being verified are computed
+//! outside of a circuit by [`crate::primitives::schnorr`].
+
+use super::{
+    ecc::{EccInstructions, FixedPoint, Point},
+    poseidon::{Hash, PoseidonDuplexInstructions},
+};
+use crate::{
+    arithmetic::CurveAffine,
+    circuit::Layouter,
+    plonk::Error,
+    primitives::{poseidon::VariableLength, schnorr},
+};
+
+mod chip;
+pub use chip::{SchnorrChip, SchnorrConfig};
+
+/// The set of circuit instructions required to use the [`PublicKey`] and [`Signature`]
+/// gadgets.
+pub trait SchnorrInstructions<C: CurveAffine>:
+    EccInstructions<C> + PoseidonDuplexInstructions<3, 2>
+{
+    /// Returns the coordinates of `point` as words that can be hashed.
+    ///
+    /// Returns an error if `point` is the identity.
+    fn point_words(
+        layouter: &mut impl Layouter<Self>,
+        point: &Self::Point,
+    ) -> Result<[Self::Word; 2], Error>;
+
+    /// Returns `[e] base`, where `e` is the scalar encoded by the
+    /// [`CHALLENGE_BITS`](crate::primitives::schnorr::CHALLENGE_BITS) little-endian
+    /// bits of `challenge` using endoscaling.
+    ///
+    /// The bits are constrained to encode `challenge`, so this fails unless it fits in
+    /// that many bits.
+    fn mul_challenge(
+        layouter: &mut impl Layouter<Self>,
+        base: &Self::Point,
+        challenge: &Self::Word,
+    ) -> Result<Self::Point, Error>;
+}
+
+/// A Schnorr signature.
+#[derive(Debug)]
+pub struct Signature<C: CurveAffine, SchnorrChip: SchnorrInstructions<C>> {
+    r: Point<C, SchnorrChip>,
+    s: Option<C::Scalar>,
+}
+
+impl<C: CurveAffine, SchnorrChip: SchnorrInstructions<C>> Signature<C, SchnorrChip> {
+    /// Witnesses a signature.
+    ///
+    /// $s$ is only witnessed when the signature is verified.
+    pub fn new(
+        layouter: impl Layouter<SchnorrChip>,
+        value: Option<schnorr::Signature<C>>,
+    ) -> Result<Self, Error> {
+        let r = Point::new(layouter, value.map(|sig| sig.r))?;
+        Ok(Signature {
+            r,
+            s: value.map(|sig| sig.s),
+        })
+    }
+
+    /// The nonce point.
+    pub fn r(&self) -> &Point<C, SchnorrChip> {
+        &self.r
+    }
+}
+
+impl<C: CurveAffine, SchnorrChip: SchnorrInstructions<C>> Clone for Signature<C, SchnorrChip> {
+    fn clone(&self) -> Self {
+        Signature {
+            r: self.r.clone(),
+            s: self.s,
+        }
+    }
+}
+
+/// A Schnorr public key.
+#[derive(Debug)]
+pub struct PublicKey<C: CurveAffine, SchnorrChip: SchnorrInstructions<C>> {
+    inner: Point<C, SchnorrChip>,
+}
+
+impl<C: CurveAffine, SchnorrChip: SchnorrInstructions<C>> PublicKey<C, SchnorrChip> {
+    /// Witnesses a public key.
+    pub fn new(layouter: impl Layouter<SchnorrChip>, value: Option<C>) -> Result<Self, Error> {
+        Point::new(layouter, value).map(|inner| PublicKey { inner })
+    }
+
+    /// The point $P$ of this public key.
+    pub fn point(&self) -> &Point<C, SchnorrChip> {
+        &self.inner
+    }
+
+    /// Constrains `signature` to be a valid signature of `message` by this key.
+    pub fn verify(
+        &self,
+        mut layouter: impl Layouter<SchnorrChip>,
+        signature: &Signature<C, SchnorrChip>,
+        message: &[SchnorrChip::Word],
+    ) -> Result<(), Error> {
+        let r = SchnorrChip::point_words(&mut layouter, signature.r.inner())?;
+        let p = SchnorrChip::point_words(&mut layouter, self.inner.inner())?;
+        let mut input = vec![r[0], r[1], p[0], p[1]];
+        input.extend_from_slice(message);
+        let challenge = Hash::<SchnorrChip, _, 3, 2>::hash(
+            layouter.namespace(|| "challenge"),
+            VariableLength,
+            &input,
+        )?;
+
+        // [s] G = R + [e] P
+        let e_p = SchnorrChip::mul_challenge(&mut layouter, self.inner.inner(), &challenge)
+            .map(Point::from_inner)?;
+        let rhs = signature.r.add(layouter.namespace(|| "R + [e] P"), &e_p)?;
+        let lhs = FixedPoint::<C, SchnorrChip>::new(C::generator())
+            .mul(layouter.namespace(|| "[s] G"), signature.s)?;
+        lhs.constrain_equal(layouter.namespace(|| "verify"), &rhs)
+    }
+}
+
+impl<C: CurveAffine, SchnorrChip: SchnorrInstructions<C>> Clone for PublicKey<C, SchnorrChip> {
+    fn clone(&self) -> Self {
+        PublicKey {
+            inner: self.inner.clone(),
+        }
+    }
+}
@@ -0,0 +1,401 @@
+//! A chip implementing [`SchnorrInstructions`] with the ECC and Poseidon chips.
+//!
+//! # Layout
+//!
+//! The chip includes an [`EccConfig`] and a [`Pow5T3Config`] configured with
+//! [`P128Pow5T3`], and implements [`EccInstructions`] and
+//! [`PoseidonDuplexInstructions`] with them. A permutation over the `x_p, y_p` columns
+//! of the ECC configuration, the Poseidon state columns and a `z` column connects the
+//! two, so the coordinates of a point are copied into the state columns to be hashed.
+//!
+//! Multiplying by a challenge lays out the rows of
+//! [`EccInstructions::mul_endo`], and places a running sum in the `z` column of each
+//! round row. For the pair of bits $(b_{2j}, b_{2j + 1})$ in the `window` and `negate`
+//! columns, from the most significant, $z = 4 z_{\mathsf{prev}} + b_{2j} + 2 b_{2j + 1}$
+//! where $z_{\mathsf{prev}}$ is in the previous round row (or zero for the first
+//! round). The final running sum is constrained to equal the challenge, and is less
+//! than $2^{254}$, so the bits are its unique encoding.
+
+use std::marker::PhantomData;
+
+use ff::{Field, PrimeField};
+
+use super::SchnorrInstructions;
+use crate::{
+    arithmetic::{CurveAffine, FieldExt},
+    circuit::{Chip, Layouter},
+    gadget::{
+        ecc::{EccChip, EccConfig, EccFixedPoint, EccInstructions, EccPoint, EccX},
+        poseidon::{
+            PoseidonDuplexInstructions, PoseidonInstructions, Pow5T3Chip, Pow5T3Config,
+            SpongeState, StateWord,
+        },
+    },
+    plonk::{Advice, Column, ConstraintSystem, Error, Expression, Permutation, Selector},
+    poly::Rotation,
+    primitives::{
+        poseidon::{P128Pow5T3, Spec, State},
+        schnorr::CHALLENGE_BITS,
+    },
+};
+
+/// Configuration for a [`SchnorrChip`].
+#[derive(Clone, Debug)]
+pub struct SchnorrConfig<F: FieldExt> {
+    ecc: EccConfig,
+    poseidon: Pow5T3Config<F>,
+    z: Column<Advice>,
+    s_challenge: Selector,
+    s_start: Selector,
+    perm: Permutation,
+}
+
+/// A chip verifying Schnorr signatures over the curve `C`.
+#[derive(Debug)]
+pub struct SchnorrChip<C: CurveAffine> {
+    _marker: PhantomData<C>,
+}
+
+impl<C: CurveAffine> SchnorrChip<C>
+where
+    P128Pow5T3: Spec<C::Base, 3, 2>,
+{
+    /// Configures this chip for use in a circuit.
+    pub fn configure(meta: &mut ConstraintSystem<C::Base>) -> SchnorrConfig<C::Base> {
+        let ecc = EccChip::<C>::configure(meta);
+        let state = [
+            meta.advice_column(),
+            meta.advice_column(),
+            meta.advice_column(),
+        ];
+        let partial_sbox = meta.advice_column();
+        let poseidon = Pow5T3Chip::configure::<P128Pow5T3>(meta, state, partial_sbox);
+        let z = meta.advice_column();
+
+        let s_challenge = meta.selector();
+        let s_start = meta.selector();
+
+        let perm = Permutation::new(
+            meta,
+            &[
+                ecc.x_p.into(),
+                ecc.y_p.into(),
+                state[0].into(),
+                state[1].into(),
+                state[2].into(),
+                z.into(),
+            ],
+        );
+
+        // z = 4 z_prev + window + 2 negate, where z_prev is taken as zero at the start.
+        meta.create_gate("challenge running sum", |meta| {
+            let z_cur = meta.query_advice(z, Rotation::cur());
+            let z_prev = meta.query_advice(z, Rotation(-2));
+            let window = meta.query_advice(ecc.window, Rotation::cur());
+            let negate = meta.query_advice(ecc.negate, Rotation::cur());
+            let s_challenge = meta.query_selector(s_challenge, Rotation::cur());
+            let s_start = meta.query_selector(s_start, Rotation::cur());
+            let one = Expression::Constant(C::Base::one());
+
+            s_challenge
+                * (z_cur
+                    - (one - s_start) * z_prev * C::Base::from_u64(4)
+                    - window
+                    - negate * C::Base::from_u64(2))
+        });
+
+        SchnorrConfig {
+            ecc,
+            poseidon,
+            z,
+            s_challenge,
+            s_start,
+            perm,
+        }
+    }
+}
+
+impl<C: CurveAffine> Chip for SchnorrChip<C> {
+    type Config = SchnorrConfig<C::Base>;
+    type Loaded = ();
+    type Field = C::Base;
+
+    fn load(_layouter: &mut impl Layouter<Self>) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<C: CurveAffine> EccInstructions<C> for SchnorrChip<C> {
+    type Point = EccPoint<C>;
+    type X = EccX<C::Base>;
+    type FixedPoint = EccFixedPoint<C>;
+
+    fn fixed_point(base: C) -> Self::FixedPoint {
+        EccChip::<C>::fixed_point(base)
+    }
+
+    fn witness_point(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<C>,
+    ) -> Result<Self::Point, Error> {
+        layouter.config().ecc.clone().witness_point(layouter, value)
+    }
+
+    fn extract_p(point: &Self::Point) -> Self::X {
+        point.extract_p()
+    }
+
+    fn add(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Point,
+        b: &Self::Point,
+    ) -> Result<Self::Point, Error> {
+        layouter.config().ecc.clone().add(layouter, a, b)
+    }
+
+    fn double(layouter: &mut impl Layouter<Self>, a: &Self::Point) -> Result<Self::Point, Error> {
+        layouter.config().ecc.clone().double(layouter, a)
+    }
+
+    fn mul_fixed(
+        layouter: &mut impl Layouter<Self>,
+        scalar: Option<C::Scalar>,
+        base: &Self::FixedPoint,
+    ) -> Result<Self::Point, Error> {
+        layouter
+            .config()
+            .ecc
+            .clone()
+            .mul_fixed(layouter, scalar, base)
+    }
+
+    fn mul_endo(
+        layouter: &mut impl Layouter<Self>,
+        base: &Self::Point,
+        bits: &[Option<bool>],
+    ) -> Result<Self::Point, Error> {
+        layouter.config().ecc.clone().mul_endo(layouter, base, bits)
+    }
+
+    fn constrain_equal(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Point,
+        b: &Self::Point,
+    ) -> Result<(), Error> {
+        layouter
+            .config()
+            .ecc
+            .clone()
+            .constrain_equal(layouter, a, b)
+    }
+}
+
+impl<C: CurveAffine> PoseidonInstructions<3> for SchnorrChip<C> {
+    type Word = StateWord<C::Base>;
+
+    fn permute(
+        layouter: &mut impl Layouter<Self>,
+        initial_state: &State<Self::Word, 3>,
+    ) -> Result<State<Self::Word, 3>, Error> {
+        layouter
+            .config()
+            .poseidon
+            .clone()
+            .permute(layouter, initial_state)
+    }
+}
+
+impl<C: CurveAffine> PoseidonDuplexInstructions<3, 2> for SchnorrChip<C> {
+    fn load_message(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<C::Base>,
+    ) -> Result<Self::Word, Error> {
+        let config = layouter.config().poseidon.sponge.clone();
+        config.load_message(layouter, value)
+    }
+
+    fn initial_state(
+        layouter: &mut impl Layouter<Self>,
+        initial_capacity_element: C::Base,
+    ) -> Result<State<Self::Word, 3>, Error> {
+        let config = layouter.config().poseidon.sponge.clone();
+        config.initial_state(layouter, initial_capacity_element)
+    }
+
+    fn pad_and_add(
+        layouter: &mut impl Layouter<Self>,
+        initial_state: &State<Self::Word, 3>,
+        input: &SpongeState<Self::Word, C::Base, 2>,
+    ) -> Result<State<Self::Word, 3>, Error> {
+        let config = layouter.config().poseidon.sponge.clone();
+        config.pad_and_add(layouter, initial_state, input)
+    }
+
+    fn get_output(state: &State<Self::Word, 3>) -> [Self::Word; 2] {
+        [state[0], state[1]]
+    }
+
+    fn absorb_blocks(
+        layouter: &mut impl Layouter<Self>,
+        initial_state: &State<Self::Word, 3>,
+        inputs: &[SpongeState<Self::Word, C::Base, 2>],
+    ) -> Result<State<Self::Word, 3>, Error> {
+        layouter
+            .config()
+            .poseidon
+            .clone()
+            .absorb_blocks(layouter, initial_state, inputs)
+    }
+}
+
+impl<C: CurveAffine> SchnorrInstructions<C> for SchnorrChip<C> {
+    fn point_words(
+        layouter: &mut impl Layouter<Self>,
+        point: &Self::Point,
+    ) -> Result<[Self::Word; 2], Error> {
+        let config = layouter.config().clone();
+        let coordinates = point
+            .point()
+            .map(|point| Option::from(point.get_xy()).ok_or(Error::SynthesisError))
+            .transpose()?;
+
+        layouter.assign_region(
+            || "point words",
+            |mut region| {
+                let mut word = |i: usize, cell, value: Option<C::Base>| {
+                    let var = region.assign_advice(
+                        || format!("coordinate {}", i),
+                        config.poseidon.sponge.state[i],
+                        0,
+                        || value.ok_or(Error::SynthesisError),
+                    )?;
+                    region.constrain_equal(&config.perm, cell, var)?;
+                    Ok(StateWord { var, value })
+                };
+                Ok([
+                    word(0, point.x(), coordinates.map(|(x, _)| x))?,
+                    word(1, point.y(), coordinates.map(|(_, y)| y))?,
+                ])
+            },
+        )
+    }
+
+    fn mul_challenge(
+        layouter: &mut impl Layouter<Self>,
+        base: &Self::Point,
+        challenge: &Self::Word,
+    ) -> Result<Self::Point, Error> {
+        let config = layouter.config().clone();
+        let bits: Vec<_> = (0..CHALLENGE_BITS)
+            .map(|i| {
+                challenge.value.map(|value| {
+                    let repr = value.to_repr();
+                    (repr.as_ref()[i / 8] >> (i % 8)) & 1 == 1
+                })
+            })
+            .collect();
+
+        layouter.assign_region(
+            || "challenge multiplication",
+            |mut region| {
+                let product = config.ecc.assign_mul_endo(&mut region, 0, base, &bits)?;
+
+                let mut z = Some(C::Base::zero());
+                let mut cell = None;
+                for (i, pair) in bits.chunks(2).rev().enumerate() {
+                    let row = 2 + 2 * i;
+                    config.s_challenge.enable(&mut region, row)?;
+                    if i == 0 {
+                        config.s_start.enable(&mut region, row)?;
+                    }
+
+                    z = z.zip(pair[0]).zip(pair[1]).map(|((z, low), high)| {
+                        z * C::Base::from_u64(4)
+                            + C::Base::from_u64(low as u64)
+                            + C::Base::from_u64(2 * high as u64)
+                    });
+                    cell = Some(region.assign_advice(
+                        || format!("z_{}", i + 1),
+                        config.z,
+                        row,
+                        || z.ok_or(Error::SynthesisError),
+                    )?);
+                }
+                region.constrain_equal(&config.perm, cell.unwrap(), challenge.var)?;
+
+                Ok(product)
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SchnorrChip, SchnorrConfig};
+    use crate::{
+        arithmetic::FieldExt,
+        circuit::{layouter, Layouter},
+        dev::MockProver,
+        gadget::{
+            poseidon::PoseidonDuplexInstructions,
+            schnorr::{PublicKey, Signature},
+        },
+        pasta::pallas,
+        plonk::{Assignment, Circuit, ConstraintSystem, Error},
+        primitives::schnorr,
+    };
+
+    struct SchnorrCircuit {
+        public_key: Option<pallas::Affine>,
+        signature: Option<schnorr::Signature<pallas::Affine>>,
+        message: Vec<Option<pallas::Base>>,
+    }
+
+    impl Circuit<pallas::Base> for SchnorrCircuit {
+        type Config = SchnorrConfig<pallas::Base>;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+            SchnorrChip::<pallas::Affine>::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<pallas::Base>,
+            config: Self::Config,
+        ) -> Result<(), Error> {
+            let mut layouter =
+                layouter::SingleChip::<SchnorrChip<pallas::Affine>, _>::new(cs, config)?;
+
+            let public_key = PublicKey::new(layouter.namespace(|| "P"), self.public_key)?;
+            let signature = Signature::new(layouter.namespace(|| "signature"), self.signature)?;
+            let message = self
+                .message
+                .iter()
+                .map(|value| SchnorrChip::load_message(&mut layouter, *value))
+                .collect::<Result<Vec<_>, _>>()?;
+            public_key.verify(layouter.namespace(|| "verify"), &signature, &message)
+        }
+    }
+
+    #[test]
+    fn schnorr_verify() {
+        let secret_key = pallas::Scalar::from_u64(0x0123_4567_89ab_cdef);
+        let nonce = pallas::Scalar::from_u64(0xfedc_ba98_7654_3210);
+        let message = [pallas::Base::from_u64(1), pallas::Base::from_u64(2)];
+        let signature = schnorr::sign(&secret_key, &message, &nonce).unwrap();
+
+        let mut circuit = SchnorrCircuit {
+            public_key: Some(schnorr::public_key(&secret_key)),
+            signature: Some(signature),
+            message: message.iter().copied().map(Some).collect(),
+        };
+        let prover = MockProver::run(10, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        circuit.message[1] = Some(pallas::Base::from_u64(3));
+        let prover = MockProver::run(10, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
@@ -0,0 +1,244 @@
+//! Gadget and chips for the SHA-256 hash function.
+//!
+//! [`Sha256`] mirrors the API of the `digest` crate: a hasher is created with
+//! [`Sha256::new`], fed with [`Sha256::update`], and consumed by [`Sha256::finalize`],
+//! or the one-shot [`Sha256::digest`] can be used. Messages are provided as 32-bit
+//! big-endian words, so their length must be a multiple of four bytes.
+//!
+//! The padding of the message is fixed by the circuit (the length of the message being
+//! part of the circuit structure), and the underlying compression function is provided
+//! by a chip implementing [`Sha256Instructions`], such as [`Sha256Chip`].
+
+use std::convert::TryInto;
+use std::fmt;
+
+use crate::{
+    circuit::{Chip, Layouter},
+    plonk::Error,
+};
+
+mod compression;
+pub use compression::{AssignedWord, Sha256Chip, Sha256Config};
+
+/// The number of 32-bit words in a message block.
+pub const BLOCK_SIZE: usize = 16;
+
+/// The number of 32-bit words in the state, and in the digest.
+pub const DIGEST_SIZE: usize = 8;
+
+/// The number of rounds of the compression function, and the number of words in the
+/// message schedule.
+pub const ROUNDS: usize = 64;
+
+/// The initial hash value.
+pub const IV: [u32; DIGEST_SIZE] = [
+    0x6a09_e667,
+    0xbb67_ae85,
+    0x3c6e_f372,
+    0xa54f_f53a,
+    0x510e_527f,
+    0x9b05_688c,
+    0x1f83_d9ab,
+    0x5be0_cd19,
+];
+
+/// The round constants.
+pub const ROUND_CONSTANTS: [u32; ROUNDS] = [
+    0x428a_2f98,
+    0x7137_4491,
+    0xb5c0_fbcf,
+    0xe9b5_dba5,
+    0x3956_c25b,
+    0x59f1_11f1,
+    0x923f_82a4,
+    0xab1c_5ed5,
+    0xd807_aa98,
+    0x1283_5b01,
+    0x2431_85be,
+    0x550c_7dc3,
+    0x72be_5d74,
+    0x80de_b1fe,
+    0x9bdc_06a7,
+    0xc19b_f174,
+    0xe49b_69c1,
+    0xefbe_4786,
+    0x0fc1_9dc6,
+    0x240c_a1cc,
+    0x2de9_2c6f,
+    0x4a74_84aa,
+    0x5cb0_a9dc,
+    0x76f9_88da,
+    0x983e_5152,
+    0xa831_c66d,
+    0xb003_27c8,
+    0xbf59_7fc7,
+    0xc6e0_0bf3,
+    0xd5a7_9147,
+    0x06ca_6351,
+    0x1429_2967,
+    0x27b7_0a85,
+    0x2e1b_2138,
+    0x4d2c_6dfc,
+    0x5338_0d13,
+    0x650a_7354,
+    0x766a_0abb,
+    0x81c2_c92e,
+    0x9272_2c85,
+    0xa2bf_e8a1,
+    0xa81a_664b,
+    0xc24b_8b70,
+    0xc76c_51a3,
+    0xd192_e819,
+    0xd699_0624,
+    0xf40e_3585,
+    0x106a_a070,
+    0x19a4_c116,
+    0x1e37_6c08,
+    0x2748_774c,
+    0x34b0_bcb5,
+    0x391c_0cb3,
+    0x4ed8_aa4a,
+    0x5b9c_ca4f,
+    0x682e_6ff3,
+    0x748f_82ee,
+    0x78a5_636f,
+    0x84c8_7814,
+    0x8cc7_0208,
+    0x90be_fffa,
+    0xa450_6ceb,
+    0xbef9_a3f7,
+    0xc671_78f2,
+];
+
+/// A word of a message block: either part of the message, or a constant padding word
+/// that is fixed by the circuit.
+#[derive(Clone, Copy, Debug)]
+pub enum BlockWord<W> {
+    /// A message word provided by the prover.
+    Message(W),
+    /// A padding word that is known when the circuit is configured.
+    Padding(u32),
+}
+
+/// The set of circuit instructions required to use the [`Sha256`] gadget.
+pub trait Sha256Instructions: Chip {
+    /// Variable representing a 32-bit word.
+    type Word: Copy + fmt::Debug;
+
+    /// Witnesses a 32-bit message word.
+    fn load_word(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<u32>,
+    ) -> Result<Self::Word, Error>;
+
+    /// Returns the initial state, fixed to [`IV`].
+    fn initialization_vector(
+        layouter: &mut impl Layouter<Self>,
+    ) -> Result<[Self::Word; DIGEST_SIZE], Error>;
+
+    /// Expands a message block into the message schedule.
+    fn message_schedule(
+        layouter: &mut impl Layouter<Self>,
+        block: &[BlockWord<Self::Word>; BLOCK_SIZE],
+    ) -> Result<[Self::Word; ROUNDS], Error>;
+
+    /// Applies the compression function to the given state and message schedule,
+    /// returning the next state (including the final addition of the initial state).
+    fn compress(
+        layouter: &mut impl Layouter<Self>,
+        initial_state: &[Self::Word; DIGEST_SIZE],
+        schedule: &[Self::Word; ROUNDS],
+    ) -> Result<[Self::Word; DIGEST_SIZE], Error>;
+}
+
+/// The output of a SHA-256 hash, as eight 32-bit big-endian words.
+#[derive(Clone, Copy, Debug)]
+pub struct Sha256Digest<W>(pub [W; DIGEST_SIZE]);
+
+/// A SHA-256 hasher.
+#[derive(Debug)]
+pub struct Sha256<Sha256Chip: Sha256Instructions> {
+    state: [Sha256Chip::Word; DIGEST_SIZE],
+    cur_block: Vec<BlockWord<Sha256Chip::Word>>,
+    len: usize,
+}
+
+impl<Sha256Chip: Sha256Instructions> Sha256<Sha256Chip> {
+    /// Creates a new hasher instance.
+    pub fn new(mut layouter: impl Layouter<Sha256Chip>) -> Result<Self, Error> {
+        Ok(Sha256 {
+            state: Sha256Chip::initialization_vector(&mut layouter)?,
+            cur_block: Vec::with_capacity(BLOCK_SIZE),
+            len: 0,
+        })
+    }
+
+    /// Digests the given message words, compressing each block as it is filled.
+    pub fn update(
+        &mut self,
+        mut layouter: impl Layouter<Sha256Chip>,
+        data: &[Sha256Chip::Word],
+    ) -> Result<(), Error> {
+        for word in data {
+            self.push(&mut layouter, BlockWord::Message(*word))?;
+        }
+        self.len += data.len();
+        Ok(())
+    }
+
+    /// Pads the digested message, and returns its hash.
+    pub fn finalize(
+        mut self,
+        mut layouter: impl Layouter<Sha256Chip>,
+    ) -> Result<Sha256Digest<Sha256Chip::Word>, Error> {
+        // The message is followed by a single one bit, zeroes until the block is two
+        // words from being full, and the length of the message in bits.
+        let bit_len = self.len as u64 * 32;
+        let mut padding = vec![0x8000_0000];
+        while (self.cur_block.len() + padding.len()) % BLOCK_SIZE != BLOCK_SIZE - 2 {
+            padding.push(0);
+        }
+        padding.extend_from_slice(&[(bit_len >> 32) as u32, bit_len as u32]);
+
+        for word in padding {
+            self.push(&mut layouter, BlockWord::Padding(word))?;
+        }
+        assert!(self.cur_block.is_empty());
+
+        Ok(Sha256Digest(self.state))
+    }
+
+    /// Convenience function to compute the hash of the given message.
+    pub fn digest(
+        mut layouter: impl Layouter<Sha256Chip>,
+        data: &[Sha256Chip::Word],
+    ) -> Result<Sha256Digest<Sha256Chip::Word>, Error> {
+        let mut hasher = Self::new(layouter.namespace(|| "init"))?;
+        hasher.update(layouter.namespace(|| "update"), data)?;
+        hasher.finalize(layouter.namespace(|| "finalize"))
+    }
+
+    fn push(
+        &mut self,
+        layouter: &mut impl Layouter<Sha256Chip>,
+        word: BlockWord<Sha256Chip::Word>,
+    ) -> Result<(), Error> {
+        self.cur_block.push(word);
+        if self.cur_block.len() == BLOCK_SIZE {
+            let block: [_; BLOCK_SIZE] = self
+                .cur_block
+                .drain(..)
+                .collect::<Vec<_>>()
+                .try_into()
+                .unwrap();
+            let schedule =
+                Sha256Chip::message_schedule(&mut layouter.namespace(|| "schedule"), &block)?;
+            self.state = Sha256Chip::compress(
+                &mut layouter.namespace(|| "compress"),
+                &self.state,
+                &schedule,
+            )?;
+        }
+        Ok(())
+    }
+}
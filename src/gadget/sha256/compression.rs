@@ -0,0 +1,749 @@
+//! A chip for the SHA-256 message schedule and compression function.
+//!
+//! Every 32-bit word is assigned alongside its bit decomposition, so that the bitwise
+//! functions of SHA-256 are low-degree polynomials in the bits, and rotations and shifts
+//! are free. The chip has two lanes (for the `A` and `E` words of the state), each made
+//! of a word column, 32 bit columns and a carry column. The carries of the modular
+//! additions are checked against a small lookup table, which is loaded by
+//! [`Chip::load`].
+//!
+//! # Layout
+//!
+//! The message schedule occupies 64 rows of the `A` lane, with $W_t$ in row $t$.
+//!
+//! The compression function occupies 72 rows. The first four rows hold the initial
+//! state, with `D, C, B, A` in the `A` lane and `H, G, F, E` in the `E` lane. Each of
+//! the next 64 rows holds the new `A` and `E` words of one round, along with $W_t$
+//! (copied from the message schedule) and $K_t$; the previous state words are read from
+//! the preceding rows. The last four rows hold the next state, in the same order as the
+//! first four rows.
+
+use std::convert::TryInto;
+use std::marker::PhantomData;
+
+use super::{
+    BlockWord, Sha256, Sha256Digest, Sha256Instructions, BLOCK_SIZE, DIGEST_SIZE, IV, ROUNDS,
+    ROUND_CONSTANTS,
+};
+use crate::{
+    arithmetic::FieldExt,
+    circuit::{Cell, Chip, Layouter, Region},
+    gadget::hash::HashInstructions,
+    plonk::{Advice, Column, ConstraintSystem, Error, Expression, Fixed, Permutation, Selector},
+    poly::Rotation,
+};
+
+const WORD_BITS: usize = 32;
+
+/// An upper bound on the carry of any modular addition performed by the chip.
+const CARRY_RANGE: usize = 8;
+
+/// The offset of the first round within the compression region.
+const FIRST_ROUND: usize = 4;
+
+/// The offset of the next state within the compression region.
+const NEXT_STATE: usize = FIRST_ROUND + ROUNDS;
+
+/// The rotations and shift of $\Sigma_0$, $\Sigma_1$, $\sigma_0$ and $\sigma_1$.
+type Sigma = (&'static [usize], Option<usize>);
+const UPPER_SIGMA_0: Sigma = (&[2, 13, 22], None);
+const UPPER_SIGMA_1: Sigma = (&[6, 11, 25], None);
+const LOWER_SIGMA_0: Sigma = (&[7, 18], Some(3));
+const LOWER_SIGMA_1: Sigma = (&[17, 19], Some(10));
+
+/// A 32-bit word assigned in the circuit.
+#[derive(Clone, Copy, Debug)]
+pub struct AssignedWord {
+    var: Cell,
+    value: Option<u32>,
+}
+
+impl AssignedWord {
+    /// The cell containing this word.
+    pub fn cell(&self) -> Cell {
+        self.var
+    }
+
+    /// The value of this word, if known.
+    pub fn value(&self) -> Option<u32> {
+        self.value
+    }
+}
+
+/// A word column, with its bit decomposition and a carry column.
+#[derive(Clone, Debug)]
+struct Lane {
+    word: Column<Advice>,
+    bits: [Column<Advice>; WORD_BITS],
+    carry: Column<Advice>,
+    s_word: Selector,
+}
+
+impl Lane {
+    fn configure<F: FieldExt>(meta: &mut ConstraintSystem<F>, range: Column<Fixed>) -> Self {
+        let word = meta.advice_column();
+        let bits: [Column<Advice>; WORD_BITS] = (0..WORD_BITS)
+            .map(|_| meta.advice_column())
+            .collect::<Vec<_>>()
+            .try_into()
+            .unwrap();
+        let carry = meta.advice_column();
+        let s_word = meta.selector();
+
+        meta.create_gate("word decomposition", |meta| {
+            let value = meta.query_advice(word, Rotation::cur());
+            let bits = query_bits(meta, &bits, 0);
+            let s_word = meta.query_selector(s_word, Rotation::cur());
+
+            s_word * (compose(bits) - value)
+        });
+        for bit in bits.iter().copied() {
+            meta.create_gate("boolean bit", |meta| {
+                let bit = meta.query_advice(bit, Rotation::cur());
+                let s_word = meta.query_selector(s_word, Rotation::cur());
+
+                s_word * bit.clone() * (Expression::Constant(F::one()) - bit)
+            });
+        }
+
+        // Carries are small, so that modular additions cannot wrap around the field.
+        let carry_value = meta.query_advice(carry, Rotation::cur());
+        let range_value = meta.query_fixed(range, Rotation::cur());
+        meta.lookup(&[carry_value], &[range_value]);
+
+        Lane {
+            word,
+            bits,
+            carry,
+            s_word,
+        }
+    }
+
+    /// Assigns `value` and its bit decomposition at `offset`.
+    fn assign_word<F: FieldExt, C: Chip<Field = F>>(
+        &self,
+        region: &mut Region<'_, C>,
+        offset: usize,
+        value: Option<u32>,
+    ) -> Result<AssignedWord, Error> {
+        self.s_word.enable(region, offset)?;
+        let var = region.assign_advice(
+            || "word",
+            self.word,
+            offset,
+            || {
+                value
+                    .map(|v| F::from_u64(v as u64))
+                    .ok_or(Error::SynthesisError)
+            },
+        )?;
+        for (i, column) in self.bits.iter().enumerate() {
+            region.assign_advice(
+                || format!("bit {}", i),
+                *column,
+                offset,
+                || {
+                    value
+                        .map(|v| F::from_u64(((v >> i) & 1) as u64))
+                        .ok_or(Error::SynthesisError)
+                },
+            )?;
+        }
+        Ok(AssignedWord { var, value })
+    }
+
+    /// Assigns a copy of `word` and its bit decomposition at `offset`.
+    fn copy_word<F: FieldExt, C: Chip<Field = F>>(
+        &self,
+        region: &mut Region<'_, C>,
+        offset: usize,
+        word: &AssignedWord,
+        perm: &Permutation,
+    ) -> Result<AssignedWord, Error> {
+        let copy = self.assign_word(region, offset, word.value)?;
+        region.constrain_equal(perm, word.var, copy.var)?;
+        Ok(copy)
+    }
+
+    /// Assigns the sum of `terms` modulo $2^{32}$, and its carry, at `offset`.
+    fn assign_sum<F: FieldExt, C: Chip<Field = F>>(
+        &self,
+        region: &mut Region<'_, C>,
+        offset: usize,
+        terms: &[Option<u32>],
+    ) -> Result<AssignedWord, Error> {
+        let sum = terms
+            .iter()
+            .copied()
+            .collect::<Option<Vec<_>>>()
+            .map(|terms| terms.into_iter().map(u64::from).sum::<u64>());
+
+        region.assign_advice(
+            || "carry",
+            self.carry,
+            offset,
+            || {
+                sum.map(|sum| F::from_u64(sum >> 32))
+                    .ok_or(Error::SynthesisError)
+            },
+        )?;
+        self.assign_word(region, offset, sum.map(|sum| sum as u32))
+    }
+}
+
+fn query_bits<F: FieldExt>(
+    meta: &mut ConstraintSystem<F>,
+    bits: &[Column<Advice>; WORD_BITS],
+    at: i32,
+) -> Vec<Expression<F>> {
+    bits.iter()
+        .map(|column| meta.query_advice(*column, Rotation(at)))
+        .collect()
+}
+
+/// Returns the word with the given little-endian bits.
+fn compose<F: FieldExt>(bits: impl IntoIterator<Item = Expression<F>>) -> Expression<F> {
+    bits.into_iter()
+        .enumerate()
+        .fold(Expression::Constant(F::zero()), |acc, (i, bit)| {
+            acc + bit * F::from_u64(1 << i)
+        })
+}
+
+/// Returns the XOR of the given bits.
+fn xor<F: FieldExt>(bits: impl IntoIterator<Item = Expression<F>>) -> Expression<F> {
+    bits.into_iter()
+        .fold(Expression::Constant(F::zero()), |acc, bit| {
+            acc.clone() + bit.clone() - acc * bit * F::from_u64(2)
+        })
+}
+
+/// Returns the bits of the XOR of the given rotations and shift of a word.
+fn sigma<F: FieldExt>(bits: &[Expression<F>], (rotations, shift): Sigma) -> Vec<Expression<F>> {
+    (0..WORD_BITS)
+        .map(|i| {
+            xor(rotations
+                .iter()
+                .map(|r| bits[(i + r) % WORD_BITS].clone())
+                .chain(shift.and_then(|s| bits.get(i + s)).cloned()))
+        })
+        .collect()
+}
+
+fn sigma_value(x: u32, (rotations, shift): Sigma) -> u32 {
+    rotations
+        .iter()
+        .fold(shift.map_or(0, |s| x >> s), |acc, r| {
+            acc ^ x.rotate_right(*r as u32)
+        })
+}
+
+/// Configuration for a [`Sha256Chip`].
+#[derive(Clone, Debug)]
+pub struct Sha256Config {
+    a: Lane,
+    e: Lane,
+    w: Column<Advice>,
+    k: Column<Fixed>,
+    range: Column<Fixed>,
+    s_round: Selector,
+    s_schedule: Selector,
+    s_digest: Selector,
+    s_constant: Selector,
+    perm: Permutation,
+}
+
+/// A chip implementing SHA-256 over bit-decomposed words.
+///
+/// The message schedule uses 64 rows, and the compression function uses 72 rows.
+#[derive(Debug)]
+pub struct Sha256Chip<F: FieldExt> {
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt> Sha256Chip<F> {
+    /// Configures this chip for use in a circuit.
+    ///
+    /// The chip allocates 69 advice columns and 2 fixed columns.
+    pub fn configure(meta: &mut ConstraintSystem<F>) -> Sha256Config {
+        let range = meta.fixed_column();
+        let k = meta.fixed_column();
+        let a = Lane::configure(meta, range);
+        let e = Lane::configure(meta, range);
+        let w = meta.advice_column();
+
+        let s_round = meta.selector();
+        let s_schedule = meta.selector();
+        let s_digest = meta.selector();
+        let s_constant = meta.selector();
+
+        let perm = Permutation::new(meta, &[a.word.into(), e.word.into(), w.into()]);
+
+        let two_pow_32 = F::from_u64(1 << 32);
+
+        // T_1 = H + Σ_1(E) + Ch(E, F, G) + K_t + W_t
+        let t_1 = |meta: &mut ConstraintSystem<F>| {
+            let e_1 = query_bits(meta, &e.bits, -1);
+            let e_2 = query_bits(meta, &e.bits, -2);
+            let e_3 = query_bits(meta, &e.bits, -3);
+            let h = meta.query_advice(e.word, Rotation(-4));
+            let k = meta.query_fixed(k, Rotation::cur());
+            let w = meta.query_advice(w, Rotation::cur());
+
+            let ch = e_1
+                .iter()
+                .zip(e_2.iter())
+                .zip(e_3.iter())
+                .map(|((e, f), g)| e.clone() * (f.clone() - g.clone()) + g.clone());
+
+            h + compose(sigma(&e_1, UPPER_SIGMA_1)) + compose(ch) + k + w
+        };
+
+        meta.create_gate("round E", |meta| {
+            let t_1 = t_1(meta);
+            let d = meta.query_advice(a.word, Rotation(-4));
+            let new_e = meta.query_advice(e.word, Rotation::cur());
+            let carry = meta.query_advice(e.carry, Rotation::cur());
+            let s_round = meta.query_selector(s_round, Rotation::cur());
+
+            s_round * (d + t_1 - new_e - carry * two_pow_32)
+        });
+        meta.create_gate("round A", |meta| {
+            let t_1 = t_1(meta);
+            let a_1 = query_bits(meta, &a.bits, -1);
+            let a_2 = query_bits(meta, &a.bits, -2);
+            let a_3 = query_bits(meta, &a.bits, -3);
+            let new_a = meta.query_advice(a.word, Rotation::cur());
+            let carry = meta.query_advice(a.carry, Rotation::cur());
+            let s_round = meta.query_selector(s_round, Rotation::cur());
+
+            let maj = a_1
+                .iter()
+                .zip(a_2.iter())
+                .zip(a_3.iter())
+                .map(|((a, b), c)| {
+                    a.clone() * b.clone() + a.clone() * c.clone() + b.clone() * c.clone()
+                        - a.clone() * b.clone() * c.clone() * F::from_u64(2)
+                });
+
+            // T_2 = Σ_0(A) + Maj(A, B, C)
+            let t_2 = compose(sigma(&a_1, UPPER_SIGMA_0)) + compose(maj);
+
+            s_round * (t_1 + t_2 - new_a - carry * two_pow_32)
+        });
+
+        // W_t = σ_1(W_{t-2}) + W_{t-7} + σ_0(W_{t-15}) + W_{t-16}
+        meta.create_gate("message schedule", |meta| {
+            let w_2 = query_bits(meta, &a.bits, -2);
+            let w_7 = meta.query_advice(a.word, Rotation(-7));
+            let w_15 = query_bits(meta, &a.bits, -15);
+            let w_16 = meta.query_advice(a.word, Rotation(-16));
+            let w = meta.query_advice(a.word, Rotation::cur());
+            let carry = meta.query_advice(a.carry, Rotation::cur());
+            let s_schedule = meta.query_selector(s_schedule, Rotation::cur());
+
+            s_schedule
+                * (compose(sigma(&w_2, LOWER_SIGMA_1))
+                    + w_7
+                    + compose(sigma(&w_15, LOWER_SIGMA_0))
+                    + w_16
+                    - w
+                    - carry * two_pow_32)
+        });
+
+        // Each word of the next state is the sum of the corresponding words of the
+        // initial state and the state after the last round.
+        for lane in [&a, &e].iter() {
+            meta.create_gate("next state", |meta| {
+                let initial = meta.query_advice(lane.word, Rotation(-(NEXT_STATE as i32)));
+                let last = meta.query_advice(lane.word, Rotation(-4));
+                let next = meta.query_advice(lane.word, Rotation::cur());
+                let carry = meta.query_advice(lane.carry, Rotation::cur());
+                let s_digest = meta.query_selector(s_digest, Rotation::cur());
+
+                s_digest * (initial + last - next - carry * two_pow_32)
+            });
+        }
+
+        meta.create_gate("constant word", |meta| {
+            let word = meta.query_advice(a.word, Rotation::cur());
+            let constant = meta.query_fixed(k, Rotation::cur());
+            let s_constant = meta.query_selector(s_constant, Rotation::cur());
+
+            s_constant * (word - constant)
+        });
+
+        Sha256Config {
+            a,
+            e,
+            w,
+            k,
+            range,
+            s_round,
+            s_schedule,
+            s_digest,
+            s_constant,
+            perm,
+        }
+    }
+
+    fn assign_constant(
+        region: &mut Region<'_, Self>,
+        config: &Sha256Config,
+        offset: usize,
+        value: u32,
+    ) -> Result<AssignedWord, Error> {
+        config.s_constant.enable(region, offset)?;
+        region.assign_fixed(
+            || "constant",
+            config.k,
+            offset,
+            || Ok(F::from_u64(value as u64)),
+        )?;
+        config.a.assign_word(region, offset, Some(value))
+    }
+}
+
+impl<F: FieldExt> Chip for Sha256Chip<F> {
+    type Config = Sha256Config;
+    type Loaded = ();
+    type Field = F;
+
+    fn load(layouter: &mut impl Layouter<Self>) -> Result<(), Error> {
+        let config = layouter.config().clone();
+
+        layouter.assign_region(
+            || "carry range table",
+            |mut region| {
+                for carry in 0..CARRY_RANGE {
+                    region.assign_fixed(
+                        || "carry",
+                        config.range,
+                        carry,
+                        || Ok(F::from_u64(carry as u64)),
+                    )?;
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+impl<F: FieldExt> Sha256Instructions for Sha256Chip<F> {
+    type Word = AssignedWord;
+
+    fn load_word(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<u32>,
+    ) -> Result<Self::Word, Error> {
+        let config = layouter.config().clone();
+
+        layouter.assign_region(
+            || "load word",
+            |mut region| config.a.assign_word(&mut region, 0, value),
+        )
+    }
+
+    fn initialization_vector(
+        layouter: &mut impl Layouter<Self>,
+    ) -> Result<[Self::Word; DIGEST_SIZE], Error> {
+        let config = layouter.config().clone();
+
+        layouter.assign_region(
+            || "initialization vector",
+            |mut region| {
+                let state = IV
+                    .iter()
+                    .enumerate()
+                    .map(|(i, value)| Self::assign_constant(&mut region, &config, i, *value))
+                    .collect::<Result<Vec<_>, Error>>()?;
+                Ok(state.try_into().unwrap())
+            },
+        )
+    }
+
+    fn message_schedule(
+        layouter: &mut impl Layouter<Self>,
+        block: &[BlockWord<Self::Word>; BLOCK_SIZE],
+    ) -> Result<[Self::Word; ROUNDS], Error> {
+        let config = layouter.config().clone();
+
+        layouter.assign_region(
+            || "message schedule",
+            |mut region| {
+                let mut schedule: Vec<AssignedWord> = Vec::with_capacity(ROUNDS);
+                for (t, word) in block.iter().enumerate() {
+                    schedule.push(match word {
+                        BlockWord::Message(word) => {
+                            config.a.copy_word(&mut region, t, word, &config.perm)?
+                        }
+                        BlockWord::Padding(value) => {
+                            Self::assign_constant(&mut region, &config, t, *value)?
+                        }
+                    });
+                }
+
+                for t in BLOCK_SIZE..ROUNDS {
+                    config.s_schedule.enable(&mut region, t)?;
+                    let w = config.a.assign_sum(
+                        &mut region,
+                        t,
+                        &[
+                            schedule[t - 2].value.map(|w| sigma_value(w, LOWER_SIGMA_1)),
+                            schedule[t - 7].value,
+                            schedule[t - 15]
+                                .value
+                                .map(|w| sigma_value(w, LOWER_SIGMA_0)),
+                            schedule[t - 16].value,
+                        ],
+                    )?;
+                    schedule.push(w);
+                }
+
+                Ok(schedule.try_into().unwrap())
+            },
+        )
+    }
+
+    fn compress(
+        layouter: &mut impl Layouter<Self>,
+        initial_state: &[Self::Word; DIGEST_SIZE],
+        schedule: &[Self::Word; ROUNDS],
+    ) -> Result<[Self::Word; DIGEST_SIZE], Error> {
+        let config = layouter.config().clone();
+
+        layouter.assign_region(
+            || "compress",
+            |mut region| {
+                // The values of the A and E lanes in each row.
+                let mut a = Vec::with_capacity(NEXT_STATE);
+                let mut e = Vec::with_capacity(NEXT_STATE);
+
+                for i in 0..FIRST_ROUND {
+                    let word = &initial_state[3 - i];
+                    a.push(
+                        config
+                            .a
+                            .copy_word(&mut region, i, word, &config.perm)?
+                            .value,
+                    );
+                    let word = &initial_state[7 - i];
+                    e.push(
+                        config
+                            .e
+                            .copy_word(&mut region, i, word, &config.perm)?
+                            .value,
+                    );
+                }
+
+                for (t, (k, w)) in ROUND_CONSTANTS.iter().zip(schedule.iter()).enumerate() {
+                    let row = FIRST_ROUND + t;
+                    config.s_round.enable(&mut region, row)?;
+                    region.assign_fixed(|| "K", config.k, row, || Ok(F::from_u64(*k as u64)))?;
+                    let var = region.assign_advice(
+                        || "W",
+                        config.w,
+                        row,
+                        || {
+                            w.value
+                                .map(|w| F::from_u64(w as u64))
+                                .ok_or(Error::SynthesisError)
+                        },
+                    )?;
+                    region.constrain_equal(&config.perm, w.var, var)?;
+
+                    let sigma_1 = e[row - 1].map(|e| sigma_value(e, UPPER_SIGMA_1));
+                    let ch = e[row - 1]
+                        .zip(e[row - 2])
+                        .zip(e[row - 3])
+                        .map(|((e, f), g)| (e & f) ^ (!e & g));
+                    let sigma_0 = a[row - 1].map(|a| sigma_value(a, UPPER_SIGMA_0));
+                    let maj = a[row - 1]
+                        .zip(a[row - 2])
+                        .zip(a[row - 3])
+                        .map(|((a, b), c)| (a & b) ^ (a & c) ^ (b & c));
+                    let t_1 = [e[row - 4], sigma_1, ch, Some(*k), w.value];
+
+                    let new_e = [&t_1[..], &[a[row - 4]]].concat();
+                    let new_e = config.e.assign_sum(&mut region, row, &new_e)?;
+                    let new_a = [&t_1[..], &[sigma_0, maj]].concat();
+                    let new_a = config.a.assign_sum(&mut region, row, &new_a)?;
+                    a.push(new_a.value);
+                    e.push(new_e.value);
+                }
+
+                let mut next_state = *initial_state;
+                for i in 0..FIRST_ROUND {
+                    let row = NEXT_STATE + i;
+                    config.s_digest.enable(&mut region, row)?;
+                    next_state[3 - i] =
+                        config.a.assign_sum(&mut region, row, &[a[i], a[row - 4]])?;
+                    next_state[7 - i] =
+                        config.e.assign_sum(&mut region, row, &[e[i], e[row - 4]])?;
+                }
+
+                Ok(next_state)
+            },
+        )
+    }
+}
+
+/// Words are 32-bit message words, and values that do not fit in 32 bits return
+/// [`Error::SynthesisError`].
+impl<F: FieldExt> HashInstructions<F> for Sha256Chip<F> {
+    type Word = AssignedWord;
+    type State = Sha256<Self>;
+    type Output = Sha256Digest<AssignedWord>;
+
+    fn witness_word(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<F>,
+    ) -> Result<Self::Word, Error> {
+        let value = value
+            .map(|value| {
+                let word = value.get_lower_32();
+                if F::from_u64(word as u64) == value {
+                    Ok(word)
+                } else {
+                    Err(Error::SynthesisError)
+                }
+            })
+            .transpose()?;
+        Self::load_word(layouter, value)
+    }
+
+    fn init(layouter: &mut impl Layouter<Self>) -> Result<Self::State, Error> {
+        Sha256::new(layouter.namespace(|| "init"))
+    }
+
+    fn absorb(
+        layouter: &mut impl Layouter<Self>,
+        state: &mut Self::State,
+        words: &[Self::Word],
+    ) -> Result<(), Error> {
+        state.update(layouter.namespace(|| "absorb"), words)
+    }
+
+    fn finalize(
+        layouter: &mut impl Layouter<Self>,
+        state: Self::State,
+    ) -> Result<Self::Output, Error> {
+        state.finalize(layouter.namespace(|| "finalize"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryInto;
+
+    use super::{Sha256Chip, Sha256Config};
+    use crate::{
+        circuit::{layouter, Layouter},
+        dev::MockProver,
+        gadget::sha256::{Sha256, Sha256Instructions, DIGEST_SIZE},
+        pasta::Fp,
+        plonk::{Assignment, Circuit, ConstraintSystem, Error},
+    };
+
+    struct Sha256Circuit {
+        message: Option<Vec<u32>>,
+        len: usize,
+        digest: [u32; DIGEST_SIZE],
+    }
+
+    impl Circuit<Fp> for Sha256Circuit {
+        type Config = Sha256Config;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Sha256Config {
+            Sha256Chip::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            config: Sha256Config,
+        ) -> Result<(), Error> {
+            let mut layouter = layouter::SingleChip::<Sha256Chip<Fp>, _>::new(cs, config)?;
+            let config = layouter.config().clone();
+
+            let message = (0..self.len)
+                .map(|i| {
+                    Sha256Chip::load_word(
+                        &mut layouter,
+                        self.message.as_ref().map(|message| message[i]),
+                    )
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+
+            let digest = Sha256::digest(layouter.namespace(|| "sha256"), &message)?;
+
+            for (word, expected) in digest.0.iter().zip(self.digest.iter()) {
+                layouter.assign_region(
+                    || "constrain digest",
+                    |mut region| {
+                        let expected =
+                            Sha256Chip::assign_constant(&mut region, &config, 0, *expected)?;
+                        region.constrain_equal(&config.perm, word.var, expected.var)
+                    },
+                )?;
+            }
+
+            Ok(())
+        }
+    }
+
+    fn words(message: &[u8]) -> Vec<u32> {
+        message
+            .chunks(4)
+            .map(|chunk| u32::from_be_bytes(chunk.try_into().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn sha256_empty() {
+        let circuit = Sha256Circuit {
+            message: Some(vec![]),
+            len: 0,
+            digest: [
+                0xe3b0_c442,
+                0x98fc_1c14,
+                0x9afb_f4c8,
+                0x996f_b924,
+                0x27ae_41e4,
+                0x649b_934c,
+                0xa495_991b,
+                0x7852_b855,
+            ],
+        };
+        let prover = MockProver::run(9, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()))
+    }
+
+    #[test]
+    fn sha256_two_blocks() {
+        // The 448-bit test vector from FIPS 180-2, which is padded into two blocks.
+        let message = words(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq");
+        let mut circuit = Sha256Circuit {
+            len: message.len(),
+            message: Some(message),
+            digest: [
+                0x248d_6a61,
+                0xd206_38b8,
+                0xe5c0_2693,
+                0x0c3e_6039,
+                0xa33c_e459,
+                0x64ff_2167,
+                0xf6ec_edd4,
+                0x19db_06c1,
+            ],
+        };
+        let prover = MockProver::run(9, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        circuit.digest[7] ^= 1;
+        let prover = MockProver::run(9, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
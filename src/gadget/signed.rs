@@ -0,0 +1,188 @@
+//! Gadget and chips for arithmetic on signed integers.
+//!
+//! An $N$-bit signed integer $a \in \[-2^{N-1}, 2^{N-1})$ is represented by the field
+//! element $a$, so that negative integers are $p - |a|$ for the modulus $p$, and
+//! addition and multiplication are the field operations. Witnessing an integer
+//! constrains it to $N$ bits by splitting it into its sign $s$ and low bits:
+//! $a = \ell - s \cdot 2^{N-1}$ with $\ell$ in $N - 1$ bits, which is the two's
+//! complement decomposition. The results of additions and multiplications are
+//! constrained in the same way, so they fail instead of overflowing.
+
+use std::fmt;
+
+use crate::{
+    circuit::{Chip, Layouter},
+    plonk::Error,
+};
+
+mod chip;
+pub use chip::{SignedChip, SignedConfig};
+
+/// The set of circuit instructions required to use the [`Signed`] gadget.
+///
+/// The inputs of each instruction must be `num_bits`-bit signed integers.
+pub trait SignedInstructions: Chip {
+    /// Variable representing a signed integer.
+    type Value: Clone + fmt::Debug;
+
+    /// Variable representing a boolean.
+    type Bool: Clone + fmt::Debug;
+
+    /// Witnesses a signed integer, constraining it to $\mathsf{num\_bits}$ bits.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `num_bits` is zero or greater than 127, or is not less than the
+    /// capacity of the field.
+    fn witness_signed(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<i128>,
+        num_bits: usize,
+    ) -> Result<Self::Value, Error>;
+
+    /// Returns whether $a$ is negative.
+    fn sign(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Value,
+        num_bits: usize,
+    ) -> Result<Self::Bool, Error>;
+
+    /// Returns $|a|$, which is a $(\mathsf{num\_bits} + 1)$-bit signed integer.
+    fn abs(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Value,
+        num_bits: usize,
+    ) -> Result<Self::Value, Error>;
+
+    /// Returns whether $a < b$.
+    fn less_than(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Value,
+        b: &Self::Value,
+        num_bits: usize,
+    ) -> Result<Self::Bool, Error>;
+
+    /// Returns $a + b$, constraining it to $\mathsf{num\_bits}$ bits.
+    fn add(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Value,
+        b: &Self::Value,
+        num_bits: usize,
+    ) -> Result<Self::Value, Error>;
+
+    /// Returns $a \cdot b$, constraining it to $\mathsf{num\_bits}$ bits.
+    ///
+    /// # Panics
+    ///
+    /// Panics if $2 \cdot \mathsf{num\_bits}$ is not less than the capacity of the
+    /// field.
+    fn mul(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Value,
+        b: &Self::Value,
+        num_bits: usize,
+    ) -> Result<Self::Value, Error>;
+}
+
+/// A signed integer of a fixed bit length, assigned in the circuit.
+#[derive(Debug)]
+pub struct Signed<SignedChip: SignedInstructions> {
+    inner: SignedChip::Value,
+    num_bits: usize,
+}
+
+impl<SignedChip: SignedInstructions> Signed<SignedChip> {
+    /// Witnesses a `num_bits`-bit signed integer.
+    pub fn new(
+        mut layouter: impl Layouter<SignedChip>,
+        value: Option<i128>,
+        num_bits: usize,
+    ) -> Result<Self, Error> {
+        SignedChip::witness_signed(&mut layouter, value, num_bits)
+            .map(|inner| Signed { inner, num_bits })
+    }
+
+    /// The bit length of this integer.
+    pub fn num_bits(&self) -> usize {
+        self.num_bits
+    }
+
+    /// Returns whether this integer is negative.
+    pub fn sign(&self, mut layouter: impl Layouter<SignedChip>) -> Result<SignedChip::Bool, Error> {
+        SignedChip::sign(&mut layouter, &self.inner, self.num_bits)
+    }
+
+    /// Returns the absolute value of this integer, which has one more bit.
+    pub fn abs(&self, mut layouter: impl Layouter<SignedChip>) -> Result<Self, Error> {
+        SignedChip::abs(&mut layouter, &self.inner, self.num_bits).map(|inner| Signed {
+            inner,
+            num_bits: self.num_bits + 1,
+        })
+    }
+
+    /// Returns whether this integer is less than `other`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the integers have different bit lengths.
+    pub fn less_than(
+        &self,
+        mut layouter: impl Layouter<SignedChip>,
+        other: &Self,
+    ) -> Result<SignedChip::Bool, Error> {
+        assert_eq!(self.num_bits, other.num_bits);
+        SignedChip::less_than(&mut layouter, &self.inner, &other.inner, self.num_bits)
+    }
+
+    /// Returns the sum of this integer and `other`, which must not overflow.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the integers have different bit lengths.
+    pub fn add(
+        &self,
+        mut layouter: impl Layouter<SignedChip>,
+        other: &Self,
+    ) -> Result<Self, Error> {
+        assert_eq!(self.num_bits, other.num_bits);
+        SignedChip::add(&mut layouter, &self.inner, &other.inner, self.num_bits).map(|inner| {
+            Signed {
+                inner,
+                num_bits: self.num_bits,
+            }
+        })
+    }
+
+    /// Returns the product of this integer and `other`, which must not overflow.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the integers have different bit lengths.
+    pub fn mul(
+        &self,
+        mut layouter: impl Layouter<SignedChip>,
+        other: &Self,
+    ) -> Result<Self, Error> {
+        assert_eq!(self.num_bits, other.num_bits);
+        SignedChip::mul(&mut layouter, &self.inner, &other.inner, self.num_bits).map(|inner| {
+            Signed {
+                inner,
+                num_bits: self.num_bits,
+            }
+        })
+    }
+
+    /// Returns the chip-specific representation of this integer.
+    pub fn inner(&self) -> &SignedChip::Value {
+        &self.inner
+    }
+}
+
+impl<SignedChip: SignedInstructions> Clone for Signed<SignedChip> {
+    fn clone(&self) -> Self {
+        Signed {
+            inner: self.inner.clone(),
+            num_bits: self.num_bits,
+        }
+    }
+}
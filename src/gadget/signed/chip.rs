@@ -0,0 +1,486 @@
+//! A chip implementing [`SignedInstructions`] with the range-check lookup.
+//!
+//! # Layout
+//!
+//! The chip uses the two advice columns of a [`RangeCheckConfig`], and a fixed column
+//! holding the power of two used by the gate of each row.
+//!
+//! The sign decomposition of $a$ uses two rows: the first holds $a$ and its sign $s$,
+//! with $2^{N-1}$ in the fixed column, and the second holds the low bits $\ell$, which
+//! are range-checked to $N - 1$ bits in the following rows. An absolute value places
+//! $a \cdot (1 - 2s)$ next to $\ell$. A comparison of $a$ and $b$ holds them in its
+//! first row, and $d = a - b + c \cdot 2^N$ and the result $c$ in its second row, and
+//! range-checks $d$ to $N$ bits as the unsigned comparison does. An addition or
+//! multiplication holds $a$ and $b$ in its first row and the result in its second row,
+//! followed by the sign decomposition of the result.
+
+use std::marker::PhantomData;
+
+use super::SignedInstructions;
+use crate::{
+    arithmetic::FieldExt,
+    circuit::{Chip, Layouter, Region},
+    gadget::range_check::{max_bits, AssignedValue, RangeCheckConfig},
+    plonk::{Column, ConstraintSystem, Error, Expression, Fixed, Selector},
+    poly::Rotation,
+};
+
+/// Returns `value` as a field element.
+fn to_field<F: FieldExt>(value: i128) -> F {
+    let magnitude = F::from_u128(value.unsigned_abs());
+    if value < 0 {
+        -magnitude
+    } else {
+        magnitude
+    }
+}
+
+/// Returns a field element encoding a signed integer of at most 128 bits as an integer.
+fn to_i128<F: FieldExt>(value: F) -> i128 {
+    if is_small(value) {
+        value.get_lower_128() as i128
+    } else {
+        0i128.wrapping_sub((-value).get_lower_128() as i128)
+    }
+}
+
+/// Returns whether `value` is less than $2^{127}$.
+fn is_small<F: FieldExt>(value: F) -> bool {
+    let repr = value.to_repr();
+    let bytes = repr.as_ref();
+    bytes[16..].iter().all(|byte| *byte == 0) && bytes[15] < 0x80
+}
+
+/// Returns $2^n$ in the field.
+fn two_pow<F: FieldExt>(n: usize) -> F {
+    (0..n).fold(F::one(), |acc, _| acc + acc)
+}
+
+/// Configuration for a [`SignedChip`].
+#[derive(Clone, Debug)]
+pub struct SignedConfig {
+    range: RangeCheckConfig,
+    constant: Column<Fixed>,
+    q_sign: Selector,
+    q_abs: Selector,
+    q_compare: Selector,
+    q_add: Selector,
+    q_mul: Selector,
+}
+
+/// A chip implementing [`SignedInstructions`].
+#[derive(Debug)]
+pub struct SignedChip<F: FieldExt> {
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt> Chip for SignedChip<F> {
+    type Config = SignedConfig;
+    type Loaded = ();
+    type Field = F;
+
+    fn load(layouter: &mut impl Layouter<Self>) -> Result<(), Error> {
+        let config = layouter.config().clone();
+        config.range.load(layouter)
+    }
+}
+
+impl<F: FieldExt> SignedChip<F> {
+    /// Configures this chip with a range-check table of `word_bits`-bit values.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `word_bits` is zero or greater than 24.
+    pub fn configure(meta: &mut ConstraintSystem<F>, word_bits: usize) -> SignedConfig {
+        let range = RangeCheckConfig::configure(meta, word_bits);
+        let config = SignedConfig {
+            range,
+            constant: meta.fixed_column(),
+            q_sign: meta.selector(),
+            q_abs: meta.selector(),
+            q_compare: meta.selector(),
+            q_add: meta.selector(),
+            q_mul: meta.selector(),
+        };
+
+        let (z, word) = (config.range.z(), config.range.word());
+        let one = Expression::Constant(F::one());
+        let two = Expression::Constant(F::from_u64(2));
+
+        // a = l - s 2^{N - 1}
+        meta.create_gate("signed sign", |meta| {
+            let a = meta.query_advice(z, Rotation::cur());
+            let s = meta.query_advice(word, Rotation::cur());
+            let low = meta.query_advice(z, Rotation::next());
+            let two_pow_n = meta.query_fixed(config.constant, Rotation::cur());
+            let q_sign = meta.query_selector(config.q_sign, Rotation::cur());
+
+            q_sign * (a - low + s * two_pow_n)
+        });
+        meta.create_gate("signed sign bool", |meta| {
+            let s = meta.query_advice(word, Rotation::cur());
+            let q_sign = meta.query_selector(config.q_sign, Rotation::cur());
+
+            q_sign * s.clone() * (one.clone() - s)
+        });
+        meta.create_gate("signed abs", |meta| {
+            let a = meta.query_advice(z, Rotation::cur());
+            let s = meta.query_advice(word, Rotation::cur());
+            let abs = meta.query_advice(word, Rotation::next());
+            let q_abs = meta.query_selector(config.q_abs, Rotation::cur());
+
+            q_abs * (abs - a.clone() + two * s * a)
+        });
+
+        // d = a - b + c 2^N
+        meta.create_gate("signed comparison", |meta| {
+            let a = meta.query_advice(z, Rotation::cur());
+            let b = meta.query_advice(word, Rotation::cur());
+            let d = meta.query_advice(z, Rotation::next());
+            let c = meta.query_advice(word, Rotation::next());
+            let two_pow_n = meta.query_fixed(config.constant, Rotation::cur());
+            let q_compare = meta.query_selector(config.q_compare, Rotation::cur());
+
+            q_compare * (a - b + c * two_pow_n - d)
+        });
+        meta.create_gate("signed comparison bool", |meta| {
+            let c = meta.query_advice(word, Rotation::next());
+            let q_compare = meta.query_selector(config.q_compare, Rotation::cur());
+
+            q_compare * c.clone() * (one - c)
+        });
+
+        meta.create_gate("signed add", |meta| {
+            let a = meta.query_advice(z, Rotation::cur());
+            let b = meta.query_advice(word, Rotation::cur());
+            let out = meta.query_advice(z, Rotation::next());
+            let q_add = meta.query_selector(config.q_add, Rotation::cur());
+
+            q_add * (out - a - b)
+        });
+        meta.create_gate("signed mul", |meta| {
+            let a = meta.query_advice(z, Rotation::cur());
+            let b = meta.query_advice(word, Rotation::cur());
+            let out = meta.query_advice(z, Rotation::next());
+            let q_mul = meta.query_selector(config.q_mul, Rotation::cur());
+
+            q_mul * (out - a * b)
+        });
+
+        config
+    }
+}
+
+impl SignedConfig {
+    /// Assigns the sign decomposition of `a` as a `num_bits`-bit integer at `offset`,
+    /// and returns the assigned copy of `a`, its sign and the number of rows used.
+    fn assign_sign<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        region: &mut Region<'_, Ch>,
+        offset: usize,
+        a: Option<F>,
+        num_bits: usize,
+    ) -> Result<(AssignedValue<F>, AssignedValue<F>, usize), Error> {
+        assert!(num_bits > 0 && num_bits <= 127 && num_bits <= max_bits::<F>());
+        let two_pow_n = two_pow::<F>(num_bits - 1);
+
+        self.q_sign.enable(region, offset)?;
+        region.assign_fixed(|| "2^{N - 1}", self.constant, offset, || Ok(two_pow_n))?;
+
+        let a_cell = self.range.assign_value(region, offset, a)?;
+        let s = a.map(|a| if to_i128(a) < 0 { F::one() } else { F::zero() });
+        let s_cell = region.assign_advice(
+            || "s",
+            self.range.word(),
+            offset,
+            || s.ok_or(Error::SynthesisError),
+        )?;
+        let low = self.range.assign_value(
+            region,
+            offset + 1,
+            a.zip(s).map(|(a, s)| a + s * two_pow_n),
+        )?;
+        let rows = self
+            .range
+            .assign_range_check(region, offset + 2, &low, num_bits - 1)?;
+
+        Ok((
+            a_cell,
+            AssignedValue {
+                cell: s_cell,
+                value: s,
+            },
+            rows + 2,
+        ))
+    }
+
+    /// Assigns a copy of `a` and the sign decomposition of it at `offset`, and
+    /// returns its sign.
+    fn copy_sign<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        region: &mut Region<'_, Ch>,
+        offset: usize,
+        a: &AssignedValue<F>,
+        num_bits: usize,
+    ) -> Result<AssignedValue<F>, Error> {
+        let (copy, s, _) = self.assign_sign(region, offset, a.value, num_bits)?;
+        region.constrain_equal(self.range.perm(), a.cell, copy.cell)?;
+        Ok(s)
+    }
+
+    /// Assigns `a` and `b` in the first row of `region`, and the result of `op` on them
+    /// in the second row with its sign decomposition.
+    fn assign_op<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        region: &mut Region<'_, Ch>,
+        selector: Selector,
+        a: &AssignedValue<F>,
+        b: &AssignedValue<F>,
+        num_bits: usize,
+        op: fn(F, F) -> F,
+    ) -> Result<AssignedValue<F>, Error> {
+        selector.enable(region, 0)?;
+        self.copy_operands(region, a, b)?;
+
+        let (out, _, _) = self.assign_sign(
+            region,
+            1,
+            a.value.zip(b.value).map(|(a, b)| op(a, b)),
+            num_bits,
+        )?;
+        Ok(out)
+    }
+
+    /// Assigns copies of `a` and `b` in the first row of `region`.
+    fn copy_operands<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        region: &mut Region<'_, Ch>,
+        a: &AssignedValue<F>,
+        b: &AssignedValue<F>,
+    ) -> Result<(), Error> {
+        let a_cell = self.range.assign_value(region, 0, a.value)?;
+        region.constrain_equal(self.range.perm(), a_cell.cell, a.cell)?;
+        let b_cell = region.assign_advice(
+            || "b",
+            self.range.word(),
+            0,
+            || b.value.ok_or(Error::SynthesisError),
+        )?;
+        region.constrain_equal(self.range.perm(), b_cell, b.cell)
+    }
+}
+
+impl<F: FieldExt> SignedInstructions for SignedChip<F> {
+    type Value = AssignedValue<F>;
+    type Bool = AssignedValue<F>;
+
+    fn witness_signed(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<i128>,
+        num_bits: usize,
+    ) -> Result<Self::Value, Error> {
+        let config = layouter.config().clone();
+        layouter.assign_region(
+            || "witness signed",
+            |mut region| {
+                let (a, _, _) =
+                    config.assign_sign(&mut region, 0, value.map(to_field), num_bits)?;
+                Ok(a)
+            },
+        )
+    }
+
+    fn sign(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Value,
+        num_bits: usize,
+    ) -> Result<Self::Bool, Error> {
+        let config = layouter.config().clone();
+        layouter.assign_region(
+            || "sign",
+            |mut region| config.copy_sign(&mut region, 0, a, num_bits),
+        )
+    }
+
+    fn abs(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Value,
+        num_bits: usize,
+    ) -> Result<Self::Value, Error> {
+        let config = layouter.config().clone();
+        layouter.assign_region(
+            || "abs",
+            |mut region| {
+                config.q_abs.enable(&mut region, 0)?;
+                let s = config.copy_sign(&mut region, 0, a, num_bits)?;
+
+                let abs = a.value.zip(s.value).map(|(a, s)| a - (s + s) * a);
+                let cell = region.assign_advice(
+                    || "|a|",
+                    config.range.word(),
+                    1,
+                    || abs.ok_or(Error::SynthesisError),
+                )?;
+                Ok(AssignedValue { cell, value: abs })
+            },
+        )
+    }
+
+    fn less_than(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Value,
+        b: &Self::Value,
+        num_bits: usize,
+    ) -> Result<Self::Bool, Error> {
+        assert!(num_bits <= max_bits::<F>());
+        let config = layouter.config().clone();
+        layouter.assign_region(
+            || "signed comparison",
+            |mut region| {
+                config.q_compare.enable(&mut region, 0)?;
+                let two_pow_n = two_pow::<F>(num_bits);
+                region.assign_fixed(|| "2^N", config.constant, 0, || Ok(two_pow_n))?;
+                config.copy_operands(&mut region, a, b)?;
+
+                let c = a.value.zip(b.value).map(|(a, b)| {
+                    if to_i128(a) < to_i128(b) {
+                        F::one()
+                    } else {
+                        F::zero()
+                    }
+                });
+                let d = a
+                    .value
+                    .zip(b.value)
+                    .zip(c)
+                    .map(|((a, b), c)| a - b + c * two_pow_n);
+                let d = config.range.assign_value(&mut region, 1, d)?;
+                let c_cell = region.assign_advice(
+                    || "c",
+                    config.range.word(),
+                    1,
+                    || c.ok_or(Error::SynthesisError),
+                )?;
+                config
+                    .range
+                    .assign_range_check(&mut region, 2, &d, num_bits)?;
+
+                Ok(AssignedValue {
+                    cell: c_cell,
+                    value: c,
+                })
+            },
+        )
+    }
+
+    fn add(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Value,
+        b: &Self::Value,
+        num_bits: usize,
+    ) -> Result<Self::Value, Error> {
+        let config = layouter.config().clone();
+        layouter.assign_region(
+            || "signed add",
+            |mut region| config.assign_op(&mut region, config.q_add, a, b, num_bits, |a, b| a + b),
+        )
+    }
+
+    fn mul(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Value,
+        b: &Self::Value,
+        num_bits: usize,
+    ) -> Result<Self::Value, Error> {
+        assert!(2 * num_bits < max_bits::<F>());
+        let config = layouter.config().clone();
+        layouter.assign_region(
+            || "signed mul",
+            |mut region| config.assign_op(&mut region, config.q_mul, a, b, num_bits, |a, b| a * b),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{to_field, to_i128, SignedChip, SignedConfig};
+    use crate::{
+        circuit::{layouter, Layouter},
+        dev::MockProver,
+        gadget::signed::Signed,
+        pasta::Fp,
+        plonk::{Assignment, Circuit, ConstraintSystem, Error},
+    };
+
+    #[test]
+    fn field_encoding() {
+        for value in [0, 1, -1, 42, -42, i128::MAX, i128::MIN] {
+            assert_eq!(to_i128(to_field::<Fp>(value)), value);
+        }
+    }
+
+    struct SignedCircuit {
+        a: i128,
+        b: i128,
+        num_bits: usize,
+    }
+
+    impl Circuit<Fp> for SignedCircuit {
+        type Config = SignedConfig;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> SignedConfig {
+            SignedChip::configure(meta, 8)
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            config: SignedConfig,
+        ) -> Result<(), Error> {
+            let mut layouter = layouter::SingleChip::<SignedChip<Fp>, _>::new(cs, config)?;
+            let bool_value = |b: bool| Some(if b { Fp::one() } else { Fp::zero() });
+            let (a_value, b_value) = (self.a, self.b);
+
+            let a = Signed::new(layouter.namespace(|| "a"), Some(a_value), self.num_bits)?;
+            let b = Signed::new(layouter.namespace(|| "b"), Some(b_value), self.num_bits)?;
+
+            let sign = a.sign(layouter.namespace(|| "sign"))?;
+            assert_eq!(sign.value(), bool_value(a_value < 0));
+            let abs = a.abs(layouter.namespace(|| "abs"))?;
+            assert_eq!(abs.inner().value(), Some(to_field(a_value.abs())));
+            assert_eq!(abs.num_bits(), self.num_bits + 1);
+            let lt = a.less_than(layouter.namespace(|| "a < b"), &b)?;
+            assert_eq!(lt.value(), bool_value(a_value < b_value));
+
+            let sum = a.add(layouter.namespace(|| "a + b"), &b)?;
+            assert_eq!(sum.inner().value(), Some(to_field(a_value + b_value)));
+            let product = a.mul(layouter.namespace(|| "a * b"), &b)?;
+            assert_eq!(product.inner().value(), Some(to_field(a_value * b_value)));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn signed() {
+        let run = |a: i128, b: i128, num_bits: usize| {
+            let circuit = SignedCircuit { a, b, num_bits };
+            MockProver::run(9, &circuit, vec![]).unwrap().verify()
+        };
+
+        assert_eq!(run(3, 5, 8), Ok(()));
+        assert_eq!(run(-3, 5, 8), Ok(()));
+        assert_eq!(run(5, -3, 8), Ok(()));
+        assert_eq!(run(-11, -11, 8), Ok(()));
+        assert_eq!(run(-128, 1, 8), Ok(()));
+        assert_eq!(run(0, 0, 1), Ok(()));
+        assert_eq!(run(-(1 << 39), 1, 40), Ok(()));
+
+        // Inputs out of range, and overflowing sums and products, fail.
+        assert!(run(128, 0, 8).is_err());
+        assert!(run(0, -129, 8).is_err());
+        assert!(run(100, 100, 8).is_err());
+        assert!(run(-16, 9, 8).is_err());
+    }
+}
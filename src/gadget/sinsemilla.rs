@@ -0,0 +1,211 @@
+//! Gadget and chips for the Sinsemilla hash function and commitment scheme.
+//!
+//! Messages are provided as a sequence of [`MessagePiece`]s, each of which is a field
+//! element encoding a whole number of $K$-bit words, so the bit length of a message must
+//! be a multiple of $K$ (shorter messages can be padded with zero bits, which does not
+//! change their hash). Pieces are decomposed into words in-circuit, and each word is
+//! mapped to its generator with a lookup, so hashing costs one row per word.
+//!
+//! The [`HashDomain`] and [`CommitDomain`] gadgets compute the same values as
+//! [`crate::primitives::sinsemilla`], using a chip implementing
+//! [`SinsemillaInstructions`], such as [`SinsemillaChip`].
+
+use std::fmt;
+use std::marker::PhantomData;
+
+use ff::{Field, PrimeField};
+
+use super::ecc::{EccInstructions, FixedPoint, Point, X};
+use crate::{
+    arithmetic::CurveAffine,
+    circuit::Layouter,
+    plonk::Error,
+    primitives::sinsemilla::{self, K},
+};
+
+mod chip;
+pub use chip::{AssignedPiece, SinsemillaChip, SinsemillaConfig};
+
+/// The domain of the Sinsemilla hashes computed through
+/// [`HashInstructions`](super::hash::HashInstructions).
+pub const GENERIC_HASH_DOMAIN: &str = "halo2:generic-hash";
+
+/// Returns the maximum number of words in a [`MessagePiece`] over the field `F`, such
+/// that the piece cannot overflow the field.
+pub fn max_piece_words<F: PrimeField>() -> usize {
+    F::CAPACITY as usize / K
+}
+
+/// The set of circuit instructions required to use the [`HashDomain`] and
+/// [`CommitDomain`] gadgets.
+pub trait SinsemillaInstructions<C: CurveAffine>: EccInstructions<C> {
+    /// Variable representing a piece of a message, made of a whole number of words.
+    type MessagePiece: Clone + fmt::Debug;
+
+    /// Witnesses a message piece with the given value, which must be less than
+    /// $2^{K \cdot \mathsf{num\_words}}$.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `num_words` is zero or greater than [`max_piece_words`].
+    fn witness_message_piece(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<C::Base>,
+        num_words: usize,
+    ) -> Result<Self::MessagePiece, Error>;
+
+    /// Returns the Sinsemilla hash of `message` as a point, starting from `q`.
+    fn hash_to_point(
+        layouter: &mut impl Layouter<Self>,
+        q: C,
+        message: &[Self::MessagePiece],
+    ) -> Result<Self::Point, Error>;
+}
+
+/// A piece of a message to be hashed.
+#[derive(Debug)]
+pub struct MessagePiece<C: CurveAffine, SinsemillaChip: SinsemillaInstructions<C>> {
+    inner: SinsemillaChip::MessagePiece,
+}
+
+impl<C: CurveAffine, SinsemillaChip: SinsemillaInstructions<C>> MessagePiece<C, SinsemillaChip> {
+    /// Witnesses a message piece from its little-endian bits.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of bits is not a nonzero multiple of [`K`], or is longer
+    /// than [`max_piece_words`] words.
+    pub fn from_bits(
+        mut layouter: impl Layouter<SinsemillaChip>,
+        bits: &[Option<bool>],
+    ) -> Result<Self, Error> {
+        assert_eq!(bits.len() % K, 0);
+
+        let value = bits.iter().rev().try_fold(C::Base::zero(), |acc, bit| {
+            bit.map(|bit| acc.double() + C::Base::from(bit))
+        });
+        SinsemillaChip::witness_message_piece(&mut layouter, value, bits.len() / K)
+            .map(|inner| MessagePiece { inner })
+    }
+
+    /// Returns the chip-specific representation of this piece.
+    pub fn inner(&self) -> &SinsemillaChip::MessagePiece {
+        &self.inner
+    }
+}
+
+impl<C: CurveAffine, SinsemillaChip: SinsemillaInstructions<C>> Clone
+    for MessagePiece<C, SinsemillaChip>
+{
+    fn clone(&self) -> Self {
+        MessagePiece {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+/// A domain in which Sinsemilla hashes are computed.
+#[derive(Debug)]
+pub struct HashDomain<C: CurveAffine, SinsemillaChip: SinsemillaInstructions<C>> {
+    q: C,
+    _marker: PhantomData<SinsemillaChip>,
+}
+
+impl<C: CurveAffine, SinsemillaChip: SinsemillaInstructions<C>> HashDomain<C, SinsemillaChip> {
+    /// Constructs the domain with the given name.
+    pub fn new(domain: &str) -> Self {
+        HashDomain {
+            q: sinsemilla::HashDomain::<C>::new(domain).q(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the Sinsemilla hash of `message` as a point.
+    pub fn hash_to_point(
+        &self,
+        mut layouter: impl Layouter<SinsemillaChip>,
+        message: &[MessagePiece<C, SinsemillaChip>],
+    ) -> Result<Point<C, SinsemillaChip>, Error> {
+        let message: Vec<_> = message.iter().map(|piece| piece.inner.clone()).collect();
+        SinsemillaChip::hash_to_point(&mut layouter, self.q, &message).map(Point::from_inner)
+    }
+
+    /// Returns the Sinsemilla hash of `message`.
+    pub fn hash(
+        &self,
+        layouter: impl Layouter<SinsemillaChip>,
+        message: &[MessagePiece<C, SinsemillaChip>],
+    ) -> Result<X<C, SinsemillaChip>, Error> {
+        self.hash_to_point(layouter, message)
+            .map(|point| point.extract_p())
+    }
+}
+
+impl<C: CurveAffine, SinsemillaChip: SinsemillaInstructions<C>> Clone
+    for HashDomain<C, SinsemillaChip>
+{
+    fn clone(&self) -> Self {
+        HashDomain {
+            q: self.q,
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// A domain in which Sinsemilla commitments are computed.
+#[derive(Debug)]
+pub struct CommitDomain<C: CurveAffine, SinsemillaChip: SinsemillaInstructions<C>> {
+    m: HashDomain<C, SinsemillaChip>,
+    r: FixedPoint<C, SinsemillaChip>,
+}
+
+impl<C: CurveAffine, SinsemillaChip: SinsemillaInstructions<C>> CommitDomain<C, SinsemillaChip> {
+    /// Constructs the domain with the given name, as in
+    /// [`sinsemilla::CommitDomain::new`].
+    pub fn new(domain: &str) -> Self {
+        let domain = sinsemilla::CommitDomain::<C>::new(domain);
+        CommitDomain {
+            m: HashDomain {
+                q: domain.hash_domain().q(),
+                _marker: PhantomData,
+            },
+            r: FixedPoint::new(domain.r()),
+        }
+    }
+
+    /// Returns the commitment to `message` with randomness `r`.
+    pub fn commit(
+        &self,
+        mut layouter: impl Layouter<SinsemillaChip>,
+        message: &[MessagePiece<C, SinsemillaChip>],
+        r: Option<C::Scalar>,
+    ) -> Result<Point<C, SinsemillaChip>, Error> {
+        let hash = self
+            .m
+            .hash_to_point(layouter.namespace(|| "hash"), message)?;
+        let blind = self.r.mul(layouter.namespace(|| "[r] R"), r)?;
+        hash.add(layouter.namespace(|| "commit"), &blind)
+    }
+
+    /// Returns the $x$-coordinate of the commitment to `message` with randomness `r`.
+    pub fn short_commit(
+        &self,
+        layouter: impl Layouter<SinsemillaChip>,
+        message: &[MessagePiece<C, SinsemillaChip>],
+        r: Option<C::Scalar>,
+    ) -> Result<X<C, SinsemillaChip>, Error> {
+        self.commit(layouter, message, r)
+            .map(|point| point.extract_p())
+    }
+}
+
+impl<C: CurveAffine, SinsemillaChip: SinsemillaInstructions<C>> Clone
+    for CommitDomain<C, SinsemillaChip>
+{
+    fn clone(&self) -> Self {
+        CommitDomain {
+            m: self.m.clone(),
+            r: self.r.clone(),
+        }
+    }
+}
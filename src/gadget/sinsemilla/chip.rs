@@ -0,0 +1,611 @@
+//! A chip implementing [`SinsemillaInstructions`] with a lookup table of generators.
+//!
+//! # Layout
+//!
+//! The chip includes an [`EccConfig`], and implements [`EccInstructions`] with it, so
+//! that hashes can be combined with other curve operations. The accumulator of a hash
+//! is kept in the `x_p, y_p` columns of the ECC configuration, so that the result is an
+//! [`EccPoint`].
+//!
+//! Hashing a message of $n$ words uses $n + 1$ rows. The first row holds $Q$, which is
+//! constrained to the values of two fixed columns. Row $i$ holds the accumulator $A_i$
+//! in `x_p, y_p`, the generator $S(m_i)$ in `x_s, y_s`, the slopes $\lambda_1$ of
+//! $R = A_i + S(m_i)$ and $\lambda_2$ of $R + A_i$, and the running sum $z_i$ of the
+//! current piece along with the word $m_i = z_i - 2^K z_{i + 1}$ (or $m_i = z_i$ for
+//! the last word of a piece). The next piece then starts at the next row, which
+//! also holds $A_{i + 1}$.
+//!
+//! The triple $(m_i, x_S, y_S)$ is looked up in a table of the $2^K$ generators, which
+//! range-checks each word and thereby constrains each piece to its number of words.
+//! The additions are incomplete, and so are only correct outside of the exceptional
+//! cases, which occur with negligible probability.
+
+use std::marker::PhantomData;
+
+use ff::Field;
+
+use super::{max_piece_words, SinsemillaInstructions, GENERIC_HASH_DOMAIN};
+use crate::{
+    arithmetic::{CurveAffine, FieldExt},
+    circuit::{Cell, Chip, Layouter, Region},
+    gadget::{
+        ecc::{EccChip, EccConfig, EccFixedPoint, EccInstructions, EccPoint, EccX},
+        hash::HashInstructions,
+    },
+    plonk::{Advice, Column, ConstraintSystem, Error, Expression, Fixed, Permutation, Selector},
+    poly::Rotation,
+    primitives::sinsemilla::{self, generators, K},
+};
+
+/// A message piece assigned in the circuit.
+#[derive(Clone, Copy, Debug)]
+pub struct AssignedPiece<F: FieldExt> {
+    cell: Cell,
+    value: Option<F>,
+    num_words: usize,
+}
+
+impl<F: FieldExt> AssignedPiece<F> {
+    /// The cell containing this piece.
+    pub fn cell(&self) -> Cell {
+        self.cell
+    }
+
+    /// The value of this piece, if known.
+    pub fn value(&self) -> Option<F> {
+        self.value
+    }
+
+    /// The number of words in this piece.
+    pub fn num_words(&self) -> usize {
+        self.num_words
+    }
+
+    /// Returns the little-endian words of this piece.
+    fn words(&self) -> Option<Vec<usize>> {
+        self.value.map(|value| {
+            let repr = value.to_repr();
+            let bytes = repr.as_ref();
+            let bit = |i: usize| bytes.get(i / 8).map_or(0, |byte| (byte >> (i % 8)) & 1);
+            (0..self.num_words)
+                .map(|w| (0..K).fold(0, |acc, i| acc | (bit(w * K + i) as usize) << i))
+                .collect()
+        })
+    }
+}
+
+/// Configuration for a [`SinsemillaChip`].
+#[derive(Clone, Debug)]
+pub struct SinsemillaConfig {
+    ecc: EccConfig,
+    z: Column<Advice>,
+    word: Column<Advice>,
+    x_s: Column<Advice>,
+    y_s: Column<Advice>,
+    lambda_1: Column<Advice>,
+    lambda_2: Column<Advice>,
+    q: [Column<Fixed>; 2],
+    table: [Column<Fixed>; 3],
+    s_q: Selector,
+    s_hash: Selector,
+    s_continue: Selector,
+    perm: Permutation,
+}
+
+/// A chip implementing the Sinsemilla hash over the curve `C`, whose base field is the
+/// circuit field.
+///
+/// The generator table uses $2^K = 1024$ rows, and hashing uses one row per word, and
+/// one more row for the result.
+#[derive(Debug)]
+pub struct SinsemillaChip<C: CurveAffine> {
+    _marker: PhantomData<C>,
+}
+
+impl<C: CurveAffine> SinsemillaChip<C> {
+    /// Configures this chip for use in a circuit.
+    ///
+    /// In addition to the columns of an [`EccChip`], the chip allocates 6 advice
+    /// columns, one of which is placed in a permutation, and 5 fixed columns.
+    pub fn configure(meta: &mut ConstraintSystem<C::Base>) -> SinsemillaConfig {
+        let ecc = EccChip::<C>::configure(meta);
+        let z = meta.advice_column();
+        let word = meta.advice_column();
+        let x_s = meta.advice_column();
+        let y_s = meta.advice_column();
+        let lambda_1 = meta.advice_column();
+        let lambda_2 = meta.advice_column();
+        let q = [meta.fixed_column(), meta.fixed_column()];
+        let table = [
+            meta.fixed_column(),
+            meta.fixed_column(),
+            meta.fixed_column(),
+        ];
+
+        let s_q = meta.selector();
+        let s_hash = meta.selector();
+        let s_continue = meta.selector();
+
+        let perm = Permutation::new(meta, &[z.into()]);
+
+        // A_0 = Q
+        for (column, fixed) in [ecc.x_p, ecc.y_p].iter().zip(q.iter()) {
+            meta.create_gate("initial point", |meta| {
+                let coordinate = meta.query_advice(*column, Rotation::cur());
+                let fixed = meta.query_fixed(*fixed, Rotation::cur());
+                let s_q = meta.query_selector(s_q, Rotation::cur());
+
+                s_q * (coordinate - fixed)
+            });
+        }
+
+        let query = |meta: &mut ConstraintSystem<C::Base>| {
+            (
+                meta.query_advice(ecc.x_p, Rotation::cur()),
+                meta.query_advice(ecc.y_p, Rotation::cur()),
+                meta.query_advice(x_s, Rotation::cur()),
+                meta.query_advice(y_s, Rotation::cur()),
+                meta.query_advice(lambda_1, Rotation::cur()),
+                meta.query_advice(lambda_2, Rotation::cur()),
+                meta.query_selector(s_hash, Rotation::cur()),
+            )
+        };
+        // x_R = lambda_1^2 - x_A - x_S
+        let x_r = |lambda_1: Expression<C::Base>, x_a, x_s| lambda_1.clone() * lambda_1 - x_a - x_s;
+
+        // lambda_1 is the slope of the line through A and S.
+        meta.create_gate("Sinsemilla", |meta| {
+            let (x_a, y_a, x_s, y_s, lambda_1, _, s_hash) = query(meta);
+
+            s_hash * (lambda_1 * (x_a - x_s) - (y_a - y_s))
+        });
+        // lambda_2 is the slope of the line through R = A + S and A.
+        meta.create_gate("Sinsemilla", |meta| {
+            let (x_a, y_a, x_s, _, lambda_1, lambda_2, s_hash) = query(meta);
+            let x_r = x_r(lambda_1.clone(), x_a.clone(), x_s);
+            let y_r = lambda_1 * (x_a.clone() - x_r.clone()) - y_a.clone();
+
+            s_hash * (lambda_2 * (x_a - x_r) - (y_a - y_r))
+        });
+        // A_{i+1} = R + A
+        meta.create_gate("Sinsemilla", |meta| {
+            let (x_a, _, x_s, _, lambda_1, lambda_2, s_hash) = query(meta);
+            let x_a_next = meta.query_advice(ecc.x_p, Rotation::next());
+            let x_r = x_r(lambda_1, x_a.clone(), x_s);
+
+            s_hash * (lambda_2.clone() * lambda_2 - x_a - x_r - x_a_next)
+        });
+        meta.create_gate("Sinsemilla", |meta| {
+            let (x_a, y_a, _, _, _, lambda_2, s_hash) = query(meta);
+            let x_a_next = meta.query_advice(ecc.x_p, Rotation::next());
+            let y_a_next = meta.query_advice(ecc.y_p, Rotation::next());
+
+            s_hash * (lambda_2 * (x_a - x_a_next) - y_a - y_a_next)
+        });
+
+        // m_i = z_i - 2^K z_{i+1}, or m_i = z_i for the last word of a piece.
+        meta.create_gate("Sinsemilla word", |meta| {
+            let z_cur = meta.query_advice(z, Rotation::cur());
+            let z_next = meta.query_advice(z, Rotation::next());
+            let word = meta.query_advice(word, Rotation::cur());
+            let s_hash = meta.query_selector(s_hash, Rotation::cur());
+            let s_continue = meta.query_selector(s_continue, Rotation::cur());
+
+            s_hash * (z_cur - s_continue * z_next * C::Base::from_u64(1 << K) - word)
+        });
+
+        // (m_i, S(m_i)) is in the table; when s_hash is disabled, the input is replaced
+        // by the first entry of the table.
+        let (x_s_0, y_s_0) = generators::<C>()[0].get_xy().unwrap();
+        let one = Expression::Constant(C::Base::one());
+        let s_hash_query = meta.query_selector(s_hash, Rotation::cur());
+        let inputs = [
+            meta.query_advice(word, Rotation::cur()),
+            meta.query_advice(x_s, Rotation::cur()),
+            meta.query_advice(y_s, Rotation::cur()),
+        ];
+        let defaults = [C::Base::zero(), x_s_0, y_s_0];
+        let inputs: Vec<_> = inputs
+            .iter()
+            .zip(defaults.iter())
+            .map(|(input, default)| {
+                s_hash_query.clone() * input.clone()
+                    + (one.clone() - s_hash_query.clone()) * *default
+            })
+            .collect();
+        let table_query: Vec<_> = table
+            .iter()
+            .map(|column| meta.query_fixed(*column, Rotation::cur()))
+            .collect();
+        meta.lookup(&inputs, &table_query);
+
+        SinsemillaConfig {
+            ecc,
+            z,
+            word,
+            x_s,
+            y_s,
+            lambda_1,
+            lambda_2,
+            q,
+            table,
+            s_q,
+            s_hash,
+            s_continue,
+            perm,
+        }
+    }
+}
+
+impl<C: CurveAffine> Chip for SinsemillaChip<C> {
+    type Config = SinsemillaConfig;
+    type Loaded = ();
+    type Field = C::Base;
+
+    fn load(layouter: &mut impl Layouter<Self>) -> Result<(), Error> {
+        let config = layouter.config().clone();
+
+        layouter.assign_region(
+            || "generator table",
+            |mut region| {
+                for (j, point) in generators::<C>().iter().enumerate() {
+                    let (x, y) = Option::from(point.get_xy()).unwrap();
+                    for (column, value) in config
+                        .table
+                        .iter()
+                        .zip([C::Base::from_u64(j as u64), x, y].iter())
+                    {
+                        region.assign_fixed(|| "table", *column, j, || Ok(*value))?;
+                    }
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+impl SinsemillaConfig {
+    /// Assigns the hash of `message` from `q` at `offset`.
+    fn assign_hash<C: CurveAffine, Ch: Chip<Field = C::Base>>(
+        &self,
+        region: &mut Region<'_, Ch>,
+        offset: usize,
+        q: C,
+        message: &[AssignedPiece<C::Base>],
+    ) -> Result<EccPoint<C>, Error> {
+        let (x_q, y_q) = Option::from(q.get_xy()).ok_or(Error::SynthesisError)?;
+        self.s_q.enable(region, offset)?;
+        for (column, value) in self.q.iter().zip([x_q, y_q].iter()) {
+            region.assign_fixed(|| "Q", *column, offset, || Ok(*value))?;
+        }
+        let mut acc =
+            self.ecc
+                .assign_point(region, (self.ecc.x_p, self.ecc.y_p), offset, Some(q))?;
+
+        let generators = generators::<C>();
+        let two_pow_k_inv = C::Base::from_u64(1 << K).invert().unwrap();
+        let mut row = offset;
+        for piece in message {
+            let z_0 = region.assign_advice(
+                || "z_0",
+                self.z,
+                row,
+                || piece.value.ok_or(Error::SynthesisError),
+            )?;
+            region.constrain_equal(&self.perm, piece.cell, z_0)?;
+
+            let words = piece.words();
+            let mut z = piece.value;
+            for i in 0..piece.num_words {
+                self.s_hash.enable(region, row)?;
+                let word = words.as_ref().map(|words| words[i]);
+                if i + 1 < piece.num_words {
+                    self.s_continue.enable(region, row)?;
+                    z = z
+                        .zip(word)
+                        .map(|(z, word)| (z - C::Base::from_u64(word as u64)) * two_pow_k_inv);
+                    region.assign_advice(
+                        || format!("z_{}", i + 1),
+                        self.z,
+                        row + 1,
+                        || z.ok_or(Error::SynthesisError),
+                    )?;
+                }
+
+                region.assign_advice(
+                    || format!("m_{}", i),
+                    self.word,
+                    row,
+                    || {
+                        word.map(|word| C::Base::from_u64(word as u64))
+                            .ok_or(Error::SynthesisError)
+                    },
+                )?;
+
+                let s = word.map(|word| generators[word]);
+                let s_xy = s.map(|s| Option::from(s.get_xy()).unwrap());
+                let a_xy = acc.point().and_then(|a| Option::from(a.get_xy()));
+                for (name, column, value) in [
+                    ("x_s", self.x_s, s_xy.map(|(x, _)| x)),
+                    ("y_s", self.y_s, s_xy.map(|(_, y)| y)),
+                ]
+                .iter()
+                {
+                    region.assign_advice(
+                        || *name,
+                        *column,
+                        row,
+                        || value.ok_or(Error::SynthesisError),
+                    )?;
+                }
+
+                // The witnesses are only meaningful outside of the exceptional cases.
+                let lambdas = a_xy.zip(s_xy).map(|((x_a, y_a), (x_s, y_s))| {
+                    let inv0 =
+                        |value: C::Base| Option::from(value.invert()).unwrap_or_else(C::Base::zero);
+                    let lambda_1 = (y_a - y_s) * inv0(x_a - x_s);
+                    let x_r = lambda_1.square() - x_a - x_s;
+                    let y_r = lambda_1 * (x_a - x_r) - y_a;
+                    let lambda_2 = (y_a - y_r) * inv0(x_a - x_r);
+                    let x_b = lambda_2.square() - x_a - x_r;
+                    let y_b = lambda_2 * (x_a - x_b) - y_a;
+                    (lambda_1, lambda_2, x_b, y_b)
+                });
+                for (name, column, value) in [
+                    ("lambda_1", self.lambda_1, lambdas.map(|l| l.0)),
+                    ("lambda_2", self.lambda_2, lambdas.map(|l| l.1)),
+                ]
+                .iter()
+                {
+                    region.assign_advice(
+                        || *name,
+                        *column,
+                        row,
+                        || value.ok_or(Error::SynthesisError),
+                    )?;
+                }
+
+                let next = lambdas.and_then(|(_, _, x, y)| Option::from(C::from_xy(x, y)));
+                row += 1;
+                acc = self
+                    .ecc
+                    .assign_point(region, (self.ecc.x_p, self.ecc.y_p), row, next)?;
+            }
+        }
+
+        Ok(acc)
+    }
+}
+
+impl<C: CurveAffine> EccInstructions<C> for SinsemillaChip<C> {
+    type Point = EccPoint<C>;
+    type X = EccX<C::Base>;
+    type FixedPoint = EccFixedPoint<C>;
+
+    fn fixed_point(base: C) -> Self::FixedPoint {
+        EccChip::<C>::fixed_point(base)
+    }
+
+    fn witness_point(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<C>,
+    ) -> Result<Self::Point, Error> {
+        layouter.config().ecc.clone().witness_point(layouter, value)
+    }
+
+    fn extract_p(point: &Self::Point) -> Self::X {
+        point.extract_p()
+    }
+
+    fn add(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Point,
+        b: &Self::Point,
+    ) -> Result<Self::Point, Error> {
+        layouter.config().ecc.clone().add(layouter, a, b)
+    }
+
+    fn double(layouter: &mut impl Layouter<Self>, a: &Self::Point) -> Result<Self::Point, Error> {
+        layouter.config().ecc.clone().double(layouter, a)
+    }
+
+    fn mul_fixed(
+        layouter: &mut impl Layouter<Self>,
+        scalar: Option<C::Scalar>,
+        base: &Self::FixedPoint,
+    ) -> Result<Self::Point, Error> {
+        layouter
+            .config()
+            .ecc
+            .clone()
+            .mul_fixed(layouter, scalar, base)
+    }
+
+    fn mul_endo(
+        layouter: &mut impl Layouter<Self>,
+        base: &Self::Point,
+        bits: &[Option<bool>],
+    ) -> Result<Self::Point, Error> {
+        layouter.config().ecc.clone().mul_endo(layouter, base, bits)
+    }
+
+    fn constrain_equal(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Point,
+        b: &Self::Point,
+    ) -> Result<(), Error> {
+        layouter
+            .config()
+            .ecc
+            .clone()
+            .constrain_equal(layouter, a, b)
+    }
+}
+
+impl<C: CurveAffine> SinsemillaInstructions<C> for SinsemillaChip<C> {
+    type MessagePiece = AssignedPiece<C::Base>;
+
+    fn witness_message_piece(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<C::Base>,
+        num_words: usize,
+    ) -> Result<Self::MessagePiece, Error> {
+        assert!(num_words > 0 && num_words <= max_piece_words::<C::Base>());
+        let config = layouter.config().clone();
+
+        layouter.assign_region(
+            || "message piece",
+            |mut region| {
+                let cell = region.assign_advice(
+                    || "piece",
+                    config.z,
+                    0,
+                    || value.ok_or(Error::SynthesisError),
+                )?;
+                Ok(AssignedPiece {
+                    cell,
+                    value,
+                    num_words,
+                })
+            },
+        )
+    }
+
+    fn hash_to_point(
+        layouter: &mut impl Layouter<Self>,
+        q: C,
+        message: &[Self::MessagePiece],
+    ) -> Result<Self::Point, Error> {
+        let config = layouter.config().clone();
+
+        layouter.assign_region(
+            || "Sinsemilla hash",
+            |mut region| config.assign_hash(&mut region, 0, q, message),
+        )
+    }
+}
+
+/// Words are message pieces of [`max_piece_words`] words, whose values must be less than
+/// $2^{K \cdot \mathsf{max\_piece\_words}}$, and are hashed in the
+/// [`GENERIC_HASH_DOMAIN`] domain.
+impl<C: CurveAffine> HashInstructions<C::Base> for SinsemillaChip<C> {
+    type Word = AssignedPiece<C::Base>;
+    type State = Vec<AssignedPiece<C::Base>>;
+    type Output = EccX<C::Base>;
+
+    fn witness_word(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<C::Base>,
+    ) -> Result<Self::Word, Error> {
+        Self::witness_message_piece(layouter, value, max_piece_words::<C::Base>())
+    }
+
+    fn init(_layouter: &mut impl Layouter<Self>) -> Result<Self::State, Error> {
+        Ok(vec![])
+    }
+
+    fn absorb(
+        _layouter: &mut impl Layouter<Self>,
+        state: &mut Self::State,
+        words: &[Self::Word],
+    ) -> Result<(), Error> {
+        state.extend_from_slice(words);
+        Ok(())
+    }
+
+    fn finalize(
+        layouter: &mut impl Layouter<Self>,
+        state: Self::State,
+    ) -> Result<Self::Output, Error> {
+        let q = sinsemilla::HashDomain::<C>::new(GENERIC_HASH_DOMAIN).q();
+        Self::hash_to_point(layouter, q, &state).map(|point| Self::extract_p(&point))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SinsemillaChip, SinsemillaConfig};
+    use crate::{
+        arithmetic::{CurveAffine, FieldExt},
+        circuit::{layouter, Layouter},
+        dev::MockProver,
+        gadget::{
+            ecc::Point,
+            sinsemilla::{CommitDomain, HashDomain, MessagePiece},
+        },
+        pasta::pallas,
+        plonk::{Assignment, Circuit, ConstraintSystem, Error},
+        primitives::sinsemilla::{self, K},
+    };
+
+    struct SinsemillaCircuit<C: CurveAffine> {
+        message: Vec<Option<bool>>,
+        r: Option<C::Scalar>,
+        hash: Option<C>,
+        commitment: Option<C>,
+    }
+
+    impl<C: CurveAffine> Circuit<C::Base> for SinsemillaCircuit<C> {
+        type Config = SinsemillaConfig;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<C::Base>) -> SinsemillaConfig {
+            SinsemillaChip::<C>::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<C::Base>,
+            config: SinsemillaConfig,
+        ) -> Result<(), Error> {
+            let mut layouter = layouter::SingleChip::<SinsemillaChip<C>, _>::new(cs, config)?;
+
+            // Split the message into pieces of two and three words.
+            let (left, right) = self.message.split_at(2 * K);
+            let message = vec![
+                MessagePiece::from_bits(layouter.namespace(|| "left"), left)?,
+                MessagePiece::from_bits(layouter.namespace(|| "right"), right)?,
+            ];
+
+            let domain = HashDomain::new("halo2:test");
+            let hash = domain.hash_to_point(layouter.namespace(|| "hash"), &message)?;
+            let expected = Point::new(layouter.namespace(|| "expected hash"), self.hash)?;
+            hash.constrain_equal(layouter.namespace(|| "hash"), &expected)?;
+
+            let domain = CommitDomain::new("halo2:test");
+            let commitment = domain.commit(layouter.namespace(|| "commit"), &message, self.r)?;
+            let expected = Point::new(
+                layouter.namespace(|| "expected commitment"),
+                self.commitment,
+            )?;
+            commitment.constrain_equal(layouter.namespace(|| "commit"), &expected)
+        }
+    }
+
+    #[test]
+    fn sinsemilla_pallas() {
+        let message: Vec<_> = (0..5 * K).map(|i| (i * 7) % 5 < 2).collect();
+        let r = pallas::Scalar::from_u64(0x0123_4567_89ab_cdef);
+
+        let hash = sinsemilla::HashDomain::<pallas::Affine>::new("halo2:test")
+            .hash_to_point(&message)
+            .unwrap();
+        let commitment = sinsemilla::CommitDomain::<pallas::Affine>::new("halo2:test")
+            .commit(&message, &r)
+            .unwrap();
+
+        let mut circuit = SinsemillaCircuit {
+            message: message.into_iter().map(Some).collect(),
+            r: Some(r),
+            hash: Some(hash),
+            commitment: Some(commitment),
+        };
+        let prover = MockProver::run(11, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        circuit.message[3] = circuit.message[3].map(|bit| !bit);
+        let prover = MockProver::run(11, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
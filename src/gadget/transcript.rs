@@ -0,0 +1,127 @@
+//! Gadget and chips for recomputing a Poseidon Fiat–Shamir transcript in a circuit over
+//! the base field of the curve.
+//!
+//! The [`Transcript`] gadget is the in-circuit counterpart of
+//! [`PoseidonRead`](crate::transcript::PoseidonRead) and
+//! [`PoseidonWrite`](crate::transcript::PoseidonWrite), and squeezes the same challenges
+//! for the same sequence of points and scalars. Points are absorbed as their affine
+//! coordinates, and scalars as the low and high 128 bits of their encoding, using the
+//! same duplex sponge and capacity element as the host transcripts.
+
+use std::fmt;
+use std::marker::PhantomData;
+
+use super::{
+    ecc::{EccInstructions, Point},
+    poseidon::{Duplex, PoseidonDuplexInstructions},
+};
+use crate::{
+    arithmetic::CurveAffine, circuit::Layouter, plonk::Error,
+    primitives::poseidon::transcript_capacity_element,
+};
+
+mod chip;
+pub use chip::{TranscriptChip, TranscriptConfig};
+
+/// The set of circuit instructions required to use the [`Transcript`] gadget.
+pub trait TranscriptInstructions<C: CurveAffine>:
+    EccInstructions<C> + PoseidonDuplexInstructions<3, 2>
+{
+    /// Variable representing a 128-bit verifier challenge.
+    type Challenge: Clone + fmt::Debug;
+
+    /// Returns the coordinates of `point` as words that can be absorbed.
+    ///
+    /// Returns an error if `point` is the identity, which cannot be absorbed by the host
+    /// transcripts either.
+    fn point_words(
+        layouter: &mut impl Layouter<Self>,
+        point: &Self::Point,
+    ) -> Result<[Self::Word; 2], Error>;
+
+    /// Witnesses the low and high 128 bits of the encoding of a scalar, constrained to
+    /// be at most 128 bits each, as words that can be absorbed.
+    ///
+    /// The limbs are not constrained to encode a canonical scalar.
+    fn scalar_words(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<C::Scalar>,
+    ) -> Result<[Self::Word; 2], Error>;
+
+    /// Returns the challenge given by the low 128 bits of the canonical encoding of
+    /// `word`, as computed by [`Challenge::get`](crate::transcript::Challenge::get).
+    fn challenge(
+        layouter: &mut impl Layouter<Self>,
+        word: &Self::Word,
+    ) -> Result<Self::Challenge, Error>;
+}
+
+/// A Poseidon transcript recomputed in a circuit.
+#[derive(Debug)]
+pub struct Transcript<C: CurveAffine, TranscriptChip: TranscriptInstructions<C>> {
+    sponge: Duplex<TranscriptChip, 3, 2>,
+    _marker: PhantomData<C>,
+}
+
+impl<C: CurveAffine, TranscriptChip: TranscriptInstructions<C>> Transcript<C, TranscriptChip> {
+    /// Initializes an empty transcript.
+    pub fn new(layouter: impl Layouter<TranscriptChip>) -> Result<Self, Error> {
+        Duplex::new(layouter, transcript_capacity_element()).map(|sponge| Transcript {
+            sponge,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Absorbs `point`, as
+    /// [`Transcript::common_point`](crate::transcript::Transcript::common_point) does.
+    pub fn common_point(
+        &mut self,
+        mut layouter: impl Layouter<TranscriptChip>,
+        point: &Point<C, TranscriptChip>,
+    ) -> Result<(), Error> {
+        let words = TranscriptChip::point_words(&mut layouter, point.inner())?;
+        self.sponge.absorb_chunk(layouter, &words)
+    }
+
+    /// Witnesses and absorbs a scalar, as
+    /// [`Transcript::common_scalar`](crate::transcript::Transcript::common_scalar) does,
+    /// and returns its limbs.
+    pub fn common_scalar(
+        &mut self,
+        mut layouter: impl Layouter<TranscriptChip>,
+        value: Option<C::Scalar>,
+    ) -> Result<[TranscriptChip::Word; 2], Error> {
+        let words = TranscriptChip::scalar_words(&mut layouter, value)?;
+        self.sponge.absorb_chunk(layouter, &words)?;
+        Ok(words)
+    }
+
+    /// Absorbs `words`, such as the limbs of a scalar that is already assigned.
+    pub fn absorb_words(
+        &mut self,
+        layouter: impl Layouter<TranscriptChip>,
+        words: &[TranscriptChip::Word],
+    ) -> Result<(), Error> {
+        self.sponge.absorb_chunk(layouter, words)
+    }
+
+    /// Squeezes a base field element, as
+    /// [`Transcript::squeeze_challenge`](crate::transcript::Transcript::squeeze_challenge)
+    /// does.
+    pub fn squeeze(
+        &mut self,
+        layouter: impl Layouter<TranscriptChip>,
+    ) -> Result<TranscriptChip::Word, Error> {
+        self.sponge.squeeze(layouter)
+    }
+
+    /// Squeezes a 128-bit challenge, as
+    /// [`Challenge::get`](crate::transcript::Challenge::get) does.
+    pub fn squeeze_challenge(
+        &mut self,
+        mut layouter: impl Layouter<TranscriptChip>,
+    ) -> Result<TranscriptChip::Challenge, Error> {
+        let word = self.sponge.squeeze(layouter.namespace(|| "squeeze"))?;
+        TranscriptChip::challenge(&mut layouter, &word)
+    }
+}
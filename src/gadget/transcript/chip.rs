@@ -0,0 +1,530 @@
+//! A chip implementing [`TranscriptInstructions`] with the ECC and Poseidon chips.
+//!
+//! # Layout
+//!
+//! The chip includes an [`EccConfig`] and a [`Pow5T3Config`] configured with
+//! [`P128Pow5T3`], and implements [`EccInstructions`] and
+//! [`PoseidonDuplexInstructions`] with them. A permutation over the `x_p, y_p` columns
+//! of the ECC configuration, the Poseidon state columns and the `z` and `low` columns
+//! of the chip connects them.
+//!
+//! Limbs and challenges are decomposed by a [`RunningSumConfig`] into 2-bit windows in
+//! the `z` and `window` columns. A scalar limb uses a strict decomposition into 64
+//! windows, so it fits in 128 bits, and takes 65 rows.
+//!
+//! A challenge is extracted from a word $w$ by a strict decomposition into 127
+//! windows, which uses 128 rows and proves that $w < 2^{254}$. As the base field has a
+//! modulus greater than $2^{254}$, this is the canonical encoding of $w$, and the
+//! challenge $w - 2^{128} z_{64}$ is constrained in the `low` column of the first row.
+//! Words of at least $2^{254}$ therefore cannot be decomposed, but occur with
+//! negligible probability for a squeezed word.
+
+use std::convert::TryInto;
+use std::marker::PhantomData;
+
+use ff::{Field, PrimeField};
+
+use super::TranscriptInstructions;
+use crate::{
+    arithmetic::{CurveAffine, FieldExt},
+    circuit::{Chip, Layouter, Region},
+    gadget::{
+        ecc::{EccChip, EccConfig, EccFixedPoint, EccInstructions, EccPoint, EccX},
+        ipa::AssignedChallenge,
+        poseidon::{
+            PoseidonDuplexInstructions, PoseidonInstructions, Pow5T3Chip, Pow5T3Config,
+            SpongeState, StateWord,
+        },
+        running_sum::{RunningSum, RunningSumConfig},
+    },
+    plonk::{Advice, Column, ConstraintSystem, Error, Permutation, Selector},
+    poly::Rotation,
+    primitives::poseidon::{P128Pow5T3, Spec, State},
+    transcript::Challenge,
+};
+
+/// The number of bits of each window of a decomposition.
+const WINDOW_BITS: usize = 2;
+
+/// The number of windows of a 128-bit limb.
+const LIMB_WINDOWS: usize = 128 / WINDOW_BITS;
+
+/// The number of windows proving that a word is less than $2^{254}$.
+const WORD_WINDOWS: usize = 254 / WINDOW_BITS;
+
+/// Configuration for a [`TranscriptChip`].
+#[derive(Clone, Debug)]
+pub struct TranscriptConfig<F: FieldExt> {
+    pub(crate) ecc: EccConfig,
+    pub(crate) poseidon: Pow5T3Config<F>,
+    pub(crate) running_sum: RunningSumConfig,
+    pub(crate) low: Column<Advice>,
+    s_low: Selector,
+    pub(crate) perm: Permutation,
+}
+
+/// A chip recomputing Poseidon transcripts over the curve `C`.
+#[derive(Debug)]
+pub struct TranscriptChip<C: CurveAffine> {
+    _marker: PhantomData<C>,
+}
+
+impl<C: CurveAffine> TranscriptChip<C>
+where
+    P128Pow5T3: Spec<C::Base, 3, 2>,
+{
+    /// Configures this chip for use in a circuit.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the modulus of the base field is not between $2^{254}$ and $2^{255}$.
+    pub fn configure(meta: &mut ConstraintSystem<C::Base>) -> TranscriptConfig<C::Base> {
+        assert_eq!(C::Base::NUM_BITS, 255);
+
+        let ecc = EccChip::<C>::configure(meta);
+        let state = [
+            meta.advice_column(),
+            meta.advice_column(),
+            meta.advice_column(),
+        ];
+        let partial_sbox = meta.advice_column();
+        let poseidon = Pow5T3Chip::configure::<P128Pow5T3>(meta, state, partial_sbox);
+
+        let z = meta.advice_column();
+        let window = meta.advice_column();
+        let running_sum = RunningSumConfig::configure(meta, z, window, WINDOW_BITS, true);
+        let low = meta.advice_column();
+        let s_low = meta.selector();
+
+        let perm = Permutation::new(
+            meta,
+            &[
+                ecc.x_p.into(),
+                ecc.y_p.into(),
+                state[0].into(),
+                state[1].into(),
+                state[2].into(),
+                z.into(),
+                low.into(),
+            ],
+        );
+
+        // low = z_0 - 2^128 z_64
+        let two_pow_128 = C::Base::from_u128(1 << 64).square();
+        meta.create_gate("challenge low bits", |meta| {
+            let z_0 = meta.query_advice(z, Rotation::cur());
+            let z_64 = meta.query_advice(z, Rotation(LIMB_WINDOWS as i32));
+            let low = meta.query_advice(low, Rotation::cur());
+            let s_low = meta.query_selector(s_low, Rotation::cur());
+
+            s_low * (z_0 - z_64 * two_pow_128 - low)
+        });
+
+        TranscriptConfig {
+            ecc,
+            poseidon,
+            running_sum,
+            low,
+            s_low,
+            perm,
+        }
+    }
+}
+
+impl<C: CurveAffine> Chip for TranscriptChip<C> {
+    type Config = TranscriptConfig<C::Base>;
+    type Loaded = ();
+    type Field = C::Base;
+
+    fn load(_layouter: &mut impl Layouter<Self>) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<C: CurveAffine> EccInstructions<C> for TranscriptChip<C> {
+    type Point = EccPoint<C>;
+    type X = EccX<C::Base>;
+    type FixedPoint = EccFixedPoint<C>;
+
+    fn fixed_point(base: C) -> Self::FixedPoint {
+        EccChip::<C>::fixed_point(base)
+    }
+
+    fn witness_point(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<C>,
+    ) -> Result<Self::Point, Error> {
+        layouter.config().ecc.clone().witness_point(layouter, value)
+    }
+
+    fn extract_p(point: &Self::Point) -> Self::X {
+        point.extract_p()
+    }
+
+    fn add(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Point,
+        b: &Self::Point,
+    ) -> Result<Self::Point, Error> {
+        layouter.config().ecc.clone().add(layouter, a, b)
+    }
+
+    fn double(layouter: &mut impl Layouter<Self>, a: &Self::Point) -> Result<Self::Point, Error> {
+        layouter.config().ecc.clone().double(layouter, a)
+    }
+
+    fn mul_fixed(
+        layouter: &mut impl Layouter<Self>,
+        scalar: Option<C::Scalar>,
+        base: &Self::FixedPoint,
+    ) -> Result<Self::Point, Error> {
+        layouter
+            .config()
+            .ecc
+            .clone()
+            .mul_fixed(layouter, scalar, base)
+    }
+
+    fn mul_endo(
+        layouter: &mut impl Layouter<Self>,
+        base: &Self::Point,
+        bits: &[Option<bool>],
+    ) -> Result<Self::Point, Error> {
+        layouter.config().ecc.clone().mul_endo(layouter, base, bits)
+    }
+
+    fn constrain_equal(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Point,
+        b: &Self::Point,
+    ) -> Result<(), Error> {
+        layouter
+            .config()
+            .ecc
+            .clone()
+            .constrain_equal(layouter, a, b)
+    }
+}
+
+impl<C: CurveAffine> PoseidonInstructions<3> for TranscriptChip<C> {
+    type Word = StateWord<C::Base>;
+
+    fn permute(
+        layouter: &mut impl Layouter<Self>,
+        initial_state: &State<Self::Word, 3>,
+    ) -> Result<State<Self::Word, 3>, Error> {
+        layouter
+            .config()
+            .poseidon
+            .clone()
+            .permute(layouter, initial_state)
+    }
+}
+
+impl<C: CurveAffine> PoseidonDuplexInstructions<3, 2> for TranscriptChip<C> {
+    fn load_message(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<C::Base>,
+    ) -> Result<Self::Word, Error> {
+        let config = layouter.config().poseidon.sponge.clone();
+        config.load_message(layouter, value)
+    }
+
+    fn initial_state(
+        layouter: &mut impl Layouter<Self>,
+        initial_capacity_element: C::Base,
+    ) -> Result<State<Self::Word, 3>, Error> {
+        let config = layouter.config().poseidon.sponge.clone();
+        config.initial_state(layouter, initial_capacity_element)
+    }
+
+    fn pad_and_add(
+        layouter: &mut impl Layouter<Self>,
+        initial_state: &State<Self::Word, 3>,
+        input: &SpongeState<Self::Word, C::Base, 2>,
+    ) -> Result<State<Self::Word, 3>, Error> {
+        let config = layouter.config().poseidon.sponge.clone();
+        config.pad_and_add(layouter, initial_state, input)
+    }
+
+    fn get_output(state: &State<Self::Word, 3>) -> [Self::Word; 2] {
+        [state[0], state[1]]
+    }
+
+    fn absorb_blocks(
+        layouter: &mut impl Layouter<Self>,
+        initial_state: &State<Self::Word, 3>,
+        inputs: &[SpongeState<Self::Word, C::Base, 2>],
+    ) -> Result<State<Self::Word, 3>, Error> {
+        layouter
+            .config()
+            .poseidon
+            .clone()
+            .absorb_blocks(layouter, initial_state, inputs)
+    }
+}
+
+/// The instructions of [`TranscriptChip`], for use by other chips that include a
+/// [`TranscriptConfig`] in their own configuration.
+impl<F: FieldExt> TranscriptConfig<F> {
+    /// Returns the coordinates of `point` as words that can be absorbed.
+    pub(crate) fn point_words<C: CurveAffine<Base = F>, Ch: Chip<Field = F>>(
+        &self,
+        layouter: &mut impl Layouter<Ch>,
+        point: &EccPoint<C>,
+    ) -> Result<[StateWord<F>; 2], Error> {
+        let coordinates = point
+            .point()
+            .map(|point| Option::from(point.get_xy()).ok_or(Error::SynthesisError))
+            .transpose()?;
+
+        layouter.assign_region(
+            || "point words",
+            |mut region| {
+                let mut word = |i: usize, cell, value: Option<F>| {
+                    let var = region.assign_advice(
+                        || format!("coordinate {}", i),
+                        self.poseidon.sponge.state[i],
+                        0,
+                        || value.ok_or(Error::SynthesisError),
+                    )?;
+                    region.constrain_equal(&self.perm, cell, var)?;
+                    Ok(StateWord { var, value })
+                };
+                Ok([
+                    word(0, point.x(), coordinates.map(|(x, _)| x))?,
+                    word(1, point.y(), coordinates.map(|(_, y)| y))?,
+                ])
+            },
+        )
+    }
+
+    /// Assigns the challenge given by the low 128 bits of `word` at `offset`, and
+    /// returns it with the decomposition of `word`, whose first 64 windows are the
+    /// pairs of bits of the challenge.
+    ///
+    /// The decomposition uses 128 rows.
+    pub(crate) fn assign_challenge<Ch: Chip<Field = F>>(
+        &self,
+        region: &mut Region<'_, Ch>,
+        offset: usize,
+        word: &StateWord<F>,
+    ) -> Result<(AssignedChallenge, RunningSum<F>), Error> {
+        let value = word.value.map(|value| Challenge(value.get_lower_128()));
+
+        let running_sum =
+            self.running_sum
+                .assign(region, offset, word.value, WORD_WINDOWS, true)?;
+        region.constrain_equal(&self.perm, running_sum.zs()[0].cell(), word.var)?;
+
+        self.s_low.enable(region, offset)?;
+        let cell = region.assign_advice(
+            || "challenge",
+            self.low,
+            offset,
+            || {
+                value
+                    .map(|value| F::from_u128(value.0))
+                    .ok_or(Error::SynthesisError)
+            },
+        )?;
+        Ok((AssignedChallenge { cell, value }, running_sum))
+    }
+}
+
+impl<C: CurveAffine> TranscriptInstructions<C> for TranscriptChip<C> {
+    type Challenge = AssignedChallenge;
+
+    fn point_words(
+        layouter: &mut impl Layouter<Self>,
+        point: &Self::Point,
+    ) -> Result<[Self::Word; 2], Error> {
+        layouter.config().clone().point_words(layouter, point)
+    }
+
+    fn scalar_words(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<C::Scalar>,
+    ) -> Result<[Self::Word; 2], Error> {
+        let config = layouter.config().clone();
+        let limbs = value.map(|value| {
+            let bytes = value.to_bytes();
+            [
+                u128::from_le_bytes(bytes[..16].try_into().unwrap()),
+                u128::from_le_bytes(bytes[16..].try_into().unwrap()),
+            ]
+        });
+
+        layouter.assign_region(
+            || "scalar words",
+            |mut region| {
+                let mut word = |i: usize| {
+                    let value = limbs.map(|limbs| C::Base::from_u128(limbs[i]));
+                    let limb = config.running_sum.assign(
+                        &mut region,
+                        i * (LIMB_WINDOWS + 1),
+                        value,
+                        LIMB_WINDOWS,
+                        true,
+                    )?;
+                    let var = region.assign_advice(
+                        || format!("limb {}", i),
+                        config.poseidon.sponge.state[i],
+                        0,
+                        || value.ok_or(Error::SynthesisError),
+                    )?;
+                    region.constrain_equal(&config.perm, limb.zs()[0].cell(), var)?;
+                    Ok(StateWord { var, value })
+                };
+                Ok([word(0)?, word(1)?])
+            },
+        )
+    }
+
+    fn challenge(
+        layouter: &mut impl Layouter<Self>,
+        word: &Self::Word,
+    ) -> Result<Self::Challenge, Error> {
+        let config = layouter.config().clone();
+        layouter.assign_region(
+            || "challenge",
+            |mut region| {
+                config
+                    .assign_challenge(&mut region, 0, word)
+                    .map(|(challenge, _)| challenge)
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use group::{Curve, Group};
+
+    use super::{TranscriptChip, TranscriptConfig};
+    use crate::{
+        arithmetic::FieldExt,
+        circuit::{layouter, Layouter},
+        dev::MockProver,
+        gadget::{
+            ecc::Point,
+            poseidon::PoseidonDuplexInstructions,
+            transcript::{Transcript, TranscriptInstructions},
+        },
+        pasta::pallas,
+        plonk::{Assignment, Circuit, ConstraintSystem, Error},
+        transcript::{Challenge, PoseidonWrite, Transcript as _, TranscriptWrite},
+    };
+
+    struct TranscriptCircuit {
+        points: [pallas::Affine; 2],
+        scalar: pallas::Scalar,
+        expected: Option<(pallas::Base, u128, u128)>,
+    }
+
+    impl Circuit<pallas::Base> for TranscriptCircuit {
+        type Config = TranscriptConfig<pallas::Base>;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+            TranscriptChip::<pallas::Affine>::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<pallas::Base>,
+            config: Self::Config,
+        ) -> Result<(), Error> {
+            let mut layouter =
+                layouter::SingleChip::<TranscriptChip<pallas::Affine>, _>::new(cs, config)?;
+
+            let mut transcript = Transcript::new(layouter.namespace(|| "init"))?;
+            let p = Point::new(layouter.namespace(|| "P"), Some(self.points[0]))?;
+            transcript.common_point(layouter.namespace(|| "absorb P"), &p)?;
+            let x = transcript.squeeze(layouter.namespace(|| "x"))?;
+            transcript.common_scalar(layouter.namespace(|| "absorb s"), Some(self.scalar))?;
+            let u = transcript.squeeze_challenge(layouter.namespace(|| "u"))?;
+            let q = Point::new(layouter.namespace(|| "Q"), Some(self.points[1]))?;
+            transcript.common_point(layouter.namespace(|| "absorb Q"), &q)?;
+            let v = transcript.squeeze_challenge(layouter.namespace(|| "v"))?;
+
+            if let Some((x_expected, u_expected, v_expected)) = self.expected {
+                assert_eq!(x.value, Some(x_expected));
+                assert_eq!(u.value().map(|u| u.0), Some(u_expected));
+                assert_eq!(v.value().map(|v| v.0), Some(v_expected));
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn transcript_matches_host() {
+        let points = [
+            pallas::Point::generator().to_affine(),
+            pallas::Point::generator().double().to_affine(),
+        ];
+        let scalar = -pallas::Scalar::from_u64(5);
+
+        let mut host = PoseidonWrite::<Vec<u8>, pallas::Affine>::init(vec![]);
+        host.write_point(points[0]).unwrap();
+        let x = host.squeeze_challenge();
+        host.write_scalar(scalar).unwrap();
+        let u = Challenge::get(&mut host);
+        host.write_point(points[1]).unwrap();
+        let v = Challenge::get(&mut host);
+
+        let circuit = TranscriptCircuit {
+            points,
+            scalar,
+            expected: Some((x, u.0, v.0)),
+        };
+        let prover = MockProver::run(11, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    struct ChallengeCircuit {
+        word: pallas::Base,
+    }
+
+    impl Circuit<pallas::Base> for ChallengeCircuit {
+        type Config = TranscriptConfig<pallas::Base>;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+            TranscriptChip::<pallas::Affine>::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<pallas::Base>,
+            config: Self::Config,
+        ) -> Result<(), Error> {
+            let mut layouter =
+                layouter::SingleChip::<TranscriptChip<pallas::Affine>, _>::new(cs, config)?;
+
+            let word = TranscriptChip::load_message(&mut layouter, Some(self.word))?;
+            TranscriptChip::challenge(&mut layouter, &word)?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn challenge_decomposition() {
+        let run = |word| {
+            MockProver::run(8, &ChallengeCircuit { word }, vec![])
+                .unwrap()
+                .verify()
+        };
+
+        let two_pow_128 = pallas::Base::from_u128(1 << 64).square();
+        assert_eq!(run(pallas::Base::from_u64(7)), Ok(()));
+        assert_eq!(
+            run(two_pow_128 * pallas::Base::from_u64(3) + two_pow_128),
+            Ok(())
+        );
+        // Words of at least 2^254 have no decomposition.
+        assert!(run(-pallas::Base::one()).is_err());
+    }
+}
@@ -0,0 +1,153 @@
+//! Small gadgets that are commonly needed by larger circuits.
+//!
+//! [`is_zero`] witnesses the inverse $x^{-1}$ of its input (or zero if $x = 0$), and
+//! returns $y = 1 - x \cdot x^{-1}$ with the constraint $x \cdot y = 0$. Both
+//! constraints are needed: without the second, a prover could witness any inverse and
+//! claim that a nonzero $x$ is zero. The constraints are provided by [`IsZeroConfig`],
+//! which other chips can embed to use $y$ in their own gates.
+//!
+//! [`select`] returns $c \cdot a + (1 - c) \cdot b$ for a condition $c$, which it
+//! constrains to be boolean.
+
+use std::fmt;
+
+use crate::{
+    arithmetic::FieldExt,
+    circuit::{Cell, Chip, Layouter, Region},
+    plonk::{Advice, Column, ConstraintSystem, Error, Expression, Selector},
+    poly::Rotation,
+};
+
+mod chip;
+pub use chip::{UtilitiesChip, UtilitiesConfig};
+
+/// A field element assigned in the circuit.
+#[derive(Clone, Copy, Debug)]
+pub struct AssignedValue<F: FieldExt> {
+    pub(crate) cell: Cell,
+    pub(crate) value: Option<F>,
+}
+
+impl<F: FieldExt> AssignedValue<F> {
+    /// The cell containing this value.
+    pub fn cell(&self) -> Cell {
+        self.cell
+    }
+
+    /// The value, if known.
+    pub fn value(&self) -> Option<F> {
+        self.value
+    }
+}
+
+/// The set of circuit instructions required to use [`is_zero`] and [`select`].
+pub trait UtilitiesInstructions: Chip {
+    /// Variable representing a field element.
+    type Var: Clone + fmt::Debug;
+
+    /// Witnesses a field element.
+    fn witness(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<Self::Field>,
+    ) -> Result<Self::Var, Error>;
+
+    /// Returns one if `x` is zero, and zero otherwise.
+    fn is_zero(layouter: &mut impl Layouter<Self>, x: &Self::Var) -> Result<Self::Var, Error>;
+
+    /// Returns `a` if `condition` is one and `b` if it is zero, constraining `condition`
+    /// to be boolean.
+    fn select(
+        layouter: &mut impl Layouter<Self>,
+        condition: &Self::Var,
+        a: &Self::Var,
+        b: &Self::Var,
+    ) -> Result<Self::Var, Error>;
+}
+
+/// Returns one if `x` is zero, and zero otherwise.
+pub fn is_zero<UtilitiesChip: UtilitiesInstructions>(
+    mut layouter: impl Layouter<UtilitiesChip>,
+    x: &UtilitiesChip::Var,
+) -> Result<UtilitiesChip::Var, Error> {
+    UtilitiesChip::is_zero(&mut layouter, x)
+}
+
+/// Returns `a` if `condition` is one and `b` if it is zero.
+///
+/// Fails to verify if `condition` is not boolean.
+pub fn select<UtilitiesChip: UtilitiesInstructions>(
+    mut layouter: impl Layouter<UtilitiesChip>,
+    condition: &UtilitiesChip::Var,
+    a: &UtilitiesChip::Var,
+    b: &UtilitiesChip::Var,
+) -> Result<UtilitiesChip::Var, Error> {
+    UtilitiesChip::select(&mut layouter, condition, a, b)
+}
+
+/// Configuration for the constraints of an is-zero check, for use in other gates.
+///
+/// The check of $x$ uses a cell in the `inverse` column of the same row as $x$.
+#[derive(Clone, Debug)]
+pub struct IsZeroConfig<F: FieldExt> {
+    q_enable: Selector,
+    inverse: Column<Advice>,
+    is_zero: Expression<F>,
+}
+
+impl<F: FieldExt> IsZeroConfig<F> {
+    /// Configures an is-zero check of the expression returned by `x`, on the rows
+    /// where `q_enable` is enabled.
+    pub fn configure(
+        meta: &mut ConstraintSystem<F>,
+        q_enable: Selector,
+        x: impl Fn(&mut ConstraintSystem<F>) -> Expression<F>,
+        inverse: Column<Advice>,
+    ) -> Self {
+        let mut is_zero = Expression::Constant(F::zero());
+        meta.create_gate("is zero", |meta| {
+            let x = x(meta);
+            let inverse = meta.query_advice(inverse, Rotation::cur());
+            let q_enable = meta.query_selector(q_enable, Rotation::cur());
+
+            // x * (1 - x * x^{-1}) = 0
+            is_zero = Expression::Constant(F::one()) - x.clone() * inverse;
+            q_enable * x * is_zero.clone()
+        });
+
+        IsZeroConfig {
+            q_enable,
+            inverse,
+            is_zero,
+        }
+    }
+
+    /// The expression that is one if $x$ is zero, and zero otherwise, on the rows
+    /// where the check is enabled.
+    pub fn expr(&self) -> Expression<F> {
+        self.is_zero.clone()
+    }
+
+    /// Assigns the inverse of `x` at `offset`, and returns whether `x` is zero.
+    ///
+    /// This does not enable the selector of the check.
+    pub fn assign<Ch: Chip<Field = F>>(
+        &self,
+        region: &mut Region<'_, Ch>,
+        offset: usize,
+        x: Option<F>,
+    ) -> Result<Option<bool>, Error> {
+        let inverse = x.map(|x| x.invert().unwrap_or(F::zero()));
+        region.assign_advice(
+            || "inverse",
+            self.inverse,
+            offset,
+            || inverse.ok_or(Error::SynthesisError),
+        )?;
+        Ok(x.map(|x| x == F::zero()))
+    }
+
+    /// The selector enabling the check.
+    pub fn q_enable(&self) -> Selector {
+        self.q_enable
+    }
+}
@@ -0,0 +1,332 @@
+//! A chip implementing [`UtilitiesInstructions`].
+//!
+//! # Layout
+//!
+//! The chip uses four advice columns $a, b, c, d$ in a permutation. An is-zero check of
+//! $x$ uses one row holding $x$ in $a$, its inverse in $b$ and the result in $c$. A
+//! selection uses one row holding the condition in $a$, the two inputs in $b$ and $c$,
+//! and the result in $d$.
+
+use std::marker::PhantomData;
+
+use super::{AssignedValue, IsZeroConfig, UtilitiesInstructions};
+use crate::{
+    arithmetic::FieldExt,
+    circuit::{Chip, Layouter},
+    plonk::{Advice, Column, ConstraintSystem, Error, Expression, Permutation, Selector},
+    poly::Rotation,
+};
+
+/// Configuration for a [`UtilitiesChip`].
+#[derive(Clone, Debug)]
+pub struct UtilitiesConfig<F: FieldExt> {
+    advices: [Column<Advice>; 4],
+    is_zero: IsZeroConfig<F>,
+    q_select: Selector,
+    perm: Permutation,
+}
+
+/// A chip implementing [`UtilitiesInstructions`].
+#[derive(Debug)]
+pub struct UtilitiesChip<F: FieldExt> {
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt> Chip for UtilitiesChip<F> {
+    type Config = UtilitiesConfig<F>;
+    type Loaded = ();
+    type Field = F;
+
+    fn load(_layouter: &mut impl Layouter<Self>) -> Result<(), Error> {
+        // This chip has no tables.
+        Ok(())
+    }
+}
+
+impl<F: FieldExt> UtilitiesChip<F> {
+    /// Configures this chip with four advice columns, which are placed in a
+    /// permutation.
+    pub fn configure(meta: &mut ConstraintSystem<F>) -> UtilitiesConfig<F> {
+        let advices = [
+            meta.advice_column(),
+            meta.advice_column(),
+            meta.advice_column(),
+            meta.advice_column(),
+        ];
+        let perm = Permutation::new(
+            meta,
+            &advices
+                .iter()
+                .map(|column| (*column).into())
+                .collect::<Vec<_>>(),
+        );
+
+        let q_is_zero = meta.selector();
+        let is_zero = IsZeroConfig::configure(
+            meta,
+            q_is_zero,
+            |meta| meta.query_advice(advices[0], Rotation::cur()),
+            advices[1],
+        );
+        meta.create_gate("is zero output", |meta| {
+            let out = meta.query_advice(advices[2], Rotation::cur());
+            let q_is_zero = meta.query_selector(q_is_zero, Rotation::cur());
+
+            q_is_zero * (out - is_zero.expr())
+        });
+
+        let q_select = meta.selector();
+        meta.create_gate("select condition", |meta| {
+            let condition = meta.query_advice(advices[0], Rotation::cur());
+            let q_select = meta.query_selector(q_select, Rotation::cur());
+
+            q_select * condition.clone() * (Expression::Constant(F::one()) - condition)
+        });
+        meta.create_gate("select", |meta| {
+            let condition = meta.query_advice(advices[0], Rotation::cur());
+            let a = meta.query_advice(advices[1], Rotation::cur());
+            let b = meta.query_advice(advices[2], Rotation::cur());
+            let out = meta.query_advice(advices[3], Rotation::cur());
+            let q_select = meta.query_selector(q_select, Rotation::cur());
+
+            // out = b + condition * (a - b)
+            q_select * (out - b.clone() - condition * (a - b))
+        });
+
+        UtilitiesConfig {
+            advices,
+            is_zero,
+            q_select,
+            perm,
+        }
+    }
+}
+
+impl<F: FieldExt> UtilitiesInstructions for UtilitiesChip<F> {
+    type Var = AssignedValue<F>;
+
+    fn witness(layouter: &mut impl Layouter<Self>, value: Option<F>) -> Result<Self::Var, Error> {
+        let config = layouter.config().clone();
+        layouter.assign_region(
+            || "witness",
+            |mut region| {
+                let cell = region.assign_advice(
+                    || "witness",
+                    config.advices[0],
+                    0,
+                    || value.ok_or(Error::SynthesisError),
+                )?;
+                Ok(AssignedValue { cell, value })
+            },
+        )
+    }
+
+    fn is_zero(layouter: &mut impl Layouter<Self>, x: &Self::Var) -> Result<Self::Var, Error> {
+        let config = layouter.config().clone();
+        layouter.assign_region(
+            || "is zero",
+            |mut region| {
+                config.is_zero.q_enable().enable(&mut region, 0)?;
+
+                let x_cell = region.assign_advice(
+                    || "x",
+                    config.advices[0],
+                    0,
+                    || x.value.ok_or(Error::SynthesisError),
+                )?;
+                region.constrain_equal(&config.perm, x_cell, x.cell)?;
+
+                let value = config
+                    .is_zero
+                    .assign(&mut region, 0, x.value)?
+                    .map(|is_zero| if is_zero { F::one() } else { F::zero() });
+                let cell = region.assign_advice(
+                    || "is zero",
+                    config.advices[2],
+                    0,
+                    || value.ok_or(Error::SynthesisError),
+                )?;
+                Ok(AssignedValue { cell, value })
+            },
+        )
+    }
+
+    fn select(
+        layouter: &mut impl Layouter<Self>,
+        condition: &Self::Var,
+        a: &Self::Var,
+        b: &Self::Var,
+    ) -> Result<Self::Var, Error> {
+        let config = layouter.config().clone();
+        layouter.assign_region(
+            || "select",
+            |mut region| {
+                config.q_select.enable(&mut region, 0)?;
+
+                for (column, input) in config.advices.iter().zip([condition, a, b]) {
+                    let cell = region.assign_advice(
+                        || "input",
+                        *column,
+                        0,
+                        || input.value.ok_or(Error::SynthesisError),
+                    )?;
+                    region.constrain_equal(&config.perm, cell, input.cell)?;
+                }
+
+                let value = condition
+                    .value
+                    .zip(a.value)
+                    .zip(b.value)
+                    .map(|((condition, a), b)| b + condition * (a - b));
+                let cell = region.assign_advice(
+                    || "selected",
+                    config.advices[3],
+                    0,
+                    || value.ok_or(Error::SynthesisError),
+                )?;
+                Ok(AssignedValue { cell, value })
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ff::Field;
+
+    use super::{UtilitiesChip, UtilitiesConfig};
+    use crate::{
+        arithmetic::FieldExt,
+        circuit::{layouter, Layouter},
+        dev::MockProver,
+        gadget::utilities::{is_zero, select, UtilitiesInstructions},
+        pasta::Fp,
+        plonk::{Assignment, Circuit, ConstraintSystem, Error},
+    };
+
+    struct UtilitiesCircuit {
+        x: Option<Fp>,
+        condition: Option<Fp>,
+    }
+
+    impl Circuit<Fp> for UtilitiesCircuit {
+        type Config = UtilitiesConfig<Fp>;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> UtilitiesConfig<Fp> {
+            UtilitiesChip::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            config: UtilitiesConfig<Fp>,
+        ) -> Result<(), Error> {
+            let mut layouter = layouter::SingleChip::<UtilitiesChip<Fp>, _>::new(cs, config)?;
+
+            let x = UtilitiesChip::witness(&mut layouter, self.x)?;
+            let x_is_zero = is_zero(layouter.namespace(|| "x == 0"), &x)?;
+            assert_eq!(
+                x_is_zero.value(),
+                self.x.map(|x| if x == Fp::zero() {
+                    Fp::one()
+                } else {
+                    Fp::zero()
+                })
+            );
+
+            let condition = UtilitiesChip::witness(&mut layouter, self.condition)?;
+            let a = UtilitiesChip::witness(&mut layouter, Some(Fp::from_u64(5)))?;
+            let b = UtilitiesChip::witness(&mut layouter, Some(Fp::from_u64(7)))?;
+            let selected = select(layouter.namespace(|| "select"), &condition, &a, &b)?;
+            if self.condition == Some(Fp::one()) {
+                assert_eq!(selected.value(), a.value());
+            } else if self.condition == Some(Fp::zero()) {
+                assert_eq!(selected.value(), b.value());
+            }
+
+            // The result of an is-zero check can be used as a condition.
+            select(layouter.namespace(|| "select x == 0"), &x_is_zero, &a, &b).map(|_| ())
+        }
+    }
+
+    #[test]
+    fn utilities() {
+        let run = |x: u64, condition: u64| {
+            let circuit = UtilitiesCircuit {
+                x: Some(Fp::from_u64(x)),
+                condition: Some(Fp::from_u64(condition)),
+            };
+            MockProver::run(3, &circuit, vec![]).unwrap().verify()
+        };
+
+        assert_eq!(run(0, 0), Ok(()));
+        assert_eq!(run(3, 1), Ok(()));
+        assert_eq!(run(0, 1), Ok(()));
+
+        // The condition of a selection must be boolean.
+        assert!(run(3, 2).is_err());
+    }
+
+    /// A circuit assigning an is-zero check with an arbitrary inverse and result.
+    struct IsZeroCircuit {
+        x: Fp,
+        inverse: Fp,
+        out: Fp,
+    }
+
+    impl Circuit<Fp> for IsZeroCircuit {
+        type Config = UtilitiesConfig<Fp>;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> UtilitiesConfig<Fp> {
+            UtilitiesChip::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            config: UtilitiesConfig<Fp>,
+        ) -> Result<(), Error> {
+            let mut layouter = layouter::SingleChip::<UtilitiesChip<Fp>, _>::new(cs, config)?;
+            let config = layouter.config().clone();
+
+            layouter.assign_region(
+                || "is zero",
+                |mut region| {
+                    config.is_zero.q_enable().enable(&mut region, 0)?;
+                    for (column, value) in
+                        config.advices.iter().zip([self.x, self.inverse, self.out])
+                    {
+                        region.assign_advice(|| "value", *column, 0, || Ok(value))?;
+                    }
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    #[test]
+    fn is_zero_soundness() {
+        let run = |x: u64, inverse: Fp, out: u64| {
+            let circuit = IsZeroCircuit {
+                x: Fp::from_u64(x),
+                inverse,
+                out: Fp::from_u64(out),
+            };
+            MockProver::run(3, &circuit, vec![]).unwrap().verify()
+        };
+        let inv_3 = Fp::from_u64(3).invert().unwrap();
+
+        assert_eq!(run(0, Fp::zero(), 1), Ok(()));
+        assert_eq!(run(3, inv_3, 0), Ok(()));
+
+        // A nonzero value cannot be claimed to be zero with a wrong inverse.
+        assert!(run(3, Fp::zero(), 1).is_err());
+        // Zero cannot be claimed to be nonzero, whatever the inverse.
+        assert!(run(0, inv_3, 0).is_err());
+        assert!(run(0, Fp::zero(), 0).is_err());
+    }
+}
@@ -0,0 +1,878 @@
+//! Gadgets and chips for verifying proofs in a circuit over the base field of the
+//! commitment curve, and for aggregating proofs of different circuits into one.
+//!
+//! The [`verify_proof`] gadget performs the checks of
+//! [`plonk::verify_proof`](crate::plonk::verify_proof) for a proof created with a
+//! [`PoseidonWrite`](crate::transcript::PoseidonWrite) transcript, recomputing the
+//! transcript with the [`Transcript`] gadget. The scalars of the proof are not native
+//! to the circuit, so they are elements of the scalar field of the curve, represented
+//! by the foreign-field relations of
+//! [`ForeignFieldInstructions`](super::foreign_field::ForeignFieldInstructions), and
+//! each challenge is mapped to its endoscaled scalar in the circuit. The opening of the
+//! proof is reduced as [`OpeningProof`](super::ipa::OpeningProof) does, and its final
+//! check is a multi-scalar multiplication in the circuit, except for the check that the
+//! purported $G$ was computed from the challenges of the opening, which is linear in
+//! $n$ and returned as an [`Accumulator`].
+//!
+//! The [`accumulate`] gadget verifies a proof of
+//! [`create_accumulation_proof`](crate::poly::commitment::create_accumulation_proof)
+//! for several accumulators, such as those of proofs of different circuits over the
+//! same parameters, and returns the single accumulator that it defers. The
+//! [`AggregationCircuit`] uses both to replace the proofs of any number of circuits by
+//! one proof and one accumulator.
+//!
+//! The [`RecursionCircuit`] composes proofs over a cycle of curves: a step of each side
+//! verifies the last proof of the other side and a proof of an application circuit, and
+//! folds their accumulators, so that one proof of the last step and the accumulators of
+//! both sides check every step. The keys of both sides are generated with [`keygen`]
+//! and a proof of the last step is checked with [`verify_recursion`].
+
+use std::convert::TryInto;
+use std::fmt;
+
+use ff::Field;
+
+use super::{
+    ecc::Point,
+    foreign_field::ForeignFieldInstructions,
+    msm::{msm, MsmInstructions, Scalar},
+    transcript::{Transcript, TranscriptInstructions},
+};
+use crate::{
+    arithmetic::{CurveAffine, FieldExt},
+    circuit::Layouter,
+    plonk::Error,
+    poly::{
+        commitment::{self, Params},
+        multiopen,
+    },
+    transcript::{read_point, read_scalar, Challenge},
+};
+
+mod chip;
+pub use chip::{VerifierChallenge, VerifierChip, VerifierConfig};
+
+mod circuit;
+pub use circuit::{AggregationCircuit, InnerProof};
+
+mod plonk;
+pub use plonk::verify_proof;
+
+mod recursion;
+pub use recursion::{
+    keygen, verify_recursion, Previous, RecursionCircuit, RecursionState, StepProof,
+};
+
+/// The set of circuit instructions required to use the [`verify_proof`] and
+/// [`accumulate`] gadgets.
+pub trait VerifierInstructions<C: CurveAffine>:
+    TranscriptInstructions<C> + MsmInstructions<C> + ForeignFieldInstructions
+{
+    /// Returns `value`, constrained to equal it.
+    fn constant_point(layouter: &mut impl Layouter<Self>, value: C) -> Result<Self::Point, Error>;
+
+    /// Returns the words that the host transcripts absorb for the scalar `value`.
+    fn constant_words(
+        layouter: &mut impl Layouter<Self>,
+        value: C::Scalar,
+    ) -> Result<[Self::Word; 2], Error>;
+
+    /// Witnesses a scalar, and returns it with the words that the host transcripts
+    /// absorb for it.
+    ///
+    /// The element is constrained to be less than the modulus of the scalar field, so
+    /// that the words are the canonical encoding of the scalar.
+    fn witness_scalar(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<C::Scalar>,
+    ) -> Result<(Self::Element, [Self::Word; 2]), Error>;
+
+    /// Returns the words that the host transcripts absorb for the scalar given by the
+    /// 128 bits of `challenge`.
+    fn challenge_words(
+        layouter: &mut impl Layouter<Self>,
+        challenge: &Self::Challenge,
+    ) -> Result<[Self::Word; 2], Error>;
+
+    /// Returns the scalar given by the 128 bits of `challenge`, whose encoding is the
+    /// words of [`challenge_words`](Self::challenge_words).
+    fn challenge_element(
+        layouter: &mut impl Layouter<Self>,
+        challenge: &Self::Challenge,
+    ) -> Result<Self::Element, Error>;
+
+    /// Returns the endoscaled scalar of `challenge`, as computed by
+    /// [`ChallengeScalar`](crate::transcript::ChallengeScalar).
+    fn challenge_scalar(
+        layouter: &mut impl Layouter<Self>,
+        challenge: &Self::Challenge,
+    ) -> Result<Self::Element, Error>;
+
+    /// The value of `challenge`, if known.
+    fn challenge_value(challenge: &Self::Challenge) -> Option<Challenge>;
+
+    /// Returns `[u] base`, where `u` is the endoscaled scalar of `challenge`.
+    fn mul_challenge(
+        layouter: &mut impl Layouter<Self>,
+        base: &Self::Point,
+        challenge: &Self::Challenge,
+    ) -> Result<Self::Point, Error>;
+
+    /// Returns `[u^{-1}] point`, where `u` is the endoscaled scalar of `challenge`.
+    ///
+    /// Returns an error if `point` is the identity.
+    fn div_challenge(
+        layouter: &mut impl Layouter<Self>,
+        point: &Self::Point,
+        challenge: &Self::Challenge,
+    ) -> Result<Self::Point, Error>;
+
+    /// Returns the element congruent to $\sum_i k_i x_i y_i + \sum_j k_j l_j + c$ for
+    /// the given `products`, `linear` terms and `constant`.
+    ///
+    /// There may be at most two products and two linear terms, with coefficients of
+    /// at most 3 in absolute value.
+    fn combine(
+        layouter: &mut impl Layouter<Self>,
+        products: &[(i64, &Self::Element, &Self::Element)],
+        linear: &[(i64, &Self::Element)],
+        constant: C::Scalar,
+    ) -> Result<Self::Element, Error>;
+
+    /// Returns the 256 little-endian bits of `element`, which can be used as a scalar
+    /// of a multi-scalar multiplication.
+    fn scalar_bits(
+        layouter: &mut impl Layouter<Self>,
+        element: &Self::Element,
+    ) -> Result<Vec<Self::Bit>, Error>;
+
+    /// Exposes the coordinates of `point` as the next two public inputs.
+    fn expose_point(layouter: &mut impl Layouter<Self>, point: &Self::Point) -> Result<(), Error>;
+
+    /// Exposes `challenge` as the next public input.
+    fn expose_challenge(
+        layouter: &mut impl Layouter<Self>,
+        challenge: &Self::Challenge,
+    ) -> Result<(), Error>;
+}
+
+/// Returns the little-endian 64-bit words of `value`.
+fn scalar_words<F: FieldExt>(value: &F) -> [u64; 4] {
+    let bytes = value.to_bytes();
+    let mut words = [0; 4];
+    for (word, chunk) in words.iter_mut().zip(bytes.chunks(8)) {
+        *word = u64::from_le_bytes(chunk.try_into().unwrap());
+    }
+    words
+}
+
+/// Witnesses a 128-bit challenge.
+fn witness_challenge<C: CurveAffine, VerifierChip: VerifierInstructions<C>>(
+    layouter: &mut impl Layouter<VerifierChip>,
+    value: Option<Challenge>,
+) -> Result<VerifierChip::Challenge, Error> {
+    let words = <VerifierChip as TranscriptInstructions<C>>::scalar_words(
+        layouter,
+        value.map(|value| C::Scalar::from_u128(value.0)),
+    )?;
+    VerifierChip::challenge(layouter, &words[0])
+}
+
+/// A scalar of a proof being verified, which is either known when the circuit is
+/// configured or assigned.
+///
+/// Operations on constants are computed when the circuit is synthesized, and small
+/// constants are folded into the coefficients of the relations of the chip.
+enum Term<C: CurveAffine, VerifierChip: VerifierInstructions<C>> {
+    Constant(C::Scalar),
+    Element(VerifierChip::Element),
+}
+
+impl<C: CurveAffine, VerifierChip: VerifierInstructions<C>> Clone for Term<C, VerifierChip> {
+    fn clone(&self) -> Self {
+        match self {
+            Term::Constant(value) => Term::Constant(*value),
+            Term::Element(element) => Term::Element(element.clone()),
+        }
+    }
+}
+
+impl<C: CurveAffine, VerifierChip: VerifierInstructions<C>> fmt::Debug for Term<C, VerifierChip> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Term::Constant(value) => f.debug_tuple("Constant").field(value).finish(),
+            Term::Element(element) => f.debug_tuple("Element").field(element).finish(),
+        }
+    }
+}
+
+/// Returns `k` if `value` is a coefficient of at most 3 in absolute value.
+fn small<F: FieldExt>(value: F) -> Option<i64> {
+    (1..=3).find_map(|k| {
+        if value == F::from_u64(k as u64) {
+            Some(k)
+        } else if value == -F::from_u64(k as u64) {
+            Some(-k)
+        } else {
+            None
+        }
+    })
+}
+
+/// Returns $\sum_i k_i x_i y_i + \sum_j k_j l_j$ for the given `products` and `linear`
+/// terms, with as few relations as the chip needs.
+#[allow(clippy::type_complexity)]
+fn combine<C: CurveAffine, VerifierChip: VerifierInstructions<C>>(
+    layouter: &mut impl Layouter<VerifierChip>,
+    products: &[(i64, &Term<C, VerifierChip>, &Term<C, VerifierChip>)],
+    linear: &[(i64, &Term<C, VerifierChip>)],
+) -> Result<Term<C, VerifierChip>, Error> {
+    let coefficient = |k: i64| {
+        let abs = C::Scalar::from_u64(k.unsigned_abs());
+        if k < 0 {
+            -abs
+        } else {
+            abs
+        }
+    };
+
+    let mut constant = C::Scalar::zero();
+    let mut element_products = vec![];
+    let mut element_linear = vec![];
+    for (k, x, y) in products {
+        match (x, y) {
+            (Term::Constant(x), Term::Constant(y)) => constant += coefficient(*k) * x * y,
+            (Term::Constant(c), Term::Element(e)) | (Term::Element(e), Term::Constant(c)) => {
+                let scaled = coefficient(*k) * c;
+                if scaled == C::Scalar::zero() {
+                } else if let Some(k) = small(scaled) {
+                    element_linear.push((k, e.clone()));
+                } else {
+                    let c = VerifierChip::constant(layouter, scalar_words(c))?;
+                    element_products.push((*k, c, e.clone()));
+                }
+            }
+            (Term::Element(x), Term::Element(y)) => {
+                element_products.push((*k, x.clone(), y.clone()))
+            }
+        }
+    }
+    for (k, l) in linear {
+        match l {
+            Term::Constant(c) => constant += coefficient(*k) * c,
+            Term::Element(e) => element_linear.push((*k, e.clone())),
+        }
+    }
+
+    if element_products.is_empty() {
+        match element_linear.as_slice() {
+            [] => return Ok(Term::Constant(constant)),
+            [(1, e)] if constant == C::Scalar::zero() => return Ok(Term::Element(e.clone())),
+            _ => (),
+        }
+    }
+
+    // Each relation takes up to two products and two linear terms, one of which
+    // carries the result of the previous relation.
+    let mut products = element_products.into_iter();
+    let mut linear = element_linear.into_iter().peekable();
+    let mut acc: Option<VerifierChip::Element> = None;
+    loop {
+        let step_products: Vec<_> = products.by_ref().take(2).collect();
+        let mut step_linear: Vec<_> = acc.take().map(|acc| (1, acc)).into_iter().collect();
+        while step_linear.len() < 2 {
+            match linear.next() {
+                Some(term) => step_linear.push(term),
+                None => break,
+            }
+        }
+        let last = products.len() == 0 && linear.peek().is_none();
+
+        let result = VerifierChip::combine(
+            layouter,
+            &step_products
+                .iter()
+                .map(|(k, x, y)| (*k, x, y))
+                .collect::<Vec<_>>(),
+            &step_linear.iter().map(|(k, l)| (*k, l)).collect::<Vec<_>>(),
+            if last { constant } else { C::Scalar::zero() },
+        )?;
+        if last {
+            return Ok(Term::Element(result));
+        }
+        acc = Some(result);
+    }
+}
+
+impl<C: CurveAffine, VerifierChip: VerifierInstructions<C>> Term<C, VerifierChip> {
+    fn one() -> Self {
+        Term::Constant(C::Scalar::one())
+    }
+
+    fn add(&self, layouter: &mut impl Layouter<VerifierChip>, other: &Self) -> Result<Self, Error> {
+        combine(layouter, &[], &[(1, self), (1, other)])
+    }
+
+    fn sub(&self, layouter: &mut impl Layouter<VerifierChip>, other: &Self) -> Result<Self, Error> {
+        combine(layouter, &[], &[(1, self), (-1, other)])
+    }
+
+    fn mul(&self, layouter: &mut impl Layouter<VerifierChip>, other: &Self) -> Result<Self, Error> {
+        combine(layouter, &[(1, self, other)], &[])
+    }
+
+    /// Returns `self * other + addend`.
+    fn mul_add(
+        &self,
+        layouter: &mut impl Layouter<VerifierChip>,
+        other: &Self,
+        addend: &Self,
+    ) -> Result<Self, Error> {
+        combine(layouter, &[(1, self, other)], &[(1, addend)])
+    }
+
+    /// Returns the inverse of `self`, which must be non-zero.
+    fn invert(&self, layouter: &mut impl Layouter<VerifierChip>) -> Result<Self, Error> {
+        match self {
+            Term::Constant(value) => Option::from(value.invert())
+                .map(Term::Constant)
+                .ok_or(Error::SynthesisError),
+            Term::Element(element) => VerifierChip::invert(layouter, element).map(Term::Element),
+        }
+    }
+
+    /// Returns `self` as an assigned element.
+    fn element(
+        &self,
+        layouter: &mut impl Layouter<VerifierChip>,
+    ) -> Result<VerifierChip::Element, Error> {
+        match self {
+            Term::Constant(value) => VerifierChip::constant(layouter, scalar_words(value)),
+            Term::Element(element) => Ok(element.clone()),
+        }
+    }
+
+    /// Constrains `self` and `other` to be the same scalar.
+    fn constrain_equal(
+        &self,
+        layouter: &mut impl Layouter<VerifierChip>,
+        other: &Self,
+    ) -> Result<(), Error> {
+        match (self, other) {
+            (Term::Constant(a), Term::Constant(b)) if a == b => Ok(()),
+            (Term::Constant(_), Term::Constant(_)) => Err(Error::SynthesisError),
+            _ => {
+                let a = self.element(layouter)?;
+                let b = other.element(layouter)?;
+                <VerifierChip as ForeignFieldInstructions>::constrain_equal(layouter, &a, &b)
+            }
+        }
+    }
+}
+
+/// Reads the messages of a proof into a [`Transcript`].
+///
+/// The proof is the output of a [`PoseidonWrite`](crate::transcript::PoseidonWrite)
+/// transcript, and only the canonical encoding of each message is accepted, as a
+/// [`PoseidonRead`](crate::transcript::PoseidonRead) created with `init_strict` does.
+///
+/// The final checks of the proof are only enforced if `enforce` is unset or one, so
+/// that a circuit can read a proof that it does not check, such as in the base case of
+/// a [`RecursionCircuit`].
+#[derive(Debug)]
+struct ProofReader<'a, C: CurveAffine, VerifierChip: VerifierInstructions<C>> {
+    transcript: Transcript<C, VerifierChip>,
+    proof: Option<&'a [u8]>,
+    enforce: Option<Term<C, VerifierChip>>,
+}
+
+impl<'a, C: CurveAffine, VerifierChip: VerifierInstructions<C>> ProofReader<'a, C, VerifierChip> {
+    fn new(layouter: impl Layouter<VerifierChip>, proof: Option<&'a [u8]>) -> Result<Self, Error> {
+        Ok(ProofReader {
+            transcript: Transcript::new(layouter)?,
+            proof,
+            enforce: None,
+        })
+    }
+
+    /// Constrains `a` and `b` to be equal, if the checks of the proof are enforced.
+    fn constrain_equal(
+        &self,
+        layouter: &mut impl Layouter<VerifierChip>,
+        a: &Term<C, VerifierChip>,
+        b: &Term<C, VerifierChip>,
+    ) -> Result<(), Error> {
+        match &self.enforce {
+            None => a.constrain_equal(layouter, b),
+            Some(enforce) => {
+                // e (a - b) = 0
+                combine(layouter, &[(1, enforce, a), (-1, enforce, b)], &[])?
+                    .constrain_equal(layouter, &Term::Constant(C::Scalar::zero()))
+            }
+        }
+    }
+
+    fn read_point(
+        &mut self,
+        layouter: &mut impl Layouter<VerifierChip>,
+    ) -> Result<Point<C, VerifierChip>, Error> {
+        let value = self
+            .proof
+            .as_mut()
+            .map(|proof| read_point::<C, _>(proof, true))
+            .transpose()
+            .map_err(|_| Error::TranscriptError)?;
+        let point = Point::new(layouter.namespace(|| "read point"), value)?;
+        self.common_point(layouter, &point)?;
+        Ok(point)
+    }
+
+    fn read_scalar(
+        &mut self,
+        layouter: &mut impl Layouter<VerifierChip>,
+    ) -> Result<Term<C, VerifierChip>, Error> {
+        let value = self
+            .proof
+            .as_mut()
+            .map(|proof| read_scalar::<C, _>(proof, true))
+            .transpose()
+            .map_err(|_| Error::TranscriptError)?;
+        let (element, words) = VerifierChip::witness_scalar(layouter, value)?;
+        self.transcript
+            .absorb_words(layouter.namespace(|| "absorb scalar"), &words)?;
+        Ok(Term::Element(element))
+    }
+
+    fn read_points(
+        &mut self,
+        layouter: &mut impl Layouter<VerifierChip>,
+        n: usize,
+    ) -> Result<Vec<Point<C, VerifierChip>>, Error> {
+        (0..n).map(|_| self.read_point(layouter)).collect()
+    }
+
+    fn read_scalars(
+        &mut self,
+        layouter: &mut impl Layouter<VerifierChip>,
+        n: usize,
+    ) -> Result<Vec<Term<C, VerifierChip>>, Error> {
+        (0..n).map(|_| self.read_scalar(layouter)).collect()
+    }
+
+    fn common_point(
+        &mut self,
+        layouter: &mut impl Layouter<VerifierChip>,
+        point: &Point<C, VerifierChip>,
+    ) -> Result<(), Error> {
+        self.transcript
+            .common_point(layouter.namespace(|| "absorb point"), point)
+    }
+
+    fn squeeze_challenge(
+        &mut self,
+        layouter: &mut impl Layouter<VerifierChip>,
+    ) -> Result<VerifierChip::Challenge, Error> {
+        self.transcript
+            .squeeze_challenge(layouter.namespace(|| "squeeze challenge"))
+    }
+
+    /// Squeezes a challenge, and returns it with its endoscaled scalar.
+    fn squeeze_scalar(
+        &mut self,
+        layouter: &mut impl Layouter<VerifierChip>,
+    ) -> Result<(VerifierChip::Challenge, Term<C, VerifierChip>), Error> {
+        let challenge = self.squeeze_challenge(layouter)?;
+        let scalar = VerifierChip::challenge_scalar(layouter, &challenge)?;
+        Ok((challenge, Term::Element(scalar)))
+    }
+
+    /// Returns an error if the proof has messages that were not read.
+    fn finalize(self) -> Result<(), Error> {
+        match self.proof {
+            Some(proof) if !proof.is_empty() => Err(Error::TranscriptError),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A deferred check that the point $G$ is the commitment, with blinding factor $1$,
+/// to $g(X) = \prod_i (1 + u_i X^{2^i})$ for the challenges $u_i$.
+///
+/// This is the in-circuit counterpart of [`commitment::Accumulator`].
+#[derive(Debug)]
+pub struct Accumulator<C: CurveAffine, VerifierChip: VerifierInstructions<C>> {
+    g: Point<C, VerifierChip>,
+    challenges: Vec<VerifierChip::Challenge>,
+    value: Option<commitment::Accumulator<C>>,
+}
+
+impl<C: CurveAffine, VerifierChip: VerifierInstructions<C>> Accumulator<C, VerifierChip> {
+    /// Witnesses the accumulator with the given challenges, whose $G$ is computed
+    /// from them.
+    fn from_challenges(
+        mut layouter: impl Layouter<VerifierChip>,
+        params: &Params<C>,
+        challenges: Vec<VerifierChip::Challenge>,
+    ) -> Result<Self, Error> {
+        let value = challenges
+            .iter()
+            .map(VerifierChip::challenge_value)
+            .collect::<Option<Vec<_>>>()
+            .map(|challenges| commitment::Accumulator::from_challenges(params, challenges));
+        let g = Point::new(
+            layouter.namespace(|| "G"),
+            value.as_ref().map(|value| value.g),
+        )?;
+        Ok(Accumulator {
+            g,
+            challenges,
+            value,
+        })
+    }
+
+    /// Witnesses `value`, an accumulator for `params`, without constraining its $G$ to
+    /// be computed from its challenges.
+    fn witness(
+        mut layouter: impl Layouter<VerifierChip>,
+        params: &Params<C>,
+        value: Option<&commitment::Accumulator<C>>,
+    ) -> Result<Self, Error> {
+        let g = Point::new(layouter.namespace(|| "G"), value.map(|value| value.g))?;
+        let challenges = (0..params.k as usize)
+            .map(|i| {
+                let challenge = value.map(|value| value.challenges_packed[i]);
+                witness_challenge(&mut layouter, challenge)
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        Ok(Accumulator {
+            g,
+            challenges,
+            value: value.cloned(),
+        })
+    }
+
+    /// The purported $G$.
+    pub fn g(&self) -> &Point<C, VerifierChip> {
+        &self.g
+    }
+
+    /// The challenges $u_i$.
+    pub fn challenges(&self) -> &[VerifierChip::Challenge] {
+        &self.challenges
+    }
+
+    /// The value of this accumulator, if known.
+    pub fn value(&self) -> Option<&commitment::Accumulator<C>> {
+        self.value.as_ref()
+    }
+
+    /// Exposes this accumulator as public inputs, in the order given by
+    /// [`public_inputs`].
+    pub fn expose(&self, mut layouter: impl Layouter<VerifierChip>) -> Result<(), Error> {
+        VerifierChip::expose_point(&mut layouter, self.g.inner())?;
+        for challenge in &self.challenges {
+            VerifierChip::expose_challenge(&mut layouter, challenge)?;
+        }
+        Ok(())
+    }
+}
+
+impl<C: CurveAffine, VerifierChip: VerifierInstructions<C>> Clone for Accumulator<C, VerifierChip> {
+    fn clone(&self) -> Self {
+        Accumulator {
+            g: self.g.clone(),
+            challenges: self.challenges.clone(),
+            value: self.value.clone(),
+        }
+    }
+}
+
+/// Returns the public inputs exposed by [`Accumulator::expose`] for `accumulator`.
+///
+/// These are the coordinates of $G$, followed by the challenges $u_i$.
+pub fn public_inputs<C: CurveAffine>(accumulator: &commitment::Accumulator<C>) -> Vec<C::Base> {
+    let (x, y) = Option::from(accumulator.g.get_xy()).unwrap_or((C::Base::zero(), C::Base::zero()));
+    let mut inputs = vec![x, y];
+    inputs.extend(
+        accumulator
+            .challenges_packed
+            .iter()
+            .map(|challenge| C::Base::from_u128(challenge.0)),
+    );
+    inputs
+}
+
+/// Returns $\prod_i (1 + u_i x^{2^i})$, as
+/// [`compute_b`](crate::poly::commitment) does.
+fn compute_b<C: CurveAffine, VerifierChip: VerifierInstructions<C>>(
+    layouter: &mut impl Layouter<VerifierChip>,
+    x: &Term<C, VerifierChip>,
+    challenges: &[Term<C, VerifierChip>],
+) -> Result<Term<C, VerifierChip>, Error> {
+    let mut tmp = Term::one();
+    let mut cur = x.clone();
+    for (i, challenge) in challenges.iter().rev().enumerate() {
+        let term = challenge.mul(layouter, &cur)?;
+        tmp = tmp.mul_add(layouter, &term, &tmp)?;
+        if i + 1 < challenges.len() {
+            cur = cur.mul(layouter, &cur)?;
+        }
+    }
+    Ok(tmp)
+}
+
+/// Verifies the inner product argument in `reader` that `commitment` opens to `v` at
+/// `x`, and returns the accumulator whose check it defers.
+///
+/// The points of the proof reduce `commitment` to
+/// $P' = P + \[\iota\] S + \sum_i (\[u_i^{-1}\] L_i + \[u_i\] R_i)$, which is
+/// constrained to equal $\[v\] G_0 + \[a b z\] U + \[\xi - a\] H + \[a\] G$ for the
+/// purported $G$, with a multi-scalar multiplication.
+fn verify_ipa<C: CurveAffine, VerifierChip: VerifierInstructions<C>>(
+    layouter: &mut impl Layouter<VerifierChip>,
+    params: &Params<C>,
+    reader: &mut ProofReader<'_, C, VerifierChip>,
+    commitment: &Point<C, VerifierChip>,
+    x: &Term<C, VerifierChip>,
+    v: &Term<C, VerifierChip>,
+) -> Result<Accumulator<C, VerifierChip>, Error> {
+    let s_poly_commitment = reader.read_point(layouter)?;
+    let iota = reader.squeeze_challenge(layouter)?;
+    let (_, z) = reader.squeeze_scalar(layouter)?;
+
+    let s = VerifierChip::mul_challenge(layouter, s_poly_commitment.inner(), &iota)
+        .map(Point::from_inner)?;
+    let mut reduced = commitment.add(layouter.namespace(|| "P + [iota] S"), &s)?;
+
+    let mut challenges = Vec::with_capacity(params.k as usize);
+    let mut scalars = Vec::with_capacity(params.k as usize);
+    for i in 0..params.k {
+        let mut layouter = layouter.namespace(|| format!("round {}", i));
+        let l = reader.read_point(&mut layouter)?;
+        let r = reader.read_point(&mut layouter)?;
+        let (challenge, scalar) = reader.squeeze_scalar(&mut layouter)?;
+
+        let l = VerifierChip::div_challenge(&mut layouter, l.inner(), &challenge)
+            .map(Point::from_inner)?;
+        let r = VerifierChip::mul_challenge(&mut layouter, r.inner(), &challenge)
+            .map(Point::from_inner)?;
+        reduced = reduced.add(layouter.namespace(|| "L"), &l)?;
+        reduced = reduced.add(layouter.namespace(|| "R"), &r)?;
+
+        challenges.push(challenge);
+        scalars.push(scalar);
+    }
+
+    let a = reader.read_scalar(layouter)?;
+    let xi = reader.read_scalar(layouter)?;
+
+    let b = compute_b(layouter, x, &scalars)?;
+    let abz = a.mul(layouter, &b)?.mul(layouter, &z)?;
+    let xi_a = xi.sub(layouter, &a)?;
+
+    let accumulator =
+        Accumulator::from_challenges(layouter.namespace(|| "accumulator"), params, challenges)?;
+
+    let mut terms = vec![];
+    for (scalar, base) in [(v, params.g[0]), (&abz, params.u), (&xi_a, params.h)] {
+        let base = VerifierChip::constant_point(layouter, base).map(Point::from_inner)?;
+        terms.push((scalar.clone(), base));
+    }
+    terms.push((a, accumulator.g.clone()));
+
+    // If the check is not always enforced, both sides are scaled by the flag, which
+    // makes them the identity when it is zero.
+    if let Some(enforce) = &reader.enforce {
+        for (scalar, _) in terms.iter_mut() {
+            *scalar = scalar.mul(layouter, enforce)?;
+        }
+        let enforce = enforce.element(layouter)?;
+        let bits = VerifierChip::scalar_bits(layouter, &enforce)?;
+        reduced = msm(
+            layouter.namespace(|| "[e] P'"),
+            &[(&Scalar::from_bits(bits), &reduced)],
+        )?;
+    }
+
+    let terms = terms
+        .iter()
+        .map(|(scalar, base)| {
+            let scalar = scalar.element(layouter)?;
+            VerifierChip::scalar_bits(layouter, &scalar).map(|bits| (Scalar::from_bits(bits), base))
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+    let expected = msm(
+        layouter.namespace(|| "msm"),
+        &terms
+            .iter()
+            .map(|(scalar, base)| (scalar, *base))
+            .collect::<Vec<_>>(),
+    )?;
+    reduced.constrain_equal(layouter.namespace(|| "P'"), &expected)?;
+
+    Ok(accumulator)
+}
+
+/// A query of a multi-opening: the rotation of its point, the index of its commitment
+/// and its evaluation.
+type Query<C, VerifierChip> = (i32, usize, Term<C, VerifierChip>);
+
+/// Verifies the multi-opening in `reader` of `queries` of `commitments`, where the
+/// point of a query with rotation $r$ is $\omega^r x$ for `omega`, and returns the
+/// accumulator whose check it defers, as
+/// [`multiopen::verify_proof`](crate::poly::multiopen::verify_proof) does.
+fn verify_opening<C: CurveAffine, VerifierChip: VerifierInstructions<C>>(
+    layouter: &mut impl Layouter<VerifierChip>,
+    params: &Params<C>,
+    reader: &mut ProofReader<'_, C, VerifierChip>,
+    x: &Term<C, VerifierChip>,
+    omega: impl Fn(i32) -> C::Scalar,
+    commitments: &[Point<C, VerifierChip>],
+    queries: &[Query<C, VerifierChip>],
+) -> Result<Accumulator<C, VerifierChip>, Error> {
+    let (x_1, x_1_scalar) = reader.squeeze_scalar(layouter)?;
+    let (_, x_2) = reader.squeeze_scalar(layouter)?;
+
+    let (commitment_map, point_sets) = multiopen::opening_sets(
+        &queries
+            .iter()
+            .map(|(rotation, commitment, _)| (*rotation, *commitment))
+            .collect::<Vec<_>>(),
+    );
+
+    // Compress the commitments and evaluations of each point set with x_1.
+    let mut q_commitments: Vec<Option<Point<C, VerifierChip>>> = vec![None; point_sets.len()];
+    let mut q_eval_sets: Vec<Vec<Option<Term<C, VerifierChip>>>> = point_sets
+        .iter()
+        .map(|points| vec![None; points.len()])
+        .collect();
+    for (commitment, set_index, evals) in commitment_map {
+        let commitment = &commitments[commitment];
+        q_commitments[set_index] = Some(match q_commitments[set_index].take() {
+            None => commitment.clone(),
+            Some(q) => {
+                let q = VerifierChip::mul_challenge(layouter, q.inner(), &x_1)
+                    .map(Point::from_inner)?;
+                q.add(layouter.namespace(|| "q"), commitment)?
+            }
+        });
+        for (set_eval, eval) in q_eval_sets[set_index].iter_mut().zip(evals) {
+            let eval = &queries[eval].2;
+            *set_eval = Some(match set_eval.take() {
+                None => eval.clone(),
+                Some(set_eval) => set_eval.mul_add(layouter, &x_1_scalar, eval)?,
+            });
+        }
+    }
+
+    let f_commitment = reader.read_point(layouter)?;
+    let (_, x_3) = reader.squeeze_scalar(layouter)?;
+    let q_evals = reader.read_scalars(layouter, point_sets.len())?;
+
+    // The points of each set, and their differences from x_3.
+    let mut msm_eval: Option<Term<C, VerifierChip>> = None;
+    for ((rotations, evals), q_eval) in point_sets.iter().zip(q_eval_sets).zip(q_evals.iter()) {
+        let evals: Vec<_> = evals.into_iter().map(Option::unwrap).collect();
+        let points = rotations
+            .iter()
+            .map(|rotation| x.mul(layouter, &Term::Constant(omega(*rotation))))
+            .collect::<Result<Vec<_>, Error>>()?;
+        let differences = points
+            .iter()
+            .map(|point| x_3.sub(layouter, point))
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        // r(x_3) for the polynomial r interpolating the evaluations at the points.
+        let mut r_eval = Term::Constant(C::Scalar::zero());
+        for (j, eval) in evals.iter().enumerate() {
+            let mut basis = eval.clone();
+            for (m, point) in points.iter().enumerate() {
+                if m != j {
+                    let denominator = points[j].sub(layouter, point)?.invert(layouter)?;
+                    basis = basis
+                        .mul(layouter, &differences[m])?
+                        .mul(layouter, &denominator)?;
+                }
+            }
+            r_eval = r_eval.add(layouter, &basis)?;
+        }
+
+        let mut eval = q_eval.sub(layouter, &r_eval)?;
+        for difference in &differences {
+            let inverse = difference.invert(layouter)?;
+            eval = eval.mul(layouter, &inverse)?;
+        }
+        msm_eval = Some(match msm_eval {
+            None => eval,
+            Some(msm_eval) => msm_eval.mul_add(layouter, &x_2, &eval)?,
+        });
+    }
+
+    // Collapse the openings at x_3 with x_4.
+    let (x_4, x_4_scalar) = reader.squeeze_scalar(layouter)?;
+    let mut commitment = f_commitment;
+    let mut v = msm_eval.unwrap();
+    for (q_commitment, q_eval) in q_commitments.into_iter().zip(q_evals.iter()) {
+        let scaled = VerifierChip::mul_challenge(layouter, commitment.inner(), &x_4)
+            .map(Point::from_inner)?;
+        commitment = scaled.add(layouter.namespace(|| "P"), &q_commitment.unwrap())?;
+        v = v.mul_add(layouter, &x_4_scalar, q_eval)?;
+    }
+
+    verify_ipa(layouter, params, reader, &commitment, &x_3, &v)
+}
+
+/// Verifies `proof`, a proof of
+/// [`create_accumulation_proof`](crate::poly::commitment::create_accumulation_proof)
+/// for `accumulators` created with a
+/// [`PoseidonWrite`](crate::transcript::PoseidonWrite) transcript, and returns the
+/// single accumulator that it defers.
+///
+/// `proof` may only be `None` if the circuit is synthesized without witnesses.
+pub fn accumulate<C: CurveAffine, VerifierChip: VerifierInstructions<C>>(
+    mut layouter: impl Layouter<VerifierChip>,
+    params: &Params<C>,
+    accumulators: &[Accumulator<C, VerifierChip>],
+    proof: Option<&[u8]>,
+) -> Result<Accumulator<C, VerifierChip>, Error> {
+    let layouter = &mut layouter;
+    let mut reader = ProofReader::new(layouter.namespace(|| "transcript"), proof)?;
+
+    for accumulator in accumulators {
+        reader.common_point(layouter, &accumulator.g)?;
+        for challenge in &accumulator.challenges {
+            let words = VerifierChip::challenge_words(layouter, challenge)?;
+            reader
+                .transcript
+                .absorb_words(layouter.namespace(|| "absorb challenge"), &words)?;
+        }
+    }
+    let (_, x) = reader.squeeze_scalar(layouter)?;
+
+    let mut queries = Vec::with_capacity(accumulators.len());
+    for (i, accumulator) in accumulators.iter().enumerate() {
+        let challenges = accumulator
+            .challenges
+            .iter()
+            .map(|challenge| VerifierChip::challenge_scalar(layouter, challenge).map(Term::Element))
+            .collect::<Result<Vec<_>, Error>>()?;
+        queries.push((0, i, compute_b(layouter, &x, &challenges)?));
+    }
+
+    let commitments: Vec<_> = accumulators
+        .iter()
+        .map(|accumulator| accumulator.g.clone())
+        .collect();
+    let accumulator = verify_opening(
+        layouter,
+        params,
+        &mut reader,
+        &x,
+        |_| C::Scalar::one(),
+        &commitments,
+        &queries,
+    )?;
+    reader.finalize()?;
+
+    Ok(accumulator)
+}
@@ -0,0 +1,814 @@
+//! A chip implementing [`VerifierInstructions`] with the transcript, IPA, MSM and
+//! foreign-field chips.
+//!
+//! # Layout
+//!
+//! The chip includes a [`TranscriptConfig`], and an [`IpaConfig`] and [`MsmConfig`]
+//! over the same [`EccConfig`](crate::gadget::ecc::EccConfig), and a
+//! [`ForeignFieldConfig`] for the scalar field of the curve, whose permutation also
+//! covers the coordinate and Poseidon state columns, and the `a`, `b` and `constant`
+//! columns of the chip. Challenges are copied from the `low` column of the transcript
+//! to the IPA gates, and bits from the foreign-field decompositions to the MSM gates.
+//!
+//! A challenge is squeezed in the rows of
+//! [`TranscriptConfig::assign_challenge`], whose first 64 windows are the pairs of bits
+//! of the challenge from the least significant. The `a` and `b` columns of the same
+//! rows hold the integers $A_i$ and $B_i$ of the endoscaling of the challenge, with
+//! $A_{64} = B_{64} = 2$ and
+//! $$A_i = 2 A_{i + 1} + f_A(k_i), \quad B_i = 2 B_{i + 1} + f_B(k_i)$$
+//! for the window $k_i$, where $f_A$ and $f_B$ are the cubic polynomials that map the
+//! windows $0, 1, 2, 3$ to $(0, 1)$, $(1, 0)$, $(0, -1)$ and $(-1, 0)$, so that the
+//! scalar of the challenge is $\zeta A_0 + B_0$. The integers are positive and less
+//! than $2^{67}$, so $A_0$ and $B_0$ are copied into the low limbs of foreign-field
+//! elements whose other limbs are zero.
+//!
+//! A scalar of a proof is witnessed as an element constrained to be less than the
+//! modulus, and the words absorbed for it are constrained in the Poseidon state columns
+//! of its first row as sums of its limbs and of the byte chunks of its middle limb.
+//! Constants are assigned in the `constant` column, which is constrained to equal a
+//! fixed column.
+
+use std::convert::TryInto;
+use std::marker::PhantomData;
+
+use ff::Field;
+
+use super::VerifierInstructions;
+use crate::{
+    arithmetic::{CurveAffine, FieldExt},
+    circuit::{Cell, Chip, Layouter, Region},
+    gadget::{
+        ecc::{EccChip, EccFixedPoint, EccInstructions, EccPoint, EccX},
+        foreign_field::{
+            AssignedBit, AssignedElement, ForeignFieldChip, ForeignFieldConfig,
+            ForeignFieldInstructions, RELATION_ROWS,
+        },
+        ipa::{AssignedChallenge, IpaConfig},
+        msm::{MsmConfig, MsmInstructions},
+        poseidon::{PoseidonDuplexInstructions, PoseidonInstructions, SpongeState, StateWord},
+        transcript::{TranscriptChip, TranscriptConfig, TranscriptInstructions},
+    },
+    plonk::{Advice, Column, ConstraintSystem, Error, Expression, Fixed, Selector},
+    poly::Rotation,
+    primitives::poseidon::{P128Pow5T3, Spec, State},
+    transcript::Challenge,
+};
+
+/// The number of 2-bit windows of a challenge.
+const CHALLENGE_WINDOWS: usize = 64;
+
+/// A challenge squeezed in the circuit, with the cells of its endoscaling integers
+/// $A_0$ and $B_0$.
+#[derive(Clone, Copy, Debug)]
+pub struct VerifierChallenge {
+    challenge: AssignedChallenge,
+    a: Cell,
+    b: Cell,
+}
+
+impl VerifierChallenge {
+    /// The challenge.
+    pub fn challenge(&self) -> AssignedChallenge {
+        self.challenge
+    }
+}
+
+/// Returns the endoscaling integers $(A_i, B_i)$ of `challenge` for $i$ from $0$ to
+/// $64$.
+fn endoscaling_integers(challenge: Challenge) -> Vec<(u128, u128)> {
+    let mut integers = vec![(2, 2)];
+    for i in (0..CHALLENGE_WINDOWS).rev() {
+        let (a, b) = integers[0];
+        let (f_a, f_b): (i128, i128) = match (challenge.0 >> (2 * i)) & 3 {
+            0 => (0, 1),
+            1 => (1, 0),
+            2 => (0, -1),
+            _ => (-1, 0),
+        };
+        integers.insert(
+            0,
+            ((2 * a as i128 + f_a) as u128, (2 * b as i128 + f_b) as u128),
+        );
+    }
+    integers
+}
+
+/// Returns the little-endian 64-bit words of `value`.
+fn words(value: u128) -> [u64; 4] {
+    [value as u64, (value >> 64) as u64, 0, 0]
+}
+
+/// Configuration for a [`VerifierChip`].
+#[derive(Clone, Debug)]
+pub struct VerifierConfig<F: FieldExt> {
+    transcript: TranscriptConfig<F>,
+    ipa: IpaConfig,
+    msm: MsmConfig,
+    ff: ForeignFieldConfig,
+    a: Column<Advice>,
+    b: Column<Advice>,
+    constant: Column<Advice>,
+    fixed: Column<Fixed>,
+    s_endo: Selector,
+    s_endo_start: Selector,
+    s_constant: Selector,
+    s_words: Selector,
+}
+
+/// A chip verifying proofs over the curve `C` in a circuit over its base field.
+#[derive(Debug)]
+pub struct VerifierChip<C: CurveAffine> {
+    _marker: PhantomData<C>,
+}
+
+impl<C: CurveAffine> VerifierChip<C>
+where
+    P128Pow5T3: Spec<C::Base, 3, 2>,
+{
+    /// Configures this chip for use in a circuit.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the modulus of the base field is not between $2^{254}$ and $2^{255}$,
+    /// or the modulus of the scalar field is not between $2^{249}$ and $2^{256}$.
+    pub fn configure(meta: &mut ConstraintSystem<C::Base>) -> VerifierConfig<C::Base> {
+        let transcript = TranscriptChip::<C>::configure(meta);
+        let state = transcript.poseidon.sponge.state;
+
+        let a = meta.advice_column();
+        let b = meta.advice_column();
+        let constant = meta.advice_column();
+        let fixed = meta.fixed_column();
+
+        let mut modulus = super::scalar_words(&-C::Scalar::one());
+        modulus[0] += 1;
+        let ff = ForeignFieldChip::configure_with_permutation(
+            meta,
+            modulus,
+            &[
+                a.into(),
+                b.into(),
+                constant.into(),
+                transcript.ecc.x_p.into(),
+                transcript.ecc.y_p.into(),
+                state[0].into(),
+                state[1].into(),
+                state[2].into(),
+            ],
+        );
+        let ipa = IpaConfig::configure(meta, transcript.ecc.clone(), &[transcript.low.into()]);
+        let msm = MsmConfig::configure(
+            meta,
+            transcript.ecc.clone(),
+            &[ff.relations.values[1].into()],
+        );
+
+        let s_endo = meta.selector();
+        let s_endo_start = meta.selector();
+        let s_constant = meta.selector();
+        let s_words = meta.selector();
+
+        // A_i = 2 A_{i + 1} + f_A(k_i) and B_i = 2 B_{i + 1} + f_B(k_i), where
+        // f_A(k) = k - k (k - 1) + k (k - 1) (k - 2) / 3 and
+        // f_B(k) = 1 - k + k (k - 1) (k - 2) / 3.
+        let window = transcript.running_sum.window();
+        let third = C::Base::from_u64(3).invert().unwrap();
+        for (name, column, is_a) in [("endoscaling A", a, true), ("endoscaling B", b, false)] {
+            meta.create_gate(name, |meta| {
+                let k = meta.query_advice(window, Rotation::cur());
+                let cur = meta.query_advice(column, Rotation::cur());
+                let next = meta.query_advice(column, Rotation::next());
+                let s_endo = meta.query_selector(s_endo, Rotation::cur());
+                let one = || Expression::Constant(C::Base::one());
+                let two = Expression::Constant(C::Base::from_u64(2));
+
+                let k_k_1 = k.clone() * (k.clone() - one());
+                let cubic = k_k_1.clone() * (k.clone() - two) * third;
+                let f = if is_a {
+                    k - k_k_1 + cubic
+                } else {
+                    one() - k + cubic
+                };
+
+                s_endo * (cur - next * C::Base::from_u64(2) - f)
+            });
+        }
+        for column in [a, b] {
+            meta.create_gate("endoscaling start", |meta| {
+                let value = meta.query_advice(column, Rotation::cur());
+                let s_endo_start = meta.query_selector(s_endo_start, Rotation::cur());
+
+                s_endo_start * (value - Expression::Constant(C::Base::from_u64(2)))
+            });
+        }
+
+        meta.create_gate("constant", |meta| {
+            let constant = meta.query_advice(constant, Rotation::cur());
+            let fixed = meta.query_fixed(fixed, Rotation::cur());
+            let s_constant = meta.query_selector(s_constant, Rotation::cur());
+
+            s_constant * (constant - fixed)
+        });
+
+        // The low word is the low limb and the low 40 bits of the middle limb, and the
+        // high word is the high 48 bits of the middle limb and the high limb.
+        let values = ff.relations.values;
+        let chunks = ff.relations.chunks[1];
+        meta.create_gate("scalar low word", |meta| {
+            let word = meta.query_advice(state[0], Rotation::cur());
+            let limb = meta.query_advice(values[0], Rotation::cur());
+            let s_words = meta.query_selector(s_words, Rotation::cur());
+
+            let sum = (0..5).fold(limb, |acc, j| {
+                acc + meta.query_advice(chunks[j], Rotation::cur())
+                    * C::Base::from_u128(1 << (88 + 8 * j))
+            });
+            s_words * (word - sum)
+        });
+        meta.create_gate("scalar high word", |meta| {
+            let word = meta.query_advice(state[1], Rotation::cur());
+            let limb = meta.query_advice(values[0], Rotation::next());
+            let s_words = meta.query_selector(s_words, Rotation::cur());
+
+            let sum = (5..11).fold(limb * C::Base::from_u128(1 << 48), |acc, j| {
+                acc + meta.query_advice(chunks[j], Rotation::cur())
+                    * C::Base::from_u128(1 << (8 * (j - 5)))
+            });
+            s_words * (word - sum)
+        });
+
+        VerifierConfig {
+            transcript,
+            ipa,
+            msm,
+            ff,
+            a,
+            b,
+            constant,
+            fixed,
+            s_endo,
+            s_endo_start,
+            s_constant,
+            s_words,
+        }
+    }
+}
+
+impl<C: CurveAffine> Chip for VerifierChip<C> {
+    type Config = VerifierConfig<C::Base>;
+    type Loaded = ();
+    type Field = C::Base;
+
+    fn load(layouter: &mut impl Layouter<Self>) -> Result<(), Error> {
+        let config = layouter.config().ff.relations.clone();
+        config.load(layouter)
+    }
+}
+
+impl<C: CurveAffine> EccInstructions<C> for VerifierChip<C> {
+    type Point = EccPoint<C>;
+    type X = EccX<C::Base>;
+    type FixedPoint = EccFixedPoint<C>;
+
+    fn fixed_point(base: C) -> Self::FixedPoint {
+        EccChip::<C>::fixed_point(base)
+    }
+
+    fn witness_point(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<C>,
+    ) -> Result<Self::Point, Error> {
+        let config = layouter.config().transcript.ecc.clone();
+        config.witness_point(layouter, value)
+    }
+
+    fn extract_p(point: &Self::Point) -> Self::X {
+        point.extract_p()
+    }
+
+    fn add(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Point,
+        b: &Self::Point,
+    ) -> Result<Self::Point, Error> {
+        let config = layouter.config().transcript.ecc.clone();
+        config.add(layouter, a, b)
+    }
+
+    fn double(layouter: &mut impl Layouter<Self>, a: &Self::Point) -> Result<Self::Point, Error> {
+        let config = layouter.config().transcript.ecc.clone();
+        config.double(layouter, a)
+    }
+
+    fn mul_fixed(
+        layouter: &mut impl Layouter<Self>,
+        scalar: Option<C::Scalar>,
+        base: &Self::FixedPoint,
+    ) -> Result<Self::Point, Error> {
+        let config = layouter.config().transcript.ecc.clone();
+        config.mul_fixed(layouter, scalar, base)
+    }
+
+    fn mul_endo(
+        layouter: &mut impl Layouter<Self>,
+        base: &Self::Point,
+        bits: &[Option<bool>],
+    ) -> Result<Self::Point, Error> {
+        let config = layouter.config().transcript.ecc.clone();
+        config.mul_endo(layouter, base, bits)
+    }
+
+    fn constrain_equal(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Point,
+        b: &Self::Point,
+    ) -> Result<(), Error> {
+        let config = layouter.config().transcript.ecc.clone();
+        config.constrain_equal(layouter, a, b)
+    }
+}
+
+impl<C: CurveAffine> PoseidonInstructions<3> for VerifierChip<C> {
+    type Word = StateWord<C::Base>;
+
+    fn permute(
+        layouter: &mut impl Layouter<Self>,
+        initial_state: &State<Self::Word, 3>,
+    ) -> Result<State<Self::Word, 3>, Error> {
+        let config = layouter.config().transcript.poseidon.clone();
+        config.permute(layouter, initial_state)
+    }
+}
+
+impl<C: CurveAffine> PoseidonDuplexInstructions<3, 2> for VerifierChip<C> {
+    fn load_message(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<C::Base>,
+    ) -> Result<Self::Word, Error> {
+        let config = layouter.config().transcript.poseidon.sponge.clone();
+        config.load_message(layouter, value)
+    }
+
+    fn initial_state(
+        layouter: &mut impl Layouter<Self>,
+        initial_capacity_element: C::Base,
+    ) -> Result<State<Self::Word, 3>, Error> {
+        let config = layouter.config().transcript.poseidon.sponge.clone();
+        config.initial_state(layouter, initial_capacity_element)
+    }
+
+    fn pad_and_add(
+        layouter: &mut impl Layouter<Self>,
+        initial_state: &State<Self::Word, 3>,
+        input: &SpongeState<Self::Word, C::Base, 2>,
+    ) -> Result<State<Self::Word, 3>, Error> {
+        let config = layouter.config().transcript.poseidon.sponge.clone();
+        config.pad_and_add(layouter, initial_state, input)
+    }
+
+    fn get_output(state: &State<Self::Word, 3>) -> [Self::Word; 2] {
+        [state[0], state[1]]
+    }
+
+    fn absorb_blocks(
+        layouter: &mut impl Layouter<Self>,
+        initial_state: &State<Self::Word, 3>,
+        inputs: &[SpongeState<Self::Word, C::Base, 2>],
+    ) -> Result<State<Self::Word, 3>, Error> {
+        let config = layouter.config().transcript.poseidon.clone();
+        config.absorb_blocks(layouter, initial_state, inputs)
+    }
+}
+
+impl<F: FieldExt> VerifierConfig<F> {
+    /// Assigns `value` in the `constant` column at `offset`, constrained to equal the
+    /// same row of the fixed column.
+    fn assign_constant<Ch: Chip<Field = F>>(
+        &self,
+        region: &mut Region<'_, Ch>,
+        offset: usize,
+        value: F,
+    ) -> Result<Cell, Error> {
+        self.s_constant.enable(region, offset)?;
+        region.assign_fixed(|| "constant", self.fixed, offset, || Ok(value))?;
+        region.assign_advice(|| "constant", self.constant, offset, || Ok(value))
+    }
+
+    /// Assigns `value` in the state column `i` at `offset`, as a word constrained to
+    /// equal `cell` by the permutation of the foreign-field relations.
+    fn assign_word<Ch: Chip<Field = F>>(
+        &self,
+        region: &mut Region<'_, Ch>,
+        i: usize,
+        offset: usize,
+        value: Option<F>,
+    ) -> Result<StateWord<F>, Error> {
+        let var = region.assign_advice(
+            || format!("word {}", i),
+            self.transcript.poseidon.sponge.state[i],
+            offset,
+            || value.ok_or(Error::SynthesisError),
+        )?;
+        Ok(StateWord { var, value })
+    }
+}
+
+/// The low and high 128 bits of the encoding of `value`.
+fn scalar_limbs<F: FieldExt>(value: &F) -> [u128; 2] {
+    let bytes = value.to_bytes();
+    [
+        u128::from_le_bytes(bytes[..16].try_into().unwrap()),
+        u128::from_le_bytes(bytes[16..].try_into().unwrap()),
+    ]
+}
+
+impl<C: CurveAffine> TranscriptInstructions<C> for VerifierChip<C> {
+    type Challenge = VerifierChallenge;
+
+    fn point_words(
+        layouter: &mut impl Layouter<Self>,
+        point: &Self::Point,
+    ) -> Result<[Self::Word; 2], Error> {
+        let config = layouter.config().transcript.clone();
+        config.point_words(layouter, point)
+    }
+
+    fn scalar_words(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<C::Scalar>,
+    ) -> Result<[Self::Word; 2], Error> {
+        Self::witness_scalar(layouter, value).map(|(_, words)| words)
+    }
+
+    fn challenge(
+        layouter: &mut impl Layouter<Self>,
+        word: &Self::Word,
+    ) -> Result<Self::Challenge, Error> {
+        let config = layouter.config().clone();
+        layouter.assign_region(
+            || "challenge",
+            |mut region| {
+                let (challenge, _) = config.transcript.assign_challenge(&mut region, 0, word)?;
+                let integers = challenge.value.map(endoscaling_integers);
+
+                let mut cells = vec![];
+                for i in 0..=CHALLENGE_WINDOWS {
+                    if i < CHALLENGE_WINDOWS {
+                        config.s_endo.enable(&mut region, i)?;
+                    } else {
+                        config.s_endo_start.enable(&mut region, i)?;
+                    }
+                    let integer = integers.as_ref().map(|integers| integers[i]);
+                    let a = region.assign_advice(
+                        || format!("A_{}", i),
+                        config.a,
+                        i,
+                        || {
+                            integer
+                                .map(|(a, _)| C::Base::from_u128(a))
+                                .ok_or(Error::SynthesisError)
+                        },
+                    )?;
+                    let b = region.assign_advice(
+                        || format!("B_{}", i),
+                        config.b,
+                        i,
+                        || {
+                            integer
+                                .map(|(_, b)| C::Base::from_u128(b))
+                                .ok_or(Error::SynthesisError)
+                        },
+                    )?;
+                    cells.push((a, b));
+                }
+
+                Ok(VerifierChallenge {
+                    challenge,
+                    a: cells[0].0,
+                    b: cells[0].1,
+                })
+            },
+        )
+    }
+}
+
+impl<C: CurveAffine> MsmInstructions<C> for VerifierChip<C> {
+    type Bit = AssignedBit;
+
+    fn witness_bit(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<bool>,
+    ) -> Result<Self::Bit, Error> {
+        let config = layouter.config().msm.clone();
+        config.witness_bit(layouter, value).map(|bit| AssignedBit {
+            cell: bit.cell,
+            value,
+        })
+    }
+
+    fn select(
+        layouter: &mut impl Layouter<Self>,
+        bit: &Self::Bit,
+        point: &Self::Point,
+    ) -> Result<Self::Point, Error> {
+        let config = layouter.config().msm.clone();
+        config.select(layouter, bit.cell, bit.value, point)
+    }
+}
+
+impl<C: CurveAffine> ForeignFieldInstructions for VerifierChip<C> {
+    type Element = AssignedElement;
+
+    fn witness_element(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<[u64; 4]>,
+    ) -> Result<Self::Element, Error> {
+        let config = layouter.config().ff.clone();
+        config.witness_element(layouter, value)
+    }
+
+    fn constant(
+        layouter: &mut impl Layouter<Self>,
+        value: [u64; 4],
+    ) -> Result<Self::Element, Error> {
+        let config = layouter.config().ff.clone();
+        config.constant(layouter, value)
+    }
+
+    fn add(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Element,
+        b: &Self::Element,
+    ) -> Result<Self::Element, Error> {
+        let config = layouter.config().ff.clone();
+        config.add(layouter, a, b)
+    }
+
+    fn sub(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Element,
+        b: &Self::Element,
+    ) -> Result<Self::Element, Error> {
+        let config = layouter.config().ff.clone();
+        config.sub(layouter, a, b)
+    }
+
+    fn mul(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Element,
+        b: &Self::Element,
+    ) -> Result<Self::Element, Error> {
+        let config = layouter.config().ff.clone();
+        config.mul(layouter, a, b)
+    }
+
+    fn invert(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Element,
+    ) -> Result<Self::Element, Error> {
+        let config = layouter.config().ff.clone();
+        config.invert(layouter, a)
+    }
+
+    fn reduce(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Element,
+    ) -> Result<Self::Element, Error> {
+        let config = layouter.config().ff.clone();
+        config.reduce(layouter, a)
+    }
+
+    fn constrain_equal(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Element,
+        b: &Self::Element,
+    ) -> Result<(), Error> {
+        let config = layouter.config().ff.clone();
+        config.constrain_equal(layouter, a, b)
+    }
+}
+
+impl<C: CurveAffine> VerifierInstructions<C> for VerifierChip<C> {
+    fn constant_point(layouter: &mut impl Layouter<Self>, value: C) -> Result<Self::Point, Error> {
+        let config = layouter.config().clone();
+        let (x, y) = Option::from(value.get_xy()).ok_or(Error::SynthesisError)?;
+        let point = config.transcript.ecc.witness_point(layouter, Some(value))?;
+        layouter.assign_region(
+            || "constant point",
+            |mut region| {
+                let x = config.assign_constant(&mut region, 0, x)?;
+                let y = config.assign_constant(&mut region, 1, y)?;
+                region.constrain_equal(&config.ff.relations.perm, x, point.x())?;
+                region.constrain_equal(&config.ff.relations.perm, y, point.y())
+            },
+        )?;
+        Ok(point)
+    }
+
+    fn constant_words(
+        layouter: &mut impl Layouter<Self>,
+        value: C::Scalar,
+    ) -> Result<[Self::Word; 2], Error> {
+        let config = layouter.config().clone();
+        let limbs = scalar_limbs(&value);
+        layouter.assign_region(
+            || "constant words",
+            |mut region| {
+                let mut word = |i: usize| {
+                    let value = C::Base::from_u128(limbs[i]);
+                    let constant = config.assign_constant(&mut region, i, value)?;
+                    let word = config.assign_word(&mut region, i, 0, Some(value))?;
+                    region.constrain_equal(&config.ff.relations.perm, constant, word.var)?;
+                    Ok(word)
+                };
+                Ok([word(0)?, word(1)?])
+            },
+        )
+    }
+
+    fn witness_scalar(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<C::Scalar>,
+    ) -> Result<(Self::Element, [Self::Word; 2]), Error> {
+        let config = layouter.config().clone();
+        let limbs = value.map(|value| scalar_limbs(&value));
+        layouter.assign_region(
+            || "witness scalar",
+            |mut region| {
+                let relations = &config.ff.relations;
+                let element = relations.assign_element(
+                    &mut region,
+                    0,
+                    value.map(|value| super::scalar_words(&value)),
+                )?;
+                relations.assign_canonical(
+                    &mut region,
+                    RELATION_ROWS,
+                    &element,
+                    config.ff.modulus(),
+                )?;
+
+                config.s_words.enable(&mut region, 0)?;
+                let mut word = |i: usize| {
+                    let value = limbs.map(|limbs| C::Base::from_u128(limbs[i]));
+                    config.assign_word(&mut region, i, 0, value)
+                };
+                Ok((element, [word(0)?, word(1)?]))
+            },
+        )
+    }
+
+    fn challenge_words(
+        layouter: &mut impl Layouter<Self>,
+        challenge: &Self::Challenge,
+    ) -> Result<[Self::Word; 2], Error> {
+        let config = layouter.config().clone();
+        let value = challenge
+            .challenge
+            .value
+            .map(|value| C::Base::from_u128(value.0));
+        layouter.assign_region(
+            || "challenge words",
+            |mut region| {
+                let low = config.assign_word(&mut region, 0, 0, value)?;
+                region.constrain_equal(
+                    &config.transcript.perm,
+                    challenge.challenge.cell,
+                    low.var,
+                )?;
+
+                let zero = config.assign_constant(&mut region, 0, C::Base::zero())?;
+                let high = config.assign_word(&mut region, 1, 0, Some(C::Base::zero()))?;
+                region.constrain_equal(&config.ff.relations.perm, zero, high.var)?;
+                Ok([low, high])
+            },
+        )
+    }
+
+    fn challenge_element(
+        layouter: &mut impl Layouter<Self>,
+        challenge: &Self::Challenge,
+    ) -> Result<Self::Element, Error> {
+        let value = challenge
+            .challenge
+            .value
+            .map(|value| C::Scalar::from_u128(value.0));
+        let (element, [low, high]) = Self::witness_scalar(layouter, value)?;
+        let config = layouter.config().clone();
+        layouter.assign_region(
+            || "challenge element",
+            |mut region| {
+                region.constrain_equal(
+                    &config.transcript.perm,
+                    challenge.challenge.cell,
+                    low.var,
+                )?;
+                let zero = config.assign_constant(&mut region, 0, C::Base::zero())?;
+                region.constrain_equal(&config.ff.relations.perm, zero, high.var)
+            },
+        )?;
+        Ok(element)
+    }
+
+    fn challenge_scalar(
+        layouter: &mut impl Layouter<Self>,
+        challenge: &Self::Challenge,
+    ) -> Result<Self::Element, Error> {
+        let config = layouter.config().clone();
+        let integers = challenge
+            .challenge
+            .value
+            .map(|value| endoscaling_integers(value)[0]);
+        let (a, b) = layouter.assign_region(
+            || "challenge integers",
+            |mut region| {
+                let relations = &config.ff.relations;
+                let perm = &relations.perm;
+                let a =
+                    relations.assign_element(&mut region, 0, integers.map(|(a, _)| words(a)))?;
+                let b = relations.assign_element(
+                    &mut region,
+                    RELATION_ROWS,
+                    integers.map(|(_, b)| words(b)),
+                )?;
+
+                // The integers are the low limbs, whose values are constrained by the
+                // endoscaling rows, and the other limbs are zero.
+                let zero = config.assign_constant(&mut region, 0, C::Base::zero())?;
+                for (element, cell) in [(&a, challenge.a), (&b, challenge.b)] {
+                    region.constrain_equal(perm, element.cells[0], cell)?;
+                    region.constrain_equal(perm, element.cells[1], zero)?;
+                    region.constrain_equal(perm, element.cells[2], zero)?;
+                }
+                Ok((a, b))
+            },
+        )?;
+
+        let zeta = config
+            .ff
+            .constant(layouter, super::scalar_words(&C::Scalar::ZETA))?;
+        config
+            .ff
+            .combine(layouter, &[(1, &zeta, &a)], &[(1, &b)], (0, [0; 4]))
+    }
+
+    fn challenge_value(challenge: &Self::Challenge) -> Option<Challenge> {
+        challenge.challenge.value
+    }
+
+    fn mul_challenge(
+        layouter: &mut impl Layouter<Self>,
+        base: &Self::Point,
+        challenge: &Self::Challenge,
+    ) -> Result<Self::Point, Error> {
+        let config = layouter.config().ipa.clone();
+        config.mul_challenge(layouter, base, &challenge.challenge)
+    }
+
+    fn div_challenge(
+        layouter: &mut impl Layouter<Self>,
+        point: &Self::Point,
+        challenge: &Self::Challenge,
+    ) -> Result<Self::Point, Error> {
+        let config = layouter.config().ipa.clone();
+        config.div_challenge(layouter, point, &challenge.challenge)
+    }
+
+    fn combine(
+        layouter: &mut impl Layouter<Self>,
+        products: &[(i64, &Self::Element, &Self::Element)],
+        linear: &[(i64, &Self::Element)],
+        constant: C::Scalar,
+    ) -> Result<Self::Element, Error> {
+        let config = layouter.config().ff.clone();
+        config.combine(
+            layouter,
+            products,
+            linear,
+            (1, super::scalar_words(&constant)),
+        )
+    }
+
+    fn scalar_bits(
+        layouter: &mut impl Layouter<Self>,
+        element: &Self::Element,
+    ) -> Result<Vec<Self::Bit>, Error> {
+        let config = layouter.config().ff.clone();
+        let mut bits = config.decompose(layouter, element, 256)?;
+        bits.reverse();
+        Ok(bits)
+    }
+
+    fn expose_point(layouter: &mut impl Layouter<Self>, point: &Self::Point) -> Result<(), Error> {
+        let config = layouter.config().ipa.clone();
+        config.expose_point(layouter, point)
+    }
+
+    fn expose_challenge(
+        layouter: &mut impl Layouter<Self>,
+        challenge: &Self::Challenge,
+    ) -> Result<(), Error> {
+        let config = layouter.config().ipa.clone();
+        config.expose_challenge(layouter, &challenge.challenge)
+    }
+}
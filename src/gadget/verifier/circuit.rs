@@ -0,0 +1,502 @@
+//! A circuit aggregating proofs of different circuits into one proof.
+
+use rand::RngCore;
+
+use super::{
+    accumulate, public_inputs, verify_proof, VerifierChip, VerifierConfig, VerifierInstructions,
+};
+use crate::{
+    arithmetic::CurveAffine,
+    circuit::{layouter, Layouter},
+    gadget::ecc::Point,
+    plonk::{self, Assignment, Circuit, ConstraintSystem, Error, VerifyingKey},
+    poly::commitment::{self, create_accumulation_proof, verify_accumulation_proof, Params},
+    primitives::poseidon::{P128Pow5T3, Spec},
+    transcript::{PoseidonRead, PoseidonWrite},
+};
+
+/// A proof of a single circuit, with the commitments to its instance columns.
+#[derive(Clone, Debug)]
+pub struct InnerProof<C: CurveAffine> {
+    /// The commitments to the instance columns of the circuit, as
+    /// [`VerifyingKey::commit_instances`] computes them.
+    pub instance_commitments: Vec<C>,
+    /// The proof, created by [`plonk::create_proof`] with a
+    /// [`PoseidonWrite`] transcript.
+    pub proof: Vec<u8>,
+}
+
+/// A circuit over `C::Base` that verifies one proof for each of several verifying
+/// keys over `C`, and folds the accumulators of the proofs into one.
+///
+/// The public inputs of the circuit are the coordinates of the instance commitments
+/// of each proof, in the order of the keys, followed by those of the folded
+/// accumulator as given by [`public_inputs`](super::public_inputs). A proof of this
+/// circuit and the folded accumulator together replace the proofs it verifies: they
+/// are valid if the proof verifies and the accumulator is valid, which can be checked
+/// with [`commitment::Accumulator::from_challenges`], or deferred again by folding the
+/// accumulator with others.
+///
+/// The keys may be for different circuits, but must share `params`.
+#[derive(Debug)]
+pub struct AggregationCircuit<'a, C: CurveAffine> {
+    params: &'a Params<C>,
+    vks: Vec<&'a VerifyingKey<C>>,
+    proofs: Option<Vec<InnerProof<C>>>,
+    accumulation_proof: Option<Vec<u8>>,
+    accumulator: Option<commitment::Accumulator<C>>,
+}
+
+impl<'a, C: CurveAffine> AggregationCircuit<'a, C>
+where
+    P128Pow5T3: Spec<C::Base, 3, 2>,
+{
+    /// Returns the circuit for proofs of `vks` without witnesses, such as for key
+    /// generation.
+    pub fn empty(params: &'a Params<C>, vks: Vec<&'a VerifyingKey<C>>) -> Self {
+        AggregationCircuit {
+            params,
+            vks,
+            proofs: None,
+            accumulation_proof: None,
+            accumulator: None,
+        }
+    }
+
+    /// Returns the circuit that verifies `proofs`, one for each of `vks`.
+    ///
+    /// Each proof is verified first, and the proof that folds their accumulators is
+    /// created with randomness from `rng`.
+    ///
+    /// Returns [`Error::IncompatibleParams`] if there are no keys or not one proof for
+    /// each key, and the error of verification if a proof does not verify.
+    pub fn new(
+        params: &'a Params<C>,
+        vks: Vec<&'a VerifyingKey<C>>,
+        proofs: Vec<InnerProof<C>>,
+        rng: impl RngCore,
+    ) -> Result<Self, Error> {
+        if vks.is_empty() || vks.len() != proofs.len() {
+            return Err(Error::IncompatibleParams);
+        }
+
+        let accumulators = vks
+            .iter()
+            .zip(proofs.iter())
+            .map(|(vk, proof)| verify(params, vk, &proof.instance_commitments, &proof.proof))
+            .collect::<Result<Vec<_>, Error>>()?;
+        let (accumulation_proof, accumulator) = fold(params, &accumulators, rng)?;
+
+        Ok(AggregationCircuit {
+            params,
+            vks,
+            proofs: Some(proofs),
+            accumulation_proof: Some(accumulation_proof),
+            accumulator: Some(accumulator),
+        })
+    }
+
+    /// Returns the public inputs of this circuit, or `None` if it has no witnesses.
+    pub fn public_inputs(&self) -> Option<Vec<C::Base>> {
+        let mut inputs = vec![];
+        for proof in self.proofs.as_ref()? {
+            for commitment in &proof.instance_commitments {
+                let (x, y) = Option::from(commitment.get_xy())?;
+                inputs.push(x);
+                inputs.push(y);
+            }
+        }
+        inputs.extend(public_inputs(self.accumulator.as_ref()?));
+        Some(inputs)
+    }
+
+    /// Returns the folded accumulator whose check this circuit defers, or `None` if it
+    /// has no witnesses.
+    pub fn accumulator(&self) -> Option<&commitment::Accumulator<C>> {
+        self.accumulator.as_ref()
+    }
+}
+
+/// Verifies `proof`, a proof for `vk` with the given instance commitments created with a
+/// [`PoseidonWrite`] transcript, and returns the accumulator whose check it defers.
+pub(super) fn verify<C: CurveAffine>(
+    params: &Params<C>,
+    vk: &VerifyingKey<C>,
+    instance_commitments: &[C],
+    proof: &[u8],
+) -> Result<commitment::Accumulator<C>, Error>
+where
+    P128Pow5T3: Spec<C::Base, 3, 2>,
+{
+    let mut transcript = PoseidonRead::<_, C>::init_strict(proof);
+    let guard = plonk::verify_proof(
+        params,
+        vk,
+        params.empty_msm(),
+        &[instance_commitments],
+        &mut transcript,
+    )?;
+    transcript.finalize().map_err(|_| Error::TranscriptError)?;
+    let g = guard.compute_g();
+    let (msm, accumulator) = guard.use_g(g);
+    if !msm.eval() {
+        return Err(Error::OpeningError);
+    }
+    Ok(accumulator)
+}
+
+/// Creates a proof that folds `accumulators`, as the [`accumulate`] gadget reads it, and
+/// returns it with the folded accumulator.
+pub(super) fn fold<C: CurveAffine>(
+    params: &Params<C>,
+    accumulators: &[commitment::Accumulator<C>],
+    rng: impl RngCore,
+) -> Result<(Vec<u8>, commitment::Accumulator<C>), Error>
+where
+    P128Pow5T3: Spec<C::Base, 3, 2>,
+{
+    let mut transcript = PoseidonWrite::<_, C>::init(vec![]);
+    create_accumulation_proof(params, rng, &mut transcript, accumulators)
+        .map_err(|_| Error::TranscriptError)?;
+    let proof = transcript.finalize();
+
+    let mut transcript = PoseidonRead::<_, C>::init_strict(&proof[..]);
+    let guard = verify_accumulation_proof(params, &mut transcript, accumulators)
+        .map_err(|_| Error::OpeningError)?;
+    let g = guard.compute_g();
+    let (msm, accumulator) = guard.use_g(g);
+    if !msm.eval() {
+        return Err(Error::OpeningError);
+    }
+    Ok((proof, accumulator))
+}
+
+impl<'a, C: CurveAffine> Circuit<C::Base> for AggregationCircuit<'a, C>
+where
+    P128Pow5T3: Spec<C::Base, 3, 2>,
+{
+    type Config = VerifierConfig<C::Base>;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn configure(meta: &mut ConstraintSystem<C::Base>) -> VerifierConfig<C::Base> {
+        VerifierChip::<C>::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        cs: &mut impl Assignment<C::Base>,
+        config: VerifierConfig<C::Base>,
+    ) -> Result<(), Error> {
+        if self.vks.is_empty() {
+            return Err(Error::IncompatibleParams);
+        }
+        let mut layouter = layouter::SingleChip::<VerifierChip<C>, _>::new(cs, config)?;
+
+        let mut accumulators = Vec::with_capacity(self.vks.len());
+        for (i, vk) in self.vks.iter().enumerate() {
+            let mut layouter = layouter.namespace(|| format!("proof {}", i));
+            let proof = self.proofs.as_ref().map(|proofs| &proofs[i]);
+
+            let instance_commitments = (0..vk.cs().num_instance_columns)
+                .map(|j| {
+                    let point = Point::new(
+                        layouter.namespace(|| format!("instance {}", j)),
+                        proof.map(|proof| proof.instance_commitments[j]),
+                    )?;
+                    VerifierChip::expose_point(&mut layouter, point.inner())?;
+                    Ok(point)
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+
+            accumulators.push(verify_proof(
+                layouter.namespace(|| "verify"),
+                self.params,
+                vk,
+                &instance_commitments,
+                proof.map(|proof| &proof.proof[..]),
+            )?);
+        }
+
+        let accumulator = accumulate(
+            layouter.namespace(|| "accumulate"),
+            self.params,
+            &accumulators,
+            self.accumulation_proof.as_deref(),
+        )?;
+        accumulator.expose(layouter.namespace(|| "expose"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::OsRng;
+
+    use super::{AggregationCircuit, InnerProof};
+    use crate::{
+        arithmetic::FieldExt,
+        dev::MockProver,
+        pasta::{pallas, EpAffine},
+        plonk::{
+            create_proof, keygen_pk, keygen_vk, Advice, Assignment, Circuit, Column,
+            ConstraintSystem, Error, Fixed, LookupArgument, Permutation, Selector, VerifyingKey,
+        },
+        poly::{
+            commitment::{Accumulator, Params},
+            Rotation,
+        },
+        transcript::PoseidonWrite,
+    };
+
+    const K: u32 = 4;
+    const AGGREGATION_K: u32 = 15;
+
+    /// A circuit over $\mathbb{F}_q$ that squares its witness twice with a copy
+    /// between the squarings, and exposes the result.
+    #[derive(Clone, Default)]
+    struct SquareCircuit {
+        x: Option<pallas::Scalar>,
+    }
+
+    #[derive(Clone, Debug)]
+    struct SquareConfig {
+        a: Column<Advice>,
+        b: Column<Advice>,
+        s_square: Selector,
+        s_public: Selector,
+        perm: Permutation,
+    }
+
+    impl Circuit<pallas::Scalar> for SquareCircuit {
+        type Config = SquareConfig;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<pallas::Scalar>) -> SquareConfig {
+            let a = meta.advice_column();
+            let b = meta.advice_column();
+            let instance = meta.instance_column();
+            let s_square = meta.selector();
+            let s_public = meta.selector();
+            let perm = Permutation::new(meta, &[a.into(), b.into()]);
+
+            meta.create_gate("square", |meta| {
+                let a = meta.query_advice(a, Rotation::cur());
+                let b = meta.query_advice(b, Rotation::cur());
+                let s_square = meta.query_selector(s_square, Rotation::cur());
+                s_square * (a.clone() * a - b)
+            });
+            meta.create_gate("public", |meta| {
+                let b = meta.query_advice(b, Rotation::cur());
+                let instance = meta.query_instance(instance, Rotation::cur());
+                let s_public = meta.query_selector(s_public, Rotation::cur());
+                s_public * (b - instance)
+            });
+
+            SquareConfig {
+                a,
+                b,
+                s_square,
+                s_public,
+                perm,
+            }
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<pallas::Scalar>,
+            config: SquareConfig,
+        ) -> Result<(), Error> {
+            let x2 = self.x.map(|x| x.square());
+            let x4 = x2.map(|x2| x2.square());
+            for (row, (a, b)) in [(self.x, x2), (x2, x4)].iter().enumerate() {
+                cs.enable_selector(|| "square", &config.s_square, row)?;
+                cs.assign_advice(|| "a", config.a, row, || a.ok_or(Error::SynthesisError))?;
+                cs.assign_advice(|| "b", config.b, row, || b.ok_or(Error::SynthesisError))?;
+            }
+            cs.enable_selector(|| "public", &config.s_public, 1)?;
+            cs.copy(&config.perm, config.b.into(), 0, config.a.into(), 1)
+        }
+    }
+
+    /// A circuit over $\mathbb{F}_q$ that looks up its witnesses in a table of small
+    /// values with the log-derivative argument, and shuffles them.
+    #[derive(Clone, Default)]
+    struct TableCircuit {
+        values: Option<[u64; 3]>,
+    }
+
+    #[derive(Clone, Debug)]
+    struct TableConfig {
+        a: Column<Advice>,
+        b: Column<Advice>,
+        table: Column<Fixed>,
+        s_lookup: Selector,
+    }
+
+    impl Circuit<pallas::Scalar> for TableCircuit {
+        type Config = TableConfig;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<pallas::Scalar>) -> TableConfig {
+            let a = meta.advice_column();
+            let b = meta.advice_column();
+            let table = meta.fixed_column();
+            let s_lookup = meta.selector();
+            meta.set_lookup_argument(LookupArgument::LogDerivative);
+
+            let input = meta.query_advice(a, Rotation::cur());
+            let s = meta.query_selector(s_lookup, Rotation::cur());
+            let table_value = meta.query_fixed(table, Rotation::cur());
+            meta.lookup(&[s * input], &[table_value]);
+
+            let input = meta.query_advice(a, Rotation::cur());
+            let shuffled = meta.query_advice(b, Rotation::cur());
+            meta.shuffle(&[input], &[shuffled]);
+
+            TableConfig {
+                a,
+                b,
+                table,
+                s_lookup,
+            }
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<pallas::Scalar>,
+            config: TableConfig,
+        ) -> Result<(), Error> {
+            for row in 0..8 {
+                cs.assign_fixed(
+                    || "table",
+                    config.table,
+                    row,
+                    || Ok(pallas::Scalar::from_u64(row as u64)),
+                )?;
+            }
+            for row in 0..3 {
+                let value = self
+                    .values
+                    .map(|values| pallas::Scalar::from_u64(values[row]));
+                let shuffled = self
+                    .values
+                    .map(|values| pallas::Scalar::from_u64(values[2 - row]));
+                cs.enable_selector(|| "lookup", &config.s_lookup, row)?;
+                cs.assign_advice(|| "a", config.a, row, || value.ok_or(Error::SynthesisError))?;
+                cs.assign_advice(
+                    || "b",
+                    config.b,
+                    row,
+                    || shuffled.ok_or(Error::SynthesisError),
+                )?;
+            }
+            Ok(())
+        }
+    }
+
+    /// Creates a proof of `circuit` for `vk` with a Poseidon transcript.
+    fn prove<ConcreteCircuit: Circuit<pallas::Scalar>>(
+        params: &Params<EpAffine>,
+        vk: &VerifyingKey<EpAffine>,
+        circuit: ConcreteCircuit,
+        instance: &[pallas::Scalar],
+    ) -> InnerProof<EpAffine> {
+        let pk = keygen_pk(params, keygen_vk(params, &circuit).unwrap(), &circuit).unwrap();
+        let columns: Vec<&[pallas::Scalar]> = if vk.cs().num_instance_columns == 0 {
+            vec![]
+        } else {
+            vec![instance]
+        };
+        let instances = vk.instance_values(&columns).unwrap();
+        let mut transcript = PoseidonWrite::<_, EpAffine>::init(vec![]);
+        create_proof(
+            params,
+            &pk,
+            &[circuit],
+            &[&instances],
+            OsRng,
+            &mut transcript,
+        )
+        .unwrap();
+        InnerProof {
+            instance_commitments: vk.commit_instances(params, &columns).unwrap(),
+            proof: transcript.finalize(),
+        }
+    }
+
+    #[test]
+    fn aggregates_proofs_of_different_circuits() {
+        let params = Params::<EpAffine>::new(K);
+        let square_vk = keygen_vk(&params, &SquareCircuit::default()).unwrap();
+        let table_vk = keygen_vk(&params, &TableCircuit::default()).unwrap();
+
+        let x = pallas::Scalar::from_u64(3);
+        let square = prove(
+            &params,
+            &square_vk,
+            SquareCircuit { x: Some(x) },
+            &[pallas::Scalar::zero(), pallas::Scalar::from_u64(81)],
+        );
+        let table = prove(
+            &params,
+            &table_vk,
+            TableCircuit {
+                values: Some([1, 5, 7]),
+            },
+            &[],
+        );
+
+        let vks = vec![&square_vk, &table_vk];
+        let circuit = AggregationCircuit::new(
+            &params,
+            vks.clone(),
+            vec![square.clone(), table.clone()],
+            OsRng,
+        )
+        .unwrap();
+
+        // The folded accumulator is valid, so the proofs are.
+        let accumulator = circuit.accumulator().unwrap();
+        let expected = Accumulator::from_challenges(&params, accumulator.challenges_packed.clone());
+        assert_eq!(accumulator.g, expected.g);
+
+        let mut instance = circuit.public_inputs().unwrap();
+        instance.resize(1 << AGGREGATION_K, pallas::Base::zero());
+        let prover = MockProver::run(AGGREGATION_K, &circuit, vec![instance.clone()]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // The public inputs bind the instance commitments of the proofs.
+        let mut bad_instance = instance;
+        bad_instance[0] += pallas::Base::one();
+        let prover = MockProver::run(AGGREGATION_K, &circuit, vec![bad_instance]).unwrap();
+        assert!(prover.verify().is_err());
+
+        // A proof for the wrong instance is rejected, both when the circuit is built
+        // and by the circuit itself.
+        let wrong = prove(
+            &params,
+            &square_vk,
+            SquareCircuit { x: Some(x) },
+            &[pallas::Scalar::zero(), pallas::Scalar::from_u64(80)],
+        );
+        assert!(AggregationCircuit::new(
+            &params,
+            vks.clone(),
+            vec![wrong.clone(), table.clone()],
+            OsRng
+        )
+        .is_err());
+        assert!(AggregationCircuit::new(&params, vks, vec![square], OsRng).is_err());
+
+        let circuit = AggregationCircuit {
+            proofs: Some(vec![wrong, table]),
+            ..circuit
+        };
+        let mut instance = circuit.public_inputs().unwrap();
+        instance.resize(1 << AGGREGATION_K, pallas::Base::zero());
+        let prover = MockProver::run(AGGREGATION_K, &circuit, vec![instance]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
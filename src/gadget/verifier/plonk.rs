@@ -0,0 +1,537 @@
+//! The [`verify_proof`] gadget, which checks a proof of a circuit in a circuit.
+
+use ff::Field;
+
+use super::{combine, verify_opening, Accumulator, ProofReader, Query, Term, VerifierInstructions};
+use crate::{
+    arithmetic::{CurveAffine, FieldExt},
+    circuit::Layouter,
+    gadget::ecc::Point,
+    plonk::{Any, Error, Expression, LookupArgument, VerifyingKey},
+    poly::{commitment::Params, Rotation},
+};
+
+/// The evaluations of the columns of a circuit, and the values of its challenges.
+struct Evals<'a, C: CurveAffine, VerifierChip: VerifierInstructions<C>> {
+    fixed: &'a [Term<C, VerifierChip>],
+    advice: &'a [Term<C, VerifierChip>],
+    instance: &'a [Term<C, VerifierChip>],
+    challenges: &'a [Term<C, VerifierChip>],
+}
+
+impl<'a, C: CurveAffine, VerifierChip: VerifierInstructions<C>> Evals<'a, C, VerifierChip> {
+    /// Evaluates `expression` at the point of the evaluations.
+    fn evaluate(
+        &self,
+        layouter: &mut impl Layouter<VerifierChip>,
+        expression: &Expression<C::Scalar>,
+    ) -> Result<Term<C, VerifierChip>, Error> {
+        Ok(match expression {
+            Expression::Constant(scalar) => Term::Constant(*scalar),
+            Expression::Selector(_, _) => {
+                panic!("virtual selectors are removed during optimization")
+            }
+            Expression::Fixed(index) => self.fixed[*index].clone(),
+            Expression::Advice(index) => self.advice[*index].clone(),
+            Expression::Instance(index) => self.instance[*index].clone(),
+            Expression::Challenge(challenge) => self.challenges[challenge.index()].clone(),
+            Expression::Sum(a, b) => {
+                let a = self.evaluate(layouter, a)?;
+                let b = self.evaluate(layouter, b)?;
+                a.add(layouter, &b)?
+            }
+            Expression::Product(a, b) => {
+                let a = self.evaluate(layouter, a)?;
+                let b = self.evaluate(layouter, b)?;
+                a.mul(layouter, &b)?
+            }
+            Expression::Scaled(a, scalar) => {
+                let a = self.evaluate(layouter, a)?;
+                a.mul(layouter, &Term::Constant(*scalar))?
+            }
+        })
+    }
+
+    /// Returns $\theta^{m - 1} e_0 + \dots + e_{m - 1} + c$ for the given
+    /// expressions $e_i$.
+    fn compress(
+        &self,
+        layouter: &mut impl Layouter<VerifierChip>,
+        expressions: &[Expression<C::Scalar>],
+        theta: &Term<C, VerifierChip>,
+        c: &Term<C, VerifierChip>,
+    ) -> Result<Term<C, VerifierChip>, Error> {
+        let mut acc = Term::Constant(C::Scalar::zero());
+        for expression in expressions {
+            let eval = self.evaluate(layouter, expression)?;
+            acc = acc.mul_add(layouter, theta, &eval)?;
+        }
+        acc.add(layouter, c)
+    }
+}
+
+/// The digest of a verifying key and the commitments to its fixed columns and to the
+/// columns of its permutation arguments, assigned in a circuit.
+///
+/// These are the values of the key that a proof is checked against, rather than the
+/// shape of its circuit. They are constants when the key is known when the circuit is
+/// configured, and witnesses when it is not, such as for a key that depends on the
+/// circuit itself.
+#[derive(Debug)]
+pub(super) struct AssignedKey<C: CurveAffine, VerifierChip: VerifierInstructions<C>> {
+    pub(super) digest: [VerifierChip::Word; 2],
+    pub(super) fixed: Vec<Point<C, VerifierChip>>,
+    pub(super) permutations: Vec<Vec<Point<C, VerifierChip>>>,
+}
+
+impl<C: CurveAffine, VerifierChip: VerifierInstructions<C>> AssignedKey<C, VerifierChip> {
+    /// Assigns the values of `vk` as constants.
+    pub(super) fn constant(
+        layouter: &mut impl Layouter<VerifierChip>,
+        vk: &VerifyingKey<C>,
+    ) -> Result<Self, Error> {
+        Self::assign(
+            layouter,
+            vk,
+            |layouter, value| VerifierChip::constant_words(layouter, value),
+            |layouter, value| VerifierChip::constant_point(layouter, value).map(Point::from_inner),
+        )
+    }
+
+    /// Witnesses the values of `vk`.
+    pub(super) fn witness(
+        layouter: &mut impl Layouter<VerifierChip>,
+        vk: &VerifyingKey<C>,
+    ) -> Result<Self, Error> {
+        Self::assign(
+            layouter,
+            vk,
+            |layouter, value| VerifierChip::witness_scalar(layouter, Some(value)).map(|(_, w)| w),
+            |layouter, value| Point::new(layouter.namespace(|| "commitment"), Some(value)),
+        )
+    }
+
+    fn assign<L: Layouter<VerifierChip>>(
+        layouter: &mut L,
+        vk: &VerifyingKey<C>,
+        scalar: impl Fn(&mut L, C::Scalar) -> Result<[VerifierChip::Word; 2], Error>,
+        point: impl Fn(&mut L, C) -> Result<Point<C, VerifierChip>, Error>,
+    ) -> Result<Self, Error> {
+        let digest = scalar(layouter, vk.digest())?;
+        let fixed = vk
+            .fixed_commitments()
+            .iter()
+            .map(|commitment| point(layouter, *commitment))
+            .collect::<Result<Vec<_>, Error>>()?;
+        let permutations = vk
+            .permutations()
+            .iter()
+            .map(|vkey| {
+                vkey.commitments()
+                    .iter()
+                    .map(|commitment| point(layouter, *commitment))
+                    .collect::<Result<Vec<_>, Error>>()
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        Ok(AssignedKey {
+            digest,
+            fixed,
+            permutations,
+        })
+    }
+}
+
+/// Verifies `proof`, a proof for `vk` with the given commitments to its instance
+/// columns created with a [`PoseidonWrite`](crate::transcript::PoseidonWrite)
+/// transcript, and returns the accumulator whose check it defers.
+///
+/// This performs the checks of [`plonk::verify_proof`](crate::plonk::verify_proof) for
+/// a single proof read by a [`PoseidonRead`](crate::transcript::PoseidonRead) created
+/// with `init_strict`. The proof is valid if the returned accumulator is.
+///
+/// `proof` may only be `None` if the circuit is synthesized without witnesses.
+///
+/// Returns [`Error::IncompatibleParams`] if there is not one commitment for each
+/// instance column of the circuit, and [`Error::TranscriptError`] if the proof cannot
+/// be read.
+pub fn verify_proof<C: CurveAffine, VerifierChip: VerifierInstructions<C>>(
+    mut layouter: impl Layouter<VerifierChip>,
+    params: &Params<C>,
+    vk: &VerifyingKey<C>,
+    instance_commitments: &[Point<C, VerifierChip>],
+    proof: Option<&[u8]>,
+) -> Result<Accumulator<C, VerifierChip>, Error> {
+    let key = AssignedKey::constant(&mut layouter.namespace(|| "key"), vk)?;
+    verify_proof_if(
+        layouter,
+        params,
+        vk,
+        &key,
+        instance_commitments,
+        proof,
+        None,
+    )
+}
+
+/// Verifies `proof` as [`verify_proof`] does, for a key with the shape of `vk` and the
+/// values of `key`, and only enforces its checks if `enforce`, which must be zero or
+/// one, is set and one.
+///
+/// The proof is still read, and the returned accumulator computed from it, if its
+/// checks are not enforced.
+pub(super) fn verify_proof_if<C: CurveAffine, VerifierChip: VerifierInstructions<C>>(
+    mut layouter: impl Layouter<VerifierChip>,
+    params: &Params<C>,
+    vk: &VerifyingKey<C>,
+    key: &AssignedKey<C, VerifierChip>,
+    instance_commitments: &[Point<C, VerifierChip>],
+    proof: Option<&[u8]>,
+    enforce: Option<Term<C, VerifierChip>>,
+) -> Result<Accumulator<C, VerifierChip>, Error> {
+    let cs = vk.cs();
+    if instance_commitments.len() != cs.num_instance_columns {
+        return Err(Error::IncompatibleParams);
+    }
+    let domain = vk.get_domain();
+    let layouter = &mut layouter;
+    let mut reader = ProofReader::new(layouter.namespace(|| "transcript"), proof)?;
+    reader.enforce = enforce;
+
+    reader
+        .transcript
+        .absorb_words(layouter.namespace(|| "absorb key"), &key.digest)?;
+    for commitment in instance_commitments {
+        reader.common_point(layouter, commitment)?;
+    }
+
+    let mut advice_commitments = vec![None; cs.num_advice_columns];
+    let mut challenges = vec![Term::Constant(C::Scalar::zero()); cs.num_challenges];
+    for phase in cs.phases() {
+        for (commitment, column_phase) in advice_commitments
+            .iter_mut()
+            .zip(cs.advice_column_phase.iter())
+        {
+            if *column_phase == phase {
+                *commitment = Some(reader.read_point(layouter)?);
+            }
+        }
+        for (challenge, challenge_phase) in challenges.iter_mut().zip(cs.challenge_phase.iter()) {
+            if *challenge_phase == phase {
+                *challenge = reader.squeeze_scalar(layouter)?.1;
+            }
+        }
+    }
+    let advice_commitments: Vec<_> = advice_commitments.into_iter().map(Option::unwrap).collect();
+
+    let (_, theta) = reader.squeeze_scalar(layouter)?;
+
+    let lookups = cs.lookups_proven_with(LookupArgument::Permutation);
+    let log_lookups = cs.lookups_proven_with(LookupArgument::LogDerivative);
+    let lookups_permuted = (0..lookups.len())
+        .map(|_| reader.read_points(layouter, 2))
+        .collect::<Result<Vec<_>, Error>>()?;
+    let log_lookup_multiplicities = reader.read_points(layouter, log_lookups.len())?;
+
+    let (_, beta) = reader.squeeze_scalar(layouter)?;
+    let (_, gamma) = reader.squeeze_scalar(layouter)?;
+
+    let permutation_chunks: Vec<_> = cs
+        .permutations
+        .iter()
+        .map(|argument| argument.chunks(cs.degree_without_permutations()))
+        .collect();
+    let permutation_products = permutation_chunks
+        .iter()
+        .map(|chunks| reader.read_points(layouter, chunks.len()))
+        .collect::<Result<Vec<_>, Error>>()?;
+    let lookup_products = reader.read_points(layouter, lookups.len())?;
+    let log_lookup_sums = reader.read_points(layouter, log_lookups.len())?;
+    let shuffle_products = reader.read_points(layouter, cs.shuffles.len())?;
+
+    let (_, y) = reader.squeeze_scalar(layouter)?;
+
+    let h_commitments = reader.read_points(layouter, domain.get_quotient_poly_degree())?;
+
+    let (_, x) = reader.squeeze_scalar(layouter)?;
+
+    let instance_evals = reader.read_scalars(layouter, cs.instance_queries.len())?;
+    let advice_evals = reader.read_scalars(layouter, cs.advice_queries.len())?;
+    let fixed_evals = reader.read_scalars(layouter, cs.fixed_queries.len())?;
+    let h_evals = reader.read_scalars(layouter, h_commitments.len())?;
+
+    // The evaluations of each permutation product at x and omega^{-1} x, followed by
+    // those of the permutation polynomials.
+    let mut permutation_evals = Vec::with_capacity(cs.permutations.len());
+    for (products, commitments) in permutation_products.iter().zip(key.permutations.iter()) {
+        let sets = (0..products.len())
+            .map(|_| reader.read_scalars(layouter, 2))
+            .collect::<Result<Vec<_>, Error>>()?;
+        let polys = reader.read_scalars(layouter, commitments.len())?;
+        permutation_evals.push((sets, polys));
+    }
+    // product, product_inv, permuted_input, permuted_input_inv, permuted_table
+    let lookup_evals = (0..lookups.len())
+        .map(|_| reader.read_scalars(layouter, 5))
+        .collect::<Result<Vec<_>, Error>>()?;
+    // sum, sum_next, multiplicity
+    let log_lookup_evals = (0..log_lookups.len())
+        .map(|_| reader.read_scalars(layouter, 3))
+        .collect::<Result<Vec<_>, Error>>()?;
+    // product, product_next
+    let shuffle_evals = (0..cs.shuffles.len())
+        .map(|_| reader.read_scalars(layouter, 2))
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    // Check that h(x) (x^n - 1) is the expected combination of the constraints.
+    {
+        let one = Term::one();
+        let mut xn = x.clone();
+        for _ in 0..params.k {
+            xn = xn.mul(layouter, &xn)?;
+        }
+        let x_inv = x.sub(layouter, &one)?.invert(layouter)?;
+        let l_0 = combine(layouter, &[(1, &x_inv, &xn)], &[(-1, &x_inv)])?
+            .mul(layouter, &Term::Constant(domain.get_barycentric_weight()))?;
+
+        let evals = Evals {
+            fixed: &fixed_evals,
+            advice: &advice_evals,
+            instance: &instance_evals,
+            challenges: &challenges,
+        };
+        let mut expressions = vec![];
+
+        for gate in cs.gates.iter() {
+            for poly in gate.polynomials() {
+                expressions.push(evals.evaluate(layouter, poly)?);
+            }
+        }
+
+        for ((argument, chunks), (sets, polys)) in cs
+            .permutations
+            .iter()
+            .zip(permutation_chunks.iter())
+            .zip(permutation_evals.iter())
+        {
+            let _ = argument;
+            // l_0(X) (1 - z_0(X))
+            expressions.push(combine(layouter, &[(-1, &l_0, &sets[0][0])], &[(1, &l_0)])?);
+
+            let mut offset = 0;
+            for (index, columns) in chunks.iter().enumerate() {
+                let column_evals = columns
+                    .iter()
+                    .map(|&column| {
+                        let index = cs.get_any_query_index(column, Rotation::cur());
+                        match column.column_type() {
+                            Any::Advice => advice_evals[index].clone(),
+                            Any::Fixed => fixed_evals[index].clone(),
+                            Any::Instance => instance_evals[index].clone(),
+                        }
+                    })
+                    .collect::<Vec<_>>();
+
+                // z_j(X) \prod (p(X) + \beta s_i(X) + \gamma)
+                let mut left = sets[index][0].clone();
+                for (eval, poly) in column_evals.iter().zip(polys[offset..].iter()) {
+                    let term = combine(layouter, &[(1, &beta, poly)], &[(1, eval), (1, &gamma)])?;
+                    left = left.mul(layouter, &term)?;
+                }
+
+                // z'_j(omega^{-1} X) \prod (p(X) + \delta^i \beta X + \gamma)
+                let mut right = sets[index][1].clone();
+                if chunks.len() > 1 {
+                    let previous = &sets[(index + chunks.len() - 1) % chunks.len()][1];
+                    let difference = previous.sub(layouter, &right)?;
+                    right = l_0.mul_add(layouter, &difference, &right)?;
+                }
+                let beta_x = beta.mul(layouter, &x)?;
+                let mut delta = C::Scalar::DELTA.pow_vartime([offset as u64, 0, 0, 0]);
+                for eval in column_evals.iter() {
+                    let term = combine(
+                        layouter,
+                        &[(1, &beta_x, &Term::Constant(delta))],
+                        &[(1, eval), (1, &gamma)],
+                    )?;
+                    right = right.mul(layouter, &term)?;
+                    delta *= &C::Scalar::DELTA;
+                }
+
+                expressions.push(left.sub(layouter, &right)?);
+                offset += columns.len();
+            }
+        }
+
+        for (argument, evals_) in lookups.iter().zip(lookup_evals.iter()) {
+            let (product, product_inv, input, input_inv, table) =
+                (&evals_[0], &evals_[1], &evals_[2], &evals_[3], &evals_[4]);
+
+            // l_0(X) (1 - z'(X))
+            expressions.push(combine(layouter, &[(-1, &l_0, product)], &[(1, &l_0)])?);
+
+            // z'(X) (a'(X) + \beta) (s'(X) + \gamma)
+            // - z'(\omega^{-1} X) (compressed inputs + \beta) (compressed tables + \gamma)
+            let input_beta = input.add(layouter, &beta)?;
+            let table_gamma = table.add(layouter, &gamma)?;
+            let left = product
+                .mul(layouter, &input_beta)?
+                .mul(layouter, &table_gamma)?;
+            let compressed_input =
+                evals.compress(layouter, &argument.input_expressions, &theta, &beta)?;
+            let compressed_table =
+                evals.compress(layouter, &argument.table_expressions, &theta, &gamma)?;
+            let right = product_inv
+                .mul(layouter, &compressed_input)?
+                .mul(layouter, &compressed_table)?;
+            expressions.push(left.sub(layouter, &right)?);
+
+            // l_0(X) (a'(X) - s'(X))
+            let difference = input.sub(layouter, table)?;
+            expressions.push(l_0.mul(layouter, &difference)?);
+
+            // (a'(X) - s'(X)) (a'(X) - a'(\omega^{-1} X))
+            let step = input.sub(layouter, input_inv)?;
+            expressions.push(difference.mul(layouter, &step)?);
+        }
+
+        for (argument, evals_) in log_lookups.iter().zip(log_lookup_evals.iter()) {
+            let (sum, sum_next, multiplicity) = (&evals_[0], &evals_[1], &evals_[2]);
+            let input = evals.compress(layouter, &argument.input_expressions, &theta, &beta)?;
+            let table = evals.compress(layouter, &argument.table_expressions, &theta, &beta)?;
+
+            // (\phi(\omega X) - \phi(X)) (A(X) + \beta) (S(X) + \beta)
+            // - m(X) (A(X) + \beta) + (S(X) + \beta)
+            let step = sum_next.sub(layouter, sum)?;
+            let input_table = input.mul(layouter, &table)?;
+            expressions.push(combine(
+                layouter,
+                &[(1, &step, &input_table), (-1, multiplicity, &input)],
+                &[(1, &table)],
+            )?);
+        }
+
+        for (argument, evals_) in cs.shuffles.iter().zip(shuffle_evals.iter()) {
+            let (product, product_next) = (&evals_[0], &evals_[1]);
+            let input = evals.compress(layouter, &argument.input_expressions, &theta, &gamma)?;
+            let shuffle =
+                evals.compress(layouter, &argument.shuffle_expressions, &theta, &gamma)?;
+
+            // l_0(X) (1 - z(X))
+            expressions.push(combine(layouter, &[(-1, &l_0, product)], &[(1, &l_0)])?);
+
+            // z(\omega X) (S(X) + \gamma) - z(X) (A(X) + \gamma)
+            expressions.push(combine(
+                layouter,
+                &[(1, product_next, &shuffle), (-1, product, &input)],
+                &[],
+            )?);
+        }
+
+        let mut expected = Term::Constant(C::Scalar::zero());
+        for expression in &expressions {
+            expected = expected.mul_add(layouter, &y, expression)?;
+        }
+
+        let mut h_eval = Term::Constant(C::Scalar::zero());
+        for eval in h_evals.iter().rev() {
+            h_eval = h_eval.mul_add(layouter, &xn, eval)?;
+        }
+        let vanishing = xn.sub(layouter, &one)?;
+        let h_eval = h_eval.mul(layouter, &vanishing)?;
+        reader.constrain_equal(layouter, &expected, &h_eval)?;
+    }
+
+    // The commitments that are opened, in the order of the queries of the proof.
+    let mut commitments: Vec<Point<C, VerifierChip>> = vec![];
+    let mut queries: Vec<Query<C, VerifierChip>> = vec![];
+    let mut commit = |commitment: Point<C, VerifierChip>| {
+        commitments.push(commitment);
+        commitments.len() - 1
+    };
+
+    let instance_indices: Vec<_> = instance_commitments
+        .iter()
+        .map(|commitment| commit(commitment.clone()))
+        .collect();
+    let advice_indices: Vec<_> = advice_commitments.into_iter().map(&mut commit).collect();
+    let mut permutation_indices = vec![];
+    for (products, commitments) in permutation_products
+        .into_iter()
+        .zip(key.permutations.iter())
+    {
+        let products: Vec<_> = products.into_iter().map(&mut commit).collect();
+        let polys: Vec<_> = commitments.iter().cloned().map(&mut commit).collect();
+        permutation_indices.push((products, polys));
+    }
+    let lookup_indices: Vec<_> = lookups_permuted
+        .into_iter()
+        .zip(lookup_products)
+        .map(|(permuted, product)| {
+            let mut permuted = permuted.into_iter();
+            let input = commit(permuted.next().unwrap());
+            let table = commit(permuted.next().unwrap());
+            (input, table, commit(product))
+        })
+        .collect();
+    let log_lookup_indices: Vec<_> = log_lookup_multiplicities
+        .into_iter()
+        .zip(log_lookup_sums)
+        .map(|(multiplicity, sum)| (commit(multiplicity), commit(sum)))
+        .collect();
+    let shuffle_indices: Vec<_> = shuffle_products.into_iter().map(&mut commit).collect();
+    let fixed_indices: Vec<_> = key.fixed.iter().cloned().map(&mut commit).collect();
+    let h_indices: Vec<_> = h_commitments.into_iter().map(&mut commit).collect();
+
+    for ((column, at), eval) in cs.instance_queries.iter().zip(instance_evals) {
+        queries.push((at.0, instance_indices[column.index()], eval));
+    }
+    for ((column, at), eval) in cs.advice_queries.iter().zip(advice_evals) {
+        queries.push((at.0, advice_indices[column.index()], eval));
+    }
+    for ((products, polys), (sets, poly_evals)) in permutation_indices.iter().zip(permutation_evals)
+    {
+        for (product, set) in products.iter().zip(sets) {
+            let mut set = set.into_iter();
+            queries.push((0, *product, set.next().unwrap()));
+            queries.push((-1, *product, set.next().unwrap()));
+        }
+        for (poly, eval) in polys.iter().zip(poly_evals) {
+            queries.push((0, *poly, eval));
+        }
+    }
+    for ((input, table, product), evals) in lookup_indices.iter().zip(lookup_evals) {
+        queries.push((0, *product, evals[0].clone()));
+        queries.push((0, *input, evals[2].clone()));
+        queries.push((0, *table, evals[4].clone()));
+        queries.push((-1, *input, evals[3].clone()));
+        queries.push((-1, *product, evals[1].clone()));
+    }
+    for ((multiplicity, sum), evals) in log_lookup_indices.iter().zip(log_lookup_evals) {
+        queries.push((0, *sum, evals[0].clone()));
+        queries.push((1, *sum, evals[1].clone()));
+        queries.push((0, *multiplicity, evals[2].clone()));
+    }
+    for (product, evals) in shuffle_indices.iter().zip(shuffle_evals) {
+        queries.push((0, *product, evals[0].clone()));
+        queries.push((1, *product, evals[1].clone()));
+    }
+    for ((column, at), eval) in cs.fixed_queries.iter().zip(fixed_evals) {
+        queries.push((at.0, fixed_indices[column.index()], eval));
+    }
+    for (h, eval) in h_indices.iter().zip(h_evals) {
+        queries.push((0, *h, eval));
+    }
+
+    let accumulator = verify_opening(
+        layouter,
+        params,
+        &mut reader,
+        &x,
+        |rotation| domain.rotate_omega(C::Scalar::one(), Rotation(rotation)),
+        &commitments,
+        &queries,
+    )?;
+    reader.finalize()?;
+
+    Ok(accumulator)
+}
@@ -0,0 +1,1251 @@
+//! Incrementally verifiable computation over a cycle of curves.
+//!
+//! A [`RecursionCircuit<C>`] is a circuit over `C::Base` whose proofs are over the
+//! other curve `O` of the cycle, the curve whose scalar field is `C::Base`, such as
+//! [`EqAffine`](crate::pasta::EqAffine) for `C` =
+//! [`EpAffine`](crate::pasta::EpAffine). It verifies proofs over `C`, which are proofs
+//! of circuits over `C::Scalar`, and so a `RecursionCircuit<C>` and a
+//! `RecursionCircuit<O>` verify each other's proofs. The two sides of the recursion
+//! alternate, each step of one side verifying the last proof of the other.
+//!
+//! Each side carries a [`RecursionState`]: the number of steps it has taken, the
+//! initial and current values of its computation, the key of the other side, and an
+//! accumulator over `C` for the checks that it defers. A step of a side:
+//!
+//! - verifies the last proof of the other side, whose public inputs are the digest of
+//!   the state that the step continues and the digest of the state of the other side;
+//! - verifies a proof of an application circuit over `C::Scalar`, if the side has one,
+//!   whose public inputs are the current values of the computation followed by the
+//!   next ones;
+//! - folds the accumulators of both proofs into that of the state; and
+//! - exposes the digest of the state of the other side and that of its next state.
+//!
+//! The first step of the side that starts the recursion has no proof of the other side
+//! to verify. It reads a dummy proof instead, whose checks are not enforced, and may
+//! only continue a state that has taken no steps and whose current values are its
+//! initial ones.
+//!
+//! The key of the other side depends on the key of this side, so it is witnessed and
+//! bound by the digest of the state rather than fixed in the circuit. The keys of both
+//! sides are found together by [`keygen`], and a proof of the last step is checked,
+//! along with the states and accumulators of both sides, by [`verify_recursion`].
+
+use std::io;
+
+use ff::Field;
+use group::Curve;
+use rand::RngCore;
+
+use super::{
+    accumulate,
+    circuit::{fold, verify},
+    combine,
+    plonk::{verify_proof_if, AssignedKey},
+    verify_proof, witness_challenge, Accumulator, Term, VerifierChip, VerifierConfig,
+    VerifierInstructions,
+};
+use crate::{
+    arithmetic::{best_multiexp, CurveAffine, FieldExt},
+    circuit::{layouter, Layouter},
+    gadget::{
+        ecc::Point,
+        msm::{msm, Scalar},
+        transcript::Transcript,
+    },
+    plonk::{keygen_vk, Assignment, Circuit, ConstraintSystem, Error, VerifyingKey},
+    poly::{
+        commitment::{self, read_opening_proof, Params},
+        Rotation,
+    },
+    primitives::poseidon::{P128Pow5T3, Spec},
+    transcript::{
+        self as host, Challenge, PoseidonWrite, Transcript as _, TranscriptRead, TranscriptWrite,
+    },
+};
+
+/// The maximum number of rounds of key generation that [`keygen`] runs before the
+/// keys of both sides stop changing.
+const KEYGEN_ROUNDS: usize = 4;
+
+/// Returns the digest and the commitments of `vk` that a [`RecursionState`] binds.
+fn key_values<C: CurveAffine>(vk: &VerifyingKey<C>) -> (C::Scalar, Vec<C>) {
+    let commitments = vk
+        .fixed_commitments()
+        .iter()
+        .chain(
+            vk.permutations()
+                .iter()
+                .flat_map(|vkey| vkey.commitments().iter()),
+        )
+        .cloned()
+        .collect();
+    (vk.digest(), commitments)
+}
+
+/// Returns `value` as a challenge, if it is less than $2^{128}$.
+fn challenge_of<F: FieldExt>(value: &F) -> Option<Challenge> {
+    let challenge = value.get_lower_128();
+    if F::from_u128(challenge) == *value {
+        Some(Challenge(challenge))
+    } else {
+        None
+    }
+}
+
+/// The state of one side of a recursion over a cycle of curves, after a number of
+/// steps.
+#[derive(Clone, Debug)]
+pub struct RecursionState<C: CurveAffine> {
+    step: u64,
+    initial: Vec<C::Scalar>,
+    current: Vec<C::Scalar>,
+    key: (C::Scalar, Vec<C>),
+    accumulator: commitment::Accumulator<C>,
+}
+
+impl<C: CurveAffine> RecursionState<C>
+where
+    P128Pow5T3: Spec<C::Base, 3, 2>,
+{
+    /// Returns the state of a side before its first step, whose computation starts
+    /// from `initial`, and which verifies proofs of the other side for `other`.
+    ///
+    /// The accumulator of the state is a valid accumulator for `params` that defers no
+    /// check.
+    pub fn new(params: &Params<C>, other: &VerifyingKey<C>, initial: Vec<C::Scalar>) -> Self {
+        RecursionState {
+            step: 0,
+            current: initial.clone(),
+            initial,
+            key: key_values(other),
+            accumulator: commitment::Accumulator::from_challenges(
+                params,
+                vec![Challenge(0); params.k as usize],
+            ),
+        }
+    }
+
+    /// The number of steps that the side has taken.
+    pub fn step(&self) -> u64 {
+        self.step
+    }
+
+    /// The values that the computation started from.
+    pub fn initial(&self) -> &[C::Scalar] {
+        &self.initial
+    }
+
+    /// The values of the computation after the steps that the side has taken.
+    pub fn current(&self) -> &[C::Scalar] {
+        &self.current
+    }
+
+    /// The accumulator of the checks that the side has deferred.
+    pub fn accumulator(&self) -> &commitment::Accumulator<C> {
+        &self.accumulator
+    }
+
+    /// Returns the digest of this state, which a proof of a step exposes.
+    ///
+    /// This is the two challenges squeezed from a [`PoseidonWrite`] transcript that
+    /// absorbs the number of steps, the initial and current values, the digest and
+    /// commitments of the key of the other side, and the accumulator, as
+    /// [`create_accumulation_proof`](commitment::create_accumulation_proof) absorbs it.
+    pub fn digest(&self) -> [Challenge; 2] {
+        let mut transcript = PoseidonWrite::<_, C>::init(io::sink());
+        // Only the identity cannot be absorbed, and the commitments of a key and the
+        // point of an accumulator are the identity with negligible probability.
+        (|| {
+            transcript.common_scalar(C::Scalar::from_u64(self.step))?;
+            for value in self.initial.iter().chain(self.current.iter()) {
+                transcript.common_scalar(*value)?;
+            }
+            transcript.common_scalar(self.key.0)?;
+            for commitment in &self.key.1 {
+                transcript.common_point(*commitment)?;
+            }
+            transcript.common_point(self.accumulator.g)?;
+            for challenge in &self.accumulator.challenges_packed {
+                transcript.common_scalar(C::Scalar::from_u128(challenge.0))?;
+            }
+            Ok::<_, io::Error>(())
+        })()
+        .expect("the state can be absorbed");
+        [
+            Challenge::get(&mut transcript),
+            Challenge::get(&mut transcript),
+        ]
+    }
+}
+
+/// Squeezes the digest of a state in a circuit, as [`RecursionState::digest`] computes
+/// it.
+#[allow(clippy::type_complexity)]
+fn assign_digest<C: CurveAffine, VerifierChip: VerifierInstructions<C>>(
+    mut layouter: impl Layouter<VerifierChip>,
+    step: &[VerifierChip::Word; 2],
+    values: &[&(VerifierChip::Element, [VerifierChip::Word; 2])],
+    key: &AssignedKey<C, VerifierChip>,
+    accumulator: &Accumulator<C, VerifierChip>,
+) -> Result<[VerifierChip::Challenge; 2], Error> {
+    let mut transcript = Transcript::new(layouter.namespace(|| "transcript"))?;
+    transcript.absorb_words(layouter.namespace(|| "step"), step)?;
+    for (_, words) in values {
+        transcript.absorb_words(layouter.namespace(|| "value"), words)?;
+    }
+    transcript.absorb_words(layouter.namespace(|| "key"), &key.digest)?;
+    for commitment in key.fixed.iter().chain(key.permutations.iter().flatten()) {
+        transcript.common_point(layouter.namespace(|| "key"), commitment)?;
+    }
+    transcript.common_point(layouter.namespace(|| "G"), accumulator.g())?;
+    for challenge in accumulator.challenges() {
+        let words = VerifierChip::challenge_words(&mut layouter, challenge)?;
+        transcript.absorb_words(layouter.namespace(|| "challenge"), &words)?;
+    }
+    Ok([
+        transcript.squeeze_challenge(layouter.namespace(|| "digest"))?,
+        transcript.squeeze_challenge(layouter.namespace(|| "digest"))?,
+    ])
+}
+
+/// Returns the commitment to an instance column with the values `instances`, as
+/// [`VerifyingKey::commit_instances`] computes it.
+fn commit_instances<C: CurveAffine, VerifierChip: VerifierInstructions<C>>(
+    layouter: &mut impl Layouter<VerifierChip>,
+    params: &Params<C>,
+    instances: &[VerifierChip::Element],
+) -> Result<Point<C, VerifierChip>, Error> {
+    let blind = VerifierChip::constant_point(layouter, params.h).map(Point::from_inner)?;
+    if instances.is_empty() {
+        return Ok(blind);
+    }
+    let terms = instances
+        .iter()
+        .zip(params.g_lagrange.iter())
+        .map(|(instance, base)| {
+            let bits = VerifierChip::scalar_bits(layouter, instance)?;
+            let base = VerifierChip::constant_point(layouter, *base).map(Point::from_inner)?;
+            Ok((Scalar::from_bits(bits), base))
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+    let sum = msm(
+        layouter.namespace(|| "instances"),
+        &terms
+            .iter()
+            .map(|(scalar, base)| (scalar, base))
+            .collect::<Vec<_>>(),
+    )?;
+    sum.add(layouter.namespace(|| "blind"), &blind)
+}
+
+/// A transcript that writes a random message for each message that is read from it.
+///
+/// Running a verifier on it writes a proof of the shape that the verifier reads, whose
+/// messages are valid encodings and whose challenges are those of the transcript.
+struct Fabricated<C: CurveAffine, R: RngCore>
+where
+    P128Pow5T3: Spec<C::Base, 3, 2>,
+{
+    transcript: PoseidonWrite<Vec<u8>, C>,
+    base: C,
+    rng: R,
+}
+
+impl<C: CurveAffine, R: RngCore> host::Transcript<C> for Fabricated<C, R>
+where
+    P128Pow5T3: Spec<C::Base, 3, 2>,
+{
+    fn squeeze_challenge(&mut self) -> C::Base {
+        self.transcript.squeeze_challenge()
+    }
+
+    fn common_point(&mut self, point: C) -> io::Result<()> {
+        self.transcript.common_point(point)
+    }
+
+    fn common_scalar(&mut self, scalar: C::Scalar) -> io::Result<()> {
+        self.transcript.common_scalar(scalar)
+    }
+}
+
+impl<C: CurveAffine, R: RngCore> TranscriptRead<C> for Fabricated<C, R>
+where
+    P128Pow5T3: Spec<C::Base, 3, 2>,
+{
+    fn read_point(&mut self) -> io::Result<C> {
+        let scalar = C::Scalar::random(&mut self.rng);
+        let point = best_multiexp(&[scalar], &[self.base]).to_affine();
+        self.transcript.write_point(point)?;
+        Ok(point)
+    }
+
+    fn read_scalar(&mut self) -> io::Result<C::Scalar> {
+        let scalar = C::Scalar::random(&mut self.rng);
+        self.transcript.write_scalar(scalar)?;
+        Ok(scalar)
+    }
+}
+
+/// Returns a proof for `vk` with the given instance commitment whose messages are
+/// random, and the accumulator that the [`verify_proof`] gadget computes from it.
+///
+/// The proof does not verify; it only has the shape of a proof for `vk`, so that the
+/// base case of a [`RecursionCircuit`] can read it without enforcing its checks.
+fn dummy_proof<C: CurveAffine>(
+    params: &Params<C>,
+    vk: &VerifyingKey<C>,
+    instance_commitment: C,
+    rng: impl RngCore,
+) -> Result<(Vec<u8>, commitment::Accumulator<C>), Error>
+where
+    P128Pow5T3: Spec<C::Base, 3, 2>,
+{
+    let mut transcript = Fabricated {
+        transcript: PoseidonWrite::init(vec![]),
+        base: params.h,
+        rng,
+    };
+
+    // The verifier reads every message before the multi-opening argument, and then
+    // fails the check of the vanishing argument, which random messages satisfy with
+    // negligible probability.
+    match crate::plonk::verify_proof(
+        params,
+        vk,
+        params.empty_msm(),
+        &[&[instance_commitment]],
+        &mut transcript,
+    ) {
+        Err(Error::ConstraintSystemFailure) => (),
+        Err(error) => return Err(error),
+        Ok(_) => return Err(Error::SynthesisError),
+    }
+
+    // The multi-opening argument: x_1, x_2, f, x_3, an evaluation for each set of
+    // points, x_4, and the opening proof.
+    let messages = (|| {
+        Challenge::get(&mut transcript);
+        Challenge::get(&mut transcript);
+        transcript.read_point()?;
+        Challenge::get(&mut transcript);
+        for _ in 0..vk.opening_point_sets() {
+            transcript.read_scalar()?;
+        }
+        Challenge::get(&mut transcript);
+        Ok::<_, io::Error>(())
+    })();
+    messages.map_err(|_| Error::TranscriptError)?;
+    let opening = read_opening_proof(params, &mut transcript).map_err(|_| Error::OpeningError)?;
+    let challenges = opening
+        .rounds
+        .iter()
+        .map(|(_, _, challenge)| *challenge)
+        .collect();
+
+    Ok((
+        transcript.transcript.finalize(),
+        commitment::Accumulator::from_challenges(params, challenges),
+    ))
+}
+
+/// A proof of a circuit over `C::Scalar`, with the values of its instance column.
+#[derive(Clone, Debug)]
+pub struct StepProof<C: CurveAffine> {
+    /// The values of the instance column of the circuit, from its first row.
+    pub instances: Vec<C::Scalar>,
+    /// The proof, created by [`create_proof`](crate::plonk::create_proof) with a
+    /// [`PoseidonWrite`] transcript.
+    pub proof: Vec<u8>,
+}
+
+/// The proof of the other side of a recursion that a step of a [`RecursionCircuit`]
+/// verifies.
+#[derive(Clone, Debug)]
+pub enum Previous<C: CurveAffine> {
+    /// No proof, for the first step of the side that starts the recursion, with the
+    /// digest of the initial state of the other side.
+    Base([Challenge; 2]),
+    /// The proof of the last step of the other side, whose public inputs are the
+    /// [`RecursionCircuit::public_inputs`] of that step.
+    Proof(StepProof<C>),
+}
+
+/// The witnesses of a step of a [`RecursionCircuit`].
+#[derive(Clone, Debug)]
+struct Witness<C: CurveAffine> {
+    state: RecursionState<C>,
+    base: bool,
+    other_digest: [Challenge; 2],
+    proof: Vec<u8>,
+    app_proof: Option<StepProof<C>>,
+    accumulation_proof: Vec<u8>,
+    next: RecursionState<C>,
+}
+
+/// A circuit over `C::Base` for a step of one side of a recursion over a cycle of
+/// curves.
+///
+/// `other` is the key of the other side, a `RecursionCircuit` over `C::Scalar` whose
+/// proofs are over `C`, and `app` is the key of the application circuit over
+/// `C::Scalar` whose proofs a step verifies, if the side has one. The computation of
+/// the side has `width` values.
+///
+/// The public inputs of the circuit are the digest of the state of the other side,
+/// followed by the digest of the next state of this side.
+#[derive(Debug)]
+pub struct RecursionCircuit<'a, C: CurveAffine> {
+    params: &'a Params<C>,
+    other: &'a VerifyingKey<C>,
+    app: Option<&'a VerifyingKey<C>>,
+    width: usize,
+    witness: Option<Witness<C>>,
+}
+
+impl<'a, C: CurveAffine> RecursionCircuit<'a, C>
+where
+    P128Pow5T3: Spec<C::Base, 3, 2>,
+{
+    /// Returns the circuit for a side without witnesses, such as for key generation.
+    ///
+    /// The values of `other` are not used, only the shape of its circuit, so this may
+    /// be the key of a circuit with the same constraint system as the other side.
+    pub fn empty(
+        params: &'a Params<C>,
+        other: &'a VerifyingKey<C>,
+        app: Option<&'a VerifyingKey<C>>,
+        width: usize,
+    ) -> Self {
+        RecursionCircuit {
+            params,
+            other,
+            app,
+            width,
+            witness: None,
+        }
+    }
+
+    /// Returns the circuit for the step of a side that continues `state`, verifying
+    /// `previous` and, if the side has an application circuit, `app_proof`.
+    ///
+    /// The proofs are verified first, and the proof that folds their accumulators is
+    /// created with randomness from `rng`, which also samples the dummy proof of the
+    /// base case.
+    ///
+    /// Returns [`Error::IncompatibleParams`] if `state` is not for `other`, if
+    /// `previous` is not for `state` or is [`Previous::Base`] for a state that has
+    /// taken steps, or if `app_proof` is not given for `app` or does not start from the
+    /// current values of `state`, and the error of verification if a proof does not
+    /// verify.
+    pub fn new(
+        params: &'a Params<C>,
+        other: &'a VerifyingKey<C>,
+        app: Option<&'a VerifyingKey<C>>,
+        state: RecursionState<C>,
+        previous: Previous<C>,
+        app_proof: Option<StepProof<C>>,
+        mut rng: impl RngCore,
+    ) -> Result<Self, Error> {
+        let width = state.current.len();
+        if state.key != key_values(other) || other.cs().num_instance_columns != 1 {
+            return Err(Error::IncompatibleParams);
+        }
+
+        let digest = state.digest();
+        let instances = |other_digest: &[Challenge; 2]| -> Vec<C::Scalar> {
+            digest
+                .iter()
+                .chain(other_digest.iter())
+                .map(|challenge| C::Scalar::from_u128(challenge.0))
+                .collect()
+        };
+        let (base, other_digest, proof, other_accumulator) = match previous {
+            Previous::Base(other_digest) => {
+                if state.step != 0 || state.current != state.initial {
+                    return Err(Error::IncompatibleParams);
+                }
+                let commitment = other.commit_instances(params, &[&instances(&other_digest)])?;
+                let (proof, accumulator) = dummy_proof(params, other, commitment[0], &mut rng)?;
+                (true, other_digest, proof, accumulator)
+            }
+            Previous::Proof(proof) => {
+                let other_digest = match proof.instances.as_slice() {
+                    [_, _, d_0, d_1] => [challenge_of(d_0), challenge_of(d_1)],
+                    _ => return Err(Error::IncompatibleParams),
+                };
+                let other_digest = match other_digest {
+                    [Some(d_0), Some(d_1)] => [d_0, d_1],
+                    _ => return Err(Error::IncompatibleParams),
+                };
+                if proof.instances != instances(&other_digest) {
+                    return Err(Error::IncompatibleParams);
+                }
+                let commitments = other.commit_instances(params, &[&proof.instances])?;
+                let accumulator = verify(params, other, &commitments, &proof.proof)?;
+                (false, other_digest, proof.proof, accumulator)
+            }
+        };
+
+        let mut accumulators = vec![state.accumulator.clone(), other_accumulator];
+        let mut current = state.current.clone();
+        match (app, &app_proof) {
+            (Some(app), Some(app_proof)) => {
+                if app_proof.instances.len() != 2 * width
+                    || app_proof.instances[..width] != state.current[..]
+                {
+                    return Err(Error::IncompatibleParams);
+                }
+                let commitments = app.commit_instances(params, &[&app_proof.instances])?;
+                accumulators.push(verify(params, app, &commitments, &app_proof.proof)?);
+                current = app_proof.instances[width..].to_vec();
+            }
+            (None, None) => (),
+            _ => return Err(Error::IncompatibleParams),
+        }
+        let (accumulation_proof, accumulator) = fold(params, &accumulators, rng)?;
+
+        let next = RecursionState {
+            step: state.step + 1,
+            initial: state.initial.clone(),
+            current,
+            key: state.key.clone(),
+            accumulator,
+        };
+        Ok(RecursionCircuit {
+            params,
+            other,
+            app,
+            width,
+            witness: Some(Witness {
+                state,
+                base,
+                other_digest,
+                proof,
+                app_proof,
+                accumulation_proof,
+                next,
+            }),
+        })
+    }
+
+    /// Returns the public inputs of this circuit, or `None` if it has no witnesses.
+    pub fn public_inputs(&self) -> Option<Vec<C::Base>> {
+        let witness = self.witness.as_ref()?;
+        Some(public_inputs(&witness.other_digest, &witness.next.digest()))
+    }
+
+    /// Returns the state of the side after this step, or `None` if the circuit has no
+    /// witnesses.
+    pub fn next_state(&self) -> Option<&RecursionState<C>> {
+        self.witness.as_ref().map(|witness| &witness.next)
+    }
+}
+
+/// Returns the public inputs of a step of a [`RecursionCircuit`] for the given digests
+/// of the state of the other side and of the next state of the side.
+fn public_inputs<F: FieldExt>(other: &[Challenge; 2], next: &[Challenge; 2]) -> Vec<F> {
+    other
+        .iter()
+        .chain(next.iter())
+        .map(|challenge| F::from_u128(challenge.0))
+        .collect()
+}
+
+impl<'a, C: CurveAffine> Circuit<C::Base> for RecursionCircuit<'a, C>
+where
+    P128Pow5T3: Spec<C::Base, 3, 2>,
+{
+    type Config = VerifierConfig<C::Base>;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn configure(meta: &mut ConstraintSystem<C::Base>) -> VerifierConfig<C::Base> {
+        VerifierChip::<C>::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        cs: &mut impl Assignment<C::Base>,
+        config: VerifierConfig<C::Base>,
+    ) -> Result<(), Error> {
+        let mut layouter = layouter::SingleChip::<VerifierChip<C>, _>::new(cs, config)?;
+        let layouter = &mut layouter;
+        let witness = self.witness.as_ref();
+        let state = witness.map(|witness| &witness.state);
+
+        // The state that the step continues.
+        let step = VerifierChip::witness_scalar(
+            layouter,
+            state.map(|state| C::Scalar::from_u64(state.step)),
+        )?;
+        let initial = (0..self.width)
+            .map(|i| VerifierChip::witness_scalar(layouter, state.map(|state| state.initial[i])))
+            .collect::<Result<Vec<_>, Error>>()?;
+        let current = (0..self.width)
+            .map(|i| VerifierChip::witness_scalar(layouter, state.map(|state| state.current[i])))
+            .collect::<Result<Vec<_>, Error>>()?;
+        let key = AssignedKey::witness(&mut layouter.namespace(|| "key"), self.other)?;
+        let accumulator = Accumulator::witness(
+            layouter.namespace(|| "accumulator"),
+            self.params,
+            state.map(|state| &state.accumulator),
+        )?;
+        let digest = assign_digest(
+            layouter.namespace(|| "digest"),
+            &step.1,
+            &initial.iter().chain(current.iter()).collect::<Vec<_>>(),
+            &key,
+            &accumulator,
+        )?;
+
+        // The checks of the previous proof are enforced unless the step continues a
+        // state that has taken no steps and whose current values are its initial ones.
+        let zero = Term::Constant(C::Scalar::zero());
+        let enforce = VerifierChip::witness_scalar(
+            layouter,
+            witness.map(|witness| {
+                if witness.base {
+                    C::Scalar::zero()
+                } else {
+                    C::Scalar::one()
+                }
+            }),
+        )?;
+        let enforce = Term::Element(enforce.0);
+        // e^2 - e = 0
+        combine(layouter, &[(1, &enforce, &enforce)], &[(-1, &enforce)])?
+            .constrain_equal(layouter, &zero)?;
+        let unless_enforced = |layouter: &mut _, value: Term<C, VerifierChip<C>>| {
+            // (1 - e) v = 0
+            combine(layouter, &[(-1, &enforce, &value)], &[(1, &value)])?
+                .constrain_equal(layouter, &zero)
+        };
+        unless_enforced(layouter, Term::Element(step.0))?;
+        for (initial, current) in initial.iter().zip(current.iter()) {
+            let difference = Term::Element(current.0).sub(layouter, &Term::Element(initial.0))?;
+            unless_enforced(layouter, difference)?;
+        }
+
+        // The previous proof, whose public inputs are the digest of the state and that
+        // of the other side.
+        let other_digest = (0..2)
+            .map(|i| witness_challenge(layouter, witness.map(|witness| witness.other_digest[i])))
+            .collect::<Result<Vec<_>, Error>>()?;
+        let instances = digest
+            .iter()
+            .chain(other_digest.iter())
+            .map(|challenge| VerifierChip::challenge_element(layouter, challenge))
+            .collect::<Result<Vec<_>, Error>>()?;
+        let commitment = commit_instances(layouter, self.params, &instances)?;
+        let other_accumulator = verify_proof_if(
+            layouter.namespace(|| "previous proof"),
+            self.params,
+            self.other,
+            &key,
+            &[commitment],
+            witness.map(|witness| &witness.proof[..]),
+            Some(enforce.clone()),
+        )?;
+
+        // The proof of the application circuit, from the current values to the next.
+        let mut accumulators = vec![accumulator, other_accumulator];
+        let next = match self.app {
+            Some(app) => {
+                let app_proof = witness.and_then(|witness| witness.app_proof.as_ref());
+                let next = (0..self.width)
+                    .map(|i| {
+                        VerifierChip::witness_scalar(
+                            layouter,
+                            app_proof.map(|proof| proof.instances[self.width + i]),
+                        )
+                    })
+                    .collect::<Result<Vec<_>, Error>>()?;
+                let instances: Vec<_> = current
+                    .iter()
+                    .chain(next.iter())
+                    .map(|(element, _)| *element)
+                    .collect();
+                let commitment = commit_instances(layouter, self.params, &instances)?;
+                accumulators.push(verify_proof(
+                    layouter.namespace(|| "application proof"),
+                    self.params,
+                    app,
+                    &[commitment],
+                    app_proof.map(|proof| &proof.proof[..]),
+                )?);
+                next
+            }
+            None => current,
+        };
+
+        let accumulator = accumulate(
+            layouter.namespace(|| "accumulate"),
+            self.params,
+            &accumulators,
+            witness.map(|witness| &witness.accumulation_proof[..]),
+        )?;
+
+        // The next state.
+        let next_step = VerifierChip::witness_scalar(
+            layouter,
+            witness.map(|witness| C::Scalar::from_u64(witness.next.step)),
+        )?;
+        Term::Element(step.0)
+            .add(layouter, &Term::one())?
+            .constrain_equal(layouter, &Term::Element(next_step.0))?;
+        let next_digest = assign_digest(
+            layouter.namespace(|| "next digest"),
+            &next_step.1,
+            &initial.iter().chain(next.iter()).collect::<Vec<_>>(),
+            &key,
+            &accumulator,
+        )?;
+
+        for challenge in other_digest.iter().chain(next_digest.iter()) {
+            VerifierChip::expose_challenge(layouter, challenge)?;
+        }
+        Ok(())
+    }
+}
+
+/// A circuit over `F` with a single instance column, which it constrains to equal an
+/// advice column, whose key has a shape to start [`keygen`] from.
+#[derive(Debug, Default)]
+struct Bootstrap;
+
+impl<F: FieldExt> Circuit<F> for Bootstrap {
+    type Config = ();
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn configure(meta: &mut ConstraintSystem<F>) {
+        let advice = meta.advice_column();
+        let instance = meta.instance_column();
+        meta.create_gate("instance", |meta| {
+            meta.query_advice(advice, Rotation::cur())
+                - meta.query_instance(instance, Rotation::cur())
+        });
+    }
+
+    fn synthesize(&self, _: &mut impl Assignment<F>, _: ()) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Returns the verifying keys of the two sides of a recursion over the cycle of `C`
+/// and `O`: that of the [`RecursionCircuit<C>`] over `C::Base` with `params_o`, and
+/// that of the [`RecursionCircuit<O>`] over `O::Base` with `params_c`.
+///
+/// Each side verifies proofs of the other, with `params_c` and `params_o`
+/// respectively, and proofs of its application circuit with the given key and the
+/// given number of values, if it has one.
+///
+/// The constraint system of each side depends on the shape of the circuit of the
+/// other, so the keys are generated in turn until they stop changing. Returns
+/// [`Error::NotEnoughRowsAvailable`] if a circuit does not fit its parameters, and
+/// [`Error::SynthesisError`] if the keys do not settle.
+pub fn keygen<C, O>(
+    params_c: &Params<C>,
+    params_o: &Params<O>,
+    app_c: Option<&VerifyingKey<C>>,
+    width_c: usize,
+    app_o: Option<&VerifyingKey<O>>,
+    width_o: usize,
+) -> Result<(VerifyingKey<O>, VerifyingKey<C>), Error>
+where
+    C: CurveAffine,
+    O: CurveAffine<Base = C::ScalarExt, ScalarExt = C::Base>,
+    P128Pow5T3: Spec<C::Base, 3, 2> + Spec<O::Base, 3, 2>,
+{
+    let mut vk_c = keygen_vk(params_c, &Bootstrap)?;
+    let mut vk_o = keygen_vk(
+        params_o,
+        &RecursionCircuit::empty(params_c, &vk_c, app_c, width_c),
+    )?;
+    for _ in 0..KEYGEN_ROUNDS {
+        vk_c = keygen_vk(
+            params_c,
+            &RecursionCircuit::empty(params_o, &vk_o, app_o, width_o),
+        )?;
+        let next = keygen_vk(
+            params_o,
+            &RecursionCircuit::empty(params_c, &vk_c, app_c, width_c),
+        )?;
+        if next.digest() == vk_o.digest() {
+            return Ok((next, vk_c));
+        }
+        vk_o = next;
+    }
+    Err(Error::SynthesisError)
+}
+
+/// Checks `proof`, a proof of the last step of the side of a recursion that is a
+/// [`RecursionCircuit<C>`] with key `vk`, whose state after the step is `state`, when
+/// the state of the other side, a [`RecursionCircuit<O>`] with key `other_vk`, is
+/// `other_state`.
+///
+/// This checks that the proof verifies for the digests of both states, that each
+/// state is for the key of the other side, and that the accumulators of both states
+/// and of the proof are valid, which together check every step of both sides.
+///
+/// Returns [`Error::IncompatibleParams`] if a state is not for the key of the other
+/// side, and [`Error::OpeningError`] if an accumulator is not valid.
+pub fn verify_recursion<C, O>(
+    params_c: &Params<C>,
+    params_o: &Params<O>,
+    vk: &VerifyingKey<O>,
+    other_vk: &VerifyingKey<C>,
+    proof: &[u8],
+    state: &RecursionState<C>,
+    other_state: &RecursionState<O>,
+) -> Result<(), Error>
+where
+    C: CurveAffine,
+    O: CurveAffine<Base = C::ScalarExt, ScalarExt = C::Base>,
+    P128Pow5T3: Spec<C::Base, 3, 2> + Spec<O::Base, 3, 2>,
+{
+    if state.key != key_values(other_vk) || other_state.key != key_values(vk) {
+        return Err(Error::IncompatibleParams);
+    }
+
+    let instances = public_inputs(&other_state.digest(), &state.digest());
+    let commitments = vk.commit_instances(params_o, &[&instances])?;
+    let accumulator = verify(params_o, vk, &commitments, proof)?;
+
+    if is_valid(params_o, &accumulator)
+        && is_valid(params_o, &other_state.accumulator)
+        && is_valid(params_c, &state.accumulator)
+    {
+        Ok(())
+    } else {
+        Err(Error::OpeningError)
+    }
+}
+
+/// Returns whether the $G$ of `accumulator` is computed from its challenges.
+fn is_valid<C: CurveAffine>(params: &Params<C>, accumulator: &commitment::Accumulator<C>) -> bool {
+    commitment::Accumulator::from_challenges(params, accumulator.challenges_packed.clone()).g
+        == accumulator.g
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::OsRng;
+
+    use group::Curve;
+
+    use super::{
+        keygen, verify_recursion, Previous, RecursionCircuit, RecursionState, StepProof, Witness,
+    };
+    use crate::{
+        arithmetic::{CurveAffine, FieldExt},
+        dev::MockProver,
+        pasta::{pallas, vesta, EpAffine, EqAffine},
+        plonk::{
+            create_proof, keygen_pk, keygen_vk, Advice, Assignment, Circuit, Column,
+            ConstraintSystem, Error, Selector, VerifyingKey,
+        },
+        poly::{
+            commitment::{self, Params},
+            Rotation,
+        },
+        primitives::poseidon::{P128Pow5T3, Spec},
+        transcript::{Challenge, PoseidonWrite},
+    };
+
+    const K: u32 = 4;
+    const RECURSION_K: u32 = 17;
+
+    /// A circuit over $\mathbb{F}_q$ that exposes its witnesses, standing in for the
+    /// other side of a recursion.
+    #[derive(Clone, Default)]
+    struct PublicCircuit {
+        values: Option<[pallas::Scalar; 4]>,
+    }
+
+    #[derive(Clone, Debug)]
+    struct PublicConfig {
+        a: Column<Advice>,
+        s_public: Selector,
+        s_square: Selector,
+    }
+
+    /// Configures an advice column whose cells may be constrained to equal the instance
+    /// column, or the square of the cell above them.
+    fn configure(meta: &mut ConstraintSystem<pallas::Scalar>) -> PublicConfig {
+        let a = meta.advice_column();
+        let instance = meta.instance_column();
+        let s_public = meta.selector();
+        let s_square = meta.selector();
+
+        meta.create_gate("public", |meta| {
+            let a = meta.query_advice(a, Rotation::cur());
+            let instance = meta.query_instance(instance, Rotation::cur());
+            let s_public = meta.query_selector(s_public, Rotation::cur());
+            s_public * (a - instance)
+        });
+        meta.create_gate("square", |meta| {
+            let a_prev = meta.query_advice(a, Rotation::prev());
+            let a = meta.query_advice(a, Rotation::cur());
+            let s_square = meta.query_selector(s_square, Rotation::cur());
+            s_square * (a_prev.clone() * a_prev - a)
+        });
+
+        PublicConfig {
+            a,
+            s_public,
+            s_square,
+        }
+    }
+
+    impl Circuit<pallas::Scalar> for PublicCircuit {
+        type Config = PublicConfig;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<pallas::Scalar>) -> PublicConfig {
+            configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<pallas::Scalar>,
+            config: PublicConfig,
+        ) -> Result<(), Error> {
+            for row in 0..4 {
+                let value = self.values.map(|values| values[row]);
+                cs.enable_selector(|| "public", &config.s_public, row)?;
+                cs.assign_advice(|| "a", config.a, row, || value.ok_or(Error::SynthesisError))?;
+            }
+            Ok(())
+        }
+    }
+
+    /// An application circuit over $\mathbb{F}_q$ that squares the current value of a
+    /// computation.
+    #[derive(Clone, Default)]
+    struct SquareCircuit {
+        z: Option<pallas::Scalar>,
+    }
+
+    impl Circuit<pallas::Scalar> for SquareCircuit {
+        type Config = PublicConfig;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<pallas::Scalar>) -> PublicConfig {
+            configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<pallas::Scalar>,
+            config: PublicConfig,
+        ) -> Result<(), Error> {
+            for (row, value) in [self.z, self.z.map(|z| z.square())].iter().enumerate() {
+                cs.enable_selector(|| "public", &config.s_public, row)?;
+                cs.assign_advice(|| "a", config.a, row, || value.ok_or(Error::SynthesisError))?;
+            }
+            cs.enable_selector(|| "square", &config.s_square, 1)
+        }
+    }
+
+    /// Creates a proof of `circuit` with a Poseidon transcript.
+    fn prove<ConcreteCircuit: Circuit<pallas::Scalar>>(
+        params: &Params<EpAffine>,
+        circuit: ConcreteCircuit,
+        instances: Vec<pallas::Scalar>,
+    ) -> StepProof<EpAffine> {
+        let vk = keygen_vk(params, &circuit).unwrap();
+        let columns = vk.instance_values(&[&instances]).unwrap();
+        let pk = keygen_pk(params, vk, &circuit).unwrap();
+        let mut transcript = PoseidonWrite::<_, EpAffine>::init(vec![]);
+        create_proof(params, &pk, &[circuit], &[&columns], OsRng, &mut transcript).unwrap();
+        StepProof {
+            instances,
+            proof: transcript.finalize(),
+        }
+    }
+
+    /// Returns the proof of the other side for the step that continues `state`, whose
+    /// other side has the state with digest `other`.
+    fn prove_previous(
+        params: &Params<EpAffine>,
+        state: &RecursionState<EpAffine>,
+        other: [Challenge; 2],
+    ) -> StepProof<EpAffine> {
+        let digest = state.digest();
+        let mut values = [pallas::Scalar::zero(); 4];
+        for (value, challenge) in values.iter_mut().zip(digest.iter().chain(other.iter())) {
+            *value = pallas::Scalar::from_u128(challenge.0);
+        }
+        prove(
+            params,
+            PublicCircuit {
+                values: Some(values),
+            },
+            values.to_vec(),
+        )
+    }
+
+    fn prove_square(params: &Params<EpAffine>, z: pallas::Scalar) -> StepProof<EpAffine> {
+        prove(params, SquareCircuit { z: Some(z) }, vec![z, z.square()])
+    }
+
+    fn run(circuit: &RecursionCircuit<'_, EpAffine>) -> Result<(), ()> {
+        let mut instance = circuit.public_inputs().unwrap();
+        instance.resize(1 << RECURSION_K, pallas::Base::zero());
+        let prover = MockProver::run(RECURSION_K, circuit, vec![instance]).unwrap();
+        prover.verify().map_err(|_| ())
+    }
+
+    #[test]
+    fn verifies_steps_of_a_recursion() {
+        let params = Params::<EpAffine>::new(K);
+        let other_vk: VerifyingKey<EpAffine> =
+            keygen_vk(&params, &PublicCircuit::default()).unwrap();
+        let app_vk = keygen_vk(&params, &SquareCircuit::default()).unwrap();
+        let other_digest = [Challenge(1), Challenge(2)];
+
+        // The first step reads a dummy proof of the other side.
+        let z = pallas::Scalar::from_u64(3);
+        let state = RecursionState::new(&params, &other_vk, vec![z]);
+        let base = RecursionCircuit::new(
+            &params,
+            &other_vk,
+            Some(&app_vk),
+            state.clone(),
+            Previous::Base(other_digest),
+            Some(prove_square(&params, z)),
+            OsRng,
+        )
+        .unwrap();
+        assert_eq!(run(&base), Ok(()));
+
+        let state = base.next_state().unwrap().clone();
+        assert_eq!(state.step(), 1);
+        assert_eq!(state.initial(), &[z]);
+        assert_eq!(state.current(), &[z.square()]);
+
+        // Only a state that has taken no steps may be continued without a proof.
+        assert!(RecursionCircuit::new(
+            &params,
+            &other_vk,
+            Some(&app_vk),
+            state.clone(),
+            Previous::Base(other_digest),
+            Some(prove_square(&params, z.square())),
+            OsRng,
+        )
+        .is_err());
+
+        // The next step verifies the proof of the other side for the digest of the
+        // state.
+        let step = RecursionCircuit::new(
+            &params,
+            &other_vk,
+            Some(&app_vk),
+            state.clone(),
+            Previous::Proof(prove_previous(&params, &state, other_digest)),
+            Some(prove_square(&params, z.square())),
+            OsRng,
+        )
+        .unwrap();
+        assert_eq!(run(&step), Ok(()));
+        assert_eq!(step.next_state().unwrap().step(), 2);
+
+        // A proof for another state is rejected, both when the circuit is built and by
+        // the circuit itself.
+        let mut wrong_state = state.clone();
+        wrong_state.current = vec![z];
+        let wrong = prove_previous(&params, &wrong_state, other_digest);
+        assert!(RecursionCircuit::new(
+            &params,
+            &other_vk,
+            Some(&app_vk),
+            state,
+            Previous::Proof(wrong.clone()),
+            Some(prove_square(&params, z.square())),
+            OsRng,
+        )
+        .is_err());
+
+        let witness = step.witness.clone().unwrap();
+        let circuit = RecursionCircuit {
+            witness: Some(Witness {
+                proof: wrong.proof,
+                ..witness.clone()
+            }),
+            ..step
+        };
+        assert_eq!(run(&circuit), Err(()));
+
+        // The checks of the proof are enforced after the first step.
+        let circuit = RecursionCircuit {
+            witness: Some(Witness {
+                base: true,
+                ..witness
+            }),
+            ..circuit
+        };
+        assert_eq!(run(&circuit), Err(()));
+    }
+
+    /// Creates a proof of a step of a side of a recursion, over the other curve `O`.
+    fn prove_step<C, O>(
+        params: &Params<O>,
+        vk: &VerifyingKey<O>,
+        circuit: RecursionCircuit<'_, C>,
+    ) -> StepProof<O>
+    where
+        C: CurveAffine,
+        O: CurveAffine<Base = C::ScalarExt, ScalarExt = C::Base>,
+        P128Pow5T3: Spec<C::Base, 3, 2> + Spec<O::Base, 3, 2>,
+    {
+        let instances = circuit.public_inputs().unwrap();
+        let columns = vk.instance_values(&[&instances]).unwrap();
+        // The key of the side, generated again from the circuit with its witnesses, is
+        // the one that keygen found.
+        let step_vk = keygen_vk(params, &circuit).unwrap();
+        assert_eq!(step_vk.digest(), vk.digest());
+        let pk = keygen_pk(params, step_vk, &circuit).unwrap();
+        let mut transcript = PoseidonWrite::<_, O>::init(vec![]);
+        create_proof(params, &pk, &[circuit], &[&columns], OsRng, &mut transcript).unwrap();
+        StepProof {
+            instances,
+            proof: transcript.finalize(),
+        }
+    }
+
+    // Generating the keys of both sides and proving their steps takes minutes, even with
+    // optimizations, and more than 6 GiB of memory.
+    #[test]
+    #[ignore = "slow and memory-hungry; run with --release -- --ignored"]
+    fn proves_steps_of_a_recursion() {
+        let params_pallas = Params::<EpAffine>::new(RECURSION_K);
+        let params_vesta = Params::<EqAffine>::new(RECURSION_K);
+        // The side over Fp, whose proofs are over Vesta, verifies proofs over Pallas, and
+        // the side over Fq the other way around.
+        let (vk_fp, vk_fq) = keygen(&params_pallas, &params_vesta, None, 1, None, 1).unwrap();
+
+        // The side over Fp starts the recursion, reading a dummy proof of the other side.
+        let state_fp =
+            RecursionState::new(&params_pallas, &vk_fq, vec![pallas::Scalar::from_u64(3)]);
+        let state_fq = RecursionState::new(&params_vesta, &vk_fp, vec![vesta::Scalar::from_u64(5)]);
+        let circuit = RecursionCircuit::new(
+            &params_pallas,
+            &vk_fq,
+            None,
+            state_fp.clone(),
+            Previous::Base(state_fq.digest()),
+            None,
+            OsRng,
+        )
+        .unwrap();
+        let state_fp = circuit.next_state().unwrap().clone();
+        let proof_fp = prove_step(&params_vesta, &vk_fp, circuit);
+        verify_recursion(
+            &params_pallas,
+            &params_vesta,
+            &vk_fp,
+            &vk_fq,
+            &proof_fp.proof,
+            &state_fp,
+            &state_fq,
+        )
+        .unwrap();
+
+        // Each side then verifies the last proof of the other.
+        let circuit = RecursionCircuit::new(
+            &params_vesta,
+            &vk_fp,
+            None,
+            state_fq,
+            Previous::Proof(proof_fp),
+            None,
+            OsRng,
+        )
+        .unwrap();
+        let state_fq = circuit.next_state().unwrap().clone();
+        let proof_fq = prove_step(&params_pallas, &vk_fq, circuit);
+        assert_eq!(state_fq.step(), 1);
+        verify_recursion(
+            &params_vesta,
+            &params_pallas,
+            &vk_fq,
+            &vk_fp,
+            &proof_fq.proof,
+            &state_fq,
+            &state_fp,
+        )
+        .unwrap();
+
+        let circuit = RecursionCircuit::new(
+            &params_pallas,
+            &vk_fq,
+            None,
+            state_fp,
+            Previous::Proof(proof_fq),
+            None,
+            OsRng,
+        )
+        .unwrap();
+        let state_fp = circuit.next_state().unwrap().clone();
+        let proof_fp = prove_step(&params_vesta, &vk_fp, circuit);
+        assert_eq!(state_fp.step(), 2);
+        verify_recursion(
+            &params_pallas,
+            &params_vesta,
+            &vk_fp,
+            &vk_fq,
+            &proof_fp.proof,
+            &state_fp,
+            &state_fq,
+        )
+        .unwrap();
+
+        // A tampered proof is rejected.
+        let mut proof = proof_fp.proof.clone();
+        let last = proof.len() - 1;
+        proof[last] ^= 1;
+        assert!(verify_recursion(
+            &params_pallas,
+            &params_vesta,
+            &vk_fp,
+            &vk_fq,
+            &proof,
+            &state_fp,
+            &state_fq
+        )
+        .is_err());
+
+        // So is a state whose accumulator is not the one that the proof exposes, even
+        // if it is valid, or one that is not valid.
+        let mut tampered = state_fp.clone();
+        tampered.accumulator = commitment::Accumulator::from_challenges(
+            &params_pallas,
+            vec![Challenge(1); RECURSION_K as usize],
+        );
+        assert!(verify_recursion(
+            &params_pallas,
+            &params_vesta,
+            &vk_fp,
+            &vk_fq,
+            &proof_fp.proof,
+            &tampered,
+            &state_fq
+        )
+        .is_err());
+        let mut tampered = state_fq.clone();
+        tampered.accumulator.g = (tampered.accumulator.g + params_vesta.g[0]).to_affine();
+        assert!(verify_recursion(
+            &params_pallas,
+            &params_vesta,
+            &vk_fp,
+            &vk_fq,
+            &proof_fp.proof,
+            &state_fp,
+            &tampered
+        )
+        .is_err());
+    }
+}
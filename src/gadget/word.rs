@@ -0,0 +1,176 @@
+//! Gadget and chips for arithmetic on fixed-width words.
+//!
+//! A word of $W \leq 64$ bits, usually 32 or 64, is a field element range-checked to
+//! $W$ bits. Addition and subtraction witness their carry or borrow bit $c$ and
+//! constrain $a \pm b = s \pm c \cdot 2^W$ with $s$ range-checked. Shifts and rotations
+//! by a constant $n$ split a word into two range-checked parts $a = h \cdot 2^m + l$,
+//! and recombine them, so they cost no more than a range check of the word.
+
+use std::fmt;
+
+use crate::{
+    circuit::{Chip, Layouter},
+    plonk::Error,
+};
+
+mod chip;
+pub use chip::{AssignedWord, WordChip, WordConfig};
+
+/// The set of circuit instructions required to use the [`Word`] gadget.
+pub trait WordInstructions: Chip {
+    /// Variable representing a word.
+    type Word: Clone + fmt::Debug;
+
+    /// Witnesses a word of `bits` bits, constraining it to fit in them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bits` is zero or greater than 64.
+    fn witness_word(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<u64>,
+        bits: usize,
+    ) -> Result<Self::Word, Error>;
+
+    /// Returns $a + b \bmod 2^W$ and the carry bit, as a one-bit word.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `a` and `b` have different widths.
+    fn add(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Word,
+        b: &Self::Word,
+    ) -> Result<(Self::Word, Self::Word), Error>;
+
+    /// Returns $a - b \bmod 2^W$ and the borrow bit, as a one-bit word.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `a` and `b` have different widths.
+    fn sub(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Word,
+        b: &Self::Word,
+    ) -> Result<(Self::Word, Self::Word), Error>;
+
+    /// Returns `a` rotated right by `n` bits.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is not less than the width of `a`.
+    fn rotate_right(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Word,
+        n: usize,
+    ) -> Result<Self::Word, Error>;
+
+    /// Returns `a` shifted right by `n` bits.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is not less than the width of `a`.
+    fn shift_right(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Word,
+        n: usize,
+    ) -> Result<Self::Word, Error>;
+
+    /// Returns `a` shifted left by `n` bits, discarding the bits shifted out.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is not less than the width of `a`.
+    fn shift_left(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Word,
+        n: usize,
+    ) -> Result<Self::Word, Error>;
+}
+
+/// A word assigned in the circuit.
+#[derive(Debug)]
+pub struct Word<WordChip: WordInstructions> {
+    inner: WordChip::Word,
+}
+
+impl<WordChip: WordInstructions> Word<WordChip> {
+    /// Witnesses a 32-bit word.
+    pub fn new_u32(layouter: impl Layouter<WordChip>, value: Option<u32>) -> Result<Self, Error> {
+        Self::new(layouter, value.map(u64::from), 32)
+    }
+
+    /// Witnesses a 64-bit word.
+    pub fn new_u64(layouter: impl Layouter<WordChip>, value: Option<u64>) -> Result<Self, Error> {
+        Self::new(layouter, value, 64)
+    }
+
+    /// Witnesses a word of `bits` bits.
+    pub fn new(
+        mut layouter: impl Layouter<WordChip>,
+        value: Option<u64>,
+        bits: usize,
+    ) -> Result<Self, Error> {
+        WordChip::witness_word(&mut layouter, value, bits).map(|inner| Word { inner })
+    }
+
+    /// Returns the wrapping sum of this word and `other`, and the carry bit.
+    pub fn add(
+        &self,
+        mut layouter: impl Layouter<WordChip>,
+        other: &Self,
+    ) -> Result<(Self, Self), Error> {
+        WordChip::add(&mut layouter, &self.inner, &other.inner)
+            .map(|(sum, carry)| (Word { inner: sum }, Word { inner: carry }))
+    }
+
+    /// Returns the wrapping difference of this word and `other`, and the borrow bit.
+    pub fn sub(
+        &self,
+        mut layouter: impl Layouter<WordChip>,
+        other: &Self,
+    ) -> Result<(Self, Self), Error> {
+        WordChip::sub(&mut layouter, &self.inner, &other.inner)
+            .map(|(difference, borrow)| (Word { inner: difference }, Word { inner: borrow }))
+    }
+
+    /// Returns this word rotated right by `n` bits.
+    pub fn rotate_right(
+        &self,
+        mut layouter: impl Layouter<WordChip>,
+        n: usize,
+    ) -> Result<Self, Error> {
+        WordChip::rotate_right(&mut layouter, &self.inner, n).map(|inner| Word { inner })
+    }
+
+    /// Returns this word shifted right by `n` bits.
+    pub fn shift_right(
+        &self,
+        mut layouter: impl Layouter<WordChip>,
+        n: usize,
+    ) -> Result<Self, Error> {
+        WordChip::shift_right(&mut layouter, &self.inner, n).map(|inner| Word { inner })
+    }
+
+    /// Returns this word shifted left by `n` bits.
+    pub fn shift_left(
+        &self,
+        mut layouter: impl Layouter<WordChip>,
+        n: usize,
+    ) -> Result<Self, Error> {
+        WordChip::shift_left(&mut layouter, &self.inner, n).map(|inner| Word { inner })
+    }
+
+    /// Returns the chip-specific representation of this word.
+    pub fn inner(&self) -> &WordChip::Word {
+        &self.inner
+    }
+}
+
+impl<WordChip: WordInstructions> Clone for Word<WordChip> {
+    fn clone(&self) -> Self {
+        Word {
+            inner: self.inner.clone(),
+        }
+    }
+}
@@ -0,0 +1,460 @@
+//! A chip implementing [`WordInstructions`] with the range-check lookup.
+//!
+//! # Layout
+//!
+//! Each operation uses two rows of the advice columns of a [`RangeCheckConfig`],
+//! followed by the range checks of its outputs.
+//!
+//! An addition or subtraction of $a$ and $b$ holds $a$ and $b$ in its first row, and
+//! the result $s$ and the carry $c$ in its second row, with the sign $\sigma = \pm 1$
+//! and $2^W$ in fixed columns, so that $a + \sigma b = s + \sigma c \cdot 2^W$.
+//!
+//! A shift or rotation of $a$ splits it into $a = h \cdot 2^m + l$, and holds $a$ and
+//! $l$ in its first row and $h$ and the result $r = \lambda l + \eta h$ in its second
+//! row, with $2^m$, $\lambda$ and $\eta$ in fixed columns.
+
+use std::marker::PhantomData;
+
+use super::WordInstructions;
+use crate::{
+    arithmetic::FieldExt,
+    circuit::{Cell, Chip, Layouter, Region},
+    gadget::range_check::{AssignedValue, RangeCheckConfig},
+    plonk::{Advice, Column, ConstraintSystem, Error, Expression, Fixed, Selector},
+    poly::Rotation,
+};
+
+/// Returns $2^n$.
+fn two_pow<F: FieldExt>(n: usize) -> F {
+    (0..n).fold(F::one(), |acc, _| acc + acc)
+}
+
+/// Returns the low `n` bits of `value`.
+fn low_bits(value: u64, n: usize) -> u64 {
+    ((value as u128) & ((1u128 << n) - 1)) as u64
+}
+
+/// A word assigned in the circuit.
+#[derive(Clone, Copy, Debug)]
+pub struct AssignedWord {
+    cell: Cell,
+    value: Option<u64>,
+    bits: usize,
+}
+
+impl AssignedWord {
+    /// The cell containing this word.
+    pub fn cell(&self) -> Cell {
+        self.cell
+    }
+
+    /// The word, if known.
+    pub fn value(&self) -> Option<u64> {
+        self.value
+    }
+
+    /// The width of this word in bits.
+    pub fn bits(&self) -> usize {
+        self.bits
+    }
+
+    fn assigned_value<F: FieldExt>(&self) -> AssignedValue<F> {
+        AssignedValue {
+            cell: self.cell,
+            value: self.value.map(F::from_u64),
+        }
+    }
+}
+
+/// Configuration for a [`WordChip`].
+#[derive(Clone, Debug)]
+pub struct WordConfig {
+    range: RangeCheckConfig,
+    two_pow: Column<Fixed>,
+    coeff_lo: Column<Fixed>,
+    coeff_hi: Column<Fixed>,
+    q_add: Selector,
+    q_split: Selector,
+}
+
+/// A chip implementing [`WordInstructions`].
+#[derive(Debug)]
+pub struct WordChip<F: FieldExt> {
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt> Chip for WordChip<F> {
+    type Config = WordConfig;
+    type Loaded = ();
+    type Field = F;
+
+    fn load(layouter: &mut impl Layouter<Self>) -> Result<(), Error> {
+        let config = layouter.config().clone();
+        config.range.load(layouter)
+    }
+}
+
+impl<F: FieldExt> WordChip<F> {
+    /// Configures this chip with a range-check table of `word_bits`-bit values.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `word_bits` is zero or greater than 24.
+    pub fn configure(meta: &mut ConstraintSystem<F>, word_bits: usize) -> WordConfig {
+        let range = RangeCheckConfig::configure(meta, word_bits);
+        let config = WordConfig {
+            range,
+            two_pow: meta.fixed_column(),
+            coeff_lo: meta.fixed_column(),
+            coeff_hi: meta.fixed_column(),
+            q_add: meta.selector(),
+            q_split: meta.selector(),
+        };
+
+        let (z, word) = (config.range.z(), config.range.word());
+        meta.create_gate("word add", |meta| {
+            let a = meta.query_advice(z, Rotation::cur());
+            let b = meta.query_advice(word, Rotation::cur());
+            let s = meta.query_advice(z, Rotation::next());
+            let c = meta.query_advice(word, Rotation::next());
+            let sign = meta.query_fixed(config.coeff_lo, Rotation::cur());
+            let two_pow_w = meta.query_fixed(config.two_pow, Rotation::cur());
+            let q_add = meta.query_selector(config.q_add, Rotation::cur());
+
+            // a + sign * b = s + sign * c * 2^W
+            q_add * (a + sign.clone() * (b - c * two_pow_w) - s)
+        });
+        meta.create_gate("word carry", |meta| {
+            let c = meta.query_advice(word, Rotation::next());
+            let q_add = meta.query_selector(config.q_add, Rotation::cur());
+
+            q_add * c.clone() * (Expression::Constant(F::one()) - c)
+        });
+
+        meta.create_gate("word split", |meta| {
+            let a = meta.query_advice(z, Rotation::cur());
+            let lo = meta.query_advice(word, Rotation::cur());
+            let hi = meta.query_advice(z, Rotation::next());
+            let two_pow_m = meta.query_fixed(config.two_pow, Rotation::cur());
+            let q_split = meta.query_selector(config.q_split, Rotation::cur());
+
+            // a = hi * 2^m + lo
+            q_split * (a - hi * two_pow_m - lo)
+        });
+        meta.create_gate("word recombine", |meta| {
+            let lo = meta.query_advice(word, Rotation::cur());
+            let hi = meta.query_advice(z, Rotation::next());
+            let r = meta.query_advice(word, Rotation::next());
+            let coeff_lo = meta.query_fixed(config.coeff_lo, Rotation::cur());
+            let coeff_hi = meta.query_fixed(config.coeff_hi, Rotation::cur());
+            let q_split = meta.query_selector(config.q_split, Rotation::cur());
+
+            q_split * (r - coeff_lo * lo - coeff_hi * hi)
+        });
+
+        config
+    }
+}
+
+impl WordConfig {
+    /// Assigns `value` in `column` at `offset`, copying it from `copy` if given.
+    fn assign_word<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        region: &mut Region<'_, Ch>,
+        column: Column<Advice>,
+        offset: usize,
+        value: Option<u64>,
+        bits: usize,
+        copy: Option<&AssignedWord>,
+    ) -> Result<AssignedWord, Error> {
+        let cell = region.assign_advice(
+            || "word",
+            column,
+            offset,
+            || value.map(F::from_u64).ok_or(Error::SynthesisError),
+        )?;
+        if let Some(copy) = copy {
+            region.constrain_equal(self.range.perm(), cell, copy.cell)?;
+        }
+        Ok(AssignedWord { cell, value, bits })
+    }
+
+    /// Range-checks `words` from `offset`.
+    fn range_check<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        region: &mut Region<'_, Ch>,
+        mut offset: usize,
+        words: &[AssignedWord],
+    ) -> Result<(), Error> {
+        for word in words {
+            offset +=
+                self.range
+                    .assign_range_check(region, offset, &word.assigned_value(), word.bits)?;
+        }
+        Ok(())
+    }
+
+    /// Assigns $a + b$, or $a - b$ if `subtract` is set, at the start of `region`.
+    fn assign_add<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        region: &mut Region<'_, Ch>,
+        a: &AssignedWord,
+        b: &AssignedWord,
+        subtract: bool,
+    ) -> Result<(AssignedWord, AssignedWord), Error> {
+        assert_eq!(a.bits, b.bits);
+        let bits = a.bits;
+        let (z, word) = (self.range.z(), self.range.word());
+
+        self.q_add.enable(region, 0)?;
+        let sign = if subtract { -F::one() } else { F::one() };
+        region.assign_fixed(|| "sign", self.coeff_lo, 0, || Ok(sign))?;
+        region.assign_fixed(|| "2^W", self.two_pow, 0, || Ok(two_pow::<F>(bits)))?;
+
+        self.assign_word(region, z, 0, a.value, bits, Some(a))?;
+        self.assign_word(region, word, 0, b.value, bits, Some(b))?;
+
+        let result = a.value.zip(b.value).map(|(a, b)| {
+            let (a, b) = (u128::from(a), u128::from(b));
+            if subtract {
+                let borrow = a < b;
+                ((a + ((borrow as u128) << bits) - b) as u64, borrow as u64)
+            } else {
+                let sum = a + b;
+                (low_bits(sum as u64, bits), (sum >> bits) as u64)
+            }
+        });
+        let s = self.assign_word(region, z, 1, result.map(|r| r.0), bits, None)?;
+        let c = self.assign_word(region, word, 1, result.map(|r| r.1), 1, None)?;
+        self.range_check(region, 2, &[s])?;
+
+        Ok((s, c))
+    }
+
+    /// Assigns the split $a = h \cdot 2^m + l$ at the start of `region`, and returns
+    /// $\lambda l + \eta h$ as a word of the width of `a`, where $\lambda$ is
+    /// $2^{\mathsf{lo\_shift}}$ or zero and $\eta$ is one if `keep_hi` is set or zero.
+    fn assign_split<F: FieldExt, Ch: Chip<Field = F>>(
+        &self,
+        region: &mut Region<'_, Ch>,
+        a: &AssignedWord,
+        m: usize,
+        lo_shift: Option<usize>,
+        keep_hi: bool,
+    ) -> Result<AssignedWord, Error> {
+        let bits = a.bits;
+        assert!(m <= bits);
+        let (z, word) = (self.range.z(), self.range.word());
+
+        self.q_split.enable(region, 0)?;
+        let coeff_lo = lo_shift.map_or_else(F::zero, two_pow::<F>);
+        let coeff_hi = if keep_hi { F::one() } else { F::zero() };
+        region.assign_fixed(|| "2^m", self.two_pow, 0, || Ok(two_pow::<F>(m)))?;
+        region.assign_fixed(|| "lambda", self.coeff_lo, 0, || Ok(coeff_lo))?;
+        region.assign_fixed(|| "eta", self.coeff_hi, 0, || Ok(coeff_hi))?;
+
+        self.assign_word(region, z, 0, a.value, bits, Some(a))?;
+        let lo = self.assign_word(region, word, 0, a.value.map(|a| low_bits(a, m)), m, None)?;
+        let hi_value = a.value.map(|a| (u128::from(a) >> m) as u64);
+        let hi = self.assign_word(region, z, 1, hi_value, bits - m, None)?;
+        let r = lo.value.zip(hi.value).map(|(lo, hi)| {
+            let lo = lo_shift.map_or(0, |shift| u128::from(lo) << shift);
+            let hi = if keep_hi { u128::from(hi) } else { 0 };
+            (lo + hi) as u64
+        });
+        let r = self.assign_word(region, word, 1, r, bits, None)?;
+        self.range_check(region, 2, &[lo, hi])?;
+
+        Ok(r)
+    }
+}
+
+impl<F: FieldExt> WordChip<F> {
+    fn split(
+        layouter: &mut impl Layouter<Self>,
+        a: &AssignedWord,
+        m: usize,
+        lo_shift: Option<usize>,
+        keep_hi: bool,
+    ) -> Result<AssignedWord, Error> {
+        let config = layouter.config().clone();
+        layouter.assign_region(
+            || "split",
+            |mut region| config.assign_split(&mut region, a, m, lo_shift, keep_hi),
+        )
+    }
+}
+
+impl<F: FieldExt> WordInstructions for WordChip<F> {
+    type Word = AssignedWord;
+
+    fn witness_word(
+        layouter: &mut impl Layouter<Self>,
+        value: Option<u64>,
+        bits: usize,
+    ) -> Result<Self::Word, Error> {
+        assert!(bits > 0 && bits <= 64);
+        let config = layouter.config().clone();
+        layouter.assign_region(
+            || "witness word",
+            |mut region| {
+                let word =
+                    config.assign_word(&mut region, config.range.z(), 0, value, bits, None)?;
+                config.range_check(&mut region, 1, &[word])?;
+                Ok(word)
+            },
+        )
+    }
+
+    fn add(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Word,
+        b: &Self::Word,
+    ) -> Result<(Self::Word, Self::Word), Error> {
+        let config = layouter.config().clone();
+        layouter.assign_region(
+            || "add",
+            |mut region| config.assign_add(&mut region, a, b, false),
+        )
+    }
+
+    fn sub(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Word,
+        b: &Self::Word,
+    ) -> Result<(Self::Word, Self::Word), Error> {
+        let config = layouter.config().clone();
+        layouter.assign_region(
+            || "sub",
+            |mut region| config.assign_add(&mut region, a, b, true),
+        )
+    }
+
+    fn rotate_right(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Word,
+        n: usize,
+    ) -> Result<Self::Word, Error> {
+        assert!(n < a.bits);
+        // The low n bits become the high bits.
+        Self::split(layouter, a, n, Some(a.bits - n), true)
+    }
+
+    fn shift_right(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Word,
+        n: usize,
+    ) -> Result<Self::Word, Error> {
+        assert!(n < a.bits);
+        Self::split(layouter, a, n, None, true)
+    }
+
+    fn shift_left(
+        layouter: &mut impl Layouter<Self>,
+        a: &Self::Word,
+        n: usize,
+    ) -> Result<Self::Word, Error> {
+        assert!(n < a.bits);
+        // The high n bits are discarded.
+        Self::split(layouter, a, a.bits - n, Some(n), false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{WordChip, WordConfig};
+    use crate::{
+        circuit::{layouter, Layouter},
+        dev::MockProver,
+        gadget::word::Word,
+        pasta::Fp,
+        plonk::{Assignment, Circuit, ConstraintSystem, Error},
+    };
+
+    struct WordCircuit {
+        a: Option<u64>,
+        b: Option<u64>,
+        bits: usize,
+    }
+
+    impl Circuit<Fp> for WordCircuit {
+        type Config = WordConfig;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> WordConfig {
+            WordChip::configure(meta, 8)
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            config: WordConfig,
+        ) -> Result<(), Error> {
+            let mut layouter = layouter::SingleChip::<WordChip<Fp>, _>::new(cs, config)?;
+            let bits = self.bits;
+            let mask = ((1u128 << bits) - 1) as u64;
+
+            let a = Word::new(layouter.namespace(|| "a"), self.a, bits)?;
+            let b = Word::new(layouter.namespace(|| "b"), self.b, bits)?;
+            let (a_u, b_u) = (self.a.unwrap(), self.b.unwrap());
+            if u128::from(a_u | b_u) >> bits != 0 {
+                // Words that do not fit in their width fail their range checks.
+                return Ok(());
+            }
+
+            let (sum, carry) = a.add(layouter.namespace(|| "add"), &b)?;
+            let full = u128::from(a_u) + u128::from(b_u);
+            assert_eq!(sum.inner().value(), Some((full as u64) & mask));
+            assert_eq!(carry.inner().value(), Some((full >> bits) as u64));
+
+            let (difference, borrow) = a.sub(layouter.namespace(|| "sub"), &b)?;
+            assert_eq!(
+                difference.inner().value(),
+                Some(a_u.wrapping_sub(b_u) & mask)
+            );
+            assert_eq!(borrow.inner().value(), Some((a_u < b_u) as u64));
+
+            let n = 7;
+            let rotated = a.rotate_right(layouter.namespace(|| "rotr"), n)?;
+            assert_eq!(
+                rotated.inner().value(),
+                Some(((a_u >> n) | (a_u << (bits - n))) & mask)
+            );
+            let shr = a.shift_right(layouter.namespace(|| "shr"), n)?;
+            assert_eq!(shr.inner().value(), Some(a_u >> n));
+            let shl = a.shift_left(layouter.namespace(|| "shl"), n)?;
+            assert_eq!(shl.inner().value(), Some((a_u << n) & mask));
+
+            // Rotating by zero is the identity.
+            let same = a.rotate_right(layouter.namespace(|| "rotr 0"), 0)?;
+            assert_eq!(same.inner().value(), self.a);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn word_arithmetic() {
+        let run = |a: u64, b: u64, bits: usize| {
+            let circuit = WordCircuit {
+                a: Some(a),
+                b: Some(b),
+                bits,
+            };
+            MockProver::run(10, &circuit, vec![]).unwrap().verify()
+        };
+
+        assert_eq!(run(0xdead_beef, 0x1234_5678, 32), Ok(()));
+        assert_eq!(run(0x1234_5678, 0xdead_beef, 32), Ok(()));
+        assert_eq!(run(0xffff_ffff, 0xffff_ffff, 32), Ok(()));
+        assert_eq!(
+            run(0xfedc_ba98_7654_3210, 0x0123_4567_89ab_cdef, 64),
+            Ok(())
+        );
+        assert_eq!(run(u64::MAX, u64::MAX, 64), Ok(()));
+
+        // Words must fit in their width.
+        assert!(run(1 << 32, 0, 32).is_err());
+        assert!(run(0, 1 << 20, 20).is_err());
+    }
+}
@@ -19,10 +19,14 @@
 
 pub mod arithmetic;
 pub mod circuit;
+pub mod gadget;
 pub mod pasta;
 pub mod plonk;
 pub mod poly;
+pub mod primitives;
 pub mod transcript;
 
+mod multicore;
+
 pub mod dev;
 pub mod model;
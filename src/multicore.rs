@@ -0,0 +1,225 @@
+//! The threads that the parallel operations of this crate run on.
+//!
+//! With the `multicore` feature, which is enabled by default, work is split across
+//! scoped threads, one per CPU. Without it, this crate spawns no threads: every
+//! "spawned" closure runs to completion on the calling thread, in the order it was
+//! spawned, which suits targets such as `wasm32-unknown-unknown` that cannot spawn
+//! threads.
+//!
+//! The threads may be bounded by a budget, set with [`with_budget`], which the threads
+//! spawned under it share with the thread that set it: a closure is only spawned on a
+//! new thread while the budget has a thread to spare, and runs on the spawning thread
+//! otherwise, so that nested parallel operations never run more threads at once than
+//! the budget allows.
+
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+thread_local! {
+    // The threads that the current thread may spawn, shared with the threads spawned
+    // under the same budget, or `None` if they are not bounded.
+    static BUDGET: RefCell<Option<Arc<Budget>>> = const { RefCell::new(None) };
+}
+
+/// A number of threads that may be spawned, besides those already running.
+#[derive(Debug)]
+struct Budget {
+    spare: AtomicUsize,
+}
+
+impl Budget {
+    /// Takes up to `threads` threads from the budget, and returns how many it took.
+    fn take(&self, threads: usize) -> usize {
+        let mut spare = self.spare.load(Ordering::Acquire);
+        loop {
+            let taken = spare.min(threads);
+            match self.spare.compare_exchange_weak(
+                spare,
+                spare - taken,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => return taken,
+                Err(current) => spare = current,
+            }
+        }
+    }
+
+    /// Returns `threads` threads to the budget.
+    fn give(&self, threads: usize) {
+        self.spare.fetch_add(threads, Ordering::AcqRel);
+    }
+}
+
+/// Threads taken from a budget, which are returned to it when this is dropped.
+#[derive(Debug)]
+struct Taken {
+    budget: Arc<Budget>,
+    threads: usize,
+}
+
+impl Drop for Taken {
+    fn drop(&mut self) {
+        self.budget.give(self.threads);
+    }
+}
+
+/// Takes a thread from the budget of the current thread, or returns `None` if the
+/// budget has no thread to spare. Returns `Some(None)` if threads are not bounded.
+#[cfg(feature = "multicore")]
+fn take_thread() -> Option<Option<Taken>> {
+    BUDGET.with(|budget| match &*budget.borrow() {
+        None => Some(None),
+        Some(budget) => match budget.take(1) {
+            0 => None,
+            threads => Some(Some(Taken {
+                budget: budget.clone(),
+                threads,
+            })),
+        },
+    })
+}
+
+/// Sets the budget of the current thread to `budget` until this is dropped.
+struct Enter(Option<Arc<Budget>>);
+
+impl Enter {
+    fn new(budget: Option<Arc<Budget>>) -> Self {
+        Enter(BUDGET.with(|current| current.replace(budget)))
+    }
+}
+
+impl Drop for Enter {
+    fn drop(&mut self) {
+        BUDGET.with(|current| *current.borrow_mut() = self.0.take());
+    }
+}
+
+/// Runs `f` with a budget of `threads` threads, counting the current thread, for the
+/// parallel operations that it runs.
+///
+/// Within a budget that is already set, the threads of the new budget are taken from
+/// it, so that the new budget can only lower the limit.
+pub(crate) fn with_budget<R>(threads: usize, f: impl FnOnce() -> R) -> R {
+    let current = BUDGET.with(|budget| budget.borrow().clone());
+    let taken = current.map(|budget| Taken {
+        threads: budget.take(threads - 1),
+        budget,
+    });
+    let spare = taken.as_ref().map_or(threads - 1, |taken| taken.threads);
+    let _enter = Enter::new(Some(Arc::new(Budget {
+        spare: AtomicUsize::new(spare),
+    })));
+    f()
+}
+
+/// Returns the number of threads, counting the current thread, that a parallel
+/// operation started now could run on.
+pub(crate) fn available_threads() -> usize {
+    BUDGET.with(|budget| match &*budget.borrow() {
+        None => current_num_threads(),
+        Some(budget) => 1 + budget.spare.load(Ordering::Acquire),
+    })
+}
+
+#[cfg(feature = "multicore")]
+pub(crate) use self::threaded::scope;
+
+/// Returns the number of threads that a parallel operation uses by default.
+#[cfg(feature = "multicore")]
+pub(crate) fn current_num_threads() -> usize {
+    num_cpus::get()
+}
+
+#[cfg(feature = "multicore")]
+mod threaded {
+    use std::any::Any;
+
+    use super::{take_thread, Enter, BUDGET};
+
+    /// A scope in which closures are spawned on scoped threads, while the budget of
+    /// the spawning thread allows it.
+    pub(crate) struct Scope<'a, 'env> {
+        inner: &'a crossbeam_utils::thread::Scope<'env>,
+    }
+
+    impl<'a, 'env> std::fmt::Debug for Scope<'a, 'env> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("Scope").finish()
+        }
+    }
+
+    impl<'a, 'env> Scope<'a, 'env> {
+        /// Runs `f` on a new thread, which shares the budget of the current thread,
+        /// or on the current thread if the budget has no thread to spare.
+        pub(crate) fn spawn<F>(&self, f: F)
+        where
+            F: for<'b> FnOnce(&Scope<'b, 'env>) + Send + 'env,
+        {
+            match take_thread() {
+                Some(taken) => {
+                    let budget = BUDGET.with(|budget| budget.borrow().clone());
+                    self.inner.spawn(move |inner| {
+                        let _enter = Enter::new(budget);
+                        let _taken = taken;
+                        f(&Scope { inner })
+                    });
+                }
+                None => f(self),
+            }
+        }
+    }
+
+    /// Runs `f` with a scope whose threads are joined before this returns, and
+    /// returns an error if one of them panicked.
+    ///
+    /// This mirrors the signature of `crossbeam_utils::thread::scope`.
+    pub(crate) fn scope<'env, F, R>(f: F) -> Result<R, Box<dyn Any + Send + 'static>>
+    where
+        F: for<'a> FnOnce(&Scope<'a, 'env>) -> R,
+    {
+        crossbeam_utils::thread::scope(|inner| f(&Scope { inner }))
+    }
+}
+
+#[cfg(not(feature = "multicore"))]
+pub(crate) use self::serial::scope;
+
+/// Returns the number of threads that a parallel operation uses by default.
+#[cfg(not(feature = "multicore"))]
+pub(crate) fn current_num_threads() -> usize {
+    1
+}
+
+#[cfg(not(feature = "multicore"))]
+mod serial {
+    use std::any::Any;
+    use std::marker::PhantomData;
+
+    /// A scope in which closures are "spawned" by running them immediately.
+    #[derive(Debug)]
+    pub(crate) struct Scope<'env> {
+        _marker: PhantomData<&'env mut &'env ()>,
+    }
+
+    impl<'env> Scope<'env> {
+        /// Runs `f` on the calling thread.
+        pub(crate) fn spawn<F: FnOnce(&Scope<'env>)>(&self, f: F) {
+            f(self)
+        }
+    }
+
+    /// Runs `f` with a scope that runs the closures spawned in it serially.
+    ///
+    /// This mirrors the signature of `crossbeam_utils::thread::scope`, and never
+    /// returns an error: a panic in a spawned closure unwinds through the caller.
+    pub(crate) fn scope<'env, F, R>(f: F) -> Result<R, Box<dyn Any + Send + 'static>>
+    where
+        F: FnOnce(&Scope<'env>) -> R,
+    {
+        Ok(f(&Scope {
+            _marker: PhantomData,
+        }))
+    }
+}
@@ -13,6 +13,17 @@ pub mod vesta;
 pub use curves::*;
 pub use fields::*;
 
+// The macros that implement the Pallas and Vesta curves also implement the other
+// curves that the proof system runs over.
+pub(crate) use curves::{
+    impl_affine_curve_specific, impl_projective_curve_ext, impl_projective_curve_specific,
+    new_curve_impl,
+};
+pub(crate) use macros::{
+    impl_add_binop_specify_output, impl_binops_additive, impl_binops_additive_specify_output,
+    impl_binops_multiplicative, impl_binops_multiplicative_mixed, impl_sub_binop_specify_output,
+};
+
 #[test]
 fn test_endo_consistency() {
     use crate::arithmetic::{CurveExt, FieldExt};
@@ -767,6 +767,22 @@ macro_rules! impl_projective_curve_specific {
             }
         }
 
+        impl_projective_curve_specific!(@double_a0, $name);
+    };
+    ($name:ident, $base:ident, special_a0_b3) => {
+        fn generator() -> Self {
+            // NOTE: This is specific to b = 3
+
+            Self {
+                x: $base::one(),
+                y: $base::from_raw([2, 0, 0, 0]),
+                z: $base::one(),
+            }
+        }
+
+        impl_projective_curve_specific!(@double_a0, $name);
+    };
+    (@double_a0, $name:ident) => {
         fn double(&self) -> Self {
             // http://www.hyperelliptic.org/EFD/g1p/auto-shortw-jacobian-0.html#doubling-dbl-2009-l
             //
@@ -867,6 +883,22 @@ macro_rules! impl_projective_curve_ext {
             }
         }
     };
+    ($name:ident, $iso:ident, $base:ident, special_a0_b3) => {
+        /// Unimplemented: hashing to this curve is not supported
+        fn hash_to_curve<'a>(_domain_prefix: &'a str) -> Box<dyn Fn(&[u8]) -> Self + 'a> {
+            unimplemented!()
+        }
+
+        /// Apply the curve endomorphism by multiplying the x-coordinate
+        /// by an element of multiplicative order 3.
+        fn endo(&self) -> Self {
+            $name {
+                x: self.x * $base::ZETA,
+                y: self.y,
+                z: self.z,
+            }
+        }
+    };
     ($name:ident, $iso:ident, $base:ident, general) => {
         /// Unimplemented: hashing to this curve is not supported
         fn hash_to_curve<'a>(_domain_prefix: &'a str) -> Box<dyn Fn(&[u8]) -> Self + 'a> {
@@ -895,6 +927,17 @@ macro_rules! impl_affine_curve_specific {
             }
         }
     };
+    ($name:ident, $base:ident, special_a0_b3) => {
+        fn generator() -> Self {
+            // NOTE: This is specific to b = 3
+
+            Self {
+                x: $base::one(),
+                y: $base::from_raw([2, 0, 0, 0]),
+                infinity: Choice::from(0u8),
+            }
+        }
+    };
     ($name:ident, $base:ident, general) => {
         /// Unimplemented: there is no standard generator for this curve.
         fn generator() -> Self {
@@ -903,6 +946,11 @@ macro_rules! impl_affine_curve_specific {
     };
 }
 
+pub(crate) use {
+    impl_affine_curve_specific, impl_projective_curve_ext, impl_projective_curve_specific,
+    new_curve_impl,
+};
+
 new_curve_impl!(
     (pub),
     Ep,
@@ -151,3 +151,8 @@ macro_rules! impl_binops_multiplicative {
         }
     };
 }
+
+pub(crate) use {
+    impl_add_binop_specify_output, impl_binops_additive, impl_binops_additive_specify_output,
+    impl_binops_multiplicative, impl_binops_multiplicative_mixed, impl_sub_binop_specify_output,
+};
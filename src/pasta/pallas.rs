@@ -14,6 +14,34 @@ pub type Point = Ep;
 /// A Pallas point in the affine coordinate space (or the point at infinity).
 pub type Affine = EpAffine;
 
+/// Hashes `message` to the two field elements that [`CurveExt::hash_to_curve`] maps to
+/// the curve, within the given domain.
+///
+/// [`CurveExt::hash_to_curve`]: crate::arithmetic::CurveExt::hash_to_curve
+pub fn hash_to_field(domain_prefix: &str, message: &[u8]) -> [Base; 2] {
+    use crate::arithmetic::CurveExt;
+
+    let mut us = [Base::zero(); 2];
+    super::hashtocurve::hash_to_field(Point::CURVE_ID, domain_prefix, message, &mut us);
+    us
+}
+
+/// Maps a field element to a Pallas point, with the simplified SWU map to iso-Pallas
+/// followed by the isogeny to Pallas.
+///
+/// [`CurveExt::hash_to_curve`] returns the sum of the points that the two outputs of
+/// [`hash_to_field`] are mapped to.
+///
+/// [`CurveExt::hash_to_curve`]: crate::arithmetic::CurveExt::hash_to_curve
+pub fn map_to_curve(u: &Base) -> Point {
+    let p = super::hashtocurve::map_to_curve_simple_swu::<Base, Point, super::IsoEp>(
+        u,
+        Ep::THETA,
+        Ep::Z,
+    );
+    super::hashtocurve::iso_map::<_, Point, super::IsoEp>(&p, &Ep::ISOGENY_CONSTANTS)
+}
+
 #[test]
 fn test_iso_map() {
     use crate::arithmetic::CurveExt;
@@ -157,3 +185,12 @@ fn test_hash_to_curve() {
     assert!(bool::from(p.is_on_curve()));
     assert!(bool::from(p.is_identity()));
 }
+
+#[test]
+fn test_hash_to_curve_decomposition() {
+    use crate::arithmetic::CurveExt;
+
+    let hash = Point::hash_to_curve("z.cash:test");
+    let us = hash_to_field("z.cash:test", b"world");
+    assert_eq!(hash(b"world"), map_to_curve(&us[0]) + map_to_curve(&us[1]));
+}
@@ -6,30 +6,46 @@
 //! [plonk]: https://eprint.iacr.org/2019/953
 
 use blake2b_simd::Params as Blake2bParams;
+use group::Curve;
 
 use crate::arithmetic::{CurveAffine, FieldExt};
+use crate::poly::Rotation;
 use crate::poly::{
-    commitment::Params, Coeff, EvaluationDomain, ExtendedLagrangeCoeff, LagrangeCoeff,
+    commitment::{Blind, CommitmentScheme},
+    Coeff, EvaluationDomain, ExtendedLagrangeCoeff, LagrangeCoeff, PartLagrangeCoeff,
     PinnedEvaluationDomain, Polynomial,
 };
 use crate::transcript::{ChallengeScalar, Transcript};
 
+mod batch;
 mod circuit;
+mod evaluation;
 mod keygen;
 mod lookup;
 pub(crate) mod permutation;
+mod proof;
+mod shuffle;
 mod vanishing;
+mod workers;
 
 mod prover;
 mod verifier;
 
+pub use batch::BatchVerifier;
 pub use circuit::*;
 pub use keygen::*;
+pub use proof::Proof;
 pub use prover::*;
 pub use verifier::*;
+pub use workers::{LocalWorkers, ProverWorkers};
 
+use std::collections::BTreeSet;
 use std::io;
 
+/// The version of the binary encoding of [`VerifyingKey`] and [`ProvingKey`], which is
+/// written at the start of every key.
+const KEY_ENCODING_VERSION: u8 = 1;
+
 /// This is a verifying key which allows for the verification of proofs for a
 /// particular circuit.
 #[derive(Debug)]
@@ -38,11 +54,29 @@ pub struct VerifyingKey<C: CurveAffine> {
     fixed_commitments: Vec<C>,
     permutations: Vec<permutation::VerifyingKey<C>>,
     cs: ConstraintSystem<C::Scalar>,
+    /// The rows at which each selector of the circuit is enabled, before they were
+    /// combined into fixed columns.
+    selectors: Vec<Vec<bool>>,
 }
 
 impl<C: CurveAffine> VerifyingKey<C> {
     /// Writes a verifying key to a buffer.
+    ///
+    /// The encoding starts with a version byte and the size of the circuit, so that
+    /// [`VerifyingKey::read`] rejects keys from other versions or parameters.
     pub fn write<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&[KEY_ENCODING_VERSION])?;
+        writer.write_all(&self.domain.k().to_le_bytes())?;
+
+        // The selectors come first, as the number of fixed columns depends on how
+        // they are combined.
+        for selector in &self.selectors {
+            let mut bytes = vec![0u8; selector.len().div_ceil(8)];
+            for (i, enabled) in selector.iter().enumerate() {
+                bytes[i / 8] |= (*enabled as u8) << (i % 8);
+            }
+            writer.write_all(&bytes)?;
+        }
         for commitment in &self.fixed_commitments {
             writer.write_all(commitment.to_bytes().as_ref())?;
         }
@@ -53,12 +87,43 @@ impl<C: CurveAffine> VerifyingKey<C> {
         Ok(())
     }
 
-    /// Reads a verification key from a buffer.
+    /// Reads a verification key from a buffer, for the circuit `ConcreteCircuit` over
+    /// `params`, configured with `circuit_params`.
     pub fn read<R: io::Read, ConcreteCircuit: Circuit<C::Scalar>>(
         reader: &mut R,
-        params: &Params<C>,
+        params: &impl CommitmentScheme<C>,
+        #[cfg(feature = "circuit-params")] circuit_params: ConcreteCircuit::Params,
     ) -> io::Result<Self> {
-        let (domain, cs, _) = keygen::create_domain::<C, ConcreteCircuit>(params);
+        let mut header = [0u8; 5];
+        reader.read_exact(&mut header)?;
+        if header[0] != KEY_ENCODING_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "unsupported key encoding version",
+            ));
+        }
+        if header[1..] != params.k().to_le_bytes() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "key is for parameters of a different size",
+            ));
+        }
+
+        let (domain, cs, _) = keygen::create_domain::<C, ConcreteCircuit>(
+            params,
+            #[cfg(feature = "circuit-params")]
+            circuit_params,
+        );
+
+        let n = params.n() as usize;
+        let selectors: Vec<Vec<bool>> = (0..cs.num_selectors)
+            .map(|_| {
+                let mut bytes = vec![0u8; n.div_ceil(8)];
+                reader.read_exact(&mut bytes)?;
+                Ok((0..n).map(|i| (bytes[i / 8] >> (i % 8)) & 1 == 1).collect())
+            })
+            .collect::<io::Result<_>>()?;
+        let (cs, _) = cs.compress_selectors(selectors.clone());
 
         let fixed_commitments: Vec<_> = (0..cs.num_fixed_columns)
             .map(|_| C::read(reader))
@@ -75,11 +140,29 @@ impl<C: CurveAffine> VerifyingKey<C> {
             fixed_commitments,
             permutations,
             cs,
+            selectors,
         })
     }
 
-    /// Hashes a verification key into a transcript.
-    pub fn hash_into<T: Transcript<C>>(&self, transcript: &mut T) -> io::Result<()> {
+    /// Hashes a verification key into a transcript, returning the digest that was
+    /// absorbed.
+    ///
+    /// The digest is that of [`VerifyingKey::digest`], which binds the domain, the
+    /// fixed and permutation commitments and the structure of the constraint system.
+    pub fn hash_into<T: Transcript<C>>(&self, transcript: &mut T) -> io::Result<C::Scalar> {
+        let digest = self.digest();
+        transcript.common_scalar(digest)?;
+
+        Ok(digest)
+    }
+
+    /// Returns a digest of the [`pinned`](VerifyingKey::pinned) representation of
+    /// this verification key.
+    ///
+    /// The digest commits to everything that the verifier uses from the key, so a
+    /// recursive circuit or a registry of keys can bind to a specific key by its
+    /// digest alone.
+    pub fn digest(&self) -> C::Scalar {
         let mut hasher = Blake2bParams::new()
             .hash_length(64)
             .personal(b"Halo2-Verify-Key")
@@ -90,10 +173,7 @@ impl<C: CurveAffine> VerifyingKey<C> {
         hasher.update(&(s.len() as u64).to_le_bytes());
         hasher.update(s.as_bytes());
 
-        // Hash in final Blake2bState
-        transcript.common_scalar(C::Scalar::from_bytes_wide(hasher.finalize().as_array()))?;
-
-        Ok(())
+        C::Scalar::from_bytes_wide(hasher.finalize().as_array())
     }
 
     /// Obtains a pinned representation of this verification key that contains
@@ -123,15 +203,26 @@ pub struct PinnedVerificationKey<'a, C: CurveAffine> {
 }
 /// This is a proving key which allows for the creation of proofs for a
 /// particular circuit.
+///
+/// Everything that the prover needs and that does not depend on the witness is
+/// computed once, when the key is generated or read: the coefficients and the
+/// extended-domain evaluations of the fixed columns, which include the columns that
+/// the selectors are combined into, and of the permutation polynomials, as well as
+/// those of $l_0(X)$. Creating a proof never computes an FFT of any of them, unless
+/// the extended-domain evaluations are discarded with
+/// [`discard_cosets`](ProvingKey::discard_cosets).
 #[derive(Debug)]
 pub struct ProvingKey<C: CurveAffine> {
     vk: VerifyingKey<C>,
-    // TODO: get rid of this?
-    l0: Polynomial<C::Scalar, ExtendedLagrangeCoeff>,
+    // The coefficients of l_0(X).
+    l0: Polynomial<C::Scalar, Coeff>,
+    // The evaluations of l_0(X) over the extended domain, unless discarded.
+    l0_coset: Option<Polynomial<C::Scalar, ExtendedLagrangeCoeff>>,
     fixed_values: Vec<Polynomial<C::Scalar, LagrangeCoeff>>,
     fixed_polys: Vec<Polynomial<C::Scalar, Coeff>>,
-    fixed_cosets: Vec<Polynomial<C::Scalar, ExtendedLagrangeCoeff>>,
+    fixed_cosets: Option<Vec<Polynomial<C::Scalar, ExtendedLagrangeCoeff>>>,
     permutations: Vec<permutation::ProvingKey<C>>,
+    evaluator: evaluation::Evaluator<C>,
 }
 
 /// This is an error that could occur during proving or circuit synthesis.
@@ -152,6 +243,15 @@ pub enum Error {
     OpeningError,
     /// Transcript error
     TranscriptError,
+    /// The circuit does not match the verifying key that a proving key is being
+    /// derived from.
+    IncompatibleVerifyingKey,
+    /// The proof was cancelled by the progress callback of
+    /// [`ProverOptions::progress`].
+    Cancelled,
+    /// A [`ProverWorkers`] implementation failed to perform an operation that the
+    /// prover handed it.
+    WorkerFailure,
 }
 
 impl<C: CurveAffine> ProvingKey<C> {
@@ -159,6 +259,103 @@ impl<C: CurveAffine> ProvingKey<C> {
     pub fn get_vk(&self) -> &VerifyingKey<C> {
         &self.vk
     }
+
+    /// Discards the evaluations of the fixed columns, of the permutation polynomials
+    /// and of $l_0(X)$ over the extended domain, which take $2^{extended\_k - k}$ times
+    /// the memory of their values.
+    ///
+    /// Proofs created with this key are unchanged, but compute the evaluations over
+    /// each part of the extended domain when they evaluate $h(X)$ over it, with an FFT
+    /// of the size of the domain per polynomial. Together with the prover, which only
+    /// holds the evaluations of the witness over one part at a time, this bounds the
+    /// memory of proof creation by a small multiple of the size of the circuit.
+    pub fn discard_cosets(&mut self) {
+        self.l0_coset = None;
+        self.fixed_cosets = None;
+        for permutation in self.permutations.iter_mut() {
+            permutation.discard_cosets();
+        }
+    }
+
+    /// Returns the values of $l_0(X)$ over the `part`-th part of the extended domain.
+    pub(crate) fn l0_part(&self, part: usize) -> Polynomial<C::Scalar, PartLagrangeCoeff> {
+        match &self.l0_coset {
+            Some(coset) => self.vk.domain.extended_to_part(coset, part),
+            None => self.vk.domain.coeff_to_extended_part(self.l0.clone(), part),
+        }
+    }
+
+    /// Returns the values of the fixed columns over the `part`-th part of the
+    /// extended domain.
+    pub(crate) fn fixed_part(&self, part: usize) -> Vec<Polynomial<C::Scalar, PartLagrangeCoeff>> {
+        let domain = &self.vk.domain;
+        match &self.fixed_cosets {
+            Some(cosets) => cosets
+                .iter()
+                .map(|coset| domain.extended_to_part(coset, part))
+                .collect(),
+            None => self
+                .fixed_polys
+                .iter()
+                .map(|poly| domain.coeff_to_extended_part(poly.clone(), part))
+                .collect(),
+        }
+    }
+
+    /// Writes a proving key to a buffer.
+    ///
+    /// The encoding is that of the verifying key, followed by the values of the fixed
+    /// columns and of the permutations.
+    pub fn write<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.vk.write(writer)?;
+        for poly in &self.fixed_values {
+            for value in poly.iter() {
+                value.write(writer)?;
+            }
+        }
+        for permutation in &self.permutations {
+            permutation.write(writer)?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads a proving key from a buffer, for the circuit `ConcreteCircuit` over
+    /// `params`, configured with `circuit_params`.
+    ///
+    /// This does not synthesize the circuit or compute any commitments, and so is much
+    /// faster than [`keygen_pk`].
+    pub fn read<R: io::Read, ConcreteCircuit: Circuit<C::Scalar>>(
+        reader: &mut R,
+        params: &impl CommitmentScheme<C>,
+        #[cfg(feature = "circuit-params")] circuit_params: ConcreteCircuit::Params,
+    ) -> io::Result<Self> {
+        let vk = VerifyingKey::read::<_, ConcreteCircuit>(
+            reader,
+            params,
+            #[cfg(feature = "circuit-params")]
+            circuit_params,
+        )?;
+
+        let fixed_values = (0..vk.cs.num_fixed_columns)
+            .map(|_| {
+                let mut poly = vk.domain.empty_lagrange();
+                for value in poly.iter_mut() {
+                    *value = C::Scalar::read(reader)?;
+                }
+                Ok(poly)
+            })
+            .collect::<io::Result<_>>()?;
+
+        let permutations = vk
+            .cs
+            .permutations
+            .iter()
+            .map(|argument| permutation::ProvingKey::read(reader, argument, &vk.domain))
+            .collect::<io::Result<_>>()?;
+
+        Ok(keygen::build_pk(vk, fixed_values, permutations))
+    }
 }
 
 impl<C: CurveAffine> VerifyingKey<C> {
@@ -166,8 +363,177 @@ impl<C: CurveAffine> VerifyingKey<C> {
     pub fn get_domain(&self) -> &EvaluationDomain<C::Scalar> {
         &self.domain
     }
+
+    /// The constraint system of the circuit.
+    pub(crate) fn cs(&self) -> &ConstraintSystem<C::Scalar> {
+        &self.cs
+    }
+
+    /// The commitments to the fixed columns of the circuit.
+    pub(crate) fn fixed_commitments(&self) -> &[C] {
+        &self.fixed_commitments
+    }
+
+    /// The verifying keys of the permutation arguments of the circuit.
+    pub(crate) fn permutations(&self) -> &[permutation::VerifyingKey<C>] {
+        &self.permutations
+    }
+
+    /// Returns the instance columns of a circuit, given the values of each of its
+    /// instance columns from the first row, as the polynomials that
+    /// [`create_proof`] takes. Rows without a value are zero.
+    ///
+    /// Returns an error if the number of columns does not match the circuit, or if a
+    /// column has more rows than the circuit.
+    pub fn instance_values(
+        &self,
+        columns: &[&[C::Scalar]],
+    ) -> Result<Vec<Polynomial<C::Scalar, LagrangeCoeff>>, Error> {
+        if columns.len() != self.cs.num_instance_columns {
+            return Err(Error::IncompatibleParams);
+        }
+
+        columns
+            .iter()
+            .map(|column| {
+                let mut poly = self.domain.empty_lagrange();
+                if column.len() > poly.len() {
+                    return Err(Error::BoundsFailure);
+                }
+                poly[..][..column.len()].copy_from_slice(column);
+                Ok(poly)
+            })
+            .collect()
+    }
+
+    /// Returns the commitments to the instance columns of a circuit, given the values
+    /// of each of its instance columns as in [`VerifyingKey::instance_values`], as
+    /// [`verify_proof`] takes them.
+    pub fn commit_instances(
+        &self,
+        params: &impl CommitmentScheme<C>,
+        columns: &[&[C::Scalar]],
+    ) -> Result<Vec<C>, Error> {
+        let polys = self.instance_values(columns)?;
+        let commitments_projective: Vec<_> = polys
+            .iter()
+            .map(|poly| params.commit_lagrange(poly, Blind::default()))
+            .collect();
+        let mut commitments = vec![C::identity(); commitments_projective.len()];
+        C::Curve::batch_normalize(&commitments_projective, &mut commitments);
+        Ok(commitments)
+    }
+
+    /// Returns the length in bytes of a proof for `num_circuits` circuits with this
+    /// key, as [`create_proof`] writes it to a [`Blake2bWrite`] transcript.
+    ///
+    /// The length only depends on the key and on the number of circuits that the
+    /// proof is for, never on the values of their instance columns, so it can be
+    /// computed without creating a proof.
+    ///
+    /// [`Blake2bWrite`]: crate::transcript::Blake2bWrite
+    pub fn proof_size(&self, num_circuits: usize) -> usize {
+        let cs = &self.cs;
+        let lookups = cs.lookups_proven_with(LookupArgument::Permutation).len();
+        let log_lookups = cs.lookups_proven_with(LookupArgument::LogDerivative).len();
+        let chunks: usize = cs
+            .permutations
+            .iter()
+            .map(|argument| argument.chunks(cs.degree_without_permutations()).len())
+            .sum();
+        let permutation_columns: usize = cs
+            .permutations
+            .iter()
+            .map(|argument| argument.get_columns().len())
+            .sum();
+        let quotient_pieces = self.domain.get_quotient_poly_degree();
+        let k = self.domain.k() as usize;
+
+        let points = num_circuits
+            * (cs.num_advice_columns + 3 * lookups + 2 * log_lookups + chunks + cs.shuffles.len())
+            + quotient_pieces
+            // The multi-opening argument, and the opening proof of its commitment.
+            + 1
+            + 1
+            + 2 * k;
+        let scalars = num_circuits
+            * (cs.instance_queries.len()
+                + cs.advice_queries.len()
+                + 2 * chunks
+                + permutation_columns
+                + 5 * lookups
+                + 3 * log_lookups
+                + 2 * cs.shuffles.len())
+            + cs.fixed_queries.len()
+            + quotient_pieces
+            + self.opening_point_sets()
+            + 2;
+
+        points * C::Repr::default().as_ref().len() + scalars * C::Scalar::default().to_bytes().len()
+    }
+
+    /// Returns the number of distinct sets of points at which the multi-opening
+    /// argument of a proof with this key opens commitments, each of which it sends one
+    /// evaluation for.
+    pub(crate) fn opening_point_sets(&self) -> usize {
+        let cs = &self.cs;
+        let lookups = cs.lookups_proven_with(LookupArgument::Permutation).len();
+        let log_lookups = cs.lookups_proven_with(LookupArgument::LogDerivative).len();
+        let chunks: usize = cs
+            .permutations
+            .iter()
+            .map(|argument| argument.chunks(cs.degree_without_permutations()).len())
+            .sum();
+        let k = self.domain.k() as usize;
+
+        // The sets are the same for every circuit of a proof.
+        let mut point_sets = BTreeSet::new();
+        let mut open_at = |rotations: &[Rotation]| {
+            let points: BTreeSet<_> = rotations
+                .iter()
+                .map(|at| i64::from(at.0).rem_euclid(1 << k))
+                .collect();
+            // Columns that are never queried are never opened.
+            if !points.is_empty() {
+                point_sets.insert(points);
+            }
+        };
+        for rotations in column_rotations(cs.num_instance_columns, &cs.instance_queries)
+            .iter()
+            .chain(column_rotations(cs.num_advice_columns, &cs.advice_queries).iter())
+            .chain(column_rotations(cs.num_fixed_columns, &cs.fixed_queries).iter())
+        {
+            open_at(rotations);
+        }
+        // The pieces of h(X), and the commitments of the arguments.
+        open_at(&[Rotation::cur()]);
+        if chunks > 0 || lookups > 0 {
+            open_at(&[Rotation::cur(), Rotation::prev()]);
+        }
+        if log_lookups > 0 || !cs.shuffles.is_empty() {
+            open_at(&[Rotation::cur(), Rotation::next()]);
+        }
+        point_sets.len()
+    }
 }
 
+/// Returns the rotations at which each of `num_columns` columns is queried.
+fn column_rotations<T: ColumnType>(
+    num_columns: usize,
+    queries: &[(Column<T>, Rotation)],
+) -> Vec<Vec<Rotation>> {
+    let mut rotations = vec![vec![]; num_columns];
+    for (column, at) in queries {
+        rotations[column.index()].push(*at);
+    }
+    rotations
+}
+
+#[derive(Clone, Copy, Debug)]
+struct User;
+/// A challenge of the circuit, allocated with [`ConstraintSystem::challenge_usable_after`].
+type ChallengeUser<F> = ChallengeScalar<F, User>;
+
 #[derive(Clone, Copy, Debug)]
 struct Theta;
 type ChallengeTheta<F> = ChallengeScalar<F, Theta>;
@@ -0,0 +1,68 @@
+use super::{verify_proof, VerifyingKey};
+use crate::arithmetic::CurveAffine;
+use crate::poly::commitment::{FoldedGuards, Params};
+use crate::transcript::Blake2bRead;
+
+/// A proof to be verified by a [`BatchVerifier`], with the commitments to its
+/// instance columns.
+#[derive(Debug)]
+struct BatchItem<C: CurveAffine> {
+    instances: Vec<Vec<C>>,
+    proof: Vec<u8>,
+}
+
+/// A verifier of many proofs for the same circuit, which checks them all with a
+/// single multiscalar multiplication.
+///
+/// This is faster than verifying each proof on its own, but if the batch fails, it
+/// does not say which of the proofs is invalid.
+#[derive(Debug)]
+pub struct BatchVerifier<C: CurveAffine> {
+    items: Vec<BatchItem<C>>,
+}
+
+impl<C: CurveAffine> Default for BatchVerifier<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C: CurveAffine> BatchVerifier<C> {
+    /// Constructs a new batch verifier.
+    pub fn new() -> Self {
+        BatchVerifier { items: vec![] }
+    }
+
+    /// Adds a proof to the batch, with the commitments to the instance columns of
+    /// each circuit that it proves.
+    pub fn add_proof(&mut self, instances: Vec<Vec<C>>, proof: Vec<u8>) {
+        self.items.push(BatchItem { instances, proof })
+    }
+
+    /// Returns the number of proofs in the batch.
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Returns whether the batch is empty.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Finalizes the batch and checks its validity, for proofs created with a
+    /// [`Blake2bWrite`](crate::transcript::Blake2bWrite) transcript.
+    ///
+    /// Returns `false` if any of the proofs is invalid or malformed.
+    pub fn finalize(self, params: &Params<C>, vk: &VerifyingKey<C>) -> bool {
+        let mut guards = FoldedGuards::new(params);
+        for item in self.items.iter() {
+            let instances: Vec<_> = item.instances.iter().map(|i| &i[..]).collect();
+            let mut transcript = Blake2bRead::init(&item.proof[..]);
+            match verify_proof(params, vk, params.empty_msm(), &instances, &mut transcript) {
+                Ok(guard) => guards.fold(guard),
+                Err(_) => return false,
+            }
+        }
+        guards.decide()
+    }
+}
@@ -1,18 +1,21 @@
 use core::cmp::max;
-use core::ops::{Add, Mul};
+use core::ops::{Add, Mul, Range};
 use ff::Field;
 use std::{
     convert::TryFrom,
-    ops::{Neg, Sub},
+    iter,
+    ops::{Deref, DerefMut, Neg, Sub},
 };
 
-use super::{lookup, permutation, Error};
+use super::{lookup, permutation, shuffle, Error};
 use crate::{
     arithmetic::FieldExt,
     circuit::{Chip, Region},
     poly::Rotation,
 };
 
+mod compress_selectors;
+
 /// A column type
 pub trait ColumnType: 'static + Sized + std::fmt::Debug {}
 
@@ -130,6 +133,51 @@ impl TryFrom<Column<Any>> for Column<Instance> {
     }
 }
 
+/// A phase of the prover, in which it assigns and commits to the advice columns of
+/// that phase, after which the challenges of that phase are squeezed.
+///
+/// Every circuit has a first phase; the advice columns of later phases can be
+/// assigned with the values of the challenges of earlier phases.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Phase(pub u8);
+
+impl Phase {
+    /// The first phase, in which advice columns are assigned by default.
+    pub fn first() -> Phase {
+        Phase(0)
+    }
+
+    /// The second phase.
+    pub fn second() -> Phase {
+        Phase(1)
+    }
+
+    /// The third phase.
+    pub fn third() -> Phase {
+        Phase(2)
+    }
+}
+
+/// A challenge squeezed from the transcript after the advice columns of its phase
+/// are committed to.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct Challenge {
+    index: usize,
+    phase: Phase,
+}
+
+impl Challenge {
+    /// Returns the index of this challenge.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns the phase after which this challenge is squeezed.
+    pub fn phase(&self) -> Phase {
+        self.phase
+    }
+}
+
 /// A selector, representing a fixed boolean value per row of the circuit.
 ///
 /// Selectors can be used to conditionally enable (portions of) gates:
@@ -178,20 +226,33 @@ impl TryFrom<Column<Any>> for Column<Instance> {
 ///     Ok(())
 /// }
 /// ```
-#[derive(Clone, Copy, Debug)]
-pub struct Selector(Column<Fixed>);
+///
+/// Selectors are not columns of the circuit: at key generation, selectors that are never
+/// enabled on the same row are combined into a single fixed column, if the degree of
+/// the circuit allows it.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct Selector(pub(crate) usize);
 
 impl Selector {
     /// Enable this selector at the given offset within the given region.
     pub fn enable<C: Chip>(&self, region: &mut Region<C>, offset: usize) -> Result<(), Error> {
-        // TODO: Ensure that the default for a selector's cells is always zero, if we
-        // alter the proving system to change the global default.
-        // TODO: Add Region::enable_selector method to allow the layouter to control the
-        // selector's assignment.
-        // https://github.com/zcash/halo2/issues/116
-        region
-            .assign_fixed(|| "", self.0, offset, || Ok(C::Field::one()))
-            .map(|_| ())
+        region.enable_selector(|| "", self, offset)
+    }
+}
+
+/// A fixed column that holds the rows of lookup tables.
+///
+/// Many logical tables can be packed into the same table columns, with a tag column
+/// that holds the tag of the table that each row belongs to; see
+/// [`ConstraintSystem::lookup_tagged`]. Tables are assigned like any other fixed
+/// column, through [`TableColumn::inner`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct TableColumn(Column<Fixed>);
+
+impl TableColumn {
+    /// Returns the fixed column that holds this table column.
+    pub fn inner(&self) -> Column<Fixed> {
+        self.0
     }
 }
 
@@ -245,6 +306,17 @@ pub trait Assignment<F: Field> {
     /// [`Layouter::assign_region`]: crate::circuit::Layouter#method.assign_region
     fn exit_region(&mut self);
 
+    /// Enables a selector at the given row.
+    fn enable_selector<A, AR>(
+        &mut self,
+        annotation: A,
+        selector: &Selector,
+        row: usize,
+    ) -> Result<(), Error>
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>;
+
     /// Assign an advice column value (witness)
     fn assign_advice<V, A, AR>(
         &mut self,
@@ -297,6 +369,18 @@ pub trait Assignment<F: Field> {
     ///
     /// [`Layouter::namespace`]: crate::circuit::Layouter#method.namespace
     fn pop_namespace(&mut self, gadget_name: Option<String>);
+
+    /// Returns the value of `challenge`, or `None` if it is not known, such as during
+    /// key generation, or before its phase has been committed to.
+    fn get_challenge(&self, challenge: Challenge) -> Option<F>;
+
+    /// Returns whether this assignment uses the values assigned to advice cells.
+    ///
+    /// Key generation only needs the fixed columns, selectors and copy constraints of
+    /// a circuit, so it never calls the closures passed to
+    /// [`Assignment::assign_advice`], and a circuit may skip computing its witness
+    /// when this returns `false`.
+    fn witness_required(&self) -> bool;
 }
 
 /// This is a trait that circuits provide implementations for so that the
@@ -306,27 +390,70 @@ pub trait Circuit<F: Field> {
     /// This is a configuration object that stores things like columns.
     type Config: Clone;
 
+    /// Runtime parameters of the circuit, such as its number of columns or which of
+    /// its chips are enabled, on which its configuration may depend.
+    #[cfg(feature = "circuit-params")]
+    type Params: Default;
+
+    /// Returns the parameters with which this circuit is configured.
+    #[cfg(feature = "circuit-params")]
+    fn params(&self) -> Self::Params {
+        Self::Params::default()
+    }
+
     /// The circuit is given an opportunity to describe the exact gate
     /// arrangement, column arrangement, etc.
     fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config;
 
+    /// Configures the circuit for the parameters `params`.
+    ///
+    /// Every caller that configures a circuit instance uses this method, with the
+    /// parameters of [`Circuit::params`]. By default it ignores them and calls
+    /// [`Circuit::configure`], which a circuit that needs its parameters may leave
+    /// unimplemented.
+    #[cfg(feature = "circuit-params")]
+    fn configure_with_params(meta: &mut ConstraintSystem<F>, params: Self::Params) -> Self::Config {
+        let _ = params;
+        Self::configure(meta)
+    }
+
     /// Given the provided `cs`, synthesize the circuit. The concrete type of
     /// the caller will be different depending on the context, and they may or
     /// may not expect to have a witness present.
     fn synthesize(&self, cs: &mut impl Assignment<F>, config: Self::Config) -> Result<(), Error>;
 }
 
+/// Configures `circuit` in `meta`, with its parameters if circuits have them.
+pub(crate) fn configure_circuit<F: Field, ConcreteCircuit: Circuit<F>>(
+    meta: &mut ConstraintSystem<F>,
+    circuit: &ConcreteCircuit,
+) -> ConcreteCircuit::Config {
+    #[cfg(feature = "circuit-params")]
+    {
+        ConcreteCircuit::configure_with_params(meta, circuit.params())
+    }
+    #[cfg(not(feature = "circuit-params"))]
+    {
+        let _ = circuit;
+        ConcreteCircuit::configure(meta)
+    }
+}
+
 /// Low-degree expression representing an identity that must hold over the committed columns.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Expression<F> {
     /// This is a constant polynomial
     Constant(F),
+    /// This is a virtual selector queried at a certain relative location
+    Selector(Selector, Rotation),
     /// This is a fixed column queried at a certain relative location
     Fixed(usize),
     /// This is an advice (witness) column queried at a certain relative location
     Advice(usize),
     /// This is an instance (external) column queried at a certain relative location
     Instance(usize),
+    /// This is a challenge, which is constant over the rows of the circuit
+    Challenge(Challenge),
     /// This is the sum of two polynomials
     Sum(Box<Expression<F>>, Box<Expression<F>>),
     /// This is the product of two polynomials
@@ -341,33 +468,41 @@ impl<F: Field> Expression<F> {
     pub fn evaluate<T>(
         &self,
         constant: &impl Fn(F) -> T,
+        selector_column: &impl Fn(Selector, Rotation) -> T,
         fixed_column: &impl Fn(usize) -> T,
         advice_column: &impl Fn(usize) -> T,
         instance_column: &impl Fn(usize) -> T,
+        challenge: &impl Fn(Challenge) -> T,
         sum: &impl Fn(T, T) -> T,
         product: &impl Fn(T, T) -> T,
         scaled: &impl Fn(T, F) -> T,
     ) -> T {
         match self {
             Expression::Constant(scalar) => constant(*scalar),
+            Expression::Selector(selector, at) => selector_column(*selector, *at),
             Expression::Fixed(index) => fixed_column(*index),
             Expression::Advice(index) => advice_column(*index),
             Expression::Instance(index) => instance_column(*index),
+            Expression::Challenge(value) => challenge(*value),
             Expression::Sum(a, b) => {
                 let a = a.evaluate(
                     constant,
+                    selector_column,
                     fixed_column,
                     advice_column,
                     instance_column,
+                    challenge,
                     sum,
                     product,
                     scaled,
                 );
                 let b = b.evaluate(
                     constant,
+                    selector_column,
                     fixed_column,
                     advice_column,
                     instance_column,
+                    challenge,
                     sum,
                     product,
                     scaled,
@@ -377,18 +512,22 @@ impl<F: Field> Expression<F> {
             Expression::Product(a, b) => {
                 let a = a.evaluate(
                     constant,
+                    selector_column,
                     fixed_column,
                     advice_column,
                     instance_column,
+                    challenge,
                     sum,
                     product,
                     scaled,
                 );
                 let b = b.evaluate(
                     constant,
+                    selector_column,
                     fixed_column,
                     advice_column,
                     instance_column,
+                    challenge,
                     sum,
                     product,
                     scaled,
@@ -398,9 +537,11 @@ impl<F: Field> Expression<F> {
             Expression::Scaled(a, f) => {
                 let a = a.evaluate(
                     constant,
+                    selector_column,
                     fixed_column,
                     advice_column,
                     instance_column,
+                    challenge,
                     sum,
                     product,
                     scaled,
@@ -414,9 +555,11 @@ impl<F: Field> Expression<F> {
     pub fn degree(&self) -> usize {
         match self {
             Expression::Constant(_) => 0,
+            Expression::Selector(_, _) => 1,
             Expression::Fixed(_) => 1,
             Expression::Advice(_) => 1,
             Expression::Instance(_) => 1,
+            Expression::Challenge(_) => 0,
             Expression::Sum(a, b) => max(a.degree(), b.degree()),
             Expression::Product(a, b) => a.degree() + b.degree(),
             Expression::Scaled(poly, _) => poly.degree(),
@@ -424,6 +567,30 @@ impl<F: Field> Expression<F> {
     }
 }
 
+impl<F: Field> Expression<F> {
+    /// Returns the selectors queried by this expression, with the rotations at which
+    /// they are queried.
+    fn queried_selectors(&self) -> Vec<(Selector, Rotation)> {
+        self.evaluate(
+            &|_| vec![],
+            &|selector, at| vec![(selector, at)],
+            &|_| vec![],
+            &|_| vec![],
+            &|_| vec![],
+            &|_| vec![],
+            &|mut a, b| {
+                a.extend(b);
+                a
+            },
+            &|mut a, b| {
+                a.extend(b);
+                a
+            },
+            &|a, _| a,
+        )
+    }
+}
+
 impl<F: Field> Neg for Expression<F> {
     type Output = Expression<F>;
     fn neg(self) -> Self::Output {
@@ -459,6 +626,80 @@ impl<F> Mul<F> for Expression<F> {
     }
 }
 
+/// A constraint of a gate: a polynomial that must vanish on every row, with a name
+/// that is reported if it does not.
+#[derive(Clone, Debug)]
+pub struct Constraint<F: Field> {
+    name: &'static str,
+    poly: Expression<F>,
+}
+
+impl<F: Field> From<Expression<F>> for Constraint<F> {
+    fn from(poly: Expression<F>) -> Self {
+        Constraint { name: "", poly }
+    }
+}
+
+impl<F: Field> From<(&'static str, Expression<F>)> for Constraint<F> {
+    fn from((name, poly): (&'static str, Expression<F>)) -> Self {
+        Constraint { name, poly }
+    }
+}
+
+/// The constraints of a gate, as returned by the closure passed to
+/// [`ConstraintSystem::create_gate`].
+///
+/// A single [`Expression`] is a gate with one unnamed constraint. A `Vec` or array of
+/// constraints, each either an `Expression` or a `(name, Expression)` pair, is a gate
+/// with several constraints.
+pub trait IntoConstraints<F: Field> {
+    /// Returns the constraints of the gate.
+    fn into_constraints(self) -> Vec<Constraint<F>>;
+}
+
+impl<F: Field> IntoConstraints<F> for Expression<F> {
+    fn into_constraints(self) -> Vec<Constraint<F>> {
+        vec![self.into()]
+    }
+}
+
+impl<F: Field, C: Into<Constraint<F>>> IntoConstraints<F> for Vec<C> {
+    fn into_constraints(self) -> Vec<Constraint<F>> {
+        self.into_iter().map(Into::into).collect()
+    }
+}
+
+impl<F: Field, C: Into<Constraint<F>>, const N: usize> IntoConstraints<F> for [C; N] {
+    fn into_constraints(self) -> Vec<Constraint<F>> {
+        Vec::from(self).into_constraints()
+    }
+}
+
+/// A gate of a constraint system, and the names of its constraints.
+#[derive(Clone, Debug)]
+pub(crate) struct Gate<F: Field> {
+    name: &'static str,
+    constraint_names: Vec<&'static str>,
+    polys: Vec<Expression<F>>,
+}
+
+impl<F: Field> Gate<F> {
+    /// Returns the name of the gate.
+    pub(crate) fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Returns the name of the constraint of this gate with the given index.
+    pub(crate) fn constraint_name(&self, constraint_index: usize) -> &'static str {
+        self.constraint_names[constraint_index]
+    }
+
+    /// Returns the polynomials of the constraints of this gate.
+    pub(crate) fn polynomials(&self) -> &[Expression<F>] {
+        &self.polys
+    }
+}
+
 /// Represents an index into a vector where each entry corresponds to a distinct
 /// point that polynomials are queried at.
 #[derive(Copy, Clone, Debug)]
@@ -471,7 +712,18 @@ pub struct ConstraintSystem<F: Field> {
     pub(crate) num_fixed_columns: usize,
     pub(crate) num_advice_columns: usize,
     pub(crate) num_instance_columns: usize,
-    pub(crate) gates: Vec<(&'static str, Expression<F>)>,
+    pub(crate) num_selectors: usize,
+    pub(crate) num_challenges: usize,
+    pub(crate) advice_column_phase: Vec<Phase>,
+    pub(crate) challenge_phase: Vec<Phase>,
+    // The indices of the advice columns that are committed to without blinding.
+    pub(crate) unblinded_advice_columns: Vec<usize>,
+    // The advice columns introduced by splitting gates, with the expression whose
+    // value each of them holds on every row.
+    pub(crate) intermediate_columns: Vec<(Column<Advice>, Expression<F>)>,
+    // Whether rows at the end of the domain are filled with random values.
+    pub(crate) blinding_rows: bool,
+    pub(crate) gates: Vec<Gate<F>>,
     pub(crate) advice_queries: Vec<(Column<Advice>, Rotation)>,
     pub(crate) instance_queries: Vec<(Column<Instance>, Rotation)>,
     pub(crate) fixed_queries: Vec<(Column<Fixed>, Rotation)>,
@@ -483,28 +735,129 @@ pub struct ConstraintSystem<F: Field> {
     // Vector of lookup arguments, where each corresponds to a sequence of
     // input expressions and a sequence of table expressions involved in the lookup.
     pub(crate) lookups: Vec<lookup::Argument<F>>,
+
+    // The argument with which the lookups are proven.
+    pub(crate) lookup_argument: LookupArgument,
+
+    // Vector of shuffle arguments, where each corresponds to a sequence of input
+    // expressions and a sequence of shuffle expressions involved in the shuffle.
+    pub(crate) shuffles: Vec<shuffle::Argument<F>>,
+}
+
+/// The argument with which a [`ConstraintSystem`] proves its lookups.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum LookupArgument {
+    /// The argument of the [PLONKup][plookup] family, which commits to a permutation of
+    /// the inputs and of the table, and to a grand product, for each lookup.
+    ///
+    /// [plookup]: https://eprint.iacr.org/2020/315
+    #[default]
+    Permutation,
+    /// The log-derivative argument of [logUp][logup], which commits to the
+    /// multiplicity of each table row and to a running sum, for each lookup.
+    ///
+    /// [logup]: https://eprint.iacr.org/2022/1530
+    LogDerivative,
 }
 
 /// Represents the minimal parameters that determine a `ConstraintSystem`.
-#[derive(Debug)]
 pub struct PinnedConstraintSystem<'a, F: Field> {
     num_fixed_columns: &'a usize,
     num_advice_columns: &'a usize,
     num_instance_columns: &'a usize,
+    num_challenges: &'a usize,
+    advice_column_phase: &'a Vec<Phase>,
+    challenge_phase: &'a Vec<Phase>,
+    unblinded_advice_columns: &'a Vec<usize>,
+    blinding_rows: &'a bool,
     gates: PinnedGates<'a, F>,
     advice_queries: &'a Vec<(Column<Advice>, Rotation)>,
     instance_queries: &'a Vec<(Column<Instance>, Rotation)>,
     fixed_queries: &'a Vec<(Column<Fixed>, Rotation)>,
     permutations: &'a Vec<permutation::Argument>,
     lookups: &'a Vec<lookup::Argument<F>>,
+    lookup_argument: &'a LookupArgument,
+    shuffles: &'a Vec<shuffle::Argument<F>>,
 }
 
-struct PinnedGates<'a, F: Field>(&'a Vec<(&'static str, Expression<F>)>);
+impl<'a, F: Field> std::fmt::Debug for PinnedConstraintSystem<'a, F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        let mut debug_struct = f.debug_struct("PinnedConstraintSystem");
+        debug_struct
+            .field("num_fixed_columns", self.num_fixed_columns)
+            .field("num_advice_columns", self.num_advice_columns)
+            .field("num_instance_columns", self.num_instance_columns);
+        // The phases are only pinned for circuits with more than one, so that the
+        // verifying keys of single-phase circuits are unchanged.
+        if self.advice_column_phase.iter().any(|phase| phase.0 > 0) {
+            debug_struct
+                .field("num_challenges", self.num_challenges)
+                .field("advice_column_phase", self.advice_column_phase)
+                .field("challenge_phase", self.challenge_phase);
+        }
+        // Likewise, the unblinded columns are only pinned for circuits that have any.
+        if !self.unblinded_advice_columns.is_empty() {
+            debug_struct.field("unblinded_advice_columns", self.unblinded_advice_columns);
+        }
+        // And the blinding rows only for circuits that reserve them.
+        if *self.blinding_rows {
+            debug_struct.field("blinding_rows", self.blinding_rows);
+        }
+        debug_struct
+            .field("gates", &self.gates)
+            .field("advice_queries", self.advice_queries)
+            .field("instance_queries", self.instance_queries)
+            .field("fixed_queries", self.fixed_queries)
+            .field("permutations", self.permutations)
+            .field("lookups", self.lookups);
+        // The lookup argument is only pinned if it is not the default, so that the
+        // verifying keys of circuits proving their lookups with permutations are
+        // unchanged.
+        if *self.lookup_argument != LookupArgument::default() {
+            debug_struct.field("lookup_argument", self.lookup_argument);
+        }
+        debug_struct.field("shuffles", self.shuffles).finish()
+    }
+}
+
+// Two constraint systems are compared by the parameters that are pinned, whether or
+// not their debug representation includes them.
+impl<'a, F: Field> PartialEq for PinnedConstraintSystem<'a, F> {
+    fn eq(&self, other: &Self) -> bool {
+        self.num_fixed_columns == other.num_fixed_columns
+            && self.num_advice_columns == other.num_advice_columns
+            && self.num_instance_columns == other.num_instance_columns
+            && self.num_challenges == other.num_challenges
+            && self.advice_column_phase == other.advice_column_phase
+            && self.challenge_phase == other.challenge_phase
+            && self.unblinded_advice_columns == other.unblinded_advice_columns
+            && self.blinding_rows == other.blinding_rows
+            && self.gates == other.gates
+            && self.advice_queries == other.advice_queries
+            && self.instance_queries == other.instance_queries
+            && self.fixed_queries == other.fixed_queries
+            && self.permutations == other.permutations
+            && self.lookups == other.lookups
+            && self.lookup_argument == other.lookup_argument
+            && self.shuffles == other.shuffles
+    }
+}
+
+struct PinnedGates<'a, F: Field>(&'a Vec<Gate<F>>);
+
+impl<'a, F: Field> PartialEq for PinnedGates<'a, F> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0
+            .iter()
+            .flat_map(|gate| gate.polynomials())
+            .eq(other.0.iter().flat_map(|gate| gate.polynomials()))
+    }
+}
 
 impl<'a, F: Field> std::fmt::Debug for PinnedGates<'a, F> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
         f.debug_list()
-            .entries(self.0.iter().map(|(_, expr)| expr))
+            .entries(self.0.iter().flat_map(|gate| gate.polynomials()))
             .finish()
     }
 }
@@ -515,12 +868,21 @@ impl<F: Field> Default for ConstraintSystem<F> {
             num_fixed_columns: 0,
             num_advice_columns: 0,
             num_instance_columns: 0,
+            num_selectors: 0,
+            num_challenges: 0,
+            advice_column_phase: Vec::new(),
+            challenge_phase: Vec::new(),
+            unblinded_advice_columns: Vec::new(),
+            intermediate_columns: Vec::new(),
+            blinding_rows: false,
             gates: vec![],
             fixed_queries: Vec::new(),
             advice_queries: Vec::new(),
             instance_queries: Vec::new(),
             permutations: Vec::new(),
             lookups: Vec::new(),
+            lookup_argument: LookupArgument::default(),
+            shuffles: Vec::new(),
         }
     }
 }
@@ -534,16 +896,28 @@ impl<F: Field> ConstraintSystem<F> {
             num_fixed_columns: &self.num_fixed_columns,
             num_advice_columns: &self.num_advice_columns,
             num_instance_columns: &self.num_instance_columns,
+            num_challenges: &self.num_challenges,
+            advice_column_phase: &self.advice_column_phase,
+            challenge_phase: &self.challenge_phase,
+            unblinded_advice_columns: &self.unblinded_advice_columns,
+            blinding_rows: &self.blinding_rows,
             gates: PinnedGates(&self.gates),
             fixed_queries: &self.fixed_queries,
             advice_queries: &self.advice_queries,
             instance_queries: &self.instance_queries,
             permutations: &self.permutations,
             lookups: &self.lookups,
+            lookup_argument: &self.lookup_argument,
+            shuffles: &self.shuffles,
         }
     }
 
     /// Add a permutation argument for some columns
+    ///
+    /// The argument does not raise the degree of the constraint system: if it has
+    /// more columns than the degree of the other constraints allows, its grand
+    /// product is split into chained chunks of columns, each with its own
+    /// commitment.
     pub fn permutation(&mut self, columns: &[Column<Any>]) -> Permutation {
         let index = self.permutations.len();
 
@@ -562,6 +936,16 @@ impl<F: Field> ConstraintSystem<F> {
     /// Add a lookup argument for some input expressions and table expressions.
     /// The function will panic if the number of input expressions and table
     /// expressions are not the same.
+    ///
+    /// The table expressions may query advice columns as well as fixed columns, in
+    /// which case the table is assigned by the prover, such as the trace of a memory
+    /// that the inputs read from. Every row of the table expressions is part of the
+    /// table, including rows that the circuit does not assign.
+    ///
+    /// Both the input and the table expressions may also query instance columns, so
+    /// public inputs can be looked up, or looked up in, without first being copied
+    /// into advice cells. The rows of an instance column beyond its public inputs are
+    /// zero.
     pub fn lookup(
         &mut self,
         input_expressions: &[Expression<F>],
@@ -577,9 +961,76 @@ impl<F: Field> ConstraintSystem<F> {
         index
     }
 
+    /// Add a lookup argument into one of the tables packed into `table_columns`, whose
+    /// rows are those where `table_tag` holds the value of the `tag` expression.
+    ///
+    /// The inputs are looked up in the first `input_expressions.len()` table columns,
+    /// so tables with fewer columns can share the columns of wider tables. The rows
+    /// that no table assigns have tag zero and zero values, so tables should have
+    /// non-zero tags, and the tag and inputs should all be zero on the rows where the
+    /// lookup is not enabled, such as by multiplying them by a selector. The tag may
+    /// depend on the row, to look up each row in a different table. The function will
+    /// panic if there are more input expressions than table columns.
+    pub fn lookup_tagged(
+        &mut self,
+        tag: Expression<F>,
+        input_expressions: &[Expression<F>],
+        table_tag: TableColumn,
+        table_columns: &[TableColumn],
+    ) -> usize {
+        assert!(input_expressions.len() <= table_columns.len());
+
+        let inputs: Vec<_> = iter::once(tag)
+            .chain(input_expressions.iter().cloned())
+            .collect();
+        let table: Vec<_> = iter::once(table_tag)
+            .chain(table_columns[..input_expressions.len()].iter().cloned())
+            .map(|column| self.query_fixed(column.inner(), Rotation::cur()))
+            .collect();
+
+        self.lookup(&inputs, &table)
+    }
+
+    /// Add a shuffle argument, which checks that the rows of some input expressions
+    /// are a permutation of the rows of some shuffle expressions, over every row of
+    /// the circuit. The function will panic if the number of input expressions and
+    /// shuffle expressions are not the same.
+    pub fn shuffle(
+        &mut self,
+        input_expressions: &[Expression<F>],
+        shuffle_expressions: &[Expression<F>],
+    ) -> usize {
+        assert_eq!(input_expressions.len(), shuffle_expressions.len());
+
+        let index = self.shuffles.len();
+
+        self.shuffles.push(shuffle::Argument::new(
+            input_expressions,
+            shuffle_expressions,
+        ));
+
+        index
+    }
+
+    /// Sets the argument with which all the lookups of this constraint system are
+    /// proven, which is [`LookupArgument::Permutation`] by default.
+    pub fn set_lookup_argument(&mut self, argument: LookupArgument) {
+        self.lookup_argument = argument;
+    }
+
+    /// Returns the lookups of this constraint system if they are proven with
+    /// `argument`, or no lookups otherwise.
+    pub(crate) fn lookups_proven_with(&self, argument: LookupArgument) -> &[lookup::Argument<F>] {
+        if self.lookup_argument == argument {
+            &self.lookups
+        } else {
+            &[]
+        }
+    }
+
     /// Query a selector at a relative position.
     pub fn query_selector(&mut self, selector: Selector, at: Rotation) -> Expression<F> {
-        Expression::Fixed(self.query_fixed_index(selector.0, at))
+        Expression::Selector(selector, at)
     }
 
     fn query_fixed_index(&mut self, column: Column<Fixed>, at: Rotation) -> usize {
@@ -711,17 +1162,189 @@ impl<F: Field> ConstraintSystem<F> {
         }
     }
 
-    /// Create a new gate
-    pub fn create_gate(&mut self, name: &'static str, f: impl FnOnce(&mut Self) -> Expression<F>) {
-        let poly = f(self);
-        self.gates.push((name, poly));
+    /// Create a new gate, whose constraints are returned by `f`.
+    ///
+    /// `f` can return a single [`Expression`], or several constraints, which a
+    /// [`MockProver`](crate::dev::MockProver) reports by their names when they are
+    /// not satisfied.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the gate has no constraints.
+    pub fn create_gate<C: IntoConstraints<F>>(
+        &mut self,
+        name: &'static str,
+        f: impl FnOnce(&mut Self) -> C,
+    ) {
+        let constraints = f(self).into_constraints();
+        assert!(!constraints.is_empty(), "gate {} has no constraints", name);
+        let (constraint_names, polys) = constraints
+            .into_iter()
+            .map(|constraint| (constraint.name, constraint.poly))
+            .unzip();
+        self.gates.push(Gate {
+            name,
+            constraint_names,
+            polys,
+        });
+    }
+
+    /// Splits the constraints of every gate so far whose degree exceeds `max_degree`
+    /// into constraints of degree at most `max_degree`.
+    ///
+    /// A factor of a product that makes a constraint exceed the bound is replaced by
+    /// a query of a new advice column, which a new gate of the same name constrains
+    /// to equal the factor on every row. The prover and the
+    /// [`MockProver`](crate::dev::MockProver) compute the values of these columns
+    /// from the cells that the factors query, after the circuit has been synthesized,
+    /// so circuits never assign them. Each column is in the first phase in which
+    /// the cells and challenges of its factor are available.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_degree` is less than 2, to which products cannot be split.
+    pub fn split_gates(&mut self, max_degree: usize) {
+        assert!(max_degree >= 2, "gates cannot be split below degree 2");
+
+        let mut gates = std::mem::take(&mut self.gates);
+        let mut definitions = vec![];
+        for gate in gates.iter_mut() {
+            for poly in gate.polys.iter_mut() {
+                if poly.degree() > max_degree {
+                    let split = self.split_expression(
+                        poly.clone(),
+                        max_degree,
+                        gate.name,
+                        &mut definitions,
+                    );
+                    *poly = split;
+                }
+            }
+        }
+        gates.extend(definitions);
+        self.gates = gates;
+    }
+
+    /// Returns `expression` with the factors of its products replaced by
+    /// intermediate columns until its degree is at most `max_degree`, pushing the gate
+    /// that defines each intermediate column to `definitions`.
+    fn split_expression(
+        &mut self,
+        expression: Expression<F>,
+        max_degree: usize,
+        name: &'static str,
+        definitions: &mut Vec<Gate<F>>,
+    ) -> Expression<F> {
+        match expression {
+            Expression::Sum(a, b) => {
+                let a = self.split_expression(*a, max_degree, name, definitions);
+                let b = self.split_expression(*b, max_degree, name, definitions);
+                a + b
+            }
+            Expression::Product(a, b) => {
+                let mut a = self.split_expression(*a, max_degree, name, definitions);
+                let mut b = self.split_expression(*b, max_degree, name, definitions);
+                // Each factor has a degree of at most max_degree, and of 1 once it is
+                // replaced, so at most both factors are replaced.
+                while a.degree() + b.degree() > max_degree {
+                    if a.degree() >= b.degree() {
+                        a = self.intermediate_column(a, name, definitions);
+                    } else {
+                        b = self.intermediate_column(b, name, definitions);
+                    }
+                }
+                a * b
+            }
+            Expression::Scaled(a, scalar) => {
+                self.split_expression(*a, max_degree, name, definitions) * scalar
+            }
+            expression => expression,
+        }
+    }
+
+    /// Allocates an advice column that holds the value of `expression` on every row,
+    /// and returns its query.
+    fn intermediate_column(
+        &mut self,
+        expression: Expression<F>,
+        name: &'static str,
+        definitions: &mut Vec<Gate<F>>,
+    ) -> Expression<F> {
+        let phase = expression.evaluate(
+            &|_| Phase::first(),
+            &|_, _| Phase::first(),
+            &|_| Phase::first(),
+            &|index| self.advice_column_phase[self.advice_queries[index].0.index()],
+            &|_| Phase::first(),
+            &|challenge| Phase(challenge.phase().0 + 1),
+            &max,
+            &max,
+            &|a, _| a,
+        );
+        let column = self.advice_column_in(phase);
+        let query = self.query_advice(column, Rotation::cur());
+        definitions.push(Gate {
+            name,
+            constraint_names: vec!["intermediate"],
+            polys: vec![expression.clone() - query.clone()],
+        });
+        self.intermediate_columns.push((column, expression));
+        query
+    }
+
+    /// Computes the values of the intermediate columns of `phase` that
+    /// [`ConstraintSystem::split_gates`] introduced, from the values of the cells and
+    /// challenges that they depend on, which must all have been assigned.
+    ///
+    /// Rows are reduced modulo the number of rows of `advice`, so that rotations wrap.
+    pub(crate) fn assign_intermediate_columns<V: DerefMut<Target = [F]>>(
+        &self,
+        phase: Phase,
+        selector: &impl Fn(Selector, usize) -> F,
+        fixed: &[impl Deref<Target = [F]>],
+        advice: &mut [V],
+        instance: &[impl Deref<Target = [F]>],
+        challenge: &impl Fn(Challenge) -> F,
+    ) {
+        for (column, expression) in self.intermediate_columns.iter() {
+            if self.advice_column_phase[column.index()] != phase {
+                continue;
+            }
+            let n = advice[column.index()].len() as i32;
+            let values: Vec<F> = (0..n)
+                .map(|row| {
+                    let resolve = |at: Rotation| (row + at.0).rem_euclid(n) as usize;
+                    expression.evaluate(
+                        &|scalar| scalar,
+                        &|selector_query, at| selector(selector_query, resolve(at)),
+                        &|index| {
+                            let (column, at) = self.fixed_queries[index];
+                            fixed[column.index()][resolve(at)]
+                        },
+                        &|index| {
+                            let (column, at) = self.advice_queries[index];
+                            advice[column.index()][resolve(at)]
+                        },
+                        &|index| {
+                            let (column, at) = self.instance_queries[index];
+                            instance[column.index()][resolve(at)]
+                        },
+                        challenge,
+                        &|a, b| a + b,
+                        &|a, b| a * b,
+                        &|a, scalar| a * scalar,
+                    )
+                })
+                .collect();
+            advice[column.index()].copy_from_slice(&values);
+        }
     }
 
     /// Allocate a new selector.
     pub fn selector(&mut self) -> Selector {
-        // TODO: Track selectors separately, and combine selectors where possible.
-        // https://github.com/zcash/halo2/issues/116
-        Selector(self.fixed_column())
+        let index = self.num_selectors;
+        self.num_selectors += 1;
+        Selector(index)
     }
 
     /// Allocate a new fixed column
@@ -734,16 +1357,108 @@ impl<F: Field> ConstraintSystem<F> {
         tmp
     }
 
-    /// Allocate a new advice column
+    /// Allocate a new fixed column for lookup tables
+    pub fn lookup_table_column(&mut self) -> TableColumn {
+        TableColumn(self.fixed_column())
+    }
+
+    /// Allocate a new advice column in the first phase
     pub fn advice_column(&mut self) -> Column<Advice> {
+        self.advice_column_in(Phase::first())
+    }
+
+    /// Allocate a new advice column in the given phase.
+    ///
+    /// Panics if the previous phase has no advice columns.
+    pub fn advice_column_in(&mut self, phase: Phase) -> Column<Advice> {
+        assert!(
+            phase.0 == 0 || self.advice_column_phase.contains(&Phase(phase.0 - 1)),
+            "Phase {:?} follows a phase without advice columns",
+            phase
+        );
         let tmp = Column {
             index: self.num_advice_columns,
             column_type: Advice,
         };
         self.num_advice_columns += 1;
+        self.advice_column_phase.push(phase);
+        tmp
+    }
+
+    /// Allocate a new advice column in the first phase, which is committed to
+    /// without blinding.
+    pub fn unblinded_advice_column(&mut self) -> Column<Advice> {
+        self.unblinded_advice_column_in(Phase::first())
+    }
+
+    /// Allocate a new advice column in the given phase, which is committed to
+    /// without blinding.
+    ///
+    /// The commitment to the column in a proof is then the commitment to its
+    /// values with a zero blinding factor, which anyone knowing the values can
+    /// recompute, such as to link proofs over the same values. The proof does not
+    /// hide the values of the column, so it must only hold values that need not be
+    /// hidden.
+    ///
+    /// Panics if the previous phase has no advice columns.
+    pub fn unblinded_advice_column_in(&mut self, phase: Phase) -> Column<Advice> {
+        let column = self.advice_column_in(phase);
+        self.unblinded_advice_columns.push(column.index());
+        column
+    }
+
+    /// Reserves rows at the end of the domain, of which there are
+    /// [`blinding_factors`](Self::blinding_factors), that the prover fills with random
+    /// values in every advice column but the unblinded ones.
+    ///
+    /// The commitments hide the advice columns, but the evaluations that the proof
+    /// reveals do not; the random rows mask them, so that the proof is
+    /// zero-knowledge. A circuit may not assign the rows, and its gates, lookup
+    /// inputs and shuffles must vanish on them, such as by being enabled by selectors.
+    pub fn reserve_blinding_rows(&mut self) {
+        self.blinding_rows = true;
+    }
+
+    /// Allocate a new challenge, squeezed after the advice columns of the given
+    /// phase are committed to.
+    ///
+    /// Panics if the phase has no advice columns.
+    pub fn challenge_usable_after(&mut self, phase: Phase) -> Challenge {
+        assert!(
+            self.advice_column_phase.contains(&phase),
+            "Challenge of phase {:?}, which has no advice columns",
+            phase
+        );
+        let tmp = Challenge {
+            index: self.num_challenges,
+            phase,
+        };
+        self.num_challenges += 1;
+        self.challenge_phase.push(phase);
         tmp
     }
 
+    /// Query a challenge, which can be used in the expressions of later phases.
+    ///
+    /// Gates, lookups and shuffles can all use the returned expression. A gate can
+    /// then enforce an identity over a random linear combination of values that were
+    /// committed to before the challenge was squeezed, such as the running product of
+    /// a permutation argument built in the circuit, with its terms in advice columns
+    /// of a later phase.
+    pub fn query_challenge(&mut self, challenge: Challenge) -> Expression<F> {
+        Expression::Challenge(challenge)
+    }
+
+    /// Returns the phases of the advice columns, in order.
+    pub(crate) fn phases(&self) -> impl Iterator<Item = Phase> {
+        let max = self
+            .advice_column_phase
+            .iter()
+            .max()
+            .map_or(0, |phase| phase.0 + 1);
+        (0..max).map(Phase)
+    }
+
     /// Allocate a new instance column
     pub fn instance_column(&mut self) -> Column<Instance> {
         let tmp = Column {
@@ -758,31 +1473,332 @@ impl<F: Field> ConstraintSystem<F> {
     /// constraints).
     pub fn degree(&self) -> usize {
         // The permutation argument will serve alongside the gates, so must be
-        // accounted for.
+        // accounted for. Its grand products are split into chunks where needed
+        // so that it fits within the degree of everything else.
+        let degree = self.degree_without_permutations();
+        self.permutations
+            .iter()
+            .map(|p| p.required_degree(degree))
+            .fold(degree, std::cmp::max)
+    }
+
+    /// Compute the maximum degree of all constraints other than those of the
+    /// permutation arguments, which the permutation arguments are chunked to fit.
+    pub(crate) fn degree_without_permutations(&self) -> usize {
+        // The lookup argument also serves alongside the gates and must be accounted
+        // for.
         let mut degree = self
-            .permutations
+            .lookups
             .iter()
-            .map(|p| p.required_degree())
+            .map(|l| l.required_degree())
             .max()
             .unwrap_or(1);
 
-        // The lookup argument also serves alongside the gates and must be accounted
+        // The shuffle argument also serves alongside the gates and must be accounted
         // for.
         degree = std::cmp::max(
             degree,
-            self.lookups
+            self.shuffles
                 .iter()
-                .map(|l| l.required_degree())
+                .map(|s| s.required_degree())
                 .max()
                 .unwrap_or(1),
         );
 
         // Account for each gate to ensure our quotient polynomial is the
         // correct degree and that our extended domain is the right size.
-        for (_, poly) in self.gates.iter() {
+        for poly in self.gates.iter().flat_map(|gate| gate.polynomials()) {
             degree = std::cmp::max(degree, poly.degree());
         }
 
         degree
     }
 }
+
+impl<F: FieldExt> ConstraintSystem<F> {
+    /// Returns the smallest `k` for which a circuit with this constraint system that
+    /// uses `rows` rows, counting those of its lookup tables, fits in the $2^k$ rows
+    /// of the evaluation domain, or `None` if the field does not support a domain of
+    /// that size.
+    ///
+    /// The rows reserved for blinding, of which there are
+    /// [`blinding_factors`](Self::blinding_factors), are counted on top of `rows`. The
+    /// extended domain, of $2^k$ times the degree of the constraint system, needs a
+    /// root of unity of its size in `F`, which limits `k`.
+    pub fn minimum_k(&self, rows: usize) -> Option<u32> {
+        let k = rows
+            .checked_add(self.blinding_factors())?
+            .checked_next_power_of_two()?
+            .trailing_zeros();
+        if k > F::S || crate::poly::extended_k(self.degree() as u32, k) > F::S {
+            return None;
+        }
+        Some(k)
+    }
+
+    /// Returns the number of rows at the end of the domain that are reserved for
+    /// blinding, which a circuit must not assign.
+    ///
+    /// This is zero unless the rows are reserved with
+    /// [`reserve_blinding_rows`](Self::reserve_blinding_rows), or by key generation for
+    /// a commitment scheme that is not [hiding][is_hiding].
+    /// The proof reveals the evaluation of each advice column at every rotation with
+    /// which it is queried, so the rows are one more than the most queries of any
+    /// advice column, and the prover fills them with random values. Gadgets should use
+    /// this, or [`usable_rows`](Self::usable_rows), rather than assume that every row
+    /// is available.
+    ///
+    /// [is_hiding]: crate::poly::commitment::CommitmentScheme::is_hiding
+    pub fn blinding_factors(&self) -> usize {
+        if !self.blinding_rows {
+            return 0;
+        }
+        let queries = (0..self.num_advice_columns)
+            .map(|index| {
+                self.advice_queries
+                    .iter()
+                    .filter(|(column, _)| column.index() == index)
+                    .count()
+            })
+            .max()
+            .unwrap_or(0);
+        queries + 1
+    }
+
+    /// Returns the rows that a circuit with this constraint system may assign in a
+    /// domain of $2^k$ rows, which are all rows but those reserved for blinding.
+    pub fn usable_rows(&self, k: u32) -> Range<usize> {
+        0..(1usize << k).saturating_sub(self.blinding_factors())
+    }
+
+    /// Combines the selectors of this constraint system into fixed columns, given the
+    /// rows on which each selector is enabled, and returns the constraint system in
+    /// which every selector query is replaced by a query of its column, along with the
+    /// values of the new fixed columns.
+    pub(crate) fn compress_selectors(mut self, selectors: Vec<Vec<bool>>) -> (Self, Vec<Vec<F>>) {
+        assert_eq!(selectors.len(), self.num_selectors);
+        let max_degree = self.degree();
+
+        // Selectors queried by lookups and shuffles, or alongside other selectors in a
+        // gate, keep a column each so that the degree of their expressions is unchanged.
+        let mut complex = vec![false; self.num_selectors];
+        let mut degrees = vec![1; self.num_selectors];
+        let arguments = self
+            .lookups
+            .iter()
+            .flat_map(|lookup| {
+                lookup
+                    .input_expressions
+                    .iter()
+                    .chain(lookup.table_expressions.iter())
+            })
+            .chain(self.shuffles.iter().flat_map(|shuffle| {
+                shuffle
+                    .input_expressions
+                    .iter()
+                    .chain(shuffle.shuffle_expressions.iter())
+            }));
+        for expression in arguments {
+            for (selector, _) in expression.queried_selectors() {
+                complex[selector.0] = true;
+            }
+        }
+        for poly in self.gates.iter().flat_map(|gate| gate.polynomials()) {
+            let mut queries: Vec<(Selector, Rotation)> = vec![];
+            for (selector, at) in poly.queried_selectors() {
+                degrees[selector.0] = max(degrees[selector.0], poly.degree());
+                if !queries.contains(&(selector, at)) {
+                    queries.push((selector, at));
+                }
+            }
+            if queries.len() > 1 {
+                for (selector, _) in queries {
+                    complex[selector.0] = true;
+                }
+            }
+        }
+
+        let (values, assignments) =
+            compress_selectors::process(&selectors, &degrees, &complex, max_degree);
+        let columns: Vec<_> = values.iter().map(|_| self.fixed_column()).collect();
+
+        // Replace each selector query by the indicator of its value in its column.
+        let mut queries: Vec<(Selector, Rotation)> = vec![];
+        let expressions = self
+            .gates
+            .iter()
+            .flat_map(|gate| gate.polynomials())
+            .chain(self.lookups.iter().flat_map(|lookup| {
+                lookup
+                    .input_expressions
+                    .iter()
+                    .chain(lookup.table_expressions.iter())
+            }))
+            .chain(self.shuffles.iter().flat_map(|shuffle| {
+                shuffle
+                    .input_expressions
+                    .iter()
+                    .chain(shuffle.shuffle_expressions.iter())
+            }));
+        for expression in expressions {
+            for query in expression.queried_selectors() {
+                if !queries.contains(&query) {
+                    queries.push(query);
+                }
+            }
+        }
+        let replacements: Vec<_> = queries
+            .into_iter()
+            .map(|(selector, at)| {
+                let assignment = assignments[selector.0];
+                let x =
+                    Expression::Fixed(self.query_fixed_index(columns[assignment.combination], at));
+                ((selector, at), compress_selectors::indicator(x, assignment))
+            })
+            .collect();
+        let replace = |expression: &Expression<F>| {
+            expression.evaluate(
+                &Expression::Constant,
+                &|selector, at| {
+                    replacements
+                        .iter()
+                        .find(|(query, _)| *query == (selector, at))
+                        .map(|(_, replacement)| replacement.clone())
+                        .expect("every selector query has a replacement")
+                },
+                &Expression::Fixed,
+                &Expression::Advice,
+                &Expression::Instance,
+                &Expression::Challenge,
+                &|a, b| a + b,
+                &|a, b| a * b,
+                &|a, scalar| a * scalar,
+            )
+        };
+
+        for poly in self.gates.iter_mut().flat_map(|gate| gate.polys.iter_mut()) {
+            *poly = replace(poly);
+        }
+        for lookup in self.lookups.iter_mut() {
+            for expression in lookup
+                .input_expressions
+                .iter_mut()
+                .chain(lookup.table_expressions.iter_mut())
+            {
+                *expression = replace(expression);
+            }
+        }
+        for shuffle in self.shuffles.iter_mut() {
+            for expression in shuffle
+                .input_expressions
+                .iter_mut()
+                .chain(shuffle.shuffle_expressions.iter_mut())
+            {
+                *expression = replace(expression);
+            }
+        }
+        for (_, expression) in self.intermediate_columns.iter_mut() {
+            *expression = replace(expression);
+        }
+        self.num_selectors = 0;
+        debug_assert!(self.degree() <= max_degree);
+
+        (self, values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ConstraintSystem;
+    use crate::pasta::Fp;
+    use crate::poly::Rotation;
+
+    #[test]
+    fn minimum_k() {
+        let mut cs = ConstraintSystem::<Fp>::default();
+        let a = cs.advice_column();
+        cs.create_gate("a^3 = 0", |meta| {
+            let a = meta.query_advice(a, Rotation::cur());
+            a.clone() * a.clone() * a
+        });
+
+        assert_eq!(cs.minimum_k(0), Some(0));
+        assert_eq!(cs.minimum_k(1), Some(0));
+        assert_eq!(cs.minimum_k(5), Some(3));
+        assert_eq!(cs.minimum_k(8), Some(3));
+        assert_eq!(cs.minimum_k(9), Some(4));
+
+        // The extended domain of the cubic gate is twice as large as the domain, so
+        // it outgrows the 2-adic subgroup of the field, of size 2^32, at k = 32.
+        assert_eq!(cs.minimum_k(1 << 31), Some(31));
+        assert_eq!(cs.minimum_k((1 << 31) + 1), None);
+        assert_eq!(cs.minimum_k(usize::MAX), None);
+    }
+
+    #[test]
+    fn usable_rows() {
+        let mut cs = ConstraintSystem::<Fp>::default();
+        let a = cs.advice_column();
+        cs.create_gate("a = 0", |meta| meta.query_advice(a, Rotation::cur()));
+
+        assert_eq!(cs.blinding_factors(), 0);
+        assert_eq!(cs.usable_rows(0), 0..1);
+        assert_eq!(cs.usable_rows(4), 0..16);
+
+        // The smallest domain for a number of rows has at least that many usable rows.
+        for rows in 1..40 {
+            let k = cs.minimum_k(rows).unwrap();
+            assert!(cs.usable_rows(k).len() >= rows);
+            assert!(k == 0 || cs.usable_rows(k - 1).len() < rows);
+        }
+    }
+
+    #[test]
+    fn pinned_equality() {
+        let configure = |rotation| {
+            let mut cs = ConstraintSystem::<Fp>::default();
+            let a = cs.advice_column();
+            cs.create_gate("a = 0", |meta| meta.query_advice(a, rotation));
+            cs
+        };
+
+        assert!(configure(Rotation::cur()).pinned() == configure(Rotation::cur()).pinned());
+        assert!(configure(Rotation::cur()).pinned() != configure(Rotation::next()).pinned());
+
+        // The parameters that are only pinned when they are set are compared too.
+        let mut cs = configure(Rotation::cur());
+        cs.reserve_blinding_rows();
+        assert!(cs.pinned() != configure(Rotation::cur()).pinned());
+    }
+
+    #[test]
+    fn blinding_rows() {
+        let mut cs = ConstraintSystem::<Fp>::default();
+        cs.reserve_blinding_rows();
+        let a = cs.advice_column();
+        let b = cs.advice_column();
+        cs.create_gate("a = b", |meta| {
+            meta.query_advice(a, Rotation::cur()) - meta.query_advice(b, Rotation::cur())
+        });
+
+        // Each column is queried once, so one row masks its evaluation, and one more.
+        assert_eq!(cs.blinding_factors(), 2);
+        assert_eq!(cs.usable_rows(4), 0..14);
+
+        cs.create_gate("a' = b", |meta| {
+            meta.query_advice(a, Rotation::next()) - meta.query_advice(b, Rotation::cur())
+        });
+        assert_eq!(cs.blinding_factors(), 3);
+        assert_eq!(cs.usable_rows(4), 0..13);
+        assert!(format!("{:?}", cs.pinned()).contains("blinding_rows: true"));
+
+        // The blinding rows are counted on top of the rows of the circuit.
+        assert_eq!(cs.minimum_k(5), Some(3));
+        assert_eq!(cs.minimum_k(6), Some(4));
+        for rows in 1..40 {
+            let k = cs.minimum_k(rows).unwrap();
+            assert!(cs.usable_rows(k).len() >= rows);
+            assert!(cs.usable_rows(k - 1).len() < rows);
+        }
+    }
+}
@@ -0,0 +1,181 @@
+//! Combination of mutually exclusive selectors into fixed columns.
+//!
+//! Selectors that are never enabled on the same row can share a fixed column, which
+//! takes the value $i$ on the rows where the $i$-th of its $k$ selectors is enabled and
+//! $0$ elsewhere. Each selector is then replaced by the Lagrange polynomial over
+//! $\{0, \dots, k\}$ that is one at $i$, which is of degree $k$, so a selector can only
+//! join a column if the gates it is queried in stay within the degree of the circuit.
+
+use std::iter;
+
+use super::Expression;
+use crate::arithmetic::FieldExt;
+
+/// The fixed column that a selector is combined into.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(super) struct SelectorAssignment {
+    /// The index of the combined column.
+    pub combination: usize,
+    /// The value of the combined column on the rows where the selector is enabled.
+    pub value: usize,
+    /// The number of selectors combined into the column.
+    pub len: usize,
+}
+
+/// Combines `selectors`, given by the rows on which each is enabled, into fixed
+/// columns, and returns the values of the columns and the assignment of each selector.
+///
+/// `degrees` holds the degree of the gates in which each selector is queried, which
+/// must not exceed `max_degree` once the selector is replaced. Selectors that are
+/// `complex` are assigned a column each.
+pub(super) fn process<F: FieldExt>(
+    selectors: &[Vec<bool>],
+    degrees: &[usize],
+    complex: &[bool],
+    max_degree: usize,
+) -> (Vec<Vec<F>>, Vec<SelectorAssignment>) {
+    let exclusive = |a: usize, b: usize| {
+        selectors[a]
+            .iter()
+            .zip(selectors[b].iter())
+            .all(|(a, b)| !(a & b))
+    };
+
+    let mut combinations: Vec<Vec<usize>> = vec![];
+    for selector in 0..selectors.len() {
+        let combination = if complex[selector] {
+            None
+        } else {
+            combinations.iter_mut().find(|combination| {
+                !complex[combination[0]]
+                    && combination
+                        .iter()
+                        .chain(iter::once(&selector))
+                        .all(|&member| degrees[member] + combination.len() <= max_degree)
+                    && combination
+                        .iter()
+                        .all(|&member| exclusive(member, selector))
+            })
+        };
+        match combination {
+            Some(combination) => combination.push(selector),
+            None => combinations.push(vec![selector]),
+        }
+    }
+
+    let mut assignments = vec![SelectorAssignment::default(); selectors.len()];
+    let columns = combinations
+        .iter()
+        .enumerate()
+        .map(|(index, combination)| {
+            let n = combination
+                .first()
+                .map_or(0, |&selector| selectors[selector].len());
+            let mut column = vec![F::zero(); n];
+            for (value, &selector) in (1..).zip(combination.iter()) {
+                assignments[selector] = SelectorAssignment {
+                    combination: index,
+                    value,
+                    len: combination.len(),
+                };
+                for (cell, enabled) in column.iter_mut().zip(selectors[selector].iter()) {
+                    if *enabled {
+                        *cell = F::from_u64(value as u64);
+                    }
+                }
+            }
+            column
+        })
+        .collect();
+
+    (columns, assignments)
+}
+
+/// Returns the expression in the combined column `x` that is one where `x` takes the
+/// value of `assignment`, and zero where it takes any other value in $0..=k$.
+pub(super) fn indicator<F: FieldExt>(
+    x: Expression<F>,
+    assignment: SelectorAssignment,
+) -> Expression<F> {
+    if assignment.len == 1 {
+        return x;
+    }
+
+    let value = F::from_u64(assignment.value as u64);
+    let mut expression = x.clone();
+    let mut denominator = value;
+    for j in (1..=assignment.len).filter(|&j| j != assignment.value) {
+        let j = F::from_u64(j as u64);
+        expression = expression * (x.clone() - Expression::Constant(j));
+        denominator *= value - j;
+    }
+    expression * denominator.invert().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{indicator, process, SelectorAssignment};
+    use crate::{arithmetic::FieldExt, pasta::Fp, plonk::Expression, poly::Rotation};
+
+    #[test]
+    fn combines_exclusive_selectors() {
+        let selectors = vec![
+            vec![true, false, false, false],
+            vec![false, true, false, false],
+            vec![true, false, true, false],
+            vec![false, false, false, true],
+        ];
+
+        // The third selector overlaps the first, and the fourth is complex.
+        let (columns, assignments) =
+            process::<Fp>(&selectors, &[2, 2, 2, 2], &[false, false, false, true], 4);
+        assert_eq!(
+            columns,
+            vec![
+                vec![Fp::one(), Fp::from_u64(2), Fp::zero(), Fp::zero()],
+                vec![Fp::one(), Fp::zero(), Fp::one(), Fp::zero()],
+                vec![Fp::zero(), Fp::zero(), Fp::zero(), Fp::one()],
+            ]
+        );
+        assert_eq!(
+            assignments[1],
+            SelectorAssignment {
+                combination: 0,
+                value: 2,
+                len: 2
+            }
+        );
+
+        // Without room for the degree, no selectors are combined.
+        let (columns, _) =
+            process::<Fp>(&selectors, &[4, 4, 4, 4], &[false, false, false, false], 4);
+        assert_eq!(columns.len(), 4);
+    }
+
+    #[test]
+    fn indicator_selects_value() {
+        let assignment = SelectorAssignment {
+            combination: 0,
+            value: 2,
+            len: 3,
+        };
+        let x = Expression::Selector(crate::plonk::Selector(0), Rotation::cur());
+        let expression = indicator::<Fp>(x, assignment);
+        assert_eq!(expression.degree(), 3);
+        for value in 0..=3 {
+            let eval = expression.evaluate(
+                &|scalar| scalar,
+                &|_, _| Fp::from_u64(value),
+                &|_| unreachable!(),
+                &|_| unreachable!(),
+                &|_| unreachable!(),
+                &|_| unreachable!(),
+                &|a, b| a + b,
+                &|a, b| a * b,
+                &|a, scalar| a * scalar,
+            );
+            let expected = if value == 2 { Fp::one() } else { Fp::zero() };
+            assert_eq!(eval, expected);
+        }
+    }
+}
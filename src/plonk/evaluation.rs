@@ -0,0 +1,408 @@
+//! Evaluation of the custom gates of a circuit over the extended domain.
+//!
+//! The gates are compiled once, when the proving key is created, into a flat list
+//! of calculations over the queried values of the columns. A subexpression that
+//! occurs several times, in one gate or across gates, is calculated once, and sums
+//! and products of a value with itself become doublings and squarings. The prover
+//! then runs the list at each point of the extended domain, one part of it at a
+//! time, instead of walking the expression tree of every gate.
+
+use ff::Field;
+use std::collections::HashMap;
+
+use super::circuit::{Column, ColumnType, ConstraintSystem, Expression};
+use crate::arithmetic::{CurveAffine, FieldExt};
+use crate::poly::{EvaluationDomain, PartLagrangeCoeff, Polynomial, Rotation};
+
+/// The source of a value used in a calculation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum ValueSource {
+    /// A constant, by its index in the constants of the evaluator
+    Constant(usize),
+    /// The result of a previous calculation, by its index
+    Intermediate(usize),
+    /// A fixed column, by its index and the index of its rotation
+    Fixed(usize, usize),
+    /// An advice column, by its index and the index of its rotation
+    Advice(usize, usize),
+    /// An instance column, by its index and the index of its rotation
+    Instance(usize, usize),
+    /// A challenge, by its index
+    Challenge(usize),
+}
+
+/// The constants zero and one, which every evaluator starts with.
+const ZERO: ValueSource = ValueSource::Constant(0);
+const ONE: ValueSource = ValueSource::Constant(1);
+
+/// The values from which the calculations of an evaluator are made.
+struct Values<'a, F: FieldExt> {
+    constants: &'a [F],
+    fixed: &'a [Polynomial<F, PartLagrangeCoeff>],
+    advice: &'a [Polynomial<F, PartLagrangeCoeff>],
+    instance: &'a [Polynomial<F, PartLagrangeCoeff>],
+    challenges: &'a [F],
+}
+
+impl ValueSource {
+    /// Returns this value at the point of a part of the extended domain whose
+    /// rotations are at the indices `rows`.
+    fn get<F: FieldExt>(&self, rows: &[usize], intermediates: &[F], values: &Values<F>) -> F {
+        match *self {
+            ValueSource::Constant(index) => values.constants[index],
+            ValueSource::Intermediate(index) => intermediates[index],
+            ValueSource::Fixed(column, rotation) => values.fixed[column][rows[rotation]],
+            ValueSource::Advice(column, rotation) => values.advice[column][rows[rotation]],
+            ValueSource::Instance(column, rotation) => values.instance[column][rows[rotation]],
+            ValueSource::Challenge(index) => values.challenges[index],
+        }
+    }
+}
+
+/// A calculation of the evaluator, whose result is stored as an intermediate value.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum Calculation {
+    Add(ValueSource, ValueSource),
+    Mul(ValueSource, ValueSource),
+    Double(ValueSource),
+    Square(ValueSource),
+}
+
+impl Calculation {
+    fn evaluate<F: FieldExt>(&self, rows: &[usize], intermediates: &[F], values: &Values<F>) -> F {
+        let get = |source: &ValueSource| source.get(rows, intermediates, values);
+        match self {
+            Calculation::Add(a, b) => get(a) + get(b),
+            Calculation::Mul(a, b) => get(a) * get(b),
+            Calculation::Double(a) => get(a).double(),
+            Calculation::Square(a) => get(a).square(),
+        }
+    }
+}
+
+/// The custom gates of a circuit, compiled for their evaluation by the prover.
+#[derive(Clone, Debug)]
+pub(crate) struct Evaluator<C: CurveAffine> {
+    /// The constants of the gates, starting with zero and one
+    constants: Vec<C::Scalar>,
+    /// The offset in a part of the extended domain of each rotation at which a column
+    /// is queried
+    rotations: Vec<usize>,
+    /// The calculations, in order, the result of each being the intermediate value
+    /// of the same index
+    calculations: Vec<Calculation>,
+    /// The value of each gate
+    gates: Vec<ValueSource>,
+    /// The mask that wraps an index into a part of the extended domain
+    mask: usize,
+}
+
+impl<C: CurveAffine> Evaluator<C> {
+    /// Compiles the gates of `cs`, for the parts of the extended domain of `domain`.
+    pub(crate) fn new(
+        domain: &EvaluationDomain<C::Scalar>,
+        cs: &ConstraintSystem<C::Scalar>,
+    ) -> Self {
+        let mut ev = Evaluator {
+            constants: vec![C::Scalar::zero(), C::Scalar::one()],
+            rotations: vec![],
+            calculations: vec![],
+            gates: vec![],
+            mask: (1 << domain.k()) - 1,
+        };
+
+        let queries = Queries {
+            fixed: ev.add_queries(domain, &cs.fixed_queries),
+            advice: ev.add_queries(domain, &cs.advice_queries),
+            instance: ev.add_queries(domain, &cs.instance_queries),
+        };
+
+        let mut cache = HashMap::new();
+        for poly in cs.gates.iter().flat_map(|gate| gate.polynomials()) {
+            let value = ev.add_expression(&mut cache, &queries, poly);
+            ev.gates.push(value);
+        }
+        ev
+    }
+
+    /// Returns the column and the rotation index of each of `queries`.
+    fn add_queries<T: ColumnType>(
+        &mut self,
+        domain: &EvaluationDomain<C::Scalar>,
+        queries: &[(Column<T>, Rotation)],
+    ) -> Vec<(usize, usize)> {
+        queries
+            .iter()
+            .map(|(column, at)| (column.index(), self.add_rotation(domain, *at)))
+            .collect()
+    }
+
+    fn add_rotation(&mut self, domain: &EvaluationDomain<C::Scalar>, at: Rotation) -> usize {
+        let offset = domain.part_rotation(at);
+        match self.rotations.iter().position(|&o| o == offset) {
+            Some(index) => index,
+            None => {
+                self.rotations.push(offset);
+                self.rotations.len() - 1
+            }
+        }
+    }
+
+    fn add_constant(&mut self, constant: C::Scalar) -> ValueSource {
+        match self.constants.iter().position(|&c| c == constant) {
+            Some(index) => ValueSource::Constant(index),
+            None => {
+                self.constants.push(constant);
+                ValueSource::Constant(self.constants.len() - 1)
+            }
+        }
+    }
+
+    /// Adds `calculation`, unless an equal calculation was already added, and returns
+    /// the source of its result.
+    fn add_calculation(
+        &mut self,
+        cache: &mut HashMap<Calculation, usize>,
+        calculation: Calculation,
+    ) -> ValueSource {
+        let calculations = &mut self.calculations;
+        let index = *cache.entry(calculation).or_insert_with_key(|calculation| {
+            calculations.push(calculation.clone());
+            calculations.len() - 1
+        });
+        ValueSource::Intermediate(index)
+    }
+
+    fn add_expression(
+        &mut self,
+        cache: &mut HashMap<Calculation, usize>,
+        queries: &Queries,
+        expression: &Expression<C::Scalar>,
+    ) -> ValueSource {
+        match expression {
+            Expression::Constant(scalar) => self.add_constant(*scalar),
+            Expression::Selector(_, _) => {
+                panic!("virtual selectors are removed during optimization")
+            }
+            Expression::Fixed(index) => {
+                let (column, rotation) = queries.fixed[*index];
+                ValueSource::Fixed(column, rotation)
+            }
+            Expression::Advice(index) => {
+                let (column, rotation) = queries.advice[*index];
+                ValueSource::Advice(column, rotation)
+            }
+            Expression::Instance(index) => {
+                let (column, rotation) = queries.instance[*index];
+                ValueSource::Instance(column, rotation)
+            }
+            Expression::Challenge(challenge) => ValueSource::Challenge(challenge.index()),
+            Expression::Sum(a, b) => {
+                let a = self.add_expression(cache, queries, a);
+                let b = self.add_expression(cache, queries, b);
+                self.add_sum(cache, a, b)
+            }
+            Expression::Product(a, b) => {
+                let a = self.add_expression(cache, queries, a);
+                let b = self.add_expression(cache, queries, b);
+                match (a, b) {
+                    (ZERO, _) | (_, ZERO) => ZERO,
+                    (ONE, value) | (value, ONE) => value,
+                    _ => self.add_product(cache, a, b),
+                }
+            }
+            Expression::Scaled(a, scalar) => {
+                let a = self.add_expression(cache, queries, a);
+                let scalar = self.add_constant(*scalar);
+                match (a, scalar) {
+                    (ZERO, _) | (_, ZERO) => ZERO,
+                    (ONE, value) | (value, ONE) => value,
+                    _ => self.add_product(cache, a, scalar),
+                }
+            }
+        }
+    }
+
+    fn add_sum(
+        &mut self,
+        cache: &mut HashMap<Calculation, usize>,
+        a: ValueSource,
+        b: ValueSource,
+    ) -> ValueSource {
+        match (a, b) {
+            (ZERO, value) | (value, ZERO) => value,
+            _ if a == b => self.add_calculation(cache, Calculation::Double(a)),
+            // Order the operands, so that a + b and b + a are calculated once.
+            _ => self.add_calculation(cache, Calculation::Add(a.min(b), a.max(b))),
+        }
+    }
+
+    fn add_product(
+        &mut self,
+        cache: &mut HashMap<Calculation, usize>,
+        a: ValueSource,
+        b: ValueSource,
+    ) -> ValueSource {
+        if a == b {
+            self.add_calculation(cache, Calculation::Square(a))
+        } else {
+            self.add_calculation(cache, Calculation::Mul(a.min(b), a.max(b)))
+        }
+    }
+
+    /// Folds the gates into `h`, the values of $h(X)$ at the points of a part of the
+    /// extended domain from index `start`, so that each gate $g_i$ is accumulated as
+    /// $h(X) \cdot y + g_i(X)$ in turn.
+    ///
+    /// The gates are evaluated at each point from the values of the columns they
+    /// query over the same part, rather than as polynomials, so that no polynomial is
+    /// allocated for a gate or its subexpressions, each column needs a single part
+    /// whatever its rotations, and no column is held over the whole extended domain.
+    pub(crate) fn fold_gates(
+        &self,
+        h: &mut [C::Scalar],
+        start: usize,
+        fixed: &[Polynomial<C::Scalar, PartLagrangeCoeff>],
+        advice: &[Polynomial<C::Scalar, PartLagrangeCoeff>],
+        instance: &[Polynomial<C::Scalar, PartLagrangeCoeff>],
+        challenges: &[C::Scalar],
+        y: C::Scalar,
+    ) {
+        let values = Values {
+            constants: &self.constants,
+            fixed,
+            advice,
+            instance,
+            challenges,
+        };
+        let mut rows = vec![0; self.rotations.len()];
+        let mut intermediates = vec![C::Scalar::zero(); self.calculations.len()];
+        for (i, h) in h.iter_mut().enumerate() {
+            let idx = start + i;
+            for (row, offset) in rows.iter_mut().zip(self.rotations.iter()) {
+                *row = (idx + offset) & self.mask;
+            }
+            for (index, calculation) in self.calculations.iter().enumerate() {
+                intermediates[index] = calculation.evaluate(&rows, &intermediates, &values);
+            }
+            *h = self.gates.iter().fold(*h, |acc, gate| {
+                acc * y + gate.get(&rows, &intermediates, &values)
+            });
+        }
+    }
+}
+
+/// The column and the rotation index of each query of a constraint system.
+struct Queries {
+    fixed: Vec<(usize, usize)>,
+    advice: Vec<(usize, usize)>,
+    instance: Vec<(usize, usize)>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Evaluator;
+    use crate::arithmetic::FieldExt;
+    use crate::pasta::{EqAffine, Fp};
+    use crate::plonk::{Column, ColumnType, ConstraintSystem, Expression};
+    use crate::poly::{EvaluationDomain, PartLagrangeCoeff, Polynomial, Rotation};
+
+    #[test]
+    fn matches_expression_evaluation() {
+        let mut cs = ConstraintSystem::<Fp>::default();
+        let a = cs.advice_column();
+        let b = cs.advice_column();
+        let f = cs.fixed_column();
+        let i = cs.instance_column();
+        cs.create_gate("shared subexpressions", |meta| {
+            let a_cur = meta.query_advice(a, Rotation::cur());
+            let a_next = meta.query_advice(a, Rotation::next());
+            let b = meta.query_advice(b, Rotation::prev());
+            let f = meta.query_fixed(f, Rotation::cur());
+            let i = meta.query_instance(i, Rotation::cur());
+            f.clone() * (a_cur.clone() * a_cur.clone() + b.clone() * Fp::from_u64(3))
+                + (i.clone() + i)
+                + Expression::Constant(Fp::one()) * a_next
+                - b * a_cur * f
+        });
+        cs.create_gate("repeated", |meta| {
+            let a_cur = meta.query_advice(a, Rotation::cur());
+            let b = meta.query_advice(b, Rotation::prev());
+            let f = meta.query_fixed(f, Rotation::cur());
+            (a_cur.clone() * a_cur + b * Fp::from_u64(3)) * Fp::one()
+                + Expression::Constant(Fp::zero()) * f
+        });
+
+        let domain = EvaluationDomain::<Fp>::new(cs.degree() as u32, 3);
+        let random_parts = |n: usize| -> Vec<Polynomial<Fp, PartLagrangeCoeff>> {
+            (0..n)
+                .map(|_| {
+                    let mut poly = domain.empty_coeff();
+                    for value in poly.iter_mut() {
+                        *value = Fp::rand();
+                    }
+                    domain.coeff_to_extended_part(poly, 1)
+                })
+                .collect()
+        };
+        let fixed = random_parts(1);
+        let advice = random_parts(2);
+        let instance = random_parts(1);
+        let y = Fp::rand();
+
+        let mut h_poly = random_parts(1).pop().unwrap();
+        let mut expected = h_poly.clone();
+        let evaluator = Evaluator::<EqAffine>::new(&domain, &cs);
+        // The gates can be folded into any chunk of a part of the extended domain.
+        let (low, high) = h_poly.split_at_mut(5);
+        evaluator.fold_gates(low, 0, &fixed, &advice, &instance, &[], y);
+        evaluator.fold_gates(high, 5, &fixed, &advice, &instance, &[], y);
+
+        // Evaluate the expressions of the gates directly at each point.
+        fn offsets<T: ColumnType>(
+            domain: &EvaluationDomain<Fp>,
+            queries: &[(Column<T>, Rotation)],
+        ) -> Vec<(usize, usize)> {
+            queries
+                .iter()
+                .map(|(column, at)| (column.index(), domain.part_rotation(*at)))
+                .collect()
+        }
+        let fixed_queries = offsets(&domain, &cs.fixed_queries);
+        let advice_queries = offsets(&domain, &cs.advice_queries);
+        let instance_queries = offsets(&domain, &cs.instance_queries);
+        let mask = (1 << domain.k()) - 1;
+        for (idx, expected) in expected.iter_mut().enumerate() {
+            let load = |queries: &[(usize, usize)], parts: &[Polynomial<Fp, _>], index: usize| {
+                let (column, offset) = queries[index];
+                parts[column][(idx + offset) & mask]
+            };
+            for gate in cs.gates.iter().flat_map(|gate| gate.polynomials()) {
+                *expected = *expected * y
+                    + gate.evaluate(
+                        &|scalar| scalar,
+                        &|_, _| unreachable!(),
+                        &|index| load(&fixed_queries, &fixed, index),
+                        &|index| load(&advice_queries, &advice, index),
+                        &|index| load(&instance_queries, &instance, index),
+                        &|_| unreachable!(),
+                        &|a, b| a + b,
+                        &|a, b| a * b,
+                        &|a, scalar| a * scalar,
+                    );
+            }
+        }
+        assert_eq!(
+            h_poly.iter().collect::<Vec<_>>(),
+            expected.iter().collect::<Vec<_>>()
+        );
+
+        // The subexpressions of the second gate were all calculated for the first.
+        let first = Evaluator::<EqAffine>::new(&domain, &{
+            let mut cs = cs.clone();
+            cs.gates.truncate(1);
+            cs
+        });
+        assert_eq!(first.calculations.len(), evaluator.calculations.len());
+    }
+}
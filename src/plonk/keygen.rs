@@ -1,18 +1,24 @@
 use ff::Field;
 use group::Curve;
+use std::ops::Range;
 
 use super::{
-    circuit::{Advice, Any, Assignment, Circuit, Column, ConstraintSystem, Fixed},
+    circuit::{
+        configure_circuit, Advice, Any, Assignment, Challenge, Circuit, Column, ConstraintSystem,
+        Fixed, Selector,
+    },
+    evaluation::Evaluator,
     permutation, Error, LagrangeCoeff, Permutation, Polynomial, ProvingKey, VerifyingKey,
 };
 use crate::arithmetic::CurveAffine;
 use crate::poly::{
-    commitment::{Blind, Params},
+    commitment::{Blind, CommitmentScheme},
     EvaluationDomain, Rotation,
 };
 
 pub(crate) fn create_domain<C, ConcreteCircuit>(
-    params: &Params<C>,
+    params: &impl CommitmentScheme<C>,
+    #[cfg(feature = "circuit-params")] circuit_params: ConcreteCircuit::Params,
 ) -> (
     EvaluationDomain<C::Scalar>,
     ConstraintSystem<C::Scalar>,
@@ -23,11 +29,17 @@ where
     ConcreteCircuit: Circuit<C::Scalar>,
 {
     let mut cs = ConstraintSystem::default();
+    if !params.is_hiding() {
+        cs.reserve_blinding_rows();
+    }
+    #[cfg(feature = "circuit-params")]
+    let config = ConcreteCircuit::configure_with_params(&mut cs, circuit_params);
+    #[cfg(not(feature = "circuit-params"))]
     let config = ConcreteCircuit::configure(&mut cs);
 
     let degree = cs.degree();
 
-    let domain = EvaluationDomain::new(degree as u32, params.k);
+    let domain = EvaluationDomain::new(degree as u32, params.k());
 
     (domain, cs, config)
 }
@@ -37,6 +49,9 @@ where
 struct Assembly<F: Field> {
     fixed: Vec<Polynomial<F, LagrangeCoeff>>,
     permutations: Vec<permutation::keygen::Assembly>,
+    selectors: Vec<Vec<bool>>,
+    // The rows that the circuit may assign, which exclude the blinding rows.
+    usable_rows: Range<usize>,
     _marker: std::marker::PhantomData<F>,
 }
 
@@ -53,6 +68,24 @@ impl<F: Field> Assignment<F> for Assembly<F> {
         // Do nothing; we don't care about regions in this context.
     }
 
+    fn enable_selector<A, AR>(&mut self, _: A, selector: &Selector, row: usize) -> Result<(), Error>
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        if !self.usable_rows.contains(&row) {
+            return Err(Error::BoundsFailure);
+        }
+
+        *self
+            .selectors
+            .get_mut(selector.0)
+            .and_then(|v| v.get_mut(row))
+            .ok_or(Error::BoundsFailure)? = true;
+
+        Ok(())
+    }
+
     fn assign_advice<V, A, AR>(
         &mut self,
         _: A,
@@ -81,6 +114,10 @@ impl<F: Field> Assignment<F> for Assembly<F> {
         A: FnOnce() -> AR,
         AR: Into<String>,
     {
+        if !self.usable_rows.contains(&row) {
+            return Err(Error::BoundsFailure);
+        }
+
         *self
             .fixed
             .get_mut(column.index())
@@ -99,7 +136,10 @@ impl<F: Field> Assignment<F> for Assembly<F> {
         right_row: usize,
     ) -> Result<(), Error> {
         // Check bounds first
-        if permutation.index() >= self.permutations.len() {
+        if permutation.index() >= self.permutations.len()
+            || !self.usable_rows.contains(&left_row)
+            || !self.usable_rows.contains(&right_row)
+        {
             return Err(Error::BoundsFailure);
         }
 
@@ -133,32 +173,60 @@ impl<F: Field> Assignment<F> for Assembly<F> {
     fn pop_namespace(&mut self, _: Option<String>) {
         // Do nothing; we don't care about namespaces in this context.
     }
+
+    fn get_challenge(&self, _: Challenge) -> Option<F> {
+        // Challenges are not known during key generation.
+        None
+    }
+
+    fn witness_required(&self) -> bool {
+        // We only care about fixed columns here
+        false
+    }
 }
 
 /// Generate a `VerifyingKey` from an instance of `Circuit`.
-pub fn keygen_vk<C, ConcreteCircuit>(
-    params: &Params<C>,
+///
+/// The circuit is synthesized without its witness: the values of advice cells are
+/// never computed, and [`Assignment::witness_required`] returns `false`, so that the
+/// circuit may skip its witness generation altogether.
+pub fn keygen_vk<C, P, ConcreteCircuit>(
+    params: &P,
     circuit: &ConcreteCircuit,
 ) -> Result<VerifyingKey<C>, Error>
 where
     C: CurveAffine,
+    P: CommitmentScheme<C>,
     ConcreteCircuit: Circuit<C::Scalar>,
 {
-    let (domain, cs, config) = create_domain::<C, ConcreteCircuit>(params);
+    let (domain, cs, config) = create_domain::<C, ConcreteCircuit>(
+        params,
+        #[cfg(feature = "circuit-params")]
+        circuit.params(),
+    );
 
     let mut assembly: Assembly<C::Scalar> = Assembly {
         fixed: vec![domain.empty_lagrange(); cs.num_fixed_columns],
         permutations: cs
             .permutations
             .iter()
-            .map(|p| permutation::keygen::Assembly::new(params.n as usize, p))
+            .map(|p| permutation::keygen::Assembly::new(params.n() as usize, p))
             .collect(),
+        selectors: vec![vec![false; params.n() as usize]; cs.num_selectors],
+        usable_rows: cs.usable_rows(params.k()),
         _marker: std::marker::PhantomData,
     };
 
     // Synthesize the circuit to obtain URS
     circuit.synthesize(&mut assembly, config)?;
 
+    let (cs, selector_values) = cs.compress_selectors(assembly.selectors.clone());
+    assembly.fixed.extend(
+        selector_values
+            .into_iter()
+            .map(|values| domain.lagrange_from_vec(values)),
+    );
+
     let permutation_helper = permutation::keygen::Assembly::build_helper(params, &cs, &domain);
 
     let permutation_vks = cs
@@ -179,51 +247,79 @@ where
         fixed_commitments,
         permutations: permutation_vks,
         cs,
+        selectors: assembly.selectors,
     })
 }
 
 /// Generate a `ProvingKey` from a `VerifyingKey` and an instance of `Circuit`.
-pub fn keygen_pk<C, ConcreteCircuit>(
-    params: &Params<C>,
+///
+/// The proving key reuses the domain and constraint system of `vk`, which may have been
+/// published or read with [`VerifyingKey::read`]. Returns
+/// [`Error::IncompatibleVerifyingKey`] if the constraint system or selectors of
+/// `circuit` differ from those of `vk`, or if its fixed columns or permutations are not
+/// those that `vk` commits to, which are committed to again to check them.
+pub fn keygen_pk<C, P, ConcreteCircuit>(
+    params: &P,
     vk: VerifyingKey<C>,
     circuit: &ConcreteCircuit,
 ) -> Result<ProvingKey<C>, Error>
 where
     C: CurveAffine,
+    P: CommitmentScheme<C>,
     ConcreteCircuit: Circuit<C::Scalar>,
 {
+    if params.k() != vk.domain.k() {
+        return Err(Error::IncompatibleParams);
+    }
+
     let mut cs = ConstraintSystem::default();
-    let config = ConcreteCircuit::configure(&mut cs);
+    if !params.is_hiding() {
+        cs.reserve_blinding_rows();
+    }
+    let config = configure_circuit(&mut cs, circuit);
 
     let mut assembly: Assembly<C::Scalar> = Assembly {
-        fixed: vec![vk.domain.empty_lagrange(); vk.cs.num_fixed_columns],
+        fixed: vec![vk.domain.empty_lagrange(); cs.num_fixed_columns],
         permutations: vk
             .cs
             .permutations
             .iter()
-            .map(|p| permutation::keygen::Assembly::new(params.n as usize, p))
+            .map(|p| permutation::keygen::Assembly::new(params.n() as usize, p))
             .collect(),
+        selectors: vec![vec![false; params.n() as usize]; cs.num_selectors],
+        usable_rows: cs.usable_rows(params.k()),
         _marker: std::marker::PhantomData,
     };
 
     // Synthesize the circuit to obtain URS
     circuit.synthesize(&mut assembly, config)?;
 
-    let fixed_polys: Vec<_> = assembly
-        .fixed
-        .iter()
-        .map(|poly| vk.domain.lagrange_to_coeff(poly.clone()))
-        .collect();
-
-    let fixed_cosets = vk
-        .cs
-        .fixed_queries
-        .iter()
-        .map(|&(column, at)| {
-            let poly = fixed_polys[column.index()].clone();
-            vk.domain.coeff_to_extended(poly, at)
-        })
-        .collect();
+    // The selectors are combined into the same columns as in the verifying key.
+    if assembly.selectors != vk.selectors {
+        return Err(Error::IncompatibleVerifyingKey);
+    }
+    let (cs, selector_values) = cs.compress_selectors(assembly.selectors);
+    if cs.pinned() != vk.cs.pinned() {
+        return Err(Error::IncompatibleVerifyingKey);
+    }
+    assembly.fixed.extend(
+        selector_values
+            .into_iter()
+            .map(|values| vk.domain.lagrange_from_vec(values)),
+    );
+
+    // The fixed columns are those that the key commits to.
+    if assembly.fixed.len() != vk.fixed_commitments.len()
+        || assembly
+            .fixed
+            .iter()
+            .zip(vk.fixed_commitments.iter())
+            .any(|(poly, commitment)| {
+                params.commit_lagrange(poly, Blind::default()).to_affine() != *commitment
+            })
+    {
+        return Err(Error::IncompatibleVerifyingKey);
+    }
 
     let permutation_helper =
         permutation::keygen::Assembly::build_helper(params, &vk.cs, &vk.domain);
@@ -234,6 +330,35 @@ where
         .iter()
         .zip(assembly.permutations.into_iter())
         .map(|(p, assembly)| assembly.build_pk(&vk.domain, &permutation_helper, p))
+        .collect::<Vec<_>>();
+
+    // And so are the permutations.
+    if permutation_pks
+        .iter()
+        .zip(vk.permutations.iter())
+        .any(|(pk, vk)| !pk.matches(params, vk))
+    {
+        return Err(Error::IncompatibleVerifyingKey);
+    }
+
+    Ok(build_pk(vk, assembly.fixed, permutation_pks))
+}
+
+/// Builds a proving key from the values of its fixed columns, precomputing the
+/// polynomials that the prover needs.
+pub(crate) fn build_pk<C: CurveAffine>(
+    vk: VerifyingKey<C>,
+    fixed_values: Vec<Polynomial<C::Scalar, LagrangeCoeff>>,
+    permutations: Vec<permutation::ProvingKey<C>>,
+) -> ProvingKey<C> {
+    let fixed_polys: Vec<_> = fixed_values
+        .iter()
+        .map(|poly| vk.domain.lagrange_to_coeff(poly.clone()))
+        .collect();
+
+    let fixed_cosets = fixed_polys
+        .iter()
+        .map(|poly| vk.domain.coeff_to_extended(poly.clone(), Rotation::cur()))
         .collect();
 
     // Compute l_0(X)
@@ -241,14 +366,18 @@ where
     let mut l0 = vk.domain.empty_lagrange();
     l0[0] = C::Scalar::one();
     let l0 = vk.domain.lagrange_to_coeff(l0);
-    let l0 = vk.domain.coeff_to_extended(l0, Rotation::cur());
+    let l0_coset = vk.domain.coeff_to_extended(l0.clone(), Rotation::cur());
 
-    Ok(ProvingKey {
+    let evaluator = Evaluator::new(&vk.domain, &vk.cs);
+
+    ProvingKey {
         vk,
         l0,
-        fixed_values: assembly.fixed,
+        l0_coset: Some(l0_coset),
+        fixed_values,
         fixed_polys,
-        fixed_cosets,
-        permutations: permutation_pks,
-    })
+        fixed_cosets: Some(fixed_cosets),
+        permutations,
+        evaluator,
+    }
 }
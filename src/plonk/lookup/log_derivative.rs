@@ -0,0 +1,14 @@
+//! The log-derivative lookup argument.
+//!
+//! For the compressed input expression $f(X)$ and table expression $t(X)$ of a
+//! lookup, the inputs are all in the table if and only if there are multiplicities
+//! $m_i$ such that
+//! $$\sum_i \frac{m_i}{\beta + t(\omega^i)} = \sum_i \frac{1}{\beta + f(\omega^i)}$$
+//! for a random $\beta$. The prover commits to $m(X)$, and to the running sum
+//! $\phi(X)$ of the difference of the two sides, and the constraint
+//! $$(\phi(\omega X) - \phi(X)) (f(X) + \beta) (t(X) + \beta) - m(X) (f(X) + \beta) + (t(X) + \beta) = 0$$
+//! holding on every row, including the last one where $\phi$ wraps around, shows that
+//! the sum is zero. This takes two committed columns per lookup instead of three.
+
+pub(crate) mod prover;
+pub(crate) mod verifier;
@@ -0,0 +1,356 @@
+use super::super::super::{ChallengeBeta, ChallengeTheta, ChallengeX, Error, ProvingKey};
+use super::super::{
+    prover::{compress_expressions, compress_expressions_part},
+    Argument,
+};
+use crate::{
+    arithmetic::{eval_polynomial, parallelize, BatchInvert, CurveAffine, FieldExt},
+    poly::{
+        commitment::{Blind, CommitmentScheme},
+        multiopen::ProverQuery,
+        Coeff, LagrangeCoeff, PartLagrangeCoeff, Polynomial, Rotation,
+    },
+    transcript::TranscriptWrite,
+};
+use ff::Field;
+use group::Curve;
+use rand::RngCore;
+use std::{collections::BTreeMap, iter};
+
+#[derive(Debug)]
+pub(in crate::plonk) struct Multiplicities<C: CurveAffine> {
+    compressed_input_expression: Polynomial<C::Scalar, LagrangeCoeff>,
+    compressed_table_expression: Polynomial<C::Scalar, LagrangeCoeff>,
+    multiplicity_expression: Polynomial<C::Scalar, LagrangeCoeff>,
+    multiplicity_poly: Polynomial<C::Scalar, Coeff>,
+    multiplicity_blind: Blind<C::Scalar>,
+}
+
+#[derive(Debug)]
+pub(in crate::plonk) struct Committed<C: CurveAffine> {
+    multiplicities: Multiplicities<C>,
+    sum_poly: Polynomial<C::Scalar, Coeff>,
+    sum_blind: Blind<C::Scalar>,
+}
+
+/// The values of the compressed expressions, the multiplicities and the running sum
+/// of a lookup over a part of the extended domain.
+pub(in crate::plonk) struct Part<C: CurveAffine> {
+    compressed_input: Polynomial<C::Scalar, PartLagrangeCoeff>,
+    compressed_table: Polynomial<C::Scalar, PartLagrangeCoeff>,
+    multiplicity: Polynomial<C::Scalar, PartLagrangeCoeff>,
+    sum: Polynomial<C::Scalar, PartLagrangeCoeff>,
+}
+
+pub(in crate::plonk) struct Constructed<C: CurveAffine> {
+    multiplicity_poly: Polynomial<C::Scalar, Coeff>,
+    multiplicity_blind: Blind<C::Scalar>,
+    sum_poly: Polynomial<C::Scalar, Coeff>,
+    sum_blind: Blind<C::Scalar>,
+}
+
+pub(in crate::plonk) struct Evaluated<C: CurveAffine> {
+    constructed: Constructed<C>,
+}
+
+impl<F: FieldExt> Argument<F> {
+    /// Given a Lookup with input expressions [A_0, A_1, ..., A_{m-1}] and table
+    /// expressions [S_0, S_1, ..., S_{m-1}], this method compresses them into A and S
+    /// as in the permutation argument, and commits to the multiplicity with which
+    /// each row of S occurs in A.
+    ///
+    /// A value that occurs in several rows of S is counted on the first of them.
+    pub(in crate::plonk) fn commit_multiplicities<
+        'a,
+        C,
+        P: CommitmentScheme<C>,
+        R: RngCore,
+        T: TranscriptWrite<C>,
+    >(
+        &self,
+        pk: &ProvingKey<C>,
+        params: &P,
+        theta: ChallengeTheta<C>,
+        advice_values: &'a [Polynomial<C::Scalar, LagrangeCoeff>],
+        fixed_values: &'a [Polynomial<C::Scalar, LagrangeCoeff>],
+        instance_values: &'a [Polynomial<C::Scalar, LagrangeCoeff>],
+        challenges: &'a [C::Scalar],
+        mut rng: R,
+        transcript: &mut T,
+    ) -> Result<Multiplicities<C>, Error>
+    where
+        C: CurveAffine<ScalarExt = F>,
+    {
+        let compress_expressions = |expressions| {
+            compress_expressions(
+                pk,
+                params.n() as usize,
+                theta,
+                expressions,
+                advice_values,
+                fixed_values,
+                instance_values,
+                challenges,
+            )
+        };
+
+        let (_, compressed_input_expression) = compress_expressions(&self.input_expressions);
+        let (_, compressed_table_expression) = compress_expressions(&self.table_expressions);
+
+        // Count the occurrences of each input value, and move the count of each to the
+        // first row of the table that has the value.
+        let mut leftover_input_map: BTreeMap<C::Scalar, u64> = compressed_input_expression
+            .iter()
+            .fold(BTreeMap::new(), |mut acc, coeff| {
+                *acc.entry(*coeff).or_insert(0) += 1;
+                acc
+            });
+        let multiplicities: Vec<_> = compressed_table_expression
+            .iter()
+            .map(|table_value| {
+                leftover_input_map
+                    .remove(table_value)
+                    .map_or(C::Scalar::zero(), C::Scalar::from_u64)
+            })
+            .collect();
+        if !leftover_input_map.is_empty() {
+            // Some input value is not in the table.
+            return Err(Error::ConstraintSystemFailure);
+        }
+        let multiplicities = pk.vk.domain.lagrange_from_vec(multiplicities);
+
+        let multiplicity_blind = Blind(C::Scalar::random(&mut rng));
+        let multiplicity_commitment = params
+            .commit_lagrange(&multiplicities, multiplicity_blind)
+            .to_affine();
+        let multiplicity_poly = pk.vk.domain.lagrange_to_coeff(multiplicities.clone());
+
+        // Hash multiplicity commitment
+        transcript
+            .write_point(multiplicity_commitment)
+            .map_err(|_| Error::TranscriptError)?;
+
+        Ok(Multiplicities {
+            compressed_input_expression,
+            compressed_table_expression,
+            multiplicity_expression: multiplicities,
+            multiplicity_poly,
+            multiplicity_blind,
+        })
+    }
+}
+
+impl<C: CurveAffine> Multiplicities<C> {
+    /// Given the compressed input expression A, table expression S and multiplicities
+    /// m, this method commits to the running sum
+    /// $\phi(\omega^{i+1}) = \phi(\omega^i) + m(\omega^i) / (S(\omega^i) + \beta) - 1 / (A(\omega^i) + \beta)$
+    /// starting from $\phi(\omega^0) = 0$.
+    pub(in crate::plonk) fn commit_sum<
+        P: CommitmentScheme<C>,
+        R: RngCore,
+        T: TranscriptWrite<C>,
+    >(
+        self,
+        pk: &ProvingKey<C>,
+        params: &P,
+        beta: ChallengeBeta<C>,
+        mut rng: R,
+        transcript: &mut T,
+    ) -> Result<Committed<C>, Error> {
+        let n = params.n() as usize;
+
+        // Batch invert to obtain 1 / (A(\omega^i) + \beta) and 1 / (S(\omega^i) + \beta)
+        let mut input_inverses = vec![C::Scalar::zero(); n];
+        let mut table_inverses = vec![C::Scalar::zero(); n];
+        parallelize(&mut input_inverses, |input_inverses, start| {
+            for (inverse, input_value) in input_inverses
+                .iter_mut()
+                .zip(self.compressed_input_expression[start..].iter())
+            {
+                *inverse = *beta + input_value;
+            }
+        });
+        parallelize(&mut table_inverses, |table_inverses, start| {
+            for (inverse, table_value) in table_inverses
+                .iter_mut()
+                .zip(self.compressed_table_expression[start..].iter())
+            {
+                *inverse = *beta + table_value;
+            }
+        });
+        input_inverses
+            .iter_mut()
+            .chain(table_inverses.iter_mut())
+            .batch_invert();
+
+        let multiplicities = &self.multiplicity_expression;
+
+        let sum: Vec<_> = iter::once(C::Scalar::zero())
+            .chain(
+                multiplicities
+                    .iter()
+                    .zip(table_inverses.iter())
+                    .zip(input_inverses.iter())
+                    .take(n - 1)
+                    .scan(C::Scalar::zero(), |state, ((m, table), input)| {
+                        *state += *m * table - input;
+                        Some(*state)
+                    }),
+            )
+            .collect();
+
+        #[cfg(feature = "sanity-checks")]
+        {
+            // The running sum wraps around to zero.
+            let last = n - 1;
+            assert_eq!(
+                sum[last] + multiplicities[last] * table_inverses[last] - input_inverses[last],
+                C::Scalar::zero()
+            );
+        }
+
+        let sum = pk.vk.domain.lagrange_from_vec(sum);
+        let sum_blind = Blind(C::Scalar::random(&mut rng));
+        let sum_commitment = params.commit_lagrange(&sum, sum_blind).to_affine();
+        let sum_poly = pk.vk.domain.lagrange_to_coeff(sum);
+
+        // Hash running sum commitment
+        transcript
+            .write_point(sum_commitment)
+            .map_err(|_| Error::TranscriptError)?;
+
+        Ok(Committed {
+            multiplicities: self,
+            sum_poly,
+            sum_blind,
+        })
+    }
+}
+
+impl<C: CurveAffine> Committed<C> {
+    /// Returns the values of this lookup over the `part`-th part of the extended
+    /// domain, compressing the expressions of `argument` from the values of the
+    /// columns over the same part.
+    pub(in crate::plonk) fn part(
+        &self,
+        pk: &ProvingKey<C>,
+        argument: &Argument<C::Scalar>,
+        theta: ChallengeTheta<C>,
+        advice: &[Polynomial<C::Scalar, PartLagrangeCoeff>],
+        fixed: &[Polynomial<C::Scalar, PartLagrangeCoeff>],
+        instance: &[Polynomial<C::Scalar, PartLagrangeCoeff>],
+        challenges: &[C::Scalar],
+        part: usize,
+    ) -> Part<C> {
+        let domain = &pk.vk.domain;
+        let compress = |expressions| {
+            compress_expressions_part(pk, theta, expressions, advice, fixed, instance, challenges)
+        };
+
+        Part {
+            compressed_input: compress(&argument.input_expressions),
+            compressed_table: compress(&argument.table_expressions),
+            multiplicity: domain
+                .coeff_to_extended_part(self.multiplicities.multiplicity_poly.clone(), part),
+            sum: domain.coeff_to_extended_part(self.sum_poly.clone(), part),
+        }
+    }
+
+    /// Drops the values of the expressions of the lookup, once its constraint has
+    /// been folded into $h(X)$.
+    pub(in crate::plonk) fn construct(self) -> Constructed<C> {
+        let multiplicities = self.multiplicities;
+        Constructed {
+            multiplicity_poly: multiplicities.multiplicity_poly,
+            multiplicity_blind: multiplicities.multiplicity_blind,
+            sum_poly: self.sum_poly,
+            sum_blind: self.sum_blind,
+        }
+    }
+}
+
+impl<C: CurveAffine> Part<C> {
+    /// Given the values of a Lookup over a part of the extended domain, this method
+    /// folds the constraint that must hold between them into `h`, the values of
+    /// $h(X)$ at the points of the part from index `start`, as $h(X) \cdot y + c(X)$.
+    pub(in crate::plonk) fn fold(
+        &self,
+        pk: &ProvingKey<C>,
+        beta: ChallengeBeta<C>,
+        y: C::Scalar,
+        h: &mut [C::Scalar],
+        start: usize,
+    ) {
+        let next = pk.vk.domain.part_rotation(Rotation::next());
+        let mask = self.sum.len() - 1;
+
+        // (\phi(\omega X) - \phi(X)) (A(X) + \beta) (S(X) + \beta) - m(X) (A(X) + \beta) + (S(X) + \beta)
+        for (i, h) in h.iter_mut().enumerate() {
+            let i = i + start;
+            let input = self.compressed_input[i] + &*beta;
+            let table = self.compressed_table[i] + &*beta;
+            let expression = (self.sum[(i + next) & mask] - &self.sum[i]) * &input * &table
+                - &(self.multiplicity[i] * &input)
+                + &table;
+            *h = *h * &y + &expression;
+        }
+    }
+}
+
+impl<C: CurveAffine> Constructed<C> {
+    pub(in crate::plonk) fn evaluate<T: TranscriptWrite<C>>(
+        self,
+        pk: &ProvingKey<C>,
+        x: ChallengeX<C>,
+        transcript: &mut T,
+    ) -> Result<Evaluated<C>, Error> {
+        let x_next = pk.vk.domain.rotate_omega(*x, Rotation::next());
+
+        let sum_eval = eval_polynomial(&self.sum_poly, *x);
+        let sum_next_eval = eval_polynomial(&self.sum_poly, x_next);
+        let multiplicity_eval = eval_polynomial(&self.multiplicity_poly, *x);
+
+        // Hash each evaluation
+        for eval in iter::empty()
+            .chain(Some(sum_eval))
+            .chain(Some(sum_next_eval))
+            .chain(Some(multiplicity_eval))
+        {
+            transcript
+                .write_scalar(eval)
+                .map_err(|_| Error::TranscriptError)?;
+        }
+
+        Ok(Evaluated { constructed: self })
+    }
+}
+
+impl<C: CurveAffine> Evaluated<C> {
+    pub(in crate::plonk) fn open<'a>(
+        &'a self,
+        pk: &'a ProvingKey<C>,
+        x: ChallengeX<C>,
+    ) -> impl Iterator<Item = ProverQuery<'a, C>> + Clone {
+        let x_next = pk.vk.domain.rotate_omega(*x, Rotation::next());
+
+        iter::empty()
+            // Open lookup running sum commitments at x
+            .chain(Some(ProverQuery {
+                point: *x,
+                poly: &self.constructed.sum_poly,
+                blind: self.constructed.sum_blind,
+            }))
+            // Open lookup running sum commitments at \omega x
+            .chain(Some(ProverQuery {
+                point: x_next,
+                poly: &self.constructed.sum_poly,
+                blind: self.constructed.sum_blind,
+            }))
+            // Open lookup multiplicity commitments at x
+            .chain(Some(ProverQuery {
+                point: *x,
+                poly: &self.constructed.multiplicity_poly,
+                blind: self.constructed.multiplicity_blind,
+            }))
+    }
+}
@@ -0,0 +1,151 @@
+use std::iter;
+
+use super::super::super::circuit::Expression;
+use super::super::Argument;
+use crate::{
+    arithmetic::{CurveAffine, FieldExt},
+    plonk::{ChallengeBeta, ChallengeTheta, ChallengeX, Error, VerifyingKey},
+    poly::{multiopen::VerifierQuery, Rotation},
+    transcript::TranscriptRead,
+};
+use ff::Field;
+
+pub struct MultiplicityCommitment<C: CurveAffine> {
+    multiplicity_commitment: C,
+}
+
+pub struct Committed<C: CurveAffine> {
+    multiplicities: MultiplicityCommitment<C>,
+    sum_commitment: C,
+}
+
+pub struct Evaluated<C: CurveAffine> {
+    committed: Committed<C>,
+    sum_eval: C::Scalar,
+    sum_next_eval: C::Scalar,
+    multiplicity_eval: C::Scalar,
+}
+
+impl<F: FieldExt> Argument<F> {
+    pub(in crate::plonk) fn read_multiplicity_commitment<C: CurveAffine, T: TranscriptRead<C>>(
+        &self,
+        transcript: &mut T,
+    ) -> Result<MultiplicityCommitment<C>, Error> {
+        let multiplicity_commitment = transcript
+            .read_point()
+            .map_err(|_| Error::TranscriptError)?;
+
+        Ok(MultiplicityCommitment {
+            multiplicity_commitment,
+        })
+    }
+}
+
+impl<C: CurveAffine> MultiplicityCommitment<C> {
+    pub(in crate::plonk) fn read_sum_commitment<T: TranscriptRead<C>>(
+        self,
+        transcript: &mut T,
+    ) -> Result<Committed<C>, Error> {
+        let sum_commitment = transcript
+            .read_point()
+            .map_err(|_| Error::TranscriptError)?;
+
+        Ok(Committed {
+            multiplicities: self,
+            sum_commitment,
+        })
+    }
+}
+
+impl<C: CurveAffine> Committed<C> {
+    pub(crate) fn evaluate<T: TranscriptRead<C>>(
+        self,
+        transcript: &mut T,
+    ) -> Result<Evaluated<C>, Error> {
+        let sum_eval = transcript
+            .read_scalar()
+            .map_err(|_| Error::TranscriptError)?;
+        let sum_next_eval = transcript
+            .read_scalar()
+            .map_err(|_| Error::TranscriptError)?;
+        let multiplicity_eval = transcript
+            .read_scalar()
+            .map_err(|_| Error::TranscriptError)?;
+
+        Ok(Evaluated {
+            committed: self,
+            sum_eval,
+            sum_next_eval,
+            multiplicity_eval,
+        })
+    }
+}
+
+impl<C: CurveAffine> Evaluated<C> {
+    pub(in crate::plonk) fn expressions<'a>(
+        &'a self,
+        argument: &'a Argument<C::Scalar>,
+        theta: ChallengeTheta<C>,
+        beta: ChallengeBeta<C>,
+        advice_evals: &[C::Scalar],
+        fixed_evals: &[C::Scalar],
+        instance_evals: &[C::Scalar],
+        challenges: &[C::Scalar],
+    ) -> impl Iterator<Item = C::Scalar> + 'a {
+        let compress_expressions = |expressions: &[Expression<C::Scalar>]| {
+            expressions
+                .iter()
+                .map(|expression| {
+                    expression.evaluate(
+                        &|scalar| scalar,
+                        &|_, _| panic!("virtual selectors are removed during optimization"),
+                        &|index| fixed_evals[index],
+                        &|index| advice_evals[index],
+                        &|index| instance_evals[index],
+                        &|challenge| challenges[challenge.index()],
+                        &|a, b| a + &b,
+                        &|a, b| a * &b,
+                        &|a, scalar| a * &scalar,
+                    )
+                })
+                .fold(C::Scalar::zero(), |acc, eval| acc * &*theta + &eval)
+        };
+        let input = compress_expressions(&argument.input_expressions) + &*beta;
+        let table = compress_expressions(&argument.table_expressions) + &*beta;
+
+        // (\phi(\omega X) - \phi(X)) (A(X) + \beta) (S(X) + \beta) - m(X) (A(X) + \beta) + (S(X) + \beta)
+        iter::once(
+            (self.sum_next_eval - &self.sum_eval) * &input * &table
+                - &(self.multiplicity_eval * &input)
+                + &table,
+        )
+    }
+
+    pub(in crate::plonk) fn queries<'a>(
+        &'a self,
+        vk: &'a VerifyingKey<C>,
+        x: ChallengeX<C>,
+    ) -> impl Iterator<Item = VerifierQuery<'a, C>> + Clone {
+        let x_next = vk.domain.rotate_omega(*x, Rotation::next());
+
+        iter::empty()
+            // Open lookup running sum commitments at x
+            .chain(Some(VerifierQuery {
+                point: *x,
+                commitment: &self.committed.sum_commitment,
+                eval: self.sum_eval,
+            }))
+            // Open lookup running sum commitments at \omega x
+            .chain(Some(VerifierQuery {
+                point: x_next,
+                commitment: &self.committed.sum_commitment,
+                eval: self.sum_next_eval,
+            }))
+            // Open lookup multiplicity commitments at x
+            .chain(Some(VerifierQuery {
+                point: *x,
+                commitment: &self.committed.multiplicities.multiplicity_commitment,
+                eval: self.multiplicity_eval,
+            }))
+    }
+}
@@ -4,16 +4,19 @@ use super::super::{
 };
 use super::Argument;
 use crate::{
-    arithmetic::{eval_polynomial, parallelize, BatchInvert, CurveAffine, FieldExt},
+    arithmetic::{
+        eval_polynomial, parallel_prefix_product, parallelize, BatchInvert, CurveAffine, FieldExt,
+    },
     poly::{
-        commitment::{Blind, Params},
+        commitment::{Blind, CommitmentScheme},
         multiopen::ProverQuery,
-        Coeff, EvaluationDomain, ExtendedLagrangeCoeff, LagrangeCoeff, Polynomial, Rotation,
+        Coeff, EvaluationDomain, LagrangeCoeff, PartLagrangeCoeff, Polynomial, Rotation,
     },
     transcript::TranscriptWrite,
 };
 use ff::Field;
 use group::Curve;
+use rand::RngCore;
 use std::{
     collections::BTreeMap,
     iter,
@@ -23,18 +26,13 @@ use std::{
 #[derive(Debug)]
 pub(in crate::plonk) struct Permuted<C: CurveAffine> {
     unpermuted_input_expressions: Vec<Polynomial<C::Scalar, LagrangeCoeff>>,
-    unpermuted_input_cosets: Vec<Polynomial<C::Scalar, ExtendedLagrangeCoeff>>,
     permuted_input_expression: Polynomial<C::Scalar, LagrangeCoeff>,
     permuted_input_poly: Polynomial<C::Scalar, Coeff>,
-    permuted_input_coset: Polynomial<C::Scalar, ExtendedLagrangeCoeff>,
-    permuted_input_inv_coset: Polynomial<C::Scalar, ExtendedLagrangeCoeff>,
     permuted_input_blind: Blind<C::Scalar>,
     permuted_input_commitment: C,
     unpermuted_table_expressions: Vec<Polynomial<C::Scalar, LagrangeCoeff>>,
-    unpermuted_table_cosets: Vec<Polynomial<C::Scalar, ExtendedLagrangeCoeff>>,
     permuted_table_expression: Polynomial<C::Scalar, LagrangeCoeff>,
     permuted_table_poly: Polynomial<C::Scalar, Coeff>,
-    permuted_table_coset: Polynomial<C::Scalar, ExtendedLagrangeCoeff>,
     permuted_table_blind: Blind<C::Scalar>,
     permuted_table_commitment: C,
 }
@@ -43,12 +41,20 @@ pub(in crate::plonk) struct Permuted<C: CurveAffine> {
 pub(in crate::plonk) struct Committed<C: CurveAffine> {
     permuted: Permuted<C>,
     product_poly: Polynomial<C::Scalar, Coeff>,
-    product_coset: Polynomial<C::Scalar, ExtendedLagrangeCoeff>,
-    product_inv_coset: Polynomial<C::Scalar, ExtendedLagrangeCoeff>,
     product_blind: Blind<C::Scalar>,
     product_commitment: C,
 }
 
+/// The values of the compressed expressions, the permuted expressions and the grand
+/// product of a lookup over a part of the extended domain.
+pub(in crate::plonk) struct Part<C: CurveAffine> {
+    compressed_input: Polynomial<C::Scalar, PartLagrangeCoeff>,
+    compressed_table: Polynomial<C::Scalar, PartLagrangeCoeff>,
+    permuted_input: Polynomial<C::Scalar, PartLagrangeCoeff>,
+    permuted_table: Polynomial<C::Scalar, PartLagrangeCoeff>,
+    product: Polynomial<C::Scalar, PartLagrangeCoeff>,
+}
+
 pub(in crate::plonk) struct Constructed<C: CurveAffine> {
     permuted_input_poly: Polynomial<C::Scalar, Coeff>,
     permuted_input_blind: Blind<C::Scalar>,
@@ -72,18 +78,23 @@ impl<F: FieldExt> Argument<F> {
     /// - constructs Permuted<C> struct using permuted_input_value = A', and
     ///   permuted_table_expression = S'.
     /// The Permuted<C> struct is used to update the Lookup, and is then returned.
-    pub(in crate::plonk) fn commit_permuted<'a, C, T: TranscriptWrite<C>>(
+    pub(in crate::plonk) fn commit_permuted<
+        'a,
+        C,
+        P: CommitmentScheme<C>,
+        R: RngCore,
+        T: TranscriptWrite<C>,
+    >(
         &self,
         pk: &ProvingKey<C>,
-        params: &Params<C>,
+        params: &P,
         domain: &EvaluationDomain<C::Scalar>,
         theta: ChallengeTheta<C>,
         advice_values: &'a [Polynomial<C::Scalar, LagrangeCoeff>],
         fixed_values: &'a [Polynomial<C::Scalar, LagrangeCoeff>],
         instance_values: &'a [Polynomial<C::Scalar, LagrangeCoeff>],
-        advice_cosets: &'a [Polynomial<C::Scalar, ExtendedLagrangeCoeff>],
-        fixed_cosets: &'a [Polynomial<C::Scalar, ExtendedLagrangeCoeff>],
-        instance_cosets: &'a [Polynomial<C::Scalar, ExtendedLagrangeCoeff>],
+        challenges: &'a [C::Scalar],
+        mut rng: R,
         transcript: &mut T,
     ) -> Result<Permuted<C>, Error>
     where
@@ -92,92 +103,32 @@ impl<F: FieldExt> Argument<F> {
     {
         // Closure to get values of expressions and compress them
         let compress_expressions = |expressions: &[Expression<C::Scalar>]| {
-            // Values of input expressions involved in the lookup
-            let unpermuted_expressions: Vec<_> = expressions
-                .iter()
-                .map(|expression| {
-                    expression.evaluate(
-                        &|scalar| pk.vk.domain.constant_lagrange(scalar),
-                        &|index| {
-                            let query = pk.vk.cs.fixed_queries[index];
-                            let column_index = query.0.index();
-                            let rotation = query.1;
-                            fixed_values[column_index].clone().rotate(rotation)
-                        },
-                        &|index| {
-                            let query = pk.vk.cs.advice_queries[index];
-                            let column_index = query.0.index();
-                            let rotation = query.1;
-                            advice_values[column_index].clone().rotate(rotation)
-                        },
-                        &|index| {
-                            let query = pk.vk.cs.instance_queries[index];
-                            let column_index = query.0.index();
-                            let rotation = query.1;
-                            instance_values[column_index].clone().rotate(rotation)
-                        },
-                        &|a, b| a + &b,
-                        &|a, b| {
-                            let mut modified_a = vec![C::Scalar::one(); params.n as usize];
-                            parallelize(&mut modified_a, |modified_a, start| {
-                                for ((modified_a, a), b) in modified_a
-                                    .iter_mut()
-                                    .zip(a[start..].iter())
-                                    .zip(b[start..].iter())
-                                {
-                                    *modified_a *= *a * b;
-                                }
-                            });
-                            pk.vk.domain.lagrange_from_vec(modified_a)
-                        },
-                        &|a, scalar| a * scalar,
-                    )
-                })
-                .collect();
-
-            let unpermuted_cosets: Vec<_> = expressions
-                .iter()
-                .map(|expression| {
-                    expression.evaluate(
-                        &|scalar| pk.vk.domain.constant_extended(scalar),
-                        &|index| fixed_cosets[index].clone(),
-                        &|index| advice_cosets[index].clone(),
-                        &|index| instance_cosets[index].clone(),
-                        &|a, b| a + &b,
-                        &|a, b| a * &b,
-                        &|a, scalar| a * scalar,
-                    )
-                })
-                .collect();
-
-            // Compressed version of expressions
-            let compressed_expression = unpermuted_expressions
-                .iter()
-                .fold(domain.empty_lagrange(), |acc, expression| {
-                    acc * *theta + expression
-                });
-
-            (
-                unpermuted_expressions,
-                unpermuted_cosets,
-                compressed_expression,
+            compress_expressions(
+                pk,
+                params.n() as usize,
+                theta,
+                expressions,
+                advice_values,
+                fixed_values,
+                instance_values,
+                challenges,
             )
         };
 
         // Closure to construct commitment to vector of values
-        let commit_values = |values: &Polynomial<C::Scalar, LagrangeCoeff>| {
+        let mut commit_values = |values: &Polynomial<C::Scalar, LagrangeCoeff>| {
             let poly = pk.vk.domain.lagrange_to_coeff(values.clone());
-            let blind = Blind(C::Scalar::rand());
+            let blind = Blind(C::Scalar::random(&mut rng));
             let commitment = params.commit_lagrange(&values, blind).to_affine();
             (poly, blind, commitment)
         };
 
         // Get values of input expressions involved in the lookup and compress them
-        let (unpermuted_input_expressions, unpermuted_input_cosets, compressed_input_expression) =
+        let (unpermuted_input_expressions, compressed_input_expression) =
             compress_expressions(&self.input_expressions);
 
         // Get values of table expressions involved in the lookup and compress them
-        let (unpermuted_table_expressions, unpermuted_table_cosets, compressed_table_expression) =
+        let (unpermuted_table_expressions, compressed_table_expression) =
             compress_expressions(&self.table_expressions);
 
         // Permute compressed (InputExpression, TableExpression) pair
@@ -205,33 +156,15 @@ impl<F: FieldExt> Argument<F> {
             .write_point(permuted_table_commitment)
             .map_err(|_| Error::TranscriptError)?;
 
-        let permuted_input_coset = pk
-            .vk
-            .domain
-            .coeff_to_extended(permuted_input_poly.clone(), Rotation::cur());
-        let permuted_input_inv_coset = pk
-            .vk
-            .domain
-            .coeff_to_extended(permuted_input_poly.clone(), Rotation(-1));
-        let permuted_table_coset = pk
-            .vk
-            .domain
-            .coeff_to_extended(permuted_table_poly.clone(), Rotation::cur());
-
         Ok(Permuted {
             unpermuted_input_expressions,
-            unpermuted_input_cosets,
             permuted_input_expression,
             permuted_input_poly,
-            permuted_input_coset,
-            permuted_input_inv_coset,
             permuted_input_blind,
             permuted_input_commitment,
             unpermuted_table_expressions,
-            unpermuted_table_cosets,
             permuted_table_expression,
             permuted_table_poly,
-            permuted_table_coset,
             permuted_table_blind,
             permuted_table_commitment,
         })
@@ -244,13 +177,18 @@ impl<C: CurveAffine> Permuted<C> {
     /// grand product polynomial over the lookup. The grand product polynomial
     /// is used to populate the Product<C> struct. The Product<C> struct is
     /// added to the Lookup and finally returned by the method.
-    pub(in crate::plonk) fn commit_product<T: TranscriptWrite<C>>(
+    pub(in crate::plonk) fn commit_product<
+        P: CommitmentScheme<C>,
+        R: RngCore,
+        T: TranscriptWrite<C>,
+    >(
         self,
         pk: &ProvingKey<C>,
-        params: &Params<C>,
+        params: &P,
         theta: ChallengeTheta<C>,
         beta: ChallengeBeta<C>,
         gamma: ChallengeGamma<C>,
+        mut rng: R,
         transcript: &mut T,
     ) -> Result<Committed<C>, Error> {
         // Goal is to compute the products of fractions
@@ -264,7 +202,7 @@ impl<C: CurveAffine> Permuted<C> {
         // s_j(X) is the jth table expression in this lookup,
         // s'(X) is the compression of the permuted table expressions,
         // and i is the ith row of the expression.
-        let mut lookup_product = vec![C::Scalar::zero(); params.n as usize];
+        let mut lookup_product = vec![C::Scalar::zero(); params.n() as usize];
         // Denominator uses the permuted input expression and permuted table expression
         parallelize(&mut lookup_product, |lookup_product, start| {
             for ((lookup_product, permuted_input_value), permuted_table_value) in lookup_product
@@ -321,13 +259,9 @@ impl<C: CurveAffine> Permuted<C> {
 
         // Compute the evaluations of the lookup product polynomial
         // over our domain, starting with z[0] = 1
-        let z = iter::once(C::Scalar::one())
-            .chain(lookup_product.into_iter().skip(1))
-            .scan(C::Scalar::one(), |state, cur| {
-                *state *= &cur;
-                Some(*state)
-            })
-            .collect::<Vec<_>>();
+        let mut z = lookup_product;
+        z[0] = C::Scalar::one();
+        parallel_prefix_product(&mut z);
         let z = pk.vk.domain.lagrange_from_vec(z);
 
         #[cfg(feature = "sanity-checks")]
@@ -335,7 +269,7 @@ impl<C: CurveAffine> Permuted<C> {
         // It can be used for debugging purposes.
         {
             // While in Lagrange basis, check that product is correctly constructed
-            let n = params.n as usize;
+            let n = params.n() as usize;
 
             // z'(X) (a'(X) + \beta) (s'(X) + \gamma)
             // - z'(\omega^{-1} X) (\theta^{m-1} a_0(X) + ... + a_{m-1}(X) + \beta) (\theta^{m-1} s_0(X) + ... + s_{m-1}(X) + \gamma)
@@ -369,11 +303,9 @@ impl<C: CurveAffine> Permuted<C> {
             }
         }
 
-        let product_blind = Blind(C::Scalar::rand());
+        let product_blind = Blind(C::Scalar::random(&mut rng));
         let product_commitment = params.commit_lagrange(&z, product_blind).to_affine();
         let z = pk.vk.domain.lagrange_to_coeff(z);
-        let product_coset = pk.vk.domain.coeff_to_extended(z.clone(), Rotation::cur());
-        let product_inv_coset = pk.vk.domain.coeff_to_extended(z.clone(), Rotation::prev());
 
         // Hash product commitment
         transcript
@@ -383,107 +315,114 @@ impl<C: CurveAffine> Permuted<C> {
         Ok(Committed::<C> {
             permuted: self,
             product_poly: z,
-            product_coset,
-            product_inv_coset,
             product_commitment,
             product_blind,
         })
     }
 }
 
-impl<'a, C: CurveAffine> Committed<C> {
-    /// Given a Lookup with input expressions, table expressions, permuted input
-    /// expression, permuted table expression, and grand product polynomial, this
-    /// method constructs constraints that must hold between these values.
-    /// This method returns the constraints as a vector of polynomials in
-    /// the extended evaluation domain.
-    pub(in crate::plonk) fn construct(
-        self,
-        pk: &'a ProvingKey<C>,
+impl<C: CurveAffine> Committed<C> {
+    /// Returns the values of this lookup over the `part`-th part of the extended
+    /// domain, compressing the expressions of `argument` from the values of the
+    /// columns over the same part.
+    pub(in crate::plonk) fn part(
+        &self,
+        pk: &ProvingKey<C>,
+        argument: &Argument<C::Scalar>,
         theta: ChallengeTheta<C>,
+        advice: &[Polynomial<C::Scalar, PartLagrangeCoeff>],
+        fixed: &[Polynomial<C::Scalar, PartLagrangeCoeff>],
+        instance: &[Polynomial<C::Scalar, PartLagrangeCoeff>],
+        challenges: &[C::Scalar],
+        part: usize,
+    ) -> Part<C> {
+        let domain = &pk.vk.domain;
+        let compress = |expressions: &[Expression<C::Scalar>]| {
+            compress_expressions_part(pk, theta, expressions, advice, fixed, instance, challenges)
+        };
+        let part_of =
+            |poly: &Polynomial<C::Scalar, Coeff>| domain.coeff_to_extended_part(poly.clone(), part);
+
+        Part {
+            compressed_input: compress(&argument.input_expressions),
+            compressed_table: compress(&argument.table_expressions),
+            permuted_input: part_of(&self.permuted.permuted_input_poly),
+            permuted_table: part_of(&self.permuted.permuted_table_poly),
+            product: part_of(&self.product_poly),
+        }
+    }
+
+    /// Drops the values of the expressions of the lookup, once its constraints have
+    /// been folded into $h(X)$.
+    pub(in crate::plonk) fn construct(self) -> Constructed<C> {
+        let permuted = self.permuted;
+        Constructed {
+            permuted_input_poly: permuted.permuted_input_poly,
+            permuted_input_blind: permuted.permuted_input_blind,
+            permuted_table_poly: permuted.permuted_table_poly,
+            permuted_table_blind: permuted.permuted_table_blind,
+            product_poly: self.product_poly,
+            product_blind: self.product_blind,
+        }
+    }
+}
+
+impl<C: CurveAffine> Part<C> {
+    /// Given the values of a Lookup over a part of the extended domain, this method
+    /// folds the constraints that must hold between them into `h`, the values of
+    /// $h(X)$ at the points of the part from index `start`, so that each constraint
+    /// is accumulated as $h(X) \cdot y + c(X)$ in turn.
+    pub(in crate::plonk) fn fold(
+        &self,
+        pk: &ProvingKey<C>,
+        l0: &Polynomial<C::Scalar, PartLagrangeCoeff>,
         beta: ChallengeBeta<C>,
         gamma: ChallengeGamma<C>,
-    ) -> (
-        Constructed<C>,
-        impl Iterator<Item = Polynomial<C::Scalar, ExtendedLagrangeCoeff>> + 'a,
+        y: C::Scalar,
+        h: &mut [C::Scalar],
+        start: usize,
     ) {
-        let permuted = self.permuted;
+        let prev = pk.vk.domain.part_rotation(Rotation::prev());
+        let mask = l0.len() - 1;
+
+        for (i, h) in h.iter_mut().enumerate() {
+            let i = i + start;
+            let i_prev = (i + prev) & mask;
+            let l0 = l0[i];
+            let product = self.product[i];
+            let permuted_input = self.permuted_input[i];
+            let permuted_table = self.permuted_table[i];
 
-        let expressions = iter::empty()
             // l_0(X) * (1 - z'(X)) = 0
-            .chain(Some(
-                Polynomial::one_minus(self.product_coset.clone()) * &pk.l0,
-            ))
+            *h = *h * &y + &(l0 * &(C::Scalar::one() - &product));
+
             // z'(X) (a'(X) + \beta) (s'(X) + \gamma)
             // - z'(\omega^{-1} X) (\theta^{m-1} a_0(X) + ... + a_{m-1}(X) + \beta) (\theta^{m-1} s_0(X) + ... + s_{m-1}(X) + \gamma)
-            .chain({
+            {
                 // z'(X) (a'(X) + \beta) (s'(X) + \gamma)
-                let mut left = self.product_coset.clone();
-                parallelize(&mut left, |left, start| {
-                    for ((left, permuted_input), permuted_table) in left
-                        .iter_mut()
-                        .zip(permuted.permuted_input_coset[start..].iter())
-                        .zip(permuted.permuted_table_coset[start..].iter())
-                    {
-                        *left *= &(*permuted_input + &(*beta));
-                        *left *= &(*permuted_table + &(*gamma));
-                    }
-                });
+                let left = product * &(permuted_input + &*beta) * &(permuted_table + &*gamma);
 
                 //  z'(\omega^{-1} X) (\theta^{m-1} a_0(X) + ... + a_{m-1}(X) + \beta) (\theta^{m-1} s_0(X) + ... + s_{m-1}(X) + \gamma)
-                let mut right = self.product_inv_coset;
-                parallelize(&mut right, |right, start| {
-                    for (i, right) in right.iter_mut().enumerate() {
-                        let i = i + start;
-
-                        // Compress the unpermuted input expressions
-                        let mut input_term = C::Scalar::zero();
-                        for input in permuted.unpermuted_input_cosets.iter() {
-                            input_term *= &*theta;
-                            input_term += &input[i];
-                        }
+                let right = self.product[i_prev]
+                    * &(self.compressed_input[i] + &*beta)
+                    * &(self.compressed_table[i] + &*gamma);
 
-                        // Compress the unpermuted table expressions
-                        let mut table_term = C::Scalar::zero();
-                        for table in permuted.unpermuted_table_cosets.iter() {
-                            table_term *= &*theta;
-                            table_term += &table[i];
-                        }
-
-                        // Add \beta and \gamma offsets
-                        *right *= &(input_term + &*beta);
-                        *right *= &(table_term + &*gamma);
-                    }
-                });
+                *h = *h * &y + &(left - &right);
+            }
 
-                Some(left - &right)
-            })
             // Check that the first values in the permuted input expression and permuted
             // fixed expression are the same.
             // l_0(X) * (a'(X) - s'(X)) = 0
-            .chain(Some(
-                (permuted.permuted_input_coset.clone() - &permuted.permuted_table_coset) * &pk.l0,
-            ))
+            *h = *h * &y + &((permuted_input - &permuted_table) * &l0);
+
             // Check that each value in the permuted lookup input expression is either
             // equal to the value above it, or the value at the same index in the
             // permuted table expression.
             // (a′(X)−s′(X))⋅(a′(X)−a′(\omega{-1} X)) = 0
-            .chain(Some(
-                (permuted.permuted_input_coset.clone() - &permuted.permuted_table_coset)
-                    * &(permuted.permuted_input_coset.clone() - &permuted.permuted_input_inv_coset),
-            ));
-
-        (
-            Constructed {
-                permuted_input_poly: permuted.permuted_input_poly,
-                permuted_input_blind: permuted.permuted_input_blind,
-                permuted_table_poly: permuted.permuted_table_poly,
-                permuted_table_blind: permuted.permuted_table_blind,
-                product_poly: self.product_poly,
-                product_blind: self.product_blind,
-            },
-            expressions,
-        )
+            *h = *h * &y
+                + &((permuted_input - &permuted_table)
+                    * &(permuted_input - &self.permuted_input[i_prev]));
+        }
     }
 }
 
@@ -562,6 +501,121 @@ impl<C: CurveAffine> Evaluated<C> {
     }
 }
 
+/// Returns the values of `expressions` over the domain, and the compression
+/// $\theta^{m-1} e_0 + ... + e_{m-1}$ of their values.
+#[allow(clippy::type_complexity)]
+pub(in crate::plonk) fn compress_expressions<C: CurveAffine>(
+    pk: &ProvingKey<C>,
+    n: usize,
+    theta: ChallengeTheta<C>,
+    expressions: &[Expression<C::Scalar>],
+    advice_values: &[Polynomial<C::Scalar, LagrangeCoeff>],
+    fixed_values: &[Polynomial<C::Scalar, LagrangeCoeff>],
+    instance_values: &[Polynomial<C::Scalar, LagrangeCoeff>],
+    challenges: &[C::Scalar],
+) -> (
+    Vec<Polynomial<C::Scalar, LagrangeCoeff>>,
+    Polynomial<C::Scalar, LagrangeCoeff>,
+) {
+    // Values of input expressions involved in the lookup
+    let unpermuted_expressions: Vec<_> = expressions
+        .iter()
+        .map(|expression| {
+            expression.evaluate(
+                &|scalar| pk.vk.domain.constant_lagrange(scalar),
+                &|_, _| panic!("virtual selectors are removed during optimization"),
+                &|index| {
+                    let query = pk.vk.cs.fixed_queries[index];
+                    let column_index = query.0.index();
+                    let rotation = query.1;
+                    fixed_values[column_index].clone().rotate(rotation)
+                },
+                &|index| {
+                    let query = pk.vk.cs.advice_queries[index];
+                    let column_index = query.0.index();
+                    let rotation = query.1;
+                    advice_values[column_index].clone().rotate(rotation)
+                },
+                &|index| {
+                    let query = pk.vk.cs.instance_queries[index];
+                    let column_index = query.0.index();
+                    let rotation = query.1;
+                    instance_values[column_index].clone().rotate(rotation)
+                },
+                &|challenge| {
+                    pk.vk
+                        .domain
+                        .constant_lagrange(challenges[challenge.index()])
+                },
+                &|a, b| a + &b,
+                &|a, b| {
+                    let mut modified_a = vec![C::Scalar::one(); n];
+                    parallelize(&mut modified_a, |modified_a, start| {
+                        for ((modified_a, a), b) in modified_a
+                            .iter_mut()
+                            .zip(a[start..].iter())
+                            .zip(b[start..].iter())
+                        {
+                            *modified_a *= *a * b;
+                        }
+                    });
+                    pk.vk.domain.lagrange_from_vec(modified_a)
+                },
+                &|a, scalar| a * scalar,
+            )
+        })
+        .collect();
+
+    // Compressed version of expressions
+    let compressed_expression = unpermuted_expressions
+        .iter()
+        .fold(pk.vk.domain.empty_lagrange(), |acc, expression| {
+            acc * *theta + expression
+        });
+
+    (unpermuted_expressions, compressed_expression)
+}
+
+/// Returns the compression $\theta^{m-1} e_0 + ... + e_{m-1}$ of the values of
+/// `expressions` over a part of the extended domain, given the values of the columns
+/// over the same part.
+pub(in crate::plonk) fn compress_expressions_part<C: CurveAffine>(
+    pk: &ProvingKey<C>,
+    theta: ChallengeTheta<C>,
+    expressions: &[Expression<C::Scalar>],
+    advice: &[Polynomial<C::Scalar, PartLagrangeCoeff>],
+    fixed: &[Polynomial<C::Scalar, PartLagrangeCoeff>],
+    instance: &[Polynomial<C::Scalar, PartLagrangeCoeff>],
+    challenges: &[C::Scalar],
+) -> Polynomial<C::Scalar, PartLagrangeCoeff> {
+    let domain = &pk.vk.domain;
+    expressions
+        .iter()
+        .fold(domain.empty_part(), |acc, expression| {
+            let values = expression.evaluate(
+                &|scalar| domain.constant_part(scalar),
+                &|_, _| panic!("virtual selectors are removed during optimization"),
+                &|index| {
+                    let (column, at) = pk.vk.cs.fixed_queries[index];
+                    fixed[column.index()].rotate(at)
+                },
+                &|index| {
+                    let (column, at) = pk.vk.cs.advice_queries[index];
+                    advice[column.index()].rotate(at)
+                },
+                &|index| {
+                    let (column, at) = pk.vk.cs.instance_queries[index];
+                    instance[column.index()].rotate(at)
+                },
+                &|challenge| domain.constant_part(challenges[challenge.index()]),
+                &|a, b| a + &b,
+                &|a, b| a * &b,
+                &|a, scalar| a * scalar,
+            );
+            acc * *theta + &values
+        })
+}
+
 type ExpressionPair<F> = (Polynomial<F, LagrangeCoeff>, Polynomial<F, LagrangeCoeff>);
 
 /// Given a vector of input values A and a vector of table values S,
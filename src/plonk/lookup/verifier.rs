@@ -107,6 +107,7 @@ impl<C: CurveAffine> Evaluated<C> {
         advice_evals: &[C::Scalar],
         fixed_evals: &[C::Scalar],
         instance_evals: &[C::Scalar],
+        challenges: &[C::Scalar],
     ) -> impl Iterator<Item = C::Scalar> + 'a {
         let product_expression = || {
             // z'(X) (a'(X) + \beta) (s'(X) + \gamma)
@@ -121,9 +122,11 @@ impl<C: CurveAffine> Evaluated<C> {
                     .map(|expression| {
                         expression.evaluate(
                             &|scalar| scalar,
+                            &|_, _| panic!("virtual selectors are removed during optimization"),
                             &|index| fixed_evals[index],
                             &|index| advice_evals[index],
                             &|index| instance_evals[index],
+                            &|challenge| challenges[challenge.index()],
                             &|a, b| a + &b,
                             &|a, b| a * &b,
                             &|a, scalar| a * &scalar,
@@ -2,8 +2,8 @@
 
 use super::circuit::{Any, Column};
 use crate::{
-    arithmetic::CurveAffine,
-    poly::{Coeff, ExtendedLagrangeCoeff, LagrangeCoeff, Polynomial},
+    arithmetic::{CurveAffine, FieldExt},
+    poly::{Coeff, EvaluationDomain, ExtendedLagrangeCoeff, LagrangeCoeff, Polynomial},
 };
 
 pub(crate) mod keygen;
@@ -13,7 +13,7 @@ pub(crate) mod verifier;
 use std::io;
 
 /// A permutation argument.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub(crate) struct Argument {
     /// A sequence of columns involved in the argument.
     columns: Vec<Column<Any>>,
@@ -24,7 +24,23 @@ impl Argument {
         Argument { columns }
     }
 
-    pub(crate) fn required_degree(&self) -> usize {
+    /// Splits the columns of this argument into the chunks that each have a grand
+    /// product, given the degree that the rest of the constraint system requires.
+    ///
+    /// The columns are a single chunk if its constraint fits within that degree (or
+    /// degree 3, which the other arguments require anyway). Otherwise they are split
+    /// into chunks whose constraints have exactly that degree, so that the
+    /// permutation argument never raises the degree of the constraint system.
+    pub(crate) fn chunks(&self, degree: usize) -> Vec<&[Column<Any>]> {
+        let degree = std::cmp::max(degree, 3);
+        if self.columns.len() < degree {
+            vec![&self.columns[..]]
+        } else {
+            self.columns.chunks(degree - 2).collect()
+        }
+    }
+
+    pub(crate) fn required_degree(&self, degree: usize) -> usize {
         // The permutation argument will serve alongside the gates, so must be
         // accounted for. There are constraints of degree 2 regardless of the
         // number of columns involved. (It doesn't make sense to make a
@@ -32,12 +48,22 @@ impl Argument {
         // for it here.)
 
         // degree 2:
-        // l_0(X) * (1 - z(X)) = 0
+        // l_0(X) * (1 - z_0(X)) = 0
         //
-        // degree columns + 1
+        // degree columns + 1, with a single chunk:
         // z(X) \prod (p(X) + \beta s_i(X) + \gamma)
         // - z(omega^{-1} X) \prod (p(X) + \delta^i \beta X + \gamma)
-        std::cmp::max(self.columns.len() + 1, 2)
+        //
+        // degree chunk columns + 2, for each of several chunks:
+        // z_j(X) \prod (p(X) + \beta s_i(X) + \gamma)
+        // - ((1 - l_0(X)) z_j(omega^{-1} X) + l_0(X) z_{j-1}(omega^{-1} X))
+        //   \prod (p(X) + \delta^i \beta X + \gamma)
+        let chunks = self.chunks(degree);
+        if chunks.len() == 1 {
+            std::cmp::max(self.columns.len() + 1, 2)
+        } else {
+            chunks[0].len() + 2
+        }
     }
 
     pub(crate) fn get_columns(&self) -> Vec<Column<Any>> {
@@ -52,6 +78,11 @@ pub(crate) struct VerifyingKey<C: CurveAffine> {
 }
 
 impl<C: CurveAffine> VerifyingKey<C> {
+    /// The commitments to the permutation polynomials of the argument.
+    pub(crate) fn commitments(&self) -> &[C] {
+        &self.commitments
+    }
+
     pub(crate) fn write<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
         for commitment in &self.commitments {
             commitment.write(writer)?;
@@ -73,5 +104,34 @@ impl<C: CurveAffine> VerifyingKey<C> {
 pub(crate) struct ProvingKey<C: CurveAffine> {
     permutations: Vec<Polynomial<C::Scalar, LagrangeCoeff>>,
     polys: Vec<Polynomial<C::Scalar, Coeff>>,
-    cosets: Vec<Polynomial<C::Scalar, ExtendedLagrangeCoeff>>,
+    cosets: Option<Vec<Polynomial<C::Scalar, ExtendedLagrangeCoeff>>>,
+}
+
+impl<C: CurveAffine> ProvingKey<C> {
+    pub(crate) fn write<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        for permutation in &self.permutations {
+            for value in permutation.iter() {
+                value.write(writer)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn read<R: io::Read>(
+        reader: &mut R,
+        argument: &Argument,
+        domain: &EvaluationDomain<C::Scalar>,
+    ) -> io::Result<Self> {
+        let permutations = (0..argument.columns.len())
+            .map(|_| {
+                let mut permutation = domain.empty_lagrange();
+                for value in permutation.iter_mut() {
+                    *value = C::Scalar::read(reader)?;
+                }
+                Ok(permutation)
+            })
+            .collect::<io::Result<_>>()?;
+        Ok(ProvingKey::from_permutations(domain, permutations))
+    }
 }
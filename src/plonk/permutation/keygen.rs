@@ -6,8 +6,8 @@ use crate::{
     arithmetic::{CurveAffine, FieldExt},
     plonk::{circuit::ConstraintSystem, Error},
     poly::{
-        commitment::{Blind, Params},
-        EvaluationDomain, Rotation,
+        commitment::{Blind, CommitmentScheme},
+        EvaluationDomain, LagrangeCoeff, Polynomial, Rotation,
     },
 };
 
@@ -90,8 +90,8 @@ impl Assembly {
         Ok(())
     }
 
-    pub(crate) fn build_helper<C: CurveAffine>(
-        params: &Params<C>,
+    pub(crate) fn build_helper<C: CurveAffine, P: CommitmentScheme<C>>(
+        params: &P,
         cs: &ConstraintSystem<C::Scalar>,
         domain: &EvaluationDomain<C::Scalar>,
     ) -> AssemblyHelper<C> {
@@ -105,10 +105,10 @@ impl Assembly {
             .unwrap_or_default();
 
         // Compute [omega^0, omega^1, ..., omega^{params.n - 1}]
-        let mut omega_powers = Vec::with_capacity(params.n as usize);
+        let mut omega_powers = Vec::with_capacity(params.n() as usize);
         {
             let mut cur = C::Scalar::one();
-            for _ in 0..params.n {
+            for _ in 0..params.n() {
                 omega_powers.push(cur);
                 cur *= &domain.get_omega();
             }
@@ -133,9 +133,9 @@ impl Assembly {
         AssemblyHelper { deltaomega }
     }
 
-    pub(crate) fn build_vk<C: CurveAffine>(
+    pub(crate) fn build_vk<C: CurveAffine, P: CommitmentScheme<C>>(
         self,
-        params: &Params<C>,
+        params: &P,
         domain: &EvaluationDomain<C::Scalar>,
         helper: &AssemblyHelper<C>,
         p: &Argument,
@@ -167,29 +167,54 @@ impl Assembly {
         helper: &AssemblyHelper<C>,
         p: &Argument,
     ) -> ProvingKey<C> {
-        // Compute permutation polynomials, convert to coset form.
-        let mut permutations = vec![];
-        let mut polys = vec![];
-        let mut cosets = vec![];
-        for i in 0..p.columns.len() {
-            // Computes the permutation polynomial based on the permutation
-            // description in the assembly.
-            let mut permutation_poly = domain.empty_lagrange();
-            for (j, p) in permutation_poly.iter_mut().enumerate() {
-                let (permuted_i, permuted_j) = self.mapping[i][j];
-                *p = helper.deltaomega[permuted_i][permuted_j];
-            }
+        // Compute permutation polynomials based on the permutation description in
+        // the assembly.
+        let permutations = (0..p.columns.len())
+            .map(|i| {
+                let mut permutation_poly = domain.empty_lagrange();
+                for (j, p) in permutation_poly.iter_mut().enumerate() {
+                    let (permuted_i, permuted_j) = self.mapping[i][j];
+                    *p = helper.deltaomega[permuted_i][permuted_j];
+                }
+                permutation_poly
+            })
+            .collect();
 
-            // Store permutation polynomial and precompute its coset evaluation
-            permutations.push(permutation_poly.clone());
-            let poly = domain.lagrange_to_coeff(permutation_poly);
-            polys.push(poly.clone());
-            cosets.push(domain.coeff_to_extended(poly, Rotation::cur()));
-        }
+        ProvingKey::from_permutations(domain, permutations)
+    }
+}
+
+impl<C: CurveAffine> ProvingKey<C> {
+    /// Builds the proving key from the permutation polynomials, precomputing their
+    /// coefficient and coset forms.
+    pub(crate) fn from_permutations(
+        domain: &EvaluationDomain<C::Scalar>,
+        permutations: Vec<Polynomial<C::Scalar, LagrangeCoeff>>,
+    ) -> Self {
+        let polys: Vec<_> = permutations
+            .iter()
+            .map(|permutation_poly| domain.lagrange_to_coeff(permutation_poly.clone()))
+            .collect();
+        let cosets = polys
+            .iter()
+            .map(|poly| domain.coeff_to_extended(poly.clone(), Rotation::cur()))
+            .collect();
         ProvingKey {
             permutations,
             polys,
-            cosets,
+            cosets: Some(cosets),
         }
     }
+
+    /// Returns whether the permutation polynomials are those that `vk` commits to.
+    pub(crate) fn matches<P: CommitmentScheme<C>>(&self, params: &P, vk: &VerifyingKey<C>) -> bool {
+        self.permutations.len() == vk.commitments.len()
+            && self
+                .permutations
+                .iter()
+                .zip(vk.commitments.iter())
+                .all(|(poly, commitment)| {
+                    params.commit_lagrange(poly, Blind::default()).to_affine() == *commitment
+                })
+    }
 }
@@ -1,40 +1,51 @@
 use ff::Field;
 use group::Curve;
+use rand::RngCore;
 use std::iter;
 
 use super::super::circuit::Any;
 use super::{Argument, ProvingKey};
 use crate::{
-    arithmetic::{eval_polynomial, parallelize, BatchInvert, CurveAffine, FieldExt},
+    arithmetic::{
+        eval_polynomial, parallel_prefix_product, parallelize, BatchInvert, CurveAffine, FieldExt,
+    },
     plonk::{self, ChallengeBeta, ChallengeGamma, ChallengeX, Error},
     poly::{
-        commitment::{Blind, Params},
+        commitment::{Blind, CommitmentScheme},
         multiopen::ProverQuery,
-        Coeff, ExtendedLagrangeCoeff, LagrangeCoeff, Polynomial, Rotation,
+        Coeff, EvaluationDomain, LagrangeCoeff, PartLagrangeCoeff, Polynomial, Rotation,
     },
     transcript::TranscriptWrite,
 };
 
-pub(crate) struct Committed<C: CurveAffine> {
+pub(crate) struct CommittedSet<C: CurveAffine> {
     permutation_product_poly: Polynomial<C::Scalar, Coeff>,
-    permutation_product_coset: Polynomial<C::Scalar, ExtendedLagrangeCoeff>,
-    permutation_product_coset_inv: Polynomial<C::Scalar, ExtendedLagrangeCoeff>,
     permutation_product_blind: Blind<C::Scalar>,
 }
 
-pub(crate) struct Constructed<C: CurveAffine> {
-    permutation_product_poly: Polynomial<C::Scalar, Coeff>,
-    permutation_product_blind: Blind<C::Scalar>,
+pub(crate) struct Committed<C: CurveAffine> {
+    sets: Vec<CommittedSet<C>>,
+}
+
+/// The values of the grand products of a permutation argument over a part of the
+/// extended domain.
+pub(crate) struct Part<C: CurveAffine> {
+    sets: Vec<Polynomial<C::Scalar, PartLagrangeCoeff>>,
 }
 
 pub(crate) struct Evaluated<C: CurveAffine> {
-    constructed: Constructed<C>,
+    committed: Committed<C>,
 }
 
 impl Argument {
-    pub(in crate::plonk) fn commit<C: CurveAffine, T: TranscriptWrite<C>>(
+    pub(in crate::plonk) fn commit<
+        C: CurveAffine,
+        P: CommitmentScheme<C>,
+        R: RngCore,
+        T: TranscriptWrite<C>,
+    >(
         &self,
-        params: &Params<C>,
+        params: &P,
         pk: &plonk::ProvingKey<C>,
         pkey: &ProvingKey<C>,
         advice: &[Polynomial<C::Scalar, LagrangeCoeff>],
@@ -42,200 +53,242 @@ impl Argument {
         instance: &[Polynomial<C::Scalar, LagrangeCoeff>],
         beta: ChallengeBeta<C>,
         gamma: ChallengeGamma<C>,
+        mut rng: R,
         transcript: &mut T,
     ) -> Result<Committed<C>, Error> {
         let domain = &pk.vk.domain;
 
-        // Goal is to compute the products of fractions
-        //
-        // (p_j(\omega^i) + \delta^j \omega^i \beta + \gamma) /
-        // (p_j(\omega^i) + \beta s_j(\omega^i) + \gamma)
-        //
-        // where p_j(X) is the jth column in this permutation,
-        // and i is the ith row of the column.
-
-        let mut modified_values = vec![C::Scalar::one(); params.n as usize];
-
-        // Iterate over each column of the permutation
-        for (&column, permuted_column_values) in self.columns.iter().zip(pkey.permutations.iter()) {
-            let values = match column.column_type() {
-                Any::Advice => advice,
-                Any::Fixed => fixed,
-                Any::Instance => instance,
-            };
-            parallelize(&mut modified_values, |modified_values, start| {
-                for ((modified_values, value), permuted_value) in modified_values
-                    .iter_mut()
-                    .zip(values[column.index()][start..].iter())
-                    .zip(permuted_column_values[start..].iter())
-                {
-                    *modified_values *= &(*beta * permuted_value + &*gamma + value);
-                }
-            });
-        }
+        // Each chunk of columns has its own grand product, which starts where the
+        // grand product of the previous chunk ends.
+        let mut sets = vec![];
+        let mut last_z = C::Scalar::one();
+        let mut offset = 0;
+        for columns in self.chunks(pk.vk.cs.degree_without_permutations()) {
+            let permutations = &pkey.permutations[offset..offset + columns.len()];
+
+            // Goal is to compute the products of fractions
+            //
+            // (p_j(\omega^i) + \delta^j \omega^i \beta + \gamma) /
+            // (p_j(\omega^i) + \beta s_j(\omega^i) + \gamma)
+            //
+            // where p_j(X) is the jth column in this chunk of the permutation,
+            // and i is the ith row of the column.
+
+            let mut modified_values = vec![C::Scalar::one(); params.n() as usize];
+
+            // Iterate over each column of the chunk
+            for (&column, permuted_column_values) in columns.iter().zip(permutations.iter()) {
+                let values = match column.column_type() {
+                    Any::Advice => advice,
+                    Any::Fixed => fixed,
+                    Any::Instance => instance,
+                };
+                parallelize(&mut modified_values, |modified_values, start| {
+                    for ((modified_values, value), permuted_value) in modified_values
+                        .iter_mut()
+                        .zip(values[column.index()][start..].iter())
+                        .zip(permuted_column_values[start..].iter())
+                    {
+                        *modified_values *= &(*beta * permuted_value + &*gamma + value);
+                    }
+                });
+            }
+
+            // Invert to obtain the denominator for the permutation product polynomial
+            modified_values.batch_invert();
+
+            // Iterate over each column again, this time finishing the computation
+            // of the entire fraction by computing the numerators
+            let mut deltaomega = C::Scalar::DELTA.pow_vartime([offset as u64, 0, 0, 0]);
+            for &column in columns.iter() {
+                let omega = domain.get_omega();
+                let values = match column.column_type() {
+                    Any::Advice => advice,
+                    Any::Fixed => fixed,
+                    Any::Instance => instance,
+                };
+                parallelize(&mut modified_values, |modified_values, start| {
+                    let mut deltaomega = deltaomega * &omega.pow_vartime(&[start as u64, 0, 0, 0]);
+                    for (modified_values, value) in modified_values
+                        .iter_mut()
+                        .zip(values[column.index()][start..].iter())
+                    {
+                        // Multiply by p_j(\omega^i) + \delta^j \omega^i \beta
+                        *modified_values *= &(deltaomega * &*beta + &*gamma + value);
+                        deltaomega *= &omega;
+                    }
+                });
+                deltaomega *= &C::Scalar::DELTA;
+            }
+
+            // The modified_values vector is a vector of products of fractions
+            // of the form
+            //
+            // (p_j(\omega^i) + \delta^j \omega^i \beta + \gamma) /
+            // (p_j(\omega^i) + \beta s_j(\omega^i) + \gamma)
+            //
+            // where i is the index into modified_values, for the jth column in
+            // the chunk
+
+            // Compute the evaluations of the permutation product polynomial
+            // over our domain, starting with z_0[0] = 1, and for each later chunk
+            // with the last value of the previous chunk times the fraction of
+            // this chunk at row 0.
+            let mut z = modified_values;
+            if sets.is_empty() {
+                z[0] = C::Scalar::one();
+            } else {
+                z[0] *= &last_z;
+            }
+            parallel_prefix_product(&mut z);
+            last_z = z[params.n() as usize - 1];
+            let z = domain.lagrange_from_vec(z);
+
+            let blind = Blind(C::Scalar::random(&mut rng));
+
+            let permutation_product_commitment_projective = params.commit_lagrange(&z, blind);
+            let permutation_product_blind = blind;
+            let permutation_product_poly = domain.lagrange_to_coeff(z);
+
+            let permutation_product_commitment =
+                permutation_product_commitment_projective.to_affine();
+
+            // Hash the permutation product commitment
+            transcript
+                .write_point(permutation_product_commitment)
+                .map_err(|_| Error::TranscriptError)?;
 
-        // Invert to obtain the denominator for the permutation product polynomial
-        modified_values.batch_invert();
-
-        // Iterate over each column again, this time finishing the computation
-        // of the entire fraction by computing the numerators
-        let mut deltaomega = C::Scalar::one();
-        for &column in self.columns.iter() {
-            let omega = domain.get_omega();
-            let values = match column.column_type() {
-                Any::Advice => advice,
-                Any::Fixed => fixed,
-                Any::Instance => instance,
-            };
-            parallelize(&mut modified_values, |modified_values, start| {
-                let mut deltaomega = deltaomega * &omega.pow_vartime(&[start as u64, 0, 0, 0]);
-                for (modified_values, value) in modified_values
-                    .iter_mut()
-                    .zip(values[column.index()][start..].iter())
-                {
-                    // Multiply by p_j(\omega^i) + \delta^j \omega^i \beta
-                    *modified_values *= &(deltaomega * &*beta + &*gamma + value);
-                    deltaomega *= &omega;
-                }
+            sets.push(CommittedSet {
+                permutation_product_poly,
+                permutation_product_blind,
             });
-            deltaomega *= &C::Scalar::DELTA;
-        }
-
-        // The modified_values vector is a vector of products of fractions
-        // of the form
-        //
-        // (p_j(\omega^i) + \delta^j \omega^i \beta + \gamma) /
-        // (p_j(\omega^i) + \beta s_j(\omega^i) + \gamma)
-        //
-        // where i is the index into modified_values, for the jth column in
-        // the permutation
-
-        // Compute the evaluations of the permutation product polynomial
-        // over our domain, starting with z[0] = 1
-        let mut z = vec![C::Scalar::one()];
-        for row in 1..(params.n as usize) {
-            let mut tmp = z[row - 1];
-
-            tmp *= &modified_values[row];
-            z.push(tmp);
+            offset += columns.len();
         }
-        let z = domain.lagrange_from_vec(z);
-
-        let blind = Blind(C::Scalar::rand());
-
-        let permutation_product_commitment_projective = params.commit_lagrange(&z, blind);
-        let permutation_product_blind = blind;
-        let z = domain.lagrange_to_coeff(z);
-        let permutation_product_poly = z.clone();
-        let permutation_product_coset = domain.coeff_to_extended(z.clone(), Rotation::cur());
-        let permutation_product_coset_inv = domain.coeff_to_extended(z, Rotation::prev());
 
-        let permutation_product_commitment = permutation_product_commitment_projective.to_affine();
-
-        // Hash the permutation product commitment
-        transcript
-            .write_point(permutation_product_commitment)
-            .map_err(|_| Error::TranscriptError)?;
-
-        Ok(Committed {
-            permutation_product_poly,
-            permutation_product_coset,
-            permutation_product_coset_inv,
-            permutation_product_blind,
-        })
+        Ok(Committed { sets })
     }
 }
 
 impl<C: CurveAffine> Committed<C> {
-    pub(in crate::plonk) fn construct<'a>(
-        self,
-        pk: &'a plonk::ProvingKey<C>,
-        p: &'a Argument,
-        pkey: &'a ProvingKey<C>,
-        advice_cosets: &'a [Polynomial<C::Scalar, ExtendedLagrangeCoeff>],
-        fixed_cosets: &'a [Polynomial<C::Scalar, ExtendedLagrangeCoeff>],
-        instance_cosets: &'a [Polynomial<C::Scalar, ExtendedLagrangeCoeff>],
+    /// Returns the values of the grand products over the `part`-th part of the
+    /// extended domain.
+    pub(in crate::plonk) fn part(
+        &self,
+        domain: &EvaluationDomain<C::Scalar>,
+        part: usize,
+    ) -> Part<C> {
+        Part {
+            sets: self
+                .sets
+                .iter()
+                .map(|set| {
+                    domain.coeff_to_extended_part(set.permutation_product_poly.clone(), part)
+                })
+                .collect(),
+        }
+    }
+}
+
+impl<C: CurveAffine> Part<C> {
+    /// Folds the constraints of this argument into `h`, the values of $h(X)$ at the
+    /// points of the `part`-th part of the extended domain from index `start`, so
+    /// that each constraint is accumulated as $h(X) \cdot y + c(X)$ in turn.
+    ///
+    /// `l0`, `permutations` and the columns are the values of $l_0(X)$, of the
+    /// permutation polynomials and of the columns over the same part.
+    pub(in crate::plonk) fn fold(
+        &self,
+        pk: &plonk::ProvingKey<C>,
+        p: &Argument,
+        part: usize,
+        l0: &Polynomial<C::Scalar, PartLagrangeCoeff>,
+        permutations: &[Polynomial<C::Scalar, PartLagrangeCoeff>],
+        advice: &[Polynomial<C::Scalar, PartLagrangeCoeff>],
+        fixed: &[Polynomial<C::Scalar, PartLagrangeCoeff>],
+        instance: &[Polynomial<C::Scalar, PartLagrangeCoeff>],
         beta: ChallengeBeta<C>,
         gamma: ChallengeGamma<C>,
-    ) -> (
-        Constructed<C>,
-        impl Iterator<Item = Polynomial<C::Scalar, ExtendedLagrangeCoeff>> + 'a,
+        y: C::Scalar,
+        h: &mut [C::Scalar],
+        start: usize,
     ) {
         let domain = &pk.vk.domain;
-        let expressions = iter::empty()
-            // l_0(X) * (1 - z(X)) = 0
-            .chain(Some(
-                Polynomial::one_minus(self.permutation_product_coset.clone()) * &pk.l0,
-            ))
-            // z(X) \prod (p(X) + \beta s_i(X) + \gamma) - z(omega^{-1} X) \prod (p(X) + \delta^i \beta X + \gamma)
-            .chain(Some({
-                let mut left = self.permutation_product_coset.clone();
-                for (values, permutation) in p
-                    .columns
-                    .iter()
-                    .map(|&column| match column.column_type() {
-                        Any::Advice => {
-                            &advice_cosets[pk.vk.cs.get_any_query_index(column, Rotation::cur())]
-                        }
-                        Any::Fixed => {
-                            &fixed_cosets[pk.vk.cs.get_any_query_index(column, Rotation::cur())]
-                        }
-                        Any::Instance => {
-                            &instance_cosets[pk.vk.cs.get_any_query_index(column, Rotation::cur())]
-                        }
-                    })
-                    .zip(pkey.cosets.iter())
-                {
-                    parallelize(&mut left, |left, start| {
-                        for ((left, value), permutation) in left
-                            .iter_mut()
-                            .zip(values[start..].iter())
-                            .zip(permutation[start..].iter())
-                        {
-                            *left *= &(*value + &(*beta * permutation) + &*gamma);
-                        }
-                    });
+        let chunks = p.chunks(pk.vk.cs.degree_without_permutations());
+        let prev = domain.part_rotation(Rotation::prev());
+        let mask = l0.len() - 1;
+        let step = domain.get_omega();
+        let mut x = domain.extended_part_start(part) * &step.pow_vartime([start as u64, 0, 0, 0]);
+        for (i, h) in h.iter_mut().enumerate() {
+            let idx = start + i;
+            let idx_prev = (idx + prev) & mask;
+            let l0 = l0[idx];
+
+            // l_0(X) * (1 - z_0(X)) = 0
+            *h = *h * &y + &(l0 * &(C::Scalar::one() - &self.sets[0][idx]));
+
+            // z_j(X) \prod (p(X) + \beta s_i(X) + \gamma) - z'_j(omega^{-1} X) \prod (p(X) + \delta^i \beta X + \gamma)
+            let mut offset = 0;
+            let mut current_delta = *beta * &x;
+            for (index, columns) in chunks.iter().enumerate() {
+                let set = &self.sets[index];
+                let mut left = set[idx];
+                let mut right = set[idx_prev];
+
+                // With several chunks, the grand product of each chunk continues at
+                // row 0 from the last row of the previous chunk, and that of the first
+                // chunk from the last row of the last chunk:
+                // z'_j(omega^{-1} X) = z_j(omega^{-1} X) + l_0(X) (z_{j-1}(omega^{-1} X) - z_j(omega^{-1} X))
+                if chunks.len() > 1 {
+                    let previous = &self.sets[(index + chunks.len() - 1) % chunks.len()];
+                    right += &(l0 * &(previous[idx_prev] - &right));
                 }
 
-                let mut right = self.permutation_product_coset_inv.clone();
-                let mut current_delta = *beta * &C::Scalar::ZETA;
-                let step = domain.get_extended_omega();
-                for values in p.columns.iter().map(|&column| match column.column_type() {
-                    Any::Advice => {
-                        &advice_cosets[pk.vk.cs.get_any_query_index(column, Rotation::cur())]
-                    }
-                    Any::Fixed => {
-                        &fixed_cosets[pk.vk.cs.get_any_query_index(column, Rotation::cur())]
-                    }
-                    Any::Instance => {
-                        &instance_cosets[pk.vk.cs.get_any_query_index(column, Rotation::cur())]
-                    }
-                }) {
-                    parallelize(&mut right, move |right, start| {
-                        let mut beta_term =
-                            current_delta * &step.pow_vartime(&[start as u64, 0, 0, 0]);
-                        for (right, value) in right.iter_mut().zip(values[start..].iter()) {
-                            *right *= &(*value + &beta_term + &*gamma);
-                            beta_term *= &step;
-                        }
-                    });
+                for (&column, permutation) in columns.iter().zip(permutations[offset..].iter()) {
+                    let value = match column.column_type() {
+                        Any::Advice => advice[column.index()][idx],
+                        Any::Fixed => fixed[column.index()][idx],
+                        Any::Instance => instance[column.index()][idx],
+                    };
+                    left *= &(value + &(*beta * &permutation[idx]) + &*gamma);
+                    right *= &(value + &current_delta + &*gamma);
                     current_delta *= &C::Scalar::DELTA;
                 }
 
-                left - &right
-            }));
-
-        (
-            Constructed {
-                permutation_product_poly: self.permutation_product_poly,
-                permutation_product_blind: self.permutation_product_blind,
-            },
-            expressions,
-        )
+                *h = *h * &y + &(left - &right);
+                offset += columns.len();
+            }
+            x *= &step;
+        }
     }
 }
 
 impl<C: CurveAffine> super::ProvingKey<C> {
+    /// Returns the values of the permutation polynomials over the `part`-th part of
+    /// the extended domain.
+    pub(in crate::plonk) fn part(
+        &self,
+        domain: &EvaluationDomain<C::Scalar>,
+        part: usize,
+    ) -> Vec<Polynomial<C::Scalar, PartLagrangeCoeff>> {
+        match &self.cosets {
+            Some(cosets) => cosets
+                .iter()
+                .map(|coset| domain.extended_to_part(coset, part))
+                .collect(),
+            None => self
+                .polys
+                .iter()
+                .map(|poly| domain.coeff_to_extended_part(poly.clone(), part))
+                .collect(),
+        }
+    }
+
+    /// Discards the evaluations of the permutation polynomials over the extended
+    /// domain.
+    pub(in crate::plonk) fn discard_cosets(&mut self) {
+        self.cosets = None;
+    }
+
     fn evaluate(&self, x: ChallengeX<C>) -> Vec<C::Scalar> {
         self.polys
             .iter()
@@ -252,7 +305,7 @@ impl<C: CurveAffine> super::ProvingKey<C> {
     }
 }
 
-impl<C: CurveAffine> Constructed<C> {
+impl<C: CurveAffine> Committed<C> {
     pub(in crate::plonk) fn evaluate<T: TranscriptWrite<C>>(
         self,
         pk: &plonk::ProvingKey<C>,
@@ -261,28 +314,32 @@ impl<C: CurveAffine> Constructed<C> {
         transcript: &mut T,
     ) -> Result<Evaluated<C>, Error> {
         let domain = &pk.vk.domain;
+        let x_inv = domain.rotate_omega(*x, Rotation(-1));
+
+        // Hash permutation product evals for each chunk
+        for set in self.sets.iter() {
+            let permutation_product_eval = eval_polynomial(&set.permutation_product_poly, *x);
+            let permutation_product_inv_eval =
+                eval_polynomial(&set.permutation_product_poly, x_inv);
+
+            for eval in iter::empty()
+                .chain(Some(permutation_product_eval))
+                .chain(Some(permutation_product_inv_eval))
+            {
+                transcript
+                    .write_scalar(eval)
+                    .map_err(|_| Error::TranscriptError)?;
+            }
+        }
 
-        let permutation_product_eval = eval_polynomial(&self.permutation_product_poly, *x);
-
-        let permutation_product_inv_eval = eval_polynomial(
-            &self.permutation_product_poly,
-            domain.rotate_omega(*x, Rotation(-1)),
-        );
-
-        let permutation_evals = pkey.evaluate(x);
-
-        // Hash permutation product evals
-        for eval in iter::empty()
-            .chain(Some(&permutation_product_eval))
-            .chain(Some(&permutation_product_inv_eval))
-            .chain(permutation_evals.iter())
-        {
+        // Hash permutation evals
+        for eval in pkey.evaluate(x) {
             transcript
-                .write_scalar(*eval)
+                .write_scalar(eval)
                 .map_err(|_| Error::TranscriptError)?;
         }
 
-        Ok(Evaluated { constructed: self })
+        Ok(Evaluated { committed: self })
     }
 }
 
@@ -295,18 +352,23 @@ impl<C: CurveAffine> Evaluated<C> {
     ) -> impl Iterator<Item = ProverQuery<'a, C>> + Clone {
         let x_inv = pk.vk.domain.rotate_omega(*x, Rotation(-1));
 
-        iter::empty()
-            // Open permutation product commitments at x and \omega^{-1} x
-            .chain(Some(ProverQuery {
-                point: *x,
-                poly: &self.constructed.permutation_product_poly,
-                blind: self.constructed.permutation_product_blind,
-            }))
-            .chain(Some(ProverQuery {
-                point: x_inv,
-                poly: &self.constructed.permutation_product_poly,
-                blind: self.constructed.permutation_product_blind,
-            }))
+        self.committed
+            .sets
+            .iter()
+            .flat_map(move |set| {
+                iter::empty()
+                    // Open permutation product commitments at x and \omega^{-1} x
+                    .chain(Some(ProverQuery {
+                        point: *x,
+                        poly: &set.permutation_product_poly,
+                        blind: set.permutation_product_blind,
+                    }))
+                    .chain(Some(ProverQuery {
+                        point: x_inv,
+                        poly: &set.permutation_product_poly,
+                        blind: set.permutation_product_blind,
+                    }))
+            })
             // Open permutation polynomial commitments at x
             .chain(pkey.open(x))
     }
@@ -11,27 +11,34 @@ use crate::{
 };
 
 pub struct Committed<C: CurveAffine> {
-    permutation_product_commitment: C,
+    permutation_product_commitments: Vec<C>,
 }
 
-pub struct Evaluated<C: CurveAffine> {
+pub struct EvaluatedSet<C: CurveAffine> {
     permutation_product_commitment: C,
     permutation_product_eval: C::Scalar,
     permutation_product_inv_eval: C::Scalar,
+}
+
+pub struct Evaluated<C: CurveAffine> {
+    sets: Vec<EvaluatedSet<C>>,
     permutation_evals: Vec<C::Scalar>,
 }
 
 impl Argument {
-    pub(crate) fn read_product_commitment<C: CurveAffine, T: TranscriptRead<C>>(
+    pub(crate) fn read_product_commitments<C: CurveAffine, T: TranscriptRead<C>>(
         &self,
+        vk: &plonk::VerifyingKey<C>,
         transcript: &mut T,
     ) -> Result<Committed<C>, Error> {
-        let permutation_product_commitment = transcript
-            .read_point()
-            .map_err(|_| Error::TranscriptError)?;
+        let permutation_product_commitments = self
+            .chunks(vk.cs.degree_without_permutations())
+            .iter()
+            .map(|_| transcript.read_point().map_err(|_| Error::TranscriptError))
+            .collect::<Result<Vec<_>, _>>()?;
 
         Ok(Committed {
-            permutation_product_commitment,
+            permutation_product_commitments,
         })
     }
 }
@@ -42,12 +49,20 @@ impl<C: CurveAffine> Committed<C> {
         vkey: &VerifyingKey<C>,
         transcript: &mut T,
     ) -> Result<Evaluated<C>, Error> {
-        let permutation_product_eval = transcript
-            .read_scalar()
-            .map_err(|_| Error::TranscriptError)?;
-        let permutation_product_inv_eval = transcript
-            .read_scalar()
-            .map_err(|_| Error::TranscriptError)?;
+        let mut sets = Vec::with_capacity(self.permutation_product_commitments.len());
+        for permutation_product_commitment in self.permutation_product_commitments {
+            let permutation_product_eval = transcript
+                .read_scalar()
+                .map_err(|_| Error::TranscriptError)?;
+            let permutation_product_inv_eval = transcript
+                .read_scalar()
+                .map_err(|_| Error::TranscriptError)?;
+            sets.push(EvaluatedSet {
+                permutation_product_commitment,
+                permutation_product_eval,
+                permutation_product_inv_eval,
+            });
+        }
         let mut permutation_evals = Vec::with_capacity(vkey.commitments.len());
         for _ in 0..vkey.commitments.len() {
             permutation_evals.push(
@@ -58,9 +73,7 @@ impl<C: CurveAffine> Committed<C> {
         }
 
         Ok(Evaluated {
-            permutation_product_commitment: self.permutation_product_commitment,
-            permutation_product_eval,
-            permutation_product_inv_eval,
+            sets,
             permutation_evals,
         })
     }
@@ -79,49 +92,55 @@ impl<C: CurveAffine> Evaluated<C> {
         gamma: ChallengeGamma<C>,
         x: ChallengeX<C>,
     ) -> impl Iterator<Item = C::Scalar> + 'a {
-        iter::empty()
-            // l_0(X) * (1 - z(X)) = 0
-            .chain(Some(
-                l_0 * &(C::Scalar::one() - &self.permutation_product_eval),
-            ))
-            // z(X) \prod (p(X) + \beta s_i(X) + \gamma)
-            // - z(omega^{-1} X) \prod (p(X) + \delta^i \beta X + \gamma)
-            .chain(Some({
-                let mut left = self.permutation_product_eval;
-                for (eval, permutation_eval) in p
-                    .columns
-                    .iter()
-                    .map(|&column| match column.column_type() {
-                        Any::Advice => {
-                            advice_evals[vk.cs.get_any_query_index(column, Rotation::cur())]
-                        }
-                        Any::Fixed => {
-                            fixed_evals[vk.cs.get_any_query_index(column, Rotation::cur())]
-                        }
-                        Any::Instance => {
-                            instance_evals[vk.cs.get_any_query_index(column, Rotation::cur())]
-                        }
-                    })
-                    .zip(self.permutation_evals.iter())
-                {
-                    left *= &(eval + &(*beta * permutation_eval) + &*gamma);
-                }
+        let chunks = p.chunks(vk.cs.degree_without_permutations());
+        let mut expressions = vec![
+            // l_0(X) * (1 - z_0(X)) = 0
+            l_0 * &(C::Scalar::one() - &self.sets[0].permutation_product_eval),
+        ];
 
-                let mut right = self.permutation_product_inv_eval;
-                let mut current_delta = *beta * &*x;
-                for eval in p.columns.iter().map(|&column| match column.column_type() {
+        let mut offset = 0;
+        for (index, columns) in chunks.iter().enumerate() {
+            let set = &self.sets[index];
+            let column_evals = || {
+                columns.iter().map(|&column| match column.column_type() {
                     Any::Advice => advice_evals[vk.cs.get_any_query_index(column, Rotation::cur())],
                     Any::Fixed => fixed_evals[vk.cs.get_any_query_index(column, Rotation::cur())],
                     Any::Instance => {
                         instance_evals[vk.cs.get_any_query_index(column, Rotation::cur())]
                     }
-                }) {
-                    right *= &(eval + &current_delta + &*gamma);
-                    current_delta *= &C::Scalar::DELTA;
-                }
+                })
+            };
+
+            // z_j(X) \prod (p(X) + \beta s_i(X) + \gamma)
+            // - z'_j(omega^{-1} X) \prod (p(X) + \delta^i \beta X + \gamma)
+            let mut left = set.permutation_product_eval;
+            for (eval, permutation_eval) in
+                column_evals().zip(self.permutation_evals[offset..].iter())
+            {
+                left *= &(eval + &(*beta * permutation_eval) + &*gamma);
+            }
+
+            // With several chunks, the grand product of each chunk continues at
+            // row 0 from the last row of the previous chunk, and that of the first
+            // chunk from the last row of the last chunk:
+            // z'_j(omega^{-1} X) = z_j(omega^{-1} X) + l_0(X) (z_{j-1}(omega^{-1} X) - z_j(omega^{-1} X))
+            let mut right = set.permutation_product_inv_eval;
+            if chunks.len() > 1 {
+                let previous = &self.sets[(index + chunks.len() - 1) % chunks.len()];
+                right += &(l_0 * &(previous.permutation_product_inv_eval - &right));
+            }
+            let mut current_delta =
+                *beta * &*x * &C::Scalar::DELTA.pow_vartime([offset as u64, 0, 0, 0]);
+            for eval in column_evals() {
+                right *= &(eval + &current_delta + &*gamma);
+                current_delta *= &C::Scalar::DELTA;
+            }
 
-                left - &right
-            }))
+            expressions.push(left - &right);
+            offset += columns.len();
+        }
+
+        expressions.into_iter()
     }
 
     pub(in crate::plonk) fn queries<'a>(
@@ -132,18 +151,22 @@ impl<C: CurveAffine> Evaluated<C> {
     ) -> impl Iterator<Item = VerifierQuery<'a, C>> + Clone {
         let x_inv = vk.domain.rotate_omega(*x, Rotation(-1));
 
-        iter::empty()
-            // Open permutation product commitments at x and \omega^{-1} x
-            .chain(Some(VerifierQuery {
-                point: *x,
-                commitment: &self.permutation_product_commitment,
-                eval: self.permutation_product_eval,
-            }))
-            .chain(Some(VerifierQuery {
-                point: x_inv,
-                commitment: &self.permutation_product_commitment,
-                eval: self.permutation_product_inv_eval,
-            }))
+        self.sets
+            .iter()
+            .flat_map(move |set| {
+                iter::empty()
+                    // Open permutation product commitments at x and \omega^{-1} x
+                    .chain(Some(VerifierQuery {
+                        point: *x,
+                        commitment: &set.permutation_product_commitment,
+                        eval: set.permutation_product_eval,
+                    }))
+                    .chain(Some(VerifierQuery {
+                        point: x_inv,
+                        commitment: &set.permutation_product_commitment,
+                        eval: set.permutation_product_inv_eval,
+                    }))
+            })
             // Open permutation commitments for each permutation argument at x
             .chain(
                 vkey.commitments
@@ -0,0 +1,241 @@
+use rand::RngCore;
+use std::convert::TryInto;
+use std::io;
+
+use super::{
+    create_proof, verify_proof, Circuit, Error, LagrangeCoeff, Polynomial, ProvingKey, VerifyingKey,
+};
+use crate::arithmetic::{CurveAffine, FieldExt};
+use crate::poly::commitment::{Prover, Verifier};
+use crate::transcript::{Blake2bRead, Blake2bWrite, Transcript, TranscriptRead, TranscriptWrite};
+
+/// The version of the binary encoding of [`Proof`], which is written at the start of
+/// every proof.
+const PROOF_ENCODING_VERSION: u8 = 1;
+
+/// The tag of a curve point in the encoding of a [`Proof`].
+const POINT_TAG: u8 = 0;
+
+/// The tag of a scalar in the encoding of a [`Proof`].
+const SCALAR_TAG: u8 = 1;
+
+/// A message from the prover to the verifier.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Element<C: CurveAffine> {
+    Point(C),
+    Scalar(C::Scalar),
+}
+
+/// A proof, in a versioned encoding that is independent of the transcript.
+///
+/// The encoding is a version byte and the number of messages from the prover, as four
+/// little-endian bytes, followed by each message as a tag byte and its encoding: a
+/// compressed curve point, or a scalar. [`Proof::from_bytes`] only accepts the
+/// canonical encoding of each message, and rejects any other version or trailing
+/// bytes, so that every proof has exactly one encoding.
+///
+/// Proofs are created and verified with a [`Blake2bWrite`] and [`Blake2bRead`]
+/// transcript, and verifying a proof checks that each message has the type that the
+/// verifier expects.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Proof<C: CurveAffine> {
+    elements: Vec<Element<C>>,
+}
+
+/// A transcript that records the messages of the prover.
+struct ProofWrite<C: CurveAffine> {
+    transcript: Blake2bWrite<io::Sink, C>,
+    elements: Vec<Element<C>>,
+}
+
+impl<C: CurveAffine> Transcript<C> for ProofWrite<C> {
+    fn squeeze_challenge(&mut self) -> C::Base {
+        self.transcript.squeeze_challenge()
+    }
+
+    fn common_point(&mut self, point: C) -> io::Result<()> {
+        self.transcript.common_point(point)
+    }
+
+    fn common_scalar(&mut self, scalar: C::Scalar) -> io::Result<()> {
+        self.transcript.common_scalar(scalar)
+    }
+}
+
+impl<C: CurveAffine> TranscriptWrite<C> for ProofWrite<C> {
+    fn write_point(&mut self, point: C) -> io::Result<()> {
+        self.transcript.write_point(point)?;
+        self.elements.push(Element::Point(point));
+        Ok(())
+    }
+
+    fn write_scalar(&mut self, scalar: C::Scalar) -> io::Result<()> {
+        self.transcript.write_scalar(scalar)?;
+        self.elements.push(Element::Scalar(scalar));
+        Ok(())
+    }
+}
+
+/// A transcript that reads the messages of the prover from a [`Proof`].
+struct ProofRead<'a, C: CurveAffine> {
+    transcript: Blake2bRead<io::Empty, C>,
+    elements: std::slice::Iter<'a, Element<C>>,
+}
+
+impl<'a, C: CurveAffine> Transcript<C> for ProofRead<'a, C> {
+    fn squeeze_challenge(&mut self) -> C::Base {
+        self.transcript.squeeze_challenge()
+    }
+
+    fn common_point(&mut self, point: C) -> io::Result<()> {
+        self.transcript.common_point(point)
+    }
+
+    fn common_scalar(&mut self, scalar: C::Scalar) -> io::Result<()> {
+        self.transcript.common_scalar(scalar)
+    }
+}
+
+impl<'a, C: CurveAffine> TranscriptRead<C> for ProofRead<'a, C> {
+    fn read_point(&mut self) -> io::Result<C> {
+        match self.elements.next() {
+            Some(Element::Point(point)) => {
+                self.transcript.common_point(*point)?;
+                Ok(*point)
+            }
+            _ => Err(invalid_data("expected a point in proof")),
+        }
+    }
+
+    fn read_scalar(&mut self) -> io::Result<C::Scalar> {
+        match self.elements.next() {
+            Some(Element::Scalar(scalar)) => {
+                self.transcript.common_scalar(*scalar)?;
+                Ok(*scalar)
+            }
+            _ => Err(invalid_data("expected a scalar in proof")),
+        }
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Reads the next `len` bytes of `bytes`.
+fn take<'b>(bytes: &mut &'b [u8], len: usize) -> io::Result<&'b [u8]> {
+    if bytes.len() < len {
+        return Err(invalid_data("truncated proof"));
+    }
+    let (taken, rest) = bytes.split_at(len);
+    *bytes = rest;
+    Ok(taken)
+}
+
+impl<C: CurveAffine> Proof<C> {
+    /// Creates a proof for `circuits` as [`create_proof`] does.
+    pub fn create<P: Prover<C>, R: RngCore, ConcreteCircuit: Circuit<C::Scalar>>(
+        params: &P,
+        pk: &ProvingKey<C>,
+        circuits: &[ConcreteCircuit],
+        instances: &[&[Polynomial<C::Scalar, LagrangeCoeff>]],
+        rng: R,
+    ) -> Result<Self, Error> {
+        let mut transcript = ProofWrite {
+            transcript: Blake2bWrite::init(io::sink()),
+            elements: vec![],
+        };
+        create_proof(params, pk, circuits, instances, rng, &mut transcript)?;
+        Ok(Proof {
+            elements: transcript.elements,
+        })
+    }
+
+    /// Verifies this proof as [`verify_proof`] does, returning the guard whose checks
+    /// the caller must complete.
+    ///
+    /// Returns [`Error::TranscriptError`] if the proof has messages of the wrong type,
+    /// or more messages than the verifier reads.
+    pub fn verify<'a, P: Verifier<C>>(
+        &self,
+        params: &'a P,
+        vk: &VerifyingKey<C>,
+        msm: P::Msm<'a>,
+        instance_commitments: &[&[C]],
+    ) -> Result<P::Guard<'a>, Error> {
+        let mut transcript = ProofRead {
+            transcript: Blake2bRead::init(io::empty()),
+            elements: self.elements.iter(),
+        };
+        let guard = verify_proof(params, vk, msm, instance_commitments, &mut transcript)?;
+        if transcript.elements.next().is_some() {
+            return Err(Error::TranscriptError);
+        }
+        Ok(guard)
+    }
+
+    /// Returns the encoding of this proof.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![PROOF_ENCODING_VERSION];
+        bytes.extend_from_slice(&(self.elements.len() as u32).to_le_bytes());
+        for element in &self.elements {
+            match element {
+                Element::Point(point) => {
+                    bytes.push(POINT_TAG);
+                    bytes.extend_from_slice(point.to_bytes().as_ref());
+                }
+                Element::Scalar(scalar) => {
+                    bytes.push(SCALAR_TAG);
+                    bytes.extend_from_slice(&scalar.to_bytes());
+                }
+            }
+        }
+        bytes
+    }
+
+    /// Parses a proof from its encoding.
+    ///
+    /// Returns an error unless `bytes` is exactly the encoding of a proof, with the
+    /// current version and the canonical encoding of every message.
+    pub fn from_bytes(mut bytes: &[u8]) -> io::Result<Self> {
+        let bytes = &mut bytes;
+        if take(bytes, 1)?[0] != PROOF_ENCODING_VERSION {
+            return Err(invalid_data("unsupported proof encoding version"));
+        }
+        let len = u32::from_le_bytes(take(bytes, 4)?.try_into().unwrap()) as usize;
+
+        // The number of messages is not trusted until they have been read.
+        let mut elements = Vec::with_capacity(len.min(bytes.len()));
+        for _ in 0..len {
+            let element = match take(bytes, 1)?[0] {
+                POINT_TAG => {
+                    let mut compressed = C::Repr::default();
+                    let encoding = take(bytes, compressed.as_ref().len())?;
+                    compressed.as_mut().copy_from_slice(encoding);
+                    let point: C = Option::from(C::from_bytes(&compressed))
+                        .ok_or_else(|| invalid_data("invalid point encoding in proof"))?;
+                    if bool::from(point.is_identity()) || point.to_bytes().as_ref() != encoding {
+                        return Err(invalid_data("non-canonical point encoding in proof"));
+                    }
+                    Element::Point(point)
+                }
+                SCALAR_TAG => {
+                    let encoding: [u8; 32] = take(bytes, 32)?.try_into().unwrap();
+                    let scalar: C::Scalar = Option::from(C::Scalar::from_bytes(&encoding))
+                        .ok_or_else(|| invalid_data("invalid scalar encoding in proof"))?;
+                    if scalar.to_bytes() != encoding {
+                        return Err(invalid_data("non-canonical scalar encoding in proof"));
+                    }
+                    Element::Scalar(scalar)
+                }
+                _ => return Err(invalid_data("invalid message tag in proof")),
+            };
+            elements.push(element);
+        }
+        if !bytes.is_empty() {
+            return Err(invalid_data("trailing bytes after proof"));
+        }
+
+        Ok(Proof { elements })
+    }
+}
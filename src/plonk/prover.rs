@@ -1,214 +1,758 @@
 use ff::Field;
 use group::Curve;
+use rand::RngCore;
+use std::io;
 use std::iter;
+use std::ops::Range;
 
 use super::{
-    circuit::{Advice, Any, Assignment, Circuit, Column, ConstraintSystem, Fixed},
-    lookup, permutation, vanishing, ChallengeBeta, ChallengeGamma, ChallengeTheta, ChallengeX,
-    ChallengeY, Error, Permutation, ProvingKey,
+    circuit::{
+        configure_circuit, Advice, Any, Assignment, Challenge, Circuit, Column, ConstraintSystem,
+        Fixed, LookupArgument, Phase, Selector,
+    },
+    lookup, permutation, shuffle, vanishing, ChallengeBeta, ChallengeGamma, ChallengeTheta,
+    ChallengeUser, ChallengeX, ChallengeY, Error, LocalWorkers, Permutation, ProverWorkers,
+    ProvingKey, VerifyingKey,
 };
-use crate::arithmetic::{eval_polynomial, CurveAffine, FieldExt};
+use crate::arithmetic::{eval_polynomial, parallelize, CurveAffine, FieldExt};
 use crate::poly::{
-    commitment::{Blind, Params},
-    multiopen::{self, ProverQuery},
-    Coeff, ExtendedLagrangeCoeff, LagrangeCoeff, Polynomial,
+    commitment::{Blind, CommitmentScheme, Prover},
+    multiopen::ProverQuery,
+    Coeff, LagrangeCoeff, Polynomial,
 };
 use crate::transcript::TranscriptWrite;
 
-/// This creates a proof for the provided `circuit` when given the public
+/// A stage of proof creation, as reported to the progress callback of
+/// [`ProverOptions::progress`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProverStage {
+    /// Synthesizing the circuits and committing to their advice columns.
+    Witness,
+    /// Committing to the permuted values and multiplicities of the lookup arguments.
+    Lookups,
+    /// Committing to the grand products and running sums of the permutation, lookup
+    /// and shuffle arguments.
+    GrandProducts,
+    /// Computing and committing to the quotient polynomial $h(X)$.
+    Vanishing,
+    /// Evaluating the committed polynomials at $x$.
+    Evaluations,
+    /// Creating the multi-opening proof.
+    Opening,
+}
+
+/// The values of the instance columns of a circuit, together with their commitments
+/// and the polynomials that the prover derives from them.
+///
+/// Committing to an instance column takes an FFT and a multiscalar multiplication. An
+/// instance that many proofs share, such as a large set of fixed public inputs, can be
+/// committed to once with [`commit_instance`] and passed to
+/// [`ProverOptions::committed_instances`], and its
+/// [`commitments`](CommittedInstance::commitments) to [`verify_proof`](super::verify_proof).
+#[derive(Clone, Debug)]
+pub struct CommittedInstance<C: CurveAffine> {
+    instance_values: Vec<Polynomial<C::Scalar, LagrangeCoeff>>,
+    instance_polys: Vec<Polynomial<C::Scalar, Coeff>>,
+    instance_commitments: Vec<C>,
+}
+
+impl<C: CurveAffine> CommittedInstance<C> {
+    /// Returns the commitments to the instance columns, as the verifier takes them.
+    pub fn commitments(&self) -> &[C] {
+        &self.instance_commitments
+    }
+}
+
+/// Commits to the values of the instance columns of a circuit with the proving key
+/// `pk`, for use in any number of proofs.
+pub fn commit_instance<C: CurveAffine, P: CommitmentScheme<C>>(
+    params: &P,
+    pk: &ProvingKey<C>,
+    instance: &[Polynomial<C::Scalar, LagrangeCoeff>],
+) -> Result<CommittedInstance<C>, Error> {
+    if instance.len() != pk.vk.cs.num_instance_columns {
+        return Err(Error::IncompatibleParams);
+    }
+    if instance.iter().any(|poly| poly.len() as u64 != params.n()) {
+        return Err(Error::IncompatibleParams);
+    }
+
+    let domain = &pk.vk.domain;
+
+    let instance_commitments_projective: Vec<_> = instance
+        .iter()
+        .map(|poly| params.commit_lagrange(poly, Blind::default()))
+        .collect();
+    let mut instance_commitments = vec![C::identity(); instance_commitments_projective.len()];
+    C::Curve::batch_normalize(&instance_commitments_projective, &mut instance_commitments);
+    drop(instance_commitments_projective);
+    metrics::counter!("instance_commitments", instance_commitments.len() as u64);
+
+    let instance_polys: Vec<_> = instance
+        .iter()
+        .map(|poly| {
+            let lagrange_vec = domain.lagrange_from_vec(poly.to_vec());
+            domain.lagrange_to_coeff(lagrange_vec)
+        })
+        .collect();
+
+    Ok(CommittedInstance {
+        instance_values: instance.to_vec(),
+        instance_polys,
+        instance_commitments,
+    })
+}
+
+/// This creates a proof for the provided `circuits` when given the public
 /// parameters `params` and the proving key [`ProvingKey`] that was
-/// generated previously for the same circuit.
-pub fn create_proof<C: CurveAffine, T: TranscriptWrite<C>, ConcreteCircuit: Circuit<C::Scalar>>(
-    params: &Params<C>,
+/// generated previously for the same circuit. The blinding factors of the
+/// proof are sampled from `rng`, so a seeded `rng` yields the same proof each time.
+///
+/// A single proof can show that many instances of the circuit are satisfied: `circuits`
+/// holds one or more circuits for `pk`, and `instances` the values of the instance
+/// columns of each of them, in the same order. The fixed columns are opened once for
+/// all of them, their constraints are combined into a single quotient polynomial, and
+/// all of their openings are made with a single multi-opening proof. Each further
+/// circuit therefore only adds the commitments and evaluations of its own advice
+/// columns and arguments to the proof, which remains much smaller than separate proofs;
+/// the verifier must be given the instance commitments of the circuits in the same
+/// order.
+pub fn create_proof<
+    C: CurveAffine,
+    P: Prover<C>,
+    R: RngCore,
+    T: TranscriptWrite<C>,
+    ConcreteCircuit: Circuit<C::Scalar>,
+>(
+    params: &P,
     pk: &ProvingKey<C>,
     circuits: &[ConcreteCircuit],
     instances: &[&[Polynomial<C::Scalar, LagrangeCoeff>]],
+    rng: R,
     transcript: &mut T,
 ) -> Result<(), Error> {
-    for instance in instances.iter() {
-        if instance.len() != pk.vk.cs.num_instance_columns {
-            return Err(Error::IncompatibleParams);
+    create_proof_with_options(
+        params,
+        pk,
+        ProverOptions::new().circuits(circuits).instances(instances),
+        rng,
+        transcript,
+    )
+}
+
+/// The inputs of a proof and the way the prover creates it, as
+/// [`create_proof_with_options`] takes them.
+///
+/// A proof is created either for circuits, which the prover synthesizes, or for the
+/// [`Witness`]es of circuits, and either for the values of their instance columns or
+/// for instances that have already been committed to with [`commit_instance`]. Every
+/// combination of these can be proven with any [`ProverWorkers`] and progress
+/// callback.
+pub struct ProverOptions<'a, C: CurveAffine, W: ProverWorkers<C> = LocalWorkers> {
+    circuits: ProverCircuits<'a, C>,
+    instances: ProverInstances<'a, C>,
+    workers: &'a W,
+    progress: Option<Box<dyn FnMut(ProverStage) -> bool + 'a>>,
+}
+
+/// The circuits whose advice columns the prover assigns.
+enum ProverCircuits<'a, C: CurveAffine> {
+    Synthesize {
+        num_circuits: usize,
+        #[allow(clippy::type_complexity)]
+        synthesize: Box<
+            dyn Fn(
+                    usize,
+                    Phase,
+                    &ConstraintSystem<C::Scalar>,
+                    &[Option<C::Scalar>],
+                    AdviceValues<C::Scalar>,
+                ) -> Result<AdviceValues<C::Scalar>, Error>
+                + 'a,
+        >,
+    },
+    Witnesses(&'a [Witness<C>]),
+}
+
+/// The instances of the circuits, in the order of the circuits.
+enum ProverInstances<'a, C: CurveAffine> {
+    Values(&'a [&'a [Polynomial<C::Scalar, LagrangeCoeff>]]),
+    Committed(&'a [&'a CommittedInstance<C>]),
+}
+
+impl<'a, C: CurveAffine, W: ProverWorkers<C>> std::fmt::Debug for ProverOptions<'a, C, W> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let circuits = match &self.circuits {
+            ProverCircuits::Synthesize { num_circuits, .. } => *num_circuits,
+            ProverCircuits::Witnesses(witnesses) => witnesses.len(),
+        };
+        let instances = match &self.instances {
+            ProverInstances::Values(instances) => instances.len(),
+            ProverInstances::Committed(instances) => instances.len(),
+        };
+        f.debug_struct("ProverOptions")
+            .field("circuits", &circuits)
+            .field("instances", &instances)
+            .field("progress", &self.progress.is_some())
+            .finish()
+    }
+}
+
+impl<'a, C: CurveAffine> ProverOptions<'a, C> {
+    /// Returns the options of a proof for no circuits yet, which is created with
+    /// [`LocalWorkers`] and runs to completion.
+    pub fn new() -> Self {
+        ProverOptions {
+            circuits: ProverCircuits::Witnesses(&[]),
+            instances: ProverInstances::Committed(&[]),
+            workers: &LocalWorkers,
+            progress: None,
         }
     }
+}
 
-    // Hash verification key into transcript
-    pk.vk
-        .hash_into(transcript)
-        .map_err(|_| Error::TranscriptError)?;
+impl<'a, C: CurveAffine> Default for ProverOptions<'a, C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-    let domain = &pk.vk.domain;
-    let mut meta = ConstraintSystem::default();
-    let config = ConcreteCircuit::configure(&mut meta);
+impl<'a, C: CurveAffine, W: ProverWorkers<C>> ProverOptions<'a, C, W> {
+    /// Proves `circuits`, synthesizing each of them to assign its advice columns.
+    pub fn circuits<ConcreteCircuit: Circuit<C::Scalar>>(
+        mut self,
+        circuits: &'a [ConcreteCircuit],
+    ) -> Self {
+        self.circuits = ProverCircuits::Synthesize {
+            num_circuits: circuits.len(),
+            synthesize: Box::new(move |index, phase, meta, challenges, advice| {
+                synthesize_phase(&circuits[index], phase, meta, challenges, advice)
+            }),
+        };
+        self
+    }
 
-    struct InstanceSingle<'a, C: CurveAffine> {
-        pub instance_values: &'a [Polynomial<C::Scalar, LagrangeCoeff>],
-        pub instance_polys: Vec<Polynomial<C::Scalar, Coeff>>,
-        pub instance_cosets: Vec<Polynomial<C::Scalar, ExtendedLagrangeCoeff>>,
+    /// Proves the circuits whose witnesses were computed with [`generate_witness`],
+    /// without synthesizing the circuits.
+    ///
+    /// The proof fails with [`Error::IncompatibleParams`] if a witness is not for a
+    /// circuit with the proving key.
+    pub fn witnesses(mut self, witnesses: &'a [Witness<C>]) -> Self {
+        self.circuits = ProverCircuits::Witnesses(witnesses);
+        self
     }
 
-    let instance: Vec<InstanceSingle<C>> = instances
-        .iter()
-        .map(|instance| -> Result<InstanceSingle<C>, Error> {
-            let instance_commitments_projective: Vec<_> = instance
+    /// Sets the values of the instance columns of each circuit, which the prover
+    /// commits to.
+    pub fn instances(
+        mut self,
+        instances: &'a [&'a [Polynomial<C::Scalar, LagrangeCoeff>]],
+    ) -> Self {
+        self.instances = ProverInstances::Values(instances);
+        self
+    }
+
+    /// Sets the instances of each circuit, which have already been committed to with
+    /// [`commit_instance`].
+    pub fn committed_instances(mut self, instances: &'a [&'a CommittedInstance<C>]) -> Self {
+        self.instances = ProverInstances::Committed(instances);
+        self
+    }
+
+    /// Hands the FFTs and multiscalar multiplications of the advice columns to
+    /// `workers`.
+    ///
+    /// This process coordinates the proof: it synthesizes the circuits, assembles the
+    /// transcript and computes the other operations of the proof, while `workers` may
+    /// shard the operations on the advice columns across other processes or machines.
+    /// The proof is the same as if it had been created with [`LocalWorkers`] and the
+    /// same `rng`.
+    pub fn workers<V: ProverWorkers<C>>(self, workers: &'a V) -> ProverOptions<'a, C, V> {
+        ProverOptions {
+            circuits: self.circuits,
+            instances: self.instances,
+            workers,
+            progress: self.progress,
+        }
+    }
+
+    /// Calls `progress` as the prover enters each [`ProverStage`] in turn.
+    ///
+    /// If `progress` returns `false`, the prover stops before that stage and returns
+    /// [`Error::Cancelled`]; the transcript is then left with a partial proof. The
+    /// prover does not check for cancellation within a stage.
+    pub fn progress<F: FnMut(ProverStage) -> bool + 'a>(mut self, progress: F) -> Self {
+        self.progress = Some(Box::new(progress));
+        self
+    }
+}
+
+/// Creates a proof as [`create_proof`] does, for the circuits and instances of
+/// `options` and in the way that it sets.
+pub fn create_proof_with_options<
+    C: CurveAffine,
+    P: Prover<C>,
+    R: RngCore,
+    T: TranscriptWrite<C>,
+    W: ProverWorkers<C>,
+>(
+    params: &P,
+    pk: &ProvingKey<C>,
+    options: ProverOptions<'_, C, W>,
+    rng: R,
+    transcript: &mut T,
+) -> Result<(), Error> {
+    let ProverOptions {
+        circuits,
+        instances,
+        workers,
+        mut progress,
+    } = options;
+
+    let committed;
+    let instances: Vec<&CommittedInstance<C>> = match instances {
+        ProverInstances::Values(instances) => {
+            committed = instances
                 .iter()
-                .map(|poly| params.commit_lagrange(poly, Blind::default()))
-                .collect();
-            let mut instance_commitments =
-                vec![C::identity(); instance_commitments_projective.len()];
-            C::Curve::batch_normalize(&instance_commitments_projective, &mut instance_commitments);
-            let instance_commitments = instance_commitments;
-            drop(instance_commitments_projective);
-            metrics::counter!("instance_commitments", instance_commitments.len() as u64);
-
-            for commitment in &instance_commitments {
-                transcript
-                    .common_point(*commitment)
-                    .map_err(|_| Error::TranscriptError)?;
+                .map(|instance| commit_instance(params, pk, instance))
+                .collect::<Result<Vec<_>, _>>()?;
+            committed.iter().collect()
+        }
+        ProverInstances::Committed(instances) => instances.to_vec(),
+    };
+    let progress = |stage| match progress.as_mut() {
+        Some(progress) => progress(stage),
+        None => true,
+    };
+
+    let meta = &pk.vk.cs;
+    match circuits {
+        ProverCircuits::Synthesize {
+            num_circuits,
+            synthesize,
+        } => prove(
+            params,
+            pk,
+            num_circuits,
+            |index, phase, challenges, advice| synthesize(index, phase, meta, challenges, advice),
+            &instances,
+            workers,
+            rng,
+            transcript,
+            progress,
+        ),
+        ProverCircuits::Witnesses(witnesses) => {
+            if meta.phases().any(|phase| phase != Phase::first()) {
+                return Err(Error::SynthesisError);
+            }
+            for witness in witnesses {
+                if witness.advice.len() != meta.num_advice_columns
+                    || witness
+                        .advice
+                        .iter()
+                        .any(|column| column.len() as u64 != params.n())
+                {
+                    return Err(Error::IncompatibleParams);
+                }
             }
 
-            let instance_polys: Vec<_> = instance
-                .iter()
-                .map(|poly| {
-                    let lagrange_vec = domain.lagrange_from_vec(poly.to_vec());
-                    domain.lagrange_to_coeff(lagrange_vec)
-                })
-                .collect();
+            prove(
+                params,
+                pk,
+                witnesses.len(),
+                |index, _, _, _| Ok(witnesses[index].advice.clone()),
+                &instances,
+                workers,
+                rng,
+                transcript,
+                progress,
+            )
+        }
+    }
+}
 
-            let instance_cosets: Vec<_> = meta
-                .instance_queries
-                .iter()
-                .map(|&(column, at)| {
-                    let poly = instance_polys[column.index()].clone();
-                    domain.coeff_to_extended(poly, at)
-                })
-                .collect();
+/// The values of the advice columns assigned by a circuit, computed ahead of proving
+/// with [`generate_witness`].
+///
+/// A witness can be generated on a machine that holds the secrets of a circuit, and
+/// sent to another machine that creates the proof with [`ProverOptions::witnesses`],
+/// without the circuit. The proof reveals nothing about the witness, but the witness
+/// itself holds every secret value of the circuit, so it must only be sent to a
+/// prover that is trusted with them.
+///
+/// Only circuits whose advice columns are all in the first phase have a witness, as
+/// the advice of later phases depends on challenges squeezed during proving.
+#[derive(Clone, Debug)]
+pub struct Witness<C: CurveAffine> {
+    k: u32,
+    advice: Vec<Polynomial<C::Scalar, LagrangeCoeff>>,
+}
 
-            Ok(InstanceSingle {
-                instance_values: *instance,
-                instance_polys,
-                instance_cosets,
+impl<C: CurveAffine> Witness<C> {
+    /// Writes this witness to a buffer.
+    ///
+    /// The encoding is the size of the circuit and the number of advice columns, as
+    /// four little-endian bytes each, followed by the value of each cell of each
+    /// column.
+    pub fn write<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.k.to_le_bytes())?;
+        writer.write_all(&(self.advice.len() as u32).to_le_bytes())?;
+        for column in &self.advice {
+            for value in column.iter() {
+                writer.write_all(&value.to_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads a witness for a circuit with the verifying key `vk` from a buffer.
+    ///
+    /// Returns an error if the witness is for a circuit of another size or with
+    /// another number of advice columns, or if a value is not the canonical encoding
+    /// of a scalar.
+    pub fn read<R: io::Read>(reader: &mut R, vk: &VerifyingKey<C>) -> io::Result<Self> {
+        let invalid_data = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message);
+        let mut header = [0u8; 8];
+        reader.read_exact(&mut header)?;
+        if header[..4] != vk.domain.k().to_le_bytes() {
+            return Err(invalid_data("witness is for a circuit of another size"));
+        }
+        if header[4..] != (vk.cs.num_advice_columns as u32).to_le_bytes() {
+            return Err(invalid_data(
+                "witness has a different number of advice columns",
+            ));
+        }
+
+        let advice = (0..vk.cs.num_advice_columns)
+            .map(|_| {
+                let mut column = vk.domain.empty_lagrange();
+                for value in column.iter_mut() {
+                    let mut bytes = [0u8; 32];
+                    reader.read_exact(&mut bytes)?;
+                    *value = Option::from(C::Scalar::from_bytes(&bytes))
+                        .filter(|value: &C::Scalar| value.to_bytes() == bytes)
+                        .ok_or_else(|| invalid_data("invalid scalar encoding in witness"))?;
+                }
+                Ok(column)
             })
+            .collect::<io::Result<_>>()?;
+        Ok(Witness {
+            k: vk.domain.k(),
+            advice,
         })
-        .collect::<Result<Vec<_>, _>>()?;
+    }
+}
 
-    struct AdviceSingle<C: CurveAffine> {
-        pub advice_values: Vec<Polynomial<C::Scalar, LagrangeCoeff>>,
-        pub advice_polys: Vec<Polynomial<C::Scalar, Coeff>>,
-        pub advice_cosets: Vec<Polynomial<C::Scalar, ExtendedLagrangeCoeff>>,
-        pub advice_blinds: Vec<Blind<C::Scalar>>,
+/// Synthesizes `circuit` to compute its witness for the verifying key `vk`, which
+/// [`ProverOptions::witnesses`] can later prove.
+///
+/// Returns [`Error::SynthesisError`] if the circuit has advice columns in phases after
+/// the first, whose values cannot be computed before proving.
+pub fn generate_witness<C: CurveAffine, ConcreteCircuit: Circuit<C::Scalar>>(
+    vk: &VerifyingKey<C>,
+    circuit: &ConcreteCircuit,
+) -> Result<Witness<C>, Error> {
+    if vk.cs.phases().any(|phase| phase != Phase::first()) {
+        return Err(Error::SynthesisError);
     }
+    let advice = synthesize_phase(
+        circuit,
+        Phase::first(),
+        &vk.cs,
+        &[],
+        vec![vk.domain.empty_lagrange(); vk.cs.num_advice_columns],
+    )?;
+    Ok(Witness {
+        k: vk.domain.k(),
+        advice,
+    })
+}
 
-    let advice: Vec<AdviceSingle<C>> = circuits
-        .iter()
-        .map(|circuit| -> Result<AdviceSingle<C>, Error> {
-            struct WitnessCollection<F: Field> {
-                pub advice: Vec<Polynomial<F, LagrangeCoeff>>,
-                _marker: std::marker::PhantomData<F>,
-            }
+/// The values of the advice columns of a circuit.
+type AdviceValues<F> = Vec<Polynomial<F, LagrangeCoeff>>;
 
-            impl<F: Field> Assignment<F> for WitnessCollection<F> {
-                fn enter_region<NR, N>(&mut self, _: N)
-                where
-                    NR: Into<String>,
-                    N: FnOnce() -> NR,
-                {
-                    // Do nothing; we don't care about regions in this context.
-                }
+/// Collects the values of the advice columns of one phase from the synthesis of a
+/// circuit.
+struct WitnessCollection<'a, F: Field> {
+    advice: Vec<Polynomial<F, LagrangeCoeff>>,
+    phase: Phase,
+    advice_column_phase: &'a [Phase],
+    challenges: &'a [Option<F>],
+    // The rows that the circuit may assign, which exclude the blinding rows.
+    usable_rows: Range<usize>,
+}
 
-                fn exit_region(&mut self) {
-                    // Do nothing; we don't care about regions in this context.
-                }
+impl<'a, F: Field> Assignment<F> for WitnessCollection<'a, F> {
+    fn enter_region<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        // Do nothing; we don't care about regions in this context.
+    }
 
-                fn assign_advice<V, A, AR>(
-                    &mut self,
-                    _: A,
-                    column: Column<Advice>,
-                    row: usize,
-                    to: V,
-                ) -> Result<(), Error>
-                where
-                    V: FnOnce() -> Result<F, Error>,
-                    A: FnOnce() -> AR,
-                    AR: Into<String>,
-                {
-                    *self
-                        .advice
-                        .get_mut(column.index())
-                        .and_then(|v| v.get_mut(row))
-                        .ok_or(Error::BoundsFailure)? = to()?;
+    fn exit_region(&mut self) {
+        // Do nothing; we don't care about regions in this context.
+    }
 
-                    Ok(())
-                }
+    fn enable_selector<A, AR>(&mut self, _: A, _: &Selector, _: usize) -> Result<(), Error>
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        // We only care about advice columns here
 
-                fn assign_fixed<V, A, AR>(
-                    &mut self,
-                    _: A,
-                    _: Column<Fixed>,
-                    _: usize,
-                    _: V,
-                ) -> Result<(), Error>
-                where
-                    V: FnOnce() -> Result<F, Error>,
-                    A: FnOnce() -> AR,
-                    AR: Into<String>,
-                {
-                    // We only care about advice columns here
+        Ok(())
+    }
 
-                    Ok(())
-                }
+    fn assign_advice<V, A, AR>(
+        &mut self,
+        _: A,
+        column: Column<Advice>,
+        row: usize,
+        to: V,
+    ) -> Result<(), Error>
+    where
+        V: FnOnce() -> Result<F, Error>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        if !self.usable_rows.contains(&row) {
+            return Err(Error::BoundsFailure);
+        }
 
-                fn copy(
-                    &mut self,
-                    _: &Permutation,
-                    _: Column<Any>,
-                    _: usize,
-                    _: Column<Any>,
-                    _: usize,
-                ) -> Result<(), Error> {
-                    // We only care about advice columns here
-
-                    Ok(())
-                }
+        // We only care about the advice columns of the current phase here
+        if self.advice_column_phase.get(column.index()) != Some(&self.phase) {
+            return Ok(());
+        }
 
-                fn push_namespace<NR, N>(&mut self, _: N)
-                where
-                    NR: Into<String>,
-                    N: FnOnce() -> NR,
-                {
-                    // Do nothing; we don't care about namespaces in this context.
-                }
+        *self
+            .advice
+            .get_mut(column.index())
+            .and_then(|v| v.get_mut(row))
+            .ok_or(Error::BoundsFailure)? = to()?;
 
-                fn pop_namespace(&mut self, _: Option<String>) {
-                    // Do nothing; we don't care about namespaces in this context.
-                }
-            }
+        Ok(())
+    }
 
-            let mut witness = WitnessCollection {
-                advice: vec![domain.empty_lagrange(); meta.num_advice_columns],
-                _marker: std::marker::PhantomData,
-            };
+    fn assign_fixed<V, A, AR>(
+        &mut self,
+        _: A,
+        _: Column<Fixed>,
+        _: usize,
+        _: V,
+    ) -> Result<(), Error>
+    where
+        V: FnOnce() -> Result<F, Error>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        // We only care about advice columns here
 
-            // Synthesize the circuit to obtain the witness and other information.
-            circuit.synthesize(&mut witness, config.clone())?;
+        Ok(())
+    }
 
-            let witness = witness;
+    fn copy(
+        &mut self,
+        _: &Permutation,
+        _: Column<Any>,
+        _: usize,
+        _: Column<Any>,
+        _: usize,
+    ) -> Result<(), Error> {
+        // We only care about advice columns here
 
-            // Compute commitments to advice column polynomials
-            let advice_blinds: Vec<_> = witness
-                .advice
-                .iter()
-                .map(|_| Blind(C::Scalar::rand()))
-                .collect();
-            let advice_commitments_projective: Vec<_> = witness
-                .advice
+        Ok(())
+    }
+
+    fn push_namespace<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        // Do nothing; we don't care about namespaces in this context.
+    }
+
+    fn pop_namespace(&mut self, _: Option<String>) {
+        // Do nothing; we don't care about namespaces in this context.
+    }
+
+    fn get_challenge(&self, challenge: Challenge) -> Option<F> {
+        self.challenges.get(challenge.index()).cloned().flatten()
+    }
+
+    fn witness_required(&self) -> bool {
+        true
+    }
+}
+
+/// Synthesizes `circuit` to assign its advice columns of `phase` in `advice`, which
+/// holds the values of the advice columns of earlier phases.
+fn synthesize_phase<F: FieldExt, ConcreteCircuit: Circuit<F>>(
+    circuit: &ConcreteCircuit,
+    phase: Phase,
+    meta: &ConstraintSystem<F>,
+    challenges: &[Option<F>],
+    advice: Vec<Polynomial<F, LagrangeCoeff>>,
+) -> Result<Vec<Polynomial<F, LagrangeCoeff>>, Error> {
+    let n = advice.first().map_or(0, |column| column.len());
+    let mut witness = WitnessCollection {
+        advice,
+        phase,
+        advice_column_phase: &meta.advice_column_phase,
+        challenges,
+        usable_rows: 0..n.saturating_sub(meta.blinding_factors()),
+    };
+    let config = configure_circuit(&mut ConstraintSystem::default(), circuit);
+    circuit.synthesize(&mut witness, config)?;
+    Ok(witness.advice)
+}
+
+/// Creates a proof for `num_circuits` circuits, whose advice columns of each phase are
+/// assigned by `synthesize`, given the index of the circuit, the values of the
+/// challenges of earlier phases and the values of its advice columns so far.
+fn prove<
+    C: CurveAffine,
+    P: Prover<C>,
+    R: RngCore,
+    T: TranscriptWrite<C>,
+    S: FnMut(
+        usize,
+        Phase,
+        &[Option<C::Scalar>],
+        AdviceValues<C::Scalar>,
+    ) -> Result<AdviceValues<C::Scalar>, Error>,
+    W: ProverWorkers<C>,
+    F: FnMut(ProverStage) -> bool,
+>(
+    params: &P,
+    pk: &ProvingKey<C>,
+    num_circuits: usize,
+    mut synthesize: S,
+    instances: &[&CommittedInstance<C>],
+    workers: &W,
+    mut rng: R,
+    transcript: &mut T,
+    mut progress: F,
+) -> Result<(), Error> {
+    let mut enter = |stage| {
+        if progress(stage) {
+            Ok(())
+        } else {
+            Err(Error::Cancelled)
+        }
+    };
+
+    if num_circuits == 0 || num_circuits != instances.len() {
+        return Err(Error::IncompatibleParams);
+    }
+    // An instance may have been committed to for another key or parameters.
+    for instance in instances.iter() {
+        if instance.instance_values.len() != pk.vk.cs.num_instance_columns {
+            return Err(Error::IncompatibleParams);
+        }
+        if instance
+            .instance_values
+            .iter()
+            .any(|poly| poly.len() as u64 != params.n())
+        {
+            return Err(Error::IncompatibleParams);
+        }
+    }
+
+    enter(ProverStage::Witness)?;
+
+    // Hash verification key into transcript
+    pk.vk
+        .hash_into(transcript)
+        .map_err(|_| Error::TranscriptError)?;
+
+    let domain = &pk.vk.domain;
+
+    // Selector optimizations cannot be applied here; use the ConstraintSystem
+    // from the verification key.
+    let meta = &pk.vk.cs;
+
+    for instance in instances.iter() {
+        for commitment in &instance.instance_commitments {
+            transcript
+                .common_point(*commitment)
+                .map_err(|_| Error::TranscriptError)?;
+        }
+    }
+
+    struct AdviceSingle<C: CurveAffine> {
+        pub advice_values: Vec<Polynomial<C::Scalar, LagrangeCoeff>>,
+        pub advice_polys: Vec<Polynomial<C::Scalar, Coeff>>,
+        pub advice_blinds: Vec<Blind<C::Scalar>>,
+    }
+
+    let mut advice_values =
+        vec![vec![domain.empty_lagrange(); meta.num_advice_columns]; num_circuits];
+    let mut advice_blinds = vec![vec![Blind::default(); meta.num_advice_columns]; num_circuits];
+    let mut challenges = vec![None; meta.num_challenges];
+
+    for phase in meta.phases() {
+        for (index, ((instance, advice_values), advice_blinds)) in instances
+            .iter()
+            .zip(advice_values.iter_mut())
+            .zip(advice_blinds.iter_mut())
+            .enumerate()
+        {
+            // Synthesize the circuit to obtain the witness of this phase.
+            *advice_values = synthesize(index, phase, &challenges, std::mem::take(advice_values))?;
+
+            // The blinding rows of the columns of this phase are filled with random
+            // values, but for the unblinded columns.
+            let usable_rows = meta.usable_rows(domain.k());
+            for (column_index, column) in advice_values.iter_mut().enumerate() {
+                if meta.advice_column_phase[column_index] == phase
+                    && !meta.unblinded_advice_columns.contains(&column_index)
+                {
+                    for value in column[usable_rows.end..].iter_mut() {
+                        *value = C::Scalar::random(&mut rng);
+                    }
+                }
+            }
+
+            // The intermediate columns of split gates are computed from the witness.
+            meta.assign_intermediate_columns(
+                phase,
+                &|_, _| panic!("virtual selectors are removed during optimization"),
+                &pk.fixed_values,
+                advice_values,
+                &instance.instance_values,
+                &|challenge| {
+                    challenges[challenge.index()].expect("challenges of earlier phases are known")
+                },
+            );
+
+            // Compute commitments to the advice column polynomials of this phase
+            let columns: Vec<_> = meta
+                .advice_column_phase
                 .iter()
-                .zip(advice_blinds.iter())
-                .map(|(poly, blind)| params.commit_lagrange(poly, *blind))
+                .enumerate()
+                .filter(|(_, column_phase)| **column_phase == phase)
+                .map(|(index, _)| index)
                 .collect();
-            let mut advice_commitments = vec![C::identity(); advice_commitments_projective.len()];
-            C::Curve::batch_normalize(&advice_commitments_projective, &mut advice_commitments);
-            let advice_commitments = advice_commitments;
-            drop(advice_commitments_projective);
+            for &index in &columns {
+                advice_blinds[index] = if meta.unblinded_advice_columns.contains(&index) {
+                    Blind(C::Scalar::zero())
+                } else {
+                    Blind(C::Scalar::random(&mut rng))
+                };
+            }
+            let advice_commitments = workers.commit_lagrange(
+                params,
+                &columns
+                    .iter()
+                    .map(|&index| &advice_values[index])
+                    .collect::<Vec<_>>(),
+                &columns
+                    .iter()
+                    .map(|&index| advice_blinds[index])
+                    .collect::<Vec<_>>(),
+            )?;
             metrics::counter!("advice_commitments", advice_commitments.len() as u64);
 
             for commitment in &advice_commitments {
@@ -216,56 +760,106 @@ pub fn create_proof<C: CurveAffine, T: TranscriptWrite<C>, ConcreteCircuit: Circ
                     .write_point(*commitment)
                     .map_err(|_| Error::TranscriptError)?;
             }
+        }
 
-            let advice_polys: Vec<_> = witness
-                .advice
-                .clone()
-                .into_iter()
-                .map(|poly| domain.lagrange_to_coeff(poly))
-                .collect();
+        // Sample the challenges of this phase, now that its advice is committed to
+        for (challenge, challenge_phase) in challenges.iter_mut().zip(meta.challenge_phase.iter()) {
+            if *challenge_phase == phase {
+                *challenge = Some(*ChallengeUser::get(transcript));
+            }
+        }
+    }
 
-            let advice_cosets: Vec<_> = meta
-                .advice_queries
-                .iter()
-                .map(|&(column, at)| {
-                    let poly = advice_polys[column.index()].clone();
-                    domain.coeff_to_extended(poly, at)
-                })
-                .collect();
+    let challenges: Vec<_> = challenges
+        .into_iter()
+        .map(|challenge| challenge.expect("every challenge has a phase"))
+        .collect();
+
+    #[cfg(feature = "sanity-checks")]
+    // Check that the witness satisfies every constraint of every gate, so that a
+    // circuit bug is reported by the name of the constraint rather than as an
+    // invalid proof.
+    {
+        let n = params.n() as i32;
+        for (advice_values, instance) in advice_values.iter().zip(instances.iter()) {
+            let instance = &instance.instance_values;
+            for gate in meta.gates.iter() {
+                for (constraint_index, poly) in gate.polynomials().iter().enumerate() {
+                    for row in 0..n {
+                        let resolve = |at: Rotation| (row + at.0).rem_euclid(n) as usize;
+                        let value = poly.evaluate(
+                            &|scalar| scalar,
+                            &|_, _| panic!("virtual selectors are removed during optimization"),
+                            &|index| {
+                                let (column, at) = meta.fixed_queries[index];
+                                pk.fixed_values[column.index()][resolve(at)]
+                            },
+                            &|index| {
+                                let (column, at) = meta.advice_queries[index];
+                                advice_values[column.index()][resolve(at)]
+                            },
+                            &|index| {
+                                let (column, at) = meta.instance_queries[index];
+                                instance[column.index()][resolve(at)]
+                            },
+                            &|challenge| challenges[challenge.index()],
+                            &|a, b| a + &b,
+                            &|a, b| a * &b,
+                            &|a, scalar| a * scalar,
+                        );
+                        assert!(
+                            value == C::Scalar::zero(),
+                            "constraint {} ({:?}) of gate {:?} is not satisfied at row {}",
+                            constraint_index,
+                            gate.constraint_name(constraint_index),
+                            gate.name(),
+                            row
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    let advice: Vec<AdviceSingle<C>> = advice_values
+        .into_iter()
+        .zip(advice_blinds)
+        .map(|(advice_values, advice_blinds)| {
+            let advice_polys = workers.lagrange_to_coeff(domain, advice_values.clone())?;
 
             Ok(AdviceSingle {
-                advice_values: witness.advice,
+                advice_values,
                 advice_polys,
-                advice_cosets,
                 advice_blinds,
             })
         })
-        .collect::<Result<Vec<_>, _>>()?;
+        .collect::<Result<_, Error>>()?;
+
+    enter(ProverStage::Lookups)?;
 
     // Sample theta challenge for keeping lookup columns linearly independent
     let theta = ChallengeTheta::get(transcript);
 
-    let lookups: Vec<Vec<lookup::prover::Permuted<C>>> = instance
+    let lookups: Vec<Vec<lookup::prover::Permuted<C>>> = instances
         .iter()
         .zip(advice.iter())
         .map(|(instance, advice)| -> Result<Vec<_>, Error> {
             // Construct and commit to permuted values for each lookup
             pk.vk
                 .cs
-                .lookups
+                .lookups_proven_with(LookupArgument::Permutation)
                 .iter()
                 .map(|lookup| {
                     lookup.commit_permuted(
                         &pk,
-                        &params,
+                        params,
                         &domain,
                         theta,
                         &advice.advice_values,
                         &pk.fixed_values,
                         &instance.instance_values,
-                        &advice.advice_cosets,
-                        &pk.fixed_cosets,
-                        &instance.instance_cosets,
+                        &challenges,
+                        &mut rng,
                         transcript,
                     )
                 })
@@ -273,13 +867,41 @@ pub fn create_proof<C: CurveAffine, T: TranscriptWrite<C>, ConcreteCircuit: Circ
         })
         .collect::<Result<Vec<_>, _>>()?;
 
+    let log_lookups: Vec<Vec<lookup::log_derivative::prover::Multiplicities<C>>> = instances
+        .iter()
+        .zip(advice.iter())
+        .map(|(instance, advice)| -> Result<Vec<_>, Error> {
+            // Construct and commit to multiplicities for each log-derivative lookup
+            pk.vk
+                .cs
+                .lookups_proven_with(LookupArgument::LogDerivative)
+                .iter()
+                .map(|lookup| {
+                    lookup.commit_multiplicities(
+                        pk,
+                        params,
+                        theta,
+                        &advice.advice_values,
+                        &pk.fixed_values,
+                        &instance.instance_values,
+                        &challenges,
+                        &mut rng,
+                        transcript,
+                    )
+                })
+                .collect()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    enter(ProverStage::GrandProducts)?;
+
     // Sample beta challenge
     let beta = ChallengeBeta::get(transcript);
 
     // Sample gamma challenge
     let gamma = ChallengeGamma::get(transcript);
 
-    let permutations: Vec<Vec<permutation::prover::Committed<C>>> = instance
+    let permutations: Vec<Vec<permutation::prover::Committed<C>>> = instances
         .iter()
         .zip(advice.iter())
         .map(|(instance, advice)| -> Result<Vec<_>, Error> {
@@ -299,6 +921,7 @@ pub fn create_proof<C: CurveAffine, T: TranscriptWrite<C>, ConcreteCircuit: Circ
                         &instance.instance_values,
                         beta,
                         gamma,
+                        &mut rng,
                         transcript,
                     )
                 })
@@ -312,89 +935,207 @@ pub fn create_proof<C: CurveAffine, T: TranscriptWrite<C>, ConcreteCircuit: Circ
             // Construct and commit to products for each lookup
             lookups
                 .into_iter()
-                .map(|lookup| lookup.commit_product(&pk, &params, theta, beta, gamma, transcript))
+                .map(|lookup| {
+                    lookup.commit_product(&pk, params, theta, beta, gamma, &mut rng, transcript)
+                })
                 .collect::<Result<Vec<_>, _>>()
         })
         .collect::<Result<Vec<_>, _>>()?;
 
-    // Obtain challenge for keeping all separate gates linearly independent
-    let y = ChallengeY::get(transcript);
-
-    let (permutations, permutation_expressions): (Vec<Vec<_>>, Vec<Vec<_>>) = permutations
+    let log_lookups: Vec<Vec<lookup::log_derivative::prover::Committed<C>>> = log_lookups
         .into_iter()
-        .zip(advice.iter())
-        .zip(instance.iter())
-        .map(|((permutations, advice), instance)| {
-            // Evaluate the h(X) polynomial's constraint system expressions for the permutation constraints, if any.
-            let tmp: Vec<_> = permutations
+        .map(|lookups| -> Result<Vec<_>, _> {
+            // Construct and commit to running sums for each log-derivative lookup
+            lookups
                 .into_iter()
-                .zip(pk.vk.cs.permutations.iter())
-                .zip(pk.permutations.iter())
-                .map(|((p, argument), pkey)| {
-                    p.construct(
+                .map(|lookup| lookup.commit_sum(pk, params, beta, &mut rng, transcript))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let shuffles: Vec<Vec<shuffle::prover::Committed<C>>> = instances
+        .iter()
+        .zip(advice.iter())
+        .map(|(instance, advice)| -> Result<Vec<_>, Error> {
+            // Construct and commit to products for each shuffle
+            pk.vk
+                .cs
+                .shuffles
+                .iter()
+                .map(|shuffle| {
+                    shuffle.commit_product(
                         pk,
-                        argument,
-                        pkey,
-                        &advice.advice_cosets,
-                        &pk.fixed_cosets,
-                        &instance.instance_cosets,
-                        beta,
+                        params,
+                        theta,
                         gamma,
+                        &advice.advice_values,
+                        &pk.fixed_values,
+                        &instance.instance_values,
+                        &challenges,
+                        &mut rng,
+                        transcript,
                     )
                 })
-                .collect();
-
-            tmp.into_iter().unzip()
+                .collect()
         })
-        .unzip();
+        .collect::<Result<Vec<_>, _>>()?;
 
-    let (lookups, lookup_expressions): (Vec<Vec<_>>, Vec<Vec<_>>) = lookups
-        .into_iter()
-        .map(|lookups| {
-            // Evaluate the h(X) polynomial's constraint system expressions for the lookup constraints, if any.
-            let tmp: Vec<_> = lookups
-                .into_iter()
-                .map(|p| p.construct(pk, theta, beta, gamma))
-                .collect();
+    enter(ProverStage::Vanishing)?;
 
-            tmp.into_iter().unzip()
-        })
-        .unzip();
+    // Obtain challenge for keeping all separate gates linearly independent
+    let y = ChallengeY::get(transcript);
 
-    let expressions = advice
-        .iter()
-        .zip(instance.iter())
-        .zip(permutation_expressions.into_iter())
-        .zip(lookup_expressions.into_iter())
-        .flat_map(
-            |(((advice, instance), permutation_expressions), lookup_expressions)| {
-                iter::empty()
-                    // Custom constraints
-                    .chain(meta.gates.iter().map(move |(_, poly)| {
-                        poly.evaluate(
-                            &|scalar| pk.vk.domain.constant_extended(scalar),
-                            &|index| pk.fixed_cosets[index].clone(),
-                            &|index| advice.advice_cosets[index].clone(),
-                            &|index| instance.instance_cosets[index].clone(),
-                            &|a, b| a + &b,
-                            &|a, b| a * &b,
-                            &|a, scalar| a * scalar,
-                        )
-                    }))
-                    // Permutation constraints, if any.
-                    .chain(permutation_expressions.into_iter().flatten())
-                    // Lookup constraints, if any.
-                    .chain(lookup_expressions.into_iter().flatten())
-            },
-        );
+    // Evaluate the h(X) polynomial's constraint system expressions for the
+    // constraints of each circuit, folding them together with y. The extended domain
+    // is evaluated one part at a time, so that only the values of the columns and
+    // arguments over that part are held, and within a part a chunk of points at a
+    // time, so that no polynomial is allocated for any of the constraints.
+    let mut h_poly = domain.empty_extended();
+    for part in 0..domain.extended_parts() {
+        let fixed = pk.fixed_part(part);
+        let l0 = pk.l0_part(part);
+        let pkeys: Vec<_> = pk
+            .permutations
+            .iter()
+            .map(|pkey| pkey.part(domain, part))
+            .collect();
+
+        let circuits = advice
+            .iter()
+            .zip(instances.iter())
+            .zip(permutations.iter())
+            .zip(lookups.iter())
+            .zip(log_lookups.iter())
+            .zip(shuffles.iter())
+            .map(
+                |(((((advice, instance), permutations), lookups), log_lookups), shuffles)| {
+                    let advice =
+                        workers.coeff_to_extended_part(domain, &advice.advice_polys, part)?;
+                    let instance: Vec<_> = instance
+                        .instance_polys
+                        .iter()
+                        .map(|poly| domain.coeff_to_extended_part(poly.clone(), part))
+                        .collect();
+                    let permutations: Vec<_> =
+                        permutations.iter().map(|p| p.part(domain, part)).collect();
+                    let lookups: Vec<_> = lookups
+                        .iter()
+                        .zip(pk.vk.cs.lookups_proven_with(LookupArgument::Permutation))
+                        .map(|(p, argument)| {
+                            p.part(
+                                pk,
+                                argument,
+                                theta,
+                                &advice,
+                                &fixed,
+                                &instance,
+                                &challenges,
+                                part,
+                            )
+                        })
+                        .collect();
+                    let log_lookups: Vec<_> = log_lookups
+                        .iter()
+                        .zip(pk.vk.cs.lookups_proven_with(LookupArgument::LogDerivative))
+                        .map(|(p, argument)| {
+                            p.part(
+                                pk,
+                                argument,
+                                theta,
+                                &advice,
+                                &fixed,
+                                &instance,
+                                &challenges,
+                                part,
+                            )
+                        })
+                        .collect();
+                    let shuffles: Vec<_> = shuffles
+                        .iter()
+                        .zip(pk.vk.cs.shuffles.iter())
+                        .map(|(p, argument)| {
+                            p.part(
+                                pk,
+                                argument,
+                                theta,
+                                &advice,
+                                &fixed,
+                                &instance,
+                                &challenges,
+                                part,
+                            )
+                        })
+                        .collect();
+                    Ok((
+                        advice,
+                        instance,
+                        permutations,
+                        lookups,
+                        log_lookups,
+                        shuffles,
+                    ))
+                },
+            )
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        let mut h = domain.empty_part();
+        parallelize(&mut h, |h, start| {
+            for (advice, instance, permutations, lookups, log_lookups, shuffles) in circuits.iter()
+            {
+                // Custom constraints
+                pk.evaluator
+                    .fold_gates(h, start, &fixed, advice, instance, &challenges, *y);
+
+                // Permutation constraints, if any.
+                for ((p, argument), pkey) in permutations
+                    .iter()
+                    .zip(pk.vk.cs.permutations.iter())
+                    .zip(pkeys.iter())
+                {
+                    p.fold(
+                        pk, argument, part, &l0, pkey, advice, &fixed, instance, beta, gamma, *y,
+                        h, start,
+                    );
+                }
+
+                // Lookup constraints, if any.
+                for p in lookups.iter() {
+                    p.fold(pk, &l0, beta, gamma, *y, h, start);
+                }
+
+                // Log-derivative lookup constraints, if any.
+                for p in log_lookups.iter() {
+                    p.fold(pk, beta, *y, h, start);
+                }
+
+                // Shuffle constraints, if any.
+                for p in shuffles.iter() {
+                    p.fold(pk, &l0, gamma, *y, h, start);
+                }
+            }
+        });
+        domain.extended_from_part(&mut h_poly, part, &h);
+    }
+
+    // The values of the expressions of the lookups are no longer needed.
+    let lookups: Vec<Vec<_>> = lookups
+        .into_iter()
+        .map(|lookups| lookups.into_iter().map(|p| p.construct()).collect())
+        .collect();
+    let log_lookups: Vec<Vec<_>> = log_lookups
+        .into_iter()
+        .map(|lookups| lookups.into_iter().map(|p| p.construct()).collect())
+        .collect();
 
     // Construct the vanishing argument
-    let vanishing = vanishing::Argument::construct(params, domain, expressions, y, transcript)?;
+    let vanishing = vanishing::Argument::construct(params, domain, h_poly, &mut rng, transcript)?;
+
+    enter(ProverStage::Evaluations)?;
 
     let x = ChallengeX::get(transcript);
 
     // Compute and hash instance evals for each circuit instance
-    for instance in instance.iter() {
+    for instance in instances.iter() {
         // Evaluate polynomials at omega^i x
         let instance_evals: Vec<_> = meta
             .instance_queries
@@ -478,57 +1219,82 @@ pub fn create_proof<C: CurveAffine, T: TranscriptWrite<C>, ConcreteCircuit: Circ
         })
         .collect::<Result<Vec<_>, _>>()?;
 
-    let instances = instance
-        .iter()
-        .zip(advice.iter())
-        .zip(permutations.iter())
-        .zip(lookups.iter())
-        .flat_map(|(((instance, advice), permutations), lookups)| {
-            iter::empty()
-                .chain(
-                    pk.vk
-                        .cs
-                        .instance_queries
-                        .iter()
-                        .map(move |&(column, at)| ProverQuery {
-                            point: domain.rotate_omega(*x, at),
-                            poly: &instance.instance_polys[column.index()],
-                            blind: Blind::default(),
-                        }),
-                )
-                .chain(
-                    pk.vk
-                        .cs
-                        .advice_queries
-                        .iter()
-                        .map(move |&(column, at)| ProverQuery {
-                            point: domain.rotate_omega(*x, at),
-                            poly: &advice.advice_polys[column.index()],
-                            blind: advice.advice_blinds[column.index()],
-                        }),
-                )
-                .chain(
-                    permutations
-                        .iter()
-                        .zip(pk.permutations.iter())
-                        .flat_map(move |(p, pkey)| p.open(pk, pkey, x))
-                        .into_iter(),
-                )
-                .chain(lookups.iter().flat_map(move |p| p.open(pk, x)).into_iter())
+    // Evaluate the log-derivative lookups, if any, at omega^i x.
+    let log_lookups: Vec<Vec<lookup::log_derivative::prover::Evaluated<C>>> = log_lookups
+        .into_iter()
+        .map(|lookups| -> Result<Vec<_>, _> {
+            lookups
+                .into_iter()
+                .map(|p| p.evaluate(pk, x, transcript))
+                .collect::<Result<Vec<_>, _>>()
         })
-        .chain(
-            pk.vk
-                .cs
-                .fixed_queries
-                .iter()
-                .map(|&(column, at)| ProverQuery {
-                    point: domain.rotate_omega(*x, at),
-                    poly: &pk.fixed_polys[column.index()],
-                    blind: Blind::default(),
-                }),
-        )
-        // We query the h(X) polynomial at x
-        .chain(vanishing.open(x));
-
-    multiopen::create_proof(params, transcript, instances).map_err(|_| Error::OpeningError)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Evaluate the shuffles, if any, at omega^i x.
+    let shuffles: Vec<Vec<shuffle::prover::Evaluated<C>>> = shuffles
+        .into_iter()
+        .map(|shuffles| -> Result<Vec<_>, _> {
+            shuffles
+                .into_iter()
+                .map(|p| p.evaluate(pk, x, transcript))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let instances =
+        instances
+            .iter()
+            .zip(advice.iter())
+            .zip(permutations.iter())
+            .zip(lookups.iter())
+            .zip(log_lookups.iter())
+            .zip(shuffles.iter())
+            .flat_map(
+                |(((((instance, advice), permutations), lookups), log_lookups), shuffles)| {
+                    iter::empty()
+                        .chain(pk.vk.cs.instance_queries.iter().map(move |&(column, at)| {
+                            ProverQuery {
+                                point: domain.rotate_omega(*x, at),
+                                poly: &instance.instance_polys[column.index()],
+                                blind: Blind::default(),
+                            }
+                        }))
+                        .chain(pk.vk.cs.advice_queries.iter().map(move |&(column, at)| {
+                            ProverQuery {
+                                point: domain.rotate_omega(*x, at),
+                                poly: &advice.advice_polys[column.index()],
+                                blind: advice.advice_blinds[column.index()],
+                            }
+                        }))
+                        .chain(
+                            permutations
+                                .iter()
+                                .zip(pk.permutations.iter())
+                                .flat_map(move |(p, pkey)| p.open(pk, pkey, x))
+                                .into_iter(),
+                        )
+                        .chain(lookups.iter().flat_map(move |p| p.open(pk, x)).into_iter())
+                        .chain(log_lookups.iter().flat_map(move |p| p.open(pk, x)))
+                        .chain(shuffles.iter().flat_map(move |p| p.open(pk, x)))
+                },
+            )
+            .chain(
+                pk.vk
+                    .cs
+                    .fixed_queries
+                    .iter()
+                    .map(|&(column, at)| ProverQuery {
+                        point: domain.rotate_omega(*x, at),
+                        poly: &pk.fixed_polys[column.index()],
+                        blind: Blind::default(),
+                    }),
+            )
+            // We query the h(X) polynomial at x
+            .chain(vanishing.open(x));
+
+    enter(ProverStage::Opening)?;
+
+    params
+        .create_multi_opening(rng, transcript, instances)
+        .map_err(|_| Error::OpeningError)
 }
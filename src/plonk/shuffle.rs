@@ -0,0 +1,42 @@
+use super::circuit::Expression;
+use ff::Field;
+
+pub(crate) mod prover;
+pub(crate) mod verifier;
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Argument<F: Field> {
+    pub input_expressions: Vec<Expression<F>>,
+    pub shuffle_expressions: Vec<Expression<F>>,
+}
+
+impl<F: Field> Argument<F> {
+    pub fn new(input_expressions: &[Expression<F>], shuffle_expressions: &[Expression<F>]) -> Self {
+        assert_eq!(input_expressions.len(), shuffle_expressions.len());
+        Argument {
+            input_expressions: input_expressions.to_vec(),
+            shuffle_expressions: shuffle_expressions.to_vec(),
+        }
+    }
+
+    pub(crate) fn required_degree(&self) -> usize {
+        assert_eq!(self.input_expressions.len(), self.shuffle_expressions.len());
+
+        // degree 2:
+        // l_0(X) * (1 - z(X)) = 0
+        //
+        // degree (1 + max(input_degree, shuffle_degree)):
+        // z(\omega X) (\theta^{m-1} s_0(X) + ... + s_{m-1}(X) + \gamma)
+        // - z(X) (\theta^{m-1} a_0(X) + ... + a_{m-1}(X) + \gamma)
+        let mut degree = 1;
+        for expr in self
+            .input_expressions
+            .iter()
+            .chain(self.shuffle_expressions.iter())
+        {
+            degree = std::cmp::max(degree, expr.degree());
+        }
+
+        1 + degree
+    }
+}
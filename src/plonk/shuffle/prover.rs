@@ -0,0 +1,242 @@
+use super::super::{
+    lookup::prover::{compress_expressions, compress_expressions_part},
+    ChallengeGamma, ChallengeTheta, ChallengeX, Error, ProvingKey,
+};
+use super::Argument;
+use crate::{
+    arithmetic::{
+        eval_polynomial, parallel_prefix_product, parallelize, BatchInvert, CurveAffine, FieldExt,
+    },
+    poly::{
+        commitment::{Blind, CommitmentScheme},
+        multiopen::ProverQuery,
+        Coeff, LagrangeCoeff, PartLagrangeCoeff, Polynomial, Rotation,
+    },
+    transcript::TranscriptWrite,
+};
+use ff::Field;
+use group::Curve;
+use rand::RngCore;
+use std::iter;
+
+#[derive(Debug)]
+pub(in crate::plonk) struct Committed<C: CurveAffine> {
+    product_poly: Polynomial<C::Scalar, Coeff>,
+    product_blind: Blind<C::Scalar>,
+}
+
+/// The values of the compressed expressions and the grand product of a shuffle over
+/// a part of the extended domain.
+pub(in crate::plonk) struct Part<C: CurveAffine> {
+    compressed_input: Polynomial<C::Scalar, PartLagrangeCoeff>,
+    compressed_shuffle: Polynomial<C::Scalar, PartLagrangeCoeff>,
+    product: Polynomial<C::Scalar, PartLagrangeCoeff>,
+}
+
+pub(in crate::plonk) struct Evaluated<C: CurveAffine> {
+    committed: Committed<C>,
+}
+
+impl<F: FieldExt> Argument<F> {
+    /// Given a Shuffle with input expressions [A_0, A_1, ..., A_{m-1}] and shuffle
+    /// expressions [S_0, S_1, ..., S_{m-1}], this method compresses them into A and S
+    /// as in the lookup argument, and commits to the grand product
+    /// $z(\omega^{i+1}) = z(\omega^i) (A(\omega^i) + \gamma) / (S(\omega^i) + \gamma)$
+    /// starting from $z(\omega^0) = 1$.
+    pub(in crate::plonk) fn commit_product<
+        'a,
+        C,
+        P: CommitmentScheme<C>,
+        R: RngCore,
+        T: TranscriptWrite<C>,
+    >(
+        &self,
+        pk: &ProvingKey<C>,
+        params: &P,
+        theta: ChallengeTheta<C>,
+        gamma: ChallengeGamma<C>,
+        advice_values: &'a [Polynomial<C::Scalar, LagrangeCoeff>],
+        fixed_values: &'a [Polynomial<C::Scalar, LagrangeCoeff>],
+        instance_values: &'a [Polynomial<C::Scalar, LagrangeCoeff>],
+        challenges: &'a [C::Scalar],
+        mut rng: R,
+        transcript: &mut T,
+    ) -> Result<Committed<C>, Error>
+    where
+        C: CurveAffine<ScalarExt = F>,
+    {
+        let n = params.n() as usize;
+        let compress_expressions = |expressions| {
+            let (_, compressed_expression) = compress_expressions(
+                pk,
+                n,
+                theta,
+                expressions,
+                advice_values,
+                fixed_values,
+                instance_values,
+                challenges,
+            );
+            compressed_expression
+        };
+
+        let compressed_input_expression = compress_expressions(&self.input_expressions);
+        let compressed_shuffle_expression = compress_expressions(&self.shuffle_expressions);
+
+        // Batch invert to obtain the denominators 1 / (S(\omega^i) + \gamma)
+        let mut shuffle_product = vec![C::Scalar::zero(); n];
+        parallelize(&mut shuffle_product, |shuffle_product, start| {
+            for (shuffle_product, shuffle_value) in shuffle_product
+                .iter_mut()
+                .zip(compressed_shuffle_expression[start..].iter())
+            {
+                *shuffle_product = *gamma + shuffle_value;
+            }
+        });
+        shuffle_product.iter_mut().batch_invert();
+
+        // Finish the computation of each fraction with the numerator A(\omega^i) + \gamma
+        parallelize(&mut shuffle_product, |shuffle_product, start| {
+            for (shuffle_product, input_value) in shuffle_product
+                .iter_mut()
+                .zip(compressed_input_expression[start..].iter())
+            {
+                *shuffle_product *= &(*gamma + input_value);
+            }
+        });
+
+        // Compute the evaluations of the grand product over our domain, starting with
+        // z[0] = 1
+        let mut z = shuffle_product;
+        z.rotate_right(1);
+        z[0] = C::Scalar::one();
+        parallel_prefix_product(&mut z);
+
+        let z = pk.vk.domain.lagrange_from_vec(z);
+        let product_blind = Blind(C::Scalar::random(&mut rng));
+        let product_commitment = params.commit_lagrange(&z, product_blind).to_affine();
+        let z = pk.vk.domain.lagrange_to_coeff(z);
+
+        // Hash product commitment
+        transcript
+            .write_point(product_commitment)
+            .map_err(|_| Error::TranscriptError)?;
+
+        Ok(Committed {
+            product_poly: z,
+            product_blind,
+        })
+    }
+}
+
+impl<C: CurveAffine> Committed<C> {
+    /// Returns the values of this shuffle over the `part`-th part of the extended
+    /// domain, compressing the expressions of `argument` from the values of the
+    /// columns over the same part.
+    pub(in crate::plonk) fn part(
+        &self,
+        pk: &ProvingKey<C>,
+        argument: &Argument<C::Scalar>,
+        theta: ChallengeTheta<C>,
+        advice: &[Polynomial<C::Scalar, PartLagrangeCoeff>],
+        fixed: &[Polynomial<C::Scalar, PartLagrangeCoeff>],
+        instance: &[Polynomial<C::Scalar, PartLagrangeCoeff>],
+        challenges: &[C::Scalar],
+        part: usize,
+    ) -> Part<C> {
+        let compress = |expressions| {
+            compress_expressions_part(pk, theta, expressions, advice, fixed, instance, challenges)
+        };
+
+        Part {
+            compressed_input: compress(&argument.input_expressions),
+            compressed_shuffle: compress(&argument.shuffle_expressions),
+            product: pk
+                .vk
+                .domain
+                .coeff_to_extended_part(self.product_poly.clone(), part),
+        }
+    }
+}
+
+impl<C: CurveAffine> Part<C> {
+    /// Given the values of a Shuffle over a part of the extended domain, this method
+    /// folds the constraints that must hold between them into `h`, the values of
+    /// $h(X)$ at the points of the part from index `start`, so that each constraint
+    /// is accumulated as $h(X) \cdot y + c(X)$ in turn.
+    pub(in crate::plonk) fn fold(
+        &self,
+        pk: &ProvingKey<C>,
+        l0: &Polynomial<C::Scalar, PartLagrangeCoeff>,
+        gamma: ChallengeGamma<C>,
+        y: C::Scalar,
+        h: &mut [C::Scalar],
+        start: usize,
+    ) {
+        let next = pk.vk.domain.part_rotation(Rotation::next());
+        let mask = l0.len() - 1;
+
+        for (i, h) in h.iter_mut().enumerate() {
+            let i = i + start;
+            let product = self.product[i];
+
+            // l_0(X) * (1 - z(X)) = 0
+            *h = *h * &y + &(l0[i] * &(C::Scalar::one() - &product));
+
+            // z(\omega X) (S(X) + \gamma) - z(X) (A(X) + \gamma)
+            let left = self.product[(i + next) & mask] * &(self.compressed_shuffle[i] + &*gamma);
+            let right = product * &(self.compressed_input[i] + &*gamma);
+            *h = *h * &y + &(left - &right);
+        }
+    }
+}
+
+impl<C: CurveAffine> Committed<C> {
+    pub(in crate::plonk) fn evaluate<T: TranscriptWrite<C>>(
+        self,
+        pk: &ProvingKey<C>,
+        x: ChallengeX<C>,
+        transcript: &mut T,
+    ) -> Result<Evaluated<C>, Error> {
+        let x_next = pk.vk.domain.rotate_omega(*x, Rotation::next());
+
+        let product_eval = eval_polynomial(&self.product_poly, *x);
+        let product_next_eval = eval_polynomial(&self.product_poly, x_next);
+
+        // Hash each evaluation
+        for eval in iter::empty()
+            .chain(Some(product_eval))
+            .chain(Some(product_next_eval))
+        {
+            transcript
+                .write_scalar(eval)
+                .map_err(|_| Error::TranscriptError)?;
+        }
+
+        Ok(Evaluated { committed: self })
+    }
+}
+
+impl<C: CurveAffine> Evaluated<C> {
+    pub(in crate::plonk) fn open<'a>(
+        &'a self,
+        pk: &'a ProvingKey<C>,
+        x: ChallengeX<C>,
+    ) -> impl Iterator<Item = ProverQuery<'a, C>> + Clone {
+        let x_next = pk.vk.domain.rotate_omega(*x, Rotation::next());
+
+        iter::empty()
+            // Open shuffle product commitments at x
+            .chain(Some(ProverQuery {
+                point: *x,
+                poly: &self.committed.product_poly,
+                blind: self.committed.product_blind,
+            }))
+            // Open shuffle product commitments at \omega x
+            .chain(Some(ProverQuery {
+                point: x_next,
+                poly: &self.committed.product_poly,
+                blind: self.committed.product_blind,
+            }))
+    }
+}
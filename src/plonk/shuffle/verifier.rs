@@ -0,0 +1,121 @@
+use std::iter;
+
+use super::super::circuit::Expression;
+use super::Argument;
+use crate::{
+    arithmetic::{CurveAffine, FieldExt},
+    plonk::{ChallengeGamma, ChallengeTheta, ChallengeX, Error, VerifyingKey},
+    poly::{multiopen::VerifierQuery, Rotation},
+    transcript::TranscriptRead,
+};
+use ff::Field;
+
+pub struct Committed<C: CurveAffine> {
+    product_commitment: C,
+}
+
+pub struct Evaluated<C: CurveAffine> {
+    committed: Committed<C>,
+    product_eval: C::Scalar,
+    product_next_eval: C::Scalar,
+}
+
+impl<F: FieldExt> Argument<F> {
+    pub(in crate::plonk) fn read_product_commitment<C: CurveAffine, T: TranscriptRead<C>>(
+        &self,
+        transcript: &mut T,
+    ) -> Result<Committed<C>, Error> {
+        let product_commitment = transcript
+            .read_point()
+            .map_err(|_| Error::TranscriptError)?;
+
+        Ok(Committed { product_commitment })
+    }
+}
+
+impl<C: CurveAffine> Committed<C> {
+    pub(crate) fn evaluate<T: TranscriptRead<C>>(
+        self,
+        transcript: &mut T,
+    ) -> Result<Evaluated<C>, Error> {
+        let product_eval = transcript
+            .read_scalar()
+            .map_err(|_| Error::TranscriptError)?;
+        let product_next_eval = transcript
+            .read_scalar()
+            .map_err(|_| Error::TranscriptError)?;
+
+        Ok(Evaluated {
+            committed: self,
+            product_eval,
+            product_next_eval,
+        })
+    }
+}
+
+impl<C: CurveAffine> Evaluated<C> {
+    pub(in crate::plonk) fn expressions<'a>(
+        &'a self,
+        l_0: C::Scalar,
+        argument: &'a Argument<C::Scalar>,
+        theta: ChallengeTheta<C>,
+        gamma: ChallengeGamma<C>,
+        advice_evals: &[C::Scalar],
+        fixed_evals: &[C::Scalar],
+        instance_evals: &[C::Scalar],
+        challenges: &[C::Scalar],
+    ) -> impl Iterator<Item = C::Scalar> + 'a {
+        let compress_expressions = |expressions: &[Expression<C::Scalar>]| {
+            expressions
+                .iter()
+                .map(|expression| {
+                    expression.evaluate(
+                        &|scalar| scalar,
+                        &|_, _| panic!("virtual selectors are removed during optimization"),
+                        &|index| fixed_evals[index],
+                        &|index| advice_evals[index],
+                        &|index| instance_evals[index],
+                        &|challenge| challenges[challenge.index()],
+                        &|a, b| a + &b,
+                        &|a, b| a * &b,
+                        &|a, scalar| a * &scalar,
+                    )
+                })
+                .fold(C::Scalar::zero(), |acc, eval| acc * &*theta + &eval)
+        };
+        let input = compress_expressions(&argument.input_expressions) + &*gamma;
+        let shuffle = compress_expressions(&argument.shuffle_expressions) + &*gamma;
+
+        iter::empty()
+            .chain(
+                // l_0(X) * (1 - z(X)) = 0
+                Some(l_0 * &(C::Scalar::one() - &self.product_eval)),
+            )
+            .chain(
+                // z(\omega X) (S(X) + \gamma) - z(X) (A(X) + \gamma)
+                Some(self.product_next_eval * &shuffle - &(self.product_eval * &input)),
+            )
+    }
+
+    pub(in crate::plonk) fn queries<'a>(
+        &'a self,
+        vk: &'a VerifyingKey<C>,
+        x: ChallengeX<C>,
+    ) -> impl Iterator<Item = VerifierQuery<'a, C>> + Clone {
+        let x_next = vk.domain.rotate_omega(*x, Rotation::next());
+
+        iter::empty()
+            // Open shuffle product commitments at x
+            .chain(Some(VerifierQuery {
+                point: *x,
+                commitment: &self.committed.product_commitment,
+                eval: self.product_eval,
+            }))
+            // Open shuffle product commitments at \omega x
+            .chain(Some(VerifierQuery {
+                point: x_next,
+                commitment: &self.committed.product_commitment,
+                eval: self.product_next_eval,
+            }))
+    }
+}
@@ -1,11 +1,13 @@
+use ff::Field;
 use group::Curve;
+use rand::RngCore;
 
 use super::Argument;
 use crate::{
-    arithmetic::{eval_polynomial, CurveAffine, FieldExt},
-    plonk::{ChallengeX, ChallengeY, Error},
+    arithmetic::{eval_polynomial, CurveAffine},
+    plonk::{ChallengeX, Error},
     poly::{
-        commitment::{Blind, Params},
+        commitment::{Blind, CommitmentScheme},
         multiopen::ProverQuery,
         Coeff, EvaluationDomain, ExtendedLagrangeCoeff, Polynomial,
     },
@@ -22,29 +24,34 @@ pub(in crate::plonk) struct Evaluated<C: CurveAffine> {
 }
 
 impl<C: CurveAffine> Argument<C> {
-    pub(in crate::plonk) fn construct<T: TranscriptWrite<C>>(
-        params: &Params<C>,
+    pub(in crate::plonk) fn construct<P: CommitmentScheme<C>, R: RngCore, T: TranscriptWrite<C>>(
+        params: &P,
         domain: &EvaluationDomain<C::Scalar>,
-        expressions: impl Iterator<Item = Polynomial<C::Scalar, ExtendedLagrangeCoeff>>,
-        y: ChallengeY<C>,
+        h_poly: Polynomial<C::Scalar, ExtendedLagrangeCoeff>,
+        mut rng: R,
         transcript: &mut T,
     ) -> Result<Constructed<C>, Error> {
-        // Evaluate the h(X) polynomial's constraint system expressions for the constraints provided
-        let h_poly = expressions.fold(domain.empty_extended(), |h_poly, v| h_poly * *y + &v);
-
         // Divide by t(X) = X^{params.n} - 1.
         let h_poly = domain.divide_by_vanishing_poly(h_poly);
 
         // Obtain final h(X) polynomial
-        let h_poly = domain.extended_to_coeff(h_poly);
+        let mut h_poly = domain.extended_to_coeff(h_poly);
 
-        // Split h(X) up into pieces
-        let h_pieces = h_poly
-            .chunks_exact(params.n as usize)
-            .map(|v| domain.coeff_from_vec(v.to_vec()))
-            .collect::<Vec<_>>();
-        drop(h_poly);
-        let h_blinds: Vec<_> = h_pieces.iter().map(|_| Blind(C::Scalar::rand())).collect();
+        // Split h(X) up into pieces, moving each piece off the end in turn so that
+        // h(X) is never held twice
+        let n = params.n() as usize;
+        let mut h_pieces = Vec::with_capacity(h_poly.len() / n);
+        while h_poly.len() > n {
+            let piece = h_poly.split_off(h_poly.len() - n);
+            h_pieces.push(domain.coeff_from_vec(piece));
+        }
+        h_poly.shrink_to_fit();
+        h_pieces.push(domain.coeff_from_vec(h_poly));
+        h_pieces.reverse();
+        let h_blinds: Vec<_> = h_pieces
+            .iter()
+            .map(|_| Blind(C::Scalar::random(&mut rng)))
+            .collect();
 
         // Compute commitments to each h(X) piece
         let h_commitments_projective: Vec<_> = h_pieces
@@ -2,25 +2,54 @@ use ff::Field;
 use std::iter;
 
 use super::{
-    vanishing, ChallengeBeta, ChallengeGamma, ChallengeTheta, ChallengeX, ChallengeY, Error,
-    VerifyingKey,
+    vanishing, ChallengeBeta, ChallengeGamma, ChallengeTheta, ChallengeUser, ChallengeX,
+    ChallengeY, Error, LookupArgument, VerifyingKey,
 };
 use crate::arithmetic::{CurveAffine, FieldExt};
-use crate::poly::{
-    commitment::{Guard, Params, MSM},
-    multiopen::{self, VerifierQuery},
-};
-use crate::transcript::{read_n_points, read_n_scalars, TranscriptRead};
+use crate::poly::{commitment::Verifier, multiopen::VerifierQuery};
+use crate::transcript::{read_n_scalars, TranscriptRead};
 
-/// Returns a boolean indicating whether or not the proof is valid
-pub fn verify_proof<'a, C: CurveAffine, T: TranscriptRead<C>>(
-    params: &'a Params<C>,
+/// Checks the proofs in `transcript`, and returns a guard that holds the deferred
+/// multiscalar multiplication of their opening.
+///
+/// `instance_commitments` holds the commitments to the instance columns of each circuit
+/// that the proof is for, in the order in which they were given to
+/// [`create_proof`](super::create_proof).
+///
+/// The verifier only needs these commitments, never the values of the instance
+/// columns, which the prover evaluates for it. A verifier that receives the
+/// commitments from elsewhere, such as a data-availability layer, can pass them here
+/// directly. They are computed from the values with
+/// [`VerifyingKey::commit_instances`], or read from
+/// [`CommittedInstance::commitments`](super::CommittedInstance::commitments) of an
+/// instance committed to by the prover.
+///
+/// This accepts whatever encodings of points and scalars `transcript` reads, and
+/// does not check that the proof ends with the last message that it reads. A
+/// transcript created with [`Blake2bRead::init_strict`] only reads canonical
+/// encodings, and its [`Blake2bRead::finalize`] rejects trailing bytes, so that a
+/// proof cannot be re-encoded without being rejected. [`Proof`](super::Proof) is
+/// always parsed in this way.
+///
+/// The proofs are valid if the guard's checks pass, as decided by
+/// [`OpeningGuard::decide`]. With the inner product argument, guards of many proofs
+/// can be decided together with [`FoldedGuards`].
+///
+/// [`Blake2bRead::init_strict`]: crate::transcript::Blake2bRead::init_strict
+/// [`Blake2bRead::finalize`]: crate::transcript::Blake2bRead::finalize
+/// [`OpeningGuard::decide`]: crate::poly::commitment::OpeningGuard::decide
+/// [`FoldedGuards`]: crate::poly::commitment::FoldedGuards
+pub fn verify_proof<'a, C: CurveAffine, P: Verifier<C>, T: TranscriptRead<C>>(
+    params: &'a P,
     vk: &VerifyingKey<C>,
-    msm: MSM<'a, C>,
+    msm: P::Msm<'a>,
     instance_commitments: &[&[C]],
     transcript: &mut T,
-) -> Result<Guard<'a, C>, Error> {
+) -> Result<P::Guard<'a>, Error> {
     // Check that instance_commitments matches the expected number of instance columns
+    if instance_commitments.is_empty() {
+        return Err(Error::IncompatibleParams);
+    }
     for instance_commitments in instance_commitments.iter() {
         if instance_commitments.len() != vk.cs.num_instance_columns {
             return Err(Error::IncompatibleParams);
@@ -42,12 +71,32 @@ pub fn verify_proof<'a, C: CurveAffine, T: TranscriptRead<C>>(
         }
     }
 
-    let advice_commitments = (0..num_proofs)
-        .map(|_| -> Result<Vec<_>, _> {
-            // Hash the prover's advice commitments into the transcript
-            read_n_points(transcript, vk.cs.num_advice_columns).map_err(|_| Error::TranscriptError)
-        })
-        .collect::<Result<Vec<_>, _>>()?;
+    let mut advice_commitments = vec![vec![C::identity(); vk.cs.num_advice_columns]; num_proofs];
+    let mut challenges = vec![C::Scalar::zero(); vk.cs.num_challenges];
+
+    for phase in vk.cs.phases() {
+        for advice_commitments in advice_commitments.iter_mut() {
+            // Hash the prover's advice commitments of this phase into the transcript
+            for (commitment, column_phase) in advice_commitments
+                .iter_mut()
+                .zip(vk.cs.advice_column_phase.iter())
+            {
+                if *column_phase == phase {
+                    *commitment = transcript
+                        .read_point()
+                        .map_err(|_| Error::TranscriptError)?;
+                }
+            }
+        }
+
+        // Sample the challenges of this phase, now that its advice is committed to
+        for (challenge, challenge_phase) in challenges.iter_mut().zip(vk.cs.challenge_phase.iter())
+        {
+            if *challenge_phase == phase {
+                *challenge = *ChallengeUser::get(transcript);
+            }
+        }
+    }
 
     // Sample theta challenge for keeping lookup columns linearly independent
     let theta = ChallengeTheta::get(transcript);
@@ -56,13 +105,24 @@ pub fn verify_proof<'a, C: CurveAffine, T: TranscriptRead<C>>(
         .map(|_| -> Result<Vec<_>, _> {
             // Hash each lookup permuted commitment
             vk.cs
-                .lookups
+                .lookups_proven_with(LookupArgument::Permutation)
                 .iter()
                 .map(|argument| argument.read_permuted_commitments(transcript))
                 .collect::<Result<Vec<_>, _>>()
         })
         .collect::<Result<Vec<_>, _>>()?;
 
+    let log_lookups_multiplicities = (0..num_proofs)
+        .map(|_| -> Result<Vec<_>, _> {
+            // Hash each log-derivative lookup multiplicity commitment
+            vk.cs
+                .lookups_proven_with(LookupArgument::LogDerivative)
+                .iter()
+                .map(|argument| argument.read_multiplicity_commitment(transcript))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
     // Sample beta challenge
     let beta = ChallengeBeta::get(transcript);
 
@@ -75,7 +135,7 @@ pub fn verify_proof<'a, C: CurveAffine, T: TranscriptRead<C>>(
             vk.cs
                 .permutations
                 .iter()
-                .map(|argument| argument.read_product_commitment(transcript))
+                .map(|argument| argument.read_product_commitments(vk, transcript))
                 .collect::<Result<Vec<_>, _>>()
         })
         .collect::<Result<Vec<_>, _>>()?;
@@ -91,6 +151,28 @@ pub fn verify_proof<'a, C: CurveAffine, T: TranscriptRead<C>>(
         })
         .collect::<Result<Vec<_>, _>>()?;
 
+    let log_lookups_committed = log_lookups_multiplicities
+        .into_iter()
+        .map(|lookups| {
+            // Hash each log-derivative lookup running sum commitment
+            lookups
+                .into_iter()
+                .map(|lookup| lookup.read_sum_commitment(transcript))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let shuffles_committed = (0..num_proofs)
+        .map(|_| -> Result<Vec<_>, _> {
+            // Hash each shuffle product commitment
+            vk.cs
+                .shuffles
+                .iter()
+                .map(|argument| argument.read_product_commitment(transcript))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
     // Sample y challenge, which keeps the gates linearly independent.
     let y = ChallengeY::get(transcript);
 
@@ -140,11 +222,31 @@ pub fn verify_proof<'a, C: CurveAffine, T: TranscriptRead<C>>(
         })
         .collect::<Result<Vec<_>, _>>()?;
 
+    let log_lookups_evaluated = log_lookups_committed
+        .into_iter()
+        .map(|lookups| -> Result<Vec<_>, _> {
+            lookups
+                .into_iter()
+                .map(|lookup| lookup.evaluate(transcript))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let shuffles_evaluated = shuffles_committed
+        .into_iter()
+        .map(|shuffles| -> Result<Vec<_>, _> {
+            shuffles
+                .into_iter()
+                .map(|shuffle| shuffle.evaluate(transcript))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
     // This check ensures the circuit is satisfied so long as the polynomial
     // commitments open to the correct values.
     {
         // x^n
-        let xn = x.pow(&[params.n as u64, 0, 0, 0]);
+        let xn = x.pow(&[params.n(), 0, 0, 0]);
 
         // TODO: bubble this error up
         // l_0(x)
@@ -153,30 +255,39 @@ pub fn verify_proof<'a, C: CurveAffine, T: TranscriptRead<C>>(
             * &vk.domain.get_barycentric_weight(); // l_0(x)
 
         // Compute the expected value of h(x)
+        let fixed_evals = &fixed_evals;
+        let challenges = &challenges;
         let expressions = advice_evals
             .iter()
             .zip(instance_evals.iter())
             .zip(permutations_evaluated.iter())
             .zip(lookups_evaluated.iter())
+            .zip(log_lookups_evaluated.iter())
+            .zip(shuffles_evaluated.iter())
             .flat_map(
-                |(((advice_evals, instance_evals), permutations), lookups)| {
-                    let fixed_evals = fixed_evals.clone();
-                    let fixed_evals_copy = fixed_evals.clone();
-                    let fixed_evals_copy_copy = fixed_evals.clone();
-
+                |(
+                    ((((advice_evals, instance_evals), permutations), lookups), log_lookups),
+                    shuffles,
+                )| {
                     std::iter::empty()
                         // Evaluate the circuit using the custom gates provided
-                        .chain(vk.cs.gates.iter().map(move |(_, poly)| {
-                            poly.evaluate(
-                                &|scalar| scalar,
-                                &|index| fixed_evals[index],
-                                &|index| advice_evals[index],
-                                &|index| instance_evals[index],
-                                &|a, b| a + &b,
-                                &|a, b| a * &b,
-                                &|a, scalar| a * &scalar,
-                            )
-                        }))
+                        .chain(vk.cs.gates.iter().flat_map(|gate| gate.polynomials()).map(
+                            move |poly| {
+                                poly.evaluate(
+                                    &|scalar| scalar,
+                                    &|_, _| {
+                                        panic!("virtual selectors are removed during optimization")
+                                    },
+                                    &|index| fixed_evals[index],
+                                    &|index| advice_evals[index],
+                                    &|index| instance_evals[index],
+                                    &|challenge| challenges[challenge.index()],
+                                    &|a, b| a + &b,
+                                    &|a, b| a * &b,
+                                    &|a, scalar| a * &scalar,
+                                )
+                            },
+                        ))
                         .chain(
                             permutations
                                 .iter()
@@ -186,7 +297,7 @@ pub fn verify_proof<'a, C: CurveAffine, T: TranscriptRead<C>>(
                                         vk,
                                         argument,
                                         &advice_evals,
-                                        &fixed_evals_copy,
+                                        fixed_evals,
                                         &instance_evals,
                                         l_0,
                                         beta,
@@ -208,12 +319,40 @@ pub fn verify_proof<'a, C: CurveAffine, T: TranscriptRead<C>>(
                                         beta,
                                         gamma,
                                         &advice_evals,
-                                        &fixed_evals_copy_copy,
+                                        fixed_evals,
                                         &instance_evals,
+                                        challenges,
                                     )
                                 })
                                 .into_iter(),
                         )
+                        .chain(log_lookups.iter().zip(vk.cs.lookups.iter()).flat_map(
+                            move |(p, argument)| {
+                                p.expressions(
+                                    argument,
+                                    theta,
+                                    beta,
+                                    advice_evals,
+                                    fixed_evals,
+                                    instance_evals,
+                                    challenges,
+                                )
+                            },
+                        ))
+                        .chain(shuffles.iter().zip(vk.cs.shuffles.iter()).flat_map(
+                            move |(p, argument)| {
+                                p.expressions(
+                                    l_0,
+                                    argument,
+                                    theta,
+                                    gamma,
+                                    advice_evals,
+                                    fixed_evals,
+                                    instance_evals,
+                                    challenges,
+                                )
+                            },
+                        ))
                 },
             );
 
@@ -227,13 +366,24 @@ pub fn verify_proof<'a, C: CurveAffine, T: TranscriptRead<C>>(
         .zip(advice_evals.iter())
         .zip(permutations_evaluated.iter())
         .zip(lookups_evaluated.iter())
+        .zip(log_lookups_evaluated.iter())
+        .zip(shuffles_evaluated.iter())
         .flat_map(
             |(
                 (
-                    (((instance_commitments, instance_evals), advice_commitments), advice_evals),
-                    permutations,
+                    (
+                        (
+                            (
+                                ((instance_commitments, instance_evals), advice_commitments),
+                                advice_evals,
+                            ),
+                            permutations,
+                        ),
+                        lookups,
+                    ),
+                    log_lookups,
                 ),
-                lookups,
+                shuffles,
             )| {
                 iter::empty()
                     .chain(vk.cs.instance_queries.iter().enumerate().map(
@@ -263,6 +413,8 @@ pub fn verify_proof<'a, C: CurveAffine, T: TranscriptRead<C>>(
                             .flat_map(move |p| p.queries(vk, x))
                             .into_iter(),
                     )
+                    .chain(log_lookups.iter().flat_map(move |p| p.queries(vk, x)))
+                    .chain(shuffles.iter().flat_map(move |p| p.queries(vk, x)))
             },
         )
         .chain(
@@ -280,5 +432,7 @@ pub fn verify_proof<'a, C: CurveAffine, T: TranscriptRead<C>>(
 
     // We are now convinced the circuit is satisfied so long as the
     // polynomial commitments open to the correct values.
-    multiopen::verify_proof(params, transcript, queries, msm).map_err(|_| Error::OpeningError)
+    params
+        .verify_multi_opening(transcript, queries, msm)
+        .map_err(|_| Error::OpeningError)
 }
@@ -0,0 +1,79 @@
+use group::Curve;
+
+use super::Error;
+use crate::arithmetic::CurveAffine;
+use crate::poly::{
+    commitment::{Blind, CommitmentScheme},
+    Coeff, EvaluationDomain, LagrangeCoeff, PartLagrangeCoeff, Polynomial,
+};
+
+/// Performs the operations on the advice columns of proof creation on behalf of
+/// [`ProverOptions::workers`](super::ProverOptions::workers).
+///
+/// The prover remains the coordinator of the proof: it synthesizes the circuits,
+/// writes every message to the transcript and squeezes every challenge. It hands the
+/// workers batches of independent operations on the advice columns: their
+/// commitments, their interpolation and their evaluation over the extended domain.
+/// The inputs and results of each batch are polynomials, blinding factors and points,
+/// so an implementation may shard a batch across threads, processes or machines, as
+/// long as it returns the results in the order of the inputs. Each method defaults to
+/// computing its batch on this machine, as [`LocalWorkers`] does, so an
+/// implementation only overrides the operations it distributes.
+///
+/// Every other operation runs on the prover's machine: the commitments and FFTs of the
+/// permutation, lookup and shuffle arguments, the evaluation of the quotient
+/// polynomial $h(X)$ and the commitments to its pieces, and the multiscalar
+/// multiplications of the opening argument.
+pub trait ProverWorkers<C: CurveAffine>: Sync {
+    /// Commits to each of `polys` with the blinding factor of the same index in
+    /// `blinds`, taking a multiscalar multiplication each.
+    fn commit_lagrange<P: CommitmentScheme<C>>(
+        &self,
+        params: &P,
+        polys: &[&Polynomial<C::Scalar, LagrangeCoeff>],
+        blinds: &[Blind<C::Scalar>],
+    ) -> Result<Vec<C>, Error> {
+        let commitments_projective: Vec<_> = polys
+            .iter()
+            .zip(blinds.iter())
+            .map(|(poly, blind)| params.commit_lagrange(poly, *blind))
+            .collect();
+        let mut commitments = vec![C::identity(); commitments_projective.len()];
+        C::Curve::batch_normalize(&commitments_projective, &mut commitments);
+        Ok(commitments)
+    }
+
+    /// Interpolates each of `polys` from its values over `domain` to its coefficients,
+    /// taking an inverse FFT each.
+    fn lagrange_to_coeff(
+        &self,
+        domain: &EvaluationDomain<C::Scalar>,
+        polys: Vec<Polynomial<C::Scalar, LagrangeCoeff>>,
+    ) -> Result<Vec<Polynomial<C::Scalar, Coeff>>, Error> {
+        Ok(polys
+            .into_iter()
+            .map(|poly| domain.lagrange_to_coeff(poly))
+            .collect())
+    }
+
+    /// Evaluates each of `polys` over the `part`-th part of the extended domain of
+    /// `domain`, taking an FFT of the size of `domain` each.
+    fn coeff_to_extended_part(
+        &self,
+        domain: &EvaluationDomain<C::Scalar>,
+        polys: &[Polynomial<C::Scalar, Coeff>],
+        part: usize,
+    ) -> Result<Vec<Polynomial<C::Scalar, PartLagrangeCoeff>>, Error> {
+        Ok(polys
+            .iter()
+            .map(|poly| domain.coeff_to_extended_part(poly.clone(), part))
+            .collect())
+    }
+}
+
+/// The [`ProverWorkers`] that compute every operation on this machine, with the
+/// prover's own threads. [`create_proof`](super::create_proof) proves with these.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LocalWorkers;
+
+impl<C: CurveAffine> ProverWorkers<C> for LocalWorkers {}
@@ -11,6 +11,7 @@ use std::ops::{Add, Deref, DerefMut, Index, IndexMut, Mul, RangeFrom, RangeFull,
 
 pub mod commitment;
 mod domain;
+pub mod kzg;
 pub mod multiopen;
 
 pub use domain::*;
@@ -44,6 +45,12 @@ impl Basis for LagrangeCoeff {}
 pub struct ExtendedLagrangeCoeff;
 impl Basis for ExtendedLagrangeCoeff {}
 
+/// The polynomial is defined as coefficients of Lagrange basis polynomials over
+/// one part of the extended domain, which is a coset of the $2^k$ size domain
+#[derive(Clone, Debug)]
+pub struct PartLagrangeCoeff;
+impl Basis for PartLagrangeCoeff {}
+
 /// Represents a univariate polynomial defined over a field and a particular
 /// basis.
 #[derive(Clone, Debug)]
@@ -187,19 +194,48 @@ impl<'a, F: Field> Mul<&'a Polynomial<F, ExtendedLagrangeCoeff>>
     }
 }
 
+impl<'a, F: Field> Mul<&'a Polynomial<F, PartLagrangeCoeff>> for Polynomial<F, PartLagrangeCoeff> {
+    type Output = Polynomial<F, PartLagrangeCoeff>;
+
+    fn mul(
+        mut self,
+        rhs: &'a Polynomial<F, PartLagrangeCoeff>,
+    ) -> Polynomial<F, PartLagrangeCoeff> {
+        parallelize(&mut self.values, |lhs, start| {
+            for (lhs, rhs) in lhs.iter_mut().zip(rhs.values[start..].iter()) {
+                *lhs *= *rhs;
+            }
+        });
+
+        self
+    }
+}
+
 impl<'a, F: Field> Polynomial<F, LagrangeCoeff> {
     /// Rotates the values in a Lagrange basis polynomial by `Rotation`
     pub fn rotate(&self, rotation: Rotation) -> Polynomial<F, LagrangeCoeff> {
-        let mut values = self.values.clone();
-        if rotation.0 < 0 {
-            values.rotate_right((-rotation.0) as usize);
-        } else {
-            values.rotate_left(rotation.0 as usize);
-        }
-        Polynomial {
-            values,
-            _marker: PhantomData,
-        }
+        rotate_values(&self.values, rotation)
+    }
+}
+
+impl<F: Field> Polynomial<F, PartLagrangeCoeff> {
+    /// Rotates the values over a part of the extended domain by `Rotation`, which
+    /// multiplies each point of the part by a power of $\omega$ as for the domain
+    pub fn rotate(&self, rotation: Rotation) -> Polynomial<F, PartLagrangeCoeff> {
+        rotate_values(&self.values, rotation)
+    }
+}
+
+fn rotate_values<F: Field, B: Basis>(values: &[F], rotation: Rotation) -> Polynomial<F, B> {
+    let mut values = values.to_vec();
+    if rotation.0 < 0 {
+        values.rotate_right((-rotation.0) as usize);
+    } else {
+        values.rotate_left(rotation.0 as usize);
+    }
+    Polynomial {
+        values,
+        _marker: PhantomData,
     }
 }
 
@@ -220,6 +256,9 @@ impl<'a, F: Field, B: Basis> Mul<F> for Polynomial<F, B> {
 /// Describes the relative rotation of a vector. Negative numbers represent
 /// reverse (leftmost) rotations and positive numbers represent forward (rightmost)
 /// rotations. Zero represents no rotation.
+///
+/// A rotation can be of any size, and wraps around the rows of the evaluation
+/// domain, so that `Rotation(n + i)` queries the same row as `Rotation(i)`.
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Rotation(pub i32);
 
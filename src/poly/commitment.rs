@@ -12,13 +12,19 @@ use ff::{Field, PrimeField};
 use group::{prime::PrimeCurveAffine, Curve, Group as _};
 use std::ops::{Add, AddAssign, Mul, MulAssign};
 
+mod accumulation;
 mod msm;
 mod prover;
+mod scheme;
 mod verifier;
 
+pub use accumulation::{create_accumulation_proof, verify_accumulation_proof};
 pub use msm::MSM;
 pub use prover::create_proof;
-pub use verifier::{verify_proof, Accumulator, Guard};
+pub use scheme::{CommitmentScheme, DeferredMsm, OpeningGuard, Prover, Verifier};
+pub use verifier::{
+    read_opening_proof, verify_proof, Accumulator, FoldedGuards, Guard, OpeningProof,
+};
 
 use std::io;
 
@@ -300,6 +306,7 @@ fn test_opening_proof() {
     const K: u32 = 6;
 
     use ff::Field;
+    use rand::rngs::OsRng;
 
     use super::{
         commitment::{Blind, Params},
@@ -336,7 +343,7 @@ fn test_opening_proof() {
     transcript.write_scalar(v).unwrap();
 
     let (proof, ch_prover) = {
-        create_proof(&params, &mut transcript, &px, blind, *x).unwrap();
+        create_proof(&params, OsRng, &mut transcript, &px, blind, *x).unwrap();
         let ch_prover = transcript.squeeze_challenge();
         (transcript.finalize(), ch_prover)
     };
@@ -368,3 +375,160 @@ fn test_opening_proof() {
         assert!(msm_g.eval());
     }
 }
+
+#[test]
+fn test_folded_guards() {
+    const K: u32 = 4;
+
+    use ff::Field;
+    use rand::rngs::OsRng;
+
+    use super::{
+        commitment::{Blind, FoldedGuards, Params},
+        EvaluationDomain,
+    };
+    use crate::arithmetic::eval_polynomial;
+    use crate::pasta::{EqAffine, Fp};
+    use crate::transcript::{
+        Blake2bRead, Blake2bWrite, ChallengeScalar, TranscriptRead, TranscriptWrite,
+    };
+
+    let params = Params::<EqAffine>::new(K);
+    let domain = EvaluationDomain::new(1, K);
+
+    // Returns an opening proof of a polynomial, and the claimed evaluation.
+    let prove = |seed: u64, claim_offset: u64| {
+        let mut px = domain.empty_coeff();
+        for (i, a) in px.iter_mut().enumerate() {
+            *a = Fp::from(seed * (i as u64 + 1));
+        }
+        let blind = Blind(Fp::rand());
+        let p = params.commit(&px, blind).to_affine();
+
+        let mut transcript = Blake2bWrite::<Vec<u8>, EqAffine>::init(vec![]);
+        transcript.write_point(p).unwrap();
+        let x = ChallengeScalar::<_, ()>::get(&mut transcript);
+        let v = eval_polynomial(&px, *x) + Fp::from(claim_offset);
+        transcript.write_scalar(v).unwrap();
+        create_proof(&params, OsRng, &mut transcript, &px, blind, *x).unwrap();
+        transcript.finalize()
+    };
+
+    let fold = |proofs: &[Vec<u8>], with_g: bool| {
+        let mut guards = FoldedGuards::new(&params);
+        for proof in proofs {
+            let mut transcript = Blake2bRead::<&[u8], EqAffine>::init(&proof[..]);
+            let p = transcript.read_point().unwrap();
+            let x = ChallengeScalar::<_, ()>::get(&mut transcript);
+            let v = transcript.read_scalar().unwrap();
+
+            let mut msm = params.empty_msm();
+            msm.append_term(Field::one(), p);
+            let guard = verify_proof(&params, msm, &mut transcript, *x, v).unwrap();
+            if with_g {
+                let g = guard.compute_g();
+                guards.fold_with_g(guard, g);
+            } else {
+                guards.fold(guard);
+            }
+        }
+        guards
+    };
+
+    let valid: Vec<_> = (1..4).map(|seed| prove(seed, 0)).collect();
+    assert!(fold(&valid, false).decide());
+    let guards = fold(&valid, true);
+    assert_eq!(guards.accumulators().len(), 3);
+    assert!(guards.decide());
+
+    // A proof of a wrong evaluation fails the folded check.
+    let mut invalid = valid.clone();
+    invalid[1] = prove(2, 1);
+    assert!(!fold(&invalid, false).decide());
+    assert!(!fold(&invalid, true).decide());
+
+    // So does a wrong G.
+    let mut guards = fold(&valid[..2], true);
+    let mut msm = params.empty_msm();
+    let mut transcript = Blake2bRead::<&[u8], EqAffine>::init(&valid[2][..]);
+    let p = transcript.read_point().unwrap();
+    let x = ChallengeScalar::<_, ()>::get(&mut transcript);
+    let v = transcript.read_scalar().unwrap();
+    msm.append_term(Field::one(), p);
+    let guard = verify_proof(&params, msm, &mut transcript, *x, v).unwrap();
+    guards.fold_with_g(guard, params.g[0]);
+    assert!(!guards.decide());
+}
+
+#[test]
+fn test_accumulation_proof() {
+    const K: u32 = 4;
+
+    use ff::Field;
+    use rand::rngs::OsRng;
+
+    use super::{
+        commitment::{
+            create_accumulation_proof, verify_accumulation_proof, Accumulator, Blind, Params,
+        },
+        EvaluationDomain,
+    };
+    use crate::arithmetic::eval_polynomial;
+    use crate::pasta::{EqAffine, Fp};
+    use crate::transcript::{Blake2bRead, Blake2bWrite, ChallengeScalar};
+
+    let params = Params::<EqAffine>::new(K);
+    let domain = EvaluationDomain::new(1, K);
+
+    // Returns the accumulator of an opening proof of a polynomial.
+    let accumulator = |seed: u64| -> Accumulator<EqAffine> {
+        let mut px = domain.empty_coeff();
+        for (i, a) in px.iter_mut().enumerate() {
+            *a = Fp::from(seed + i as u64);
+        }
+        let blind = Blind(Fp::rand());
+        let p = params.commit(&px, blind).to_affine();
+
+        let mut transcript = Blake2bWrite::<Vec<u8>, EqAffine>::init(vec![]);
+        let x = ChallengeScalar::<_, ()>::get(&mut transcript);
+        create_proof(&params, OsRng, &mut transcript, &px, blind, *x).unwrap();
+        let proof = transcript.finalize();
+
+        let mut transcript = Blake2bRead::<&[u8], EqAffine>::init(&proof[..]);
+        let x = ChallengeScalar::<_, ()>::get(&mut transcript);
+        let mut msm = params.empty_msm();
+        msm.append_term(Field::one(), p);
+        let guard =
+            verify_proof(&params, msm, &mut transcript, *x, eval_polynomial(&px, *x)).unwrap();
+        let g = guard.compute_g();
+        let (msm, accumulator) = guard.use_g(g);
+        assert!(msm.eval());
+        accumulator
+    };
+
+    let accumulators: Vec<_> = (1..4).map(accumulator).collect();
+    let mut transcript = Blake2bWrite::<Vec<u8>, EqAffine>::init(vec![]);
+    create_accumulation_proof(&params, OsRng, &mut transcript, &accumulators).unwrap();
+    let proof = transcript.finalize();
+
+    let verify = |accumulators: &[Accumulator<EqAffine>]| {
+        let mut transcript = Blake2bRead::<&[u8], EqAffine>::init(&proof[..]);
+        let guard = verify_accumulation_proof(&params, &mut transcript, accumulators).unwrap();
+
+        // The folded accumulator is checked like any other.
+        let g = guard.compute_g();
+        let (msm, folded) = guard.use_g(g);
+        msm.eval() && Accumulator::decide_batch(&params, &[folded])
+    };
+    assert!(verify(&accumulators));
+
+    // An accumulator with the wrong G does not verify.
+    let mut invalid = accumulators.clone();
+    invalid[1].g = accumulators[2].g;
+    assert!(!verify(&invalid));
+
+    // Nor does an accumulator with the wrong challenges.
+    let mut invalid = accumulators.clone();
+    invalid[1].challenges_packed = accumulators[2].challenges_packed.clone();
+    assert!(!verify(&invalid));
+}
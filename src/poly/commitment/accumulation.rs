@@ -0,0 +1,111 @@
+//! Folding of many accumulators into one.
+//!
+//! An [`Accumulator`] claims that its point $G$ is the commitment, with blinding
+//! factor $1$, to $g(X) = \prod_i (1 + u_i X^{2^i})$ for its challenges $u_i$. Checking
+//! this directly takes a multiscalar multiplication of size $n$. Instead, the prover
+//! opens every $G$ at a random point $x$ with a single multi-opening proof, whose
+//! evaluations the verifier computes in $O(k)$ each, so the checks of accumulators from
+//! any number of proofs, for any circuits over the same parameters, are replaced by
+//! the check of the single accumulator of the returned [`Guard`].
+//!
+//! The point $x$ is squeezed after the transcript absorbs the point and the challenges
+//! of every accumulator, so that a prover cannot choose the claims it folds after
+//! learning $x$.
+
+use ff::Field;
+use rand::RngCore;
+use std::io;
+use std::marker::PhantomData;
+
+use super::super::{multiopen, Coeff, Error, Polynomial};
+use super::verifier::{compute_b, compute_s};
+use super::{Accumulator, Blind, Guard, Params};
+use crate::arithmetic::{CurveAffine, FieldExt};
+use crate::transcript::{ChallengeScalar, Transcript, TranscriptRead, TranscriptWrite};
+
+#[derive(Clone, Copy, Debug)]
+struct X {}
+/// Challenge point at which the polynomials of the accumulators are opened.
+type ChallengeX<C> = ChallengeScalar<C, X>;
+
+/// Returns the challenges of `accumulator` as scalars.
+fn challenges<C: CurveAffine>(accumulator: &Accumulator<C>) -> Vec<C::Scalar> {
+    accumulator
+        .challenges_packed
+        .iter()
+        .map(|challenge| *ChallengeScalar::<C, ()>::from(*challenge))
+        .collect()
+}
+
+/// Absorbs the point $G$ and the challenges of each of `accumulators` into
+/// `transcript`, so that the challenge of the fold depends on every claim it folds.
+fn absorb<C: CurveAffine, T: Transcript<C>>(
+    transcript: &mut T,
+    accumulators: &[Accumulator<C>],
+) -> io::Result<()> {
+    for accumulator in accumulators {
+        transcript.common_point(accumulator.g)?;
+        for challenge in &accumulator.challenges_packed {
+            transcript.common_scalar(C::Scalar::from_u128(challenge.0))?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes to `transcript` a proof that the point $G$ of each of `accumulators` was
+/// computed from its challenges.
+///
+/// This takes time linear in $n$ for each accumulator. The accumulators must be
+/// valid, or the proof will not verify. The blinding of the proof is sampled from
+/// `rng`.
+pub fn create_accumulation_proof<C: CurveAffine, R: RngCore, T: TranscriptWrite<C>>(
+    params: &Params<C>,
+    rng: R,
+    transcript: &mut T,
+    accumulators: &[Accumulator<C>],
+) -> io::Result<()> {
+    absorb(transcript, accumulators)?;
+    let x = ChallengeX::get(transcript);
+
+    let polys: Vec<Polynomial<C::Scalar, Coeff>> = accumulators
+        .iter()
+        .map(|accumulator| Polynomial {
+            values: compute_s(&challenges(accumulator), C::Scalar::one()),
+            _marker: PhantomData,
+        })
+        .collect();
+    let queries = polys.iter().map(|poly| multiopen::ProverQuery {
+        point: *x,
+        poly,
+        blind: Blind::default(),
+    });
+    multiopen::create_proof(params, rng, transcript, queries)
+}
+
+/// Reads from `transcript` a proof that the point $G$ of each of `accumulators` was
+/// computed from its challenges, and returns the guard of the opening.
+///
+/// The accumulators are valid if the checks of the guard pass; in particular, the
+/// single accumulator of [`Guard::use_g`] can be folded again.
+pub fn verify_accumulation_proof<'a, C: CurveAffine, T: TranscriptRead<C>>(
+    params: &'a Params<C>,
+    transcript: &mut T,
+    accumulators: &[Accumulator<C>],
+) -> Result<Guard<'a, C>, Error> {
+    absorb(transcript, accumulators).map_err(|_| Error::OpeningError)?;
+    let x = ChallengeX::get(transcript);
+
+    let evals: Vec<_> = accumulators
+        .iter()
+        .map(|accumulator| compute_b(*x, &challenges(accumulator)))
+        .collect();
+    let queries = accumulators
+        .iter()
+        .zip(evals.iter())
+        .map(|(accumulator, eval)| multiopen::VerifierQuery {
+            point: *x,
+            commitment: &accumulator.g,
+            eval: *eval,
+        });
+    multiopen::verify_proof(params, transcript, queries, params.empty_msm())
+}
@@ -0,0 +1,226 @@
+//! Traits for the polynomial commitment schemes that the proof system can use.
+//!
+//! The plonk prover and verifier only commit to polynomials, open sets of
+//! commitments at sets of points, and accumulate the commitments being opened in a
+//! deferred multiscalar multiplication, so they are generic over these traits.
+//! [`Params`] implements them with the inner product argument of this module, and
+//! opens many commitments with the strategy of [`multiopen`], which a scheme may
+//! replace with its own.
+
+use rand::RngCore;
+use std::fmt::Debug;
+use std::io;
+
+use super::super::{
+    multiopen::{self, ProverQuery, VerifierQuery},
+    Coeff, Error, LagrangeCoeff, Polynomial,
+};
+use super::{Blind, Guard, Params, MSM};
+use crate::arithmetic::CurveAffine;
+use crate::transcript::{TranscriptRead, TranscriptWrite};
+
+/// The public parameters of a polynomial commitment scheme over `C`.
+pub trait CommitmentScheme<C: CurveAffine>: Debug + Sync {
+    /// Returns the log of the number of coefficients of a committed polynomial.
+    fn k(&self) -> u32;
+
+    /// Returns the number of coefficients of a committed polynomial.
+    fn n(&self) -> u64;
+
+    /// Commits to a polynomial given by its coefficients, with the blinding factor `r`.
+    fn commit(&self, poly: &Polynomial<C::Scalar, Coeff>, r: Blind<C::Scalar>) -> C::Curve;
+
+    /// Commits to a polynomial given by its evaluations over the $2^k$ size evaluation
+    /// domain, with the blinding factor `r`.
+    fn commit_lagrange(
+        &self,
+        poly: &Polynomial<C::Scalar, LagrangeCoeff>,
+        r: Blind<C::Scalar>,
+    ) -> C::Curve;
+
+    /// Returns whether a commitment with a random blinding factor hides the committed
+    /// polynomial, which it does by default.
+    ///
+    /// Key generation reserves blinding rows, as with
+    /// [`ConstraintSystem::reserve_blinding_rows`](crate::plonk::ConstraintSystem::reserve_blinding_rows),
+    /// for the circuits of a scheme that does not.
+    fn is_hiding(&self) -> bool {
+        true
+    }
+}
+
+/// A commitment scheme that can prove openings of commitments.
+pub trait Prover<C: CurveAffine>: CommitmentScheme<C> {
+    /// Writes to `transcript` a proof that the polynomial `px`, committed to with the
+    /// blinding factor `blind`, opens at `x` to its evaluation, with any randomness
+    /// of the proof sampled from `rng`.
+    ///
+    /// The transcript must already have seen the commitment, the point and the
+    /// evaluation.
+    fn create_opening<R: RngCore, T: TranscriptWrite<C>>(
+        &self,
+        rng: R,
+        transcript: &mut T,
+        px: &Polynomial<C::Scalar, Coeff>,
+        blind: Blind<C::Scalar>,
+        x: C::Scalar,
+    ) -> io::Result<()>;
+
+    /// Writes to `transcript` a proof that each polynomial of `queries` opens at the
+    /// point of its query to its evaluation, with any randomness of the proof sampled
+    /// from `rng`.
+    ///
+    /// The transcript must already have seen the commitments and the evaluations. This
+    /// defaults to reducing the queries to a single opening with
+    /// [`multiopen::create_proof`].
+    fn create_multi_opening<'q, I, R: RngCore, T: TranscriptWrite<C>>(
+        &self,
+        rng: R,
+        transcript: &mut T,
+        queries: I,
+    ) -> io::Result<()>
+    where
+        I: IntoIterator<Item = ProverQuery<'q, C>> + Clone,
+        Self: Sized,
+    {
+        multiopen::create_proof(self, rng, transcript, queries)
+    }
+}
+
+/// A multiscalar multiplication whose evaluation a verifier defers.
+pub trait DeferredMsm<C: CurveAffine>: Clone + Debug {
+    /// Adds the terms of `other` to this multiscalar multiplication.
+    fn add_msm(&mut self, other: &Self);
+
+    /// Adds the term `[scalar] point`.
+    fn append_term(&mut self, scalar: C::Scalar, point: C);
+
+    /// Scales every term by `factor`.
+    fn scale(&mut self, factor: C::Scalar);
+}
+
+/// The result of checking an opening whose checks are deferred, which decides whether
+/// the opening is valid once they are completed.
+pub trait OpeningGuard {
+    /// Completes the deferred checks, and returns whether they pass.
+    fn decide(self) -> bool;
+}
+
+/// A commitment scheme that can check openings of commitments.
+pub trait Verifier<C: CurveAffine>: CommitmentScheme<C> {
+    /// The multiscalar multiplication that evaluates to the commitment being opened.
+    type Msm<'a>: DeferredMsm<C>
+    where
+        Self: 'a;
+
+    /// The result of checking an opening, which is only valid once the caller has
+    /// completed the checks that it defers.
+    type Guard<'a>: OpeningGuard
+    where
+        Self: 'a;
+
+    /// Returns an empty multiscalar multiplication.
+    fn empty_msm(&self) -> Self::Msm<'_>;
+
+    /// Reads from `transcript` a proof that the commitment `msm` opens to `v` at `x`.
+    fn verify_opening<'a, T: TranscriptRead<C>>(
+        &'a self,
+        msm: Self::Msm<'a>,
+        transcript: &mut T,
+        x: C::Scalar,
+        v: C::Scalar,
+    ) -> Result<Self::Guard<'a>, Error>;
+
+    /// Reads from `transcript` a proof that the commitment of each of `queries` opens
+    /// to its evaluation at the point of its query, adding the commitments to `msm`.
+    ///
+    /// This defaults to reducing the queries to a single opening with
+    /// [`multiopen::verify_proof`], and must check the proofs of
+    /// [`Prover::create_multi_opening`] of the same scheme.
+    fn verify_multi_opening<'q, 'a: 'q, I, T: TranscriptRead<C>>(
+        &'a self,
+        transcript: &mut T,
+        queries: I,
+        msm: Self::Msm<'a>,
+    ) -> Result<Self::Guard<'a>, Error>
+    where
+        I: IntoIterator<Item = VerifierQuery<'q, C>> + Clone,
+        Self: Sized,
+    {
+        multiopen::verify_proof(self, transcript, queries, msm)
+    }
+}
+
+impl<C: CurveAffine> CommitmentScheme<C> for Params<C> {
+    fn k(&self) -> u32 {
+        self.k
+    }
+
+    fn n(&self) -> u64 {
+        self.n
+    }
+
+    fn commit(&self, poly: &Polynomial<C::Scalar, Coeff>, r: Blind<C::Scalar>) -> C::Curve {
+        Params::commit(self, poly, r)
+    }
+
+    fn commit_lagrange(
+        &self,
+        poly: &Polynomial<C::Scalar, LagrangeCoeff>,
+        r: Blind<C::Scalar>,
+    ) -> C::Curve {
+        Params::commit_lagrange(self, poly, r)
+    }
+}
+
+impl<C: CurveAffine> Prover<C> for Params<C> {
+    fn create_opening<R: RngCore, T: TranscriptWrite<C>>(
+        &self,
+        rng: R,
+        transcript: &mut T,
+        px: &Polynomial<C::Scalar, Coeff>,
+        blind: Blind<C::Scalar>,
+        x: C::Scalar,
+    ) -> io::Result<()> {
+        super::create_proof(self, rng, transcript, px, blind, x)
+    }
+}
+
+impl<'a, C: CurveAffine> DeferredMsm<C> for MSM<'a, C> {
+    fn add_msm(&mut self, other: &Self) {
+        MSM::add_msm(self, other)
+    }
+
+    fn append_term(&mut self, scalar: C::Scalar, point: C) {
+        MSM::append_term(self, scalar, point)
+    }
+
+    fn scale(&mut self, factor: C::Scalar) {
+        MSM::scale(self, factor)
+    }
+}
+
+impl<'a, C: CurveAffine> OpeningGuard for Guard<'a, C> {
+    fn decide(self) -> bool {
+        self.use_challenges().eval()
+    }
+}
+
+impl<C: CurveAffine> Verifier<C> for Params<C> {
+    type Msm<'a> = MSM<'a, C>;
+    type Guard<'a> = Guard<'a, C>;
+
+    fn empty_msm(&self) -> MSM<'_, C> {
+        Params::empty_msm(self)
+    }
+
+    fn verify_opening<'a, T: TranscriptRead<C>>(
+        &'a self,
+        msm: MSM<'a, C>,
+        transcript: &mut T,
+        x: C::Scalar,
+        v: C::Scalar,
+    ) -> Result<Guard<'a, C>, Error> {
+        super::verify_proof(self, msm, transcript, x, v)
+    }
+}
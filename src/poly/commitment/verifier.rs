@@ -5,7 +5,7 @@ use super::super::Error;
 use super::{Params, MSM};
 use crate::transcript::{Challenge, ChallengeScalar, TranscriptRead};
 
-use crate::arithmetic::{best_multiexp, BatchInvert, CurveAffine};
+use crate::arithmetic::{best_multiexp, BatchInvert, CurveAffine, FieldExt};
 
 /// A guard returned by the verifier
 #[derive(Debug, Clone)]
@@ -62,6 +62,101 @@ impl<'a, C: CurveAffine> Guard<'a, C> {
     }
 }
 
+impl<C: CurveAffine> Accumulator<C> {
+    /// Returns the accumulator with the given challenges, whose $G$ is computed from
+    /// them with a multiscalar multiplication of size $n$.
+    pub fn from_challenges(params: &Params<C>, challenges_packed: Vec<Challenge>) -> Self {
+        let challenges: Vec<_> = challenges_packed
+            .iter()
+            .map(|challenge| *ChallengeScalar::<C, ()>::from(*challenge))
+            .collect();
+        let s = compute_s(&challenges, C::Scalar::one());
+
+        let mut g = best_multiexp(&s, &params.g);
+        g += params.h;
+        Accumulator {
+            g: g.to_affine(),
+            challenges_packed,
+        }
+    }
+
+    /// Checks that the purported $G$ of each of `accumulators` was computed from its
+    /// challenges, with a single multiscalar multiplication of size $n$ plus the
+    /// number of accumulators.
+    ///
+    /// This is the check that [`Guard::use_g`] defers.
+    pub fn decide_batch(params: &Params<C>, accumulators: &[Self]) -> bool {
+        let mut msm = params.empty_msm();
+        for accumulator in accumulators {
+            // r (<s, params.g> + H - G) for a random r
+            let r = C::Scalar::rand();
+            let challenges: Vec<_> = accumulator
+                .challenges_packed
+                .iter()
+                .map(|challenge| *ChallengeScalar::<C, ()>::from(*challenge))
+                .collect();
+            msm.add_to_g_scalars(&compute_s(&challenges, r));
+            msm.add_to_h_scalar(r);
+            msm.append_term(-r, accumulator.g);
+        }
+        msm.eval()
+    }
+}
+
+/// The deferred checks of many guards, folded together so that they are decided by a
+/// single multiscalar multiplication.
+///
+/// Each guard is scaled by a random factor before it is folded in, so that a guard
+/// that does not pass cannot cancel out another.
+#[derive(Debug, Clone)]
+pub struct FoldedGuards<'a, C: CurveAffine> {
+    msm: MSM<'a, C>,
+    accumulators: Vec<Accumulator<C>>,
+}
+
+impl<'a, C: CurveAffine> FoldedGuards<'a, C> {
+    /// Returns an empty set of guards.
+    pub fn new(params: &'a Params<C>) -> Self {
+        FoldedGuards {
+            msm: params.empty_msm(),
+            accumulators: vec![],
+        }
+    }
+
+    fn fold_msm(&mut self, msm: MSM<'a, C>) {
+        self.msm.scale(C::Scalar::rand());
+        self.msm.add_msm(&msm);
+    }
+
+    /// Folds in `guard`, computing its $G$ from its challenges.
+    pub fn fold(&mut self, guard: Guard<'a, C>) {
+        self.fold_msm(guard.use_challenges());
+    }
+
+    /// Folds in `guard` with the purported $G$ `g`, and defers the check of `g` to
+    /// [`FoldedGuards::decide`].
+    ///
+    /// This is the amortized strategy of Halo: the check of each $G$ is linear in
+    /// $n$, but the checks of all of them share a single multiscalar multiplication.
+    pub fn fold_with_g(&mut self, guard: Guard<'a, C>, g: C) {
+        let (msm, accumulator) = guard.use_g(g);
+        self.fold_msm(msm);
+        self.accumulators.push(accumulator);
+    }
+
+    /// Returns the accumulators of the guards folded in with
+    /// [`FoldedGuards::fold_with_g`], whose checks have not been performed.
+    pub fn accumulators(&self) -> &[Accumulator<C>] {
+        &self.accumulators
+    }
+
+    /// Checks every folded guard.
+    pub fn decide(self) -> bool {
+        let params = self.msm.params;
+        self.msm.eval() && Accumulator::decide_batch(params, &self.accumulators)
+    }
+}
+
 /// Checks to see if the proof represented within `transcript` is valid, and a
 /// point `x` that the polynomial commitment `P` opens purportedly to the value
 /// `v`. The provided `msm` should evaluate to the commitment `P` being opened.
@@ -72,48 +167,30 @@ pub fn verify_proof<'a, C: CurveAffine, T: TranscriptRead<C>>(
     x: C::Scalar,
     v: C::Scalar,
 ) -> Result<Guard<'a, C>, Error> {
-    let k = params.k as usize;
-
     //     P - [v] G_0 + S * iota
     //   + \sum(L_i * u_i^2) + \sum(R_i * u_i^-2)
     msm.add_constant_term(-v);
-    let s_poly_commitment = transcript.read_point().map_err(|_| Error::OpeningError)?;
-
-    let iota = *ChallengeScalar::<C, ()>::get(transcript);
-    msm.append_term(iota, s_poly_commitment);
+    let proof = read_opening_proof(params, transcript)?;
 
-    let z = *ChallengeScalar::<C, ()>::get(transcript);
+    let iota = *ChallengeScalar::<C, ()>::from(proof.iota);
+    msm.append_term(iota, proof.s_poly_commitment);
 
-    let mut rounds = vec![];
-    for _ in 0..k {
-        // Read L and R from the proof and write them to the transcript
-        let l = transcript.read_point().map_err(|_| Error::OpeningError)?;
-        let r = transcript.read_point().map_err(|_| Error::OpeningError)?;
+    let z = *ChallengeScalar::<C, ()>::from(proof.z);
 
-        let challenge_packed = Challenge::get(transcript);
-        let challenge = *ChallengeScalar::<C, ()>::from(challenge_packed);
+    let challenges = proof.challenges();
+    let mut challenges_inv = challenges.clone();
+    challenges_inv.iter_mut().batch_invert();
 
-        rounds.push((
-            l,
-            r,
-            challenge,
-            /* to be inverted */ challenge,
-            challenge_packed,
-        ));
-    }
-
-    rounds
-        .iter_mut()
-        .map(|&mut (_, _, _, ref mut challenge, _)| challenge)
-        .batch_invert();
-
-    let mut challenges = Vec::with_capacity(k);
-    let mut challenges_packed: Vec<Challenge> = Vec::with_capacity(k);
-    for (l, r, challenge, challenge_inv, challenge_packed) in rounds {
+    let mut challenges_packed: Vec<Challenge> = Vec::with_capacity(proof.rounds.len());
+    for ((&(l, r, challenge_packed), &challenge), &challenge_inv) in proof
+        .rounds
+        .iter()
+        .zip(challenges.iter())
+        .zip(challenges_inv.iter())
+    {
         msm.append_term(challenge_inv, l);
         msm.append_term(challenge, r);
 
-        challenges.push(challenge);
         challenges_packed.push(challenge_packed);
     }
 
@@ -130,13 +207,11 @@ pub fn verify_proof<'a, C: CurveAffine, T: TranscriptRead<C>>(
     // but subtracting to get the desired equality
     //   ... + [-a] G + [-abz] U + [a - \xi] H = 0
 
-    let a = transcript.read_scalar().map_err(|_| Error::SamplingError)?;
-    let neg_a = -a;
-    let xi = transcript.read_scalar().map_err(|_| Error::SamplingError)?;
+    let neg_a = -proof.a;
     let b = compute_b(x, &challenges);
 
     msm.add_to_u_scalar(neg_a * &b * &z);
-    msm.add_to_h_scalar(a - &xi);
+    msm.add_to_h_scalar(proof.a - &proof.xi);
 
     let guard = Guard {
         msm,
@@ -148,8 +223,120 @@ pub fn verify_proof<'a, C: CurveAffine, T: TranscriptRead<C>>(
     Ok(guard)
 }
 
+/// An opening proof read from a transcript, along with the challenges sampled while
+/// reading it.
+#[derive(Debug, Clone)]
+pub struct OpeningProof<C: CurveAffine> {
+    /// The commitment $S$ to the random polynomial that blinds the opening.
+    pub s_poly_commitment: C,
+    /// The challenge $\iota$ by which $S$ is scaled.
+    pub iota: Challenge,
+    /// The challenge $z$ by which $U$ is scaled.
+    pub z: Challenge,
+    /// The points $L_i$ and $R_i$ of each round, with the challenge $u_i$ sampled
+    /// after them.
+    pub rounds: Vec<(C, C, Challenge)>,
+    /// The opening $a$ of the folded polynomial.
+    pub a: C::Scalar,
+    /// The blinding factor $\xi$ of the folded commitment.
+    pub xi: C::Scalar,
+}
+
+impl<C: CurveAffine> OpeningProof<C> {
+    /// The challenges $u_i$ of the rounds.
+    fn challenges(&self) -> Vec<C::Scalar> {
+        self.rounds
+            .iter()
+            .map(|&(_, _, challenge)| *ChallengeScalar::<C, ()>::from(challenge))
+            .collect()
+    }
+
+    /// Reduces the commitment $P$ being opened to
+    /// $P' = P + \[\iota\] S + \sum_i (\[u_i^{-1}\] L_i + \[u_i\] R_i)$.
+    ///
+    /// This only depends on the points of the proof and the challenges, and is the part
+    /// of the verification that [`crate::gadget::ipa`] performs in a circuit.
+    pub fn reduce(&self, commitment: C) -> C {
+        let challenges = self.challenges();
+        let mut challenges_inv = challenges.clone();
+        challenges_inv.iter_mut().batch_invert();
+
+        let mut scalars = vec![C::Scalar::one(), *ChallengeScalar::<C, ()>::from(self.iota)];
+        let mut bases = vec![commitment, self.s_poly_commitment];
+        for ((&(l, r, _), &challenge), &challenge_inv) in self
+            .rounds
+            .iter()
+            .zip(challenges.iter())
+            .zip(challenges_inv.iter())
+        {
+            scalars.extend_from_slice(&[challenge_inv, challenge]);
+            bases.extend_from_slice(&[l, r]);
+        }
+
+        best_multiexp(&scalars, &bases).to_affine()
+    }
+
+    /// Computes G + H, where G = ⟨s, params.g⟩ and H is used for blinding
+    pub fn compute_g(&self, params: &Params<C>) -> C {
+        let s = compute_s(&self.challenges(), C::Scalar::one());
+
+        let mut tmp = best_multiexp(&s, &params.g);
+        tmp += params.h;
+        tmp.to_affine()
+    }
+
+    /// Checks the reduced commitment `reduced` of a proof that the committed
+    /// polynomial opens to `v` at `x`, and the purported `g` returned by
+    /// [`OpeningProof::compute_g`].
+    ///
+    /// This checks that
+    /// $P' = \[v\] G_0 + \[a \cdot b(x) \cdot z\] U + \[\xi - a\] H + \[a\] G$ and that $G$
+    /// was computed from the challenges, which together with [`OpeningProof::reduce`]
+    /// is equivalent to [`verify_proof`].
+    pub fn decide(&self, params: &Params<C>, x: C::Scalar, v: C::Scalar, reduced: C, g: C) -> bool {
+        let b = compute_b(x, &self.challenges());
+        let z = *ChallengeScalar::<C, ()>::from(self.z);
+
+        let expected = best_multiexp(
+            &[v, self.a * &b * &z, self.xi - &self.a, self.a],
+            &[params.g[0], params.u, params.h, g],
+        );
+        expected.to_affine() == reduced && g == self.compute_g(params)
+    }
+}
+
+/// Reads an opening proof for `params` from `transcript`, sampling its challenges.
+pub fn read_opening_proof<C: CurveAffine, T: TranscriptRead<C>>(
+    params: &Params<C>,
+    transcript: &mut T,
+) -> Result<OpeningProof<C>, Error> {
+    let s_poly_commitment = transcript.read_point().map_err(|_| Error::OpeningError)?;
+    let iota = Challenge::get(transcript);
+    let z = Challenge::get(transcript);
+
+    let mut rounds = Vec::with_capacity(params.k as usize);
+    for _ in 0..params.k {
+        // Read L and R from the proof and write them to the transcript
+        let l = transcript.read_point().map_err(|_| Error::OpeningError)?;
+        let r = transcript.read_point().map_err(|_| Error::OpeningError)?;
+        rounds.push((l, r, Challenge::get(transcript)));
+    }
+
+    let a = transcript.read_scalar().map_err(|_| Error::SamplingError)?;
+    let xi = transcript.read_scalar().map_err(|_| Error::SamplingError)?;
+
+    Ok(OpeningProof {
+        s_poly_commitment,
+        iota,
+        z,
+        rounds,
+        a,
+        xi,
+    })
+}
+
 /// Computes $\prod\limits_{i=0}^{k-1} (1 + u_i x^{2^i})$.
-fn compute_b<F: Field>(x: F, challenges: &[F]) -> F {
+pub(super) fn compute_b<F: Field>(x: F, challenges: &[F]) -> F {
     let mut tmp = F::one();
     let mut cur = x;
     for challenge in challenges.iter().rev() {
@@ -160,7 +347,7 @@ fn compute_b<F: Field>(x: F, challenges: &[F]) -> F {
 }
 
 /// Computes the coefficients of $g(X) = \prod\limits_{i=0}^{k-1} (1 + u_i X^{2^i})$.
-fn compute_s<F: Field>(challenges: &[F], init: F) -> Vec<F> {
+pub(super) fn compute_s<F: Field>(challenges: &[F], init: F) -> Vec<F> {
     assert!(!challenges.is_empty());
     let mut v = vec![F::zero(); 1 << challenges.len()];
     v[0] = init;
@@ -1,12 +1,15 @@
 //! Contains utilities for performing polynomial arithmetic over an evaluation
 //! domain that is of a suitable size for the application.
 
-use crate::arithmetic::{best_fft, parallelize, BatchInvert, FieldExt, Group};
+use crate::arithmetic::{
+    best_fft_with_twiddles, parallelize, BatchInvert, FftTwiddles, FieldExt, Group,
+};
 
-use super::{Coeff, ExtendedLagrangeCoeff, LagrangeCoeff, Polynomial, Rotation};
+use super::{Coeff, ExtendedLagrangeCoeff, LagrangeCoeff, PartLagrangeCoeff, Polynomial, Rotation};
 
 use ff::{Field, PrimeField};
 use std::marker::PhantomData;
+use std::sync::OnceLock;
 
 /// This structure contains precomputed constants and other details needed for
 /// performing operations on an evaluation domain of size $2^k$ and an extended
@@ -19,7 +22,6 @@ pub struct EvaluationDomain<G: Group> {
     omega: G::Scalar,
     omega_inv: G::Scalar,
     extended_omega: G::Scalar,
-    extended_omega_inv: G::Scalar,
     g_coset: G::Scalar,
     g_coset_inv: G::Scalar,
     quotient_poly_degree: u64,
@@ -27,6 +29,27 @@ pub struct EvaluationDomain<G: Group> {
     extended_ifft_divisor: G::Scalar,
     t_evaluations: Vec<G::Scalar>,
     barycentric_weight: G::Scalar,
+    /// The twiddle factors of the FFTs over `extended_omega`, which also serve those
+    /// over `omega`. They are computed on the first FFT, so that a domain that is
+    /// never transformed over, such as that of a verifier, does not hold them.
+    extended_twiddles: OnceLock<FftTwiddles<G::Scalar>>,
+    /// The twiddle factors of the FFTs over the inverse of `extended_omega`, which also
+    /// serve those over `omega_inv`, computed on the first inverse FFT.
+    extended_inv_twiddles: OnceLock<FftTwiddles<G::Scalar>>,
+}
+
+/// Returns the log of the size of the extended domain of an [`EvaluationDomain`] for
+/// the values $j, k$.
+pub(crate) fn extended_k(j: u32, k: u32) -> u32 {
+    // We need to work within an extended domain, not params.k but params.k + i
+    // for some integer i such that 2^(params.k + i) is sufficiently large to
+    // describe the quotient polynomial.
+    let quotient_poly_degree = (j - 1) as u64;
+    let mut extended_k = k;
+    while (1 << extended_k) < ((1u64 << k) * quotient_poly_degree) {
+        extended_k += 1;
+    }
+    extended_k
 }
 
 impl<G: Group> EvaluationDomain<G> {
@@ -39,13 +62,7 @@ impl<G: Group> EvaluationDomain<G> {
         // n = 2^k
         let n = 1u64 << k;
 
-        // We need to work within an extended domain, not params.k but params.k + i
-        // for some integer i such that 2^(params.k + i) is sufficiently large to
-        // describe the quotient polynomial.
-        let mut extended_k = k;
-        while (1 << extended_k) < (n * quotient_poly_degree) {
-            extended_k += 1;
-        }
+        let extended_k = extended_k(j, k);
 
         let mut extended_omega = G::Scalar::ROOT_OF_UNITY;
 
@@ -56,7 +73,6 @@ impl<G: Group> EvaluationDomain<G> {
             extended_omega = extended_omega.square();
         }
         let extended_omega = extended_omega;
-        let mut extended_omega_inv = extended_omega; // Inversion computed later
 
         // Get omega, the 2^{k}'th root of unity (i.e. n'th root of unity)
         // The loop computes omega = extended_omega ^ {2 ^ (extended_k - k)}
@@ -115,7 +131,6 @@ impl<G: Group> EvaluationDomain<G> {
             .chain(Some(&mut ifft_divisor))
             .chain(Some(&mut extended_ifft_divisor))
             .chain(Some(&mut barycentric_weight))
-            .chain(Some(&mut extended_omega_inv))
             .chain(Some(&mut omega_inv))
             .batch_invert();
 
@@ -126,7 +141,6 @@ impl<G: Group> EvaluationDomain<G> {
             omega,
             omega_inv,
             extended_omega,
-            extended_omega_inv,
             g_coset,
             g_coset_inv,
             quotient_poly_degree,
@@ -134,6 +148,8 @@ impl<G: Group> EvaluationDomain<G> {
             extended_ifft_divisor,
             t_evaluations,
             barycentric_weight,
+            extended_twiddles: OnceLock::new(),
+            extended_inv_twiddles: OnceLock::new(),
         }
     }
 
@@ -203,6 +219,22 @@ impl<G: Group> EvaluationDomain<G> {
         }
     }
 
+    /// Returns an empty (zero) polynomial over a part of the extended domain
+    pub fn empty_part(&self) -> Polynomial<G, PartLagrangeCoeff> {
+        Polynomial {
+            values: vec![G::group_zero(); self.n as usize],
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns a constant polynomial over a part of the extended domain
+    pub fn constant_part(&self, scalar: G) -> Polynomial<G, PartLagrangeCoeff> {
+        Polynomial {
+            values: vec![scalar; self.n as usize],
+            _marker: PhantomData,
+        }
+    }
+
     /// This takes us from an n-length vector into the coefficient form.
     ///
     /// This function will panic if the provided vector is not the correct
@@ -212,7 +244,12 @@ impl<G: Group> EvaluationDomain<G> {
 
         // Perform inverse FFT to obtain the polynomial in coefficient form
         metrics::increment_counter!("ifft", "size" => format!("{}", a.len()), "fn" => "lagrange_to_coeff");
-        Self::ifft(&mut a.values, self.omega_inv, self.k, self.ifft_divisor);
+        Self::ifft(
+            &mut a.values,
+            self.extended_inv_twiddles(),
+            self.k,
+            self.ifft_divisor,
+        );
 
         Polynomial {
             values: a.values,
@@ -247,7 +284,68 @@ impl<G: Group> EvaluationDomain<G> {
         }
         a.values.resize(self.extended_len(), G::group_zero());
         metrics::increment_counter!("fft", "size" => format!("{}", self.extended_len()), "fn" => "coeff_to_extended");
-        best_fft(&mut a.values, self.extended_omega, self.extended_k);
+        best_fft_with_twiddles(&mut a.values, self.extended_k, self.extended_twiddles());
+
+        Polynomial {
+            values: a.values,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Rotates a polynomial in the extended evaluation domain by `rotation`, as
+    /// [`EvaluationDomain::coeff_to_extended`] would, without another FFT.
+    pub fn rotate_extended(
+        &self,
+        poly: &Polynomial<G, ExtendedLagrangeCoeff>,
+        rotation: Rotation,
+    ) -> Polynomial<G, ExtendedLagrangeCoeff> {
+        let mut values = poly.values.clone();
+        values.rotate_left(self.extended_rotation(rotation));
+
+        Polynomial {
+            values,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the offset in the extended evaluation domain of the evaluations of a
+    /// polynomial rotated by `rotation`.
+    ///
+    /// Since $\omega$ is $\omega_{ext}^{2^{k_{ext} - k}}$, the rotated polynomial takes
+    /// at the $i$-th point of the coset the value of the original polynomial at the
+    /// $(i + 2^{k_{ext} - k} \cdot rotation)$-th point.
+    pub(crate) fn extended_rotation(&self, rotation: Rotation) -> usize {
+        let shift = (rotation.0 as i64) << (self.extended_k - self.k);
+        shift.rem_euclid(self.extended_len() as i64) as usize
+    }
+
+    /// Returns the number of parts of the extended domain, $2^{k_{ext} - k}$.
+    ///
+    /// The $j$-th part is the coset $\zeta \omega_{ext}^j \langle \omega \rangle$ of the
+    /// $2^k$ size domain, whose $i$-th point is the $(j + i \cdot 2^{k_{ext} - k})$-th
+    /// point of the extended domain. A polynomial rotated by $\omega^r$ therefore takes
+    /// at the $i$-th point of a part the value of the original polynomial at the
+    /// $(i + r)$-th point of the same part.
+    pub fn extended_parts(&self) -> usize {
+        1 << (self.extended_k - self.k)
+    }
+
+    /// This takes us from an n-length coefficient vector into the `part`-th part of
+    /// the extended domain, with an FFT over the $2^k$ size domain.
+    ///
+    /// The parts of a polynomial hold the values of its coset of the extended domain,
+    /// so that the extended domain can be evaluated over one part at a time.
+    pub fn coeff_to_extended_part(
+        &self,
+        mut a: Polynomial<G, Coeff>,
+        part: usize,
+    ) -> Polynomial<G, PartLagrangeCoeff> {
+        assert_eq!(a.values.len(), 1 << self.k);
+        assert!(part < self.extended_parts());
+
+        Self::distribute_powers(&mut a.values, self.extended_part_start(part));
+        metrics::increment_counter!("fft", "size" => format!("{}", a.len()), "fn" => "coeff_to_extended_part");
+        best_fft_with_twiddles(&mut a.values, self.k, self.extended_twiddles());
 
         Polynomial {
             values: a.values,
@@ -255,6 +353,60 @@ impl<G: Group> EvaluationDomain<G> {
         }
     }
 
+    /// Returns the `part`-th part of a polynomial in the extended evaluation domain.
+    pub(crate) fn extended_to_part(
+        &self,
+        poly: &Polynomial<G, ExtendedLagrangeCoeff>,
+        part: usize,
+    ) -> Polynomial<G, PartLagrangeCoeff> {
+        assert_eq!(poly.values.len(), self.extended_len());
+
+        Polynomial {
+            values: poly
+                .values
+                .iter()
+                .skip(part)
+                .step_by(self.extended_parts())
+                .cloned()
+                .collect(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Writes the values of the `part`-th part of a polynomial into their points of
+    /// `extended`, its values over the extended evaluation domain.
+    pub fn extended_from_part(
+        &self,
+        extended: &mut Polynomial<G, ExtendedLagrangeCoeff>,
+        part: usize,
+        values: &Polynomial<G, PartLagrangeCoeff>,
+    ) {
+        assert_eq!(extended.values.len(), self.extended_len());
+        assert_eq!(values.values.len(), 1 << self.k);
+
+        for (extended, value) in extended
+            .values
+            .iter_mut()
+            .skip(part)
+            .step_by(self.extended_parts())
+            .zip(values.values.iter())
+        {
+            *extended = *value;
+        }
+    }
+
+    /// Returns $\zeta \omega_{ext}^j$, the first point of the $j$-th part of the
+    /// extended domain.
+    pub(crate) fn extended_part_start(&self, part: usize) -> G::Scalar {
+        self.g_coset * self.extended_omega.pow_vartime([part as u64])
+    }
+
+    /// Returns the offset in a part of the extended domain of the evaluations of a
+    /// polynomial rotated by `rotation`.
+    pub(crate) fn part_rotation(&self, rotation: Rotation) -> usize {
+        (rotation.0 as i64).rem_euclid(self.n as i64) as usize
+    }
+
     /// This takes us from the extended evaluation domain and gets us the
     /// quotient polynomial coefficients.
     ///
@@ -268,7 +420,7 @@ impl<G: Group> EvaluationDomain<G> {
         metrics::increment_counter!("ifft", "size" => format!("{}", a.len()), "fn" => "extended_to_coeff");
         Self::ifft(
             &mut a.values,
-            self.extended_omega_inv,
+            self.extended_inv_twiddles(),
             self.extended_k,
             self.extended_ifft_divisor,
         );
@@ -339,8 +491,8 @@ impl<G: Group> EvaluationDomain<G> {
         });
     }
 
-    fn ifft(a: &mut [G], omega_inv: G::Scalar, log_n: u32, divisor: G::Scalar) {
-        best_fft(a, omega_inv, log_n);
+    fn ifft(a: &mut [G], twiddles: &FftTwiddles<G::Scalar>, log_n: u32, divisor: G::Scalar) {
+        best_fft_with_twiddles(a, log_n, twiddles);
         parallelize(a, |a, _| {
             for a in a {
                 // Finish iFFT
@@ -349,6 +501,25 @@ impl<G: Group> EvaluationDomain<G> {
         });
     }
 
+    /// Returns the twiddle factors of the FFTs over `extended_omega`, computing them
+    /// if this is the first.
+    fn extended_twiddles(&self) -> &FftTwiddles<G::Scalar> {
+        self.extended_twiddles
+            .get_or_init(|| FftTwiddles::new(self.extended_omega, self.extended_k))
+    }
+
+    /// Returns the twiddle factors of the FFTs over the inverse of `extended_omega`,
+    /// computing them if this is the first.
+    fn extended_inv_twiddles(&self) -> &FftTwiddles<G::Scalar> {
+        self.extended_inv_twiddles
+            .get_or_init(|| self.extended_twiddles().inverse())
+    }
+
+    /// Get $k$, the log of the size of the domain.
+    pub fn k(&self) -> u32 {
+        self.k
+    }
+
     /// Get the size of the extended domain
     pub fn extended_len(&self) -> usize {
         1 << self.extended_k
@@ -450,3 +621,68 @@ fn test_rotate() {
         eval_polynomial(&poly_rotated_prev[..], x)
     );
 }
+
+#[test]
+fn test_rotate_extended() {
+    use crate::pasta::pallas::Scalar;
+    let domain = EvaluationDomain::<Scalar>::new(3, 3);
+
+    let mut poly = domain.empty_coeff();
+    for value in poly.iter_mut() {
+        *value = Scalar::rand();
+    }
+
+    let coset = domain.coeff_to_extended(poly.clone(), Rotation::cur());
+    for rotation in [Rotation::next(), Rotation::prev(), Rotation(5)].iter() {
+        assert_eq!(
+            domain.rotate_extended(&coset, *rotation).values,
+            domain.coeff_to_extended(poly.clone(), *rotation).values
+        );
+    }
+}
+
+#[test]
+fn test_extended_parts() {
+    use crate::pasta::pallas::Scalar;
+    let domain = EvaluationDomain::<Scalar>::new(5, 3);
+    assert_eq!(domain.extended_parts(), 4);
+
+    let mut poly = domain.empty_coeff();
+    for value in poly.iter_mut() {
+        *value = Scalar::rand();
+    }
+
+    // The parts of a polynomial are the values of its coset, and put it together.
+    let coset = domain.coeff_to_extended(poly.clone(), Rotation::cur());
+    let mut assembled = domain.empty_extended();
+    for part in 0..domain.extended_parts() {
+        let values = domain.coeff_to_extended_part(poly.clone(), part);
+        assert_eq!(values.values, domain.extended_to_part(&coset, part).values);
+        domain.extended_from_part(&mut assembled, part, &values);
+
+        // A rotation within a part is a rotation of the polynomial.
+        let rotated = domain.coeff_to_extended(poly.clone(), Rotation::prev());
+        assert_eq!(
+            values.rotate(Rotation::prev()).values,
+            domain.extended_to_part(&rotated, part).values
+        );
+    }
+    assert_eq!(assembled.values, coset.values);
+}
+
+#[test]
+fn test_lazy_twiddles() {
+    use crate::pasta::pallas::Scalar;
+    let domain = EvaluationDomain::<Scalar>::new(3, 3);
+
+    // A domain only holds the twiddle factors of the FFTs it has performed.
+    assert!(domain.extended_twiddles.get().is_none());
+    assert!(domain.extended_inv_twiddles.get().is_none());
+
+    let coset = domain.coeff_to_extended(domain.empty_coeff(), Rotation::cur());
+    assert!(domain.extended_twiddles.get().is_some());
+    assert!(domain.extended_inv_twiddles.get().is_none());
+
+    domain.extended_to_coeff(coset);
+    assert!(domain.extended_inv_twiddles.get().is_some());
+}
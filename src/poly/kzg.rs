@@ -0,0 +1,505 @@
+//! This module contains an implementation of the polynomial commitment scheme
+//! described in the [KZG][kzg] paper, over the BN254 curve.
+//!
+//! Unlike the scheme in [`commitment`](super::commitment), it requires a trusted setup,
+//! but its proofs are a single point of $\mathbb{G}_1$, and are checked with two
+//! pairings, which is cheap on chains that have pairing precompiles.
+//!
+//! [`Params`] implements the traits of [`commitment`](super::commitment), so that
+//! [`create_proof`](crate::plonk::create_proof) and
+//! [`verify_proof`](crate::plonk::verify_proof) can prove circuits over the scalar field
+//! of BN254 with KZG commitments. Its commitments are hiding: a blinding factor $r$
+//! adds $[r] H$ for a point $H = [\tau^n] G_1$ whose discrete logarithm is unknown, and
+//! an opening masks the quotient it reveals with a random polynomial, as the inner
+//! product argument does, and reveals only the blinding factor of the masked
+//! polynomial, which is uniformly random.
+//!
+//! The curve arithmetic of [`bn254`](crate::primitives::bn254) is not constant-time, and
+//! its pairing is not that of the EVM, as described there.
+//!
+//! [kzg]: https://www.iacr.org/archive/asiacrypt2010/6477178/6477178.pdf
+
+use ff::{Field, PrimeField};
+use group::{prime::PrimeCurveAffine, Curve, Group as _};
+use rand::RngCore;
+use std::io;
+
+use super::{
+    commitment::{Blind, CommitmentScheme, DeferredMsm, OpeningGuard, Prover, Verifier},
+    Coeff, Error, LagrangeCoeff, Polynomial,
+};
+use crate::arithmetic::{best_fft, best_multiexp, parallelize, FieldExt};
+use crate::primitives::bn254::{multi_pairing, G1Affine, G2Affine, Scalar, G1};
+use crate::transcript::{ChallengeScalar, TranscriptRead, TranscriptWrite};
+
+/// These are the public parameters for the polynomial commitment scheme: the powers
+/// $[\tau^i] G_1$ of a secret $\tau$ for $i \leq n$, and $[\tau] G_2$.
+///
+/// The last power $H = [\tau^n] G_1$ is only used to blind commitments.
+#[derive(Clone, Debug)]
+pub struct Params {
+    pub(crate) k: u32,
+    pub(crate) n: u64,
+    pub(crate) g: Vec<G1Affine>,
+    pub(crate) g_lagrange: Vec<G1Affine>,
+    pub(crate) h: G1Affine,
+    pub(crate) g2: G2Affine,
+    pub(crate) s_g2: G2Affine,
+}
+
+impl Params {
+    /// Initializes parameters for polynomials of degree less than $2^k$ from the
+    /// secret `tau`.
+    ///
+    /// Anyone who knows `tau` can open commitments to any value, so it must be
+    /// discarded. In production, parameters should instead come from a multi-party
+    /// ceremony, via [`Params::from_parts`].
+    pub fn setup(k: u32, tau: Scalar) -> Self {
+        assert!(k < 32);
+        let n: u64 = 1 << k;
+
+        let mut g_projective = vec![G1::identity(); n as usize + 1];
+        parallelize(&mut g_projective, |g, start| {
+            let g1 = G1Affine::generator();
+            let mut power = tau.pow_vartime([start as u64]);
+            for g in g.iter_mut() {
+                *g = g1 * power;
+                power *= tau;
+            }
+        });
+
+        let g2 = G2Affine::generator();
+        Self::from_projective(k, g_projective, g2, g2.mul(&tau))
+    }
+
+    /// Returns parameters with the powers `g` of $\tau$ in $\mathbb{G}_1$, from
+    /// $[\tau^0] G_1$ to $[\tau^n] G_1$, and the points $G_2$ and $[\tau] G_2$.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of powers is not one more than a power of two.
+    pub fn from_parts(g: Vec<G1Affine>, g2: G2Affine, s_g2: G2Affine) -> Self {
+        assert!(!g.is_empty() && (g.len() - 1).is_power_of_two());
+        let k = (g.len() - 1).trailing_zeros();
+        let g_projective = g.iter().map(|g| g.to_curve()).collect();
+        Self::from_projective(k, g_projective, g2, s_g2)
+    }
+
+    fn from_projective(k: u32, mut g_projective: Vec<G1>, g2: G2Affine, s_g2: G2Affine) -> Self {
+        let h = g_projective.pop().unwrap().to_affine();
+        let n = g_projective.len();
+        let mut g = vec![G1Affine::identity(); n];
+        G1::batch_normalize(&g_projective, &mut g);
+
+        // Let's evaluate all of the Lagrange basis polynomials
+        // using an inverse FFT.
+        let mut alpha_inv = Scalar::ROOT_OF_UNITY_INV;
+        for _ in k..Scalar::S {
+            alpha_inv = alpha_inv.square();
+        }
+        let mut g_lagrange_projective = g_projective;
+        best_fft(&mut g_lagrange_projective, alpha_inv, k);
+        let minv = Scalar::TWO_INV.pow_vartime([k as u64, 0, 0, 0]);
+        parallelize(&mut g_lagrange_projective, |g, _| {
+            for g in g.iter_mut() {
+                *g *= minv;
+            }
+        });
+        let mut g_lagrange = vec![G1Affine::identity(); n];
+        G1::batch_normalize(&g_lagrange_projective, &mut g_lagrange);
+
+        Params {
+            k,
+            n: n as u64,
+            g,
+            g_lagrange,
+            h,
+            g2,
+            s_g2,
+        }
+    }
+
+    /// Returns the log of the maximum number of coefficients of a committed
+    /// polynomial.
+    pub fn k(&self) -> u32 {
+        self.k
+    }
+
+    /// Returns the maximum number of coefficients of a committed polynomial.
+    pub fn n(&self) -> u64 {
+        self.n
+    }
+
+    /// This commits to a polynomial using its coefficients, from the constant term,
+    /// without blinding it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the polynomial has more than $n$ coefficients.
+    pub fn commit(&self, poly: &[Scalar]) -> G1Affine {
+        assert!(poly.len() as u64 <= self.n);
+        best_multiexp(poly, &self.g[..poly.len()]).to_affine()
+    }
+}
+
+impl CommitmentScheme<G1Affine> for Params {
+    fn k(&self) -> u32 {
+        self.k
+    }
+
+    fn n(&self) -> u64 {
+        self.n
+    }
+
+    fn commit(&self, poly: &Polynomial<Scalar, Coeff>, r: Blind<Scalar>) -> G1 {
+        best_multiexp(poly, &self.g[..poly.len()]) + self.h * r.0
+    }
+
+    fn commit_lagrange(&self, poly: &Polynomial<Scalar, LagrangeCoeff>, r: Blind<Scalar>) -> G1 {
+        best_multiexp(poly, &self.g_lagrange[..poly.len()]) + self.h * r.0
+    }
+}
+
+impl Prover<G1Affine> for Params {
+    fn create_opening<R: RngCore, T: TranscriptWrite<G1Affine>>(
+        &self,
+        mut rng: R,
+        transcript: &mut T,
+        px: &Polynomial<Scalar, Coeff>,
+        blind: Blind<Scalar>,
+        x: Scalar,
+    ) -> io::Result<()> {
+        assert!(px.len() as u64 <= self.n);
+
+        // Commit to a random polynomial with a root at x, which masks the quotient.
+        let mut s_poly = px.clone();
+        for coeff in s_poly.iter_mut() {
+            *coeff = Scalar::random(&mut rng);
+        }
+        let v_prime = eval_polynomial(&s_poly, x);
+        s_poly[0] -= v_prime;
+        let s_poly_blind = Blind(Scalar::random(&mut rng));
+        transcript
+            .write_point(CommitmentScheme::commit(self, &s_poly, s_poly_blind).to_affine())?;
+        let iota = *ChallengeScalar::<G1Affine, ()>::get(transcript);
+
+        // Open P + [iota] S, which has the same evaluation at x, and reveal its blinding
+        // factor, which the random blinding factor of S makes uniform.
+        let final_poly = s_poly * iota + px;
+        transcript.write_point(create_proof(self, &final_poly[..], x))?;
+        transcript.write_scalar((s_poly_blind * Blind(iota) + blind).0)
+    }
+}
+
+/// A multiscalar multiplication in $\mathbb{G}_1$, whose evaluation a verifier defers.
+#[derive(Clone, Debug, Default)]
+pub struct MSM {
+    scalars: Vec<Scalar>,
+    bases: Vec<G1Affine>,
+}
+
+impl MSM {
+    /// Adds the terms of `other` to this multiscalar multiplication.
+    pub fn add_msm(&mut self, other: &Self) {
+        self.scalars.extend(other.scalars.iter());
+        self.bases.extend(other.bases.iter());
+    }
+
+    /// Adds the term `[scalar] point`.
+    pub fn append_term(&mut self, scalar: Scalar, point: G1Affine) {
+        self.scalars.push(scalar);
+        self.bases.push(point);
+    }
+
+    /// Scales every term by `factor`.
+    pub fn scale(&mut self, factor: Scalar) {
+        if !self.scalars.is_empty() {
+            parallelize(&mut self.scalars, |scalars, _| {
+                for scalar in scalars {
+                    *scalar *= factor;
+                }
+            })
+        }
+    }
+
+    /// Evaluates this multiscalar multiplication.
+    pub fn eval(&self) -> G1 {
+        best_multiexp(&self.scalars, &self.bases)
+    }
+}
+
+impl DeferredMsm<G1Affine> for MSM {
+    fn add_msm(&mut self, other: &Self) {
+        MSM::add_msm(self, other)
+    }
+
+    fn append_term(&mut self, scalar: Scalar, point: G1Affine) {
+        MSM::append_term(self, scalar, point)
+    }
+
+    fn scale(&mut self, factor: Scalar) {
+        MSM::scale(self, factor)
+    }
+}
+
+/// An opening proof that has been read but not yet checked, because its pairing check
+/// is deferred to [`Guard::decide`](OpeningGuard::decide).
+#[derive(Clone, Debug)]
+pub struct Guard<'a> {
+    params: &'a Params,
+    msm: MSM,
+    x: Scalar,
+    v: Scalar,
+    proof: G1Affine,
+    blind: Scalar,
+}
+
+impl<'a> OpeningGuard for Guard<'a> {
+    fn decide(self) -> bool {
+        let commitment = self.msm.eval() - self.params.h * self.blind;
+        verify_proof(
+            self.params,
+            commitment.to_affine(),
+            self.x,
+            self.v,
+            self.proof,
+        )
+    }
+}
+
+impl Verifier<G1Affine> for Params {
+    type Msm<'a> = MSM;
+    type Guard<'a> = Guard<'a>;
+
+    fn empty_msm(&self) -> MSM {
+        MSM::default()
+    }
+
+    fn verify_opening<'a, T: TranscriptRead<G1Affine>>(
+        &'a self,
+        mut msm: MSM,
+        transcript: &mut T,
+        x: Scalar,
+        v: Scalar,
+    ) -> Result<Guard<'a>, Error> {
+        let s_poly_commitment = transcript.read_point().map_err(|_| Error::OpeningError)?;
+        let iota = *ChallengeScalar::<G1Affine, ()>::get(transcript);
+        msm.append_term(iota, s_poly_commitment);
+
+        let proof = transcript.read_point().map_err(|_| Error::OpeningError)?;
+        let blind = transcript.read_scalar().map_err(|_| Error::OpeningError)?;
+        Ok(Guard {
+            params: self,
+            msm,
+            x,
+            v,
+            proof,
+            blind,
+        })
+    }
+}
+
+/// Returns the evaluation of `poly` at `x`.
+pub fn eval_polynomial(poly: &[Scalar], x: Scalar) -> Scalar {
+    poly.iter()
+        .rev()
+        .fold(Scalar::zero(), |acc, coeff| acc * x + *coeff)
+}
+
+/// Returns the quotient $(p(X) - p(x)) / (X - x)$.
+fn quotient(poly: &[Scalar], x: Scalar) -> Vec<Scalar> {
+    // Synthetic division, from the leading coefficient; the remainder is p(x).
+    let mut quotient = vec![Scalar::zero(); poly.len().saturating_sub(1)];
+    let mut acc = Scalar::zero();
+    for (i, coeff) in poly.iter().enumerate().skip(1).rev() {
+        acc = acc * x + *coeff;
+        quotient[i - 1] = acc;
+    }
+    quotient
+}
+
+/// Returns $\sum_i \gamma^i p_i(X)$.
+fn combine(polys: &[&[Scalar]], gamma: Scalar) -> Vec<Scalar> {
+    let len = polys.iter().map(|poly| poly.len()).max().unwrap_or(0);
+    let mut combined = vec![Scalar::zero(); len];
+    let mut factor = Scalar::one();
+    for poly in polys {
+        for (acc, coeff) in combined.iter_mut().zip(poly.iter()) {
+            *acc = *acc + factor * *coeff;
+        }
+        factor = factor * gamma;
+    }
+    combined
+}
+
+/// Creates a proof that the polynomial `poly` evaluates to
+/// [`eval_polynomial(poly, x)`](eval_polynomial) at `x`.
+pub fn create_proof(params: &Params, poly: &[Scalar], x: Scalar) -> G1Affine {
+    params.commit(&quotient(poly, x))
+}
+
+/// Checks that `proof` shows the polynomial committed to by `commitment` evaluates to
+/// `v` at `x`.
+pub fn verify_proof(
+    params: &Params,
+    commitment: G1Affine,
+    x: Scalar,
+    v: Scalar,
+    proof: G1Affine,
+) -> bool {
+    // C - [v] G + [x] W = [tau] W
+    let g = G1Affine::generator();
+    let lhs = (commitment.to_curve() - g * v + proof * x).to_affine();
+    multi_pairing(&[(lhs, params.g2), (-proof, params.s_g2)]).is_identity()
+}
+
+/// Creates a proof that each of `polys` evaluates at `x` to its value under
+/// [`eval_polynomial`], given a challenge `gamma` that the verifier chooses after the
+/// polynomials are committed to and evaluated.
+pub fn create_batch_proof(
+    params: &Params,
+    polys: &[&[Scalar]],
+    x: Scalar,
+    gamma: Scalar,
+) -> G1Affine {
+    create_proof(params, &combine(polys, gamma), x)
+}
+
+/// Checks that `proof` shows each polynomial committed to in `commitments` evaluates to
+/// the corresponding value of `values` at `x`, for the challenge `gamma`.
+///
+/// # Panics
+///
+/// Panics if `commitments` and `values` have different lengths.
+pub fn verify_batch_proof(
+    params: &Params,
+    commitments: &[G1Affine],
+    x: Scalar,
+    values: &[Scalar],
+    gamma: Scalar,
+    proof: G1Affine,
+) -> bool {
+    assert_eq!(commitments.len(), values.len());
+    let mut commitment = G1::identity();
+    let mut value = Scalar::zero();
+    let mut factor = Scalar::one();
+    for (c, v) in commitments.iter().zip(values.iter()) {
+        commitment += c * factor;
+        value = value + factor * *v;
+        factor = factor * gamma;
+    }
+    verify_proof(params, commitment.to_affine(), x, value, proof)
+}
+
+#[cfg(test)]
+mod tests {
+    use ff::Field;
+    use rand::rngs::OsRng;
+
+    use super::{
+        create_batch_proof, create_proof, eval_polynomial, quotient, verify_batch_proof,
+        verify_proof, Params,
+    };
+    use crate::poly::{
+        commitment::{Blind, CommitmentScheme},
+        EvaluationDomain,
+    };
+    use crate::primitives::bn254::Scalar;
+
+    #[test]
+    fn test_quotient() {
+        let poly: Vec<_> = [3, 0, 5, 7].iter().map(|c| Scalar::from_u64(*c)).collect();
+        let x = Scalar::from_u64(11);
+        let z = Scalar::from_u64(4);
+        // (p(z) - p(x)) = (z - x) q(z)
+        let q = quotient(&poly, x);
+        assert_eq!(
+            eval_polynomial(&poly, z) - eval_polynomial(&poly, x),
+            (z - x) * eval_polynomial(&q, z)
+        );
+    }
+
+    #[test]
+    fn test_opening_proof() {
+        let params = Params::setup(2, Scalar::from_u64(0x1234_5678));
+        let poly: Vec<_> = [1, 2, 3, 4].iter().map(|c| Scalar::from_u64(*c)).collect();
+        let other: Vec<_> = [5, 6].iter().map(|c| Scalar::from_u64(*c)).collect();
+        let x = Scalar::from_u64(0xabcd);
+        let v = eval_polynomial(&poly, x);
+        let commitment = params.commit(&poly);
+
+        let proof = create_proof(&params, &poly, x);
+        assert!(verify_proof(&params, commitment, x, v, proof));
+        assert!(!verify_proof(
+            &params,
+            commitment,
+            x,
+            v + Scalar::one(),
+            proof
+        ));
+        assert!(!verify_proof(
+            &params,
+            commitment,
+            x + Scalar::one(),
+            v,
+            proof
+        ));
+
+        let gamma = Scalar::from_u64(0x9876);
+        let commitments = [commitment, params.commit(&other)];
+        let values = [v, eval_polynomial(&other, x)];
+        let proof = create_batch_proof(&params, &[&poly, &other], x, gamma);
+        assert!(verify_batch_proof(
+            &params,
+            &commitments,
+            x,
+            &values,
+            gamma,
+            proof
+        ));
+        assert!(!verify_batch_proof(
+            &params,
+            &commitments,
+            x,
+            &[values[0], values[0]],
+            gamma,
+            proof
+        ));
+    }
+
+    #[test]
+    fn test_hiding_commitments() {
+        const K: u32 = 3;
+        let tau = Scalar::random(OsRng);
+        let params = Params::setup(K, tau);
+        let domain = EvaluationDomain::<Scalar>::new(1, K);
+
+        let mut witness = domain.empty_lagrange();
+        let mut other_witness = domain.empty_lagrange();
+        for (i, (a, b)) in witness.iter_mut().zip(other_witness.iter_mut()).enumerate() {
+            *a = Scalar::from_u64(i as u64);
+            *b = Scalar::from_u64(100 + i as u64);
+        }
+
+        // Commitments to the same witness with fresh blinding factors differ.
+        let r = Blind(Scalar::random(OsRng));
+        let commitment = params.commit_lagrange(&witness, r);
+        assert_ne!(
+            commitment,
+            params.commit_lagrange(&witness, Blind(Scalar::random(OsRng)))
+        );
+
+        // For every blinding factor of one witness, there is one of the other witness
+        // that gives the same commitment, so the commitments of both witnesses are
+        // distributed identically: with H = [tau^n] G, it is
+        // r' = r + (a(tau) - b(tau)) / tau^n.
+        let a = domain.lagrange_to_coeff(witness);
+        let b = domain.lagrange_to_coeff(other_witness);
+        let tau_n = tau.pow_vartime([params.n]);
+        let other_r = Blind(
+            r.0 + (eval_polynomial(&a, tau) - eval_polynomial(&b, tau)) * tau_n.invert().unwrap(),
+        );
+        assert_eq!(commitment, CommitmentScheme::commit(&params, &b, other_r));
+        assert_eq!(commitment, CommitmentScheme::commit(&params, &a, r));
+    }
+}
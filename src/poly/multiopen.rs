@@ -6,10 +6,7 @@
 use std::collections::{BTreeMap, BTreeSet};
 
 use super::*;
-use crate::{
-    arithmetic::{CurveAffine, FieldExt},
-    transcript::ChallengeScalar,
-};
+use crate::{arithmetic::CurveAffine, transcript::ChallengeScalar};
 
 mod prover;
 mod verifier;
@@ -92,7 +89,7 @@ type IntermediateSets<F, Q> = (
     Vec<Vec<F>>,
 );
 
-fn construct_intermediate_sets<F: FieldExt, I, Q: Query<F>>(queries: I) -> IntermediateSets<F, Q>
+fn construct_intermediate_sets<F: Ord + Copy, I, Q: Query<F>>(queries: I) -> IntermediateSets<F, Q>
 where
     I: IntoIterator<Item = Q> + Clone,
 {
@@ -204,9 +201,64 @@ where
     (commitment_map, point_sets)
 }
 
+/// A query that refers to its commitment and evaluation by index, for a verifier that
+/// computes the opening in a circuit.
+#[derive(Clone, Copy, Debug)]
+struct IndexQuery<P> {
+    point: P,
+    commitment: usize,
+    eval: usize,
+}
+
+impl<P: Ord + Copy> Query<P> for IndexQuery<P> {
+    type Commitment = usize;
+    type Eval = usize;
+
+    fn get_point(&self) -> P {
+        self.point
+    }
+    fn get_eval(&self) -> usize {
+        self.eval
+    }
+    fn get_commitment(&self) -> usize {
+        self.commitment
+    }
+}
+
+/// The commitments of a multi-opening, each with the index of its point set and the
+/// indices of its queries in the order of the points of the set, in the order in which
+/// [`verify_proof`] folds them, followed by the point sets.
+pub(crate) type OpeningSets<P> = (Vec<(usize, usize, Vec<usize>)>, Vec<Vec<P>>);
+
+/// Groups `queries`, given as a point and the index of a commitment, into point sets
+/// as [`verify_proof`] does for queries of the same points and commitments.
+///
+/// Queries of the same commitment must have the same index, and a query is referred
+/// to by its position in `queries`.
+pub(crate) fn opening_sets<P: Ord + Copy>(queries: &[(P, usize)]) -> OpeningSets<P> {
+    let queries = queries
+        .iter()
+        .enumerate()
+        .map(|(eval, &(point, commitment))| IndexQuery {
+            point,
+            commitment,
+            eval,
+        });
+    let (commitment_map, point_sets) = construct_intermediate_sets(queries);
+
+    (
+        commitment_map
+            .into_iter()
+            .map(|data| (data.commitment, data.set_index, data.evals))
+            .collect(),
+        point_sets,
+    )
+}
+
 #[test]
 fn test_roundtrip() {
     use group::Curve;
+    use rand::rngs::OsRng;
 
     use super::commitment::{Blind, Params};
     use crate::arithmetic::{eval_polynomial, FieldExt};
@@ -247,6 +299,7 @@ fn test_roundtrip() {
     let mut transcript = crate::transcript::Blake2bWrite::init(vec![]);
     create_proof(
         &params,
+        OsRng,
         &mut transcript,
         std::iter::empty()
             .chain(Some(ProverQuery {
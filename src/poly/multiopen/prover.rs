@@ -1,5 +1,5 @@
 use super::super::{
-    commitment::{self, Blind, Params},
+    commitment::{self, Blind, Prover},
     Coeff, Polynomial,
 };
 use super::{
@@ -7,11 +7,12 @@ use super::{
     Query,
 };
 
-use crate::arithmetic::{eval_polynomial, kate_division, CurveAffine, FieldExt};
+use crate::arithmetic::{eval_polynomial, kate_division, CurveAffine};
 use crate::transcript::TranscriptWrite;
 
 use ff::Field;
 use group::Curve;
+use rand::RngCore;
 use std::io;
 use std::marker::PhantomData;
 
@@ -23,9 +24,10 @@ struct CommitmentData<C: CurveAffine> {
     evals: Vec<C::Scalar>,
 }
 
-/// Create a multi-opening proof
-pub fn create_proof<'a, I, C: CurveAffine, T: TranscriptWrite<C>>(
-    params: &Params<C>,
+/// Create a multi-opening proof, with blinding sampled from `rng`
+pub fn create_proof<'a, I, C: CurveAffine, P: Prover<C>, R: RngCore, T: TranscriptWrite<C>>(
+    params: &P,
+    mut rng: R,
     transcript: &mut T,
     queries: I,
 ) -> io::Result<()>
@@ -72,7 +74,7 @@ where
                 .fold(poly.clone().unwrap().values, |poly, point| {
                     kate_division(&poly, *point)
                 });
-            poly.resize(params.n as usize, C::Scalar::zero());
+            poly.resize(params.n() as usize, C::Scalar::zero());
             let poly = Polynomial {
                 values: poly,
                 _marker: PhantomData,
@@ -86,7 +88,7 @@ where
         })
         .unwrap();
 
-    let f_blind = Blind(C::Scalar::rand());
+    let f_blind = Blind(C::Scalar::random(&mut rng));
     let f_commitment = params.commit(&f_poly, f_blind).to_affine();
 
     transcript.write_point(f_commitment)?;
@@ -114,7 +116,7 @@ where
         },
     );
 
-    commitment::create_proof(&params, transcript, &f_poly, f_blind_try, *x_3)
+    params.create_opening(rng, transcript, &f_poly, f_blind_try, *x_3)
 }
 
 #[doc(hidden)]
@@ -1,7 +1,7 @@
 use ff::Field;
 
 use super::super::{
-    commitment::{Guard, Params, MSM},
+    commitment::{DeferredMsm, Verifier},
     Error,
 };
 use super::{
@@ -18,12 +18,12 @@ struct CommitmentData<C: CurveAffine> {
 }
 
 /// Verify a multi-opening proof
-pub fn verify_proof<'b, 'a: 'b, I, C: CurveAffine, T: TranscriptRead<C>>(
-    params: &'a Params<C>,
+pub fn verify_proof<'b, 'a: 'b, I, C: CurveAffine, P: Verifier<C>, T: TranscriptRead<C>>(
+    params: &'a P,
     transcript: &mut T,
     queries: I,
-    mut msm: MSM<'a, C>,
-) -> Result<Guard<'a, C>, Error>
+    mut msm: P::Msm<'a>,
+) -> Result<P::Guard<'a>, Error>
 where
     I: IntoIterator<Item = VerifierQuery<'b, C>> + Clone,
 {
@@ -118,7 +118,7 @@ where
     );
 
     // Verify the opening proof
-    super::commitment::verify_proof(params, msm, transcript, *x_3, msm_eval)
+    params.verify_opening(msm, transcript, *x_3, msm_eval)
 }
 
 #[doc(hidden)]
@@ -0,0 +1,21 @@
+//! Implementations of common cryptographic primitives, evaluated outside of a circuit.
+//!
+//! These are the native counterparts of the chips in [`crate::gadget`], and can be used
+//! to compute the values that a circuit is expected to constrain (e.g. public inputs).
+
+pub mod aes;
+pub mod biguint;
+pub mod bn254;
+pub mod ed25519;
+pub mod endoscale;
+pub mod keccak;
+pub mod mimc;
+pub mod pedersen;
+pub mod poseidon;
+pub(crate) mod prime_field;
+pub mod rescue;
+pub mod rsa;
+pub mod schnorr;
+pub mod secp256k1;
+pub mod sha512;
+pub mod sinsemilla;
@@ -0,0 +1,178 @@
+//! The AES-128 block cipher, as specified in FIPS 197.
+//!
+//! The state is 16 bytes, with the byte in row $r$ and column $c$ at index $r + 4c$, so
+//! that a block is loaded into the state in order. Only encryption is provided.
+
+/// The number of bytes in a block, and in a key.
+pub const BLOCK_BYTES: usize = 16;
+
+/// The number of rounds of AES-128.
+pub const ROUNDS: usize = 10;
+
+/// An AES block, or the state of the cipher.
+pub type Block = [u8; BLOCK_BYTES];
+
+/// The round constants of the key schedule.
+pub const ROUND_CONSTANTS: [u8; ROUNDS] =
+    [0x01, 0x02, 0x04, 0x08, 0x10, 0x20, 0x40, 0x80, 0x1b, 0x36];
+
+/// Returns $2 \cdot x$ in $\mathbb{F}_{2^8}$, with the AES reduction polynomial
+/// $x^8 + x^4 + x^3 + x + 1$.
+pub fn xtime(x: u8) -> u8 {
+    (x << 1) ^ if x & 0x80 != 0 { 0x1b } else { 0 }
+}
+
+/// Returns $a \cdot b$ in $\mathbb{F}_{2^8}$.
+fn mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0;
+    while b != 0 {
+        if b & 1 != 0 {
+            product ^= a;
+        }
+        a = xtime(a);
+        b >>= 1;
+    }
+    product
+}
+
+/// Returns the S-box of `x`: its inverse in $\mathbb{F}_{2^8}$ (with $0$ mapped to
+/// itself), followed by the affine transformation of the cipher.
+pub fn sbox(x: u8) -> u8 {
+    // x^254 = x^{-1} for nonzero x.
+    let mut inverse = 1;
+    for _ in 0..254 {
+        inverse = mul(inverse, x);
+    }
+    let inverse = if x == 0 { 0 } else { inverse };
+
+    inverse
+        ^ inverse.rotate_left(1)
+        ^ inverse.rotate_left(2)
+        ^ inverse.rotate_left(3)
+        ^ inverse.rotate_left(4)
+        ^ 0x63
+}
+
+/// Returns the round keys of `key`, one per round and an initial one.
+pub fn expand_key(key: &Block) -> [Block; ROUNDS + 1] {
+    let mut round_keys = [[0; BLOCK_BYTES]; ROUNDS + 1];
+    round_keys[0] = *key;
+    for round in 0..ROUNDS {
+        let prev = round_keys[round];
+        let mut next = [0; BLOCK_BYTES];
+
+        // RotWord, SubWord and the round constant, on the last word.
+        let mut temp = [prev[13], prev[14], prev[15], prev[12]];
+        for byte in temp.iter_mut() {
+            *byte = sbox(*byte);
+        }
+        temp[0] ^= ROUND_CONSTANTS[round];
+
+        for i in 0..BLOCK_BYTES {
+            next[i] = prev[i] ^ if i < 4 { temp[i] } else { next[i - 4] };
+        }
+        round_keys[round + 1] = next;
+    }
+    round_keys
+}
+
+/// Returns the state after the ShiftRows step, which rotates row $r$ left by $r$.
+pub fn shift_rows(state: &Block) -> Block {
+    let mut shifted = [0; BLOCK_BYTES];
+    for r in 0..4 {
+        for c in 0..4 {
+            shifted[r + 4 * c] = state[r + 4 * ((c + r) % 4)];
+        }
+    }
+    shifted
+}
+
+/// Returns the given column after the MixColumns step.
+pub fn mix_column(column: [u8; 4]) -> [u8; 4] {
+    let sum = column[0] ^ column[1] ^ column[2] ^ column[3];
+    let mut mixed = [0; 4];
+    for i in 0..4 {
+        mixed[i] = column[i] ^ sum ^ xtime(column[i] ^ column[(i + 1) % 4]);
+    }
+    mixed
+}
+
+/// Encrypts `block` with `key`.
+pub fn encrypt(key: &Block, block: &Block) -> Block {
+    let round_keys = expand_key(key);
+
+    let mut state = *block;
+    for (byte, key) in state.iter_mut().zip(round_keys[0].iter()) {
+        *byte ^= key;
+    }
+    for (round, round_key) in round_keys.iter().enumerate().skip(1) {
+        for byte in state.iter_mut() {
+            *byte = sbox(*byte);
+        }
+        state = shift_rows(&state);
+        if round != ROUNDS {
+            for c in 0..4 {
+                let mixed = mix_column([
+                    state[4 * c],
+                    state[4 * c + 1],
+                    state[4 * c + 2],
+                    state[4 * c + 3],
+                ]);
+                state[4 * c..4 * c + 4].copy_from_slice(&mixed);
+            }
+        }
+        for (byte, key) in state.iter_mut().zip(round_key.iter()) {
+            *byte ^= key;
+        }
+    }
+    state
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryInto;
+
+    use super::{encrypt, expand_key, sbox, Block};
+
+    fn hex(bytes: &str) -> Block {
+        (0..16)
+            .map(|i| u8::from_str_radix(&bytes[2 * i..2 * i + 2], 16).unwrap())
+            .collect::<Vec<_>>()
+            .try_into()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_vectors() {
+        // FIPS 197, appendix C.1.
+        assert_eq!(
+            encrypt(
+                &hex("000102030405060708090a0b0c0d0e0f"),
+                &hex("00112233445566778899aabbccddeeff")
+            ),
+            hex("69c4e0d86a7b0430d8cdb78070b4c55a")
+        );
+        // FIPS 197, appendix B.
+        assert_eq!(
+            encrypt(
+                &hex("2b7e151628aed2a6abf7158809cf4f3c"),
+                &hex("3243f6a8885a308d313198a2e0370734")
+            ),
+            hex("3925841d02dc09fbdc118597196a0b32")
+        );
+    }
+
+    #[test]
+    fn key_schedule() {
+        // FIPS 197, appendix A.1.
+        let round_keys = expand_key(&hex("2b7e151628aed2a6abf7158809cf4f3c"));
+        assert_eq!(round_keys[10], hex("d014f9a8c9ee2589e13f0cc8b6630ca6"));
+    }
+
+    #[test]
+    fn sbox_values() {
+        assert_eq!(sbox(0x00), 0x63);
+        assert_eq!(sbox(0x53), 0xed);
+        assert_eq!(sbox(0xff), 0x16);
+    }
+}
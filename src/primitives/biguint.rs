@@ -0,0 +1,359 @@
+//! Arbitrary-precision unsigned integers.
+//!
+//! A [`BigUint`] is a sequence of little-endian 64-bit limbs with no trailing zero
+//! limbs, so that every integer has a unique representation. These are the values
+//! constrained by the [`crate::gadget::biguint`] gadget.
+
+use std::cmp::Ordering;
+
+use crate::arithmetic::{adc, mac, sbb};
+
+/// An arbitrary-precision unsigned integer.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct BigUint {
+    limbs: Vec<u64>,
+}
+
+impl BigUint {
+    /// Returns zero.
+    pub fn zero() -> Self {
+        BigUint::default()
+    }
+
+    /// Returns the integer given by little-endian 64-bit limbs.
+    pub fn from_limbs(limbs: &[u64]) -> Self {
+        let mut res = BigUint {
+            limbs: limbs.to_vec(),
+        };
+        res.normalize();
+        res
+    }
+
+    /// Returns the integer given by big-endian bytes.
+    pub fn from_bytes_be(bytes: &[u8]) -> Self {
+        let limbs: Vec<u64> = bytes
+            .rchunks(8)
+            .map(|chunk| chunk.iter().fold(0, |acc, byte| (acc << 8) | *byte as u64))
+            .collect();
+        BigUint::from_limbs(&limbs)
+    }
+
+    /// Returns the big-endian encoding of this integer in `len` bytes, or `None` if it
+    /// does not fit.
+    pub fn to_bytes_be(&self, len: usize) -> Option<Vec<u8>> {
+        if self.bits() > 8 * len {
+            return None;
+        }
+        Some(
+            (0..len)
+                .rev()
+                .map(|i| (self.limb(i / 8) >> (8 * (i % 8))) as u8)
+                .collect(),
+        )
+    }
+
+    /// The little-endian 64-bit limbs of this integer, without trailing zeros.
+    pub fn limbs(&self) -> &[u64] {
+        &self.limbs
+    }
+
+    /// Returns the `i`-th limb of this integer, which is zero beyond its length.
+    pub fn limb(&self, i: usize) -> u64 {
+        self.limbs.get(i).copied().unwrap_or(0)
+    }
+
+    /// Returns whether this integer is zero.
+    pub fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    /// Returns the number of bits needed to represent this integer.
+    pub fn bits(&self) -> usize {
+        self.limbs.last().map_or(0, |top| {
+            64 * self.limbs.len() - top.leading_zeros() as usize
+        })
+    }
+
+    /// Returns the `i`-th bit of this integer.
+    pub fn bit(&self, i: usize) -> bool {
+        (self.limb(i / 64) >> (i % 64)) & 1 == 1
+    }
+
+    /// Returns `self + other`.
+    pub fn add(&self, other: &Self) -> Self {
+        let len = self.limbs.len().max(other.limbs.len());
+        let mut limbs = Vec::with_capacity(len + 1);
+        let mut carry = 0;
+        for i in 0..len {
+            let (limb, c) = adc(self.limb(i), other.limb(i), carry);
+            limbs.push(limb);
+            carry = c;
+        }
+        limbs.push(carry);
+        BigUint::from_limbs(&limbs)
+    }
+
+    /// Returns `self - other`, or `None` if `other` is greater than `self`.
+    pub fn checked_sub(&self, other: &Self) -> Option<Self> {
+        if *self < *other {
+            return None;
+        }
+        let mut limbs = Vec::with_capacity(self.limbs.len());
+        let mut borrow = 0;
+        for i in 0..self.limbs.len() {
+            let (limb, b) = sbb(self.limb(i), other.limb(i), borrow);
+            limbs.push(limb);
+            borrow = b;
+        }
+        Some(BigUint::from_limbs(&limbs))
+    }
+
+    /// Returns `self * other`.
+    pub fn mul(&self, other: &Self) -> Self {
+        let mut limbs = vec![0; self.limbs.len() + other.limbs.len()];
+        for (i, a) in self.limbs.iter().enumerate() {
+            let mut carry = 0;
+            for (j, b) in other.limbs.iter().enumerate() {
+                let (limb, c) = mac(limbs[i + j], *a, *b, carry);
+                limbs[i + j] = limb;
+                carry = c;
+            }
+            limbs[i + other.limbs.len()] = carry;
+        }
+        BigUint::from_limbs(&limbs)
+    }
+
+    /// Returns `self << bits`.
+    pub fn shl(&self, bits: usize) -> Self {
+        let (words, bits) = (bits / 64, bits % 64);
+        let mut limbs = vec![0; words];
+        let mut carry = 0;
+        for limb in self.limbs.iter() {
+            limbs.push((limb << bits) | carry);
+            carry = if bits == 0 { 0 } else { limb >> (64 - bits) };
+        }
+        limbs.push(carry);
+        BigUint::from_limbs(&limbs)
+    }
+
+    /// Returns `self >> bits`.
+    pub fn shr(&self, bits: usize) -> Self {
+        let (words, bits) = (bits / 64, bits % 64);
+        let limbs: Vec<u64> = (words..self.limbs.len())
+            .map(|i| {
+                let high = if bits == 0 {
+                    0
+                } else {
+                    self.limb(i + 1) << (64 - bits)
+                };
+                (self.limbs[i] >> bits) | high
+            })
+            .collect();
+        BigUint::from_limbs(&limbs)
+    }
+
+    /// Returns the quotient and remainder of `self` divided by `divisor`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `divisor` is zero.
+    pub fn div_rem(&self, divisor: &Self) -> (Self, Self) {
+        assert!(!divisor.is_zero(), "division by zero");
+        if *self < *divisor {
+            return (BigUint::zero(), self.clone());
+        }
+
+        // Long division by normalized 64-bit digits, following Knuth's Algorithm D.
+        let shift = divisor.limbs.last().unwrap().leading_zeros() as usize;
+        let v = divisor.shl(shift).limbs;
+        let mut u = self.shl(shift).limbs;
+        u.resize(self.limbs.len() + 1, 0);
+        let n = v.len();
+        let m = u.len() - n - 1;
+
+        let base = 1u128 << 64;
+        let mut q = vec![0; m + 1];
+        for j in (0..=m).rev() {
+            let numerator = ((u[j + n] as u128) << 64) | u[j + n - 1] as u128;
+            let mut q_hat = numerator / v[n - 1] as u128;
+            let mut r_hat = numerator % v[n - 1] as u128;
+            while q_hat >= base
+                || (n >= 2 && q_hat * v[n - 2] as u128 > ((r_hat << 64) | u[j + n - 2] as u128))
+            {
+                q_hat -= 1;
+                r_hat += v[n - 1] as u128;
+                if r_hat >= base {
+                    break;
+                }
+            }
+
+            // u[j..=j + n] -= q_hat v
+            let mut borrow = 0i128;
+            let mut carry = 0u128;
+            for i in 0..n {
+                let p = q_hat * v[i] as u128 + carry;
+                carry = p >> 64;
+                let t = u[i + j] as i128 - borrow - (p as u64) as i128;
+                u[i + j] = t as u64;
+                borrow = (t < 0) as i128;
+            }
+            let t = u[j + n] as i128 - borrow - carry as i128;
+            u[j + n] = t as u64;
+
+            // The estimate was one too large, so add v back.
+            if t < 0 {
+                q_hat -= 1;
+                let mut carry = 0;
+                for i in 0..n {
+                    let (limb, c) = adc(u[i + j], v[i], carry);
+                    u[i + j] = limb;
+                    carry = c;
+                }
+                u[j + n] = u[j + n].wrapping_add(carry);
+            }
+            q[j] = q_hat as u64;
+        }
+
+        (
+            BigUint::from_limbs(&q),
+            BigUint::from_limbs(&u[..n]).shr(shift),
+        )
+    }
+
+    /// Returns `self` modulo `modulus`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `modulus` is zero.
+    pub fn rem(&self, modulus: &Self) -> Self {
+        self.div_rem(modulus).1
+    }
+
+    /// Returns `self^exponent` modulo `modulus`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `modulus` is zero.
+    pub fn pow_mod(&self, exponent: &Self, modulus: &Self) -> Self {
+        let base = self.rem(modulus);
+        (0..exponent.bits())
+            .rev()
+            .fold(BigUint::from_limbs(&[1]).rem(modulus), |acc, i| {
+                let acc = acc.mul(&acc).rem(modulus);
+                if exponent.bit(i) {
+                    acc.mul(&base).rem(modulus)
+                } else {
+                    acc
+                }
+            })
+    }
+
+    /// Returns the inverse of `self` modulo `modulus`, or `None` if there is none.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `modulus` is zero.
+    pub fn mod_inverse(&self, modulus: &Self) -> Option<Self> {
+        // Extended Euclid, keeping the Bezout coefficients of self modulo the modulus.
+        let (mut r0, mut r1) = (modulus.clone(), self.rem(modulus));
+        let (mut s0, mut s1) = (BigUint::zero(), BigUint::from(1));
+        while !r1.is_zero() {
+            let (q, r) = r0.div_rem(&r1);
+            let s = s0
+                .add(modulus)
+                .checked_sub(&q.mul(&s1).rem(modulus))
+                .unwrap()
+                .rem(modulus);
+            r0 = std::mem::replace(&mut r1, r);
+            s0 = std::mem::replace(&mut s1, s);
+        }
+        if r0 == BigUint::from(1) {
+            Some(s0.rem(modulus))
+        } else {
+            None
+        }
+    }
+
+    fn normalize(&mut self) {
+        while self.limbs.last() == Some(&0) {
+            self.limbs.pop();
+        }
+    }
+}
+
+impl From<u64> for BigUint {
+    fn from(value: u64) -> Self {
+        BigUint::from_limbs(&[value])
+    }
+}
+
+impl Ord for BigUint {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.limbs
+            .len()
+            .cmp(&other.limbs.len())
+            .then_with(|| self.limbs.iter().rev().cmp(other.limbs.iter().rev()))
+    }
+}
+
+impl PartialOrd for BigUint {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BigUint;
+
+    #[test]
+    fn arithmetic() {
+        let a = BigUint::from_limbs(&[u64::MAX, 1, u64::MAX, 3, 0]);
+        let b = BigUint::from_limbs(&[5, 1 << 63, 7]);
+        assert_eq!(a.limbs().len(), 4);
+        assert_eq!(a.bits(), 194);
+
+        let product = a.mul(&b);
+        assert_eq!(product.div_rem(&b), (a.clone(), BigUint::zero()));
+        assert_eq!(product.div_rem(&a), (b.clone(), BigUint::zero()));
+
+        let c = BigUint::from_limbs(&[12345, 678]);
+        let (q, r) = product.add(&c).div_rem(&a);
+        assert_eq!(q, b);
+        assert_eq!(r, c);
+        assert_eq!(q.mul(&a).add(&r), product.add(&c));
+
+        // A single-limb divisor, and a quotient estimate that must be corrected.
+        let (q, r) = a.div_rem(&BigUint::from(10));
+        assert_eq!(q.mul(&BigUint::from(10)).add(&r), a);
+        assert!(r < BigUint::from(10));
+        let d = BigUint::from_limbs(&[0, 1 << 63]);
+        let n = BigUint::from_limbs(&[0, 0, (1 << 63) - 1]);
+        let (q, r) = n.div_rem(&d);
+        assert_eq!(q.mul(&d).add(&r), n);
+        assert!(r < d);
+
+        assert_eq!(b.checked_sub(&a), None);
+        assert_eq!(a.checked_sub(&b).unwrap().add(&b), a);
+        assert_eq!(a.shl(100).shr(100), a);
+        assert_eq!(a.shr(250), BigUint::zero());
+
+        let bytes = a.to_bytes_be(32).unwrap();
+        assert_eq!(bytes[7], 3);
+        assert_eq!(BigUint::from_bytes_be(&bytes), a);
+        assert_eq!(a.to_bytes_be(24), None);
+    }
+
+    #[test]
+    fn pow_mod() {
+        // Fermat's little theorem for the prime 2^127 - 1.
+        let p = BigUint::from_limbs(&[u64::MAX, u64::MAX >> 1]);
+        let a = BigUint::from_limbs(&[0x0123_4567_89ab_cdef, 42]);
+        let exponent = p.checked_sub(&BigUint::from(1)).unwrap();
+        assert_eq!(a.pow_mod(&exponent, &p), BigUint::from(1));
+        assert_eq!(a.pow_mod(&BigUint::zero(), &p), BigUint::from(1));
+        assert_eq!(a.pow_mod(&BigUint::from(3), &p), a.mul(&a).mul(&a).rem(&p));
+        assert_eq!(a.mod_inverse(&p).unwrap().mul(&a).rem(&p), BigUint::from(1));
+        assert_eq!(BigUint::from(6).mod_inverse(&BigUint::from(9)), None);
+    }
+}
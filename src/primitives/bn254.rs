@@ -0,0 +1,704 @@
+//! The BN254 pairing-friendly curve, also known as alt_bn128.
+//!
+//! This is a straightforward implementation of the groups and the pairing of BN254, to
+//! support the [KZG](crate::poly::kzg) commitment scheme. Field elements are stored as
+//! canonical little-endian 64-bit limbs, and multiplied in Montgomery form.
+//!
+//! [`G1Affine`] implements [`CurveAffine`], with the same curve arithmetic as the
+//! Pallas and Vesta curves, so that the proof system can run over BN254 with its
+//! commitments in $\mathbb{G}_1$. Hashing to the curve is not supported.
+//!
+//! # Security
+//!
+//! This implementation has not been audited, and it is **not constant-time**: the
+//! field arithmetic, the scalar multiplications of [`G2Affine`] and [`Gt`], and the
+//! pairing branch on the values they process. It must not be used where the time of a
+//! computation over secret values, such as the witness of a proof, can be observed.
+//!
+//! # Compatibility
+//!
+//! The pairing is the reduced Tate pairing $e(P, Q) = f_{r,P}(\psi(Q))^{(p^{12} - 1) / r}$,
+//! where $\psi$ maps the sextic twist that holds $\mathbb{G}_2$ into
+//! $E(\mathbb{F}_{p^{12}})$. It is **not compatible with the EVM**: the pairing
+//! precompile of Ethereum, and most other BN254 libraries, compute the optimal ate
+//! pairing, whose values in $\mathbb{G}_T$ differ from those of [`pairing`] and
+//! [`multi_pairing`], and [`Gt`] has no encoding that they accept. Only the
+//! identity of a product of pairings, as the precompile checks it, is the same for
+//! both pairings, because both are bilinear and non-degenerate.
+
+use core::cmp;
+use core::iter::Sum;
+use std::ops::{Add, Mul, Neg, Sub};
+
+use ff::Field;
+use group::{
+    prime::{PrimeCurve, PrimeCurveAffine, PrimeGroup},
+    Curve as _, Group as _, GroupEncoding,
+};
+use rand::RngCore;
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq, CtOption};
+
+use crate::arithmetic::{CurveAffine, CurveExt, FieldExt, Group};
+use crate::pasta::{
+    impl_add_binop_specify_output, impl_affine_curve_specific, impl_binops_additive,
+    impl_binops_additive_specify_output, impl_binops_multiplicative,
+    impl_binops_multiplicative_mixed, impl_projective_curve_ext, impl_projective_curve_specific,
+    impl_sub_binop_specify_output, new_curve_impl,
+};
+use crate::primitives::{
+    biguint::BigUint,
+    prime_field::{field_element, field_ext},
+};
+
+/// An element of the base field of BN254, of order
+/// $p = 21888242871839275222246405745257275088696311157297823662689037894645226208583$.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Base([u64; 4]);
+
+field_element!(
+    Base,
+    [
+        0x3c20_8c16_d87c_fd47,
+        0x9781_6a91_6871_ca8d,
+        0xb850_45b6_8181_585d,
+        0x3064_4e72_e131_a029,
+    ]
+);
+
+field_ext!(
+    Base,
+    modulus: "0x30644e72e131a029b85045b68181585d97816a916871ca8d3c208c16d87cfd47",
+    num_bits: 254,
+    s: 1,
+    generator: 3,
+    root_of_unity: [
+        0x3c20_8c16_d87c_fd46,
+        0x9781_6a91_6871_ca8d,
+        0xb850_45b6_8181_585d,
+        0x3064_4e72_e131_a029,
+    ],
+    root_of_unity_inv: [
+        0x3c20_8c16_d87c_fd46,
+        0x9781_6a91_6871_ca8d,
+        0xb850_45b6_8181_585d,
+        0x3064_4e72_e131_a029,
+    ],
+    t_minus1_over2: [
+        0x4f08_2305_b61f_3f51,
+        0x65e0_5aa4_5a1c_72a3,
+        0x6e14_116d_a060_5617,
+        0x0c19_139c_b84c_680a,
+    ],
+    delta: [9, 0, 0, 0],
+    two_inv: [
+        0x9e10_460b_6c3e_7ea4,
+        0xcbc0_b548_b438_e546,
+        0xdc28_22db_40c0_ac2e,
+        0x1832_2739_7098_d014,
+    ],
+    rescue_alpha: 5,
+    rescue_invalpha: [
+        0x180d_04d5_f031_fee9,
+        0xd633_c43a_29c7_1dd2,
+        0x49b9_b57c_33cd_568b,
+        0x135b_5294_5a13_d9aa,
+    ],
+    zeta: [
+        0x5763_4731_77ff_fffe,
+        0xd4f2_63f1_acdb_5c4f,
+        0x59e2_6bce_a0d4_8bac,
+        0x0000_0000_0000_0000,
+    ],
+);
+
+/// An element of the scalar field of BN254, whose order
+/// $r = 21888242871839275222246405745257275088548364400416034343698204186575808495617$
+/// is the order of $\mathbb{G}_1$ and $\mathbb{G}_2$.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Scalar([u64; 4]);
+
+field_element!(
+    Scalar,
+    [
+        0x43e1_f593_f000_0001,
+        0x2833_e848_79b9_7091,
+        0xb850_45b6_8181_585d,
+        0x3064_4e72_e131_a029,
+    ]
+);
+
+field_ext!(
+    Scalar,
+    modulus: "0x30644e72e131a029b85045b68181585d2833e84879b9709143e1f593f0000001",
+    num_bits: 254,
+    s: 28,
+    generator: 5,
+    root_of_unity: [
+        0x9bd6_1b6e_725b_19f0,
+        0x402d_111e_4111_2ed4,
+        0x00e0_a7eb_8ef6_2abc,
+        0x2a3c_09f0_a58a_7e85,
+    ],
+    root_of_unity_inv: [
+        0x7239_4277_9d18_157e,
+        0xec9d_51f8_fd39_9d5d,
+        0x6117_635d_49d5_387f,
+        0x01b7_7519_9c22_9cd5,
+    ],
+    t_minus1_over2: [
+        0xcdcb_848a_1f0f_ac9f,
+        0x0c0a_c2e9_419f_4243,
+        0x098d_014d_c282_2db4,
+        0x0000_0001_8322_7397,
+    ],
+    delta: [
+        0xa145_8af6_83d2_8e01,
+        0x3f54_4c14_cc40_ac6b,
+        0x21f1_ec72_be9f_9a26,
+        0x0ba4_9465_f79c_e3ca,
+    ],
+    two_inv: [
+        0xa1f0_fac9_f800_0001,
+        0x9419_f424_3cdc_b848,
+        0xdc28_22db_40c0_ac2e,
+        0x1832_2739_7098_d014,
+    ],
+    rescue_alpha: 5,
+    rescue_invalpha: [
+        0xcfe7_f7a9_8ccc_cccd,
+        0x535c_b9d3_9494_5a0d,
+        0x9373_6af8_679a_ad17,
+        0x26b6_a528_b427_b354,
+    ],
+    zeta: [
+        0x8b17_ea66_b99c_90dd,
+        0x5bfc_4108_8d8d_aaa7,
+        0xb3c4_d79d_41a9_1758,
+        0x0000_0000_0000_0000,
+    ],
+);
+
+/// An element of $\mathbb{F}_{p^2} = \mathbb{F}_p\[u\] / (u^2 + 1)$, as $c_0 + c_1 u$.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Fq2 {
+    /// The constant coefficient.
+    pub c0: Base,
+    /// The coefficient of $u$.
+    pub c1: Base,
+}
+
+impl Fq2 {
+    /// Returns the element $c_0 + c_1 u$.
+    pub const fn new(c0: Base, c1: Base) -> Self {
+        Fq2 { c0, c1 }
+    }
+
+    /// Returns zero.
+    pub const fn zero() -> Self {
+        Fq2::new(Base::zero(), Base::zero())
+    }
+
+    /// Returns one.
+    pub const fn one() -> Self {
+        Fq2::new(Base::one(), Base::zero())
+    }
+
+    /// Returns the element with the given value.
+    pub const fn from_u64(value: u64) -> Self {
+        Fq2::new(Base::from_u64(value), Base::zero())
+    }
+
+    /// Returns whether this element is zero.
+    pub fn is_zero(&self) -> bool {
+        self.c0.is_zero() && self.c1.is_zero()
+    }
+
+    /// Returns the square of this element.
+    pub fn square(&self) -> Self {
+        *self * *self
+    }
+
+    /// Returns the multiplicative inverse of this element, or `None` if it is zero.
+    pub fn invert(&self) -> Option<Self> {
+        // (c0 + c1 u)^-1 = (c0 - c1 u) / (c0^2 + c1^2)
+        (self.c0.square() + self.c1.square())
+            .invert()
+            .map(|t| Fq2::new(self.c0 * t, -(self.c1 * t)))
+    }
+
+    /// Returns this element multiplied by $\xi = 9 + u$, the non-residue that defines
+    /// $\mathbb{F}_{p^{12}}$.
+    fn mul_by_xi(&self) -> Self {
+        let nine = Base::from_u64(9);
+        Fq2::new(nine * self.c0 - self.c1, self.c0 + nine * self.c1)
+    }
+}
+
+impl Add for Fq2 {
+    type Output = Fq2;
+
+    fn add(self, other: Fq2) -> Fq2 {
+        Fq2::new(self.c0 + other.c0, self.c1 + other.c1)
+    }
+}
+
+impl Sub for Fq2 {
+    type Output = Fq2;
+
+    fn sub(self, other: Fq2) -> Fq2 {
+        Fq2::new(self.c0 - other.c0, self.c1 - other.c1)
+    }
+}
+
+impl Neg for Fq2 {
+    type Output = Fq2;
+
+    fn neg(self) -> Fq2 {
+        Fq2::new(-self.c0, -self.c1)
+    }
+}
+
+impl Mul for Fq2 {
+    type Output = Fq2;
+
+    fn mul(self, other: Fq2) -> Fq2 {
+        Fq2::new(
+            self.c0 * other.c0 - self.c1 * other.c1,
+            self.c0 * other.c1 + self.c1 * other.c0,
+        )
+    }
+}
+
+/// An element of $\mathbb{F}_{p^{12}} = \mathbb{F}_{p^2}\[w\] / (w^6 - \xi)$, as its
+/// coefficients in $1, w, \ldots, w^5$.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Fq12([Fq2; 6]);
+
+impl Fq12 {
+    fn one() -> Self {
+        let mut c = [Fq2::zero(); 6];
+        c[0] = Fq2::one();
+        Fq12(c)
+    }
+
+    /// Returns this element raised to the power with the given little-endian limbs.
+    fn pow(&self, exp: &[u64]) -> Self {
+        let mut res = Self::one();
+        for limb in exp.iter().rev() {
+            for i in (0..64).rev() {
+                res = res * res;
+                if (limb >> i) & 1 == 1 {
+                    res = res * *self;
+                }
+            }
+        }
+        res
+    }
+}
+
+impl Mul for Fq12 {
+    type Output = Fq12;
+
+    fn mul(self, other: Fq12) -> Fq12 {
+        // Products of degree 6 and above are reduced by w^6 = xi.
+        let mut low = [Fq2::zero(); 6];
+        let mut high = [Fq2::zero(); 5];
+        for (i, a) in self.0.iter().enumerate() {
+            for (j, b) in other.0.iter().enumerate() {
+                let product = *a * *b;
+                if i + j < 6 {
+                    low[i + j] = low[i + j] + product;
+                } else {
+                    high[i + j - 6] = high[i + j - 6] + product;
+                }
+            }
+        }
+        for (low, high) in low.iter_mut().zip(high.iter()) {
+            *low = *low + high.mul_by_xi();
+        }
+        Fq12(low)
+    }
+}
+
+/// Implements affine arithmetic for a curve $y^2 = x^3 + b$ over the field `$field`.
+macro_rules! affine_point {
+    ($name:ident, $field:ident, $b:expr) => {
+        impl $name {
+            /// Returns the identity.
+            pub fn identity() -> Self {
+                $name(None)
+            }
+
+            /// Returns the point with the given coordinates, or `None` if it is not on
+            /// the curve.
+            pub fn from_xy(x: $field, y: $field) -> Option<Self> {
+                if y.square() == x.square() * x + $b {
+                    Some($name(Some((x, y))))
+                } else {
+                    None
+                }
+            }
+
+            /// The coordinates of this point, or `None` for the identity.
+            pub fn coordinates(&self) -> Option<($field, $field)> {
+                self.0
+            }
+
+            /// Returns whether this point is the identity.
+            pub fn is_identity(&self) -> bool {
+                self.0.is_none()
+            }
+
+            /// Returns `[2] self`.
+            pub fn double(&self) -> Self {
+                match self.0 {
+                    Some((x, y)) if !y.is_zero() => {
+                        let lambda = $field::from_u64(3) * x.square() * (y + y).invert().unwrap();
+                        let x_r = lambda.square() - x - x;
+                        $name(Some((x_r, lambda * (x - x_r) - y)))
+                    }
+                    _ => $name::identity(),
+                }
+            }
+
+            /// Returns `[scalar] self`.
+            pub fn mul(&self, scalar: &Scalar) -> Self {
+                self.mul_by_limbs(&scalar.to_limbs())
+            }
+
+            /// Returns `[k] self` for the integer with little-endian limbs `k`.
+            fn mul_by_limbs(&self, k: &[u64; 4]) -> Self {
+                k.iter()
+                    .rev()
+                    .flat_map(|limb| (0..64).rev().map(move |i| (limb >> i) & 1 == 1))
+                    .fold($name::identity(), |acc, bit| {
+                        let acc = acc.double();
+                        if bit {
+                            acc + *self
+                        } else {
+                            acc
+                        }
+                    })
+            }
+        }
+
+        impl Add for $name {
+            type Output = $name;
+
+            fn add(self, other: $name) -> $name {
+                match (self.0, other.0) {
+                    (None, _) => other,
+                    (_, None) => self,
+                    (Some((x_p, y_p)), Some((x_q, y_q))) => {
+                        if x_p == x_q {
+                            if y_p == y_q {
+                                self.double()
+                            } else {
+                                $name::identity()
+                            }
+                        } else {
+                            let lambda = (y_q - y_p) * (x_q - x_p).invert().unwrap();
+                            let x_r = lambda.square() - x_p - x_q;
+                            $name(Some((x_r, lambda * (x_p - x_r) - y_p)))
+                        }
+                    }
+                }
+            }
+        }
+
+        impl Neg for $name {
+            type Output = $name;
+
+            fn neg(self) -> $name {
+                $name(self.0.map(|(x, y)| (x, -y)))
+            }
+        }
+    };
+}
+
+/// The curve constant $b = 3$ of $\mathbb{G}_1$, in $y^2 = x^3 + b$.
+pub const B: Base = Base::from_u64(3);
+
+/// Returns the curve constant $b / \xi$ of the twist that holds $\mathbb{G}_2$.
+fn twist_b() -> Fq2 {
+    Fq2::from_u64(3) * Fq2::new(Base::from_u64(9), Base::one()).invert().unwrap()
+}
+
+new_curve_impl!(
+    (pub),
+    G1,
+    G1Affine,
+    G1,
+    Base,
+    Scalar,
+    "bn254",
+    [0, 0, 0, 0],
+    [3, 0, 0, 0],
+    special_a0_b3
+);
+
+impl G1Affine {
+    /// Returns the encoding of this point used by the Ethereum precompiles: the
+    /// big-endian coordinates $x$ and $y$, or zeros for the identity.
+    pub fn to_uncompressed(&self) -> [u8; 64] {
+        let mut bytes = [0u8; 64];
+        if let Some((x, y)) = Option::<(Base, Base)>::from(self.get_xy()) {
+            bytes[..32].copy_from_slice(&x.to_be_bytes());
+            bytes[32..].copy_from_slice(&y.to_be_bytes());
+        }
+        bytes
+    }
+
+    /// Returns the point with the encoding of [`G1Affine::to_uncompressed`], or `None`
+    /// if it is not a point of the curve.
+    pub fn from_uncompressed(bytes: &[u8; 64]) -> Option<Self> {
+        if bytes.iter().all(|b| *b == 0) {
+            return Some(G1Affine::identity());
+        }
+        let mut x = [0u8; 32];
+        let mut y = [0u8; 32];
+        x.copy_from_slice(&bytes[..32]);
+        y.copy_from_slice(&bytes[32..]);
+        G1Affine::from_xy(Base::from_be_bytes(&x)?, Base::from_be_bytes(&y)?).into()
+    }
+}
+
+/// A point of $\mathbb{G}_2$, on the twist $y^2 = x^3 + 3 / \xi$ over
+/// $\mathbb{F}_{p^2}$, in affine coordinates, or the identity.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct G2Affine(Option<(Fq2, Fq2)>);
+
+affine_point!(G2Affine, Fq2, twist_b());
+
+impl G2Affine {
+    /// Returns the standard generator, as in EIP-197.
+    pub fn generator() -> Self {
+        G2Affine(Some((
+            Fq2::new(
+                Base([
+                    0x46de_bd5c_d992_f6ed,
+                    0x6743_22d4_f75e_dadd,
+                    0x426a_0066_5e5c_4479,
+                    0x1800_deef_121f_1e76,
+                ]),
+                Base([
+                    0x97e4_85b7_aef3_12c2,
+                    0xf1aa_4933_35a9_e712,
+                    0x7260_bfb7_31fb_5d25,
+                    0x198e_9393_920d_483a,
+                ]),
+            ),
+            Fq2::new(
+                Base([
+                    0x4ce6_cc01_66fa_7daa,
+                    0xe3d1_e769_0c43_d37b,
+                    0x4aab_7180_8dcb_408f,
+                    0x12c8_5ea5_db8c_6deb,
+                ]),
+                Base([
+                    0x55ac_dadc_d122_975b,
+                    0xbc4b_3133_70b3_8ef3,
+                    0xec9e_99ad_690c_3395,
+                    0x0906_89d0_585f_f075,
+                ]),
+            ),
+        )))
+    }
+}
+
+/// An element of the target group $\mathbb{G}_T$ of the pairing, written
+/// multiplicatively.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Gt(Fq12);
+
+impl Gt {
+    /// Returns the identity.
+    pub fn identity() -> Self {
+        Gt(Fq12::one())
+    }
+
+    /// Returns whether this element is the identity.
+    pub fn is_identity(&self) -> bool {
+        self.0 == Fq12::one()
+    }
+
+    /// Returns this element raised to the power `scalar`.
+    pub fn pow(&self, scalar: &Scalar) -> Self {
+        Gt(self.0.pow(&scalar.to_limbs()))
+    }
+}
+
+impl Mul for Gt {
+    type Output = Gt;
+
+    fn mul(self, other: Gt) -> Gt {
+        Gt(self.0 * other.0)
+    }
+}
+
+/// Returns the line through `t` with slope `lambda`, evaluated at $\psi(q)$.
+///
+/// $\psi(x, y) = (x w^2, y w^3)$, so the line $y - y_t - \lambda (x - x_t)$ only has
+/// coefficients in $1$, $w^2$ and $w^3$.
+fn line(t: (Base, Base), lambda: Base, q: (Fq2, Fq2)) -> Fq12 {
+    let (x_t, y_t) = t;
+    let (x_q, y_q) = q;
+    let lambda2 = Fq2::new(lambda, Base::zero());
+    let mut c = [Fq2::zero(); 6];
+    c[0] = Fq2::new(lambda * x_t - y_t, Base::zero());
+    c[2] = -(lambda2 * x_q);
+    c[3] = y_q;
+    Fq12(c)
+}
+
+/// Returns the Miller function $f_{r,p}$ evaluated at $\psi(q)$, up to factors in
+/// $\mathbb{F}_{p^6}$.
+///
+/// The vertical lines of the loop lie in $\mathbb{F}_{p^6}$, because the $x$-coordinate
+/// of $\psi(q)$ does, so they are omitted: the final exponentiation maps them to one.
+fn miller_loop(p: &G1Affine, q: &G2Affine) -> Fq12 {
+    let coordinates = |t: &G1Affine| Option::<(Base, Base)>::from(t.get_xy());
+    let (x_p, q) = match (coordinates(p), q.0) {
+        (Some((x_p, _)), Some(q)) => (x_p, q),
+        _ => return Fq12::one(),
+    };
+    let r = Scalar::MODULUS;
+    let top = 255 - r[3].leading_zeros() as usize;
+
+    let mut f = Fq12::one();
+    let mut t = *p;
+    for i in (0..top).rev() {
+        if let Some((x_t, y_t)) = coordinates(&t) {
+            // t has odd order, so y_t is not zero.
+            let lambda = Base::from_u64(3) * x_t.square() * (y_t + y_t).invert().unwrap();
+            f = f * f * line((x_t, y_t), lambda, q);
+            t = t.to_curve().double().to_affine();
+        }
+
+        if (r[i / 64] >> (i % 64)) & 1 == 1 {
+            if let Some((x_t, y_t)) = coordinates(&t) {
+                if x_t != x_p {
+                    let lambda = (p.y - y_t) * (x_p - x_t).invert().unwrap();
+                    f = f * line((x_t, y_t), lambda, q);
+                }
+            }
+            t = (t + p).to_affine();
+        }
+    }
+    f
+}
+
+/// Raises `f` to the power $(p^{12} - 1) / r$.
+fn final_exponentiation(f: Fq12) -> Gt {
+    let p = BigUint::from_limbs(&Base::MODULUS);
+    let r = BigUint::from_limbs(&Scalar::MODULUS);
+    let p12 = (0..11).fold(p.clone(), |acc, _| acc.mul(&p));
+    let (exp, _) = p12.checked_sub(&BigUint::from(1)).unwrap().div_rem(&r);
+    Gt(f.pow(exp.limbs()))
+}
+
+/// Returns the pairing $e(p, q)$.
+pub fn pairing(p: &G1Affine, q: &G2Affine) -> Gt {
+    multi_pairing(&[(*p, *q)])
+}
+
+/// Returns the product of the pairings of `terms`, with a single final
+/// exponentiation.
+pub fn multi_pairing(terms: &[(G1Affine, G2Affine)]) -> Gt {
+    final_exponentiation(
+        terms
+            .iter()
+            .fold(Fq12::one(), |acc, (p, q)| acc * miller_loop(p, q)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use ff::Field;
+    use group::{prime::PrimeCurveAffine, Curve, Group as _, GroupEncoding};
+    use rand::rngs::OsRng;
+
+    use super::{multi_pairing, pairing, Base, G1Affine, G2Affine, Scalar, G1};
+    use crate::arithmetic::{CurveAffine, CurveExt, FieldExt};
+
+    #[test]
+    fn field_constants() {
+        fn check<F: FieldExt>() {
+            let two_s = |x: F| (0..F::S).fold(x, |x, _| x.square());
+            let root = F::ROOT_OF_UNITY;
+            assert_eq!(two_s(root), F::one());
+            // The root has order exactly 2^S, so it is not a square.
+            assert!(bool::from(root.sqrt().is_none()));
+            assert_eq!(root * F::ROOT_OF_UNITY_INV, F::one());
+            assert_eq!(two_s(F::multiplicative_generator()), F::DELTA);
+            assert_eq!(F::TWO_INV.double(), F::one());
+            assert_ne!(F::ZETA, F::one());
+            assert_eq!(F::ZETA.cube(), F::one());
+
+            for _ in 0..10 {
+                let a = F::random(OsRng);
+                assert_eq!(a.square().sqrt().unwrap().square(), a.square());
+                let (is_square, root) = F::sqrt_ratio(&a, &F::one());
+                if bool::from(is_square) {
+                    assert_eq!(root.square(), a);
+                } else {
+                    assert_eq!(root.square(), a * F::ROOT_OF_UNITY);
+                }
+                assert_eq!(F::from_bytes(&a.to_bytes()).unwrap(), a);
+                assert_eq!(a.pow_vartime([F::RESCUE_ALPHA]).pow(&F::RESCUE_INVALPHA), a);
+            }
+        }
+        check::<Base>();
+        check::<Scalar>();
+    }
+
+    #[test]
+    fn group_order() {
+        let g1 = G1Affine::generator();
+        let g2 = G2Affine::generator();
+        assert!(bool::from(g1.is_on_curve()));
+        assert!(G2Affine::from_xy(g2.0.unwrap().0, g2.0.unwrap().1).is_some());
+        assert!(bool::from((g1 * -Scalar::one() + g1).is_identity()));
+        assert!(g2.mul_by_limbs(&Scalar::MODULUS).is_identity());
+
+        let a = Scalar::from_u64(0x0123_4567_89ab_cdef).square();
+        assert_eq!(g1 * a + g1 * -a, G1::identity());
+        assert_eq!(g2.mul(&a).mul(&a), g2.mul(&a.square()));
+
+        let point = (g1 * a).to_affine();
+        assert_eq!(G1Affine::from_bytes(&point.to_bytes()).unwrap(), point);
+        let encoded = point.to_uncompressed();
+        assert_eq!(G1Affine::from_uncompressed(&encoded), Some(point));
+        assert_eq!(
+            G1Affine::from_uncompressed(&[0; 64]),
+            Some(G1Affine::identity())
+        );
+        let mut invalid = encoded;
+        invalid[63] ^= 1;
+        assert_eq!(G1Affine::from_uncompressed(&invalid), None);
+    }
+
+    #[test]
+    fn endo_consistency() {
+        let a = G1::generator() * Scalar::random(OsRng);
+        assert_eq!(a * Scalar::ZETA, a.endo());
+    }
+
+    #[test]
+    fn bilinearity() {
+        let g1 = G1Affine::generator();
+        let g2 = G2Affine::generator();
+        let a = Scalar::from_u64(0xdead_beef);
+        let b = Scalar::from_u64(0x0123_4567);
+        let mul = |s: Scalar| (g1 * s).to_affine();
+
+        let e = pairing(&g1, &g2);
+        assert!(!e.is_identity());
+        assert_eq!(pairing(&mul(a), &g2.mul(&b)), e.pow(&(a * b)));
+        assert!(pairing(&G1Affine::identity(), &g2).is_identity());
+
+        // e([a] g1, [b] g2) e([-ab] g1, g2) = 1
+        assert!(multi_pairing(&[(mul(a), g2.mul(&b)), (mul(-(a * b)), g2)]).is_identity());
+        assert!(!multi_pairing(&[(mul(a), g2.mul(&b)), (mul(-a), g2)]).is_identity());
+    }
+}
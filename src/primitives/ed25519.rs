@@ -0,0 +1,387 @@
+//! The Ed25519 signature scheme over edwards25519.
+//!
+//! This is a straightforward (and not constant-time) implementation of RFC 8032, as
+//! used by SSH, DNSSEC and many credential formats, and computes the witnesses of
+//! [`crate::gadget::ed25519`]. Points are on the twisted Edwards curve
+//! $-x^2 + y^2 = 1 + d x^2 y^2$ over the field of order $p = 2^{255} - 19$, and are
+//! encoded as the little-endian $y$-coordinate with the parity of $x$ in the top bit.
+//!
+//! Verification checks the cofactorless equation $\[S\] B = R + \[k\] A$, which is
+//! also what the circuit checks.
+
+use std::convert::TryInto;
+use std::ops::{Add, Neg};
+
+use super::sha512::sha512;
+use crate::primitives::prime_field::field_element;
+
+/// An element of the base field of edwards25519, of order $p = 2^{255} - 19$.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Base([u64; 4]);
+
+field_element!(
+    Base,
+    [
+        0xffff_ffff_ffff_ffed,
+        0xffff_ffff_ffff_ffff,
+        0xffff_ffff_ffff_ffff,
+        0x7fff_ffff_ffff_ffff,
+    ]
+);
+
+/// A square root of $-1$ in the base field.
+const SQRT_M1: Base = Base([
+    0xc4ee_1b27_4a0e_a0b0,
+    0x2f43_1806_ad2f_e478,
+    0x2b4d_0099_3dfb_d7a7,
+    0x2b83_2480_4fc1_df0b,
+]);
+
+impl Base {
+    /// Returns a square root of this element, or `None` if it is not a square.
+    pub fn sqrt(&self) -> Option<Self> {
+        // p = 5 mod 8, so a^((p + 3) / 8) is a square root of either a or -a.
+        let root = self.pow(&[
+            0xffff_ffff_ffff_fffe,
+            0xffff_ffff_ffff_ffff,
+            0xffff_ffff_ffff_ffff,
+            0x0fff_ffff_ffff_ffff,
+        ]);
+        if root.square() == *self {
+            Some(root)
+        } else if root.square() == -*self {
+            Some(root * SQRT_M1)
+        } else {
+            None
+        }
+    }
+
+    /// Returns whether the canonical representative of this element is odd.
+    pub fn is_odd(&self) -> bool {
+        self.0[0] & 1 == 1
+    }
+}
+
+/// An element of the scalar field of edwards25519, whose order
+/// $\ell = 2^{252} + 27742317777372353535851937790883648493$ is the order of the base
+/// point.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Scalar([u64; 4]);
+
+field_element!(
+    Scalar,
+    [
+        0x5812_631a_5cf5_d3ed,
+        0x14de_f9de_a2f7_9cd6,
+        0x0000_0000_0000_0000,
+        0x1000_0000_0000_0000,
+    ]
+);
+
+impl Scalar {
+    /// Returns the scalar congruent to the integer with the given 64-byte
+    /// little-endian encoding, such as a SHA-512 digest.
+    pub fn from_bytes_wide(bytes: &[u8; 64]) -> Self {
+        let radix = Scalar::from_u64(256);
+        bytes.iter().rev().fold(Scalar::zero(), |acc, byte| {
+            acc * radix + Scalar::from_u64(*byte as u64)
+        })
+    }
+}
+
+/// The curve constant $d = -121665 / 121666$.
+pub const D: Base = Base([
+    0x75eb_4dca_1359_78a3,
+    0x0070_0a4d_4141_d8ab,
+    0x8cc7_4079_7779_e898,
+    0x5203_6cee_2b6f_fe73,
+]);
+
+/// A point on edwards25519, in affine coordinates.
+///
+/// The addition law is complete, so the identity $(0, 1)$ needs no special case.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Affine {
+    x: Base,
+    y: Base,
+}
+
+impl Affine {
+    /// Returns the identity.
+    pub fn identity() -> Self {
+        Affine {
+            x: Base::zero(),
+            y: Base::one(),
+        }
+    }
+
+    /// Returns the base point $B$, whose $y$-coordinate is $4 / 5$.
+    pub fn generator() -> Self {
+        Affine {
+            x: Base([
+                0xc956_2d60_8f25_d51a,
+                0x692c_c760_9525_a7b2,
+                0xc0a4_e231_fdd6_dc5c,
+                0x2169_36d3_cd6e_53fe,
+            ]),
+            y: Base([
+                0x6666_6666_6666_6658,
+                0x6666_6666_6666_6666,
+                0x6666_6666_6666_6666,
+                0x6666_6666_6666_6666,
+            ]),
+        }
+    }
+
+    /// Returns the point with the given coordinates, or `None` if it is not on the
+    /// curve.
+    pub fn from_xy(x: Base, y: Base) -> Option<Self> {
+        let (xx, yy) = (x.square(), y.square());
+        if yy - xx == Base::one() + D * xx * yy {
+            Some(Affine { x, y })
+        } else {
+            None
+        }
+    }
+
+    /// The coordinates of this point.
+    pub fn coordinates(&self) -> (Base, Base) {
+        (self.x, self.y)
+    }
+
+    /// Returns whether this point is the identity.
+    pub fn is_identity(&self) -> bool {
+        *self == Affine::identity()
+    }
+
+    /// Returns the point with the given encoding, or `None` if it is not the canonical
+    /// encoding of a point.
+    pub fn from_bytes(bytes: &[u8; 32]) -> Option<Self> {
+        let sign = bytes[31] >> 7 == 1;
+        let mut y = *bytes;
+        y[31] &= 0x7f;
+        let y = Base::from_le_bytes(&y)?;
+
+        // x^2 = (y^2 - 1) / (d y^2 + 1), where the denominator is never zero.
+        let yy = y.square();
+        let x = ((yy - Base::one()) * (D * yy + Base::one()).invert()?).sqrt()?;
+        if x.is_zero() && sign {
+            return None;
+        }
+        let x = if x.is_odd() == sign { x } else { -x };
+        Some(Affine { x, y })
+    }
+
+    /// The encoding of this point.
+    pub fn to_bytes(&self) -> [u8; 32] {
+        let mut bytes = self.y.to_le_bytes();
+        bytes[31] |= (self.x.is_odd() as u8) << 7;
+        bytes
+    }
+
+    /// Returns `[2] self`.
+    pub fn double(&self) -> Self {
+        *self + *self
+    }
+
+    /// Returns `[scalar] self`.
+    pub fn mul(&self, scalar: &Scalar) -> Self {
+        scalar
+            .to_limbs()
+            .iter()
+            .rev()
+            .flat_map(|limb| (0..64).rev().map(move |i| (limb >> i) & 1 == 1))
+            .fold(Affine::identity(), |acc, bit| {
+                let acc = acc.double();
+                if bit {
+                    acc + *self
+                } else {
+                    acc
+                }
+            })
+    }
+}
+
+impl Add for Affine {
+    type Output = Affine;
+
+    fn add(self, other: Affine) -> Affine {
+        let f = D * self.x * other.x * self.y * other.y;
+        let x = (self.x * other.y + self.y * other.x) * (Base::one() + f).invert().unwrap();
+        let y = (self.y * other.y + self.x * other.x) * (Base::one() - f).invert().unwrap();
+        Affine { x, y }
+    }
+}
+
+impl Neg for Affine {
+    type Output = Affine;
+
+    fn neg(self) -> Affine {
+        Affine {
+            x: -self.x,
+            y: self.y,
+        }
+    }
+}
+
+/// An Ed25519 signature.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Signature {
+    /// The nonce point.
+    pub r: Affine,
+    /// The response to the challenge.
+    pub s: Scalar,
+}
+
+impl Signature {
+    /// Returns the signature with the given encoding, or `None` if it is not canonical.
+    pub fn from_bytes(bytes: &[u8; 64]) -> Option<Self> {
+        let r = Affine::from_bytes(bytes[..32].try_into().unwrap())?;
+        let s = Scalar::from_le_bytes(bytes[32..].try_into().unwrap())?;
+        Some(Signature { r, s })
+    }
+
+    /// The encoding of this signature.
+    pub fn to_bytes(&self) -> [u8; 64] {
+        let mut bytes = [0; 64];
+        bytes[..32].copy_from_slice(&self.r.to_bytes());
+        bytes[32..].copy_from_slice(&self.s.to_le_bytes());
+        bytes
+    }
+}
+
+/// Returns the secret scalar and the nonce prefix derived from a secret key.
+fn expand(secret_key: &[u8; 32]) -> (Scalar, [u8; 32]) {
+    let digest = sha512(secret_key);
+    let mut scalar: [u8; 32] = digest[..32].try_into().unwrap();
+    scalar[0] &= 0xf8;
+    scalar[31] &= 0x7f;
+    scalar[31] |= 0x40;
+
+    // The clamped scalar may exceed the group order, which does not change its
+    // multiples of the base point.
+    let mut wide = [0; 64];
+    wide[..32].copy_from_slice(&scalar);
+    (
+        Scalar::from_bytes_wide(&wide),
+        digest[32..].try_into().unwrap(),
+    )
+}
+
+/// Returns the challenge $k = H(R \| A \| M) \bmod \ell$ of a signature with the nonce
+/// point `r` on `message` by `public_key`, where $H$ is SHA-512.
+pub fn challenge(r: &Affine, public_key: &Affine, message: &[u8]) -> Scalar {
+    let mut input = r.to_bytes().to_vec();
+    input.extend_from_slice(&public_key.to_bytes());
+    input.extend_from_slice(message);
+    Scalar::from_bytes_wide(&sha512(&input))
+}
+
+/// Returns the public key $A$ of a secret key.
+pub fn public_key(secret_key: &[u8; 32]) -> Affine {
+    let (scalar, _) = expand(secret_key);
+    Affine::generator().mul(&scalar)
+}
+
+/// Signs `message` with `secret_key`.
+pub fn sign(secret_key: &[u8; 32], message: &[u8]) -> Signature {
+    let (scalar, prefix) = expand(secret_key);
+    let public_key = Affine::generator().mul(&scalar);
+
+    let mut input = prefix.to_vec();
+    input.extend_from_slice(message);
+    let nonce = Scalar::from_bytes_wide(&sha512(&input));
+    let r = Affine::generator().mul(&nonce);
+    let s = nonce + challenge(&r, &public_key, message) * scalar;
+    Signature { r, s }
+}
+
+/// Returns whether `signature` is a valid signature of `message` by `public_key`.
+pub fn verify(public_key: &Affine, message: &[u8], signature: &Signature) -> bool {
+    let k = challenge(&signature.r, public_key, message);
+    Affine::generator().mul(&signature.s) == signature.r + public_key.mul(&k)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryInto;
+
+    use super::{public_key, sign, verify, Affine, Base, Scalar, Signature};
+
+    fn hex(bytes: &str) -> Vec<u8> {
+        (0..bytes.len() / 2)
+            .map(|i| u8::from_str_radix(&bytes[2 * i..2 * i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn field_arithmetic() {
+        let a = Base::from_be_bytes_reduced(&[0xab; 32]);
+        assert_eq!(a * a.invert().unwrap(), Base::one());
+        for a in [a, -a, a + Base::one()].iter() {
+            assert_eq!(
+                a.square().sqrt().map(|root| root.square()),
+                Some(a.square())
+            );
+        }
+        assert_eq!(Base::from_le_bytes(&a.to_le_bytes()), Some(a));
+        assert_eq!(Base::from_le_bytes(&[0xff; 32]), None);
+
+        let mut wide = [0; 64];
+        wide[32] = 1;
+        // 2^256 = 16 * 2^252, and 2^252 < l.
+        assert_eq!(
+            Scalar::from_bytes_wide(&wide),
+            Scalar::from_u64(16) * Scalar::from_limbs([0, 0, 0, 1 << 60]).unwrap()
+        );
+    }
+
+    #[test]
+    fn group_arithmetic() {
+        let b = Affine::generator();
+        let (x, y) = b.coordinates();
+        assert_eq!(Affine::from_xy(x, y), Some(b));
+        assert_eq!(
+            b.to_bytes().to_vec(),
+            hex("5866666666666666666666666666666666666666666666666666666666666666")
+        );
+        assert_eq!(Affine::from_bytes(&b.to_bytes()), Some(b));
+        assert_eq!(Affine::from_bytes(&(-b).to_bytes()), Some(-b));
+
+        assert_eq!(b.mul(&Scalar::from_u64(3)), b.double() + b);
+        assert_eq!(b + -b, Affine::identity());
+        assert_eq!(b + Affine::identity(), b);
+
+        // The base point has order l.
+        assert_eq!(b.mul(&-Scalar::one()), -b);
+    }
+
+    #[test]
+    fn rfc_8032_test_1() {
+        let secret_key: [u8; 32] =
+            hex("9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60")
+                .try_into()
+                .unwrap();
+        let public = public_key(&secret_key);
+        assert_eq!(
+            public.to_bytes().to_vec(),
+            hex("d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a")
+        );
+
+        let signature = sign(&secret_key, b"");
+        let expected: [u8; 64] = hex(
+            "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e065224901555\
+             fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b",
+        )
+        .try_into()
+        .unwrap();
+        assert_eq!(signature.to_bytes(), expected);
+        assert_eq!(Signature::from_bytes(&expected), Some(signature));
+
+        assert!(verify(&public, b"", &signature));
+        assert!(!verify(&public, b"x", &signature));
+        let mut forged = signature;
+        forged.s = forged.s + Scalar::one();
+        assert!(!verify(&public, b"", &forged));
+    }
+}
@@ -0,0 +1,72 @@
+//! Endoscaling, the mapping of Algorithm 1 from the [Halo](https://eprint.iacr.org/2019/1021)
+//! paper.
+//!
+//! A bit string is mapped to a scalar by processing the bits in pairs, from the most
+//! significant pair to the least significant. Each pair selects one of $\pm 1$ or
+//! $\pm \zeta$, where $\zeta$ is [`FieldExt::ZETA`], and the accumulator (initially
+//! $2(\zeta + 1)$) is doubled and added to the selected value. In each pair, the lower
+//! bit selects $\zeta$ and the upper bit selects negation.
+//!
+//! Multiplying a point by this scalar only requires point additions and the curve
+//! endomorphism $(x, y) \mapsto (\zeta_B x, y)$, which is how
+//! [`EccInstructions::mul_endo`](crate::gadget::ecc::EccInstructions::mul_endo) computes
+//! it in-circuit. Verifier challenges are mapped in the same way, so that
+//! [`ChallengeScalar`](crate::transcript::ChallengeScalar) values can be used as the
+//! scalars of such multiplications.
+
+use crate::arithmetic::FieldExt;
+
+/// Returns the scalar encoded by the little-endian bit string `bits`.
+///
+/// # Panics
+///
+/// Panics if `bits` has an odd length.
+pub fn endoscale_scalar<F: FieldExt>(bits: &[bool]) -> F {
+    assert_eq!(bits.len() % 2, 0);
+
+    bits.chunks(2)
+        .rev()
+        .fold((F::ZETA + F::one()).double(), |acc, pair| {
+            let q = if pair[1] { -F::one() } else { F::one() };
+            let q = if pair[0] { q * F::ZETA } else { q };
+            acc.double() + q
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use group::Group;
+
+    use super::endoscale_scalar;
+    use crate::{
+        arithmetic::{CurveExt, FieldExt},
+        pasta::pallas,
+    };
+
+    #[test]
+    fn empty_bits() {
+        assert_eq!(
+            endoscale_scalar::<pallas::Scalar>(&[]),
+            (pallas::Scalar::ZETA + pallas::Scalar::one()).double()
+        );
+    }
+
+    #[test]
+    fn matches_point_endoscaling() {
+        let challenge = 0x0123_4567_89ab_cdef_fedc_ba98_7654_3210u128;
+        let bits: Vec<_> = (0..128).map(|i| (challenge >> i) & 1 == 1).collect();
+
+        // Algorithm 1, using the curve endomorphism instead of scalar multiplication.
+        let p = pallas::Point::generator();
+        let acc = bits
+            .chunks(2)
+            .rev()
+            .fold((p.endo() + p).double(), |acc, pair| {
+                let s = if pair[1] { -p } else { p };
+                let s = if pair[0] { s.endo() } else { s };
+                acc + s + acc
+            });
+
+        assert_eq!(p * endoscale_scalar::<pallas::Scalar>(&bits), acc);
+    }
+}
@@ -0,0 +1,178 @@
+//! The Keccak-f\[1600\] permutation and the Keccak-256 hash function.
+//!
+//! Keccak-256 is the original Keccak submission with a capacity of 512 bits, as used by
+//! Ethereum. It differs from SHA3-256 only in the domain separation of its padding.
+//!
+//! The state is 25 lanes of 64 bits, with the lane at position $(x, y)$ at index
+//! $x + 5y$. Bytes are absorbed into and squeezed from the lanes in little-endian order.
+
+use std::convert::TryInto;
+
+/// The number of lanes in the Keccak-f\[1600\] state.
+pub const LANES: usize = 25;
+
+/// The number of rounds of Keccak-f\[1600\].
+pub const ROUNDS: usize = 24;
+
+/// The number of lanes absorbed per permutation by Keccak-256.
+pub const RATE: usize = 17;
+
+/// The number of lanes in a Keccak-256 digest.
+pub const DIGEST_LANES: usize = 4;
+
+/// The Keccak-f\[1600\] state.
+pub type State = [u64; LANES];
+
+/// The rotation offsets of the ρ step, indexed by $x$ and then $y$.
+pub const ROTATIONS: [[u32; 5]; 5] = [
+    [0, 36, 3, 41, 18],
+    [1, 44, 10, 45, 2],
+    [62, 6, 43, 15, 61],
+    [28, 55, 25, 21, 56],
+    [27, 20, 39, 8, 14],
+];
+
+/// The round constants of the ι step.
+pub const ROUND_CONSTANTS: [u64; ROUNDS] = [
+    0x0000_0000_0000_0001,
+    0x0000_0000_0000_8082,
+    0x8000_0000_0000_808a,
+    0x8000_0000_8000_8000,
+    0x0000_0000_0000_808b,
+    0x0000_0000_8000_0001,
+    0x8000_0000_8000_8081,
+    0x8000_0000_0000_8009,
+    0x0000_0000_0000_008a,
+    0x0000_0000_0000_0088,
+    0x0000_0000_8000_8009,
+    0x0000_0000_8000_000a,
+    0x0000_0000_8000_808b,
+    0x8000_0000_0000_008b,
+    0x8000_0000_0000_8089,
+    0x8000_0000_0000_8003,
+    0x8000_0000_0000_8002,
+    0x8000_0000_0000_0080,
+    0x0000_0000_0000_800a,
+    0x8000_0000_8000_000a,
+    0x8000_0000_8000_8081,
+    0x8000_0000_0000_8080,
+    0x0000_0000_8000_0001,
+    0x8000_0000_8000_8008,
+];
+
+/// Returns the index of the lane to which the π step moves the lane at $(x, y)$.
+pub fn pi(x: usize, y: usize) -> usize {
+    y + 5 * ((2 * x + 3 * y) % 5)
+}
+
+/// Runs the Keccak-f\[1600\] permutation on the given state.
+pub fn keccak_f(state: &mut State) {
+    for rc in ROUND_CONSTANTS.iter() {
+        // θ
+        let mut c = [0u64; 5];
+        for (x, c) in c.iter_mut().enumerate() {
+            *c = (0..5).fold(0, |acc, y| acc ^ state[x + 5 * y]);
+        }
+        for x in 0..5 {
+            let d = c[(x + 4) % 5] ^ c[(x + 1) % 5].rotate_left(1);
+            for y in 0..5 {
+                state[x + 5 * y] ^= d;
+            }
+        }
+
+        // ρ and π
+        let mut b = [0u64; LANES];
+        for x in 0..5 {
+            for y in 0..5 {
+                b[pi(x, y)] = state[x + 5 * y].rotate_left(ROTATIONS[x][y]);
+            }
+        }
+
+        // χ
+        for x in 0..5 {
+            for y in 0..5 {
+                state[x + 5 * y] =
+                    b[x + 5 * y] ^ (!b[(x + 1) % 5 + 5 * y] & b[(x + 2) % 5 + 5 * y]);
+            }
+        }
+
+        // ι
+        state[0] ^= rc;
+    }
+}
+
+/// Returns the padding lanes for a message of `len` whole lanes, given the first
+/// padding byte.
+pub(crate) fn padding(len: usize, domain: u8) -> Vec<u64> {
+    let mut padding = vec![0; RATE - len % RATE];
+    padding[0] = domain as u64;
+    *padding.last_mut().unwrap() |= 0x80 << 56;
+    padding
+}
+
+fn sponge(message: &[u8], domain: u8) -> [u8; 32] {
+    let mut bytes = message.to_vec();
+    bytes.resize(message.len() + RATE * 8 - message.len() % (RATE * 8), 0);
+    bytes[message.len()] = domain;
+    *bytes.last_mut().unwrap() |= 0x80;
+
+    let mut state = [0u64; LANES];
+    for block in bytes.chunks(RATE * 8) {
+        for (lane, chunk) in state.iter_mut().zip(block.chunks(8)) {
+            *lane ^= u64::from_le_bytes(chunk.try_into().unwrap());
+        }
+        keccak_f(&mut state);
+    }
+
+    let mut digest = [0u8; 32];
+    for (chunk, lane) in digest.chunks_mut(8).zip(state.iter()) {
+        chunk.copy_from_slice(&lane.to_le_bytes());
+    }
+    digest
+}
+
+/// Returns the Keccak-256 hash of the given message.
+pub fn keccak256(message: &[u8]) -> [u8; 32] {
+    sponge(message, 0x01)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryInto;
+
+    use super::{keccak256, padding, sponge, RATE};
+
+    fn hex(bytes: &str) -> [u8; 32] {
+        (0..32)
+            .map(|i| u8::from_str_radix(&bytes[2 * i..2 * i + 2], 16).unwrap())
+            .collect::<Vec<_>>()
+            .try_into()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_vectors() {
+        assert_eq!(
+            keccak256(b""),
+            hex("c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470")
+        );
+        assert_eq!(
+            keccak256(b"abc"),
+            hex("4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45")
+        );
+
+        // SHA3-256 uses the same permutation and rate; this exercises multiple blocks.
+        let message: Vec<u8> = (0..200).collect();
+        assert_eq!(
+            sponge(&message, 0x06),
+            hex("5f728f63bf5ee48c77f453c0490398fa645b8d4c4e56be9a41cfec344d6ca899")
+        );
+    }
+
+    #[test]
+    fn lane_padding() {
+        assert_eq!(padding(0, 0x01).len(), RATE);
+        assert_eq!(padding(RATE - 1, 0x01), vec![0x8000_0000_0000_0001]);
+        assert_eq!(padding(RATE + 2, 0x01).len(), RATE - 2);
+    }
+}
@@ -0,0 +1,149 @@
+//! The MiMC and GMiMC permutations.
+//!
+//! GMiMC with an expanding round function (GMiMC-erf) is a generalized Feistel network
+//! over `T` branches: each round computes $f = (x_0 + c_i)^\alpha$, adds $f$ to every
+//! other branch, and rotates the branches by one. With `T = 2`, this is exactly the
+//! MiMC-2p/p Feistel network, provided here as [`MimcFeistel`].
+//!
+//! Each round applies a single S-box, so these permutations need many more rounds than
+//! Poseidon, but have a very small number of constraints per round. They can be used
+//! directly as permutations ("Feistel mode"), or in the same sponge construction as
+//! [`crate::primitives::poseidon`] via [`hash`] ("sponge mode"). The same modes are
+//! available in-circuit via [`crate::gadget::mimc::MimcChip`].
+//!
+//! The S-box exponent is [`FieldExt::RESCUE_ALPHA`], which is chosen so that
+//! $x^\alpha$ is a permutation of the field.
+//!
+//! # Constants
+//!
+//! The round constants are sampled from the Grain LFSR (as the constants of a Poseidon
+//! instance with no full rounds, and one partial round per MiMC round), so outputs do
+//! not match other MiMC implementations.
+
+use std::fmt;
+
+use crate::arithmetic::FieldExt;
+use crate::primitives::poseidon::{grain::Grain, sponge_hash, Domain, SboxType, State};
+
+/// A specification for a GMiMC-erf permutation with `T` branches.
+///
+/// When used in a sponge, `RATE` elements are absorbed or squeezed per invocation of
+/// the permutation.
+pub trait MimcSpec<F: FieldExt, const T: usize, const RATE: usize>: fmt::Debug {
+    /// The number of rounds for this specification.
+    fn rounds() -> usize;
+
+    /// Generates the round constants for this specification, one per round.
+    fn constants() -> Vec<F> {
+        let mut grain = Grain::new(SboxType::Pow, T as u16, 0, Self::rounds() as u16);
+        (0..Self::rounds())
+            .map(|_| grain.next_field_element())
+            .collect()
+    }
+}
+
+/// The MiMC-2p/p Feistel permutation, with a rate of one field element.
+///
+/// This uses $2 \lceil \log_\alpha p \rceil$ rounds, as recommended by the MiMC paper.
+#[derive(Debug)]
+pub struct MimcFeistel;
+
+impl<F: FieldExt> MimcSpec<F, 2, 1> for MimcFeistel {
+    fn rounds() -> usize {
+        2 * (F::NUM_BITS as f64 / (F::RESCUE_ALPHA as f64).log2()).ceil() as usize
+    }
+}
+
+/// Runs the GMiMC-erf permutation on the given state.
+pub fn permute<F: FieldExt, S: MimcSpec<F, T, RATE>, const T: usize, const RATE: usize>(
+    state: &mut State<F, T>,
+    round_constants: &[F],
+) {
+    assert!(T >= 2);
+
+    for rc in round_constants {
+        let f = (state[0] + rc).pow_vartime([F::RESCUE_ALPHA, 0, 0, 0]);
+        for word in state.iter_mut().skip(1) {
+            *word += f;
+        }
+        state.rotate_left(1);
+    }
+}
+
+/// Hashes the given message within the given domain, using the same sponge
+/// construction as [`crate::primitives::poseidon::Hash`].
+pub fn hash<
+    F: FieldExt,
+    S: MimcSpec<F, T, RATE>,
+    D: Domain<F, RATE>,
+    const T: usize,
+    const RATE: usize,
+>(
+    domain: D,
+    message: &[F],
+) -> F {
+    let round_constants = S::constants();
+    sponge_hash(domain, message, |state| {
+        permute::<F, S, T, RATE>(state, &round_constants)
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use ff::Field;
+
+    use super::{hash, permute, MimcFeistel, MimcSpec};
+    use crate::{
+        arithmetic::FieldExt,
+        pasta::Fp,
+        primitives::poseidon::{ConstantLength, Domain, VariableLength},
+    };
+
+    /// A width-3 GMiMC specification, used to exercise wider permutations. The number
+    /// of rounds is not a vetted security parameter.
+    #[derive(Debug)]
+    pub(crate) struct GmimcT3;
+
+    impl<F: FieldExt> MimcSpec<F, 3, 2> for GmimcT3 {
+        fn rounds() -> usize {
+            <MimcFeistel as MimcSpec<F, 2, 1>>::rounds() + 3
+        }
+    }
+
+    #[test]
+    fn feistel_rounds() {
+        assert_eq!(<MimcFeistel as MimcSpec<Fp, 2, 1>>::rounds(), 220);
+        assert_eq!(<MimcFeistel as MimcSpec<Fp, 2, 1>>::constants().len(), 220);
+    }
+
+    #[test]
+    fn feistel_network() {
+        // A single round is a Feistel step: (x_L, x_R) -> (x_R + (x_L + c)^5, x_L).
+        let (x_l, x_r, c) = (Fp::from_u64(3), Fp::from_u64(7), Fp::from_u64(11));
+        let mut state = [x_l, x_r];
+        permute::<_, MimcFeistel, 2, 1>(&mut state, &[c]);
+        assert_eq!(state, [x_r + (x_l + c).pow_vartime([5, 0, 0, 0]), x_l]);
+    }
+
+    #[test]
+    fn hash_is_consistent() {
+        let round_constants = <GmimcT3 as MimcSpec<Fp, 3, 2>>::constants();
+
+        let message = [Fp::from_u64(1), Fp::from_u64(2)];
+        let mut state = [
+            message[0],
+            message[1],
+            Domain::<Fp, 2>::initial_capacity_element(&ConstantLength::<2>),
+        ];
+        permute::<_, GmimcT3, 3, 2>(&mut state, &round_constants);
+        assert_eq!(
+            hash::<_, GmimcT3, _, 3, 2>(ConstantLength::<2>, &message),
+            state[0]
+        );
+
+        assert_ne!(
+            hash::<_, MimcFeistel, _, 2, 1>(VariableLength, &message),
+            hash::<_, MimcFeistel, _, 2, 1>(VariableLength, &message[..1])
+        );
+    }
+}
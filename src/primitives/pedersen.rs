@@ -0,0 +1,68 @@
+//! Pedersen commitments to scalars.
+//!
+//! A commitment to a value $v$ with the blinding factor $r$ is $\[v\] G + \[r\] H$,
+//! where the generators $G$ and $H$ of a [`CommitmentKey`] are derived by hashing to
+//! the curve within a domain, so that no discrete logarithm relation between them is
+//! known. Commitments are additively homomorphic: the sum of the commitments to $v_1$
+//! and $v_2$ is a commitment to $v_1 + v_2$ with the sum of their blinding factors.
+
+use group::Curve;
+
+use crate::arithmetic::{CurveAffine, CurveExt};
+
+/// The generators of Pedersen commitments within a domain.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CommitmentKey<C: CurveAffine> {
+    g: C,
+    h: C,
+}
+
+impl<C: CurveAffine> CommitmentKey<C> {
+    /// Derives the generators for the domain `domain_prefix`.
+    pub fn new(domain_prefix: &str) -> Self {
+        let hasher = C::CurveExt::hash_to_curve(domain_prefix);
+        CommitmentKey {
+            g: hasher(b"g").to_affine(),
+            h: hasher(b"h").to_affine(),
+        }
+    }
+
+    /// The generator $G$ for values.
+    pub fn g(&self) -> C {
+        self.g
+    }
+
+    /// The generator $H$ for blinding factors.
+    pub fn h(&self) -> C {
+        self.h
+    }
+
+    /// Returns the commitment $\[v\] G + \[r\] H$ to `value` with the blinding factor
+    /// `blind`.
+    pub fn commit(&self, value: &C::Scalar, blind: &C::Scalar) -> C {
+        (self.g * *value + self.h * *blind).to_affine()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use group::Curve;
+
+    use super::CommitmentKey;
+    use crate::{arithmetic::FieldExt, pasta::pallas};
+
+    #[test]
+    fn homomorphic() {
+        let key = CommitmentKey::<pallas::Affine>::new("halo2:test-pedersen");
+        assert_ne!(key.g(), key.h());
+
+        let (v1, r1) = (pallas::Scalar::from_u64(3), pallas::Scalar::from_u64(11));
+        let (v2, r2) = (pallas::Scalar::from_u64(4), pallas::Scalar::from_u64(20));
+        let sum = (key.commit(&v1, &r1) + key.commit(&v2, &r2)).to_affine();
+        assert_eq!(sum, key.commit(&(v1 + v2), &(r1 + r2)));
+        assert_ne!(key.commit(&v1, &r1), key.commit(&v1, &r2));
+
+        let other = CommitmentKey::<pallas::Affine>::new("halo2:test-pedersen-other");
+        assert_ne!(key.commit(&v1, &r1), other.commit(&v1, &r1));
+    }
+}
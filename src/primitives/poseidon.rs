@@ -0,0 +1,976 @@
+//! The Poseidon algebraic hash function.
+//!
+//! This module implements the Poseidon permutation for any width, together with a
+//! duplex sponge construction over it that can hash arbitrary-length sequences of field
+//! elements.
+//!
+//! These are the same constants and constructions used by the Poseidon chips in
+//! [`crate::gadget::poseidon`], so values computed here (for example with [`hash`]) can
+//! be used as public inputs to circuits that compute the same hash in-circuit.
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt;
+use std::iter;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+
+use lazy_static::lazy_static;
+
+use crate::arithmetic::FieldExt;
+
+pub(crate) mod grain;
+pub(crate) mod mds;
+
+pub mod encryption;
+pub mod export;
+pub(crate) mod fp;
+pub(crate) mod fq;
+pub mod merkle;
+mod p128pow5t3;
+pub mod poseidon2;
+pub mod rounds;
+pub mod sparse;
+pub use p128pow5t3::P128Pow5T3;
+pub use poseidon2::Poseidon2Pow5T3;
+
+pub use grain::SboxType;
+
+/// The type used to hold permutation state.
+pub type State<F, const T: usize> = [F; T];
+
+/// The type used to hold sponge rate.
+pub type SpongeState<F, const RATE: usize> = [Option<F>; RATE];
+
+/// The type used to hold the MDS matrix and its inverse.
+pub type Mds<F, const T: usize> = [[F; T]; T];
+
+/// The permutation instantiated by a [`Spec`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PermutationType {
+    /// The original Poseidon permutation, using a single MDS matrix.
+    Poseidon,
+    /// The [Poseidon2](poseidon2) permutation, using separate linear layers for the
+    /// full (external) and partial (internal) rounds.
+    Poseidon2,
+}
+
+/// The convention used to generate the constants for a [`PermutationType::Poseidon`]
+/// specification.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ParameterMode {
+    /// Round constants and a secure MDS matrix sampled from the Grain LFSR, following
+    /// the Poseidon reference implementation.
+    Standard,
+    /// The parameters used by the [neptune](https://github.com/filecoin-project/neptune)
+    /// crate (and Filecoin).
+    ///
+    /// The round constants are sampled from the Grain LFSR as in
+    /// [`ParameterMode::Standard`], but the MDS matrix is the fixed Cauchy matrix
+    /// $M_{ij} = 1/(i + T + j)$, which neptune applies to the state as a row vector.
+    /// [`Spec::constants`] returns it transposed, so that [`permute`] matches neptune's
+    /// permutation exactly.
+    ///
+    /// Note that neptune places the capacity element at the start of the state, which
+    /// differs from the layout used by [`Duplex`]; reproducing neptune's hashes
+    /// requires calling [`permute`] directly with neptune's state layout.
+    Neptune,
+}
+
+/// A specification for a Poseidon permutation.
+///
+/// The permutation has a width of `T` field elements. When used in a sponge, `RATE`
+/// elements are absorbed or squeezed per invocation of the permutation, and the
+/// remaining `T - RATE` elements form the capacity.
+pub trait Spec<F: FieldExt, const T: usize, const RATE: usize>: fmt::Debug {
+    /// The number of full rounds for this specification.
+    ///
+    /// This must be an even number.
+    fn full_rounds() -> usize;
+
+    /// The number of partial rounds for this specification.
+    fn partial_rounds() -> usize;
+
+    /// The S-box for this specification.
+    fn sbox(val: F) -> F;
+
+    /// The type of S-box used by [`Spec::sbox`].
+    ///
+    /// This determines the round constants generated by the default implementation of
+    /// [`Spec::constants`], and which chips can be configured with this specification.
+    fn sbox_type() -> SboxType {
+        SboxType::Pow
+    }
+
+    /// The permutation that this specification instantiates.
+    ///
+    /// This determines the meaning of the matrices returned by [`Spec::constants`],
+    /// and which chips can be configured with this specification.
+    fn permutation_type() -> PermutationType {
+        PermutationType::Poseidon
+    }
+
+    /// The convention used by the default implementation of [`Spec::constants`].
+    ///
+    /// [`ParameterMode::Neptune`] is only defined for [`PermutationType::Poseidon`]
+    /// with [`SboxType::Pow`].
+    fn parameter_mode() -> ParameterMode {
+        ParameterMode::Standard
+    }
+
+    /// The S-box exponent used to check the security of this specification.
+    ///
+    /// The default implementation assumes that [`SboxType::Pow`] is $x^5$.
+    fn alpha() -> rounds::Alpha {
+        Self::sbox_type().into()
+    }
+
+    /// The security level, in bits, that the round numbers of this specification must
+    /// provide.
+    fn security_level() -> usize {
+        128
+    }
+
+    /// Side-loaded index of the first correct and secure MDS that will be generated by
+    /// the reference implementation.
+    ///
+    /// This is used by the default implementation of [`Spec::constants`], which skips
+    /// this many matrices, and then resamples until it obtains a matrix that passes the
+    /// reference implementation's security checks. If you are hard-coding the constants,
+    /// you may leave this unimplemented.
+    fn secure_mds() -> usize;
+
+    /// Generates `(round_constants, mds, mds^-1)` corresponding to this specification.
+    ///
+    /// For [`PermutationType::Poseidon2`], this instead returns
+    /// `(round_constants, external_matrix, internal_matrix)`.
+    ///
+    /// The default implementation runs the Grain LFSR the first time it is called for
+    /// a given set of parameters, and caches the result for subsequent calls.
+    /// Specifications with hard-coded constants should override this.
+    ///
+    /// # Panics
+    ///
+    /// The default implementation panics if the round numbers of this specification do
+    /// not provide [`Spec::security_level`] bits of security, as determined by
+    /// [`rounds::check_spec`].
+    fn constants() -> (Vec<[F; T]>, Mds<F, T>, Mds<F, T>)
+    where
+        Self: Sized,
+    {
+        if let Err(e) = rounds::check_spec::<F, Self, T, RATE>() {
+            panic!(
+                "insecure Poseidon specification {}: {}",
+                std::any::type_name::<Self>(),
+                e
+            );
+        }
+        generate_constants::<F, T>(
+            Self::permutation_type(),
+            Self::parameter_mode(),
+            Self::sbox_type(),
+            Self::full_rounds(),
+            Self::partial_rounds(),
+            Self::secure_mds(),
+        )
+    }
+}
+
+/// The parameters that uniquely determine the constants generated by the Grain LFSR.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct ConstantsKey {
+    field: TypeId,
+    permutation: PermutationType,
+    mode: ParameterMode,
+    sbox: SboxType,
+    width: usize,
+    full_rounds: usize,
+    partial_rounds: usize,
+    secure_mds: usize,
+}
+
+lazy_static! {
+    static ref CONSTANTS: Mutex<HashMap<ConstantsKey, Arc<dyn Any + Send + Sync>>> =
+        Mutex::new(HashMap::new());
+}
+
+type Constants<F, const T: usize> = (Vec<[F; T]>, Mds<F, T>, Mds<F, T>);
+
+/// Generates the round constants and MDS matrices for the given parameters, caching
+/// them for subsequent calls with the same parameters.
+fn generate_constants<F: FieldExt, const T: usize>(
+    permutation: PermutationType,
+    mode: ParameterMode,
+    sbox: SboxType,
+    r_f: usize,
+    r_p: usize,
+    secure_mds: usize,
+) -> Constants<F, T> {
+    let key = ConstantsKey {
+        field: TypeId::of::<F>(),
+        permutation,
+        mode,
+        sbox,
+        width: T,
+        full_rounds: r_f,
+        partial_rounds: r_p,
+        secure_mds,
+    };
+
+    let cached = CONSTANTS.lock().unwrap().get(&key).cloned();
+    let constants = match cached {
+        Some(constants) => constants,
+        None => {
+            // Generate the constants without holding the lock; if another thread races
+            // us, both will produce identical constants.
+            let constants: Arc<dyn Any + Send + Sync> = Arc::new(match permutation {
+                PermutationType::Poseidon => {
+                    generate_constants_uncached::<F, T>(mode, sbox, r_f, r_p, secure_mds)
+                }
+                PermutationType::Poseidon2 => {
+                    assert_eq!(mode, ParameterMode::Standard);
+                    poseidon2::generate_constants::<F, T>(sbox, r_f, r_p)
+                }
+            });
+            CONSTANTS
+                .lock()
+                .unwrap()
+                .entry(key)
+                .or_insert(constants)
+                .clone()
+        }
+    };
+
+    constants
+        .downcast_ref::<Constants<F, T>>()
+        .expect("keyed by field type and width")
+        .clone()
+}
+
+/// Generates the round constants and MDS matrices for the given parameters by running
+/// the Grain LFSR.
+fn generate_constants_uncached<F: FieldExt, const T: usize>(
+    mode: ParameterMode,
+    sbox: SboxType,
+    r_f: usize,
+    r_p: usize,
+    secure_mds: usize,
+) -> Constants<F, T> {
+    let mut grain = grain::Grain::new(sbox, T as u16, r_f as u16, r_p as u16);
+
+    let round_constants = (0..(r_f + r_p))
+        .map(|_| {
+            let mut rc_row = [F::zero(); T];
+            for (rc, value) in rc_row
+                .iter_mut()
+                .zip((0..T).map(|_| grain.next_field_element()))
+            {
+                *rc = value;
+            }
+            rc_row
+        })
+        .collect();
+
+    let (mds, mds_inv) = match mode {
+        ParameterMode::Standard => mds::generate_mds(&mut grain, secure_mds),
+        ParameterMode::Neptune => {
+            assert_eq!(sbox, SboxType::Pow);
+            mds::generate_neptune_mds()
+        }
+    };
+
+    (round_constants, mds, mds_inv)
+}
+
+/// Generates round constants for a Poseidon-like permutation of width `T` over
+/// GF(2^n), where `n = field_bits`, by running the Grain LFSR in binary-field mode.
+///
+/// Each element is represented as a `u128`, where bit `i` is the coefficient of $x^i$
+/// in the polynomial basis, so `field_bits` must be at most 128. The constants are
+/// independent of the choice of irreducible polynomial.
+pub fn generate_binary_round_constants<const T: usize>(
+    field_bits: u16,
+    sbox: SboxType,
+    r_f: usize,
+    r_p: usize,
+) -> Vec<[u128; T]> {
+    let mut grain = grain::Grain::new_binary(field_bits, sbox, T as u16, r_f as u16, r_p as u16);
+
+    (0..(r_f + r_p))
+        .map(|_| {
+            let mut rc_row = [0; T];
+            for rc in rc_row.iter_mut() {
+                *rc = grain.next_binary_element();
+            }
+            rc_row
+        })
+        .collect()
+}
+
+/// Runs the Poseidon permutation on the given state.
+///
+/// This is only valid for [`PermutationType::Poseidon`] specifications; use
+/// [`poseidon2::permute`] for Poseidon2.
+pub fn permute<F: FieldExt, S: Spec<F, T, RATE>, const T: usize, const RATE: usize>(
+    state: &mut State<F, T>,
+    mds: &Mds<F, T>,
+    round_constants: &[[F; T]],
+) {
+    let r_f = S::full_rounds() / 2;
+    let r_p = S::partial_rounds();
+
+    let apply_mds = |state: &mut State<F, T>| {
+        let mut new_state = [F::zero(); T];
+        // Matrix multiplication
+        #[allow(clippy::needless_range_loop)]
+        for i in 0..T {
+            for j in 0..T {
+                new_state[i] += mds[i][j] * state[j];
+            }
+        }
+        *state = new_state;
+    };
+
+    let full_round = |state: &mut State<F, T>, rcs: &[F; T]| {
+        for (word, rc) in state.iter_mut().zip(rcs.iter()) {
+            *word = S::sbox(*word + rc);
+        }
+        apply_mds(state);
+    };
+
+    let part_round = |state: &mut State<F, T>, rcs: &[F; T]| {
+        for (word, rc) in state.iter_mut().zip(rcs.iter()) {
+            *word += rc;
+        }
+        // In a partial round, the S-box is only applied to the first state word.
+        state[0] = S::sbox(state[0]);
+        apply_mds(state);
+    };
+
+    let mut round_constants = round_constants.iter();
+    for _ in 0..r_f {
+        full_round(state, round_constants.next().unwrap());
+    }
+    for _ in 0..r_p {
+        part_round(state, round_constants.next().unwrap());
+    }
+    for _ in 0..r_f {
+        full_round(state, round_constants.next().unwrap());
+    }
+}
+
+/// Adds the given input to the rate portion of the state, runs the permutation, and
+/// returns the rate portion of the resulting state.
+///
+/// Missing input words are treated as zero.
+fn poseidon_duplex<F: FieldExt, S: Spec<F, T, RATE>, const T: usize, const RATE: usize>(
+    state: &mut State<F, T>,
+    input: &SpongeState<F, RATE>,
+    constants: &Constants<F, T>,
+) -> SpongeState<F, RATE> {
+    // `Iterator::zip` short-circuits when one iterator completes, so this will only
+    // mutate the rate portion of the state.
+    for (word, value) in state.iter_mut().zip(input.iter()) {
+        if let Some(value) = value {
+            *word += value;
+        }
+    }
+
+    let (round_constants, m_a, m_b) = constants;
+    match S::permutation_type() {
+        PermutationType::Poseidon => permute::<F, S, T, RATE>(state, m_a, round_constants),
+        PermutationType::Poseidon2 => {
+            poseidon2::permute::<F, S, T, RATE>(state, m_a, m_b, round_constants)
+        }
+    }
+
+    let mut output = [None; RATE];
+    for (word, value) in output.iter_mut().zip(state.iter()) {
+        *word = Some(*value);
+    }
+    output
+}
+
+#[derive(Clone, Debug)]
+enum Sponge<F, const RATE: usize> {
+    Absorbing(SpongeState<F, RATE>),
+    Squeezing(SpongeState<F, RATE>),
+}
+
+impl<F: Copy, const RATE: usize> Sponge<F, RATE> {
+    fn absorb(val: F) -> Self {
+        let mut input = [None; RATE];
+        input[0] = Some(val);
+        Sponge::Absorbing(input)
+    }
+}
+
+/// A Poseidon duplex sponge.
+///
+/// Field elements can be absorbed and squeezed in any order. Squeezing after absorbing
+/// fewer than `RATE` elements treats the missing elements as zero, so callers that
+/// need an injective encoding of variable-length input must pad it themselves (as
+/// [`Hash`](struct@Hash) does).
+#[derive(Debug)]
+pub struct Duplex<F: FieldExt, S: Spec<F, T, RATE>, const T: usize, const RATE: usize> {
+    sponge: Sponge<F, RATE>,
+    state: State<F, T>,
+    constants: Constants<F, T>,
+    _marker: PhantomData<S>,
+}
+
+// Implemented manually so that `S` doesn't need to be `Clone`.
+impl<F: FieldExt, S: Spec<F, T, RATE>, const T: usize, const RATE: usize> Clone
+    for Duplex<F, S, T, RATE>
+{
+    fn clone(&self) -> Self {
+        Duplex {
+            sponge: self.sponge.clone(),
+            state: self.state,
+            constants: self.constants.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F: FieldExt, S: Spec<F, T, RATE>, const T: usize, const RATE: usize> Duplex<F, S, T, RATE> {
+    /// Constructs a new duplex sponge for the given Poseidon specification, with the
+    /// capacity portion of the state set to `initial_capacity_element`.
+    pub fn new(initial_capacity_element: F) -> Self {
+        let constants = S::constants();
+
+        let input = [None; RATE];
+        let mut state = [F::zero(); T];
+        state[RATE] = initial_capacity_element;
+
+        Duplex {
+            sponge: Sponge::Absorbing(input),
+            state,
+            constants,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Absorbs an element into the sponge.
+    pub fn absorb(&mut self, value: F) {
+        match self.sponge {
+            Sponge::Absorbing(ref mut input) => {
+                for entry in input.iter_mut() {
+                    if entry.is_none() {
+                        *entry = Some(value);
+                        return;
+                    }
+                }
+
+                // We've already absorbed as many elements as we can
+                let _ = poseidon_duplex::<F, S, T, RATE>(&mut self.state, input, &self.constants);
+                self.sponge = Sponge::absorb(value);
+            }
+            Sponge::Squeezing(_) => {
+                // Drop the remaining output elements
+                self.sponge = Sponge::absorb(value);
+            }
+        }
+    }
+
+    /// Squeezes an element from the sponge.
+    pub fn squeeze(&mut self) -> F {
+        loop {
+            match self.sponge {
+                Sponge::Absorbing(ref input) => {
+                    self.sponge = Sponge::Squeezing(poseidon_duplex::<F, S, T, RATE>(
+                        &mut self.state,
+                        input,
+                        &self.constants,
+                    ));
+                }
+                Sponge::Squeezing(ref mut output) => {
+                    for entry in output.iter_mut() {
+                        if let Some(e) = entry.take() {
+                            return e;
+                        }
+                    }
+
+                    // We've already squeezed out all available elements
+                    self.sponge = Sponge::Absorbing([None; RATE]);
+                }
+            }
+        }
+    }
+}
+
+/// A domain in which a Poseidon hash function is being used.
+///
+/// The domain determines the initial capacity element of the sponge, and the padding
+/// applied to the input. Hashes computed in different domains with the same [`Spec`]
+/// are therefore independent of each other.
+///
+/// The capacity elements of the domains in this module are of the form
+/// `tag * 2^120 + param * 2^64`, where `tag` identifies the domain and `param` is a
+/// domain-specific parameter (less than `2^56`). This generalizes the encoding in
+/// section 4.2 of the Poseidon paper, which [`VariableLength`] uses unchanged.
+pub trait Domain<F: FieldExt, const RATE: usize>: Copy + fmt::Debug {
+    /// The initial capacity element, encoding this domain.
+    fn initial_capacity_element(&self) -> F;
+
+    /// Returns the padding to be appended to an input of `input_len` field elements.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this domain does not accept inputs of length `input_len`.
+    fn padding(&self, input_len: usize) -> Vec<F>;
+}
+
+fn domain_capacity_element<F: FieldExt>(tag: u128, param: u128) -> F {
+    assert!(param < 1 << 56);
+    F::from_u128((tag << 120) | (param << 64))
+}
+
+/// Returns the initial capacity element of the duplex sponge used by
+/// [`crate::transcript::PoseidonRead`] and [`crate::transcript::PoseidonWrite`].
+pub fn transcript_capacity_element<F: FieldExt>() -> F {
+    domain_capacity_element(4, 0)
+}
+
+/// A domain for hashing inputs of any length.
+///
+/// Input is padded with a single `1` followed by the minimal number of `0`s needed to
+/// reach a multiple of `RATE`, so that inputs of different lengths cannot collide.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct VariableLength;
+
+impl<F: FieldExt, const RATE: usize> Domain<F, RATE> for VariableLength {
+    fn initial_capacity_element(&self) -> F {
+        domain_capacity_element(0, 1)
+    }
+
+    fn padding(&self, input_len: usize) -> Vec<F> {
+        padding::<F, RATE>(input_len).collect()
+    }
+}
+
+/// A domain for hashing inputs of exactly `L` field elements.
+///
+/// The length is encoded in the capacity element, so the input only needs to be padded
+/// with `0`s to a multiple of `RATE`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ConstantLength<const L: usize>;
+
+impl<F: FieldExt, const RATE: usize, const L: usize> Domain<F, RATE> for ConstantLength<L> {
+    fn initial_capacity_element(&self) -> F {
+        domain_capacity_element(1, L as u128)
+    }
+
+    fn padding(&self, input_len: usize) -> Vec<F> {
+        assert_eq!(input_len, L);
+        vec![F::zero(); (RATE - L % RATE) % RATE]
+    }
+}
+
+/// A domain for hashing the `RATE` children of a node at the given layer of a Merkle
+/// tree.
+///
+/// Including the layer prevents the hash of a node from being used as the hash of a
+/// node at a different height.
+#[derive(Clone, Copy, Debug)]
+pub struct MerkleNode {
+    /// The layer of the node being computed, where the parents of the leaves are at
+    /// layer 0.
+    pub layer: u32,
+}
+
+impl<F: FieldExt, const RATE: usize> Domain<F, RATE> for MerkleNode {
+    fn initial_capacity_element(&self) -> F {
+        domain_capacity_element(2, self.layer.into())
+    }
+
+    fn padding(&self, input_len: usize) -> Vec<F> {
+        assert_eq!(input_len, RATE);
+        vec![]
+    }
+}
+
+/// A Poseidon hash function, used within the given [`Domain`].
+#[derive(Debug)]
+pub struct Hash<
+    F: FieldExt,
+    S: Spec<F, T, RATE>,
+    D: Domain<F, RATE>,
+    const T: usize,
+    const RATE: usize,
+> {
+    duplex: Duplex<F, S, T, RATE>,
+    domain: D,
+    len: usize,
+}
+
+impl<
+        F: FieldExt,
+        S: Spec<F, T, RATE>,
+        D: Domain<F, RATE> + Default,
+        const T: usize,
+        const RATE: usize,
+    > Default for Hash<F, S, D, T, RATE>
+{
+    fn default() -> Self {
+        Self::init(D::default())
+    }
+}
+
+impl<F: FieldExt, S: Spec<F, T, RATE>, D: Domain<F, RATE>, const T: usize, const RATE: usize>
+    Hash<F, S, D, T, RATE>
+{
+    /// Initializes a new hasher for the given domain.
+    pub fn init(domain: D) -> Self {
+        Hash {
+            duplex: Duplex::new(domain.initial_capacity_element()),
+            domain,
+            len: 0,
+        }
+    }
+
+    /// Absorbs the given message into the hasher.
+    pub fn update(&mut self, message: &[F]) {
+        for value in message {
+            self.duplex.absorb(*value);
+        }
+        self.len += message.len();
+    }
+
+    /// Pads the absorbed message and returns its hash.
+    pub fn finalize(self) -> F {
+        self.finalize_n(1)[0]
+    }
+
+    /// Pads the absorbed message and squeezes `n` output elements.
+    ///
+    /// The sponge is permuted after every `RATE` output elements. The first output
+    /// element is the hash returned by [`Hash::finalize`], and in general the outputs
+    /// for smaller `n` are a prefix of the outputs for larger `n`; callers that need
+    /// unrelated outputs for different `n` should encode `n` in the domain.
+    pub fn finalize_n(mut self, n: usize) -> Vec<F> {
+        for value in self.domain.padding(self.len) {
+            self.duplex.absorb(value);
+        }
+        (0..n).map(|_| self.duplex.squeeze()).collect()
+    }
+
+    /// Hashes the given message within the given domain.
+    pub fn hash(domain: D, message: &[F]) -> F {
+        let mut hasher = Self::init(domain);
+        hasher.update(message);
+        hasher.finalize()
+    }
+
+    /// Hashes the given message within the given domain, returning `n` output
+    /// elements. See [`Hash::finalize_n`].
+    pub fn hash_n(domain: D, message: &[F], n: usize) -> Vec<F> {
+        let mut hasher = Self::init(domain);
+        hasher.update(message);
+        hasher.finalize_n(n)
+    }
+}
+
+/// Hashes the given message with [`P128Pow5T3`].
+///
+/// This uses the same constants as the Poseidon chips configured with [`P128Pow5T3`],
+/// so it can be used to compute public inputs for circuits that hash in-circuit.
+///
+/// ```
+/// use halo2::{arithmetic::FieldExt, pasta::Fp, primitives::poseidon};
+///
+/// let output = poseidon::hash(&[Fp::from_u64(1), Fp::from_u64(2)]);
+/// # let _ = output;
+/// ```
+pub fn hash<F: FieldExt>(message: &[F]) -> F
+where
+    P128Pow5T3: Spec<F, 3, 2>,
+{
+    Hash::<F, P128Pow5T3, _, 3, 2>::hash(VariableLength, message)
+}
+
+/// Hashes the given message within the given domain using the sponge construction of
+/// [`Hash`](struct@Hash), over an arbitrary permutation.
+///
+/// This is used by the other permutations in [`crate::primitives`], so that they are
+/// compatible with the in-circuit [`crate::gadget::poseidon::Hash`] gadget.
+pub(crate) fn sponge_hash<F: FieldExt, D: Domain<F, RATE>, const T: usize, const RATE: usize>(
+    domain: D,
+    message: &[F],
+    mut permute: impl FnMut(&mut State<F, T>),
+) -> F {
+    let mut state = [F::zero(); T];
+    state[RATE] = domain.initial_capacity_element();
+
+    let padded: Vec<_> = message
+        .iter()
+        .copied()
+        .chain(domain.padding(message.len()))
+        .collect();
+
+    // The sponge always permutes at least once before squeezing.
+    if padded.is_empty() {
+        permute(&mut state);
+    }
+    for chunk in padded.chunks(RATE) {
+        for (word, value) in state.iter_mut().zip(chunk.iter()) {
+            *word += value;
+        }
+        permute(&mut state);
+    }
+
+    state[0]
+}
+
+/// Returns the padding for a variable-length message of `len` field elements.
+fn padding<F: FieldExt, const RATE: usize>(len: usize) -> impl Iterator<Item = F> {
+    let k = len / RATE + 1;
+    iter::once(F::one()).chain((1..(k * RATE - len)).map(|_| F::zero()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        hash, padding, permute, ConstantLength, Duplex, Hash, MerkleNode, P128Pow5T3,
+        ParameterMode, Spec, VariableLength,
+    };
+    use crate::{
+        arithmetic::FieldExt,
+        pasta::{Fp, Fq},
+    };
+
+    /// A width-5 specification, used to exercise wider permutations.
+    #[derive(Debug)]
+    pub(crate) struct P128Pow5T5;
+
+    impl<F: FieldExt> Spec<F, 5, 4> for P128Pow5T5 {
+        fn full_rounds() -> usize {
+            8
+        }
+
+        fn partial_rounds() -> usize {
+            56
+        }
+
+        fn sbox(val: F) -> F {
+            val.pow_vartime([5])
+        }
+
+        fn secure_mds() -> usize {
+            0
+        }
+    }
+
+    /// A width-3 specification using neptune's parameters.
+    #[derive(Debug)]
+    struct NeptunePow5T3;
+
+    impl<F: FieldExt> Spec<F, 3, 2> for NeptunePow5T3 {
+        fn full_rounds() -> usize {
+            8
+        }
+
+        fn partial_rounds() -> usize {
+            56
+        }
+
+        fn sbox(val: F) -> F {
+            val.pow_vartime([5])
+        }
+
+        fn parameter_mode() -> ParameterMode {
+            ParameterMode::Neptune
+        }
+
+        fn secure_mds() -> usize {
+            0
+        }
+    }
+
+    #[test]
+    fn neptune_parameters() {
+        let (round_constants, mds, mds_inv) = <NeptunePow5T3 as Spec<Fp, 3, 2>>::constants();
+        let (standard_round_constants, standard_mds, _) =
+            <P128Pow5T3 as Spec<Fp, 3, 2>>::constants();
+
+        // neptune samples the round constants in the same way as the reference
+        // implementation, but uses a fixed MDS matrix.
+        assert_eq!(round_constants, standard_round_constants);
+        assert_ne!(mds, standard_mds);
+        for (i, row) in mds.iter().enumerate() {
+            for (j, entry) in row.iter().enumerate() {
+                assert_eq!(*entry * Fp::from_u64((i + j + 3) as u64), Fp::one());
+            }
+        }
+
+        let mut state = [Fp::from_u64(3), Fp::from_u64(1), Fp::from_u64(2)];
+        permute::<_, NeptunePow5T3, 3, 2>(&mut state, &mds, &round_constants);
+        let mut standard = [Fp::from_u64(3), Fp::from_u64(1), Fp::from_u64(2)];
+        permute::<_, P128Pow5T3, 3, 2>(&mut standard, &standard_mds, &round_constants);
+        assert_ne!(state, standard);
+
+        // The inverse is consistent with the transposed matrix.
+        #[allow(clippy::needless_range_loop)]
+        for i in 0..3 {
+            for j in 0..3 {
+                let expected = if i == j { Fp::one() } else { Fp::zero() };
+                assert_eq!(
+                    (0..3).fold(Fp::zero(), |acc, k| acc + mds[i][k] * mds_inv[k][j]),
+                    expected
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn padding_is_injective() {
+        for len in 0..6 {
+            let padding: Vec<Fp> = padding::<_, 2>(len).collect();
+            assert_eq!((len + padding.len()) % 2, 0);
+            assert!(!padding.is_empty() && padding.len() <= 2);
+            assert_eq!(padding[0], Fp::one());
+        }
+
+        // Messages that differ only in trailing zeroes must have different hashes.
+        let a = Hash::<Fp, P128Pow5T3, _, 3, 2>::hash(VariableLength, &[Fp::from_u64(7)]);
+        let b =
+            Hash::<Fp, P128Pow5T3, _, 3, 2>::hash(VariableLength, &[Fp::from_u64(7), Fp::zero()]);
+        let c = Hash::<Fp, P128Pow5T3, _, 3, 2>::hash(VariableLength, &[]);
+        assert_ne!(a, b);
+        assert_ne!(a, c);
+        assert_ne!(b, c);
+    }
+
+    #[test]
+    fn hash_matches_duplex() {
+        let message = [Fp::from_u64(1), Fp::from_u64(2), Fp::from_u64(3)];
+
+        // The streaming interface must agree with the one-shot interface.
+        let mut hasher = Hash::<Fp, P128Pow5T3, _, 3, 2>::init(VariableLength);
+        hasher.update(&message[..1]);
+        hasher.update(&message[1..]);
+        let output = hasher.finalize();
+        assert_eq!(
+            output,
+            Hash::<Fp, P128Pow5T3, _, 3, 2>::hash(VariableLength, &message)
+        );
+
+        // Compute the same hash by hand: two full blocks, with the variable-length IV.
+        let (round_constants, mds, _) = <P128Pow5T3 as Spec<Fp, 3, 2>>::constants();
+        let mut state = [Fp::zero(), Fp::zero(), Fp::from_u128(1 << 64)];
+        state[0] += message[0];
+        state[1] += message[1];
+        permute::<_, P128Pow5T3, 3, 2>(&mut state, &mds, &round_constants);
+        state[0] += message[2];
+        state[1] += Fp::one();
+        permute::<_, P128Pow5T3, 3, 2>(&mut state, &mds, &round_constants);
+        assert_eq!(output, state[0]);
+
+        // The duplex squeezes the rate portion of the state in order.
+        let mut duplex = Duplex::<Fp, P128Pow5T3, 3, 2>::new(Fp::zero());
+        let mut state = [Fp::zero(); 3];
+        permute::<_, P128Pow5T3, 3, 2>(&mut state, &mds, &round_constants);
+        assert_eq!(duplex.squeeze(), state[0]);
+        assert_eq!(duplex.squeeze(), state[1]);
+        permute::<_, P128Pow5T3, 3, 2>(&mut state, &mds, &round_constants);
+        assert_eq!(duplex.squeeze(), state[0]);
+    }
+
+    #[test]
+    fn hash_multiple_outputs() {
+        let message = [Fp::from_u64(1), Fp::from_u64(2), Fp::from_u64(3)];
+        let outputs = Hash::<Fp, P128Pow5T3, _, 3, 2>::hash_n(VariableLength, &message, 5);
+        assert_eq!(outputs.len(), 5);
+        assert_eq!(outputs[0], hash(&message));
+        assert_eq!(
+            Hash::<Fp, P128Pow5T3, _, 3, 2>::hash_n(VariableLength, &message, 2),
+            &outputs[..2]
+        );
+
+        // Compute the outputs by hand: the rate is squeezed in order, with a permutation
+        // between each pair of outputs.
+        let (round_constants, mds, _) = <P128Pow5T3 as Spec<Fp, 3, 2>>::constants();
+        let mut state = [Fp::zero(), Fp::zero(), Fp::from_u128(1 << 64)];
+        state[0] += message[0];
+        state[1] += message[1];
+        permute::<_, P128Pow5T3, 3, 2>(&mut state, &mds, &round_constants);
+        state[0] += message[2];
+        state[1] += Fp::one();
+        let mut expected = vec![];
+        for _ in 0..3 {
+            permute::<_, P128Pow5T3, 3, 2>(&mut state, &mds, &round_constants);
+            expected.extend_from_slice(&state[..2]);
+        }
+        assert_eq!(outputs, &expected[..5]);
+    }
+
+    #[test]
+    fn wide_hash() {
+        let message: Vec<_> = (0..9).map(Fp::from_u64).collect();
+
+        // Two full blocks of four elements, then the last element with padding.
+        let mut padded = message.clone();
+        padded.extend_from_slice(&[Fp::one(), Fp::zero(), Fp::zero()]);
+
+        let (round_constants, mds, _) = <P128Pow5T5 as Spec<Fp, 5, 4>>::constants();
+        let mut state = [Fp::zero(); 5];
+        state[4] = Fp::from_u128(1 << 64);
+        for block in padded.chunks(4) {
+            for (word, value) in state.iter_mut().zip(block.iter()) {
+                *word += value;
+            }
+            permute::<_, P128Pow5T5, 5, 4>(&mut state, &mds, &round_constants);
+        }
+
+        assert_eq!(
+            Hash::<Fp, P128Pow5T5, _, 5, 4>::hash(VariableLength, &message),
+            state[0]
+        );
+    }
+
+    #[test]
+    fn hash_over_both_fields() {
+        let fp_message = [Fp::from_u64(1), Fp::from_u64(2)];
+        assert_eq!(
+            hash(&fp_message),
+            Hash::<Fp, P128Pow5T3, _, 3, 2>::hash(VariableLength, &fp_message)
+        );
+
+        let fq_message = [Fq::from_u64(1), Fq::from_u64(2)];
+        assert_eq!(
+            hash(&fq_message),
+            Hash::<Fq, P128Pow5T3, _, 3, 2>::hash(VariableLength, &fq_message)
+        );
+
+        // The two fields use different constants, so the outputs differ.
+        assert_ne!(hash(&fp_message).to_bytes(), hash(&fq_message).to_bytes());
+    }
+
+    #[test]
+    fn domains_are_separated() {
+        type H<D> = Hash<Fp, P128Pow5T3, D, 3, 2>;
+
+        // Without domain separation in the capacity element, these would absorb the
+        // same padded input ([1, 0]) into the same initial state.
+        assert_ne!(
+            H::hash(VariableLength, &[]),
+            H::hash(ConstantLength::<1>, &[Fp::one()])
+        );
+
+        let children = [Fp::from_u64(1), Fp::from_u64(2)];
+        assert_ne!(
+            H::hash(ConstantLength::<2>, &children),
+            H::hash(MerkleNode { layer: 0 }, &children)
+        );
+        assert_ne!(
+            H::hash(MerkleNode { layer: 0 }, &children),
+            H::hash(MerkleNode { layer: 1 }, &children)
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn constant_length_rejects_wrong_length() {
+        Hash::<Fp, P128Pow5T3, _, 3, 2>::hash(ConstantLength::<2>, &[Fp::one()]);
+    }
+}
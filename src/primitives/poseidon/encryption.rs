@@ -0,0 +1,176 @@
+//! Authenticated encryption with a Poseidon duplex sponge.
+//!
+//! This follows the construction of Khovratovich's "Encryption with Poseidon" (as used
+//! for note ciphertexts in several private-payment protocols), adapted to the sponge
+//! layout of this crate:
+//!
+//! 1. The capacity element is initialized with a domain tag that commits to the
+//!    message length, and the key and nonce are absorbed into the rate, permuting after
+//!    every block of up to `RATE` elements.
+//! 2. Each block of up to `RATE` message elements is added to the rate portion of the
+//!    state; the resulting rate elements are the ciphertext for that block, and the
+//!    state is then permuted.
+//! 3. After the last block, the first rate element of the state is the authentication
+//!    tag.
+//!
+//! The key is typically derived from a Diffie-Hellman shared secret with the recipient.
+//! A (key, nonce) pair must never be used to encrypt more than one message.
+//!
+//! The in-circuit counterparts are [`crate::gadget::poseidon::encrypt`] and
+//! [`crate::gadget::poseidon::decrypt`].
+
+use super::{domain_capacity_element, poseidon_duplex, Constants, Spec, State};
+use crate::arithmetic::FieldExt;
+
+/// An encrypted message, together with its authentication tag.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Ciphertext<W> {
+    /// The encrypted message, with one element per message element.
+    pub words: Vec<W>,
+    /// The authentication tag.
+    pub tag: W,
+}
+
+/// Returns the initial capacity element for encrypting a message of `message_len`
+/// field elements.
+pub fn initial_capacity_element<F: FieldExt>(message_len: usize) -> F {
+    domain_capacity_element(3, message_len as u128)
+}
+
+/// Runs the duplex over `key` and `nonce`, returning the state that the first message
+/// block is added to.
+fn keyed_state<F: FieldExt, S: Spec<F, T, RATE>, const T: usize, const RATE: usize>(
+    key: &[F],
+    nonce: F,
+    message_len: usize,
+    constants: &Constants<F, T>,
+) -> State<F, T> {
+    let mut state = [F::zero(); T];
+    state[RATE] = initial_capacity_element(message_len);
+
+    let input: Vec<_> = key.iter().copied().chain(Some(nonce)).collect();
+    for chunk in input.chunks(RATE) {
+        let mut block = [None; RATE];
+        for (word, value) in block.iter_mut().zip(chunk.iter()) {
+            *word = Some(*value);
+        }
+        poseidon_duplex::<F, S, T, RATE>(&mut state, &block, constants);
+    }
+
+    state
+}
+
+/// Encrypts `message` under the given key and nonce.
+pub fn encrypt<F: FieldExt, S: Spec<F, T, RATE>, const T: usize, const RATE: usize>(
+    key: &[F],
+    nonce: F,
+    message: &[F],
+) -> Ciphertext<F> {
+    let constants = S::constants();
+    let mut state = keyed_state::<F, S, T, RATE>(key, nonce, message.len(), &constants);
+
+    let mut words = Vec::with_capacity(message.len());
+    for block in message.chunks(RATE) {
+        for (word, value) in state.iter_mut().zip(block.iter()) {
+            *word += value;
+            words.push(*word);
+        }
+        poseidon_duplex::<F, S, T, RATE>(&mut state, &[None; RATE], &constants);
+    }
+
+    Ciphertext {
+        words,
+        tag: state[0],
+    }
+}
+
+/// Decrypts `ciphertext` under the given key and nonce, returning `None` if the
+/// authentication tag is invalid.
+pub fn decrypt<F: FieldExt, S: Spec<F, T, RATE>, const T: usize, const RATE: usize>(
+    key: &[F],
+    nonce: F,
+    ciphertext: &Ciphertext<F>,
+) -> Option<Vec<F>> {
+    let constants = S::constants();
+    let mut state = keyed_state::<F, S, T, RATE>(key, nonce, ciphertext.words.len(), &constants);
+
+    let mut message = Vec::with_capacity(ciphertext.words.len());
+    for block in ciphertext.words.chunks(RATE) {
+        for (word, value) in state.iter_mut().zip(block.iter()) {
+            message.push(*value - *word);
+            *word = *value;
+        }
+        poseidon_duplex::<F, S, T, RATE>(&mut state, &[None; RATE], &constants);
+    }
+
+    if bool::from(state[0].ct_eq(&ciphertext.tag)) {
+        Some(message)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decrypt, encrypt};
+    use crate::{
+        arithmetic::FieldExt,
+        pasta::Fp,
+        primitives::poseidon::{tests::P128Pow5T5, P128Pow5T3},
+    };
+
+    #[test]
+    fn round_trip() {
+        let key = [Fp::from_u64(11), Fp::from_u64(12)];
+        let nonce = Fp::from_u64(7);
+
+        for len in 0..6 {
+            let message: Vec<_> = (0..len).map(|i| Fp::from_u64(i + 100)).collect();
+
+            let ciphertext = encrypt::<_, P128Pow5T3, 3, 2>(&key, nonce, &message);
+            assert_eq!(ciphertext.words.len(), message.len());
+            assert_eq!(
+                decrypt::<_, P128Pow5T3, 3, 2>(&key, nonce, &ciphertext),
+                Some(message.clone())
+            );
+
+            let ciphertext = encrypt::<_, P128Pow5T5, 5, 4>(&key, nonce, &message);
+            assert_eq!(
+                decrypt::<_, P128Pow5T5, 5, 4>(&key, nonce, &ciphertext),
+                Some(message)
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_tampering() {
+        let key = [Fp::from_u64(11), Fp::from_u64(12)];
+        let nonce = Fp::from_u64(7);
+        let message = [Fp::from_u64(1), Fp::from_u64(2), Fp::from_u64(3)];
+        let ciphertext = encrypt::<_, P128Pow5T3, 3, 2>(&key, nonce, &message);
+
+        // Wrong key or nonce.
+        let wrong_key = [Fp::from_u64(11), Fp::from_u64(13)];
+        assert_eq!(
+            decrypt::<_, P128Pow5T3, 3, 2>(&wrong_key, nonce, &ciphertext),
+            None
+        );
+        assert_eq!(
+            decrypt::<_, P128Pow5T3, 3, 2>(&key, nonce + Fp::one(), &ciphertext),
+            None
+        );
+
+        // Modified ciphertext.
+        let mut modified = ciphertext.clone();
+        modified.words[2] += Fp::one();
+        assert_eq!(decrypt::<_, P128Pow5T3, 3, 2>(&key, nonce, &modified), None);
+
+        // Truncated ciphertext.
+        let mut truncated = ciphertext;
+        truncated.words.pop();
+        assert_eq!(
+            decrypt::<_, P128Pow5T3, 3, 2>(&key, nonce, &truncated),
+            None
+        );
+    }
+}
@@ -0,0 +1,309 @@
+//! Serialization of Poseidon parameters.
+//!
+//! [`Parameters`] captures everything that determines a Poseidon permutation: its round
+//! numbers, S-box, round constants and linear layers. It can be written to a compact
+//! binary format (and read back), or to JSON, so that external implementations can load
+//! exactly the parameters used by this crate rather than reimplementing the Grain LFSR.
+//!
+//! # Binary format
+//!
+//! All integers are little-endian.
+//!
+//! | Field              | Encoding                                           |
+//! |--------------------|----------------------------------------------------|
+//! | Magic              | The four bytes `PSDN`                              |
+//! | Version            | `u8`, currently `1`                                |
+//! | Permutation        | `u8`: `0` for Poseidon, `1` for Poseidon2          |
+//! | S-box              | `u8` tag (`0` for $x^\alpha$, `1` for $x^{-1}$), then $\alpha$ as `u64` (`0` for $x^{-1}$) |
+//! | Width $t$          | `u32`                                              |
+//! | Rate               | `u32`                                              |
+//! | Full rounds        | `u32`                                              |
+//! | Partial rounds     | `u32`                                              |
+//! | Round constants    | `u32` number of rows, then each row of $t$ elements |
+//! | Matrices           | Two $t \times t$ matrices, in row-major order       |
+//!
+//! Each field element is encoded as its canonical 32-byte little-endian representation,
+//! as in proofs. For Poseidon the two matrices are the MDS matrix and its inverse; for
+//! Poseidon2 they are the external and internal matrices.
+//!
+//! # JSON format
+//!
+//! The JSON format contains the same information, with field elements encoded as
+//! `0x`-prefixed big-endian hexadecimal strings, and also records the field modulus.
+
+use std::convert::TryInto;
+use std::fmt::Write as _;
+use std::io;
+
+use super::{rounds::Alpha, Mds, PermutationType, Spec};
+use crate::arithmetic::FieldExt;
+
+const MAGIC: [u8; 4] = *b"PSDN";
+const VERSION: u8 = 1;
+
+/// The complete set of parameters for a Poseidon permutation with a width of `T` field
+/// elements.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Parameters<F: FieldExt, const T: usize> {
+    /// The permutation that these parameters instantiate.
+    pub permutation: PermutationType,
+    /// The S-box.
+    pub alpha: Alpha,
+    /// The sponge rate.
+    pub rate: usize,
+    /// The number of full rounds.
+    pub full_rounds: usize,
+    /// The number of partial rounds.
+    pub partial_rounds: usize,
+    /// The round constants.
+    pub round_constants: Vec<[F; T]>,
+    /// The MDS matrix, or the external matrix for [`PermutationType::Poseidon2`].
+    pub mds: Mds<F, T>,
+    /// The inverse of the MDS matrix, or the internal matrix for
+    /// [`PermutationType::Poseidon2`].
+    pub mds_inv: Mds<F, T>,
+}
+
+impl<F: FieldExt, const T: usize> Parameters<F, T> {
+    /// Returns the parameters for the given specification.
+    pub fn from_spec<S: Spec<F, T, RATE>, const RATE: usize>() -> Self {
+        let (round_constants, mds, mds_inv) = S::constants();
+        Parameters {
+            permutation: S::permutation_type(),
+            alpha: S::alpha(),
+            rate: RATE,
+            full_rounds: S::full_rounds(),
+            partial_rounds: S::partial_rounds(),
+            round_constants,
+            mds,
+            mds_inv,
+        }
+    }
+
+    /// Writes these parameters to a buffer, in the binary format.
+    pub fn write<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        let write_u32 = |writer: &mut W, value: usize| {
+            let value: u32 = value
+                .try_into()
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "value too large"))?;
+            writer.write_all(&value.to_le_bytes())
+        };
+
+        writer.write_all(&MAGIC)?;
+        writer.write_all(&[VERSION])?;
+        writer.write_all(&[match self.permutation {
+            PermutationType::Poseidon => 0,
+            PermutationType::Poseidon2 => 1,
+        }])?;
+        let (tag, alpha) = match self.alpha {
+            Alpha::Pow(alpha) => (0, alpha),
+            Alpha::Inv => (1, 0),
+        };
+        writer.write_all(&[tag])?;
+        writer.write_all(&alpha.to_le_bytes())?;
+        write_u32(writer, T)?;
+        write_u32(writer, self.rate)?;
+        write_u32(writer, self.full_rounds)?;
+        write_u32(writer, self.partial_rounds)?;
+
+        write_u32(writer, self.round_constants.len())?;
+        for row in self.round_constants.iter() {
+            for value in row.iter() {
+                writer.write_all(&value.to_bytes())?;
+            }
+        }
+        for matrix in [&self.mds, &self.mds_inv].iter() {
+            for row in matrix.iter() {
+                for value in row.iter() {
+                    writer.write_all(&value.to_bytes())?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads parameters in the binary format from a buffer.
+    pub fn read<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+        let invalid = |msg| io::Error::new(io::ErrorKind::InvalidData, msg);
+
+        let mut read_bytes = |len: usize| -> io::Result<Vec<u8>> {
+            let mut buf = vec![0u8; len];
+            reader.read_exact(&mut buf)?;
+            Ok(buf)
+        };
+
+        if read_bytes(4)? != MAGIC {
+            return Err(invalid("invalid magic bytes"));
+        }
+        if read_bytes(1)?[0] != VERSION {
+            return Err(invalid("unsupported version"));
+        }
+        let permutation = match read_bytes(1)?[0] {
+            0 => PermutationType::Poseidon,
+            1 => PermutationType::Poseidon2,
+            _ => return Err(invalid("invalid permutation type")),
+        };
+        let tag = read_bytes(1)?[0];
+        let alpha = u64::from_le_bytes(read_bytes(8)?.try_into().unwrap());
+        let alpha = match (tag, alpha) {
+            (0, alpha) if alpha >= 3 => Alpha::Pow(alpha),
+            (1, 0) => Alpha::Inv,
+            _ => return Err(invalid("invalid S-box")),
+        };
+
+        let mut read_u32 = || -> io::Result<usize> {
+            Ok(u32::from_le_bytes(read_bytes(4)?.try_into().unwrap()) as usize)
+        };
+        if read_u32()? != T {
+            return Err(invalid("unexpected width"));
+        }
+        let rate = read_u32()?;
+        let full_rounds = read_u32()?;
+        let partial_rounds = read_u32()?;
+        let num_rows = read_u32()?;
+
+        let mut read_element = || -> io::Result<F> {
+            let bytes: [u8; 32] = read_bytes(32)?.try_into().unwrap();
+            Option::from(F::from_bytes(&bytes))
+                .ok_or_else(|| invalid("invalid field element encoding"))
+        };
+        let mut read_row = || -> io::Result<[F; T]> {
+            let mut row = [F::zero(); T];
+            for value in row.iter_mut() {
+                *value = read_element()?;
+            }
+            Ok(row)
+        };
+
+        let round_constants = (0..num_rows)
+            .map(|_| read_row())
+            .collect::<io::Result<Vec<_>>>()?;
+        let mut read_matrix = || -> io::Result<Mds<F, T>> {
+            let mut matrix = [[F::zero(); T]; T];
+            for row in matrix.iter_mut() {
+                *row = read_row()?;
+            }
+            Ok(matrix)
+        };
+        let mds = read_matrix()?;
+        let mds_inv = read_matrix()?;
+
+        Ok(Parameters {
+            permutation,
+            alpha,
+            rate,
+            full_rounds,
+            partial_rounds,
+            round_constants,
+            mds,
+            mds_inv,
+        })
+    }
+
+    /// Returns these parameters in the JSON format.
+    pub fn to_json(&self) -> String {
+        fn hex<F: FieldExt>(value: &F) -> String {
+            let mut s = String::from("\"0x");
+            for byte in value.to_bytes().iter().rev() {
+                write!(s, "{:02x}", byte).unwrap();
+            }
+            s.push('"');
+            s
+        }
+
+        fn row<F: FieldExt>(values: &[F]) -> String {
+            let values: Vec<_> = values.iter().map(hex).collect();
+            format!("[{}]", values.join(", "))
+        }
+
+        fn rows<F: FieldExt, const T: usize>(rows: &[[F; T]]) -> String {
+            let rows: Vec<_> = rows.iter().map(|r| format!("    {}", row(r))).collect();
+            format!("[\n{}\n  ]", rows.join(",\n"))
+        }
+
+        let (permutation, matrices) = match self.permutation {
+            PermutationType::Poseidon => ("poseidon", ["mds", "mds_inv"]),
+            PermutationType::Poseidon2 => ("poseidon2", ["external_matrix", "internal_matrix"]),
+        };
+        let alpha = match self.alpha {
+            Alpha::Pow(alpha) => alpha.to_string(),
+            Alpha::Inv => "-1".to_string(),
+        };
+
+        let mut json = String::from("{\n");
+        writeln!(json, "  \"permutation\": \"{}\",", permutation).unwrap();
+        writeln!(json, "  \"modulus\": \"{}\",", F::MODULUS).unwrap();
+        writeln!(json, "  \"alpha\": {},", alpha).unwrap();
+        writeln!(json, "  \"width\": {},", T).unwrap();
+        writeln!(json, "  \"rate\": {},", self.rate).unwrap();
+        writeln!(json, "  \"full_rounds\": {},", self.full_rounds).unwrap();
+        writeln!(json, "  \"partial_rounds\": {},", self.partial_rounds).unwrap();
+        writeln!(
+            json,
+            "  \"round_constants\": {},",
+            rows(&self.round_constants)
+        )
+        .unwrap();
+        writeln!(json, "  \"{}\": {},", matrices[0], rows(&self.mds)).unwrap();
+        writeln!(json, "  \"{}\": {}", matrices[1], rows(&self.mds_inv)).unwrap();
+        json.push('}');
+        json
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Parameters;
+    use crate::{
+        pasta::Fp,
+        primitives::poseidon::{rounds::Alpha, P128Pow5T3, Poseidon2Pow5T3},
+    };
+
+    #[test]
+    fn binary_round_trip() {
+        let params = Parameters::<Fp, 3>::from_spec::<P128Pow5T3, 2>();
+        assert_eq!(params.alpha, Alpha::Pow(5));
+        assert_eq!(params.round_constants.len(), 64);
+
+        let mut buf = vec![];
+        params.write(&mut buf).unwrap();
+        assert_eq!(buf.len(), 4 + 1 + 1 + 9 + 5 * 4 + (64 * 3 + 18) * 32);
+        assert_eq!(Parameters::read(&mut &buf[..]).unwrap(), params);
+
+        let params = Parameters::<Fp, 3>::from_spec::<Poseidon2Pow5T3, 2>();
+        let mut buf = vec![];
+        params.write(&mut buf).unwrap();
+        assert_eq!(Parameters::read(&mut &buf[..]).unwrap(), params);
+
+        // Wrong width.
+        assert!(Parameters::<Fp, 4>::read(&mut &buf[..]).is_err());
+
+        // Truncated parameters.
+        assert!(Parameters::<Fp, 3>::read(&mut &buf[..buf.len() - 1]).is_err());
+
+        // Non-canonical field element.
+        let mut modified = buf.clone();
+        let offset = 4 + 1 + 1 + 9 + 5 * 4;
+        for byte in modified[offset..offset + 32].iter_mut() {
+            *byte = 0xff;
+        }
+        assert!(Parameters::<Fp, 3>::read(&mut &modified[..]).is_err());
+    }
+
+    #[test]
+    fn json() {
+        let params = Parameters::<Fp, 3>::from_spec::<P128Pow5T3, 2>();
+        let json = params.to_json();
+
+        assert!(json.starts_with("{\n  \"permutation\": \"poseidon\",\n"));
+        assert!(json.contains("\"alpha\": 5,"));
+        assert!(json.contains("\"partial_rounds\": 56,"));
+        // The first round constant is big-endian.
+        assert!(json.contains(
+            "\"round_constants\": [\n    \
+             [\"0x360d7470611e473d353f628f76d110f34e71162f31003b7057538c2596426303\""
+        ));
+        assert!(json.ends_with("]\n}"));
+    }
+}
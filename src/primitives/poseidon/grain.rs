@@ -0,0 +1,266 @@
+//! The Grain LFSR in self-shrinking mode, as used by Poseidon.
+
+use std::marker::PhantomData;
+
+use crate::arithmetic::FieldExt;
+
+const STATE: usize = 80;
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum FieldType {
+    /// GF(2^n)
+    Binary,
+    /// GF(p)
+    PrimeOrder,
+}
+
+impl FieldType {
+    fn tag(&self) -> u8 {
+        match self {
+            FieldType::Binary => 0,
+            FieldType::PrimeOrder => 1,
+        }
+    }
+}
+
+/// The type of S-box used by a Poseidon permutation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SboxType {
+    /// x^alpha
+    Pow,
+    /// x^(-1), with 0^(-1) defined as 0.
+    Inv,
+}
+
+impl SboxType {
+    fn tag(&self) -> u8 {
+        match self {
+            SboxType::Pow => 0,
+            SboxType::Inv => 1,
+        }
+    }
+}
+
+/// The Grain LFSR, sampling elements of the field `F`.
+///
+/// For prime fields `F` is the field type. For binary fields, elements of GF(2^n) are
+/// represented as `u128`, where bit `i` is the coefficient of $x^i$ in the polynomial
+/// basis; this supports any $n \leq 128$.
+pub(crate) struct Grain<F> {
+    state: [bool; STATE],
+    next_bit: usize,
+    field_bits: usize,
+    _field: PhantomData<F>,
+}
+
+impl<F> Grain<F> {
+    fn init(
+        field_type: FieldType,
+        field_bits: u16,
+        sbox: SboxType,
+        t: u16,
+        r_f: u16,
+        r_p: u16,
+    ) -> Self {
+        // Initialize the LFSR state.
+        let mut state = [true; STATE];
+        let mut set_bits = |offset: usize, len, value| {
+            // Poseidon reference impl sets initial state bits in MSB order.
+            for i in 0..len {
+                state[offset + len - 1 - i] = (value >> i) & 1 != 0;
+            }
+        };
+        set_bits(0, 2, field_type.tag() as u16);
+        set_bits(2, 4, sbox.tag() as u16);
+        set_bits(6, 12, field_bits);
+        set_bits(18, 12, t);
+        set_bits(30, 10, r_f);
+        set_bits(40, 10, r_p);
+
+        let mut grain = Grain {
+            state,
+            next_bit: STATE,
+            field_bits: field_bits as usize,
+            _field: PhantomData,
+        };
+
+        // Discard the first 160 bits.
+        for _ in 0..20 {
+            grain.load_next_8_bits();
+            grain.next_bit = STATE;
+        }
+
+        grain
+    }
+
+    fn load_next_8_bits(&mut self) {
+        let mut new_bits = 0u8;
+        for i in 0..8 {
+            new_bits |= ((self.state[i + 62]
+                ^ self.state[i + 51]
+                ^ self.state[i + 38]
+                ^ self.state[i + 23]
+                ^ self.state[i + 13]
+                ^ self.state[i]) as u8)
+                << i;
+        }
+        self.state.rotate_left(8);
+        self.next_bit -= 8;
+        for i in 0..8 {
+            self.state[self.next_bit + i] = (new_bits >> i) & 1 != 0;
+        }
+    }
+
+    fn get_next_bit(&mut self) -> bool {
+        if self.next_bit == STATE {
+            self.load_next_8_bits();
+        }
+        let ret = self.state[self.next_bit];
+        self.next_bit += 1;
+        ret
+    }
+}
+
+impl<F: FieldExt> Grain<F> {
+    pub(crate) fn new(sbox: SboxType, t: u16, r_f: u16, r_p: u16) -> Self {
+        Grain::init(FieldType::PrimeOrder, F::NUM_BITS as u16, sbox, t, r_f, r_p)
+    }
+
+    /// Returns the next field element from this Grain instantiation.
+    pub(crate) fn next_field_element(&mut self) -> F {
+        // Loop until we get an element in the field.
+        loop {
+            let mut bytes = [0u8; 32];
+
+            // Poseidon reference impl interprets the bits as a repr in MSB order, because
+            // it's easy to do that in Python. Meanwhile, our field elements all use LSB
+            // order. There's little motivation to diverge from the reference impl; these
+            // are all constants, so we aren't introducing big-endianness into the rest of
+            // the circuit (assuming unkeyed Poseidon, but we probably wouldn't want to
+            // implement Grain inside a circuit, so we'd use a different round constant
+            // derivation function there).
+            for (i, bit) in self.take(F::NUM_BITS as usize).enumerate() {
+                // If we diverged from the reference impl and interpreted the bits in LSB
+                // order, we would remove this line.
+                let i = F::NUM_BITS as usize - 1 - i;
+
+                bytes[i / 8] |= if bit { 1 << (i % 8) } else { 0 };
+            }
+
+            if let Some(f) = Option::from(F::from_bytes(&bytes)) {
+                break f;
+            }
+        }
+    }
+
+    /// Returns the next field element from this Grain instantiation, without using
+    /// rejection sampling.
+    pub(crate) fn next_field_element_without_rejection(&mut self) -> F {
+        let mut bytes = [0u8; 64];
+
+        // Poseidon reference impl interprets the bits into a repr in MSB order, because
+        // it's easy to do that in Python. Additionally, it does not use rejection
+        // sampling in cases where the constants don't specifically need to be uniformly
+        // random for security. We do not provide APIs that take a field-element-sized
+        // array and reduce it modulo the field order, because those are unsafe APIs to
+        // offer generally (accidentally using them can lead to divergence in consensus
+        // systems due to not rejecting canonical forms).
+        //
+        // Given that we don't want to diverge from the reference implementation, we hack
+        // around this restriction by serializing the bits into a 64-byte array and then
+        // calling FieldExt::from_bytes_wide. PLEASE DO NOT COPY THIS INTO YOUR OWN CODE!
+        for (i, bit) in self.take(F::NUM_BITS as usize).enumerate() {
+            // If we diverged from the reference impl and interpreted the bits in LSB
+            // order, we would remove this line.
+            let i = F::NUM_BITS as usize - 1 - i;
+
+            bytes[i / 8] |= if bit { 1 << (i % 8) } else { 0 };
+        }
+
+        F::from_bytes_wide(&bytes)
+    }
+}
+
+impl Grain<u128> {
+    /// Constructs a Grain instance for a permutation over GF(2^n), where
+    /// `n = field_bits`.
+    pub(crate) fn new_binary(field_bits: u16, sbox: SboxType, t: u16, r_f: u16, r_p: u16) -> Self {
+        assert!(0 < field_bits && field_bits <= 128);
+        Grain::init(FieldType::Binary, field_bits, sbox, t, r_f, r_p)
+    }
+
+    /// Returns the next element of GF(2^n) from this Grain instantiation.
+    ///
+    /// Every n-bit string is a valid element, so no rejection sampling is needed. As
+    /// with prime fields, the bits are interpreted in MSB order, so the first bit
+    /// output is the coefficient of $x^{n-1}$.
+    pub(crate) fn next_binary_element(&mut self) -> u128 {
+        let field_bits = self.field_bits;
+        self.take(field_bits)
+            .fold(0, |acc, bit| (acc << 1) | bit as u128)
+    }
+}
+
+impl<F> Iterator for Grain<F> {
+    type Item = bool;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Evaluate bits in pairs:
+        // - If the first bit is a 1, output the second bit.
+        // - If the first bit is a 0, discard the second bit.
+        while !self.get_next_bit() {
+            self.get_next_bit();
+        }
+        Some(self.get_next_bit())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FieldType, Grain, SboxType};
+    use crate::pasta::Fp;
+
+    #[test]
+    fn grain_is_deterministic() {
+        let mut a = Grain::<Fp>::new(SboxType::Pow, 3, 8, 56);
+        let mut b = Grain::<Fp>::new(SboxType::Pow, 3, 8, 56);
+        for _ in 0..10 {
+            assert_eq!(a.next_field_element(), b.next_field_element());
+        }
+
+        // Different parameters must produce different constants.
+        let mut c = Grain::<Fp>::new(SboxType::Pow, 3, 8, 57);
+        let mut a = Grain::<Fp>::new(SboxType::Pow, 3, 8, 56);
+        assert_ne!(a.next_field_element(), c.next_field_element());
+    }
+
+    #[test]
+    fn binary_elements() {
+        let mut a = Grain::new_binary(64, SboxType::Pow, 3, 8, 56);
+        let mut b = Grain::new_binary(64, SboxType::Pow, 3, 8, 56);
+        let mut bits = Grain::new_binary(64, SboxType::Pow, 3, 8, 56);
+        for _ in 0..10 {
+            let element = a.next_binary_element();
+            assert_eq!(element, b.next_binary_element());
+
+            // The first bit output is the most significant coefficient.
+            for i in (0..64).rev() {
+                assert_eq!(bits.next().unwrap(), (element >> i) & 1 == 1);
+            }
+        }
+
+        // Elements are reduced to the field size.
+        let mut small = Grain::new_binary(7, SboxType::Inv, 5, 8, 40);
+        for _ in 0..100 {
+            assert!(small.next_binary_element() < 1 << 7);
+        }
+
+        // The field type is part of the initial state.
+        let mut binary = Grain::new_binary(64, SboxType::Pow, 3, 8, 56);
+        let mut prime = Grain::<u128>::init(FieldType::PrimeOrder, 64, SboxType::Pow, 3, 8, 56);
+        assert_ne!(
+            binary.by_ref().take(128).collect::<Vec<_>>(),
+            prime.by_ref().take(128).collect::<Vec<_>>()
+        );
+    }
+}
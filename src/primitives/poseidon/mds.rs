@@ -0,0 +1,329 @@
+use super::{grain::Grain, Mds};
+use crate::arithmetic::FieldExt;
+
+pub(crate) fn generate_mds<F: FieldExt, const T: usize>(
+    grain: &mut Grain<F>,
+    mut select: usize,
+) -> (Mds<F, T>, Mds<F, T>) {
+    loop {
+        // Generate two [F; T] arrays of unique field elements.
+        let (xs, ys) = loop {
+            let mut vals: Vec<_> = (0..2 * T)
+                .map(|_| grain.next_field_element_without_rejection())
+                .collect();
+
+            // Check that we have unique field elements.
+            let mut unique = vals.clone();
+            unique.sort_unstable();
+            unique.dedup();
+            if vals.len() == unique.len() {
+                let rhs = vals.split_off(T);
+                break (vals, rhs);
+            }
+        };
+
+        // The Cauchy matrix is only defined if x_i + y_j != 0.
+        if xs
+            .iter()
+            .any(|x| ys.iter().any(|y| bool::from((*x + y).ct_is_zero())))
+        {
+            continue;
+        }
+
+        // The number of MDS matrices that the reference implementation rejects before
+        // obtaining a secure matrix can be side-loaded, so that we skip them without
+        // checking. This can be determined out-of-band via the reference implementation
+        // in Sage.
+        if select != 0 {
+            select -= 1;
+            continue;
+        }
+
+        // We also perform the checks ourselves, and resample if they fail, so that an
+        // incorrect (or absent) side-loaded index cannot result in an insecure matrix.
+        let (mds, mds_inv) = cauchy(&xs, &ys);
+        if is_secure(&mds) {
+            break (mds, mds_inv);
+        }
+    }
+}
+
+/// Returns `true` if the given matrix passes the security checks of the Poseidon
+/// reference implementation, for a permutation whose partial rounds apply the S-box to
+/// the first state element.
+///
+/// These are the checks performed by `algorithm_1`, `algorithm_2` and `algorithm_3` in
+/// the reference implementation's `generate_parameters_grain.sage`, which rule out
+/// invariant subspaces and infinitely long subspace trails through the partial rounds.
+/// With a single S-box, they reduce to requiring that:
+///
+/// - the matrix is invertible;
+/// - for $r \in [1, 4t]$, no proper subspace containing $e_0$ is invariant under
+///   $M^r$ (`algorithm_2` and `algorithm_3`);
+/// - for $i \in [1, t)$, no non-trivial subspace on which the S-box is inactive is
+///   invariant under $M^i$ (`algorithm_1`).
+///
+/// The last check is slightly stronger than the reference, which only considers
+/// subspaces spanned by eigenvectors with eigenvalues in the base field.
+pub(crate) fn is_secure<F: FieldExt, const T: usize>(mds: &Mds<F, T>) -> bool {
+    let identity = {
+        let mut identity = [[F::zero(); T]; T];
+        for (i, row) in identity.iter_mut().enumerate() {
+            row[i] = F::one();
+        }
+        identity
+    };
+
+    if rank(mds.iter().map(|row| row.to_vec()).collect()) != T {
+        return false;
+    }
+
+    let mut power = identity;
+    for r in 1..=(4 * T) {
+        power = mul(&power, mds);
+
+        // The smallest M^r-invariant subspace containing e_0 must be the full space.
+        if krylov_rank(&power, &identity[0]) != T {
+            return false;
+        }
+
+        // The largest M^r-invariant subspace on which the S-box is inactive (that is,
+        // whose elements have a zero first coordinate) is the orthogonal complement of
+        // the smallest (M^r)^T-invariant subspace containing e_0, and must be trivial.
+        if r < T && krylov_rank(&transpose(&power), &identity[0]) != T {
+            return false;
+        }
+    }
+
+    true
+}
+
+fn mul<F: FieldExt, const T: usize>(a: &Mds<F, T>, b: &Mds<F, T>) -> Mds<F, T> {
+    let mut product = [[F::zero(); T]; T];
+    #[allow(clippy::needless_range_loop)]
+    for i in 0..T {
+        for j in 0..T {
+            product[i][j] = (0..T).fold(F::zero(), |acc, k| acc + a[i][k] * b[k][j]);
+        }
+    }
+    product
+}
+
+/// Returns the dimension of the span of `v, Mv, ..., M^{T-1} v`.
+fn krylov_rank<F: FieldExt, const T: usize>(matrix: &Mds<F, T>, v: &[F; T]) -> usize {
+    let mut vectors = Vec::with_capacity(T);
+    let mut v = *v;
+    for _ in 0..T {
+        vectors.push(v.to_vec());
+        let mut next = [F::zero(); T];
+        for (next, row) in next.iter_mut().zip(matrix.iter()) {
+            *next = row
+                .iter()
+                .zip(v.iter())
+                .fold(F::zero(), |acc, (m, v)| acc + *m * v);
+        }
+        v = next;
+    }
+    rank(vectors)
+}
+
+/// Returns the rank of the matrix with the given rows, by Gaussian elimination.
+fn rank<F: FieldExt>(mut rows: Vec<Vec<F>>) -> usize {
+    let width = rows.first().map_or(0, |row| row.len());
+    let mut rank = 0;
+    for col in 0..width {
+        let pivot = match (rank..rows.len()).find(|&i| !bool::from(rows[i][col].ct_is_zero())) {
+            Some(pivot) => pivot,
+            None => continue,
+        };
+        rows.swap(rank, pivot);
+
+        let inv = rows[rank][col].invert().unwrap();
+        let pivot_row = rows[rank].clone();
+        for row in rows.iter_mut().skip(rank + 1) {
+            let factor = row[col] * inv;
+            for (value, pivot_value) in row.iter_mut().zip(pivot_row.iter()) {
+                *value -= factor * pivot_value;
+            }
+        }
+        rank += 1;
+    }
+    rank
+}
+
+/// Generates the fixed MDS matrix used by neptune, which is the Cauchy matrix with
+/// `xs = [0, T)` and `ys = [T, 2T)`.
+///
+/// neptune multiplies the state as a row vector by this matrix, so we return its
+/// transpose (and the inverse of that) for use with [`super::permute`].
+pub(crate) fn generate_neptune_mds<F: FieldExt, const T: usize>() -> (Mds<F, T>, Mds<F, T>) {
+    let xs: Vec<_> = (0..T as u64).map(F::from_u64).collect();
+    let ys: Vec<_> = (T as u64..2 * T as u64).map(F::from_u64).collect();
+    let (mds, mds_inv) = cauchy(&xs, &ys);
+    (transpose(&mds), transpose(&mds_inv))
+}
+
+fn transpose<F: FieldExt, const T: usize>(matrix: &Mds<F, T>) -> Mds<F, T> {
+    let mut transposed = [[F::zero(); T]; T];
+    #[allow(clippy::needless_range_loop)]
+    for i in 0..T {
+        for j in 0..T {
+            transposed[i][j] = matrix[j][i];
+        }
+    }
+    transposed
+}
+
+/// Returns the Cauchy matrix for the given `xs` and `ys`, and its inverse. The elements
+/// of `xs` must be distinct, as must the elements of `ys`.
+fn cauchy<F: FieldExt, const T: usize>(xs: &[F], ys: &[F]) -> (Mds<F, T>, Mds<F, T>) {
+    // Generate a Cauchy matrix, with elements a_ij in the form:
+    //     a_ij = 1/(x_i + y_j); x_i + y_j != 0
+    //
+    // It would be much easier to use the alternate definition:
+    //     a_ij = 1/(x_i - y_j); x_i - y_j != 0
+    //
+    // These are clearly equivalent on `y <- -y`, but it is easier to work with the
+    // negative formulation, because ensuring that xs ∪ ys is unique implies that
+    // x_i - y_j != 0 by construction (whereas the positive case does not hold). It
+    // also makes computation of the matrix inverse simpler below (the theorem used
+    // was formulated for the negative definition).
+    //
+    // However, the Poseidon paper and reference impl use the positive formulation,
+    // and we want to rely on the reference impl for MDS security, so we use the same
+    // formulation.
+    let mut mds = [[F::zero(); T]; T];
+    #[allow(clippy::needless_range_loop)]
+    for i in 0..T {
+        for j in 0..T {
+            let sum = xs[i] + ys[j];
+            // This is checked by `generate_mds` before calling this function.
+            assert!(!bool::from(sum.ct_is_zero()));
+            mds[i][j] = sum.invert().unwrap();
+        }
+    }
+
+    // Compute the inverse. All square Cauchy matrices have a non-zero determinant and
+    // thus are invertible. The inverse for a Cauchy matrix of the form:
+    //
+    //     a_ij = 1/(x_i - y_j); x_i - y_j != 0
+    //
+    // has elements b_ij given by:
+    //
+    //     b_ij = (x_j - y_i) A_j(y_i) B_i(x_j)    (Schechter 1959, Theorem 1)
+    //
+    // where A_i(x) and B_i(x) are the Lagrange polynomials for xs and ys respectively.
+    //
+    // We adapt this to the positive Cauchy formulation by negating ys.
+    let mut mds_inv = [[F::zero(); T]; T];
+    let l = |xs: &[F], j, x: F| {
+        let x_j = xs[j];
+        xs.iter().enumerate().fold(F::one(), |acc, (m, x_m)| {
+            if m == j {
+                acc
+            } else {
+                // We can invert freely; by construction, the elements of xs are distinct.
+                acc * (x - x_m) * (x_j - x_m).invert().unwrap()
+            }
+        })
+    };
+    let neg_ys: Vec<_> = ys.iter().map(|y| -*y).collect();
+    #[allow(clippy::needless_range_loop)]
+    for i in 0..T {
+        for j in 0..T {
+            mds_inv[i][j] = (xs[j] - neg_ys[i]) * l(xs, j, neg_ys[i]) * l(&neg_ys, i, xs[j]);
+        }
+    }
+
+    (mds, mds_inv)
+}
+
+#[cfg(test)]
+mod tests {
+    use ff::Field;
+
+    use super::{generate_mds, generate_neptune_mds, is_secure, Grain};
+    use crate::{arithmetic::FieldExt, pasta::Fp, primitives::poseidon::grain::SboxType};
+
+    #[test]
+    fn poseidon_mds() {
+        fn check<const T: usize>() {
+            let mut grain = Grain::<Fp>::new(SboxType::Pow, T as u16, 8, 56);
+            let (mds, mds_inv) = generate_mds::<_, T>(&mut grain, 0);
+
+            // Verify that MDS * MDS^-1 = I.
+            #[allow(clippy::needless_range_loop)]
+            for i in 0..T {
+                for j in 0..T {
+                    let expected = if i == j { Fp::one() } else { Fp::zero() };
+                    assert_eq!(
+                        (0..T).fold(Fp::zero(), |acc, k| acc + (mds[i][k] * mds_inv[k][j])),
+                        expected
+                    );
+                }
+            }
+        }
+
+        check::<3>();
+        check::<4>();
+        check::<5>();
+        check::<9>();
+        check::<12>();
+    }
+
+    #[test]
+    fn mds_security_checks() {
+        let m = |rows: [[u64; 3]; 3]| {
+            let mut matrix = [[Fp::zero(); 3]; 3];
+            for (row, values) in matrix.iter_mut().zip(rows.iter()) {
+                for (entry, value) in row.iter_mut().zip(values.iter()) {
+                    *entry = Fp::from_u64(*value);
+                }
+            }
+            matrix
+        };
+
+        // Generated matrices are secure.
+        let mut grain = Grain::<Fp>::new(SboxType::Pow, 3, 8, 56);
+        let (mds, _) = generate_mds::<_, 3>(&mut grain, 0);
+        assert!(is_secure(&mds));
+
+        // Singular.
+        assert!(!is_secure(&m([[1, 2, 3], [2, 4, 6], [0, 0, 1]])));
+        // Scalar multiple of the identity.
+        assert!(!is_secure(&m([[2, 0, 0], [0, 2, 0], [0, 0, 2]])));
+        // span{e_0, e_1} is invariant.
+        assert!(!is_secure(&m([[1, 2, 0], [3, 4, 0], [5, 6, 7]])));
+        // span{e_2} is invariant, and the S-box is inactive on it.
+        assert!(!is_secure(&m([[1, 1, 0], [1, 2, 0], [1, 0, 3]])));
+        // No proper subspace containing e_0 is invariant under M, but M^3 is a scalar
+        // multiple of the identity.
+        assert!(!is_secure(&m([[0, 0, 2], [1, 0, 0], [0, 1, 0]])));
+    }
+
+    #[test]
+    fn neptune_mds() {
+        fn check<const T: usize>() {
+            let (mds, mds_inv) = generate_neptune_mds::<Fp, T>();
+
+            #[allow(clippy::needless_range_loop)]
+            for i in 0..T {
+                for j in 0..T {
+                    // neptune's matrix is M_ij = 1/(i + (T + j)), which we transpose.
+                    let sum = Fp::from_u64((j + T + i) as u64);
+                    assert_eq!(mds[i][j], sum.invert().unwrap());
+
+                    let expected = if i == j { Fp::one() } else { Fp::zero() };
+                    assert_eq!(
+                        (0..T).fold(Fp::zero(), |acc, k| acc + (mds[i][k] * mds_inv[k][j])),
+                        expected
+                    );
+                }
+            }
+        }
+
+        check::<3>();
+        check::<5>();
+        check::<9>();
+    }
+}
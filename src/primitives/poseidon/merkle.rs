@@ -0,0 +1,309 @@
+//! Binary Merkle trees hashed with Poseidon.
+//!
+//! Each internal node is the hash of its two children in the [`MerkleNode`] domain for
+//! its layer, where the parents of the leaves are at layer 0. This matches the hashing
+//! performed by [`crate::gadget::poseidon::MerklePath`].
+
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+use super::{Hash, MerkleNode, Spec};
+use crate::arithmetic::FieldExt;
+
+/// Returns the hash of the node at `layer` with the given children.
+pub fn hash_node<F: FieldExt, S: Spec<F, T, 2>, const T: usize>(
+    layer: u32,
+    left: F,
+    right: F,
+) -> F {
+    Hash::<F, S, _, T, 2>::hash(MerkleNode { layer }, &[left, right])
+}
+
+/// Computes the root of a tree from a leaf, its position, and its authentication path
+/// (the siblings of the nodes from the leaf up to, but excluding, the root).
+pub fn root_from_path<F: FieldExt, S: Spec<F, T, 2>, const T: usize>(
+    leaf: F,
+    position: u64,
+    path: &[F],
+) -> F {
+    path.iter()
+        .enumerate()
+        .fold(leaf, |node, (layer, sibling)| {
+            if (position >> layer) & 1 == 0 {
+                hash_node::<F, S, T>(layer as u32, node, *sibling)
+            } else {
+                hash_node::<F, S, T>(layer as u32, *sibling, node)
+            }
+        })
+}
+
+/// An append-only Merkle tree of fixed depth.
+///
+/// Leaves that have not been appended yet are treated as zero. All appended leaves are
+/// retained, so that an authentication path can be produced for any of them.
+#[derive(Debug)]
+pub struct IncrementalTree<F: FieldExt, S: Spec<F, T, 2>, const T: usize> {
+    /// `layers[0]` holds the leaves, and `layers[depth]` the root (once a leaf has
+    /// been appended).
+    layers: Vec<Vec<F>>,
+    /// `empty_roots[i]` is the root of an empty subtree of height `i`.
+    empty_roots: Vec<F>,
+    _marker: PhantomData<S>,
+}
+
+impl<F: FieldExt, S: Spec<F, T, 2>, const T: usize> IncrementalTree<F, S, T> {
+    /// Constructs an empty tree with `2^depth` leaves.
+    pub fn new(depth: usize) -> Self {
+        assert!(depth < 64);
+
+        let mut empty_roots = vec![F::zero()];
+        for layer in 0..depth {
+            let child = empty_roots[layer];
+            empty_roots.push(hash_node::<F, S, T>(layer as u32, child, child));
+        }
+
+        IncrementalTree {
+            layers: vec![vec![]; depth + 1],
+            empty_roots,
+            _marker: PhantomData,
+        }
+    }
+
+    /// The depth of this tree.
+    pub fn depth(&self) -> usize {
+        self.layers.len() - 1
+    }
+
+    /// The number of leaves that have been appended to this tree.
+    pub fn len(&self) -> u64 {
+        self.layers[0].len() as u64
+    }
+
+    /// Returns `true` if no leaves have been appended to this tree.
+    pub fn is_empty(&self) -> bool {
+        self.layers[0].is_empty()
+    }
+
+    /// Appends a leaf to the tree, returning its position, or `None` if the tree is
+    /// full.
+    pub fn append(&mut self, leaf: F) -> Option<u64> {
+        let position = self.len();
+        if position >> self.depth() != 0 {
+            return None;
+        }
+
+        self.layers[0].push(leaf);
+        let mut index = position as usize;
+        for layer in 0..self.depth() {
+            let parent = index / 2;
+            let left = self.node(layer, parent * 2);
+            let right = self.node(layer, parent * 2 + 1);
+            let hash = hash_node::<F, S, T>(layer as u32, left, right);
+
+            let parents = &mut self.layers[layer + 1];
+            if parent < parents.len() {
+                parents[parent] = hash;
+            } else {
+                parents.push(hash);
+            }
+            index = parent;
+        }
+
+        Some(position)
+    }
+
+    /// The current root of the tree.
+    pub fn root(&self) -> F {
+        self.node(self.depth(), 0)
+    }
+
+    /// Returns the authentication path for the leaf at `position`, or `None` if no leaf
+    /// has been appended at that position.
+    pub fn path(&self, position: u64) -> Option<Vec<F>> {
+        if position >= self.len() {
+            return None;
+        }
+
+        Some(
+            (0..self.depth())
+                .map(|layer| self.node(layer, ((position >> layer) ^ 1) as usize))
+                .collect(),
+        )
+    }
+
+    /// Returns the node at the given height and index, where height 0 is the leaves.
+    fn node(&self, height: usize, index: usize) -> F {
+        self.layers[height]
+            .get(index)
+            .copied()
+            .unwrap_or(self.empty_roots[height])
+    }
+}
+
+/// A sparse Merkle tree of fixed depth, in which the leaf at each key may be set.
+///
+/// Leaves that have not been set are treated as zero, so a leaf set to zero cannot be
+/// distinguished from an empty one. Only the nodes above leaves that have been set are
+/// stored.
+#[derive(Debug)]
+pub struct SparseTree<F: FieldExt, S: Spec<F, T, 2>, const T: usize> {
+    /// `layers[0]` holds the leaves that have been set, and `layers[depth]` the root
+    /// (once a leaf has been set).
+    layers: Vec<HashMap<u64, F>>,
+    /// `empty_roots[i]` is the root of an empty subtree of height `i`.
+    empty_roots: Vec<F>,
+    _marker: PhantomData<S>,
+}
+
+impl<F: FieldExt, S: Spec<F, T, 2>, const T: usize> SparseTree<F, S, T> {
+    /// Constructs an empty tree with `2^depth` leaves.
+    pub fn new(depth: usize) -> Self {
+        assert!(depth < 64);
+
+        let mut empty_roots = vec![F::zero()];
+        for layer in 0..depth {
+            let child = empty_roots[layer];
+            empty_roots.push(hash_node::<F, S, T>(layer as u32, child, child));
+        }
+
+        SparseTree {
+            layers: vec![HashMap::new(); depth + 1],
+            empty_roots,
+            _marker: PhantomData,
+        }
+    }
+
+    /// The depth of this tree.
+    pub fn depth(&self) -> usize {
+        self.layers.len() - 1
+    }
+
+    /// Returns the leaf at `key`.
+    pub fn get(&self, key: u64) -> F {
+        self.node(0, key)
+    }
+
+    /// Sets the leaf at `key` to `leaf`, returning its previous value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `key` is not less than `2^depth`.
+    pub fn set(&mut self, key: u64, leaf: F) -> F {
+        assert_eq!(key >> self.depth(), 0);
+
+        let old = self.get(key);
+        self.layers[0].insert(key, leaf);
+        let mut index = key;
+        for layer in 0..self.depth() {
+            let parent = index / 2;
+            let left = self.node(layer, parent * 2);
+            let right = self.node(layer, parent * 2 + 1);
+            let hash = hash_node::<F, S, T>(layer as u32, left, right);
+            self.layers[layer + 1].insert(parent, hash);
+            index = parent;
+        }
+
+        old
+    }
+
+    /// The current root of the tree.
+    pub fn root(&self) -> F {
+        self.node(self.depth(), 0)
+    }
+
+    /// Returns the authentication path for the leaf at `key`, which may be empty.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `key` is not less than `2^depth`.
+    pub fn path(&self, key: u64) -> Vec<F> {
+        assert_eq!(key >> self.depth(), 0);
+
+        (0..self.depth())
+            .map(|layer| self.node(layer, (key >> layer) ^ 1))
+            .collect()
+    }
+
+    /// Returns the node at the given height and index, where height 0 is the leaves.
+    fn node(&self, height: usize, index: u64) -> F {
+        self.layers[height]
+            .get(&index)
+            .copied()
+            .unwrap_or(self.empty_roots[height])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{hash_node, root_from_path, IncrementalTree, SparseTree};
+    use crate::{arithmetic::FieldExt, pasta::Fp, primitives::poseidon::P128Pow5T3};
+
+    type Tree = IncrementalTree<Fp, P128Pow5T3, 3>;
+
+    #[test]
+    fn empty_tree() {
+        let tree = Tree::new(2);
+        let empty = hash_node::<_, P128Pow5T3, 3>(0, Fp::zero(), Fp::zero());
+        assert_eq!(tree.root(), hash_node::<_, P128Pow5T3, 3>(1, empty, empty));
+        assert!(tree.is_empty());
+        assert_eq!(tree.path(0), None);
+    }
+
+    #[test]
+    fn paths_match_root() {
+        let mut tree = Tree::new(4);
+        let leaves: Vec<_> = (0..11).map(|i| Fp::from_u64(i + 100)).collect();
+
+        for (i, leaf) in leaves.iter().enumerate() {
+            assert_eq!(tree.append(*leaf), Some(i as u64));
+
+            // Every leaf appended so far has a valid path to the current root.
+            for (position, leaf) in leaves[..=i].iter().enumerate() {
+                let path = tree.path(position as u64).unwrap();
+                assert_eq!(
+                    root_from_path::<_, P128Pow5T3, 3>(*leaf, position as u64, &path),
+                    tree.root()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn full_tree() {
+        let mut tree = Tree::new(1);
+        assert_eq!(tree.append(Fp::one()), Some(0));
+        assert_eq!(tree.append(Fp::one()), Some(1));
+        assert_eq!(tree.append(Fp::one()), None);
+        assert_eq!(
+            tree.root(),
+            hash_node::<_, P128Pow5T3, 3>(0, Fp::one(), Fp::one())
+        );
+    }
+
+    #[test]
+    fn sparse_tree() {
+        let mut tree = SparseTree::<Fp, P128Pow5T3, 3>::new(8);
+        let mut incremental = Tree::new(8);
+        assert_eq!(tree.root(), incremental.root());
+
+        // Setting the first leaves in order matches appending them.
+        for i in 0..5 {
+            assert_eq!(tree.set(i, Fp::from_u64(i + 1)), Fp::zero());
+            incremental.append(Fp::from_u64(i + 1));
+            assert_eq!(tree.root(), incremental.root());
+            assert_eq!(Some(tree.path(i)), incremental.path(i));
+        }
+
+        for &(key, old) in &[(3, 4), (200, 0), (255, 0)] {
+            assert_eq!(tree.set(key, Fp::from_u64(key * 5)), Fp::from_u64(old));
+            for &key in &[2, 3, 100, 200, 255] {
+                assert_eq!(
+                    root_from_path::<_, P128Pow5T3, 3>(tree.get(key), key, &tree.path(key)),
+                    tree.root()
+                );
+            }
+        }
+        assert_eq!(tree.get(3), Fp::from_u64(15));
+        assert_eq!(tree.get(100), Fp::zero());
+    }
+}
@@ -0,0 +1,112 @@
+use ff::Field;
+
+use super::{Mds, Spec};
+use crate::pasta::{Fp, Fq};
+
+/// Poseidon-128 using the $x^5$ S-box, with a width of 3 field elements, and the
+/// standard number of rounds for 128-bit security "with margin".
+///
+/// The standard specification for this set of parameters uses $R_F = 8, R_P = 56$.
+/// This is conveniently an even number of partial rounds, making it easier to
+/// construct a Halo 2 circuit.
+///
+/// The round constants and MDS matrices for the Pasta fields are hard-coded, so no
+/// constant generation is performed at runtime.
+#[derive(Debug)]
+pub struct P128Pow5T3;
+
+macro_rules! impl_p128pow5t3 {
+    ($field:ident, $constants:ident) => {
+        impl Spec<$field, 3, 2> for P128Pow5T3 {
+            fn full_rounds() -> usize {
+                8
+            }
+
+            fn partial_rounds() -> usize {
+                56
+            }
+
+            fn sbox(val: $field) -> $field {
+                val.pow_vartime([5])
+            }
+
+            fn secure_mds() -> usize {
+                0
+            }
+
+            fn constants() -> (Vec<[$field; 3]>, Mds<$field, 3>, Mds<$field, 3>) {
+                (
+                    super::$constants::ROUND_CONSTANTS[..].to_vec(),
+                    super::$constants::MDS,
+                    super::$constants::MDS_INV,
+                )
+            }
+        }
+    };
+}
+
+impl_p128pow5t3!(Fp, fp);
+impl_p128pow5t3!(Fq, fq);
+
+#[cfg(test)]
+mod tests {
+    use std::marker::PhantomData;
+
+    use super::{super::Spec, P128Pow5T3};
+    use crate::{
+        arithmetic::FieldExt,
+        pasta::{Fp, Fq},
+    };
+
+    /// The same Poseidon specification as [`P128Pow5T3`], but with the default
+    /// (generated) constants.
+    #[derive(Debug)]
+    struct P128Pow5T3Gen<F: FieldExt>(PhantomData<F>);
+
+    impl<F: FieldExt> Spec<F, 3, 2> for P128Pow5T3Gen<F> {
+        fn full_rounds() -> usize {
+            8
+        }
+
+        fn partial_rounds() -> usize {
+            56
+        }
+
+        fn sbox(val: F) -> F {
+            val.pow_vartime([5])
+        }
+
+        fn secure_mds() -> usize {
+            0
+        }
+    }
+
+    fn verify_constants<F: FieldExt>()
+    where
+        P128Pow5T3: Spec<F, 3, 2>,
+    {
+        let (round_constants, mds, mds_inv) = <P128Pow5T3 as Spec<F, 3, 2>>::constants();
+        let (gen_round_constants, gen_mds, gen_mds_inv) = P128Pow5T3Gen::<F>::constants();
+
+        assert_eq!(round_constants, gen_round_constants);
+        assert_eq!(mds, gen_mds);
+        assert_eq!(mds_inv, gen_mds_inv);
+    }
+
+    #[test]
+    fn verify_constants_fp() {
+        verify_constants::<Fp>();
+    }
+
+    #[test]
+    fn verify_constants_fq() {
+        verify_constants::<Fq>();
+    }
+
+    #[test]
+    fn generated_constants_are_cached() {
+        let a = P128Pow5T3Gen::<Fp>::constants();
+        let b = P128Pow5T3Gen::<Fp>::constants();
+        assert_eq!(a, b);
+    }
+}
@@ -0,0 +1,248 @@
+//! The Poseidon2 permutation.
+//!
+//! Poseidon2 replaces the single MDS matrix of Poseidon with two cheaper linear layers:
+//!
+//! - an external matrix $M_E$, applied once to the input and after every full round;
+//! - an internal matrix $M_I$, applied after every partial round, which is the all-ones
+//!   matrix with its diagonal replaced by $(\mu_0, \ldots, \mu_{t-1})$.
+//!
+//! Partial rounds also only add a single round constant (to the first state word).
+//! Specifications select Poseidon2 by returning [`PermutationType::Poseidon2`] from
+//! [`Spec::permutation_type`].
+
+use super::{grain::Grain, Mds, PermutationType, SboxType, Spec, State};
+use crate::arithmetic::FieldExt;
+
+/// Poseidon2 using the $x^5$ S-box, with a width of 3 field elements, and the
+/// standard number of rounds for 128-bit security.
+///
+/// The Poseidon2 specification for this set of parameters uses $R_F = 8, R_P = 56$.
+#[derive(Debug)]
+pub struct Poseidon2Pow5T3;
+
+impl<F: FieldExt> Spec<F, 3, 2> for Poseidon2Pow5T3 {
+    fn full_rounds() -> usize {
+        8
+    }
+
+    fn partial_rounds() -> usize {
+        56
+    }
+
+    fn sbox(val: F) -> F {
+        val.pow_vartime([5])
+    }
+
+    fn permutation_type() -> PermutationType {
+        PermutationType::Poseidon2
+    }
+
+    fn secure_mds() -> usize {
+        0
+    }
+}
+
+/// Returns the external matrix $M_E$ for a Poseidon2 permutation of width `T`.
+///
+/// Poseidon2 defines this matrix for widths 2, 3, and multiples of 4.
+pub fn external_matrix<F: FieldExt, const T: usize>() -> Mds<F, T> {
+    let mut m = [[F::zero(); T]; T];
+    match T {
+        // circ(2, 1) and circ(2, 1, 1)
+        2 | 3 => {
+            for (i, row) in m.iter_mut().enumerate() {
+                for (j, m_ij) in row.iter_mut().enumerate() {
+                    *m_ij = F::from_u64(if i == j { 2 } else { 1 });
+                }
+            }
+        }
+        // circ(2 M_4, M_4, ..., M_4)
+        _ if T & 3 == 0 => {
+            const M_4: [[u64; 4]; 4] = [[5, 7, 1, 3], [4, 6, 1, 1], [1, 3, 5, 7], [1, 1, 4, 6]];
+            for (i, row) in m.iter_mut().enumerate() {
+                for (j, m_ij) in row.iter_mut().enumerate() {
+                    let scale = if i / 4 == j / 4 { 2 } else { 1 };
+                    *m_ij = F::from_u64(scale * M_4[i % 4][j % 4]);
+                }
+            }
+        }
+        _ => panic!("Poseidon2 is not defined for a width of {}", T),
+    }
+    m
+}
+
+/// Returns the internal matrix $M_I$ with the given diagonal.
+pub fn internal_matrix<F: FieldExt, const T: usize>(diagonal: &[F; T]) -> Mds<F, T> {
+    let mut m = [[F::one(); T]; T];
+    for (i, row) in m.iter_mut().enumerate() {
+        row[i] = diagonal[i];
+    }
+    m
+}
+
+/// Returns whether the internal matrix with the given diagonal is invertible.
+///
+/// Writing $M_I = J + D$ for the all-ones matrix $J$ and $D = \mathrm{diag}(\mu_i - 1)$,
+/// the matrix determinant lemma gives $\det(M_I) = \det(D) (1 + \sum_i 1/(\mu_i - 1))$.
+fn internal_matrix_is_invertible<F: FieldExt, const T: usize>(diagonal: &[F; T]) -> bool {
+    diagonal
+        .iter()
+        .try_fold(F::one(), |acc, mu| {
+            Option::<F>::from((*mu - F::one()).invert()).map(|inv| acc + inv)
+        })
+        .map(|sum| !bool::from(sum.ct_is_zero()))
+        .unwrap_or(false)
+}
+
+/// Generates `(round_constants, M_E, M_I)` for a Poseidon2 permutation.
+///
+/// Partial rounds only use the first round constant of their row; the remaining
+/// entries are zero. For widths 2 and 3 the internal matrix uses the diagonals given
+/// in the Poseidon2 paper. For wider states the diagonal is sampled from the Grain
+/// LFSR until the internal matrix is invertible; specifications that require a
+/// specific (e.g. externally-audited) diagonal should override [`Spec::constants`].
+pub(crate) fn generate_constants<F: FieldExt, const T: usize>(
+    sbox: SboxType,
+    r_f: usize,
+    r_p: usize,
+) -> (Vec<[F; T]>, Mds<F, T>, Mds<F, T>) {
+    let mut grain = Grain::new(sbox, T as u16, r_f as u16, r_p as u16);
+
+    let half_full_rounds = r_f / 2;
+    let round_constants = (0..(r_f + r_p))
+        .map(|round| {
+            let mut rc_row = [F::zero(); T];
+            if round < half_full_rounds || round >= half_full_rounds + r_p {
+                for rc in rc_row.iter_mut() {
+                    *rc = grain.next_field_element();
+                }
+            } else {
+                rc_row[0] = grain.next_field_element();
+            }
+            rc_row
+        })
+        .collect();
+
+    let diagonal = match T {
+        2 | 3 => {
+            let mut diagonal = [F::from_u64(2); T];
+            diagonal[T - 1] = F::from_u64(3);
+            diagonal
+        }
+        _ => loop {
+            let mut diagonal = [F::zero(); T];
+            for mu in diagonal.iter_mut() {
+                *mu = grain.next_field_element();
+            }
+            if internal_matrix_is_invertible(&diagonal) {
+                break diagonal;
+            }
+        },
+    };
+
+    (
+        round_constants,
+        external_matrix(),
+        internal_matrix(&diagonal),
+    )
+}
+
+/// Runs the Poseidon2 permutation on the given state.
+pub fn permute<F: FieldExt, S: Spec<F, T, RATE>, const T: usize, const RATE: usize>(
+    state: &mut State<F, T>,
+    external: &Mds<F, T>,
+    internal: &Mds<F, T>,
+    round_constants: &[[F; T]],
+) {
+    let r_f = S::full_rounds() / 2;
+    let r_p = S::partial_rounds();
+
+    let apply = |m: &Mds<F, T>, state: &mut State<F, T>| {
+        let mut new_state = [F::zero(); T];
+        // Matrix multiplication
+        #[allow(clippy::needless_range_loop)]
+        for i in 0..T {
+            for j in 0..T {
+                new_state[i] += m[i][j] * state[j];
+            }
+        }
+        *state = new_state;
+    };
+
+    let external_round = |state: &mut State<F, T>, rcs: &[F; T]| {
+        for (word, rc) in state.iter_mut().zip(rcs.iter()) {
+            *word = S::sbox(*word + rc);
+        }
+        apply(external, state);
+    };
+
+    let internal_round = |state: &mut State<F, T>, rcs: &[F; T]| {
+        state[0] = S::sbox(state[0] + rcs[0]);
+        apply(internal, state);
+    };
+
+    apply(external, state);
+
+    let mut round_constants = round_constants.iter();
+    for _ in 0..r_f {
+        external_round(state, round_constants.next().unwrap());
+    }
+    for _ in 0..r_p {
+        internal_round(state, round_constants.next().unwrap());
+    }
+    for _ in 0..r_f {
+        external_round(state, round_constants.next().unwrap());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::{permute as poseidon_permute, Hash, P128Pow5T3, SboxType, Spec, VariableLength},
+        internal_matrix_is_invertible, permute, Poseidon2Pow5T3,
+    };
+    use crate::{arithmetic::FieldExt, pasta::Fp};
+
+    #[test]
+    fn internal_matrices_are_invertible() {
+        fn check<const T: usize>() {
+            let (_, _, m_i) = super::generate_constants::<Fp, T>(SboxType::Pow, 8, 56);
+            let mut diagonal = [Fp::zero(); T];
+            for (i, mu) in diagonal.iter_mut().enumerate() {
+                *mu = m_i[i][i];
+            }
+            assert!(internal_matrix_is_invertible(&diagonal));
+        }
+
+        check::<2>();
+        check::<3>();
+        check::<4>();
+        check::<8>();
+    }
+
+    #[test]
+    fn differs_from_poseidon() {
+        let mut state = [Fp::from_u64(0), Fp::from_u64(1), Fp::from_u64(2)];
+        let mut poseidon2_state = state;
+
+        let (round_constants, mds, _) = <P128Pow5T3 as Spec<Fp, 3, 2>>::constants();
+        poseidon_permute::<_, P128Pow5T3, 3, 2>(&mut state, &mds, &round_constants);
+
+        let (round_constants, external, internal) =
+            <Poseidon2Pow5T3 as Spec<Fp, 3, 2>>::constants();
+        permute::<_, Poseidon2Pow5T3, 3, 2>(
+            &mut poseidon2_state,
+            &external,
+            &internal,
+            &round_constants,
+        );
+
+        assert_ne!(state, poseidon2_state);
+
+        // The sponge dispatches to the Poseidon2 permutation.
+        assert_ne!(
+            Hash::<Fp, P128Pow5T3, _, 3, 2>::hash(VariableLength, &[Fp::one()]),
+            Hash::<Fp, Poseidon2Pow5T3, _, 3, 2>::hash(VariableLength, &[Fp::one()])
+        );
+    }
+}
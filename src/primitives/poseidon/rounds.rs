@@ -0,0 +1,300 @@
+//! Calculation of secure round numbers for Poseidon.
+//!
+//! This implements the security inequalities from section 5.5 of the Poseidon paper
+//! (covering statistical, interpolation and Gröbner basis attacks), following the
+//! reference script `calc_round_numbers.py`. The field is described by its size in bits
+//! $n = \lceil \log_2 p \rceil$, and $\log_2 p$ is approximated by $n$.
+
+use std::error;
+use std::fmt;
+
+use super::{SboxType, Spec};
+use crate::arithmetic::FieldExt;
+
+/// The S-box of a Poseidon permutation, as used by the security inequalities.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Alpha {
+    /// The power S-box $x^\alpha$, for $\alpha \geq 3$.
+    Pow(u64),
+    /// The inverse S-box $x^{-1}$.
+    Inv,
+}
+
+/// A number of full and partial rounds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RoundNumbers {
+    /// The number of full rounds $R_F$.
+    pub full_rounds: usize,
+    /// The number of partial rounds $R_P$.
+    pub partial_rounds: usize,
+}
+
+/// An error indicating that a set of Poseidon parameters does not provide the
+/// requested security level.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InsecureParameters {
+    /// The parameters that were checked.
+    pub provided: RoundNumbers,
+    /// The smallest secure parameters (without a security margin) for the same field,
+    /// width and S-box.
+    pub minimum: RoundNumbers,
+    /// The security level in bits that was requested.
+    pub security_level: usize,
+}
+
+impl fmt::Display for InsecureParameters {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "R_F = {}, R_P = {} does not provide {}-bit security (the smallest secure choice is R_F = {}, R_P = {})",
+            self.provided.full_rounds,
+            self.provided.partial_rounds,
+            self.security_level,
+            self.minimum.full_rounds,
+            self.minimum.partial_rounds,
+        )
+    }
+}
+
+impl error::Error for InsecureParameters {}
+
+/// Returns whether the given round numbers provide `security_level` bits of security
+/// for a Poseidon permutation of width `width` over a field of `field_bits` bits.
+pub fn is_secure(
+    field_bits: u32,
+    width: usize,
+    alpha: Alpha,
+    security_level: usize,
+    rounds: RoundNumbers,
+) -> bool {
+    let n = field_bits as f64;
+    let t = width as f64;
+    let m = security_level as f64;
+    let r_f = rounds.full_rounds as f64;
+    let r_p = rounds.partial_rounds as f64;
+
+    match alpha {
+        Alpha::Pow(alpha) => {
+            assert!(alpha >= 3);
+            let log2_alpha = (alpha as f64).log2();
+            // log_alpha(2) and log_alpha(t)
+            let log_alpha_2 = 1.0 / log2_alpha;
+            let log_alpha_t = t.log2() / log2_alpha;
+
+            // Statistical attacks
+            let r_f_1 = if m <= (n - (alpha as f64 - 1.0) / 2.0).floor() * (t + 1.0) {
+                6.0
+            } else {
+                10.0
+            };
+            // Interpolation attacks
+            let r_f_2 = 1.0 + (log_alpha_2 * m.min(n)).ceil() + log_alpha_t.ceil() - r_p;
+            // Gröbner basis attacks
+            let r_f_3 = log_alpha_2 * m.min(n) - r_p;
+            let r_f_4 = t - 1.0 + log_alpha_2 * (m / (t + 1.0)).min(n / 2.0) - r_p;
+            let r_f_5 = (t - 2.0 + m / (2.0 * log2_alpha) - r_p) / (t - 1.0);
+
+            let r_f_max = [r_f_1, r_f_2, r_f_3, r_f_4, r_f_5]
+                .iter()
+                .fold(f64::MIN, |acc, r| acc.max(r.ceil()));
+            r_f >= r_f_max
+        }
+        Alpha::Inv => {
+            let log2_t = t.log2();
+
+            // Statistical attacks
+            let r_f_1 = if m <= (n - 2.0).floor() * (t + 1.0) {
+                6.0
+            } else {
+                10.0
+            };
+            // Interpolation attacks
+            let r_p_1 = 1.0 + (0.5 * m.min(n)).ceil() + log2_t.ceil() - (r_f * log2_t).floor();
+            // Gröbner basis attacks
+            let r_p_2 = t - 1.0 + log2_t.ceil() + (m / (t + 1.0)).ceil().min((0.5 * n).ceil())
+                - (r_f * log2_t).floor();
+
+            r_f >= r_f_1 && r_p >= r_p_1.max(r_p_2)
+        }
+    }
+}
+
+/// Finds the secure round numbers that minimize the number of S-boxes
+/// ($t \cdot R_F + R_P$) for the given parameters.
+///
+/// If `with_margin` is set, the security margin recommended by the Poseidon paper is
+/// added: two full rounds, and 7.5% more partial rounds. This is how the standard
+/// parameters (e.g. $R_F = 8, R_P = 56$ for $t = 3$ over a 255-bit field) are derived.
+pub fn find_round_numbers(
+    field_bits: u32,
+    width: usize,
+    alpha: Alpha,
+    security_level: usize,
+    with_margin: bool,
+) -> RoundNumbers {
+    let mut best: Option<(usize, RoundNumbers)> = None;
+
+    for partial_rounds in 1..500 {
+        for full_rounds in (4..100).step_by(2) {
+            let rounds = RoundNumbers {
+                full_rounds,
+                partial_rounds,
+            };
+            if !is_secure(field_bits, width, alpha, security_level, rounds) {
+                continue;
+            }
+
+            let rounds = if with_margin {
+                RoundNumbers {
+                    full_rounds: full_rounds + 2,
+                    partial_rounds: (partial_rounds as f64 * 1.075).ceil() as usize,
+                }
+            } else {
+                rounds
+            };
+
+            let cost = width * rounds.full_rounds + rounds.partial_rounds;
+            let better = match best {
+                None => true,
+                Some((best_cost, best_rounds)) => {
+                    cost < best_cost
+                        || (cost == best_cost && rounds.full_rounds < best_rounds.full_rounds)
+                }
+            };
+            if better {
+                best = Some((cost, rounds));
+            }
+        }
+    }
+
+    best.expect("secure parameters exist for any security level below 500 rounds")
+        .1
+}
+
+/// Checks that the given round numbers provide `security_level` bits of security.
+pub fn check_round_numbers(
+    field_bits: u32,
+    width: usize,
+    alpha: Alpha,
+    security_level: usize,
+    rounds: RoundNumbers,
+) -> Result<(), InsecureParameters> {
+    if is_secure(field_bits, width, alpha, security_level, rounds) {
+        Ok(())
+    } else {
+        Err(InsecureParameters {
+            provided: rounds,
+            minimum: find_round_numbers(field_bits, width, alpha, security_level, false),
+            security_level,
+        })
+    }
+}
+
+/// Checks that the given specification provides [`Spec::security_level`] bits of
+/// security.
+pub fn check_spec<F: FieldExt, S: Spec<F, T, RATE>, const T: usize, const RATE: usize>(
+) -> Result<(), InsecureParameters> {
+    check_round_numbers(
+        F::NUM_BITS,
+        T,
+        S::alpha(),
+        S::security_level(),
+        RoundNumbers {
+            full_rounds: S::full_rounds(),
+            partial_rounds: S::partial_rounds(),
+        },
+    )
+}
+
+impl From<SboxType> for Alpha {
+    /// Returns the S-box for the given type, assuming that power S-boxes use
+    /// $\alpha = 5$.
+    fn from(sbox: SboxType) -> Self {
+        match sbox {
+            SboxType::Pow => Alpha::Pow(5),
+            SboxType::Inv => Alpha::Inv,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_round_numbers, check_spec, find_round_numbers, Alpha, RoundNumbers};
+    use crate::{
+        arithmetic::FieldExt,
+        pasta::{Fp, Fq},
+        primitives::poseidon::{tests::P128Pow5T5, P128Pow5T3, Poseidon2Pow5T3, Spec},
+    };
+
+    fn rounds(full_rounds: usize, partial_rounds: usize) -> RoundNumbers {
+        RoundNumbers {
+            full_rounds,
+            partial_rounds,
+        }
+    }
+
+    #[test]
+    fn standard_parameters() {
+        // The parameters used by P128Pow5T3.
+        assert_eq!(
+            find_round_numbers(255, 3, Alpha::Pow(5), 128, true),
+            rounds(8, 56)
+        );
+        assert!(check_round_numbers(255, 3, Alpha::Pow(5), 128, rounds(8, 56)).is_ok());
+    }
+
+    #[test]
+    fn rejects_insecure_parameters() {
+        let err = check_round_numbers(255, 3, Alpha::Pow(5), 128, rounds(8, 20)).unwrap_err();
+        assert_eq!(err.provided, rounds(8, 20));
+        assert!(check_round_numbers(255, 3, Alpha::Pow(5), 128, err.minimum).is_ok());
+
+        assert!(check_round_numbers(255, 3, Alpha::Pow(5), 128, rounds(4, 56)).is_err());
+        assert!(check_round_numbers(255, 3, Alpha::Inv, 128, rounds(8, 20)).is_err());
+    }
+
+    #[test]
+    fn margin_adds_rounds() {
+        for &(width, alpha) in &[(3, Alpha::Pow(5)), (5, Alpha::Pow(5)), (3, Alpha::Inv)] {
+            let minimum = find_round_numbers(255, width, alpha, 128, false);
+            let with_margin = find_round_numbers(255, width, alpha, 128, true);
+            assert!(with_margin.full_rounds >= minimum.full_rounds + 2);
+            assert!(with_margin.partial_rounds >= minimum.partial_rounds);
+        }
+    }
+
+    #[test]
+    fn specs_are_secure() {
+        assert!(check_spec::<Fp, P128Pow5T3, 3, 2>().is_ok());
+        assert!(check_spec::<Fq, P128Pow5T3, 3, 2>().is_ok());
+        assert!(check_spec::<Fp, P128Pow5T5, 5, 4>().is_ok());
+        assert!(check_spec::<Fp, Poseidon2Pow5T3, 3, 2>().is_ok());
+    }
+
+    #[derive(Debug)]
+    struct Insecure;
+
+    impl<F: FieldExt> Spec<F, 3, 2> for Insecure {
+        fn full_rounds() -> usize {
+            8
+        }
+
+        fn partial_rounds() -> usize {
+            10
+        }
+
+        fn sbox(val: F) -> F {
+            val.pow_vartime([5])
+        }
+
+        fn secure_mds() -> usize {
+            0
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "does not provide 128-bit security")]
+    fn insecure_spec_panics() {
+        let _ = <Insecure as Spec<Fp, 3, 2>>::constants();
+    }
+}
@@ -0,0 +1,344 @@
+//! An equivalent representation of the Poseidon permutation with sparse partial rounds.
+//!
+//! This implements the optimization described in Appendix B of the Poseidon paper. The
+//! round constants of the partial rounds are moved so that each partial round only adds
+//! a single constant (to the S-box output), and the MDS matrix of each partial round is
+//! replaced by a sparse matrix of the form
+//!
+//! ```text
+//! [ m_00  w_1  ...  w_{t-1} ]
+//! [ v_1    1   ...     0    ]
+//! [ ...         ...         ]
+//! [ v_{t-1} 0  ...     1    ]
+//! ```
+//!
+//! at the cost of a single dense matrix multiplication at the start of the partial
+//! rounds. The permutation computed is identical to [`super::permute`].
+
+use super::{Mds, Spec, State};
+use crate::arithmetic::FieldExt;
+
+/// A sparse matrix whose only non-identity entries are in its first row and column.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SparseMatrix<F: FieldExt, const T: usize> {
+    /// The first row of the matrix.
+    pub row: [F; T],
+    /// The first column of the matrix. `col[0]` is equal to `row[0]`.
+    pub col: [F; T],
+}
+
+impl<F: FieldExt, const T: usize> SparseMatrix<F, T> {
+    /// Multiplies the given state by this matrix.
+    pub fn apply(&self, state: &mut State<F, T>) {
+        let state_0 = state[0];
+        state[0] = self
+            .row
+            .iter()
+            .zip(state.iter())
+            .fold(F::zero(), |acc, (w, s)| acc + *w * s);
+        for (word, v) in state.iter_mut().zip(self.col.iter()).skip(1) {
+            *word += *v * state_0;
+        }
+    }
+}
+
+/// The constants for the sparse representation of a Poseidon permutation.
+#[derive(Clone, Debug)]
+pub struct SparseConstants<F: FieldExt, const T: usize> {
+    /// The round constants for the full rounds, in order.
+    pub full_round_constants: Vec<[F; T]>,
+    /// The constants added to the state before the first partial round.
+    pub partial_initial_constants: [F; T],
+    /// The dense matrix applied to the state before the first partial round.
+    pub partial_initial_matrix: Mds<F, T>,
+    /// The constants added to the S-box output of each partial round. The last entry is
+    /// always zero.
+    pub partial_round_constants: Vec<F>,
+    /// The sparse matrices used by each partial round, in order.
+    pub sparse_matrices: Vec<SparseMatrix<F, T>>,
+    /// The MDS matrix used by the full rounds.
+    pub mds: Mds<F, T>,
+}
+
+impl<F: FieldExt, const T: usize> SparseConstants<F, T> {
+    /// Derives the sparse representation of the permutation defined by the given
+    /// specification.
+    pub fn for_spec<S: Spec<F, T, RATE>, const RATE: usize>() -> Self {
+        let (round_constants, mds, _) = S::constants();
+        Self::new(
+            &round_constants,
+            &mds,
+            S::full_rounds() / 2,
+            S::partial_rounds(),
+        )
+    }
+
+    /// Derives the sparse representation of a permutation with `half_full_rounds` full
+    /// rounds on either side of `partial_rounds` partial rounds.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `partial_rounds` is zero, or if the bottom-right $(t-1) \times (t-1)$
+    /// submatrix of `mds` is not invertible (which does not happen for the Cauchy
+    /// matrices generated by the default [`Spec::constants`]).
+    pub fn new(
+        round_constants: &[[F; T]],
+        mds: &Mds<F, T>,
+        half_full_rounds: usize,
+        partial_rounds: usize,
+    ) -> Self {
+        assert!(partial_rounds > 0);
+        assert_eq!(round_constants.len(), 2 * half_full_rounds + partial_rounds);
+        let mds_inv = invert(&to_vec(mds)).expect("MDS matrices are invertible");
+
+        // Move the partial round constants up, so that each partial round after the
+        // first only adds a constant to the S-box output of the preceding round:
+        //
+        //     M * S(x + c_i) + c_{i+1} = M * (S(x + c_i) + u),   u = M^{-1} * c_{i+1}
+        //                              = M * (S(x + c_i + (0, u_1, ...)) + (u_0, 0, ...))
+        let mut partial = round_constants[half_full_rounds..half_full_rounds + partial_rounds]
+            .iter()
+            .map(|rc| rc.to_vec())
+            .collect::<Vec<_>>();
+        let mut partial_round_constants = vec![F::zero(); partial_rounds];
+        for i in (0..partial_rounds - 1).rev() {
+            let u = mul_vec(&mds_inv, &partial[i + 1]);
+            for (c, u) in partial[i].iter_mut().zip(u.iter()).skip(1) {
+                *c += u;
+            }
+            partial_round_constants[i] = u[0];
+        }
+
+        // Factor each partial round's matrix N into a sparse matrix B and a matrix
+        // A = diag(1, N_hat). A commutes with the partial round S-box, so it is absorbed
+        // into the matrix of the preceding round (or the initial matrix).
+        let mut sparse_matrices = Vec::with_capacity(partial_rounds);
+        let mut a_hat: Option<Vec<Vec<F>>> = None;
+        for _ in 0..partial_rounds {
+            let n = match &a_hat {
+                None => to_vec(mds),
+                Some(a_hat) => {
+                    let a = embed(a_hat);
+                    mul(&a, &to_vec(mds))
+                }
+            };
+            let n_hat = n[1..]
+                .iter()
+                .map(|row| row[1..].to_vec())
+                .collect::<Vec<_>>();
+            let n_hat_inv = invert(&n_hat).expect("MDS submatrices are invertible");
+
+            let mut row = [F::zero(); T];
+            let mut col = [F::zero(); T];
+            row[0] = n[0][0];
+            col[0] = n[0][0];
+            for (j, w) in row.iter_mut().enumerate().skip(1) {
+                *w = (1..T).fold(F::zero(), |acc, k| acc + n[0][k] * n_hat_inv[k - 1][j - 1]);
+            }
+            for (i, v) in col.iter_mut().enumerate().skip(1) {
+                *v = n[i][0];
+            }
+            sparse_matrices.push(SparseMatrix { row, col });
+            a_hat = Some(n_hat);
+        }
+        sparse_matrices.reverse();
+
+        let mut partial_initial_constants = [F::zero(); T];
+        for (c, value) in partial_initial_constants.iter_mut().zip(partial[0].iter()) {
+            *c = *value;
+        }
+
+        Self {
+            full_round_constants: round_constants[..half_full_rounds]
+                .iter()
+                .chain(round_constants[half_full_rounds + partial_rounds..].iter())
+                .cloned()
+                .collect(),
+            partial_initial_constants,
+            partial_initial_matrix: from_vec(&embed(&a_hat.unwrap())),
+            partial_round_constants,
+            sparse_matrices,
+            mds: *mds,
+        }
+    }
+
+    /// The number of full rounds on either side of the partial rounds.
+    pub fn half_full_rounds(&self) -> usize {
+        self.full_round_constants.len() / 2
+    }
+}
+
+/// Runs the Poseidon permutation on the given state, using its sparse representation.
+pub fn permute<F: FieldExt, S: Spec<F, T, RATE>, const T: usize, const RATE: usize>(
+    state: &mut State<F, T>,
+    constants: &SparseConstants<F, T>,
+) {
+    let full_round = |state: &mut State<F, T>, rcs: &[F; T]| {
+        for (word, rc) in state.iter_mut().zip(rcs.iter()) {
+            *word = S::sbox(*word + rc);
+        }
+        *state = mul_state(&constants.mds, state);
+    };
+
+    let half_full_rounds = constants.half_full_rounds();
+    for rcs in &constants.full_round_constants[..half_full_rounds] {
+        full_round(state, rcs);
+    }
+
+    for (word, rc) in state
+        .iter_mut()
+        .zip(constants.partial_initial_constants.iter())
+    {
+        *word += rc;
+    }
+    *state = mul_state(&constants.partial_initial_matrix, state);
+    for (rc, m) in constants
+        .partial_round_constants
+        .iter()
+        .zip(constants.sparse_matrices.iter())
+    {
+        state[0] = S::sbox(state[0]) + rc;
+        m.apply(state);
+    }
+
+    for rcs in &constants.full_round_constants[half_full_rounds..] {
+        full_round(state, rcs);
+    }
+}
+
+fn mul_state<F: FieldExt, const T: usize>(m: &Mds<F, T>, state: &State<F, T>) -> State<F, T> {
+    let mut new_state = [F::zero(); T];
+    for (new, m_i) in new_state.iter_mut().zip(m.iter()) {
+        *new = m_i
+            .iter()
+            .zip(state.iter())
+            .fold(F::zero(), |acc, (m_ij, s_j)| acc + *m_ij * s_j);
+    }
+    new_state
+}
+
+fn to_vec<F: FieldExt, const T: usize>(m: &Mds<F, T>) -> Vec<Vec<F>> {
+    m.iter().map(|row| row.to_vec()).collect()
+}
+
+fn from_vec<F: FieldExt, const T: usize>(m: &[Vec<F>]) -> Mds<F, T> {
+    let mut res = [[F::zero(); T]; T];
+    for (res_row, row) in res.iter_mut().zip(m.iter()) {
+        res_row.copy_from_slice(row);
+    }
+    res
+}
+
+/// Returns the matrix `diag(1, m)`.
+fn embed<F: FieldExt>(m: &[Vec<F>]) -> Vec<Vec<F>> {
+    let n = m.len() + 1;
+    (0..n)
+        .map(|i| {
+            (0..n)
+                .map(|j| match (i, j) {
+                    (0, 0) => F::one(),
+                    (0, _) | (_, 0) => F::zero(),
+                    _ => m[i - 1][j - 1],
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn mul<F: FieldExt>(a: &[Vec<F>], b: &[Vec<F>]) -> Vec<Vec<F>> {
+    a.iter()
+        .map(|a_i| {
+            (0..b[0].len())
+                .map(|j| {
+                    a_i.iter()
+                        .zip(b.iter())
+                        .fold(F::zero(), |acc, (a_ik, b_k)| acc + *a_ik * b_k[j])
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn mul_vec<F: FieldExt>(m: &[Vec<F>], v: &[F]) -> Vec<F> {
+    m.iter()
+        .map(|m_i| {
+            m_i.iter()
+                .zip(v.iter())
+                .fold(F::zero(), |acc, (m_ij, v_j)| acc + *m_ij * v_j)
+        })
+        .collect()
+}
+
+/// Inverts the given square matrix using Gauss-Jordan elimination, returning `None` if
+/// it is singular.
+fn invert<F: FieldExt>(m: &[Vec<F>]) -> Option<Vec<Vec<F>>> {
+    let n = m.len();
+    let mut a = m.to_vec();
+    let mut inv = (0..n)
+        .map(|i| {
+            (0..n)
+                .map(|j| if i == j { F::one() } else { F::zero() })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    for col in 0..n {
+        let pivot = (col..n).find(|&row| !bool::from(a[row][col].ct_is_zero()))?;
+        a.swap(col, pivot);
+        inv.swap(col, pivot);
+
+        let scale = a[col][col].invert().unwrap();
+        for j in 0..n {
+            a[col][j] *= scale;
+            inv[col][j] *= scale;
+        }
+
+        for row in 0..n {
+            if row != col {
+                let factor = a[row][col];
+                for j in 0..n {
+                    let (a_cj, inv_cj) = (a[col][j], inv[col][j]);
+                    a[row][j] -= factor * a_cj;
+                    inv[row][j] -= factor * inv_cj;
+                }
+            }
+        }
+    }
+
+    Some(inv)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::permute as dense_permute, permute, SparseConstants};
+    use crate::{
+        arithmetic::FieldExt,
+        pasta::{Fp, Fq},
+        primitives::poseidon::{tests::P128Pow5T5, P128Pow5T3, Spec},
+    };
+
+    fn check<F: FieldExt, S: Spec<F, T, RATE>, const T: usize, const RATE: usize>() {
+        let (round_constants, mds, _) = S::constants();
+        let sparse = SparseConstants::<F, T>::for_spec::<S, RATE>();
+        assert_eq!(sparse.sparse_matrices.len(), S::partial_rounds());
+
+        for _ in 0..5 {
+            let mut state = [F::zero(); T];
+            for word in state.iter_mut() {
+                *word = F::rand();
+            }
+            let mut expected = state;
+
+            dense_permute::<F, S, T, RATE>(&mut expected, &mds, &round_constants);
+            permute::<F, S, T, RATE>(&mut state, &sparse);
+            assert_eq!(state, expected);
+        }
+    }
+
+    #[test]
+    fn sparse_matches_dense() {
+        check::<Fp, P128Pow5T3, 3, 2>();
+        check::<Fq, P128Pow5T3, 3, 2>();
+        check::<Fp, P128Pow5T5, 5, 4>();
+    }
+}
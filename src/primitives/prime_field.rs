@@ -0,0 +1,675 @@
+//! Arithmetic in prime fields of at most 256 bits.
+//!
+//! This is shared by the curves in [`crate::primitives`]. Elements are stored as
+//! canonical little-endian 64-bit limbs, and multiplied in Montgomery form. The fields
+//! of a curve that the proof system runs over, such as BN254, additionally implement
+//! [`FieldExt`](crate::arithmetic::FieldExt) with `field_ext!`.
+
+use crate::arithmetic::{adc, mac, sbb};
+
+/// Returns `a - b` and whether it borrowed.
+pub(crate) const fn sub_limbs(a: &[u64; 4], b: &[u64; 4]) -> ([u64; 4], bool) {
+    let (d0, borrow) = sbb(a[0], b[0], 0);
+    let (d1, borrow) = sbb(a[1], b[1], borrow);
+    let (d2, borrow) = sbb(a[2], b[2], borrow);
+    let (d3, borrow) = sbb(a[3], b[3], borrow);
+    ([d0, d1, d2, d3], borrow != 0)
+}
+
+/// Returns `a + b` and the carry out.
+pub(crate) const fn add_limbs(a: &[u64; 4], b: &[u64; 4]) -> ([u64; 4], u64) {
+    let (d0, carry) = adc(a[0], b[0], 0);
+    let (d1, carry) = adc(a[1], b[1], carry);
+    let (d2, carry) = adc(a[2], b[2], carry);
+    let (d3, carry) = adc(a[3], b[3], carry);
+    ([d0, d1, d2, d3], carry)
+}
+
+/// Returns `a + b mod m`, for `a, b < m`.
+pub(crate) const fn add_mod(a: &[u64; 4], b: &[u64; 4], m: &[u64; 4]) -> [u64; 4] {
+    let (sum, carry) = add_limbs(a, b);
+    let (reduced, borrow) = sub_limbs(&sum, m);
+    if carry != 0 || !borrow {
+        reduced
+    } else {
+        sum
+    }
+}
+
+/// Returns `a - b mod m`, for `a, b < m`.
+pub(crate) const fn sub_mod(a: &[u64; 4], b: &[u64; 4], m: &[u64; 4]) -> [u64; 4] {
+    let (diff, borrow) = sub_limbs(a, b);
+    if borrow {
+        add_limbs(&diff, m).0
+    } else {
+        diff
+    }
+}
+
+/// Returns $-m^{-1} \bmod 2^{64}$.
+pub(crate) const fn compute_inv(m: &[u64; 4]) -> u64 {
+    let mut inv = 1u64;
+    let mut i = 0;
+    while i < 63 {
+        inv = inv.wrapping_mul(inv);
+        inv = inv.wrapping_mul(m[0]);
+        i += 1;
+    }
+    inv.wrapping_neg()
+}
+
+/// Returns $2^{512} \bmod m$.
+pub(crate) const fn compute_r2(m: &[u64; 4]) -> [u64; 4] {
+    let mut r = [1, 0, 0, 0];
+    let mut i = 0;
+    while i < 512 {
+        r = add_mod(&r, &r, m);
+        i += 1;
+    }
+    r
+}
+
+/// Returns $a b 2^{-256} \bmod m$.
+pub(crate) fn mont_mul(a: &[u64; 4], b: &[u64; 4], m: &[u64; 4], inv: u64) -> [u64; 4] {
+    let mut t = [0u64; 6];
+    for b_i in b.iter() {
+        let mut carry = 0;
+        for j in 0..4 {
+            let (limb, c) = mac(t[j], a[j], *b_i, carry);
+            t[j] = limb;
+            carry = c;
+        }
+        let (limb, c) = adc(t[4], carry, 0);
+        t[4] = limb;
+        t[5] = c;
+
+        let factor = t[0].wrapping_mul(inv);
+        let (_, mut carry) = mac(t[0], factor, m[0], 0);
+        for j in 1..4 {
+            let (limb, c) = mac(t[j], factor, m[j], carry);
+            t[j - 1] = limb;
+            carry = c;
+        }
+        let (limb, c) = adc(t[4], carry, 0);
+        t[3] = limb;
+        t[4] = t[5] + c;
+    }
+
+    let result = [t[0], t[1], t[2], t[3]];
+    let (reduced, borrow) = sub_limbs(&result, m);
+    if t[4] != 0 || !borrow {
+        reduced
+    } else {
+        result
+    }
+}
+
+macro_rules! field_element {
+    ($name:ident, $modulus:expr) => {
+        impl $name {
+            /// The modulus, as little-endian limbs.
+            pub const MODULUS: [u64; 4] = $modulus;
+            const INV: u64 = $crate::primitives::prime_field::compute_inv(&Self::MODULUS);
+            const R2: [u64; 4] = $crate::primitives::prime_field::compute_r2(&Self::MODULUS);
+
+            /// Returns zero.
+            pub const fn zero() -> Self {
+                $name([0; 4])
+            }
+
+            /// Returns one.
+            pub const fn one() -> Self {
+                $name([1, 0, 0, 0])
+            }
+
+            /// Returns the element with the given value.
+            pub const fn from_u64(value: u64) -> Self {
+                $name([value, 0, 0, 0])
+            }
+
+            /// Returns the element with the given little-endian limbs, or `None` if
+            /// they are not less than the modulus.
+            pub fn from_limbs(limbs: [u64; 4]) -> Option<Self> {
+                if $crate::primitives::prime_field::sub_limbs(&limbs, &Self::MODULUS).1 {
+                    Some($name(limbs))
+                } else {
+                    None
+                }
+            }
+
+            /// Returns the element congruent to the integer with the given
+            /// little-endian limbs.
+            pub const fn reduce(mut limbs: [u64; 4]) -> Self {
+                loop {
+                    let (reduced, borrow) =
+                        $crate::primitives::prime_field::sub_limbs(&limbs, &Self::MODULUS);
+                    if borrow {
+                        return $name(limbs);
+                    }
+                    limbs = reduced;
+                }
+            }
+
+            /// The canonical little-endian limbs of this element.
+            pub fn to_limbs(&self) -> [u64; 4] {
+                self.0
+            }
+
+            /// Returns the element with the given big-endian encoding, or `None` if it
+            /// is not canonical.
+            pub fn from_be_bytes(bytes: &[u8; 32]) -> Option<Self> {
+                Self::from_limbs(Self::limbs_from_be_bytes(bytes))
+            }
+
+            /// Returns the element congruent to the integer with the given big-endian
+            /// encoding.
+            pub fn from_be_bytes_reduced(bytes: &[u8; 32]) -> Self {
+                Self::reduce(Self::limbs_from_be_bytes(bytes))
+            }
+
+            /// The canonical big-endian encoding of this element.
+            pub fn to_be_bytes(&self) -> [u8; 32] {
+                let mut bytes = [0; 32];
+                for (chunk, limb) in bytes.chunks_mut(8).zip(self.0.iter().rev()) {
+                    chunk.copy_from_slice(&limb.to_be_bytes());
+                }
+                bytes
+            }
+
+            fn limbs_from_be_bytes(bytes: &[u8; 32]) -> [u64; 4] {
+                let mut limbs = [0; 4];
+                for (limb, chunk) in limbs.iter_mut().rev().zip(bytes.chunks(8)) {
+                    let mut buf = [0; 8];
+                    buf.copy_from_slice(chunk);
+                    *limb = u64::from_be_bytes(buf);
+                }
+                limbs
+            }
+
+            /// Returns the element with the given little-endian encoding, or `None` if
+            /// it is not canonical.
+            pub fn from_le_bytes(bytes: &[u8; 32]) -> Option<Self> {
+                let mut reversed = *bytes;
+                reversed.reverse();
+                Self::from_be_bytes(&reversed)
+            }
+
+            /// The canonical little-endian encoding of this element.
+            pub fn to_le_bytes(&self) -> [u8; 32] {
+                let mut bytes = self.to_be_bytes();
+                bytes.reverse();
+                bytes
+            }
+
+            /// Returns whether this element is zero.
+            pub fn is_zero(&self) -> bool {
+                self.0 == [0; 4]
+            }
+
+            /// Returns the square of this element.
+            pub fn square(&self) -> Self {
+                *self * *self
+            }
+
+            /// Returns this element raised to the power with the given little-endian
+            /// limbs.
+            pub fn pow(&self, exp: &[u64; 4]) -> Self {
+                let mut res = Self::one();
+                for limb in exp.iter().rev() {
+                    for i in (0..64).rev() {
+                        res = res.square();
+                        if (limb >> i) & 1 == 1 {
+                            res = res * *self;
+                        }
+                    }
+                }
+                res
+            }
+
+            /// Returns the multiplicative inverse of this element, or `None` if it is
+            /// zero.
+            pub fn invert(&self) -> Option<Self> {
+                if self.is_zero() {
+                    None
+                } else {
+                    Some(
+                        self.pow(
+                            &$crate::primitives::prime_field::sub_limbs(
+                                &Self::MODULUS,
+                                &[2, 0, 0, 0],
+                            )
+                            .0,
+                        ),
+                    )
+                }
+            }
+        }
+
+        impl std::ops::Add for $name {
+            type Output = $name;
+
+            fn add(self, other: $name) -> $name {
+                $name($crate::primitives::prime_field::add_mod(
+                    &self.0,
+                    &other.0,
+                    &Self::MODULUS,
+                ))
+            }
+        }
+
+        impl std::ops::Sub for $name {
+            type Output = $name;
+
+            fn sub(self, other: $name) -> $name {
+                $name($crate::primitives::prime_field::sub_mod(
+                    &self.0,
+                    &other.0,
+                    &Self::MODULUS,
+                ))
+            }
+        }
+
+        impl std::ops::Neg for $name {
+            type Output = $name;
+
+            fn neg(self) -> $name {
+                $name::zero() - self
+            }
+        }
+
+        impl std::ops::Mul for $name {
+            type Output = $name;
+
+            fn mul(self, other: $name) -> $name {
+                let product = $crate::primitives::prime_field::mont_mul(
+                    &self.0,
+                    &other.0,
+                    &Self::MODULUS,
+                    Self::INV,
+                );
+                $name($crate::primitives::prime_field::mont_mul(
+                    &product,
+                    &Self::R2,
+                    &Self::MODULUS,
+                    Self::INV,
+                ))
+            }
+        }
+    };
+}
+
+pub(crate) use field_element;
+
+/// Implements [`FieldExt`](crate::arithmetic::FieldExt), and the `ff` traits that it
+/// extends, for a field defined with `field_element!`, so that the proof system can
+/// run over it.
+///
+/// The constants are those of `FieldExt`, given as canonical little-endian limbs, along
+/// with the multiplicative generator $g$ of the field, from which `ROOT_OF_UNITY` is
+/// $g^T$ and `DELTA` is $g^{2^S}$. Square roots are computed with Tonelli-Shanks, which,
+/// like the rest of this module, is not constant-time.
+macro_rules! field_ext {
+    (
+        $name:ident,
+        modulus: $modulus_str:expr,
+        num_bits: $num_bits:expr,
+        s: $s:expr,
+        generator: $generator:expr,
+        root_of_unity: $root_of_unity:expr,
+        root_of_unity_inv: $root_of_unity_inv:expr,
+        t_minus1_over2: $t_minus1_over2:expr,
+        delta: $delta:expr,
+        two_inv: $two_inv:expr,
+        rescue_alpha: $rescue_alpha:expr,
+        rescue_invalpha: $rescue_invalpha:expr,
+        zeta: $zeta:expr $(,)?
+    ) => {
+        impl $name {
+            /// Returns the element congruent to the integer with the given
+            /// little-endian limbs, as [`Self::reduce`] does.
+            pub const fn from_raw(limbs: [u64; 4]) -> Self {
+                Self::reduce(limbs)
+            }
+
+            /// Returns a square root of `self`, or `None` if it is not a square.
+            fn tonelli_shanks(&self) -> Option<Self> {
+                // With p - 1 = 2^S T, x = a^((T + 1) / 2) satisfies x^2 = a b for
+                // b = a^T, whose order divides 2^(S - 1) exactly when a is a square.
+                // Each step multiplies b by a smaller power of two root of unity, until
+                // b = 1.
+                let w = self.pow(&$t_minus1_over2);
+                let mut x = *self * w;
+                let mut b = x * w;
+                let mut z = <Self as $crate::arithmetic::FieldExt>::ROOT_OF_UNITY;
+                let mut v = $s;
+                while b != Self::one() {
+                    let mut k = 0;
+                    let mut b2k = b;
+                    while b2k != Self::one() {
+                        b2k = b2k.square();
+                        k += 1;
+                    }
+                    if k == v {
+                        return None;
+                    }
+                    let w = (0..v - k - 1).fold(z, |w, _| w.square());
+                    z = w.square();
+                    b = b * z;
+                    x = x * w;
+                    v = k;
+                }
+                Some(x)
+            }
+        }
+
+        impl<'a> std::ops::Neg for &'a $name {
+            type Output = $name;
+
+            fn neg(self) -> $name {
+                -*self
+            }
+        }
+
+        $crate::primitives::prime_field::impl_ref_binop!($name, Add, add, AddAssign, add_assign);
+        $crate::primitives::prime_field::impl_ref_binop!($name, Sub, sub, SubAssign, sub_assign);
+        $crate::primitives::prime_field::impl_ref_binop!($name, Mul, mul, MulAssign, mul_assign);
+
+        impl Default for $name {
+            fn default() -> Self {
+                Self::zero()
+            }
+        }
+
+        impl From<bool> for $name {
+            fn from(bit: bool) -> Self {
+                Self::from_u64(bit as u64)
+            }
+        }
+
+        impl From<u64> for $name {
+            fn from(value: u64) -> Self {
+                Self::from_u64(value)
+            }
+        }
+
+        impl subtle::ConstantTimeEq for $name {
+            fn ct_eq(&self, other: &Self) -> subtle::Choice {
+                let ct_eq = subtle::ConstantTimeEq::ct_eq;
+                ct_eq(&self.0[0], &other.0[0])
+                    & ct_eq(&self.0[1], &other.0[1])
+                    & ct_eq(&self.0[2], &other.0[2])
+                    & ct_eq(&self.0[3], &other.0[3])
+            }
+        }
+
+        impl subtle::ConditionallySelectable for $name {
+            fn conditional_select(a: &Self, b: &Self, choice: subtle::Choice) -> Self {
+                let select = <u64 as subtle::ConditionallySelectable>::conditional_select;
+                $name([
+                    select(&a.0[0], &b.0[0], choice),
+                    select(&a.0[1], &b.0[1], choice),
+                    select(&a.0[2], &b.0[2], choice),
+                    select(&a.0[3], &b.0[3], choice),
+                ])
+            }
+        }
+
+        impl std::cmp::Ord for $name {
+            fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+                self.0.iter().rev().cmp(other.0.iter().rev())
+            }
+        }
+
+        impl std::cmp::PartialOrd for $name {
+            fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        impl $crate::arithmetic::Group for $name {
+            type Scalar = $name;
+
+            fn group_zero() -> Self {
+                Self::zero()
+            }
+            fn group_add(&mut self, rhs: &Self) {
+                *self = *self + *rhs;
+            }
+            fn group_sub(&mut self, rhs: &Self) {
+                *self = *self - *rhs;
+            }
+            fn group_scale(&mut self, by: &Self::Scalar) {
+                *self = *self * *by;
+            }
+        }
+
+        impl ff::Field for $name {
+            fn random(mut rng: impl rand::RngCore) -> Self {
+                let mut random_bytes = [0; 64];
+                rng.fill_bytes(&mut random_bytes[..]);
+
+                <Self as $crate::arithmetic::FieldExt>::from_bytes_wide(&random_bytes)
+            }
+
+            fn zero() -> Self {
+                Self::zero()
+            }
+
+            fn one() -> Self {
+                Self::one()
+            }
+
+            fn is_zero(&self) -> bool {
+                Self::is_zero(self)
+            }
+
+            fn double(&self) -> Self {
+                *self + *self
+            }
+
+            fn square(&self) -> Self {
+                Self::square(self)
+            }
+
+            fn sqrt(&self) -> subtle::CtOption<Self> {
+                let root = self.tonelli_shanks();
+                subtle::CtOption::new(
+                    root.unwrap_or_else(Self::zero),
+                    subtle::Choice::from(root.is_some() as u8),
+                )
+            }
+
+            fn invert(&self) -> subtle::CtOption<Self> {
+                let inverse = Self::invert(self);
+                subtle::CtOption::new(
+                    inverse.unwrap_or_else(Self::zero),
+                    subtle::Choice::from(inverse.is_some() as u8),
+                )
+            }
+        }
+
+        impl ff::PrimeField for $name {
+            type Repr = [u8; 32];
+            #[cfg(target_pointer_width = "64")]
+            type ReprBits = [u64; 4];
+            #[cfg(not(target_pointer_width = "64"))]
+            type ReprBits = [u32; 8];
+
+            const NUM_BITS: u32 = $num_bits;
+            const CAPACITY: u32 = $num_bits - 1;
+            const S: u32 = $s;
+
+            fn from_repr(repr: Self::Repr) -> Option<Self> {
+                Self::from_le_bytes(&repr)
+            }
+
+            fn to_repr(&self) -> Self::Repr {
+                self.to_le_bytes()
+            }
+
+            fn to_le_bits(&self) -> ff::FieldBits<Self::ReprBits> {
+                ff::FieldBits::new($crate::primitives::prime_field::repr_bits(&self.0))
+            }
+
+            fn is_odd(&self) -> bool {
+                self.0[0] & 1 == 1
+            }
+
+            fn char_le_bits() -> ff::FieldBits<Self::ReprBits> {
+                ff::FieldBits::new($crate::primitives::prime_field::repr_bits(&Self::MODULUS))
+            }
+
+            fn multiplicative_generator() -> Self {
+                Self::from_u64($generator)
+            }
+
+            fn root_of_unity() -> Self {
+                <Self as $crate::arithmetic::FieldExt>::ROOT_OF_UNITY
+            }
+        }
+
+        impl $crate::arithmetic::FieldExt for $name {
+            const MODULUS: &'static str = $modulus_str;
+            const ROOT_OF_UNITY: Self = $name($root_of_unity);
+            const ROOT_OF_UNITY_INV: Self = $name($root_of_unity_inv);
+            const T_MINUS1_OVER2: [u64; 4] = $t_minus1_over2;
+            const DELTA: Self = $name($delta);
+            const TWO_INV: Self = $name($two_inv);
+            const RESCUE_ALPHA: u64 = $rescue_alpha;
+            const RESCUE_INVALPHA: [u64; 4] = $rescue_invalpha;
+            const ZETA: Self = $name($zeta);
+
+            fn sqrt_ratio(num: &Self, div: &Self) -> (subtle::Choice, Self) {
+                let ratio = match Self::invert(div) {
+                    _ if num.is_zero() => return (subtle::Choice::from(1), Self::zero()),
+                    None => return (subtle::Choice::from(0), Self::zero()),
+                    Some(div_inv) => *num * div_inv,
+                };
+                match ratio.tonelli_shanks() {
+                    Some(root) => (subtle::Choice::from(1), root),
+                    None => (
+                        subtle::Choice::from(0),
+                        (ratio * Self::ROOT_OF_UNITY).tonelli_shanks().unwrap(),
+                    ),
+                }
+            }
+
+            fn ct_is_zero(&self) -> subtle::Choice {
+                subtle::Choice::from(Self::is_zero(self) as u8)
+            }
+
+            fn from_u64(v: u64) -> Self {
+                $name::from_u64(v)
+            }
+
+            fn from_u128(v: u128) -> Self {
+                $name::from_raw([v as u64, (v >> 64) as u64, 0, 0])
+            }
+
+            fn to_bytes(&self) -> [u8; 32] {
+                self.to_le_bytes()
+            }
+
+            fn from_bytes(bytes: &[u8; 32]) -> subtle::CtOption<Self> {
+                let element = Self::from_le_bytes(bytes);
+                subtle::CtOption::new(
+                    element.unwrap_or_else(Self::zero),
+                    subtle::Choice::from(element.is_some() as u8),
+                )
+            }
+
+            fn from_bytes_wide(bytes: &[u8; 64]) -> Self {
+                let limbs = |bytes: &[u8]| {
+                    let mut limbs = [0u64; 4];
+                    for (limb, chunk) in limbs.iter_mut().zip(bytes.chunks(8)) {
+                        let mut buf = [0u8; 8];
+                        buf.copy_from_slice(chunk);
+                        *limb = u64::from_le_bytes(buf);
+                    }
+                    limbs
+                };
+                // 2^256 mod m is the Montgomery product of 2^512 and one.
+                let two_256 = $name($crate::primitives::prime_field::mont_mul(
+                    &Self::R2,
+                    &[1, 0, 0, 0],
+                    &Self::MODULUS,
+                    Self::INV,
+                ));
+                Self::reduce(limbs(&bytes[..32])) + Self::reduce(limbs(&bytes[32..])) * two_256
+            }
+
+            fn get_lower_128(&self) -> u128 {
+                u128::from(self.0[0]) | (u128::from(self.0[1]) << 64)
+            }
+
+            fn get_lower_32(&self) -> u32 {
+                self.0[0] as u32
+            }
+        }
+    };
+}
+
+pub(crate) use field_ext;
+
+/// Implements `$trait` for references to `$name`, and `$assign_trait` for `$name`, in
+/// terms of the implementation of `$trait` for `$name`.
+macro_rules! impl_ref_binop {
+    ($name:ident, $trait:ident, $fn:ident, $assign_trait:ident, $assign_fn:ident) => {
+        impl<'b> std::ops::$trait<&'b $name> for $name {
+            type Output = $name;
+
+            fn $fn(self, other: &'b $name) -> $name {
+                std::ops::$trait::$fn(self, *other)
+            }
+        }
+
+        impl<'a, 'b> std::ops::$trait<&'b $name> for &'a $name {
+            type Output = $name;
+
+            fn $fn(self, other: &'b $name) -> $name {
+                std::ops::$trait::$fn(*self, *other)
+            }
+        }
+
+        impl<'a> std::ops::$trait<$name> for &'a $name {
+            type Output = $name;
+
+            fn $fn(self, other: $name) -> $name {
+                std::ops::$trait::$fn(*self, other)
+            }
+        }
+
+        impl std::ops::$assign_trait for $name {
+            fn $assign_fn(&mut self, other: $name) {
+                *self = std::ops::$trait::$fn(*self, other);
+            }
+        }
+
+        impl<'b> std::ops::$assign_trait<&'b $name> for $name {
+            fn $assign_fn(&mut self, other: &'b $name) {
+                *self = std::ops::$trait::$fn(*self, *other);
+            }
+        }
+    };
+}
+
+pub(crate) use impl_ref_binop;
+
+/// Returns the limbs of `ff::FieldBits` for an element with the little-endian limbs
+/// `limbs`.
+#[cfg(target_pointer_width = "64")]
+pub(crate) fn repr_bits(limbs: &[u64; 4]) -> [u64; 4] {
+    *limbs
+}
+
+/// Returns the limbs of `ff::FieldBits` for an element with the little-endian limbs
+/// `limbs`.
+#[cfg(not(target_pointer_width = "64"))]
+pub(crate) fn repr_bits(limbs: &[u64; 4]) -> [u32; 8] {
+    let mut bits = [0; 8];
+    for (i, limb) in limbs.iter().enumerate() {
+        bits[2 * i] = *limb as u32;
+        bits[2 * i + 1] = (*limb >> 32) as u32;
+    }
+    bits
+}
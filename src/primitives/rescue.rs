@@ -0,0 +1,193 @@
+//! The Rescue-Prime permutation.
+//!
+//! Each round of Rescue-Prime applies a forward S-box layer ($x^\alpha$), followed by an
+//! inverse S-box layer ($x^{1/\alpha}$), with an MDS matrix multiplication and round
+//! constant addition after each. The exponents are given by
+//! [`FieldExt::RESCUE_ALPHA`] and [`FieldExt::RESCUE_INVALPHA`]. The inverse S-box has a high degree natively, but a
+//! low degree to verify, which gives Rescue-Prime a different algebraic profile to
+//! Poseidon for the same width.
+//!
+//! The sponge construction is the same as for [`crate::primitives::poseidon`], with the
+//! capacity element placed after the rate, and uses the same [`Domain`]s. This matches
+//! the in-circuit hashing performed by [`crate::gadget::rescue::RescueChip`] with the
+//! [`crate::gadget::poseidon::Hash`] gadget.
+//!
+//! # Constants
+//!
+//! The round constants and MDS matrix are sampled from the same Grain LFSR as Poseidon
+//! (as the constants of a Poseidon instance with $2N$ full rounds and no partial rounds),
+//! rather than from SHAKE-256 as in the Rescue-Prime reference implementation. Outputs
+//! therefore do not match other Rescue-Prime implementations.
+
+use std::fmt;
+
+use crate::arithmetic::FieldExt;
+use crate::primitives::poseidon::{grain::Grain, mds, sponge_hash, Domain, Mds, SboxType, State};
+
+/// A specification for a Rescue-Prime permutation.
+///
+/// The permutation has a width of `T` field elements, of which `RATE` elements are
+/// absorbed or squeezed per invocation when used in a sponge.
+pub trait RescueSpec<F: FieldExt, const T: usize, const RATE: usize>: fmt::Debug {
+    /// The number of rounds $N$ for this specification. Each round applies both S-box
+    /// layers.
+    fn rounds() -> usize;
+
+    /// Side-loaded index of the first correct and secure MDS that will be generated by
+    /// Grain. See [`crate::primitives::poseidon::Spec::secure_mds`].
+    fn secure_mds() -> usize;
+
+    /// Generates `(round_constants, mds)` corresponding to this specification. There
+    /// are `2N` round constant rows, one for each half-round.
+    fn constants() -> (Vec<[F; T]>, Mds<F, T>) {
+        let half_rounds = 2 * Self::rounds();
+        let mut grain = Grain::new(SboxType::Pow, T as u16, half_rounds as u16, 0);
+
+        let round_constants = (0..half_rounds)
+            .map(|_| {
+                let mut rc_row = [F::zero(); T];
+                for rc in rc_row.iter_mut() {
+                    *rc = grain.next_field_element();
+                }
+                rc_row
+            })
+            .collect();
+
+        let (mds, _) = mds::generate_mds(&mut grain, Self::secure_mds());
+
+        (round_constants, mds)
+    }
+}
+
+/// Rescue-Prime with a width of 3 field elements and 128-bit security.
+#[derive(Debug)]
+pub struct RescuePrimeT3;
+
+impl<F: FieldExt> RescueSpec<F, 3, 2> for RescuePrimeT3 {
+    fn rounds() -> usize {
+        rounds(3, 1, F::RESCUE_ALPHA, 128)
+    }
+
+    fn secure_mds() -> usize {
+        0
+    }
+}
+
+/// Returns the number of rounds $N$ of Rescue-Prime that provides `security_level` bits
+/// of security, following `get_number_of_rounds` in the Rescue-Prime reference
+/// implementation.
+///
+/// This is the smallest number of rounds that resists Gröbner basis attacks, with a
+/// minimum of 5, plus a 50% margin.
+pub fn rounds(width: usize, capacity: usize, alpha: u64, security_level: usize) -> usize {
+    assert!(capacity < width);
+    let rate = width - capacity;
+
+    let log2_binomial = |n: usize, k: usize| {
+        (1..=k).fold(0.0, |acc, i| {
+            acc + ((n - k + i) as f64).log2() - (i as f64).log2()
+        })
+    };
+
+    let l1 = (1..25)
+        .find(|&l| {
+            let v = width * (l - 1) + rate;
+            let d_con = (0.5 * (alpha - 1) as f64 * width as f64 * (l - 1) as f64 + 2.0) as usize;
+            2.0 * log2_binomial(v + d_con, v) > security_level as f64
+        })
+        .unwrap_or(25);
+
+    (1.5 * l1.max(5) as f64).ceil() as usize
+}
+
+/// Runs the Rescue-Prime permutation on the given state.
+pub fn permute<F: FieldExt, S: RescueSpec<F, T, RATE>, const T: usize, const RATE: usize>(
+    state: &mut State<F, T>,
+    mds: &Mds<F, T>,
+    round_constants: &[[F; T]],
+) {
+    let alpha = [F::RESCUE_ALPHA, 0, 0, 0];
+
+    let linear_layer = |state: &mut State<F, T>, rcs: &[F; T]| {
+        let mut new_state = [F::zero(); T];
+        #[allow(clippy::needless_range_loop)]
+        for i in 0..T {
+            for j in 0..T {
+                new_state[i] += mds[i][j] * state[j];
+            }
+            new_state[i] += rcs[i];
+        }
+        *state = new_state;
+    };
+
+    for rcs in round_constants.chunks(2) {
+        for word in state.iter_mut() {
+            *word = word.pow_vartime(alpha);
+        }
+        linear_layer(state, &rcs[0]);
+
+        for word in state.iter_mut() {
+            *word = word.pow_vartime(F::RESCUE_INVALPHA);
+        }
+        linear_layer(state, &rcs[1]);
+    }
+}
+
+/// Hashes the given message within the given domain, using the same sponge
+/// construction as [`crate::primitives::poseidon::Hash`].
+pub fn hash<
+    F: FieldExt,
+    S: RescueSpec<F, T, RATE>,
+    D: Domain<F, RATE>,
+    const T: usize,
+    const RATE: usize,
+>(
+    domain: D,
+    message: &[F],
+) -> F {
+    let (round_constants, mds) = S::constants();
+    sponge_hash(domain, message, |state| {
+        permute::<F, S, T, RATE>(state, &mds, &round_constants)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{hash, permute, rounds, RescuePrimeT3, RescueSpec};
+    use crate::{
+        arithmetic::FieldExt,
+        pasta::Fp,
+        primitives::poseidon::{ConstantLength, Domain, VariableLength},
+    };
+
+    #[test]
+    fn round_numbers() {
+        assert_eq!(rounds(3, 1, 5, 128), 14);
+        // The parameters from the Rescue-Prime paper for a width of 12.
+        assert_eq!(rounds(12, 4, 5, 128), 8);
+    }
+
+    #[test]
+    fn hash_is_consistent() {
+        let (round_constants, mds) = <RescuePrimeT3 as RescueSpec<Fp, 3, 2>>::constants();
+        assert_eq!(round_constants.len(), 28);
+
+        let message = [Fp::from_u64(1), Fp::from_u64(2)];
+        let mut state = [
+            message[0],
+            message[1],
+            Domain::<Fp, 2>::initial_capacity_element(&ConstantLength::<2>),
+        ];
+        permute::<_, RescuePrimeT3, 3, 2>(&mut state, &mds, &round_constants);
+        assert_eq!(
+            hash::<_, RescuePrimeT3, _, 3, 2>(ConstantLength::<2>, &message),
+            state[0]
+        );
+
+        assert_ne!(
+            hash::<_, RescuePrimeT3, _, 3, 2>(VariableLength, &message),
+            state[0]
+        );
+    }
+}
@@ -0,0 +1,176 @@
+//! RSA signatures and accumulators.
+//!
+//! Signatures use the PKCS #1 v1.5 encoding of a SHA-256 digest: a signature $s$ on a
+//! digest $H$ under the public key $(n, e)$ is valid if $s < n$ and
+//! $s^e \bmod n = \mathsf{EM}$, where the encoded message
+//! $\mathsf{EM} = \mathtt{00\,01}\,\mathtt{FF} \dots \mathtt{FF}\,\mathtt{00}
+//! \,\mathsf{DigestInfo}\,H$ has the length in bytes of $n$.
+//!
+//! An accumulator of a set of elements $x_i$, which are usually primes, is
+//! $A = g^{\prod_i x_i} \bmod n$ for a base $g$ and a modulus $n$ of unknown
+//! factorization. A membership witness for $x$ is the accumulator $w$ of the other
+//! elements, and is valid if $w^x \bmod n = A$.
+
+use super::biguint::BigUint;
+
+/// The DER encoding of the `DigestInfo` prefix of a SHA-256 digest.
+pub const SHA256_DIGEST_INFO: [u8; 19] = [
+    0x30, 0x31, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01, 0x05,
+    0x00, 0x04, 0x20,
+];
+
+/// The number of bytes of a SHA-256 digest.
+pub const DIGEST_BYTES: usize = 32;
+
+/// An RSA public key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PublicKey {
+    /// The modulus $n$.
+    pub modulus: BigUint,
+    /// The public exponent $e$.
+    pub exponent: BigUint,
+}
+
+impl PublicKey {
+    /// The length in bytes of the modulus, and of encoded messages.
+    pub fn modulus_bytes(&self) -> usize {
+        self.modulus.bits().div_ceil(8)
+    }
+}
+
+/// Returns the PKCS #1 v1.5 encoding of `digest` in `len` bytes, as an integer.
+///
+/// Returns `None` if `len` is too short, which requires at least eight bytes of padding.
+pub fn encode_pkcs1_v15(digest: &[u8; DIGEST_BYTES], len: usize) -> Option<BigUint> {
+    let padding = len.checked_sub(3 + SHA256_DIGEST_INFO.len() + DIGEST_BYTES)?;
+    if padding < 8 {
+        return None;
+    }
+
+    let mut encoded = vec![0x00, 0x01];
+    encoded.extend(std::iter::repeat_n(0xff, padding));
+    encoded.push(0x00);
+    encoded.extend_from_slice(&SHA256_DIGEST_INFO);
+    encoded.extend_from_slice(digest);
+    Some(BigUint::from_bytes_be(&encoded))
+}
+
+/// Signs `digest` with the private exponent `d` of `public_key`.
+///
+/// Returns `None` if the modulus is too short for the encoding.
+pub fn sign_pkcs1_v15(
+    public_key: &PublicKey,
+    d: &BigUint,
+    digest: &[u8; DIGEST_BYTES],
+) -> Option<BigUint> {
+    encode_pkcs1_v15(digest, public_key.modulus_bytes())
+        .map(|encoded| encoded.pow_mod(d, &public_key.modulus))
+}
+
+/// Returns whether `signature` is a valid signature of `digest` by `public_key`.
+pub fn verify_pkcs1_v15(
+    public_key: &PublicKey,
+    digest: &[u8; DIGEST_BYTES],
+    signature: &BigUint,
+) -> bool {
+    *signature < public_key.modulus
+        && encode_pkcs1_v15(digest, public_key.modulus_bytes()).is_some_and(|encoded| {
+            signature.pow_mod(&public_key.exponent, &public_key.modulus) == encoded
+        })
+}
+
+/// Returns the accumulator of `elements` with the base `g` modulo `modulus`.
+pub fn accumulate(g: &BigUint, elements: &[BigUint], modulus: &BigUint) -> BigUint {
+    elements
+        .iter()
+        .fold(g.rem(modulus), |acc, x| acc.pow_mod(x, modulus))
+}
+
+/// Returns whether `witness` proves that `element` is in `accumulator` modulo
+/// `modulus`.
+pub fn verify_membership(
+    accumulator: &BigUint,
+    element: &BigUint,
+    witness: &BigUint,
+    modulus: &BigUint,
+) -> bool {
+    witness.pow_mod(element, modulus) == *accumulator
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::{accumulate, sign_pkcs1_v15, verify_membership, verify_pkcs1_v15, PublicKey};
+    use crate::primitives::biguint::BigUint;
+
+    /// Returns $2^k - 1$.
+    fn mersenne(k: usize) -> BigUint {
+        BigUint::from(1)
+            .shl(k)
+            .checked_sub(&BigUint::from(1))
+            .unwrap()
+    }
+
+    /// Returns a test key whose modulus is the product of the Mersenne primes
+    /// $2^{521} - 1$ and $2^{127} - 1$, and its private exponent.
+    pub(crate) fn test_key() -> (PublicKey, BigUint) {
+        let (p, q) = (mersenne(521), mersenne(127));
+        let one = BigUint::from(1);
+        let phi = p
+            .checked_sub(&one)
+            .unwrap()
+            .mul(&q.checked_sub(&one).unwrap());
+        let exponent = BigUint::from(65537);
+        let d = exponent.mod_inverse(&phi).unwrap();
+        (
+            PublicKey {
+                modulus: p.mul(&q),
+                exponent,
+            },
+            d,
+        )
+    }
+
+    #[test]
+    fn pkcs1_v15() {
+        let (public_key, d) = test_key();
+        assert_eq!(public_key.modulus_bytes(), 81);
+
+        let digest = [0x5a; 32];
+        let signature = sign_pkcs1_v15(&public_key, &d, &digest).unwrap();
+        assert!(verify_pkcs1_v15(&public_key, &digest, &signature));
+
+        let mut other = digest;
+        other[31] ^= 1;
+        assert!(!verify_pkcs1_v15(&public_key, &other, &signature));
+        assert!(!verify_pkcs1_v15(
+            &public_key,
+            &digest,
+            &signature.add(&public_key.modulus)
+        ));
+    }
+
+    #[test]
+    fn membership() {
+        let modulus = mersenne(61).mul(&mersenne(89));
+        let g = BigUint::from(3);
+        let elements: Vec<_> = [251, 257, 65537]
+            .iter()
+            .map(|x| BigUint::from(*x))
+            .collect();
+
+        let accumulator = accumulate(&g, &elements, &modulus);
+        let witness = accumulate(&g, &elements[1..], &modulus);
+        assert!(verify_membership(
+            &accumulator,
+            &elements[0],
+            &witness,
+            &modulus
+        ));
+        assert!(!verify_membership(
+            &accumulator,
+            &elements[1],
+            &witness,
+            &modulus
+        ));
+    }
+}
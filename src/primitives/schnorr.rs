@@ -0,0 +1,151 @@
+//! Schnorr signatures over a curve whose base field is the circuit field, such as
+//! Pallas.
+//!
+//! Messages are sequences of base field elements. The challenge of a signature
+//! $(R, s)$ on a message $M$ under a public key $P$ is derived from
+//! $h = \mathsf{Poseidon}(x_R, y_R, x_P, y_P, M)$, hashed with [`P128Pow5T3`] in the
+//! [`VariableLength`] domain. The [`CHALLENGE_BITS`] little-endian bits of $h$ are
+//! mapped to the challenge scalar $e$ by [`endoscale_scalar`], so that $\[e\] P$ can
+//! be computed in-circuit with endoscaling. The signature is valid if
+//! $\[s\] G = R + \[e\] P$.
+//!
+//! Values of $h$ that do not fit in [`CHALLENGE_BITS`] bits are rejected, so that each
+//! challenge has a unique encoding. This happens with probability about $2^{-128}$ over
+//! the Pasta fields, and a signer then chooses another nonce.
+
+use ff::PrimeField;
+use group::Curve;
+
+use crate::{
+    arithmetic::CurveAffine,
+    primitives::{
+        endoscale::endoscale_scalar,
+        poseidon::{self, P128Pow5T3, Spec, VariableLength},
+    },
+};
+
+/// The number of bits of a challenge.
+pub const CHALLENGE_BITS: usize = 254;
+
+/// A Schnorr signature.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Signature<C: CurveAffine> {
+    /// The nonce point.
+    pub r: C,
+    /// The response to the challenge.
+    pub s: C::Scalar,
+}
+
+/// Returns the little-endian bits of the challenge hash of a signature with the nonce
+/// point `r` on `message` by `public_key`.
+///
+/// Returns `None` if either point is the identity, or if the hash does not fit in
+/// [`CHALLENGE_BITS`] bits.
+pub fn challenge_bits<C: CurveAffine>(
+    r: &C,
+    public_key: &C,
+    message: &[C::Base],
+) -> Option<Vec<bool>>
+where
+    P128Pow5T3: Spec<C::Base, 3, 2>,
+{
+    let (x_r, y_r) = Option::from(r.get_xy())?;
+    let (x_p, y_p) = Option::from(public_key.get_xy())?;
+    let mut input = vec![x_r, y_r, x_p, y_p];
+    input.extend_from_slice(message);
+    let hash = poseidon::Hash::<C::Base, P128Pow5T3, _, 3, 2>::hash(VariableLength, &input);
+
+    let repr = hash.to_repr();
+    let bytes = repr.as_ref();
+    let bit = |i: usize| (bytes[i / 8] >> (i % 8)) & 1 == 1;
+    if (CHALLENGE_BITS..8 * bytes.len()).any(bit) {
+        None
+    } else {
+        Some((0..CHALLENGE_BITS).map(bit).collect())
+    }
+}
+
+/// Returns the challenge scalar $e$ of a signature with the nonce point `r` on
+/// `message` by `public_key`, or `None` if it is not defined.
+///
+/// See [`challenge_bits`].
+pub fn challenge<C: CurveAffine>(r: &C, public_key: &C, message: &[C::Base]) -> Option<C::Scalar>
+where
+    P128Pow5T3: Spec<C::Base, 3, 2>,
+{
+    challenge_bits(r, public_key, message).map(|bits| endoscale_scalar(&bits))
+}
+
+/// Returns the public key $P = \[x\] G$ of the secret key $x$.
+pub fn public_key<C: CurveAffine>(secret_key: &C::Scalar) -> C {
+    (C::generator() * *secret_key).to_affine()
+}
+
+/// Signs `message` with `secret_key`, using the given nonce.
+///
+/// Returns `None` if the nonce is zero or the challenge is not defined, in which case
+/// another nonce must be chosen. The nonce must be secret and never reused.
+pub fn sign<C: CurveAffine>(
+    secret_key: &C::Scalar,
+    message: &[C::Base],
+    nonce: &C::Scalar,
+) -> Option<Signature<C>>
+where
+    P128Pow5T3: Spec<C::Base, 3, 2>,
+{
+    let r = public_key::<C>(nonce);
+    let e = challenge(&r, &public_key(secret_key), message)?;
+    Some(Signature {
+        r,
+        s: *nonce + e * *secret_key,
+    })
+}
+
+/// Returns whether `signature` is a valid signature of `message` by `public_key`.
+pub fn verify<C: CurveAffine>(public_key: &C, message: &[C::Base], signature: &Signature<C>) -> bool
+where
+    P128Pow5T3: Spec<C::Base, 3, 2>,
+{
+    challenge(&signature.r, public_key, message).is_some_and(|e| {
+        (C::generator() * signature.s).to_affine()
+            == (signature.r.to_curve() + *public_key * e).to_affine()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{challenge_bits, public_key, sign, verify, Signature};
+    use crate::{arithmetic::FieldExt, pasta::pallas};
+
+    #[test]
+    fn sign_and_verify() {
+        let secret_key = pallas::Scalar::from_u64(0x0123_4567_89ab_cdef);
+        let nonce = pallas::Scalar::from_u64(0xfedc_ba98_7654_3210);
+        let message = [pallas::Base::from_u64(1), pallas::Base::from_u64(2)];
+
+        let pk = public_key::<pallas::Affine>(&secret_key);
+        let signature = sign::<pallas::Affine>(&secret_key, &message, &nonce).unwrap();
+        assert!(verify(&pk, &message, &signature));
+
+        assert!(!verify(&pk, &message[..1], &signature));
+        assert!(!verify(
+            &pk,
+            &message,
+            &Signature {
+                s: signature.s + pallas::Scalar::one(),
+                ..signature
+            }
+        ));
+        assert!(!verify(&public_key(&nonce), &message, &signature));
+
+        // A zero nonce gives the identity as the nonce point.
+        assert_eq!(
+            sign::<pallas::Affine>(&secret_key, &message, &pallas::Scalar::zero()),
+            None
+        );
+        assert_eq!(
+            challenge_bits(&signature.r, &pk, &message).map(|bits| bits.len()),
+            Some(254)
+        );
+    }
+}
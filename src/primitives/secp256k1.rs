@@ -0,0 +1,293 @@
+//! The secp256k1 curve and ECDSA signatures over it.
+//!
+//! This is a straightforward (and not constant-time) implementation of the arithmetic
+//! needed to produce and check ECDSA signatures, as used by Bitcoin and Ethereum, and to
+//! compute the witnesses of [`crate::gadget::ecdsa`]. Field elements are stored as
+//! canonical little-endian 64-bit limbs, and multiplied in Montgomery form.
+
+use std::ops::{Add, Neg};
+
+use crate::primitives::prime_field::{add_limbs, field_element};
+
+/// An element of the base field of secp256k1, of order
+/// $p = 2^{256} - 2^{32} - 977$.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Base([u64; 4]);
+
+field_element!(
+    Base,
+    [
+        0xffff_fffe_ffff_fc2f,
+        0xffff_ffff_ffff_ffff,
+        0xffff_ffff_ffff_ffff,
+        0xffff_ffff_ffff_ffff,
+    ]
+);
+
+impl Base {
+    /// Returns a square root of this element, or `None` if it is not a square.
+    pub fn sqrt(&self) -> Option<Self> {
+        // p = 3 mod 4, so a square root is given by a^((p + 1) / 4).
+        let (exp, _) = add_limbs(&Self::MODULUS, &[1, 0, 0, 0]);
+        let exp = [
+            exp[0] >> 2 | exp[1] << 62,
+            exp[1] >> 2 | exp[2] << 62,
+            exp[2] >> 2 | exp[3] << 62,
+            exp[3] >> 2,
+        ];
+        let root = self.pow(&exp);
+        if root.square() == *self {
+            Some(root)
+        } else {
+            None
+        }
+    }
+}
+
+/// An element of the scalar field of secp256k1, whose order $n$ is the order of the
+/// group.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Scalar([u64; 4]);
+
+field_element!(
+    Scalar,
+    [
+        0xbfd2_5e8c_d036_4141,
+        0xbaae_dce6_af48_a03b,
+        0xffff_ffff_ffff_fffe,
+        0xffff_ffff_ffff_ffff,
+    ]
+);
+
+impl Scalar {
+    /// Returns the scalar congruent to the integer representing `value`.
+    pub fn from_base(value: &Base) -> Self {
+        Self::reduce(value.to_limbs())
+    }
+}
+
+/// The curve constant $b$, in $y^2 = x^3 + b$.
+pub const B: Base = Base::from_u64(7);
+
+/// A point on secp256k1, in affine coordinates, or the identity.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Affine(Option<(Base, Base)>);
+
+impl Affine {
+    /// Returns the identity.
+    pub fn identity() -> Self {
+        Affine(None)
+    }
+
+    /// Returns the standard generator.
+    pub fn generator() -> Self {
+        Affine(Some((
+            Base([
+                0x59f2_815b_16f8_1798,
+                0x029b_fcdb_2dce_28d9,
+                0x55a0_6295_ce87_0b07,
+                0x79be_667e_f9dc_bbac,
+            ]),
+            Base([
+                0x9c47_d08f_fb10_d4b8,
+                0xfd17_b448_a685_5419,
+                0x5da4_fbfc_0e11_08a8,
+                0x483a_da77_26a3_c465,
+            ]),
+        )))
+    }
+
+    /// Returns the point with the given coordinates, or `None` if it is not on the
+    /// curve.
+    pub fn from_xy(x: Base, y: Base) -> Option<Self> {
+        if y.square() == x.square() * x + B {
+            Some(Affine(Some((x, y))))
+        } else {
+            None
+        }
+    }
+
+    /// The coordinates of this point, or `None` for the identity.
+    pub fn coordinates(&self) -> Option<(Base, Base)> {
+        self.0
+    }
+
+    /// Returns whether this point is the identity.
+    pub fn is_identity(&self) -> bool {
+        self.0.is_none()
+    }
+
+    /// Returns `[2] self`.
+    pub fn double(&self) -> Self {
+        match self.0 {
+            Some((x, y)) if !y.is_zero() => {
+                let lambda = Base::from_u64(3) * x.square() * (y + y).invert().unwrap();
+                let x_r = lambda.square() - x - x;
+                Affine(Some((x_r, lambda * (x - x_r) - y)))
+            }
+            _ => Affine::identity(),
+        }
+    }
+
+    /// Returns `[scalar] self`.
+    pub fn mul(&self, scalar: &Scalar) -> Self {
+        scalar
+            .to_limbs()
+            .iter()
+            .rev()
+            .flat_map(|limb| (0..64).rev().map(move |i| (limb >> i) & 1 == 1))
+            .fold(Affine::identity(), |acc, bit| {
+                let acc = acc.double();
+                if bit {
+                    acc + *self
+                } else {
+                    acc
+                }
+            })
+    }
+}
+
+impl Add for Affine {
+    type Output = Affine;
+
+    fn add(self, other: Affine) -> Affine {
+        match (self.0, other.0) {
+            (None, _) => other,
+            (_, None) => self,
+            (Some((x_p, y_p)), Some((x_q, y_q))) => {
+                if x_p == x_q {
+                    if y_p == y_q {
+                        self.double()
+                    } else {
+                        Affine::identity()
+                    }
+                } else {
+                    let lambda = (y_q - y_p) * (x_q - x_p).invert().unwrap();
+                    let x_r = lambda.square() - x_p - x_q;
+                    Affine(Some((x_r, lambda * (x_p - x_r) - y_p)))
+                }
+            }
+        }
+    }
+}
+
+impl Neg for Affine {
+    type Output = Affine;
+
+    fn neg(self) -> Affine {
+        Affine(self.0.map(|(x, y)| (x, -y)))
+    }
+}
+
+/// An ECDSA signature.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Signature {
+    /// The $x$-coordinate of the nonce point, reduced modulo $n$.
+    pub r: Scalar,
+    /// The proof of knowledge of the secret key.
+    pub s: Scalar,
+}
+
+/// Signs `message_hash` with `secret_key`, using the given nonce.
+///
+/// Returns `None` if the nonce is zero or produces an invalid signature, in which case
+/// another nonce must be chosen. The nonce must be secret and never reused; it would
+/// usually be derived deterministically as in RFC 6979.
+pub fn sign(secret_key: &Scalar, message_hash: &Scalar, nonce: &Scalar) -> Option<Signature> {
+    let (x, _) = Affine::generator().mul(nonce).coordinates()?;
+    let r = Scalar::from_base(&x);
+    let s = nonce.invert()? * (*message_hash + r * *secret_key);
+    if r.is_zero() || s.is_zero() {
+        None
+    } else {
+        Some(Signature { r, s })
+    }
+}
+
+/// Returns whether `signature` is a valid signature of `message_hash` by
+/// `public_key`.
+pub fn verify(public_key: &Affine, message_hash: &Scalar, signature: &Signature) -> bool {
+    if public_key.is_identity() || signature.r.is_zero() {
+        return false;
+    }
+    let w = match signature.s.invert() {
+        Some(w) => w,
+        None => return false,
+    };
+
+    let u_1 = *message_hash * w;
+    let u_2 = signature.r * w;
+    let point = Affine::generator().mul(&u_1) + public_key.mul(&u_2);
+    point
+        .coordinates()
+        .is_some_and(|(x, _)| Scalar::from_base(&x) == signature.r)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sign, verify, Affine, Base, Scalar};
+
+    #[test]
+    fn field_arithmetic() {
+        let a = Base::from_be_bytes_reduced(&[0xab; 32]);
+        assert_eq!(a * a.invert().unwrap(), Base::one());
+        assert_eq!(
+            a.square().sqrt().map(|root| root.square()),
+            Some(a.square())
+        );
+        assert_eq!(-a + a, Base::zero());
+        assert_eq!(Base::from_be_bytes(&a.to_be_bytes()), Some(a));
+        assert_eq!(Base::from_be_bytes(&[0xff; 32]), None);
+
+        let b = Scalar::from_u64(0x0123_4567_89ab_cdef);
+        assert_eq!(b * b.invert().unwrap(), Scalar::one());
+        assert_eq!(Scalar::zero().invert(), None);
+    }
+
+    #[test]
+    fn group_arithmetic() {
+        let g = Affine::generator();
+        assert_eq!(
+            Affine::from_xy(g.coordinates().unwrap().0, g.coordinates().unwrap().1),
+            Some(g)
+        );
+
+        let double = g.double();
+        assert_eq!(
+            double.coordinates().unwrap().0.to_be_bytes(),
+            [
+                0xc6, 0x04, 0x7f, 0x94, 0x41, 0xed, 0x7d, 0x6d, 0x30, 0x45, 0x40, 0x6e, 0x95, 0xc0,
+                0x7c, 0xd8, 0x5c, 0x77, 0x8e, 0x4b, 0x8c, 0xef, 0x3c, 0xa7, 0xab, 0xac, 0x09, 0xb9,
+                0x5c, 0x70, 0x9e, 0xe5,
+            ]
+        );
+        assert_eq!(g + g, double);
+        assert_eq!(g.mul(&Scalar::from_u64(3)), double + g);
+        assert_eq!(g + -g, Affine::identity());
+
+        // The generator has order n.
+        assert_eq!(g.mul(&-Scalar::one()), -g);
+        assert_eq!(g.mul(&-Scalar::one()) + g, Affine::identity());
+    }
+
+    #[test]
+    fn ecdsa() {
+        let secret_key = Scalar::from_be_bytes_reduced(&[0x42; 32]);
+        let public_key = Affine::generator().mul(&secret_key);
+        let message_hash = Scalar::from_be_bytes_reduced(&[0xe3; 32]);
+        let nonce = Scalar::from_be_bytes_reduced(&[0x17; 32]);
+
+        let signature = sign(&secret_key, &message_hash, &nonce).unwrap();
+        assert!(verify(&public_key, &message_hash, &signature));
+
+        let other_hash = message_hash + Scalar::one();
+        assert!(!verify(&public_key, &other_hash, &signature));
+        let mut forged = signature;
+        forged.s = -forged.s;
+        // Negating s corresponds to negating the nonce, which gives the same r.
+        assert!(verify(&public_key, &message_hash, &forged));
+        forged.r = forged.r + Scalar::one();
+        assert!(!verify(&public_key, &message_hash, &forged));
+        assert!(!verify(&Affine::generator(), &message_hash, &signature));
+    }
+}
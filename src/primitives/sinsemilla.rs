@@ -0,0 +1,243 @@
+//! The Sinsemilla hash function and commitment scheme.
+//!
+//! Sinsemilla hashes a bit string by splitting it into $K$-bit words $m_i$, and
+//! computing $A_{i+1} = (A_i + S(m_i)) + A_i$ from a domain-specific starting point
+//! $A_0 = Q$, where $S$ is a table of $2^K$ independent generators. Each word therefore
+//! costs two incomplete additions, and a single lookup of $S(m_i)$ in-circuit, which
+//! makes it much cheaper than an algebraic hash for long inputs.
+//!
+//! The hash of a message is the $x$-coordinate of the final point, and a commitment to
+//! a message with randomness $r$ is obtained by adding $\[r\] R$ to the hash point, for a
+//! domain-specific generator $R$.
+//!
+//! These are the constructions computed in-circuit by [`crate::gadget::sinsemilla`].
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use ff::Field;
+use group::Curve;
+use lazy_static::lazy_static;
+
+use crate::arithmetic::{CurveAffine, CurveExt};
+
+/// The number of bits in a message word.
+pub const K: usize = 10;
+
+/// The maximum number of words in a message.
+pub const C: usize = 253;
+
+/// The personalization used to derive the starting point $Q$ of a domain.
+pub const Q_PERSONALIZATION: &str = "z.cash:SinsemillaQ";
+
+/// The personalization used to derive the generators $S$.
+pub const S_PERSONALIZATION: &str = "z.cash:SinsemillaS";
+
+lazy_static! {
+    static ref GENERATORS: Mutex<HashMap<TypeId, Arc<dyn Any + Send + Sync>>> =
+        Mutex::new(HashMap::new());
+}
+
+/// Returns the $2^K$ generators $S(j) = \mathsf{GroupHash}(\texttt{S\_PERSONALIZATION},
+/// j)$ for the curve `C`, where $j$ is encoded as four little-endian bytes, caching them
+/// for subsequent calls.
+pub fn generators<C: CurveAffine>() -> Arc<Vec<C>> {
+    let cached = GENERATORS.lock().unwrap().get(&TypeId::of::<C>()).cloned();
+    let generators = match cached {
+        Some(generators) => generators,
+        None => {
+            // Generate the points without holding the lock; if another thread races
+            // us, both will produce identical points.
+            let hasher = C::CurveExt::hash_to_curve(S_PERSONALIZATION);
+            let points: Vec<_> = (0..(1u32 << K))
+                .map(|j| hasher(&j.to_le_bytes()).to_affine())
+                .collect();
+            let generators: Arc<dyn Any + Send + Sync> = Arc::new(Arc::new(points));
+            GENERATORS
+                .lock()
+                .unwrap()
+                .entry(TypeId::of::<C>())
+                .or_insert(generators)
+                .clone()
+        }
+    };
+
+    generators
+        .downcast_ref::<Arc<Vec<C>>>()
+        .expect("keyed by curve type")
+        .clone()
+}
+
+/// Returns the $x$-coordinate of `point`, or zero for the identity.
+pub fn extract_p<C: CurveAffine>(point: &C) -> C::Base {
+    Option::from(point.get_xy()).map_or_else(C::Base::zero, |(x, _)| x)
+}
+
+/// Returns `a + b`, or `None` if either point is the identity or their
+/// $x$-coordinates are equal, which are the cases the in-circuit incomplete addition
+/// cannot handle.
+fn incomplete_add<C: CurveAffine>(a: C, b: C) -> Option<C> {
+    let (x_a, _) = Option::from(a.get_xy())?;
+    let (x_b, _) = Option::from(b.get_xy())?;
+    if x_a == x_b {
+        None
+    } else {
+        Some((a + b).to_affine())
+    }
+}
+
+/// Returns the little-endian $K$-bit words of `message`, padded with zero bits.
+fn words(message: &[bool]) -> Vec<usize> {
+    message
+        .chunks(K)
+        .map(|chunk| {
+            chunk
+                .iter()
+                .enumerate()
+                .fold(0, |acc, (i, bit)| acc | (*bit as usize) << i)
+        })
+        .collect()
+}
+
+/// A domain in which Sinsemilla hashes are computed.
+#[derive(Clone, Debug)]
+pub struct HashDomain<C: CurveAffine> {
+    q: C,
+}
+
+impl<C: CurveAffine> HashDomain<C> {
+    /// Constructs the domain with the given name.
+    pub fn new(domain: &str) -> Self {
+        HashDomain {
+            q: C::CurveExt::hash_to_curve(Q_PERSONALIZATION)(domain.as_bytes()).to_affine(),
+        }
+    }
+
+    /// The starting point $Q$ of this domain.
+    pub fn q(&self) -> C {
+        self.q
+    }
+
+    /// Returns the Sinsemilla hash of `message` as a point.
+    ///
+    /// Returns `None` if `message` is longer than $K \cdot C$ bits, or if an exceptional
+    /// case of the incomplete additions is reached (which happens with negligible
+    /// probability).
+    pub fn hash_to_point(&self, message: &[bool]) -> Option<C> {
+        if message.len() > K * C {
+            return None;
+        }
+
+        let generators = generators::<C>();
+        words(message).into_iter().try_fold(self.q, |acc, word| {
+            incomplete_add(incomplete_add(acc, generators[word])?, acc)
+        })
+    }
+
+    /// Returns the Sinsemilla hash of `message`, which is the $x$-coordinate of
+    /// [`HashDomain::hash_to_point`].
+    pub fn hash(&self, message: &[bool]) -> Option<C::Base> {
+        self.hash_to_point(message).map(|point| extract_p(&point))
+    }
+}
+
+/// A domain in which Sinsemilla commitments are computed.
+#[derive(Clone, Debug)]
+pub struct CommitDomain<C: CurveAffine> {
+    m: HashDomain<C>,
+    r: C,
+}
+
+impl<C: CurveAffine> CommitDomain<C> {
+    /// Constructs the domain with the given name, whose hash domain is named
+    /// `"{domain}-M"` and whose randomness generator is derived from `"{domain}-r"`.
+    pub fn new(domain: &str) -> Self {
+        CommitDomain {
+            m: HashDomain::new(&format!("{}-M", domain)),
+            r: C::CurveExt::hash_to_curve(&format!("{}-r", domain))(&[]).to_affine(),
+        }
+    }
+
+    /// The hash domain used for the message.
+    pub fn hash_domain(&self) -> &HashDomain<C> {
+        &self.m
+    }
+
+    /// The generator $R$ multiplied by the commitment randomness.
+    pub fn r(&self) -> C {
+        self.r
+    }
+
+    /// Returns the commitment $\mathsf{hash\_to\_point}(\mathsf{message}) + \[r\] R$.
+    pub fn commit(&self, message: &[bool], r: &C::Scalar) -> Option<C> {
+        self.m
+            .hash_to_point(message)
+            .map(|hash| (hash.to_curve() + self.r * *r).to_affine())
+    }
+
+    /// Returns the $x$-coordinate of [`CommitDomain::commit`].
+    pub fn short_commit(&self, message: &[bool], r: &C::Scalar) -> Option<C::Base> {
+        self.commit(message, r).map(|point| extract_p(&point))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use group::Curve;
+
+    use super::{generators, words, CommitDomain, HashDomain, K};
+    use crate::{
+        arithmetic::{CurveAffine, FieldExt},
+        pasta::pallas,
+    };
+
+    #[test]
+    fn word_decomposition() {
+        let mut message = vec![false; 2 * K + 3];
+        message[0] = true;
+        message[K + 1] = true;
+        message[2 * K + 2] = true;
+        assert_eq!(words(&message), vec![1, 2, 4]);
+    }
+
+    #[test]
+    fn hash_matches_definition() {
+        let domain = HashDomain::<pallas::Affine>::new("halo2:test");
+        let message: Vec<_> = (0..25).map(|i| i % 3 == 0).collect();
+
+        // The set bits 0, 3, 6, 9 | 12, 15, 18 | 21, 24 give these words.
+        let s = generators::<pallas::Affine>();
+        let hash = [0x249, 0x124, 0x12].iter().fold(domain.q(), |acc, word| {
+            ((acc + s[*word]).to_affine() + acc).to_affine()
+        });
+        assert_eq!(domain.hash_to_point(&message), Some(hash));
+
+        // Trailing zero bits within the last word do not change the hash.
+        let mut padded = message.clone();
+        padded.resize(3 * K, false);
+        assert_eq!(domain.hash(&padded), domain.hash(&message));
+
+        let too_long = vec![false; K * super::C + 1];
+        assert_eq!(domain.hash(&too_long), None);
+    }
+
+    #[test]
+    fn commit() {
+        let domain = CommitDomain::<pallas::Affine>::new("halo2:test");
+        let message = vec![true; 3 * K];
+        let r = pallas::Scalar::from_u64(42);
+
+        let hash = domain.hash_domain().hash_to_point(&message).unwrap();
+        let expected = (hash + domain.r() * r).to_affine();
+        assert_eq!(domain.commit(&message, &r), Some(expected));
+        assert_eq!(
+            domain.short_commit(&message, &r),
+            Some(expected.get_xy().unwrap().0)
+        );
+        assert_ne!(
+            domain.commit(&message, &pallas::Scalar::zero()),
+            domain.commit(&message, &r)
+        );
+    }
+}
@@ -2,11 +2,12 @@
 //! transcripts.
 
 use blake2b_simd::{Params as Blake2bParams, State as Blake2bState};
-use ff::Field;
 use std::convert::TryInto;
 use std::ops::Deref;
 
 use crate::arithmetic::{CurveAffine, FieldExt};
+use crate::primitives::endoscale::endoscale_scalar;
+use crate::primitives::poseidon::{transcript_capacity_element, Duplex, P128Pow5T3, Spec};
 
 use std::io::{self, Read, Write};
 use std::marker::PhantomData;
@@ -45,11 +46,80 @@ pub trait TranscriptWrite<C: CurveAffine>: Transcript<C> {
     fn write_scalar(&mut self, scalar: C::Scalar) -> io::Result<()>;
 }
 
+/// Reads an encoded curve point from the proof, which must be the canonical encoding
+/// of the point if `strict` is set.
+pub(crate) fn read_point<C: CurveAffine, R: Read>(reader: &mut R, strict: bool) -> io::Result<C> {
+    let mut compressed = C::Repr::default();
+    reader.read_exact(compressed.as_mut())?;
+    let point: C = Option::from(C::from_bytes(&compressed))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "invalid point encoding in proof"))?;
+    if strict && point.to_bytes().as_ref() != compressed.as_ref() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "non-canonical point encoding in proof",
+        ));
+    }
+    Ok(point)
+}
+
+/// Reads an encoded scalar from the proof, which must be the canonical encoding of
+/// the scalar if `strict` is set.
+pub(crate) fn read_scalar<C: CurveAffine, R: Read>(
+    reader: &mut R,
+    strict: bool,
+) -> io::Result<C::Scalar> {
+    let mut data = [0u8; 32];
+    reader.read_exact(&mut data)?;
+    let scalar: C::Scalar = Option::from(C::Scalar::from_bytes(&data)).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::Other,
+            "invalid field element encoding in proof",
+        )
+    })?;
+    if strict && scalar.to_bytes() != data {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "non-canonical field element encoding in proof",
+        ));
+    }
+    Ok(scalar)
+}
+
+/// Returns the reader of a proof, which must have no bytes left if `strict` is set.
+fn finalize_reader<R: Read>(mut reader: R, strict: bool) -> io::Result<R> {
+    if strict && reader.read(&mut [0u8])? != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "trailing bytes after proof",
+        ));
+    }
+    Ok(reader)
+}
+
+/// Returns the affine coordinates of `point`, which must not be the point at infinity.
+fn point_coordinates<C: CurveAffine>(point: C) -> io::Result<(C::Base, C::Base)> {
+    Option::from(point.get_xy()).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::Other,
+            "cannot write points at infinity to the transcript",
+        )
+    })
+}
+
 /// We will replace BLAKE2b with an algebraic hash function in a later version.
+///
+/// A transcript created with [`Blake2bRead::init`] accepts any encoding of a point or
+/// a scalar that the curve can decode, which need not be the canonical encoding for
+/// every curve, and ignores any bytes after the messages that the verifier reads. The
+/// same proof can then have more than one encoding. Users that need every proof to
+/// have exactly one encoding, such as for consensus, should create the transcript
+/// with [`Blake2bRead::init_strict`] instead and check it with
+/// [`Blake2bRead::finalize`] once the proof has been verified.
 #[derive(Debug, Clone)]
 pub struct Blake2bRead<R: Read, C: CurveAffine> {
     state: Blake2bState,
     reader: R,
+    strict: bool,
     _marker: PhantomData<C>,
 }
 
@@ -62,32 +132,39 @@ impl<R: Read, C: CurveAffine> Blake2bRead<R, C> {
                 .personal(b"Halo2-Transcript")
                 .to_state(),
             reader,
+            strict: false,
             _marker: PhantomData,
         }
     }
+
+    /// Initialize a transcript given an input buffer, which only accepts the
+    /// canonical encoding of each point and scalar.
+    pub fn init_strict(reader: R) -> Self {
+        Blake2bRead {
+            strict: true,
+            ..Self::init(reader)
+        }
+    }
+
+    /// Conclude the interaction and return the input buffer (reader).
+    ///
+    /// For a transcript created with [`Blake2bRead::init_strict`], this returns an
+    /// error if the buffer has bytes left after the messages that have been read.
+    pub fn finalize(self) -> io::Result<R> {
+        finalize_reader(self.reader, self.strict)
+    }
 }
 
 impl<R: Read, C: CurveAffine> TranscriptRead<C> for Blake2bRead<R, C> {
     fn read_point(&mut self) -> io::Result<C> {
-        let mut compressed = C::Repr::default();
-        self.reader.read_exact(compressed.as_mut())?;
-        let point: C = Option::from(C::from_bytes(&compressed)).ok_or_else(|| {
-            io::Error::new(io::ErrorKind::Other, "invalid point encoding in proof")
-        })?;
+        let point = read_point(&mut self.reader, self.strict)?;
         self.common_point(point)?;
 
         Ok(point)
     }
 
     fn read_scalar(&mut self) -> io::Result<C::Scalar> {
-        let mut data = [0u8; 32];
-        self.reader.read_exact(&mut data)?;
-        let scalar: C::Scalar = Option::from(C::Scalar::from_bytes(&data)).ok_or_else(|| {
-            io::Error::new(
-                io::ErrorKind::Other,
-                "invalid field element encoding in proof",
-            )
-        })?;
+        let scalar = read_scalar::<C, _>(&mut self.reader, self.strict)?;
         self.common_scalar(scalar)?;
 
         Ok(scalar)
@@ -96,12 +173,7 @@ impl<R: Read, C: CurveAffine> TranscriptRead<C> for Blake2bRead<R, C> {
 
 impl<R: Read, C: CurveAffine> Transcript<C> for Blake2bRead<R, C> {
     fn common_point(&mut self, point: C) -> io::Result<()> {
-        let (x, y) = Option::from(point.get_xy()).ok_or_else(|| {
-            io::Error::new(
-                io::ErrorKind::Other,
-                "cannot write points at infinity to the transcript",
-            )
-        })?;
+        let (x, y) = point_coordinates(point)?;
         self.state.update(&x.to_bytes());
         self.state.update(&y.to_bytes());
 
@@ -165,12 +237,7 @@ impl<W: Write, C: CurveAffine> TranscriptWrite<C> for Blake2bWrite<W, C> {
 
 impl<W: Write, C: CurveAffine> Transcript<C> for Blake2bWrite<W, C> {
     fn common_point(&mut self, point: C) -> io::Result<()> {
-        let (x, y) = Option::from(point.get_xy()).ok_or_else(|| {
-            io::Error::new(
-                io::ErrorKind::Other,
-                "cannot write points at infinity to the transcript",
-            )
-        })?;
+        let (x, y) = point_coordinates(point)?;
         self.state.update(&x.to_bytes());
         self.state.update(&y.to_bytes());
 
@@ -191,6 +258,161 @@ impl<W: Write, C: CurveAffine> Transcript<C> for Blake2bWrite<W, C> {
     }
 }
 
+/// The duplex sponge used by the Poseidon transcripts, over the base field of `C`.
+type PoseidonSponge<C, S> = Duplex<<C as CurveAffine>::Base, S, 3, 2>;
+
+/// Absorbs the affine coordinates of `point` into the sponge.
+fn poseidon_common_point<C: CurveAffine, S: Spec<C::Base, 3, 2>>(
+    sponge: &mut PoseidonSponge<C, S>,
+    point: C,
+) -> io::Result<()> {
+    let (x, y) = point_coordinates(point)?;
+    sponge.absorb(x);
+    sponge.absorb(y);
+
+    Ok(())
+}
+
+/// Absorbs `scalar` into the sponge as two base field elements, holding the low and
+/// high 128 bits of its canonical encoding. This is injective regardless of the relative
+/// sizes of the scalar and base fields.
+fn poseidon_common_scalar<C: CurveAffine, S: Spec<C::Base, 3, 2>>(
+    sponge: &mut PoseidonSponge<C, S>,
+    scalar: C::Scalar,
+) -> io::Result<()> {
+    let bytes = scalar.to_bytes();
+    let lo = u128::from_le_bytes(bytes[..16].try_into().unwrap());
+    let hi = u128::from_le_bytes(bytes[16..].try_into().unwrap());
+    sponge.absorb(C::Base::from_u128(lo));
+    sponge.absorb(C::Base::from_u128(hi));
+
+    Ok(())
+}
+
+/// A transcript that reads a proof, backed by the Poseidon permutation `S` over the base
+/// field of `C`.
+///
+/// Points are absorbed as their affine coordinates, and scalars as two 128-bit limbs,
+/// so that the transcript can be recomputed inside a circuit over the base field
+/// without emulating a bit-oriented hash function. The proof encoding is the same as
+/// for [`Blake2bRead`], and so is the strict mode of [`PoseidonRead::init_strict`].
+#[derive(Debug, Clone)]
+pub struct PoseidonRead<R: Read, C: CurveAffine, S: Spec<C::Base, 3, 2> = P128Pow5T3> {
+    sponge: PoseidonSponge<C, S>,
+    reader: R,
+    strict: bool,
+}
+
+impl<R: Read, C: CurveAffine, S: Spec<C::Base, 3, 2>> PoseidonRead<R, C, S> {
+    /// Initialize a transcript given an input buffer.
+    pub fn init(reader: R) -> Self {
+        PoseidonRead {
+            sponge: Duplex::new(transcript_capacity_element()),
+            reader,
+            strict: false,
+        }
+    }
+
+    /// Initialize a transcript given an input buffer, which only accepts the
+    /// canonical encoding of each point and scalar.
+    pub fn init_strict(reader: R) -> Self {
+        PoseidonRead {
+            strict: true,
+            ..Self::init(reader)
+        }
+    }
+
+    /// Conclude the interaction and return the input buffer (reader).
+    ///
+    /// For a transcript created with [`PoseidonRead::init_strict`], this returns an
+    /// error if the buffer has bytes left after the messages that have been read.
+    pub fn finalize(self) -> io::Result<R> {
+        finalize_reader(self.reader, self.strict)
+    }
+}
+
+impl<R: Read, C: CurveAffine, S: Spec<C::Base, 3, 2>> TranscriptRead<C> for PoseidonRead<R, C, S> {
+    fn read_point(&mut self) -> io::Result<C> {
+        let point = read_point(&mut self.reader, self.strict)?;
+        self.common_point(point)?;
+
+        Ok(point)
+    }
+
+    fn read_scalar(&mut self) -> io::Result<C::Scalar> {
+        let scalar = read_scalar::<C, _>(&mut self.reader, self.strict)?;
+        self.common_scalar(scalar)?;
+
+        Ok(scalar)
+    }
+}
+
+impl<R: Read, C: CurveAffine, S: Spec<C::Base, 3, 2>> Transcript<C> for PoseidonRead<R, C, S> {
+    fn common_point(&mut self, point: C) -> io::Result<()> {
+        poseidon_common_point(&mut self.sponge, point)
+    }
+
+    fn common_scalar(&mut self, scalar: C::Scalar) -> io::Result<()> {
+        poseidon_common_scalar::<C, S>(&mut self.sponge, scalar)
+    }
+
+    fn squeeze_challenge(&mut self) -> C::Base {
+        self.sponge.squeeze()
+    }
+}
+
+/// A transcript that writes a proof, backed by the Poseidon permutation `S` over the
+/// base field of `C`. See [`PoseidonRead`] for details.
+#[derive(Debug, Clone)]
+pub struct PoseidonWrite<W: Write, C: CurveAffine, S: Spec<C::Base, 3, 2> = P128Pow5T3> {
+    sponge: PoseidonSponge<C, S>,
+    writer: W,
+}
+
+impl<W: Write, C: CurveAffine, S: Spec<C::Base, 3, 2>> PoseidonWrite<W, C, S> {
+    /// Initialize a transcript given an output buffer.
+    pub fn init(writer: W) -> Self {
+        PoseidonWrite {
+            sponge: Duplex::new(transcript_capacity_element()),
+            writer,
+        }
+    }
+
+    /// Conclude the interaction and return the output buffer (writer).
+    pub fn finalize(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write, C: CurveAffine, S: Spec<C::Base, 3, 2>> TranscriptWrite<C>
+    for PoseidonWrite<W, C, S>
+{
+    fn write_point(&mut self, point: C) -> io::Result<()> {
+        self.common_point(point)?;
+        let compressed = point.to_bytes();
+        self.writer.write_all(compressed.as_ref())
+    }
+    fn write_scalar(&mut self, scalar: C::Scalar) -> io::Result<()> {
+        self.common_scalar(scalar)?;
+        let data = scalar.to_bytes();
+        self.writer.write_all(&data[..])
+    }
+}
+
+impl<W: Write, C: CurveAffine, S: Spec<C::Base, 3, 2>> Transcript<C> for PoseidonWrite<W, C, S> {
+    fn common_point(&mut self, point: C) -> io::Result<()> {
+        poseidon_common_point(&mut self.sponge, point)
+    }
+
+    fn common_scalar(&mut self, scalar: C::Scalar) -> io::Result<()> {
+        poseidon_common_scalar::<C, S>(&mut self.sponge, scalar)
+    }
+
+    fn squeeze_challenge(&mut self) -> C::Base {
+        self.sponge.squeeze()
+    }
+}
+
 /// This is a 128-bit verifier challenge.
 #[derive(Copy, Clone, Debug)]
 pub struct Challenge(pub(crate) u128);
@@ -216,20 +438,8 @@ impl<C: CurveAffine, Type> From<Challenge> for ChallengeScalar<C, Type> {
     /// This algorithm applies the mapping of Algorithm 1 from the
     /// [Halo](https://eprint.iacr.org/2019/1021) paper.
     fn from(challenge: Challenge) -> Self {
-        let mut acc = (C::Scalar::ZETA + &C::Scalar::one()).double();
-
-        for i in (0..64).rev() {
-            let should_negate = ((challenge.0 >> ((i << 1) + 1)) & 1) == 1;
-            let should_endo = ((challenge.0 >> (i << 1)) & 1) == 1;
-
-            let q = if should_negate {
-                -C::Scalar::one()
-            } else {
-                C::Scalar::one()
-            };
-            let q = if should_endo { q * &C::Scalar::ZETA } else { q };
-            acc = acc + &q + &acc;
-        }
+        let bits: Vec<_> = (0..128).map(|i| (challenge.0 >> i) & 1 == 1).collect();
+        let acc = endoscale_scalar(&bits);
 
         ChallengeScalar {
             inner: acc,
@@ -269,3 +479,92 @@ pub(crate) fn read_n_scalars<C: CurveAffine, T: TranscriptRead<C>>(
 ) -> io::Result<Vec<C::Scalar>> {
     (0..n).map(|_| transcript.read_scalar()).collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use group::{prime::PrimeCurveAffine, Curve};
+
+    use super::{
+        Blake2bRead, Blake2bWrite, PoseidonRead, PoseidonWrite, Transcript, TranscriptRead,
+        TranscriptWrite,
+    };
+    use crate::{
+        arithmetic::FieldExt,
+        pasta::{EqAffine, Fp},
+    };
+
+    #[test]
+    fn poseidon_transcript() {
+        let point = (EqAffine::generator() * Fp::from_u64(5)).to_affine();
+        let scalar = Fp::from_u64(7);
+
+        let mut transcript = PoseidonWrite::<_, EqAffine>::init(vec![]);
+        transcript.common_point(EqAffine::generator()).unwrap();
+        transcript.write_point(point).unwrap();
+        let a = transcript.squeeze_challenge();
+        transcript.write_scalar(scalar).unwrap();
+        let b = transcript.squeeze_challenge();
+        let proof = transcript.finalize();
+
+        let mut transcript = PoseidonRead::<_, EqAffine>::init(&proof[..]);
+        transcript.common_point(EqAffine::generator()).unwrap();
+        assert_eq!(transcript.read_point().unwrap(), point);
+        assert_eq!(transcript.squeeze_challenge(), a);
+        assert_eq!(transcript.read_scalar().unwrap(), scalar);
+        assert_eq!(transcript.squeeze_challenge(), b);
+        assert_ne!(a, b);
+
+        // A different scalar results in a different challenge.
+        let mut transcript = PoseidonWrite::<_, EqAffine>::init(vec![]);
+        transcript.common_point(EqAffine::generator()).unwrap();
+        transcript.write_point(point).unwrap();
+        transcript.squeeze_challenge();
+        transcript.write_scalar(scalar + Fp::one()).unwrap();
+        assert_ne!(transcript.squeeze_challenge(), b);
+
+        // Points at infinity cannot be absorbed.
+        let mut transcript = PoseidonWrite::<_, EqAffine>::init(vec![]);
+        assert!(transcript.write_point(EqAffine::identity()).is_err());
+    }
+
+    #[test]
+    fn strict_reading() {
+        let point = (EqAffine::generator() * Fp::from_u64(5)).to_affine();
+        let scalar = Fp::from_u64(7);
+
+        let mut transcript = Blake2bWrite::<_, EqAffine>::init(vec![]);
+        transcript.write_point(point).unwrap();
+        transcript.write_scalar(scalar).unwrap();
+        let proof = transcript.finalize();
+        let mut padded = proof.clone();
+        padded.push(0);
+
+        // Both modes read the proof, but only the strict mode rejects trailing bytes.
+        let read = |transcript: &mut Blake2bRead<&[u8], EqAffine>| {
+            assert_eq!(transcript.read_point().unwrap(), point);
+            assert_eq!(transcript.read_scalar().unwrap(), scalar);
+        };
+        for proof in [&proof, &padded] {
+            let mut transcript = Blake2bRead::init(&proof[..]);
+            read(&mut transcript);
+            assert!(transcript.finalize().is_ok());
+        }
+        let mut transcript = Blake2bRead::init_strict(&proof[..]);
+        read(&mut transcript);
+        assert!(transcript.finalize().is_ok());
+        let mut transcript = Blake2bRead::init_strict(&padded[..]);
+        read(&mut transcript);
+        assert!(transcript.finalize().is_err());
+
+        let mut transcript = PoseidonRead::<_, EqAffine>::init_strict(&padded[..]);
+        assert_eq!(transcript.read_point().unwrap(), point);
+        assert_eq!(transcript.read_scalar().unwrap(), scalar);
+        assert!(transcript.finalize().is_err());
+
+        // Scalars must be reduced in both modes.
+        let unreduced = [0xff; 32];
+        assert!(Blake2bRead::<_, EqAffine>::init(&unreduced[..])
+            .read_scalar()
+            .is_err());
+    }
+}
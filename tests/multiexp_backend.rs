@@ -0,0 +1,93 @@
+//! The backend of `best_multiexp` is registered for the whole process, so it is tested
+//! in a binary of its own, where no other test computes multi-exponentiations.
+
+use group::{Curve, Group};
+use halo2::arithmetic::{
+    best_multiexp, clear_multiexp_backend, set_multiexp_backend, small_multiexp, FieldExt,
+    MultiexpBackend,
+};
+use halo2::pasta::{Eq, EqAffine, Fp};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// A backend that only accepts multi-exponentiations of at least 16 terms.
+struct ThresholdBackend(AtomicUsize);
+
+impl MultiexpBackend<EqAffine> for ThresholdBackend {
+    fn multiexp(&self, coeffs: &[Fp], bases: &[EqAffine]) -> Option<Eq> {
+        if coeffs.len() < 16 {
+            return None;
+        }
+        self.0.fetch_add(1, Ordering::SeqCst);
+        Some(small_multiexp(coeffs, bases))
+    }
+}
+
+fn multiexp(n: usize) {
+    let coeffs: Vec<_> = (0..n).map(|_| Fp::rand()).collect();
+    let bases: Vec<_> = (0..n)
+        .map(|_| (Eq::generator() * Fp::rand()).to_affine())
+        .collect();
+    assert_eq!(
+        best_multiexp(&coeffs, &bases),
+        small_multiexp(&coeffs, &bases)
+    );
+}
+
+#[test]
+fn multiexp_backend() {
+    let backend = Arc::new(ThresholdBackend(AtomicUsize::new(0)));
+    set_multiexp_backend::<EqAffine>(backend.clone());
+    multiexp(4);
+    assert_eq!(backend.0.load(Ordering::SeqCst), 0);
+    multiexp(32);
+    assert_eq!(backend.0.load(Ordering::SeqCst), 1);
+
+    clear_multiexp_backend::<EqAffine>();
+    multiexp(32);
+    assert_eq!(backend.0.load(Ordering::SeqCst), 1);
+}
+
+#[cfg(feature = "cuda")]
+#[test]
+fn cuda_multiexp() {
+    use group::prime::PrimeCurveAffine;
+    use halo2::arithmetic::CudaMultiexp;
+    use halo2::pasta::{EpAffine, Fq};
+
+    // Without a device the backend is unavailable, and proofs are computed on the CPU.
+    let backend = match CudaMultiexp::new() {
+        Some(backend) => backend.with_min_size(0),
+        None => return,
+    };
+
+    // Over the other curve of the cycle, so that the registered backend does not meet
+    // the other test of this binary.
+    for n in [1, 100, 5000] {
+        let mut bases: Vec<EpAffine> = (0..n)
+            .map(|_| (EpAffine::generator() * Fq::rand()).to_affine())
+            .collect();
+        let mut coeffs: Vec<Fq> = (0..n).map(|_| Fq::rand()).collect();
+        if n > 7 {
+            bases[3] = EpAffine::identity();
+            bases[7] = bases[6];
+            coeffs[5] = Fq::zero();
+            coeffs[6] = -Fq::one();
+        }
+        let expected = small_multiexp(&coeffs, &bases);
+
+        assert_eq!(
+            MultiexpBackend::<EpAffine>::multiexp(&backend, &coeffs, &bases),
+            Some(expected)
+        );
+    }
+
+    let coeffs: Vec<Fq> = (0..100).map(|_| Fq::rand()).collect();
+    let bases: Vec<EpAffine> = (0..100)
+        .map(|_| (EpAffine::generator() * Fq::rand()).to_affine())
+        .collect();
+    let expected = small_multiexp(&coeffs, &bases);
+    set_multiexp_backend::<EpAffine>(Arc::new(backend));
+    assert_eq!(best_multiexp(&coeffs, &bases), expected);
+    clear_multiexp_backend::<EpAffine>();
+}
@@ -1,21 +1,74 @@
 #![allow(clippy::many_single_char_names)]
 #![allow(clippy::op_ref)]
 
-use group::Curve;
-use halo2::arithmetic::FieldExt;
-use halo2::dev::MockProver;
+use group::{Curve, GroupEncoding};
+use halo2::arithmetic::{CurveAffine, Field, FieldExt};
+use halo2::circuit::{layouter::SingleChip, Chip, Layouter};
+use halo2::dev::{MockProver, VerifyFailure};
 use halo2::pasta::{EqAffine, Fp};
 use halo2::plonk::{
-    create_proof, keygen_pk, keygen_vk, verify_proof, Advice, Assignment, Circuit, Column,
-    ConstraintSystem, Error, Fixed, Permutation, VerifyingKey,
+    commit_instance, create_proof, create_proof_with_options, generate_witness, keygen_pk,
+    keygen_vk, verify_proof, Advice, Assignment, BatchVerifier, Challenge, Circuit, Column,
+    ConstraintSystem, Error, Expression, Fixed, LookupArgument, Permutation, Phase, Proof,
+    ProverOptions, ProverStage, ProverWorkers, ProvingKey, Selector, TableColumn, VerifyingKey,
+    Witness,
 };
 use halo2::poly::{
-    commitment::{Blind, Params},
-    Rotation,
+    commitment::{Blind, CommitmentScheme, OpeningGuard, Params, Prover, Verifier},
+    kzg, Coeff, EvaluationDomain, LagrangeCoeff, PartLagrangeCoeff, Polynomial, Rotation,
 };
+use halo2::primitives::bn254;
 use halo2::transcript::{Blake2bRead, Blake2bWrite};
+use rand::rngs::{OsRng, StdRng};
+use rand::SeedableRng;
 use std::marker::PhantomData;
 
+/// Creates a proof for `circuits` with the commitment scheme `params` and the proving
+/// key `pk`, for the values of their instance columns in `instances`, and returns
+/// whether it verifies.
+///
+/// A proof that the prover cannot create, such as one for a lookup input that is not
+/// in its table, does not verify either.
+fn prove_and_verify<C, P, ConcreteCircuit>(
+    params: &P,
+    pk: &ProvingKey<C>,
+    circuits: &[ConcreteCircuit],
+    instances: &[&[Polynomial<C::Scalar, LagrangeCoeff>]],
+) -> bool
+where
+    C: CurveAffine,
+    P: Prover<C> + Verifier<C>,
+    ConcreteCircuit: Circuit<C::Scalar>,
+{
+    let mut transcript = Blake2bWrite::init(vec![]);
+    if create_proof(params, pk, circuits, instances, OsRng, &mut transcript).is_err() {
+        return false;
+    }
+    let proof: Vec<u8> = transcript.finalize();
+
+    let commitments: Vec<Vec<C>> = instances
+        .iter()
+        .map(|instance| {
+            instance
+                .iter()
+                .map(|poly| params.commit_lagrange(poly, Blind::default()).to_affine())
+                .collect()
+        })
+        .collect();
+    let commitments: Vec<&[C]> = commitments.iter().map(|c| &c[..]).collect();
+    let mut transcript = Blake2bRead::init(&proof[..]);
+    match verify_proof(
+        params,
+        pk.get_vk(),
+        params.empty_msm(),
+        &commitments,
+        &mut transcript,
+    ) {
+        Ok(guard) => guard.decide(),
+        Err(_) => false,
+    }
+}
+
 #[test]
 fn plonk_api() {
     const K: u32 = 5;
@@ -241,6 +294,8 @@ fn plonk_api() {
 
     impl<F: FieldExt> Circuit<F> for MyCircuit<F> {
         type Config = PLONKConfig;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
 
         fn configure(meta: &mut ConstraintSystem<F>) -> PLONKConfig {
             let e = meta.advice_column();
@@ -383,6 +438,33 @@ fn plonk_api() {
     let vk = keygen_vk(&params, &empty_circuit).expect("keygen_vk should not fail");
     let pk = keygen_pk(&params, vk, &empty_circuit).expect("keygen_pk should not fail");
 
+    // The proving key can be written out and read back instead of being generated
+    // again, but not with parameters of another size.
+    let mut pk_buffer = vec![];
+    pk.write(&mut pk_buffer).unwrap();
+    let pk = ProvingKey::<EqAffine>::read::<_, MyCircuit<Fp>>(
+        &mut &pk_buffer[..],
+        &params,
+        #[cfg(feature = "circuit-params")]
+        (),
+    )
+    .expect("proving key should be readable");
+    assert!(ProvingKey::<EqAffine>::read::<_, MyCircuit<Fp>>(
+        &mut &pk_buffer[..],
+        &Params::<EqAffine>::new(K + 1),
+        #[cfg(feature = "circuit-params")]
+        ()
+    )
+    .is_err());
+    pk_buffer[0] += 1;
+    assert!(ProvingKey::<EqAffine>::read::<_, MyCircuit<Fp>>(
+        &mut &pk_buffer[..],
+        &params,
+        #[cfg(feature = "circuit-params")]
+        ()
+    )
+    .is_err());
+
     let mut pubinputs = pk.get_vk().get_domain().empty_lagrange();
     pubinputs[0] = instance;
     let pubinput = params
@@ -396,6 +478,7 @@ fn plonk_api() {
     };
     assert_eq!(prover.verify(), Ok(()));
 
+    let mut proofs = vec![];
     for _ in 0..10 {
         let mut transcript = Blake2bWrite::init(vec![]);
         // Create a proof
@@ -404,10 +487,13 @@ fn plonk_api() {
             &pk,
             &[circuit.clone(), circuit.clone()],
             &[&[pubinputs.clone()], &[pubinputs.clone()]],
+            OsRng,
             &mut transcript,
         )
         .expect("proof generation should not fail");
         let proof: Vec<u8> = transcript.finalize();
+        assert_eq!(proof.len(), pk.get_vk().proof_size(2));
+        proofs.push(proof.clone());
 
         let pubinput_slice = &[pubinput];
         let pubinput_slice_copy = &[pubinput];
@@ -435,8 +521,13 @@ fn plonk_api() {
         let mut transcript = Blake2bRead::init(&proof[..]);
         let mut vk_buffer = vec![];
         pk.get_vk().write(&mut vk_buffer).unwrap();
-        let vk = VerifyingKey::<EqAffine>::read::<_, MyCircuit<Fp>>(&mut &vk_buffer[..], &params)
-            .unwrap();
+        let vk = VerifyingKey::<EqAffine>::read::<_, MyCircuit<Fp>>(
+            &mut &vk_buffer[..],
+            &params,
+            #[cfg(feature = "circuit-params")]
+            (),
+        )
+        .unwrap();
         let guard = verify_proof(
             &params,
             &vk,
@@ -456,6 +547,254 @@ fn plonk_api() {
         }
     }
 
+    // Check that the proofs verify as a batch, and that a batch fails if one of its
+    // proofs has a wrong opening.
+    {
+        let batch = |proofs: &[Vec<u8>]| {
+            let mut batch = BatchVerifier::new();
+            for proof in proofs {
+                batch.add_proof(vec![vec![pubinput], vec![pubinput]], proof.clone());
+            }
+            batch.finalize(&params, pk.get_vk())
+        };
+        assert!(batch(&proofs[..3]));
+
+        let mut corrupted = proofs[..3].to_vec();
+        let a = corrupted[1].len() - 64;
+        corrupted[1][a] ^= 1;
+        assert!(!batch(&corrupted));
+    }
+
+    // Check that proofs are byte-identical when their blinding factors come from
+    // the same seed, and are blinded differently otherwise.
+    {
+        let prove = |rng: StdRng| {
+            let mut transcript = Blake2bWrite::init(vec![]);
+            create_proof(
+                &params,
+                &pk,
+                &[circuit.clone(), circuit.clone()],
+                &[&[pubinputs.clone()], &[pubinputs.clone()]],
+                rng,
+                &mut transcript,
+            )
+            .expect("proof generation should not fail");
+            transcript.finalize()
+        };
+        let proof = prove(StdRng::seed_from_u64(42));
+        assert_eq!(proof, prove(StdRng::seed_from_u64(42)));
+        assert_ne!(proof, prove(StdRng::seed_from_u64(43)));
+        assert_ne!(proofs[0], proofs[1]);
+
+        // A proving key without its extended-domain evaluations creates the same
+        // proof.
+        let vk = keygen_vk(&params, &empty_circuit).expect("keygen_vk should not fail");
+        let mut pk = keygen_pk(&params, vk, &empty_circuit).expect("keygen_pk should not fail");
+        pk.discard_cosets();
+        let mut transcript = Blake2bWrite::init(vec![]);
+        create_proof(
+            &params,
+            &pk,
+            &[circuit.clone(), circuit.clone()],
+            &[&[pubinputs.clone()], &[pubinputs.clone()]],
+            StdRng::seed_from_u64(42),
+            &mut transcript,
+        )
+        .expect("proof generation should not fail");
+        assert_eq!(proof, transcript.finalize());
+    }
+
+    // Check that a prover that hands the operations on its advice columns to workers
+    // creates the same proof, and that it hands them every batch.
+    {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        #[derive(Default)]
+        struct CountingWorkers {
+            commitments: AtomicUsize,
+            ffts: AtomicUsize,
+        }
+
+        impl ProverWorkers<EqAffine> for CountingWorkers {
+            fn commit_lagrange<P: CommitmentScheme<EqAffine>>(
+                &self,
+                params: &P,
+                polys: &[&Polynomial<Fp, LagrangeCoeff>],
+                blinds: &[Blind<Fp>],
+            ) -> Result<Vec<EqAffine>, Error> {
+                self.commitments.fetch_add(polys.len(), Ordering::SeqCst);
+                Ok(polys
+                    .iter()
+                    .zip(blinds.iter())
+                    .map(|(poly, blind)| params.commit_lagrange(poly, *blind).to_affine())
+                    .collect())
+            }
+
+            fn coeff_to_extended_part(
+                &self,
+                domain: &EvaluationDomain<Fp>,
+                polys: &[Polynomial<Fp, Coeff>],
+                part: usize,
+            ) -> Result<Vec<Polynomial<Fp, PartLagrangeCoeff>>, Error> {
+                self.ffts.fetch_add(polys.len(), Ordering::SeqCst);
+                Ok(polys
+                    .iter()
+                    .map(|poly| domain.coeff_to_extended_part(poly.clone(), part))
+                    .collect())
+            }
+        }
+
+        let workers = CountingWorkers::default();
+        let mut stages = 0;
+        let mut transcript = Blake2bWrite::init(vec![]);
+        create_proof_with_options(
+            &params,
+            &pk,
+            ProverOptions::new()
+                .circuits(&[circuit.clone(), circuit.clone()])
+                .instances(&[&[pubinputs.clone()], &[pubinputs.clone()]])
+                .workers(&workers)
+                .progress(|_| {
+                    stages += 1;
+                    true
+                }),
+            StdRng::seed_from_u64(42),
+            &mut transcript,
+        )
+        .expect("proof generation should not fail");
+        let proof: Vec<u8> = transcript.finalize();
+        assert_eq!(stages, 6);
+
+        let mut transcript = Blake2bWrite::init(vec![]);
+        create_proof(
+            &params,
+            &pk,
+            &[circuit.clone(), circuit.clone()],
+            &[&[pubinputs.clone()], &[pubinputs.clone()]],
+            StdRng::seed_from_u64(42),
+            &mut transcript,
+        )
+        .expect("proof generation should not fail");
+        assert_eq!(proof, transcript.finalize());
+
+        // Each advice column of each circuit is committed to once, and evaluated once
+        // over each part of the extended domain.
+        let commitments = workers.commitments.load(Ordering::SeqCst);
+        assert!(commitments > 0);
+        assert_eq!(
+            workers.ffts.load(Ordering::SeqCst),
+            commitments * pk.get_vk().get_domain().extended_parts()
+        );
+    }
+
+    // Check that the prover reports each of its stages, and stops at the stage
+    // where it is cancelled.
+    {
+        let prove = |cancel_at: Option<ProverStage>| {
+            let mut stages = vec![];
+            let mut transcript = Blake2bWrite::init(vec![]);
+            let result = create_proof_with_options(
+                &params,
+                &pk,
+                ProverOptions::new()
+                    .circuits(std::slice::from_ref(&circuit))
+                    .instances(&[&[pubinputs.clone()]])
+                    .progress(|stage| {
+                        stages.push(stage);
+                        Some(stage) != cancel_at
+                    }),
+                OsRng,
+                &mut transcript,
+            );
+            (result, stages)
+        };
+        let (result, stages) = prove(None);
+        assert!(result.is_ok());
+        assert_eq!(
+            stages,
+            vec![
+                ProverStage::Witness,
+                ProverStage::Lookups,
+                ProverStage::GrandProducts,
+                ProverStage::Vanishing,
+                ProverStage::Evaluations,
+                ProverStage::Opening,
+            ]
+        );
+        let (result, stages) = prove(Some(ProverStage::Vanishing));
+        assert!(matches!(result, Err(Error::Cancelled)));
+        assert_eq!(stages.last(), Some(&ProverStage::Vanishing));
+        assert_eq!(stages.len(), 4);
+    }
+
+    // Check that proofs round-trip through their versioned encoding, which carries
+    // the same messages as the transcript, and that any other encoding is rejected.
+    {
+        let proof = Proof::create(
+            &params,
+            &pk,
+            &[circuit.clone(), circuit.clone()],
+            &[&[pubinputs.clone()], &[pubinputs.clone()]],
+            StdRng::seed_from_u64(42),
+        )
+        .expect("proof generation should not fail");
+        let bytes = proof.to_bytes();
+        assert_eq!(Proof::from_bytes(&bytes).unwrap(), proof);
+
+        let mut transcript = Blake2bWrite::init(vec![]);
+        create_proof(
+            &params,
+            &pk,
+            &[circuit.clone(), circuit.clone()],
+            &[&[pubinputs.clone()], &[pubinputs.clone()]],
+            StdRng::seed_from_u64(42),
+            &mut transcript,
+        )
+        .expect("proof generation should not fail");
+        let messages: Vec<u8> = bytes[5..]
+            .chunks(33)
+            .flat_map(|m| m[1..].to_vec())
+            .collect();
+        assert_eq!(messages, transcript.finalize());
+
+        let verify = |proof: &Proof<EqAffine>| {
+            proof
+                .verify(
+                    &params,
+                    pk.get_vk(),
+                    params.empty_msm(),
+                    &[&[pubinput], &[pubinput]],
+                )
+                .map(|guard| guard.use_challenges().eval())
+        };
+        assert!(matches!(verify(&proof), Ok(true)));
+
+        let tampered = |f: &dyn Fn(&mut Vec<u8>)| {
+            let mut bytes = bytes.clone();
+            f(&mut bytes);
+            Proof::<EqAffine>::from_bytes(&bytes)
+        };
+        assert!(tampered(&|bytes| bytes[0] = 2).is_err());
+        assert!(tampered(&|bytes| bytes.push(0)).is_err());
+        assert!(tampered(&|bytes| bytes.truncate(bytes.len() - 1)).is_err());
+        assert!(tampered(&|bytes| bytes[5] = 2).is_err());
+        // A scalar that is not reduced has no canonical encoding.
+        assert!(tampered(&|bytes| {
+            let last = bytes.len() - 33;
+            bytes[last + 1..].copy_from_slice(&[0xff; 32]);
+        })
+        .is_err());
+
+        // A message of the wrong type is rejected by the verifier.
+        let retagged = tampered(&|bytes| {
+            let last = bytes.len() - 33;
+            bytes[last] = 0;
+            bytes[last + 1..].copy_from_slice(&pubinput.to_bytes());
+        })
+        .unwrap();
+        assert!(matches!(verify(&retagged), Err(Error::OpeningError)));
+    }
+
     // Check that the verification key has not changed unexpectedly
     {
         assert_eq!(
@@ -753,6 +1092,7 @@ fn plonk_api() {
                 ],
             },
         ],
+        shuffles: [],
     },
     fixed_commitments: [
         (0x046711bb0579a337420e33de9d54438e7c3a9cc47b6728b873d1fd0214d7eb58, 0x2416b30fadfacd828cf76891a2a5f0fe90d7ae0e5a8df947e98660ffbebf72e4),
@@ -784,3 +1124,2429 @@ fn plonk_api() {
         );
     }
 }
+
+#[derive(Clone)]
+struct MemoryConfig {
+    addr: Column<Advice>,
+    value: Column<Advice>,
+    read_addr: Column<Advice>,
+    read_value: Column<Advice>,
+}
+
+/// A circuit whose prover writes a memory, and reads from it with a lookup, proven
+/// with the log-derivative argument if `LOG_DERIVATIVE` is set.
+#[derive(Clone, Default)]
+struct MemoryCircuit<const LOG_DERIVATIVE: bool> {
+    memory: Vec<(Fp, Fp)>,
+    reads: Vec<(Fp, Fp)>,
+}
+
+impl<const LOG_DERIVATIVE: bool> Circuit<Fp> for MemoryCircuit<LOG_DERIVATIVE> {
+    type Config = MemoryConfig;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> MemoryConfig {
+        if LOG_DERIVATIVE {
+            meta.set_lookup_argument(LookupArgument::LogDerivative);
+        }
+
+        let addr = meta.advice_column();
+        let value = meta.advice_column();
+        let read_addr = meta.advice_column();
+        let read_value = meta.advice_column();
+
+        // Each read of (address, value) must be in the memory assigned by the prover.
+        let table = [
+            meta.query_advice(addr, Rotation::cur()),
+            meta.query_advice(value, Rotation::cur()),
+        ];
+        let input = [
+            meta.query_advice(read_addr, Rotation::cur()),
+            meta.query_advice(read_value, Rotation::cur()),
+        ];
+        meta.lookup(&input, &table);
+
+        MemoryConfig {
+            addr,
+            value,
+            read_addr,
+            read_value,
+        }
+    }
+
+    fn synthesize(&self, cs: &mut impl Assignment<Fp>, config: MemoryConfig) -> Result<(), Error> {
+        for (row, (addr, value)) in self.memory.iter().enumerate() {
+            cs.assign_advice(|| "addr", config.addr, row, || Ok(*addr))?;
+            cs.assign_advice(|| "value", config.value, row, || Ok(*value))?;
+        }
+        for (row, (addr, value)) in self.reads.iter().enumerate() {
+            cs.assign_advice(|| "read addr", config.read_addr, row, || Ok(*addr))?;
+            cs.assign_advice(|| "read value", config.read_value, row, || Ok(*value))?;
+        }
+        Ok(())
+    }
+}
+
+/// Proves and verifies reads from a memory, and returns the length of the proof.
+fn memory_lookups<const LOG_DERIVATIVE: bool>() -> usize {
+    const K: u32 = 4;
+
+    // Initialize the polynomial commitment parameters
+    let params: Params<EqAffine> = Params::new(K);
+
+    let memory: Vec<_> = (1..6)
+        .map(|addr| (Fp::from_u64(addr), Fp::from_u64(addr * addr + 7)))
+        .collect();
+    let circuit = MemoryCircuit::<LOG_DERIVATIVE> {
+        reads: vec![memory[3], memory[0], memory[3], memory[4]],
+        memory: memory.clone(),
+    };
+    let bad_circuit = MemoryCircuit::<LOG_DERIVATIVE> {
+        reads: vec![memory[1], (memory[2].0, memory[2].1 + Fp::one())],
+        memory,
+    };
+
+    let empty_circuit = MemoryCircuit::<LOG_DERIVATIVE>::default();
+    let vk = keygen_vk(&params, &empty_circuit).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &empty_circuit).expect("keygen_pk should not fail");
+
+    let prover = MockProver::run(K, &circuit, vec![]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+    let prover = MockProver::run(K, &bad_circuit, vec![]).unwrap();
+    assert_eq!(
+        prover.verify(),
+        Err(VerifyFailure::Lookup {
+            lookup_index: 0,
+            row: 1
+        })
+    );
+
+    assert!(prove_and_verify(&params, &pk, &[circuit], &[&[]]));
+    // The prover cannot read a value that is not in the memory.
+    assert!(!prove_and_verify(&params, &pk, &[bad_circuit], &[&[]]));
+
+    pk.get_vk().proof_size(1)
+}
+
+#[test]
+fn lookup_advice_table() {
+    memory_lookups::<false>();
+}
+
+#[test]
+fn lookup_log_derivative() {
+    // The log-derivative argument commits to fewer columns.
+    assert!(memory_lookups::<true>() < memory_lookups::<false>());
+
+    // The verifying key binds the argument with which its lookups are proven.
+    let params: Params<EqAffine> = Params::new(4);
+    let vk = keygen_vk(&params, &MemoryCircuit::<true>::default()).unwrap();
+    let other_vk = keygen_vk(&params, &MemoryCircuit::<false>::default()).unwrap();
+    assert_ne!(vk.digest(), other_vk.digest());
+}
+
+#[test]
+fn shuffle_api() {
+    const K: u32 = 4;
+
+    // Initialize the polynomial commitment parameters
+    let params: Params<EqAffine> = Params::new(K);
+
+    #[derive(Clone)]
+    struct ShuffleConfig {
+        original: [Column<Advice>; 2],
+        shuffled: [Column<Advice>; 2],
+    }
+
+    /// A circuit whose prover claims that the rows of `shuffled` are a permutation of
+    /// the rows of `original`.
+    #[derive(Clone, Default)]
+    struct ShuffleCircuit {
+        original: Vec<[u64; 2]>,
+        shuffled: Vec<[u64; 2]>,
+    }
+
+    impl Circuit<Fp> for ShuffleCircuit {
+        type Config = ShuffleConfig;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> ShuffleConfig {
+            let original = [meta.advice_column(), meta.advice_column()];
+            let shuffled = [meta.advice_column(), meta.advice_column()];
+
+            let input: Vec<_> = original
+                .iter()
+                .map(|column| meta.query_advice(*column, Rotation::cur()))
+                .collect();
+            let shuffle: Vec<_> = shuffled
+                .iter()
+                .map(|column| meta.query_advice(*column, Rotation::cur()))
+                .collect();
+            meta.shuffle(&input, &shuffle);
+
+            ShuffleConfig { original, shuffled }
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            config: ShuffleConfig,
+        ) -> Result<(), Error> {
+            for (columns, rows) in [
+                (config.original, &self.original),
+                (config.shuffled, &self.shuffled),
+            ]
+            .iter()
+            {
+                for (row, values) in rows.iter().enumerate() {
+                    for (column, value) in columns.iter().zip(values.iter()) {
+                        cs.assign_advice(|| "value", *column, row, || Ok(Fp::from_u64(*value)))?;
+                    }
+                }
+            }
+            Ok(())
+        }
+    }
+
+    let original = vec![[1, 10], [2, 20], [3, 30], [2, 20]];
+    let circuit = ShuffleCircuit {
+        original: original.clone(),
+        shuffled: vec![[2, 20], [3, 30], [2, 20], [1, 10]],
+    };
+    // The same values in each column, but with the rows broken up.
+    let bad_circuit = ShuffleCircuit {
+        original,
+        shuffled: vec![[2, 20], [3, 20], [2, 30], [1, 10]],
+    };
+
+    let vk = keygen_vk(&params, &ShuffleCircuit::default()).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &ShuffleCircuit::default()).expect("keygen_pk should not fail");
+
+    let prover = MockProver::run(K, &circuit, vec![]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+    let prover = MockProver::run(K, &bad_circuit, vec![]).unwrap();
+    assert_eq!(
+        prover.verify(),
+        Err(VerifyFailure::Shuffle { shuffle_index: 0 })
+    );
+
+    assert!(prove_and_verify(&params, &pk, &[circuit.clone()], &[&[]]));
+    assert!(!prove_and_verify(&params, &pk, &[bad_circuit], &[&[]]));
+
+    // The grand product of the shuffle is part of the proof.
+    let mut transcript = Blake2bWrite::init(vec![]);
+    create_proof(&params, &pk, &[circuit], &[&[]], OsRng, &mut transcript)
+        .expect("proof generation should not fail");
+    assert_eq!(transcript.finalize().len(), pk.get_vk().proof_size(1));
+}
+
+#[test]
+fn challenge_api() {
+    const K: u32 = 4;
+
+    // Initialize the polynomial commitment parameters
+    let params: Params<EqAffine> = Params::new(K);
+
+    #[derive(Clone)]
+    struct ChallengeConfig {
+        a: Column<Advice>,
+        b: Column<Advice>,
+        z: Column<Advice>,
+        q_first: Column<Fixed>,
+        q_step: Column<Fixed>,
+        q_last: Column<Fixed>,
+        c: Challenge,
+    }
+
+    /// A circuit whose prover claims that the values of `b` are a permutation of the
+    /// values of `a`, with a grand product over a challenge squeezed after `a` and `b`
+    /// are committed to.
+    #[derive(Clone, Default)]
+    struct ChallengeCircuit {
+        a: Vec<u64>,
+        b: Vec<u64>,
+    }
+
+    impl Circuit<Fp> for ChallengeCircuit {
+        type Config = ChallengeConfig;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> ChallengeConfig {
+            let a = meta.advice_column();
+            let b = meta.advice_column();
+            let c = meta.challenge_usable_after(Phase::first());
+            let z = meta.advice_column_in(Phase::second());
+            let q_first = meta.fixed_column();
+            let q_step = meta.fixed_column();
+            let q_last = meta.fixed_column();
+
+            meta.create_gate("first", |meta| {
+                let q_first = meta.query_fixed(q_first, Rotation::cur());
+                let z = meta.query_advice(z, Rotation::cur());
+                q_first * (z - Expression::Constant(Fp::one()))
+            });
+            meta.create_gate("step", |meta| {
+                let q_step = meta.query_fixed(q_step, Rotation::cur());
+                let a = meta.query_advice(a, Rotation::cur());
+                let b = meta.query_advice(b, Rotation::cur());
+                let z_next = meta.query_advice(z, Rotation::next());
+                let z = meta.query_advice(z, Rotation::cur());
+                let c = meta.query_challenge(c);
+                q_step * (z_next * (b + c.clone()) - z * (a + c))
+            });
+            meta.create_gate("last", |meta| {
+                let q_last = meta.query_fixed(q_last, Rotation::cur());
+                let z = meta.query_advice(z, Rotation::cur());
+                q_last * (z - Expression::Constant(Fp::one()))
+            });
+
+            ChallengeConfig {
+                a,
+                b,
+                z,
+                q_first,
+                q_step,
+                q_last,
+                c,
+            }
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            config: ChallengeConfig,
+        ) -> Result<(), Error> {
+            const ROWS: usize = 4;
+
+            for row in 0..=ROWS {
+                let enable = |enabled: bool| if enabled { Fp::one() } else { Fp::zero() };
+                cs.assign_fixed(|| "q_first", config.q_first, row, || Ok(enable(row == 0)))?;
+                cs.assign_fixed(|| "q_step", config.q_step, row, || Ok(enable(row < ROWS)))?;
+                cs.assign_fixed(|| "q_last", config.q_last, row, || Ok(enable(row == ROWS)))?;
+            }
+            for (row, (a, b)) in self.a.iter().zip(self.b.iter()).enumerate() {
+                cs.assign_advice(|| "a", config.a, row, || Ok(Fp::from_u64(*a)))?;
+                cs.assign_advice(|| "b", config.b, row, || Ok(Fp::from_u64(*b)))?;
+            }
+
+            // The grand product can only be computed once the challenge is known.
+            let z = cs.get_challenge(config.c).map(|c| {
+                let mut z = vec![Fp::one()];
+                for (a, b) in self.a.iter().zip(self.b.iter()) {
+                    let ratio = (Fp::from_u64(*a) + c) * (Fp::from_u64(*b) + c).invert().unwrap();
+                    z.push(z[z.len() - 1] * ratio);
+                }
+                z
+            });
+            for row in 0..=ROWS {
+                let value = z.as_ref().and_then(|z| z.get(row).cloned());
+                cs.assign_advice(|| "z", config.z, row, || value.ok_or(Error::SynthesisError))?;
+            }
+            Ok(())
+        }
+    }
+
+    let circuit = ChallengeCircuit {
+        a: vec![1, 2, 3, 2],
+        b: vec![2, 3, 2, 1],
+    };
+    let bad_circuit = ChallengeCircuit {
+        a: vec![1, 2, 3, 2],
+        b: vec![2, 3, 3, 1],
+    };
+
+    let vk = keygen_vk(&params, &ChallengeCircuit::default()).expect("keygen_vk should not fail");
+    let pk =
+        keygen_pk(&params, vk, &ChallengeCircuit::default()).expect("keygen_pk should not fail");
+
+    let prover = MockProver::run(K, &circuit, vec![]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+    let prover = MockProver::run(K, &bad_circuit, vec![]).unwrap();
+    assert_eq!(
+        prover.verify(),
+        Err(VerifyFailure::Gate {
+            gate_index: 2,
+            gate_name: "last",
+            constraint_index: 0,
+            constraint_name: "",
+            row: 4
+        })
+    );
+
+    assert!(prove_and_verify(&params, &pk, &[circuit.clone()], &[&[]]));
+    assert!(!prove_and_verify(&params, &pk, &[bad_circuit], &[&[]]));
+
+    // The advice of the second phase depends on the challenge, so the circuit has no
+    // witness to compute ahead of proving.
+    assert!(matches!(
+        generate_witness(pk.get_vk(), &circuit),
+        Err(Error::SynthesisError)
+    ));
+}
+
+#[test]
+fn instance_columns() {
+    const K: u32 = 4;
+
+    // Initialize the polynomial commitment parameters
+    let params: Params<EqAffine> = Params::new(K);
+
+    #[derive(Clone)]
+    struct InstanceConfig {
+        a: Column<Advice>,
+        q: Column<Fixed>,
+    }
+
+    /// A circuit whose prover claims that `a[i] = x[i] * y[i + 1] + y[i]` for the
+    /// public inputs `x` and `y` in two instance columns.
+    #[derive(Clone, Default)]
+    struct InstanceCircuit {
+        a: Vec<u64>,
+    }
+
+    impl Circuit<Fp> for InstanceCircuit {
+        type Config = InstanceConfig;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> InstanceConfig {
+            let a = meta.advice_column();
+            let q = meta.fixed_column();
+            let x = meta.instance_column();
+            let y = meta.instance_column();
+
+            meta.create_gate("row", |meta| {
+                let q = meta.query_fixed(q, Rotation::cur());
+                let a = meta.query_advice(a, Rotation::cur());
+                let x = meta.query_instance(x, Rotation::cur());
+                let y_next = meta.query_instance(y, Rotation::next());
+                let y = meta.query_instance(y, Rotation::cur());
+                q * (a - (x * y_next + y))
+            });
+
+            InstanceConfig { a, q }
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            config: InstanceConfig,
+        ) -> Result<(), Error> {
+            for (row, a) in self.a.iter().enumerate() {
+                cs.assign_fixed(|| "q", config.q, row, || Ok(Fp::one()))?;
+                cs.assign_advice(|| "a", config.a, row, || Ok(Fp::from_u64(*a)))?;
+            }
+            Ok(())
+        }
+    }
+
+    let x: Vec<_> = [2, 3, 4].iter().map(|x| Fp::from_u64(*x)).collect();
+    let y: Vec<_> = [5, 6, 7, 8].iter().map(|y| Fp::from_u64(*y)).collect();
+    let circuit = InstanceCircuit {
+        a: vec![2 * 6 + 5, 3 * 7 + 6, 4 * 8 + 7],
+    };
+    let bad_y: Vec<_> = [5, 6, 7, 9].iter().map(|y| Fp::from_u64(*y)).collect();
+
+    // The fixed column `q` is assigned by the circuit, so the keys are generated from
+    // it rather than from an empty circuit.
+    let vk = keygen_vk(&params, &circuit).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &circuit).expect("keygen_pk should not fail");
+    let vk = pk.get_vk();
+
+    let prover = MockProver::run(K, &circuit, vec![x.clone(), y.clone()]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+    let prover = MockProver::run(K, &circuit, vec![x.clone(), bad_y.clone()]).unwrap();
+    assert_eq!(
+        prover.verify(),
+        Err(VerifyFailure::Gate {
+            gate_index: 0,
+            gate_name: "row",
+            constraint_index: 0,
+            constraint_name: "",
+            row: 2
+        })
+    );
+    assert!(MockProver::run(K, &circuit, vec![x.clone()]).is_err());
+
+    // The instance columns are given by their values, and committed to by the
+    // verifier.
+    let instance = vk.instance_values(&[&x, &y]).unwrap();
+    let bad_instance = vk.instance_values(&[&x, &bad_y]).unwrap();
+    assert!(vk.instance_values(&[&x, &[Fp::zero(); 17]]).is_err());
+    assert_eq!(
+        vk.commit_instances(&params, &[&x, &y]).unwrap(),
+        commit_instance(&params, &pk, &instance)
+            .unwrap()
+            .commitments()
+    );
+    assert!(prove_and_verify(
+        &params,
+        &pk,
+        std::slice::from_ref(&circuit),
+        &[&instance]
+    ));
+    assert!(!prove_and_verify(
+        &params,
+        &pk,
+        std::slice::from_ref(&circuit),
+        &[&bad_instance]
+    ));
+
+    // An instance committed to once can be reused across proofs, which are the same
+    // as those created from its values.
+    let committed = commit_instance(&params, &pk, &instance).unwrap();
+    assert!(commit_instance(&params, &pk, &instance[..1]).is_err());
+    let prove = |reuse: bool, seed: u64| {
+        let mut transcript = Blake2bWrite::init(vec![]);
+        let rng = StdRng::seed_from_u64(seed);
+        if reuse {
+            create_proof_with_options(
+                &params,
+                &pk,
+                ProverOptions::new()
+                    .circuits(std::slice::from_ref(&circuit))
+                    .committed_instances(&[&committed]),
+                rng,
+                &mut transcript,
+            )
+        } else {
+            create_proof(
+                &params,
+                &pk,
+                std::slice::from_ref(&circuit),
+                &[&instance],
+                rng,
+                &mut transcript,
+            )
+        }
+        .expect("proof generation should not fail");
+        transcript.finalize()
+    };
+    assert_eq!(prove(true, 1), prove(false, 1));
+    for seed in 0..2 {
+        let proof: Vec<u8> = prove(true, seed);
+        let mut transcript = Blake2bRead::init(&proof[..]);
+        let guard = verify_proof(
+            &params,
+            vk,
+            params.empty_msm(),
+            &[committed.commitments()],
+            &mut transcript,
+        )
+        .expect("proof should verify");
+        assert!(guard.use_challenges().eval());
+    }
+}
+
+#[test]
+fn combined_selectors() {
+    const K: u32 = 4;
+
+    // Initialize the polynomial commitment parameters
+    let params: Params<EqAffine> = Params::new(K);
+
+    #[derive(Clone)]
+    struct SelectorConfig {
+        a: Column<Advice>,
+        b: Column<Advice>,
+        c: Column<Advice>,
+        q_add: Selector,
+        q_sub: Selector,
+        q_mul: Selector,
+    }
+
+    /// A circuit whose rows each add, subtract or multiply `a` and `b` into `c`.
+    #[derive(Clone, Default)]
+    struct SelectorCircuit {
+        rows: Vec<(u64, u64, u64)>,
+    }
+
+    impl Circuit<Fp> for SelectorCircuit {
+        type Config = SelectorConfig;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> SelectorConfig {
+            let a = meta.advice_column();
+            let b = meta.advice_column();
+            let c = meta.advice_column();
+            let q_add = meta.selector();
+            let q_sub = meta.selector();
+            let q_mul = meta.selector();
+
+            meta.create_gate("add", |meta| {
+                let q = meta.query_selector(q_add, Rotation::cur());
+                let a = meta.query_advice(a, Rotation::cur());
+                let b = meta.query_advice(b, Rotation::cur());
+                let c = meta.query_advice(c, Rotation::cur());
+                q * (a + b - c)
+            });
+            meta.create_gate("sub", |meta| {
+                let q = meta.query_selector(q_sub, Rotation::cur());
+                let a = meta.query_advice(a, Rotation::cur());
+                let b = meta.query_advice(b, Rotation::cur());
+                let c = meta.query_advice(c, Rotation::cur());
+                q * (a - b - c)
+            });
+            meta.create_gate("mul", |meta| {
+                let q = meta.query_selector(q_mul, Rotation::cur());
+                let a = meta.query_advice(a, Rotation::cur());
+                let b = meta.query_advice(b, Rotation::cur());
+                let c = meta.query_advice(c, Rotation::cur());
+                q * (a * b - c)
+            });
+
+            SelectorConfig {
+                a,
+                b,
+                c,
+                q_add,
+                q_sub,
+                q_mul,
+            }
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            config: SelectorConfig,
+        ) -> Result<(), Error> {
+            for row in 0..6 {
+                let q = match row % 3 {
+                    0 => &config.q_add,
+                    1 => &config.q_sub,
+                    _ => &config.q_mul,
+                };
+                cs.enable_selector(|| "q", q, row)?;
+            }
+            for (row, (a, b, c)) in self.rows.iter().enumerate() {
+                cs.assign_advice(|| "a", config.a, row, || Ok(Fp::from_u64(*a)))?;
+                cs.assign_advice(|| "b", config.b, row, || Ok(Fp::from_u64(*b)))?;
+                cs.assign_advice(|| "c", config.c, row, || Ok(Fp::from_u64(*c)))?;
+            }
+            Ok(())
+        }
+    }
+
+    let circuit = SelectorCircuit {
+        rows: vec![
+            (2, 3, 5),
+            (7, 4, 3),
+            (3, 4, 12),
+            (1, 1, 2),
+            (9, 9, 0),
+            (5, 6, 30),
+        ],
+    };
+    let bad_circuit = SelectorCircuit {
+        rows: vec![
+            (2, 3, 5),
+            (7, 4, 3),
+            (3, 4, 12),
+            (1, 1, 2),
+            (9, 9, 18),
+            (5, 6, 30),
+        ],
+    };
+
+    let prover = MockProver::run(K, &circuit, vec![]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+    let prover = MockProver::run(K, &bad_circuit, vec![]).unwrap();
+    assert_eq!(
+        prover.verify(),
+        Err(VerifyFailure::Gate {
+            gate_index: 1,
+            gate_name: "sub",
+            constraint_index: 0,
+            constraint_name: "",
+            row: 4
+        })
+    );
+
+    let vk = keygen_vk(&params, &SelectorCircuit::default()).expect("keygen_vk should not fail");
+    // The linear gates share a fixed column, but the degree of the circuit leaves no
+    // room for the multiplication gate to join them.
+    assert!(format!("{:?}", vk.pinned()).contains("num_fixed_columns: 2,"));
+
+    // The selectors are kept in the serialized verifying key.
+    let mut bytes = vec![];
+    vk.write(&mut bytes).unwrap();
+    let read_vk = VerifyingKey::<EqAffine>::read::<_, SelectorCircuit>(
+        &mut &bytes[..],
+        &params,
+        #[cfg(feature = "circuit-params")]
+        (),
+    )
+    .expect("verifying key should be readable");
+    assert_eq!(
+        format!("{:?}", read_vk.pinned()),
+        format!("{:?}", vk.pinned())
+    );
+
+    let pk =
+        keygen_pk(&params, vk, &SelectorCircuit::default()).expect("keygen_pk should not fail");
+
+    assert!(prove_and_verify(&params, &pk, &[circuit], &[&[]]));
+    assert!(!prove_and_verify(&params, &pk, &[bad_circuit], &[&[]]));
+}
+
+#[test]
+fn keygen_pk_from_vk() {
+    const K: u32 = 3;
+
+    // Initialize the polynomial commitment parameters
+    let params: Params<EqAffine> = Params::new(K);
+
+    /// A circuit that constrains `a` to `q` on the rows where `s` is enabled, with
+    /// `s` enabled on its first `rows` rows, `q` assigned the row number plus
+    /// `offset`, and the first two rows of `a` copied to each other if `copy`.
+    #[derive(Clone)]
+    struct RowsCircuit {
+        rows: usize,
+        offset: u64,
+        copy: bool,
+    }
+
+    impl RowsCircuit {
+        fn new(rows: usize) -> Self {
+            RowsCircuit {
+                rows,
+                offset: 0,
+                copy: false,
+            }
+        }
+    }
+
+    impl Circuit<Fp> for RowsCircuit {
+        type Config = (Column<Advice>, Column<Fixed>, Selector, Permutation);
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let a = meta.advice_column();
+            let q = meta.fixed_column();
+            let s = meta.selector();
+
+            meta.create_gate("a = q", |meta| {
+                let s = meta.query_selector(s, Rotation::cur());
+                let a = meta.query_advice(a, Rotation::cur());
+                let q = meta.query_fixed(q, Rotation::cur());
+                s * (a - q)
+            });
+
+            let perm = meta.permutation(&[a.into()]);
+
+            (a, q, s, perm)
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            (a, q, s, perm): Self::Config,
+        ) -> Result<(), Error> {
+            for row in 0..self.rows {
+                let value = Fp::from_u64(row as u64 + self.offset);
+                cs.enable_selector(|| "s", &s, row)?;
+                cs.assign_fixed(|| "q", q, row, || Ok(value))?;
+                cs.assign_advice(|| "a", a, row, || Ok(value))?;
+            }
+            if self.copy {
+                cs.copy(&perm, a.into(), 0, a.into(), 1)?;
+            }
+            Ok(())
+        }
+    }
+
+    let vk = keygen_vk(&params, &RowsCircuit::new(2)).expect("keygen_vk should not fail");
+
+    // The proving key is derived from a verifying key that was published, and read
+    // back by the prover.
+    let mut vk_buffer = vec![];
+    vk.write(&mut vk_buffer).unwrap();
+    let read_vk = || {
+        VerifyingKey::<EqAffine>::read::<_, RowsCircuit>(
+            &mut &vk_buffer[..],
+            &params,
+            #[cfg(feature = "circuit-params")]
+            (),
+        )
+        .unwrap()
+    };
+    let pk =
+        keygen_pk(&params, read_vk(), &RowsCircuit::new(2)).expect("keygen_pk should not fail");
+
+    let mut transcript = Blake2bWrite::init(vec![]);
+    create_proof(
+        &params,
+        &pk,
+        &[RowsCircuit::new(2)],
+        &[&[]],
+        OsRng,
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+    let proof: Vec<u8> = transcript.finalize();
+    let mut transcript = Blake2bRead::init(&proof[..]);
+    let guard = verify_proof(&params, &vk, params.empty_msm(), &[&[]], &mut transcript)
+        .expect("proof should verify");
+    assert!(guard.use_challenges().eval());
+
+    // A strict transcript also verifies the proof, but not once a byte is appended.
+    let mut transcript = Blake2bRead::init_strict(&proof[..]);
+    let guard = verify_proof(&params, &vk, params.empty_msm(), &[&[]], &mut transcript)
+        .expect("proof should verify");
+    assert!(guard.use_challenges().eval());
+    assert!(transcript.finalize().is_ok());
+    let mut padded = proof.clone();
+    padded.push(0);
+    let mut transcript = Blake2bRead::init_strict(&padded[..]);
+    verify_proof(&params, &vk, params.empty_msm(), &[&[]], &mut transcript)
+        .expect("proof should verify");
+    assert!(transcript.finalize().is_err());
+
+    // The digest of the key is that of the key it was read back as, and is the one
+    // absorbed into transcripts.
+    assert_eq!(read_vk().digest(), vk.digest());
+    let mut transcript = Blake2bWrite::<_, EqAffine>::init(vec![]);
+    assert_eq!(vk.hash_into(&mut transcript).unwrap(), vk.digest());
+    let other_vk = keygen_vk(&params, &RowsCircuit::new(3)).expect("keygen_vk should not fail");
+    assert_ne!(other_vk.digest(), vk.digest());
+
+    // A circuit that enables its selector on other rows does not match the key.
+    assert!(matches!(
+        keygen_pk(&params, read_vk(), &RowsCircuit::new(3)),
+        Err(Error::IncompatibleVerifyingKey)
+    ));
+    // Nor does one that assigns other values to its fixed column, or that copies
+    // other cells, although its constraint system and selectors are those of the key.
+    assert!(matches!(
+        keygen_pk(
+            &params,
+            read_vk(),
+            &RowsCircuit {
+                offset: 1,
+                ..RowsCircuit::new(2)
+            }
+        ),
+        Err(Error::IncompatibleVerifyingKey)
+    ));
+    assert!(matches!(
+        keygen_pk(
+            &params,
+            read_vk(),
+            &RowsCircuit {
+                copy: true,
+                ..RowsCircuit::new(2)
+            }
+        ),
+        Err(Error::IncompatibleVerifyingKey)
+    ));
+    // Nor do parameters of another size.
+    assert!(matches!(
+        keygen_pk(
+            &Params::<EqAffine>::new(K + 1),
+            read_vk(),
+            &RowsCircuit::new(2)
+        ),
+        Err(Error::IncompatibleParams)
+    ));
+}
+
+#[test]
+fn unblinded_advice() {
+    const K: u32 = 3;
+
+    // Initialize the polynomial commitment parameters
+    let params: Params<EqAffine> = Params::new(K);
+
+    /// A circuit that copies the public state `a`, committed to without blinding, into
+    /// the blinded column `b`, with `b_offset` added to the first copy.
+    #[derive(Clone, Default)]
+    struct StateCircuit {
+        a: Vec<u64>,
+        b_offset: u64,
+    }
+
+    impl Circuit<Fp> for StateCircuit {
+        type Config = (Column<Advice>, Column<Advice>, Column<Fixed>);
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let a = meta.unblinded_advice_column();
+            let b = meta.advice_column();
+            let q = meta.fixed_column();
+
+            meta.create_gate("a = b", |meta| {
+                let q = meta.query_fixed(q, Rotation::cur());
+                let a = meta.query_advice(a, Rotation::cur());
+                let b = meta.query_advice(b, Rotation::cur());
+                q * (a - b)
+            });
+
+            (a, b, q)
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            (a, b, q): Self::Config,
+        ) -> Result<(), Error> {
+            for (row, value) in self.a.iter().enumerate() {
+                cs.assign_fixed(|| "q", q, row, || Ok(Fp::one()))?;
+                cs.assign_advice(|| "a", a, row, || Ok(Fp::from_u64(*value)))?;
+                let offset = if row == 0 { self.b_offset } else { 0 };
+                cs.assign_advice(|| "b", b, row, || Ok(Fp::from_u64(*value + offset)))?;
+            }
+            Ok(())
+        }
+    }
+
+    // The fixed column `q` is assigned on the rows of the state, so the keys are
+    // generated from the circuit.
+    let circuit = StateCircuit {
+        a: vec![3, 1, 4, 1],
+        b_offset: 0,
+    };
+    let vk = keygen_vk(&params, &circuit).expect("keygen_vk should not fail");
+    assert!(format!("{:?}", vk.pinned()).contains("unblinded_advice_columns: [0]"));
+    let pk = keygen_pk(&params, vk, &circuit).expect("keygen_pk should not fail");
+    assert!(!prove_and_verify(
+        &params,
+        &pk,
+        &[StateCircuit {
+            b_offset: 1,
+            ..circuit.clone()
+        }],
+        &[&[]]
+    ));
+    let proofs: Vec<Vec<u8>> = (0..2)
+        .map(|_| {
+            let mut transcript = Blake2bWrite::init(vec![]);
+            create_proof(
+                &params,
+                &pk,
+                std::slice::from_ref(&circuit),
+                &[&[]],
+                OsRng,
+                &mut transcript,
+            )
+            .expect("proof generation should not fail");
+            transcript.finalize()
+        })
+        .collect();
+
+    for proof in proofs.iter() {
+        let mut transcript = Blake2bRead::init(&proof[..]);
+        let guard = verify_proof(
+            &params,
+            pk.get_vk(),
+            params.empty_msm(),
+            &[&[]],
+            &mut transcript,
+        )
+        .expect("proof should verify");
+        assert!(guard.use_challenges().eval());
+    }
+
+    // The proofs start with the commitments to the advice columns. That of `a` is the
+    // same in every proof, and can be recomputed from its values, unlike that of `b`.
+    let values: Vec<_> = circuit.a.iter().map(|a| Fp::from_u64(*a)).collect();
+    let mut a = pk.get_vk().get_domain().empty_lagrange();
+    a[..][..values.len()].copy_from_slice(&values);
+    let mut commitment = vec![];
+    params
+        .commit_lagrange(&a, Blind(Fp::zero()))
+        .to_affine()
+        .write(&mut commitment)
+        .unwrap();
+    assert_eq!(proofs[0][..32], commitment[..]);
+    assert_eq!(proofs[1][..32], commitment[..]);
+    assert_ne!(&proofs[0][32..64], &proofs[1][32..64]);
+}
+
+#[test]
+fn blinding_rows() {
+    const K: u32 = 3;
+
+    // Initialize the polynomial commitment parameters
+    let params: Params<EqAffine> = Params::new(K);
+
+    /// A circuit that counts up from zero in the column `a`, over `rows` rows, with the
+    /// gate enabled by a selector if `GATED`.
+    #[derive(Clone, Default)]
+    struct CountCircuit<const GATED: bool> {
+        rows: usize,
+    }
+
+    impl<const GATED: bool> Circuit<Fp> for CountCircuit<GATED> {
+        type Config = (Column<Advice>, Selector);
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            meta.reserve_blinding_rows();
+            let a = meta.advice_column();
+            let q = meta.selector();
+
+            meta.create_gate("a' = a + 1", |meta| {
+                let a_next = meta.query_advice(a, Rotation::next());
+                let a = meta.query_advice(a, Rotation::cur());
+                let constraint = a_next - a - Expression::Constant(Fp::one());
+                if GATED {
+                    meta.query_selector(q, Rotation::cur()) * constraint
+                } else {
+                    constraint
+                }
+            });
+
+            (a, q)
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            (a, q): Self::Config,
+        ) -> Result<(), Error> {
+            for row in 0..self.rows {
+                if row + 1 < self.rows {
+                    cs.enable_selector(|| "q", &q, row)?;
+                }
+                cs.assign_advice(|| "a", a, row, || Ok(Fp::from_u64(row as u64)))?;
+            }
+            Ok(())
+        }
+    }
+
+    // The column is queried at two rotations, so three of the eight rows are reserved.
+    let mut cs = ConstraintSystem::<Fp>::default();
+    CountCircuit::<true>::configure(&mut cs);
+    assert_eq!(cs.blinding_factors(), 3);
+    assert_eq!(cs.usable_rows(K), 0..5);
+
+    // The blinding rows are filled with random values, which the gated circuit
+    // ignores, and may not be assigned.
+    let prover = MockProver::run(K, &CountCircuit::<true> { rows: 5 }, vec![]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+    assert!(matches!(
+        MockProver::run(K, &CountCircuit::<true> { rows: 6 }, vec![]),
+        Err(Error::BoundsFailure)
+    ));
+    let prover = MockProver::run(K, &CountCircuit::<false> { rows: 5 }, vec![]).unwrap();
+    assert!(matches!(
+        prover.verify(),
+        Err(VerifyFailure::Gate { row: 4, .. })
+    ));
+
+    let circuit = CountCircuit::<true> { rows: 5 };
+    let vk = keygen_vk(&params, &circuit).expect("keygen_vk should not fail");
+    assert!(format!("{:?}", vk.pinned()).contains("blinding_rows: true"));
+    assert!(matches!(
+        keygen_vk(&params, &CountCircuit::<true> { rows: 7 }),
+        Err(Error::BoundsFailure)
+    ));
+    let pk = keygen_pk(&params, vk, &circuit).expect("keygen_pk should not fail");
+    assert!(prove_and_verify(&params, &pk, &[circuit], &[&[]]));
+}
+
+#[test]
+fn arbitrary_rotations() {
+    const K: u32 = 6;
+    const N: usize = 1 << K;
+
+    // Initialize the polynomial commitment parameters
+    let params: Params<EqAffine> = Params::new(K);
+
+    /// A circuit with `a` set to its row numbers, shifted by `offset`, and gates that
+    /// check them at strides beyond the neighbouring rows.
+    #[derive(Clone, Default)]
+    struct StrideCircuit {
+        offset: u64,
+    }
+
+    impl Circuit<Fp> for StrideCircuit {
+        type Config = (Column<Advice>, Selector, Selector, Selector);
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let a = meta.advice_column();
+            let forward = meta.selector();
+            let backward = meta.selector();
+            let wrapping = meta.selector();
+
+            meta.create_gate("a(7) = a + 7", |meta| {
+                let s = meta.query_selector(forward, Rotation::cur());
+                let a_7 = meta.query_advice(a, Rotation(7));
+                let a = meta.query_advice(a, Rotation::cur());
+                s * (a_7 - a - Expression::Constant(Fp::from_u64(7)))
+            });
+            meta.create_gate("a(-32) = a - 32", |meta| {
+                let s = meta.query_selector(backward, Rotation::cur());
+                let a_32 = meta.query_advice(a, Rotation(-32));
+                let a = meta.query_advice(a, Rotation::cur());
+                s * (a_32 - a + Expression::Constant(Fp::from_u64(32)))
+            });
+            // A rotation by more than the number of rows wraps around the domain.
+            meta.create_gate("a(-70) = a - 6", |meta| {
+                let s = meta.query_selector(wrapping, Rotation::cur());
+                let a_70 = meta.query_advice(a, Rotation(-(N as i32) - 6));
+                let a = meta.query_advice(a, Rotation::cur());
+                s * (a_70 - a + Expression::Constant(Fp::from_u64(6)))
+            });
+
+            (a, forward, backward, wrapping)
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            (a, forward, backward, wrapping): Self::Config,
+        ) -> Result<(), Error> {
+            for row in 0..N {
+                let value = row as u64 + if row == N / 2 { self.offset } else { 0 };
+                cs.assign_advice(|| "a", a, row, || Ok(Fp::from_u64(value)))?;
+                if row + 7 < N {
+                    cs.enable_selector(|| "forward", &forward, row)?;
+                }
+                if row >= 32 {
+                    cs.enable_selector(|| "backward", &backward, row)?;
+                }
+                if row >= 6 {
+                    cs.enable_selector(|| "wrapping", &wrapping, row)?;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    let circuit = StrideCircuit::default();
+    let prover = MockProver::run(K, &circuit, vec![]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    // Changing one row breaks the gates that reach it from 7 and 32 rows away.
+    let bad_circuit = StrideCircuit { offset: 1 };
+    let prover = MockProver::run(K, &bad_circuit, vec![]).unwrap();
+    assert_eq!(
+        prover.verify(),
+        Err(VerifyFailure::Gate {
+            gate_index: 0,
+            gate_name: "a(7) = a + 7",
+            constraint_index: 0,
+            constraint_name: "",
+            row: N / 2 - 7,
+        })
+    );
+
+    let vk = keygen_vk(&params, &circuit).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &circuit).expect("keygen_pk should not fail");
+
+    assert!(prove_and_verify(&params, &pk, &[circuit], &[&[]]));
+    // A proof of the unsatisfied circuit does not verify.
+    assert!(!prove_and_verify(&params, &pk, &[bad_circuit], &[&[]]));
+}
+
+#[test]
+fn named_constraints() {
+    const K: u32 = 4;
+
+    // Initialize the polynomial commitment parameters
+    let params: Params<EqAffine> = Params::new(K);
+
+    /// A circuit checking that `b = a + 1` and `c = a * b`, with each check named.
+    #[derive(Clone, Default)]
+    struct NamedCircuit {
+        c_offset: u64,
+    }
+
+    impl Circuit<Fp> for NamedCircuit {
+        type Config = (Column<Advice>, Column<Advice>, Column<Advice>, Selector);
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let a = meta.advice_column();
+            let b = meta.advice_column();
+            let c = meta.advice_column();
+            let s = meta.selector();
+
+            meta.create_gate("successor product", |meta| {
+                let s = meta.query_selector(s, Rotation::cur());
+                let a = meta.query_advice(a, Rotation::cur());
+                let b = meta.query_advice(b, Rotation::cur());
+                let c = meta.query_advice(c, Rotation::cur());
+                vec![
+                    (
+                        "b = a + 1",
+                        s.clone() * (b.clone() - a.clone() - Expression::Constant(Fp::one())),
+                    ),
+                    ("c = a * b", s * (c - a * b)),
+                ]
+            });
+
+            (a, b, c, s)
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            (a, b, c, s): Self::Config,
+        ) -> Result<(), Error> {
+            for row in 0..4 {
+                let value = row as u64 + 2;
+                let c_value = value * (value + 1) + if row == 3 { self.c_offset } else { 0 };
+                cs.assign_advice(|| "a", a, row, || Ok(Fp::from_u64(value)))?;
+                cs.assign_advice(|| "b", b, row, || Ok(Fp::from_u64(value + 1)))?;
+                cs.assign_advice(|| "c", c, row, || Ok(Fp::from_u64(c_value)))?;
+                cs.enable_selector(|| "s", &s, row)?;
+            }
+            Ok(())
+        }
+    }
+
+    let circuit = NamedCircuit::default();
+    let prover = MockProver::run(K, &circuit, vec![]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    // The failure names the constraint that is not satisfied, not only its gate.
+    let bad_circuit = NamedCircuit { c_offset: 1 };
+    let prover = MockProver::run(K, &bad_circuit, vec![]).unwrap();
+    assert_eq!(
+        prover.verify(),
+        Err(VerifyFailure::Gate {
+            gate_index: 0,
+            gate_name: "successor product",
+            constraint_index: 1,
+            constraint_name: "c = a * b",
+            row: 3,
+        })
+    );
+
+    // Naming the constraints does not change the proof system.
+    let vk = keygen_vk(&params, &circuit).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &circuit).expect("keygen_pk should not fail");
+    assert!(prove_and_verify(&params, &pk, &[circuit], &[&[]]));
+    assert!(!prove_and_verify(&params, &pk, &[bad_circuit], &[&[]]));
+}
+
+#[cfg(feature = "circuit-params")]
+#[test]
+fn circuit_params() {
+    const K: u32 = 4;
+
+    // Initialize the polynomial commitment parameters
+    let params: Params<EqAffine> = Params::new(K);
+
+    /// A circuit with `width` advice columns, each set to its index in every row, with
+    /// `offset` added to the last column in the last row.
+    #[derive(Clone, Default)]
+    struct WideCircuit {
+        width: usize,
+        offset: u64,
+    }
+
+    impl Circuit<Fp> for WideCircuit {
+        type Config = (Vec<Column<Advice>>, Selector);
+        type Params = usize;
+
+        fn params(&self) -> usize {
+            self.width
+        }
+
+        fn configure(_: &mut ConstraintSystem<Fp>) -> Self::Config {
+            unimplemented!("the circuit is configured with its width")
+        }
+
+        fn configure_with_params(meta: &mut ConstraintSystem<Fp>, width: usize) -> Self::Config {
+            let columns: Vec<_> = (0..width).map(|_| meta.advice_column()).collect();
+            let s = meta.selector();
+
+            meta.create_gate("column index", |meta| {
+                let s = meta.query_selector(s, Rotation::cur());
+                columns
+                    .iter()
+                    .enumerate()
+                    .map(|(i, column)| {
+                        s.clone()
+                            * (meta.query_advice(*column, Rotation::cur())
+                                - Expression::Constant(Fp::from_u64(i as u64)))
+                    })
+                    .collect::<Vec<_>>()
+            });
+
+            (columns, s)
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            (columns, s): Self::Config,
+        ) -> Result<(), Error> {
+            for row in 0..4 {
+                for (i, column) in columns.iter().enumerate() {
+                    let value = i as u64
+                        + if (row, i + 1) == (3, self.width) {
+                            self.offset
+                        } else {
+                            0
+                        };
+                    cs.assign_advice(|| "a", *column, row, || Ok(Fp::from_u64(value)))?;
+                }
+                cs.enable_selector(|| "s", &s, row)?;
+            }
+            Ok(())
+        }
+    }
+
+    // The same definition scales to any number of columns.
+    for width in [1, 3, 5] {
+        let prover = MockProver::run(K, &WideCircuit { width, offset: 0 }, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    let circuit = WideCircuit {
+        width: 3,
+        offset: 0,
+    };
+    let bad_circuit = WideCircuit {
+        width: 3,
+        offset: 1,
+    };
+    let vk = keygen_vk(&params, &circuit).expect("keygen_vk should not fail");
+    let wider_vk = keygen_vk(
+        &params,
+        &WideCircuit {
+            width: 5,
+            offset: 0,
+        },
+    )
+    .expect("keygen_vk should not fail");
+    assert_ne!(
+        format!("{:?}", vk.pinned()),
+        format!("{:?}", wider_vk.pinned())
+    );
+
+    // A key is read back with the parameters of its circuit.
+    let mut bytes = vec![];
+    vk.write(&mut bytes).unwrap();
+    let read_vk = VerifyingKey::<EqAffine>::read::<_, WideCircuit>(&mut &bytes[..], &params, 3)
+        .expect("verifying key should be readable");
+    assert_eq!(
+        format!("{:?}", read_vk.pinned()),
+        format!("{:?}", vk.pinned())
+    );
+
+    let pk = keygen_pk(&params, read_vk, &circuit).expect("keygen_pk should not fail");
+    assert!(prove_and_verify(&params, &pk, &[circuit], &[&[]]));
+    assert!(!prove_and_verify(&params, &pk, &[bad_circuit], &[&[]]));
+}
+
+#[test]
+fn layouter_challenges() {
+    const K: u32 = 4;
+    const VALUES: [u64; 4] = [3, 1, 4, 1];
+
+    // Initialize the polynomial commitment parameters
+    let params: Params<EqAffine> = Params::new(K);
+
+    #[derive(Clone, Debug)]
+    struct RlcConfig {
+        a: Column<Advice>,
+        acc: Column<Advice>,
+        q_first: Column<Fixed>,
+        q_step: Column<Fixed>,
+        r: Challenge,
+    }
+
+    /// A chip that accumulates the random linear combination of the values of `a`
+    /// over a challenge squeezed after they are committed to.
+    #[derive(Debug)]
+    struct RlcChip;
+
+    impl Chip for RlcChip {
+        type Config = RlcConfig;
+        type Loaded = ();
+        type Field = Fp;
+
+        fn load(_: &mut impl Layouter<Self>) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    /// A circuit assigning `VALUES` and their random linear combination, with `acc_offset`
+    /// added to the last accumulator.
+    #[derive(Clone, Default)]
+    struct RlcCircuit {
+        acc_offset: u64,
+    }
+
+    impl Circuit<Fp> for RlcCircuit {
+        type Config = RlcConfig;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> RlcConfig {
+            let a = meta.advice_column();
+            let r = meta.challenge_usable_after(Phase::first());
+            let acc = meta.advice_column_in(Phase::second());
+            let q_first = meta.fixed_column();
+            let q_step = meta.fixed_column();
+
+            meta.create_gate("first", |meta| {
+                let q_first = meta.query_fixed(q_first, Rotation::cur());
+                let a = meta.query_advice(a, Rotation::cur());
+                let acc = meta.query_advice(acc, Rotation::cur());
+                q_first * (acc - a)
+            });
+            meta.create_gate("step", |meta| {
+                let q_step = meta.query_fixed(q_step, Rotation::cur());
+                let a_next = meta.query_advice(a, Rotation::next());
+                let acc_next = meta.query_advice(acc, Rotation::next());
+                let acc = meta.query_advice(acc, Rotation::cur());
+                let r = meta.query_challenge(r);
+                q_step * (acc_next - (acc * r + a_next))
+            });
+
+            RlcConfig {
+                a,
+                acc,
+                q_first,
+                q_step,
+                r,
+            }
+        }
+
+        fn synthesize(&self, cs: &mut impl Assignment<Fp>, config: RlcConfig) -> Result<(), Error> {
+            let mut layouter = SingleChip::<RlcChip, _>::new(cs, config)?;
+            let config = layouter.config().clone();
+
+            layouter.assign_region(
+                || "rlc",
+                |mut region| {
+                    // The accumulators are only known once the challenge is squeezed.
+                    let r = region.get_challenge(config.r);
+                    let mut acc = r.map(|_| Fp::zero());
+                    for (offset, value) in VALUES.iter().enumerate() {
+                        let enable = |enabled: bool| if enabled { Fp::one() } else { Fp::zero() };
+                        let value = Fp::from_u64(*value);
+                        region.assign_fixed(
+                            || "q_first",
+                            config.q_first,
+                            offset,
+                            || Ok(enable(offset == 0)),
+                        )?;
+                        region.assign_fixed(
+                            || "q_step",
+                            config.q_step,
+                            offset,
+                            || Ok(enable(offset + 1 < VALUES.len())),
+                        )?;
+                        region.assign_advice(|| "a", config.a, offset, || Ok(value))?;
+
+                        acc = acc.zip(r).map(|(acc, r)| acc * r + value);
+                        let offset_by = if offset + 1 == VALUES.len() {
+                            self.acc_offset
+                        } else {
+                            0
+                        };
+                        let acc = acc.map(|acc| acc + Fp::from_u64(offset_by));
+                        region.assign_advice(
+                            || "acc",
+                            config.acc,
+                            offset,
+                            || acc.ok_or(Error::SynthesisError),
+                        )?;
+                    }
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    let circuit = RlcCircuit::default();
+    let prover = MockProver::run(K, &circuit, vec![]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    let bad_circuit = RlcCircuit { acc_offset: 1 };
+    let prover = MockProver::run(K, &bad_circuit, vec![]).unwrap();
+    assert_eq!(
+        prover.verify(),
+        Err(VerifyFailure::Gate {
+            gate_index: 1,
+            gate_name: "step",
+            constraint_index: 0,
+            constraint_name: "",
+            row: VALUES.len() - 2,
+        })
+    );
+
+    // Key generation synthesizes the circuit without the challenge.
+    let vk = keygen_vk(&params, &circuit).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &circuit).expect("keygen_pk should not fail");
+    assert!(prove_and_verify(&params, &pk, &[circuit], &[&[]]));
+    assert!(!prove_and_verify(&params, &pk, &[bad_circuit], &[&[]]));
+}
+
+#[test]
+fn many_circuits() {
+    const K: u32 = 4;
+
+    // Initialize the polynomial commitment parameters
+    let params: Params<EqAffine> = Params::new(K);
+
+    #[derive(Clone)]
+    struct PaymentConfig {
+        balance: Column<Advice>,
+        amount: Column<Advice>,
+        q: Column<Fixed>,
+        q_total: Column<Fixed>,
+    }
+
+    /// A circuit whose prover claims that its three payments, each added to the balance
+    /// before it, give the next balance, and that the last balance is the public total.
+    #[derive(Clone, Default)]
+    struct PaymentCircuit {
+        amounts: Vec<u64>,
+    }
+
+    impl Circuit<Fp> for PaymentCircuit {
+        type Config = PaymentConfig;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> PaymentConfig {
+            let balance = meta.advice_column();
+            let amount = meta.advice_column();
+            let q = meta.fixed_column();
+            let q_total = meta.fixed_column();
+            let total = meta.instance_column();
+
+            meta.create_gate("payment", |meta| {
+                let q = meta.query_fixed(q, Rotation::cur());
+                let next = meta.query_advice(balance, Rotation::next());
+                let balance = meta.query_advice(balance, Rotation::cur());
+                let amount = meta.query_advice(amount, Rotation::cur());
+                q * (next - balance - amount)
+            });
+            meta.create_gate("total", |meta| {
+                let q_total = meta.query_fixed(q_total, Rotation::cur());
+                let last = meta.query_advice(balance, Rotation(3));
+                let total = meta.query_instance(total, Rotation::cur());
+                q_total * (last - total)
+            });
+
+            PaymentConfig {
+                balance,
+                amount,
+                q,
+                q_total,
+            }
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            config: PaymentConfig,
+        ) -> Result<(), Error> {
+            let mut balance = Fp::zero();
+            cs.assign_fixed(|| "q_total", config.q_total, 0, || Ok(Fp::one()))?;
+            cs.assign_advice(|| "balance", config.balance, 0, || Ok(balance))?;
+            for (row, amount) in self.amounts.iter().enumerate() {
+                let amount = Fp::from_u64(*amount);
+                cs.assign_fixed(|| "q", config.q, row, || Ok(Fp::one()))?;
+                cs.assign_advice(|| "amount", config.amount, row, || Ok(amount))?;
+                balance += amount;
+                cs.assign_advice(|| "balance", config.balance, row + 1, || Ok(balance))?;
+            }
+            Ok(())
+        }
+    }
+
+    // The fixed column `q` is assigned on the row of each payment, so the keys are
+    // generated from a circuit with three of them.
+    let shape = PaymentCircuit {
+        amounts: vec![0; 3],
+    };
+    let vk = keygen_vk(&params, &shape).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &shape).expect("keygen_pk should not fail");
+    let vk = pk.get_vk();
+
+    let batch: Vec<_> = [[1, 2, 3], [4, 5, 6], [7, 8, 9]]
+        .iter()
+        .map(|amounts| PaymentCircuit {
+            amounts: amounts.to_vec(),
+        })
+        .collect();
+    let instances: Vec<_> = batch
+        .iter()
+        .map(|circuit| {
+            let total = Fp::from_u64(circuit.amounts.iter().sum());
+            vk.instance_values(&[&[total]]).unwrap()
+        })
+        .collect();
+    let commitments: Vec<_> = instances
+        .iter()
+        .map(|instance| commit_instance(&params, &pk, instance).unwrap())
+        .collect();
+
+    let prove = |count: usize| {
+        let instances: Vec<_> = instances[..count].iter().map(|i| &i[..]).collect();
+        let mut transcript = Blake2bWrite::init(vec![]);
+        create_proof(
+            &params,
+            &pk,
+            &batch[..count],
+            &instances,
+            OsRng,
+            &mut transcript,
+        )
+        .map(|_| transcript.finalize())
+    };
+    let verify = |proof: &[u8], commitments: &[&[EqAffine]]| {
+        let mut transcript = Blake2bRead::init(proof);
+        match verify_proof(
+            &params,
+            vk,
+            params.empty_msm(),
+            commitments,
+            &mut transcript,
+        ) {
+            Ok(guard) => guard.use_challenges().eval(),
+            Err(_) => false,
+        }
+    };
+
+    // Every payment circuit of the batch is proven at once, against its own total.
+    let proof = prove(3).expect("proof generation should not fail");
+    let all: Vec<_> = commitments.iter().map(|c| c.commitments()).collect();
+    assert!(verify(&proof, &all));
+    assert!(!verify(&proof, &[all[1], all[0], all[2]]));
+    assert!(!verify(&proof, &all[..2]));
+
+    // Each further circuit adds the same number of bytes, which is much less than a
+    // proof of its own.
+    let sizes: Vec<_> = (1..=3).map(|count| prove(count).unwrap().len()).collect();
+    assert_eq!(sizes[2] - sizes[1], sizes[1] - sizes[0]);
+    for (count, size) in (1..=3).zip(sizes.iter()) {
+        assert_eq!(*size, vk.proof_size(count));
+    }
+    assert!(sizes[1] - sizes[0] < sizes[0] / 2);
+
+    // The circuits and instances must correspond, and there must be at least one.
+    let mut transcript = Blake2bWrite::init(vec![]);
+    let single: &[_] = &instances[0];
+    assert!(matches!(
+        create_proof(&params, &pk, &batch[..2], &[single], OsRng, &mut transcript),
+        Err(Error::IncompatibleParams)
+    ));
+    assert!(matches!(prove(0), Err(Error::IncompatibleParams)));
+    assert!(!verify(&proof, &[]));
+}
+
+#[test]
+fn tagged_lookup_tables() {
+    const K: u32 = 6;
+    const RANGE_TAG: u64 = 1;
+    const SQUARE_TAG: u64 = 2;
+
+    // Initialize the polynomial commitment parameters
+    let params: Params<EqAffine> = Params::new(K);
+
+    #[derive(Clone)]
+    struct TaggedConfig {
+        a: Column<Advice>,
+        b: Column<Advice>,
+        q_range: Column<Fixed>,
+        q_square: Column<Fixed>,
+        tag: TableColumn,
+        table: [TableColumn; 2],
+    }
+
+    /// A circuit whose prover claims that each of `range` is less than 8, and that
+    /// each of `squares` is a number less than 16 and its square, with both tables
+    /// packed into the same table columns.
+    #[derive(Clone, Default)]
+    struct TaggedCircuit {
+        range: Vec<u64>,
+        squares: Vec<(u64, u64)>,
+    }
+
+    impl Circuit<Fp> for TaggedCircuit {
+        type Config = TaggedConfig;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> TaggedConfig {
+            let a = meta.advice_column();
+            let b = meta.advice_column();
+            let q_range = meta.fixed_column();
+            let q_square = meta.fixed_column();
+            let tag = meta.lookup_table_column();
+            let table = [meta.lookup_table_column(), meta.lookup_table_column()];
+
+            let q = meta.query_fixed(q_range, Rotation::cur());
+            let a_cur = meta.query_advice(a, Rotation::cur());
+            meta.lookup_tagged(
+                q.clone() * Expression::Constant(Fp::from_u64(RANGE_TAG)),
+                &[q * a_cur.clone()],
+                tag,
+                &table,
+            );
+            let q = meta.query_fixed(q_square, Rotation::cur());
+            let b_cur = meta.query_advice(b, Rotation::cur());
+            meta.lookup_tagged(
+                q.clone() * Expression::Constant(Fp::from_u64(SQUARE_TAG)),
+                &[q.clone() * a_cur, q * b_cur],
+                tag,
+                &table,
+            );
+
+            TaggedConfig {
+                a,
+                b,
+                q_range,
+                q_square,
+                tag,
+                table,
+            }
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            config: TaggedConfig,
+        ) -> Result<(), Error> {
+            let tables = (0..8)
+                .map(|x| (RANGE_TAG, x, 0))
+                .chain((0..16).map(|x| (SQUARE_TAG, x, x * x)));
+            for (row, (tag, x, y)) in tables.enumerate() {
+                let [c0, c1] = config.table;
+                cs.assign_fixed(|| "tag", config.tag.inner(), row, || Ok(Fp::from_u64(tag)))?;
+                cs.assign_fixed(|| "c0", c0.inner(), row, || Ok(Fp::from_u64(x)))?;
+                cs.assign_fixed(|| "c1", c1.inner(), row, || Ok(Fp::from_u64(y)))?;
+            }
+
+            let rows = self
+                .range
+                .iter()
+                .map(|a| (config.q_range, *a, 0))
+                .chain(self.squares.iter().map(|(a, b)| (config.q_square, *a, *b)));
+            for (row, (q, a, b)) in rows.enumerate() {
+                cs.assign_fixed(|| "q", q, row, || Ok(Fp::one()))?;
+                cs.assign_advice(|| "a", config.a, row, || Ok(Fp::from_u64(a)))?;
+                cs.assign_advice(|| "b", config.b, row, || Ok(Fp::from_u64(b)))?;
+            }
+            Ok(())
+        }
+    }
+
+    let circuit = TaggedCircuit {
+        range: vec![0, 7, 3],
+        squares: vec![(3, 9), (12, 144), (0, 0)],
+    };
+    let prover = MockProver::run(K, &circuit, vec![]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    // A value is only in the table that its tag selects: 12 is in the table of
+    // squares, but is not less than 8.
+    let prover = MockProver::run(
+        K,
+        &TaggedCircuit {
+            range: vec![0, 12],
+            squares: vec![],
+        },
+        vec![],
+    )
+    .unwrap();
+    assert_eq!(
+        prover.verify(),
+        Err(VerifyFailure::Lookup {
+            lookup_index: 0,
+            row: 1
+        })
+    );
+    let prover = MockProver::run(
+        K,
+        &TaggedCircuit {
+            range: vec![],
+            squares: vec![(3, 9), (4, 15)],
+        },
+        vec![],
+    )
+    .unwrap();
+    assert_eq!(
+        prover.verify(),
+        Err(VerifyFailure::Lookup {
+            lookup_index: 1,
+            row: 1
+        })
+    );
+
+    // The selectors of the lookups are fixed columns assigned by the circuit, so the
+    // keys are generated from each circuit rather than from an empty one.
+    let verify = |circuit: TaggedCircuit| {
+        let vk = keygen_vk(&params, &circuit).expect("keygen_vk should not fail");
+        let pk = keygen_pk(&params, vk, &circuit).expect("keygen_pk should not fail");
+        prove_and_verify(&params, &pk, &[circuit], &[&[]])
+    };
+    assert!(verify(circuit));
+    assert!(!verify(TaggedCircuit {
+        range: vec![0, 12],
+        squares: vec![],
+    }));
+}
+
+fn instance_lookups<const LOG_DERIVATIVE: bool>() {
+    const K: u32 = 4;
+
+    // Initialize the polynomial commitment parameters
+    let params: Params<EqAffine> = Params::new(K);
+
+    #[derive(Clone)]
+    struct WhitelistConfig {
+        a: Column<Advice>,
+        q: Column<Fixed>,
+        q_public: Column<Fixed>,
+        range: Column<Fixed>,
+    }
+
+    /// A circuit whose prover claims that each of `values` is in the whitelist given by
+    /// the first instance column, and each of the public inputs of the second instance
+    /// column is less than 8, without copying either into advice cells.
+    #[derive(Clone, Default)]
+    struct WhitelistCircuit<const LOG_DERIVATIVE: bool> {
+        values: Vec<u64>,
+    }
+
+    impl<const LOG_DERIVATIVE: bool> Circuit<Fp> for WhitelistCircuit<LOG_DERIVATIVE> {
+        type Config = WhitelistConfig;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> WhitelistConfig {
+            let a = meta.advice_column();
+            let q = meta.fixed_column();
+            let q_public = meta.fixed_column();
+            let range = meta.fixed_column();
+            let whitelist = meta.instance_column();
+            let public = meta.instance_column();
+            if LOG_DERIVATIVE {
+                meta.set_lookup_argument(LookupArgument::LogDerivative);
+            }
+
+            let q_cur = meta.query_fixed(q, Rotation::cur());
+            let a_cur = meta.query_advice(a, Rotation::cur());
+            let whitelist = meta.query_instance(whitelist, Rotation::cur());
+            meta.lookup(&[q_cur * a_cur], &[whitelist]);
+
+            let q_public_cur = meta.query_fixed(q_public, Rotation::cur());
+            let public = meta.query_instance(public, Rotation::cur());
+            let range_cur = meta.query_fixed(range, Rotation::cur());
+            meta.lookup(&[q_public_cur * public], &[range_cur]);
+
+            WhitelistConfig {
+                a,
+                q,
+                q_public,
+                range,
+            }
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            config: WhitelistConfig,
+        ) -> Result<(), Error> {
+            for row in 0..8 {
+                cs.assign_fixed(
+                    || "range",
+                    config.range,
+                    row,
+                    || Ok(Fp::from_u64(row as u64)),
+                )?;
+            }
+            for row in 0..3 {
+                cs.assign_fixed(|| "q_public", config.q_public, row, || Ok(Fp::one()))?;
+            }
+            for (row, value) in self.values.iter().enumerate() {
+                cs.assign_fixed(|| "q", config.q, row, || Ok(Fp::one()))?;
+                cs.assign_advice(|| "a", config.a, row, || Ok(Fp::from_u64(*value)))?;
+            }
+            Ok(())
+        }
+    }
+
+    let columns = |whitelist: &[u64], public: &[u64]| {
+        let column = |values: &[u64]| -> Vec<Fp> {
+            values.iter().map(|value| Fp::from_u64(*value)).collect()
+        };
+        vec![column(whitelist), column(public)]
+    };
+    let whitelist = [13, 21, 34, 55];
+    let circuit = WhitelistCircuit::<LOG_DERIVATIVE> {
+        values: vec![34, 13, 34],
+    };
+
+    let prover = MockProver::run(K, &circuit, columns(&whitelist, &[1, 7, 0])).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+    let prover = MockProver::run(K, &circuit, columns(&[13, 21, 35], &[1, 7, 0])).unwrap();
+    assert_eq!(
+        prover.verify(),
+        Err(VerifyFailure::Lookup {
+            lookup_index: 0,
+            row: 0
+        })
+    );
+    let prover = MockProver::run(K, &circuit, columns(&whitelist, &[1, 8, 0])).unwrap();
+    assert_eq!(
+        prover.verify(),
+        Err(VerifyFailure::Lookup {
+            lookup_index: 1,
+            row: 1
+        })
+    );
+
+    let vk = keygen_vk(&params, &circuit).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &circuit).expect("keygen_pk should not fail");
+    let vk = pk.get_vk();
+
+    let verify = |whitelist: &[u64]| {
+        let instance = columns(whitelist, &[1, 7, 0]);
+        let instance: Vec<&[Fp]> = instance.iter().map(|column| &column[..]).collect();
+        let instance = vk.instance_values(&instance).unwrap();
+        prove_and_verify(&params, &pk, std::slice::from_ref(&circuit), &[&instance])
+    };
+    assert!(verify(&whitelist));
+    // The circuit is only proven for a whitelist that holds the values it reads.
+    assert!(!verify(&[13, 21, 55]));
+}
+
+#[test]
+fn lookup_instance_columns() {
+    instance_lookups::<false>();
+    instance_lookups::<true>();
+}
+
+#[test]
+fn chunked_permutation() {
+    const K: u32 = 4;
+    const WIDTH: usize = 7;
+
+    // Initialize the polynomial commitment parameters
+    let params: Params<EqAffine> = Params::new(K);
+
+    #[derive(Clone)]
+    struct WideConfig {
+        advice: [Column<Advice>; WIDTH],
+        q: Column<Fixed>,
+        perm: Permutation,
+    }
+
+    /// A circuit checking that `a_3 = a_0 * a_1 * a_2` on the first row, and copying
+    /// `a_3` diagonally through every column, with copies into a cell whose value
+    /// is off by `offset`.
+    #[derive(Clone, Default)]
+    struct WideCircuit {
+        offset: u64,
+    }
+
+    impl Circuit<Fp> for WideCircuit {
+        type Config = WideConfig;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> WideConfig {
+            let advice = [(); WIDTH].map(|_| meta.advice_column());
+            let q = meta.fixed_column();
+            let columns: Vec<_> = advice.iter().map(|&column| column.into()).collect();
+            let perm = meta.permutation(&columns);
+
+            meta.create_gate("product", |meta| {
+                let q = meta.query_fixed(q, Rotation::cur());
+                let a: Vec<_> = advice[..4]
+                    .iter()
+                    .map(|&column| meta.query_advice(column, Rotation::cur()))
+                    .collect();
+                q * (a[0].clone() * a[1].clone() * a[2].clone() - a[3].clone())
+            });
+
+            WideConfig { advice, q, perm }
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            config: WideConfig,
+        ) -> Result<(), Error> {
+            cs.assign_fixed(|| "q", config.q, 0, || Ok(Fp::one()))?;
+            for (i, value) in [2, 3, 4, 24].iter().enumerate() {
+                cs.assign_advice(|| "a", config.advice[i], 0, || Ok(Fp::from_u64(*value)))?;
+            }
+
+            // Copy a_3 through the cells (a_4, 1), ..., (a_6, 3) and then
+            // (a_0, 4), ..., (a_3, 7).
+            let mut previous = (config.advice[3], 0);
+            for i in 1..WIDTH + 1 {
+                let cell = (config.advice[(3 + i) % WIDTH], i);
+                let value = 24 + if i == WIDTH { self.offset } else { 0 };
+                cs.assign_advice(|| "copy", cell.0, cell.1, || Ok(Fp::from_u64(value)))?;
+                cs.copy(
+                    &config.perm,
+                    previous.0.into(),
+                    previous.1,
+                    cell.0.into(),
+                    cell.1,
+                )?;
+                previous = cell;
+            }
+            Ok(())
+        }
+    }
+
+    // The permutation over seven columns is split into chunks of two columns, so
+    // that it does not raise the degree of the degree-4 gate.
+    let mut cs = ConstraintSystem::<Fp>::default();
+    WideCircuit::configure(&mut cs);
+    assert_eq!(cs.degree(), 4);
+
+    let circuit = WideCircuit::default();
+    let bad_circuit = WideCircuit { offset: 1 };
+    let prover = MockProver::run(K, &circuit, vec![]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+    let prover = MockProver::run(K, &bad_circuit, vec![]).unwrap();
+    assert!(prover.verify().is_err());
+
+    let vk = keygen_vk(&params, &circuit).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &circuit).expect("keygen_pk should not fail");
+
+    assert!(prove_and_verify(&params, &pk, &[circuit.clone()], &[&[]]));
+    assert!(!prove_and_verify(&params, &pk, &[bad_circuit], &[&[]]));
+
+    // The proof holds the grand product of each chunk of the permutation.
+    let mut transcript = Blake2bWrite::init(vec![]);
+    create_proof(&params, &pk, &[circuit], &[&[]], OsRng, &mut transcript)
+        .expect("proof generation should not fail");
+    assert_eq!(transcript.finalize().len(), pk.get_vk().proof_size(1));
+}
+
+#[test]
+fn keygen_skips_witness() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const K: u32 = 4;
+
+    // Initialize the polynomial commitment parameters
+    let params: Params<EqAffine> = Params::new(K);
+
+    /// A circuit checking that `b = a^2`, with `offset` added to the first `b`, which
+    /// counts how many times it computes its witness.
+    #[derive(Default)]
+    struct SquareCircuit {
+        offset: u64,
+        witnesses: AtomicUsize,
+    }
+
+    impl Circuit<Fp> for SquareCircuit {
+        type Config = (Column<Advice>, Column<Advice>, Selector);
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let a = meta.advice_column();
+            let b = meta.advice_column();
+            let s = meta.selector();
+
+            meta.create_gate("square", |meta| {
+                let s = meta.query_selector(s, Rotation::cur());
+                let a = meta.query_advice(a, Rotation::cur());
+                let b = meta.query_advice(b, Rotation::cur());
+                s * (a.clone() * a - b)
+            });
+
+            (a, b, s)
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            (a, b, s): Self::Config,
+        ) -> Result<(), Error> {
+            // The witness is only computed when it is used.
+            let witness = if cs.witness_required() {
+                self.witnesses.fetch_add(1, Ordering::SeqCst);
+                Some((0..8).map(|i| Fp::from_u64(i + 1)).collect::<Vec<_>>())
+            } else {
+                None
+            };
+
+            for row in 0..8 {
+                cs.enable_selector(|| "s", &s, row)?;
+                let value = || {
+                    witness
+                        .as_ref()
+                        .map(|witness| witness[row])
+                        .ok_or(Error::SynthesisError)
+                };
+                cs.assign_advice(|| "a", a, row, value)?;
+                cs.assign_advice(
+                    || "b",
+                    b,
+                    row,
+                    || {
+                        let offset = if row == 0 { self.offset } else { 0 };
+                        value().map(|a| a * a + Fp::from_u64(offset))
+                    },
+                )?;
+            }
+            Ok(())
+        }
+    }
+
+    let circuit = SquareCircuit::default();
+    let vk = keygen_vk(&params, &circuit).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &circuit).expect("keygen_pk should not fail");
+    assert_eq!(circuit.witnesses.load(Ordering::SeqCst), 0);
+
+    let prover = MockProver::run(K, &circuit, vec![]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+    assert_eq!(circuit.witnesses.load(Ordering::SeqCst), 1);
+
+    assert!(prove_and_verify(
+        &params,
+        &pk,
+        std::slice::from_ref(&circuit),
+        &[&[]]
+    ));
+    assert_eq!(circuit.witnesses.load(Ordering::SeqCst), 2);
+
+    let bad_circuit = SquareCircuit {
+        offset: 1,
+        ..Default::default()
+    };
+    assert!(!prove_and_verify(&params, &pk, &[bad_circuit], &[&[]]));
+}
+
+#[test]
+fn split_gates() {
+    const K: u32 = 4;
+    const ROWS: usize = 4;
+
+    let params: Params<EqAffine> = Params::new(K);
+
+    #[derive(Clone)]
+    struct SplitConfig {
+        a: Column<Advice>,
+        b: Column<Advice>,
+        c: Column<Advice>,
+        s: Selector,
+        r: Challenge,
+    }
+
+    /// A circuit with a gate of degree 6, split into gates of degree 3, whose
+    /// intermediate columns are in the first and second phases.
+    #[derive(Clone, Default)]
+    struct SplitCircuit {
+        offset: u64,
+    }
+
+    impl Circuit<Fp> for SplitCircuit {
+        type Config = SplitConfig;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> SplitConfig {
+            let a = meta.advice_column();
+            let b = meta.advice_column();
+            let r = meta.challenge_usable_after(Phase::first());
+            let c = meta.advice_column_in(Phase::second());
+            let s = meta.selector();
+
+            meta.create_gate("power", |meta| {
+                let s = meta.query_selector(s, Rotation::cur());
+                let a_next = meta.query_advice(a, Rotation::next());
+                let a = meta.query_advice(a, Rotation::cur());
+                let b = meta.query_advice(b, Rotation::cur());
+                let c = meta.query_advice(c, Rotation::cur());
+                let r = meta.query_challenge(r);
+                s * (a.clone() * a.clone() * a * a_next * b.clone() - r * c * b.clone() * b)
+            });
+            meta.split_gates(3);
+
+            SplitConfig { a, b, c, s, r }
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            config: SplitConfig,
+        ) -> Result<(), Error> {
+            let a = |row: usize| Fp::from_u64(row as u64 + 2);
+            let b = |row: usize| Fp::from_u64(row as u64 + 1);
+            for row in 0..=ROWS {
+                cs.assign_advice(|| "a", config.a, row, || Ok(a(row)))?;
+                cs.assign_advice(|| "b", config.b, row, || Ok(b(row)))?;
+            }
+            let r = cs.get_challenge(config.r);
+            for row in 0..ROWS {
+                cs.enable_selector(|| "s", &config.s, row)?;
+                let c = r.map(|r| {
+                    a(row).square() * a(row) * a(row + 1) * (r * b(row)).invert().unwrap()
+                        + Fp::from_u64(if row == 1 { self.offset } else { 0 })
+                });
+                cs.assign_advice(|| "c", config.c, row, || c.ok_or(Error::SynthesisError))?;
+            }
+            Ok(())
+        }
+    }
+
+    let mut cs = ConstraintSystem::default();
+    SplitCircuit::configure(&mut cs);
+    assert_eq!(cs.degree(), 3);
+
+    let circuit = SplitCircuit::default();
+    let bad_circuit = SplitCircuit { offset: 1 };
+    let prover = MockProver::run(K, &circuit, vec![]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+    let prover = MockProver::run(K, &bad_circuit, vec![]).unwrap();
+    assert_eq!(
+        prover.verify(),
+        Err(VerifyFailure::Gate {
+            gate_index: 0,
+            gate_name: "power",
+            constraint_index: 0,
+            constraint_name: "",
+            row: 1
+        })
+    );
+
+    let vk = keygen_vk(&params, &circuit).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &circuit).expect("keygen_pk should not fail");
+    assert!(prove_and_verify(&params, &pk, &[circuit], &[&[]]));
+    assert!(!prove_and_verify(&params, &pk, &[bad_circuit], &[&[]]));
+}
+
+#[test]
+fn witness_file() {
+    const K: u32 = 4;
+
+    let params: Params<EqAffine> = Params::new(K);
+
+    #[derive(Clone)]
+    struct SquareConfig {
+        a: Column<Advice>,
+        b: Column<Advice>,
+        s: Selector,
+    }
+
+    /// A circuit that proves knowledge of the square roots of the values of `b`.
+    #[derive(Clone, Default)]
+    struct SquareCircuit {
+        roots: Vec<u64>,
+        offset: u64,
+    }
+
+    impl Circuit<Fp> for SquareCircuit {
+        type Config = SquareConfig;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> SquareConfig {
+            let a = meta.advice_column();
+            let b = meta.advice_column();
+            let s = meta.selector();
+            meta.create_gate("square", |meta| {
+                let s = meta.query_selector(s, Rotation::cur());
+                let a = meta.query_advice(a, Rotation::cur());
+                let b = meta.query_advice(b, Rotation::cur());
+                s * (a.clone() * a - b)
+            });
+            SquareConfig { a, b, s }
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Fp>,
+            config: SquareConfig,
+        ) -> Result<(), Error> {
+            for (row, root) in self.roots.iter().enumerate() {
+                let root = Fp::from_u64(*root);
+                cs.enable_selector(|| "s", &config.s, row)?;
+                cs.assign_advice(|| "a", config.a, row, || Ok(root))?;
+                let square = root.square() + Fp::from_u64(self.offset);
+                cs.assign_advice(|| "b", config.b, row, || Ok(square))?;
+            }
+            Ok(())
+        }
+    }
+
+    let circuit = SquareCircuit {
+        roots: vec![3, 5, 7],
+        offset: 0,
+    };
+    let vk = keygen_vk(&params, &circuit).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &circuit).expect("keygen_pk should not fail");
+    let vk = pk.get_vk();
+
+    // The witness is computed and written by one party, and read and proven by another.
+    let mut witness_buffer = vec![];
+    generate_witness(vk, &circuit)
+        .expect("witness generation should not fail")
+        .write(&mut witness_buffer)
+        .unwrap();
+    let witness = Witness::read(&mut &witness_buffer[..], vk).unwrap();
+
+    let verify = |witnesses: &[Witness<EqAffine>]| {
+        let mut transcript = Blake2bWrite::init(vec![]);
+        let instances: Vec<&[_]> = witnesses.iter().map(|_| &[][..]).collect();
+        create_proof_with_options(
+            &params,
+            &pk,
+            ProverOptions::new()
+                .witnesses(witnesses)
+                .instances(&instances),
+            OsRng,
+            &mut transcript,
+        )
+        .expect("proof generation should not fail");
+        let proof: Vec<u8> = transcript.finalize();
+        let mut transcript = Blake2bRead::init(&proof[..]);
+        let commitments: Vec<&[EqAffine]> = witnesses.iter().map(|_| &[][..]).collect();
+        match verify_proof(
+            &params,
+            vk,
+            params.empty_msm(),
+            &commitments,
+            &mut transcript,
+        ) {
+            Ok(guard) => guard.use_challenges().eval(),
+            Err(_) => false,
+        }
+    };
+    assert!(verify(std::slice::from_ref(&witness)));
+    assert!(verify(&[witness.clone(), witness.clone()]));
+
+    let bad_witness = generate_witness(
+        vk,
+        &SquareCircuit {
+            roots: vec![3, 5, 7],
+            offset: 1,
+        },
+    )
+    .unwrap();
+    assert!(!verify(&[witness, bad_witness]));
+
+    // A witness is only read for a circuit of the same shape.
+    assert!(Witness::read(&mut &witness_buffer[..witness_buffer.len() - 1], vk).is_err());
+    let mut other_size = witness_buffer.clone();
+    other_size[0] += 1;
+    assert!(Witness::read(&mut &other_size[..], vk).is_err());
+}
+
+#[test]
+fn kzg_commitments() {
+    use bn254::Scalar;
+
+    const K: u32 = 4;
+
+    // Initialize the polynomial commitment parameters from a secret that is discarded.
+    let params = kzg::Params::setup(K, Scalar::random(OsRng));
+
+    #[derive(Clone)]
+    struct ChainConfig {
+        a: Column<Advice>,
+        b: Column<Advice>,
+        q: Column<Fixed>,
+        perm: Permutation,
+    }
+
+    /// A circuit whose prover claims that `b[i] = a[i]^2 + x[i]` for the public input
+    /// `x`, where each `b[i]` is copied to `a[i + 1]`, except that `a[1]` is shifted by
+    /// `offset`.
+    #[derive(Clone, Default)]
+    struct ChainCircuit {
+        a: u64,
+        offset: u64,
+    }
+
+    impl Circuit<Scalar> for ChainCircuit {
+        type Config = ChainConfig;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn configure(meta: &mut ConstraintSystem<Scalar>) -> ChainConfig {
+            let a = meta.advice_column();
+            let b = meta.advice_column();
+            let q = meta.fixed_column();
+            let x = meta.instance_column();
+            let perm = meta.permutation(&[a.into(), b.into()]);
+
+            meta.create_gate("square", |meta| {
+                let q = meta.query_fixed(q, Rotation::cur());
+                let a = meta.query_advice(a, Rotation::cur());
+                let b = meta.query_advice(b, Rotation::cur());
+                let x = meta.query_instance(x, Rotation::cur());
+                q * (a.clone() * a + x - b)
+            });
+
+            ChainConfig { a, b, q, perm }
+        }
+
+        fn synthesize(
+            &self,
+            cs: &mut impl Assignment<Scalar>,
+            config: ChainConfig,
+        ) -> Result<(), Error> {
+            let mut a = self.a;
+            for row in 0..3 {
+                if row == 1 {
+                    a += self.offset;
+                }
+                let b = a * a + row as u64 + 1;
+                cs.assign_fixed(|| "q", config.q, row, || Ok(Scalar::one()))?;
+                cs.assign_advice(|| "a", config.a, row, || Ok(Scalar::from_u64(a)))?;
+                cs.assign_advice(|| "b", config.b, row, || Ok(Scalar::from_u64(b)))?;
+                if row > 0 {
+                    cs.copy(&config.perm, config.b.into(), row - 1, config.a.into(), row)?;
+                }
+                a = b;
+            }
+            Ok(())
+        }
+    }
+
+    let x: Vec<_> = [1, 2, 3].iter().map(|x| Scalar::from_u64(*x)).collect();
+    let bad_x: Vec<_> = [1, 2, 4].iter().map(|x| Scalar::from_u64(*x)).collect();
+    let circuit = ChainCircuit { a: 2, offset: 0 };
+    let unchained_circuit = ChainCircuit { a: 2, offset: 1 };
+
+    let prover = MockProver::run(K, &circuit, vec![x.clone()]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    let vk = keygen_vk(&params, &circuit).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &circuit).expect("keygen_pk should not fail");
+    let instance = pk.get_vk().instance_values(&[&x]).unwrap();
+    let bad_instance = pk.get_vk().instance_values(&[&bad_x]).unwrap();
+
+    assert!(prove_and_verify(
+        &params,
+        &pk,
+        std::slice::from_ref(&circuit),
+        &[&instance]
+    ));
+    // Proofs with a wrong public input, or a broken copy constraint, do not verify.
+    assert!(!prove_and_verify(
+        &params,
+        &pk,
+        std::slice::from_ref(&circuit),
+        &[&bad_instance]
+    ));
+    assert!(!prove_and_verify(
+        &params,
+        &pk,
+        &[unchained_circuit],
+        &[&instance]
+    ));
+}